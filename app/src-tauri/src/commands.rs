@@ -1,16 +1,47 @@
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use space_saver_core::compress_plugins::{
+    CancellationToken, Cancelled, CompressionResult, PluginManager, PluginProgress, ProgressSink,
+    TimedOut,
+};
 use space_saver_core::hash_cache::HashCache;
 use space_saver_core::skip_cache::{FileFingerprint, SkipCache};
+use space_saver_core::{ArchiveFormat, Compressor, FileInfo, IoPolicy};
+use space_saver_db::{
+    ArchiveRecord, CompressionRecord, Database, DuplicateRecord, FileQuery, FileRecord,
+    IgnoredGroupRecord, MaintenanceRecord, Page, PooledConnection, QuarantineRecord, ReferenceRoot,
+    RestorePoint, SavingsSummary, SqliteDatabase, TagCount, TagRecord,
+};
 use space_saver_service::api::{
-    BrokenFile, DuplicateGroup, EmptyScanResult, FilterConfig, MediaKind, ScanResult, SimilarGroup,
-    StorageStats,
+    BrokenFile, DirectorySize, DuplicateGroup, DuplicateReport, EmptyScanResult, FilterConfig,
+    MediaKind, ScanResult, SimilarGroup, StaleFilesReport, StorageStats, TreemapNode,
+};
+use space_saver_service::daemon::{self, DaemonStatus};
+use space_saver_service::dev_artifacts::DEFAULT_UNTOUCHED_MONTHS;
+use space_saver_service::restore;
+use space_saver_service::task::TaskType;
+use space_saver_service::{
+    ActionPlan, AppCacheReport, BackupPurge, CacheCategory, CleanupReport, CompressionBackup,
+    CompressionEstimate, DeleteMode, DeleteResult, DevArtifactReport, DuplicateDirectoryGroup,
+    DuplicateResolution, ExportFormat, ExportResults, FileDetails, FileOperations,
+    FixExtensionResult, HookOutcome, Hooks, IgnoreKind, IgnoreList, KeepRule, NetworkShares,
+    PathCheck, ProtectedPaths, Quarantine, ReferenceCheck, ReflinkResult, ScanCache, ScanDetails,
+    ScanDiff, ScanHistoryEntry, ScheduleStatus, StorageHistory, TagTarget, UpcomingRun,
+};
+use space_saver_service::{
+    JobId, JobInfo, JobManager, Notifications, PartialOutputs, ProgressUpdate, ServiceApi,
+    ShutdownReport, MAINTENANCE_INTERVAL,
 };
-use space_saver_service::ServiceApi;
-use space_saver_service::{DeleteMode, DeleteResult, FileOperations, FixExtensionResult};
+use space_saver_utils::{format_size, CleanupRule, Error, ErrorCode, ErrorInfo, Schedule};
+use std::future::Future;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot};
 
 /// Remembers files a plugin already failed to shrink at a given quality so
 /// scans can exclude them. Keyed by (path, plugin, quality), guarded by a
@@ -36,6 +67,203 @@ fn skip_cache_path() -> PathBuf {
     ))
 }
 
+/// The app database with scan, duplicate and compression history, or `None` if it cannot open.
+static APP_DATABASE: Lazy<Option<Database>> = Lazy::new(|| {
+    let path = app_database_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match Database::open(&path) {
+        Ok(db) => Some(db),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to open the app database");
+            None
+        }
+    }
+});
+
+/// A connection to the app database; `None` when it could not be opened
+fn database_connection() -> Result<Option<PooledConnection>, Error> {
+    APP_DATABASE
+        .as_ref()
+        .map(|db| db.get().map_err(Error::from))
+        .transpose()
+}
+
+/// Record the results of `api`'s scans and duplicate checks in the app
+/// database
+fn recording(api: ServiceApi) -> ServiceApi {
+    match APP_DATABASE.as_ref() {
+        Some(db) => api.with_database(db.clone()),
+        None => api,
+    }
+}
+
+/// Directory walks shared by the commands below, so finding duplicates or
+/// compression candidates right after a scan does not walk the same folders
+/// again. Commands that change files drop the walks that saw them.
+static SCAN_CACHE: Lazy<ScanCache> = Lazy::new(ScanCache::new);
+
+/// Let `api` reuse the walks in [`SCAN_CACHE`], and keep its own there
+fn cached(api: ServiceApi) -> ServiceApi {
+    api.with_scan_cache(SCAN_CACHE.clone())
+}
+
+/// Files at or beneath `paths` changed: the next command to look at them
+/// walks the disk again
+fn files_changed<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) {
+    for path in paths {
+        SCAN_CACHE.invalidate(path.as_ref());
+    }
+}
+
+#[cfg(not(test))]
+fn app_database_path() -> PathBuf {
+    space_saver_utils::Config::load_or_default().database_path
+}
+
+#[cfg(test)]
+fn app_database_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "space-saver-test-history-{}.db",
+        std::process::id()
+    ))
+}
+
+/// Append a record to the compression history, logging (not failing) on error
+fn record_compression(record: CompressionRecord) {
+    if let Ok(history) = database_connection() {
+        if let Some(db) = history.as_deref() {
+            if let Err(e) = db.insert_compression(&record) {
+                tracing::warn!(path = %record.path, error = %e, "Failed to record compression history");
+            }
+        }
+    }
+}
+
+/// Add a compression to the savings ledger, logging (not failing) on error
+fn record_savings(source: &std::path::Path, result: &CompressionResult) {
+    if let Ok(history) = database_connection() {
+        if let Some(db) = history.as_deref() {
+            let op = space_saver_service::compression_operation(source, result);
+            if let Err(e) = db.insert_compression_operation(&op) {
+                tracing::warn!(path = %op.source_path, error = %e, "Failed to record compression savings");
+            }
+        }
+    }
+}
+
+/// System directories plus the config's protected folders; delete, dedupe
+/// and in-place compression refuse anything beneath them
+#[cfg(not(test))]
+fn protected_paths() -> ProtectedPaths {
+    ProtectedPaths::new().with_paths(space_saver_utils::Config::load_or_default().protected_paths)
+}
+
+/// Tests must not depend on the user's config
+#[cfg(test)]
+fn protected_paths() -> ProtectedPaths {
+    ProtectedPaths::new()
+}
+
+/// Read-rate limit and IO priority for background jobs, from the config.
+/// Jobs share one throttle, so several running at once stay under the limit
+/// together; it is replaced when the limit changes.
+#[cfg(not(test))]
+fn background_io() -> IoPolicy {
+    use space_saver_core::IoThrottle;
+
+    static THROTTLE: Lazy<Mutex<Option<IoThrottle>>> = Lazy::new(|| Mutex::new(None));
+    let config = space_saver_utils::Config::load_or_default();
+    let throttle = config.background_io_limit.and_then(|limit| {
+        let mut shared = THROTTLE.lock().ok()?;
+        if shared.as_ref().map(IoThrottle::bytes_per_sec) != Some(limit) {
+            *shared = Some(IoThrottle::new(limit));
+        }
+        shared.clone()
+    });
+    IoPolicy::new(throttle, config.background_io_low_priority)
+}
+
+/// Tests run at full speed whatever the user's config says
+#[cfg(test)]
+fn background_io() -> IoPolicy {
+    IoPolicy::default()
+}
+
+/// Network shares mounted now, read as the scan settings say
+#[cfg(not(test))]
+fn network_shares() -> NetworkShares {
+    NetworkShares::detect(&space_saver_utils::Config::load_or_default().scan)
+}
+
+/// Tests treat every drive as local
+#[cfg(test)]
+fn network_shares() -> NetworkShares {
+    NetworkShares::default()
+}
+
+/// Hook commands from the config, run around deletions, compressions and
+/// cleanups
+#[cfg(not(test))]
+fn hooks() -> Hooks {
+    Hooks::new(space_saver_utils::Config::load_or_default().hooks)
+}
+
+/// Tests must not run the user's hook commands
+#[cfg(test)]
+fn hooks() -> Hooks {
+    Hooks::default()
+}
+
+/// Quarantine directory and retention days from the config
+#[cfg(not(test))]
+fn quarantine_settings() -> (PathBuf, u32) {
+    let config = space_saver_utils::Config::load_or_default();
+    (config.quarantine_dir, config.quarantine_days)
+}
+
+#[cfg(test)]
+fn quarantine_settings() -> (PathBuf, u32) {
+    let dir = std::env::temp_dir().join(format!(
+        "space-saver-test-quarantine-{}",
+        std::process::id()
+    ));
+    (dir, 30)
+}
+
+/// Runs `f` on the quarantine, which is tracked in the same database as the
+/// compression history
+fn with_quarantine<T, E>(f: impl FnOnce(&Quarantine) -> Result<T, E>) -> Result<T, Error>
+where
+    Error: From<E>,
+{
+    let history = database_connection()?;
+    let db = history.as_deref().ok_or_else(|| {
+        Error::Database(
+            "Database unavailable; the quarantine cannot track files without it".to_string(),
+        )
+    })?;
+    let (dir, days) = quarantine_settings();
+    let quarantine = Quarantine::new(db, dir)
+        .with_retention_days(days)
+        .with_protected_paths(protected_paths())
+        .with_hooks(hooks());
+    f(&quarantine).map_err(Error::from)
+}
+
+/// Runs `f` on the app database, the one holding the compression history
+fn with_database<T, E>(f: impl FnOnce(&SqliteDatabase) -> Result<T, E>) -> Result<T, Error>
+where
+    Error: From<E>,
+{
+    let history = database_connection()?;
+    let db = history
+        .as_deref()
+        .ok_or_else(|| Error::Database("Database unavailable".to_string()))?;
+    f(db).map_err(Error::from)
+}
+
 /// Content-hash cache for duplicate scans: unchanged files (same size+mtime)
 /// are not re-read on subsequent scans
 static HASH_CACHE: Lazy<Arc<RwLock<HashCache>>> = Lazy::new(|| {
@@ -58,437 +286,2227 @@ fn hash_cache_path() -> PathBuf {
     ))
 }
 
-/// Scan multiple directories
-#[tauri::command]
-pub async fn scan(
-    paths: Vec<String>,
-    filter: Option<FilterConfig>,
-) -> Result<Vec<ScanResult>, String> {
-    let api = ServiceApi::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+/// Event carrying a `PluginProgress` for the file being compressed while
+/// `compress_files_in_place` runs
+const COMPRESS_FILE_PROGRESS_EVENT: &str = "compress://file-progress";
+
+/// Event carrying a [`CompressionFileEvent`] when `compress_files_in_place`
+/// starts a file and when it is done with it
+const COMPRESS_FILE_EVENT: &str = "compress://file";
+
+/// A file's way through a `compress_files_in_place` batch, for a live queue
+#[derive(Debug, Clone, Serialize)]
+struct CompressionFileEvent {
+    /// The job compressing the batch; 0 outside a job
+    job_id: JobId,
+    /// The file as it was passed in
+    path: String,
+    /// Position in the batch, from 0
+    index: usize,
+    total: usize,
+    /// "started", then the file's result status: "compressed", "skipped",
+    /// "failed", "timed_out" or "cancelled"
+    status: String,
+    /// Bytes a compressed file saved
+    savings: Option<u64>,
+    /// Why the file was skipped or failed
+    reason: Option<String>,
+    /// Share of the batch done, 0-100
+    percent: f64,
+}
 
-    api.scan_directories(paths, filter)
-        .await
-        .map_err(|e| e.to_string())
+impl CompressionFileEvent {
+    fn started(path: &str, index: usize, total: usize) -> Self {
+        Self {
+            job_id: 0,
+            path: path.to_string(),
+            index,
+            total,
+            status: "started".to_string(),
+            savings: None,
+            reason: None,
+            percent: index as f64 * 100.0 / total as f64,
+        }
+    }
+
+    /// The file ended as `result`, one of `compress_files_blocking`'s
+    fn finished(path: &str, index: usize, total: usize, result: &serde_json::Value) -> Self {
+        let text = |key: &str| result[key].as_str().map(str::to_string);
+        Self {
+            status: text("status").unwrap_or_default(),
+            savings: result["savings"].as_u64(),
+            reason: text("reason").or_else(|| text("error")),
+            percent: (index + 1) as f64 * 100.0 / total as f64,
+            ..Self::started(path, index, total)
+        }
+    }
 }
 
-/// Find duplicate files across multiple paths
-#[tauri::command]
-pub async fn duplicate_file_check(
-    paths: Vec<String>,
-    filter: Option<FilterConfig>,
-) -> Result<Vec<DuplicateGroup>, String> {
-    let api = ServiceApi::new().with_hash_cache(Arc::clone(&HASH_CACHE));
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+/// Background jobs started by the commands below, for `list_jobs`,
+/// `get_job_status` and `cancel_job`
+static JOBS: Lazy<JobManager> = Lazy::new(JobManager::new);
 
-    let result = api
-        .find_duplicates_in_paths(paths, filter)
-        .await
-        .map_err(|e| e.to_string())?;
+/// Archives and scratch copies being written, removed if the app closes
+/// before they are finished
+static PARTIAL_OUTPUTS: Lazy<PartialOutputs> = Lazy::new(PartialOutputs::new);
+
+/// How long closing the app waits for cancelled jobs to wind down
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
 
-    // Persist newly computed hashes; cache failures must not fail the scan
+#[cfg(not(test))]
+fn shutdown_report_path() -> PathBuf {
+    space_saver_utils::Config::load_or_default()
+        .cache_dir
+        .join("last_shutdown.json")
+}
+
+#[cfg(test)]
+fn shutdown_report_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "space-saver-test-last-shutdown-{}.json",
+        std::process::id()
+    ))
+}
+
+/// Called once at launch: when the last database maintenance pass is older
+/// than [`MAINTENANCE_INTERVAL`], prune and vacuum the database as a
+/// background job (`maintenance://` events). Nothing runs without a database.
+pub fn start_maintenance(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let due = tokio::task::spawn_blocking(|| {
+            recording(ServiceApi::new()).maintenance_due(MAINTENANCE_INTERVAL)
+        })
+        .await;
+        if !matches!(due, Ok(Ok(true))) {
+            return;
+        }
+        spawn_job(
+            app,
+            "maintenance",
+            TaskType::Maintenance,
+            |record: &MaintenanceRecord| {
+                format!(
+                    "Pruned {} stored files, database now {}",
+                    record.pruned_files,
+                    format_size(record.size_after)
+                )
+            },
+            |_, _, _| async {
+                tokio::task::spawn_blocking(|| recording(ServiceApi::new()).maintain_database())
+                    .await
+                    .map_err(join_failed)?
+            },
+        );
+    });
+}
+
+/// Called once as the app exits: cancels running jobs and waits for them to
+/// wind down, removes partial archives, flushes the database and caches, and
+/// saves what was interrupted for `take_shutdown_report` on the next launch
+pub fn shutdown_app() {
+    let report = recording(ServiceApi::new())
+        .with_jobs(JOBS.clone())
+        .with_partial_outputs(PARTIAL_OUTPUTS.clone())
+        .shutdown(SHUTDOWN_GRACE);
+    if let Ok(mut cache) = SKIP_CACHE.write() {
+        if let Err(e) = cache.save() {
+            tracing::warn!(error = %e, "Failed to persist compression skip cache");
+        }
+    }
     if let Ok(mut cache) = HASH_CACHE.write() {
         if let Err(e) = cache.save() {
             tracing::warn!(error = %e, "Failed to persist duplicate hash cache");
         }
     }
-
-    Ok(result)
+    if !report.is_empty() {
+        if let Err(e) = report.save(&shutdown_report_path()) {
+            tracing::warn!(error = %e, "Failed to save shutdown report");
+        }
+    }
 }
 
-/// Find similar media (images today; videos pending ffmpeg) across multiple
-/// paths. `media_types` selects which kinds to scan ("Image"/"Video"); an
-/// empty list defaults to images.
-#[tauri::command]
-pub async fn find_similar_media(
-    paths: Vec<String>,
-    threshold: f32,
-    media_types: Vec<MediaKind>,
-    filter: Option<FilterConfig>,
-) -> Result<Vec<SimilarGroup>, String> {
-    let api = ServiceApi::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+/// Payload of `<kind>://progress` events
+#[derive(Clone, Serialize)]
+struct JobProgress {
+    job_id: u64,
+    update: ProgressUpdate,
+}
 
-    api.find_similar_media_in_paths(paths, threshold, media_types, filter)
-        .await
-        .map_err(|e| e.to_string())
+/// Payload of the `<kind>://done` event ending a job: its result, or the
+/// error the blocking command used to return
+#[derive(Clone, Serialize)]
+struct JobDone<T> {
+    job_id: u64,
+    result: Option<T>,
+    error: Option<ErrorInfo>,
 }
 
-/// Generate a PNG thumbnail for an image, returned as a `data:` URL the
-/// frontend can use directly as an `<img src>`. `max_size` bounds both
-/// dimensions (aspect ratio preserved). Errors for missing or non-image files.
-#[tauri::command]
-pub async fn read_image_thumbnail(path: String, max_size: u32) -> Result<String, String> {
-    space_saver_core::thumbnail_data_url(&PathBuf::from(path), max_size).map_err(|e| e.to_string())
+/// A blocking task that panicked or was aborted
+fn join_failed(e: tokio::task::JoinError) -> Error {
+    Error::Task(e.to_string())
 }
 
-/// Find empty files (0 bytes) and empty folders (no files anywhere beneath
-/// them, reported topmost-only) across multiple paths. `filter` applies to
-/// files only.
-#[tauri::command]
-pub async fn empty_folder_check(
-    paths: Vec<String>,
-    filter: Option<FilterConfig>,
-) -> Result<EmptyScanResult, String> {
-    let api = ServiceApi::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+/// Shows notifications through the OS while the window is in the
+/// background; the user is looking already when it has the focus
+#[cfg(not(test))]
+struct WindowNotifier {
+    app: AppHandle,
+}
 
-    api.find_empty_in_paths(paths, filter)
-        .await
-        .map_err(|e| e.to_string())
+#[cfg(not(test))]
+impl space_saver_service::Notifier for WindowNotifier {
+    fn show(&self, notification: &space_saver_service::Notification) -> anyhow::Result<()> {
+        use space_saver_service::Notifier;
+        use tauri::Manager;
+
+        let focused = self
+            .app
+            .get_webview_window("main")
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(false);
+        if focused {
+            return Ok(());
+        }
+        space_saver_service::SystemNotifier.show(notification)
+    }
 }
 
-/// Find broken (invalid or corrupted) files across multiple paths. Reports
-/// only files that are provably unusable — corrupted/truncated content, or
-/// content that does not match its extension. Empty files are excluded.
-#[tauri::command]
-pub async fn broken_file_check(
-    paths: Vec<String>,
-    filter: Option<FilterConfig>,
-) -> Result<Vec<BrokenFile>, String> {
-    let api = ServiceApi::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+/// Notifications for finished jobs, as the config says
+#[cfg(not(test))]
+fn notifications(app: &AppHandle) -> Notifications {
+    Notifications::new(
+        space_saver_utils::Config::load_or_default().notifications,
+        Arc::new(WindowNotifier { app: app.clone() }),
+    )
+}
 
-    api.find_broken_files_in_paths(paths, filter)
-        .await
-        .map_err(|e| e.to_string())
+/// Tests must not pop up notifications
+#[cfg(test)]
+fn notifications(_app: &AppHandle) -> Notifications {
+    Notifications::default()
 }
 
-/// Rename misnamed files (whose content does not match their extension) to the
-/// extension matching their real content, reporting a per-file outcome. This
-/// is the safe action for `extension_mismatch` results from `broken_file_check`
-/// — the file is valid, just named wrong, so it is renamed rather than deleted.
-#[tauri::command]
-pub async fn fix_file_extensions(paths: Vec<String>) -> Result<Vec<FixExtensionResult>, String> {
-    let ops = FileOperations::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+/// Runs `work` in the background as a job registered in `JOBS` and returns
+/// its id at once. `work` gets the job's id and cancellation token. Progress is
+/// emitted as `<kind>://progress` events (`Started`, the work's own reports,
+/// then `Completed`, `Failed` or `Cancelled`), followed by exactly one
+/// `<kind>://done` event carrying the result. Every progress event of a job
+/// is emitted before its done event. A job that succeeds or fails (rather
+/// than being cancelled) also notifies, with `summary` of its result.
+fn spawn_job<T, F, Fut>(
+    app: AppHandle,
+    kind: &'static str,
+    task_type: TaskType,
+    summary: fn(&T) -> String,
+    work: F,
+) -> JobId
+where
+    T: Serialize + Clone + Send + 'static,
+    F: FnOnce(JobId, mpsc::Sender<ProgressUpdate>, CancellationToken) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, Error>> + Send + 'static,
+{
+    let total_items = task_type.item_count();
+    let task_kind = task_type.kind();
+    let started = Instant::now();
+    let handle = JOBS.register(task_type);
+    let job_id = handle.id();
+    let token = handle.token();
+    let cancelled = token.clone();
+    let (progress_tx, mut progress_rx) = mpsc::channel(256);
+    let (done_tx, done_rx) = oneshot::channel();
+
+    tauri::async_runtime::spawn(async move {
+        let _ = progress_tx
+            .send(ProgressUpdate::Started {
+                task_type: kind.to_string(),
+                total_items,
+            })
+            .await;
+        let outcome = match work(job_id, progress_tx.clone(), token.clone()).await {
+            Err(_) if token.is_cancelled() => Err(Error::Cancelled),
+            outcome => outcome,
+        };
+        // A cancelled compression still returns its per-file results
+        let last = match &outcome {
+            _ if token.is_cancelled() => ProgressUpdate::Cancelled,
+            Ok(_) => ProgressUpdate::Completed {
+                message: format!("{} finished", kind),
+            },
+            Err(error) => ProgressUpdate::Failed {
+                error: error.to_string(),
+            },
+        };
+        let _ = progress_tx.send(last).await;
+        let _ = done_tx.send(outcome);
+    });
+
+    // The channel closes once the work and every clone of its sender are
+    // gone, so draining it first keeps progress ahead of the done event
+    tauri::async_runtime::spawn(async move {
+        let progress_event = format!("{}://progress", kind);
+        while let Some(update) = progress_rx.recv().await {
+            handle.update(&update);
+            // Progress is best effort; a closed window must not fail the job
+            let _ = app.emit(&progress_event, JobProgress { job_id, update });
+        }
+        let outcome = done_rx
+            .await
+            .unwrap_or_else(|_| Err(Error::Task("Job stopped unexpectedly".to_string())));
+        if !cancelled.is_cancelled() {
+            let described = outcome.as_ref().map(summary).map_err(Error::to_string);
+            notifications(&app).task_finished(
+                task_kind,
+                described.as_deref().map_err(String::as_str),
+                started.elapsed(),
+            );
+        }
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(ErrorInfo::from(&error))),
+        };
+        let _ = app.emit(
+            &format!("{}://done", kind),
+            JobDone {
+                job_id,
+                result,
+                error,
+            },
+        );
+    });
 
-    Ok(ops.fix_extensions(&paths))
+    job_id
 }
 
-/// Delete files, reporting a per-file outcome. `mode` defaults to "trash"
-/// (recoverable); "permanent" removes from disk immediately.
-#[tauri::command]
-pub async fn delete_files(
-    paths: Vec<String>,
-    mode: Option<DeleteMode>,
-) -> Result<Vec<DeleteResult>, String> {
-    let ops = FileOperations::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
-    let mode = mode.unwrap_or(DeleteMode::Trash);
+/// Cancellation tokens of the compression runs currently in flight, keyed by
+/// a per-run id. The frontend runs several `compress_files_in_place` calls in
+/// parallel, so `cancel_compression` stops all of them at once.
+static ACTIVE_COMPRESSIONS: Lazy<Mutex<HashMap<u64, CancellationToken>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_COMPRESSION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Registers a compression run's token for its lifetime; dropping the guard
+/// unregisters it, even if the run fails or panics
+struct CompressionRun {
+    id: u64,
+    token: CancellationToken,
+}
 
-    Ok(ops.delete_files_with_mode(&paths, mode))
+impl CompressionRun {
+    fn register(token: CancellationToken) -> Self {
+        let id = NEXT_COMPRESSION_ID.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut runs) = ACTIVE_COMPRESSIONS.lock() {
+            runs.insert(id, token.clone());
+        }
+        Self { id, token }
+    }
 }
 
-/// Get storage statistics across multiple paths
+impl Drop for CompressionRun {
+    fn drop(&mut self) {
+        if let Ok(mut runs) = ACTIVE_COMPRESSIONS.lock() {
+            runs.remove(&self.id);
+        }
+    }
+}
+
+/// Scan multiple directories in the background, returning the job id.
+/// Progress arrives as `scan://progress` events and a [`ScanSummary`] as the
+/// `scan://done` event; the files stay here, for `get_scan_page`.
 #[tauri::command]
-pub async fn get_storage_stats(
+pub async fn scan(
+    app: AppHandle,
     paths: Vec<String>,
     filter: Option<FilterConfig>,
-) -> Result<StorageStats, String> {
-    let api = ServiceApi::new();
-    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+) -> Result<u64, Error> {
+    let task_type = TaskType::Scan(paths.iter().map(PathBuf::from).collect());
+    Ok(spawn_job(
+        app,
+        "scan",
+        task_type,
+        |scan: &ScanSummary| format!("Found {} files", scan.file_count),
+        move |job_id, progress, cancel| async move {
+            let results = scan_paths(paths, filter, Some(progress), cancel).await?;
+            Ok(store_scan(job_id, results))
+        },
+    ))
+}
 
-    api.get_storage_stats_for_paths(paths, filter)
-        .await
-        .map_err(|e| e.to_string())
+/// How many finished scans `get_scan_page` can still page through; older
+/// ones are dropped as new scans finish
+const KEPT_SCANS: usize = 3;
+
+/// The files of the latest scan jobs, oldest first
+static SCAN_RESULTS: Lazy<Mutex<VecDeque<StoredScan>>> = Lazy::new(Default::default);
+
+struct StoredScan {
+    job_id: JobId,
+    results: Vec<ScanResult>,
+    /// The last order asked for, as (result, file) indices, so paging
+    /// through it does not sort again
+    sorted: Option<(ScanView, Vec<(usize, usize)>)>,
 }
 
-/// Get available compression plugins
-#[tauri::command]
-pub async fn get_compression_plugins() -> Result<Vec<serde_json::Value>, String> {
-    let manager = space_saver_core::compress_plugins::global_plugin_manager();
-    let manager = manager.read().map_err(|e| e.to_string())?;
-    let plugins = manager.get_plugins();
+/// Which files of a scan `get_scan_page` pages through, and in what order
+#[derive(Clone, PartialEq)]
+struct ScanView {
+    sort_by: ScanSort,
+    order: SortOrder,
+    path: Option<String>,
+}
 
-    Ok(plugins
+/// Totals of one scanned path
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedPath {
+    pub path: PathBuf,
+    pub file_count: usize,
+    pub total_size: u64,
+}
+
+/// What a scan job hands the webview: totals only
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanSummary {
+    pub job_id: JobId,
+    pub file_count: usize,
+    pub total_size: u64,
+    pub paths: Vec<ScannedPath>,
+}
+
+/// How `get_scan_page` orders files; ties go by path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanSort {
+    #[default]
+    Size,
+    Path,
+    Name,
+    Modified,
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// One page of a scan's files
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanPage {
+    pub files: Vec<FileInfo>,
+    /// Files in the whole view, for the page count
+    pub total: usize,
+    pub offset: usize,
+}
+
+/// Keep `results` for `get_scan_page` under `job_id` and sum them up
+fn store_scan(job_id: JobId, results: Vec<ScanResult>) -> ScanSummary {
+    let paths: Vec<ScannedPath> = results
         .iter()
-        .map(|p| {
-            serde_json::json!({
-                "name": p.name,
-                "description": p.description,
-                "version": p.version,
-                "quality": manager.get_plugin_quality(&p.name),
-            })
+        .map(|result| ScannedPath {
+            path: result.path.clone(),
+            file_count: result.file_count,
+            total_size: result.total_size,
         })
-        .collect())
+        .collect();
+    if let Ok(mut scans) = SCAN_RESULTS.lock() {
+        while scans.len() >= KEPT_SCANS {
+            scans.pop_front();
+        }
+        scans.push_back(StoredScan {
+            job_id,
+            results,
+            sorted: None,
+        });
+    }
+    ScanSummary {
+        job_id,
+        file_count: paths.iter().map(|p| p.file_count).sum(),
+        total_size: paths.iter().map(|p| p.total_size).sum(),
+        paths,
+    }
 }
 
-/// Set the quality (0-100) of a compression plugin. The new value is also
-/// written to the config file so it survives a restart (config is the single
-/// source of truth for quality; the plugin manager is seeded from it at boot).
+/// Up to `limit` files of the scan job `job_id`, from `offset` on, sorted by
+/// `sort_by` (size unless given) in `order` (descending unless given).
+/// `path` narrows the page to the files of one scanned path.
 #[tauri::command]
-pub async fn set_plugin_quality(plugin_name: String, quality: f32) -> Result<(), String> {
+pub async fn get_scan_page(
+    job_id: JobId,
+    offset: usize,
+    limit: usize,
+    sort_by: Option<ScanSort>,
+    order: Option<SortOrder>,
+    path: Option<String>,
+) -> Result<ScanPage, Error> {
+    let view = ScanView {
+        sort_by: sort_by.unwrap_or_default(),
+        order: order.unwrap_or_default(),
+        path,
+    };
+    let mut scans = SCAN_RESULTS.lock().map_err(Error::from)?;
+    let scan = scans
+        .iter_mut()
+        .find(|scan| scan.job_id == job_id)
+        .ok_or_else(|| {
+            Error::NotFound(format!(
+                "The results of scan {} are gone; scan again",
+                job_id
+            ))
+        })?;
+    if scan
+        .sorted
+        .as_ref()
+        .is_none_or(|(sorted, _)| *sorted != view)
     {
-        let manager = space_saver_core::compress_plugins::global_plugin_manager();
-        let mut manager = manager.write().map_err(|e| e.to_string())?;
-        manager
-            .set_plugin_quality(&plugin_name, quality)
-            .map_err(|e| e.to_string())?;
+        let order = sorted_view(&scan.results, &view);
+        scan.sorted = Some((view, order));
     }
-    persist_plugin_quality(&config_path(), &plugin_name, quality)
+    let (_, order) = scan.sorted.as_ref().expect("sorted just above");
+    let files = order
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|&(result, file)| scan.results[result].files[file].clone())
+        .collect();
+    Ok(ScanPage {
+        files,
+        total: order.len(),
+        offset,
+    })
 }
 
-/// Record a plugin's quality in the config file. The stored value is clamped to
-/// match what the plugin manager applies, so config and runtime never diverge.
-fn persist_plugin_quality(
-    path: &std::path::Path,
-    plugin_name: &str,
-    quality: f32,
-) -> Result<(), String> {
-    let mut config = load_config_from(path)?;
-    config
-        .plugin_quality
-        .insert(plugin_name.to_string(), quality.clamp(0.0, 100.0));
-    save_config_to(path, &config)
+/// The files of `results` in `view`'s order, as (result, file) indices
+fn sorted_view(results: &[ScanResult], view: &ScanView) -> Vec<(usize, usize)> {
+    use std::cmp::Ordering;
+
+    /// Alphabetical by type name
+    fn type_rank(file_type: &space_saver_core::scanner::FileType) -> u8 {
+        use space_saver_core::scanner::FileType;
+        match file_type {
+            FileType::Archive => 0,
+            FileType::Document => 1,
+            FileType::Image => 2,
+            FileType::Other => 3,
+            FileType::Video => 4,
+        }
+    }
+
+    let mut indices: Vec<(usize, usize)> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| {
+            view.path
+                .as_ref()
+                .is_none_or(|path| result.path == Path::new(path))
+        })
+        .flat_map(|(r, result)| (0..result.files.len()).map(move |f| (r, f)))
+        .collect();
+    let file = |&(r, f): &(usize, usize)| &results[r].files[f];
+    indices.sort_by(|a, b| {
+        let (a, b) = (file(a), file(b));
+        let by = match view.sort_by {
+            ScanSort::Size => a.size.cmp(&b.size),
+            ScanSort::Modified => a.modified.cmp(&b.modified),
+            ScanSort::Name => a.path.file_name().cmp(&b.path.file_name()),
+            ScanSort::Type => type_rank(&a.file_type).cmp(&type_rank(&b.file_type)),
+            ScanSort::Path => Ordering::Equal,
+        };
+        let by = by.then_with(|| a.path.cmp(&b.path));
+        match view.order {
+            SortOrder::Asc => by,
+            SortOrder::Desc => by.reverse(),
+        }
+    });
+    indices
 }
 
-/// Seed the global plugin manager with the per-plugin qualities saved in config.
-/// Called once at startup so persisted quality takes effect. Unknown plugin
-/// names in config are ignored rather than failing the launch.
-pub fn seed_plugin_quality_from_config() {
-    let config = load_config_from(&config_path()).unwrap_or_default();
-    if config.plugin_quality.is_empty() {
-        return;
-    }
-    let manager = space_saver_core::compress_plugins::global_plugin_manager();
-    let mut guard = match manager.write() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
-    for (name, quality) in &config.plugin_quality {
-        let _ = guard.set_plugin_quality(name, *quality);
+async fn scan_paths(
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+    progress: Option<mpsc::Sender<ProgressUpdate>>,
+    cancel: CancellationToken,
+) -> Result<Vec<ScanResult>, Error> {
+    // Unfiltered scans also go into the storage history
+    let mut api = cached(recording(ServiceApi::new()))
+        .with_io_policy(background_io())
+        .with_cancel(cancel);
+    if let Some(progress) = progress {
+        api = api.with_progress(progress);
     }
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.scan_directories(paths, filter).await
 }
 
-/// Scan paths and find compressible files with estimates
+/// Make the next commands walk `paths` (every folder when None) from disk
+/// instead of reusing an earlier walk, e.g. after files changed outside the
+/// app. Returns how many walks were dropped.
 #[tauri::command]
-pub async fn scan_compressible_files(
-    paths: Vec<String>,
-    active_plugins: Vec<String>,
-    filter: Option<FilterConfig>,
-) -> Result<serde_json::Value, String> {
-    use space_saver_core::{scanner::DefaultFileScanner, FileScanner};
-    use std::path::PathBuf;
+pub async fn invalidate_scan_cache(paths: Option<Vec<String>>) -> Result<usize, Error> {
+    Ok(match paths {
+        Some(paths) => paths
+            .iter()
+            .map(|path| SCAN_CACHE.invalidate(Path::new(path)))
+            .sum(),
+        None => SCAN_CACHE.clear(),
+    })
+}
 
-    // Get the global plugin manager
-    let manager = space_saver_core::compress_plugins::global_plugin_manager();
-    let manager = manager.read().map_err(|e| e.to_string())?;
+/// The most recent recorded scans of any path, newest first (20 unless
+/// `limit` says otherwise), each with the space the recorded duplicates
+/// under its path waste
+#[tauri::command]
+pub async fn get_scan_history(limit: Option<usize>) -> Result<Vec<ScanHistoryEntry>, Error> {
+    tokio::task::spawn_blocking(move || {
+        recording(ServiceApi::new()).scan_history(limit.unwrap_or(20))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-    // Step 1: Validate active plugins
-    let all_plugin_names: Vec<String> = manager
-        .get_plugins()
-        .iter()
-        .map(|p| p.name.clone())
-        .collect();
-    for plugin_name in &active_plugins {
-        if !all_plugin_names.contains(plugin_name) {
-            return Err(format!("Active plugin not found: {}", plugin_name));
-        }
-    }
+/// A recorded scan (ids from `get_scan_history`) with its totals per
+/// category, largest files and the recorded duplicates under its path
+#[tauri::command]
+pub async fn get_scan_details(id: i64) -> Result<ScanDetails, Error> {
+    tokio::task::spawn_blocking(move || recording(ServiceApi::new()).scan_details(id))
+        .await
+        .map_err(join_failed)?
+}
 
-    // Step 2: Merge plugin supported extensions with filterConfig
-    let mut supported_extensions = HashSet::new();
-    for plugin_name in &active_plugins {
-        let exts = manager.get_supported_extensions(plugin_name);
-        supported_extensions.extend(exts);
-    }
+/// Forget a recorded scan and the files it saw. Unknown ids are
+/// `NotFound`.
+#[tauri::command]
+pub async fn delete_history_entry(id: i64) -> Result<(), Error> {
+    tokio::task::spawn_blocking(move || recording(ServiceApi::new()).delete_scan(id))
+        .await
+        .map_err(join_failed)?
+}
 
-    // Create or update filter config with plugin extensions
-    let mut merged_filter = filter.unwrap_or_default();
-    if !supported_extensions.is_empty() {
-        // If filter already has extensions, intersect them with plugin extensions
-        // Otherwise, use plugin extensions
-        if let Some(existing_exts) = &merged_filter.extensions {
-            let existing_set: HashSet<String> = existing_exts.iter().cloned().collect();
-            let intersection: Vec<String> = supported_extensions
-                .intersection(&existing_set)
-                .cloned()
-                .collect();
-            merged_filter.extensions = if intersection.is_empty() {
-                // If no intersection, use plugin extensions (plugin extensions take precedence)
-                Some(supported_extensions.into_iter().collect())
-            } else {
-                Some(intersection)
-            };
-        } else {
-            // No existing extensions, use plugin extensions
-            merged_filter.extensions = Some(supported_extensions.into_iter().collect());
-        }
-    }
+/// A page of the files earlier scans recorded, browsed without rescanning
+/// (the first 100 unless `page` says otherwise)
+#[tauri::command]
+pub async fn browse_recorded_files(
+    query: FileQuery,
+    page: Option<Page>,
+) -> Result<Vec<FileRecord>, Error> {
+    tokio::task::spawn_blocking(move || {
+        recording(ServiceApi::new()).recorded_files(&query, page.unwrap_or(Page::first(100)))
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// The duplicate groups last found under each scanned folder, most wasted
+/// space first
+#[tauri::command]
+pub async fn get_duplicate_history() -> Result<Vec<DuplicateRecord>, Error> {
+    tokio::task::spawn_blocking(|| recording(ServiceApi::new()).recorded_duplicates())
+        .await
+        .map_err(join_failed)?
+}
 
-    // Use the global scanner to scan all paths
-    let scanner = DefaultFileScanner::new();
-    let mut all_files = Vec::new();
+/// Recorded scans of `path`, oldest first, with growth per file category
+/// since the first. Scans are recorded by `scan` when no filter is set.
+#[tauri::command]
+pub async fn get_storage_history(path: String) -> Result<StorageHistory, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().get_storage_history(Path::new(&path), db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-    for path_str in paths {
-        let path = PathBuf::from(path_str);
-        let mut files = scanner.scan(&path).map_err(|e| e.to_string())?;
+/// Files added, removed or resized between two recorded scans of one path
+#[tauri::command]
+pub async fn diff_scans(from_scan: i64, to_scan: i64) -> Result<ScanDiff, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().diff_scans(from_scan, to_scan, db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-        // Apply merged filters
-        files = merged_filter.apply(files);
+/// Find duplicate files across multiple paths in the background, returning
+/// the job id. Progress arrives as `duplicates://progress` events and the
+/// `DuplicateReport` (groups, and files that could not be read) as the
+/// `duplicates://done` event.
+#[tauri::command]
+pub async fn duplicate_file_check(
+    app: AppHandle,
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+) -> Result<u64, Error> {
+    let task_type = TaskType::FindDuplicates(paths.iter().map(PathBuf::from).collect());
+    Ok(spawn_job(
+        app,
+        "duplicates",
+        task_type,
+        |report: &DuplicateReport| {
+            let wasted: u64 = report.groups.iter().map(|g| g.wasted_space).sum();
+            format!(
+                "{} duplicate groups, {} wasted",
+                report.groups.len(),
+                format_size(wasted)
+            )
+        },
+        move |_, progress, cancel| check_duplicates(paths, filter, Some(progress), cancel),
+    ))
+}
 
-        all_files.extend(files);
+async fn check_duplicates(
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+    progress: Option<mpsc::Sender<ProgressUpdate>>,
+    cancel: CancellationToken,
+) -> Result<DuplicateReport, Error> {
+    let mut api = cached(recording(ServiceApi::new()))
+        .with_hash_cache(Arc::clone(&HASH_CACHE))
+        .with_ignore_list(ignore_list())
+        .with_io_policy(background_io())
+        .with_network_shares(network_shares())
+        .with_cancel(cancel);
+    if let Some(progress) = progress {
+        api = api.with_progress(progress);
     }
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
 
-    // Step 3: Try each active plugin (in order) on each file, collecting
-    // rejection reasons along the way in a single pass
-    let mut compressible_files = Vec::new();
-    let mut rejected_files = Vec::new();
+    let result = api.find_duplicate_report(paths, filter).await?;
 
-    let skip_cache = SKIP_CACHE.read().map_err(|e| e.to_string())?;
+    save_hash_cache();
+    Ok(result)
+}
 
-    for file_info in all_files {
-        let mut rejection_reasons = Vec::new();
-        let mut accepted = None;
+/// Groups the user chose to keep; an unavailable database must not fail the
+/// scan, it only shows every group
+fn ignore_list() -> IgnoreList {
+    with_database(IgnoreList::load).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to load ignored groups");
+        IgnoreList::default()
+    })
+}
 
-        // The scanner already stat'ed the file; reuse size + mtime
-        let fingerprint = FileFingerprint {
-            size: file_info.size,
-            mtime: file_info.modified,
-        };
-        let path_str = file_info.path.to_string_lossy().to_string();
+/// Stop showing a duplicate (`hash` required) or similar group. Later scans
+/// leave it out until it gains a file or its content changes.
+#[tauri::command]
+pub async fn ignore_group(
+    kind: IgnoreKind,
+    paths: Vec<String>,
+    hash: Option<String>,
+) -> Result<IgnoredGroupRecord, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().ignore_group(kind, &paths, hash, db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-        for plugin_name in &active_plugins {
-            match manager.check_plugin_capability(&file_info.path, plugin_name) {
-                Ok(Some((metadata, can_handle, reason, estimate_ratio))) => {
-                    if can_handle {
-                        // Skip-cache: this exact file state already produced no
-                        // size reduction with this plugin at this quality
-                        let quality = manager.get_plugin_quality(plugin_name);
-                        if skip_cache.is_known_skip(&path_str, &fingerprint, plugin_name, quality) {
-                            rejection_reasons.push(serde_json::json!({
-                                "plugin_name": metadata.name,
-                                "reason": format!(
-                                    "Previously produced no size reduction{} (cached result; file unchanged)",
-                                    quality.map(|q| format!(" at quality {}", q)).unwrap_or_default()
-                                ),
-                            }));
-                            continue;
-                        }
+/// Show an ignored group again; false when `id` is unknown
+#[tauri::command]
+pub async fn unignore_group(id: i64) -> Result<bool, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().unignore_group(id, db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-                        let ratio = estimate_ratio.unwrap_or(0.0);
-                        let estimated_compressed =
-                            (file_info.size as f64 * (1.0 - ratio as f64)) as u64;
-                        let estimated_savings = file_info.size.saturating_sub(estimated_compressed);
+/// Ignored duplicate and similar groups, newest first
+#[tauri::command]
+pub async fn list_ignored_groups() -> Result<Vec<IgnoredGroupRecord>, Error> {
+    tokio::task::spawn_blocking(|| with_database(|db| ServiceApi::new().list_ignored_groups(db)))
+        .await
+        .map_err(join_failed)?
+}
 
-                        accepted = Some(serde_json::json!({
-                            "path": file_info.path.to_string_lossy(),
-                            "original_size": file_info.size,
-                            "estimated_compressed_size": estimated_compressed,
-                            "estimated_savings": estimated_savings,
-                            "plugin_name": metadata.name,
-                            "can_handle": true,
-                            "reason": reason,
-                        }));
-                        break;
-                    }
+/// Tag a file or duplicate group; false when it already had the tag
+#[tauri::command]
+pub async fn add_tag(target: TagTarget, tag: String) -> Result<bool, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().add_tag(&target, &tag, db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-                    rejection_reasons.push(serde_json::json!({
-                        "plugin_name": metadata.name,
-                        "reason": reason.unwrap_or_else(|| "Unknown reason".to_string()),
-                    }));
-                }
-                // Plugin not found (already validated above), skip
-                Ok(None) => continue,
-                // A plugin failing on one file (e.g. a corrupt archive) must
-                // not abort the whole scan; record it as a rejection reason
-                Err(e) => {
-                    rejection_reasons.push(serde_json::json!({
-                        "plugin_name": plugin_name,
-                        "reason": format!("Error: {}", e),
-                    }));
-                }
-            }
-        }
+/// Take a tag off; false when the target did not have it
+#[tauri::command]
+pub async fn remove_tag(target: TagTarget, tag: String) -> Result<bool, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().remove_tag(&target, &tag, db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-        match accepted {
-            Some(compress_info) => compressible_files.push(compress_info),
-            None => {
-                if !rejection_reasons.is_empty() {
-                    let extension = file_info
-                        .path
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .unwrap_or("");
-                    rejected_files.push(serde_json::json!({
-                        "path": file_info.path.to_string_lossy(),
-                        "size": file_info.size,
-                        "extension": extension,
-                        "rejection_reasons": rejection_reasons,
-                    }));
-                }
-            }
+/// Tags of a file or duplicate group, alphabetically
+#[tauri::command]
+pub async fn get_tags(target: TagTarget) -> Result<Vec<String>, Error> {
+    tokio::task::spawn_blocking(move || with_database(|db| ServiceApi::new().tags_of(&target, db)))
+        .await
+        .map_err(join_failed)?
+}
+
+/// Files and duplicate groups tagged `tag`, newest first
+#[tauri::command]
+pub async fn find_tagged(tag: String) -> Result<Vec<TagRecord>, Error> {
+    tokio::task::spawn_blocking(move || with_database(|db| ServiceApi::new().find_tagged(&tag, db)))
+        .await
+        .map_err(join_failed)?
+}
+
+/// Tags in use with how many files and groups carry each
+#[tauri::command]
+pub async fn list_tags() -> Result<Vec<TagCount>, Error> {
+    tokio::task::spawn_blocking(|| with_database(|db| ServiceApi::new().list_tags(db)))
+        .await
+        .map_err(join_failed)?
+}
+
+/// Persist newly computed hashes; cache failures must not fail the scan
+fn save_hash_cache() {
+    if let Ok(mut cache) = HASH_CACHE.write() {
+        if let Err(e) = cache.save() {
+            tracing::warn!(error = %e, "Failed to persist duplicate hash cache");
         }
     }
+}
 
-    Ok(serde_json::json!({
-        "compressible": compressible_files,
-        "rejected": rejected_files,
-    }))
+/// Index the reference volume (e.g. a NAS) at `path`: hash every file and
+/// store the hashes, replacing its previous index. A path that is not a
+/// directory (say, an unmounted volume) is refused and the old index kept.
+#[tauri::command]
+pub async fn index_reference(path: String) -> Result<ReferenceRoot, Error> {
+    let result = tokio::task::spawn_blocking(move || {
+        with_database(|db| {
+            ServiceApi::new()
+                .with_hash_cache(Arc::clone(&HASH_CACHE))
+                .index_reference(Path::new(&path), db)
+        })
+    })
+    .await
+    .map_err(join_failed)?;
+    save_hash_cache();
+    result
 }
 
-/// Compress files in place. With `create_backup` the original is kept as
-/// `<name>.bak` next to the output; without it the original is deleted once
-/// compression fully succeeds (failures and skips never touch it). Each file
-/// ends up in one of three states: "compressed", "skipped" (output was not
-/// smaller, original kept untouched), or "failed".
+/// Check files across multiple paths against every indexed reference volume.
+/// Files with an identical copy there are listed as safe to delete; pass
+/// their paths to `delete_files`.
 #[tauri::command]
-pub async fn compress_files_in_place(
-    file_paths: Vec<String>,
-    plugin_orders: Vec<String>, // Ordered list of active plugin names
-    create_backup: bool,        // false: delete the original once compression succeeds
-) -> Result<Vec<serde_json::Value>, String> {
-    use space_saver_core::CompressionOutcome;
-    use std::path::PathBuf;
+pub async fn check_against_reference(
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+) -> Result<ReferenceCheck, Error> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let result = tokio::task::spawn_blocking(move || {
+        with_database(|db| {
+            ServiceApi::new()
+                .with_hash_cache(Arc::clone(&HASH_CACHE))
+                .check_against_reference(paths, filter, db)
+        })
+    })
+    .await
+    .map_err(join_failed)?;
+    save_hash_cache();
+    result
+}
 
-    // Get the global plugin manager (all plugins pre-registered with priorities)
-    let manager = space_saver_core::compress_plugins::global_plugin_manager();
-    let manager = manager.read().map_err(|e| e.to_string())?;
+/// Indexed reference volumes, by path
+#[tauri::command]
+pub async fn list_references() -> Result<Vec<ReferenceRoot>, Error> {
+    tokio::task::spawn_blocking(|| with_database(|db| ServiceApi::new().list_references(db)))
+        .await
+        .map_err(join_failed)?
+}
 
-    let mut results = Vec::new();
+/// Forget the index of a reference volume, returning how many files it held
+#[tauri::command]
+pub async fn remove_reference(path: String) -> Result<usize, Error> {
+    tokio::task::spawn_blocking(move || {
+        with_database(|db| ServiceApi::new().remove_reference(Path::new(&path), db))
+    })
+    .await
+    .map_err(join_failed)?
+}
 
-    // Convert plugin_orders to Option for process_file
-    let orders = if plugin_orders.is_empty() {
-        None
-    } else {
-        Some(plugin_orders.as_slice())
-    };
+/// Split duplicate groups into copies to keep and copies to delete under
+/// `rule`. Protected copies are always kept. Nothing is deleted; the
+/// frontend passes the delete lists to `delete_files` after confirmation.
+#[tauri::command]
+pub async fn resolve_duplicates(
+    groups: Vec<DuplicateGroup>,
+    rule: KeepRule,
+) -> Result<Vec<DuplicateResolution>, Error> {
+    let api = ServiceApi::new().with_protected_paths(protected_paths());
+    Ok(api.resolve_duplicates(&groups, rule.strategy().as_ref()))
+}
 
-    for path_str in file_paths {
-        let source = PathBuf::from(&path_str);
+/// What resolving duplicate groups with a keep rule would do
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolutionPreview {
+    /// The copies kept and removed per group; `apply_duplicate_resolution`
+    /// takes it back
+    pub plan: Vec<DuplicateResolution>,
+    pub keep_count: usize,
+    pub remove_count: usize,
+    /// Bytes removing them would free
+    pub reclaimable: u64,
+    /// Copies kept only because they are protected
+    pub protected_count: usize,
+}
 
-        if !source.exists() {
-            results.push(serde_json::json!({
-                "status": "failed",
-                "success": false,
-                "path": path_str,
-                "error": "File not found",
-            }));
-            continue;
-        }
+/// Preview resolving `groups` with `strategy` ("keep newest", ...): which
+/// copies stay, which go and the space that frees. Nothing changes on disk.
+#[tauri::command]
+pub async fn preview_duplicate_resolution(
+    groups: Vec<DuplicateGroup>,
+    strategy: KeepRule,
+) -> Result<ResolutionPreview, Error> {
+    let api = ServiceApi::new().with_protected_paths(protected_paths());
+    let plan = api.resolve_duplicates(&groups, strategy.strategy().as_ref());
+    let count = |files: fn(&DuplicateResolution) -> usize| plan.iter().map(files).sum();
+    Ok(ResolutionPreview {
+        keep_count: count(|r| r.keep.len()),
+        remove_count: count(|r| r.delete.len()),
+        protected_count: count(|r| r.protected.len()),
+        reclaimable: plan.iter().map(|r| r.reclaimable).sum(),
+        plan,
+    })
+}
 
-        let source_dir = source.parent().ok_or("Failed to get parent directory")?;
+/// What `apply_duplicate_resolution` does with the copies a plan removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeAction {
+    Trash,
+    Delete,
+    /// Replace them with hard links to the kept copy
+    Hardlink,
+}
 
-        // Only the plugins listed in plugin_orders are considered; the
-        // manager performs the backup before replacing anything
-        match manager.process_file(&source, source_dir, orders, create_backup) {
-            Ok(CompressionOutcome::Compressed(compress_result)) => {
-                // Any remembered no-reduction results for this path are stale
-                // (the file at this path was replaced or renamed away)
-                if let Ok(mut cache) = SKIP_CACHE.write() {
-                    cache.invalidate_path(&path_str);
-                }
-                results.push(serde_json::json!({
-                    "status": "compressed",
-                    "success": true,
-                    "path": compress_result.output_path.to_string_lossy(),
+/// Per-copy outcome of `apply_duplicate_resolution`
+#[derive(Debug, Clone, Serialize)]
+pub struct DedupeResult {
+    pub path: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub protected: bool,
+    /// Bytes freed
+    pub reclaimed: u64,
+}
+
+/// Carry out a plan from `preview_duplicate_resolution`: every copy it
+/// removes goes to the trash, is deleted, or becomes a hard link to the
+/// group's kept copy. Copies changed since the scan, or whose kept copy
+/// did, are left alone and reported failed, as are protected ones.
+#[tauri::command]
+pub async fn apply_duplicate_resolution(
+    plan: Vec<DuplicateResolution>,
+    action: DedupeAction,
+) -> Result<Vec<DedupeResult>, Error> {
+    let results = tokio::task::spawn_blocking(move || apply_resolution(&plan, action))
+        .await
+        .map_err(join_failed)?;
+    files_changed(results.iter().map(|result| &result.path));
+    Ok(results)
+}
+
+fn apply_resolution(plan: &[DuplicateResolution], action: DedupeAction) -> Vec<DedupeResult> {
+    let mode = match action {
+        DedupeAction::Hardlink => {
+            return ServiceApi::new()
+                .with_protected_paths(protected_paths())
+                .hardlink_duplicates(plan)
+                .into_iter()
+                .map(|result| DedupeResult {
+                    path: result.path,
+                    success: result.success,
+                    error: result.error,
+                    protected: result.protected,
+                    reclaimed: result.reclaimed,
+                })
+                .collect();
+        }
+        DedupeAction::Trash => DeleteMode::Trash,
+        DedupeAction::Delete => DeleteMode::Permanent,
+    };
+    let mut ops = FileOperations::new()
+        .with_protected_paths(protected_paths())
+        .with_hooks(hooks());
+    if let Some(db) = APP_DATABASE.as_ref() {
+        ops = ops.with_database(db.clone());
+    }
+    let sizes = plan.iter().flat_map(|r| r.delete.iter().map(|f| f.size));
+    ops.remove_duplicates(plan, mode)
+        .into_iter()
+        .zip(sizes)
+        .map(|(result, size)| DedupeResult {
+            reclaimed: if result.success { size } else { 0 },
+            path: result.path,
+            success: result.success,
+            error: result.error,
+            protected: result.protected,
+        })
+        .collect()
+}
+
+/// Deduplicate with copy-on-write clones instead of deleting: each copy in a
+/// resolution's delete list is replaced by a clone of its first kept copy.
+/// Files on file systems without clone support are reported `unsupported`
+/// and left as they were.
+#[tauri::command]
+pub async fn reflink_duplicates(
+    resolutions: Vec<DuplicateResolution>,
+) -> Result<Vec<ReflinkResult>, Error> {
+    tokio::task::spawn_blocking(move || {
+        files_changed(resolutions.iter().flat_map(|r| &r.delete).map(|f| &f.path));
+        ServiceApi::new()
+            .with_protected_paths(protected_paths())
+            .reflink_duplicates(&resolutions)
+    })
+    .await
+    .map_err(join_failed)
+}
+
+/// Whether the file system holding `path` supports copy-on-write clones
+#[tauri::command]
+pub async fn check_reflink_support(path: String) -> Result<bool, Error> {
+    tokio::task::spawn_blocking(move || ServiceApi::new().supports_reflink(Path::new(&path)))
+        .await
+        .map_err(join_failed)
+}
+
+/// Find similar media (images today; videos pending ffmpeg) across multiple
+/// paths in the background, returning the job id. `media_types` selects
+/// which kinds to scan ("Image"/"Video"); an empty list defaults to images.
+/// Progress arrives as `similar://progress` events and the
+/// `Vec<SimilarGroup>` as the `similar://done` event.
+#[tauri::command]
+pub async fn find_similar_media(
+    app: AppHandle,
+    paths: Vec<String>,
+    threshold: f32,
+    media_types: Vec<MediaKind>,
+    filter: Option<FilterConfig>,
+) -> Result<u64, Error> {
+    let task_type =
+        TaskType::FindSimilarImages(paths.iter().map(PathBuf::from).collect(), threshold);
+    Ok(spawn_job(
+        app,
+        "similar",
+        task_type,
+        |groups: &Vec<SimilarGroup>| format!("{} groups of similar files", groups.len()),
+        move |_, progress, cancel| {
+            check_similar_media(
+                paths,
+                threshold,
+                media_types,
+                filter,
+                Some(progress),
+                cancel,
+            )
+        },
+    ))
+}
+
+async fn check_similar_media(
+    paths: Vec<String>,
+    threshold: f32,
+    media_types: Vec<MediaKind>,
+    filter: Option<FilterConfig>,
+    progress: Option<mpsc::Sender<ProgressUpdate>>,
+    cancel: CancellationToken,
+) -> Result<Vec<SimilarGroup>, Error> {
+    let mut api = cached(ServiceApi::new())
+        .with_ignore_list(ignore_list())
+        .with_io_policy(background_io())
+        .with_cancel(cancel);
+    if let Some(progress) = progress {
+        api = api.with_progress(progress);
+    }
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.find_similar_media_in_paths(paths, threshold, media_types, filter)
+        .await
+}
+
+/// Write duplicate groups, similar groups, storage stats or a
+/// compressible-file scan to `path` as CSV, JSON or a self-contained HTML
+/// report, replacing any existing file
+#[tauri::command]
+pub async fn export_results(
+    results: ExportResults,
+    format: ExportFormat,
+    path: String,
+) -> Result<(), Error> {
+    tokio::task::spawn_blocking(move || {
+        ServiceApi::new().export_results(&results, format, Path::new(&path))
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Generate a PNG thumbnail for an image, returned as a `data:` URL the
+/// frontend can use directly as an `<img src>`. `max_size` bounds both
+/// dimensions (aspect ratio preserved). Errors for missing or non-image files.
+#[tauri::command]
+pub async fn read_image_thumbnail(path: String, max_size: u32) -> Result<String, Error> {
+    space_saver_core::thumbnail_data_url(&PathBuf::from(path), max_size).map_err(Error::from)
+}
+
+/// Size, timestamps, image dimensions, EXIF (camera, date taken, GPS) and,
+/// when ffprobe is available, video length, resolution and codec of `path`,
+/// for showing next to its thumbnail
+#[tauri::command]
+pub async fn get_file_details(path: String) -> Result<FileDetails, Error> {
+    tokio::task::spawn_blocking(move || {
+        let ffprobe =
+            space_saver_service::ffprobe_path(&space_saver_utils::Config::load_or_default());
+        space_saver_service::file_details(Path::new(&path), ffprobe.as_deref()).map_err(Error::from)
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Show `path` selected in the system file manager: Explorer, Finder, or
+/// its folder through the desktop's file manager elsewhere. Only for paths
+/// under a scanned folder, see [`scanned_path`].
+#[tauri::command]
+pub async fn reveal_in_explorer(path: String) -> Result<(), Error> {
+    tokio::task::spawn_blocking(move || {
+        let path = scanned_path(&path)?;
+        tauri_plugin_opener::reveal_item_in_dir(&path).map_err(|e| {
+            Error::FileOperation(format!("Could not reveal {}: {}", path.display(), e))
+        })
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Open `path` with the app the system associates with it. Only for paths
+/// under a scanned folder, see [`scanned_path`].
+#[tauri::command]
+pub async fn open_file(path: String) -> Result<(), Error> {
+    tokio::task::spawn_blocking(move || {
+        let path = scanned_path(&path)?;
+        tauri_plugin_opener::open_path(&path, None::<&str>)
+            .map_err(|e| Error::FileOperation(format!("Could not open {}: {}", path.display(), e)))
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// `path` resolved, when it lies under a folder scanned this session or
+/// recorded in the app database, or a duplicate check recorded the file
+/// itself. Keeps the webview from getting arbitrary files opened.
+fn scanned_path(path: &str) -> Result<PathBuf, Error> {
+    let resolved = std::fs::canonicalize(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Error::NotFound(format!("{} no longer exists", path)),
+        _ => Error::from(e),
+    })?;
+    let mut roots: Vec<PathBuf> = SCAN_RESULTS
+        .lock()
+        .map_err(Error::from)?
+        .iter()
+        .flat_map(|scan| scan.results.iter().map(|result| result.path.clone()))
+        .collect();
+    let api = recording(ServiceApi::new());
+    // Without a database only this session's scans count
+    if let Ok(scans) = api.recent_scans(100) {
+        roots.extend(scans.into_iter().map(|scan| PathBuf::from(scan.path)));
+    }
+    let under_root = roots
+        .iter()
+        .filter_map(|root| std::fs::canonicalize(root).ok())
+        .any(|root| resolved.starts_with(root));
+    if under_root || matches!(api.recorded_file(Path::new(path)), Ok(Some(_))) {
+        return Ok(resolved);
+    }
+    Err(Error::PermissionDenied(format!(
+        "{} is not in a scanned folder",
+        path
+    )))
+}
+
+/// Find empty files (0 bytes) and empty folders (no files anywhere beneath
+/// them, reported topmost-only) across multiple paths. `filter` applies to
+/// files only.
+#[tauri::command]
+pub async fn empty_folder_check(
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+) -> Result<EmptyScanResult, Error> {
+    let api = cached(ServiceApi::new());
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.find_empty_in_paths(paths, filter).await
+}
+
+/// Find broken (invalid or corrupted) files across multiple paths. Reports
+/// only files that are provably unusable — corrupted/truncated content, or
+/// content that does not match its extension. Empty files are excluded.
+#[tauri::command]
+pub async fn broken_file_check(
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+) -> Result<Vec<BrokenFile>, Error> {
+    let api = cached(ServiceApi::new());
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.find_broken_files_in_paths(paths, filter).await
+}
+
+/// The `limit` largest files across multiple paths, largest first
+#[tauri::command]
+pub async fn get_largest_files(
+    paths: Vec<String>,
+    limit: usize,
+    filter: Option<FilterConfig>,
+) -> Result<Vec<FileInfo>, Error> {
+    let api = cached(ServiceApi::new());
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.get_largest_files(paths, limit, filter).await
+}
+
+/// The `limit` largest folders beneath the given paths (sizes cover each
+/// whole subtree), largest first. The paths themselves are not listed.
+#[tauri::command]
+pub async fn get_largest_directories(
+    paths: Vec<String>,
+    limit: usize,
+) -> Result<Vec<DirectorySize>, Error> {
+    let api = ServiceApi::new();
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.get_largest_directories(paths, limit).await
+}
+
+/// A size-weighted directory tree per path, `depth` levels deep, with a
+/// file-type breakdown per node, for the treemap view
+#[tauri::command]
+pub async fn get_treemap(paths: Vec<String>, depth: usize) -> Result<Vec<TreemapNode>, Error> {
+    let api = ServiceApi::new();
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.get_treemap(paths, depth).await
+}
+
+/// Find files neither modified nor accessed for `older_than_days` across
+/// multiple paths, grouped by directory with the total reclaimable size
+#[tauri::command]
+pub async fn find_stale_files(
+    paths: Vec<String>,
+    older_than_days: u32,
+    filter: Option<FilterConfig>,
+) -> Result<StaleFilesReport, Error> {
+    let api = cached(ServiceApi::new());
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.find_stale_files(paths, older_than_days, filter).await
+}
+
+/// Find folders whose whole trees are identical across multiple paths, one
+/// group per duplicated tree with the largest waste first
+#[tauri::command]
+pub async fn find_duplicate_directories(
+    paths: Vec<String>,
+) -> Result<Vec<DuplicateDirectoryGroup>, Error> {
+    let api = ServiceApi::new();
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.find_duplicate_directories(paths).await
+}
+
+/// Delete one copy of a duplicated folder. Both trees are compared again
+/// first; a copy that no longer matches `keep`, or is protected, is left
+/// alone. `mode` defaults to "trash".
+#[tauri::command]
+pub async fn delete_duplicate_directory(
+    target: String,
+    keep: String,
+    mode: Option<DeleteMode>,
+) -> Result<DeleteResult, Error> {
+    let mode = mode.unwrap_or(DeleteMode::Trash);
+    tokio::task::spawn_blocking(move || {
+        files_changed([&target]);
+        ServiceApi::new()
+            .with_protected_paths(protected_paths())
+            .delete_duplicate_directory(Path::new(&target), Path::new(&keep), mode)
+    })
+    .await
+    .map_err(join_failed)
+}
+
+/// Find application caches and build output that are rebuilt when needed:
+/// browser, package manager and thumbnail caches, and the node_modules and
+/// target folders beneath `roots`, by category
+#[tauri::command]
+pub async fn scan_app_caches(roots: Vec<String>) -> Result<AppCacheReport, Error> {
+    let roots: Vec<PathBuf> = roots.into_iter().map(PathBuf::from).collect();
+    tokio::task::spawn_blocking(move || ServiceApi::new().scan_app_caches(&roots))
+        .await
+        .map_err(join_failed)?
+}
+
+/// What `clean_app_caches` would do with the `categories` of `report`,
+/// touching nothing. `mode` defaults to "trash".
+#[tauri::command]
+pub async fn plan_app_cache_cleaning(
+    report: AppCacheReport,
+    categories: Vec<CacheCategory>,
+    mode: Option<DeleteMode>,
+) -> Result<ActionPlan, Error> {
+    Ok(ServiceApi::new()
+        .with_protected_paths(protected_paths())
+        .plan_app_cache_cleaning(&report, &categories, mode.unwrap_or(DeleteMode::Trash)))
+}
+
+/// Remove the `categories` of a `scan_app_caches` report, reporting a
+/// per-folder outcome. Each folder is checked again first; one that is no
+/// longer a folder, no longer next to its project manifest, or protected is
+/// left alone. `mode` defaults to "trash".
+#[tauri::command]
+pub async fn clean_app_caches(
+    report: AppCacheReport,
+    categories: Vec<CacheCategory>,
+    mode: Option<DeleteMode>,
+) -> Result<Vec<DeleteResult>, Error> {
+    let mode = mode.unwrap_or(DeleteMode::Trash);
+    tokio::task::spawn_blocking(move || {
+        files_changed(report.locations(&categories).map(|l| &l.path));
+        ServiceApi::new()
+            .with_protected_paths(protected_paths())
+            .with_hooks(hooks())
+            .clean_app_caches(&report, &categories, mode)
+    })
+    .await
+    .map_err(join_failed)
+}
+
+/// Find project folders beneath `roots` with their build output (target,
+/// node_modules, dist, .venv) and when it was last built. Projects nobody
+/// touched for `untouched_months` (default 6) are marked `suggested`.
+#[tauri::command]
+pub async fn find_dev_artifacts(
+    roots: Vec<String>,
+    untouched_months: Option<u32>,
+) -> Result<DevArtifactReport, Error> {
+    let roots: Vec<PathBuf> = roots.into_iter().map(PathBuf::from).collect();
+    let months = untouched_months.unwrap_or(DEFAULT_UNTOUCHED_MONTHS);
+    tokio::task::spawn_blocking(move || ServiceApi::new().find_dev_artifacts(&roots, months))
+        .await
+        .map_err(join_failed)?
+}
+
+/// What `remove_dev_artifacts` would do with `report`, touching nothing.
+/// `mode` defaults to "trash".
+#[tauri::command]
+pub async fn plan_dev_artifact_removal(
+    report: DevArtifactReport,
+    mode: Option<DeleteMode>,
+) -> Result<ActionPlan, Error> {
+    Ok(ServiceApi::new()
+        .with_protected_paths(protected_paths())
+        .plan_dev_artifact_removal(&report, mode.unwrap_or(DeleteMode::Trash)))
+}
+
+/// Remove the build output of the projects a `find_dev_artifacts` report
+/// suggests, reporting a per-folder outcome. Each folder is checked again
+/// first; one that is no longer build output of its project, or is
+/// protected, is left alone. `mode` defaults to "trash".
+#[tauri::command]
+pub async fn remove_dev_artifacts(
+    report: DevArtifactReport,
+    mode: Option<DeleteMode>,
+) -> Result<Vec<DeleteResult>, Error> {
+    let mode = mode.unwrap_or(DeleteMode::Trash);
+    tokio::task::spawn_blocking(move || {
+        files_changed(report.suggested().map(|a| &a.path));
+        ServiceApi::new()
+            .with_protected_paths(protected_paths())
+            .with_hooks(hooks())
+            .remove_dev_artifacts(&report, mode)
+    })
+    .await
+    .map_err(join_failed)
+}
+
+/// Rename misnamed files (whose content does not match their extension) to the
+/// extension matching their real content, reporting a per-file outcome. This
+/// is the safe action for `extension_mismatch` results from `broken_file_check`
+/// — the file is valid, just named wrong, so it is renamed rather than deleted.
+#[tauri::command]
+pub async fn fix_file_extensions(paths: Vec<String>) -> Result<Vec<FixExtensionResult>, Error> {
+    let ops = FileOperations::new();
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    files_changed(&paths);
+
+    Ok(ops.fix_extensions(&paths))
+}
+
+/// Delete files, reporting a per-file outcome. `mode` defaults to "trash"
+/// (recoverable, and recorded so `space-saver restore` can bring files
+/// back); "permanent" removes from disk immediately. Protected paths are
+/// reported with `protected` set and left alone.
+#[tauri::command]
+pub async fn delete_files(
+    paths: Vec<String>,
+    mode: Option<DeleteMode>,
+) -> Result<Vec<DeleteResult>, Error> {
+    let mut ops = FileOperations::new()
+        .with_protected_paths(protected_paths())
+        .with_hooks(hooks());
+    if let Some(db) = APP_DATABASE.as_ref() {
+        ops = ops.with_database(db.clone());
+    }
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let mode = mode.unwrap_or(DeleteMode::Trash);
+    files_changed(&paths);
+
+    Ok(ops.delete_files_with_mode(&paths, mode))
+}
+
+/// What `delete_files` (or `quarantine_files`, for mode "quarantine") would
+/// do to `paths`, touching nothing: each file with the space it would free,
+/// and the ones it would refuse (protected, missing, a folder with files
+/// inside). `mode` defaults to "trash".
+#[tauri::command]
+pub async fn plan_delete_files(
+    paths: Vec<String>,
+    mode: Option<String>,
+) -> Result<ActionPlan, Error> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    plan_removal(
+        &paths,
+        mode.as_deref().unwrap_or("trash"),
+        protected_paths(),
+    )
+}
+
+fn plan_removal(
+    paths: &[PathBuf],
+    mode: &str,
+    protected: ProtectedPaths,
+) -> Result<ActionPlan, Error> {
+    let action = match mode {
+        "trash" => DeleteMode::Trash.action(),
+        "permanent" => DeleteMode::Permanent.action(),
+        "quarantine" => "quarantine",
+        other => {
+            return Err(Error::InvalidInput(format!(
+                "Unknown delete mode: {} (expected trash, permanent or quarantine)",
+                other
+            )))
+        }
+    };
+    Ok(FileOperations::new()
+        .with_protected_paths(protected)
+        .plan_removal(paths, action))
+}
+
+/// Move files into the quarantine instead of deleting them, reporting a
+/// per-file outcome like `delete_files`. They can be restored with
+/// `restore_quarantined` until the configured retention period ends.
+#[tauri::command]
+pub async fn quarantine_files(paths: Vec<String>) -> Result<Vec<DeleteResult>, Error> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    files_changed(&paths);
+    tokio::task::spawn_blocking(move || {
+        with_quarantine(|quarantine| {
+            quarantine
+                .purge_expired(space_saver_utils::time::now())
+                .map(|_| quarantine.quarantine_files(&paths))
+        })
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Quarantined files, newest first. Files past their retention period are
+/// purged first.
+#[tauri::command]
+pub async fn list_quarantine() -> Result<Vec<QuarantineRecord>, Error> {
+    tokio::task::spawn_blocking(|| {
+        with_quarantine(|quarantine| {
+            quarantine.purge_expired(space_saver_utils::time::now())?;
+            quarantine.list()
+        })
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Move a quarantined file back to its original path
+#[tauri::command]
+pub async fn restore_quarantined(id: i64) -> Result<QuarantineRecord, Error> {
+    let record =
+        tokio::task::spawn_blocking(move || with_quarantine(|quarantine| quarantine.restore(id)))
+            .await
+            .map_err(join_failed)??;
+    files_changed([&record.original_path]);
+    Ok(record)
+}
+
+/// Permanently delete quarantined files past their retention period,
+/// returning them
+#[tauri::command]
+pub async fn purge_quarantine() -> Result<Vec<QuarantineRecord>, Error> {
+    tokio::task::spawn_blocking(|| {
+        with_quarantine(|quarantine| quarantine.purge_expired(space_saver_utils::time::now()))
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Compressions that kept their original, with what each saved, newest
+/// first
+#[tauri::command]
+pub async fn list_compression_backups() -> Result<Vec<CompressionBackup>, Error> {
+    tokio::task::spawn_blocking(|| with_database(restore::compression_backups))
+        .await
+        .map_err(join_failed)?
+}
+
+/// Undo a compression from [`list_compression_backups`]: the backup
+/// replaces the compressed output
+#[tauri::command]
+pub async fn restore_compression(id: i64) -> Result<RestorePoint, Error> {
+    let restored =
+        tokio::task::spawn_blocking(move || with_database(|db| restore::restore_backup(db, id)))
+            .await
+            .map_err(join_failed)??;
+    files_changed(
+        [Some(&restored.path), restored.output_path.as_ref()]
+            .into_iter()
+            .flatten(),
+    );
+    Ok(restored)
+}
+
+/// Delete the backups of compressions made before `older_than` (a date or a
+/// duration such as "30d"); those compressions can no longer be undone
+#[tauri::command]
+pub async fn purge_backups(older_than: String) -> Result<BackupPurge, Error> {
+    let now = space_saver_utils::time::now();
+    let before = space_saver_utils::time::parse_time_bound(&older_than, now)
+        .map_err(|e| Error::InvalidInput(format!("{:#}", e)))?;
+    tokio::task::spawn_blocking(move || with_database(|db| restore::purge_backups(db, before)))
+        .await
+        .map_err(join_failed)?
+}
+
+/// Archive a file or directory into a ZIP (default) or 7z at `dest`,
+/// returning the archive size. With a password, contents are encrypted with
+/// AES-256; an existing `dest` is never overwritten.
+#[tauri::command]
+pub async fn create_archive(
+    source: String,
+    dest: String,
+    password: Option<String>,
+    format: Option<String>,
+) -> Result<u64, Error> {
+    let format: ArchiveFormat = match format {
+        Some(format) => format.parse::<ArchiveFormat>().map_err(Error::from)?,
+        None => ArchiveFormat::default(),
+    };
+    tokio::task::spawn_blocking(move || {
+        let source = PathBuf::from(source);
+        let dest = PathBuf::from(dest);
+        if !source.exists() {
+            return Err(Error::NotFound(format!(
+                "Source not found: {}",
+                source.display()
+            )));
+        }
+        if dest.exists() {
+            return Err(Error::InvalidInput(format!(
+                "Destination already exists: {}",
+                dest.display()
+            )));
+        }
+        Compressor::new_archive(format, password)
+            .and_then(|compressor| compressor.compress_path(&source, &dest))
+            .map_err(Error::from)
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Move a directory into cold storage: archive it to `dest` (tar.zst by
+/// default), verify the archive, record it in the app database and delete
+/// the original. The original is kept whenever any step fails.
+#[tauri::command]
+pub async fn archive_directory(
+    source: String,
+    dest: String,
+    format: Option<String>,
+    password: Option<String>,
+) -> Result<ArchiveRecord, Error> {
+    let format: ArchiveFormat = match format {
+        Some(format) => format.parse::<ArchiveFormat>().map_err(Error::from)?,
+        None => ArchiveFormat::TarZstd,
+    };
+    tokio::task::spawn_blocking(move || {
+        // Archive records live in the same database as the compression history
+        let history = database_connection()?;
+        let db = history.as_deref().ok_or_else(|| {
+            Error::Database(
+                "Database unavailable; refusing to archive without recording it".to_string(),
+            )
+        })?;
+        files_changed([&source]);
+        ServiceApi::new()
+            .with_partial_outputs(PARTIAL_OUTPUTS.clone())
+            .archive_directory(
+                &PathBuf::from(source),
+                &PathBuf::from(dest),
+                format,
+                password,
+                db,
+            )
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Get storage statistics across multiple paths
+#[tauri::command]
+pub async fn get_storage_stats(
+    paths: Vec<String>,
+    filter: Option<FilterConfig>,
+) -> Result<StorageStats, Error> {
+    let api = cached(ServiceApi::new());
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+
+    api.get_storage_stats_for_paths(paths, filter).await
+}
+
+/// Get available compression plugins
+#[tauri::command]
+pub async fn get_compression_plugins() -> Result<Vec<serde_json::Value>, Error> {
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    let manager = manager.read().map_err(Error::from)?;
+    let plugins = manager.get_plugins();
+
+    Ok(plugins
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "name": p.name,
+                "description": p.description,
+                "version": p.version,
+                "quality": manager.get_plugin_quality(&p.name),
+                "enabled": manager.is_plugin_enabled(&p.name),
+                "lossy": p.lossy,
+                "destructive": p.destructive,
+                "requires_external_tools": p.requires_external_tools,
+            })
+        })
+        .collect())
+}
+
+/// Set the quality (0-100) of a compression plugin. The new value is also
+/// written to the config file so it survives a restart (config is the single
+/// source of truth for quality; the plugin manager is seeded from it at boot).
+#[tauri::command]
+pub async fn set_plugin_quality(plugin_name: String, quality: f32) -> Result<(), Error> {
+    {
+        let manager = space_saver_core::compress_plugins::global_plugin_manager();
+        let mut manager = manager.write().map_err(Error::from)?;
+        manager
+            .set_plugin_quality(&plugin_name, quality)
+            .map_err(Error::from)?;
+    }
+    persist_plugin_quality(&config_path(), &plugin_name, quality)
+}
+
+/// Turn a compression plugin on or off. Disabled plugins are still listed
+/// but never picked for a file; the choice is saved to the config.
+#[tauri::command]
+pub async fn set_plugin_enabled(plugin_name: String, enabled: bool) -> Result<(), Error> {
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    let known = manager
+        .read()
+        .map_err(Error::from)?
+        .get_plugins()
+        .iter()
+        .any(|p| p.name == plugin_name);
+    if !known {
+        return Err(Error::NotFound(format!(
+            "Plugin not found: {}",
+            plugin_name
+        )));
+    }
+    let config = persist_plugin_enabled(&config_path(), &plugin_name, enabled)?;
+    apply_manager_settings(&config);
+    Ok(())
+}
+
+/// Record whether a plugin is enabled in the config file
+fn persist_plugin_enabled(
+    path: &std::path::Path,
+    plugin_name: &str,
+    enabled: bool,
+) -> Result<space_saver_utils::Config, Error> {
+    let mut config = load_config_from(path)?;
+    config.disabled_plugins.retain(|name| name != plugin_name);
+    if !enabled {
+        config.disabled_plugins.push(plugin_name.to_string());
+    }
+    save_config_to(path, &config)?;
+    Ok(config)
+}
+
+/// Record a plugin's quality in the config file. The stored value is clamped to
+/// match what the plugin manager applies, so config and runtime never diverge.
+fn persist_plugin_quality(
+    path: &std::path::Path,
+    plugin_name: &str,
+    quality: f32,
+) -> Result<(), Error> {
+    let mut config = load_config_from(path)?;
+    config
+        .plugin_quality
+        .insert(plugin_name.to_string(), quality.clamp(0.0, 100.0));
+    save_config_to(path, &config)
+}
+
+/// Seed the global plugin manager with the per-plugin qualities and manager
+/// settings saved in config. Called once at startup so persisted settings
+/// take effect. Unknown plugin names in config are ignored rather than
+/// failing the launch.
+pub fn seed_plugin_manager_from_config() {
+    let config = load_config_from(&config_path()).unwrap_or_default();
+    apply_manager_settings(&config);
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    let mut guard = match manager.write() {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+    for (name, quality) in &config.plugin_quality {
+        let _ = guard.set_plugin_quality(name, *quality);
+    }
+}
+
+/// Apply the config's manager-wide settings (worker count, required output
+/// verification, safe mode, metadata copying, minimum savings, tool paths)
+/// to the global plugin manager
+fn apply_manager_settings(config: &space_saver_utils::Config) {
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    if let Ok(mut guard) = manager.write() {
+        space_saver_service::compress::configure_manager(&mut guard, config);
+    }
+}
+
+/// Scan paths and find compressible files with estimates
+#[tauri::command]
+pub async fn scan_compressible_files(
+    paths: Vec<String>,
+    active_plugins: Vec<String>,
+    filter: Option<FilterConfig>,
+) -> Result<serde_json::Value, Error> {
+    // Get the global plugin manager
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    let manager = manager.read().map_err(Error::from)?;
+
+    // Step 1: Validate active plugins
+    let all_plugin_names: Vec<String> = manager
+        .get_plugins()
+        .iter()
+        .map(|p| p.name.clone())
+        .collect();
+    for plugin_name in &active_plugins {
+        if !all_plugin_names.contains(plugin_name) {
+            return Err(Error::NotFound(format!(
+                "Active plugin not found: {}",
+                plugin_name
+            )));
+        }
+    }
+
+    // Step 2: Merge plugin supported extensions with filterConfig
+    let mut supported_extensions = HashSet::new();
+    for plugin_name in &active_plugins {
+        let exts = manager.get_supported_extensions(plugin_name);
+        supported_extensions.extend(exts);
+    }
+
+    // Create or update filter config with plugin extensions
+    let mut merged_filter = filter.unwrap_or_default();
+    if !supported_extensions.is_empty() {
+        // If filter already has extensions, intersect them with plugin extensions
+        // Otherwise, use plugin extensions
+        if let Some(existing_exts) = &merged_filter.extensions {
+            let existing_set: HashSet<String> = existing_exts.iter().cloned().collect();
+            let intersection: Vec<String> = supported_extensions
+                .intersection(&existing_set)
+                .cloned()
+                .collect();
+            merged_filter.extensions = if intersection.is_empty() {
+                // If no intersection, use plugin extensions (plugin extensions take precedence)
+                Some(supported_extensions.into_iter().collect())
+            } else {
+                Some(intersection)
+            };
+        } else {
+            // No existing extensions, use plugin extensions
+            merged_filter.extensions = Some(supported_extensions.into_iter().collect());
+        }
+    }
+
+    // Reuse the walk of an earlier scan of these paths where there is one
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let all_files = cached(ServiceApi::new()).collect_files(&paths, Some(&merged_filter))?;
+
+    // Step 3: Try each active plugin (in order) on each file, collecting
+    // rejection reasons along the way in a single pass
+    let mut compressible_files = Vec::new();
+    let mut rejected_files = Vec::new();
+
+    let skip_cache = SKIP_CACHE.read().map_err(Error::from)?;
+    let history = database_connection()?;
+
+    for file_info in all_files {
+        let mut rejection_reasons = Vec::new();
+        let mut accepted = None;
+
+        // The scanner already stat'ed the file; reuse size + mtime
+        let fingerprint = FileFingerprint {
+            size: file_info.size,
+            mtime: file_info.modified,
+        };
+        let path_str = file_info.path.to_string_lossy().to_string();
+
+        // History: this exact file state was produced by a compression or
+        // the user chose not to compress it. No-reduction skips are left to
+        // the skip cache, which re-evaluates them when the quality changes.
+        let processed = history
+            .as_deref()
+            .and_then(|db| {
+                db.find_compression(&path_str, file_info.size, file_info.modified)
+                    .ok()
+                    .flatten()
+            })
+            .filter(|record| record.action != "skipped");
+        let plugins_to_try: &[String] = match processed {
+            Some(record) => {
+                rejection_reasons.push(serde_json::json!({
+                    "plugin_name": record.plugin_name.unwrap_or_default(),
+                    "reason": history_reason(&record.action, &record.source_path, &path_str),
+                }));
+                &[]
+            }
+            None => &active_plugins,
+        };
+
+        for plugin_name in plugins_to_try {
+            match manager.check_plugin_capability(&file_info.path, plugin_name) {
+                Ok(Some((metadata, can_handle, reason, estimate_ratio))) => {
+                    if can_handle {
+                        // Skip-cache: this exact file state already produced no
+                        // size reduction with this plugin at this quality
+                        let quality = manager.get_plugin_quality(plugin_name);
+                        if skip_cache.is_known_skip(&path_str, &fingerprint, plugin_name, quality) {
+                            rejection_reasons.push(serde_json::json!({
+                                "plugin_name": metadata.name,
+                                "reason": format!(
+                                    "Previously produced no size reduction{} (cached result; file unchanged)",
+                                    quality.map(|q| format!(" at quality {}", q)).unwrap_or_default()
+                                ),
+                            }));
+                            continue;
+                        }
+
+                        let ratio = estimate_ratio.unwrap_or(0.0);
+                        let estimated_compressed =
+                            (file_info.size as f64 * (1.0 - ratio as f64)) as u64;
+                        let estimated_savings = file_info.size.saturating_sub(estimated_compressed);
+
+                        accepted = Some(serde_json::json!({
+                            "path": file_info.path.to_string_lossy(),
+                            "original_size": file_info.size,
+                            "estimated_compressed_size": estimated_compressed,
+                            "estimated_savings": estimated_savings,
+                            "plugin_name": metadata.name,
+                            "can_handle": true,
+                            "reason": reason,
+                        }));
+                        break;
+                    }
+
+                    rejection_reasons.push(serde_json::json!({
+                        "plugin_name": metadata.name,
+                        "reason": reason.unwrap_or_else(|| "Unknown reason".to_string()),
+                    }));
+                }
+                // Plugin not found (already validated above), skip
+                Ok(None) => continue,
+                // A plugin failing on one file (e.g. a corrupt archive) must
+                // not abort the whole scan; record it as a rejection reason
+                Err(e) => {
+                    rejection_reasons.push(serde_json::json!({
+                        "plugin_name": plugin_name,
+                        "reason": format!("Error: {}", e),
+                    }));
+                }
+            }
+        }
+
+        match accepted {
+            Some(compress_info) => compressible_files.push(compress_info),
+            None => {
+                if !rejection_reasons.is_empty() {
+                    let extension = file_info
+                        .path
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .unwrap_or("");
+                    rejected_files.push(serde_json::json!({
+                        "path": file_info.path.to_string_lossy(),
+                        "size": file_info.size,
+                        "extension": extension,
+                        "rejection_reasons": rejection_reasons,
+                    }));
+                }
+            }
+        }
+    }
+
+    Ok(serde_json::json!({
+        "compressible": compressible_files,
+        "rejected": rejected_files,
+    }))
+}
+
+/// Measure what compressing the files under `paths` with `plugins` would
+/// save: up to `sample_count` files (5 by default) of each plugin and
+/// extension are converted into a temporary directory and their savings
+/// extrapolated to the rest. The files themselves are left alone.
+#[tauri::command]
+pub async fn estimate_compression(
+    paths: Vec<String>,
+    plugins: Vec<String>,
+    sample_count: Option<usize>,
+) -> Result<CompressionEstimate, Error> {
+    tokio::task::spawn_blocking(move || {
+        let manager = space_saver_core::compress_plugins::global_plugin_manager();
+        let manager = manager.read().map_err(Error::from)?;
+        let known: Vec<String> = manager.get_plugins().into_iter().map(|p| p.name).collect();
+        if let Some(unknown) = plugins.iter().find(|name| !known.contains(name)) {
+            return Err(Error::NotFound(format!("Plugin not found: {}", unknown)));
+        }
+
+        let extensions: HashSet<String> = plugins
+            .iter()
+            .flat_map(|name| manager.get_supported_extensions(name))
+            .collect();
+        let filter = FilterConfig {
+            extensions: Some(extensions.into_iter().collect()),
+            ..Default::default()
+        };
+        let roots: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        let files = cached(ServiceApi::new()).collect_files(&roots, Some(&filter))?;
+        let sample_count =
+            sample_count.unwrap_or(space_saver_service::estimate::DEFAULT_SAMPLE_COUNT);
+        space_saver_service::estimate_compression(
+            &manager,
+            files,
+            &plugins,
+            sample_count,
+            &ProgressSink::new(),
+        )
+        .map_err(Error::from)
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// Compress files in place. With `create_backup` the original is kept as
+/// `<name>.bak` next to the output; without it the original is deleted once
+/// compression fully succeeds (failures and skips never touch it). Each file
+/// ends up in one of five states: "compressed", "skipped" (output was not
+/// smaller, original kept untouched), "failed", "timed_out" (exceeded the
+/// per-file timeout, original kept untouched) or "cancelled" (stopped by
+/// `cancel_compression` before the original was touched).
+///
+/// Runs in the background and returns the job id; the per-file results
+/// arrive as the `compress://done` event. While a file is being processed,
+/// plugin progress is emitted both as `compress://file-progress` events
+/// carrying a `PluginProgress` and as `compress://progress` job events.
+/// Every file also emits a `compress://file` event when it starts and one
+/// when it ends, with its status, savings or reason and how much of the
+/// batch is done.
+#[tauri::command]
+pub async fn compress_files_in_place(
+    app: AppHandle,
+    file_paths: Vec<String>,
+    plugin_orders: Vec<String>, // Ordered list of active plugin names
+    create_backup: bool,        // false: delete the original once compression succeeds
+) -> Result<u64, Error> {
+    let task_type = TaskType::CompressFiles(file_paths.iter().map(PathBuf::from).collect());
+    let emitter = app.clone();
+    Ok(spawn_job(
+        app,
+        "compress",
+        task_type,
+        |results: &Vec<serde_json::Value>| {
+            let outcome = compression_hook_outcome(results);
+            format!(
+                "{} files done, {} saved, {} failed",
+                outcome.succeeded,
+                format_size(outcome.freed),
+                outcome.failed
+            )
+        },
+        move |job_id, progress, cancel| async move {
+            // Plugins block (image encoding, external tools); keep them off the
+            // async runtime so cancel_compression can run while a batch is in flight
+            tokio::task::spawn_blocking(move || {
+                let run = CompressionRun::register(cancel);
+                let forward = space_saver_service::forward_progress(progress);
+                let files = emitter.clone();
+                let sink = ProgressSink::new()
+                    .with_cancel(run.token.clone())
+                    .on_progress(move |progress: &PluginProgress| {
+                        // Progress is best effort; a closed window must not fail the run
+                        let _ = emitter.emit(COMPRESS_FILE_PROGRESS_EVENT, progress);
+                        forward(progress);
+                    });
+                let on_file = move |event: CompressionFileEvent| {
+                    if event.status == "compressed" {
+                        files_changed([&event.path]);
+                    }
+                    let _ = files.emit(
+                        COMPRESS_FILE_EVENT,
+                        CompressionFileEvent { job_id, ..event },
+                    );
+                };
+                compress_files_blocking(file_paths, plugin_orders, create_backup, &sink, &on_file)
+            })
+            .await
+            .map_err(join_failed)?
+        },
+    ))
+}
+
+/// Cancel every compression run in flight. Files already compressed stay
+/// compressed; the file being processed has its partial output removed and,
+/// like every file not yet started, is reported as "cancelled". Returns how
+/// many runs were signalled.
+#[tauri::command]
+pub async fn cancel_compression() -> Result<usize, Error> {
+    let runs = ACTIVE_COMPRESSIONS.lock().map_err(Error::from)?;
+    for token in runs.values() {
+        token.cancel();
+    }
+    Ok(runs.len())
+}
+
+/// Background jobs started by scan, duplicate, similar and compress
+/// commands: running ones and the most recently finished
+#[tauri::command]
+pub async fn list_jobs() -> Result<Vec<JobInfo>, Error> {
+    Ok(ServiceApi::new().with_jobs(JOBS.clone()).list_jobs())
+}
+
+#[tauri::command]
+pub async fn get_job_status(job_id: JobId) -> Result<JobInfo, Error> {
+    ServiceApi::new().with_jobs(JOBS.clone()).job_status(job_id)
+}
+
+/// What closing the app last time interrupted: jobs cut short and partial
+/// outputs removed. Returned once; `None` after a clean exit.
+#[tauri::command]
+pub async fn take_shutdown_report() -> Result<Option<ShutdownReport>, Error> {
+    ShutdownReport::take(&shutdown_report_path()).map_err(Error::from)
+}
+
+/// Ask a job to stop; it finishes with a `Cancelled` progress event. Returns
+/// false when the job had already finished.
+#[tauri::command]
+pub async fn cancel_job(job_id: JobId) -> Result<bool, Error> {
+    ServiceApi::new().with_jobs(JOBS.clone()).cancel_job(job_id)
+}
+
+/// Body of `compress_files_in_place`, run on a blocking thread. Split from the
+/// command so tests can drive it with their own sink (cancellation token and
+/// progress callback). `on_file` hears when each file starts and how it
+/// ended.
+fn compress_files_blocking(
+    file_paths: Vec<String>,
+    plugin_orders: Vec<String>,
+    create_backup: bool,
+    sink: &ProgressSink,
+    on_file: &dyn Fn(CompressionFileEvent),
+) -> Result<Vec<serde_json::Value>, Error> {
+    // Get the global plugin manager (all plugins pre-registered with priorities)
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    let manager = manager.read().map_err(Error::from)?;
+
+    let protected = protected_paths();
+    let io = background_io();
+
+    // Convert plugin_orders to Option for process_file
+    let orders = if plugin_orders.is_empty() {
+        None
+    } else {
+        Some(plugin_orders.as_slice())
+    };
+
+    let total = file_paths.len();
+    let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    let results = hooks()
+        .around(
+            "compress",
+            &paths,
+            || -> Result<Vec<serde_json::Value>, Error> {
+                let mut results = Vec::new();
+                for (index, path_str) in file_paths.into_iter().enumerate() {
+                    let result = if sink.is_cancelled() {
+                        cancelled_result(&path_str)
+                    } else {
+                        on_file(CompressionFileEvent::started(&path_str, index, total));
+                        compress_file(
+                            &manager,
+                            &path_str,
+                            orders,
+                            create_backup,
+                            &protected,
+                            &io,
+                            sink,
+                        )?
+                    };
+                    on_file(CompressionFileEvent::finished(
+                        &path_str, index, total, &result,
+                    ));
+                    results.push(result);
+                }
+                Ok(results)
+            },
+            |results| match results {
+                Ok(results) => compression_hook_outcome(results),
+                Err(_) => HookOutcome {
+                    failed: total,
+                    ..Default::default()
+                },
+            },
+        )
+        .map_err(Error::from)??;
+
+    // Persist new skip-cache entries; the cache is an optimization, so a
+    // failed save must not fail the compression that already happened
+    if let Ok(mut cache) = SKIP_CACHE.write() {
+        if let Err(e) = cache.save() {
+            tracing::warn!(error = %e, "Failed to persist compression skip cache");
+        }
+    }
+
+    Ok(results)
+}
+
+/// How a `compress_files_blocking` batch went, for the after "compress"
+/// hooks: compressed and skipped files succeeded, and each compressed one
+/// freed what it saved
+fn compression_hook_outcome(results: &[serde_json::Value]) -> HookOutcome {
+    let mut outcome = HookOutcome::default();
+    for result in results {
+        match result["status"].as_str() {
+            Some("compressed") => {
+                outcome.succeeded += 1;
+                outcome.freed += result["savings"].as_u64().unwrap_or(0);
+            }
+            Some("skipped") => outcome.succeeded += 1,
+            _ => outcome.failed += 1,
+        }
+    }
+    outcome
+}
+
+/// Compress one file of a `compress_files_blocking` batch, recording the
+/// outcome in the history, skip cache and savings ledger. Per-file failures
+/// are part of the returned result.
+fn compress_file(
+    manager: &PluginManager,
+    path_str: &str,
+    orders: Option<&[String]>,
+    create_backup: bool,
+    protected: &ProtectedPaths,
+    io: &IoPolicy,
+    sink: &ProgressSink,
+) -> Result<serde_json::Value, Error> {
+    use space_saver_core::CompressionOutcome;
+
+    let source = PathBuf::from(path_str);
+    let path_str = path_str.to_string();
+
+    if let Err(e) = protected.check(&source) {
+        return Ok(serde_json::json!({
+            "status": "failed",
+            "success": false,
+            "path": path_str,
+            "error": e.to_string(),
+            "error_code": ErrorCode::Protected,
+            "protected": true,
+        }));
+    }
+
+    if !source.exists() {
+        return Ok(serde_json::json!({
+            "status": "failed",
+            "success": false,
+            "path": path_str,
+            "error": "File not found",
+            "error_code": ErrorCode::NotFound,
+        }));
+    }
+
+    let source_dir = source
+        .parent()
+        .ok_or_else(|| Error::InvalidInput(format!("No parent directory: {}", source.display())))?;
+
+    // Plugins read the file their own way; charge its size up front
+    let _priority = io.enter();
+    io.consume(source.metadata().map(|m| m.len()).unwrap_or(0));
+
+    // Only the plugins listed in plugin_orders are considered; the
+    // manager performs the backup before replacing anything
+    Ok(
+        match manager.process_file(&source, source_dir, orders, create_backup, Some(sink)) {
+            Ok(CompressionOutcome::Compressed(compress_result)) => {
+                // Any remembered no-reduction results for this path are stale
+                // (the file at this path was replaced or renamed away)
+                if let Ok(mut cache) = SKIP_CACHE.write() {
+                    cache.invalidate_path(&path_str);
+                }
+                record_savings(&source, &compress_result);
+                if let Ok(fingerprint) = FileFingerprint::of(&compress_result.output_path) {
+                    let mut record = CompressionRecord::new(
+                        path_str.clone(),
+                        compress_result.output_path.to_string_lossy().to_string(),
+                        "compressed",
+                        compress_result.original_size,
+                        fingerprint.size,
+                        fingerprint.mtime,
+                    );
+                    record.plugin_name = Some(compress_result.plugin_name.clone());
+                    record.compressed_size = Some(compress_result.compressed_size);
+                    record_compression(record);
+                }
+                serde_json::json!({
+                    "status": "compressed",
+                    "success": true,
+                    "path": compress_result.output_path.to_string_lossy(),
                     "backup_path": compress_result.backup_path.as_ref().map(|p| p.to_string_lossy()),
                     "original_size": compress_result.original_size,
                     "compressed_size": compress_result.compressed_size,
                     "savings": compress_result.original_size.saturating_sub(compress_result.compressed_size),
                     "plugin_name": compress_result.plugin_name,
-                }));
+                    "metadata_preserved": compress_result.metadata_preserved,
+                    "extra_outputs": compress_result.extra_outputs,
+                })
             }
             Ok(CompressionOutcome::Skipped {
                 plugin_name,
@@ -501,566 +2519,1980 @@ pub async fn compress_files_in_place(
                     if let Ok(mut cache) = SKIP_CACHE.write() {
                         cache.record_skip(&path_str, fingerprint, &plugin_name, quality);
                     }
+                    let mut record = CompressionRecord::new(
+                        path_str.clone(),
+                        path_str.clone(),
+                        "skipped",
+                        fingerprint.size,
+                        fingerprint.size,
+                        fingerprint.mtime,
+                    );
+                    record.plugin_name = Some(plugin_name.clone());
+                    record_compression(record);
                 }
-                results.push(serde_json::json!({
+                serde_json::json!({
                     "status": "skipped",
                     "success": true,
                     "path": path_str,
                     "plugin_name": plugin_name,
                     "reason": reason,
-                }));
+                })
+            }
+            Err(e) if e.is::<Cancelled>() => cancelled_result(&path_str),
+            Err(e) if e.is::<TimedOut>() => {
+                serde_json::json!({
+                    "status": "timed_out",
+                    "success": false,
+                    "path": path_str,
+                    "error": e.to_string(),
+                    "error_code": ErrorCode::TimedOut,
+                })
             }
             Err(e) => {
-                results.push(serde_json::json!({
+                let error = Error::from(e);
+                serde_json::json!({
                     "status": "failed",
                     "success": false,
                     "path": path_str,
-                    "error": e.to_string(),
-                }));
+                    "error": error.to_string(),
+                    "error_code": error.code(),
+                })
             }
+        },
+    )
+}
+
+fn cancelled_result(path: &str) -> serde_json::Value {
+    serde_json::json!({
+        "status": "cancelled",
+        "success": false,
+        "path": path,
+        "error": "Cancelled",
+        "error_code": ErrorCode::Cancelled,
+    })
+}
+
+/// Rejection reason for a file excluded by the compression history
+fn history_reason(action: &str, source_path: &str, path: &str) -> String {
+    match action {
+        "user_skipped" => {
+            "You chose not to compress this file (history; file unchanged)".to_string()
+        }
+        _ if source_path != path => format!(
+            "Already compressed from {} (history; file unchanged)",
+            source_path
+        ),
+        _ => "Already compressed (history; file unchanged)".to_string(),
+    }
+}
+
+/// Space saved by every compression so far, in total, per plugin and per
+/// month. Clearing the compression history does not reset it.
+#[tauri::command]
+pub async fn get_savings_summary() -> Result<SavingsSummary, Error> {
+    tokio::task::spawn_blocking(|| recording(ServiceApi::new()).savings_summary())
+        .await
+        .map_err(join_failed)?
+}
+
+/// Number of remembered no-size-reduction results
+#[tauri::command]
+pub async fn get_skip_cache_info() -> Result<serde_json::Value, Error> {
+    let cache = SKIP_CACHE.read().map_err(Error::from)?;
+    let history = database_connection()?;
+    let history_entries = match history.as_deref() {
+        Some(db) => db.count_compressions().map_err(Error::from)?,
+        None => 0,
+    };
+    Ok(serde_json::json!({
+        "entries": cache.len(),
+        "history_entries": history_entries,
+    }))
+}
+
+/// Forget all remembered no-size-reduction results and the compression
+/// history, so every file is evaluated afresh; returns how many were removed
+#[tauri::command]
+pub async fn clear_skip_cache() -> Result<usize, Error> {
+    let mut cache = SKIP_CACHE.write().map_err(Error::from)?;
+    let mut removed = cache.clear();
+    cache.save().map_err(Error::from)?;
+
+    let history = database_connection()?;
+    if let Some(db) = history.as_deref() {
+        removed += db.clear_compressions().map_err(Error::from)?;
+    }
+    Ok(removed)
+}
+
+/// Record the user's decision not to compress these files, so scans leave
+/// them out until they change. Missing files are ignored; returns how many
+/// were recorded.
+#[tauri::command]
+pub async fn skip_compression_files(file_paths: Vec<String>) -> Result<usize, Error> {
+    let mut recorded = 0;
+    for path_str in file_paths {
+        let Ok(fingerprint) = FileFingerprint::of(std::path::Path::new(&path_str)) else {
+            continue;
+        };
+        record_compression(CompressionRecord::new(
+            path_str.clone(),
+            path_str,
+            "user_skipped",
+            fingerprint.size,
+            fingerprint.size,
+            fingerprint.mtime,
+        ));
+        recorded += 1;
+    }
+    Ok(recorded)
+}
+
+/// Location of the on-disk config file (the single source of truth for settings)
+fn config_path() -> PathBuf {
+    space_saver_utils::Config::default_path()
+}
+
+/// Load config from a path, falling back to defaults when the file is absent.
+/// Split from the command so it can be tested against a temp path.
+fn load_config_from(path: &std::path::Path) -> Result<space_saver_utils::Config, Error> {
+    if path.exists() {
+        space_saver_utils::Config::load(path).map_err(Error::from)
+    } else {
+        Ok(space_saver_utils::Config::default())
+    }
+}
+
+/// Validate then persist config to a path. Split from the command so it can be
+/// tested against a temp path without touching the real user config.
+fn save_config_to(path: &std::path::Path, config: &space_saver_utils::Config) -> Result<(), Error> {
+    config
+        .validate()
+        .map_err(|e| Error::InvalidInput(format!("{:#}", e)))?;
+    config.save(path).map_err(Error::from)
+}
+
+/// Write the default configuration to a path, returning it. Split from the
+/// command so it can be tested against a temp path.
+fn reset_config_at(path: &std::path::Path) -> Result<space_saver_utils::Config, Error> {
+    let config = space_saver_utils::Config::default();
+    config.save(path).map_err(Error::from)?;
+    Ok(config)
+}
+
+/// Get the current application configuration (or defaults if none saved yet)
+#[tauri::command]
+pub async fn get_config() -> Result<space_saver_utils::Config, Error> {
+    load_config_from(&config_path())
+}
+
+/// Validate and persist the application configuration, returning what was saved
+#[tauri::command]
+pub async fn set_config(
+    config: space_saver_utils::Config,
+) -> Result<space_saver_utils::Config, Error> {
+    save_config_to(&config_path(), &config)?;
+    apply_live_settings(&config);
+    Ok(config)
+}
+
+/// What `update_config` saved
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigUpdate {
+    pub config: space_saver_utils::Config,
+    /// Changed settings that only take effect once the app (or, for the
+    /// daemon's, the daemon) restarts
+    pub restart_required: Vec<String>,
+}
+
+/// Settings read once at startup; every other one applies at once
+const RESTART_SETTINGS: [&str; 4] = ["database_path", "cache_dir", "daemon_log", "daemon_socket"];
+
+/// Change some settings: `changes` is a partial config, whose tables merge
+/// into the current ones (see `Config::update`). The result must validate
+/// and the folders it newly points at must be writable; the log level and
+/// plugin settings such as concurrency apply without a restart.
+#[tauri::command]
+pub async fn update_config(changes: serde_json::Value) -> Result<ConfigUpdate, Error> {
+    let update = update_config_at(&config_path(), &changes)?;
+    apply_live_settings(&update.config);
+    Ok(update)
+}
+
+/// Reset the configuration to defaults, persisting and returning them
+#[tauri::command]
+pub async fn reset_config() -> Result<space_saver_utils::Config, Error> {
+    let config = reset_config_at(&config_path())?;
+    apply_live_settings(&config);
+    Ok(config)
+}
+
+/// Apply `changes` to the config at a path and persist it. Split from the
+/// command so it can be tested against a temp path.
+fn update_config_at(
+    path: &std::path::Path,
+    changes: &serde_json::Value,
+) -> Result<ConfigUpdate, Error> {
+    let previous = load_config_from(path)?;
+    let mut config = previous.clone();
+    config.update(changes).map_err(Error::from)?;
+    let folders = |config: &space_saver_utils::Config| {
+        [
+            config.database_path.parent().map(Path::to_path_buf),
+            Some(config.cache_dir.clone()),
+            Some(config.quarantine_dir.clone()),
+            config.daemon_log.parent().map(Path::to_path_buf),
+        ]
+    };
+    for (folder, before) in folders(&config).into_iter().zip(folders(&previous)) {
+        match folder {
+            Some(folder) if Some(&folder) != before.as_ref() => check_writable(&folder)?,
+            _ => {}
+        }
+    }
+    config.save(path).map_err(Error::from)?;
+
+    let (before, after) = (
+        serde_json::to_value(&previous)?,
+        serde_json::to_value(&config)?,
+    );
+    let restart_required = RESTART_SETTINGS
+        .iter()
+        .filter(|key| before.get(**key) != after.get(**key))
+        .map(|key| key.to_string())
+        .collect();
+    Ok(ConfigUpdate {
+        config,
+        restart_required,
+    })
+}
+
+/// Fail unless files can be written into `folder`, creating it if needed
+fn check_writable(folder: &Path) -> Result<(), Error> {
+    let probe = folder.join(format!(".space-saver-probe-{}", std::process::id()));
+    std::fs::create_dir_all(folder)
+        .and_then(|_| std::fs::write(&probe, b""))
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|e| Error::InvalidInput(format!("{} is not writable: {}", folder.display(), e)))
+}
+
+/// Apply the settings that take effect without a restart: the log level
+/// and the plugin manager's
+fn apply_live_settings(config: &space_saver_utils::Config) {
+    if let Err(e) = space_saver_utils::set_log_level(&config.log_level) {
+        tracing::warn!(level = %config.log_level, error = %e, "Failed to change the log level");
+    }
+    apply_manager_settings(config);
+}
+
+/// The cleanup rules from the config, in order
+#[tauri::command]
+pub async fn get_cleanup_rules() -> Result<Vec<CleanupRule>, Error> {
+    Ok(load_config_from(&config_path())?.cleanup_rules)
+}
+
+/// Validate and persist the cleanup rules (replacing all of them), returning
+/// what was saved
+#[tauri::command]
+pub async fn set_cleanup_rules(rules: Vec<CleanupRule>) -> Result<Vec<CleanupRule>, Error> {
+    let path = config_path();
+    let config = space_saver_utils::Config {
+        cleanup_rules: rules,
+        ..load_config_from(&path)?
+    };
+    save_config_to(&path, &config)?;
+    Ok(config.cleanup_rules)
+}
+
+/// Run the enabled cleanup rules, or only those named in `names`. With
+/// `dry_run` nothing is touched and the report is the plan.
+#[tauri::command]
+pub async fn run_cleanup(
+    names: Option<Vec<String>>,
+    dry_run: bool,
+) -> Result<CleanupReport, Error> {
+    let rules = select_rules(load_config_from(&config_path())?.cleanup_rules, names)?;
+    run_cleanup_rules(rules, dry_run).await
+}
+
+/// The rules named in `names` (all of them when None), refusing unknown names
+fn select_rules(
+    rules: Vec<CleanupRule>,
+    names: Option<Vec<String>>,
+) -> Result<Vec<CleanupRule>, Error> {
+    let Some(names) = names else {
+        return Ok(rules);
+    };
+    if let Some(unknown) = names.iter().find(|n| !rules.iter().any(|r| &r.name == *n)) {
+        return Err(Error::NotFound(format!(
+            "Unknown cleanup rule: {}",
+            unknown
+        )));
+    }
+    Ok(rules
+        .into_iter()
+        .filter(|rule| names.contains(&rule.name))
+        .collect())
+}
+
+async fn run_cleanup_rules(rules: Vec<CleanupRule>, dry_run: bool) -> Result<CleanupReport, Error> {
+    tokio::task::spawn_blocking(move || {
+        if !dry_run {
+            files_changed(rules.iter().flat_map(|rule| &rule.paths));
+        }
+        with_database(|db| {
+            ServiceApi::new()
+                .with_protected_paths(protected_paths())
+                .with_hooks(hooks())
+                .run_cleanup(&rules, dry_run, db)
+        })
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+/// A schedule from the config, with what the daemon reports of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub schedule: Schedule,
+    /// None when no daemon is running, or it runs without this schedule
+    /// (disabled, or added since it started)
+    pub status: Option<ScheduleStatus>,
+}
+
+/// Outcome of run_schedule_now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    pub name: String,
+    /// Queued on the running daemon; the outcome then shows in
+    /// list_schedules
+    pub on_daemon: bool,
+    /// What the run did, when it ran in the app
+    pub result: Option<String>,
+}
+
+/// What the daemon listening on the config's socket reports; None when no
+/// daemon answers
+async fn daemon_status(config: &space_saver_utils::Config) -> Option<DaemonStatus> {
+    daemon::send_command(&config.daemon_socket, "status")
+        .await
+        .ok()?
+        .status
+}
+
+/// The schedules from the config, in order, with the daemon's view of them
+#[tauri::command]
+pub async fn list_schedules() -> Result<Vec<ScheduleEntry>, Error> {
+    list_schedules_at(&config_path()).await
+}
+
+async fn list_schedules_at(path: &Path) -> Result<Vec<ScheduleEntry>, Error> {
+    let config = load_config_from(path)?;
+    let status = daemon_status(&config).await;
+    Ok(config
+        .schedules
+        .into_iter()
+        .map(|schedule| ScheduleEntry {
+            status: status
+                .as_ref()
+                .and_then(|s| s.schedules.iter().find(|s| s.name == schedule.name))
+                .cloned(),
+            schedule,
+        })
+        .collect())
+}
+
+/// Validate and add a schedule to the config, returning it. A running
+/// daemon picks it up when restarted.
+#[tauri::command]
+pub async fn create_schedule(schedule: Schedule) -> Result<Schedule, Error> {
+    create_schedule_at(&config_path(), schedule)
+}
+
+fn create_schedule_at(path: &Path, schedule: Schedule) -> Result<Schedule, Error> {
+    let mut config = load_config_from(path)?;
+    config.schedules.push(schedule.clone());
+    save_config_to(path, &config)?;
+    Ok(schedule)
+}
+
+/// Remove the schedule called `name` from the config. A running daemon
+/// keeps running it until restarted.
+#[tauri::command]
+pub async fn delete_schedule(name: String) -> Result<(), Error> {
+    delete_schedule_at(&config_path(), &name)
+}
+
+fn delete_schedule_at(path: &Path, name: &str) -> Result<(), Error> {
+    let mut config = load_config_from(path)?;
+    let count = config.schedules.len();
+    config.schedules.retain(|s| s.name != name);
+    if config.schedules.len() == count {
+        return Err(Error::NotFound(format!("Unknown schedule: {}", name)));
+    }
+    save_config_to(path, &config)
+}
+
+/// Run the schedule called `name` now: queued on the daemon when one is
+/// running, otherwise run in the app, waiting for it to finish
+#[tauri::command]
+pub async fn run_schedule_now(name: String) -> Result<ScheduleRun, Error> {
+    run_schedule_now_at(&config_path(), name).await
+}
+
+async fn run_schedule_now_at(path: &Path, name: String) -> Result<ScheduleRun, Error> {
+    let config = load_config_from(path)?;
+    let schedule = config
+        .schedules
+        .iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| Error::NotFound(format!("Unknown schedule: {}", name)))?;
+    if daemon_status(&config).await.is_some() {
+        let response = daemon::send_command(&config.daemon_socket, &format!("run {}", name))
+            .await
+            .map_err(Error::from)?;
+        if !response.ok {
+            return Err(Error::InvalidInput(response.error.unwrap_or_default()));
+        }
+        return Ok(ScheduleRun {
+            name,
+            on_daemon: true,
+            result: None,
+        });
+    }
+    let db = APP_DATABASE
+        .clone()
+        .ok_or_else(|| Error::Database("Database unavailable".to_string()))?;
+    // Its cleanup rules may have removed anything
+    SCAN_CACHE.clear();
+    let result = space_saver_service::run_schedule_once(
+        schedule,
+        &config.cleanup_rules,
+        db,
+        protected_paths(),
+        hooks(),
+    )
+    .await
+    .map_err(Error::from)?;
+    Ok(ScheduleRun {
+        name,
+        on_daemon: false,
+        result: Some(result),
+    })
+}
+
+/// The next `count` runs of the daemon's schedules, soonest first. Empty
+/// when no daemon is running: schedules only run on the daemon.
+#[tauri::command]
+pub async fn get_next_runs(count: usize) -> Result<Vec<UpcomingRun>, Error> {
+    let config = load_config_from(&config_path())?;
+    Ok(daemon_status(&config)
+        .await
+        .map(|status| status.upcoming_runs(count))
+        .unwrap_or_default())
+}
+
+/// Mounted drives with their capacity, free space, file system and kind
+/// (fixed, removable or network), for the starting view
+#[tauri::command]
+pub async fn list_drives() -> Result<Vec<space_saver_service::DiskInfo>, Error> {
+    tokio::task::spawn_blocking(space_saver_service::list_drives)
+        .await
+        .map_err(join_failed)
+}
+
+/// Check paths dropped for scanning: whether each exists and can be read,
+/// the kind of drive it is on and about how many files it holds, with
+/// warnings for the user (missing, unreadable, network or removable drive,
+/// a very large tree)
+#[tauri::command]
+pub async fn validate_paths(paths: Vec<String>) -> Result<Vec<PathCheck>, Error> {
+    let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    tokio::task::spawn_blocking(move || space_saver_service::check_paths(&paths))
+        .await
+        .map_err(join_failed)
+}
+
+/// Detect optional external tools (ffmpeg etc.) on PATH. Runs the (blocking)
+/// PATH lookup + version queries off the async runtime.
+#[tauri::command]
+pub async fn detect_tools() -> Result<Vec<space_saver_service::ToolStatus>, Error> {
+    tokio::task::spawn_blocking(space_saver_service::detect_tools)
+        .await
+        .map_err(join_failed)
+}
+
+/// Health-check the external tools each compression plugin can use: the
+/// configured path (or PATH lookup) plus a version query. Runs off the async
+/// runtime since it spawns processes.
+#[tauri::command]
+pub async fn check_plugin_requirements() -> Result<Vec<space_saver_core::PluginRequirements>, Error>
+{
+    tokio::task::spawn_blocking(|| {
+        let manager = space_saver_core::compress_plugins::global_plugin_manager();
+        let manager = manager.read().map_err(Error::from)?;
+        Ok(manager.check_plugin_requirements())
+    })
+    .await
+    .map_err(join_failed)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+    use std::fs;
+    use std::path::Path;
+
+    /// Noise PNG: stores poorly as PNG, so WebP conversion reliably shrinks it
+    fn save_noise_png(path: &Path, width: u32, height: u32) {
+        let mut seed = 0x2545F491u32;
+        let img: image::RgbImage = ImageBuffer::from_fn(width, height, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            Rgb([
+                (seed & 0xFF) as u8,
+                ((seed >> 8) & 0xFF) as u8,
+                ((seed >> 16) & 0xFF) as u8,
+            ])
+        });
+        img.save(path).unwrap();
+    }
+
+    fn paths_of(dir: &tempfile::TempDir) -> Vec<String> {
+        vec![dir.path().to_string_lossy().to_string()]
+    }
+
+    #[test]
+    fn plan_removal_touches_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "12345").unwrap();
+
+        let plan = plan_removal(
+            &[file.clone(), dir.path().join("gone.txt")],
+            "permanent",
+            ProtectedPaths::new(),
+        )
+        .unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].action, "delete");
+        assert_eq!(plan.reclaimed, 5);
+        assert_eq!(plan.skipped[0].code, ErrorCode::NotFound);
+        assert!(file.exists());
+        assert!(plan_removal(&[file], "shred", ProtectedPaths::new()).is_err());
+    }
+
+    /// Tests touching the shared SKIP_CACHE must not run concurrently
+    /// (clear_skip_cache would wipe another test's entries mid-flight).
+    /// Async-aware so the guard may be held across await points.
+    static CACHE_TEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn scan_finds_compressible_and_rejected_files() {
+        let dir = tempfile::tempdir().unwrap();
+        save_noise_png(&dir.path().join("noise.png"), 64, 64);
+        // A corrupt "ZIP" must land in rejected with a reason, not abort the scan
+        fs::write(dir.path().join("fake.zip"), b"this is not a zip archive").unwrap();
+
+        let result = scan_compressible_files(
+            paths_of(&dir),
+            vec![
+                "Image ZIP to WebP ZIP".to_string(),
+                "WebP Converter".to_string(),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+
+        let compressible = result["compressible"].as_array().unwrap();
+        assert_eq!(compressible.len(), 1);
+        assert!(compressible[0]["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("noise.png"));
+        assert_eq!(compressible[0]["plugin_name"], "WebP Converter");
+        assert!(compressible[0]["original_size"].as_u64().unwrap() > 0);
+
+        let rejected = result["rejected"].as_array().unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert!(rejected[0]["path"].as_str().unwrap().ends_with("fake.zip"));
+        let reasons = rejected[0]["rejection_reasons"].as_array().unwrap();
+        assert!(!reasons.is_empty());
+        assert!(reasons.iter().all(|r| r["plugin_name"].is_string()));
+    }
+
+    #[tokio::test]
+    async fn estimate_compression_measures_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        save_noise_png(&dir.path().join("a.png"), 64, 64);
+        save_noise_png(&dir.path().join("b.png"), 64, 64);
+        fs::write(dir.path().join("notes.txt"), b"not an image").unwrap();
+
+        let estimate =
+            estimate_compression(paths_of(&dir), vec!["WebP Converter".to_string()], Some(1))
+                .await
+                .unwrap();
+        assert_eq!(estimate.file_count, 2);
+        assert_eq!(estimate.groups.len(), 1);
+        assert_eq!(estimate.groups[0].sampled, 1);
+        assert!(estimate.groups[0].measured);
+        assert!(estimate.estimated_savings > 0);
+        assert!(dir.path().join("a.png").exists() && !dir.path().join("a.webp").exists());
+
+        let err = estimate_compression(paths_of(&dir), vec!["No Such Plugin".to_string()], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn scan_rejects_unknown_plugin_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let result =
+            scan_compressible_files(paths_of(&dir), vec!["No Such Plugin".to_string()], None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn compress_in_place_reports_compressed_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 128, 128);
+
+        let results = compress_files_blocking(
+            vec![source.to_string_lossy().to_string()],
+            vec!["WebP Converter".to_string()],
+            true,
+            &ProgressSink::new(),
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["status"], "compressed");
+        assert_eq!(results[0]["success"], true);
+        assert!(results[0]["path"].as_str().unwrap().ends_with("noise.webp"));
+
+        let backup = results[0]["backup_path"].as_str().unwrap().to_string();
+        assert!(backup.ends_with("noise.png.bak"));
+        assert!(Path::new(&backup).exists(), "backup file must exist");
+        assert!(!source.exists(), "original renamed to backup");
+        assert!(
+            results[0]["savings"].as_u64().unwrap() > 0,
+            "noise PNG must shrink as WebP"
+        );
+    }
+
+    #[tokio::test]
+    async fn compress_in_place_reports_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 64, 64);
+
+        let results = compress_files_blocking(
+            vec![
+                // No active plugin can handle a PNG when only the ZIP plugin is active
+                source.to_string_lossy().to_string(),
+                // Nonexistent file
+                dir.path().join("missing.png").to_string_lossy().to_string(),
+            ],
+            vec!["Image ZIP to WebP ZIP".to_string()],
+            true,
+            &ProgressSink::new(),
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["status"], "failed");
+        assert!(results[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("No active plugin"));
+        assert!(source.exists(), "file must be untouched on failure");
+
+        assert_eq!(results[1]["status"], "failed");
+        assert_eq!(results[1]["error"], "File not found");
+        assert_eq!(results[1]["error_code"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn compress_in_place_reports_each_file_as_it_goes() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 128, 128);
+        let missing = dir.path().join("missing.png").to_string_lossy().to_string();
+
+        let events = Mutex::new(Vec::new());
+        compress_files_blocking(
+            vec![source.to_string_lossy().to_string(), missing.clone()],
+            vec!["WebP Converter".to_string()],
+            true,
+            &ProgressSink::new(),
+            &|event| events.lock().unwrap().push(event),
+        )
+        .unwrap();
+
+        let events = events.into_inner().unwrap();
+        let statuses: Vec<&str> = events.iter().map(|e| e.status.as_str()).collect();
+        assert_eq!(statuses, ["started", "compressed", "started", "failed"]);
+        assert!(events[1].savings.unwrap() > 0);
+        assert_eq!(events[1].percent, 50.0);
+        assert_eq!(events[3].path, missing);
+        assert_eq!(events[3].reason.as_deref(), Some("File not found"));
+        assert_eq!(events[3].percent, 100.0);
+    }
+
+    #[tokio::test]
+    async fn compress_in_place_without_backup_leaves_no_bak_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 128, 128);
+
+        let results = compress_files_blocking(
+            vec![source.to_string_lossy().to_string()],
+            vec!["WebP Converter".to_string()],
+            false,
+            &ProgressSink::new(),
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["status"], "compressed");
+        assert!(results[0]["backup_path"].is_null());
+        assert!(!source.exists(), "original deleted after success");
+        assert!(
+            !dir.path().join("noise.png.bak").exists(),
+            "no .bak file when backups are disabled"
+        );
+        assert!(dir.path().join("noise.webp").exists());
+    }
+
+    #[test]
+    fn compress_with_cancelled_token_reports_cancelled_and_keeps_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 64, 64);
+        let original = fs::read(&source).unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let results = compress_files_blocking(
+            vec![source.to_string_lossy().to_string()],
+            vec!["WebP Converter".to_string()],
+            false,
+            &ProgressSink::new().with_cancel(cancel),
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["status"], "cancelled");
+        assert_eq!(results[0]["success"], false);
+        assert_eq!(fs::read(&source).unwrap(), original);
+        assert!(!dir.path().join("noise.webp").exists());
+    }
+
+    #[test]
+    fn compression_run_unregisters_on_drop() {
+        let run = CompressionRun::register(CancellationToken::new());
+        let id = run.id;
+        assert!(ACTIVE_COMPRESSIONS.lock().unwrap().contains_key(&id));
+        drop(run);
+        assert!(!ACTIVE_COMPRESSIONS.lock().unwrap().contains_key(&id));
+    }
+
+    #[tokio::test]
+    async fn skip_cache_excludes_unchanged_files_from_scan() {
+        let _guard = CACHE_TEST_LOCK.lock().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 64, 64);
+        let path_str = source.to_string_lossy().to_string();
+
+        let active = vec!["WebP Converter".to_string()];
+
+        // First scan: compressible
+        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(result["compressible"].as_array().unwrap().len(), 1);
+
+        // Simulate a remembered "no size reduction" result for this exact state
+        {
+            let manager = space_saver_core::compress_plugins::global_plugin_manager();
+            let quality = manager.read().unwrap().get_plugin_quality("WebP Converter");
+            let fp = FileFingerprint::of(&source).unwrap();
+            SKIP_CACHE
+                .write()
+                .unwrap()
+                .record_skip(&path_str, fp, "WebP Converter", quality);
         }
+
+        // Second scan: excluded, with a cached-result rejection reason
+        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(result["compressible"].as_array().unwrap().len(), 0);
+        let rejected = result["rejected"].as_array().unwrap();
+        assert_eq!(rejected.len(), 1);
+        let reason = rejected[0]["rejection_reasons"][0]["reason"]
+            .as_str()
+            .unwrap();
+        assert!(reason.contains("cached"), "reason: {reason}");
+
+        // Touch the file (content change bumps size): cache entry no longer matches
+        std::fs::write(&source, b"changed").unwrap();
+        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+            .await
+            .unwrap();
+        // The png is no longer a valid image but it must not be cache-rejected;
+        // it should not appear with a "cached" reason anymore
+        let all = serde_json::to_string(&result).unwrap();
+        assert!(
+            !all.contains("cached result"),
+            "stale fingerprint must miss: {all}"
+        );
+
+        SKIP_CACHE.write().unwrap().invalidate_path(&path_str);
+    }
+
+    #[tokio::test]
+    async fn skip_cache_clear_restores_files() {
+        let _guard = CACHE_TEST_LOCK.lock().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photo.png");
+        save_noise_png(&source, 32, 32);
+        let path_str = source.to_string_lossy().to_string();
+
+        let fp = FileFingerprint::of(&source).unwrap();
+        let manager = space_saver_core::compress_plugins::global_plugin_manager();
+        let quality = manager.read().unwrap().get_plugin_quality("WebP Converter");
+        SKIP_CACHE
+            .write()
+            .unwrap()
+            .record_skip(&path_str, fp, "WebP Converter", quality);
+
+        let info = get_skip_cache_info().await.unwrap();
+        assert!(info["entries"].as_u64().unwrap() >= 1);
+
+        let removed = clear_skip_cache().await.unwrap();
+        assert!(removed >= 1);
+
+        let result =
+            scan_compressible_files(paths_of(&dir), vec!["WebP Converter".to_string()], None)
+                .await
+                .unwrap();
+        assert_eq!(result["compressible"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn successful_compression_invalidates_skip_entries() {
+        let _guard = CACHE_TEST_LOCK.lock().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("invalidate-me.png");
+        save_noise_png(&source, 64, 64);
+        let path_str = source.to_string_lossy().to_string();
+
+        // A (stale) skip entry exists for the path
+        let fp = FileFingerprint::of(&source).unwrap();
+        SKIP_CACHE
+            .write()
+            .unwrap()
+            .record_skip(&path_str, fp, "Some Old Plugin", None);
+
+        let results = compress_files_blocking(
+            vec![path_str.clone()],
+            vec!["WebP Converter".to_string()],
+            true,
+            &ProgressSink::new(),
+            &|_| {},
+        )
+        .unwrap();
+        assert_eq!(results[0]["status"], "compressed");
+
+        let cache = SKIP_CACHE.read().unwrap();
+        assert!(
+            !cache.is_known_skip(&path_str, &fp, "Some Old Plugin", None),
+            "entries for a compressed path must be invalidated"
+        );
+    }
+
+    #[tokio::test]
+    async fn user_skipped_files_are_excluded_until_changed() {
+        let _guard = CACHE_TEST_LOCK.lock().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("keep-as-is.png");
+        save_noise_png(&source, 64, 64);
+        let path_str = source.to_string_lossy().to_string();
+        let active = vec!["WebP Converter".to_string()];
+
+        let missing = dir.path().join("missing.png").to_string_lossy().to_string();
+        let recorded = skip_compression_files(vec![path_str.clone(), missing])
+            .await
+            .unwrap();
+        assert_eq!(recorded, 1, "missing files are not recorded");
+
+        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(result["compressible"].as_array().unwrap().len(), 0);
+        let reason = result["rejected"][0]["rejection_reasons"][0]["reason"]
+            .as_str()
+            .unwrap();
+        assert!(reason.contains("chose not to compress"), "reason: {reason}");
+
+        // A different image at the same path no longer matches the record
+        save_noise_png(&source, 96, 96);
+        let result = scan_compressible_files(paths_of(&dir), active, None)
+            .await
+            .unwrap();
+        assert_eq!(result["compressible"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn compression_results_are_recorded_in_history() {
+        let _guard = CACHE_TEST_LOCK.lock().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("history.png");
+        save_noise_png(&source, 64, 64);
+        let path_str = source.to_string_lossy().to_string();
+
+        let results = compress_files_blocking(
+            vec![path_str.clone()],
+            vec!["WebP Converter".to_string()],
+            true,
+            &ProgressSink::new(),
+            &|_| {},
+        )
+        .unwrap();
+        assert_eq!(results[0]["status"], "compressed");
+
+        let output = dir.path().join("history.webp");
+        let fp = FileFingerprint::of(&output).unwrap();
+        let history = database_connection().unwrap();
+        let record = history
+            .as_deref()
+            .unwrap()
+            .find_compression(&output.to_string_lossy(), fp.size, fp.mtime)
+            .unwrap()
+            .expect("compressed output is recorded");
+        assert_eq!(record.action, "compressed");
+        assert_eq!(record.source_path, path_str);
+        assert_eq!(record.plugin_name.as_deref(), Some("WebP Converter"));
+    }
+
+    #[tokio::test]
+    async fn empty_check_finds_files_and_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("zero.txt"), b"").unwrap();
+        fs::write(dir.path().join("full.txt"), b"content").unwrap();
+        fs::create_dir_all(dir.path().join("hollow/nested")).unwrap();
+
+        let result = empty_folder_check(paths_of(&dir), None).await.unwrap();
+
+        assert_eq!(
+            result.empty_files,
+            vec![dir.path().join("zero.txt").to_string_lossy().to_string()]
+        );
+        // Only the topmost empty folder is reported
+        assert_eq!(
+            result.empty_folders,
+            vec![dir.path().join("hollow").to_string_lossy().to_string()]
+        );
     }
 
-    // Persist new skip-cache entries; the cache is an optimization, so a
-    // failed save must not fail the compression that already happened
-    if let Ok(mut cache) = SKIP_CACHE.write() {
-        if let Err(e) = cache.save() {
-            tracing::warn!(error = %e, "Failed to persist compression skip cache");
-        }
+    #[tokio::test]
+    async fn empty_check_errors_on_nonexistent_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("nope").to_string_lossy().to_string();
+        assert!(empty_folder_check(vec![missing], None).await.is_err());
     }
 
-    Ok(results)
-}
-
-/// Number of remembered no-size-reduction results
-#[tauri::command]
-pub async fn get_skip_cache_info() -> Result<serde_json::Value, String> {
-    let cache = SKIP_CACHE.read().map_err(|e| e.to_string())?;
-    Ok(serde_json::json!({ "entries": cache.len() }))
-}
+    #[tokio::test]
+    async fn empty_check_with_no_paths_returns_empty_result() {
+        let result = empty_folder_check(vec![], None).await.unwrap();
+        assert!(result.empty_files.is_empty());
+        assert!(result.empty_folders.is_empty());
+    }
 
-/// Forget all remembered no-size-reduction results; returns how many were removed
-#[tauri::command]
-pub async fn clear_skip_cache() -> Result<usize, String> {
-    let mut cache = SKIP_CACHE.write().map_err(|e| e.to_string())?;
-    let removed = cache.clear();
-    cache.save().map_err(|e| e.to_string())?;
-    Ok(removed)
-}
+    #[tokio::test]
+    async fn delete_files_removes_empty_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let hollow = dir.path().join("hollow");
+        fs::create_dir(&hollow).unwrap();
+        let occupied = dir.path().join("occupied");
+        fs::create_dir(&occupied).unwrap();
+        fs::write(occupied.join("file.txt"), b"data").unwrap();
 
-/// Location of the on-disk config file (the single source of truth for settings)
-fn config_path() -> PathBuf {
-    space_saver_utils::Config::default_path()
-}
+        let results = delete_files(
+            vec![
+                hollow.to_string_lossy().to_string(),
+                occupied.to_string_lossy().to_string(),
+            ],
+            Some(space_saver_service::DeleteMode::Permanent),
+        )
+        .await
+        .unwrap();
 
-/// Load config from a path, falling back to defaults when the file is absent.
-/// Split from the command so it can be tested against a temp path.
-fn load_config_from(path: &std::path::Path) -> Result<space_saver_utils::Config, String> {
-    if path.exists() {
-        space_saver_utils::Config::load(path).map_err(|e| e.to_string())
-    } else {
-        Ok(space_saver_utils::Config::default())
+        assert!(results[0].success);
+        assert!(!hollow.exists());
+        // A folder that gained content after the scan must be refused
+        assert!(!results[1].success);
+        assert!(occupied.join("file.txt").exists());
     }
-}
 
-/// Validate then persist config to a path. Split from the command so it can be
-/// tested against a temp path without touching the real user config.
-fn save_config_to(
-    path: &std::path::Path,
-    config: &space_saver_utils::Config,
-) -> Result<(), String> {
-    config.validate().map_err(|e| e.to_string())?;
-    config.save(path).map_err(|e| e.to_string())
-}
+    #[tokio::test]
+    async fn delete_files_reports_per_file_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("delete-me.txt");
+        std::fs::write(&existing, b"x").unwrap();
+        let missing = dir.path().join("not-there.txt");
 
-/// Write the default configuration to a path, returning it. Split from the
-/// command so it can be tested against a temp path.
-fn reset_config_at(path: &std::path::Path) -> Result<space_saver_utils::Config, String> {
-    let config = space_saver_utils::Config::default();
-    config.save(path).map_err(|e| e.to_string())?;
-    Ok(config)
-}
+        let results = delete_files(
+            vec![
+                existing.to_string_lossy().to_string(),
+                missing.to_string_lossy().to_string(),
+            ],
+            Some(space_saver_service::DeleteMode::Permanent),
+        )
+        .await
+        .unwrap();
 
-/// Get the current application configuration (or defaults if none saved yet)
-#[tauri::command]
-pub async fn get_config() -> Result<space_saver_utils::Config, String> {
-    load_config_from(&config_path())
-}
+        assert_eq!(results.len(), 2);
+        assert!(results[0].success);
+        assert!(!existing.exists());
+        assert!(
+            !results[1].success,
+            "missing file must be reported as failed"
+        );
+        assert!(results[1].error.is_some());
+        assert_eq!(results[1].error_code, Some(ErrorCode::NotFound));
+    }
 
-/// Validate and persist the application configuration, returning what was saved
-#[tauri::command]
-pub async fn set_config(
-    config: space_saver_utils::Config,
-) -> Result<space_saver_utils::Config, String> {
-    save_config_to(&config_path(), &config)?;
-    Ok(config)
-}
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn delete_files_refuses_system_directories() {
+        let results = delete_files(
+            vec!["/etc/hosts".to_string()],
+            Some(space_saver_service::DeleteMode::Permanent),
+        )
+        .await
+        .unwrap();
 
-/// Reset the configuration to defaults, persisting and returning them
-#[tauri::command]
-pub async fn reset_config() -> Result<space_saver_utils::Config, String> {
-    reset_config_at(&config_path())
-}
+        assert!(!results[0].success);
+        assert!(results[0].protected);
+        assert_eq!(results[0].error_code, Some(ErrorCode::Protected));
+        assert!(std::path::Path::new("/etc/hosts").exists());
+    }
 
-/// Detect optional external tools (ffmpeg etc.) on PATH. Runs the (blocking)
-/// PATH lookup + version queries off the async runtime.
-#[tauri::command]
-pub async fn detect_tools() -> Result<Vec<space_saver_service::ToolStatus>, String> {
-    tokio::task::spawn_blocking(space_saver_service::detect_tools)
-        .await
-        .map_err(|e| e.to_string())
-}
+    #[tokio::test]
+    async fn broken_check_finds_corrupted_and_mismatched_files() {
+        let dir = tempfile::tempdir().unwrap();
+        // Truncated JPEG: valid signature, unparseable body
+        fs::write(dir.path().join("truncated.jpg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        // PDF bytes wearing a .png extension
+        fs::write(dir.path().join("fake.png"), b"%PDF-1.7\nnot a png").unwrap();
+        // A healthy noise PNG must not be flagged
+        save_noise_png(&dir.path().join("ok.png"), 32, 32);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use image::{ImageBuffer, Rgb};
-    use std::fs;
-    use std::path::Path;
+        let broken = broken_file_check(paths_of(&dir), None).await.unwrap();
 
-    /// Noise PNG: stores poorly as PNG, so WebP conversion reliably shrinks it
-    fn save_noise_png(path: &Path, width: u32, height: u32) {
-        let mut seed = 0x2545F491u32;
-        let img: image::RgbImage = ImageBuffer::from_fn(width, height, |_, _| {
-            seed ^= seed << 13;
-            seed ^= seed >> 17;
-            seed ^= seed << 5;
-            Rgb([
-                (seed & 0xFF) as u8,
-                ((seed >> 8) & 0xFF) as u8,
-                ((seed >> 16) & 0xFF) as u8,
-            ])
-        });
-        img.save(path).unwrap();
+        assert_eq!(broken.len(), 2);
+        assert!(broken.iter().any(|b| b.path.ends_with("truncated.jpg")));
+        assert!(broken.iter().any(|b| b.path.ends_with("fake.png")));
+        assert!(broken.iter().all(|b| !b.reason.is_empty()));
     }
 
-    fn paths_of(dir: &tempfile::TempDir) -> Vec<String> {
-        vec![dir.path().to_string_lossy().to_string()]
-    }
+    #[tokio::test]
+    async fn fix_file_extensions_renames_misnamed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // PDF bytes wearing a .jpg extension
+        let path = dir.path().join("scan.jpg");
+        fs::write(&path, b"%PDF-1.7\nbody").unwrap();
 
-    /// Tests touching the shared SKIP_CACHE must not run concurrently
-    /// (clear_skip_cache would wipe another test's entries mid-flight).
-    /// Async-aware so the guard may be held across await points.
-    static CACHE_TEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+        let results = fix_file_extensions(vec![path.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success);
+        assert!(results[0].new_path.as_ref().unwrap().ends_with("scan.pdf"));
+        assert!(!path.exists());
+        assert!(dir.path().join("scan.pdf").exists());
+    }
 
     #[tokio::test]
-    async fn scan_finds_compressible_and_rejected_files() {
+    async fn fix_file_extensions_reports_failure_for_unrecognized_content() {
         let dir = tempfile::tempdir().unwrap();
-        save_noise_png(&dir.path().join("noise.png"), 64, 64);
-        // A corrupt "ZIP" must land in rejected with a reason, not abort the scan
-        fs::write(dir.path().join("fake.zip"), b"this is not a zip archive").unwrap();
+        let path = dir.path().join("junk.jpg");
+        fs::write(&path, b"not a recognizable format").unwrap();
 
-        let result = scan_compressible_files(
-            paths_of(&dir),
-            vec![
-                "Image ZIP to WebP ZIP".to_string(),
-                "WebP Converter".to_string(),
-            ],
+        let results = fix_file_extensions(vec![path.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+
+        assert!(!results[0].success);
+        assert!(results[0].error.is_some());
+        assert!(path.exists(), "file untouched when nothing to fix");
+    }
+
+    #[tokio::test]
+    async fn create_archive_encrypts_with_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("old-docs");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("letter.txt"), "dear diary").unwrap();
+        let dest = dir.path().join("old-docs.zip");
+        let as_string = |p: &Path| p.to_string_lossy().to_string();
+
+        let size = create_archive(
+            as_string(&source),
+            as_string(&dest),
+            Some("pw".into()),
             None,
         )
         .await
         .unwrap();
+        assert_eq!(size, fs::metadata(&dest).unwrap().len());
 
-        let compressible = result["compressible"].as_array().unwrap();
-        assert_eq!(compressible.len(), 1);
-        assert!(compressible[0]["path"]
-            .as_str()
-            .unwrap()
-            .ends_with("noise.png"));
-        assert_eq!(compressible[0]["plugin_name"], "WebP Converter");
-        assert!(compressible[0]["original_size"].as_u64().unwrap() > 0);
+        let out = dir.path().join("out");
+        Compressor::new_encrypted_zip("pw")
+            .extract_archive(&dest, &out)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(out.join("letter.txt")).unwrap(),
+            "dear diary"
+        );
+        assert!(Compressor::new_zip()
+            .extract_archive(&dest, &dir.path().join("plain"))
+            .is_err());
 
-        let rejected = result["rejected"].as_array().unwrap();
-        assert_eq!(rejected.len(), 1);
-        assert!(rejected[0]["path"].as_str().unwrap().ends_with("fake.zip"));
-        let reasons = rejected[0]["rejection_reasons"].as_array().unwrap();
-        assert!(!reasons.is_empty());
-        assert!(reasons.iter().all(|r| r["plugin_name"].is_string()));
+        // The archive now exists and must not be overwritten
+        let err = create_archive(as_string(&source), as_string(&dest), None, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
     }
 
     #[tokio::test]
-    async fn scan_rejects_unknown_plugin_name() {
+    async fn archive_directory_records_and_removes_original() {
         let dir = tempfile::tempdir().unwrap();
-        let result =
-            scan_compressible_files(paths_of(&dir), vec!["No Such Plugin".to_string()], None).await;
-        assert!(result.is_err());
+        let source = dir.path().join("old-project");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("notes.txt"), "done and dusted").unwrap();
+        let dest = dir.path().join("old-project.tar.zst");
+        let as_string = |p: &Path| p.to_string_lossy().to_string();
+
+        let record = archive_directory(as_string(&source), as_string(&dest), None, None)
+            .await
+            .unwrap();
+        assert_eq!(record.format, "tar.zst");
+        assert_eq!(record.file_count, 1);
+        assert!(!source.exists());
+
+        let history = database_connection().unwrap();
+        let found = history
+            .as_deref()
+            .unwrap()
+            .find_archive(&record.source_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.archive_path, as_string(&dest));
     }
 
     #[tokio::test]
-    async fn compress_in_place_reports_compressed_with_backup() {
+    async fn create_archive_writes_7z() {
         let dir = tempfile::tempdir().unwrap();
-        let source = dir.path().join("noise.png");
-        save_noise_png(&source, 128, 128);
-
-        let results = compress_files_in_place(
-            vec![source.to_string_lossy().to_string()],
-            vec!["WebP Converter".to_string()],
-            true,
-        )
-        .await
-        .unwrap();
-
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0]["status"], "compressed");
-        assert_eq!(results[0]["success"], true);
-        assert!(results[0]["path"].as_str().unwrap().ends_with("noise.webp"));
-
-        let backup = results[0]["backup_path"].as_str().unwrap().to_string();
-        assert!(backup.ends_with("noise.png.bak"));
-        assert!(Path::new(&backup).exists(), "backup file must exist");
-        assert!(!source.exists(), "original renamed to backup");
-        assert!(
-            results[0]["savings"].as_u64().unwrap() > 0,
-            "noise PNG must shrink as WebP"
+        let source = dir.path().join("old-docs");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("letter.txt"), "dear diary").unwrap();
+        let dest = dir.path().join("old-docs.7z");
+        let as_string = |p: &Path| p.to_string_lossy().to_string();
+
+        create_archive(
+            as_string(&source),
+            as_string(&dest),
+            None,
+            Some("7z".into()),
+        )
+        .await
+        .unwrap();
+        let out = dir.path().join("out");
+        Compressor::new_7z().extract_archive(&dest, &out).unwrap();
+        assert_eq!(
+            fs::read_to_string(out.join("letter.txt")).unwrap(),
+            "dear diary"
         );
+
+        let err = create_archive(
+            as_string(&source),
+            as_string(&dir.path().join("old-docs.rar")),
+            None,
+            Some("rar".into()),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Unsupported archive format"));
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
     }
 
     #[tokio::test]
-    async fn compress_in_place_reports_failures() {
+    async fn largest_lists_files_and_folders_by_size() {
         let dir = tempfile::tempdir().unwrap();
-        let source = dir.path().join("noise.png");
-        save_noise_png(&source, 64, 64);
+        fs::create_dir(dir.path().join("big")).unwrap();
+        fs::write(dir.path().join("big/a.bin"), vec![0u8; 64]).unwrap();
+        fs::write(dir.path().join("b.bin"), vec![0u8; 16]).unwrap();
+
+        let files = get_largest_files(paths_of(&dir), 1, None).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].path.ends_with("a.bin"));
+
+        let dirs = get_largest_directories(paths_of(&dir), 10).await.unwrap();
+        assert_eq!(dirs.len(), 1);
+        assert!(dirs[0].path.ends_with("big"));
+        assert_eq!(dirs[0].total_size, 64);
+    }
 
-        let results = compress_files_in_place(
-            vec![
-                // No active plugin can handle a PNG when only the ZIP plugin is active
-                source.to_string_lossy().to_string(),
-                // Nonexistent file
-                dir.path().join("missing.png").to_string_lossy().to_string(),
-            ],
-            vec!["Image ZIP to WebP ZIP".to_string()],
-            true,
-        )
-        .await
-        .unwrap();
+    #[tokio::test]
+    async fn treemap_returns_one_tree_per_path() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/a.jpg"), vec![0u8; 32]).unwrap();
+        fs::write(dir.path().join("b.txt"), vec![0u8; 8]).unwrap();
+
+        let trees = get_treemap(paths_of(&dir), 1).await.unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].size, 40);
+        assert_eq!(trees[0].children.len(), 1);
+        assert_eq!(trees[0].children[0].name, "sub");
+        assert_eq!(trees[0].children[0].types[0].category, "Image");
+    }
 
-        assert_eq!(results.len(), 2);
-        assert_eq!(results[0]["status"], "failed");
-        assert!(results[0]["error"]
-            .as_str()
+    #[tokio::test]
+    async fn stale_files_skips_recently_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.txt");
+        fs::write(&old, b"old").unwrap();
+        fs::write(dir.path().join("new.txt"), b"new").unwrap();
+        let long_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(90 * 86_400);
+        fs::File::options()
+            .write(true)
+            .open(&old)
             .unwrap()
-            .contains("No active plugin"));
-        assert!(source.exists(), "file must be untouched on failure");
+            .set_times(
+                fs::FileTimes::new()
+                    .set_accessed(long_ago)
+                    .set_modified(long_ago),
+            )
+            .unwrap();
 
-        assert_eq!(results[1]["status"], "failed");
-        assert_eq!(results[1]["error"], "File not found");
+        let report = find_stale_files(paths_of(&dir), 30, None).await.unwrap();
+        assert_eq!(report.file_count, 1);
+        assert_eq!(report.total_size, 3);
+        assert!(report.directories[0].files[0].path.ends_with("old.txt"));
     }
 
     #[tokio::test]
-    async fn compress_in_place_without_backup_leaves_no_bak_file() {
+    async fn duplicate_directories_found_and_one_copy_deleted() {
         let dir = tempfile::tempdir().unwrap();
-        let source = dir.path().join("noise.png");
-        save_noise_png(&source, 128, 128);
+        for copy in ["export", "export copy"] {
+            fs::create_dir_all(dir.path().join(copy).join("day1")).unwrap();
+            fs::write(dir.path().join(copy).join("day1/a.jpg"), b"photo a").unwrap();
+            fs::write(dir.path().join(copy).join("b.jpg"), b"photo b").unwrap();
+        }
 
-        let results = compress_files_in_place(
-            vec![source.to_string_lossy().to_string()],
-            vec!["WebP Converter".to_string()],
-            false,
+        let groups = find_duplicate_directories(paths_of(&dir)).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].file_count, 2);
+        assert_eq!(groups[0].wasted_space, 14);
+
+        let result = delete_duplicate_directory(
+            groups[0].directories[0].clone(),
+            groups[0].directories[1].clone(),
+            Some(DeleteMode::Permanent),
         )
         .await
         .unwrap();
-
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0]["status"], "compressed");
-        assert!(results[0]["backup_path"].is_null());
-        assert!(!source.exists(), "original deleted after success");
-        assert!(
-            !dir.path().join("noise.png.bak").exists(),
-            "no .bak file when backups are disabled"
-        );
-        assert!(dir.path().join("noise.webp").exists());
+        assert!(result.success, "error: {:?}", result.error);
+        assert!(!dir.path().join("export copy").exists());
+        assert!(dir.path().join("export/day1/a.jpg").exists());
     }
 
     #[tokio::test]
-    async fn skip_cache_excludes_unchanged_files_from_scan() {
-        let _guard = CACHE_TEST_LOCK.lock().await;
+    async fn app_caches_scanned_planned_and_cleaned() {
         let dir = tempfile::tempdir().unwrap();
-        let source = dir.path().join("noise.png");
-        save_noise_png(&source, 64, 64);
-        let path_str = source.to_string_lossy().to_string();
-
-        let active = vec!["WebP Converter".to_string()];
+        for project in ["site", "moved"] {
+            let project = dir.path().join(project);
+            fs::create_dir_all(project.join("node_modules/left-pad")).unwrap();
+            fs::write(project.join("package.json"), b"{}").unwrap();
+            fs::write(
+                project.join("node_modules/left-pad/index.js"),
+                b"module.exports",
+            )
+            .unwrap();
+        }
+        let site = dir.path().join("site/node_modules");
+        let moved = dir.path().join("moved/node_modules");
+
+        let report = scan_app_caches(paths_of(&dir)).await.unwrap();
+        let mut found: Vec<PathBuf> = report
+            .locations(&[CacheCategory::NodeModules])
+            .map(|l| l.path.clone())
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![moved.clone(), site.clone()]);
+
+        fs::remove_file(dir.path().join("moved/package.json")).unwrap();
+        let categories = vec![CacheCategory::NodeModules];
+        let plan = plan_app_cache_cleaning(
+            report.clone(),
+            categories.clone(),
+            Some(DeleteMode::Permanent),
+        )
+        .await
+        .unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].path, site);
+        assert_eq!(plan.actions[0].action, "delete");
+        assert_eq!(plan.skipped[0].path, moved);
+        assert_eq!(plan.skipped[0].code, ErrorCode::InvalidInput);
+        assert!(site.exists(), "planning must not touch anything");
+
+        let results = clean_app_caches(
+            report.clone(),
+            categories.clone(),
+            Some(DeleteMode::Permanent),
+        )
+        .await
+        .unwrap();
+        let outcome = |path: &Path| {
+            let path = path.to_string_lossy();
+            results.iter().find(|r| r.path == path).unwrap().clone()
+        };
+        assert!(outcome(&site).success);
+        assert_eq!(outcome(&moved).error_code, Some(ErrorCode::InvalidInput));
+        assert!(!site.exists());
+        assert!(moved.exists());
 
-        // First scan: compressible
-        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+        let plan = plan_app_cache_cleaning(report, categories, None)
             .await
             .unwrap();
-        assert_eq!(result["compressible"].as_array().unwrap().len(), 1);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.skipped.len(), 2);
+        let gone = plan.skipped.iter().find(|s| s.path == site).unwrap();
+        assert_eq!(gone.code, ErrorCode::NotFound);
+    }
 
-        // Simulate a remembered "no size reduction" result for this exact state
-        {
-            let manager = space_saver_core::compress_plugins::global_plugin_manager();
-            let quality = manager.read().unwrap().get_plugin_quality("WebP Converter");
-            let fp = FileFingerprint::of(&source).unwrap();
-            SKIP_CACHE
-                .write()
+    #[tokio::test]
+    async fn dev_artifacts_found_planned_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["old", "active"] {
+            let project = dir.path().join(project);
+            fs::create_dir_all(project.join("target/debug")).unwrap();
+            fs::write(project.join("Cargo.toml"), b"[package]").unwrap();
+            fs::write(project.join("target/debug/app"), b"binary").unwrap();
+        }
+        let long_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(400 * 86_400);
+        for file in ["old/Cargo.toml", "old/target/debug/app"] {
+            fs::File::options()
+                .write(true)
+                .open(dir.path().join(file))
                 .unwrap()
-                .record_skip(&path_str, fp, "WebP Converter", quality);
+                .set_modified(long_ago)
+                .unwrap();
         }
+        let old = dir.path().join("old/target");
+        let active = dir.path().join("active/target");
+
+        let report = find_dev_artifacts(paths_of(&dir), None).await.unwrap();
+        assert_eq!(report.untouched_months, DEFAULT_UNTOUCHED_MONTHS);
+        assert_eq!(report.projects.len(), 2);
+        let suggested: Vec<&PathBuf> = report
+            .projects
+            .iter()
+            .filter(|p| p.suggested)
+            .flat_map(|p| &p.artifacts)
+            .map(|a| &a.path)
+            .collect();
+        assert_eq!(suggested, [&old]);
 
-        // Second scan: excluded, with a cached-result rejection reason
-        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+        let plan = plan_dev_artifact_removal(report.clone(), Some(DeleteMode::Permanent))
             .await
             .unwrap();
-        assert_eq!(result["compressible"].as_array().unwrap().len(), 0);
-        let rejected = result["rejected"].as_array().unwrap();
-        assert_eq!(rejected.len(), 1);
-        let reason = rejected[0]["rejection_reasons"][0]["reason"]
-            .as_str()
-            .unwrap();
-        assert!(reason.contains("cached"), "reason: {reason}");
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].path, old);
+        assert_eq!(plan.actions[0].action, "delete");
+        assert!(old.exists(), "planning must not touch anything");
 
-        // Touch the file (content change bumps size): cache entry no longer matches
-        std::fs::write(&source, b"changed").unwrap();
-        let result = scan_compressible_files(paths_of(&dir), active.clone(), None)
+        let results = remove_dev_artifacts(report.clone(), Some(DeleteMode::Permanent))
             .await
             .unwrap();
-        // The png is no longer a valid image but it must not be cache-rejected;
-        // it should not appear with a "cached" reason anymore
-        let all = serde_json::to_string(&result).unwrap();
-        assert!(
-            !all.contains("cached result"),
-            "stale fingerprint must miss: {all}"
-        );
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert!(!old.exists());
+        assert!(active.exists());
 
-        SKIP_CACHE.write().unwrap().invalidate_path(&path_str);
+        // Gone since the search: refused rather than removed again
+        let plan = plan_dev_artifact_removal(report.clone(), None)
+            .await
+            .unwrap();
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.skipped[0].code, ErrorCode::NotFound);
+        let results = remove_dev_artifacts(report, None).await.unwrap();
+        assert_eq!(results[0].error_code, Some(ErrorCode::NotFound));
     }
 
     #[tokio::test]
-    async fn skip_cache_clear_restores_files() {
-        let _guard = CACHE_TEST_LOCK.lock().await;
-        let dir = tempfile::tempdir().unwrap();
-        let source = dir.path().join("photo.png");
-        save_noise_png(&source, 32, 32);
-        let path_str = source.to_string_lossy().to_string();
-
-        let fp = FileFingerprint::of(&source).unwrap();
-        let manager = space_saver_core::compress_plugins::global_plugin_manager();
-        let quality = manager.read().unwrap().get_plugin_quality("WebP Converter");
-        SKIP_CACHE
-            .write()
+    async fn reference_check_finds_local_copies() {
+        let (nas, laptop) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::write(nas.path().join("photo.jpg"), b"holiday photo").unwrap();
+        fs::write(laptop.path().join("copy.jpg"), b"holiday photo").unwrap();
+        fs::write(laptop.path().join("new.jpg"), b"only on laptop").unwrap();
+        let nas_path = nas.path().to_string_lossy().to_string();
+
+        let root = index_reference(nas_path.clone()).await.unwrap();
+        assert_eq!(root.file_count, 1);
+        assert!(list_references()
+            .await
             .unwrap()
-            .record_skip(&path_str, fp, "WebP Converter", quality);
+            .iter()
+            .any(|r| r.path == root.path));
 
-        let info = get_skip_cache_info().await.unwrap();
-        assert!(info["entries"].as_u64().unwrap() >= 1);
+        let check = check_against_reference(paths_of(&laptop), None)
+            .await
+            .unwrap();
+        assert_eq!(check.matched.len(), 1);
+        assert!(check.matched[0].path.ends_with("copy.jpg"));
+        assert_eq!(check.unmatched_count, 1);
 
-        let removed = clear_skip_cache().await.unwrap();
-        assert!(removed >= 1);
+        assert_eq!(remove_reference(nas_path).await.unwrap(), 1);
+        let check = check_against_reference(paths_of(&laptop), None)
+            .await
+            .unwrap();
+        assert!(check.matched.is_empty());
+    }
 
-        let result =
-            scan_compressible_files(paths_of(&dir), vec!["WebP Converter".to_string()], None)
-                .await
-                .unwrap();
-        assert_eq!(result["compressible"].as_array().unwrap().len(), 1);
+    #[tokio::test]
+    async fn broken_check_with_no_paths_returns_empty() {
+        let broken = broken_file_check(vec![], None).await.unwrap();
+        assert!(broken.is_empty());
     }
 
     #[tokio::test]
-    async fn successful_compression_invalidates_skip_entries() {
-        let _guard = CACHE_TEST_LOCK.lock().await;
+    async fn broken_check_nonexistent_path_yields_no_results() {
+        // Matches the sibling scan-based commands: a missing root is empty,
+        // not an error.
         let dir = tempfile::tempdir().unwrap();
-        let source = dir.path().join("invalidate-me.png");
-        save_noise_png(&source, 64, 64);
-        let path_str = source.to_string_lossy().to_string();
+        let missing = dir.path().join("nope").to_string_lossy().to_string();
+        let broken = broken_file_check(vec![missing], None).await.unwrap();
+        assert!(broken.is_empty());
+    }
 
-        // A (stale) skip entry exists for the path
-        let fp = FileFingerprint::of(&source).unwrap();
-        SKIP_CACHE
-            .write()
+    #[tokio::test]
+    async fn export_results_writes_the_requested_format() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.bin"), b"exported twice").unwrap();
+        std::fs::write(dir.path().join("b.bin"), b"exported twice").unwrap();
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
             .unwrap()
-            .record_skip(&path_str, fp, "Some Old Plugin", None);
+            .groups;
 
-        let results = compress_files_in_place(
-            vec![path_str.clone()],
-            vec!["WebP Converter".to_string()],
-            true,
+        let out = dir.path().join("report.csv");
+        export_results(
+            ExportResults::Duplicates(groups),
+            ExportFormat::Csv,
+            out.to_string_lossy().to_string(),
         )
         .await
         .unwrap();
-        assert_eq!(results[0]["status"], "compressed");
+        let csv = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.contains("a.bin"));
+
+        let missing = dir.path().join("no/such/dir/report.html");
+        let err = export_results(
+            ExportResults::Similar(Vec::new()),
+            ExportFormat::Html,
+            missing.to_string_lossy().to_string(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Failed to write"));
+    }
 
-        let cache = SKIP_CACHE.read().unwrap();
-        assert!(
-            !cache.is_known_skip(&path_str, &fp, "Some Old Plugin", None),
-            "entries for a compressed path must be invalidated"
-        );
+    #[tokio::test]
+    async fn ignored_duplicate_group_is_left_out_until_unignored() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.bin"), b"kept on purpose").unwrap();
+        std::fs::write(dir.path().join("b.bin"), b"kept on purpose").unwrap();
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        assert_eq!(groups.len(), 1);
+
+        let files = groups[0]
+            .files
+            .iter()
+            .map(|f| f.path.to_string_lossy().to_string())
+            .collect();
+        let ignored = ignore_group(IgnoreKind::Duplicate, files, Some(groups[0].hash.clone()))
+            .await
+            .unwrap();
+        assert!(list_ignored_groups()
+            .await
+            .unwrap()
+            .iter()
+            .any(|g| g.id == ignored.id));
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        assert!(groups.is_empty());
+
+        assert!(unignore_group(ignored.id).await.unwrap());
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        assert_eq!(groups.len(), 1);
     }
 
     #[tokio::test]
-    async fn empty_check_finds_files_and_folders() {
+    async fn duplicate_check_finds_groups_and_populates_hash_cache() {
         let dir = tempfile::tempdir().unwrap();
-        fs::write(dir.path().join("zero.txt"), b"").unwrap();
-        fs::write(dir.path().join("full.txt"), b"content").unwrap();
-        fs::create_dir_all(dir.path().join("hollow/nested")).unwrap();
+        std::fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
+        std::fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
+        std::fs::write(dir.path().join("unique.bin"), b"something else!!").unwrap();
 
-        let result = empty_folder_check(paths_of(&dir), None).await.unwrap();
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].count, 2);
+
+        // Second scan resolves from the cache and agrees
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn scan_paths_reports_progress_per_path() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("a.txt"), b"a").unwrap();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        let paths = [paths_of(&first), paths_of(&second)].concat();
+        let results = scan_paths(paths, None, Some(tx), CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let mut reported = Vec::new();
+        while let Some(update) = rx.recv().await {
+            if let ProgressUpdate::Progress { current, total, .. } = update {
+                reported.push((current, total));
+            }
+        }
+        assert_eq!(reported, vec![(1, 2), (2, 2)]);
+    }
+
+    #[tokio::test]
+    async fn invalidated_scans_are_walked_again() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.txt"), b"aaa").unwrap();
+        scan_paths(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap();
 
+        // Added where the scan found no files, so only a new walk sees it
+        fs::write(dir.path().join("sub").join("b.txt"), b"bb").unwrap();
+        let other = tempfile::tempdir().unwrap();
         assert_eq!(
-            result.empty_files,
-            vec![dir.path().join("zero.txt").to_string_lossy().to_string()]
+            invalidate_scan_cache(Some(paths_of(&other))).await.unwrap(),
+            0
         );
-        // Only the topmost empty folder is reported
+        invalidate_scan_cache(Some(paths_of(&dir))).await.unwrap();
+        let largest = get_largest_files(paths_of(&dir), 10, None).await.unwrap();
+        assert_eq!(largest.len(), 2);
+
+        // Deleting through the app leaves nothing stale behind
+        let deleted = dir.path().join("a.txt").to_string_lossy().to_string();
+        delete_files(vec![deleted], Some(DeleteMode::Permanent))
+            .await
+            .unwrap();
+        let largest = get_largest_files(paths_of(&dir), 10, None).await.unwrap();
+        assert_eq!(largest.len(), 1);
+        assert_eq!(largest[0].size, 2);
+    }
+
+    #[tokio::test]
+    async fn scan_pages_are_sorted_and_bounded() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        for (dir, name, size) in [
+            (&first, "b.txt", 30),
+            (&first, "a.txt", 10),
+            (&second, "c.txt", 20),
+        ] {
+            std::fs::write(dir.path().join(name), vec![0u8; size]).unwrap();
+        }
+        let paths = [paths_of(&first), paths_of(&second)].concat();
+        let results = scan_paths(paths, None, None, CancellationToken::new())
+            .await
+            .unwrap();
+        let job_id = u64::MAX - 1;
+        let summary = store_scan(job_id, results);
+        assert_eq!((summary.file_count, summary.total_size), (3, 60));
+        assert_eq!(summary.paths.len(), 2);
+
+        let names = |page: &ScanPage| -> Vec<String> {
+            page.files
+                .iter()
+                .map(|f| f.path.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        let page = get_scan_page(job_id, 0, 2, None, None, None).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(names(&page), ["b.txt", "c.txt"]);
+        let page = get_scan_page(job_id, 2, 2, None, None, None).await.unwrap();
+        assert_eq!(names(&page), ["a.txt"]);
+        let page = get_scan_page(
+            job_id,
+            0,
+            10,
+            Some(ScanSort::Name),
+            Some(SortOrder::Asc),
+            Some(paths_of(&first).remove(0)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (page.total, names(&page)),
+            (2, vec!["a.txt".to_string(), "b.txt".to_string()])
+        );
+
+        let err = get_scan_page(u64::MAX - 2, 0, 10, None, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+    }
+
+    /// The id `only_scanned_paths_can_be_opened` stores its scan under, well
+    /// past any id the job manager hands out
+    const OPENING_SCAN_ID: JobId = u64::MAX - 3;
+
+    #[tokio::test]
+    async fn only_scanned_paths_can_be_opened() {
+        let scanned = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        std::fs::create_dir(scanned.path().join("sub")).unwrap();
+        std::fs::write(scanned.path().join("sub/a.txt"), b"a").unwrap();
+        std::fs::write(elsewhere.path().join("b.txt"), b"b").unwrap();
+        let results = scan_paths(paths_of(&scanned), None, None, CancellationToken::new())
+            .await
+            .unwrap();
+        store_scan(OPENING_SCAN_ID, results);
+
+        let inside = scanned.path().join("sub/../sub/a.txt");
         assert_eq!(
-            result.empty_folders,
-            vec![dir.path().join("hollow").to_string_lossy().to_string()]
+            scanned_path(&inside.to_string_lossy()).unwrap(),
+            scanned.path().canonicalize().unwrap().join("sub/a.txt")
         );
+        let outside = elsewhere.path().join("b.txt");
+        let err = scanned_path(&outside.to_string_lossy()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PermissionDenied);
+        let escaping = scanned
+            .path()
+            .join("../")
+            .join(elsewhere.path().file_name().unwrap());
+        assert!(scanned_path(&escaping.join("b.txt").to_string_lossy()).is_err());
+        let err = scanned_path(&scanned.path().join("gone.txt").to_string_lossy()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
     }
 
     #[tokio::test]
-    async fn empty_check_errors_on_nonexistent_path() {
+    async fn reflink_duplicates_keeps_every_path() {
         let dir = tempfile::tempdir().unwrap();
-        let missing = dir.path().join("nope").to_string_lossy().to_string();
-        assert!(empty_folder_check(vec![missing], None).await.is_err());
+        std::fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
+        std::fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
+
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        let resolved = resolve_duplicates(groups, KeepRule::ShortestPath)
+            .await
+            .unwrap();
+        let results = reflink_duplicates(resolved).await.unwrap();
+        let supported = check_reflink_support(dir.path().to_string_lossy().to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].success, supported);
+        assert_eq!(results[0].unsupported, !supported);
+        for name in ["a.bin", "b.bin"] {
+            assert_eq!(
+                std::fs::read(dir.path().join(name)).unwrap(),
+                b"identical bytes"
+            );
+        }
     }
 
     #[tokio::test]
-    async fn empty_check_with_no_paths_returns_empty_result() {
-        let result = empty_folder_check(vec![], None).await.unwrap();
-        assert!(result.empty_files.is_empty());
-        assert!(result.empty_folders.is_empty());
+    async fn quarantined_file_can_be_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("copy.jpg");
+        std::fs::write(&file, b"jpeg").unwrap();
+
+        let results = quarantine_files(vec![file.to_string_lossy().to_string()])
+            .await
+            .unwrap();
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert!(!file.exists());
+
+        let listed = list_quarantine().await.unwrap();
+        let record = listed
+            .iter()
+            .find(|r| Path::new(&r.original_path) == file)
+            .expect("quarantined file listed");
+        restore_quarantined(record.id).await.unwrap();
+        assert_eq!(std::fs::read(&file).unwrap(), b"jpeg");
+        assert!(restore_quarantined(record.id).await.is_err());
     }
 
     #[tokio::test]
-    async fn delete_files_removes_empty_directories() {
+    async fn compression_backups_are_listed_and_restored() {
         let dir = tempfile::tempdir().unwrap();
-        let hollow = dir.path().join("hollow");
-        fs::create_dir(&hollow).unwrap();
-        let occupied = dir.path().join("occupied");
-        fs::create_dir(&occupied).unwrap();
-        fs::write(occupied.join("file.txt"), b"data").unwrap();
-
-        let results = delete_files(
-            vec![
-                hollow.to_string_lossy().to_string(),
-                occupied.to_string_lossy().to_string(),
-            ],
-            Some(space_saver_service::DeleteMode::Permanent),
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source, 64, 64);
+        let original = std::fs::read(&source).unwrap();
+        compress_files_blocking(
+            vec![source.to_string_lossy().to_string()],
+            vec!["WebP Converter".to_string()],
+            true,
+            &ProgressSink::new(),
+            &|_| {},
         )
-        .await
         .unwrap();
 
-        assert!(results[0].success);
-        assert!(!hollow.exists());
-        // A folder that gained content after the scan must be refused
-        assert!(!results[1].success);
-        assert!(occupied.join("file.txt").exists());
+        let backups = list_compression_backups().await.unwrap();
+        let backup = backups
+            .iter()
+            .find(|b| Path::new(&b.path) == source)
+            .expect("compression listed");
+        assert!(backup.backup_exists);
+        assert_eq!(backup.plugin_name.as_deref(), Some("WebP Converter"));
+        assert!(backup.saved > 0);
+
+        restore_compression(backup.id).await.unwrap();
+        assert_eq!(std::fs::read(&source).unwrap(), original);
+        assert!(!dir.path().join("noise.webp").exists());
+        assert!(restore_compression(backup.id).await.is_err());
+
+        let err = purge_backups("whenever".to_string()).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
     }
 
     #[tokio::test]
-    async fn delete_files_reports_per_file_results() {
+    async fn unfiltered_scans_are_recorded_and_compared() {
         let dir = tempfile::tempdir().unwrap();
-        let existing = dir.path().join("delete-me.txt");
-        std::fs::write(&existing, b"x").unwrap();
-        let missing = dir.path().join("not-there.txt");
-
-        let results = delete_files(
-            vec![
-                existing.to_string_lossy().to_string(),
-                missing.to_string_lossy().to_string(),
-            ],
-            Some(space_saver_service::DeleteMode::Permanent),
+        let path = dir.path().to_string_lossy().to_string();
+        fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        scan_paths(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap();
+        fs::write(dir.path().join("b.jpg"), b"bbbb").unwrap();
+        let filtered = FilterConfig {
+            min_size: Some(2),
+            ..Default::default()
+        };
+        scan_paths(
+            paths_of(&dir),
+            Some(filtered),
+            None,
+            CancellationToken::new(),
         )
         .await
         .unwrap();
+        scan_paths(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap();
 
-        assert_eq!(results.len(), 2);
-        assert!(results[0].success);
-        assert!(!existing.exists());
-        assert!(
-            !results[1].success,
-            "missing file must be reported as failed"
-        );
-        assert!(results[1].error.is_some());
+        let history = get_storage_history(path.clone()).await.unwrap();
+        assert_eq!(history.snapshots.len(), 2);
+        let recent = get_scan_history(Some(100)).await.unwrap();
+        assert_eq!(recent.iter().filter(|e| e.scan.path == path).count(), 2);
+        let [first, second] = [&history.snapshots[0], &history.snapshots[1]];
+        let diff = diff_scans(first.scan_id, second.scan_id).await.unwrap();
+        assert_eq!(diff.size_change, 4);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.added[0].path.ends_with("b.jpg"));
+
+        let details = get_scan_details(second.scan_id).await.unwrap();
+        assert_eq!(details.scan.file_count, 2);
+        delete_history_entry(first.scan_id).await.unwrap();
+        let history = get_storage_history(path.clone()).await.unwrap();
+        assert_eq!(history.snapshots.len(), 1);
+        let err = delete_history_entry(first.scan_id).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
     }
 
     #[tokio::test]
-    async fn broken_check_finds_corrupted_and_mismatched_files() {
+    async fn scan_history_shows_reclaimable_space_and_details() {
         let dir = tempfile::tempdir().unwrap();
-        // Truncated JPEG: valid signature, unparseable body
-        fs::write(dir.path().join("truncated.jpg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
-        // PDF bytes wearing a .png extension
-        fs::write(dir.path().join("fake.png"), b"%PDF-1.7\nnot a png").unwrap();
-        // A healthy noise PNG must not be flagged
-        save_noise_png(&dir.path().join("ok.png"), 32, 32);
-
-        let broken = broken_file_check(paths_of(&dir), None).await.unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        fs::write(dir.path().join("a.bin"), b"fourteen bytes").unwrap();
+        fs::write(dir.path().join("b.bin"), b"fourteen bytes").unwrap();
+        fs::write(dir.path().join("c.txt"), b"c").unwrap();
+        scan_paths(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap();
+        check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap();
 
-        assert_eq!(broken.len(), 2);
-        assert!(broken.iter().any(|b| b.path.ends_with("truncated.jpg")));
-        assert!(broken.iter().any(|b| b.path.ends_with("fake.png")));
-        assert!(broken.iter().all(|b| !b.reason.is_empty()));
+        let history = get_scan_history(Some(100)).await.unwrap();
+        let entry = history
+            .iter()
+            .find(|e| e.scan.path == path)
+            .expect("the scan is recorded");
+        // One of the two copies can go
+        assert_eq!(entry.reclaimable, 14);
+
+        let details = get_scan_details(entry.scan.id).await.unwrap();
+        assert_eq!(details.scan.file_count, 3);
+        assert_eq!(details.largest[0].size, 14);
+        assert!(details.largest.last().unwrap().path.ends_with("c.txt"));
+        assert_eq!(details.duplicates.len(), 1);
+        assert_eq!(details.duplicates[0].wasted_space, 14);
+
+        delete_history_entry(entry.scan.id).await.unwrap();
+        let history = get_scan_history(Some(100)).await.unwrap();
+        assert!(history.iter().all(|e| e.scan.id != entry.scan.id));
+        let err = get_scan_details(entry.scan.id).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        // The duplicates were found by their own check and stay recorded
+        let recorded = get_duplicate_history().await.unwrap();
+        assert!(recorded
+            .iter()
+            .any(|r| r.file_paths.iter().all(|p| p.starts_with(&path))));
     }
 
     #[tokio::test]
-    async fn fix_file_extensions_renames_misnamed_file() {
+    async fn duplicate_checks_are_recorded() {
         let dir = tempfile::tempdir().unwrap();
-        // PDF bytes wearing a .jpg extension
-        let path = dir.path().join("scan.jpg");
-        fs::write(&path, b"%PDF-1.7\nbody").unwrap();
-
-        let results = fix_file_extensions(vec![path.to_string_lossy().to_string()])
+        fs::write(dir.path().join("a.bin"), b"recorded twice").unwrap();
+        fs::write(dir.path().join("b.bin"), b"recorded twice").unwrap();
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
             .await
-            .unwrap();
+            .unwrap()
+            .groups;
 
-        assert_eq!(results.len(), 1);
-        assert!(results[0].success);
-        assert!(results[0].new_path.as_ref().unwrap().ends_with("scan.pdf"));
-        assert!(!path.exists());
-        assert!(dir.path().join("scan.pdf").exists());
+        let recorded = get_duplicate_history().await.unwrap();
+        let group = recorded
+            .iter()
+            .find(|r| r.hash == groups[0].hash)
+            .expect("the group is recorded");
+        assert_eq!(group.file_count, 2);
+        assert!(group.file_paths[0].starts_with(&*dir.path().to_string_lossy()));
     }
 
     #[tokio::test]
-    async fn fix_file_extensions_reports_failure_for_unrecognized_content() {
+    async fn cancelled_scan_job_is_listed_as_cancelled() {
         let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("junk.jpg");
-        fs::write(&path, b"not a recognizable format").unwrap();
+        let handle = JOBS.register(TaskType::Scan(vec![dir.path().to_path_buf()]));
+        assert!(cancel_job(handle.id()).await.unwrap());
 
-        let results = fix_file_extensions(vec![path.to_string_lossy().to_string()])
+        let err = scan_paths(paths_of(&dir), None, None, handle.token())
             .await
-            .unwrap();
-
-        assert!(!results[0].success);
-        assert!(results[0].error.is_some());
-        assert!(path.exists(), "file untouched when nothing to fix");
+            .unwrap_err();
+        handle.update(&ProgressUpdate::Failed {
+            error: err.to_string(),
+        });
+        let info = get_job_status(handle.id()).await.unwrap();
+        assert_eq!(
+            info.status,
+            space_saver_service::task::TaskStatus::Cancelled
+        );
+        assert!(list_jobs()
+            .await
+            .unwrap()
+            .iter()
+            .any(|j| j.id == handle.id()));
+        // Finished jobs cannot be cancelled again; unknown ones are errors
+        assert!(!cancel_job(handle.id()).await.unwrap());
+        assert_eq!(
+            get_job_status(u64::MAX).await.unwrap_err().code(),
+            ErrorCode::NotFound
+        );
     }
 
     #[tokio::test]
-    async fn broken_check_with_no_paths_returns_empty() {
-        let broken = broken_file_check(vec![], None).await.unwrap();
-        assert!(broken.is_empty());
-    }
+    async fn shutdown_report_is_shown_once() {
+        let report = ShutdownReport {
+            at: 1_700_000_000,
+            interrupted: vec![space_saver_service::InterruptedJob {
+                id: 7,
+                task_type: TaskType::CompressFiles(vec![PathBuf::from("/photos/a.png")]),
+                current: 2,
+                total: 5,
+                message: "Compressing /photos/c.png".to_string(),
+                stopped: true,
+            }],
+            removed: vec![PathBuf::from("/backup/photos.tar.zst")],
+        };
+        report.save(&shutdown_report_path()).unwrap();
 
-    #[tokio::test]
-    async fn broken_check_nonexistent_path_yields_no_results() {
-        // Matches the sibling scan-based commands: a missing root is empty,
-        // not an error.
-        let dir = tempfile::tempdir().unwrap();
-        let missing = dir.path().join("nope").to_string_lossy().to_string();
-        let broken = broken_file_check(vec![missing], None).await.unwrap();
-        assert!(broken.is_empty());
+        let taken = take_shutdown_report().await.unwrap().unwrap();
+        assert_eq!(taken.interrupted[0].id, 7);
+        assert_eq!(taken.removed, report.removed);
+        assert!(take_shutdown_report().await.unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn duplicate_check_finds_groups_and_populates_hash_cache() {
+    async fn resolve_duplicates_keeps_one_copy_per_group() {
         let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
         std::fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
-        std::fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
-        std::fs::write(dir.path().join("unique.bin"), b"something else!!").unwrap();
-
-        let groups = duplicate_file_check(paths_of(&dir), None).await.unwrap();
-        assert_eq!(groups.len(), 1);
-        assert_eq!(groups[0].count, 2);
+        std::fs::write(dir.path().join("nested/a.bin"), b"identical bytes").unwrap();
 
-        // Second scan resolves from the cache and agrees
-        let groups = duplicate_file_check(paths_of(&dir), None).await.unwrap();
-        assert_eq!(groups.len(), 1);
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+        let resolved = resolve_duplicates(groups, KeepRule::ShortestPath)
+            .await
+            .unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].keep[0].path, dir.path().join("a.bin"));
+        assert_eq!(resolved[0].delete[0].path, dir.path().join("nested/a.bin"));
+        // Planning only: both copies are still on disk
+        assert!(dir.path().join("nested/a.bin").exists());
     }
 
     #[tokio::test]
     async fn plugin_quality_roundtrip() {
         let plugins = get_compression_plugins().await.unwrap();
-        assert_eq!(plugins.len(), 3);
-        assert!(plugins.iter().all(|p| p["quality"].is_number()));
+        assert_eq!(plugins.len(), 5);
+        // Every plugin but the minifier has a quality knob
+        assert!(plugins
+            .iter()
+            .all(|p| p["quality"].is_number() == (p["name"] != "Text Asset Minifier")));
 
         // Use the ZIP plugin here so parallel WebP-Converter tests are unaffected
         let name = "Image ZIP to WebP ZIP".to_string();
@@ -1085,9 +4517,16 @@ mod tests {
         save_noise_png(&dir.path().join("a.png"), 64, 48);
         save_noise_png(&dir.path().join("b.png"), 64, 48);
 
-        let groups = find_similar_media(paths_of(&dir), 0.9, vec![MediaKind::Image], None)
-            .await
-            .unwrap();
+        let groups = check_similar_media(
+            paths_of(&dir),
+            0.9,
+            vec![MediaKind::Image],
+            None,
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].media_kind, MediaKind::Image);
@@ -1105,9 +4544,16 @@ mod tests {
         save_noise_png(&dir.path().join("b.png"), 32, 32);
 
         // Video similarity is not implemented; a video-only request finds nothing
-        let groups = find_similar_media(paths_of(&dir), 0.9, vec![MediaKind::Video], None)
-            .await
-            .unwrap();
+        let groups = check_similar_media(
+            paths_of(&dir),
+            0.9,
+            vec![MediaKind::Video],
+            None,
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
         assert!(groups.is_empty());
     }
 
@@ -1123,6 +4569,24 @@ mod tests {
         assert!(url.starts_with("data:image/png;base64,"));
     }
 
+    #[tokio::test]
+    async fn get_file_details_reads_image_dimensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("img.png");
+        save_noise_png(&path, 128, 96);
+
+        let details = get_file_details(path.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!((details.width, details.height), (Some(128), Some(96)));
+        assert!(details.video.is_none());
+        let missing = dir.path().join("nope.png").to_string_lossy().to_string();
+        assert_eq!(
+            get_file_details(missing).await.unwrap_err().code(),
+            ErrorCode::NotFound
+        );
+    }
+
     #[tokio::test]
     async fn read_image_thumbnail_errors_on_missing_file() {
         let dir = tempfile::tempdir().unwrap();
@@ -1130,6 +4594,35 @@ mod tests {
         assert!(read_image_thumbnail(missing, 64).await.is_err());
     }
 
+    #[tokio::test]
+    async fn cleanup_rules_plan_then_run() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), b"old log").unwrap();
+        fs::write(dir.path().join("keep.txt"), b"notes").unwrap();
+        let rule = |name: &str| CleanupRule {
+            name: name.to_string(),
+            paths: vec![dir.path().to_path_buf()],
+            filter: Some("ext in (log)".to_string()),
+            older_than_days: None,
+            action: "delete".to_string(),
+            archive_dir: None,
+            enabled: true,
+        };
+        let rules = vec![rule("logs"), rule("other")];
+        assert!(select_rules(rules.clone(), Some(vec!["nope".to_string()])).is_err());
+        let rules = select_rules(rules, Some(vec!["logs".to_string()])).unwrap();
+
+        let plan = run_cleanup_rules(rules.clone(), true).await.unwrap();
+        assert!(plan.dry_run);
+        assert_eq!(plan.matched_count, 1);
+        assert!(dir.path().join("a.log").exists());
+
+        let report = run_cleanup_rules(rules, false).await.unwrap();
+        assert_eq!(report.freed, 7);
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("keep.txt").exists());
+    }
+
     #[test]
     fn load_config_returns_default_when_file_absent() {
         let dir = tempfile::tempdir().unwrap();
@@ -1179,6 +4672,124 @@ mod tests {
         assert!(save_config_to(&path, &bad_mode).is_err());
     }
 
+    #[tokio::test]
+    async fn duplicate_resolution_previews_then_applies() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"same bytes").unwrap();
+        fs::write(dir.path().join("b.bin"), b"same bytes").unwrap();
+        let groups = check_duplicates(paths_of(&dir), None, None, CancellationToken::new())
+            .await
+            .unwrap()
+            .groups;
+
+        let preview = preview_duplicate_resolution(groups, KeepRule::FirstPath)
+            .await
+            .unwrap();
+        assert_eq!((preview.keep_count, preview.remove_count), (1, 1));
+        assert_eq!(preview.reclaimable, 10);
+
+        let results = apply_duplicate_resolution(preview.plan.clone(), DedupeAction::Hardlink)
+            .await
+            .unwrap();
+        assert!(results[0].success, "{:?}", results[0].error);
+        assert_eq!(results[0].reclaimed, 10);
+        // Nothing is removed once the kept copy changed since the scan
+        fs::write(dir.path().join("a.bin"), b"edited since").unwrap();
+        let results = apply_duplicate_resolution(preview.plan, DedupeAction::Delete)
+            .await
+            .unwrap();
+        assert!(!results[0].success);
+        assert!(dir.path().join("b.bin").exists());
+    }
+
+    #[tokio::test]
+    async fn schedules_are_created_run_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        // No daemon listens here, so runs happen in the app
+        let config = space_saver_utils::Config {
+            daemon_socket: dir.path().join("daemon.sock"),
+            ..Default::default()
+        };
+        save_config_to(&path, &config).unwrap();
+
+        let weekly = Schedule {
+            name: "weekly".to_string(),
+            task: "maintenance".to_string(),
+            every_minutes: 7 * 24 * 60,
+            paths: vec![],
+            rules: vec![],
+            enabled: true,
+        };
+        create_schedule_at(&path, weekly.clone()).unwrap();
+        let err = create_schedule_at(&path, weekly).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        let entries = list_schedules_at(&path).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].status.is_none());
+
+        let run = run_schedule_now_at(&path, "weekly".to_string())
+            .await
+            .unwrap();
+        assert!(!run.on_daemon);
+        assert!(run.result.unwrap().starts_with("Pruned"));
+
+        delete_schedule_at(&path, "weekly").unwrap();
+        assert!(list_schedules_at(&path).await.unwrap().is_empty());
+        let err = delete_schedule_at(&path, "weekly").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        let err = run_schedule_now_at(&path, "weekly".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn update_config_merges_changes_and_checks_new_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let update = update_config_at(
+            &path,
+            &serde_json::json!({
+                "log_level": "debug",
+                "max_concurrent_tasks": 2,
+                "scan": { "min_file_size": 10 },
+            }),
+        )
+        .unwrap();
+        assert!(update.restart_required.is_empty());
+        let loaded = load_config_from(&path).unwrap();
+        assert_eq!(
+            (loaded.log_level.as_str(), loaded.max_concurrent_tasks),
+            ("debug", 2)
+        );
+        assert_eq!(loaded.scan.min_file_size, 10);
+        assert!(!loaded.scan.exclude_patterns.is_empty());
+
+        let cache_dir = dir.path().join("cache");
+        let update =
+            update_config_at(&path, &serde_json::json!({ "cache_dir": cache_dir })).unwrap();
+        assert_eq!(update.restart_required, ["cache_dir"]);
+        assert!(cache_dir.is_dir());
+
+        // A file where a folder should be, and a bad value: nothing is saved
+        let file = dir.path().join("file");
+        fs::write(&file, b"").unwrap();
+        let err =
+            update_config_at(&path, &serde_json::json!({ "quarantine_dir": file })).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        let err = update_config_at(
+            &path,
+            &serde_json::json!({ "log_level": "info", "image_similarity_threshold": 2.0 }),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Config);
+        let loaded = load_config_from(&path).unwrap();
+        assert_eq!(loaded.log_level, "debug");
+        assert_eq!(loaded.cache_dir, cache_dir);
+    }
+
     #[test]
     fn reset_config_writes_defaults() {
         let dir = tempfile::tempdir().unwrap();
@@ -1214,6 +4825,60 @@ mod tests {
         assert_eq!(loaded.plugin_quality.get("WebP Converter"), Some(&100.0));
     }
 
+    #[test]
+    fn persist_plugin_enabled_lists_disabled_plugins_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        persist_plugin_enabled(&path, "RAW to JPEG", false).unwrap();
+        persist_plugin_enabled(&path, "RAW to JPEG", false).unwrap();
+        let loaded = load_config_from(&path).unwrap();
+        assert_eq!(loaded.disabled_plugins, vec!["RAW to JPEG".to_string()]);
+
+        persist_plugin_enabled(&path, "RAW to JPEG", true).unwrap();
+        let loaded = load_config_from(&path).unwrap();
+        assert!(loaded.disabled_plugins.is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_plugin_requirements_covers_every_plugin() {
+        let reports = check_plugin_requirements().await.unwrap();
+        assert_eq!(reports.len(), 5);
+        let animated = reports
+            .iter()
+            .find(|r| r.plugin_name == "Animated WebP Converter")
+            .unwrap();
+        assert!(animated.ready);
+        let tools: Vec<&str> = animated.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tools, ["gif2webp", "ffmpeg"]);
+    }
+
+    #[tokio::test]
+    async fn list_drives_command_reports_space_per_drive() {
+        let drives = list_drives().await.unwrap();
+        for drive in &drives {
+            assert!(drive.available_space <= drive.total_space);
+            assert!(!drive.mount_point.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_paths_probes_each_path() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        fs::write(dir.path().join("b.txt"), b"b").unwrap();
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+
+        let checks = validate_paths(vec![paths_of(&dir).remove(0), missing])
+            .await
+            .unwrap();
+        assert!(checks[0].exists && checks[0].readable);
+        assert_eq!(checks[0].estimated_files, Some(2));
+        assert!(checks[0].exact);
+        assert!(!checks[1].exists);
+        assert!(!checks[1].warnings.is_empty());
+    }
+
     #[tokio::test]
     async fn detect_tools_command_lists_known_tools() {
         let tools = detect_tools().await.unwrap();
@@ -4,11 +4,13 @@ use commands::*;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logger
-    space_saver_utils::init_logger();
+    // Initialize logger; update_config changes its level later
+    space_saver_utils::init_logger_with_level(
+        &space_saver_utils::Config::load_or_default().log_level,
+    );
 
-    // Apply persisted per-plugin quality before any command runs
-    seed_plugin_quality_from_config();
+    // Apply persisted plugin settings before any command runs
+    seed_plugin_manager_from_config();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -16,25 +18,107 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             scan,
+            get_scan_page,
+            invalidate_scan_cache,
+            get_storage_history,
+            diff_scans,
+            get_scan_history,
+            get_scan_details,
+            delete_history_entry,
+            get_duplicate_history,
+            browse_recorded_files,
             empty_folder_check,
             duplicate_file_check,
+            resolve_duplicates,
+            preview_duplicate_resolution,
+            apply_duplicate_resolution,
+            reflink_duplicates,
+            check_reflink_support,
             find_similar_media,
             read_image_thumbnail,
+            get_file_details,
+            reveal_in_explorer,
+            open_file,
             broken_file_check,
             fix_file_extensions,
+            find_stale_files,
+            find_duplicate_directories,
+            delete_duplicate_directory,
+            scan_app_caches,
+            plan_app_cache_cleaning,
+            clean_app_caches,
+            find_dev_artifacts,
+            plan_dev_artifact_removal,
+            remove_dev_artifacts,
+            index_reference,
+            check_against_reference,
+            list_references,
+            remove_reference,
+            ignore_group,
+            unignore_group,
+            list_ignored_groups,
+            add_tag,
+            remove_tag,
+            get_tags,
+            find_tagged,
+            list_tags,
+            export_results,
+            get_cleanup_rules,
+            set_cleanup_rules,
+            run_cleanup,
+            list_schedules,
+            create_schedule,
+            delete_schedule,
+            run_schedule_now,
+            get_next_runs,
             delete_files,
+            plan_delete_files,
+            quarantine_files,
+            list_quarantine,
+            restore_quarantined,
+            purge_quarantine,
+            list_compression_backups,
+            restore_compression,
+            purge_backups,
+            create_archive,
+            archive_directory,
             get_storage_stats,
+            get_largest_files,
+            get_largest_directories,
+            get_treemap,
             get_compression_plugins,
             set_plugin_quality,
+            set_plugin_enabled,
             scan_compressible_files,
+            estimate_compression,
             compress_files_in_place,
+            cancel_compression,
+            list_jobs,
+            get_job_status,
+            cancel_job,
+            take_shutdown_report,
             get_skip_cache_info,
+            get_savings_summary,
             clear_skip_cache,
+            skip_compression_files,
             get_config,
             set_config,
+            update_config,
             reset_config,
-            detect_tools
+            list_drives,
+            validate_paths,
+            detect_tools,
+            check_plugin_requirements
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .setup(|app| {
+            start_maintenance(app.handle().clone());
+            Ok(())
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown_app();
+            }
+        });
 }
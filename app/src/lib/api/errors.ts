@@ -0,0 +1,38 @@
+/**
+ * Errors from the backend. Commands reject, and jobs finish, with an
+ * `ErrorInfo` (`{ code, message }`); the API layer rethrows it as a
+ * ServiceError so callers can branch on `code` and still read `message`
+ * like any Error.
+ */
+
+import type { ErrorCode, ErrorInfo } from "../types";
+
+export class ServiceError extends Error {
+  readonly code: ErrorCode;
+
+  constructor(code: ErrorCode, message: string) {
+    super(message);
+    this.name = "ServiceError";
+    this.code = code;
+  }
+}
+
+function isErrorInfo(value: unknown): value is ErrorInfo {
+  return (
+    typeof value === "object" &&
+    value !== null &&
+    typeof (value as ErrorInfo).code === "string" &&
+    typeof (value as ErrorInfo).message === "string"
+  );
+}
+
+/**
+ * Whatever a command or job rejected with, as a ServiceError. Anything
+ * without a code (an IPC failure, a plain string) becomes `unknown`.
+ */
+export function toServiceError(error: unknown): ServiceError {
+  if (error instanceof ServiceError) return error;
+  if (isErrorInfo(error)) return new ServiceError(error.code, error.message);
+  if (error instanceof Error) return new ServiceError("unknown", error.message);
+  return new ServiceError("unknown", String(error));
+}
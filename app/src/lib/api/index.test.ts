@@ -1,31 +1,119 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
 import {
   scanDirectory,
+  scanDirectories,
+  startScan,
+  getScanPage,
+  invalidateScanCache,
   findDuplicates,
+  findDuplicateReport,
+  resolveDuplicates,
+  previewDuplicateResolution,
+  applyDuplicateResolution,
+  reflinkDuplicates,
+  checkReflinkSupport,
   findSimilarMedia,
   getImageThumbnail,
+  getFileDetails,
+  revealInExplorer,
+  openFile,
   findEmptyItems,
   findBrokenFiles,
+  findStaleFiles,
+  findDuplicateDirectories,
+  deleteDuplicateDirectory,
+  indexReference,
+  checkAgainstReference,
+  listReferences,
+  ignoreGroup,
+  unignoreGroup,
+  listIgnoredGroups,
+  addTag,
+  removeTag,
+  getTags,
+  findTagged,
+  listTags,
+  exportResults,
+  removeReference,
   fixFileExtensions,
   deleteFiles,
+  planDeleteFiles,
+  createArchive,
+  archiveDirectory,
   getStorageStats,
+  getTreemap,
+  getStorageHistory,
+  diffScans,
+  getScanHistory,
+  getScanDetails,
+  deleteHistoryEntry,
+  getDuplicateHistory,
+  getRecordedDuplicates,
+  browseRecordedFiles,
+  getLargestFiles,
+  getLargestDirectories,
   getCompressionPlugins,
   setPluginQuality,
+  setPluginEnabled,
   scanCompressibleFiles,
+  estimateCompression,
   compressFilesInPlace,
+  cancelCompression,
+  onCompressionProgress,
+  onCompressionFile,
+  listJobs,
+  getJobStatus,
+  cancelJob,
+  quarantineFiles,
+  listQuarantine,
+  restoreQuarantined,
+  purgeQuarantine,
+  listCompressionBackups,
+  restoreCompression,
+  purgeBackups,
   getSkipCacheInfo,
+  getSavingsSummary,
   clearSkipCache,
+  skipCompressionFiles,
   getConfig,
   setConfig,
+  updateConfig,
   resetConfig,
+  getCleanupRules,
+  setCleanupRules,
+  listSchedules,
+  createSchedule,
+  deleteSchedule,
+  runScheduleNow,
+  getNextRuns,
+  runCleanup,
   detectTools,
+  checkPluginRequirements,
+  listDrives,
+  validatePaths,
+  scanAppCaches,
+  planAppCacheCleaning,
+  cleanAppCaches,
+  findDevArtifacts,
+  planDevArtifactRemoval,
+  removeDevArtifacts,
+  takeShutdownReport,
+  ServiceError,
+  toServiceError,
+  type CompressionFileEvent,
+  type AppCacheReport,
+  type DevArtifactReport,
 } from './index';
 import { resetMockConfig, defaultConfig } from '../../mock/config';
+import { mockExport } from '../../mock/export';
 
 // Mock Tauri API
 vi.mock('@tauri-apps/api/core', () => ({
   invoke: vi.fn(),
 }));
+vi.mock('@tauri-apps/api/event', () => ({
+  listen: vi.fn(),
+}));
 
 describe('API Layer', () => {
   describe('Web Mode', () => {
@@ -46,6 +134,36 @@ describe('API Layer', () => {
       expect(result.total_size).toBeGreaterThan(0);
     });
 
+    it('scanDirectories returns every file of each scanned path', async () => {
+      const results = await scanDirectories(['/test/path', '/data/empty-dir']);
+
+      expect(results.map(r => r.path)).toEqual(['/test/path', '/data/empty-dir']);
+      const [full, empty] = results;
+      expect(full.files).toHaveLength(full.file_count);
+      expect(full.files.reduce((sum, f) => sum + f.size, 0)).toBe(full.total_size);
+      expect(empty).toMatchObject({ file_count: 0, total_size: 0, files: [] });
+    });
+
+    it('startScan returns totals and getScanPage pages through the files', async () => {
+      const summary = await startScan(['/test/path']);
+      expect(summary.paths).toHaveLength(1);
+
+      const first = await getScanPage(summary.job_id, 0, 2);
+      expect(first.files).toHaveLength(2);
+      expect(first.files[0].size).toBeGreaterThanOrEqual(first.files[1].size);
+      const byName = await getScanPage(summary.job_id, 0, 10, 'name', 'asc');
+      expect(byName.total).toBe(first.total);
+      expect(byName.files.map(f => f.path.split('/').pop())).toEqual(
+        [...byName.files.map(f => f.path.split('/').pop())].sort()
+      );
+      await expect(getScanPage(-1, 0, 10)).rejects.toMatchObject({ code: 'not_found' });
+    });
+
+    it('invalidateScanCache has nothing to drop in web mode', async () => {
+      expect(await invalidateScanCache(['/test/path'])).toBe(0);
+      expect(await invalidateScanCache()).toBe(0);
+    });
+
     it('findDuplicates returns mock data in web mode', async () => {
       const result = await findDuplicates(['/test/path']);
       
@@ -87,6 +205,46 @@ describe('API Layer', () => {
       expect(url.startsWith('data:image/')).toBe(true);
     });
 
+    it('getFileDetails reports EXIF for photos and stream info for videos in web mode', async () => {
+      const photo = await getFileDetails('/test/path/trip/sunset.jpg');
+      expect(photo.file_type).toBe('Image');
+      expect(photo.exif?.camera_model).toBeTruthy();
+      expect(photo.exif?.has_gps).toBe(true);
+      expect(photo.video).toBeNull();
+
+      const video = await getFileDetails('/test/path/clips/holiday.mp4');
+      expect(video.exif).toBeNull();
+      expect(video.video?.codec).toBe('h264');
+      expect(video.width).toBe(video.video?.width);
+
+      await expect(getFileDetails('/test/path/missing.jpg')).rejects.toMatchObject({ code: 'not_found' });
+    });
+
+    it('revealInExplorer and openFile do nothing in web mode', async () => {
+      await expect(revealInExplorer('/test/path/photos/sunset.jpg')).resolves.toBeUndefined();
+      await expect(openFile('/test/path/photos/sunset.jpg')).resolves.toBeUndefined();
+    });
+
+    it('revealInExplorer and openFile reject missing files as not_found in web mode', async () => {
+      for (const open of [revealInExplorer, openFile]) {
+        await expect(open('/test/path/missing.jpg')).rejects.toMatchObject({
+          code: 'not_found',
+          message: '/test/path/missing.jpg no longer exists',
+        });
+      }
+    });
+
+    it('revealInExplorer and openFile refuse files outside the scanned folders in web mode', async () => {
+      for (const open of [revealInExplorer, openFile]) {
+        const err = await open('/locked/secret.txt').catch((e) => e);
+        expect(err).toBeInstanceOf(ServiceError);
+        expect(err).toMatchObject({
+          code: 'permission_denied',
+          message: '/locked/secret.txt is not in a scanned folder',
+        });
+      }
+    });
+
     it('findEmptyItems returns empty files and folders in web mode', async () => {
       const result = await findEmptyItems(['/test/path']);
 
@@ -126,6 +284,152 @@ describe('API Layer', () => {
       expect(result.every(b => b.reason.length > 0)).toBe(true);
     });
 
+    it('findStaleFiles groups old files by directory in web mode', async () => {
+      const report = await findStaleFiles(['/test/path'], 365);
+
+      expect(report.directories.length).toBeGreaterThan(0);
+      const sizes = report.directories.map(d => d.total_size);
+      expect(sizes).toEqual([...sizes].sort((a, b) => b - a));
+      expect(report.total_size).toBe(sizes.reduce((sum, s) => sum + s, 0));
+      expect(report.directories.flatMap(d => d.files).some(f => f.path.endsWith('report.pdf'))).toBe(false);
+
+      const longer = await findStaleFiles(['/test/path'], 5000);
+      expect(longer.file_count).toBe(0);
+    });
+
+    it('findDuplicateDirectories reports whole trees, most wasted first', async () => {
+      const groups = await findDuplicateDirectories(['/test/path']);
+
+      expect(groups.length).toBeGreaterThan(0);
+      const wasted = groups.map(g => g.wasted_space);
+      expect(wasted).toEqual([...wasted].sort((a, b) => b - a));
+      for (const group of groups) {
+        expect(group.directories.length).toBeGreaterThan(1);
+        expect(group.wasted_space).toBe(group.size * (group.directories.length - 1));
+      }
+      expect(await findDuplicateDirectories(['/empty-dir'])).toEqual([]);
+    });
+
+    it('deleteDuplicateDirectory refuses overlapping copies', async () => {
+      const deleted = await deleteDuplicateDirectory('/test/a copy', '/test/a');
+      expect(deleted.success).toBe(true);
+
+      const nested = await deleteDuplicateDirectory('/test/a/inner', '/test/a');
+      expect(nested.success).toBe(false);
+      expect(nested.error).toContain('overlap');
+    });
+
+    it('deleteDuplicateDirectory fails on locked copies and on usb-drive copies in trash mode', async () => {
+      const locked = await deleteDuplicateDirectory('/test/locked copy', '/test/a');
+      expect(locked.success).toBe(false);
+      expect(locked.error).toContain('Permission denied (os error 13)');
+      expect(locked.error_code).toBe('permission_denied');
+
+      const trashed = await deleteDuplicateDirectory('/usb-drive/a copy', '/test/a');
+      expect(trashed.success).toBe(false);
+      expect(trashed.error).toContain('Retry with permanent deletion');
+      expect(trashed.error_code).toBe('file_operation');
+
+      const permanent = await deleteDuplicateDirectory('/usb-drive/a copy', '/test/a', 'permanent');
+      expect(permanent.success).toBe(true);
+    });
+
+    it('checkAgainstReference matches local copies of indexed files', async () => {
+      await indexReference('/mnt/nas');
+      expect((await listReferences()).map(r => r.path)).toContain('/mnt/nas');
+
+      const check = await checkAgainstReference(['/home/me']);
+      expect(check.matched.length).toBeGreaterThan(0);
+      expect(check.matched.every(m => m.reference_path.startsWith('/mnt/nas/'))).toBe(true);
+      expect(check.matched_size).toBe(check.matched.reduce((sum, m) => sum + m.size, 0));
+      expect(check.unmatched_count).toBeGreaterThan(0);
+
+      await expect(indexReference('/mnt/unmounted')).rejects.toMatchObject({ code: 'not_found' });
+      expect(await removeReference('/mnt/nas')).toBeGreaterThan(0);
+      expect((await checkAgainstReference(['/home/me'])).matched).toEqual([]);
+    });
+
+    it('indexReference and checkAgainstReference fail on locked and missing paths', async () => {
+      await expect(indexReference('/mnt/locked')).rejects.toMatchObject({
+        code: 'permission_denied',
+        message: expect.stringContaining('Permission denied (os error 13)'),
+      });
+      await expect(indexReference('/mnt/missing')).rejects.toMatchObject({ code: 'not_found' });
+      await expect(checkAgainstReference(['/home/locked'])).rejects.toMatchObject({ code: 'permission_denied' });
+      await expect(checkAgainstReference(['/home/me', '/home/missing'])).rejects.toMatchObject({ code: 'not_found' });
+      expect((await listReferences()).map(r => r.path)).not.toContain('/mnt/missing');
+    });
+
+    it('empty-dir reference volumes and folders hold no files', async () => {
+      const root = await indexReference('/mnt/empty-dir');
+      expect(root.file_count).toBe(0);
+      expect(root.total_size).toBe(0);
+
+      const check = await checkAgainstReference(['/home/me']);
+      expect(check.matched).toEqual([]);
+      expect(check.references.map(r => r.path)).toContain('/mnt/empty-dir');
+
+      const empty = await checkAgainstReference(['/home/empty-dir']);
+      expect(empty.unmatched_count).toBe(0);
+      expect(await removeReference('/mnt/empty-dir')).toBe(0);
+    });
+
+    it('ignored duplicate and similar groups are left out until unignored', async () => {
+      const [group] = await findDuplicates(['/ignore-test']);
+      const paths = group.files.map(f => f.path);
+      const ignored = await ignoreGroup('duplicate', paths, group.hash);
+      expect((await listIgnoredGroups())[0].id).toBe(ignored.id);
+      const groups = await findDuplicates(['/ignore-test']);
+      expect(groups.some(g => g.hash === group.hash)).toBe(false);
+
+      const [pair] = await findSimilarMedia(['/ignore-test']);
+      const before = (await findSimilarMedia(['/ignore-test'])).length;
+      await ignoreGroup('similar', pair.files.map(f => f.path));
+      expect(await findSimilarMedia(['/ignore-test'])).toHaveLength(before - 1);
+
+      await expect(ignoreGroup('duplicate', paths)).rejects.toThrow('hash');
+      await expect(ignoreGroup('similar', [paths[0]])).rejects.toThrow('two files');
+      expect(await unignoreGroup(ignored.id)).toBe(true);
+      expect(await unignoreGroup(ignored.id)).toBe(false);
+      expect((await findDuplicates(['/ignore-test'])).some(g => g.hash === group.hash)).toBe(true);
+    });
+
+    it('tags are normalized, counted and removed', async () => {
+      const photo = { kind: 'file', path: '/tag-test/a.jpg' } as const;
+      const group = { kind: 'duplicate_group', hash: 'tag-test-hash' } as const;
+      expect(await addTag(photo, ' Keep ')).toBe(true);
+      expect(await addTag(photo, 'keep')).toBe(false);
+      await addTag(photo, 'client-x');
+      await addTag(group, 'keep');
+
+      expect(await getTags(photo)).toEqual(['client-x', 'keep']);
+      const kept = await findTagged('KEEP');
+      expect(kept.map(t => t.target)).toEqual(['tag-test-hash', '/tag-test/a.jpg']);
+      expect((await listTags()).find(t => t.tag === 'keep')).toMatchObject({ files: 1, groups: 1 });
+
+      expect(await removeTag(photo, 'keep')).toBe(true);
+      expect(await removeTag(photo, 'keep')).toBe(false);
+      expect(await getTags(photo)).toEqual(['client-x']);
+      await expect(addTag(photo, '  ')).rejects.toThrow('empty');
+    });
+
+    it('exportResults writes duplicates and rejects locked and missing directories', async () => {
+      const groups = await findDuplicates(['/test/path']);
+      await exportResults({ kind: 'duplicates', data: groups }, 'csv', '/tmp/duplicates.csv');
+      expect(mockExport.read('/tmp/duplicates.csv')?.results.data).toEqual(groups);
+
+      await expect(
+        exportResults({ kind: 'similar', data: [] }, 'html', '/locked/report.html')
+      ).rejects.toMatchObject({
+        code: 'permission_denied',
+        message: expect.stringContaining('Permission denied (os error 13)'),
+      });
+      await expect(
+        exportResults({ kind: 'similar', data: [] }, 'html', '/missing/report.html')
+      ).rejects.toMatchObject({ code: 'not_found', message: expect.stringContaining('Failed to write') });
+      expect(mockExport.read('/missing/report.html')).toBeUndefined();
+    });
+
     it('findBrokenFiles mock covers both broken categories', async () => {
       const result = await findBrokenFiles(['/test/path']);
       const categories = new Set(result.map(b => b.category));
@@ -144,6 +448,148 @@ describe('API Layer', () => {
       expect(corrupted?.suggested_extension == null).toBe(true);
     });
 
+    it('findDuplicates reports job progress in web mode', async () => {
+      const updates: unknown[] = [];
+      await findDuplicates(['/a', '/b'], undefined, (update) => updates.push(update));
+
+      expect(updates[0]).toEqual({ Started: { task_type: 'duplicates', total_items: 2 } });
+      expect(updates[2]).toEqual({ Progress: { current: 2, total: 2, message: 'Scanned /b' } });
+      expect(updates.at(-1)).toHaveProperty('Completed');
+    });
+
+    it('resolveDuplicates keeps one copy per group in web mode', async () => {
+      const groups = await findDuplicates(['/test/path']);
+      const resolved = await resolveDuplicates(groups, { strategy: 'shortest_path' });
+
+      expect(resolved).toHaveLength(groups.length);
+      for (const [i, resolution] of resolved.entries()) {
+        expect(resolution.keep).toHaveLength(1);
+        expect(resolution.delete).toHaveLength(groups[i].files.length - 1);
+        expect(resolution.reclaimable).toBe(
+          resolution.delete.reduce((sum, f) => sum + f.size, 0)
+        );
+      }
+    });
+
+    it('previewDuplicateResolution sums up a plan applyDuplicateResolution carries out', async () => {
+      const groups = await findDuplicates(['/test/path']);
+      const preview = await previewDuplicateResolution(groups, { strategy: 'newest' });
+
+      expect(preview.plan).toHaveLength(groups.length);
+      expect(preview.keep_count + preview.remove_count).toBe(
+        groups.reduce((sum, g) => sum + g.files.length, 0)
+      );
+      // The usb-drive copy has no trash to go to, so delete it for good
+      const results = await applyDuplicateResolution(preview.plan, 'delete');
+      expect(results).toHaveLength(preview.remove_count);
+      expect(results.every(r => r.success)).toBe(true);
+      expect(results.reduce((sum, r) => sum + r.reclaimed, 0)).toBe(preview.reclaimable);
+    });
+
+    it('applyDuplicateResolution fails locked, usb-drive and missing copies as each action would', async () => {
+      const file = (path: string) => ({ path, size: 100, modified: 0, file_type: 'Other' as const });
+      const plan = [
+        {
+          hash: 'abc',
+          keep: [file('/photos/a.jpg')],
+          delete: [
+            file('/photos/b.jpg'),
+            file('/photos/locked/c.jpg'),
+            file('/media/usb-drive/d.jpg'),
+            file('/photos/missing/e.jpg'),
+          ],
+          reclaimable: 400,
+          protected: [],
+        },
+        {
+          hash: 'def',
+          keep: [file('/photos/missing/f.jpg')],
+          delete: [file('/photos/g.jpg')],
+          reclaimable: 100,
+          protected: [],
+        },
+      ];
+      const outcome = async (action: 'trash' | 'delete' | 'hardlink') =>
+        Object.fromEntries(
+          (await applyDuplicateResolution(plan, action)).map(r => [r.path, r.success ? 'ok' : r.error])
+        );
+
+      const trashed = await outcome('trash');
+      expect(trashed).toEqual({
+        '/photos/b.jpg': 'ok',
+        '/photos/locked/c.jpg': 'IO error: Permission denied (os error 13)',
+        '/media/usb-drive/d.jpg':
+          'File operation error: Cannot move to trash: the volume has no trash directory. Retry with permanent deletion.',
+        '/photos/missing/e.jpg': 'File not found: /photos/missing/e.jpg',
+        '/photos/g.jpg': 'File not found: /photos/missing/f.jpg',
+      });
+
+      const deleted = await outcome('delete');
+      expect(deleted['/media/usb-drive/d.jpg']).toBe('ok');
+      expect(deleted['/photos/locked/c.jpg']).toBe('IO error: Permission denied (os error 13)');
+
+      const linked = await outcome('hardlink');
+      expect(linked['/photos/b.jpg']).toBe('ok');
+      expect(linked['/photos/locked/c.jpg']).toBe(
+        'Failed to link /photos/locked/c.jpg to /photos/a.jpg: Permission denied (os error 13)'
+      );
+      expect(linked['/media/usb-drive/d.jpg']).toBe(
+        'Failed to link /media/usb-drive/d.jpg to /photos/a.jpg: Invalid cross-device link (os error 18)'
+      );
+      expect(linked['/photos/missing/e.jpg']).toBe('File not found: /photos/missing/e.jpg');
+
+      const [ok, locked] = await applyDuplicateResolution(plan, 'delete');
+      expect(ok).toMatchObject({ success: true, reclaimed: 100, error: null, protected: false });
+      expect(locked).toMatchObject({ success: false, reclaimed: 0, protected: false });
+    });
+
+    it('reflinkDuplicates clones copies where the file system allows in web mode', async () => {
+      const file = (path: string) => ({ path, size: 100, modified: 0, file_type: 'Other' as const });
+      const results = await reflinkDuplicates([
+        {
+          hash: 'abc',
+          keep: [file('/photos/a.jpg')],
+          delete: [file('/photos/b.jpg'), file('/usb-drive/a.jpg')],
+          reclaimable: 200,
+          protected: [],
+        },
+      ]);
+
+      expect(results[0]).toEqual({ path: '/photos/b.jpg', source: '/photos/a.jpg', success: true, reclaimed: 100 });
+      expect(results[1].unsupported).toBe(true);
+      expect(results[1].reclaimed).toBe(0);
+      await expect(checkReflinkSupport('/usb-drive')).resolves.toBe(false);
+    });
+
+    it('resolveDuplicates leaves groups without a copy in the kept folder alone', async () => {
+      const groups = await findDuplicates(['/test/path']);
+      const resolved = await resolveDuplicates(groups, {
+        strategy: 'in_directory',
+        prefix: '/nowhere',
+      });
+
+      for (const resolution of resolved) {
+        expect(resolution.delete).toHaveLength(0);
+        expect(resolution.reclaimable).toBe(0);
+      }
+    });
+
+    it('resolveDuplicates keeps protected copies', async () => {
+      const config = await getConfig();
+      await setConfig({ ...config, protected_paths: ['/test/path/photos'] });
+      const groups = await findDuplicates(['/test/path']);
+      const resolved = await resolveDuplicates(groups, { strategy: 'shortest_path' });
+
+      const guarded = resolved.flatMap(r => r.protected);
+      expect(guarded.length).toBeGreaterThan(0);
+      for (const resolution of resolved) {
+        expect(resolution.delete.every(f => !f.path.startsWith('/test/path/photos/'))).toBe(true);
+        for (const file of resolution.protected) {
+          expect(resolution.keep).toContain(file);
+        }
+      }
+    });
+
     it('fixFileExtensions renames misnamed files in web mode', async () => {
       const results = await fixFileExtensions(['/photos/scan.jpg']);
 
@@ -175,6 +621,122 @@ describe('API Layer', () => {
       expect(result).toHaveProperty('images');
     });
 
+    it('getTreemap nests directories to the requested depth in web mode', async () => {
+      const [root] = await getTreemap(['/data'], 1);
+
+      expect(root.path).toBe('/data');
+      expect(root.size).toBe(root.types.reduce((sum, t) => sum + t.total_size, 0));
+      const sizes = root.children.map(c => c.size);
+      expect(sizes).toEqual([...sizes].sort((a, b) => b - a));
+      expect(root.children.every(c => c.children.length === 0)).toBe(true);
+      expect(root.children[0].path).toBe(`/data/${root.children[0].name}`);
+
+      const [deeper] = await getTreemap(['/data'], 3);
+      expect(deeper.children.some(c => c.children.length > 0)).toBe(true);
+      expect(deeper.size).toBe(root.size);
+    });
+
+    it('getStorageHistory and diffScans compare recorded scans in web mode', async () => {
+      const history = await getStorageHistory('/history');
+      expect(history.snapshots.length).toBeGreaterThan(1);
+      const times = history.snapshots.map(s => s.scanned_at);
+      expect(times).toEqual([...times].sort((a, b) => a - b));
+      const video = history.growth.find(g => g.category === 'Video');
+      expect(video?.file_count_change).toBe(1);
+
+      const first = history.snapshots[0];
+      const last = history.snapshots[history.snapshots.length - 1];
+      const diff = await diffScans(first.scan_id, last.scan_id);
+      expect(diff.size_change).toBe(last.total_size - first.total_size);
+      expect(diff.added.some(c => c.path.endsWith('clip.mp4'))).toBe(true);
+      expect(diff.removed.some(c => c.path.endsWith('.dmg'))).toBe(true);
+      expect(diff.resized.map(c => c.path.split('/').pop())).toEqual(['beach.jpg', 'notes.txt']);
+      expect(diff.resized[0].new_size).toBeLessThan(diff.resized[0].old_size);
+      expect(diff.categories).toEqual(history.growth);
+
+      const other = await getStorageHistory('/elsewhere');
+      await expect(diffScans(first.scan_id, other.snapshots[0].scan_id)).rejects.toThrow('different paths');
+      await expect(diffScans(first.scan_id, 99999)).rejects.toMatchObject({
+        code: 'not_found',
+        message: 'Unknown scan: 99999',
+      });
+      expect((await getStorageHistory('/empty-dir')).snapshots).toEqual([]);
+
+      const recent = await getScanHistory(2);
+      expect(recent).toHaveLength(2);
+      expect(recent[0].scan.created_at).toBeGreaterThanOrEqual(recent[1].scan.created_at);
+    });
+
+    it('getScanDetails and deleteHistoryEntry work on recorded scans in web mode', async () => {
+      const history = await getStorageHistory('/home/user');
+      const latest = history.snapshots[history.snapshots.length - 1];
+      const entry = (await getScanHistory(100)).find(e => e.scan.id === latest.scan_id);
+      expect(entry?.reclaimable).toBeGreaterThan(0);
+
+      const details = await getScanDetails(latest.scan_id);
+      expect(details.scan.path).toBe('/home/user');
+      expect(details.largest[0].size).toBeGreaterThanOrEqual(details.largest[1].size);
+      expect(details.duplicates.reduce((sum, g) => sum + g.wasted_space, 0)).toBe(entry?.reclaimable);
+
+      await deleteHistoryEntry(latest.scan_id);
+      expect((await getStorageHistory('/home/user')).snapshots).toHaveLength(history.snapshots.length - 1);
+      await expect(getScanDetails(latest.scan_id)).rejects.toMatchObject({ code: 'not_found' });
+      await expect(deleteHistoryEntry(latest.scan_id)).rejects.toMatchObject({ code: 'not_found' });
+    });
+
+    it('getRecordedDuplicates lists groups by wasted space in web mode', async () => {
+      const groups = await getRecordedDuplicates();
+      expect(groups.length).toBeGreaterThan(0);
+      for (const group of groups) {
+        expect(group.file_paths).toHaveLength(group.file_count);
+      }
+      const wasted = groups.map(g => g.wasted_space);
+      expect(wasted).toEqual([...wasted].sort((a, b) => b - a));
+    });
+
+    it('getDuplicateHistory lists groups by wasted space in web mode', async () => {
+      const groups = await getDuplicateHistory();
+      expect(groups.length).toBeGreaterThan(0);
+      for (const group of groups) {
+        expect(group.file_paths).toHaveLength(group.file_count);
+      }
+      const wasted = groups.map(g => g.wasted_space);
+      expect(wasted).toEqual([...wasted].sort((a, b) => b - a));
+    });
+
+    it('browseRecordedFiles pages through the recorded files in web mode', async () => {
+      const first = await browseRecordedFiles({ by: 'largest' }, { offset: 0, limit: 2 });
+      const second = await browseRecordedFiles({ by: 'largest' }, { offset: 2, limit: 2 });
+      expect(first).toHaveLength(2);
+      const sizes = [...first, ...second].map(f => f.size);
+      expect(sizes).toEqual([...sizes].sort((a, b) => b - a));
+
+      const images = await browseRecordedFiles({ by: 'type', file_type: 'Image' });
+      expect(images.length).toBeGreaterThan(0);
+      expect(images.every(f => f.file_type === 'Image')).toBe(true);
+
+      const photos = await browseRecordedFiles({ by: 'under', path: '/home/user/Photos/' });
+      expect(photos.length).toBeGreaterThan(0);
+      expect(photos.every(f => f.path.startsWith('/home/user/Photos/'))).toBe(true);
+      expect(await browseRecordedFiles({ by: 'under', path: '/data/empty-dir' })).toEqual([]);
+
+      const before = Math.floor(Date.now() / 1000) - 150 * 86400;
+      const old = await browseRecordedFiles({ by: 'older_than', before });
+      expect(old.every(f => f.modified < before)).toBe(true);
+      expect(old.map(f => f.modified)).toEqual([...old.map(f => f.modified)].sort((a, b) => a - b));
+    });
+
+    it('getLargestFiles and getLargestDirectories sort and limit in web mode', async () => {
+      const files = await getLargestFiles(['/a', '/b'], 3);
+      expect(files).toHaveLength(3);
+      expect(files.map(f => f.size)).toEqual([...files.map(f => f.size)].sort((x, y) => y - x));
+
+      const dirs = await getLargestDirectories(['/a'], 2);
+      expect(dirs).toHaveLength(2);
+      expect(dirs[0].total_size).toBeGreaterThanOrEqual(dirs[1].total_size);
+      expect(await getLargestDirectories(['/empty-dir'], 10)).toEqual([]);
+    });
+
     it('deleteFiles reports per-file results in web mode', async () => {
       const results = await deleteFiles(['/file1.txt', '/locked/file2.txt']);
 
@@ -184,6 +746,108 @@ describe('API Layer', () => {
       expect(results[1].error).toBeTruthy();
     });
 
+    it('findDuplicateReport lists files that could not be read', async () => {
+      const report = await findDuplicateReport(['/data/locked']);
+      expect(report.unreadable).toEqual([
+        {
+          path: '/data/locked/image1.jpg',
+          error: 'Permission denied (os error 13)',
+          code: 'permission_denied',
+        },
+      ]);
+      expect(report.groups.length).toBeGreaterThan(0);
+      expect((await findDuplicateReport(['/test/path'])).unreadable).toEqual([]);
+    });
+
+    it('deleteFiles says what kind of failure each file hit', async () => {
+      const results = await deleteFiles(['/locked/a.txt', '/usb-drive/b.mp4', '/etc/hosts'], 'trash');
+      expect(results.map(r => r.error_code)).toEqual([
+        'permission_denied',
+        'file_operation',
+        'protected',
+      ]);
+    });
+
+    it('planDeleteFiles lists what deleteFiles would do in web mode', async () => {
+      const plan = await planDeleteFiles(['/photos/a.jpg', '/photos/b.jpg']);
+      expect(plan.actions.map(a => [a.path, a.action])).toEqual([
+        ['/photos/a.jpg', 'trash'],
+        ['/photos/b.jpg', 'trash'],
+      ]);
+      expect(plan.skipped).toEqual([]);
+
+      const permanent = await planDeleteFiles(['/photos/a.jpg'], 'permanent');
+      expect(permanent.actions[0].action).toBe('delete');
+      const quarantine = await planDeleteFiles(['/photos/a.jpg'], 'quarantine');
+      expect(quarantine.actions[0].action).toBe('quarantine');
+    });
+
+    it('planDeleteFiles skips protected, locked and missing files in web mode', async () => {
+      const plan = await planDeleteFiles(['/etc/hosts', '/locked/a.txt', '/missing/b.txt', '/photos/c.jpg']);
+
+      expect(plan.actions.map(a => a.path)).toEqual(['/photos/c.jpg']);
+      expect(plan.skipped.map(s => [s.path, s.code])).toEqual([
+        ['/etc/hosts', 'protected'],
+        ['/locked/a.txt', 'permission_denied'],
+        ['/missing/b.txt', 'not_found'],
+      ]);
+      expect(plan.skipped[0].error).toBe('Protected path: /etc/hosts is inside /etc');
+    });
+
+    it('deleteFiles in quarantine mode keeps files restorable in web mode', async () => {
+      const results = await deleteFiles(['/photos/copy.jpg', '/locked/b.jpg'], 'quarantine');
+      expect(results[0]).toEqual({ path: '/photos/copy.jpg', success: true });
+      expect(results[1].success).toBe(false);
+
+      const [record] = await listQuarantine();
+      expect(record.original_path).toBe('/photos/copy.jpg');
+      expect(record.expires_at - record.quarantined_at).toBe(30 * 86_400);
+      // Nothing has expired yet
+      await expect(purgeQuarantine()).resolves.toEqual([]);
+
+      await expect(restoreQuarantined(record.id)).resolves.toEqual(record);
+      expect(await listQuarantine()).toEqual([]);
+      await expect(restoreQuarantined(record.id)).rejects.toMatchObject({ code: 'not_found' });
+    });
+
+    it('quarantineFiles reports a per-file outcome in web mode', async () => {
+      const results = await quarantineFiles(['/photos/old.jpg', '/locked/a.jpg', '/missing/b.jpg']);
+      expect(results[0]).toEqual({ path: '/photos/old.jpg', success: true });
+      expect(results[1]).toMatchObject({ success: false, error_code: 'permission_denied' });
+      expect(results[2]).toMatchObject({
+        success: false,
+        error: 'File not found: /missing/b.jpg',
+        error_code: 'not_found',
+      });
+
+      // Only the file that was moved can be restored
+      const paths = (await listQuarantine()).map(r => r.original_path);
+      expect(paths).toContain('/photos/old.jpg');
+      expect(paths).not.toContain('/locked/a.jpg');
+      expect(paths).not.toContain('/missing/b.jpg');
+    });
+
+    it('compression backups can be browsed, restored and purged in web mode', async () => {
+      await compressFilesInPlace(['/photos/backup-me.png'], ['WebP Converter']);
+      const [latest] = await listCompressionBackups();
+      expect(latest).toMatchObject({
+        path: '/photos/backup-me.png',
+        backup_path: '/photos/backup-me.png.bak',
+        plugin_name: 'WebP Converter',
+        saved: 307200,
+      });
+
+      const point = await restoreCompression(latest.id);
+      expect(point).toMatchObject({ kind: 'compression', path: '/photos/backup-me.png' });
+      await expect(restoreCompression(latest.id)).rejects.toMatchObject({ code: 'not_found' });
+
+      // Only the seeded compression from 40 days ago is older than a month
+      const purge = await purgeBackups('30d');
+      expect(purge.purged.map(b => b.plugin_name)).toEqual(['WebP Converter']);
+      expect(purge.failed).toEqual([]);
+      await expect(purgeBackups('whenever')).rejects.toMatchObject({ code: 'invalid_input' });
+    });
+
     it('deleteFiles mock simulates a volume without a trash directory', async () => {
       // Trash mode fails for the USB-drive file...
       const trashed = await deleteFiles(['/usb-drive/video.mp4'], 'trash');
@@ -195,20 +859,136 @@ describe('API Layer', () => {
       expect(permanent[0].success).toBe(true);
     });
 
-    it('getCompressionPlugins returns all three plugins with quality in web mode', async () => {
+    it('deleteFiles refuses system directories and configured protected paths', async () => {
+      const config = await getConfig();
+      await setConfig({ ...config, protected_paths: ['/home/me/Keep'] });
+      const results = await deleteFiles(
+        ['/etc/hosts', '/home/me/Keep/a.txt', '/home/me/Keeper/a.txt'],
+        'permanent'
+      );
+
+      expect(results[0]).toMatchObject({ success: false, protected: true });
+      expect(results[0].error).toContain('Protected path');
+      expect(results[1].protected).toBe(true);
+      // Component-wise: a sibling sharing the name prefix is not protected
+      expect(results[2]).toEqual({ path: '/home/me/Keeper/a.txt', success: true });
+    });
+
+    it('createArchive resolves to the archive size in web mode', async () => {
+      const size = await createArchive('/docs/2019', '/backup/2019.zip', 'secret');
+      expect(size).toBeGreaterThan(0);
+    });
+
+    it('createArchive mock makes 7z archives smaller than zip', async () => {
+      const zip = await createArchive('/docs/2019', '/backup/2019.zip');
+      const sevenZip = await createArchive('/docs/2019', '/backup/2019.7z', null, '7z');
+      expect(sevenZip).toBeLessThan(zip);
+    });
+
+    it('archiveDirectory mock records the archive in web mode', async () => {
+      const record = await archiveDirectory('/projects/old', '/cold/old.tar.zst');
+      expect(record.source_path).toBe('/projects/old');
+      expect(record.archive_path).toBe('/cold/old.tar.zst');
+      expect(record.format).toBe('tar.zst');
+      expect(record.archive_size).toBeLessThan(record.original_size);
+    });
+
+    it('archiveDirectory mock rejects a missing source and a tar.zst password', async () => {
+      await expect(archiveDirectory('/missing/old', '/cold/old.tar.zst')).rejects.toThrow(
+        'Source not found'
+      );
+      await expect(
+        archiveDirectory('/projects/old', '/cold/old.tar.zst', 'tar.zst', 'pw')
+      ).rejects.toThrow('cannot be password protected');
+    });
+
+    it('createArchive mock rejects a missing source and an empty password', async () => {
+      await expect(createArchive('/missing/docs', '/backup/docs.zip')).rejects.toThrow(
+        'Source not found'
+      );
+      await expect(createArchive('/docs', '/backup/docs.zip', '')).rejects.toThrow(
+        'must not be empty'
+      );
+    });
+
+    it('getCompressionPlugins returns all five plugins with quality in web mode', async () => {
       const plugins = await getCompressionPlugins();
 
       expect(plugins.map(p => p.name)).toEqual([
         'Image ZIP to WebP ZIP',
         'WebP Converter',
         'Animated WebP Converter',
+        'RAW to JPEG',
+        'Text Asset Minifier',
       ]);
+      const expectedQuality: Record<string, number | null> = {
+        'RAW to JPEG': 92,
+        'Text Asset Minifier': null,
+      };
       for (const plugin of plugins) {
         expect(plugin.description).toBeTruthy();
-        expect(plugin.quality).toBe(85);
+        expect(plugin.quality).toBe(expectedQuality[plugin.name] ?? 85);
       }
     });
 
+    it('getCompressionPlugins exposes lossy/destructive/external-tool flags', async () => {
+      const plugins = await getCompressionPlugins();
+
+      const zip = plugins.find(p => p.name === 'Image ZIP to WebP ZIP');
+      expect(zip?.destructive).toBe(true);
+      expect(plugins.find(p => p.name === 'WebP Converter')?.destructive).toBe(false);
+      expect(plugins.filter(p => !p.lossy).map(p => p.name)).toEqual(['Text Asset Minifier']);
+      expect(plugins.find(p => p.name === 'Text Asset Minifier')?.destructive).toBe(true);
+      expect(plugins.find(p => p.name === 'Animated WebP Converter')?.requires_external_tools)
+        .toEqual(['gif2webp', 'ffmpeg']);
+    });
+
+    it('scanCompressibleFiles rejects destructive plugins in safe mode', async () => {
+      const config = await getConfig();
+      await setConfig({ ...config, safe_mode: true });
+
+      const result = await scanCompressibleFiles(['/test/path'], ['Image ZIP to WebP ZIP', 'WebP Converter']);
+      expect(result.compressible.some(f => f.plugin_name === 'Image ZIP to WebP ZIP')).toBe(false);
+      const zip = result.rejected.find(f => f.path.endsWith('photos.zip'));
+      expect(zip?.rejection_reasons[0].reason).toContain('safe mode');
+    });
+
+    it('estimateCompression groups files per plugin and extension', async () => {
+      const estimate = await estimateCompression(['/test/path'], ['WebP Converter'], 2);
+      const png = estimate.groups.find(g => g.extension === 'png');
+      expect(png).toMatchObject({ plugin_name: 'WebP Converter', measured: true, sampled: 2 });
+      expect(png!.file_count).toBeGreaterThan(2);
+      expect(png!.estimated_savings).toBe(Math.floor(png!.total_size * png!.savings_ratio));
+      expect(estimate.groups.every(g => g.plugin_name === 'WebP Converter')).toBe(true);
+
+      await expect(estimateCompression(['/test/path'], ['No Such Plugin']))
+        .rejects.toMatchObject({ code: 'not_found' });
+    });
+
+    it('scanCompressibleFiles only offers minification inside configured directories', async () => {
+      const svg = '/path/to/site/assets/logo.svg';
+      let result = await scanCompressibleFiles(['/test/path'], ['Text Asset Minifier']);
+      expect(result.compressible.some(f => f.path === svg)).toBe(false);
+      expect(result.rejected.find(f => f.path === svg)?.rejection_reasons[0].reason)
+        .toContain('No directories');
+
+      const config = await getConfig();
+      await setConfig({ ...config, minify_dirs: ['/path/to/site'] });
+      result = await scanCompressibleFiles(['/test/path'], ['Text Asset Minifier']);
+      expect(result.compressible.some(f => f.path === svg)).toBe(true);
+
+      await setConfig({ ...config, minify_dirs: ['/elsewhere'] });
+      result = await scanCompressibleFiles(['/test/path'], ['Text Asset Minifier']);
+      expect(result.rejected.find(f => f.path === svg)?.rejection_reasons[0].reason)
+        .toContain('Outside');
+    });
+
+    it('setPluginQuality rejects plugins without a quality setting', async () => {
+      await expect(setPluginQuality('Text Asset Minifier', 50)).rejects.toThrow(
+        'does not support a quality setting'
+      );
+    });
+
     it('setPluginQuality resolves in web mode', async () => {
       await expect(setPluginQuality('WebP Converter', 60)).resolves.toBeUndefined();
     });
@@ -231,6 +1011,32 @@ describe('API Layer', () => {
       expect((await getConfig()).plugin_quality['WebP Converter']).toBe(100);
     });
 
+    it('setPluginEnabled turns plugins off and on in web mode', async () => {
+      const enabled = async (name: string) =>
+        (await getCompressionPlugins()).find(p => p.name === name)?.enabled;
+
+      await setPluginEnabled('WebP Converter', false);
+      expect(await enabled('WebP Converter')).toBe(false);
+      expect(await enabled('RAW to JPEG')).toBe(true);
+      expect((await getConfig()).disabled_plugins).toEqual(['WebP Converter']);
+
+      // Turning it off twice lists it once
+      await setPluginEnabled('WebP Converter', false);
+      expect((await getConfig()).disabled_plugins).toEqual(['WebP Converter']);
+
+      await setPluginEnabled('WebP Converter', true);
+      expect(await enabled('WebP Converter')).toBe(true);
+      expect((await getConfig()).disabled_plugins).toEqual([]);
+    });
+
+    it('setPluginEnabled rejects unknown plugins like the backend', async () => {
+      await expect(setPluginEnabled('AVIF Encoder', false)).rejects.toMatchObject({
+        code: 'not_found',
+        message: 'Plugin not found: AVIF Encoder',
+      });
+      expect((await getConfig()).disabled_plugins ?? []).toEqual([]);
+    });
+
     it('resetConfig clears persisted plugin quality back to the default', async () => {
       await setPluginQuality('WebP Converter', 40);
       expect((await getCompressionPlugins()).find(p => p.name === 'WebP Converter')?.quality).toBe(40);
@@ -263,6 +1069,78 @@ describe('API Layer', () => {
       expect(results[0].savings).toBeGreaterThan(0);
     });
 
+    it('compressFilesInPlace reports metadata preservation per the config', async () => {
+      let results = await compressFilesInPlace(['/photos/a.jpg', '/photos/b.png'], ['WebP Converter']);
+      expect(results[0].metadata_preserved).toBe(true);
+      expect(results[1].metadata_preserved).toBeNull();
+
+      const config = await getConfig();
+      await setConfig({ ...config, preserve_metadata: false });
+      results = await compressFilesInPlace(['/photos/a.jpg'], ['WebP Converter']);
+      expect(results[0].metadata_preserved).toBe(false);
+    });
+
+    it('compressFilesInPlace skips results below min_savings_percent', async () => {
+      const config = await getConfig();
+      await setConfig({ ...config, min_savings_percent: 40 });
+
+      const [result] = await compressFilesInPlace(['/photos/a.png'], ['WebP Converter']);
+      expect(result.status).toBe('skipped');
+      expect(result.success).toBe(true);
+      expect(result.reason).toContain('Insufficient savings');
+    });
+
+    it('setConfig rejects out-of-range min_savings_percent', async () => {
+      const config = await getConfig();
+      await expect(setConfig({ ...config, min_savings_percent: 150 })).rejects.toThrow(
+        'min_savings_percent must be between 0 and 100'
+      );
+    });
+
+    it('compressFilesInPlace converts RAWs and archives them per raw_policy', async () => {
+      let [result] = await compressFilesInPlace(['/photos/IMG_0042.CR2'], ['RAW to JPEG']);
+      expect(result.status).toBe('compressed');
+      expect(result.path).toBe('/photos/IMG_0042.jpg');
+      expect(result.extra_outputs).toEqual([]);
+
+      const config = await getConfig();
+      await setConfig({ ...config, raw_policy: 'archive' });
+      [result] = await compressFilesInPlace(['/photos/IMG_0042.CR2'], ['RAW to JPEG']);
+      expect(result.extra_outputs).toEqual(['/photos/IMG_0042.CR2.zip']);
+      expect(result.compressed_size).toBeGreaterThan(6291456);
+
+      await expect(
+        setConfig({ ...config, raw_policy: 'shred' as unknown as 'delete' })
+      ).rejects.toThrow("raw_policy must be 'delete' or 'archive'");
+    });
+
+    it('compressFilesInPlace reports timed_out files unless the timeout is off', async () => {
+      let [result] = await compressFilesInPlace(['/videos/slow-tool.gif'], ['Animated WebP Converter']);
+      expect(result.status).toBe('timed_out');
+      expect(result.success).toBe(false);
+      expect(result.error).toBe('Timed out after 300s');
+
+      const config = await getConfig();
+      await setConfig({ ...config, file_timeout_secs: 0 });
+      [result] = await compressFilesInPlace(['/videos/slow-tool.gif'], ['Animated WebP Converter']);
+      expect(result.status).toBe('compressed');
+    });
+
+    it('setConfig rejects out-of-range tool limits', async () => {
+      const config = await getConfig();
+      await expect(setConfig({ ...config, tool_nice_level: 25 })).rejects.toThrow(
+        'tool_nice_level must be between 0 and 19'
+      );
+      await expect(setConfig({ ...config, tool_max_memory_mb: 0 })).rejects.toThrow(
+        'tool_max_memory_mb must be at least 1'
+      );
+      await expect(setConfig({ ...config, background_io_limit: 0 })).rejects.toThrow(
+        'background_io_limit must be at least 1'
+      );
+      await setConfig({ ...config, background_io_limit: 10485760, background_io_low_priority: true });
+      expect((await getConfig()).background_io_limit).toBe(10485760);
+    });
+
     it('compressFilesInPlace mock covers all three result states', async () => {
       const results = await compressFilesInPlace(
         ['/photos/a.png', '/photos/already-tiny.png', '/photos/locked.png'],
@@ -279,6 +1157,7 @@ describe('API Layer', () => {
       const failed = results[2];
       expect(failed.success).toBe(false);
       expect(failed.error).toBeTruthy();
+      expect(failed.error_code).toBe('permission_denied');
     });
 
     it('compressFilesInPlace omits backup_path when backups are disabled', async () => {
@@ -288,12 +1167,121 @@ describe('API Layer', () => {
       expect(results[0].backup_path).toBeUndefined();
     });
 
+    it('cancelCompression reports in-flight files as cancelled in web mode', async () => {
+      const pending = compressFilesInPlace(['/photos/a.png', '/photos/b.png'], ['WebP Converter']);
+      await expect(cancelCompression()).resolves.toBe(1);
+
+      const results = await pending;
+      expect(results.map(r => r.status)).toEqual(['cancelled', 'cancelled']);
+      expect(results.every(r => !r.success && r.backup_path === undefined)).toBe(true);
+    });
+
+    it('listJobs records finished jobs in web mode', async () => {
+      await findDuplicates(['/a', '/b']);
+      const job = (await listJobs()).at(-1)!;
+
+      expect(job.task_type).toEqual({ FindDuplicates: ['/a', '/b'] });
+      expect(job.status).toBe('Completed');
+      expect([job.current, job.total]).toEqual([2, 2]);
+      await expect(getJobStatus(job.id)).resolves.toEqual(job);
+      // Finished jobs cannot be cancelled; unknown ones are errors
+      await expect(cancelJob(job.id)).resolves.toBe(false);
+      await expect(getJobStatus(-1)).rejects.toMatchObject({ code: 'not_found', message: 'Unknown job: -1' });
+    });
+
+    it('takeShutdownReport has nothing to report in web mode', async () => {
+      await expect(takeShutdownReport()).resolves.toBeNull();
+    });
+
+    it('cancelJob cancels a running compression in web mode', async () => {
+      const pending = compressFilesInPlace(['/photos/a.png'], ['WebP Converter']);
+      const job = (await listJobs()).at(-1)!;
+      expect(job.status).toBe('Running');
+      await expect(cancelJob(job.id)).resolves.toBe(true);
+
+      const results = await pending;
+      expect(results[0].status).toBe('cancelled');
+      expect((await getJobStatus(job.id)).status).toBe('Cancelled');
+    });
+
+    it('onCompressionFile reports each file starting and ending', async () => {
+      const events: CompressionFileEvent[] = [];
+      const unlisten = await onCompressionFile(e => events.push(e));
+      await compressFilesInPlace(['/photos/queue.png', '/photos/missing.png'], ['WebP Converter']);
+      unlisten();
+
+      expect(events.map(e => [e.status, e.percent])).toEqual([
+        ['started', 0],
+        ['compressed', 50],
+        ['started', 50],
+        ['failed', 100],
+      ]);
+      expect(events[1].savings).toBe(307200);
+      expect(events[3]).toMatchObject({ path: '/photos/missing.png', reason: 'File not found', index: 1, total: 2 });
+    });
+
+    it('onCompressionProgress receives per-file progress in web mode', async () => {
+      const updates: Array<[string, number, number]> = [];
+      const unlisten = await onCompressionProgress(p => updates.push([p.path, p.current, p.total]));
+
+      await compressFilesInPlace(['/photos/a.png'], ['WebP Converter']);
+      unlisten();
+      await compressFilesInPlace(['/photos/b.png'], ['WebP Converter']);
+
+      expect(updates).toEqual([
+        ['/photos/a.png', 0, 1],
+        ['/photos/a.png', 1, 1],
+      ]);
+    });
+
+    it('cancelCompression with nothing in flight signals no runs', async () => {
+      await expect(cancelCompression()).resolves.toBe(0);
+
+      // A later run is unaffected by the earlier cancel
+      const results = await compressFilesInPlace(['/photos/a.png'], ['WebP Converter']);
+      expect(results[0].status).toBe('compressed');
+    });
+
     it('skip cache info and clear resolve in web mode', async () => {
       const info = await getSkipCacheInfo();
       expect(info.entries).toBeGreaterThanOrEqual(0);
       await expect(clearSkipCache()).resolves.toBeGreaterThanOrEqual(0);
     });
 
+    it('getSavingsSummary adds up compressions per plugin and month in web mode', async () => {
+      const before = await getSavingsSummary();
+      expect(before.saved).toBe(before.by_plugin.reduce((sum, p) => sum + p.saved, 0));
+      expect(before.saved).toBe(before.by_month.reduce((sum, m) => sum + m.saved, 0));
+      const months = before.by_month.map(m => m.month);
+      expect(months).toEqual([...months].sort());
+
+      await compressFilesInPlace(['/photos/savings-a.png', '/photos/already-tiny.png'], ['WebP Converter']);
+      await clearSkipCache();
+      const after = await getSavingsSummary();
+      expect(after.file_count).toBe(before.file_count + 1);
+      expect(after.saved).toBe(before.saved + 307200);
+      const saved = after.by_plugin.map(p => p.saved);
+      expect(saved).toEqual([...saved].sort((a, b) => b - a));
+    });
+
+    it('skipCompressionFiles excludes files from the next scan until cleared', async () => {
+      const scan = await scanCompressibleFiles(['/test/path'], ['WebP Converter']);
+      const target = scan.compressible[0].path;
+
+      // Missing files are not recorded
+      await expect(skipCompressionFiles([target, '/photos/missing.png'])).resolves.toBe(1);
+      expect((await getSkipCacheInfo()).history_entries).toBe(1);
+
+      const rescan = await scanCompressibleFiles(['/test/path'], ['WebP Converter']);
+      expect(rescan.compressible.some(f => f.path === target)).toBe(false);
+      const skipped = rescan.rejected.find(f => f.path === target);
+      expect(skipped?.rejection_reasons[0].reason).toContain('chose not to compress');
+
+      expect(await clearSkipCache()).toBe(1);
+      const restored = await scanCompressibleFiles(['/test/path'], ['WebP Converter']);
+      expect(restored.compressible.some(f => f.path === target)).toBe(true);
+    });
+
     it('scanCompressibleFiles mock includes files that will skip and fail', async () => {
       const result = await scanCompressibleFiles(['/test/path'], ['WebP Converter']);
       const paths = result.compressible.map(f => f.path);
@@ -303,15 +1291,25 @@ describe('API Layer', () => {
     });
 
     it('setPluginQuality rejects unknown plugins with the backend error string', async () => {
-      await expect(setPluginQuality('No Such Plugin', 50)).rejects.toBe(
-        'Plugin not found: No Such Plugin'
-      );
+      await expect(setPluginQuality('No Such Plugin', 50)).rejects.toMatchObject({
+        code: 'not_found',
+        message: 'Plugin not found: No Such Plugin',
+      });
     });
 
     it('scanCompressibleFiles rejects unknown active plugins like the backend', async () => {
-      await expect(scanCompressibleFiles(['/test/path'], ['No Such Plugin'])).rejects.toBe(
-        'Active plugin not found: No Such Plugin'
-      );
+      await expect(scanCompressibleFiles(['/test/path'], ['No Such Plugin'])).rejects.toMatchObject({
+        code: 'not_found',
+        message: 'Active plugin not found: No Such Plugin',
+      });
+    });
+
+    it('compressFilesInPlace refuses protected files', async () => {
+      const results = await compressFilesInPlace(['/usr/share/pixmaps/logo.png'], []);
+
+      expect(results[0].status).toBe('failed');
+      expect(results[0].protected).toBe(true);
+      expect(results[0].error).toContain('Protected path');
     });
 
     it('compressFilesInPlace reports missing files as File not found', async () => {
@@ -466,7 +1464,8 @@ describe('API Layer', () => {
       const config = await getConfig();
       config.image_similarity_threshold = 5;
 
-      await expect(setConfig(config)).rejects.toContain('between 0.0 and 1.0');
+      await expect(setConfig(config)).rejects.toThrow('between 0.0 and 1.0');
+      await expect(setConfig(config)).rejects.toMatchObject({ code: 'invalid_input' });
     });
 
     it('setConfig rejects an invalid delete mode like the backend', async () => {
@@ -474,14 +1473,83 @@ describe('API Layer', () => {
       // @ts-expect-error deliberately invalid to exercise the validation path
       config.default_delete_mode = 'shred';
 
-      await expect(setConfig(config)).rejects.toContain("'trash' or 'permanent'");
+      await expect(setConfig(config)).rejects.toThrow("'quarantine' or 'permanent'");
     });
 
     it('setConfig rejects a max_concurrent_tasks below 1', async () => {
       const config = await getConfig();
       config.max_concurrent_tasks = 0;
 
-      await expect(setConfig(config)).rejects.toContain('at least 1');
+      await expect(setConfig(config)).rejects.toThrow('at least 1');
+    });
+
+    it('updateConfig merges partial changes and reports what needs a restart', async () => {
+      const before = await getConfig();
+      const update = await updateConfig({ log_level: 'debug', scan: { min_file_size: 10 } });
+      expect(update.restart_required).toEqual([]);
+      expect(update.config.scan.min_file_size).toBe(10);
+      expect(update.config.scan.exclude_patterns).toEqual(before.scan.exclude_patterns);
+      expect((await getConfig()).log_level).toBe('debug');
+
+      const moved = await updateConfig({ cache_dir: '/home/demo/cache' });
+      expect(moved.restart_required).toEqual(['cache_dir']);
+      await expect(updateConfig({ log_level: 'info', max_concurrent_tasks: 0 })).rejects.toThrow('at least 1');
+      expect((await getConfig()).log_level).toBe('debug');
+    });
+
+    it('cleanup rules persist, validate and run as a plan first', async () => {
+      const rule = {
+        name: 'old downloads',
+        paths: ['/home/demo/Downloads'],
+        filter: 'ext in (dmg,log)',
+        older_than_days: 90,
+        action: 'trash' as const,
+        enabled: true,
+      };
+      await setCleanupRules([rule]);
+      expect(await getCleanupRules()).toEqual([rule]);
+      await expect(setCleanupRules([rule, rule])).rejects.toThrow('unique');
+      await expect(setCleanupRules([{ ...rule, action: 'archive' }])).rejects.toThrow('archive_dir');
+
+      const plan = await runCleanup(null, true);
+      expect(plan.dry_run).toBe(true);
+      expect(plan.matched_count).toBeGreaterThan(0);
+      expect(plan.freed).toBe(0);
+
+      const report = await runCleanup(['old downloads'], false);
+      expect(report.freed).toBe(report.matched_size);
+      await expect(runCleanup(['nope'], true)).rejects.toThrow('Unknown cleanup rule');
+    });
+
+    it('schedules are created, run now, listed with next runs and deleted in web mode', async () => {
+      const weekly = {
+        name: 'weekly cleanup',
+        task: 'cleanup' as const,
+        every_minutes: 7 * 24 * 60,
+        paths: [],
+        rules: [],
+        enabled: true,
+      };
+      await createSchedule(weekly);
+      await createSchedule({ ...weekly, name: 'hourly tidy', task: 'maintenance', every_minutes: 60 });
+      await expect(createSchedule(weekly)).rejects.toThrow('unique');
+      await expect(createSchedule({ ...weekly, name: 'x', rules: ['nope'] })).rejects.toThrow('unknown cleanup rule');
+
+      const run = await runScheduleNow('weekly cleanup');
+      expect(run.on_daemon).toBe(true);
+      const entries = await listSchedules();
+      expect(entries.map(e => e.schedule.name)).toEqual(['weekly cleanup', 'hourly tidy']);
+      expect(entries[0].status?.last_result).toBeTruthy();
+
+      const runs = await getNextRuns(3);
+      expect(runs).toHaveLength(3);
+      expect(runs.every(r => r.schedule === 'hourly tidy')).toBe(true);
+      expect(runs[1].at - runs[0].at).toBe(3600);
+
+      await deleteSchedule('weekly cleanup');
+      expect(await listSchedules()).toHaveLength(1);
+      await expect(deleteSchedule('weekly cleanup')).rejects.toMatchObject({ code: 'not_found' });
+      await expect(runScheduleNow('weekly cleanup')).rejects.toMatchObject({ code: 'not_found' });
     });
 
     it('resetConfig restores defaults and persists them in web mode', async () => {
@@ -501,6 +1569,29 @@ describe('API Layer', () => {
       expect(reloaded.default_delete_mode).toBe('trash');
     });
 
+    it('checkPluginRequirements reports tool health and honours configured paths', async () => {
+      let reports = await checkPluginRequirements();
+      expect(reports.map(r => r.plugin_name)).toHaveLength(5);
+      expect(reports.every(r => r.ready)).toBe(true);
+
+      let animated = reports.find(r => r.plugin_name === 'Animated WebP Converter')!;
+      expect(animated.tools.map(t => t.name)).toEqual(['gif2webp', 'ffmpeg']);
+      expect(animated.tools[0].available).toBe(false);
+      expect(animated.message).toContain('ffmpeg');
+
+      const config = await getConfig();
+      await setConfig({
+        ...config,
+        tool_paths: { gif2webp: '/opt/libwebp/bin/gif2webp', ffmpeg: '/missing/ffmpeg' },
+      });
+      reports = await checkPluginRequirements();
+      animated = reports.find(r => r.plugin_name === 'Animated WebP Converter')!;
+      expect(animated.tools[0]).toMatchObject({ program: '/opt/libwebp/bin/gif2webp', available: true });
+      expect(animated.tools[1].available).toBe(false);
+      expect(animated.tools[1].error).toBeTruthy();
+      expect(animated.message).toContain('gif2webp');
+    });
+
     it('detectTools reports both available and missing tools in web mode', async () => {
       const tools = await detectTools();
 
@@ -520,6 +1611,226 @@ describe('API Layer', () => {
       expect(cwebp?.available).toBe(false);
       expect(cwebp?.path == null).toBe(true);
     });
+
+    it('listDrives covers every drive kind with consistent space', async () => {
+      const drives = await listDrives();
+
+      expect(new Set(drives.map(d => d.kind))).toEqual(new Set(['fixed', 'removable', 'network']));
+      for (const drive of drives) {
+        expect(drive.used_space + drive.available_space).toBe(drive.total_space);
+      }
+      expect(drives.map(d => d.mount_point)).toEqual([...drives.map(d => d.mount_point)].sort());
+    });
+
+    it('validatePaths warns about missing paths, slow drives and huge trees', async () => {
+      const [missing, nas, huge, file] = await validatePaths([
+        '/home/missing',
+        '/mnt/nas/photos',
+        '/home/data/huge',
+        '/media/usb-drive/a.jpg',
+      ]);
+      expect(missing).toMatchObject({ exists: false, estimated_files: null });
+      expect(nas).toMatchObject({ drive_kind: 'network', mount_point: '/mnt/nas', readable: true });
+      expect(nas.warnings[0]).toContain('network drive');
+      expect(huge.mount_point).toBe('/home/data');
+      expect(huge.warnings.some(w => w.includes('long time'))).toBe(true);
+      expect(file).toMatchObject({ is_dir: false, estimated_files: 1, exact: true, drive_kind: 'removable' });
+    });
+
+    it('scanAppCaches finds caches and the build output beneath each root', async () => {
+      const report = await scanAppCaches(['/home/user/code']);
+
+      const sizes = report.categories.map(c => c.size);
+      expect(sizes).toEqual([...sizes].sort((a, b) => b - a));
+      expect(report.total_size).toBe(sizes.reduce((sum, s) => sum + s, 0));
+      const modules = report.categories.find(c => c.category === 'node_modules');
+      expect(modules?.locations.map(l => l.path)).toEqual(['/home/user/code/web/node_modules']);
+      expect(report.categories.some(c => c.category === 'browser')).toBe(true);
+    });
+
+    it('scanAppCaches finds nothing in empty folders and rejects locked or missing roots', async () => {
+      await expect(scanAppCaches(['/tmp/empty-dir'])).resolves.toEqual({ categories: [], total_size: 0 });
+
+      const locked = await scanAppCaches(['/home/user/code', '/root/locked']).catch((e) => e);
+      expect(locked).toBeInstanceOf(ServiceError);
+      expect(locked).toMatchObject({ code: 'permission_denied', message: 'Permission denied (os error 13)' });
+
+      const missing = await scanAppCaches(['/home/missing']).catch((e) => e);
+      expect(missing).toBeInstanceOf(ServiceError);
+      expect(missing).toMatchObject({ code: 'not_found', message: 'Folder not found: /home/missing' });
+    });
+
+    const cacheReport: AppCacheReport = {
+      categories: [
+        {
+          category: 'target',
+          label: 'Rust target folders',
+          locations: [
+            { category: 'target', path: '/code/tool/target', size: 3000, file_count: 30 },
+            { category: 'target', path: '/code/locked/target', size: 2000, file_count: 20 },
+            { category: 'target', path: '/code/missing/target', size: 1000, file_count: 10 },
+          ],
+          size: 6000,
+          file_count: 60,
+        },
+        {
+          category: 'browser',
+          label: 'Browser caches',
+          locations: [{ category: 'browser', path: '/home/user/.cache/chromium', size: 500, file_count: 5 }],
+          size: 500,
+          file_count: 5,
+        },
+      ],
+      total_size: 6500,
+    };
+
+    it('planAppCacheCleaning plans the chosen categories and skips locked and missing folders', async () => {
+      const plan = await planAppCacheCleaning(cacheReport, ['target'], 'permanent');
+
+      expect(plan.actions).toEqual([
+        { path: '/code/tool/target', action: 'delete', size: 3000, reclaimed: 3000, detail: 'Rust target folders' },
+      ]);
+      expect(plan.skipped).toEqual([
+        { path: '/code/locked/target', error: 'IO error: Permission denied (os error 13)', code: 'permission_denied' },
+        { path: '/code/missing/target', error: 'No longer a folder: /code/missing/target', code: 'not_found' },
+      ]);
+      expect(plan).toMatchObject({ total_size: 3000, reclaimed: 3000 });
+
+      const empty = await planAppCacheCleaning(cacheReport, []);
+      expect(empty).toEqual({ actions: [], skipped: [], total_size: 0, reclaimed: 0 });
+    });
+
+    it('cleanAppCaches reports a per-folder outcome', async () => {
+      const results = await cleanAppCaches(cacheReport, ['target', 'browser']);
+
+      expect(results).toEqual([
+        { path: '/code/tool/target', success: true },
+        {
+          path: '/code/locked/target',
+          success: false,
+          error: 'IO error: Permission denied (os error 13)',
+          error_code: 'permission_denied',
+        },
+        {
+          path: '/code/missing/target',
+          success: false,
+          error: 'No longer a folder: /code/missing/target',
+          error_code: 'not_found',
+        },
+        { path: '/home/user/.cache/chromium', success: true },
+      ]);
+      await expect(cleanAppCaches(cacheReport, [])).resolves.toEqual([]);
+    });
+
+    it('findDevArtifacts suggests the projects untouched for the given months', async () => {
+      const report = await findDevArtifacts(['/home/user/code']);
+
+      expect(report.untouched_months).toBe(6);
+      const suggested = report.projects.filter(p => p.suggested).map(p => p.root);
+      expect(suggested.sort()).toEqual(['/home/user/code/old-site', '/home/user/code/old-tool']);
+      expect(report.projects.findIndex(p => !p.suggested)).toBe(suggested.length);
+      expect(report.reclaimable).toBe(
+        report.projects.filter(p => p.suggested).reduce((sum, p) => sum + p.size, 0)
+      );
+
+      const patient = await findDevArtifacts(['/home/user/code'], 24);
+      expect(patient.projects.some(p => p.suggested)).toBe(false);
+      expect(patient.reclaimable).toBe(0);
+    });
+
+    it('findDevArtifacts finds nothing in empty folders and rejects locked or missing roots', async () => {
+      const empty = await findDevArtifacts(['/tmp/empty-dir']);
+      expect(empty).toEqual({ projects: [], total_size: 0, reclaimable: 0, untouched_months: 6 });
+
+      const locked = await findDevArtifacts(['/root/locked']).catch((e) => e);
+      expect(locked).toBeInstanceOf(ServiceError);
+      expect(locked).toMatchObject({ code: 'permission_denied', message: 'Permission denied (os error 13)' });
+
+      const missing = await findDevArtifacts(['/home/user/code', '/home/missing']).catch((e) => e);
+      expect(missing).toBeInstanceOf(ServiceError);
+      expect(missing).toMatchObject({ code: 'not_found', message: 'Folder not found: /home/missing' });
+    });
+
+    const artifactReport: DevArtifactReport = {
+      projects: [
+        {
+          root: '/code/old',
+          markers: ['Cargo.toml'],
+          last_activity: 1,
+          artifacts: [
+            { kind: 'target', path: '/code/old/target', size: 3000, file_count: 30, last_build: 1 },
+            { kind: 'dist', path: '/code/old/locked/dist', size: 2000, file_count: 20, last_build: 1 },
+            { kind: 'venv', path: '/code/old/missing/.venv', size: 1000, file_count: 10, last_build: 1 },
+          ],
+          size: 6000,
+          suggested: true,
+        },
+        {
+          root: '/code/current',
+          markers: ['package.json'],
+          last_activity: 2,
+          artifacts: [{ kind: 'node_modules', path: '/code/current/node_modules', size: 500, file_count: 5, last_build: 2 }],
+          size: 500,
+          suggested: false,
+        },
+      ],
+      total_size: 6500,
+      reclaimable: 6000,
+      untouched_months: 6,
+    };
+
+    it('planDevArtifactRemoval plans the suggested projects and skips locked and missing folders', async () => {
+      const plan = await planDevArtifactRemoval(artifactReport, 'permanent');
+
+      expect(plan.actions).toEqual([
+        { path: '/code/old/target', action: 'delete', size: 3000, reclaimed: 3000, detail: null },
+      ]);
+      expect(plan.skipped).toEqual([
+        { path: '/code/old/locked/dist', error: 'IO error: Permission denied (os error 13)', code: 'permission_denied' },
+        { path: '/code/old/missing/.venv', error: 'No longer a folder: /code/old/missing/.venv', code: 'not_found' },
+      ]);
+      expect(plan).toMatchObject({ total_size: 3000, reclaimed: 3000 });
+    });
+
+    it('removeDevArtifacts reports a per-folder outcome for the suggested projects', async () => {
+      const results = await removeDevArtifacts(artifactReport);
+
+      expect(results).toEqual([
+        { path: '/code/old/target', success: true },
+        {
+          path: '/code/old/locked/dist',
+          success: false,
+          error: 'IO error: Permission denied (os error 13)',
+          error_code: 'permission_denied',
+        },
+        {
+          path: '/code/old/missing/.venv',
+          success: false,
+          error: 'No longer a folder: /code/old/missing/.venv',
+          error_code: 'not_found',
+        },
+      ]);
+    });
+  });
+
+  describe('Service errors', () => {
+    it('web mode rejects with a coded ServiceError like the backend', async () => {
+      const err = await createArchive('/docs', '/backup/docs.zip', '').catch((e) => e);
+      expect(err).toBeInstanceOf(ServiceError);
+      expect(err).toBeInstanceOf(Error);
+      expect(err.code).toBe('invalid_input');
+      expect(err.message).toBe('Archive password must not be empty');
+    });
+
+    it('converts what invoke and job events reject with', () => {
+      const coded = toServiceError({ code: 'permission_denied', message: 'Permission denied (os error 13)' });
+      expect(coded).toBeInstanceOf(ServiceError);
+      expect(coded).toMatchObject({ code: 'permission_denied', message: 'Permission denied (os error 13)' });
+
+      expect(toServiceError('IPC failed')).toMatchObject({ code: 'unknown', message: 'IPC failed' });
+      expect(toServiceError(new Error('boom'))).toMatchObject({ code: 'unknown', message: 'boom' });
+      expect(toServiceError(coded)).toBe(coded);
+    });
   });
 
   describe('Tauri Mode', () => {
@@ -531,5 +1842,167 @@ describe('API Layer', () => {
     it('detects Tauri mode correctly', () => {
       expect('__TAURI_INTERNALS__' in window).toBe(true);
     });
+
+    // isTauri is read when the module loads, so load a fresh copy of the API
+    // with the Tauri globals in place
+    async function tauriApi() {
+      vi.resetModules();
+      const core = await import('@tauri-apps/api/core');
+      const api = await import('./index');
+      const invoke = vi.mocked(core.invoke);
+      invoke.mockReset();
+      return { api, invoke };
+    }
+
+    it('history wrappers invoke the history commands', async () => {
+      const { api, invoke } = await tauriApi();
+      invoke.mockResolvedValue([]);
+
+      await api.getScanHistory(5);
+      expect(invoke).toHaveBeenLastCalledWith('get_scan_history', { limit: 5 });
+      await api.getDuplicateHistory();
+      expect(invoke).toHaveBeenLastCalledWith('get_duplicate_history', undefined);
+      await api.getRecordedDuplicates();
+      expect(invoke).toHaveBeenLastCalledWith('get_duplicate_history', undefined);
+
+      invoke.mockResolvedValue(undefined);
+      await api.deleteHistoryEntry(7);
+      expect(invoke).toHaveBeenLastCalledWith('delete_history_entry', { id: 7 });
+      invoke.mockRejectedValue({ code: 'not_found', message: 'Unknown scan: 7' });
+      await expect(api.getScanDetails(7)).rejects.toMatchObject({ code: 'not_found' });
+      expect(invoke).toHaveBeenLastCalledWith('get_scan_details', { id: 7 });
+    });
+
+    it('setPluginEnabled invokes set_plugin_enabled', async () => {
+      const { api, invoke } = await tauriApi();
+      invoke.mockResolvedValue(undefined);
+
+      await expect(api.setPluginEnabled('WebP Converter', false)).resolves.toBeUndefined();
+      expect(invoke).toHaveBeenCalledWith('set_plugin_enabled', {
+        pluginName: 'WebP Converter',
+        enabled: false,
+      });
+    });
+
+    it('setPluginEnabled passes on the backend rejecting an unknown plugin', async () => {
+      const { api, invoke } = await tauriApi();
+      invoke.mockRejectedValue({ code: 'not_found', message: 'Plugin not found: AVIF Encoder' });
+
+      const err = await api.setPluginEnabled('AVIF Encoder', true).catch((e) => e);
+      expect(err).toBeInstanceOf(api.ServiceError);
+      expect(err).toMatchObject({ code: 'not_found', message: 'Plugin not found: AVIF Encoder' });
+    });
+
+    it('scanDirectories fetches the files a page at a time', async () => {
+      const { api, invoke } = await tauriApi();
+      const event = await import('@tauri-apps/api/event');
+      const files = Array.from({ length: api.SCAN_PAGE_SIZE * 2 + 5 }, (_, i) => ({
+        path: `/big/${String(i).padStart(5, '0')}.bin`,
+        size: 1,
+        modified: 0,
+        file_type: 'Other' as const,
+      }));
+      const summary = {
+        job_id: 5,
+        file_count: files.length,
+        total_size: files.length,
+        paths: [{ path: '/big', file_count: files.length, total_size: files.length }],
+      };
+      let done: ((event: { payload: unknown }) => void) | undefined;
+      vi.mocked(event.listen).mockImplementation(async (name, handler) => {
+        if (name === 'scan://done') done = handler as typeof done;
+        return () => {};
+      });
+      invoke.mockImplementation(async (command, args) => {
+        if (command === 'scan') {
+          done?.({ payload: { job_id: 5, result: summary, error: null } });
+          return 5;
+        }
+        const { offset, limit } = args as { offset: number; limit: number };
+        return { files: files.slice(offset, offset + limit), total: files.length, offset };
+      });
+
+      const [result] = await api.scanDirectories(['/big']);
+      expect(result.files).toEqual(files);
+      const pages = invoke.mock.calls.filter(([command]) => command === 'get_scan_page');
+      expect(pages.map(([, args]) => args)).toEqual(
+        [0, api.SCAN_PAGE_SIZE, api.SCAN_PAGE_SIZE * 2].map(offset => ({
+          jobId: 5,
+          offset,
+          limit: api.SCAN_PAGE_SIZE,
+          sortBy: 'path',
+          order: 'asc',
+          path: '/big',
+        }))
+      );
+    });
+
+    it('app cache wrappers invoke the app cache commands', async () => {
+      const { api, invoke } = await tauriApi();
+      const report: AppCacheReport = { categories: [], total_size: 0 };
+
+      invoke.mockResolvedValue(report);
+      await expect(api.scanAppCaches(['/home/user/code'])).resolves.toBe(report);
+      expect(invoke).toHaveBeenLastCalledWith('scan_app_caches', { roots: ['/home/user/code'] });
+
+      invoke.mockResolvedValue({ actions: [], skipped: [], total_size: 0, reclaimed: 0 });
+      await api.planAppCacheCleaning(report, ['npm']);
+      expect(invoke).toHaveBeenLastCalledWith('plan_app_cache_cleaning', {
+        report,
+        categories: ['npm'],
+        mode: 'trash',
+      });
+
+      invoke.mockResolvedValue([]);
+      await api.cleanAppCaches(report, ['target'], 'permanent');
+      expect(invoke).toHaveBeenLastCalledWith('clean_app_caches', {
+        report,
+        categories: ['target'],
+        mode: 'permanent',
+      });
+    });
+
+    it('scanAppCaches passes on the backend refusing a root', async () => {
+      const { api, invoke } = await tauriApi();
+      invoke.mockRejectedValue({ code: 'permission_denied', message: 'Permission denied (os error 13)' });
+
+      const err = await api.scanAppCaches(['/root']).catch((e) => e);
+      expect(err).toBeInstanceOf(api.ServiceError);
+      expect(err).toMatchObject({ code: 'permission_denied' });
+    });
+
+    it('dev artifact wrappers invoke the dev artifact commands', async () => {
+      const { api, invoke } = await tauriApi();
+      const report: DevArtifactReport = { projects: [], total_size: 0, reclaimable: 0, untouched_months: 12 };
+
+      invoke.mockResolvedValue(report);
+      await expect(api.findDevArtifacts(['/home/user/code'], 12)).resolves.toBe(report);
+      expect(invoke).toHaveBeenLastCalledWith('find_dev_artifacts', {
+        roots: ['/home/user/code'],
+        untouchedMonths: 12,
+      });
+      await api.findDevArtifacts(['/home/user/code']);
+      expect(invoke).toHaveBeenLastCalledWith('find_dev_artifacts', {
+        roots: ['/home/user/code'],
+        untouchedMonths: 6,
+      });
+
+      invoke.mockResolvedValue({ actions: [], skipped: [], total_size: 0, reclaimed: 0 });
+      await api.planDevArtifactRemoval(report);
+      expect(invoke).toHaveBeenLastCalledWith('plan_dev_artifact_removal', { report, mode: 'trash' });
+
+      invoke.mockResolvedValue([]);
+      await api.removeDevArtifacts(report, 'permanent');
+      expect(invoke).toHaveBeenLastCalledWith('remove_dev_artifacts', { report, mode: 'permanent' });
+    });
+
+    it('findDevArtifacts passes on the backend refusing a root', async () => {
+      const { api, invoke } = await tauriApi();
+      invoke.mockRejectedValue({ code: 'not_found', message: 'Folder not found: /home/missing' });
+
+      const err = await api.findDevArtifacts(['/home/missing']).catch((e) => e);
+      expect(err).toBeInstanceOf(api.ServiceError);
+      expect(err).toMatchObject({ code: 'not_found', message: 'Folder not found: /home/missing' });
+    });
   });
 });
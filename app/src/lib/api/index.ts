@@ -3,23 +3,54 @@
  * Automatically detects Tauri or Web mode and routes to appropriate backend
  */
 
-import { invoke } from "@tauri-apps/api/core";
-import type { ScanResult, DuplicateGroup, SimilarGroup, SimilarFile, MediaKind, StorageStats, FileInfo, EmptyScanResult, BrokenFile, BrokenCategory, FixExtensionResult, AppConfig, ScanConfig, HashAlgorithm, ToolStatus } from "../types";
+import { invoke as tauriInvoke, type InvokeArgs } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { ScanResult, ScannedPath, ScanSummary, ScanSort, SortOrder, ScanPage, DuplicateGroup, SimilarGroup, SimilarFile, FileDetails, ExifInfo, VideoInfo, MediaKind, StorageStats, FileInfo, EmptyScanResult, BrokenFile, BrokenCategory, FixExtensionResult, AppConfig, ConfigChanges, ConfigUpdate, ScanConfig, HashAlgorithm, ToolStatus, PluginRequirements, ToolCheck, ArchiveFormat, ArchiveRecord, KeepRule, DuplicateResolution, ResolutionPreview, DedupeAction, DedupeResult, ProgressUpdate, JobInfo, TaskType, QuarantineRecord, RestorePoint, CompressionBackup, BackupPurge, ReflinkResult, StaleFile, StaleDirectory, StaleFilesReport, DirectorySize, StorageHistory, StorageSnapshot, CategoryTotal, CategoryGrowth, ScanRecord, ScanHistoryEntry, ScanFileRecord, ScanDetails, ScanDiff, FileChange, TreemapNode, DuplicateDirectoryGroup, ReferenceRoot, ReferenceMatch, ReferenceCheck, CleanupRule, Schedule, ScheduleEntry, ScheduleStatus, ScheduleRun, UpcomingRun, CleanupFile, CleanupRuleReport, CleanupReport, IgnoreKind, IgnoredGroup, TagTarget, TagRecord, TagCount, DiskInfo, DriveKind, PathCheck, DuplicateRecord, FileRecord, FileQuery, Page, SavingsSummary, PluginSavings, MonthlySavings, InterruptedJob, ShutdownReport, ErrorCode, ErrorInfo, FileError, DuplicateReport, PlannedAction, ActionPlan, CacheCategory, CacheLocation, CacheCategoryReport, AppCacheReport, ArtifactKind, BuildArtifact, DevProject, DevArtifactReport } from "../types";
+import { ServiceError, toServiceError } from "./errors";
 import type { FilterConfig } from "../stores/app";
-import { mockScanResult } from "../../mock/scan";
+import { mockScanResult, mockScanPages } from "../../mock/scan";
 import { mockFindDuplicates } from "../../mock/duplicates";
-import { mockFindSimilarMedia, mockImageThumbnail } from "../../mock/similar";
+import { mockFindSimilarMedia, mockImageThumbnail, mockFileDetails } from "../../mock/similar";
 import { mockEmptyItems } from "../../mock/empty";
 import { mockFindBroken, mockFixExtensions } from "../../mock/broken";
+import { mockFindStale } from "../../mock/stale";
+import { mockFindDuplicateDirs } from "../../mock/duplicateDirs";
+import { mockCacheRefusal, mockScanAppCaches } from "../../mock/appCaches";
+import { mockArtifactRefusal, mockFindDevArtifacts } from "../../mock/devArtifacts";
+import { mockIgnore } from "../../mock/ignore";
+import { mockTags } from "../../mock/tags";
+import { mockExport } from "../../mock/export";
+import { mockLargestFiles, mockLargestDirectories } from "../../mock/largest";
+import { mockStorageHistory, mockDiffScans, mockScanHistory, mockScanDetails, mockDeleteScan, mockDuplicateHistory, mockRecordedFiles } from "../../mock/history";
+import { mockTreemap } from "../../mock/treemap";
 import { mockStorageStats } from "../../mock/stats";
 import { mockPlugins, isKnownPlugin } from "../../mock/plugins";
-import { mockSkipCache } from "../../mock/skipCache";
-import { getMockConfig, setMockConfig, resetMockConfig } from "../../mock/config";
-import { mockDetectTools } from "../../mock/tools";
+import { mockSkipCache, mockCompressionHistory } from "../../mock/skipCache";
+import { mockSavings } from "../../mock/savings";
+import { mockCompressionRuns, mockCompressionProgress, mockCompressionFiles } from "../../mock/compression";
+import { getMockConfig, setMockConfig, resetMockConfig, mergeConfig } from "../../mock/config";
+import { mockDetectTools, mockPluginRequirements } from "../../mock/tools";
+import { mockListDrives, mockValidatePaths } from "../../mock/drives";
+import { mockJobs } from "../../mock/jobs";
+import { mockQuarantine } from "../../mock/quarantine";
+import { mockBackups } from "../../mock/backups";
+import { mockReference } from "../../mock/reference";
+import { mockRunCleanup, validateCleanupRules } from "../../mock/cleanup";
+import { mockScheduleEntries, mockRunSchedule, mockNextRuns, validateSchedules } from "../../mock/schedules";
+import { keepByRule } from "../utils/duplicates";
 
 // Check if running in Tauri environment
 const isTauri = "__TAURI_INTERNALS__" in window;
 
+/** Invoke a backend command, rejecting with a ServiceError */
+async function invoke<T>(cmd: string, args?: InvokeArgs): Promise<T> {
+  try {
+    return await tauriInvoke<T>(cmd, args);
+  } catch (error) {
+    throw toServiceError(error);
+  }
+}
+
 /**
  * Whether a path sits at or beneath one of the excluded paths. Mirrors the
  * backend's ExcludePathsFilter (component-wise prefix match), so excluding
@@ -33,19 +64,121 @@ function isExcludedPath(path: string, excludePaths?: string[]): boolean {
   });
 }
 
-export { type ScanResult, type DuplicateGroup, type SimilarGroup, type SimilarFile, type MediaKind, type StorageStats, type FileInfo, type FilterConfig, type EmptyScanResult, type BrokenFile, type BrokenCategory, type FixExtensionResult, type AppConfig, type ScanConfig, type HashAlgorithm, type ToolStatus };
+/** Directories the backend always protects from destructive operations */
+const MOCK_SYSTEM_PATHS = ["/bin", "/boot", "/dev", "/etc", "/lib", "/proc", "/sbin", "/sys", "/usr", "/System", "/Library"];
+
+/**
+ * The protected directory `path` falls under, if any: a system directory or
+ * one of the config's protected_paths. Mirrors the backend's ProtectedPaths.
+ */
+function protectedRoot(path: string): string | undefined {
+  const roots = [...MOCK_SYSTEM_PATHS, ...(getMockConfig().protected_paths ?? [])];
+  return roots.find((root) => isExcludedPath(path, [root]));
+}
+
+export { type ScanResult, type ScannedPath, type ScanSummary, type ScanSort, type SortOrder, type ScanPage, type DuplicateGroup, type SimilarGroup, type SimilarFile, type FileDetails, type ExifInfo, type VideoInfo, type MediaKind, type StorageStats, type FileInfo, type FilterConfig, type EmptyScanResult, type BrokenFile, type BrokenCategory, type FixExtensionResult, type AppConfig, type ConfigChanges, type ConfigUpdate, type ScanConfig, type HashAlgorithm, type ToolStatus, type PluginRequirements, type ToolCheck, type KeepRule, type DuplicateResolution, type ResolutionPreview, type DedupeAction, type DedupeResult, type ProgressUpdate, type JobInfo, type TaskType, type QuarantineRecord, type RestorePoint, type CompressionBackup, type BackupPurge, type ReflinkResult, type StaleFile, type StaleDirectory, type StaleFilesReport, type DirectorySize, type StorageHistory, type StorageSnapshot, type CategoryTotal, type CategoryGrowth, type ScanRecord, type ScanHistoryEntry, type ScanFileRecord, type ScanDetails, type ScanDiff, type FileChange, type TreemapNode, type DuplicateDirectoryGroup, type ReferenceRoot, type ReferenceMatch, type ReferenceCheck, type CleanupRule, type Schedule, type ScheduleEntry, type ScheduleStatus, type ScheduleRun, type UpcomingRun, type CleanupFile, type CleanupRuleReport, type CleanupReport, type IgnoreKind, type IgnoredGroup, type TagTarget, type TagRecord, type TagCount, type DiskInfo, type DriveKind, type PathCheck, type DuplicateRecord, type FileRecord, type FileQuery, type Page, type SavingsSummary, type PluginSavings, type MonthlySavings, type InterruptedJob, type ShutdownReport, type ErrorCode, type ErrorInfo, type FileError, type DuplicateReport, type PlannedAction, type ActionPlan, type CacheCategory, type CacheLocation, type CacheCategoryReport, type AppCacheReport, type ArtifactKind, type BuildArtifact, type DevProject, type DevArtifactReport };
+export { ServiceError, toServiceError };
+
+/** Background job kinds; each emits `<kind>://progress` and `<kind>://done` */
+type JobKind = "scan" | "duplicates" | "similar" | "compress";
+
+interface JobDone<T> {
+  job_id: number;
+  result?: T | null;
+  error?: ErrorInfo | null;
+}
+
+/**
+ * Start a backend job and resolve with its result. `start` invokes the
+ * command, which returns a job id at once; the result arrives as the
+ * `<kind>://done` event and progress as `<kind>://progress` events. The
+ * listeners are attached before the command runs so a fast job cannot
+ * finish unseen. Rejects with the job's error as a ServiceError.
+ */
+async function runJob<T>(
+  kind: JobKind,
+  start: () => Promise<number>,
+  onProgress?: (update: ProgressUpdate) => void
+): Promise<T> {
+  let jobId: number | null = null;
+  // Events that arrived before the job id was known
+  const early: JobDone<T>[] = [];
+  const earlyProgress: { job_id: number; update: ProgressUpdate }[] = [];
+  let finish = (done: JobDone<T>) => early.push(done);
+
+  const unlistenDone = await listen<JobDone<T>>(`${kind}://done`, (event) => {
+    if (jobId === null) early.push(event.payload);
+    else if (event.payload.job_id === jobId) finish(event.payload);
+  });
+  const unlistenProgress = await listen<{ job_id: number; update: ProgressUpdate }>(
+    `${kind}://progress`,
+    (event) => {
+      if (jobId === null) earlyProgress.push(event.payload);
+      else if (event.payload.job_id === jobId) onProgress?.(event.payload.update);
+    }
+  );
+  try {
+    const id = await start();
+    jobId = id;
+    earlyProgress.filter((p) => p.job_id === id).forEach((p) => onProgress?.(p.update));
+    const done =
+      early.find((d) => d.job_id === id) ??
+      (await new Promise<JobDone<T>>((resolve) => {
+        finish = resolve;
+      }));
+    if (done.error != null) throw toServiceError(done.error);
+    return done.result as T;
+  } finally {
+    unlistenDone();
+    unlistenProgress();
+  }
+}
+
+/**
+ * Web-mode stand-in for a job's progress events: `Started`, one `Progress`
+ * per item, then `Completed`. The job is recorded in the mock job registry.
+ */
+function mockJobProgress(
+  kind: JobKind,
+  taskType: TaskType,
+  items: string[],
+  onProgress?: (update: ProgressUpdate) => void
+): number {
+  const id = mockJobs.register(taskType);
+  const emit = (update: ProgressUpdate) => {
+    mockJobs.update(id, update);
+    onProgress?.(update);
+  };
+  emit({ Started: { task_type: kind, total_items: items.length } });
+  items.forEach((item, i) =>
+    emit({ Progress: { current: i + 1, total: items.length, message: `Scanned ${item}` } })
+  );
+  emit({ Completed: { message: `${kind} finished` } });
+  return id;
+}
 
 /**
- * Scan multiple directories for files
+ * Scan multiple directories, resolving with the totals only: the files stay
+ * in the backend, to be fetched with `getScanPage` as the list scrolls.
+ * Runs as a background job; `onProgress` receives its progress updates.
  */
-export async function scanDirectories(paths: string[], filter?: FilterConfig): Promise<ScanResult[]> {
+export async function startScan(
+  paths: string[],
+  filter?: FilterConfig,
+  onProgress?: (update: ProgressUpdate) => void
+): Promise<ScanSummary> {
   if (isTauri) {
-    return await invoke<ScanResult[]>("scan", { paths, filter: filter || null });
+    return await runJob<ScanSummary>(
+      "scan",
+      () => invoke<number>("scan", { paths, filter: filter || null }),
+      onProgress
+    );
   } else {
     const results = await Promise.all(paths.map(path => mockScanResult(path)));
+    const jobId = mockJobProgress("scan", { Scan: paths }, paths, onProgress);
     // Mirror the backend's exclude-paths filter so Web mode can demo it: drop
     // excluded files and recompute the per-directory totals.
-    return results.map(result => {
+    const filtered = results.map(result => {
       const files = result.files.filter(f => !isExcludedPath(f.path, filter?.excludePaths));
       return {
         ...result,
@@ -54,6 +187,76 @@ export async function scanDirectories(paths: string[], filter?: FilterConfig): P
         total_size: files.reduce((sum, f) => sum + f.size, 0),
       };
     });
+    return mockScanPages.store(jobId, filtered);
+  }
+}
+
+/**
+ * Up to `limit` files of the scan `jobId`, from `offset` on, ordered by
+ * `sortBy` (size unless given) and `order` (descending unless given).
+ * `path` narrows the page to one scanned path. Rejects with NotFound once
+ * the backend has dropped the scan for newer ones.
+ */
+export async function getScanPage(
+  jobId: number,
+  offset: number,
+  limit: number,
+  sortBy?: ScanSort,
+  order?: SortOrder,
+  path?: string
+): Promise<ScanPage> {
+  if (isTauri) {
+    return await invoke<ScanPage>("get_scan_page", {
+      jobId,
+      offset,
+      limit,
+      sortBy: sortBy ?? null,
+      order: order ?? null,
+      path: path ?? null,
+    });
+  } else {
+    return mockScanPages.page(jobId, offset, limit, sortBy ?? "size", order ?? "desc", path);
+  }
+}
+
+/** Files fetched per getScanPage call when a whole scan is wanted */
+export const SCAN_PAGE_SIZE = 1000;
+
+/**
+ * Scan multiple directories for files, fetching every file a page of
+ * SCAN_PAGE_SIZE at a time. Fine for small folders; large ones should page
+ * with `startScan` and `getScanPage` as the list scrolls.
+ */
+export async function scanDirectories(
+  paths: string[],
+  filter?: FilterConfig,
+  onProgress?: (update: ProgressUpdate) => void
+): Promise<ScanResult[]> {
+  const summary = await startScan(paths, filter, onProgress);
+  const results: ScanResult[] = [];
+  for (const scanned of summary.paths) {
+    const files: FileInfo[] = [];
+    while (files.length < scanned.file_count) {
+      const page = await getScanPage(summary.job_id, files.length, SCAN_PAGE_SIZE, "path", "asc", scanned.path);
+      if (page.files.length === 0) break;
+      files.push(...page.files);
+    }
+    results.push({ ...scanned, files });
+  }
+  return results;
+}
+
+/**
+ * Make the next operations on `paths` (every folder when omitted) walk the
+ * disk again instead of reusing an earlier scan, e.g. after files changed
+ * outside the app. Resolves to the number of cached walks dropped.
+ */
+export async function invalidateScanCache(paths?: string[]): Promise<number> {
+  if (isTauri) {
+    return await invoke<number>("invalidate_scan_cache", { paths: paths ?? null });
+  } else {
+    // The mock backend walks nothing, so it has nothing cached
+    return 0;
   }
 }
 
@@ -66,16 +269,28 @@ export async function scanDirectory(path: string, filter?: FilterConfig): Promis
 }
 
 /**
- * Find duplicate files across multiple directories
+ * Find duplicate files across multiple directories, also listing the
+ * candidates that could not be read. Runs as a background job in Tauri mode;
+ * `onProgress` receives its progress updates. Web mode reports one
+ * unreadable file under each path containing "locked".
  */
-export async function findDuplicates(paths: string[], filter?: FilterConfig): Promise<DuplicateGroup[]> {
+export async function findDuplicateReport(
+  paths: string[],
+  filter?: FilterConfig,
+  onProgress?: (update: ProgressUpdate) => void
+): Promise<DuplicateReport> {
   if (isTauri) {
-    return await invoke<DuplicateGroup[]>("duplicate_file_check", { paths, filter: filter || null });
+    return await runJob<DuplicateReport>(
+      "duplicates",
+      () => invoke<number>("duplicate_file_check", { paths, filter: filter || null }),
+      onProgress
+    );
   } else {
     const results = await Promise.all(paths.map(path => mockFindDuplicates(path)));
+    mockJobProgress("duplicates", { FindDuplicates: paths }, paths, onProgress);
     // Drop excluded files; a group needs >1 file to remain a duplicate group,
     // matching the backend (totals/wasted space recomputed from what's left).
-    return results.flat().flatMap(group => {
+    const groups = results.flat().flatMap(group => {
       const files = group.files.filter(f => !isExcludedPath(f.path, filter?.excludePaths));
       if (files.length < 2) return [];
       const total_size = files.reduce((sum, f) => sum + f.size, 0);
@@ -86,38 +301,229 @@ export async function findDuplicates(paths: string[], filter?: FilterConfig): Pr
         total_size,
         wasted_space: total_size - files[0].size,
       }];
+    }).filter(group => !mockIgnore.ignoresDuplicate(group));
+    const unreadable: FileError[] = paths
+      .filter(path => path.includes("locked"))
+      .map(path => ({
+        path: `${path}/image1.jpg`,
+        error: "Permission denied (os error 13)",
+        code: "permission_denied",
+      }));
+    return { groups, unreadable };
+  }
+}
+
+/**
+ * Find duplicate files across paths; findDuplicateReport also tells which
+ * files could not be read
+ */
+export async function findDuplicates(
+  paths: string[],
+  filter?: FilterConfig,
+  onProgress?: (update: ProgressUpdate) => void
+): Promise<DuplicateGroup[]> {
+  return (await findDuplicateReport(paths, filter, onProgress)).groups;
+}
+
+/**
+ * Split duplicate groups into copies to keep and copies to delete under
+ * `rule`. Nothing is deleted; pass the delete lists to deleteFiles.
+ */
+export async function resolveDuplicates(
+  groups: DuplicateGroup[],
+  rule: KeepRule
+): Promise<DuplicateResolution[]> {
+  if (isTauri) {
+    return await invoke<DuplicateResolution[]>("resolve_duplicates", { groups, rule });
+  } else {
+    return groups.map((group) => {
+      const keepPaths = new Set(keepByRule(group, rule).map((f) => f.path));
+      // Keeping nothing leaves the group untouched, like the backend
+      const untouched = keepPaths.size === 0;
+      // Protected copies are always kept
+      const isProtected = (f: FileInfo) => protectedRoot(f.path) !== undefined;
+      const keep = group.files.filter((f) => untouched || keepPaths.has(f.path) || isProtected(f));
+      const del = group.files.filter((f) => !keep.includes(f));
+      const guarded = group.files.filter((f) => !untouched && !keepPaths.has(f.path) && isProtected(f));
+      return {
+        hash: group.hash,
+        keep,
+        delete: del,
+        reclaimable: del.reduce((sum, f) => sum + f.size, 0),
+        protected: guarded,
+      };
+    });
+  }
+}
+
+/**
+ * Mock file systems without copy-on-write clones: paths on the "usb-drive"
+ * volume (FAT), like the trash demo in deleteFiles
+ */
+function mockSupportsReflink(path: string): boolean {
+  return !path.includes("usb-drive");
+}
+
+/**
+ * Deduplicate with copy-on-write clones instead of deleting (Btrfs, XFS,
+ * APFS): each copy in a resolution's `delete` list is replaced by a clone
+ * of its first `keep` copy, so every path stays a separate file but the data
+ * is stored once. Files where cloning is unsupported are reported with
+ * `unsupported` set and left as they were.
+ */
+/**
+ * Preview resolving duplicate groups with a keep rule ("keep newest", ...):
+ * which copies stay, which go and the space that frees. Nothing changes.
+ */
+export async function previewDuplicateResolution(
+  groups: DuplicateGroup[],
+  strategy: KeepRule
+): Promise<ResolutionPreview> {
+  if (isTauri) {
+    return await invoke<ResolutionPreview>("preview_duplicate_resolution", { groups, strategy });
+  } else {
+    const plan = await resolveDuplicates(groups, strategy);
+    const count = (files: (r: DuplicateResolution) => FileInfo[]) =>
+      plan.reduce((sum, r) => sum + files(r).length, 0);
+    return {
+      plan,
+      keep_count: count(r => r.keep),
+      remove_count: count(r => r.delete),
+      reclaimable: plan.reduce((sum, r) => sum + r.reclaimable, 0),
+      protected_count: count(r => r.protected),
+    };
+  }
+}
+
+/**
+ * Carry out a plan from previewDuplicateResolution: every copy it removes
+ * goes to the trash, is deleted, or becomes a hard link to the kept copy.
+ * Copies changed since the scan, or whose kept copy did, are left alone and
+ * reported failed, as are protected ones.
+ */
+export async function applyDuplicateResolution(
+  plan: DuplicateResolution[],
+  action: DedupeAction
+): Promise<DedupeResult[]> {
+  if (isTauri) {
+    return await invoke<DedupeResult[]>("apply_duplicate_resolution", { plan, action });
+  } else {
+    // Mock dedupe, with the failure modes of deleteFiles and hard links:
+    // - "missing" copies, or kept copies, are gone since the scan
+    // - "locked" copies cannot be removed or replaced (permission denied)
+    // - "usb-drive" copies fail in trash mode (no trash directory on that
+    //   volume) and as hard links (another file system), but can be deleted
+    const failure = (kept: FileInfo | undefined, file: FileInfo): string | null => {
+      if (!kept) return `No copy of ${file.path} is kept`;
+      const gone = [kept, file].find((f) => f.path.includes("missing"));
+      if (gone) return `File not found: ${gone.path}`;
+      const root = protectedRoot(file.path);
+      if (root) return `Protected path: ${file.path} is inside ${root}`;
+      const linkFailed = `Failed to link ${file.path} to ${kept.path}`;
+      if (file.path.includes("locked")) {
+        return action === "hardlink"
+          ? `${linkFailed}: Permission denied (os error 13)`
+          : "IO error: Permission denied (os error 13)";
+      }
+      if (file.path.includes("usb-drive") && action === "trash") {
+        return "File operation error: Cannot move to trash: the volume has no trash directory. Retry with permanent deletion.";
+      }
+      if (file.path.includes("usb-drive") && action === "hardlink") {
+        return `${linkFailed}: Invalid cross-device link (os error 18)`;
+      }
+      return null;
+    };
+    const results = plan.flatMap(({ keep, delete: del }) =>
+      del.map((file) => {
+        const error = failure(keep[0], file);
+        return {
+          path: file.path,
+          success: error === null,
+          error,
+          protected: error?.startsWith("Protected path") ?? false,
+          reclaimed: error === null ? file.size : 0,
+        };
+      })
+    );
+    return new Promise((resolve) => setTimeout(() => resolve(results), 300));
+  }
+}
+
+export async function reflinkDuplicates(resolutions: DuplicateResolution[]): Promise<ReflinkResult[]> {
+  if (isTauri) {
+    return await invoke<ReflinkResult[]>("reflink_duplicates", { resolutions });
+  } else {
+    return resolutions.flatMap(({ keep, delete: del }) => {
+      const source = keep[0];
+      if (!source) return [];
+      return del.map((file) => {
+        const base = { path: file.path, source: source.path };
+        const root = protectedRoot(file.path);
+        if (root) {
+          return { ...base, success: false, error: `Protected path: ${file.path} is inside ${root}`, protected: true, reclaimed: 0 };
+        }
+        if (!mockSupportsReflink(file.path)) {
+          return {
+            ...base,
+            success: false,
+            error: "Copy-on-write clones are not supported here (Operation not supported (os error 95))",
+            unsupported: true,
+            reclaimed: 0,
+          };
+        }
+        return { ...base, success: true, reclaimed: file.size };
+      });
     });
   }
 }
 
+/**
+ * Whether the file system holding `path` supports copy-on-write clones
+ */
+export async function checkReflinkSupport(path: string): Promise<boolean> {
+  if (isTauri) {
+    return await invoke<boolean>("check_reflink_support", { path });
+  } else {
+    return mockSupportsReflink(path);
+  }
+}
+
 /**
  * Find similar media across multiple directories. `mediaTypes` selects which
  * kinds to scan ("Image"/"Video"); an empty list defaults to images on the
  * backend. Video similarity is not implemented yet (the backend returns no
- * video groups), so the UI keeps the Videos option disabled.
+ * video groups), so the UI keeps the Videos option disabled. Runs as a
+ * background job in Tauri mode; `onProgress` receives its progress updates.
  */
 export async function findSimilarMedia(
   paths: string[],
   threshold: number = 0.9,
   mediaTypes: MediaKind[] = ["Image"],
-  filter?: FilterConfig
+  filter?: FilterConfig,
+  onProgress?: (update: ProgressUpdate) => void
 ): Promise<SimilarGroup[]> {
   if (isTauri) {
-    return await invoke<SimilarGroup[]>("find_similar_media", {
-      paths,
-      threshold,
-      mediaTypes,
-      filter: filter || null,
-    });
+    return await runJob<SimilarGroup[]>(
+      "similar",
+      () =>
+        invoke<number>("find_similar_media", {
+          paths,
+          threshold,
+          mediaTypes,
+          filter: filter || null,
+        }),
+      onProgress
+    );
   } else {
     const results = await Promise.all(
       paths.map(path => mockFindSimilarMedia(path, threshold, mediaTypes))
     );
+    mockJobProgress("similar", { FindSimilarImages: [paths, threshold] }, paths, onProgress);
     // Drop excluded files; a similar group needs >1 file to remain meaningful
     return results.flat().flatMap(group => {
       const files = group.files.filter(f => !isExcludedPath(f.path, filter?.excludePaths));
       return files.length < 2 ? [] : [{ ...group, files }];
-    });
+    }).filter(group => !mockIgnore.ignoresSimilar(group));
   }
 }
 
@@ -133,35 +539,442 @@ export async function getImageThumbnail(path: string, maxSize: number = 160): Pr
   }
 }
 
+/**
+ * Size, timestamps, image dimensions, EXIF (camera, date taken, GPS) and
+ * video metadata of a file, for showing next to its thumbnail. Video details
+ * need ffprobe; without it `video` is null.
+ */
+export async function getFileDetails(path: string): Promise<FileDetails> {
+  if (isTauri) {
+    return await invoke<FileDetails>("get_file_details", { path });
+  } else {
+    return await mockFileDetails(path);
+  }
+}
+
+/**
+ * The backend's check before revealing or opening `path`: "missing" files
+ * are gone, and "locked" ones stand for files outside the scanned folders
+ */
+function mockCheckScannedPath(path: string): void {
+  if (path.includes("missing")) {
+    throw new ServiceError("not_found", `${path} no longer exists`);
+  }
+  if (path.includes("locked")) {
+    throw new ServiceError("permission_denied", `${path} is not in a scanned folder`);
+  }
+}
+
+/**
+ * Show a file selected in the system file manager. Only files under a
+ * scanned folder; others reject with a `permission_denied` error. Does
+ * nothing else in web mode.
+ */
+export async function revealInExplorer(path: string): Promise<void> {
+  if (isTauri) {
+    await invoke("reveal_in_explorer", { path });
+  } else {
+    mockCheckScannedPath(path);
+  }
+}
+
+/**
+ * Open a file with the app the system associates with it. Only files under
+ * a scanned folder; others reject with a `permission_denied` error. Does
+ * nothing else in web mode.
+ */
+export async function openFile(path: string): Promise<void> {
+  if (isTauri) {
+    await invoke("open_file", { path });
+  } else {
+    mockCheckScannedPath(path);
+  }
+}
+
 /**
  * Find empty files (0 bytes) and empty folders (no files anywhere beneath
  * them, topmost-only) across multiple directories. The filter applies to
  * files only.
  */
-export async function findEmptyItems(paths: string[], filter?: FilterConfig): Promise<EmptyScanResult> {
+export async function findEmptyItems(paths: string[], filter?: FilterConfig): Promise<EmptyScanResult> {
+  if (isTauri) {
+    return await invoke<EmptyScanResult>("empty_folder_check", { paths, filter: filter || null });
+  } else {
+    const results = await Promise.all(paths.map(path => mockEmptyItems(path)));
+    // Excluded paths drop both empty files and empty folders beneath them
+    return {
+      empty_files: results.flatMap(r => r.empty_files).filter(p => !isExcludedPath(p, filter?.excludePaths)),
+      empty_folders: results.flatMap(r => r.empty_folders).filter(p => !isExcludedPath(p, filter?.excludePaths)),
+    };
+  }
+}
+
+/**
+ * Find broken (invalid or corrupted) files across multiple directories.
+ * Reports only files that are provably unusable — corrupted/truncated content
+ * or content that does not match its extension. Empty files are excluded.
+ */
+export async function findBrokenFiles(paths: string[], filter?: FilterConfig): Promise<BrokenFile[]> {
+  if (isTauri) {
+    return await invoke<BrokenFile[]>("broken_file_check", { paths, filter: filter || null });
+  } else {
+    const results = await Promise.all(paths.map(path => mockFindBroken(path)));
+    return results.flat().filter(b => !isExcludedPath(b.path, filter?.excludePaths));
+  }
+}
+
+/**
+ * Find files neither modified nor accessed for `olderThanDays` across
+ * multiple directories, grouped by directory (largest first) with the total
+ * reclaimable size — candidates for archiving.
+ */
+export async function findStaleFiles(paths: string[], olderThanDays: number, filter?: FilterConfig): Promise<StaleFilesReport> {
+  if (isTauri) {
+    return await invoke<StaleFilesReport>("find_stale_files", { paths, olderThanDays, filter: filter || null });
+  } else {
+    const results = await Promise.all(paths.map(path => mockFindStale(path, olderThanDays)));
+    const directories = results
+      .flat()
+      .filter(d => !isExcludedPath(d.path, filter?.excludePaths))
+      .sort((a, b) => b.total_size - a.total_size);
+    return {
+      cutoff: Math.floor(Date.now() / 1000) - olderThanDays * 86_400,
+      file_count: directories.reduce((sum, d) => sum + d.file_count, 0),
+      total_size: directories.reduce((sum, d) => sum + d.total_size, 0),
+      directories,
+    };
+  }
+}
+
+/**
+ * Find folders whose whole trees are identical across multiple directories:
+ * one group per duplicated tree (a photo export copied twice is one group,
+ * not one per photo), most wasted space first.
+ */
+export async function findDuplicateDirectories(paths: string[]): Promise<DuplicateDirectoryGroup[]> {
+  if (isTauri) {
+    return await invoke<DuplicateDirectoryGroup[]>("find_duplicate_directories", { paths });
+  } else {
+    const results = await Promise.all(paths.map(path => mockFindDuplicateDirs(path)));
+    return results.flat().sort((a, b) => b.wasted_space - a.wasted_space);
+  }
+}
+
+/**
+ * Delete one copy of a duplicated folder. Both trees are compared again
+ * first, so a copy changed since the scan (or protected) is left alone.
+ */
+export async function deleteDuplicateDirectory(
+  target: string,
+  keep: string,
+  mode: Exclude<DeleteMode, "quarantine"> = "trash"
+): Promise<DeleteResult> {
+  if (isTauri) {
+    return await invoke<DeleteResult>("delete_duplicate_directory", { target, keep, mode });
+  } else {
+    const root = protectedRoot(target);
+    if (root) {
+      return { path: target, success: false, error: `Protected path: ${target} is inside ${root}`, protected: true };
+    }
+    if (target === keep || target.startsWith(`${keep}/`) || keep.startsWith(`${target}/`)) {
+      return { path: target, success: false, error: `${target} and ${keep} overlap` };
+    }
+    // "locked" copies cannot be removed, and "usb-drive" copies fail in trash
+    // mode only, as in deleteFiles
+    let result: DeleteResult = { path: target, success: true };
+    if (target.includes("locked")) {
+      result = {
+        path: target,
+        success: false,
+        error: "IO error: Permission denied (os error 13)",
+        error_code: "permission_denied",
+      };
+    } else if (target.includes("usb-drive") && mode === "trash") {
+      result = {
+        path: target,
+        success: false,
+        error:
+          "File operation error: Cannot move to trash: the volume has no trash directory. Retry with permanent deletion.",
+        error_code: "file_operation",
+      };
+    }
+    return new Promise((resolve) => setTimeout(() => resolve(result), 300));
+  }
+}
+
+/**
+ * Find application caches and build output that are rebuilt when needed:
+ * browser, package manager and thumbnail caches, and the node_modules and
+ * target folders beneath `roots`, by category
+ */
+export async function scanAppCaches(roots: string[]): Promise<AppCacheReport> {
+  if (isTauri) {
+    return await invoke<AppCacheReport>("scan_app_caches", { roots });
+  } else {
+    return mockScanAppCaches(roots);
+  }
+}
+
+/**
+ * What cleanAppCaches would do with the `categories` of `report`, touching
+ * nothing
+ */
+export async function planAppCacheCleaning(
+  report: AppCacheReport,
+  categories: CacheCategory[],
+  mode: Exclude<DeleteMode, "quarantine"> = "trash"
+): Promise<ActionPlan> {
+  if (isTauri) {
+    return await invoke<ActionPlan>("plan_app_cache_cleaning", { report, categories, mode });
+  } else {
+    const action = mode === "permanent" ? "delete" : mode;
+    const actions: PlannedAction[] = [];
+    const skipped: FileError[] = [];
+    for (const c of report.categories.filter((c) => categories.includes(c.category))) {
+      for (const l of c.locations) {
+        const refusal = mockCacheRefusal(l.path);
+        if (refusal) {
+          skipped.push(refusal);
+        } else {
+          actions.push({ path: l.path, action, size: l.size, reclaimed: l.size, detail: c.label });
+        }
+      }
+    }
+    const total = actions.reduce((sum, a) => sum + a.size, 0);
+    return { actions, skipped, total_size: total, reclaimed: total };
+  }
+}
+
+/**
+ * Remove the `categories` of a scanAppCaches report, each folder checked
+ * again first: one that moved, is no longer next to its project manifest or
+ * is protected is left alone
+ */
+export async function cleanAppCaches(
+  report: AppCacheReport,
+  categories: CacheCategory[],
+  mode: Exclude<DeleteMode, "quarantine"> = "trash"
+): Promise<DeleteResult[]> {
+  if (isTauri) {
+    return await invoke<DeleteResult[]>("clean_app_caches", { report, categories, mode });
+  } else {
+    return report.categories
+      .filter((c) => categories.includes(c.category))
+      .flatMap((c) => c.locations)
+      .map((l) => {
+        const refusal = mockCacheRefusal(l.path);
+        return refusal
+          ? { path: l.path, success: false, error: refusal.error, error_code: refusal.code }
+          : { path: l.path, success: true };
+      });
+  }
+}
+
+/**
+ * Find project folders (Cargo.toml, package.json, pyproject.toml, .git)
+ * beneath `roots` with their build output and when it was last built;
+ * projects untouched for `untouchedMonths` are marked `suggested`
+ */
+export async function findDevArtifacts(roots: string[], untouchedMonths = 6): Promise<DevArtifactReport> {
+  if (isTauri) {
+    return await invoke<DevArtifactReport>("find_dev_artifacts", { roots, untouchedMonths });
+  } else {
+    return mockFindDevArtifacts(roots, untouchedMonths);
+  }
+}
+
+/**
+ * What removeDevArtifacts would do with `report`, touching nothing
+ */
+export async function planDevArtifactRemoval(
+  report: DevArtifactReport,
+  mode: Exclude<DeleteMode, "quarantine"> = "trash"
+): Promise<ActionPlan> {
+  if (isTauri) {
+    return await invoke<ActionPlan>("plan_dev_artifact_removal", { report, mode });
+  } else {
+    const action = mode === "permanent" ? "delete" : mode;
+    const actions: PlannedAction[] = [];
+    const skipped: FileError[] = [];
+    for (const a of report.projects.filter((p) => p.suggested).flatMap((p) => p.artifacts)) {
+      const refusal = mockArtifactRefusal(a.path);
+      if (refusal) {
+        skipped.push(refusal);
+      } else {
+        actions.push({ path: a.path, action, size: a.size, reclaimed: a.size, detail: null });
+      }
+    }
+    const total = actions.reduce((sum, a) => sum + a.size, 0);
+    return { actions, skipped, total_size: total, reclaimed: total };
+  }
+}
+
+/**
+ * Remove the build output of the projects a findDevArtifacts report
+ * suggests, each folder checked again first
+ */
+export async function removeDevArtifacts(
+  report: DevArtifactReport,
+  mode: Exclude<DeleteMode, "quarantine"> = "trash"
+): Promise<DeleteResult[]> {
+  if (isTauri) {
+    return await invoke<DeleteResult[]>("remove_dev_artifacts", { report, mode });
+  } else {
+    return report.projects
+      .filter((p) => p.suggested)
+      .flatMap((p) => p.artifacts)
+      .map((a) => {
+        const refusal = mockArtifactRefusal(a.path);
+        return refusal
+          ? { path: a.path, success: false, error: refusal.error, error_code: refusal.code }
+          : { path: a.path, success: true };
+      });
+  }
+}
+
+/**
+ * Index a reference volume (e.g. a NAS): hash every file and store the
+ * hashes, replacing its previous index. Rejects when the path is not a
+ * directory (an unmounted volume), keeping the old index.
+ */
+export async function indexReference(path: string): Promise<ReferenceRoot> {
+  if (isTauri) {
+    return await invoke<ReferenceRoot>("index_reference", { path });
+  } else {
+    return mockReference.index(path);
+  }
+}
+
+/**
+ * Check files across multiple directories against every indexed reference
+ * volume. Matched files have an identical copy there and are safe to delete
+ * with deleteFiles.
+ */
+export async function checkAgainstReference(paths: string[], filter?: FilterConfig): Promise<ReferenceCheck> {
+  if (isTauri) {
+    return await invoke<ReferenceCheck>("check_against_reference", { paths, filter: filter || null });
+  } else {
+    const check = await mockReference.check(paths);
+    const matched = check.matched.filter(m => !isExcludedPath(m.path, filter?.excludePaths));
+    return { ...check, matched, matched_size: matched.reduce((sum, m) => sum + m.size, 0) };
+  }
+}
+
+/**
+ * Indexed reference volumes, by path
+ */
+export async function listReferences(): Promise<ReferenceRoot[]> {
+  if (isTauri) {
+    return await invoke<ReferenceRoot[]>("list_references");
+  } else {
+    return mockReference.list();
+  }
+}
+
+/**
+ * Forget the index of a reference volume, resolving to how many files it held
+ */
+export async function removeReference(path: string): Promise<number> {
+  if (isTauri) {
+    return await invoke<number>("remove_reference", { path });
+  } else {
+    return mockReference.remove(path);
+  }
+}
+
+/**
+ * Stop showing a duplicate or similar group the user keeps on purpose.
+ * Duplicate groups need their `hash`. Later scans leave the group out until
+ * it gains a file or its content changes.
+ */
+export async function ignoreGroup(
+  kind: IgnoreKind,
+  paths: string[],
+  hash?: string
+): Promise<IgnoredGroup> {
+  if (isTauri) {
+    return await invoke<IgnoredGroup>("ignore_group", { kind, paths, hash: hash ?? null });
+  } else {
+    return mockIgnore.add(kind, paths, hash);
+  }
+}
+
+/**
+ * Show an ignored group again, resolving to false when `id` is unknown
+ */
+export async function unignoreGroup(id: number): Promise<boolean> {
+  if (isTauri) {
+    return await invoke<boolean>("unignore_group", { id });
+  } else {
+    return mockIgnore.remove(id);
+  }
+}
+
+/**
+ * Ignored duplicate and similar groups, newest first
+ */
+export async function listIgnoredGroups(): Promise<IgnoredGroup[]> {
+  if (isTauri) {
+    return await invoke<IgnoredGroup[]>("list_ignored_groups");
+  } else {
+    return mockIgnore.list();
+  }
+}
+
+/**
+ * Tag a file or duplicate group ("keep", "review later", ...), resolving to
+ * false when it already had the tag. Tags are stored lowercase; tagged files
+ * can be picked out of scans with a `tag:keep` filter expression.
+ */
+export async function addTag(target: TagTarget, tag: string): Promise<boolean> {
+  if (isTauri) {
+    return await invoke<boolean>("add_tag", { target, tag });
+  } else {
+    return mockTags.add(target, tag);
+  }
+}
+
+/**
+ * Take a tag off, resolving to false when the target did not have it
+ */
+export async function removeTag(target: TagTarget, tag: string): Promise<boolean> {
+  if (isTauri) {
+    return await invoke<boolean>("remove_tag", { target, tag });
+  } else {
+    return mockTags.remove(target, tag);
+  }
+}
+
+/**
+ * Tags of a file or duplicate group, alphabetically
+ */
+export async function getTags(target: TagTarget): Promise<string[]> {
+  if (isTauri) {
+    return await invoke<string[]>("get_tags", { target });
+  } else {
+    return mockTags.of(target);
+  }
+}
+
+/**
+ * Files and duplicate groups carrying `tag`, newest first
+ */
+export async function findTagged(tag: string): Promise<TagRecord[]> {
   if (isTauri) {
-    return await invoke<EmptyScanResult>("empty_folder_check", { paths, filter: filter || null });
+    return await invoke<TagRecord[]>("find_tagged", { tag });
   } else {
-    const results = await Promise.all(paths.map(path => mockEmptyItems(path)));
-    // Excluded paths drop both empty files and empty folders beneath them
-    return {
-      empty_files: results.flatMap(r => r.empty_files).filter(p => !isExcludedPath(p, filter?.excludePaths)),
-      empty_folders: results.flatMap(r => r.empty_folders).filter(p => !isExcludedPath(p, filter?.excludePaths)),
-    };
+    return mockTags.tagged(tag);
   }
 }
 
 /**
- * Find broken (invalid or corrupted) files across multiple directories.
- * Reports only files that are provably unusable — corrupted/truncated content
- * or content that does not match its extension. Empty files are excluded.
+ * Tags in use, alphabetically, with how many files and groups carry each
  */
-export async function findBrokenFiles(paths: string[], filter?: FilterConfig): Promise<BrokenFile[]> {
+export async function listTags(): Promise<TagCount[]> {
   if (isTauri) {
-    return await invoke<BrokenFile[]>("broken_file_check", { paths, filter: filter || null });
+    return await invoke<TagCount[]>("list_tags");
   } else {
-    const results = await Promise.all(paths.map(path => mockFindBroken(path)));
-    return results.flat().filter(b => !isExcludedPath(b.path, filter?.excludePaths));
+    return mockTags.counts();
   }
 }
 
@@ -182,7 +995,7 @@ export async function fixFileExtensions(paths: string[]): Promise<FixExtensionRe
  * How files are removed: "trash" (system recycle bin, recoverable) or
  * "permanent" (unrecoverable). Defaults to trash.
  */
-export type DeleteMode = "trash" | "permanent";
+export type DeleteMode = "trash" | "quarantine" | "permanent";
 
 /**
  * Per-file outcome of a delete operation
@@ -191,15 +1004,23 @@ export interface DeleteResult {
   path: string;
   success: boolean;
   error?: string | null;
+  /** The path is protected and was left alone */
+  protected?: boolean;
+  /** What kind of failure `error` is */
+  error_code?: ErrorCode | null;
 }
 
 /**
- * Delete files, reporting a per-file outcome
+ * Delete files, reporting a per-file outcome. "quarantine" mode goes
+ * through quarantineFiles.
  */
 export async function deleteFiles(
   paths: string[],
   mode: DeleteMode = "trash"
 ): Promise<DeleteResult[]> {
+  if (mode === "quarantine") {
+    return await quarantineFiles(paths);
+  }
   if (isTauri) {
     return await invoke<DeleteResult[]>("delete_files", { paths, mode });
   } else {
@@ -212,15 +1033,31 @@ export async function deleteFiles(
         () =>
           resolve(
             paths.map((path) => {
+              const root = protectedRoot(path);
+              if (root) {
+                return {
+                  path,
+                  success: false,
+                  error: `Protected path: ${path} is inside ${root}`,
+                  protected: true,
+                  error_code: "protected",
+                };
+              }
               if (path.includes("locked")) {
-                return { path, success: false, error: "Permission denied (os error 13)" };
+                return {
+                  path,
+                  success: false,
+                  error: "IO error: Permission denied (os error 13)",
+                  error_code: "permission_denied",
+                };
               }
               if (path.includes("usb-drive") && mode === "trash") {
                 return {
                   path,
                   success: false,
                   error:
-                    "Cannot move to trash: the volume has no trash directory. Retry with permanent deletion.",
+                    "File operation error: Cannot move to trash: the volume has no trash directory. Retry with permanent deletion.",
+                  error_code: "file_operation",
                 };
               }
               return { path, success: true };
@@ -232,6 +1069,391 @@ export async function deleteFiles(
   }
 }
 
+/**
+ * What deleteFiles would do to `paths` in `mode`, touching nothing: each file
+ * with the space it would free, and the ones it would refuse
+ */
+export async function planDeleteFiles(
+  paths: string[],
+  mode: DeleteMode = "trash"
+): Promise<ActionPlan> {
+  if (isTauri) {
+    return await invoke<ActionPlan>("plan_delete_files", { paths, mode });
+  } else {
+    // Mock sizes are unknown; protected files are refused as in deleteFiles,
+    // and "locked" and "missing" ones as when the backend can't stat them
+    const action = mode === "permanent" ? "delete" : mode;
+    const actions: PlannedAction[] = [];
+    const skipped: FileError[] = [];
+    for (const path of paths) {
+      const root = protectedRoot(path);
+      if (root) {
+        skipped.push({
+          path,
+          error: `Protected path: ${path} is inside ${root}`,
+          code: "protected",
+        });
+      } else if (path.includes("locked")) {
+        skipped.push({
+          path,
+          error: "IO error: Permission denied (os error 13)",
+          code: "permission_denied",
+        });
+      } else if (path.includes("missing")) {
+        skipped.push({
+          path,
+          error: "IO error: No such file or directory (os error 2)",
+          code: "not_found",
+        });
+      } else {
+        actions.push({ path, action, size: 0, reclaimed: 0, detail: null });
+      }
+    }
+    return { actions, skipped, total_size: 0, reclaimed: 0 };
+  }
+}
+
+/**
+ * Move files into quarantine instead of deleting them, reporting a per-file
+ * outcome like deleteFiles. They can be restored with restoreQuarantined
+ * until the configured retention period ends, then are purged.
+ */
+export async function quarantineFiles(paths: string[]): Promise<DeleteResult[]> {
+  if (isTauri) {
+    return await invoke<DeleteResult[]>("quarantine_files", { paths });
+  } else {
+    // Protected, "locked" and "missing" files fail as in deleteFiles
+    const { quarantine_days: days, quarantine_dir: dir } = getMockConfig();
+    mockQuarantine.purgeExpired(Math.floor(Date.now() / 1000));
+    return paths.map((path) => {
+      const root = protectedRoot(path);
+      if (root) {
+        return {
+          path,
+          success: false,
+          error: `Protected path: ${path} is inside ${root}`,
+          protected: true,
+          error_code: "protected",
+        };
+      }
+      if (path.includes("locked")) {
+        return {
+          path,
+          success: false,
+          error: "Permission denied (os error 13)",
+          error_code: "permission_denied",
+        };
+      }
+      if (path.includes("missing")) {
+        return {
+          path,
+          success: false,
+          error: `File not found: ${path}`,
+          error_code: "not_found",
+        };
+      }
+      mockQuarantine.add(path, days, dir);
+      return { path, success: true };
+    });
+  }
+}
+
+/**
+ * Quarantined files, newest first. Files past their retention period are
+ * purged first.
+ */
+export async function listQuarantine(): Promise<QuarantineRecord[]> {
+  if (isTauri) {
+    return await invoke<QuarantineRecord[]>("list_quarantine");
+  } else {
+    mockQuarantine.purgeExpired(Math.floor(Date.now() / 1000));
+    return mockQuarantine.list();
+  }
+}
+
+/**
+ * Move a quarantined file back to its original path. Rejects when the id is
+ * unknown or something else now occupies that path.
+ */
+export async function restoreQuarantined(id: number): Promise<QuarantineRecord> {
+  if (isTauri) {
+    return await invoke<QuarantineRecord>("restore_quarantined", { id });
+  } else {
+    return mockQuarantine.restore(id);
+  }
+}
+
+/**
+ * Permanently delete quarantined files past their retention period,
+ * resolving to the purged entries
+ */
+export async function purgeQuarantine(): Promise<QuarantineRecord[]> {
+  if (isTauri) {
+    return await invoke<QuarantineRecord[]>("purge_quarantine");
+  } else {
+    return mockQuarantine.purgeExpired(Math.floor(Date.now() / 1000));
+  }
+}
+
+/**
+ * Compressions that kept their original, with what each saved, newest first
+ */
+export async function listCompressionBackups(): Promise<CompressionBackup[]> {
+  if (isTauri) {
+    return await invoke<CompressionBackup[]>("list_compression_backups");
+  } else {
+    return mockBackups.list();
+  }
+}
+
+/**
+ * Undo a compression from listCompressionBackups: the backup replaces the
+ * compressed output. Rejects with "not_found" for an unknown id and
+ * "invalid_input" for a restore point that is not a compression.
+ */
+export async function restoreCompression(id: number): Promise<RestorePoint> {
+  if (isTauri) {
+    return await invoke<RestorePoint>("restore_compression", { id });
+  } else {
+    return mockBackups.restore(id);
+  }
+}
+
+/**
+ * Delete the backups of compressions made before `olderThan`, an ISO date or
+ * a duration such as "30d"; those compressions can no longer be undone.
+ * Rejects with "invalid_input" when `olderThan` cannot be read.
+ */
+export async function purgeBackups(olderThan: string): Promise<BackupPurge> {
+  if (isTauri) {
+    return await invoke<BackupPurge>("purge_backups", { olderThan });
+  } else {
+    return mockBackups.purge(olderThan);
+  }
+}
+
+/**
+ * Archive a file or directory into a ZIP (default) or 7z at `dest`,
+ * resolving to the archive size in bytes. With a password the contents are
+ * AES-256 encrypted.
+ * Rejects when the source is missing or `dest` already exists.
+ */
+export async function createArchive(
+  source: string,
+  dest: string,
+  password?: string | null,
+  format?: ArchiveFormat
+): Promise<number> {
+  if (isTauri) {
+    return await invoke<number>("create_archive", {
+      source,
+      dest,
+      password: password ?? null,
+      format: format ?? null,
+    });
+  } else {
+    // Rejected with a ServiceError, as invoke() rejects a failed command
+    if (source.includes("missing")) {
+      throw new ServiceError("not_found", `Source not found: ${source}`);
+    }
+    if (dest.includes("locked")) {
+      throw new ServiceError("permission_denied", "Permission denied (os error 13)");
+    }
+    if (password === "") {
+      throw new ServiceError("invalid_input", "Archive password must not be empty");
+    }
+    if (password != null && format === "tar.zst") {
+      throw new ServiceError("invalid_input", "tar.zst archives cannot be password protected; use zip or 7z");
+    }
+    // LZMA2 typically lands well under deflate
+    return format === "7z" ? 384 * 1024 : 512 * 1024;
+  }
+}
+
+/**
+ * Move a directory into cold storage: archive it to `dest` (tar.zst by
+ * default), verify the archive, record it and delete the original. Rejects,
+ * keeping the original, when any step fails.
+ */
+export async function archiveDirectory(
+  source: string,
+  dest: string,
+  format?: ArchiveFormat,
+  password?: string | null
+): Promise<ArchiveRecord> {
+  if (isTauri) {
+    return await invoke<ArchiveRecord>("archive_directory", {
+      source,
+      dest,
+      format: format ?? null,
+      password: password ?? null,
+    });
+  } else {
+    // Rejected with a ServiceError, as invoke() rejects a failed command
+    if (source.includes("missing")) {
+      throw new ServiceError("not_found", `Source not found: ${source}`);
+    }
+    if (dest.includes("locked")) {
+      throw new ServiceError("permission_denied", "Permission denied (os error 13)");
+    }
+    if (password != null && (format ?? "tar.zst") === "tar.zst") {
+      throw new ServiceError("invalid_input", "tar.zst archives cannot be password protected; use zip or 7z");
+    }
+    const originalSize = 48 * 1024 * 1024;
+    return {
+      id: 1,
+      source_path: source,
+      archive_path: dest,
+      format: format ?? "tar.zst",
+      original_size: originalSize,
+      archive_size: originalSize / 4,
+      file_count: 1234,
+      created_at: Math.floor(Date.now() / 1000),
+    };
+  }
+}
+
+/**
+ * A size-weighted directory tree per path for a treemap view, `depth`
+ * levels deep (0 gives just the roots). Each node counts everything beneath
+ * it, hidden files included, split by file type; children are sorted
+ * largest first.
+ */
+export async function getTreemap(paths: string[], depth: number): Promise<TreemapNode[]> {
+  if (isTauri) {
+    return await invoke<TreemapNode[]>("get_treemap", { paths, depth });
+  } else {
+    return await Promise.all(paths.map(path => mockTreemap(path, depth)));
+  }
+}
+
+/**
+ * Recorded scans of a path, oldest first, with growth per file category
+ * since the first. A path is recorded whenever it is scanned without filters.
+ */
+export async function getStorageHistory(path: string): Promise<StorageHistory> {
+  if (isTauri) {
+    return await invoke<StorageHistory>("get_storage_history", { path });
+  } else {
+    return mockStorageHistory(path);
+  }
+}
+
+/**
+ * Files added, removed or resized between two recorded scans of the same path
+ * (ids from getStorageHistory). Rejects for unknown ids or scans of
+ * different paths.
+ */
+export async function diffScans(fromScan: number, toScan: number): Promise<ScanDiff> {
+  if (isTauri) {
+    return await invoke<ScanDiff>("diff_scans", { fromScan, toScan });
+  } else {
+    return mockDiffScans(fromScan, toScan);
+  }
+}
+
+/**
+ * The most recent recorded scans of any path, newest first (20 unless
+ * `limit` says otherwise), each with the space the recorded duplicates under
+ * its path waste: "last scanned 3 days ago, 12 GB reclaimable"
+ */
+export async function getScanHistory(limit?: number): Promise<ScanHistoryEntry[]> {
+  if (isTauri) {
+    return await invoke<ScanHistoryEntry[]>("get_scan_history", { limit });
+  } else {
+    return mockScanHistory(limit ?? 20);
+  }
+}
+
+/**
+ * A recorded scan with its totals per category, largest files and the
+ * recorded duplicates under its path. Rejects for unknown ids.
+ */
+export async function getScanDetails(id: number): Promise<ScanDetails> {
+  if (isTauri) {
+    return await invoke<ScanDetails>("get_scan_details", { id });
+  } else {
+    return mockScanDetails(id);
+  }
+}
+
+/**
+ * Forget a recorded scan and the files it saw. Rejects for unknown ids.
+ */
+export async function deleteHistoryEntry(id: number): Promise<void> {
+  if (isTauri) {
+    await invoke("delete_history_entry", { id });
+  } else {
+    mockDeleteScan(id);
+  }
+}
+
+/**
+ * The duplicate groups last found under each checked folder, most wasted
+ * space first
+ */
+export async function getDuplicateHistory(): Promise<DuplicateRecord[]> {
+  if (isTauri) {
+    return await invoke<DuplicateRecord[]>("get_duplicate_history");
+  } else {
+    return mockDuplicateHistory();
+  }
+}
+
+/**
+ * The duplicate groups last found under each checked folder, most wasted
+ * space first
+ * @deprecated Use getDuplicateHistory
+ */
+export async function getRecordedDuplicates(): Promise<DuplicateRecord[]> {
+  return getDuplicateHistory();
+}
+
+/**
+ * Browse the files earlier scans recorded without rescanning, a page at a
+ * time (the first 100 unless `page` says otherwise)
+ */
+export async function browseRecordedFiles(query: FileQuery, page?: Page): Promise<FileRecord[]> {
+  if (isTauri) {
+    return await invoke<FileRecord[]>("browse_recorded_files", { query, page: page || null });
+  } else {
+    return mockRecordedFiles(query, page ?? { offset: 0, limit: 100 });
+  }
+}
+
+/**
+ * The `limit` largest files across multiple directories, largest first
+ */
+export async function getLargestFiles(paths: string[], limit: number, filter?: FilterConfig): Promise<FileInfo[]> {
+  if (isTauri) {
+    return await invoke<FileInfo[]>("get_largest_files", { paths, limit, filter: filter || null });
+  } else {
+    const results = await Promise.all(paths.map(path => mockLargestFiles(path)));
+    return results
+      .flat()
+      .filter(f => !isExcludedPath(f.path, filter?.excludePaths))
+      .sort((a, b) => b.size - a.size)
+      .slice(0, limit);
+  }
+}
+
+/**
+ * The `limit` largest folders beneath the given directories, largest first.
+ * A folder's size covers its whole subtree, so nested folders appear
+ * alongside their parents; the scan roots themselves are not listed.
+ */
+export async function getLargestDirectories(paths: string[], limit: number): Promise<DirectorySize[]> {
+  if (isTauri) {
+    return await invoke<DirectorySize[]>("get_largest_directories", { paths, limit });
+  } else {
+    const results = await Promise.all(paths.map(path => mockLargestDirectories(path)));
+    return results
+      .flat()
+      .sort((a, b) => b.total_size - a.total_size)
+      .slice(0, limit);
+  }
+}
+
 /**
  * Get storage statistics across multiple directories
  */
@@ -272,6 +1494,14 @@ export interface CompressionPlugin {
   version: string;
   /** Quality setting (0-100), or null if the plugin has no quality knob */
   quality?: number | null;
+  /** Disabled plugins are listed but never picked for a file */
+  enabled?: boolean;
+  /** Output is not bit-for-bit recoverable (lossy encoding) */
+  lossy: boolean;
+  /** Output replaces the original at its path; refused in safe mode */
+  destructive: boolean;
+  /** External programs the plugin shells out to (any one is enough) */
+  requires_external_tools: string[];
 }
 
 /**
@@ -346,13 +1576,66 @@ export interface ScanCompressibleResult {
   rejected: RejectedFile[];
 }
 
+/**
+ * The files of one extension a plugin would compress, and what they save
+ * (mirrors the service's EstimateGroup)
+ */
+export interface EstimateGroup {
+  plugin_name: string;
+  /** Lowercase, without the dot; empty for files without one */
+  extension: string;
+  file_count: number;
+  total_size: number;
+  /** Files converted to measure the ratio */
+  sampled: number;
+  /**
+   * Share of the size a compression saves, as measured on the sample; the
+   * plugin's own guess when no sample could be converted
+   */
+  savings_ratio: number;
+  /** False when savings_ratio is the plugin's guess */
+  measured: boolean;
+  estimated_savings: number;
+  /** Sampled files the plugin failed on */
+  failed: FileError[];
+}
+
+/**
+ * What compressing a set of files would save, per plugin and extension
+ * (largest savings first)
+ */
+export interface CompressionEstimate {
+  groups: EstimateGroup[];
+  file_count: number;
+  total_size: number;
+  estimated_savings: number;
+}
+
+/**
+ * File format of an export: CSV rows (sizes in bytes), the results as JSON,
+ * or a self-contained HTML report
+ */
+export type ExportFormat = "csv" | "json" | "html";
+
+/**
+ * Results to export, tagged with what they are (mirrors the service's
+ * ExportResults enum)
+ */
+export type ExportResults =
+  | { kind: "duplicates"; data: DuplicateGroup[] }
+  | { kind: "similar"; data: SimilarGroup[] }
+  | { kind: "stats"; data: StorageStats }
+  | { kind: "compressible"; data: CompressibleFile[] };
+
 /**
  * Status of an in-place compression:
  * - compressed: original renamed to backup, smaller file written
  * - skipped: output was not smaller, original kept untouched
  * - failed: an error occurred, original kept untouched
+ * - timed_out: exceeded the per-file timeout, original kept untouched
+ * - cancelled: stopped by cancelCompression, original kept untouched
  */
-export type CompressionStatus = "compressed" | "skipped" | "failed";
+export type CompressionStatus = "compressed" | "skipped" | "failed" | "timed_out" | "cancelled";
 
 /**
  * In-place compression result
@@ -366,8 +1649,52 @@ export interface InPlaceCompressionResult {
   compressed_size?: number;
   savings?: number;
   plugin_name?: string;
+  /** Whether EXIF/XMP/ICC metadata was copied; null when the source had none */
+  metadata_preserved?: boolean | null;
+  /** Files written alongside the output, e.g. the archived camera RAW */
+  extra_outputs?: string[];
   reason?: string;
   error?: string;
+  /** What kind of failure `error` is */
+  error_code?: ErrorCode;
+  /** Failed because the file is protected */
+  protected?: boolean;
+}
+
+/**
+ * Progress reported by a plugin while it compresses one file: entries of an
+ * archive, or a single 0/1 step for whole-file plugins
+ */
+export interface CompressionProgress {
+  path: string;
+  current: number;
+  total: number;
+  message?: string | null;
+}
+
+/**
+ * A file's way through a compressFilesInPlace batch: one event when it
+ * starts, one when it ends
+ */
+export interface CompressionFileEvent {
+  /** The job compressing the batch */
+  job_id: number;
+  /** The file as it was passed in */
+  path: string;
+  /** Position in the batch, from 0 */
+  index: number;
+  total: number;
+  /**
+   * "started", then the file's result status: "compressed", "skipped",
+   * "failed", "timed_out" or "cancelled"
+   */
+  status: "started" | CompressionStatus;
+  /** Bytes a compressed file saved */
+  savings: number | null;
+  /** Why the file was skipped or failed */
+  reason: string | null;
+  /** Share of the batch done, 0-100 */
+  percent: number;
 }
 
 /**
@@ -381,7 +1708,11 @@ export async function getCompressionPlugins(): Promise<CompressionPlugin[]> {
     // plugin manager from config at startup); copies prevent UI mutation from
     // leaking into the shared mock list.
     const cfg = getMockConfig();
-    return mockPlugins.map(p => ({ ...p, quality: cfg.plugin_quality[p.name] ?? p.quality }));
+    return mockPlugins.map(p => ({
+      ...p,
+      quality: cfg.plugin_quality[p.name] ?? p.quality,
+      enabled: !(cfg.disabled_plugins ?? []).includes(p.name),
+    }));
   }
 }
 
@@ -393,18 +1724,57 @@ export async function setPluginQuality(pluginName: string, quality: number): Pro
   if (isTauri) {
     await invoke("set_plugin_quality", { pluginName, quality });
   } else {
-    // Mirrors the backend: unknown plugin names fail. Like a real invoke()
-    // failure, the rejection value is the backend's plain error string.
+    // Mirrors the backend: unknown plugin names fail. Like a real invoke()
+    // failure, the rejection value is the backend's plain error string.
+    if (!isKnownPlugin(pluginName)) {
+      return Promise.reject(new ServiceError("not_found", `Plugin not found: ${pluginName}`));
+    }
+    if (mockPlugins.find(p => p.name === pluginName)?.quality == null) {
+      return Promise.reject(
+        new ServiceError("invalid_input", `Plugin '${pluginName}' does not support a quality setting`)
+      );
+    }
+    // Persist the clamped value into the mock config, just like the backend
+    const cfg = getMockConfig();
+    cfg.plugin_quality[pluginName] = Math.max(0, Math.min(100, quality));
+    setMockConfig(cfg);
+  }
+}
+
+/**
+ * Turn a compression plugin on or off. Persisted to the config; disabled
+ * plugins are still listed but never picked for a file.
+ */
+export async function setPluginEnabled(pluginName: string, enabled: boolean): Promise<void> {
+  if (isTauri) {
+    await invoke("set_plugin_enabled", { pluginName, enabled });
+  } else {
     if (!isKnownPlugin(pluginName)) {
-      return Promise.reject(`Plugin not found: ${pluginName}`);
+      return Promise.reject(new ServiceError("not_found", `Plugin not found: ${pluginName}`));
     }
-    // Persist the clamped value into the mock config, just like the backend
     const cfg = getMockConfig();
-    cfg.plugin_quality[pluginName] = Math.max(0, Math.min(100, quality));
+    const disabled = (cfg.disabled_plugins ?? []).filter(name => name !== pluginName);
+    cfg.disabled_plugins = enabled ? disabled : [...disabled, pluginName];
     setMockConfig(cfg);
   }
 }
 
+/**
+ * Write results to `path` as CSV, JSON or a self-contained HTML report,
+ * replacing any existing file
+ */
+export async function exportResults(
+  results: ExportResults,
+  format: ExportFormat,
+  path: string
+): Promise<void> {
+  if (isTauri) {
+    await invoke<void>("export_results", { results, format, path });
+  } else {
+    await mockExport.write(results, format, path);
+  }
+}
+
 /**
  * Scan paths for compressible files
  */
@@ -421,10 +1791,10 @@ export async function scanCompressibleFiles(
     });
   } else {
     // Mirrors the backend: unknown active plugin names abort the scan with
-    // the same plain-string error a real invoke() would reject with
+    // the same error a real invoke() would reject with
     for (const name of activePlugins) {
       if (!isKnownPlugin(name)) {
-        return Promise.reject(`Active plugin not found: ${name}`);
+        return Promise.reject(new ServiceError("not_found", `Active plugin not found: ${name}`));
       }
     }
 
@@ -471,6 +1841,20 @@ export async function scanCompressibleFiles(
         estimated_compressed_size: 358400,
         estimated_savings: 153600,
         plugin_name: "WebP Converter"
+      },
+      {
+        path: "/path/to/IMG_0042.CR2",
+        original_size: 25165824,
+        estimated_compressed_size: 6291456,
+        estimated_savings: 18874368,
+        plugin_name: "RAW to JPEG"
+      },
+      {
+        path: "/path/to/site/assets/logo.svg",
+        original_size: 48128,
+        estimated_compressed_size: 36096,
+        estimated_savings: 12032,
+        plugin_name: "Text Asset Minifier"
       }
     ];
     const rejected: RejectedFile[] = [
@@ -490,9 +1874,55 @@ export async function scanCompressibleFiles(
     // Files remembered as "no size reduction" (recorded by the
     // compressFilesInPlace mock when a file skips) are excluded from
     // compressible and surfaced as rejections, like the backend skip cache
+    // Safe mode disables plugins that replace the original file, which the
+    // backend reports as a capability rejection
+    const { safe_mode: safeMode, minify_dirs: minifyDirs } = getMockConfig();
     const remaining: CompressibleFile[] = [];
     for (const file of compressible) {
-      if (mockSkipCache.has(file.path)) {
+      // The minifier only touches files under its configured directories
+      if (file.plugin_name === "Text Asset Minifier") {
+        const dirs = minifyDirs.map(d => d.trim()).filter(Boolean);
+        const reason = dirs.length === 0
+          ? "No directories are configured for minification"
+          : dirs.some(d => file.path.startsWith(d.replace(/\/?$/, "/")))
+            ? null
+            : "Outside the configured minification directories";
+        if (reason) {
+          rejected.push({
+            path: file.path,
+            size: file.original_size,
+            extension: "svg",
+            rejection_reasons: [{ plugin_name: file.plugin_name, reason }]
+          });
+          continue;
+        }
+      }
+      const destructive = mockPlugins.some(p => p.name === file.plugin_name && p.destructive);
+      if (safeMode && destructive) {
+        rejected.push({
+          path: file.path,
+          size: file.original_size,
+          extension: file.path.split(".").pop() ?? "",
+          rejection_reasons: [
+            {
+              plugin_name: file.plugin_name,
+              reason: "Disabled in safe mode (replaces original files)"
+            }
+          ]
+        });
+      } else if (mockCompressionHistory.has(file.path)) {
+        rejected.push({
+          path: file.path,
+          size: file.original_size,
+          extension: file.path.split(".").pop() ?? "",
+          rejection_reasons: [
+            {
+              plugin_name: "",
+              reason: "You chose not to compress this file (history; file unchanged)"
+            }
+          ]
+        });
+      } else if (mockSkipCache.has(file.path)) {
         rejected.push({
           path: file.path,
           size: file.original_size,
@@ -513,6 +1943,62 @@ export async function scanCompressibleFiles(
   }
 }
 
+/**
+ * Measure what compressing the files under `paths` with `plugins` would
+ * save: up to `sampleCount` files (5 by default) of each plugin and
+ * extension are converted to a temporary directory and their savings
+ * extrapolated to the rest. Rejects with "not_found" for an unknown plugin.
+ */
+export async function estimateCompression(
+  paths: string[],
+  plugins: string[],
+  sampleCount?: number
+): Promise<CompressionEstimate> {
+  if (isTauri) {
+    return await invoke<CompressionEstimate>("estimate_compression", {
+      paths,
+      plugins,
+      sampleCount: sampleCount ?? null
+    });
+  } else {
+    // The mock "measures" the mock scan's own estimates on the sampled files
+    const { compressible } = await scanCompressibleFiles(paths, plugins);
+    const groups = new Map<string, CompressibleFile[]>();
+    for (const file of compressible.filter(f => plugins.includes(f.plugin_name))) {
+      const name = file.path.split("/").pop() ?? "";
+      const extension = name.includes(".") ? name.split(".").pop()!.toLowerCase() : "";
+      const key = `${file.plugin_name}\0${extension}`;
+      groups.set(key, [...(groups.get(key) ?? []), file]);
+    }
+    const estimate: CompressionEstimate = { groups: [], file_count: 0, total_size: 0, estimated_savings: 0 };
+    for (const [key, files] of groups) {
+      const [plugin_name, extension] = key.split("\0");
+      const sample = files.slice(0, sampleCount ?? 5);
+      const sampleSize = sample.reduce((sum, f) => sum + f.original_size, 0);
+      const sampleSaved = sample.reduce((sum, f) => sum + f.estimated_savings, 0);
+      const savings_ratio = sampleSize > 0 ? sampleSaved / sampleSize : 0;
+      const total_size = files.reduce((sum, f) => sum + f.original_size, 0);
+      const group: EstimateGroup = {
+        plugin_name,
+        extension,
+        file_count: files.length,
+        total_size,
+        sampled: sample.length,
+        savings_ratio,
+        measured: sample.length > 0,
+        estimated_savings: Math.floor(total_size * savings_ratio),
+        failed: []
+      };
+      estimate.groups.push(group);
+      estimate.file_count += group.file_count;
+      estimate.total_size += group.total_size;
+      estimate.estimated_savings += group.estimated_savings;
+    }
+    estimate.groups.sort((a, b) => b.estimated_savings - a.estimated_savings);
+    return estimate;
+  }
+}
+
 /**
  * Compress files in place. With createBackup the original is kept as
  * <name>.bak; without it the original is deleted once compression fully
@@ -524,19 +2010,68 @@ export async function compressFilesInPlace(
   createBackup: boolean = true
 ): Promise<InPlaceCompressionResult[]> {
   if (isTauri) {
-    return await invoke<InPlaceCompressionResult[]>("compress_files_in_place", {
-      filePaths,
-      pluginOrders,
-      createBackup
-    });
+    return await runJob<InPlaceCompressionResult[]>("compress", () =>
+      invoke<number>("compress_files_in_place", {
+        filePaths,
+        pluginOrders,
+        createBackup
+      })
+    );
   } else {
     // Mock in-place compression. Status is derived from the file name so the
     // three-state UI (compressed / skipped / failed) can be previewed in web
     // mode: "already-tiny" files skip (and are remembered by the mock skip
     // cache, like the backend), "locked" files fail with a permission error,
-    // "missing" files fail with "File not found", the rest compress.
-    await new Promise(resolve => setTimeout(resolve, 200));
-    return filePaths.map(path => {
+    // "missing" files fail with "File not found", the rest compress. A
+    // cancelCompression() or cancelJob() during the delay reports every file
+    // as cancelled.
+    const run = mockCompressionRuns.register();
+    const jobId = mockJobs.register({ CompressFiles: filePaths }, () => {
+      run.cancelled = true;
+    });
+    mockJobs.update(jobId, { Started: { task_type: "compress", total_items: filePaths.length } });
+    for (const path of filePaths) {
+      mockCompressionProgress.emit({ path, current: 0, total: 1 });
+    }
+    try {
+      await new Promise(resolve => setTimeout(resolve, 200));
+    } finally {
+      mockCompressionRuns.unregister(run);
+    }
+    mockJobs.update(jobId, run.cancelled ? "Cancelled" : { Completed: { message: "compress finished" } });
+    if (!run.cancelled) {
+      for (const path of filePaths) {
+        mockCompressionProgress.emit({ path, current: 1, total: 1 });
+      }
+    }
+    // Mock JPEGs carry camera metadata; other mock sources have none
+    const {
+      preserve_metadata: preserveMetadata,
+      min_savings_percent: minSavings,
+      file_timeout_secs: timeoutSecs,
+      raw_policy: rawPolicy
+    } = getMockConfig();
+    const results = filePaths.map(path => {
+      if (run.cancelled) {
+        return {
+          status: "cancelled" as const,
+          success: false,
+          path,
+          error: "Cancelled",
+          error_code: "cancelled" as const
+        };
+      }
+      const root = protectedRoot(path);
+      if (root) {
+        return {
+          status: "failed" as const,
+          success: false,
+          path,
+          error: `Protected path: ${path} is inside ${root}`,
+          error_code: "protected" as const,
+          protected: true
+        };
+      }
       if (path.includes("already-tiny")) {
         mockSkipCache.record(path);
         return {
@@ -547,12 +2082,35 @@ export async function compressFilesInPlace(
           reason: "Compressed output (102400 bytes) is not smaller than the original (98304 bytes); original kept"
         };
       }
+      // Every mock conversion saves 30%; a higher minimum discards it like
+      // the backend's central threshold check
+      if (minSavings > 30) {
+        return {
+          status: "skipped" as const,
+          success: true,
+          path,
+          plugin_name: "WebP Converter",
+          reason: `Insufficient savings: 30.0% is below the ${minSavings}% minimum; original kept`
+        };
+      }
+      // Paths containing "slow-tool" never finish, so they hit the per-file
+      // timeout when one is set
+      if (path.includes("slow-tool") && timeoutSecs > 0) {
+        return {
+          status: "timed_out" as const,
+          success: false,
+          path,
+          error: `Timed out after ${timeoutSecs}s`,
+          error_code: "timed_out" as const
+        };
+      }
       if (path.includes("missing")) {
         return {
           status: "failed" as const,
           success: false,
           path,
-          error: "File not found"
+          error: "File not found",
+          error_code: "not_found" as const
         };
       }
       if (path.includes("locked")) {
@@ -560,7 +2118,26 @@ export async function compressFilesInPlace(
           status: "failed" as const,
           success: false,
           path,
-          error: "Failed to back up original file: Permission denied (os error 13)"
+          error: "Failed to back up original file: Permission denied (os error 13)",
+          error_code: "permission_denied" as const
+        };
+      }
+      // Camera RAWs become JPEGs; the archive policy also keeps the RAW in a
+      // ZIP next to it, which counts against the savings
+      if (/\.(cr2|nef|arw)$/i.test(path)) {
+        const archive = rawPolicy === "archive";
+        const compressedSize = archive ? 6291456 + 21495808 : 6291456;
+        return {
+          status: "compressed" as const,
+          success: true,
+          path: path.replace(/\.[^.]+$/, ".jpg"),
+          ...(createBackup ? { backup_path: `${path}.bak` } : {}),
+          original_size: 25165824,
+          compressed_size: compressedSize,
+          savings: 25165824 - compressedSize,
+          plugin_name: "RAW to JPEG",
+          metadata_preserved: preserveMetadata,
+          extra_outputs: archive ? [`${path}.zip`] : []
         };
       }
       return {
@@ -571,9 +2148,124 @@ export async function compressFilesInPlace(
         original_size: 1024000,
         compressed_size: 716800,
         savings: 307200,
-        plugin_name: "WebP Converter"
+        plugin_name: "WebP Converter",
+        metadata_preserved: /\.jpe?g$/i.test(path) ? preserveMetadata : null
       };
     });
+    filePaths.forEach((path, index) => {
+      const result = results[index];
+      const event = { job_id: jobId, path, index, total: filePaths.length, savings: null, reason: null };
+      if (result.status !== "cancelled") {
+        mockCompressionFiles.emit({ ...event, status: "started", percent: (index * 100) / filePaths.length });
+      }
+      mockCompressionFiles.emit({
+        ...event,
+        status: result.status,
+        savings: result.status === "compressed" ? result.savings : null,
+        reason: "reason" in result ? result.reason : "error" in result ? result.error : null,
+        percent: ((index + 1) * 100) / filePaths.length
+      });
+    });
+    for (const [i, result] of results.entries()) {
+      if (result.status === "compressed") {
+        mockSavings.record(result.plugin_name, result.original_size, result.compressed_size);
+        if (result.backup_path) {
+          mockBackups.record(filePaths[i], result.path, result.plugin_name, result.original_size, result.compressed_size);
+        }
+      }
+    }
+    return results;
+  }
+}
+
+/**
+ * Subscribe to per-file progress while compressFilesInPlace runs. Resolves to
+ * a function that removes the listener.
+ */
+export async function onCompressionProgress(
+  callback: (progress: CompressionProgress) => void
+): Promise<UnlistenFn> {
+  if (isTauri) {
+    return await listen<CompressionProgress>("compress://file-progress", event => callback(event.payload));
+  } else {
+    return mockCompressionProgress.subscribe(callback);
+  }
+}
+
+/**
+ * Subscribe to each file of a compressFilesInPlace batch starting and
+ * ending, with its savings or the reason it was skipped or failed, to show
+ * the queue live. Resolves to a function that removes the listener.
+ */
+export async function onCompressionFile(
+  callback: (event: CompressionFileEvent) => void
+): Promise<UnlistenFn> {
+  if (isTauri) {
+    return await listen<CompressionFileEvent>("compress://file", event => callback(event.payload));
+  } else {
+    return mockCompressionFiles.subscribe(event => callback(event as CompressionFileEvent));
+  }
+}
+
+/**
+ * Cancel every in-place compression in flight. Files already compressed stay
+ * compressed; the rest are reported as "cancelled" with their originals
+ * untouched. Resolves to the number of runs that were signalled.
+ */
+export async function cancelCompression(): Promise<number> {
+  if (isTauri) {
+    return await invoke<number>("cancel_compression");
+  } else {
+    return mockCompressionRuns.cancelAll();
+  }
+}
+
+/**
+ * Background jobs (scans, duplicate and similar searches, compressions):
+ * the running ones and the most recently finished, oldest first
+ */
+export async function listJobs(): Promise<JobInfo[]> {
+  if (isTauri) {
+    return await invoke<JobInfo[]>("list_jobs");
+  } else {
+    return mockJobs.list();
+  }
+}
+
+/**
+ * One background job's status and progress. Rejects for an unknown id.
+ */
+export async function getJobStatus(jobId: number): Promise<JobInfo> {
+  if (isTauri) {
+    return await invoke<JobInfo>("get_job_status", { jobId });
+  } else {
+    return mockJobs.status(jobId);
+  }
+}
+
+/**
+ * Ask a background job to stop; its promise settles once it notices, with
+ * a ServiceError coded "cancelled". Resolves to false when the job had already
+ * finished and rejects for an unknown id.
+ */
+export async function cancelJob(jobId: number): Promise<boolean> {
+  if (isTauri) {
+    return await invoke<boolean>("cancel_job", { jobId });
+  } else {
+    return mockJobs.cancel(jobId);
+  }
+}
+
+/**
+ * What closing the app last time interrupted: jobs cut short and partial
+ * archives removed. Resolves once with the report, then null; null after a
+ * clean exit.
+ */
+export async function takeShutdownReport(): Promise<ShutdownReport | null> {
+  if (isTauri) {
+    return await invoke<ShutdownReport | null>("take_shutdown_report");
+  } else {
+    return mockJobs.takeShutdownReport();
   }
 }
 
@@ -582,27 +2274,58 @@ export async function compressFilesInPlace(
  */
 export interface SkipCacheInfo {
   entries: number;
+  /** Compression history records (compressed files and user skip decisions) */
+  history_entries: number;
+}
+
+/**
+ * Space saved by every compression so far, in total, per plugin and per
+ * month. Clearing the skip cache does not reset it.
+ */
+export async function getSavingsSummary(): Promise<SavingsSummary> {
+  if (isTauri) {
+    return await invoke<SavingsSummary>("get_savings_summary");
+  } else {
+    return mockSavings.summary();
+  }
 }
 
 /**
- * Get the number of remembered no-size-reduction results
+ * Get the number of remembered no-size-reduction results and compression
+ * history records
  */
 export async function getSkipCacheInfo(): Promise<SkipCacheInfo> {
   if (isTauri) {
     return await invoke<SkipCacheInfo>("get_skip_cache_info");
   } else {
-    return { entries: mockSkipCache.size() };
+    return { entries: mockSkipCache.size(), history_entries: mockCompressionHistory.size() };
   }
 }
 
 /**
- * Forget all remembered no-size-reduction results; returns how many were removed
+ * Forget all remembered no-size-reduction results and the compression
+ * history; returns how many were removed
  */
 export async function clearSkipCache(): Promise<number> {
   if (isTauri) {
     return await invoke<number>("clear_skip_cache");
   } else {
-    return mockSkipCache.clear();
+    return mockSkipCache.clear() + mockCompressionHistory.clear();
+  }
+}
+
+/**
+ * Record the user's decision not to compress these files; scans leave them
+ * out until they change. Missing files are ignored. Resolves to how many
+ * were recorded.
+ */
+export async function skipCompressionFiles(filePaths: string[]): Promise<number> {
+  if (isTauri) {
+    return await invoke<number>("skip_compression_files", { filePaths });
+  } else {
+    const existing = filePaths.filter(path => !path.includes("missing"));
+    existing.forEach(path => mockCompressionHistory.skip(path));
+    return existing.length;
   }
 }
 
@@ -618,32 +2341,205 @@ export async function getConfig(): Promise<AppConfig> {
   }
 }
 
+/** Rejection for a config the backend's validate() refuses */
+function invalidConfig(message: string): Promise<never> {
+  return Promise.reject(new ServiceError("invalid_input", message));
+}
+
 /**
  * Validate and persist the application configuration, returning what was saved.
  * The web branch mirrors the backend's validate() so the settings UI surfaces
- * the same rejection strings (rejected as a plain string, like a real invoke).
+ * the same rejections.
  */
 export async function setConfig(config: AppConfig): Promise<AppConfig> {
   if (isTauri) {
     return await invoke<AppConfig>("set_config", { config });
   } else {
     if (config.image_similarity_threshold < 0 || config.image_similarity_threshold > 1) {
-      return Promise.reject(
+      return invalidConfig(
         `image_similarity_threshold must be between 0.0 and 1.0, got ${config.image_similarity_threshold}`
       );
     }
     if (config.max_concurrent_tasks < 1) {
-      return Promise.reject("max_concurrent_tasks must be at least 1");
+      return invalidConfig("max_concurrent_tasks must be at least 1");
     }
-    if (config.default_delete_mode !== "trash" && config.default_delete_mode !== "permanent") {
-      return Promise.reject(
-        `default_delete_mode must be 'trash' or 'permanent', got '${config.default_delete_mode}'`
+    if (!["trash", "quarantine", "permanent"].includes(config.default_delete_mode)) {
+      return invalidConfig(
+        `default_delete_mode must be 'trash', 'quarantine' or 'permanent', got '${config.default_delete_mode}'`
       );
     }
+    if (config.min_savings_percent < 0 || config.min_savings_percent > 100) {
+      return invalidConfig(
+        `min_savings_percent must be between 0 and 100, got ${config.min_savings_percent}`
+      );
+    }
+    if (config.tool_nice_level != null && (config.tool_nice_level < 0 || config.tool_nice_level > 19)) {
+      return invalidConfig(`tool_nice_level must be between 0 and 19, got ${config.tool_nice_level}`);
+    }
+    if (config.raw_policy !== "delete" && config.raw_policy !== "archive") {
+      return invalidConfig(`raw_policy must be 'delete' or 'archive', got '${config.raw_policy}'`);
+    }
+    if (config.tool_max_memory_mb === 0) {
+      return invalidConfig("tool_max_memory_mb must be at least 1");
+    }
+    if (config.background_io_limit === 0) {
+      return invalidConfig("background_io_limit must be at least 1 byte per second");
+    }
+    const ruleError = validateCleanupRules(config.cleanup_rules ?? []);
+    if (ruleError) {
+      return invalidConfig(ruleError);
+    }
+    const scheduleError = validateSchedules(
+      config.schedules ?? [],
+      (config.cleanup_rules ?? []).map(rule => rule.name)
+    );
+    if (scheduleError) {
+      return invalidConfig(scheduleError);
+    }
     return setMockConfig(config);
   }
 }
 
+/** Settings the backend reads only at startup */
+const RESTART_SETTINGS = ["database_path", "cache_dir", "daemon_log", "daemon_socket"] as const;
+
+/**
+ * Change some settings without sending the whole config: tables merge into
+ * the current ones. Rejects like setConfig when the result is invalid, or
+ * when a folder it newly points at is not writable. The log level and plugin
+ * settings such as concurrency apply at once; `restart_required` lists the
+ * changed settings that wait for a restart.
+ */
+export async function updateConfig(changes: ConfigChanges): Promise<ConfigUpdate> {
+  if (isTauri) {
+    return await invoke<ConfigUpdate>("update_config", { changes });
+  } else {
+    const previous = getMockConfig();
+    const config = await setConfig(mergeConfig(previous, changes));
+    return {
+      config,
+      restart_required: RESTART_SETTINGS.filter(key => previous[key] !== config[key]),
+    };
+  }
+}
+
+/**
+ * The cleanup rules from the config, in order
+ */
+export async function getCleanupRules(): Promise<CleanupRule[]> {
+  if (isTauri) {
+    return await invoke<CleanupRule[]>("get_cleanup_rules");
+  } else {
+    return getMockConfig().cleanup_rules ?? [];
+  }
+}
+
+/**
+ * Validate and persist the cleanup rules, replacing all of them. Rejects
+ * with the backend's message for an invalid rule.
+ */
+export async function setCleanupRules(rules: CleanupRule[]): Promise<CleanupRule[]> {
+  if (isTauri) {
+    return await invoke<CleanupRule[]>("set_cleanup_rules", { rules });
+  } else {
+    const ruleError = validateCleanupRules(rules);
+    if (ruleError) {
+      return invalidConfig(ruleError);
+    }
+    return setMockConfig({ ...getMockConfig(), cleanup_rules: rules }).cleanup_rules;
+  }
+}
+
+/**
+ * Run the enabled cleanup rules, or only those named in `names`. With
+ * `dryRun` nothing is touched and the report is the plan.
+ */
+export async function runCleanup(names: string[] | null, dryRun: boolean): Promise<CleanupReport> {
+  if (isTauri) {
+    return await invoke<CleanupReport>("run_cleanup", { names, dryRun });
+  } else {
+    const rules = getMockConfig().cleanup_rules ?? [];
+    const unknown = names?.find(name => !rules.some(r => r.name === name));
+    if (unknown) {
+      return Promise.reject(new ServiceError("not_found", `Unknown cleanup rule: ${unknown}`));
+    }
+    return mockRunCleanup(rules.filter(r => !names || names.includes(r.name)), dryRun);
+  }
+}
+
+/**
+ * The schedules from the config, in order, each with what the daemon reports
+ * of it (null when no daemon runs it)
+ */
+export async function listSchedules(): Promise<ScheduleEntry[]> {
+  if (isTauri) {
+    return await invoke<ScheduleEntry[]>("list_schedules");
+  } else {
+    return mockScheduleEntries(getMockConfig().schedules ?? []);
+  }
+}
+
+/**
+ * Validate and add a schedule to the config, returning it. Rejects with the
+ * backend's message for an invalid schedule or a taken name. A running
+ * daemon picks it up when restarted.
+ */
+export async function createSchedule(schedule: Schedule): Promise<Schedule> {
+  if (isTauri) {
+    return await invoke<Schedule>("create_schedule", { schedule });
+  } else {
+    const config = getMockConfig();
+    await setConfig({ ...config, schedules: [...(config.schedules ?? []), schedule] });
+    return schedule;
+  }
+}
+
+/**
+ * Remove a schedule from the config. A running daemon keeps running it until
+ * restarted.
+ */
+export async function deleteSchedule(name: string): Promise<void> {
+  if (isTauri) {
+    await invoke("delete_schedule", { name });
+  } else {
+    const config = getMockConfig();
+    const schedules = config.schedules ?? [];
+    if (!schedules.some(s => s.name === name)) {
+      return Promise.reject(new ServiceError("not_found", `Unknown schedule: ${name}`));
+    }
+    setMockConfig({ ...config, schedules: schedules.filter(s => s.name !== name) });
+  }
+}
+
+/**
+ * Run a schedule now: queued on the daemon when one is running (its outcome
+ * then shows in listSchedules), otherwise run in the app, resolving once it
+ * finished with what it did.
+ */
+export async function runScheduleNow(name: string): Promise<ScheduleRun> {
+  if (isTauri) {
+    return await invoke<ScheduleRun>("run_schedule_now", { name });
+  } else {
+    const schedule = (getMockConfig().schedules ?? []).find(s => s.name === name);
+    if (!schedule) {
+      return Promise.reject(new ServiceError("not_found", `Unknown schedule: ${name}`));
+    }
+    return mockRunSchedule(schedule);
+  }
+}
+
+/**
+ * The next `count` runs of the daemon's schedules, soonest first. Empty when
+ * no daemon is running, since only the daemon runs schedules.
+ */
+export async function getNextRuns(count: number = 10): Promise<UpcomingRun[]> {
+  if (isTauri) {
+    return await invoke<UpcomingRun[]>("get_next_runs", { count });
+  } else {
+    return mockNextRuns(getMockConfig().schedules ?? [], count);
+  }
+}
+
 /**
  * Reset the configuration to defaults, persisting and returning them. The
  * backend's Config::default() is the single source of truth for the values.
@@ -656,6 +2552,32 @@ export async function resetConfig(): Promise<AppConfig> {
   }
 }
 
+/**
+ * Mounted drives with their capacity, free space, file system and kind
+ * (fixed, removable or network), for the starting view
+ */
+export async function listDrives(): Promise<DiskInfo[]> {
+  if (isTauri) {
+    return await invoke<DiskInfo[]>("list_drives");
+  } else {
+    return mockListDrives();
+  }
+}
+
+/**
+ * Check paths dropped for scanning before starting the scan: whether each
+ * exists and can be read, the kind of drive it is on and about how many
+ * files it holds, with warnings to show the user. Never rejects for a bad
+ * path; that is what the warnings are for.
+ */
+export async function validatePaths(paths: string[]): Promise<PathCheck[]> {
+  if (isTauri) {
+    return await invoke<PathCheck[]>("validate_paths", { paths });
+  } else {
+    return mockValidatePaths(paths);
+  }
+}
+
 /**
  * Detect optional external tools (ffmpeg etc.) on PATH. Used by the settings
  * page to show the environment and gate features that build on these tools.
@@ -668,6 +2590,19 @@ export async function detectTools(): Promise<ToolStatus[]> {
   }
 }
 
+/**
+ * Health-check the external tools each compression plugin can use (the
+ * configured path from config.tool_paths, or a PATH lookup, plus a version
+ * query)
+ */
+export async function checkPluginRequirements(): Promise<PluginRequirements[]> {
+  if (isTauri) {
+    return await invoke<PluginRequirements[]>("check_plugin_requirements");
+  } else {
+    return mockPluginRequirements(getMockConfig().tool_paths);
+  }
+}
+
 /**
  * Check if running in Tauri mode
  */
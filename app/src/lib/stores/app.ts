@@ -7,7 +7,22 @@ export interface FilterConfig {
   maxSize?: number;       // in bytes
   extensions?: string[];  // array of extensions
   filePattern?: string;   // pattern to match in filename
+  fileRegex?: string;     // regular expression the filename must match
+  includeGlobs?: string[]; // full-path globs a file must match ("**/Downloads/**")
+  excludeGlobs?: string[]; // full-path globs whose matches are dropped ("**/*.raw")
+  newerThan?: string;     // modified since: ISO date or duration ("2024-01-01", "30d")
+  olderThan?: string;     // untouched since: ISO date or duration ("2020", "1y")
+  excludeExtensions?: string[]; // extensions to drop
+  excludePatterns?: string[];   // filename substrings to drop
   excludePaths?: string[]; // paths to exclude (files at or beneath are dropped)
+  query?: string;         // filter expression ("size>100MB and ext in (mp4,mkv)")
+  currentUserOnly?: boolean; // only files owned by the current user (Unix)
+  ownerUid?: number;      // only files owned by this uid (Unix)
+  ownerGid?: number;      // only files owned by this gid (Unix)
+  permissions?: string;   // octal bits a file must have, e.g. "002" (Unix)
+  includeHidden?: boolean; // scan dot-files and hidden/system files (default false)
+  maxDepth?: number;      // directory levels below the root to descend (1 = root only)
+  followLinks?: boolean;  // follow symlinked directories (default false)
 }
 
 export interface AppState {
@@ -11,6 +11,9 @@ export interface FileInfo {
   modified: number;
   file_type: string;
   hash?: string;
+  uid?: number;  // owner user id (Unix only)
+  gid?: number;  // owner group id (Unix only)
+  mode?: number; // permission bits (Unix only)
 }
 
 /**
@@ -23,6 +26,41 @@ export interface ScanResult {
   files: FileInfo[];
 }
 
+/**
+ * Totals of one scanned path
+ */
+export interface ScannedPath {
+  path: string;
+  file_count: number;
+  total_size: number;
+}
+
+/**
+ * What a scan job returns: totals only. The files stay in the backend and
+ * are fetched a page at a time with the job id.
+ */
+export interface ScanSummary {
+  job_id: number;
+  file_count: number;
+  total_size: number;
+  paths: ScannedPath[];
+}
+
+/** How scan pages are ordered; ties go by path */
+export type ScanSort = "size" | "path" | "name" | "modified" | "type";
+
+export type SortOrder = "asc" | "desc";
+
+/**
+ * One page of a scan's files
+ */
+export interface ScanPage {
+  files: FileInfo[];
+  /** Files in the whole (possibly path-narrowed) view */
+  total: number;
+  offset: number;
+}
+
 /**
  * Duplicate file group
  */
@@ -34,6 +72,124 @@ export interface DuplicateGroup {
   wasted_space: number;
 }
 
+/**
+ * A file a batch operation could not process, and why
+ */
+export interface FileError {
+  path: string;
+  error: string;
+  code: ErrorCode;
+}
+
+/**
+ * What an operation would do to one file, from a dry run
+ */
+export interface PlannedAction {
+  path: string;
+  /** "trash", "delete", "quarantine", "hardlink", "reflink" or "compress" */
+  action: string;
+  size: number;
+  /** Bytes the action would free; null when that cannot be told in advance */
+  reclaimed: number | null;
+  /** The copy kept in its place for dedupe, the plugin for compress */
+  detail: string | null;
+}
+
+/**
+ * Everything an operation would do, and the files it would refuse. Nothing
+ * on disk changes while planning.
+ */
+export interface ActionPlan {
+  actions: PlannedAction[];
+  skipped: FileError[];
+  total_size: number;
+  reclaimed: number;
+}
+
+/**
+ * Duplicate groups found, with the candidates that could not be read (and
+ * so could not be compared)
+ */
+export interface DuplicateReport {
+  groups: DuplicateGroup[];
+  unreadable: FileError[];
+}
+
+/**
+ * Which copies of a duplicate group to keep. Mirrors the Rust `KeepRule`
+ * enum (internally tagged by "strategy"). `first_path` keeps the copy whose
+ * path sorts first; `in_directory` keeps every copy beneath `prefix` and
+ * leaves groups with no copy there untouched.
+ */
+export type KeepRule =
+  | { strategy: "newest" }
+  | { strategy: "oldest" }
+  | { strategy: "shortest_path" }
+  | { strategy: "first_path" }
+  | { strategy: "in_directory"; prefix: string };
+
+/**
+ * A duplicate group split by a KeepRule. `delete` is empty when the rule
+ * kept nothing, so a group never loses every copy.
+ */
+export interface DuplicateResolution {
+  hash: string;
+  keep: FileInfo[];
+  delete: FileInfo[];
+  /** Bytes freed by deleting `delete` */
+  reclaimable: number;
+  /** Copies the rule would have deleted but that are protected; also in `keep` */
+  protected: FileInfo[];
+}
+
+/**
+ * What resolving duplicate groups with a keep rule would do
+ */
+export interface ResolutionPreview {
+  /** The copies kept and removed per group; applyDuplicateResolution takes it back */
+  plan: DuplicateResolution[];
+  keep_count: number;
+  remove_count: number;
+  /** Bytes removing them would free */
+  reclaimable: number;
+  /** Copies kept only because they are protected */
+  protected_count: number;
+}
+
+/**
+ * What applyDuplicateResolution does with the copies a plan removes
+ */
+export type DedupeAction = "trash" | "delete" | "hardlink";
+
+/**
+ * Per-copy outcome of applyDuplicateResolution
+ */
+export interface DedupeResult {
+  path: string;
+  success: boolean;
+  error: string | null;
+  protected: boolean;
+  /** Bytes freed */
+  reclaimed: number;
+}
+
+/**
+ * Per-file outcome of reflinkDuplicates: the copy replaced by a
+ * copy-on-write clone of `source`
+ */
+export interface ReflinkResult {
+  path: string;
+  /** The kept copy it now shares its data with */
+  source: string;
+  success: boolean;
+  error?: string | null;
+  /** The file system cannot clone here; the file was left as it was */
+  unsupported?: boolean;
+  protected?: boolean;
+  /** Bytes no longer stored twice */
+  reclaimed: number;
+}
+
 /**
  * Kind of media a similar-group is made of. A group is homogeneous (all files
  * the same kind), so the UI can pick the right preview and "keep best"
@@ -55,6 +211,52 @@ export interface SimilarFile {
   height?: number | null;
 }
 
+/**
+ * Camera details from a photo's EXIF
+ */
+export interface ExifInfo {
+  camera_make: string | null;
+  camera_model: string | null;
+  /** As the camera wrote it, e.g. "2024:05:01 09:59:58" */
+  date_taken: string | null;
+  /** 1-8, see the EXIF Orientation tag */
+  orientation: number | null;
+  has_gps: boolean;
+}
+
+/**
+ * A video's length and its first video stream, as ffprobe reports them
+ */
+export interface VideoInfo {
+  duration_secs: number | null;
+  width: number | null;
+  height: number | null;
+  /** e.g. "h264" or "hevc" */
+  codec: string | null;
+  frame_rate: number | null;
+  /** Bits per second */
+  bit_rate: number | null;
+}
+
+/**
+ * What getFileDetails knows about a file. Timestamps are Unix seconds.
+ */
+export interface FileDetails {
+  path: string;
+  size: number;
+  /** "Image", "Video", "Document", "Archive" or "Other" */
+  file_type: string;
+  modified: number;
+  created: number | null;
+  accessed: number | null;
+  /** Pixel dimensions of an image, or of a video's picture */
+  width: number | null;
+  height: number | null;
+  exif: ExifInfo | null;
+  /** Null for other files, and for videos when ffprobe is missing */
+  video: VideoInfo | null;
+}
+
 /**
  * Similar media group (images today; videos once ffmpeg-backed similarity
  * lands). All files in a group share `media_kind`.
@@ -112,6 +314,476 @@ export interface FixExtensionResult {
   error?: string | null;
 }
 
+/**
+ * Files and bytes of one file category ("Image", "Video", "Document",
+ * "Archive" or "Other") in a recorded scan
+ */
+export interface CategoryTotal {
+  category: string;
+  file_count: number;
+  total_size: number;
+}
+
+/**
+ * One recorded scan of a path
+ */
+export interface StorageSnapshot {
+  scan_id: number;
+  /** Unix timestamp (seconds) of the scan */
+  scanned_at: number;
+  file_count: number;
+  total_size: number;
+  categories: CategoryTotal[];
+}
+
+/**
+ * Change in one category between two recorded scans
+ */
+export interface CategoryGrowth {
+  category: string;
+  file_count_change: number;
+  size_change: number;
+}
+
+/**
+ * Recorded scans of a path, oldest first. Scans are recorded whenever a path
+ * is scanned without filters.
+ */
+export interface StorageHistory {
+  path: string;
+  snapshots: StorageSnapshot[];
+  /**
+   * Change per category from the first to the latest snapshot; empty until
+   * the path has been scanned at least twice
+   */
+  growth: CategoryGrowth[];
+}
+
+/**
+ * A duplicate group as recorded by the last duplicate check of its folder
+ */
+export interface DuplicateRecord {
+  id: number;
+  hash: string;
+  file_paths: string[];
+  file_count: number;
+  total_size: number;
+  wasted_space: number;
+  /** Unix timestamp (seconds) of the check that found it */
+  created_at: number;
+}
+
+/**
+ * Space saved by one compression plugin
+ */
+export interface PluginSavings {
+  plugin_name: string;
+  file_count: number;
+  original_size: number;
+  compressed_size: number;
+  saved: number;
+}
+
+/**
+ * Space saved in one calendar month (UTC)
+ */
+export interface MonthlySavings {
+  /** e.g. "2026-10" */
+  month: string;
+  file_count: number;
+  saved: number;
+}
+
+/**
+ * Everything compression has saved, overall, per plugin (most first) and
+ * per month (oldest first). Clearing the compression history keeps it.
+ */
+export interface SavingsSummary {
+  file_count: number;
+  original_size: number;
+  compressed_size: number;
+  saved: number;
+  by_plugin: PluginSavings[];
+  by_month: MonthlySavings[];
+}
+
+/**
+ * A file as recorded by the last scan or duplicate check that saw it
+ */
+export interface FileRecord {
+  id: number;
+  path: string;
+  size: number;
+  /** Content hash, once a duplicate check has hashed the file */
+  hash?: string | null;
+  file_type: string;
+  /** Unix timestamp (seconds) of the file's last modification */
+  modified: number;
+  /** Unix timestamp (seconds) the file was first recorded */
+  created_at: number;
+}
+
+/**
+ * Which recorded files to list: every file or one category largest first,
+ * the files beneath a folder by path, or those last modified before a Unix
+ * timestamp oldest first
+ */
+export type FileQuery =
+  | { by: "largest" }
+  | { by: "type"; file_type: string }
+  | { by: "under"; path: string }
+  | { by: "older_than"; before: number };
+
+/**
+ * At most `limit` items after the first `offset`
+ */
+export interface Page {
+  offset: number;
+  limit: number;
+}
+
+/**
+ * A recorded scan as stored in the database
+ */
+export interface ScanRecord {
+  id: number;
+  path: string;
+  file_count: number;
+  total_size: number;
+  /** Seconds the scan took */
+  scan_time: number;
+  /** Unix timestamp (seconds) of the scan */
+  created_at: number;
+}
+
+/**
+ * A recorded scan with the space recorded duplicates waste under its path
+ */
+export interface ScanHistoryEntry {
+  scan: ScanRecord;
+  /** Bytes keeping one copy of each recorded duplicate group with a copy under the path would free */
+  reclaimable: number;
+}
+
+/**
+ * A file as a recorded scan saw it
+ */
+export interface ScanFileRecord {
+  path: string;
+  size: number;
+  /** File category, e.g. "Image" or "Other" */
+  file_type: string;
+}
+
+/**
+ * One recorded scan in detail
+ */
+export interface ScanDetails {
+  scan: ScanRecord;
+  /** Totals per file category, by category name */
+  categories: CategoryTotal[];
+  /** The largest files it saw (up to 10), largest first */
+  largest: ScanFileRecord[];
+  /** Recorded duplicate groups with a copy under the scanned path, most wasted space first */
+  duplicates: DuplicateRecord[];
+}
+
+/**
+ * A file that differs between two scans; a size of 0 on one side means it
+ * was absent there
+ */
+export interface FileChange {
+  path: string;
+  old_size: number;
+  new_size: number;
+}
+
+/**
+ * Files added, removed or resized between two recorded scans, largest change
+ * first, with the change per category
+ */
+export interface ScanDiff {
+  from: ScanRecord;
+  to: ScanRecord;
+  added: FileChange[];
+  removed: FileChange[];
+  /** Files present in both scans whose size changed, either way */
+  resized: FileChange[];
+  categories: CategoryGrowth[];
+  /** Change in total size, negative when space was freed */
+  size_change: number;
+}
+
+/**
+ * A directory in a treemap: the total size of everything beneath it, split
+ * by file type, and its subdirectories largest first
+ */
+export interface TreemapNode {
+  /** Last path component (the whole path for a root without one) */
+  name: string;
+  path: string;
+  size: number;
+  /** Files anywhere in the subtree */
+  file_count: number;
+  /** Size per file category ("Image", "Video", ...), largest first */
+  types: CategoryTotal[];
+  /** Empty beyond the requested depth */
+  children: TreemapNode[];
+}
+
+/**
+ * A directory and the total size of everything beneath it
+ */
+export interface DirectorySize {
+  path: string;
+  total_size: number;
+  /** Files anywhere in the subtree */
+  file_count: number;
+}
+
+/**
+ * A file neither modified nor accessed since a cutoff (Unix seconds)
+ */
+export interface StaleFile {
+  path: string;
+  size: number;
+  modified: number;
+  /** Last access time; null where the platform does not report it */
+  accessed?: number | null;
+}
+
+/**
+ * Stale files sharing a parent directory, largest first
+ */
+export interface StaleDirectory {
+  path: string;
+  file_count: number;
+  total_size: number;
+  files: StaleFile[];
+}
+
+/**
+ * Files untouched for a number of days, grouped by directory
+ */
+export interface StaleFilesReport {
+  /** Unix timestamp (seconds) the files were last used before */
+  cutoff: number;
+  file_count: number;
+  /** Bytes archiving or deleting every stale file would free */
+  total_size: number;
+  /** Directories with stale files, largest total first */
+  directories: StaleDirectory[];
+}
+
+/**
+ * Folders whose whole trees are identical, from findDuplicateDirectories
+ */
+export interface DuplicateDirectoryGroup {
+  hash: string;
+  /** Every copy of the tree, sorted by path */
+  directories: string[];
+  /** Files in each copy */
+  file_count: number;
+  /** Bytes in each copy */
+  size: number;
+  /** Bytes freed by deleting all copies but one */
+  wasted_space: number;
+}
+
+/**
+ * A kind of application cache or build output, from scanAppCaches
+ */
+export type CacheCategory =
+  | "browser"
+  | "npm"
+  | "pip"
+  | "cargo"
+  | "node_modules"
+  | "target"
+  | "thumbnails";
+
+/**
+ * One cache folder or build output folder that can be removed
+ */
+export interface CacheLocation {
+  category: CacheCategory;
+  path: string;
+  size: number;
+  file_count: number;
+}
+
+/**
+ * The locations of one cache category
+ */
+export interface CacheCategoryReport {
+  category: CacheCategory;
+  /** e.g. "Rust target folders" */
+  label: string;
+  /** Largest first */
+  locations: CacheLocation[];
+  size: number;
+  file_count: number;
+}
+
+/**
+ * What scanAppCaches found: categories with something to reclaim, largest
+ * first
+ */
+export interface AppCacheReport {
+  categories: CacheCategoryReport[];
+  total_size: number;
+}
+
+/**
+ * A kind of build output, from findDevArtifacts
+ */
+export type ArtifactKind = "target" | "node_modules" | "dist" | "venv";
+
+/**
+ * One build output folder of a project
+ */
+export interface BuildArtifact {
+  kind: ArtifactKind;
+  path: string;
+  size: number;
+  file_count: number;
+  /** When a file in it was last written (Unix seconds) */
+  last_build: number | null;
+}
+
+/**
+ * A project folder and its build output
+ */
+export interface DevProject {
+  root: string;
+  /** What makes it a project, e.g. ["Cargo.toml", ".git"] */
+  markers: string[];
+  /** When one of its own files or its git checkout last changed (Unix seconds) */
+  last_activity: number | null;
+  /** Largest first */
+  artifacts: BuildArtifact[];
+  size: number;
+  /** Untouched for `untouched_months`: its build output can go */
+  suggested: boolean;
+}
+
+/**
+ * What findDevArtifacts found: suggested projects first, then the largest
+ */
+export interface DevArtifactReport {
+  projects: DevProject[];
+  total_size: number;
+  /** Bytes in the build output of suggested projects */
+  reclaimable: number;
+  untouched_months: number;
+}
+
+/**
+ * An indexed reference volume, e.g. a NAS (mirrors the db's ReferenceRoot)
+ */
+export interface ReferenceRoot {
+  path: string;
+  file_count: number;
+  total_size: number;
+  /** Unix timestamp (seconds) of the latest indexing */
+  indexed_at: number;
+}
+
+/**
+ * A local file with an identical copy on a reference volume
+ */
+export interface ReferenceMatch {
+  path: string;
+  size: number;
+  hash: string;
+  /** The indexed copy */
+  reference_path: string;
+}
+
+/**
+ * Local files checked against every indexed reference volume, from
+ * checkAgainstReference
+ */
+export interface ReferenceCheck {
+  /** Files safe to delete locally, largest first */
+  matched: ReferenceMatch[];
+  matched_size: number;
+  /** Files with no copy on any reference volume */
+  unmatched_count: number;
+  unmatched_size: number;
+  /** The volumes checked against */
+  references: ReferenceRoot[];
+}
+
+/**
+ * Which results an ignored group applies to
+ */
+export type IgnoreKind = "duplicate" | "similar";
+
+/**
+ * A duplicate or similar group kept on purpose (mirrors the db's
+ * IgnoredGroupRecord). Scans leave it out until it gains a file or, for
+ * duplicates, its content changes.
+ */
+export interface IgnoredGroup {
+  id: number;
+  kind: IgnoreKind;
+  /** Content hash of a duplicate group; null for similar groups */
+  hash: string | null;
+  paths: string[];
+  /** Unix timestamp (seconds) it was ignored */
+  created_at: number;
+}
+
+/** What a tag is put on: a file by path, a duplicate group by content hash */
+export type TagTarget =
+  | { kind: "file"; path: string }
+  | { kind: "duplicate_group"; hash: string };
+
+/** A tag on a file or duplicate group (mirrors the db's TagRecord) */
+export interface TagRecord {
+  id: number;
+  kind: TagTarget["kind"];
+  /** Absolute path of a file, content hash of a duplicate group */
+  target: string;
+  /** Lowercase, as stored */
+  tag: string;
+  /** Unix timestamp (seconds) it was tagged */
+  created_at: number;
+}
+
+/** A tag in use and how many files and duplicate groups carry it */
+export interface TagCount {
+  tag: string;
+  files: number;
+  groups: number;
+}
+
+/**
+ * Archive container: "7z" (LZMA2) is slower but usually smaller than "zip",
+ * especially for many similar files; "tar.zst" keeps permissions and
+ * symlinks but cannot be password protected
+ */
+export type ArchiveFormat = "zip" | "7z" | "tar.zst";
+
+/**
+ * A directory moved to cold storage by archiveDirectory: the original was
+ * deleted after its archive was verified
+ */
+export interface ArchiveRecord {
+  id: number;
+  /** Where the directory used to be */
+  source_path: string;
+  archive_path: string;
+  /** Archive format extension, e.g. "tar.zst" */
+  format: string;
+  original_size: number;
+  archive_size: number;
+  file_count: number;
+  /** Unix timestamp (seconds) */
+  created_at: number;
+}
+
 /**
  * Hash algorithm used for duplicate detection. Serialized by serde as the
  * bare variant name, so the strings must match the Rust enum exactly.
@@ -126,6 +798,16 @@ export interface ScanConfig {
   max_depth?: number | null;
   min_file_size: number;
   exclude_patterns: string[];
+  /** Hash files on network shares with fewer threads and retry failed reads */
+  network_aware?: boolean;
+  /** Files hashed at once on each network share */
+  network_parallelism?: number;
+  /** Retries after a read on a network share times out or drops */
+  network_retries?: number;
+  /** Wait before the first retry in ms, doubled for each further one */
+  network_retry_backoff_ms?: number;
+  /** Read-rate caps in bytes per second keyed by a share's mount point */
+  share_bandwidth?: Record<string, number>;
 }
 
 /**
@@ -140,13 +822,293 @@ export interface AppConfig {
   max_concurrent_tasks: number;
   hash_algorithm: HashAlgorithm;
   image_similarity_threshold: number;
-  default_delete_mode: "trash" | "permanent";
+  default_delete_mode: "trash" | "quarantine" | "permanent";
   default_compress_backup: boolean;
+  /** Refuse backup-less compression when a plugin can't verify its output */
+  require_output_verification: boolean;
+  /** Refuse plugins that replace original files in place */
+  safe_mode: boolean;
+  /** Copy EXIF/XMP/ICC metadata into converted images */
+  preserve_metadata: boolean;
+  /** Minimum savings (percent of the original) for a result to be kept */
+  min_savings_percent: number;
+  /** Per-file processing timeout in seconds; 0 = no limit */
+  file_timeout_secs: number;
+  /** Niceness (0-19) for external tool processes */
+  tool_nice_level?: number | null;
+  /** Address-space cap in MiB for external tool processes */
+  tool_max_memory_mb?: number | null;
+  /** Cap in bytes per second on file reads by background jobs; null = full speed */
+  background_io_limit?: number | null;
+  /** Run background jobs and their tools at low IO priority */
+  background_io_low_priority?: boolean;
+  /** Camera RAWs after JPEG conversion: handled like any original, or also archived */
+  raw_policy: "delete" | "archive";
+  /** Directories whose SVG (and optionally JSON/XML) may be minified in place; empty = off */
+  minify_dirs: string[];
+  /** Text assets smaller than this many bytes are left alone */
+  minify_min_size: number;
+  /** Also minify pretty-printed JSON and XML */
+  minify_data_files: boolean;
+  /** Folders delete, dedupe and compression never touch, besides system directories */
+  protected_paths: string[];
+  /** Where quarantined files are kept until restored or purged */
+  quarantine_dir: string;
+  /** Days a quarantined file can be restored before it is purged */
+  quarantine_days: number;
+  /** Explicit external tool locations keyed by tool name; others use PATH */
+  tool_paths: Record<string, string>;
   /** Per-plugin quality (0-100) keyed by plugin name; absent = built-in default */
   plugin_quality: Record<string, number>;
+  /** Plugins turned off by name; never picked for a file */
+  disabled_plugins?: string[];
+  /** Rules run by runCleanup, in order */
+  cleanup_rules: CleanupRule[];
+  /** Tasks the headless daemon runs on a timer */
+  schedules?: Schedule[];
+  /** Folders the headless daemon watches */
+  watch_rules?: WatchRule[];
+  /** Commands run before and after deletions, compressions and cleanups */
+  hooks?: Hook[];
+  /** Desktop notifications when background tasks finish */
+  notifications?: NotificationConfig;
+  daemon_log?: string;
+  daemon_socket?: string;
   scan: ScanConfig;
 }
 
+/**
+ * Settings to change with updateConfig: tables merge into the current ones,
+ * other values (lists included) replace them
+ */
+export type ConfigChanges = Partial<Omit<AppConfig, "scan">> & { scan?: Partial<ScanConfig> };
+
+/**
+ * What updateConfig saved
+ */
+export interface ConfigUpdate {
+  config: AppConfig;
+  /** Changed settings that only take effect once the app (or, for the daemon's, the daemon) restarts */
+  restart_required: string[];
+}
+
+/**
+ * When finished tasks show a desktop notification (mirrors the config's
+ * NotificationConfig)
+ */
+export interface NotificationConfig {
+  enabled: boolean;
+  on_success: boolean;
+  on_failure: boolean;
+  /** Quicker successes do not notify; failures always do */
+  min_duration_secs: number;
+  /** Kinds of task turned on or off; kinds not listed notify */
+  tasks: Partial<
+    Record<
+      | "scan"
+      | "duplicates"
+      | "similar"
+      | "empty"
+      | "compress"
+      | "delete"
+      | "cleanup"
+      | "maintenance",
+      boolean
+    >
+  >;
+}
+
+/**
+ * A command run around an operation (mirrors the config's Hook). It sees the
+ * operation in SPACE_SAVER_* environment variables; a failing "before" hook
+ * with on_failure "abort" stops the operation.
+ */
+export interface Hook {
+  /** Unique name, shown when it fails */
+  name: string;
+  operation: "delete" | "compress" | "cleanup";
+  when: "before" | "after";
+  command: string;
+  timeout_secs: number;
+  on_failure: "abort" | "warn" | "ignore";
+  enabled: boolean;
+}
+
+/**
+ * An automatic cleanup rule (mirrors the config's CleanupRule): files under
+ * `paths` matching `filter` and untouched for `older_than_days` get `action`
+ */
+export interface CleanupRule {
+  /** Unique name, used to pick rules to run */
+  name: string;
+  paths: string[];
+  /** Filter expression, e.g. "ext in (log,tmp)"; none matches every file */
+  filter?: string | null;
+  older_than_days?: number | null;
+  /** "delete" is permanent; "compress" works in place */
+  action: "delete" | "trash" | "archive" | "compress";
+  /** Where "archive" stores its archives (required for it) */
+  archive_dir?: string | null;
+  enabled: boolean;
+}
+
+/**
+ * A task the daemon runs every `every_minutes` (mirrors the config's Schedule)
+ */
+export interface Schedule {
+  name: string;
+  task: "scan" | "cleanup" | "maintenance";
+  every_minutes: number;
+  /** Folders a "scan" covers */
+  paths: string[];
+  /** Cleanup rules a "cleanup" runs; none runs them all */
+  rules: string[];
+  enabled: boolean;
+}
+
+/**
+ * What the daemon reports of a schedule it runs
+ */
+export interface ScheduleStatus {
+  name: string;
+  task: string;
+  every_minutes: number;
+  /** Unix seconds */
+  next_run: number;
+  last_run: number | null;
+  /** What the last run did, or why it failed */
+  last_result: string | null;
+  last_failed: boolean;
+  /** Queued or running; a run due meanwhile is skipped */
+  busy: boolean;
+}
+
+/**
+ * A schedule from the config, with the daemon's view of it
+ */
+export interface ScheduleEntry {
+  schedule: Schedule;
+  /** Null when no daemon is running, or it runs without this schedule (disabled, or added since it started) */
+  status: ScheduleStatus | null;
+}
+
+/**
+ * Outcome of runScheduleNow
+ */
+export interface ScheduleRun {
+  name: string;
+  /** Queued on the running daemon; the outcome then shows in listSchedules */
+  on_daemon: boolean;
+  /** What the run did, when it ran in the app */
+  result: string | null;
+}
+
+/**
+ * A run the daemon will start
+ */
+export interface UpcomingRun {
+  schedule: string;
+  task: string;
+  /** Unix seconds */
+  at: number;
+}
+
+/**
+ * Files appearing or changing under `paths` that match `filter` get `action`
+ * once unchanged for `settle_secs` (mirrors the config's WatchRule)
+ */
+export interface WatchRule {
+  name: string;
+  paths: string[];
+  filter?: string | null;
+  action: "delete" | "trash" | "archive" | "compress";
+  archive_dir?: string | null;
+  settle_secs: number;
+  enabled: boolean;
+}
+
+/**
+ * What a cleanup rule did, or would do, to one file
+ */
+export interface CleanupFile {
+  path: string;
+  size: number;
+  modified: number;
+  /** Bytes freed; 0 in a dry run and when the action failed */
+  freed: number;
+  error?: string | null;
+  protected?: boolean;
+}
+
+/**
+ * The files one cleanup rule matched and what became of them
+ */
+export interface CleanupRuleReport {
+  name: string;
+  action: string;
+  files: CleanupFile[];
+  matched_size: number;
+  freed: number;
+  /** Set when the rule could not run at all; its files are then empty */
+  error?: string | null;
+  /** The archive written by an "archive" rule */
+  archive_path?: string | null;
+}
+
+/**
+ * Plan (in a dry run) or outcome of running cleanup rules
+ */
+export interface CleanupReport {
+  dry_run: boolean;
+  rules: CleanupRuleReport[];
+  matched_count: number;
+  matched_size: number;
+  freed: number;
+}
+
+/**
+ * Where a drive lives: a local disk, a removable one (USB stick, SD card) or
+ * a network share
+ */
+export type DriveKind = "fixed" | "removable" | "network";
+
+/**
+ * A mounted drive or volume with its capacity, in bytes
+ */
+export interface DiskInfo {
+  /** Device or volume name, e.g. "/dev/sda1" or "Backup" */
+  name: string;
+  mount_point: string;
+  /** File system type, e.g. "ext4", "NTFS" or "apfs" */
+  file_system: string;
+  total_space: number;
+  available_space: number;
+  used_space: number;
+  kind: DriveKind;
+  read_only: boolean;
+}
+
+/**
+ * What a path dropped for scanning turned out to be (mirrors the service's
+ * PathCheck)
+ */
+export interface PathCheck {
+  path: string;
+  exists: boolean;
+  is_dir: boolean;
+  /** Whether the file can be opened or the directory listed */
+  readable: boolean;
+  /** The drive holding the path; null when it does not exist */
+  drive_kind: DriveKind | null;
+  mount_point: string | null;
+  /** Files under a directory (1 for a file), estimated unless exact */
+  estimated_files: number | null;
+  /** Whether the probe saw the whole tree */
+  exact: boolean;
+  /** Why scanning this path may fail or take long, for the user */
+  warnings: string[];
+}
+
 /**
  * Status of an optional external command-line tool (ffmpeg etc.) on PATH.
  */
@@ -158,6 +1120,28 @@ export interface ToolStatus {
   purpose: string;
 }
 
+/**
+ * Health check of one external tool a compression plugin can run
+ */
+export interface ToolCheck {
+  name: string;
+  /** Configured path, or the bare name looked up on PATH */
+  program: string;
+  available: boolean;
+  version?: string | null;
+  error?: string | null;
+}
+
+/**
+ * Whether a compression plugin can run with the tools on this machine
+ */
+export interface PluginRequirements {
+  plugin_name: string;
+  tools: ToolCheck[];
+  ready: boolean;
+  message?: string | null;
+}
+
 /**
  * Storage statistics
  */
@@ -171,3 +1155,182 @@ export interface StorageStats {
   others: number;
   empty_files: number;
 }
+
+/**
+ * Progress of a background job, as emitted on `<kind>://progress` events
+ * (mirrors the service's ProgressUpdate enum)
+ */
+export type ProgressUpdate =
+  | { Started: { task_type: string; total_items: number } }
+  | {
+      Progress: {
+        current: number;
+        total: number;
+        message: string;
+        /** Bytes of total_bytes processed so far; 0 when not counted */
+        bytes?: number;
+        total_bytes?: number;
+      };
+    }
+  | { Completed: { message: string } }
+  | { Failed: { error: string } }
+  | "Cancelled";
+
+/**
+ * A file moved into quarantine instead of being deleted (mirrors the db's
+ * QuarantineRecord)
+ */
+export interface QuarantineRecord {
+  id: number;
+  /** Where the file used to be, and is restored to */
+  original_path: string;
+  quarantine_path: string;
+  size: number;
+  /** Unix timestamps (seconds) */
+  quarantined_at: number;
+  /** After this the file is purged for good */
+  expires_at: number;
+}
+
+/**
+ * A change that can be undone (mirrors the db's RestorePoint)
+ */
+export interface RestorePoint {
+  id: number;
+  kind: "compression" | "trash";
+  /** Where the file was before the change, and goes back to */
+  path: string;
+  /** The original kept by a compression */
+  backup_path: string | null;
+  /** The file a compression produced, removed when the original is restored */
+  output_path: string | null;
+  size: number;
+  operation_id: number | null;
+  /** Unix timestamp (seconds) */
+  created_at: number;
+}
+
+/**
+ * A compression that kept its original, and can be undone (mirrors the
+ * service's CompressionBackup)
+ */
+export interface CompressionBackup {
+  /** The restore point, what restoreCompression takes */
+  id: number;
+  /** The original's path, where restoring puts it back */
+  path: string;
+  backup_path: string;
+  output_path: string | null;
+  /** Null when the compression is no longer in the savings ledger */
+  plugin_name: string | null;
+  original_size: number;
+  compressed_size: number | null;
+  /** Bytes the compression saves; 0 when the output came out larger */
+  saved: number;
+  /** A backup no longer on disk cannot be restored */
+  backup_exists: boolean;
+  /** Unix timestamp (seconds) */
+  created_at: number;
+}
+
+/**
+ * Backups removed by purgeBackups, and those that could not be
+ */
+export interface BackupPurge {
+  purged: CompressionBackup[];
+  /** Bytes the removed backups took */
+  freed: number;
+  failed: FileError[];
+}
+
+/**
+ * What a background job works on (mirrors the service's TaskType enum)
+ */
+export type TaskType =
+  | { Scan: string[] }
+  | { FindDuplicates: string[] }
+  | { FindSimilarImages: [string[], number] }
+  | { CompressFiles: string[] }
+  | { DeleteFiles: string[] }
+  /** Cleanup rules run over their folders */
+  | { Cleanup: string[] }
+  | { CleanEmpty: string }
+  /** Pruning and vacuuming the app database, started at launch when due */
+  | "Maintenance";
+
+/**
+ * Where a background job is (mirrors the service's TaskStatus enum)
+ */
+export type TaskStatus = "Pending" | "Running" | "Completed" | { Failed: string } | "Cancelled";
+
+/**
+ * Snapshot of a background job, from listJobs/getJobStatus
+ */
+export interface JobInfo {
+  id: number;
+  task_type: TaskType;
+  status: TaskStatus;
+  /** Items done and in total, from the latest progress update */
+  current: number;
+  total: number;
+  /** Latest progress message */
+  message: string;
+  /** Unix timestamp (seconds) the job started */
+  started_at: number;
+}
+
+/**
+ * A job that was still running when the app closed
+ */
+export interface InterruptedJob {
+  id: number;
+  task_type: TaskType;
+  /** Items done and in total when it stopped */
+  current: number;
+  total: number;
+  /** Latest progress message, e.g. the file in hand */
+  message: string;
+  /** False when the job had not stopped by the end of the grace period */
+  stopped: boolean;
+}
+
+/**
+ * What closing the app interrupted, from takeShutdownReport
+ */
+export interface ShutdownReport {
+  /** Unix timestamp (seconds) of the shutdown */
+  at: number;
+  interrupted: InterruptedJob[];
+  /** Partial outputs (archives being written) that were removed */
+  removed: string[];
+}
+
+/**
+ * What kind of failure a backend error is. Mirrors the Rust `ErrorCode`.
+ */
+export type ErrorCode =
+  | "io"
+  | "not_found"
+  | "permission_denied"
+  | "cancelled"
+  | "timed_out"
+  | "protected"
+  | "invalid_input"
+  | "database"
+  | "config"
+  | "file_operation"
+  | "hash"
+  | "compression"
+  | "image"
+  | "video"
+  | "task"
+  | "serialization"
+  | "unknown";
+
+/**
+ * A failed command or job, as the backend sends it
+ */
+export interface ErrorInfo {
+  code: ErrorCode;
+  message: string;
+}
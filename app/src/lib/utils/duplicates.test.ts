@@ -4,6 +4,7 @@ import {
   fullySelectedGroups,
   keepOnePerGroup,
   applyDeletions,
+  keepByRule,
 } from './duplicates';
 import type { DuplicateGroup } from '../types';
 
@@ -37,6 +38,33 @@ describe('duplicates selection utils', () => {
     ]),
   ];
 
+  describe('keepByRule', () => {
+    it('mirrors the single-keeper strategies', () => {
+      expect(keepByRule(groups[0], { strategy: 'newest' }).map((f) => f.path)).toEqual([
+        '/docs/new.txt',
+      ]);
+      expect(keepByRule(groups[0], { strategy: 'shortest_path' }).map((f) => f.path)).toEqual([
+        '/docs/old.txt',
+      ]);
+      expect(keepByRule(groups[0], { strategy: 'first_path' }).map((f) => f.path)).toEqual([
+        '/backup/deeply/nested/copy.txt',
+      ]);
+    });
+
+    it('keeps every copy inside the directory, component-wise', () => {
+      const g = group('ccc', [
+        ['/archive/a.pdf', 1, 0],
+        ['/archive/old/a.pdf', 1, 0],
+        ['/archive2/a.pdf', 1, 0],
+      ]);
+      expect(keepByRule(g, { strategy: 'in_directory', prefix: '/archive/' }).map((f) => f.path)).toEqual([
+        '/archive/a.pdf',
+        '/archive/old/a.pdf',
+      ]);
+      expect(keepByRule(g, { strategy: 'in_directory', prefix: '/elsewhere' })).toEqual([]);
+    });
+  });
+
   describe('selectDuplicates', () => {
     it('keeps the newest file per group', () => {
       const toDelete = selectDuplicates(groups, 'newest');
@@ -62,7 +90,7 @@ describe('duplicates selection utils', () => {
     });
 
     it('always leaves at least one file unselected per group', () => {
-      for (const strategy of ['newest', 'oldest', 'shortest-path'] as const) {
+      for (const strategy of ['newest', 'oldest', 'shortest-path', 'first-path'] as const) {
         const toDelete = selectDuplicates(groups, strategy);
         for (const g of groups) {
           const remaining = g.files.filter((f) => !toDelete.has(f.path));
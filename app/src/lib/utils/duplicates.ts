@@ -2,10 +2,10 @@
  * Selection helpers for the duplicates page
  */
 
-import type { DuplicateGroup } from '../types';
+import type { DuplicateGroup, FileInfo, KeepRule } from '../types';
 
 /** Which copy to keep when auto-selecting duplicates for deletion */
-export type KeepStrategy = 'newest' | 'oldest' | 'shortest-path';
+export type KeepStrategy = 'newest' | 'oldest' | 'shortest-path' | 'first-path';
 
 /**
  * Auto-select files for deletion: in every group, keep exactly one file
@@ -26,7 +26,9 @@ export function selectDuplicates(groups: DuplicateGroup[], strategy: KeepStrateg
           ? candidate.modified > current.modified
           : strategy === 'oldest'
             ? candidate.modified < current.modified
-            : candidate.path.length < current.path.length;
+            : strategy === 'first-path'
+              ? candidate.path < current.path
+              : candidate.path.length < current.path.length;
       if (better) keepIndex = i;
     }
 
@@ -38,6 +40,27 @@ export function selectDuplicates(groups: DuplicateGroup[], strategy: KeepStrateg
   return toDelete;
 }
 
+/**
+ * The copies of `group` to keep under `rule`, mirroring the backend's keep
+ * strategies. An empty result means the group is left untouched.
+ */
+export function keepByRule(group: DuplicateGroup, rule: KeepRule): FileInfo[] {
+  if (rule.strategy === 'in_directory') {
+    const prefix = rule.prefix.replace(/[\\/]+$/, '');
+    return group.files.filter(
+      (f) => f.path === prefix || f.path.startsWith(`${prefix}/`) || f.path.startsWith(`${prefix}\\`)
+    );
+  }
+  const strategy: KeepStrategy =
+    rule.strategy === 'shortest_path'
+      ? 'shortest-path'
+      : rule.strategy === 'first_path'
+        ? 'first-path'
+        : rule.strategy;
+  const toDelete = selectDuplicates([group], strategy);
+  return group.files.filter((f) => !toDelete.has(f.path));
+}
+
 /**
  * Groups in which EVERY file is selected — deleting the selection would
  * destroy all copies of that content.
@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { formatSize, formatDate, formatDuration, percentage } from './format';
+import { formatSize, formatDate, formatDuration, percentage, formatProgress } from './format';
 
 describe('formatSize', () => {
   it('formats bytes correctly', () => {
@@ -70,3 +70,15 @@ describe('formatDate', () => {
     expect(formatted).not.toBe('');
   });
 });
+
+describe('formatProgress', () => {
+  it('describes each kind of update', () => {
+    expect(formatProgress({ Started: { task_type: 'scan', total_items: 2 } })).toBe('Starting…');
+    expect(
+      formatProgress({ Progress: { current: 3, total: 10, message: 'Hashing /a.jpg' } })
+    ).toBe('Hashing /a.jpg (3/10)');
+    expect(formatProgress({ Progress: { current: 0, total: 0, message: 'Working' } })).toBe('Working');
+    expect(formatProgress({ Failed: { error: 'Permission denied' } })).toBe('Permission denied');
+    expect(formatProgress('Cancelled')).toBe('Cancelled');
+  });
+});
@@ -1,3 +1,5 @@
+import type { ProgressUpdate } from '../types';
+
 /**
  * Format file size to human-readable format
  */
@@ -69,3 +71,18 @@ export function getFileName(path: string): string {
   const parts = path.split(/[\\/]/);
   return parts[parts.length - 1];
 }
+
+/**
+ * One-line status for a background job's progress update, e.g.
+ * "Hashing /photos/a.jpg (3/10)"
+ */
+export function formatProgress(update: ProgressUpdate): string {
+  if (update === 'Cancelled') return 'Cancelled';
+  if ('Started' in update) return 'Starting…';
+  if ('Progress' in update) {
+    const { current, total, message } = update.Progress;
+    return total > 0 ? `${message} (${current}/${total})` : message;
+  }
+  if ('Completed' in update) return update.Completed.message;
+  return update.Failed.error;
+}
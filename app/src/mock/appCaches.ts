@@ -0,0 +1,79 @@
+import type { AppCacheReport, CacheCategory, CacheLocation, FileError } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+const LABELS: Record<CacheCategory, string> = {
+  browser: "Browser caches",
+  npm: "npm and yarn caches",
+  pip: "pip cache",
+  cargo: "Cargo registry",
+  node_modules: "node_modules folders",
+  target: "Rust target folders",
+  thumbnails: "Thumbnail caches",
+};
+
+// Mock caches in the usual places plus build output beneath each root.
+// Roots containing "empty-dir" hold no build output; when every root does,
+// nothing is found at all (empty-state UI). A root containing "locked" fails
+// the scan with a permission error and one containing "missing" as a folder
+// that does not exist.
+export function mockScanAppCaches(roots: string[]): Promise<AppCacheReport> {
+  const locked = roots.find((root) => root.includes("locked"));
+  if (locked) {
+    return Promise.reject(new ServiceError("permission_denied", "Permission denied (os error 13)"));
+  }
+  const missing = roots.find((root) => root.includes("missing"));
+  if (missing) {
+    return Promise.reject(new ServiceError("not_found", `Folder not found: ${missing}`));
+  }
+  if (roots.length > 0 && roots.every((root) => root.includes("empty-dir"))) {
+    return Promise.resolve({ categories: [], total_size: 0 });
+  }
+  const locations: CacheLocation[] = [
+    { category: "browser", path: "/home/user/.cache/mozilla/firefox", size: 734003200, file_count: 5120 },
+    { category: "npm", path: "/home/user/.npm/_cacache", size: 1288490188, file_count: 23310 },
+    { category: "cargo", path: "/home/user/.cargo/registry/cache", size: 2147483648, file_count: 1840 },
+    { category: "thumbnails", path: "/home/user/.cache/thumbnails", size: 94371840, file_count: 3902 },
+  ];
+  for (const root of roots.filter((root) => !root.includes("empty-dir"))) {
+    locations.push(
+      { category: "node_modules", path: `${root}/web/node_modules`, size: 419430400, file_count: 41022 },
+      { category: "target", path: `${root}/tool/target`, size: 3221225472, file_count: 6210 },
+    );
+  }
+
+  const categories = (Object.keys(LABELS) as CacheCategory[])
+    .map((category) => {
+      const found = locations
+        .filter((l) => l.category === category)
+        .sort((a, b) => b.size - a.size);
+      return {
+        category,
+        label: LABELS[category],
+        locations: found,
+        size: found.reduce((sum, l) => sum + l.size, 0),
+        file_count: found.reduce((sum, l) => sum + l.file_count, 0),
+      };
+    })
+    .filter((c) => c.locations.length > 0)
+    .sort((a, b) => b.size - a.size);
+  const report = {
+    categories,
+    total_size: categories.reduce((sum, c) => sum + c.size, 0),
+  };
+  return new Promise((resolve) => {
+    setTimeout(() => resolve(report), 300);
+  });
+}
+
+// Why cleaning would leave a reported location alone: one whose path
+// contains "locked" cannot be removed, one containing "missing" is gone
+// since the scan. Worded like the backend.
+export function mockCacheRefusal(path: string): FileError | null {
+  if (path.includes("locked")) {
+    return { path, error: "IO error: Permission denied (os error 13)", code: "permission_denied" };
+  }
+  if (path.includes("missing")) {
+    return { path, error: `No longer a folder: ${path}`, code: "not_found" };
+  }
+  return null;
+}
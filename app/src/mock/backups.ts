@@ -0,0 +1,98 @@
+import type { BackupPurge, CompressionBackup, RestorePoint } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for the backend's compression backups: two older
+// compressions so the browser is never empty, plus every file the mock
+// in-place compression backs up during the session. Backups live in memory
+// only; restoring or purging one just forgets it.
+const DAY = 86_400;
+
+function seeded(): CompressionBackup[] {
+  const now = Math.floor(Date.now() / 1000);
+  return [
+    {
+      id: 2,
+      path: "/home/user/Pictures/trip/IMG_0042.cr2",
+      backup_path: "/home/user/Pictures/trip/IMG_0042.cr2.bak",
+      output_path: "/home/user/Pictures/trip/IMG_0042.jpg",
+      plugin_name: "RAW to JPEG",
+      original_size: 25165824,
+      compressed_size: 6291456,
+      saved: 18874368,
+      backup_exists: true,
+      created_at: now - 5 * DAY,
+    },
+    {
+      id: 1,
+      path: "/home/user/Pictures/screenshot.png",
+      backup_path: "/home/user/Pictures/screenshot.png.bak",
+      output_path: "/home/user/Pictures/screenshot.webp",
+      plugin_name: "WebP Converter",
+      original_size: 1024000,
+      compressed_size: 716800,
+      saved: 307200,
+      backup_exists: true,
+      created_at: now - 40 * DAY,
+    },
+  ];
+}
+
+const backups: CompressionBackup[] = seeded();
+let nextId = backups.length;
+
+const UNITS: Record<string, number> = { d: DAY, w: 7 * DAY, mo: 30 * DAY, y: 365 * DAY };
+
+/**
+ * An ISO date or a duration before now ("30d", "2w", "6mo", "1y"), as Unix
+ * seconds; the backend accepts a few more spellings
+ */
+function parseTimeBound(value: string, now: number): number {
+  const duration = /^(\d+)\s*(d|w|mo|y)$/.exec(value.trim().toLowerCase());
+  if (duration) return now - Number(duration[1]) * UNITS[duration[2]];
+  const date = Date.parse(value.trim());
+  if (!Number.isNaN(date)) return Math.floor(date / 1000);
+  throw new ServiceError("invalid_input", `Invalid date or duration: ${value}`);
+}
+
+export const mockBackups = {
+  record(path: string, output: string, plugin_name: string, original_size: number, compressed_size: number): void {
+    nextId += 1;
+    backups.unshift({
+      id: nextId,
+      path,
+      backup_path: `${path}.bak`,
+      output_path: output,
+      plugin_name,
+      original_size,
+      compressed_size,
+      saved: Math.max(original_size - compressed_size, 0),
+      backup_exists: true,
+      created_at: Math.floor(Date.now() / 1000),
+    });
+  },
+  /** Newest first, like the backend */
+  list(): CompressionBackup[] {
+    return [...backups];
+  },
+  restore(id: number): RestorePoint {
+    const index = backups.findIndex((b) => b.id === id);
+    if (index < 0) throw new ServiceError("not_found", `Restore point not found: ${id}`);
+    const [backup] = backups.splice(index, 1);
+    return {
+      id: backup.id,
+      kind: "compression",
+      path: backup.path,
+      backup_path: backup.backup_path,
+      output_path: backup.output_path,
+      size: backup.original_size,
+      operation_id: backup.id,
+      created_at: backup.created_at,
+    };
+  },
+  purge(olderThan: string): BackupPurge {
+    const before = parseTimeBound(olderThan, Math.floor(Date.now() / 1000));
+    const purged = backups.filter((b) => b.created_at < before);
+    for (const backup of purged) backups.splice(backups.indexOf(backup), 1);
+    return { purged, freed: purged.reduce((sum, b) => sum + b.original_size, 0), failed: [] };
+  },
+};
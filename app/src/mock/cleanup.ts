@@ -0,0 +1,75 @@
+import type { CleanupFile, CleanupReport, CleanupRule, CleanupRuleReport } from "../lib/types";
+
+const DAY = 86_400;
+
+/**
+ * Mirrors CleanupRule::validate plus the unique-name check in
+ * Config::validate, returning the backend's rejection string or null.
+ */
+export function validateCleanupRules(rules: CleanupRule[]): string | null {
+  const names = new Set<string>();
+  for (const rule of rules) {
+    if (!rule.name.trim()) return "Cleanup rules need a name";
+    if (rule.paths.length === 0) return `Cleanup rule '${rule.name}' has no folders`;
+    if (!["delete", "trash", "archive", "compress"].includes(rule.action)) {
+      return `Cleanup rule '${rule.name}': action must be 'delete', 'trash', 'archive' or 'compress', got '${rule.action}'`;
+    }
+    if (rule.action === "archive" && !rule.archive_dir) {
+      return `Cleanup rule '${rule.name}' archives files but has no archive_dir`;
+    }
+    if (names.has(rule.name)) {
+      return `Cleanup rule names must be unique, '${rule.name}' is used twice`;
+    }
+    names.add(rule.name);
+  }
+  return null;
+}
+
+// Mock run of cleanup rules: every folder holds the same few files, all
+// matching. Folders containing "missing" fail like a folder that is not
+// there; applying an action frees each file's size (compress frees 40%).
+export function mockRunCleanup(rules: CleanupRule[], dryRun: boolean): CleanupReport {
+  const now = Math.floor(Date.now() / 1000);
+  const reports: CleanupRuleReport[] = rules.filter(r => r.enabled).map((rule) => {
+    const missing = rule.paths.find(p => p.includes("missing"));
+    if (missing) {
+      return {
+        name: rule.name,
+        action: rule.action,
+        files: [],
+        matched_size: 0,
+        freed: 0,
+        error: `Folder not found: ${missing}`,
+        archive_path: null,
+      };
+    }
+    const files: CleanupFile[] = rule.paths.flatMap(path => [
+      { path: `${path}/build.log`, size: 5242880 },
+      { path: `${path}/installer.dmg`, size: 314572800 },
+    ]).map(file => ({
+      ...file,
+      modified: now - ((rule.older_than_days ?? 0) + 10) * DAY,
+      freed: dryRun ? 0 : rule.action === "compress" ? Math.floor(file.size * 0.4) : file.size,
+      error: null,
+      protected: false,
+    }));
+    return {
+      name: rule.name,
+      action: rule.action,
+      files,
+      matched_size: files.reduce((sum, f) => sum + f.size, 0),
+      freed: files.reduce((sum, f) => sum + f.freed, 0),
+      error: null,
+      archive_path: !dryRun && rule.action === "archive"
+        ? `${rule.archive_dir}/${rule.name.replace(/[^\p{L}\p{N}]/gu, "-")}-${now}.tar.zst`
+        : null,
+    };
+  });
+  return {
+    dry_run: dryRun,
+    rules: reports,
+    matched_count: reports.reduce((sum, r) => sum + r.files.length, 0),
+    matched_size: reports.reduce((sum, r) => sum + r.matched_size, 0),
+    freed: reports.reduce((sum, r) => sum + r.freed, 0),
+  };
+}
@@ -0,0 +1,83 @@
+// Web-mode stand-in for the backend's registry of in-flight compression runs:
+// each compressFilesInPlace call registers while its simulated work is
+// pending, and cancelCompression flags every registered run so it reports
+// its files as "cancelled", like the backend's cancellation tokens.
+export interface MockCompressionRun {
+  cancelled: boolean;
+}
+
+const runs = new Set<MockCompressionRun>();
+
+export const mockCompressionRuns = {
+  register(): MockCompressionRun {
+    const run = { cancelled: false };
+    runs.add(run);
+    return run;
+  },
+  unregister(run: MockCompressionRun): void {
+    runs.delete(run);
+  },
+  /** Returns how many runs were signalled, like the backend command */
+  cancelAll(): number {
+    for (const run of runs) {
+      run.cancelled = true;
+    }
+    return runs.size;
+  },
+};
+
+export interface MockCompressionProgress {
+  path: string;
+  current: number;
+  total: number;
+  message?: string | null;
+}
+
+type ProgressListener = (progress: MockCompressionProgress) => void;
+
+const progressListeners = new Set<ProgressListener>();
+
+// Web-mode stand-in for the backend's "compress://file-progress" events
+export const mockCompressionProgress = {
+  subscribe(listener: ProgressListener): () => void {
+    progressListeners.add(listener);
+    return () => {
+      progressListeners.delete(listener);
+    };
+  },
+  emit(progress: MockCompressionProgress): void {
+    for (const listener of progressListeners) {
+      listener(progress);
+    }
+  },
+};
+
+export interface MockCompressionFileEvent {
+  job_id: number;
+  path: string;
+  index: number;
+  total: number;
+  status: string;
+  savings: number | null;
+  reason: string | null;
+  percent: number;
+}
+
+type FileListener = (event: MockCompressionFileEvent) => void;
+
+const fileListeners = new Set<FileListener>();
+
+// Web-mode stand-in for the backend's "compress://file" events
+export const mockCompressionFiles = {
+  subscribe(listener: FileListener): () => void {
+    fileListeners.add(listener);
+    return () => {
+      fileListeners.delete(listener);
+    };
+  },
+  emit(event: MockCompressionFileEvent): void {
+    for (const listener of fileListeners) {
+      listener(event);
+    }
+  },
+};
@@ -1,4 +1,4 @@
-import type { AppConfig } from '$lib/types';
+import type { AppConfig, ConfigChanges } from '$lib/types';
 import { loadFromStorage, saveToStorage } from '$lib/utils/storage';
 
 const CONFIG_KEY = 'space-saver:config';
@@ -19,12 +19,43 @@ export function defaultConfig(): AppConfig {
     image_similarity_threshold: 0.9,
     default_delete_mode: 'trash',
     default_compress_backup: true,
+    require_output_verification: true,
+    safe_mode: false,
+    preserve_metadata: true,
+    min_savings_percent: 5,
+    file_timeout_secs: 300,
+    tool_nice_level: null,
+    tool_max_memory_mb: null,
+    background_io_limit: null,
+    background_io_low_priority: false,
+    raw_policy: 'delete',
+    minify_dirs: [],
+    minify_min_size: 4096,
+    minify_data_files: false,
+    protected_paths: [],
+    quarantine_dir: '/home/demo/.local/share/Space-Saver/quarantine',
+    quarantine_days: 30,
+    tool_paths: {},
     plugin_quality: {},
+    cleanup_rules: [],
+    hooks: [],
+    notifications: {
+      enabled: true,
+      on_success: true,
+      on_failure: true,
+      min_duration_secs: 10,
+      tasks: {},
+    },
     scan: {
       follow_links: false,
       max_depth: null,
       min_file_size: 0,
       exclude_patterns: ['*.tmp', '*.cache', '.git/*', 'node_modules/*'],
+      network_aware: true,
+      network_parallelism: 2,
+      network_retries: 3,
+      network_retry_backoff_ms: 500,
+      share_bandwidth: {},
     },
   };
 }
@@ -38,6 +69,21 @@ export function setMockConfig(config: AppConfig): AppConfig {
   return config;
 }
 
+/** `config` with `changes` merged in: tables merge, other values replace */
+export function mergeConfig(config: AppConfig, changes: ConfigChanges): AppConfig {
+  const merge = (current: unknown, change: unknown): unknown => {
+    const isTable = (value: unknown) =>
+      typeof value === 'object' && value !== null && !Array.isArray(value);
+    if (!isTable(current) || !isTable(change)) return change;
+    const merged: Record<string, unknown> = { ...(current as Record<string, unknown>) };
+    for (const [key, value] of Object.entries(change as Record<string, unknown>)) {
+      merged[key] = merge(merged[key], value);
+    }
+    return merged;
+  };
+  return merge(config, changes) as AppConfig;
+}
+
 /** Reset persisted config back to defaults, returning the defaults. */
 export function resetMockConfig(): AppConfig {
   const config = defaultConfig();
@@ -0,0 +1,83 @@
+import type { DevArtifactReport, DevProject, FileError } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+const DAY = 86400;
+
+// Mock projects beneath each root: a Rust tool and a web app left alone for
+// over a year, and a project worked on this week. Roots containing
+// "empty-dir" hold none (empty-state UI). A root containing "locked" fails
+// the search with a permission error and one containing "missing" as a
+// folder that does not exist.
+export function mockFindDevArtifacts(roots: string[], untouchedMonths: number): Promise<DevArtifactReport> {
+  const locked = roots.find((root) => root.includes("locked"));
+  if (locked) {
+    return Promise.reject(new ServiceError("permission_denied", "Permission denied (os error 13)"));
+  }
+  const missing = roots.find((root) => root.includes("missing"));
+  if (missing) {
+    return Promise.reject(new ServiceError("not_found", `Folder not found: ${missing}`));
+  }
+  const now = Math.floor(Date.now() / 1000);
+  const cutoff = now - untouchedMonths * 30 * DAY;
+  const projects: DevProject[] = roots
+    .filter((root) => !root.includes("empty-dir"))
+    .flatMap((root) => [
+      project(`${root}/old-tool`, ["Cargo.toml", ".git"], now - 420 * DAY, [
+        ["target", "target", 3221225472, 6210, now - 430 * DAY],
+      ]),
+      project(`${root}/old-site`, ["package.json", ".git"], now - 390 * DAY, [
+        ["node_modules", "node_modules", 419430400, 41022, now - 400 * DAY],
+        ["dist", "dist", 5242880, 38, now - 395 * DAY],
+      ]),
+      project(`${root}/current`, ["pyproject.toml", ".git"], now - 2 * DAY, [
+        ["venv", ".venv", 157286400, 9120, now - 20 * DAY],
+      ]),
+    ])
+    .map((p) => ({ ...p, suggested: p.last_activity !== null && p.last_activity < cutoff }))
+    .sort((a, b) => Number(b.suggested) - Number(a.suggested) || b.size - a.size);
+  const report = {
+    projects,
+    total_size: projects.reduce((sum, p) => sum + p.size, 0),
+    reclaimable: projects.filter((p) => p.suggested).reduce((sum, p) => sum + p.size, 0),
+    untouched_months: untouchedMonths,
+  };
+  return new Promise((resolve) => {
+    setTimeout(() => resolve(report), 300);
+  });
+}
+
+function project(
+  root: string,
+  markers: string[],
+  lastActivity: number,
+  artifacts: [DevProject["artifacts"][number]["kind"], string, number, number, number][]
+): DevProject {
+  const built = artifacts.map(([kind, dir, size, file_count, last_build]) => ({
+    kind,
+    path: `${root}/${dir}`,
+    size,
+    file_count,
+    last_build,
+  }));
+  return {
+    root,
+    markers,
+    last_activity: lastActivity,
+    artifacts: built,
+    size: built.reduce((sum, a) => sum + a.size, 0),
+    suggested: false,
+  };
+}
+
+// Why removal would leave a build output folder alone: one whose path
+// contains "locked" cannot be removed, one containing "missing" is gone
+// since the search. Worded like the backend.
+export function mockArtifactRefusal(path: string): FileError | null {
+  if (path.includes("locked")) {
+    return { path, error: "IO error: Permission denied (os error 13)", code: "permission_denied" };
+  }
+  if (path.includes("missing")) {
+    return { path, error: `No longer a folder: ${path}`, code: "not_found" };
+  }
+  return null;
+}
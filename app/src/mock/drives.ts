@@ -0,0 +1,93 @@
+import type { DiskInfo, PathCheck } from '$lib/types';
+
+const GB = 1024 ** 3;
+
+function drive(
+  mount_point: string,
+  name: string,
+  file_system: string,
+  kind: DiskInfo['kind'],
+  total: number,
+  available: number,
+  read_only = false,
+): DiskInfo {
+  return {
+    name,
+    mount_point,
+    file_system,
+    total_space: total * GB,
+    available_space: available * GB,
+    used_space: (total - available) * GB,
+    kind,
+    read_only,
+  };
+}
+
+/**
+ * Web-mode drive list, one of each kind the backend reports: the system
+ * disk, a data disk, a USB stick, a NAS share and a read-only disc image.
+ * Sorted by mount point like crates/service/src/drives.rs list_drives().
+ */
+export function mockListDrives(): Promise<DiskInfo[]> {
+  return new Promise((resolve) =>
+    setTimeout(
+      () =>
+        resolve([
+          drive('/', '/dev/nvme0n1p2', 'ext4', 'fixed', 476, 118),
+          drive('/home/data', '/dev/sda1', 'btrfs', 'fixed', 1863, 912),
+          drive('/media/cdrom', 'Install disc', 'iso9660', 'removable', 4, 0, true),
+          drive('/media/usb-drive', 'USB DRIVE', 'vfat', 'removable', 29, 21),
+          drive('/mnt/nas', 'nas:/volume1/photos', 'nfs4', 'network', 7452, 3120),
+        ]),
+      150
+    )
+  );
+}
+
+/**
+ * Web-mode path check against the mock drives. Paths containing "missing"
+ * do not exist, "locked" ones cannot be read and "huge" folders hold far
+ * too many files; anything with an extension is a file.
+ */
+export async function mockValidatePaths(paths: string[]): Promise<PathCheck[]> {
+  const drives = await mockListDrives();
+  return paths.map((path) => {
+    if (path.includes('missing')) {
+      return {
+        path,
+        exists: false,
+        is_dir: false,
+        readable: false,
+        drive_kind: null,
+        mount_point: null,
+        estimated_files: null,
+        exact: false,
+        warnings: ['Path does not exist'],
+      };
+    }
+    const warnings: string[] = [];
+    const readable = !path.includes('locked');
+    if (!readable) warnings.push('Permission denied');
+    const drive = drives
+      .filter((d) => d.mount_point === '/' || path === d.mount_point || path.startsWith(`${d.mount_point}/`))
+      .sort((a, b) => b.mount_point.length - a.mount_point.length)[0];
+    if (drive?.kind === 'network') warnings.push('On a network drive; scanning may be slow');
+    if (drive?.kind === 'removable') warnings.push('On a removable drive; keep it connected while scanning');
+    const is_dir = !/\.[^/]+$/.test(path);
+    const estimated_files = !is_dir ? 1 : !readable ? null : path.includes('huge') ? 350_000 : 1_200;
+    if ((estimated_files ?? 0) > 200_000) {
+      warnings.push(`About ${estimated_files} files; scanning may take a long time`);
+    }
+    return {
+      path,
+      exists: true,
+      is_dir,
+      readable,
+      drive_kind: drive?.kind ?? null,
+      mount_point: drive?.mount_point ?? null,
+      estimated_files,
+      exact: !is_dir,
+      warnings,
+    };
+  });
+}
@@ -0,0 +1,30 @@
+import type { DuplicateDirectoryGroup } from "../lib/types";
+
+// Mock duplicate folders under a scan root: a photo export copied twice and
+// a project backed up next to itself. Paths containing "empty-dir" have none
+// (empty-state UI).
+export function mockFindDuplicateDirs(path: string): Promise<DuplicateDirectoryGroup[]> {
+  const groups: DuplicateDirectoryGroup[] = path.includes("empty-dir") ? [] : [
+    {
+      hash: "d41f0c9a7e",
+      directories: [`${path}/Pictures/Export 2023`, `${path}/Pictures/Export 2023 copy`],
+      file_count: 1284,
+      size: 6442450944,
+      wasted_space: 6442450944,
+    },
+    {
+      hash: "8be27a113c",
+      directories: [
+        `${path}/Backup/website`,
+        `${path}/Backup/website (1)`,
+        `${path}/Projects/website`,
+      ],
+      file_count: 312,
+      size: 52428800,
+      wasted_space: 104857600,
+    },
+  ];
+  return new Promise((resolve) => {
+    setTimeout(() => resolve(groups), 300);
+  });
+}
@@ -0,0 +1,37 @@
+import type { ExportFormat, ExportResults } from "../lib/api";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for exporting results: nothing reaches the disk, the
+// exports are kept in memory by path. Paths containing "locked" fail with a
+// permission error, and ones containing "missing" like a missing parent
+// directory would.
+const exported = new Map<string, { format: ExportFormat; results: ExportResults }>();
+
+function write(results: ExportResults, format: ExportFormat, path: string): void {
+  if (!["csv", "json", "html"].includes(format)) throw new ServiceError("invalid_input", `Unsupported export format: ${format}`);
+  if (path.includes("locked")) {
+    throw new ServiceError("permission_denied", `Failed to write ${path}: Permission denied (os error 13)`);
+  }
+  if (path.includes("missing")) {
+    throw new ServiceError("not_found", `Failed to write ${path}: No such file or directory (os error 2)`);
+  }
+  exported.set(path, { format, results });
+}
+
+export const mockExport = {
+  write(results: ExportResults, format: ExportFormat, path: string): Promise<void> {
+    return new Promise((resolve, reject) =>
+      setTimeout(() => {
+        try {
+          resolve(write(results, format, path));
+        } catch (e) {
+          reject(e);
+        }
+      }, 200)
+    );
+  },
+  /** The latest export to `path`, for tests */
+  read(path: string) {
+    return exported.get(path);
+  },
+};
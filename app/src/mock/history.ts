@@ -0,0 +1,254 @@
+import type {
+  CategoryGrowth,
+  CategoryTotal,
+  DuplicateRecord,
+  FileChange,
+  FileQuery,
+  FileRecord,
+  Page,
+  ScanDetails,
+  ScanDiff,
+  ScanHistoryEntry,
+  ScanRecord,
+  StorageHistory,
+} from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for recorded scans: the first time a path's history is
+// asked for, three weekly scans of it are made up, growing over time (one
+// photo is recompressed, so a file shrinks too). Paths
+// containing "empty-dir" have never been scanned. Diffing unknown ids or
+// scans of different paths fails like the backend.
+interface MockScan {
+  record: ScanRecord;
+  files: { path: string; size: number; file_type: string }[];
+}
+
+const scans = new Map<number, MockScan>();
+let nextId = 0;
+
+const DAY = 86_400;
+
+function seed(path: string): MockScan[] {
+  const existing = [...scans.values()].filter((s) => s.record.path === path);
+  if (existing.length > 0 || path.includes("empty-dir")) return existing;
+  const now = Math.floor(Date.now() / 1000);
+  const base = [
+    { path: `${path}/Photos/beach.jpg`, size: 4194304, file_type: "Image" },
+    { path: `${path}/Documents/notes.txt`, size: 8192, file_type: "Document" },
+    { path: `${path}/Downloads/old-installer.dmg`, size: 209715200, file_type: "Other" },
+  ];
+  const weeks = [
+    base,
+    [...base, { path: `${path}/Videos/clip.mp4`, size: 524288000, file_type: "Video" }],
+    [
+      { path: `${path}/Photos/beach.jpg`, size: 2097152, file_type: "Image" },
+      { path: `${path}/Documents/notes.txt`, size: 65536, file_type: "Document" },
+      { path: `${path}/Videos/clip.mp4`, size: 524288000, file_type: "Video" },
+      { path: `${path}/Photos/party.jpg`, size: 6291456, file_type: "Image" },
+    ],
+  ];
+  return weeks.map((files, week) => {
+    nextId += 1;
+    const scan = {
+      record: {
+        id: nextId,
+        path,
+        file_count: files.length,
+        total_size: files.reduce((sum, f) => sum + f.size, 0),
+        scan_time: 2,
+        created_at: now - (weeks.length - 1 - week) * 7 * DAY,
+      },
+      files,
+    };
+    scans.set(scan.record.id, scan);
+    return scan;
+  });
+}
+
+function categories(scan: MockScan): CategoryTotal[] {
+  const totals = new Map<string, CategoryTotal>();
+  for (const file of scan.files) {
+    const total = totals.get(file.file_type) ?? { category: file.file_type, file_count: 0, total_size: 0 };
+    total.file_count += 1;
+    total.total_size += file.size;
+    totals.set(file.file_type, total);
+  }
+  return [...totals.values()].sort((a, b) => a.category.localeCompare(b.category));
+}
+
+/** Change per category from the totals `before` to the totals `after` */
+function categoryGrowth(before: CategoryTotal[], after: CategoryTotal[]): CategoryGrowth[] {
+  const growth = new Map<string, CategoryGrowth>();
+  const change = (totals: CategoryTotal[], sign: number) => {
+    for (const t of totals) {
+      const g = growth.get(t.category) ?? { category: t.category, file_count_change: 0, size_change: 0 };
+      g.file_count_change += sign * t.file_count;
+      g.size_change += sign * t.total_size;
+      growth.set(t.category, g);
+    }
+  };
+  change(before, -1);
+  change(after, 1);
+  return [...growth.values()].sort((a, b) => a.category.localeCompare(b.category));
+}
+
+export function mockStorageHistory(path: string): StorageHistory {
+  const snapshots = seed(path).map((scan) => ({
+    scan_id: scan.record.id,
+    scanned_at: scan.record.created_at,
+    file_count: scan.record.file_count,
+    total_size: scan.record.total_size,
+    categories: categories(scan),
+  }));
+  return {
+    path,
+    snapshots,
+    growth:
+      snapshots.length > 1
+        ? categoryGrowth(snapshots[0].categories, snapshots[snapshots.length - 1].categories)
+        : [],
+  };
+}
+
+/** The recorded duplicate groups with a copy under `root` */
+function duplicatesUnder(root: string): DuplicateRecord[] {
+  const dir = root.replace(/\/+$/, "");
+  return mockDuplicateHistory().filter((group) =>
+    group.file_paths.some((p) => p === dir || p.startsWith(`${dir}/`))
+  );
+}
+
+/**
+ * Recorded scans of every path, newest first, with what their duplicates
+ * waste. Before any history was asked for, a home folder's scans are made
+ * up so the list is never empty.
+ */
+export function mockScanHistory(limit: number): ScanHistoryEntry[] {
+  if (scans.size === 0) seed("/home/user");
+  return [...scans.values()]
+    .map((scan) => scan.record)
+    .sort((a, b) => b.created_at - a.created_at || b.id - a.id)
+    .slice(0, limit)
+    .map((scan) => ({
+      scan,
+      reclaimable: duplicatesUnder(scan.path).reduce((sum, g) => sum + g.wasted_space, 0),
+    }));
+}
+
+export function mockScanDetails(id: number): ScanDetails {
+  const scan = scans.get(id);
+  if (!scan) throw new ServiceError("not_found", `Unknown scan: ${id}`);
+  return {
+    scan: scan.record,
+    categories: categories(scan),
+    largest: [...scan.files].sort((a, b) => b.size - a.size || a.path.localeCompare(b.path)).slice(0, 10),
+    duplicates: duplicatesUnder(scan.record.path),
+  };
+}
+
+export function mockDeleteScan(id: number): void {
+  if (!scans.delete(id)) throw new ServiceError("not_found", `Unknown scan: ${id}`);
+}
+
+/**
+ * The duplicate groups last found, most wasted space first
+ */
+export function mockDuplicateHistory(): DuplicateRecord[] {
+  const foundAt = Math.floor(Date.now() / 1000) - DAY;
+  const group = (hash: string, paths: string[], size: number): DuplicateRecord => ({
+    id: 0,
+    hash,
+    file_paths: paths,
+    file_count: paths.length,
+    total_size: size * paths.length,
+    wasted_space: size * (paths.length - 1),
+    created_at: foundAt,
+  });
+  return [
+    group("9f86d081884c7d65", ["/home/user/Videos/clip.mp4", "/home/user/Downloads/clip.mp4"], 524288000),
+    group(
+      "2c26b46b68ffc68f",
+      ["/home/user/Photos/beach.jpg", "/home/user/Desktop/beach.jpg", "/home/user/Backup/beach.jpg"],
+      4194304,
+    ),
+  ].map((record, index) => ({ ...record, id: index + 1 }));
+}
+
+/**
+ * A page of the files the latest scan of each path recorded, ordered like
+ * the backend orders each kind of query
+ */
+export function mockRecordedFiles(query: FileQuery, page: Page): FileRecord[] {
+  if (scans.size === 0) seed("/home/user");
+  const latest = new Map<string, MockScan>();
+  for (const scan of scans.values()) {
+    const seen = latest.get(scan.record.path);
+    if (!seen || scan.record.created_at >= seen.record.created_at) latest.set(scan.record.path, scan);
+  }
+  let files: FileRecord[] = [...latest.values()].flatMap((scan) =>
+    scan.files.map((file, index) => ({
+      id: 0,
+      ...file,
+      hash: null,
+      modified: scan.record.created_at - (index + 1) * 90 * DAY,
+      created_at: scan.record.created_at,
+    }))
+  );
+  files.forEach((file, index) => (file.id = index + 1));
+
+  const bySize = (a: FileRecord, b: FileRecord) => b.size - a.size || a.path.localeCompare(b.path);
+  switch (query.by) {
+    case "largest":
+      files.sort(bySize);
+      break;
+    case "type":
+      files = files.filter((f) => f.file_type === query.file_type).sort(bySize);
+      break;
+    case "under": {
+      const dir = query.path.replace(/\/+$/, "");
+      files = files.filter((f) => f.path.startsWith(`${dir}/`)).sort((a, b) => a.path.localeCompare(b.path));
+      break;
+    }
+    case "older_than":
+      files = files
+        .filter((f) => f.modified < query.before)
+        .sort((a, b) => a.modified - b.modified || a.path.localeCompare(b.path));
+      break;
+  }
+  return files.slice(page.offset, page.offset + page.limit);
+}
+
+export function mockDiffScans(fromScan: number, toScan: number): ScanDiff {
+  const from = scans.get(fromScan);
+  const to = scans.get(toScan);
+  if (!from) throw new ServiceError("not_found", `Unknown scan: ${fromScan}`);
+  if (!to) throw new ServiceError("not_found", `Unknown scan: ${toScan}`);
+  if (from.record.path !== to.record.path) {
+    throw new ServiceError(
+      "invalid_input",
+      `Scans ${fromScan} and ${toScan} are of different paths (${from.record.path} and ${to.record.path})`
+    );
+  }
+  const before = new Map(from.files.map((f) => [f.path, f.size]));
+  const added: FileChange[] = [];
+  const resized: FileChange[] = [];
+  for (const file of to.files) {
+    const oldSize = before.get(file.path);
+    before.delete(file.path);
+    if (oldSize === undefined) added.push({ path: file.path, old_size: 0, new_size: file.size });
+    else if (file.size !== oldSize) resized.push({ path: file.path, old_size: oldSize, new_size: file.size });
+  }
+  const removed = [...before].map(([path, size]) => ({ path, old_size: size, new_size: 0 }));
+  const bySize = (a: FileChange, b: FileChange) =>
+    Math.abs(b.new_size - b.old_size) - Math.abs(a.new_size - a.old_size);
+  return {
+    from: from.record,
+    to: to.record,
+    added: added.sort(bySize),
+    removed: removed.sort(bySize),
+    resized: resized.sort(bySize),
+    categories: categoryGrowth(categories(from), categories(to)),
+    size_change: to.record.total_size - from.record.total_size,
+  };
+}
@@ -0,0 +1,49 @@
+import type { DuplicateGroup, IgnoredGroup, IgnoreKind, SimilarGroup } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for the ignore list: entries live in memory only. Like
+// the backend, a group stays hidden only while all of its files are in one
+// ignored entry (and, for duplicates, its hash still matches).
+let entries: IgnoredGroup[] = [];
+let nextId = 1;
+
+function covers(entry: IgnoredGroup, kind: IgnoreKind, paths: string[], hash?: string): boolean {
+  return (
+    entry.kind === kind &&
+    (hash === undefined || entry.hash === hash) &&
+    paths.every(p => entry.paths.includes(p))
+  );
+}
+
+export const mockIgnore = {
+  add(kind: IgnoreKind, paths: string[], hash?: string): IgnoredGroup {
+    const unique = [...new Set(paths)].sort();
+    if (unique.length < 2) throw new ServiceError("invalid_input", "An ignored group needs at least two files");
+    if (kind === "duplicate" && !hash) throw new ServiceError("invalid_input", "An ignored duplicate group needs its content hash");
+    const entry: IgnoredGroup = {
+      id: nextId++,
+      kind,
+      hash: kind === "duplicate" ? hash! : null,
+      paths: unique,
+      created_at: Math.floor(Date.now() / 1000),
+    };
+    entries.push(entry);
+    return entry;
+  },
+  remove(id: number): boolean {
+    const before = entries.length;
+    entries = entries.filter(e => e.id !== id);
+    return entries.length < before;
+  },
+  list(): IgnoredGroup[] {
+    return [...entries].reverse();
+  },
+  ignoresDuplicate(group: DuplicateGroup): boolean {
+    const paths = group.files.map(f => f.path);
+    return entries.some(e => covers(e, "duplicate", paths, group.hash));
+  },
+  ignoresSimilar(group: SimilarGroup): boolean {
+    const paths = group.files.map(f => f.path);
+    return entries.some(e => covers(e, "similar", paths));
+  },
+};
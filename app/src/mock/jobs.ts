@@ -0,0 +1,99 @@
+import type { JobInfo, ProgressUpdate, TaskType } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for the backend's JobManager: scan, duplicate, similar
+// and compress calls register a job here and feed it their progress updates,
+// so listJobs/getJobStatus/cancelJob behave like the Tauri commands.
+// Finished jobs are kept up to MAX_FINISHED_JOBS, like the backend.
+const MAX_FINISHED_JOBS = 50;
+
+interface MockJob {
+  info: JobInfo;
+  cancelled: boolean;
+  onCancel?: () => void;
+}
+
+const jobs = new Map<number, MockJob>();
+let nextId = 0;
+
+function isFinished(info: JobInfo): boolean {
+  return info.status !== "Pending" && info.status !== "Running";
+}
+
+function prune(): void {
+  const finished = [...jobs.values()].filter((job) => isFinished(job.info));
+  for (const job of finished.slice(0, Math.max(0, finished.length - MAX_FINISHED_JOBS))) {
+    jobs.delete(job.info.id);
+  }
+}
+
+export const mockJobs = {
+  /** `onCancel` runs when cancelJob signals the job */
+  register(taskType: TaskType, onCancel?: () => void): number {
+    nextId += 1;
+    jobs.set(nextId, {
+      info: {
+        id: nextId,
+        task_type: taskType,
+        status: "Pending",
+        current: 0,
+        total: 0,
+        message: "",
+        started_at: Math.floor(Date.now() / 1000),
+      },
+      cancelled: false,
+      onCancel,
+    });
+    return nextId;
+  },
+  update(id: number, update: ProgressUpdate): void {
+    const job = jobs.get(id);
+    if (!job) return;
+    const info = job.info;
+    if (update === "Cancelled") {
+      info.status = "Cancelled";
+    } else if ("Started" in update) {
+      info.status = "Running";
+      info.total = update.Started.total_items;
+    } else if ("Progress" in update) {
+      info.status = "Running";
+      info.current = update.Progress.current;
+      info.total = update.Progress.total;
+      info.message = update.Progress.message;
+    } else if ("Completed" in update) {
+      info.status = "Completed";
+      info.message = update.Completed.message;
+    } else {
+      // A job that fails after being asked to stop was cancelled
+      info.status = job.cancelled ? "Cancelled" : { Failed: update.Failed.error };
+    }
+    if (isFinished(info)) prune();
+  },
+  list(): JobInfo[] {
+    return [...jobs.values()].map((job) => ({ ...job.info }));
+  },
+  status(id: number): JobInfo {
+    const job = jobs.get(id);
+    if (!job) throw new ServiceError("not_found", `Unknown job: ${id}`);
+    return { ...job.info };
+  },
+  /** False when the job had already finished, like the backend */
+  cancel(id: number): boolean {
+    const job = jobs.get(id);
+    if (!job) throw new ServiceError("not_found", `Unknown job: ${id}`);
+    if (isFinished(job.info)) return false;
+    job.cancelled = true;
+    job.onCancel?.();
+    return true;
+  },
+  /**
+   * Web-mode jobs live and die with the page, so a reload never leaves
+   * anything interrupted to report
+   */
+  takeShutdownReport(): null {
+    return null;
+  },
+  clear(): void {
+    jobs.clear();
+  },
+};
@@ -0,0 +1,29 @@
+import type { DirectorySize, FileInfo } from "../lib/types";
+
+// Mock space hogs under a scan root, largest first. Paths containing
+// "empty-dir" have no files, like the backend scanning an empty directory.
+export function mockLargestFiles(path: string): Promise<FileInfo[]> {
+  const files: FileInfo[] = path.includes("empty-dir") ? [] : [
+    { path: `${path}/Videos/holiday.mp4`, size: 1610612736, modified: 1700000000, file_type: "Video" },
+    { path: `${path}/Projects/2019-thesis/data.csv`, size: 734003200, modified: 1560000000, file_type: "Other" },
+    { path: `${path}/Downloads/installer.dmg`, size: 524288000, modified: 1650000000, file_type: "Other" },
+    { path: `${path}/Photos/panorama.tiff`, size: 209715200, modified: 1690000000, file_type: "Image" },
+    { path: `${path}/Downloads/backup.zip`, size: 104857600, modified: 1680000000, file_type: "Archive" },
+  ];
+  return new Promise((resolve) => {
+    setTimeout(() => resolve(files), 300);
+  });
+}
+
+export function mockLargestDirectories(path: string): Promise<DirectorySize[]> {
+  const dirs: DirectorySize[] = path.includes("empty-dir") ? [] : [
+    { path: `${path}/Videos`, total_size: 2147483648, file_count: 12 },
+    { path: `${path}/Projects`, total_size: 943718400, file_count: 340 },
+    { path: `${path}/Projects/2019-thesis`, total_size: 736100352, file_count: 58 },
+    { path: `${path}/Downloads`, total_size: 681574400, file_count: 95 },
+    { path: `${path}/Photos`, total_size: 419430400, file_count: 452 },
+  ];
+  return new Promise((resolve) => {
+    setTimeout(() => resolve(dirs), 300);
+  });
+}
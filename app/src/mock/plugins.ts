@@ -1,6 +1,6 @@
 import type { CompressionPlugin } from '$lib/api';
 
-// Mirrors the three plugins registered in the backend's global plugin
+// Mirrors the five plugins registered in the backend's global plugin
 // manager (crates/core/src/compress_plugins.rs). Shared by the
 // getCompressionPlugins, setPluginQuality and scanCompressibleFiles mocks
 // so plugin-name validation behaves like the backend.
@@ -10,18 +10,45 @@ export const mockPlugins: CompressionPlugin[] = [
     description: 'Converts images inside ZIP archives to WebP format',
     version: '1.0.0',
     quality: 85,
+    lossy: true,
+    destructive: true,
+    requires_external_tools: [],
   },
   {
     name: 'WebP Converter',
-    description: 'Converts PNG, JPEG, and other image formats to WebP',
+    description: 'Converts PNG, JPEG, static GIF and other image formats to WebP',
     version: '1.0.0',
     quality: 85,
+    lossy: true,
+    destructive: false,
+    requires_external_tools: [],
   },
   {
     name: 'Animated WebP Converter',
-    description: 'Convert GIF to Animated WebP with lossy compression for better file size',
+    description: 'Convert animated GIF and PNG to Animated WebP with lossy compression for better file size',
     version: '1.0.0',
     quality: 85,
+    lossy: true,
+    destructive: false,
+    requires_external_tools: ['gif2webp', 'ffmpeg'],
+  },
+  {
+    name: 'RAW to JPEG',
+    description: 'Convert camera RAW files (CR2, NEF, ARW) to high-quality JPEG',
+    version: '1.0.0',
+    quality: 92,
+    lossy: true,
+    destructive: false,
+    requires_external_tools: ['dcraw'],
+  },
+  {
+    name: 'Text Asset Minifier',
+    description: 'Losslessly minify SVG (and optionally JSON/XML) files in place',
+    version: '1.0.0',
+    quality: null,
+    lossy: false,
+    destructive: true,
+    requires_external_tools: [],
   },
 ];
 
@@ -0,0 +1,46 @@
+import type { QuarantineRecord } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for the backend's quarantine: records live in memory
+// only, and every quarantined mock file is 1 MiB. Restoring or purging an
+// id that is not quarantined fails like the backend.
+const records = new Map<number, QuarantineRecord>();
+let nextId = 0;
+
+const DAY = 86_400;
+
+export const mockQuarantine = {
+  add(path: string, retentionDays: number, quarantineDir: string): QuarantineRecord {
+    nextId += 1;
+    const now = Math.floor(Date.now() / 1000);
+    const name = path.split("/").pop() ?? "";
+    const record = {
+      id: nextId,
+      original_path: path,
+      quarantine_path: `${quarantineDir}/${now}-0-${name}`,
+      size: 1024 * 1024,
+      quarantined_at: now,
+      expires_at: now + retentionDays * DAY,
+    };
+    records.set(record.id, record);
+    return record;
+  },
+  /** Newest first, like the backend */
+  list(): QuarantineRecord[] {
+    return [...records.values()].reverse();
+  },
+  restore(id: number): QuarantineRecord {
+    const record = records.get(id);
+    if (!record) throw new ServiceError("not_found", `Unknown quarantine entry: ${id}`);
+    records.delete(id);
+    return record;
+  },
+  purgeExpired(now: number): QuarantineRecord[] {
+    const expired = [...records.values()].filter((r) => r.expires_at <= now);
+    for (const record of expired) records.delete(record.id);
+    return expired;
+  },
+  clear(): void {
+    records.clear();
+  },
+};
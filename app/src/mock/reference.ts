@@ -0,0 +1,113 @@
+import type { ReferenceCheck, ReferenceMatch, ReferenceRoot } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for indexed reference volumes: indexes live in memory
+// only. Every volume holds the same few photos, and a local file counts as a
+// copy when an indexed file has its name and size. Trigger words (shared mock
+// conventions):
+// - "unmounted" or "missing" volumes are refused like a volume that is not
+//   there, and "missing" folders fail a check as a folder that does not exist
+// - "locked" volumes and folders fail with a permission error
+// - "empty-dir" volumes index no files, and "empty-dir" folders hold none
+const PHOTOS = [
+  { name: "IMG_0001.jpg", size: 4194304 },
+  { name: "IMG_0002.jpg", size: 3670016 },
+  { name: "VID_0003.mp4", size: 157286400 },
+];
+
+const LOCAL = [
+  ...PHOTOS,
+  { name: "IMG_0004.jpg", size: 3145728 },
+  { name: "notes.txt", size: 2048 },
+];
+
+const roots = new Map<string, ReferenceRoot>();
+
+// Indexing and checking hash files, so they settle after a moment
+function delayed<T>(work: () => T): Promise<T> {
+  return new Promise((resolve, reject) =>
+    setTimeout(() => {
+      try {
+        resolve(work());
+      } catch (e) {
+        reject(e);
+      }
+    }, 300)
+  );
+}
+
+function index(path: string): ReferenceRoot {
+  if (path.includes("locked")) throw new ServiceError("permission_denied", "Permission denied (os error 13)");
+  if (path.includes("unmounted") || path.includes("missing")) {
+    throw new ServiceError("not_found", `Reference volume not found: ${path}`);
+  }
+  const photos = path.includes("empty-dir") ? [] : PHOTOS;
+  const root = {
+    path,
+    file_count: photos.length,
+    total_size: photos.reduce((sum, f) => sum + f.size, 0),
+    indexed_at: Math.floor(Date.now() / 1000),
+  };
+  roots.set(path, root);
+  return root;
+}
+
+function check(paths: string[]): ReferenceCheck {
+  if (paths.some(path => path.includes("locked"))) {
+    throw new ServiceError("permission_denied", "Permission denied (os error 13)");
+  }
+  const missing = paths.find(path => path.includes("missing"));
+  if (missing) throw new ServiceError("not_found", `Folder not found: ${missing}`);
+
+  const references = [...roots.values()].sort((a, b) => a.path.localeCompare(b.path));
+  // An empty volume holds no copies
+  const volume = references.find(r => r.file_count > 0);
+  const matched: ReferenceMatch[] = [];
+  let unmatched_count = 0;
+  let unmatched_size = 0;
+  for (const path of paths.filter(p => !p.includes("empty-dir"))) {
+    for (const file of LOCAL) {
+      const copy = PHOTOS.find(p => p.name === file.name && p.size === file.size);
+      if (copy && volume) {
+        matched.push({
+          path: `${path}/Pictures/${file.name}`,
+          size: file.size,
+          hash: `mock-${file.name}`,
+          reference_path: `${volume.path}/${copy.name}`,
+        });
+      } else {
+        unmatched_count += 1;
+        unmatched_size += file.size;
+      }
+    }
+  }
+  matched.sort((a, b) => b.size - a.size || a.path.localeCompare(b.path));
+  return {
+    matched,
+    matched_size: matched.reduce((sum, m) => sum + m.size, 0),
+    unmatched_count,
+    unmatched_size,
+    references,
+  };
+}
+
+export const mockReference = {
+  index(path: string): Promise<ReferenceRoot> {
+    return delayed(() => index(path));
+  },
+  check(paths: string[]): Promise<ReferenceCheck> {
+    return delayed(() => check(paths));
+  },
+  /** By path, like the backend */
+  list(): ReferenceRoot[] {
+    return [...roots.values()].sort((a, b) => a.path.localeCompare(b.path));
+  },
+  remove(path: string): number {
+    const root = roots.get(path);
+    roots.delete(path);
+    return root?.file_count ?? 0;
+  },
+  clear(): void {
+    roots.clear();
+  },
+};
@@ -0,0 +1,72 @@
+import type { MonthlySavings, PluginSavings, SavingsSummary } from "../lib/types";
+
+// Web-mode stand-in for the backend's compression_operations ledger: a few
+// compressions from earlier months so the dashboard is never empty, plus
+// every file the mock in-place compression compresses during the session.
+// Clearing the skip cache does not touch it, like the backend.
+interface MockOperation {
+  plugin_name: string;
+  original_size: number;
+  compressed_size: number;
+  /** Unix timestamp (seconds) */
+  created_at: number;
+}
+
+const DAY = 86_400;
+
+function seeded(): MockOperation[] {
+  const now = Math.floor(Date.now() / 1000);
+  return [
+    { plugin_name: "WebP Converter", original_size: 52428800, compressed_size: 15728640, created_at: now - 70 * DAY },
+    { plugin_name: "RAW to JPEG", original_size: 251658240, compressed_size: 62914560, created_at: now - 40 * DAY },
+    { plugin_name: "WebP Converter", original_size: 10485760, compressed_size: 3145728, created_at: now - 40 * DAY },
+    { plugin_name: "Text Asset Minifier", original_size: 2097152, compressed_size: 1572864, created_at: now - 5 * DAY },
+  ];
+}
+
+const operations: MockOperation[] = seeded();
+
+const saved = (op: { original_size: number; compressed_size: number }) =>
+  Math.max(op.original_size - op.compressed_size, 0);
+
+export const mockSavings = {
+  record(plugin_name: string, original_size: number, compressed_size: number): void {
+    operations.push({ plugin_name, original_size, compressed_size, created_at: Math.floor(Date.now() / 1000) });
+  },
+
+  /** Totals, per plugin (most saved first) and per UTC month (oldest first) */
+  summary(): SavingsSummary {
+    const plugins = new Map<string, PluginSavings>();
+    const months = new Map<string, MonthlySavings>();
+    for (const op of operations) {
+      const plugin = plugins.get(op.plugin_name) ?? {
+        plugin_name: op.plugin_name,
+        file_count: 0,
+        original_size: 0,
+        compressed_size: 0,
+        saved: 0,
+      };
+      plugin.file_count += 1;
+      plugin.original_size += op.original_size;
+      plugin.compressed_size += op.compressed_size;
+      plugin.saved += saved(op);
+      plugins.set(op.plugin_name, plugin);
+
+      const key = new Date(op.created_at * 1000).toISOString().slice(0, 7);
+      const month = months.get(key) ?? { month: key, file_count: 0, saved: 0 };
+      month.file_count += 1;
+      month.saved += saved(op);
+      months.set(key, month);
+    }
+    return {
+      file_count: operations.length,
+      original_size: operations.reduce((sum, op) => sum + op.original_size, 0),
+      compressed_size: operations.reduce((sum, op) => sum + op.compressed_size, 0),
+      saved: operations.reduce((sum, op) => sum + saved(op), 0),
+      by_plugin: [...plugins.values()].sort(
+        (a, b) => b.saved - a.saved || a.plugin_name.localeCompare(b.plugin_name)
+      ),
+      by_month: [...months.values()].sort((a, b) => a.month.localeCompare(b.month)),
+    };
+  },
+};
@@ -1,4 +1,5 @@
-import type { ScanResult } from '$lib/types';
+import type { FileInfo, ScanPage, ScanResult, ScanSort, ScanSummary, SortOrder } from '$lib/types';
+import { ServiceError } from '../lib/api/errors';
 
 // Mock scan result. Paths containing "empty-dir" return no files, like the
 // backend scanning an empty or nonexistent directory (demos the empty-state UI).
@@ -50,3 +51,48 @@ export function mockScanResult(path: string): Promise<ScanResult> {
     }, 800);
   });
 }
+
+// Web-mode stand-in for the backend's scan result store: the files of the
+// latest scans, paged through by job id. Like the backend it keeps only a
+// few scans, so paging an old one fails with NotFound.
+const KEPT_SCANS = 3;
+const scans = new Map<number, ScanResult[]>();
+
+/** Ascending by `sortBy`, then by path; types sort by name, as in the backend */
+function compareFiles(a: FileInfo, b: FileInfo, sortBy: ScanSort): number {
+  const name = (f: FileInfo) => f.path.split('/').pop() ?? '';
+  const by =
+    sortBy === 'size' ? a.size - b.size
+    : sortBy === 'modified' ? a.modified - b.modified
+    : sortBy === 'name' ? name(a).localeCompare(name(b))
+    : sortBy === 'type' ? a.file_type.localeCompare(b.file_type)
+    : 0;
+  return by !== 0 ? by : a.path < b.path ? -1 : a.path > b.path ? 1 : 0;
+}
+
+export const mockScanPages = {
+  store(jobId: number, results: ScanResult[]): ScanSummary {
+    scans.set(jobId, results);
+    for (const old of [...scans.keys()].slice(0, Math.max(0, scans.size - KEPT_SCANS))) {
+      scans.delete(old);
+    }
+    const paths = results.map(({ path, file_count, total_size }) => ({ path, file_count, total_size }));
+    return {
+      job_id: jobId,
+      file_count: paths.reduce((sum, p) => sum + p.file_count, 0),
+      total_size: paths.reduce((sum, p) => sum + p.total_size, 0),
+      paths,
+    };
+  },
+  page(jobId: number, offset: number, limit: number, sortBy: ScanSort, order: SortOrder, path?: string): ScanPage {
+    const results = scans.get(jobId);
+    if (!results) {
+      throw new ServiceError('not_found', `The results of scan ${jobId} are gone; scan again`);
+    }
+    const files = results
+      .filter((r) => path === undefined || r.path === path)
+      .flatMap((r) => r.files)
+      .sort((a, b) => (order === 'asc' ? 1 : -1) * compareFiles(a, b, sortBy));
+    return { files: files.slice(offset, offset + limit), total: files.length, offset };
+  },
+};
@@ -0,0 +1,93 @@
+import type { Schedule, ScheduleEntry, ScheduleRun, ScheduleStatus, UpcomingRun } from "../lib/types";
+
+/**
+ * Mirrors Schedule::validate plus the schedule checks in Config::validate,
+ * returning the backend's rejection string or null.
+ */
+export function validateSchedules(schedules: Schedule[], ruleNames: string[]): string | null {
+  const names = new Set<string>();
+  for (const schedule of schedules) {
+    if (!schedule.name.trim()) return "Schedules need a name";
+    if (!["scan", "cleanup", "maintenance"].includes(schedule.task)) {
+      return `Schedule '${schedule.name}': task must be 'scan', 'cleanup' or 'maintenance', got '${schedule.task}'`;
+    }
+    if (schedule.every_minutes < 1) return `Schedule '${schedule.name}' must run at least a minute apart`;
+    if (schedule.task === "scan" && schedule.paths.length === 0) {
+      return `Schedule '${schedule.name}' scans but has no folders`;
+    }
+    if (names.has(schedule.name)) {
+      return `Schedule names must be unique, '${schedule.name}' is used twice`;
+    }
+    names.add(schedule.name);
+    const unknown = schedule.rules.find(rule => !ruleNames.includes(rule));
+    if (unknown) return `Schedule '${schedule.name}' runs an unknown cleanup rule: ${unknown}`;
+  }
+  return null;
+}
+
+// Web mode pretends a daemon started with the page and runs every enabled
+// schedule, first one period after it started. Runs asked for through
+// runSchedule finish at once and show as the schedule's last run.
+const STARTED = Math.floor(Date.now() / 1000);
+const lastRuns = new Map<string, { at: number; result: string }>();
+
+function mockResult(schedule: Schedule): string {
+  switch (schedule.task) {
+    case "scan":
+      return `Scanned ${schedule.paths.length * 1200} files (${schedule.paths.length * 3} GB)`;
+    case "cleanup":
+      return "Freed 305 MB from 2 files";
+    default:
+      return "Pruned 0 stored files, database now 2.1 MB";
+  }
+}
+
+function status(schedule: Schedule): ScheduleStatus {
+  const period = schedule.every_minutes * 60;
+  const now = Math.floor(Date.now() / 1000);
+  const periods = Math.max(1, Math.ceil((now - STARTED) / period));
+  const last = lastRuns.get(schedule.name);
+  return {
+    name: schedule.name,
+    task: schedule.task,
+    every_minutes: schedule.every_minutes,
+    next_run: STARTED + periods * period,
+    last_run: last?.at ?? null,
+    last_result: last?.result ?? null,
+    last_failed: false,
+    busy: false,
+  };
+}
+
+export function mockScheduleEntries(schedules: Schedule[]): ScheduleEntry[] {
+  return schedules.map(schedule => ({
+    schedule,
+    status: schedule.enabled ? status(schedule) : null,
+  }));
+}
+
+/** Enabled schedules go to the mock daemon; disabled ones run "in the app" */
+export function mockRunSchedule(schedule: Schedule): ScheduleRun {
+  const result = mockResult(schedule);
+  if (schedule.enabled) {
+    lastRuns.set(schedule.name, { at: Math.floor(Date.now() / 1000), result });
+    return { name: schedule.name, on_daemon: true, result: null };
+  }
+  return { name: schedule.name, on_daemon: false, result };
+}
+
+/** Mirrors DaemonStatus::upcoming_runs */
+export function mockNextRuns(schedules: Schedule[], count: number): UpcomingRun[] {
+  return schedules
+    .filter(schedule => schedule.enabled)
+    .flatMap(schedule => {
+      const first = status(schedule).next_run;
+      return Array.from({ length: count }, (_, n) => ({
+        schedule: schedule.name,
+        task: schedule.task,
+        at: first + n * schedule.every_minutes * 60,
+      }));
+    })
+    .sort((a, b) => a.at - b.at || a.schedule.localeCompare(b.schedule))
+    .slice(0, count);
+}
@@ -1,4 +1,5 @@
-import type { SimilarGroup, MediaKind } from '$lib/types';
+import type { SimilarGroup, MediaKind, FileDetails } from '$lib/types';
+import { ServiceError } from '../lib/api/errors';
 
 // Unix seconds (the backend's FileInfo.modified is seconds, not millis)
 const nowSecs = () => Math.floor(Date.now() / 1000);
@@ -26,7 +27,7 @@ export function mockFindSimilarMedia(
   }
   if (path.includes('locked')) {
     return new Promise((_resolve, reject) =>
-      setTimeout(() => reject(new Error('Permission denied (os error 13)')), 300)
+      setTimeout(() => reject(new ServiceError('permission_denied', 'Permission denied (os error 13)')), 300)
     );
   }
 
@@ -139,3 +140,45 @@ export function mockImageThumbnail(path: string, _maxSize: number): Promise<stri
     setTimeout(() => resolve(`data:image/svg+xml;utf8,${encodeURIComponent(svg)}`), 150)
   );
 }
+
+// Details to go with the mock groups above: resolution follows the variant
+// suffix (edit / copy / thumb), JPEGs carry camera EXIF (with GPS under
+// "trip"), videos get ffprobe-style stream info. Paths containing "missing"
+// reject like a file deleted since the scan.
+export function mockFileDetails(path: string): Promise<FileDetails> {
+  if (path.includes('missing')) {
+    return Promise.reject(new ServiceError('not_found', `No such file or directory (os error 2): ${path}`));
+  }
+  const ext = path.split('.').pop()?.toLowerCase() ?? '';
+  const isVideo = ['mp4', 'mov', 'mkv', 'avi', 'webm'].includes(ext);
+  const isImage = ['jpg', 'jpeg', 'png', 'webp', 'gif'].includes(ext);
+  const [width, height] = /-thumb\./.test(path) ? [640, 360]
+    : /-copy\./.test(path) ? [1280, 720]
+    : /-edit\./.test(path) ? [1920, 1440]
+    : isVideo ? [1920, 1080] : [4032, 3024];
+  const modified = nowSecs() - 86400;
+  return new Promise((resolve) =>
+    setTimeout(() => resolve({
+      path,
+      size: 1000 + (hashHue(path) * 7919),
+      file_type: isVideo ? 'Video' : isImage ? 'Image' : 'Other',
+      modified,
+      created: modified - 3600,
+      accessed: nowSecs(),
+      width: isVideo || isImage ? width : null,
+      height: isVideo || isImage ? height : null,
+      exif: ext === 'jpg' || ext === 'jpeg'
+        ? {
+            camera_make: 'Canon',
+            camera_model: 'Canon EOS R6',
+            date_taken: '2024:05:01 09:59:58',
+            orientation: 1,
+            has_gps: path.includes('trip'),
+          }
+        : null,
+      video: isVideo
+        ? { duration_secs: 42.5, width, height, codec: 'h264', frame_rate: 29.97, bit_rate: 8_000_000 }
+        : null,
+    }), 100)
+  );
+}
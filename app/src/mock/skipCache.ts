@@ -23,3 +23,26 @@ export const mockSkipCache = {
     return removed;
   },
 };
+
+// Web-mode stand-in for the backend's compression history table, limited to
+// user skip decisions: skipCompressionFiles records a path, the next
+// compressible-files scan rejects it, and clearing skip memory forgets it.
+const userSkipped = new Set<string>();
+
+export const mockCompressionHistory = {
+  skip(path: string): void {
+    userSkipped.add(path);
+  },
+  has(path: string): boolean {
+    return userSkipped.has(path);
+  },
+  size(): number {
+    return userSkipped.size;
+  },
+  /** Returns how many records were removed */
+  clear(): number {
+    const removed = userSkipped.size;
+    userSkipped.clear();
+    return removed;
+  },
+};
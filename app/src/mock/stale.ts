@@ -0,0 +1,34 @@
+import type { StaleDirectory } from "../lib/types";
+
+const DAY = 86_400;
+
+// Mock old files under a scan root. Each file has a fixed age in days, so
+// raising olderThanDays in web mode shrinks the report like the backend
+// would. Paths containing "empty-dir" have nothing old (empty-state UI).
+export function mockFindStale(path: string, olderThanDays: number): Promise<StaleDirectory[]> {
+  const files = path.includes("empty-dir") ? [] : [
+    { path: `${path}/Projects/2019-thesis/data.csv`, size: 734003200, ageDays: 1900 },
+    { path: `${path}/Projects/2019-thesis/draft.docx`, size: 2097152, ageDays: 1750 },
+    { path: `${path}/Downloads/installer.dmg`, size: 524288000, ageDays: 800 },
+    { path: `${path}/Downloads/report.pdf`, size: 1048576, ageDays: 120 },
+    { path: `${path}/Videos/holiday.mp4`, size: 1610612736, ageDays: 400 },
+  ];
+  const now = Math.floor(Date.now() / 1000);
+  const byDir = new Map<string, StaleDirectory>();
+  for (const file of files.filter(f => f.ageDays >= olderThanDays)) {
+    const dir = file.path.slice(0, file.path.lastIndexOf("/"));
+    const entry = byDir.get(dir) ?? { path: dir, file_count: 0, total_size: 0, files: [] };
+    entry.files.push({
+      path: file.path,
+      size: file.size,
+      modified: now - file.ageDays * DAY,
+      accessed: now - file.ageDays * DAY,
+    });
+    entry.file_count += 1;
+    entry.total_size += file.size;
+    byDir.set(dir, entry);
+  }
+  return new Promise((resolve) => {
+    setTimeout(() => resolve([...byDir.values()]), 300);
+  });
+}
@@ -0,0 +1,62 @@
+import type { TagCount, TagRecord, TagTarget } from "../lib/types";
+import { ServiceError } from "../lib/api/errors";
+
+// Web-mode stand-in for the tags table: entries live in memory only. Tags
+// are normalized like the backend's (trimmed, lowercase).
+let records: TagRecord[] = [];
+let nextId = 1;
+
+function normalize(tag: string): string {
+  const normalized = tag.trim().toLowerCase();
+  if (!normalized) throw new ServiceError("invalid_input", "A tag cannot be empty");
+  return normalized;
+}
+
+function key(target: TagTarget): string {
+  return target.kind === "file" ? target.path : target.hash;
+}
+
+function find(target: TagTarget, tag: string): number {
+  return records.findIndex(r => r.kind === target.kind && r.target === key(target) && r.tag === tag);
+}
+
+export const mockTags = {
+  add(target: TagTarget, tag: string): boolean {
+    const normalized = normalize(tag);
+    if (find(target, normalized) >= 0) return false;
+    records.push({
+      id: nextId++,
+      kind: target.kind,
+      target: key(target),
+      tag: normalized,
+      created_at: Math.floor(Date.now() / 1000),
+    });
+    return true;
+  },
+  remove(target: TagTarget, tag: string): boolean {
+    const index = find(target, normalize(tag));
+    if (index < 0) return false;
+    records.splice(index, 1);
+    return true;
+  },
+  of(target: TagTarget): string[] {
+    return records
+      .filter(r => r.kind === target.kind && r.target === key(target))
+      .map(r => r.tag)
+      .sort();
+  },
+  tagged(tag: string): TagRecord[] {
+    const normalized = normalize(tag);
+    return records.filter(r => r.tag === normalized).reverse();
+  },
+  counts(): TagCount[] {
+    const counts = new Map<string, TagCount>();
+    for (const r of records) {
+      const count = counts.get(r.tag) ?? { tag: r.tag, files: 0, groups: 0 };
+      if (r.kind === "file") count.files++;
+      else count.groups++;
+      counts.set(r.tag, count);
+    }
+    return [...counts.values()].sort((a, b) => a.tag.localeCompare(b.tag));
+  },
+};
@@ -1,4 +1,4 @@
-import type { ToolStatus } from '$lib/types';
+import type { PluginRequirements, ToolCheck, ToolStatus } from '$lib/types';
 
 /**
  * Web-mode external tool detection. Returns a deliberate mix so the settings
@@ -41,3 +41,63 @@ export function mockDetectTools(): Promise<ToolStatus[]> {
     )
   );
 }
+
+/**
+ * Web-mode plugin requirement check, mirroring check_plugin_requirements.
+ * By default gif2webp and dcraw are missing and ffmpeg (as in
+ * mockDetectTools) works.
+ * A configured tool path is probed instead of PATH; paths containing
+ * "missing" fail like a nonexistent binary.
+ */
+export function mockPluginRequirements(
+  toolPaths: Record<string, string>
+): Promise<PluginRequirements[]> {
+  const check = (name: string, onPath: boolean, version: string): ToolCheck => {
+    const configured = toolPaths[name]?.trim();
+    const available = configured ? !configured.includes('missing') : onPath;
+    return {
+      name,
+      program: configured || name,
+      available,
+      version: available ? version : null,
+      error: available ? null : 'No such file or directory (os error 2)',
+    };
+  };
+  const tools = [check('gif2webp', false, '1.4.0'), check('ffmpeg', true, 'ffmpeg version 6.1.1')];
+  const working = tools.find((t) => t.available);
+  const dcraw = check('dcraw', false, 'Raw photo decoder "dcraw" v9.28');
+  const ready = (plugin_name: string): PluginRequirements => ({
+    plugin_name,
+    tools: [],
+    ready: true,
+    message: null,
+  });
+
+  return new Promise((resolve) =>
+    setTimeout(
+      () =>
+        resolve([
+          ready('Image ZIP to WebP ZIP'),
+          ready('WebP Converter'),
+          ready('Text Asset Minifier'),
+          {
+            plugin_name: 'Animated WebP Converter',
+            tools,
+            ready: true,
+            message: working
+              ? `GIFs are converted with ${working.name}`
+              : 'Neither gif2webp nor ffmpeg works; GIFs use the built-in encoder',
+          },
+          {
+            plugin_name: 'RAW to JPEG',
+            tools: [dcraw],
+            ready: true,
+            message: dcraw.available
+              ? 'RAW files are developed in full with dcraw'
+              : 'dcraw not found; RAWs with a full-size embedded preview are converted from it',
+          },
+        ]),
+      150
+    )
+  );
+}
@@ -0,0 +1,65 @@
+import type { CategoryTotal, TreemapNode } from "../lib/types";
+
+// Mock treemap under a scan root: a fixed set of files, folded into
+// directory nodes `depth` levels deep like the backend. Paths containing
+// "empty-dir" give a root with nothing beneath it.
+const FILES: { path: string; size: number; category: string }[] = [
+  { path: "Videos/2023/holiday.mp4", size: 1610612736, category: "Video" },
+  { path: "Videos/2024/birthday.mp4", size: 838860800, category: "Video" },
+  { path: "Photos/2024/beach.jpg", size: 6291456, category: "Image" },
+  { path: "Photos/2024/raw/beach.cr2", size: 31457280, category: "Other" },
+  { path: "Projects/thesis/data.csv", size: 734003200, category: "Other" },
+  { path: "Projects/thesis/draft.docx", size: 2097152, category: "Document" },
+  { path: "Downloads/backup.zip", size: 104857600, category: "Archive" },
+  { path: "notes.txt", size: 8192, category: "Document" },
+];
+
+interface Builder {
+  size: number;
+  file_count: number;
+  types: Map<string, CategoryTotal>;
+  children: Map<string, Builder>;
+}
+
+function builder(): Builder {
+  return { size: 0, file_count: 0, types: new Map(), children: new Map() };
+}
+
+function add(node: Builder, category: string, size: number) {
+  node.size += size;
+  node.file_count += 1;
+  const total = node.types.get(category) ?? { category, file_count: 0, total_size: 0 };
+  total.file_count += 1;
+  total.total_size += size;
+  node.types.set(category, total);
+}
+
+function build(node: Builder, path: string): TreemapNode {
+  return {
+    name: path.split("/").filter(Boolean).pop() ?? path,
+    path,
+    size: node.size,
+    file_count: node.file_count,
+    types: [...node.types.values()].sort((a, b) => b.total_size - a.total_size),
+    children: [...node.children]
+      .map(([name, child]) => build(child, `${path}/${name}`))
+      .sort((a, b) => b.size - a.size),
+  };
+}
+
+export function mockTreemap(path: string, depth: number): Promise<TreemapNode> {
+  const root = builder();
+  for (const file of path.includes("empty-dir") ? [] : FILES) {
+    let node = root;
+    add(node, file.category, file.size);
+    for (const dir of file.path.split("/").slice(0, -1).slice(0, depth)) {
+      const child = node.children.get(dir) ?? builder();
+      node.children.set(dir, child);
+      node = child;
+      add(node, file.category, file.size);
+    }
+  }
+  return new Promise((resolve) => {
+    setTimeout(() => resolve(build(root, path)), 300);
+  });
+}
@@ -1,12 +1,45 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+mod output;
+mod progress;
+mod review;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
 use comfy_table::{presets::UTF8_FULL, Table};
-use indicatif::{ProgressBar, ProgressStyle};
-use std::path::PathBuf;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::io::BufRead;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use space_saver_core::compress_plugins::{
+    CompressionOutcome, PluginManager, PluginMetadata, PluginProgress, ProgressSink, ToolCheck,
+};
+use space_saver_core::{
+    parse_size, scanner::DefaultFileScanner, ArchiveFormat, Compressor, FileFilter, FileInfo,
+    FileScanner, HashKind, StorageBackend,
+};
+use space_saver_db::{Database, QuarantineRecord, ReferenceRoot, RestorePoint, SqliteDatabase};
+use space_saver_service::api::{FileError, FilterConfig, ScanResult, StaleFoldersReport};
+use space_saver_service::checksum::{self, ChecksumResult, ChecksumStatus, Manifest};
+use space_saver_service::daemon::{self, Daemon};
+use space_saver_service::dev_artifacts::DEFAULT_UNTOUCHED_MONTHS;
+use space_saver_service::restore;
+use space_saver_service::{
+    ActionPlan, CacheCategory, CleanupReport, DeleteMode, DeleteResult, DriveKind,
+    DuplicateResolution, ExportFormat, ExportResults, FileOperations, HookOutcome, Hooks,
+    IgnoreList, KeepRule, NetworkShares, PlannedAction, ProtectedPaths, Quarantine, ServiceApi,
+    TagTarget,
+};
+use space_saver_utils::{
+    format_duration, format_size, format_timestamp, init_logger, init_logger_with_file,
+    parse_time_bound, Config, Error, ErrorCode,
+};
 
-use space_saver_core::{scanner::DefaultFileScanner, FileFilter, FileScanner};
-use space_saver_service::{FileOperations, ServiceApi};
-use space_saver_utils::{format_duration, format_size, init_logger, Config};
+use output::{csv, emit, note, OutputFormat};
+use progress::LiveProgress;
+use review::{Decision, ReviewFile, ReviewGroup};
 
 /// Space Saver - Disk space management utility
 #[derive(Parser)]
@@ -20,6 +53,32 @@ struct Cli {
     /// Verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Output format: table for people, or json or csv on stdout for
+    /// scripts (status lines then go to stderr)
+    #[arg(long, global = true, value_enum, default_value = "table")]
+    format: OutputFormat,
+
+    /// Only show what would change: the files, what happens to each and the
+    /// space freed. Commands that cannot plan refuse to run.
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Go ahead without asking for confirmation, for scripts (needed when
+    /// paths come from --stdin)
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Act on paths protected in config.toml instead of refusing to run
+    /// (the operating system's directories stay protected)
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Worker threads for hashing and compression (defaults to one per core
+    /// for hashing and to max_concurrent_tasks from config.toml for
+    /// compression)
+    #[arg(short, long, global = true, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
 }
 
 #[derive(Subcommand)]
@@ -32,16 +91,84 @@ enum Commands {
         /// Show detailed output
         #[arg(short, long)]
         detailed: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
     },
 
     /// Find duplicate files
     Duplicates {
+        /// Directory to scan (leave out with --stdin or --stdin0)
+        #[arg(required_unless_present_any = ["stdin", "stdin0"], conflicts_with_all = ["stdin", "stdin0"])]
+        path: Option<PathBuf>,
+
+        #[command(flatten)]
+        input: StdinArgs,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+
+        #[command(flatten)]
+        export: ExportArgs,
+    },
+
+    /// Remove duplicate files, keeping one copy per group
+    Dedupe {
+        /// Directory to scan
+        path: PathBuf,
+
+        /// Which copy to keep: newest, oldest, shortest-path or first-path
+        /// (the path that sorts first)
+        #[arg(short, long, default_value = "shortest-path")]
+        keep: KeepRule,
+
+        /// Keep the copies inside this directory instead (groups with no
+        /// copy there are left alone)
+        #[arg(long, value_name = "DIR", conflicts_with = "keep")]
+        keep_in: Option<PathBuf>,
+
+        /// What to do with the other copies
+        #[arg(short, long, value_enum, default_value_t = DedupeAction::Trash)]
+        action: DedupeAction,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Go through duplicate (or similar) groups one at a time, mark which
+    /// copies to keep and delete the rest at the end
+    Review {
         /// Directory to scan
         path: PathBuf,
 
-        /// Minimum file size to consider (in bytes)
-        #[arg(short, long, default_value = "0")]
-        min_size: u64,
+        /// Review similar images instead of identical duplicates
+        #[arg(long)]
+        similar: bool,
+
+        /// Similarity threshold for --similar (0.0 to 1.0)
+        #[arg(short, long, default_value = "0.9", requires = "similar")]
+        threshold: f32,
+
+        /// How duplicates are pre-marked: newest, oldest or shortest-path
+        #[arg(
+            short,
+            long,
+            default_value = "shortest-path",
+            conflicts_with = "similar"
+        )]
+        keep: KeepRule,
+
+        /// Delete permanently instead of moving to the trash
+        #[arg(long)]
+        permanent: bool,
+
+        /// Move the files into quarantine instead, restorable until the
+        /// configured retention period ends
+        #[arg(long, conflicts_with = "permanent")]
+        quarantine: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
     },
 
     /// Find similar images
@@ -52,6 +179,12 @@ enum Commands {
         /// Similarity threshold (0.0 to 1.0)
         #[arg(short, long, default_value = "0.9")]
         threshold: f32,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+
+        #[command(flatten)]
+        export: ExportArgs,
     },
 
     /// Find empty files
@@ -59,248 +192,6002 @@ enum Commands {
         /// Directory to scan
         path: PathBuf,
 
-        /// Delete empty files
+        /// Delete empty files (moved to the trash unless --permanent)
+        #[arg(short, long)]
+        delete: bool,
+
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, requires = "delete")]
+        permanent: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Find empty folders: the topmost folder of every tree that holds no
+    /// files
+    EmptyDirs {
+        /// Directory to scan (never reported itself)
+        path: PathBuf,
+
+        /// Delete the empty trees, deepest folders first (moved to the trash
+        /// whole unless --permanent)
         #[arg(short, long)]
         delete: bool,
+
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, requires = "delete")]
+        permanent: bool,
+    },
+
+    /// Delete files, or empty folders, named on the command line or piped
+    /// in: moved to the trash unless --permanent or --quarantine
+    Delete {
+        /// Files to delete (leave out with --stdin or --stdin0)
+        #[arg(required_unless_present_any = ["stdin", "stdin0"], conflicts_with_all = ["stdin", "stdin0"])]
+        paths: Vec<PathBuf>,
+
+        #[command(flatten)]
+        input: StdinArgs,
+
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, conflicts_with = "quarantine")]
+        permanent: bool,
+
+        /// Move them into quarantine, restorable until the configured
+        /// retention period ends
+        #[arg(long)]
+        quarantine: bool,
     },
 
     /// Show storage statistics
     Stats {
-        /// Directory to analyze
+        /// Directory to analyze (leave out with --stdin or --stdin0)
+        #[arg(required_unless_present_any = ["stdin", "stdin0"], conflicts_with_all = ["stdin", "stdin0"])]
+        path: Option<PathBuf>,
+
+        #[command(flatten)]
+        input: StdinArgs,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+
+        #[command(flatten)]
+        export: ExportArgs,
+    },
+
+    /// Compare recorded scans of a directory: growth per file category and
+    /// the files added, removed or resized
+    Diff {
+        /// Directory whose recorded scans to compare
         path: PathBuf,
+
+        /// Earlier scan id (defaults to the second most recent scan)
+        #[arg(long)]
+        from: Option<i64>,
+
+        /// Later scan id (defaults to the most recent scan)
+        #[arg(long)]
+        to: Option<i64>,
     },
 
-    /// Show configuration
-    Config,
-}
+    /// List recorded scans, or the duplicate groups last found
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+        /// How many recent scans to list
+        #[arg(short = 'n', long, alias = "count", default_value = "20")]
+        limit: usize,
 
-    // Initialize logger
-    if cli.verbose {
-        init_logger();
-    }
+        /// List the recorded duplicate groups instead
+        #[arg(long)]
+        duplicates: bool,
+    },
 
-    match cli.command {
-        Commands::Scan { path, detailed } => {
-            scan_command(path, detailed).await?;
-        }
-        Commands::Duplicates { path, min_size } => {
-            duplicates_command(path, min_size).await?;
-        }
-        Commands::Similar { path, threshold } => {
-            similar_command(path, threshold).await?;
-        }
-        Commands::Empty { path, delete } => {
-            empty_command(path, delete).await?;
-        }
-        Commands::Stats { path } => {
-            stats_command(path).await?;
-        }
-        Commands::Config => {
-            config_command().await?;
-        }
-    }
+    /// Report the space compression has saved, per plugin and per month
+    Savings,
 
-    Ok(())
-}
+    /// Forget recorded files no longer on disk and compact the app database
+    Maintenance,
 
-async fn scan_command(path: PathBuf, detailed: bool) -> Result<()> {
-    println!("Scanning: {}", path.display());
+    /// Show the largest files and folders
+    Largest {
+        /// Directory to scan
+        path: PathBuf,
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    pb.set_message("Scanning files...");
+        /// How many files and folders to list
+        #[arg(short = 'n', long, default_value = "10")]
+        count: usize,
 
-    let scanner = DefaultFileScanner::new();
-    let start = std::time::Instant::now();
-    let files = scanner.scan(&path)?;
-    let duration = start.elapsed();
+        /// Filters apply to the file list; folder sizes always count everything
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
 
-    pb.finish_with_message("Scan completed");
+    /// Write a standalone HTML report to share: storage by file type, the
+    /// largest files and folders, duplicates and compression opportunities
+    Report {
+        /// Directory to report on
+        path: PathBuf,
 
-    let total_size: u64 = files.iter().map(|f| f.size).sum();
+        /// HTML file to write
+        #[arg(short, long, default_value = "space-saver-report.html")]
+        output: PathBuf,
 
-    println!("\n📊 Scan Results:");
-    println!("  Files found: {}", files.len());
-    println!("  Total size: {}", format_size(total_size));
-    println!("  Duration: {}", format_duration(duration));
+        /// How many entries each list of the report shows
+        #[arg(short = 'n', long, default_value_t = space_saver_service::DEFAULT_REPORT_TOP)]
+        top: usize,
 
-    if detailed && !files.is_empty() {
-        println!("\n📁 Top 10 largest files:");
-        let mut sorted_files = files;
-        sorted_files.sort_by_key(|f| std::cmp::Reverse(f.size));
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
 
-        let mut table = Table::new();
-        table.load_preset(UTF8_FULL);
-        table.set_header(vec!["Size", "Path"]);
+    /// Hash a file, or every file in a directory, into a checksum manifest
+    /// that `sha256sum -c`, `b3sum -c` or `checksum verify` can check
+    Hash {
+        /// File or directory to hash
+        path: PathBuf,
 
-        for file in sorted_files.iter().take(10) {
-            table.add_row(vec![
-                format_size(file.size),
-                file.path.display().to_string(),
-            ]);
-        }
+        /// Hash algorithm: blake3, sha256 or xxh3
+        #[arg(short, long, default_value_t = HashKind::Blake3)]
+        algo: HashKind,
 
-        println!("{table}");
-    }
+        /// Write the manifest to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 
-    Ok(())
-}
+    /// Work with checksum manifests written by `hash`
+    Checksum {
+        #[command(subcommand)]
+        action: ChecksumAction,
+    },
 
-async fn duplicates_command(path: PathBuf, min_size: u64) -> Result<()> {
-    println!("Finding duplicates in: {}", path.display());
+    /// Find folders whose whole trees are identical
+    DuplicateDirs {
+        /// Directory to scan
+        path: PathBuf,
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    pb.set_message("Scanning and hashing files...");
+        /// Delete all copies but the one with the shortest path
+        #[arg(short, long)]
+        delete: bool,
 
-    let api = ServiceApi::new();
-    let duplicates = api.find_duplicates(path, None).await?;
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, requires = "delete")]
+        permanent: bool,
+    },
 
-    pb.finish_with_message("Analysis completed");
+    /// Find files untouched for a while, grouped by the folders directly
+    /// beneath the directory with what each would free, and list, archive
+    /// or trash them
+    #[command(alias = "old-files")]
+    Stale {
+        /// Directory to scan
+        path: PathBuf,
 
-    if duplicates.is_empty() {
-        println!("\n✅ No duplicate files found!");
-        return Ok(());
-    }
+        /// Files neither modified nor accessed for this many days; or give
+        /// --older-than a date or duration such as 1y (defaults to a year)
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u32>,
 
-    let filtered: Vec<_> = duplicates
-        .into_iter()
-        .filter(|d| d.files[0].size >= min_size)
-        .collect();
+        /// What to do with the stale files
+        #[arg(short, long, value_enum, default_value_t = StaleAction::List)]
+        action: StaleAction,
 
-    let total_wasted: u64 = filtered.iter().map(|d| d.wasted_space).sum();
+        /// Archive type for --action archive: zip, 7z or tar.zst
+        #[arg(short = 't', long = "type", default_value = "zip")]
+        archive_type: ArchiveFormat,
 
-    println!("\n📊 Duplicate Files:");
-    println!("  Groups found: {}", filtered.len());
-    println!("  Wasted space: {}", format_size(total_wasted));
+        /// Where --action archive stores the archives (defaults to the
+        /// scanned directory, next to each folder)
+        #[arg(long, value_name = "DIR")]
+        archive_dir: Option<PathBuf>,
 
-    for (idx, group) in filtered.iter().take(10).enumerate() {
-        println!("\n  Group {} (Hash: {}...)", idx + 1, &group.hash[..8]);
-        println!("    Files: {}", group.count);
-        println!("    Size each: {}", format_size(group.files[0].size));
-        println!("    Wasted: {}", format_size(group.wasted_space));
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
 
-        for file in &group.files {
-            println!("      - {}", file.path.display());
-        }
-    }
+    /// Compress files in place with the compression plugins: shows the
+    /// estimated savings and asks before touching anything
+    Compress {
+        /// Directory to scan (leave out with --stdin or --stdin0)
+        #[arg(required_unless_present_any = ["stdin", "stdin0"], conflicts_with_all = ["stdin", "stdin0"])]
+        path: Option<PathBuf>,
 
-    Ok(())
+        #[command(flatten)]
+        input: StdinArgs,
+
+        /// Plugins to use, in order of preference, e.g. 'webp,raw'; a name
+        /// picks the plugin called that, or else every plugin whose name
+        /// contains it (defaults to all)
+        #[arg(short, long, value_name = "NAME", value_delimiter = ',')]
+        plugins: Vec<String>,
+
+        /// Leave files alone that would shrink by less than this percent
+        /// (defaults to min_savings_percent from config.toml)
+        #[arg(long, value_name = "PERCENT")]
+        min_savings: Option<f32>,
+
+        /// Delete the originals instead of keeping them as <name>.bak
+        /// (defaults to default_compress_backup from config.toml)
+        #[arg(long)]
+        no_backup: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Inspect, configure and try the compression plugins (lists them
+    /// without an action)
+    Plugins {
+        #[command(subcommand)]
+        action: Option<PluginsAction>,
+    },
+
+    /// Archive a file or directory into a ZIP, 7z or tar.zst
+    Archive {
+        /// File or directory to archive
+        path: PathBuf,
+
+        /// Output archive (defaults to <path>.<type>)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Archive type: zip, 7z for better ratios (LZMA2), or tar.zst to
+        /// keep permissions and symlinks
+        #[arg(short = 't', long = "type", default_value = "zip")]
+        archive_type: ArchiveFormat,
+
+        /// Cold storage: verify the archive, record it in the database and
+        /// delete the original directory
+        #[arg(long)]
+        replace: bool,
+
+        /// Encrypt with AES-256 using the password stored in this
+        /// environment variable (kept off the command line)
+        #[arg(long, value_name = "VAR")]
+        password_env: Option<String>,
+    },
+
+    /// Undo compressions that kept a backup, and take files this tool moved
+    /// to the trash back out (lists what can be undone without --id or
+    /// --path)
+    Restore {
+        /// List what can be undone, under --path when given
+        #[arg(short, long, conflicts_with = "id")]
+        list: bool,
+
+        /// Undo one change, by its id in the list
+        #[arg(long, conflicts_with = "path")]
+        id: Option<i64>,
+
+        /// Undo the latest change to this file, or to every file under this
+        /// directory
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+
+    /// List, restore or purge quarantined files
+    Quarantine {
+        #[command(subcommand)]
+        action: Option<QuarantineAction>,
+    },
+
+    /// Run the cleanup rules from config.toml (only shows the plan unless
+    /// --execute is given)
+    Clean {
+        /// Only run the rule with this name; repeatable
+        #[arg(short, long = "rule", value_name = "NAME")]
+        rules: Vec<String>,
+
+        /// Apply each rule's action instead of only showing the plan
+        #[arg(short, long)]
+        execute: bool,
+    },
+
+    /// Find application caches and build output that are rebuilt when
+    /// needed: browser, package manager and thumbnail caches, and
+    /// node_modules and target folders beneath the paths
+    Caches {
+        /// Directories to look for node_modules and target folders in
+        paths: Vec<PathBuf>,
+
+        /// Remove these categories (comma separated, e.g. npm,target);
+        /// `all` for every one
+        #[arg(long, value_delimiter = ',', value_name = "CATEGORY")]
+        clean: Vec<String>,
+
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, requires = "clean")]
+        permanent: bool,
+    },
+
+    /// Find project folders (Cargo.toml, package.json, pyproject.toml,
+    /// .git) with their build output (target, node_modules, dist, .venv)
+    /// and when it was last built, suggesting it for removal in projects
+    /// untouched for a while
+    #[command(alias = "artifacts")]
+    DevArtifacts {
+        /// Directories to look for projects in
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Suggest projects untouched for this many months
+        #[arg(short, long, default_value_t = DEFAULT_UNTOUCHED_MONTHS)]
+        months: u32,
+
+        /// Remove the build output of the suggested projects
+        #[arg(long)]
+        clean: bool,
+
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, requires = "clean")]
+        permanent: bool,
+    },
+
+    /// Run the schedules and watch rules from config.toml in the background,
+    /// or ask a running daemon for its status or to stop
+    Daemon {
+        #[command(subcommand)]
+        action: Option<DaemonAction>,
+    },
+
+    /// Serve scans, duplicate checks, compression and the scan history over
+    /// HTTP/JSON, for scripts or a web dashboard on a headless machine
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        bind: std::net::SocketAddr,
+
+        /// Token clients send as `Authorization: Bearer <token>`; taken from
+        /// SPACE_SAVER_TOKEN when not given, and generated when neither is
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Also serve the gRPC API on this address
+        #[cfg(feature = "grpc")]
+        #[arg(long)]
+        grpc_bind: Option<std::net::SocketAddr>,
+    },
+
+    /// Index a reference volume (e.g. a NAS) and check local files against it
+    Reference {
+        #[command(subcommand)]
+        action: ReferenceAction,
+    },
+
+    /// Tag files and duplicate groups ("keep", "review later", ...); pick
+    /// tagged files out of scans with --filter tag:keep
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// List mounted drives with their capacity and free space
+    Drives,
+
+    /// Show the configuration, or read and change single settings
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
 }
 
-async fn similar_command(path: PathBuf, threshold: f32) -> Result<()> {
-    println!("Finding similar images in: {}", path.display());
-    println!("Threshold: {:.2}", threshold);
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print one setting, by its dotted key (e.g. `log_level`,
+    /// `scan.max_depth`, `tool_paths.ffmpeg`, `cleanup_rules.0.enabled`)
+    Get { key: String },
+    /// Change one setting; the value is written as in TOML (`30`, `true`,
+    /// `["a", "b"]`), anything else is a string, and `null` clears an
+    /// optional setting
+    Set { key: String, value: String },
+    /// Open the config file in $VISUAL or $EDITOR; the edit is saved only if
+    /// it parses and validates
+    Edit,
+    /// Check a config file without changing anything
+    Validate {
+        /// File to check [default: the config file in use]
+        file: Option<PathBuf>,
+    },
+    /// Put one setting, or the whole configuration, back to its default
+    Reset { key: Option<String> },
+}
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_message("Analyzing images...");
+#[derive(Subcommand)]
+enum QuarantineAction {
+    /// List quarantined files (the default)
+    List,
+    /// Move a quarantined file back to where it came from
+    Restore {
+        /// Entry id, as shown by `quarantine list`
+        id: i64,
+    },
+    /// Permanently delete files whose retention period has ended
+    Purge,
+}
 
-    let api = ServiceApi::new();
-    let similar = api
-        .find_similar_media(path, threshold, vec![], None)
-        .await?;
+#[derive(Subcommand)]
+enum PluginsAction {
+    /// List the plugins, their settings and whether they can run here
+    List,
+    /// Show a plugin's settings, file types and external tools
+    Info {
+        /// Plugin name, or part of it
+        name: String,
+    },
+    /// Turn a plugin back on
+    Enable {
+        /// Plugin name, or part of it
+        name: String,
+    },
+    /// Turn a plugin off so it is never picked for a file
+    Disable {
+        /// Plugin name, or part of it
+        name: String,
+    },
+    /// Change plugin settings: quality=0-100 or enabled=true|false
+    Set {
+        /// Plugin name, or part of it
+        name: String,
 
-    pb.finish_with_message("Analysis completed");
+        /// Settings as key=value
+        #[arg(required = true, value_name = "KEY=VALUE")]
+        settings: Vec<String>,
+    },
+    /// Compress a copy of one file and report what it would save; the file
+    /// itself is left alone
+    Test {
+        file: PathBuf,
 
-    if similar.is_empty() {
-        println!("\n✅ No similar images found!");
-        return Ok(());
-    }
+        /// Plugin to try (defaults to the first enabled one taking the file)
+        #[arg(short, long)]
+        plugin: Option<String>,
+    },
+}
 
-    println!("\n📊 Similar Images:");
-    println!("  Groups found: {}", similar.len());
+#[derive(Subcommand)]
+enum ChecksumAction {
+    /// Hash the files of a manifest again and report the ones that changed,
+    /// went missing or cannot be read; fails when any did
+    Verify {
+        /// Manifest file of `<hash>  <path>` lines
+        manifest: PathBuf,
 
-    for (idx, group) in similar.iter().take(10).enumerate() {
-        println!(
-            "\n  Group {} (Similarity: {:.2}%)",
-            idx + 1,
-            group.similarity_score * 100.0
-        );
-        for file in &group.files {
-            println!("    - {}", file.path);
-        }
-    }
+        /// Directory the manifest's paths are relative to [default: the
+        /// manifest's directory]
+        #[arg(short, long)]
+        root: Option<PathBuf>,
 
-    Ok(())
+        /// Hash algorithm of the manifest [default: from its extension
+        /// (.sha256, .b3, .xxh3), else blake3]
+        #[arg(short, long)]
+        algo: Option<HashKind>,
+    },
 }
 
-async fn empty_command(path: PathBuf, delete: bool) -> Result<()> {
-    println!("Finding empty files in: {}", path.display());
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Show one recorded scan: its categories, largest files and the
+    /// duplicate groups found under its path
+    Show {
+        /// Scan id, as listed by `space-saver history`
+        id: i64,
+    },
+}
 
-    let scanner = DefaultFileScanner::new();
-    let files = scanner.scan(&path)?;
-    let filter = FileFilter::empty_files();
-    let empty_files = filter.filter_files(files);
+#[derive(Subcommand)]
+enum DaemonAction {
+    /// Run in the foreground until stopped, logging to the daemon log (the
+    /// default)
+    Run,
+    /// Show what a running daemon is doing
+    Status,
+    /// Ask a running daemon to stop
+    Stop,
+}
 
-    if empty_files.is_empty() {
-        println!("\n✅ No empty files found!");
-        return Ok(());
-    }
+#[derive(Subcommand)]
+enum ReferenceAction {
+    /// Hash every file of a reference volume and store the hashes, replacing
+    /// its previous index
+    Index {
+        /// Root of the reference volume
+        path: PathBuf,
+    },
+    /// List files that have an identical copy on an indexed reference volume
+    Check {
+        /// Directory to check
+        path: PathBuf,
 
-    println!("\n📊 Empty Files:");
-    println!("  Count: {}", empty_files.len());
+        /// Delete the local files that have a copy
+        #[arg(short, long)]
+        delete: bool,
 
-    if delete {
-        let ops = FileOperations::new();
-        let paths: Vec<_> = empty_files.iter().map(|f| f.path.clone()).collect();
-        let deleted = ops.delete_files(&paths)?;
-        println!("  Deleted: {}", deleted);
-    } else {
-        for file in empty_files.iter().take(20) {
-            println!("  - {}", file.path.display());
-        }
-        if empty_files.len() > 20 {
-            println!("  ... and {} more", empty_files.len() - 20);
-        }
-        println!("\nUse --delete flag to remove these files.");
-    }
+        /// Delete permanently instead of moving to the trash
+        #[arg(long, requires = "delete")]
+        permanent: bool,
 
-    Ok(())
+        #[command(flatten)]
+        filter: Box<FilterArgs>,
+    },
+    /// List indexed reference volumes
+    List,
+    /// Forget the index of a reference volume
+    Remove {
+        /// Root of the reference volume
+        path: PathBuf,
+    },
 }
 
-async fn stats_command(path: PathBuf) -> Result<()> {
-    println!("Analyzing: {}", path.display());
+#[derive(Subcommand)]
+enum TagAction {
+    /// Put a tag on files or duplicate groups
+    Add {
+        tag: String,
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_message("Analyzing storage...");
+        /// Files to tag
+        #[arg(required_unless_present = "groups")]
+        paths: Vec<PathBuf>,
 
-    let api = ServiceApi::new();
-    let stats = api.get_storage_stats(path, None).await?;
+        /// Content hash of a duplicate group to tag; repeatable
+        #[arg(long = "group", value_name = "HASH")]
+        groups: Vec<String>,
+    },
+    /// Take a tag off files or duplicate groups
+    Remove {
+        tag: String,
 
-    pb.finish_with_message("Analysis completed");
+        /// Files to untag
+        #[arg(required_unless_present = "groups")]
+        paths: Vec<PathBuf>,
+
+        /// Content hash of a duplicate group to untag; repeatable
+        #[arg(long = "group", value_name = "HASH")]
+        groups: Vec<String>,
+    },
+    /// List tags in use, or what carries one tag
+    List {
+        /// Show the files and groups with this tag
+        tag: Option<String>,
+    },
+    /// Show the tags of a file
+    Show { path: PathBuf },
+}
 
-    println!("\n📊 Storage Statistics:");
-    println!("  Total files: {}", stats.total_files);
-    println!("  Total size: {}", format_size(stats.total_size));
-    println!("\n📁 By Type:");
-    println!("  Images: {}", stats.images);
-    println!("  Videos: {}", stats.videos);
-    println!("  Documents: {}", stats.documents);
-    println!("  Archives: {}", stats.archives);
-    println!("  Others: {}", stats.others);
-    println!("\n⚠️  Empty files: {}", stats.empty_files);
+/// Writing a command's results to a file
+#[derive(Args)]
+struct ExportArgs {
+    /// Also write the results to this file
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
 
-    Ok(())
+    /// Export format: csv, json or html (defaults to the output file's
+    /// extension)
+    #[arg(long, value_name = "FORMAT", requires = "output")]
+    export_format: Option<ExportFormat>,
 }
 
-async fn config_command() -> Result<()> {
-    let config = Config::load_or_default();
+impl ExportArgs {
+    /// Where to write the results, if anywhere. Checked before scanning so a
+    /// bad format fails fast.
+    fn into_target(self) -> Result<Option<ExportTarget>> {
+        let Some(path) = self.output else {
+            return Ok(None);
+        };
+        let format = match self.export_format {
+            Some(format) => format,
+            None => ExportFormat::from_path(&path).with_context(|| {
+                format!(
+                    "Cannot tell the export format of {}; pass --export-format csv, json or html",
+                    path.display()
+                )
+            })?,
+        };
+        Ok(Some(ExportTarget { path, format }))
+    }
+}
 
-    println!("📝 Configuration:");
-    println!("{}", toml::to_string_pretty(&config)?);
-    println!("\nConfig file: {}", Config::default_path().display());
+struct ExportTarget {
+    path: PathBuf,
+    format: ExportFormat,
+}
 
-    Ok(())
+impl ExportTarget {
+    fn write(&self, results: ExportResults) -> Result<()> {
+        ServiceApi::new().export_results(&results, self.format, &self.path)?;
+        note!("\n💾 Results written to {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// `results` as they are exported to CSV
+fn render_csv(results: &ExportResults) -> String {
+    space_saver_service::export::render(results, ExportFormat::Csv).unwrap_or_default()
+}
+
+/// Taking a command's paths from stdin, to compose with `find` and friends
+#[derive(Args)]
+struct StdinArgs {
+    /// Read the paths to work on from stdin, one per line
+    #[arg(long, conflicts_with = "stdin0")]
+    stdin: bool,
+
+    /// Read the paths from stdin separated by NUL bytes, as
+    /// `find -print0` writes them
+    #[arg(long)]
+    stdin0: bool,
+}
+
+impl StdinArgs {
+    fn is_set(&self) -> bool {
+        self.stdin || self.stdin0
+    }
+
+    /// A command reading its paths from stdin has nothing left to read an
+    /// answer from, so it must be told to go ahead
+    fn check_unattended(&self, yes: bool, dry_run: bool) -> Result<()> {
+        if self.is_set() && !yes && !dry_run {
+            bail!(Error::InvalidInput(
+                "Paths piped in on stdin leave no way to confirm; pass --yes (or --dry-run)"
+                    .to_string()
+            ));
+        }
+        Ok(())
+    }
+
+    /// `paths` from the command line, or the ones piped in on stdin
+    fn paths(&self, paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+        use std::io::{IsTerminal, Read};
+
+        if !self.is_set() {
+            return Ok(paths);
+        }
+        let mut stdin = std::io::stdin();
+        if stdin.is_terminal() {
+            bail!(Error::InvalidInput(
+                "--stdin reads paths piped in, e.g. `find . -print0 | space-saver ... --stdin0`"
+                    .to_string()
+            ));
+        }
+        let mut input = Vec::new();
+        stdin.read_to_end(&mut input)?;
+        let paths = split_paths(&input, if self.stdin0 { b'\0' } else { b'\n' });
+        if paths.is_empty() {
+            bail!(Error::InvalidInput("No paths on stdin".to_string()));
+        }
+        let missing = paths
+            .iter()
+            .filter(|p| p.symlink_metadata().is_err())
+            .count();
+        if missing > 0 {
+            note!("⚠️  {} path(s) from stdin do not exist", missing);
+        }
+        Ok(paths)
+    }
+}
+
+/// The non-empty paths in `input` between `separator` bytes; lines may end
+/// in `\r\n`
+fn split_paths(input: &[u8], separator: u8) -> Vec<PathBuf> {
+    input
+        .split(|&byte| byte == separator)
+        .map(|path| match separator {
+            b'\n' => path.strip_suffix(b"\r").unwrap_or(path),
+            _ => path,
+        })
+        .filter(|path| !path.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+/// Unix file names are bytes, not necessarily UTF-8
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// `paths` for status lines: the path when there is one
+fn describe_paths(paths: &[PathBuf]) -> String {
+    match paths {
+        [path] => path.display().to_string(),
+        _ => format!("{} paths", paths.len()),
+    }
+}
+
+/// File filters shared by the scanning commands
+#[derive(Args)]
+struct FilterArgs {
+    /// Only include files at least this large, e.g. 1MB
+    #[arg(short = 'm', long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Only include files at most this large, e.g. 4GB
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Only include files with these extensions, e.g. 'jpg,png'
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    ext: Vec<String>,
+
+    /// Only include files whose name matches this regular expression,
+    /// e.g. '^IMG_\d{4}\.(jpg|jpeg)$'
+    #[arg(long, value_name = "REGEX")]
+    name_regex: Option<String>,
+
+    /// Only include paths matching this glob, e.g. '**/Downloads/**';
+    /// repeatable, and a leading '!' excludes instead
+    #[arg(long = "include", value_name = "GLOB")]
+    include_globs: Vec<String>,
+
+    /// Skip paths matching this glob, e.g. '**/*.raw'; repeatable
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude_globs: Vec<String>,
+
+    /// Skip files with these extensions, e.g. 'tmp,part'
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    exclude_ext: Vec<String>,
+
+    /// Skip files whose name contains this text; repeatable
+    #[arg(long, value_name = "TEXT")]
+    exclude_name: Vec<String>,
+
+    /// Skip everything at or beneath this path; repeatable
+    #[arg(long, value_name = "PATH")]
+    exclude_path: Vec<String>,
+
+    /// Include dot-files, hidden directories and Windows hidden/system files
+    #[arg(long)]
+    hidden: bool,
+
+    /// Descend at most this many directory levels (1 = only the files
+    /// directly in the directory)
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Follow symbolic links to directories
+    #[arg(long)]
+    follow_links: bool,
+
+    /// Only include files owned by the current user (Unix)
+    #[arg(long)]
+    mine: bool,
+
+    /// Only include files owned by this user id (Unix)
+    #[arg(long, value_name = "UID")]
+    uid: Option<u32>,
+
+    /// Only include files owned by this group id (Unix)
+    #[arg(long, value_name = "GID")]
+    gid: Option<u32>,
+
+    /// Only include files with all these octal permission bits set,
+    /// e.g. 002 for world-writable (Unix)
+    #[arg(long, value_name = "OCTAL")]
+    perm: Option<String>,
+
+    /// Filter expression, e.g.
+    /// 'size>100MB and ext in (mp4,mkv) and modified<2023-01-01'
+    #[arg(long, value_name = "EXPR")]
+    filter: Option<String>,
+
+    /// Only include files modified since a date or within a duration,
+    /// e.g. 2024-01-01 or 30d
+    #[arg(long, value_name = "WHEN")]
+    newer_than: Option<String>,
+
+    /// Only include files untouched since a date or for a duration,
+    /// e.g. 2020 or 1y
+    #[arg(long, value_name = "WHEN")]
+    older_than: Option<String>,
+}
+
+impl FilterArgs {
+    fn into_config(self) -> Option<FilterConfig> {
+        let config = FilterConfig {
+            min_size: self.min_size,
+            max_size: self.max_size,
+            extensions: (!self.ext.is_empty()).then_some(self.ext),
+            file_regex: self.name_regex,
+            include_globs: (!self.include_globs.is_empty()).then_some(self.include_globs),
+            exclude_globs: (!self.exclude_globs.is_empty()).then_some(self.exclude_globs),
+            exclude_extensions: (!self.exclude_ext.is_empty()).then_some(self.exclude_ext),
+            exclude_patterns: (!self.exclude_name.is_empty()).then_some(self.exclude_name),
+            exclude_paths: (!self.exclude_path.is_empty()).then_some(self.exclude_path),
+            newer_than: self.newer_than,
+            older_than: self.older_than,
+            query: self.filter,
+            current_user_only: self.mine.then_some(true),
+            owner_uid: self.uid,
+            owner_gid: self.gid,
+            permissions: self.perm,
+            include_hidden: self.hidden.then_some(true),
+            max_depth: self.max_depth,
+            follow_links: self.follow_links.then_some(true),
+            ..Default::default()
+        };
+        (!config.is_empty()).then_some(config)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(exit_code(Error::from(e).code()))
+        }
+    }
+}
+
+/// Commands that change files, records or settings without being able to
+/// plan first. They refuse --dry-run rather than run for real.
+fn lacks_dry_run(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Archive { .. } => Some("archive"),
+        Commands::Restore {
+            list: false,
+            id,
+            path,
+        } if id.is_some() || path.is_some() => Some("restore"),
+        Commands::Quarantine {
+            action: Some(QuarantineAction::Restore { .. } | QuarantineAction::Purge),
+        } => Some("quarantine"),
+        Commands::Maintenance => Some("maintenance"),
+        Commands::Plugins {
+            action:
+                Some(
+                    PluginsAction::Enable { .. }
+                    | PluginsAction::Disable { .. }
+                    | PluginsAction::Set { .. },
+                ),
+        } => Some("plugins"),
+        Commands::Config {
+            action: Some(ConfigAction::Set { .. } | ConfigAction::Edit | ConfigAction::Reset { .. }),
+        } => Some("config"),
+        Commands::Tag {
+            action: TagAction::Add { .. } | TagAction::Remove { .. },
+        } => Some("tag"),
+        Commands::Reference {
+            action: ReferenceAction::Index { .. } | ReferenceAction::Remove { .. },
+        } => Some("reference"),
+        Commands::Daemon { .. } => Some("daemon"),
+        #[cfg(feature = "server")]
+        Commands::Serve { .. } => Some("serve"),
+        _ => None,
+    }
+}
+
+/// Process exit status for a failure of kind `code`, following the BSD
+/// sysexits convention where one fits; 130 for a cancelled run, as for
+/// Ctrl+C
+fn exit_code(code: ErrorCode) -> u8 {
+    match code {
+        ErrorCode::InvalidInput => 64,
+        ErrorCode::NotFound => 66,
+        ErrorCode::Io => 74,
+        ErrorCode::TimedOut => 75,
+        ErrorCode::PermissionDenied | ErrorCode::Protected => 77,
+        ErrorCode::Config => 78,
+        ErrorCode::Cancelled => 130,
+        _ => 1,
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    // Initialize logger; a running daemon logs to its file instead
+    let daemon = matches!(
+        cli.command,
+        Commands::Daemon {
+            action: None | Some(DaemonAction::Run)
+        }
+    );
+    if cli.verbose && !daemon {
+        init_logger();
+    }
+    output::set_format(cli.format);
+    if let Some(jobs) = cli.jobs {
+        set_jobs(jobs.get())?;
+    }
+    let _ = FORCE.set(cli.force);
+    if let (true, Some(command)) = (cli.dry_run, lacks_dry_run(&cli.command)) {
+        bail!(Error::InvalidInput(format!(
+            "`{}` cannot do a dry run; leave out --dry-run",
+            command
+        )));
+    }
+
+    match cli.command {
+        Commands::Scan {
+            path,
+            detailed,
+            filter,
+        } => {
+            scan_command(path, detailed, filter.into_config()).await?;
+        }
+        Commands::Duplicates {
+            path,
+            input,
+            filter,
+            export,
+        } => {
+            let export = export.into_target()?;
+            let paths = input.paths(path.into_iter().collect())?;
+            duplicates_command(paths, filter.into_config(), export).await?;
+        }
+        Commands::Dedupe {
+            path,
+            keep,
+            keep_in,
+            action,
+            filter,
+        } => {
+            let rule = match keep_in {
+                Some(dir) => KeepRule::InDirectory {
+                    prefix: std::path::absolute(dir)?.to_string_lossy().to_string(),
+                },
+                None => keep,
+            };
+            dedupe_command(
+                path,
+                rule,
+                action,
+                cli.dry_run,
+                cli.yes,
+                filter.into_config(),
+            )
+            .await?;
+        }
+        Commands::Review {
+            path,
+            similar,
+            threshold,
+            keep,
+            permanent,
+            quarantine,
+            filter,
+        } => {
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            let find = if similar {
+                ReviewGroups::Similar { threshold }
+            } else {
+                ReviewGroups::Duplicates { keep }
+            };
+            review_command(
+                path,
+                find,
+                mode,
+                quarantine,
+                cli.dry_run,
+                filter.into_config(),
+            )
+            .await?;
+        }
+        Commands::Similar {
+            path,
+            threshold,
+            filter,
+            export,
+        } => {
+            similar_command(path, threshold, filter.into_config(), export.into_target()?).await?;
+        }
+        Commands::Empty {
+            path,
+            delete,
+            permanent,
+            filter,
+        } => {
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            let delete = delete.then_some(mode);
+            empty_command(path, delete, cli.dry_run, cli.yes, filter.into_config()).await?;
+        }
+        Commands::EmptyDirs {
+            path,
+            delete,
+            permanent,
+        } => {
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            empty_dirs_command(path, delete.then_some(mode), cli.dry_run, cli.yes)?;
+        }
+        Commands::Delete {
+            paths,
+            input,
+            permanent,
+            quarantine,
+        } => {
+            input.check_unattended(cli.yes, cli.dry_run)?;
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            let paths = input.paths(paths)?;
+            delete_command(paths, mode, quarantine, cli.yes, cli.dry_run)?;
+        }
+        Commands::Stats {
+            path,
+            input,
+            filter,
+            export,
+        } => {
+            let export = export.into_target()?;
+            let paths = input.paths(path.into_iter().collect())?;
+            stats_command(paths, filter.into_config(), export).await?;
+        }
+        Commands::Diff { path, from, to } => {
+            diff_command(path, from, to)?;
+        }
+        Commands::History {
+            action,
+            limit,
+            duplicates,
+        } => match action {
+            Some(HistoryAction::Show { id }) => history_show_command(id)?,
+            None => history_command(limit, duplicates)?,
+        },
+        Commands::Savings => {
+            savings_command()?;
+        }
+        Commands::Maintenance => {
+            maintenance_command()?;
+        }
+        Commands::Largest {
+            path,
+            count,
+            filter,
+        } => {
+            largest_command(path, count, filter.into_config()).await?;
+        }
+        Commands::Report {
+            path,
+            output,
+            top,
+            filter,
+        } => {
+            report_command(path, output, top, filter.into_config())?;
+        }
+        Commands::Hash { path, algo, output } => {
+            hash_command(path, algo, output)?;
+        }
+        Commands::Checksum {
+            action:
+                ChecksumAction::Verify {
+                    manifest,
+                    root,
+                    algo,
+                },
+        } => {
+            checksum_verify_command(manifest, root, algo)?;
+        }
+        Commands::DuplicateDirs {
+            path,
+            delete,
+            permanent,
+        } => {
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            duplicate_dirs_command(path, delete.then_some(mode), cli.dry_run, cli.yes).await?;
+        }
+        Commands::Stale {
+            path,
+            days,
+            action,
+            archive_type,
+            archive_dir,
+            mut filter,
+        } => {
+            // --older-than sets the threshold here rather than filtering
+            let now = space_saver_utils::time::now();
+            let cutoff = match filter.older_than.take() {
+                Some(when) => parse_time_bound(&when, now)?,
+                None => now - i64::from(days.unwrap_or(365)) * 86_400,
+            };
+            let options = StaleOptions {
+                action,
+                archive_type,
+                archive_dir,
+                yes: cli.yes,
+                dry_run: cli.dry_run,
+            };
+            stale_command(path, cutoff, options, filter.into_config()).await?;
+        }
+        Commands::Compress {
+            path,
+            input,
+            plugins,
+            min_savings,
+            no_backup,
+            filter,
+        } => {
+            input.check_unattended(cli.yes, cli.dry_run)?;
+            let options = CompressOptions {
+                plugins,
+                dry_run: cli.dry_run,
+                min_savings,
+                no_backup,
+                yes: cli.yes,
+                piped: input.is_set(),
+            };
+            let paths = input.paths(path.into_iter().collect())?;
+            compress_command(paths, options, filter.into_config()).await?;
+        }
+        Commands::Plugins { action } => {
+            plugins_command(action.unwrap_or(PluginsAction::List))?;
+        }
+        Commands::Archive {
+            path,
+            output,
+            archive_type,
+            replace,
+            password_env,
+        } => {
+            archive_command(path, output, archive_type, replace, password_env).await?;
+        }
+        Commands::Restore { list, id, path } => {
+            restore_command(list, id, path)?;
+        }
+        Commands::Quarantine { action } => {
+            quarantine_command(action.unwrap_or(QuarantineAction::List))?;
+        }
+        Commands::Clean { rules, execute } => {
+            clean_command(rules, execute && !cli.dry_run, cli.yes)?;
+        }
+        Commands::Caches {
+            paths,
+            clean,
+            permanent,
+        } => {
+            let categories = if clean.iter().any(|c| c.eq_ignore_ascii_case("all")) {
+                CacheCategory::ALL.to_vec()
+            } else {
+                clean
+                    .iter()
+                    .map(|c| c.parse())
+                    .collect::<Result<Vec<CacheCategory>>>()?
+            };
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            caches_command(paths, &categories, mode, cli.dry_run, cli.yes)?;
+        }
+        Commands::DevArtifacts {
+            paths,
+            months,
+            clean,
+            permanent,
+        } => {
+            let mode = if permanent {
+                DeleteMode::Permanent
+            } else {
+                DeleteMode::Trash
+            };
+            dev_artifacts_command(paths, months, clean.then_some(mode), cli.dry_run, cli.yes)?;
+        }
+        Commands::Daemon { action } => {
+            daemon_command(action.unwrap_or(DaemonAction::Run)).await?;
+        }
+        #[cfg(feature = "server")]
+        Commands::Serve {
+            bind,
+            token,
+            #[cfg(feature = "grpc")]
+            grpc_bind,
+        } => {
+            let server = serve_command(token)?;
+            #[cfg(feature = "grpc")]
+            let server = match grpc_bind {
+                Some(address) => {
+                    note!("Serving the gRPC API on {}", address);
+                    server.with_grpc_listener(listen(address).await?)
+                }
+                None => server,
+            };
+            note!("Serving the REST API on http://{}/api", bind);
+            note!("Stop it with Ctrl-C");
+            server
+                .serve(listen(bind).await?, async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await?;
+            note!("Server stopped");
+        }
+        Commands::Reference { action } => {
+            reference_command(action, cli.dry_run, cli.yes)?;
+        }
+        Commands::Tag { action } => {
+            tag_command(action)?;
+        }
+        Commands::Drives => {
+            drives_command()?;
+        }
+        Commands::Config { action } => match action {
+            None => config_command().await?,
+            Some(ConfigAction::Get { key }) => config_get_command(&key)?,
+            Some(ConfigAction::Set { key, value }) => config_set_command(&key, &value)?,
+            Some(ConfigAction::Edit) => config_edit_command()?,
+            Some(ConfigAction::Validate { file }) => config_validate_command(file)?,
+            Some(ConfigAction::Reset { key }) => config_reset_command(key)?,
+        },
+    }
+
+    Ok(())
+}
+
+async fn scan_command(path: PathBuf, detailed: bool, filter: Option<FilterConfig>) -> Result<()> {
+    note!("Scanning: {}", path.display());
+
+    let pb = LiveProgress::start("Scanning files...");
+
+    // Unfiltered scans go into the history, so `diff` compares like with
+    // like
+    let api = recording(ServiceApi::new().with_progress(pb.sender()));
+    let start = std::time::Instant::now();
+    let result = api.scan_directory(path, filter).await?;
+    let duration = start.elapsed();
+
+    pb.finish_with_message("Scan completed");
+    if emit(&result, || file_csv(&result.files))? {
+        return Ok(());
+    }
+    let ScanResult {
+        files, total_size, ..
+    } = result;
+
+    note!("\n📊 Scan Results:");
+    note!("  Files found: {}", files.len());
+    note!("  Total size: {}", format_size(total_size));
+    note!("  Duration: {}", format_duration(duration));
+
+    if detailed && !files.is_empty() {
+        note!("\n📁 Top 10 largest files:");
+        let mut sorted_files = files;
+        sorted_files.sort_by_key(|f| std::cmp::Reverse(f.size));
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Size", "Path"]);
+
+        for file in sorted_files.iter().take(10) {
+            table.add_row(vec![
+                format_size(file.size),
+                file.path.display().to_string(),
+            ]);
+        }
+
+        note!("{table}");
+    }
+
+    Ok(())
+}
+
+async fn duplicates_command(
+    paths: Vec<PathBuf>,
+    filter: Option<FilterConfig>,
+    export: Option<ExportTarget>,
+) -> Result<()> {
+    note!("Finding duplicates in: {}", describe_paths(&paths));
+
+    let pb = LiveProgress::start("Scanning and hashing files...");
+
+    let api = recording(
+        ServiceApi::new()
+            .with_ignore_list(ignore_list())
+            .with_network_shares(network_shares())
+            .with_progress(pb.sender()),
+    );
+    let report = match remote_storage(&paths)? {
+        Some(storage) => {
+            api.find_duplicates_in_storage(storage.as_ref(), paths, filter)
+                .await?
+        }
+        None => api.find_duplicate_report(paths, filter).await?,
+    };
+
+    pb.finish_with_message("Analysis completed");
+    print_unreadable(&report.unreadable);
+
+    let filtered = report.groups;
+    if let Some(export) = &export {
+        export.write(ExportResults::Duplicates(filtered.clone()))?;
+    }
+    if emit(&filtered, || {
+        render_csv(&ExportResults::Duplicates(filtered.clone()))
+    })? {
+        return Ok(());
+    }
+
+    if filtered.is_empty() {
+        note!("\n✅ No duplicate files found!");
+        return Ok(());
+    }
+
+    let total_wasted: u64 = filtered.iter().map(|d| d.wasted_space).sum();
+
+    note!("\n📊 Duplicate Files:");
+    note!("  Groups found: {}", filtered.len());
+    note!("  Wasted space: {}", format_size(total_wasted));
+
+    for (idx, group) in filtered.iter().take(10).enumerate() {
+        note!("\n  Group {} (Hash: {}...)", idx + 1, &group.hash[..8]);
+        note!("    Files: {}", group.count);
+        note!("    Size each: {}", format_size(group.files[0].size));
+        note!("    Wasted: {}", format_size(group.wasted_space));
+
+        for file in &group.files {
+            note!("      - {}", file.path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// The object store `paths` are in when they are URLs (`s3://bucket/…`, a
+/// WebDAV `https://host/…`) rather than local paths; all of them must then
+/// be in the same store
+fn remote_storage(paths: &[PathBuf]) -> Result<Option<Box<dyn StorageBackend>>> {
+    let urls: Vec<&str> = paths
+        .iter()
+        .filter_map(|path| path.to_str())
+        .filter(|path| path.contains("://"))
+        .collect();
+    let Some(first) = urls.first() else {
+        return Ok(None);
+    };
+    if urls.len() != paths.len() {
+        bail!(Error::InvalidInput(
+            "Local paths and URLs cannot be mixed".to_string()
+        ));
+    }
+
+    #[cfg(feature = "remote")]
+    {
+        use space_saver_service::object_storage::{is_remote, ObjectStorage};
+        if let Some(url) = urls.iter().find(|url| !is_remote(url)) {
+            bail!(Error::InvalidInput(format!(
+                "{} is not an s3://, http:// or https:// URL",
+                url
+            )));
+        }
+        let storage = ObjectStorage::from_url(first)?;
+        let base = storage.describe();
+        if let Some(url) = urls.iter().find(|url| !url.starts_with(&base)) {
+            bail!(Error::InvalidInput(format!("{} is not in {}", url, base)));
+        }
+        Ok(Some(Box::new(storage)))
+    }
+    #[cfg(not(feature = "remote"))]
+    bail!(Error::InvalidInput(format!(
+        "{} is a URL, but this build cannot read remote storage (the remote feature is off)",
+        first
+    )))
+}
+
+/// One line per file: path, size in bytes, kind and modification time
+fn file_csv(files: &[FileInfo]) -> String {
+    csv(
+        &["path", "size", "file_type", "modified"],
+        files
+            .iter()
+            .map(|file| {
+                vec![
+                    file.path.display().to_string(),
+                    file.size.to_string(),
+                    format!("{:?}", file.file_type),
+                    file.modified.to_string(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+/// Per-file outcomes of deleting, trashing or quarantining files
+/// One line per planned change, then one per file left alone
+fn plan_csv(plan: &ActionPlan) -> String {
+    let planned = plan.actions.iter().map(|action| {
+        vec![
+            action.action.clone(),
+            action.path.display().to_string(),
+            action.size.to_string(),
+            action.reclaimed.map(|r| r.to_string()).unwrap_or_default(),
+            action.detail.clone().unwrap_or_default(),
+        ]
+    });
+    let skipped = plan.skipped.iter().map(|skipped| {
+        vec![
+            "skip".to_string(),
+            skipped.path.display().to_string(),
+            String::new(),
+            String::new(),
+            skipped.error.clone(),
+        ]
+    });
+    csv(
+        &["action", "path", "size", "reclaimed", "detail"],
+        planned.chain(skipped).collect(),
+    )
+}
+
+/// Print what `plan` would change and the space it frees
+fn print_plan(plan: &ActionPlan) {
+    if plan.is_empty() && plan.skipped.is_empty() {
+        note!("\n✅ Nothing to change");
+        return;
+    }
+    if !plan.is_empty() {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Action", "Size", "Frees", "Path", "Detail"]);
+        for action in plan.actions.iter().take(20) {
+            table.add_row(vec![
+                action.action.clone(),
+                format_size(action.size),
+                action
+                    .reclaimed
+                    .map(format_size)
+                    .unwrap_or_else(|| "?".to_string()),
+                action.path.display().to_string(),
+                action.detail.clone().unwrap_or_default(),
+            ]);
+        }
+        note!("{table}");
+        if plan.actions.len() > 20 {
+            note!("  ... and {} more", plan.actions.len() - 20);
+        }
+    }
+    note!("\n📊 Plan:");
+    note!("  Files: {}", plan.actions.len());
+    note!("  Total size: {}", format_size(plan.total_size));
+    note!("  Space freed: {}", format_size(plan.reclaimed));
+    if !plan.skipped.is_empty() {
+        note!("  Left alone: {}", plan.skipped.len());
+        for skipped in plan.skipped.iter().take(10) {
+            note!("    ⏭  {}: {}", skipped.path.display(), skipped.error);
+        }
+        if plan.skipped.len() > 10 {
+            note!("    ... and {} more", plan.skipped.len() - 10);
+        }
+    }
+}
+
+/// Show the plan a --dry-run ends with, as data with --format json or csv
+fn show_dry_run(plan: &ActionPlan) -> Result<()> {
+    if !emit(plan, || plan_csv(plan))? {
+        print_plan(plan);
+        note!("\n🔍 Dry run: nothing was changed.");
+    }
+    Ok(())
+}
+
+fn delete_csv(results: &[DeleteResult]) -> String {
+    csv(
+        &["path", "success", "error"],
+        results
+            .iter()
+            .map(|result| {
+                vec![
+                    result.path.clone(),
+                    result.success.to_string(),
+                    result.error.clone().unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+/// Warn about files that could not be read, listing the first few
+fn print_unreadable(unreadable: &[FileError]) {
+    if unreadable.is_empty() {
+        return;
+    }
+    note!(
+        "\n⚠️  {} file(s) could not be read and were not compared:",
+        unreadable.len()
+    );
+    for failed in unreadable.iter().take(10) {
+        note!("  ❌ {}: {}", failed.path.display(), failed.error);
+    }
+}
+
+/// What `dedupe` does with the copies it does not keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DedupeAction {
+    /// Move them to the trash
+    Trash,
+    /// Delete them permanently
+    Delete,
+    /// Move them into quarantine, restorable until the configured retention
+    /// period ends
+    Quarantine,
+    /// Replace them with hard links to the kept copy: the paths become one
+    /// file, so editing it through any of them changes all
+    Hardlink,
+    /// Replace them with copy-on-write clones of the kept copy (Btrfs, XFS,
+    /// APFS): every path stays a separate file, but the data is stored once
+    Reflink,
+}
+
+impl DedupeAction {
+    /// As written on the command line
+    fn name(self) -> String {
+        clap::ValueEnum::to_possible_value(&self)
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn question(self, files: usize, size: u64) -> String {
+        let files = format!("{} file(s) ({})", files, format_size(size));
+        match self {
+            DedupeAction::Trash => format!("Move {} to the trash?", files),
+            DedupeAction::Delete => format!("Delete {} permanently?", files),
+            DedupeAction::Quarantine => format!("Move {} into quarantine?", files),
+            DedupeAction::Hardlink => {
+                format!("Replace {} with hard links to the kept copies?", files)
+            }
+            DedupeAction::Reflink => {
+                format!("Replace {} with clones of the kept copies?", files)
+            }
+        }
+    }
+}
+
+async fn dedupe_command(
+    path: PathBuf,
+    rule: KeepRule,
+    action: DedupeAction,
+    dry_run: bool,
+    yes: bool,
+    filter: Option<FilterConfig>,
+) -> Result<()> {
+    note!("Finding duplicates in: {}", path.display());
+
+    let pb = LiveProgress::start("Scanning and hashing files...");
+
+    let protected = protected_paths();
+    let api = recording(
+        ServiceApi::new()
+            .with_protected_paths(protected.clone())
+            .with_ignore_list(ignore_list())
+            .with_network_shares(network_shares())
+            .with_progress(pb.sender()),
+    );
+    let report = api
+        .find_duplicate_report(vec![std::path::absolute(&path)?], filter)
+        .await?;
+    pb.finish_and_clear();
+    print_unreadable(&report.unreadable);
+    let groups = report.groups;
+
+    let resolutions = api.resolve_duplicates(&groups, rule.strategy().as_ref());
+    let to_delete: Vec<PathBuf> = resolutions
+        .iter()
+        .flat_map(|r| r.delete.iter().map(|f| f.path.clone()))
+        .collect();
+    let reclaimable: u64 = resolutions.iter().map(|r| r.reclaimable).sum();
+    let untouched = resolutions.iter().filter(|r| r.delete.is_empty()).count();
+    let protected_count: usize = resolutions.iter().map(|r| r.protected.len()).sum();
+    if dry_run {
+        show_dry_run(&api.plan_dedupe(&resolutions, &action.name()))?;
+        if untouched > 0 {
+            note!("  {} duplicate group(s) had no copy to keep", untouched);
+        }
+        return Ok(());
+    }
+    // Nothing to do reads as an empty plan
+    if to_delete.is_empty() && emit(&resolutions, || resolution_csv(&resolutions))? {
+        return Ok(());
+    }
+
+    if to_delete.is_empty() {
+        note!("\n✅ Nothing to deduplicate");
+        if untouched > 0 {
+            note!("  {} duplicate group(s) had no copy to keep", untouched);
+        }
+        return Ok(());
+    }
+
+    note!("\n📊 Dedupe Plan:");
+    note!("  Groups: {}", resolutions.len());
+    note!("  Copies to {}: {}", action.name(), to_delete.len());
+    note!("  Space to reclaim: {}", format_size(reclaimable));
+    if untouched > 0 {
+        note!("  Groups left alone: {}", untouched);
+    }
+    if protected_count > 0 {
+        note!("  Protected copies kept: {}", protected_count);
+    }
+
+    let changed: Vec<_> = resolutions
+        .iter()
+        .filter(|r| !r.delete.is_empty())
+        .collect();
+    for resolution in changed.iter().take(10) {
+        for file in &resolution.keep {
+            note!("\n  {:<10} {}", "keep", file.path.display());
+        }
+        for file in &resolution.delete {
+            note!("  {:<10} {}", action.name(), file.path.display());
+        }
+    }
+    if changed.len() > 10 {
+        note!("\n  ... and {} more group(s)", changed.len() - 10);
+    }
+    if !yes && !confirm(&action.question(to_delete.len(), reclaimable))? {
+        note!("Nothing changed.");
+        return Ok(());
+    }
+
+    // Each copy goes only while it and its kept copy are as the scan saw
+    // them: either may have changed while the plan was on screen
+    let (mode, quarantine) = match action {
+        DedupeAction::Hardlink => return hardlink_dedupe(&api, &resolutions),
+        DedupeAction::Reflink => return reflink_dedupe(&api, &resolutions),
+        DedupeAction::Trash => (DeleteMode::Trash, false),
+        DedupeAction::Delete => (DeleteMode::Permanent, false),
+        DedupeAction::Quarantine => (DeleteMode::Trash, true),
+    };
+    let results = remove_duplicates(&resolutions, mode, quarantine, protected)?;
+    emit(&results, || delete_csv(&results))?;
+    let failed: Vec<_> = results.iter().filter(|r| !r.success).collect();
+    if quarantine {
+        note!("  Quarantined: {}", results.len() - failed.len());
+    } else {
+        note!("  Deleted: {}", results.len() - failed.len());
+    }
+    for result in &failed {
+        note!(
+            "  ❌ {}: {}",
+            result.path,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    if !failed.is_empty() {
+        bail!("Failed to delete {} file(s)", failed.len());
+    }
+    Ok(())
+}
+
+/// One line per copy of each group, with what the plan does with it
+fn resolution_csv(resolutions: &[DuplicateResolution]) -> String {
+    let rows = resolutions
+        .iter()
+        .flat_map(|resolution| {
+            let keep = resolution.keep.iter().map(|file| {
+                let protected = resolution.protected.iter().any(|p| p.path == file.path);
+                (if protected { "protected" } else { "keep" }, file)
+            });
+            let delete = resolution.delete.iter().map(|file| ("delete", file));
+            keep.chain(delete).map(|(action, file)| {
+                vec![
+                    resolution.hash.clone(),
+                    action.to_string(),
+                    file.path.display().to_string(),
+                    file.size.to_string(),
+                ]
+            })
+        })
+        .collect();
+    csv(&["hash", "action", "path", "size"], rows)
+}
+
+/// Delete `paths` in `mode`, or move them into quarantine
+fn remove_files(
+    paths: &[PathBuf],
+    mode: DeleteMode,
+    quarantine: bool,
+    protected: ProtectedPaths,
+) -> Result<Vec<DeleteResult>> {
+    if !quarantine {
+        return Ok(recording_trash(
+            FileOperations::new()
+                .with_protected_paths(protected)
+                .with_hooks(hooks()),
+        )
+        .delete_files_with_mode(paths, mode));
+    }
+    with_quarantine(protected, |quarantine| quarantine.quarantine_files(paths))
+}
+
+/// [`remove_files`] for the copies `resolutions` do not keep, each checked
+/// against its kept copy first
+fn remove_duplicates(
+    resolutions: &[DuplicateResolution],
+    mode: DeleteMode,
+    quarantine: bool,
+    protected: ProtectedPaths,
+) -> Result<Vec<DeleteResult>> {
+    if !quarantine {
+        return Ok(recording_trash(
+            FileOperations::new()
+                .with_protected_paths(protected)
+                .with_hooks(hooks()),
+        )
+        .remove_duplicates(resolutions, mode));
+    }
+    with_quarantine(protected, |quarantine| {
+        quarantine.quarantine_duplicates(resolutions)
+    })
+}
+
+/// Run `quarantine` with the configured quarantine, expired files purged
+/// first
+fn with_quarantine(
+    protected: ProtectedPaths,
+    quarantine: impl FnOnce(&Quarantine) -> Vec<DeleteResult>,
+) -> Result<Vec<DeleteResult>> {
+    let config = Config::load_or_default();
+    config.ensure_directories()?;
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let configured = Quarantine::new(&db, &config.quarantine_dir)
+        .with_retention_days(config.quarantine_days)
+        .with_protected_paths(protected)
+        .with_hooks(Hooks::new(config.hooks.clone()));
+    configured.purge_expired(space_saver_utils::time::now())?;
+    Ok(quarantine(&configured))
+}
+
+/// What [`remove_files`] would do to `paths`
+fn plan_remove_files(
+    paths: &[PathBuf],
+    mode: DeleteMode,
+    quarantine: bool,
+    protected: ProtectedPaths,
+) -> ActionPlan {
+    let action = if quarantine {
+        "quarantine"
+    } else {
+        mode.action()
+    };
+    FileOperations::new()
+        .with_protected_paths(protected)
+        .plan_removal(paths, action)
+}
+
+/// What `review` goes through
+enum ReviewGroups {
+    /// Identical copies, pre-marked by a keep rule
+    Duplicates {
+        keep: KeepRule,
+    },
+    Similar {
+        threshold: f32,
+    },
+}
+
+async fn review_command(
+    path: PathBuf,
+    find: ReviewGroups,
+    mode: DeleteMode,
+    quarantine: bool,
+    dry_run: bool,
+    filter: Option<FilterConfig>,
+) -> Result<()> {
+    note!("Finding groups to review in: {}", path.display());
+    let pb = LiveProgress::start("Scanning and comparing files...");
+
+    let protected = protected_paths();
+    let api = recording(
+        ServiceApi::new()
+            .with_protected_paths(protected.clone())
+            .with_ignore_list(ignore_list())
+            .with_network_shares(network_shares())
+            .with_progress(pb.sender()),
+    );
+    let path = std::path::absolute(&path)?;
+    let mut groups: Vec<ReviewGroup> = match find {
+        ReviewGroups::Duplicates { keep } => {
+            let report = api.find_duplicate_report(vec![path], filter).await?;
+            pb.finish_and_clear();
+            print_unreadable(&report.unreadable);
+            api.resolve_duplicates(&report.groups, keep.strategy().as_ref())
+                .iter()
+                .map(ReviewGroup::from_resolution)
+                .collect()
+        }
+        ReviewGroups::Similar { threshold } => {
+            let similar = api
+                .find_similar_media(path, threshold, vec![], filter)
+                .await?;
+            pb.finish_and_clear();
+            similar
+                .iter()
+                .map(|group| ReviewGroup::from_similar(group, &protected))
+                .collect()
+        }
+    };
+    if groups.is_empty() {
+        note!("\n✅ Nothing to review");
+        return Ok(());
+    }
+
+    // The full-screen view needs a terminal to draw on and read keys from
+    use std::io::IsTerminal;
+    if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+        review::run_tui(&mut groups)?;
+    } else {
+        review::run(&mut groups, &mut std::io::stdin().lock())?;
+    }
+
+    let to_delete: Vec<&ReviewFile> = groups.iter().flat_map(|g| g.to_delete()).collect();
+    let decided = |decision| groups.iter().filter(|g| g.decision == decision).count();
+    note!("\n📊 Review:");
+    note!("  Groups accepted: {}", decided(Decision::Accepted));
+    note!("  Groups skipped: {}", decided(Decision::Skipped));
+    if decided(Decision::Pending) > 0 {
+        note!("  Groups not reviewed: {}", decided(Decision::Pending));
+    }
+    note!("  Files to remove: {}", to_delete.len());
+    note!(
+        "  Space to reclaim: {}",
+        format_size(to_delete.iter().map(|f| f.size).sum())
+    );
+    if to_delete.is_empty() {
+        return Ok(());
+    }
+    let paths: Vec<PathBuf> = to_delete.iter().map(|f| f.path.clone()).collect();
+    if dry_run {
+        return show_dry_run(&plan_remove_files(&paths, mode, quarantine, protected));
+    }
+
+    let how = match (quarantine, mode) {
+        (true, _) => "Move them into quarantine?",
+        (false, DeleteMode::Trash) => "Move them to the trash?",
+        (false, DeleteMode::Permanent) => "Delete them permanently?",
+    };
+    if !confirm(how)? {
+        note!("Nothing removed.");
+        return Ok(());
+    }
+    let results = remove_files(&paths, mode, quarantine, protected)?;
+    emit(&results, || delete_csv(&results))?;
+    let failed: Vec<_> = results.iter().filter(|r| !r.success).collect();
+    note!("  Removed: {}", results.len() - failed.len());
+    for result in &failed {
+        note!(
+            "  ❌ {}: {}",
+            result.path,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    if !failed.is_empty() {
+        bail!("Failed to remove {} file(s)", failed.len());
+    }
+    Ok(())
+}
+
+async fn similar_command(
+    path: PathBuf,
+    threshold: f32,
+    filter: Option<FilterConfig>,
+    export: Option<ExportTarget>,
+) -> Result<()> {
+    note!("Finding similar images in: {}", path.display());
+    note!("Threshold: {:.2}", threshold);
+
+    let pb = LiveProgress::start("Analyzing images...");
+
+    let api = ServiceApi::new()
+        .with_ignore_list(ignore_list())
+        .with_progress(pb.sender());
+    let similar = api
+        .find_similar_media(path, threshold, vec![], filter)
+        .await?;
+
+    pb.finish_with_message("Analysis completed");
+    if let Some(export) = &export {
+        export.write(ExportResults::Similar(similar.clone()))?;
+    }
+    if emit(&similar, || {
+        render_csv(&ExportResults::Similar(similar.clone()))
+    })? {
+        return Ok(());
+    }
+
+    if similar.is_empty() {
+        note!("\n✅ No similar images found!");
+        return Ok(());
+    }
+
+    note!("\n📊 Similar Images:");
+    note!("  Groups found: {}", similar.len());
+
+    for (idx, group) in similar.iter().take(10).enumerate() {
+        note!(
+            "\n  Group {} (Similarity: {:.2}%)",
+            idx + 1,
+            group.similarity_score * 100.0
+        );
+        for file in &group.files {
+            note!("    - {}", file.path);
+        }
+    }
+
+    Ok(())
+}
+
+async fn empty_command(
+    path: PathBuf,
+    delete: Option<DeleteMode>,
+    dry_run: bool,
+    yes: bool,
+    filter: Option<FilterConfig>,
+) -> Result<()> {
+    note!("Finding empty files in: {}", path.display());
+
+    let files = match &filter {
+        Some(filter) => filter.apply(
+            filter
+                .configure_scanner(DefaultFileScanner::new())
+                .scan(&path)?,
+        )?,
+        None => DefaultFileScanner::new().scan(&path)?,
+    };
+    let filter = FileFilter::empty_files();
+    let empty_files = filter.filter_files(files);
+    if let (Some(mode), true) = (delete, dry_run) {
+        let paths: Vec<_> = empty_files.iter().map(|f| f.path.clone()).collect();
+        return show_dry_run(&plan_remove_files(&paths, mode, false, protected_paths()));
+    }
+    if delete.is_none() && emit(&empty_files, || file_csv(&empty_files))? {
+        return Ok(());
+    }
+
+    if empty_files.is_empty() {
+        note!("\n✅ No empty files found!");
+        return Ok(());
+    }
+
+    note!("\n📊 Empty Files:");
+    note!("  Count: {}", empty_files.len());
+
+    if let Some(mode) = delete {
+        let paths: Vec<_> = empty_files.iter().map(|f| f.path.clone()).collect();
+        let plan = plan_remove_files(&paths, mode, false, protected_paths());
+        let question = removal_question(&plan, "empty file(s)", mode, false);
+        if !approve(
+            &plan,
+            &question,
+            yes,
+            forced(),
+            &mut std::io::stdin().lock(),
+        )? {
+            return Ok(());
+        }
+        let ops = recording_trash(
+            FileOperations::new()
+                .with_protected_paths(protected_paths())
+                .with_hooks(hooks()),
+        );
+        let results = ops.delete_files_with_mode(&paths, mode);
+        if emit(&results, || delete_csv(&results))? {
+            return Ok(());
+        }
+        let deleted = results.iter().filter(|r| r.success).count();
+        match mode {
+            DeleteMode::Trash => note!("  Moved to trash: {}", deleted),
+            DeleteMode::Permanent => note!("  Deleted: {}", deleted),
+        }
+        for failed in results.iter().filter(|r| !r.success) {
+            note!(
+                "  ❌ {}: {}",
+                failed.path,
+                failed.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+    } else {
+        for file in empty_files.iter().take(20) {
+            note!("  - {}", file.path.display());
+        }
+        if empty_files.len() > 20 {
+            note!("  ... and {} more", empty_files.len() - 20);
+        }
+        note!("\nUse --delete flag to remove these files.");
+    }
+
+    Ok(())
+}
+
+fn empty_dirs_command(
+    path: PathBuf,
+    delete: Option<DeleteMode>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    note!("Finding empty folders in: {}", path.display());
+    let dirs = space_saver_core::scanner::find_empty_dirs(&path)?;
+    if let (Some(mode), true) = (delete, dry_run) {
+        return show_dry_run(&plan_remove_files(&dirs, mode, false, protected_paths()));
+    }
+
+    let Some(mode) = delete else {
+        let listed: Vec<String> = dirs.iter().map(|d| d.display().to_string()).collect();
+        if emit(&listed, || {
+            csv(&["path"], listed.iter().map(|d| vec![d.clone()]).collect())
+        })? {
+            return Ok(());
+        }
+        if dirs.is_empty() {
+            note!("\n✅ No empty folders found!");
+            return Ok(());
+        }
+        note!("\n📊 Empty folders: {}", dirs.len());
+        for dir in dirs.iter().take(20) {
+            note!("  - {}", dir.display());
+        }
+        if dirs.len() > 20 {
+            note!("  ... and {} more", dirs.len() - 20);
+        }
+        note!("\nUse --delete flag to remove them.");
+        return Ok(());
+    };
+
+    let plan = plan_remove_files(&dirs, mode, false, protected_paths());
+    let question = removal_question(&plan, "empty folder(s)", mode, false);
+    if !approve(
+        &plan,
+        &question,
+        yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+    let ops = recording_trash(FileOperations::new().with_protected_paths(protected_paths()));
+    let results = ops.remove_empty_trees(&dirs, mode);
+    if emit(&results, || {
+        csv(
+            &["path", "success", "removed", "error"],
+            results
+                .iter()
+                .map(|result| {
+                    vec![
+                        result.path.clone(),
+                        result.success.to_string(),
+                        result.removed.len().to_string(),
+                        result.error.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        )
+    })? {
+        return Ok(());
+    }
+    let trees = results.iter().filter(|r| r.success).count();
+    let removed: usize = results.iter().map(|r| r.removed.len()).sum();
+    match mode {
+        DeleteMode::Trash => note!(
+            "\n🗑️  Moved {} empty tree(s) to the trash: {} folder(s)",
+            trees,
+            removed
+        ),
+        DeleteMode::Permanent => note!(
+            "\n🗑️  Removed {} folder(s) from {} empty tree(s)",
+            removed,
+            trees
+        ),
+    }
+    for failed in results.iter().filter(|r| !r.success) {
+        note!(
+            "  ❌ {}: {}",
+            failed.path,
+            failed.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    Ok(())
+}
+
+/// Delete `paths` in `mode`, or move them into quarantine, after showing
+/// what that frees and asking unless `yes`. Protected paths stop it unless
+/// --force.
+fn delete_command(
+    paths: Vec<PathBuf>,
+    mode: DeleteMode,
+    quarantine: bool,
+    yes: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let plan = plan_remove_files(&paths, mode, quarantine, protected_paths());
+    if dry_run {
+        return show_dry_run(&plan);
+    }
+    let question = removal_question(&plan, "file(s)", mode, quarantine);
+    if !approve(
+        &plan,
+        &question,
+        yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+
+    let results = remove_files(&paths, mode, quarantine, protected_paths())?;
+    let failed = results.iter().filter(|r| !r.success).count();
+    if !emit(&results, || delete_csv(&results))? {
+        let removed = results.len() - failed;
+        match (quarantine, mode) {
+            (true, _) => note!("\n📦 Quarantined: {}", removed),
+            (false, DeleteMode::Trash) => note!("\n🗑️  Moved to trash: {}", removed),
+            (false, DeleteMode::Permanent) => note!("\n🗑️  Deleted: {}", removed),
+        }
+        for result in results.iter().filter(|r| !r.success) {
+            note!(
+                "  ❌ {}: {}",
+                result.path,
+                result.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{} of {} path(s) could not be deleted",
+            failed,
+            results.len()
+        );
+    }
+    Ok(())
+}
+
+async fn stats_command(
+    paths: Vec<PathBuf>,
+    filter: Option<FilterConfig>,
+    export: Option<ExportTarget>,
+) -> Result<()> {
+    note!("Analyzing: {}", describe_paths(&paths));
+
+    let pb = LiveProgress::start("Analyzing storage...");
+
+    let api = ServiceApi::new().with_progress(pb.sender());
+    let stats = match remote_storage(&paths)? {
+        Some(storage) => {
+            api.get_storage_stats_in(storage.as_ref(), paths, filter)
+                .await?
+        }
+        None => api.get_storage_stats_for_paths(paths, filter).await?,
+    };
+
+    pb.finish_with_message("Analysis completed");
+    if let Some(export) = &export {
+        export.write(ExportResults::Stats(stats.clone()))?;
+    }
+    if emit(&stats, || render_csv(&ExportResults::Stats(stats.clone())))? {
+        return Ok(());
+    }
+
+    note!("\n📊 Storage Statistics:");
+    note!("  Total files: {}", stats.total_files);
+    note!("  Total size: {}", format_size(stats.total_size));
+    note!("\n📁 By Type:");
+    note!("  Images: {}", stats.images);
+    note!("  Videos: {}", stats.videos);
+    note!("  Documents: {}", stats.documents);
+    note!("  Archives: {}", stats.archives);
+    note!("  Others: {}", stats.others);
+    note!("\n⚠️  Empty files: {}", stats.empty_files);
+
+    Ok(())
+}
+
+fn report_command(
+    path: PathBuf,
+    output: PathBuf,
+    top: usize,
+    filter: Option<FilterConfig>,
+) -> Result<()> {
+    let config = Config::load_or_default();
+    let path = std::path::absolute(&path)?;
+    note!("Reporting on: {}", path.display());
+
+    let pb = LiveProgress::start("Scanning, hashing and estimating savings...");
+    let manager = configured_plugin_manager(&config)?;
+    let manager = manager.read().map_err(Error::from)?;
+    let api = recording(
+        ServiceApi::new()
+            .with_ignore_list(ignore_list())
+            .with_network_shares(network_shares())
+            .with_progress(pb.sender()),
+    );
+    let report = api.storage_report(path, filter, top, Some(&manager))?;
+    drop(manager);
+    pb.finish_and_clear();
+
+    space_saver_service::report::write_report(&report, &output)?;
+    if emit(&report, || {
+        csv(
+            &["category", "file_count", "total_size"],
+            report
+                .categories
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.category.clone(),
+                        c.file_count.to_string(),
+                        c.total_size.to_string(),
+                    ]
+                })
+                .collect(),
+        )
+    })? {
+        return Ok(());
+    }
+
+    note!(
+        "\n📊 {} files, {}",
+        report.total_files,
+        format_size(report.total_size)
+    );
+    note!(
+        "  Duplicates: {} wasted",
+        format_size(report.duplicate_waste)
+    );
+    note!(
+        "  Compression: {} estimated savings",
+        format_size(report.compressible_savings)
+    );
+    note!("\n✅ Report written to {}", output.display());
+    Ok(())
+}
+
+fn hash_command(path: PathBuf, algo: HashKind, output: Option<PathBuf>) -> Result<()> {
+    let pb = ProgressBar::new_spinner();
+    pb.set_message(format!("Hashing {} with {}...", path.display(), algo));
+    let tree = checksum::hash_tree(&path, algo)?;
+    pb.finish_and_clear();
+
+    let text = tree.manifest.to_text();
+    if let Some(output) = &output {
+        std::fs::write(output, &text)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+    }
+    let emitted = emit(&tree, || {
+        let rows = tree
+            .manifest
+            .entries
+            .iter()
+            .map(|e| vec![e.hash.clone(), e.path.clone()])
+            .collect();
+        csv(&["hash", "path"], rows)
+    })?;
+    if !emitted {
+        match &output {
+            Some(output) => note!(
+                "✅ Hashed {} file(s) with {} into {}",
+                tree.manifest.entries.len(),
+                algo,
+                output.display()
+            ),
+            None => print!("{}", text),
+        }
+    }
+    if !tree.unreadable.is_empty() {
+        eprintln!("⚠️  Could not read {} file(s):", tree.unreadable.len());
+        for failed in &tree.unreadable {
+            eprintln!("  ❌ {}: {}", failed.path.display(), failed.error);
+        }
+        bail!("Failed to hash {} file(s)", tree.unreadable.len());
+    }
+    Ok(())
+}
+
+fn checksum_verify_command(
+    manifest_path: PathBuf,
+    root: Option<PathBuf>,
+    algo: Option<HashKind>,
+) -> Result<()> {
+    let algo = algo
+        .or_else(|| checksum::algorithm_for(&manifest_path))
+        .unwrap_or_default();
+    let text = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let manifest = Manifest::parse(&text, algo)?;
+    let root = root.unwrap_or_else(|| {
+        manifest_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf()
+    });
+    note!(
+        "Verifying {} file(s) with {} against {}",
+        manifest.entries.len(),
+        algo,
+        root.display()
+    );
+
+    let pb = ProgressBar::new_spinner();
+    pb.set_message("Hashing...");
+    let results = checksum::verify_manifest(&manifest, &root);
+    pb.finish_and_clear();
+
+    let failed: Vec<&ChecksumResult> = results
+        .iter()
+        .filter(|r| r.status != ChecksumStatus::Ok)
+        .collect();
+    let emitted = emit(&results, || {
+        let rows = results
+            .iter()
+            .map(|r| {
+                vec![
+                    r.path.clone(),
+                    checksum_status_name(r.status).to_string(),
+                    r.expected.clone(),
+                    r.actual.clone().unwrap_or_default(),
+                    r.error.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        csv(&["path", "status", "expected", "actual", "error"], rows)
+    })?;
+    if !emitted && !failed.is_empty() {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Status", "Path", "Detail"]);
+        for result in &failed {
+            let detail = match result.status {
+                ChecksumStatus::Changed => format!(
+                    "expected {}, found {}",
+                    result.expected,
+                    result.actual.as_deref().unwrap_or_default()
+                ),
+                _ => result.error.clone().unwrap_or_default(),
+            };
+            table.add_row(vec![
+                checksum_status_name(result.status).to_string(),
+                result.path.clone(),
+                detail,
+            ]);
+        }
+        println!("{table}");
+    }
+    let count = |status| failed.iter().filter(|r| r.status == status).count();
+    note!(
+        "{} {} OK, {} changed, {} missing, {} unreadable",
+        if failed.is_empty() { "✅" } else { "❌" },
+        results.len() - failed.len(),
+        count(ChecksumStatus::Changed),
+        count(ChecksumStatus::Missing),
+        count(ChecksumStatus::Unreadable)
+    );
+    if !failed.is_empty() {
+        bail!("{} file(s) failed verification", failed.len());
+    }
+    Ok(())
+}
+
+fn checksum_status_name(status: ChecksumStatus) -> &'static str {
+    match status {
+        ChecksumStatus::Ok => "ok",
+        ChecksumStatus::Changed => "changed",
+        ChecksumStatus::Missing => "missing",
+        ChecksumStatus::Unreadable => "unreadable",
+    }
+}
+
+fn diff_command(path: PathBuf, from: Option<i64>, to: Option<i64>) -> Result<()> {
+    let config = Config::load_or_default();
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let api = ServiceApi::new();
+    let history = api.get_storage_history(&path, &db)?;
+
+    let ids: Vec<i64> = history.snapshots.iter().map(|s| s.scan_id).collect();
+    let to = to.or(ids.last().copied());
+    let from = from.or_else(|| ids.iter().rev().nth(1).copied());
+    let diff = match (from, to) {
+        (Some(from), Some(to)) if !ids.is_empty() => Some(api.diff_scans(from, to, &db)?),
+        _ => None,
+    };
+    let data = serde_json::json!({ "history": history, "diff": diff });
+    if emit(&data, || {
+        let rows = diff
+            .iter()
+            .flat_map(|diff| {
+                [
+                    ("added", &diff.added),
+                    ("removed", &diff.removed),
+                    ("resized", &diff.resized),
+                ]
+            })
+            .flat_map(|(change, files)| {
+                files.iter().map(move |file| {
+                    vec![
+                        change.to_string(),
+                        file.path.clone(),
+                        file.old_size.to_string(),
+                        file.new_size.to_string(),
+                    ]
+                })
+            })
+            .collect();
+        csv(&["change", "path", "old_size", "new_size"], rows)
+    })? {
+        return Ok(());
+    }
+
+    if history.snapshots.is_empty() {
+        note!("No recorded scans of {}.", history.path);
+        note!("Run `space-saver scan` on it (without filters) to start its history.");
+        return Ok(());
+    }
+
+    note!("📈 Scans of {}:", history.path);
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["ID", "Scanned", "Files", "Size"]);
+    for snapshot in &history.snapshots {
+        table.add_row(vec![
+            snapshot.scan_id.to_string(),
+            format_timestamp(snapshot.scanned_at),
+            snapshot.file_count.to_string(),
+            format_size(snapshot.total_size),
+        ]);
+    }
+    note!("{table}");
+
+    if !history.growth.is_empty() {
+        note!("\n📊 Growth since the first scan:");
+        for growth in &history.growth {
+            note!(
+                "  {}: {} ({:+} files)",
+                growth.category,
+                format_size_change(growth.size_change),
+                growth.file_count_change
+            );
+        }
+    }
+
+    let Some(diff) = diff else {
+        note!("\nScan it again to compare the two scans.");
+        return Ok(());
+    };
+    note!("\n🔍 Scan {} → {}:", diff.from.id, diff.to.id);
+    note!("  Size change: {}", format_size_change(diff.size_change));
+    for change in diff.categories.iter().filter(|c| c.size_change != 0) {
+        note!(
+            "    {}: {} ({:+} files)",
+            change.category,
+            format_size_change(change.size_change),
+            change.file_count_change
+        );
+    }
+    let sections = [
+        ("Added", &diff.added),
+        ("Removed", &diff.removed),
+        ("Resized", &diff.resized),
+    ];
+    for (label, changes) in sections {
+        note!("\n  {}: {}", label, changes.len());
+        for change in changes.iter().take(10) {
+            note!(
+                "    {} {}",
+                format_size_change(change.new_size as i64 - change.old_size as i64),
+                change.path
+            );
+        }
+        if changes.len() > 10 {
+            note!("    ... and {} more", changes.len() - 10);
+        }
+    }
+
+    Ok(())
+}
+
+fn history_command(limit: usize, duplicates: bool) -> Result<()> {
+    let config = Config::load_or_default();
+    let api = ServiceApi::new().with_database(Database::open(&config.database_path)?);
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    if duplicates {
+        let groups = api.recorded_duplicates()?;
+        let rows = || {
+            groups
+                .iter()
+                .flat_map(|group| {
+                    group.file_paths.iter().map(|path| {
+                        vec![
+                            group.hash.clone(),
+                            path.clone(),
+                            group.wasted_space.to_string(),
+                            group.created_at.to_string(),
+                        ]
+                    })
+                })
+                .collect()
+        };
+        if emit(&groups, || {
+            csv(&["hash", "path", "wasted_space", "created_at"], rows())
+        })? {
+            return Ok(());
+        }
+        if groups.is_empty() {
+            note!("No recorded duplicate groups. Run `space-saver duplicates` first.");
+            return Ok(());
+        }
+        table.set_header(vec!["Hash", "Files", "Wasted", "Found", "Paths"]);
+        for group in &groups {
+            table.add_row(vec![
+                group.hash.chars().take(8).collect(),
+                group.file_count.to_string(),
+                format_size(group.wasted_space),
+                format_timestamp(group.created_at),
+                group.file_paths.join("\n"),
+            ]);
+        }
+        note!("{table}");
+        note!(
+            "\n{} group(s), {} wasted",
+            groups.len(),
+            format_size(groups.iter().map(|g| g.wasted_space).sum())
+        );
+        return Ok(());
+    }
+
+    let scans = api.recent_scans(limit)?;
+    if emit(&scans, || {
+        csv(
+            &[
+                "id",
+                "path",
+                "file_count",
+                "total_size",
+                "scan_time",
+                "created_at",
+            ],
+            scans
+                .iter()
+                .map(|scan| {
+                    vec![
+                        scan.id.to_string(),
+                        scan.path.clone(),
+                        scan.file_count.to_string(),
+                        scan.total_size.to_string(),
+                        scan.scan_time.to_string(),
+                        scan.created_at.to_string(),
+                    ]
+                })
+                .collect(),
+        )
+    })? {
+        return Ok(());
+    }
+    if scans.is_empty() {
+        note!("No recorded scans. Run `space-saver scan` (without filters) first.");
+        return Ok(());
+    }
+    table.set_header(vec!["ID", "Scanned", "Path", "Files", "Size", "Took"]);
+    for scan in &scans {
+        table.add_row(vec![
+            scan.id.to_string(),
+            format_timestamp(scan.created_at),
+            scan.path.clone(),
+            scan.file_count.to_string(),
+            format_size(scan.total_size),
+            format_duration(std::time::Duration::from_secs(scan.scan_time as u64)),
+        ]);
+    }
+    note!("{table}");
+    let groups = api.recorded_duplicates()?;
+    if !groups.is_empty() {
+        note!(
+            "\n{} recorded duplicate group(s), {} wasted (`space-saver history --duplicates`)",
+            groups.len(),
+            format_size(groups.iter().map(|g| g.wasted_space).sum())
+        );
+    }
+    note!("\nShow one with `space-saver history show <id>`; compare scans of one folder with `space-saver diff <path>`.");
+    Ok(())
+}
+
+fn history_show_command(id: i64) -> Result<()> {
+    let config = Config::load_or_default();
+    let api = ServiceApi::new().with_database(Database::open(&config.database_path)?);
+    let details = api.scan_details(id)?;
+    if emit(&details, || {
+        let categories = details.categories.iter().map(|c| {
+            vec![
+                "category".to_string(),
+                c.category.clone(),
+                c.file_count.to_string(),
+                c.total_size.to_string(),
+            ]
+        });
+        let files = details.largest.iter().map(|f| {
+            vec![
+                "file".to_string(),
+                f.path.clone(),
+                "1".to_string(),
+                f.size.to_string(),
+            ]
+        });
+        let duplicates = details.duplicates.iter().map(|g| {
+            vec![
+                "duplicates".to_string(),
+                g.file_paths.join(";"),
+                g.file_count.to_string(),
+                g.wasted_space.to_string(),
+            ]
+        });
+        csv(
+            &["kind", "name", "file_count", "size"],
+            categories.chain(files).chain(duplicates).collect(),
+        )
+    })? {
+        return Ok(());
+    }
+
+    let scan = &details.scan;
+    note!("Scan {} of {}", scan.id, scan.path);
+    note!(
+        "{} · {} file(s) · {} · took {}\n",
+        format_timestamp(scan.created_at),
+        scan.file_count,
+        format_size(scan.total_size),
+        format_duration(std::time::Duration::from_secs(scan.scan_time as u64))
+    );
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Category", "Files", "Size"]);
+    for category in &details.categories {
+        table.add_row(vec![
+            category.category.clone(),
+            category.file_count.to_string(),
+            format_size(category.total_size),
+        ]);
+    }
+    note!("{table}");
+
+    if !details.largest.is_empty() {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Largest files", "Size"]);
+        for file in &details.largest {
+            table.add_row(vec![file.path.clone(), format_size(file.size)]);
+        }
+        note!("\n{table}");
+    }
+
+    if details.duplicates.is_empty() {
+        note!("\nNo recorded duplicate groups under this path.");
+    } else {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Hash", "Files", "Wasted", "Paths"]);
+        for group in &details.duplicates {
+            table.add_row(vec![
+                group.hash.chars().take(8).collect(),
+                group.file_count.to_string(),
+                format_size(group.wasted_space),
+                group.file_paths.join("\n"),
+            ]);
+        }
+        note!("\n{table}");
+        note!(
+            "\n{} group(s), {} wasted",
+            details.duplicates.len(),
+            format_size(details.duplicates.iter().map(|g| g.wasted_space).sum())
+        );
+    }
+    Ok(())
+}
+
+fn savings_command() -> Result<()> {
+    let config = Config::load_or_default();
+    let api = ServiceApi::new().with_database(Database::open(&config.database_path)?);
+    let summary = api.savings_summary()?;
+    if emit(&summary, || {
+        csv(
+            &[
+                "plugin",
+                "file_count",
+                "original_size",
+                "compressed_size",
+                "saved",
+            ],
+            summary
+                .by_plugin
+                .iter()
+                .map(|plugin| {
+                    vec![
+                        plugin.plugin_name.clone(),
+                        plugin.file_count.to_string(),
+                        plugin.original_size.to_string(),
+                        plugin.compressed_size.to_string(),
+                        plugin.saved.to_string(),
+                    ]
+                })
+                .collect(),
+        )
+    })? {
+        return Ok(());
+    }
+    if summary.file_count == 0 {
+        note!(
+            "Nothing compressed yet. Compress files in the app, with `space-saver compress` or with a `compress` cleanup rule."
+        );
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Plugin", "Files", "Before", "After", "Saved"]);
+    for plugin in &summary.by_plugin {
+        table.add_row(vec![
+            plugin.plugin_name.clone(),
+            plugin.file_count.to_string(),
+            format_size(plugin.original_size),
+            format_size(plugin.compressed_size),
+            format_size(plugin.saved),
+        ]);
+    }
+    note!("{table}");
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Month", "Files", "Saved"]);
+    for month in &summary.by_month {
+        table.add_row(vec![
+            month.month.clone(),
+            month.file_count.to_string(),
+            format_size(month.saved),
+        ]);
+    }
+    note!("{table}");
+
+    note!(
+        "\n{} file(s) compressed from {} to {}: {} saved",
+        summary.file_count,
+        format_size(summary.original_size),
+        format_size(summary.compressed_size),
+        format_size(summary.saved)
+    );
+    Ok(())
+}
+
+fn maintenance_command() -> Result<()> {
+    let config = Config::load_or_default();
+    let api = ServiceApi::new().with_database(Database::open(&config.database_path)?);
+    note!("Maintaining {}...", config.database_path.display());
+    let record = api.maintain_database()?;
+    if emit(&record, || {
+        csv(
+            &["pruned_files", "size_before", "size_after", "ran_at"],
+            vec![vec![
+                record.pruned_files.to_string(),
+                record.size_before.to_string(),
+                record.size_after.to_string(),
+                record.ran_at.to_string(),
+            ]],
+        )
+    })? {
+        return Ok(());
+    }
+    note!(
+        "Forgot {} recorded file(s) no longer on disk",
+        record.pruned_files
+    );
+    note!(
+        "Database: {} -> {}",
+        format_size(record.size_before),
+        format_size(record.size_after)
+    );
+    Ok(())
+}
+
+/// Results are recorded in the app database when it can be opened; the
+/// command still runs without it
+fn recording(api: ServiceApi) -> ServiceApi {
+    let config = Config::load_or_default();
+    match Database::open(&config.database_path) {
+        Ok(db) => api.with_database(db),
+        Err(e) => {
+            note!("⚠️  Results not recorded: {:#}", e);
+            api
+        }
+    }
+}
+
+/// `ops` recording what it moves to the trash, so `restore` can bring it
+/// back
+fn recording_trash(ops: FileOperations) -> FileOperations {
+    let config = Config::load_or_default();
+    match Database::open(&config.database_path) {
+        Ok(db) => ops.with_database(db),
+        Err(e) => {
+            note!("⚠️  Trashed files not recorded: {:#}", e);
+            ops
+        }
+    }
+}
+
+/// A size difference with its sign, e.g. "+1.50 MB"
+fn format_size_change(change: i64) -> String {
+    let sign = if change < 0 { '-' } else { '+' };
+    format!("{}{}", sign, format_size(change.unsigned_abs()))
+}
+
+async fn largest_command(path: PathBuf, count: usize, filter: Option<FilterConfig>) -> Result<()> {
+    note!(
+        "Finding the largest files and folders in: {}",
+        path.display()
+    );
+
+    let pb = LiveProgress::start("Scanning files...");
+
+    let api = ServiceApi::new().with_progress(pb.sender());
+    let files = api
+        .get_largest_files(vec![path.clone()], count, filter)
+        .await?;
+    let directories = api.get_largest_directories(vec![path], count).await?;
+
+    pb.finish_with_message("Scan completed");
+    let data = serde_json::json!({ "files": files, "directories": directories });
+    if emit(&data, || {
+        let files = files.iter().map(|file| {
+            vec![
+                "file".to_string(),
+                file.path.display().to_string(),
+                file.size.to_string(),
+                "1".to_string(),
+            ]
+        });
+        let directories = directories.iter().map(|dir| {
+            vec![
+                "directory".to_string(),
+                dir.path.clone(),
+                dir.total_size.to_string(),
+                dir.file_count.to_string(),
+            ]
+        });
+        csv(
+            &["kind", "path", "size", "file_count"],
+            files.chain(directories).collect(),
+        )
+    })? {
+        return Ok(());
+    }
+
+    if files.is_empty() {
+        note!("\n✅ No files found!");
+        return Ok(());
+    }
+
+    note!("\n📄 Largest files:");
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Size", "Path"]);
+    for file in &files {
+        table.add_row(vec![
+            format_size(file.size),
+            file.path.display().to_string(),
+        ]);
+    }
+    note!("{table}");
+
+    if !directories.is_empty() {
+        note!("\n📁 Largest folders:");
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Size", "Files", "Path"]);
+        for dir in &directories {
+            table.add_row(vec![
+                format_size(dir.total_size),
+                dir.file_count.to_string(),
+                dir.path.clone(),
+            ]);
+        }
+        note!("{table}");
+    }
+
+    Ok(())
+}
+
+async fn duplicate_dirs_command(
+    path: PathBuf,
+    delete: Option<DeleteMode>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    note!("Finding duplicate folders in: {}", path.display());
+
+    let pb = LiveProgress::start("Scanning files...");
+
+    let api = ServiceApi::new()
+        .with_protected_paths(protected_paths())
+        .with_progress(pb.sender());
+    let groups = api.find_duplicate_directories(vec![path]).await?;
+
+    pb.finish_with_message("Scan completed");
+    if let (Some(mode), true) = (delete, dry_run) {
+        return show_dry_run(&api.plan_duplicate_directory_deletion(&groups, mode));
+    }
+    if delete.is_none()
+        && emit(&groups, || {
+            let rows = groups
+                .iter()
+                .flat_map(|group| {
+                    group.directories.iter().map(|dir| {
+                        vec![
+                            group.hash.clone(),
+                            dir.clone(),
+                            group.file_count.to_string(),
+                            group.size.to_string(),
+                            group.wasted_space.to_string(),
+                        ]
+                    })
+                })
+                .collect();
+            csv(
+                &["hash", "path", "file_count", "size", "wasted_space"],
+                rows,
+            )
+        })?
+    {
+        return Ok(());
+    }
+
+    if groups.is_empty() {
+        note!("\n✅ No duplicate folders found!");
+        return Ok(());
+    }
+
+    let wasted: u64 = groups.iter().map(|g| g.wasted_space).sum();
+    note!("\n📊 Duplicate Folders:");
+    note!("  Groups: {}", groups.len());
+    note!("  Wasted space: {}", format_size(wasted));
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Wasted", "Files", "Copies"]);
+    for group in groups.iter().take(20) {
+        table.add_row(vec![
+            format_size(group.wasted_space),
+            group.file_count.to_string(),
+            group.directories.join("\n"),
+        ]);
+    }
+    note!("{table}");
+    if groups.len() > 20 {
+        note!("  ... and {} more groups", groups.len() - 20);
+    }
+
+    let Some(mode) = delete else {
+        note!("\nUse --delete flag to remove all copies but one.");
+        return Ok(());
+    };
+    let plan = api.plan_duplicate_directory_deletion(&groups, mode);
+    let question = removal_question(&plan, "folder(s)", mode, false);
+    if !approve(
+        &plan,
+        &question,
+        yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+    let mut results = Vec::new();
+    for group in &groups {
+        let Some(keep) = group.kept() else {
+            continue;
+        };
+        for target in group.directories.iter().filter(|d| *d != keep) {
+            let result = api.delete_duplicate_directory(Path::new(target), Path::new(keep), mode);
+            if !result.success {
+                note!(
+                    "  ❌ {}: {}",
+                    result.path,
+                    result.error.as_deref().unwrap_or("unknown error")
+                );
+            }
+            results.push(result);
+        }
+    }
+    emit(&results, || delete_csv(&results))?;
+    let deleted = results.iter().filter(|r| r.success).count();
+    match mode {
+        DeleteMode::Trash => note!("  Folders moved to trash: {}", deleted),
+        DeleteMode::Permanent => note!("  Folders deleted: {}", deleted),
+    }
+
+    Ok(())
+}
+
+fn caches_command(
+    paths: Vec<PathBuf>,
+    clean: &[CacheCategory],
+    mode: DeleteMode,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    note!("Finding application caches and build output...");
+
+    let pb = LiveProgress::start("Measuring caches...");
+
+    let api = ServiceApi::new()
+        .with_protected_paths(protected_paths())
+        .with_hooks(hooks())
+        .with_progress(pb.sender());
+    let report = api.scan_app_caches(&paths)?;
+
+    pb.finish_with_message("Scan completed");
+    if !clean.is_empty() && dry_run {
+        return show_dry_run(&api.plan_app_cache_cleaning(&report, clean, mode));
+    }
+    if clean.is_empty()
+        && emit(&report, || {
+            let rows = report
+                .categories
+                .iter()
+                .flat_map(|category| &category.locations)
+                .map(|location| {
+                    vec![
+                        location.category.to_string(),
+                        location.path.display().to_string(),
+                        location.file_count.to_string(),
+                        location.size.to_string(),
+                    ]
+                })
+                .collect();
+            csv(&["category", "path", "file_count", "size"], rows)
+        })?
+    {
+        return Ok(());
+    }
+
+    if report.categories.is_empty() {
+        note!("\n✅ No caches or build output found!");
+        return Ok(());
+    }
+
+    note!("\n📊 Caches and Build Output:");
+    note!("  Reclaimable: {}", format_size(report.total_size));
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Category", "Size", "Files", "Largest"]);
+    for category in &report.categories {
+        let mut largest: Vec<String> = category
+            .locations
+            .iter()
+            .take(3)
+            .map(|l| format!("{} ({})", l.path.display(), format_size(l.size)))
+            .collect();
+        if category.locations.len() > 3 {
+            largest.push(format!("... and {} more", category.locations.len() - 3));
+        }
+        table.add_row(vec![
+            format!("{} ({})", category.label, category.category),
+            format_size(category.size),
+            category.file_count.to_string(),
+            largest.join("\n"),
+        ]);
+    }
+    note!("{table}");
+
+    if clean.is_empty() {
+        note!("\nUse --clean <category,...> (or --clean all) to remove them.");
+        return Ok(());
+    }
+    let plan = api.plan_app_cache_cleaning(&report, clean, mode);
+    let question = removal_question(&plan, "folder(s)", mode, false);
+    if !approve(
+        &plan,
+        &question,
+        yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+    let results = api.clean_app_caches(&report, clean, mode);
+    for result in results.iter().filter(|r| !r.success) {
+        note!(
+            "  ❌ {}: {}",
+            result.path,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    emit(&results, || delete_csv(&results))?;
+    let cleaned = results.iter().filter(|r| r.success).count();
+    match mode {
+        DeleteMode::Trash => note!("  Folders moved to trash: {}", cleaned),
+        DeleteMode::Permanent => note!("  Folders deleted: {}", cleaned),
+    }
+
+    Ok(())
+}
+
+fn dev_artifacts_command(
+    paths: Vec<PathBuf>,
+    months: u32,
+    clean: Option<DeleteMode>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    note!("Finding build output in project folders...");
+
+    let pb = LiveProgress::start("Measuring projects...");
+
+    let api = ServiceApi::new()
+        .with_protected_paths(protected_paths())
+        .with_hooks(hooks())
+        .with_progress(pb.sender());
+    let report = api.find_dev_artifacts(&paths, months)?;
+
+    pb.finish_with_message("Scan completed");
+    if let (Some(mode), true) = (clean, dry_run) {
+        return show_dry_run(&api.plan_dev_artifact_removal(&report, mode));
+    }
+    if clean.is_none()
+        && emit(&report, || {
+            let rows = report
+                .projects
+                .iter()
+                .flat_map(|project| {
+                    project.artifacts.iter().map(|artifact| {
+                        vec![
+                            project.root.display().to_string(),
+                            artifact.kind.name().to_string(),
+                            artifact.path.display().to_string(),
+                            artifact.file_count.to_string(),
+                            artifact.size.to_string(),
+                            artifact
+                                .last_build
+                                .map(format_timestamp)
+                                .unwrap_or_default(),
+                            project
+                                .last_activity
+                                .map(format_timestamp)
+                                .unwrap_or_default(),
+                            project.suggested.to_string(),
+                        ]
+                    })
+                })
+                .collect();
+            csv(
+                &[
+                    "project",
+                    "kind",
+                    "path",
+                    "file_count",
+                    "size",
+                    "last_build",
+                    "last_activity",
+                    "suggested",
+                ],
+                rows,
+            )
+        })?
+    {
+        return Ok(());
+    }
+
+    if report.projects.is_empty() {
+        note!("\n✅ No build output found!");
+        return Ok(());
+    }
+
+    let suggested = report.projects.iter().filter(|p| p.suggested).count();
+    note!("\n📊 Build Output in Projects:");
+    note!("  Projects: {}", report.projects.len());
+    note!("  Total size: {}", format_size(report.total_size));
+    note!(
+        "  Untouched for {}+ months: {} project(s), {}",
+        months,
+        suggested,
+        format_size(report.reclaimable)
+    );
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Project", "Last Activity", "Build Output", "Size"]);
+    for project in report.projects.iter().take(20) {
+        let marker = if project.suggested { "🧹 " } else { "" };
+        let artifacts: Vec<String> = project
+            .artifacts
+            .iter()
+            .map(|artifact| {
+                let built = artifact
+                    .last_build
+                    .map(|at| format!(", built {}", format_timestamp(at)))
+                    .unwrap_or_default();
+                format!(
+                    "{} ({}{})",
+                    artifact.kind.name(),
+                    format_size(artifact.size),
+                    built
+                )
+            })
+            .collect();
+        table.add_row(vec![
+            format!("{}{}", marker, project.root.display()),
+            project
+                .last_activity
+                .map(format_timestamp)
+                .unwrap_or_else(|| "-".to_string()),
+            artifacts.join("\n"),
+            format_size(project.size),
+        ]);
+    }
+    note!("{table}");
+    if report.projects.len() > 20 {
+        note!("  ... and {} more projects", report.projects.len() - 20);
+    }
+
+    let Some(mode) = clean else {
+        if suggested > 0 {
+            note!(
+                "\n🧹 marks projects untouched for {}+ months; their build output comes back with \
+                 `cargo build`, `npm install` or the project's build. Use --clean to remove it.",
+                months
+            );
+        }
+        return Ok(());
+    };
+    let plan = api.plan_dev_artifact_removal(&report, mode);
+    let question = removal_question(&plan, "folder(s)", mode, false);
+    if !approve(
+        &plan,
+        &question,
+        yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+    let results = api.remove_dev_artifacts(&report, mode);
+    for result in results.iter().filter(|r| !r.success) {
+        note!(
+            "  ❌ {}: {}",
+            result.path,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    emit(&results, || delete_csv(&results))?;
+    let removed = results.iter().filter(|r| r.success).count();
+    match mode {
+        DeleteMode::Trash => note!("  Folders moved to trash: {}", removed),
+        DeleteMode::Permanent => note!("  Folders deleted: {}", removed),
+    }
+
+    Ok(())
+}
+
+/// What `stale` does with the files it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum StaleAction {
+    /// Only list them
+    List,
+    /// Move each folder holding nothing but stale files into cold storage:
+    /// archive it, verify the archive, record it and delete the folder
+    Archive,
+    /// Move the stale files to the trash
+    Trash,
+}
+
+/// What `stale` was asked to do besides where and which files
+struct StaleOptions {
+    action: StaleAction,
+    archive_type: ArchiveFormat,
+    archive_dir: Option<PathBuf>,
+    yes: bool,
+    dry_run: bool,
+}
+
+async fn stale_command(
+    path: PathBuf,
+    cutoff: i64,
+    options: StaleOptions,
+    filter: Option<FilterConfig>,
+) -> Result<()> {
+    note!(
+        "Finding files untouched since {} in: {}",
+        format_timestamp(cutoff),
+        path.display()
+    );
+
+    let pb = LiveProgress::start("Scanning files...");
+
+    let api = ServiceApi::new().with_progress(pb.sender());
+    let report = api.find_stale_folders(&path, cutoff, filter).await?;
+
+    pb.finish_with_message("Scan completed");
+    match options.action {
+        StaleAction::List => {
+            if emit(&report, || stale_csv(&report))? {
+                return Ok(());
+            }
+            print_stale_folders(&report);
+            Ok(())
+        }
+        StaleAction::Trash => {
+            let paths: Vec<PathBuf> = report
+                .folders
+                .iter()
+                .flat_map(|folder| &folder.files)
+                .map(|file| PathBuf::from(&file.path))
+                .collect();
+            if paths.is_empty() {
+                note!("\n✅ No stale files found!");
+                return Ok(());
+            }
+            print_stale_folders(&report);
+            delete_command(
+                paths,
+                DeleteMode::Trash,
+                false,
+                options.yes,
+                options.dry_run,
+            )
+        }
+        StaleAction::Archive => archive_stale_folders(&api, &report, &options),
+    }
+}
+
+fn stale_csv(report: &StaleFoldersReport) -> String {
+    let rows = report
+        .folders
+        .iter()
+        .flat_map(|folder| folder.files.iter().map(move |file| (folder, file)))
+        .map(|(folder, file)| {
+            vec![
+                folder.path.clone(),
+                file.path.clone(),
+                file.size.to_string(),
+                file.modified.to_string(),
+                file.accessed.map(|a| a.to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect();
+    csv(&["folder", "path", "size", "modified", "accessed"], rows)
+}
+
+fn print_stale_folders(report: &StaleFoldersReport) {
+    if report.folders.is_empty() {
+        note!("\n✅ No stale files found!");
+        return;
+    }
+
+    note!("\n📊 Stale Files:");
+    note!("  Files: {}", report.file_count);
+    note!("  Reclaimable: {}", format_size(report.total_size));
+    note!("  Last used before: {}", format_timestamp(report.cutoff));
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        "Reclaimable",
+        "Stale files",
+        "Recently used",
+        "Folder",
+    ]);
+    for folder in report.folders.iter().take(20) {
+        let name = if folder.loose {
+            format!("{} (loose files)", folder.path)
+        } else {
+            folder.path.clone()
+        };
+        table.add_row(vec![
+            format_size(folder.total_size),
+            folder.file_count.to_string(),
+            folder.recent_count.to_string(),
+            name,
+        ]);
+    }
+    note!("{table}");
+    if report.folders.len() > 20 {
+        note!("  ... and {} more folders", report.folders.len() - 20);
+    }
+}
+
+/// A folder `stale --action archive` moved into cold storage, or could not
+#[derive(Serialize)]
+struct StaleArchiveResult {
+    path: String,
+    archive: PathBuf,
+    original_size: u64,
+    archive_size: Option<u64>,
+    error: Option<String>,
+}
+
+/// Archive every folder of `report` holding only stale files; loose files,
+/// folders with recently used files and protected folders are left alone
+fn archive_stale_folders(
+    api: &ServiceApi,
+    report: &StaleFoldersReport,
+    options: &StaleOptions,
+) -> Result<()> {
+    let protected = protected_paths();
+    let mut actions = Vec::new();
+    let mut archives = Vec::new();
+    let mut skipped = Vec::new();
+    for folder in &report.folders {
+        let path = PathBuf::from(&folder.path);
+        let reason = if folder.loose {
+            Some(Error::InvalidInput(
+                "Files directly in the scanned directory are not archived".to_string(),
+            ))
+        } else if folder.recent_count > 0 {
+            Some(Error::InvalidInput(format!(
+                "Also holds {} recently used file(s)",
+                folder.recent_count
+            )))
+        } else {
+            protected.check(&path).err().map(Error::from)
+        };
+        if let Some(reason) = reason {
+            skipped.push(FileError::new(&path, reason));
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let dest = options
+            .archive_dir
+            .as_deref()
+            .or(path.parent())
+            .unwrap_or(Path::new(""))
+            .join(format!("{}.{}", name, options.archive_type.extension()));
+        if dest.exists() {
+            skipped.push(FileError::new(
+                &path,
+                Error::InvalidInput(format!("Output already exists: {}", dest.display())),
+            ));
+            continue;
+        }
+        actions.push(PlannedAction {
+            path,
+            action: "archive".to_string(),
+            size: folder.total_size,
+            reclaimed: None,
+            detail: Some(format!("into {}", dest.display())),
+        });
+        archives.push(dest);
+    }
+    let plan = ActionPlan::new(actions, skipped);
+    if options.dry_run {
+        return show_dry_run(&plan);
+    }
+
+    print_stale_folders(report);
+    let question = format!(
+        "Archive {} folder(s) ({}) and delete the originals?",
+        plan.actions.len(),
+        format_size(plan.total_size)
+    );
+    if !approve(
+        &plan,
+        &question,
+        options.yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+
+    let config = Config::load_or_default();
+    config.ensure_directories()?;
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let mut results = Vec::new();
+    for (action, archive) in plan.actions.iter().zip(archives) {
+        note!("Archiving: {}", action.path.display());
+        let outcome =
+            api.archive_directory(&action.path, &archive, options.archive_type, None, &db);
+        let (sizes, error) = match outcome {
+            Ok(record) => (Some((record.original_size, record.archive_size)), None),
+            Err(e) => (None, Some(format!("{:#}", e))),
+        };
+        results.push(StaleArchiveResult {
+            path: action.path.display().to_string(),
+            archive,
+            original_size: sizes.map_or(action.size, |(original, _)| original),
+            archive_size: sizes.map(|(_, archive)| archive),
+            error,
+        });
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    if !emit(&results, || {
+        csv(
+            &["path", "archive", "original_size", "archive_size", "error"],
+            results
+                .iter()
+                .map(|r| {
+                    vec![
+                        r.path.clone(),
+                        r.archive.display().to_string(),
+                        r.original_size.to_string(),
+                        r.archive_size.map(|s| s.to_string()).unwrap_or_default(),
+                        r.error.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        )
+    })? {
+        let archived: Vec<&StaleArchiveResult> =
+            results.iter().filter(|r| r.error.is_none()).collect();
+        let freed: u64 = archived
+            .iter()
+            .map(|r| {
+                r.original_size
+                    .saturating_sub(r.archive_size.unwrap_or_default())
+            })
+            .sum();
+        note!(
+            "\n📦 Archived {} folder(s), freeing {}",
+            archived.len(),
+            format_size(freed)
+        );
+        for result in results.iter().filter(|r| r.error.is_some()) {
+            note!(
+                "  ❌ {}: {}",
+                result.path,
+                result.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{} of {} folder(s) could not be archived",
+            failed,
+            results.len()
+        );
+    }
+    Ok(())
+}
+
+async fn archive_command(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    format: ArchiveFormat,
+    replace: bool,
+    password_env: Option<String>,
+) -> Result<()> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Not found: {}", path.display()))?;
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{}.{}", name, format.extension()))
+        }
+    };
+    if output.exists() {
+        bail!("Output already exists: {}", output.display());
+    }
+
+    let password = match &password_env {
+        Some(var) => Some(
+            std::env::var(var)
+                .with_context(|| format!("Environment variable {} is not set", var))?,
+        ),
+        None => None,
+    };
+
+    note!("Archiving: {}", path.display());
+    let (size, record) = if replace {
+        let config = Config::load_or_default();
+        config.ensure_directories()?;
+        let db = SqliteDatabase::new(&config.database_path)?;
+        let record = ServiceApi::new().archive_directory(&path, &output, format, password, &db)?;
+        (record.archive_size, Some(record))
+    } else {
+        let size = Compressor::new_archive(format, password)?.compress_path(&path, &output)?;
+        (size, None)
+    };
+
+    let data = serde_json::json!({
+        "path": path,
+        "output": output,
+        "size": size,
+        "encrypted": password_env.is_some(),
+        "record": record,
+    });
+    if emit(&data, || {
+        csv(
+            &["path", "output", "size", "encrypted", "replaced"],
+            vec![vec![
+                path.display().to_string(),
+                output.display().to_string(),
+                size.to_string(),
+                password_env.is_some().to_string(),
+                record.is_some().to_string(),
+            ]],
+        )
+    })? {
+        return Ok(());
+    }
+
+    note!("\n✅ Archive created: {}", output.display());
+    note!("  Size: {}", format_size(size));
+    if password_env.is_some() {
+        note!("  Encryption: AES-256");
+    }
+    if let Some(record) = record {
+        note!(
+            "  Verified, recorded and removed the original ({} files, {})",
+            record.file_count,
+            format_size(record.original_size)
+        );
+    }
+
+    Ok(())
+}
+
+/// What `compress` was asked to do besides where and which files
+struct CompressOptions {
+    plugins: Vec<String>,
+    dry_run: bool,
+    min_savings: Option<f32>,
+    no_backup: bool,
+    yes: bool,
+    /// The paths came from stdin: compressing them is no scan worth
+    /// recording
+    piped: bool,
+}
+
+async fn compress_command(
+    paths: Vec<PathBuf>,
+    options: CompressOptions,
+    filter: Option<FilterConfig>,
+) -> Result<()> {
+    let config = Config::load_or_default();
+    config.ensure_directories()?;
+    let db = Database::open(&config.database_path)?;
+
+    let min_savings = min_savings_percent(options.min_savings, config.min_savings_percent)?;
+    let manager = configured_plugin_manager(&config)?;
+    let (plugins, extensions) = {
+        let mut manager = manager.write().map_err(Error::from)?;
+        manager.set_min_savings_percent(min_savings);
+        let plugins = enabled_plugins(&manager, &options.plugins)?;
+        let extensions: Vec<String> = plugins
+            .iter()
+            .flat_map(|name| manager.get_supported_extensions(name))
+            .collect();
+        (plugins, extensions)
+    };
+
+    // Only files some chosen plugin takes by extension are worth checking
+    let filter = FilterConfig {
+        extensions: Some(extensions),
+        ..filter.unwrap_or_default()
+    };
+
+    note!("Scanning: {}", describe_paths(&paths));
+    let pb = LiveProgress::start("Scanning files...");
+    let api = ServiceApi::new()
+        .with_ignore_list(ignore_list())
+        .with_progress(pb.sender());
+    let paths = paths
+        .iter()
+        .map(std::path::absolute)
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let files = match paths.as_slice() {
+        [path] if !options.piped => {
+            let api = api.with_database(db.clone());
+            let ScanResult { files, .. } = api.scan_directory(path.clone(), Some(filter)).await?;
+            files
+        }
+        _ => api.collect_files(&paths, Some(&filter))?,
+    };
+
+    pb.finish_and_clear();
+    let pb = ProgressBar::new_spinner();
+    pb.set_message("Estimating savings...");
+    let manager = manager.read().map_err(Error::from)?;
+    let plan = space_saver_service::compress::compression_plan(
+        &manager,
+        files,
+        &plugins,
+        min_savings,
+        &protected_paths(),
+        &*db.get()?,
+    )?;
+    pb.finish_and_clear();
+
+    if options.dry_run {
+        return show_dry_run(&plan);
+    }
+    // Nothing to compress reads as an empty plan
+    if plan.is_empty() && emit(&plan, || plan_csv(&plan))? {
+        return Ok(());
+    }
+    let keep_backup = config.default_compress_backup && !options.no_backup;
+    let subject = format!(
+        "{} file(s) ({})",
+        plan.actions.len(),
+        format_size(plan.total_size)
+    );
+    let question = if keep_backup {
+        format!("Compress {}, keeping the originals as .bak?", subject)
+    } else {
+        format!("Compress {} and delete the originals?", subject)
+    };
+    if !approve(
+        &plan,
+        &question,
+        options.yes,
+        forced(),
+        &mut std::io::stdin().lock(),
+    )? {
+        return Ok(());
+    }
+
+    let sources: Vec<PathBuf> = plan.actions.into_iter().map(|p| p.path).collect();
+    let results = hooks().around(
+        "compress",
+        &sources,
+        || compress_with_progress(&manager, &sources, &plugins, keep_backup),
+        |results| match results {
+            Ok(results) => space_saver_service::compression_outcome(results),
+            Err(_) => HookOutcome {
+                failed: sources.len(),
+                ..Default::default()
+            },
+        },
+    )??;
+
+    let ledger = db.get()?;
+    let mut report = Vec::new();
+    for (source, result) in sources.into_iter().zip(results) {
+        let mut file = CompressedFile {
+            path: source,
+            status: "compressed",
+            plugin: None,
+            original_size: None,
+            compressed_size: None,
+            error: None,
+        };
+        match result {
+            Ok(CompressionOutcome::Compressed(result)) => {
+                let op = space_saver_service::compression_operation(&file.path, &result);
+                if let Err(e) = ledger.insert_compression_operation(&op) {
+                    note!(
+                        "⚠️  Savings of {} not recorded: {:#}",
+                        file.path.display(),
+                        e
+                    );
+                }
+                file.plugin = Some(result.plugin_name);
+                file.original_size = Some(result.original_size);
+                file.compressed_size = Some(result.compressed_size);
+            }
+            Ok(CompressionOutcome::Skipped {
+                plugin_name,
+                reason,
+            }) => {
+                file.status = "skipped";
+                file.plugin = Some(plugin_name);
+                file.error = Some(reason);
+            }
+            Err(e) => {
+                note!("  ❌ {}: {:#}", file.path.display(), e);
+                file.status = "failed";
+                file.error = Some(format!("{:#}", e));
+            }
+        }
+        report.push(file);
+    }
+    emit(&report, || {
+        let rows = report
+            .iter()
+            .map(|file| {
+                let size = |size: Option<u64>| size.map(|s| s.to_string()).unwrap_or_default();
+                vec![
+                    file.path.display().to_string(),
+                    file.status.to_string(),
+                    file.plugin.clone().unwrap_or_default(),
+                    size(file.original_size),
+                    size(file.compressed_size),
+                    file.error.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        csv(
+            &[
+                "path",
+                "status",
+                "plugin",
+                "original_size",
+                "compressed_size",
+                "error",
+            ],
+            rows,
+        )
+    })?;
+
+    let count = |status: &str| report.iter().filter(|f| f.status == status).count();
+    let (compressed, skipped, failed) = (count("compressed"), count("skipped"), count("failed"));
+    let saved: u64 = report
+        .iter()
+        .filter_map(|f| Some(f.original_size?.saturating_sub(f.compressed_size?)))
+        .sum();
+    note!(
+        "\n✅ Compressed {} file(s), saving {}",
+        compressed,
+        format_size(saved)
+    );
+    if skipped > 0 {
+        note!("  Skipped (not enough smaller): {}", skipped);
+    }
+    if failed > 0 {
+        bail!("Failed to compress {} file(s)", failed);
+    }
+    Ok(())
+}
+
+/// Worker threads --jobs asks for
+static JOBS: OnceLock<usize> = OnceLock::new();
+
+/// Hash on `jobs` threads, and compress that many files at once
+fn set_jobs(jobs: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+        .context("Failed to set up the worker threads")?;
+    let _ = JOBS.set(jobs);
+    Ok(())
+}
+
+/// The global plugin manager with the config's settings and per-plugin
+/// qualities applied, and the worker count --jobs asks for
+fn configured_plugin_manager(config: &Config) -> Result<Arc<RwLock<PluginManager>>> {
+    let manager = space_saver_core::compress_plugins::global_plugin_manager();
+    {
+        let mut manager = manager.write().map_err(Error::from)?;
+        space_saver_service::configure_manager(&mut manager, config);
+        if let Some(&jobs) = JOBS.get() {
+            manager.set_max_workers(jobs);
+        }
+        for (name, quality) in &config.plugin_quality {
+            let _ = manager.set_plugin_quality(name, *quality);
+        }
+    }
+    Ok(manager)
+}
+
+/// The `--min-savings` percent, or the config's when it isn't given
+fn min_savings_percent(requested: Option<f32>, configured: f32) -> Result<f32> {
+    let percent = requested.unwrap_or(configured);
+    if !(0.0..=100.0).contains(&percent) {
+        bail!(Error::InvalidInput(format!(
+            "--min-savings must be between 0 and 100, got {}",
+            percent
+        )));
+    }
+    Ok(percent)
+}
+
+/// The plugins `compress --plugins` picks that are turned on, failing when
+/// none is left
+fn enabled_plugins(manager: &PluginManager, wanted: &[String]) -> Result<Vec<String>> {
+    let mut plugins = select_plugins(&manager.get_plugins(), wanted)?;
+    plugins.retain(|name| manager.is_plugin_enabled(name));
+    if plugins.is_empty() {
+        bail!(Error::InvalidInput(
+            "Every chosen plugin is disabled; turn one on with `space-saver plugins enable <name>`"
+                .to_string()
+        ));
+    }
+    Ok(plugins)
+}
+
+/// The single registered plugin `name` picks, matched like `compress
+/// --plugins` does
+fn plugin_named(registered: &[PluginMetadata], name: &str) -> Result<String> {
+    let mut matches = select_plugins(registered, &[name.to_string()])?;
+    if matches.len() > 1 {
+        bail!(Error::InvalidInput(format!(
+            "'{}' matches several plugins: {}",
+            name,
+            matches.join(", ")
+        )));
+    }
+    Ok(matches.remove(0))
+}
+
+/// The registered plugins `wanted` names, in the order given: a name picks
+/// the plugin called that (ignoring case), or else every plugin whose name
+/// contains it. All plugins when nothing is named.
+fn select_plugins(registered: &[PluginMetadata], wanted: &[String]) -> Result<Vec<String>> {
+    if wanted.is_empty() {
+        return Ok(registered.iter().map(|p| p.name.clone()).collect());
+    }
+    let mut selected: Vec<String> = Vec::new();
+    for name in wanted {
+        let needle = name.trim().to_lowercase();
+        let exact: Vec<&PluginMetadata> = registered
+            .iter()
+            .filter(|p| p.name.to_lowercase() == needle)
+            .collect();
+        let matches = if exact.is_empty() {
+            registered
+                .iter()
+                .filter(|p| p.name.to_lowercase().contains(&needle))
+                .collect()
+        } else {
+            exact
+        };
+        if needle.is_empty() || matches.is_empty() {
+            let names: Vec<&str> = registered.iter().map(|p| p.name.as_str()).collect();
+            bail!(Error::NotFound(format!(
+                "No compression plugin matches '{}'; available: {}",
+                name,
+                names.join(", ")
+            )));
+        }
+        for plugin in matches {
+            if !selected.contains(&plugin.name) {
+                selected.push(plugin.name.clone());
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// A plugin as `plugins list` and `plugins info` show it
+#[derive(Serialize)]
+struct PluginInfo {
+    #[serde(flatten)]
+    metadata: PluginMetadata,
+    enabled: bool,
+    quality: Option<f32>,
+    extensions: Vec<String>,
+    /// It can process files with the tools on this machine
+    ready: bool,
+    /// How it will run, or why it cannot
+    message: Option<String>,
+    tools: Vec<ToolCheck>,
+}
+
+fn plugin_info(manager: &PluginManager, metadata: PluginMetadata) -> PluginInfo {
+    let requirements = manager
+        .check_plugin_requirements()
+        .into_iter()
+        .find(|r| r.plugin_name == metadata.name);
+    PluginInfo {
+        enabled: manager.is_plugin_enabled(&metadata.name),
+        quality: manager.get_plugin_quality(&metadata.name),
+        extensions: manager.get_supported_extensions(&metadata.name),
+        ready: requirements.as_ref().is_none_or(|r| r.ready),
+        message: requirements.as_ref().and_then(|r| r.message.clone()),
+        tools: requirements.map(|r| r.tools).unwrap_or_default(),
+        metadata,
+    }
+}
+
+/// What trying a plugin on a copy of one file gave
+#[derive(Serialize)]
+struct PluginTest {
+    path: PathBuf,
+    plugin: String,
+    /// "compressed", or "skipped" when the output was not smaller enough
+    status: &'static str,
+    original_size: u64,
+    compressed_size: Option<u64>,
+    seconds: f64,
+    /// Why it was skipped
+    reason: Option<String>,
+}
+
+/// The config file and its settings, for commands that change them. Unlike
+/// `Config::load_or_default`, a file that does not parse is an error
+/// rather than being overwritten with defaults.
+fn load_config_for_update() -> Result<(PathBuf, Config)> {
+    let path = Config::default_path();
+    let config = if path.exists() {
+        Config::load(&path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        Config::default()
+    };
+    Ok((path, config))
+}
+
+fn parse_switch(value: &str) -> Result<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "on" | "yes" | "1" => Ok(true),
+        "false" | "off" | "no" | "0" => Ok(false),
+        other => bail!(Error::InvalidInput(format!(
+            "Expected true or false, got '{}'",
+            other
+        ))),
+    }
+}
+
+/// Apply one key=value `setting` of `plugin` to the manager and the config
+fn apply_plugin_setting(
+    manager: &mut PluginManager,
+    config: &mut Config,
+    plugin: &str,
+    setting: &str,
+) -> Result<()> {
+    let (key, value) = setting
+        .split_once('=')
+        .ok_or_else(|| Error::InvalidInput(format!("Expected key=value, got '{}'", setting)))?;
+    match key.trim() {
+        "quality" => {
+            let quality: f32 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|q| (0.0..=100.0).contains(q))
+                .ok_or_else(|| {
+                    Error::InvalidInput(format!(
+                        "quality must be between 0 and 100, got '{}'",
+                        value
+                    ))
+                })?;
+            manager.set_plugin_quality(plugin, quality)?;
+            config.plugin_quality.insert(plugin.to_string(), quality);
+        }
+        "enabled" => {
+            let enabled = parse_switch(value)?;
+            config.disabled_plugins.retain(|name| name != plugin);
+            if !enabled {
+                config.disabled_plugins.push(plugin.to_string());
+            }
+            manager.set_disabled_plugins(&config.disabled_plugins);
+        }
+        other => bail!(Error::InvalidInput(format!(
+            "Unknown plugin setting '{}'; expected quality or enabled",
+            other
+        ))),
+    }
+    Ok(())
+}
+
+fn plugins_command(action: PluginsAction) -> Result<()> {
+    let (config_path, mut config) = load_config_for_update()?;
+    let manager = configured_plugin_manager(&config)?;
+    let mut manager = manager.write().map_err(Error::from)?;
+    let registered = manager.get_plugins();
+
+    let (name, settings) = match action {
+        PluginsAction::List => {
+            let plugins: Vec<PluginInfo> = registered
+                .into_iter()
+                .map(|metadata| plugin_info(&manager, metadata))
+                .collect();
+            return print_plugins(&plugins);
+        }
+        PluginsAction::Info { name } => {
+            let name = plugin_named(&registered, &name)?;
+            let metadata = registered.into_iter().find(|p| p.name == name);
+            return print_plugin(&plugin_info(&manager, metadata.expect("matched plugin")));
+        }
+        PluginsAction::Test { file, plugin } => {
+            let plugin = plugin
+                .map(|name| plugin_named(&registered, &name))
+                .transpose()?;
+            return print_plugin_test(&test_plugin(&manager, &file, plugin.as_deref())?);
+        }
+        PluginsAction::Enable { name } => (name, vec!["enabled=true".to_string()]),
+        PluginsAction::Disable { name } => (name, vec!["enabled=false".to_string()]),
+        PluginsAction::Set { name, settings } => (name, settings),
+    };
+
+    let name = plugin_named(&registered, &name)?;
+    for setting in &settings {
+        apply_plugin_setting(&mut manager, &mut config, &name, setting)?;
+    }
+    config.save(&config_path)?;
+    let metadata = registered.into_iter().find(|p| p.name == name);
+    let info = plugin_info(&manager, metadata.expect("matched plugin"));
+    if emit(&info, || plugins_csv(std::slice::from_ref(&info)))? {
+        return Ok(());
+    }
+    let quality = info
+        .quality
+        .map(|q| format!(", quality {}", q))
+        .unwrap_or_default();
+    let state = if info.enabled { "enabled" } else { "disabled" };
+    note!("✅ {}: {}{}", info.metadata.name, state, quality);
+    note!("Saved to {}", config_path.display());
+    Ok(())
+}
+
+fn plugins_csv(plugins: &[PluginInfo]) -> String {
+    csv(
+        &[
+            "name",
+            "enabled",
+            "quality",
+            "lossy",
+            "destructive",
+            "extensions",
+            "ready",
+        ],
+        plugins
+            .iter()
+            .map(|p| {
+                vec![
+                    p.metadata.name.clone(),
+                    p.enabled.to_string(),
+                    p.quality.map(|q| q.to_string()).unwrap_or_default(),
+                    p.metadata.lossy.to_string(),
+                    p.metadata.destructive.to_string(),
+                    p.extensions.join(";"),
+                    p.ready.to_string(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn print_plugins(plugins: &[PluginInfo]) -> Result<()> {
+    if emit(&plugins, || plugins_csv(plugins))? {
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        "Plugin",
+        "Enabled",
+        "Quality",
+        "Lossy",
+        "File types",
+        "Ready",
+    ]);
+    for plugin in plugins {
+        table.add_row(vec![
+            plugin.metadata.name.clone(),
+            yes_no(plugin.enabled).to_string(),
+            plugin.quality.map(|q| q.to_string()).unwrap_or_default(),
+            yes_no(plugin.metadata.lossy).to_string(),
+            plugin.extensions.join(", "),
+            yes_no(plugin.ready).to_string(),
+        ]);
+    }
+    note!("{table}");
+    note!("\nMore on one with `space-saver plugins info <name>`.");
+    Ok(())
+}
+
+fn print_plugin(plugin: &PluginInfo) -> Result<()> {
+    if emit(plugin, || plugins_csv(std::slice::from_ref(plugin)))? {
+        return Ok(());
+    }
+    let metadata = &plugin.metadata;
+    note!("{} {}", metadata.name, metadata.version);
+    note!("  {}", metadata.description);
+    note!("  Enabled: {}", yes_no(plugin.enabled));
+    if let Some(quality) = plugin.quality {
+        note!("  Quality: {}", quality);
+    }
+    note!("  Lossy: {}", yes_no(metadata.lossy));
+    note!("  Replaces the original: {}", yes_no(metadata.destructive));
+    note!("  File types: {}", plugin.extensions.join(", "));
+    note!(
+        "  Ready: {}{}",
+        yes_no(plugin.ready),
+        plugin
+            .message
+            .as_ref()
+            .map(|m| format!(" ({})", m))
+            .unwrap_or_default()
+    );
+    if !plugin.tools.is_empty() {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Tool", "Program", "Found", "Version"]);
+        for tool in &plugin.tools {
+            table.add_row(vec![
+                tool.name.clone(),
+                tool.program.clone(),
+                yes_no(tool.available).to_string(),
+                tool.version
+                    .clone()
+                    .or_else(|| tool.error.clone())
+                    .unwrap_or_default(),
+            ]);
+        }
+        note!("\n{table}");
+    }
+    Ok(())
+}
+
+/// Compress a copy of `file` in a scratch directory with `plugin`, or the
+/// first enabled plugin taking it, then remove the copy and its output
+fn test_plugin(manager: &PluginManager, file: &Path, plugin: Option<&str>) -> Result<PluginTest> {
+    let metadata =
+        std::fs::metadata(file).with_context(|| format!("Cannot read {}", file.display()))?;
+    if !metadata.is_file() {
+        bail!(Error::InvalidInput(format!(
+            "{} is not a file",
+            file.display()
+        )));
+    }
+    let scratch =
+        std::env::temp_dir().join(format!("space-saver-plugin-test-{}", std::process::id()));
+    std::fs::create_dir_all(&scratch)?;
+    let copy = scratch.join(file.file_name().unwrap_or_default());
+
+    let start = std::time::Instant::now();
+    let outcome = std::fs::copy(file, &copy)
+        .with_context(|| format!("Failed to copy {}", file.display()))
+        .and_then(|_| match plugin {
+            Some(name) => manager.process_with_plugin(&copy, &scratch, name, true),
+            None => manager.process_file(&copy, &scratch, None, true, None),
+        });
+    let seconds = start.elapsed().as_secs_f64();
+    let _ = std::fs::remove_dir_all(&scratch);
+
+    let (plugin, status, compressed_size, reason) = match outcome? {
+        CompressionOutcome::Compressed(result) => (
+            result.plugin_name,
+            "compressed",
+            Some(result.compressed_size),
+            None,
+        ),
+        CompressionOutcome::Skipped {
+            plugin_name,
+            reason,
+        } => (plugin_name, "skipped", None, Some(reason)),
+    };
+    Ok(PluginTest {
+        path: file.to_path_buf(),
+        plugin,
+        status,
+        original_size: metadata.len(),
+        compressed_size,
+        seconds,
+        reason,
+    })
+}
+
+fn print_plugin_test(test: &PluginTest) -> Result<()> {
+    if emit(test, || {
+        csv(
+            &[
+                "path",
+                "plugin",
+                "status",
+                "original_size",
+                "compressed_size",
+                "seconds",
+                "reason",
+            ],
+            vec![vec![
+                test.path.display().to_string(),
+                test.plugin.clone(),
+                test.status.to_string(),
+                test.original_size.to_string(),
+                test.compressed_size
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                format!("{:.3}", test.seconds),
+                test.reason.clone().unwrap_or_default(),
+            ]],
+        )
+    })? {
+        return Ok(());
+    }
+    note!("Plugin: {}", test.plugin);
+    match test.compressed_size {
+        Some(compressed) => {
+            let saved = test.original_size.saturating_sub(compressed);
+            note!(
+                "  {} → {} (saves {}, {:.1}%) in {:.2}s",
+                format_size(test.original_size),
+                format_size(compressed),
+                format_size(saved),
+                saved as f64 * 100.0 / test.original_size.max(1) as f64,
+                test.seconds
+            );
+        }
+        None => note!(
+            "  Skipped after {:.2}s: {}",
+            test.seconds,
+            test.reason.as_deref().unwrap_or_default()
+        ),
+    }
+    note!("\n{} itself was not changed.", test.path.display());
+    Ok(())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// What happened to one file of a `compress` run
+#[derive(Serialize)]
+struct CompressedFile {
+    path: PathBuf,
+    /// "compressed", "skipped" (the output was not smaller enough) or
+    /// "failed"
+    status: &'static str,
+    plugin: Option<String>,
+    original_size: Option<u64>,
+    compressed_size: Option<u64>,
+    /// Why it was skipped or failed
+    error: Option<String>,
+}
+
+/// Run `sources` through `process_batch`, one batch per directory so each
+/// output lands next to its source, with an overall bar and one for the file
+/// in hand. Results line up with `sources`.
+fn compress_with_progress(
+    manager: &PluginManager,
+    sources: &[PathBuf],
+    plugins: &[String],
+    keep_backup: bool,
+) -> Result<Vec<Result<CompressionOutcome>>> {
+    let bars = MultiProgress::new();
+    let overall = bars.add(ProgressBar::new(sources.len() as u64));
+    overall.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40.green} {pos}/{len} files {prefix} {msg}")
+            .unwrap(),
+    );
+    let sizes: Vec<u64> = sources
+        .iter()
+        .map(|source| source.metadata().map(|m| m.len()).unwrap_or(0))
+        .collect();
+    let total_bytes: u64 = sizes.iter().sum();
+    let started = std::time::Instant::now();
+    let file = bars.add(ProgressBar::new(0));
+    file.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40.cyan} {wide_msg}")
+            .unwrap(),
+    );
+    let sink = ProgressSink::new().on_progress({
+        let file = file.clone();
+        move |progress: &PluginProgress| {
+            file.set_length(progress.total);
+            file.set_position(progress.current);
+            file.set_message(progress.path.display().to_string());
+        }
+    });
+
+    let mut batches: Vec<(&Path, Vec<usize>)> = Vec::new();
+    for (index, source) in sources.iter().enumerate() {
+        let dir = source.parent().unwrap_or_else(|| Path::new("."));
+        match batches.iter_mut().find(|(d, _)| *d == dir) {
+            Some((_, indices)) => indices.push(index),
+            None => batches.push((dir, vec![index])),
+        }
+    }
+
+    let mut results: Vec<Option<Result<CompressionOutcome>>> =
+        sources.iter().map(|_| None).collect();
+    // Batches of a few files per worker keep the statistics moving
+    // without leaving workers idle for long
+    let chunk = manager.max_workers() * 4;
+    let mut done_bytes = 0;
+    for (dir, indices) in batches {
+        overall.set_message(dir.display().to_string());
+        for indices in indices.chunks(chunk) {
+            let batch: Vec<PathBuf> = indices.iter().map(|&i| sources[i].clone()).collect();
+            let outcomes =
+                manager.process_batch(&batch, dir, Some(plugins), keep_backup, Some(&sink))?;
+            for (&index, outcome) in indices.iter().zip(outcomes) {
+                results[index] = Some(outcome);
+                done_bytes += sizes[index];
+            }
+            overall.inc(batch.len() as u64);
+            overall.set_prefix(progress::live_stats(
+                (overall.position() as usize, sources.len()),
+                (done_bytes, total_bytes),
+                started.elapsed(),
+            ));
+        }
+    }
+    file.finish_and_clear();
+    overall.finish_and_clear();
+
+    Ok(results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow::anyhow!("File was not processed"))))
+        .collect())
+}
+
+/// Ask a yes/no question on stdin; anything but y or yes is no. With
+/// stdin closed there is no one to answer, which is an error pointing at
+/// --yes rather than a silent no.
+fn confirm(question: &str) -> Result<bool> {
+    ask(question, &mut std::io::stdin().lock())
+}
+
+/// [`confirm`], reading the answer from `input`
+fn ask(question: &str, input: &mut impl BufRead) -> Result<bool> {
+    output::prompt(&format!("{} [y/N] ", question))?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        bail!(Error::InvalidInput(
+            "No answer to the confirmation prompt; pass --yes to go ahead without one".to_string()
+        ));
+    }
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The check every destructive step goes through: refuse when `plan` would
+/// have to leave protected paths alone (unless `force`), then show it and
+/// ask `question`, reading the answer from `input` (unless `yes`). True to
+/// go ahead.
+fn approve(
+    plan: &ActionPlan,
+    question: &str,
+    yes: bool,
+    force: bool,
+    input: &mut impl BufRead,
+) -> Result<bool> {
+    refuse_protected(plan, force)?;
+    if plan.is_empty() {
+        print_plan(plan);
+        return Ok(false);
+    }
+    if yes {
+        return Ok(true);
+    }
+    print_plan(plan);
+    if !ask(question, input)? {
+        note!("Nothing changed.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Err naming the protected paths `plan` skips, unless `force`
+fn refuse_protected(plan: &ActionPlan, force: bool) -> Result<()> {
+    if force {
+        return Ok(());
+    }
+    let protected: Vec<&FileError> = plan
+        .skipped
+        .iter()
+        .filter(|skipped| skipped.code == ErrorCode::Protected)
+        .collect();
+    if protected.is_empty() {
+        return Ok(());
+    }
+    for skipped in protected.iter().take(10) {
+        note!("  🔒 {}", skipped.path.display());
+    }
+    if protected.len() > 10 {
+        note!("  ... and {} more", protected.len() - 10);
+    }
+    bail!(Error::Protected(format!(
+        "{} path(s) are protected; leave them out, or pass --force to act on paths protected in config.toml",
+        protected.len()
+    )))
+}
+
+/// "Move 3 file(s) (1.20 MB) to the trash?" for a removal of `plan`'s
+/// actions, `what` naming them
+fn removal_question(plan: &ActionPlan, what: &str, mode: DeleteMode, quarantine: bool) -> String {
+    let mut subject = format!("{} {}", plan.actions.len(), what);
+    if plan.total_size > 0 {
+        subject = format!("{} ({})", subject, format_size(plan.total_size));
+    }
+    match (quarantine, mode) {
+        (true, _) => format!("Move {} into quarantine?", subject),
+        (false, DeleteMode::Trash) => format!("Move {} to the trash?", subject),
+        (false, DeleteMode::Permanent) => format!("Delete {} permanently?", subject),
+    }
+}
+
+fn reflink_dedupe(api: &ServiceApi, resolutions: &[DuplicateResolution]) -> Result<()> {
+    let results = api.reflink_duplicates(resolutions);
+    emit(&results, || {
+        csv(
+            &[
+                "path",
+                "source",
+                "success",
+                "unsupported",
+                "reclaimed",
+                "error",
+            ],
+            results
+                .iter()
+                .map(|r| {
+                    vec![
+                        r.path.clone(),
+                        r.source.clone(),
+                        r.success.to_string(),
+                        r.unsupported.to_string(),
+                        r.reclaimed.to_string(),
+                        r.error.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        )
+    })?;
+    let cloned: Vec<_> = results.iter().filter(|r| r.success).collect();
+    let unsupported = results.iter().filter(|r| r.unsupported).count();
+    note!("  Cloned: {}", cloned.len());
+    note!(
+        "  Space reclaimed: {}",
+        format_size(cloned.iter().map(|r| r.reclaimed).sum())
+    );
+    if unsupported > 0 {
+        note!(
+            "  ⚠️  {} file(s) left as they were: their file system does not support clones",
+            unsupported
+        );
+    }
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|r| !r.success && !r.unsupported)
+        .collect();
+    for result in &failed {
+        note!(
+            "  ❌ {}: {}",
+            result.path,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    if !failed.is_empty() {
+        bail!("Failed to clone {} file(s)", failed.len());
+    }
+    Ok(())
+}
+
+fn hardlink_dedupe(api: &ServiceApi, resolutions: &[DuplicateResolution]) -> Result<()> {
+    let results = api.hardlink_duplicates(resolutions);
+    emit(&results, || {
+        csv(
+            &["path", "source", "success", "reclaimed", "error"],
+            results
+                .iter()
+                .map(|r| {
+                    vec![
+                        r.path.clone(),
+                        r.source.clone(),
+                        r.success.to_string(),
+                        r.reclaimed.to_string(),
+                        r.error.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        )
+    })?;
+    let (linked, failed): (Vec<_>, Vec<_>) = results.iter().partition(|r| r.success);
+    note!("  Linked: {}", linked.len());
+    note!(
+        "  Space reclaimed: {}",
+        format_size(linked.iter().map(|r| r.reclaimed).sum())
+    );
+    for result in &failed {
+        note!(
+            "  ❌ {}: {}",
+            result.path,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    if !failed.is_empty() {
+        bail!("Failed to link {} file(s)", failed.len());
+    }
+    Ok(())
+}
+
+fn restore_command(list: bool, id: Option<i64>, path: Option<PathBuf>) -> Result<()> {
+    let config = Config::load_or_default();
+    config.ensure_directories()?;
+    let db = SqliteDatabase::new(&config.database_path)?;
+
+    if let Some(id) = id {
+        let point = restore::restore(&db, id)?;
+        if emit(&point, || restore_point_csv(std::slice::from_ref(&point)))? {
+            return Ok(());
+        }
+        note!("✅ Restored {}", point.path);
+        return Ok(());
+    }
+    if let (Some(path), false) = (&path, list) {
+        let results = restore::restore_under(&db, path)?;
+        if emit(&results, || {
+            let rows = results
+                .iter()
+                .map(|r| {
+                    vec![
+                        r.id.to_string(),
+                        r.kind.clone(),
+                        r.path.clone(),
+                        r.success.to_string(),
+                        r.error.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            csv(&["id", "kind", "path", "success", "error"], rows)
+        })? {
+            return Ok(());
+        }
+        if results.is_empty() {
+            note!("Nothing to restore under {}", path.display());
+            return Ok(());
+        }
+        let failed: Vec<_> = results.iter().filter(|r| !r.success).collect();
+        note!("✅ Restored: {}", results.len() - failed.len());
+        for result in &failed {
+            note!(
+                "  ❌ {}: {}",
+                result.path,
+                result.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+        if !failed.is_empty() {
+            bail!("Failed to restore {} file(s)", failed.len());
+        }
+        return Ok(());
+    }
+
+    let points = restore::restore_points(&db, path.as_deref())?;
+    if emit(&points, || restore_point_csv(&points))? {
+        return Ok(());
+    }
+    if points.is_empty() {
+        note!("✅ Nothing to restore");
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["ID", "Change", "Path", "Size", "When"]);
+    for point in &points {
+        let change = match (point.kind.as_str(), &point.output_path) {
+            ("compression", Some(output)) if *output != point.path => {
+                format!("compressed to {}", output)
+            }
+            ("compression", _) => "compressed".to_string(),
+            ("trash", _) => "moved to the trash".to_string(),
+            (kind, _) => kind.to_string(),
+        };
+        table.add_row(vec![
+            point.id.to_string(),
+            change,
+            point.path.clone(),
+            format_size(point.size),
+            format_timestamp(point.created_at),
+        ]);
+    }
+    note!("{}", table);
+    note!("\nUndo one with --id, or every file under a folder with --path.");
+    Ok(())
+}
+
+/// One line per change that can be undone
+fn restore_point_csv(points: &[RestorePoint]) -> String {
+    let rows = points
+        .iter()
+        .map(|p| {
+            vec![
+                p.id.to_string(),
+                p.kind.clone(),
+                p.path.clone(),
+                p.backup_path.clone().unwrap_or_default(),
+                p.output_path.clone().unwrap_or_default(),
+                p.size.to_string(),
+                p.created_at.to_string(),
+            ]
+        })
+        .collect();
+    csv(
+        &[
+            "id",
+            "kind",
+            "path",
+            "backup_path",
+            "output_path",
+            "size",
+            "created_at",
+        ],
+        rows,
+    )
+}
+
+fn quarantine_command(action: QuarantineAction) -> Result<()> {
+    let config = Config::load_or_default();
+    config.ensure_directories()?;
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let quarantine = Quarantine::new(&db, &config.quarantine_dir)
+        .with_retention_days(config.quarantine_days)
+        .with_protected_paths(protected_paths());
+    // Expired files go whenever the quarantine is touched
+    let purged = quarantine.purge_expired(space_saver_utils::time::now())?;
+
+    match action {
+        QuarantineAction::List => {
+            let records = quarantine.list()?;
+            if emit(&records, || quarantine_csv(&records))? {
+                return Ok(());
+            }
+            if records.is_empty() {
+                note!("✅ Quarantine is empty");
+                return Ok(());
+            }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["ID", "Original path", "Size", "Purged after"]);
+            for record in &records {
+                table.add_row(vec![
+                    record.id.to_string(),
+                    record.original_path.clone(),
+                    format_size(record.size),
+                    format_timestamp(record.expires_at),
+                ]);
+            }
+            note!("{}", table);
+            note!(
+                "\nTotal: {} in {} file(s)",
+                format_size(records.iter().map(|r| r.size).sum()),
+                records.len()
+            );
+        }
+        QuarantineAction::Restore { id } => {
+            let record = quarantine.restore(id)?;
+            if emit(&record, || quarantine_csv(std::slice::from_ref(&record)))? {
+                return Ok(());
+            }
+            note!("✅ Restored {}", record.original_path);
+        }
+        QuarantineAction::Purge => {
+            if emit(&purged, || quarantine_csv(&purged))? {
+                return Ok(());
+            }
+            note!(
+                "🗑️  Purged {} expired file(s), {}",
+                purged.len(),
+                format_size(purged.iter().map(|r| r.size).sum())
+            );
+        }
+    }
+    Ok(())
+}
+
+/// One line per quarantined file
+fn quarantine_csv(records: &[QuarantineRecord]) -> String {
+    csv(
+        &[
+            "id",
+            "original_path",
+            "quarantine_path",
+            "size",
+            "quarantined_at",
+            "expires_at",
+        ],
+        records
+            .iter()
+            .map(|record| {
+                vec![
+                    record.id.to_string(),
+                    record.original_path.clone(),
+                    record.quarantine_path.clone(),
+                    record.size.to_string(),
+                    record.quarantined_at.to_string(),
+                    record.expires_at.to_string(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn clean_command(names: Vec<String>, execute: bool, yes: bool) -> Result<()> {
+    let config = Config::load_or_default();
+    if config.cleanup_rules.is_empty() {
+        note!("No cleanup rules configured.");
+        note!(
+            "Add [[cleanup_rules]] entries to {}.",
+            Config::default_path().display()
+        );
+        return Ok(());
+    }
+    for name in &names {
+        if !config.cleanup_rules.iter().any(|rule| &rule.name == name) {
+            bail!("Unknown cleanup rule: {}", name);
+        }
+    }
+    let rules: Vec<_> = config
+        .cleanup_rules
+        .iter()
+        .filter(|rule| names.is_empty() || names.contains(&rule.name))
+        .cloned()
+        .collect();
+
+    config.ensure_directories()?;
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let api = ServiceApi::new()
+        .with_protected_paths(protected_paths())
+        .with_hooks(hooks());
+    if execute && !yes {
+        let plan = api.run_cleanup(&rules, true, &db)?;
+        print_cleanup(&plan);
+        if plan.matched_count == 0 {
+            return Ok(());
+        }
+        let question = format!(
+            "Apply {} rule(s) to {} file(s) ({})?",
+            plan.rules.len(),
+            plan.matched_count,
+            format_size(plan.matched_size)
+        );
+        if !confirm(&question)? {
+            note!("Nothing changed.");
+            return Ok(());
+        }
+    }
+    let pb = ProgressBar::new_spinner();
+    pb.set_message("Running cleanup rules...");
+    let report = api.run_cleanup(&rules, !execute, &db)?;
+    pb.finish_with_message("Cleanup completed");
+    if emit(&report, || {
+        let rows = report
+            .rules
+            .iter()
+            .flat_map(|rule| {
+                rule.files.iter().map(|file| {
+                    vec![
+                        rule.name.clone(),
+                        rule.action.clone(),
+                        file.path.clone(),
+                        file.size.to_string(),
+                        file.freed.to_string(),
+                        file.error.clone().unwrap_or_default(),
+                    ]
+                })
+            })
+            .collect();
+        csv(&["rule", "action", "path", "size", "freed", "error"], rows)
+    })? {
+        return Ok(());
+    }
+
+    print_cleanup(&report);
+    if report.dry_run {
+        note!(
+            "\n{} file(s), {}, match. Use --execute to apply the rules.",
+            report.matched_count,
+            format_size(report.matched_size)
+        );
+        return Ok(());
+    }
+    for rule in &report.rules {
+        if let Some(archive) = &rule.archive_path {
+            note!("  📦 {}: archived to {}", rule.name, archive);
+        }
+        for file in rule.files.iter().filter(|f| f.error.is_some()) {
+            note!(
+                "  ❌ {}: {}",
+                file.path,
+                file.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+    }
+    note!("\n✅ Freed {}", format_size(report.freed));
+    Ok(())
+}
+
+/// One row per rule: what it matched, and what it freed
+fn print_cleanup(report: &CleanupReport) {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec!["Rule", "Action", "Files", "Size", "Freed", "Status"]);
+    for rule in &report.rules {
+        let failed = rule.files.iter().filter(|f| f.error.is_some()).count();
+        let status = match (&rule.error, failed) {
+            (Some(error), _) => format!("❌ {}", error),
+            (None, 0) => "✅".to_string(),
+            (None, failed) => format!("⚠️  {} file(s) left alone", failed),
+        };
+        table.add_row(vec![
+            rule.name.clone(),
+            rule.action.clone(),
+            rule.files.len().to_string(),
+            format_size(rule.matched_size),
+            format_size(rule.freed),
+            status,
+        ]);
+    }
+    note!("{table}");
+}
+
+fn reference_command(action: ReferenceAction, dry_run: bool, yes: bool) -> Result<()> {
+    let config = Config::load_or_default();
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let api = ServiceApi::new();
+
+    match action {
+        ReferenceAction::Index { path } => {
+            let pb = LiveProgress::start("Hashing files...");
+            let api = ServiceApi::new().with_progress(pb.sender());
+            let root = api.index_reference(&path, &db)?;
+            pb.finish_with_message("Indexing completed");
+            if emit(&root, || reference_csv(std::slice::from_ref(&root)))? {
+                return Ok(());
+            }
+            note!(
+                "✅ Indexed {} file(s), {}, in {}",
+                root.file_count,
+                format_size(root.total_size),
+                root.path
+            );
+        }
+        ReferenceAction::Check {
+            path,
+            delete,
+            permanent,
+            filter,
+        } => {
+            let references = api.list_references(&db)?;
+            if references.is_empty() {
+                bail!("No reference volume indexed yet; run `space-saver reference index <path>` first");
+            }
+            let pb = LiveProgress::start("Checking files...");
+            let api = ServiceApi::new().with_progress(pb.sender());
+            let check = api.check_against_reference(vec![path], filter.into_config(), &db)?;
+            pb.finish_with_message("Check completed");
+            if delete && dry_run {
+                let mode = if permanent {
+                    DeleteMode::Permanent
+                } else {
+                    DeleteMode::Trash
+                };
+                let paths: Vec<_> = check
+                    .matched
+                    .iter()
+                    .map(|m| PathBuf::from(&m.path))
+                    .collect();
+                return show_dry_run(&plan_remove_files(&paths, mode, false, protected_paths()));
+            }
+            if !delete
+                && emit(&check, || {
+                    let rows = check
+                        .matched
+                        .iter()
+                        .map(|m| {
+                            vec![
+                                m.path.clone(),
+                                m.size.to_string(),
+                                m.hash.clone(),
+                                m.reference_path.clone(),
+                            ]
+                        })
+                        .collect();
+                    csv(&["path", "size", "hash", "reference_path"], rows)
+                })?
+            {
+                return Ok(());
+            }
+
+            note!("\n📊 Reference Check:");
+            for root in &check.references {
+                note!(
+                    "  Against: {} (indexed {})",
+                    root.path,
+                    format_timestamp(root.indexed_at)
+                );
+            }
+            note!(
+                "  With a copy: {} file(s), {}",
+                check.matched.len(),
+                format_size(check.matched_size)
+            );
+            note!(
+                "  Without a copy: {} file(s), {}",
+                check.unmatched_count,
+                format_size(check.unmatched_size)
+            );
+            if check.matched.is_empty() {
+                return Ok(());
+            }
+
+            if delete {
+                let mode = if permanent {
+                    DeleteMode::Permanent
+                } else {
+                    DeleteMode::Trash
+                };
+                let paths: Vec<_> = check
+                    .matched
+                    .iter()
+                    .map(|m| PathBuf::from(&m.path))
+                    .collect();
+                let plan = plan_remove_files(&paths, mode, false, protected_paths());
+                let question = removal_question(&plan, "file(s)", mode, false);
+                if !approve(
+                    &plan,
+                    &question,
+                    yes,
+                    forced(),
+                    &mut std::io::stdin().lock(),
+                )? {
+                    return Ok(());
+                }
+                let ops = recording_trash(
+                    FileOperations::new()
+                        .with_protected_paths(protected_paths())
+                        .with_hooks(hooks()),
+                );
+                let results = ops.delete_files_with_mode(&paths, mode);
+                if emit(&results, || delete_csv(&results))? {
+                    return Ok(());
+                }
+                let deleted = results.iter().filter(|r| r.success).count();
+                match mode {
+                    DeleteMode::Trash => note!("  Moved to trash: {}", deleted),
+                    DeleteMode::Permanent => note!("  Deleted: {}", deleted),
+                }
+                for failed in results.iter().filter(|r| !r.success) {
+                    note!(
+                        "  ❌ {}: {}",
+                        failed.path,
+                        failed.error.as_deref().unwrap_or("unknown error")
+                    );
+                }
+            } else {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_header(vec!["Size", "Local file", "Copy"]);
+                for m in check.matched.iter().take(20) {
+                    table.add_row(vec![
+                        format_size(m.size),
+                        m.path.clone(),
+                        m.reference_path.clone(),
+                    ]);
+                }
+                note!("{table}");
+                if check.matched.len() > 20 {
+                    note!("  ... and {} more", check.matched.len() - 20);
+                }
+                note!("\nUse --delete flag to remove the local files that have a copy.");
+            }
+        }
+        ReferenceAction::List => {
+            let roots = api.list_references(&db)?;
+            if emit(&roots, || reference_csv(&roots))? {
+                return Ok(());
+            }
+            if roots.is_empty() {
+                note!("No reference volume indexed yet.");
+                return Ok(());
+            }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Path", "Files", "Size", "Indexed"]);
+            for root in &roots {
+                table.add_row(vec![
+                    root.path.clone(),
+                    root.file_count.to_string(),
+                    format_size(root.total_size),
+                    format_timestamp(root.indexed_at),
+                ]);
+            }
+            note!("{table}");
+        }
+        ReferenceAction::Remove { path } => {
+            let removed = api.remove_reference(&path, &db)?;
+            if removed == 0 {
+                bail!("{} is not an indexed reference volume", path.display());
+            }
+            let data = serde_json::json!({ "path": path, "removed": removed });
+            if emit(&data, || {
+                csv(
+                    &["path", "removed"],
+                    vec![vec![path.display().to_string(), removed.to_string()]],
+                )
+            })? {
+                return Ok(());
+            }
+            note!("✅ Forgot {} indexed file(s)", removed);
+        }
+    }
+    Ok(())
+}
+
+/// One line per indexed reference volume
+fn reference_csv(roots: &[ReferenceRoot]) -> String {
+    csv(
+        &["path", "file_count", "total_size", "indexed_at"],
+        roots
+            .iter()
+            .map(|root| {
+                vec![
+                    root.path.clone(),
+                    root.file_count.to_string(),
+                    root.total_size.to_string(),
+                    root.indexed_at.to_string(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+/// How many items a tag was put on or taken off
+fn emit_tag_change(tag: &str, changed: usize) -> Result<bool> {
+    let tag = tag.trim().to_lowercase();
+    let data = serde_json::json!({ "tag": tag, "changed": changed });
+    emit(&data, || {
+        csv(
+            &["tag", "changed"],
+            vec![vec![tag.clone(), changed.to_string()]],
+        )
+    })
+}
+
+fn tag_command(action: TagAction) -> Result<()> {
+    let config = Config::load_or_default();
+    config.ensure_directories()?;
+    let db = SqliteDatabase::new(&config.database_path)?;
+    let api = ServiceApi::new();
+    let targets = |paths: Vec<PathBuf>, groups: Vec<String>| {
+        paths
+            .into_iter()
+            .map(|path| TagTarget::File {
+                path: path.to_string_lossy().to_string(),
+            })
+            .chain(
+                groups
+                    .into_iter()
+                    .map(|hash| TagTarget::DuplicateGroup { hash }),
+            )
+            .collect::<Vec<_>>()
+    };
+
+    match action {
+        TagAction::Add { tag, paths, groups } => {
+            let mut added = 0;
+            for target in targets(paths, groups) {
+                if api.add_tag(&target, &tag, &db)? {
+                    added += 1;
+                }
+            }
+            if emit_tag_change(&tag, added)? {
+                return Ok(());
+            }
+            note!(
+                "✅ Tagged {} item(s) '{}'",
+                added,
+                tag.trim().to_lowercase()
+            );
+        }
+        TagAction::Remove { tag, paths, groups } => {
+            let mut removed = 0;
+            for target in targets(paths, groups) {
+                if api.remove_tag(&target, &tag, &db)? {
+                    removed += 1;
+                }
+            }
+            if emit_tag_change(&tag, removed)? {
+                return Ok(());
+            }
+            note!("✅ Untagged {} item(s)", removed);
+        }
+        TagAction::List { tag: None } => {
+            let tags = api.list_tags(&db)?;
+            if emit(&tags, || {
+                let rows = tags
+                    .iter()
+                    .map(|t| vec![t.tag.clone(), t.files.to_string(), t.groups.to_string()])
+                    .collect();
+                csv(&["tag", "files", "groups"], rows)
+            })? {
+                return Ok(());
+            }
+            if tags.is_empty() {
+                note!("No tags yet.");
+                return Ok(());
+            }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Tag", "Files", "Duplicate groups"]);
+            for tag in &tags {
+                table.add_row(vec![
+                    tag.tag.clone(),
+                    tag.files.to_string(),
+                    tag.groups.to_string(),
+                ]);
+            }
+            note!("{table}");
+        }
+        TagAction::List { tag: Some(tag) } => {
+            let tagged = api.find_tagged(&tag, &db)?;
+            if emit(&tagged, || {
+                let rows = tagged
+                    .iter()
+                    .map(|record| {
+                        vec![
+                            record.kind.clone(),
+                            record.target.clone(),
+                            record.tag.clone(),
+                            record.created_at.to_string(),
+                        ]
+                    })
+                    .collect();
+                csv(&["kind", "target", "tag", "created_at"], rows)
+            })? {
+                return Ok(());
+            }
+            if tagged.is_empty() {
+                note!("Nothing is tagged '{}'.", tag);
+                return Ok(());
+            }
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL);
+            table.set_header(vec!["Kind", "Path or hash", "Tagged"]);
+            for record in &tagged {
+                let kind = if record.kind == "file" {
+                    "file"
+                } else {
+                    "duplicate group"
+                };
+                table.add_row(vec![
+                    kind.to_string(),
+                    record.target.clone(),
+                    format_timestamp(record.created_at),
+                ]);
+            }
+            note!("{table}");
+        }
+        TagAction::Show { path } => {
+            let target = TagTarget::File {
+                path: path.to_string_lossy().to_string(),
+            };
+            let tags = api.tags_of(&target, &db)?;
+            let data = serde_json::json!({ "path": path, "tags": tags });
+            if emit(&data, || {
+                let rows = tags
+                    .iter()
+                    .map(|tag| vec![path.display().to_string(), tag.clone()])
+                    .collect();
+                csv(&["path", "tag"], rows)
+            })? {
+                return Ok(());
+            }
+            if tags.is_empty() {
+                note!("{} has no tags.", path.display());
+            } else {
+                note!("{}: {}", path.display(), tags.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn drives_command() -> Result<()> {
+    let drives = space_saver_service::list_drives();
+    if emit(&drives, || {
+        let rows = drives
+            .iter()
+            .map(|drive| {
+                vec![
+                    drive.mount_point.clone(),
+                    drive.name.clone(),
+                    drive.file_system.clone(),
+                    format!("{:?}", drive.kind).to_lowercase(),
+                    drive.read_only.to_string(),
+                    drive.total_space.to_string(),
+                    drive.used_space.to_string(),
+                    drive.available_space.to_string(),
+                ]
+            })
+            .collect();
+        csv(
+            &[
+                "mount_point",
+                "name",
+                "file_system",
+                "kind",
+                "read_only",
+                "total_space",
+                "used_space",
+                "available_space",
+            ],
+            rows,
+        )
+    })? {
+        return Ok(());
+    }
+    if drives.is_empty() {
+        note!("No drives found");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        "Mount point",
+        "Name",
+        "File system",
+        "Kind",
+        "Size",
+        "Used",
+        "Free",
+    ]);
+    for drive in &drives {
+        let kind = match drive.kind {
+            DriveKind::Fixed => "fixed",
+            DriveKind::Removable => "removable",
+            DriveKind::Network => "network",
+        };
+        table.add_row(vec![
+            drive.mount_point.clone(),
+            drive.name.clone(),
+            drive.file_system.clone(),
+            if drive.read_only {
+                format!("{} (read-only)", kind)
+            } else {
+                kind.to_string()
+            },
+            format_size(drive.total_space),
+            format!(
+                "{} ({:.0}%)",
+                format_size(drive.used_space),
+                drive.used_space as f64 * 100.0 / drive.total_space as f64
+            ),
+            format_size(drive.available_space),
+        ]);
+    }
+    note!("{table}");
+    Ok(())
+}
+
+/// The server `space-saver serve` runs, set up from the config
+#[cfg(feature = "server")]
+fn serve_command(token: Option<String>) -> Result<space_saver_server::Server> {
+    let config = Config::load_or_default();
+    config.validate()?;
+    config.ensure_directories()?;
+    let token = match token.or_else(|| std::env::var("SPACE_SAVER_TOKEN").ok()) {
+        Some(token) if token.trim().is_empty() => {
+            bail!(Error::InvalidInput("The token cannot be empty".to_string()))
+        }
+        Some(token) => token,
+        None => {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            note!("Generated token: {}", token);
+            token
+        }
+    };
+    Ok(space_saver_server::Server::new(token)
+        .with_database(Database::open(&config.database_path)?)
+        .with_protected_paths(protected_paths())
+        .with_network_shares(NetworkShares::detect(&config.scan))
+        .with_hooks(Hooks::new(config.hooks.clone()))
+        .with_plugin_manager(configured_plugin_manager(&config)?))
+}
+
+#[cfg(feature = "server")]
+async fn listen(address: std::net::SocketAddr) -> Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen on {}", address))
+}
+
+/// The system directories plus the folders protected in the config
+async fn daemon_command(action: DaemonAction) -> Result<()> {
+    let config = Config::load_or_default();
+    match action {
+        DaemonAction::Run => {
+            config.validate()?;
+            if daemon::send_command(&config.daemon_socket, "status")
+                .await
+                .is_ok()
+            {
+                bail!(
+                    "A daemon is already running (socket {})",
+                    config.daemon_socket.display()
+                );
+            }
+            config.ensure_directories()?;
+            if let Some(parent) = config.daemon_log.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Watch rules scan their folders every few seconds; keep those
+            // scans out of the log
+            init_logger_with_file(
+                &config.daemon_log.to_string_lossy(),
+                &format!("{},space_saver_core::scanner=warn", config.log_level),
+            )?;
+            let db = Database::open(&config.database_path)?;
+            note!(
+                "Daemon running {} schedule(s) and {} watch rule(s)",
+                config.schedules.iter().filter(|s| s.enabled).count(),
+                config.watch_rules.iter().filter(|r| r.enabled).count()
+            );
+            note!("Logging to {}", config.daemon_log.display());
+            note!("Stop it with Ctrl-C or `space-saver daemon stop`");
+            Daemon::new(config, db)
+                .with_protected_paths(protected_paths())
+                .run()
+                .await?;
+            note!("Daemon stopped");
+        }
+        DaemonAction::Status => {
+            let response = daemon::send_command(&config.daemon_socket, "status").await?;
+            let status = response
+                .status
+                .context("The daemon did not report its status")?;
+            if emit(&status, || {
+                let schedules = status.schedules.iter().map(|s| {
+                    vec![
+                        "schedule".to_string(),
+                        s.name.clone(),
+                        s.task.clone(),
+                        s.last_run.map(|t| t.to_string()).unwrap_or_default(),
+                        s.last_result.clone().unwrap_or_default(),
+                    ]
+                });
+                let watches = status.watches.iter().map(|w| {
+                    vec![
+                        "watch".to_string(),
+                        w.name.clone(),
+                        w.action.clone(),
+                        w.last_event.map(|t| t.to_string()).unwrap_or_default(),
+                        w.last_error.clone().unwrap_or_default(),
+                    ]
+                });
+                csv(
+                    &["kind", "name", "task", "last_run", "last_result"],
+                    schedules.chain(watches).collect(),
+                )
+            })? {
+                return Ok(());
+            }
+
+            note!(
+                "Daemon {} running since {} · {} task(s) running, {} queued",
+                status.pid,
+                format_timestamp(status.started_at),
+                status.running,
+                status.queued
+            );
+            if !status.schedules.is_empty() {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_header(vec![
+                    "Schedule", "Task", "Every", "Last run", "Result", "Next run",
+                ]);
+                for s in &status.schedules {
+                    table.add_row(vec![
+                        s.name.clone(),
+                        s.task.clone(),
+                        format_duration(std::time::Duration::from_secs(
+                            s.every_minutes as u64 * 60,
+                        )),
+                        s.last_run
+                            .map(format_timestamp)
+                            .unwrap_or_else(|| "never".to_string()),
+                        match (&s.last_result, s.last_failed) {
+                            (Some(result), true) => format!("❌ {}", result),
+                            (Some(result), false) => result.clone(),
+                            (None, _) => String::new(),
+                        },
+                        if s.busy {
+                            "running".to_string()
+                        } else {
+                            format_timestamp(s.next_run)
+                        },
+                    ]);
+                }
+                note!("{}", table);
+            }
+            if !status.watches.is_empty() {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_header(vec![
+                    "Watch rule",
+                    "Action",
+                    "Folders",
+                    "Waiting",
+                    "Handled",
+                    "Freed",
+                    "Last error",
+                ]);
+                for w in &status.watches {
+                    table.add_row(vec![
+                        w.name.clone(),
+                        w.action.clone(),
+                        w.paths.join("\n"),
+                        w.pending.to_string(),
+                        w.handled.to_string(),
+                        format_size(w.freed),
+                        w.last_error.clone().unwrap_or_default(),
+                    ]);
+                }
+                note!("{}", table);
+            }
+        }
+        DaemonAction::Stop => {
+            daemon::send_command(&config.daemon_socket, "stop").await?;
+            note!("✅ Daemon stopping");
+        }
+    }
+    Ok(())
+}
+
+/// Whether --force lifts the protection config.toml adds
+static FORCE: OnceLock<bool> = OnceLock::new();
+
+fn forced() -> bool {
+    FORCE.get().copied().unwrap_or(false)
+}
+
+/// The operating system's directories, and the config's protected paths
+/// unless --force
+fn protected_paths() -> ProtectedPaths {
+    if forced() {
+        return ProtectedPaths::new();
+    }
+    ProtectedPaths::new().with_paths(Config::load_or_default().protected_paths)
+}
+
+/// The enabled hooks of the config
+fn hooks() -> Hooks {
+    Hooks::new(Config::load_or_default().hooks)
+}
+
+/// Network shares mounted now, read as the scan settings say
+fn network_shares() -> NetworkShares {
+    NetworkShares::detect(&Config::load_or_default().scan)
+}
+
+/// Groups ignored in the app; without a database every group is shown
+fn ignore_list() -> IgnoreList {
+    SqliteDatabase::new(&Config::load_or_default().database_path)
+        .and_then(|db| IgnoreList::load(&db))
+        .unwrap_or_default()
+}
+
+async fn config_command() -> Result<()> {
+    let config = Config::load_or_default();
+    if emit(&config, || {
+        // Top-level settings; lists and tables as JSON
+        let rows = match serde_json::to_value(&config) {
+            Ok(serde_json::Value::Object(settings)) => settings
+                .into_iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(value) => vec![key, value],
+                    value => vec![key, value.to_string()],
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        csv(&["setting", "value"], rows)
+    })? {
+        return Ok(());
+    }
+
+    note!("📝 Configuration:");
+    note!("{}", toml::to_string_pretty(&config)?);
+    note!("\nConfig file: {}", Config::default_path().display());
+
+    Ok(())
+}
+
+fn config_get_command(key: &str) -> Result<()> {
+    let (_, config) = load_config_for_update()?;
+    let value = config.get_key(key)?;
+    if emit(&value, || {
+        csv(
+            &["setting", "value"],
+            vec![vec![key.to_string(), setting_text(&value)]],
+        )
+    })? {
+        return Ok(());
+    }
+    match value {
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+            println!("{}", serde_json::to_string_pretty(&value)?)
+        }
+        value => println!("{}", setting_text(&value)),
+    }
+    Ok(())
+}
+
+fn config_set_command(key: &str, value: &str) -> Result<()> {
+    let (path, mut config) = load_config_for_update()?;
+    config.set_key(key, value)?;
+    config.save(&path)?;
+    note!("✅ {} = {}", key, setting_text(&config.get_key(key)?));
+    Ok(())
+}
+
+fn config_reset_command(key: Option<String>) -> Result<()> {
+    let path = Config::default_path();
+    match key {
+        Some(key) => {
+            let (_, mut config) = load_config_for_update()?;
+            config.reset_key(&key)?;
+            config.save(&path)?;
+            match config.get_key(&key) {
+                Ok(value) => note!("✅ {} = {}", key, setting_text(&value)),
+                Err(_) => note!("✅ Removed {}", key),
+            }
+        }
+        None => {
+            Config::default().save(&path)?;
+            note!("✅ Reset {} to the defaults", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn config_validate_command(file: Option<PathBuf>) -> Result<()> {
+    let path = file.unwrap_or_else(Config::default_path);
+    if !path.exists() {
+        bail!(Error::NotFound(format!(
+            "No config file at {}",
+            path.display()
+        )));
+    }
+    check_config_file(&path)?;
+    note!("✅ {} is valid", path.display());
+    Ok(())
+}
+
+/// Parse and validate the config file at `path`, as a config error naming it
+fn check_config_file(path: &Path) -> Result<Config> {
+    let config = Config::load(path)
+        .and_then(|config| config.validate().map(|()| config))
+        .map_err(|e| Error::Config(format!("{}: {:#}", path.display(), e)))?;
+    Ok(config)
+}
+
+fn config_edit_command() -> Result<()> {
+    let path = Config::default_path();
+    if !path.exists() {
+        Config::default().save(&path)?;
+    }
+    // Edit a copy, so a mistake never leaves a config the app cannot load
+    let draft = path.with_extension("toml.edit");
+    std::fs::copy(&path, &draft).with_context(|| format!("Failed to copy {}", path.display()))?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    // The editor may come with arguments, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| Error::Config("$EDITOR is empty".to_string()))?;
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(&draft)
+        .status()
+        .with_context(|| format!("Failed to start {}", program))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&draft);
+        bail!(
+            "{} exited with {}; the configuration is unchanged",
+            program,
+            status
+        );
+    }
+
+    if std::fs::read(&draft)? == std::fs::read(&path)? {
+        std::fs::remove_file(&draft)?;
+        note!("No changes");
+        return Ok(());
+    }
+    if let Err(e) = check_config_file(&draft) {
+        return Err(e.context(format!(
+            "The configuration is unchanged; your edit is kept in {}",
+            draft.display()
+        )));
+    }
+    std::fs::rename(&draft, &path).with_context(|| format!("Failed to save {}", path.display()))?;
+    note!("✅ Saved {}", path.display());
+    Ok(())
+}
+
+/// A setting as typed on the command line: strings bare, the rest as JSON
+fn setting_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(name: &str) -> PluginMetadata {
+        PluginMetadata {
+            name: name.to_string(),
+            description: String::new(),
+            version: "1.0.0".to_string(),
+            lossy: false,
+            destructive: false,
+            requires_external_tools: Vec::new(),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn error_code(error: &anyhow::Error) -> Option<ErrorCode> {
+        error.downcast_ref::<Error>().map(Error::code)
+    }
+
+    fn parse(args: &[&str]) -> std::result::Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("space-saver").chain(args.iter().copied()))
+    }
+
+    fn scan_filter(args: &[&str]) -> Option<FilterConfig> {
+        let args: Vec<&str> = ["scan", "/data"].iter().chain(args).copied().collect();
+        match parse(&args).unwrap().command {
+            Commands::Scan { filter, .. } => filter.into_config(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_jobs_is_global_and_positive() {
+        assert_eq!(parse(&["scan", "/data"]).unwrap().jobs, None);
+        for args in [
+            &["-j", "4", "duplicates", "/data"][..],
+            &["duplicates", "/data", "--jobs", "4"],
+            &["compress", "/data", "-j4"],
+        ] {
+            assert_eq!(
+                parse(args).unwrap().jobs,
+                NonZeroUsize::new(4),
+                "{:?}",
+                args
+            );
+        }
+
+        for jobs in ["0", "many"] {
+            let err = parse(&["duplicates", "/data", "--jobs", jobs])
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+    }
+
+    #[test]
+    fn test_no_filter_flags_means_no_filter() {
+        assert_eq!(scan_filter(&[]), None);
+    }
+
+    #[test]
+    fn test_filter_flags_become_a_filter_config() {
+        let filter = scan_filter(&[
+            "--min-size",
+            "1MB",
+            "--ext",
+            "jpg,png",
+            "--exclude",
+            "**/cache/**",
+            "--exclude",
+            "**/*.raw",
+            "--exclude-path",
+            "/data/tmp",
+            "--hidden",
+            "--mine",
+            "--max-depth",
+            "2",
+            "--newer-than",
+            "30d",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            filter,
+            FilterConfig {
+                min_size: Some(parse_size("1MB").unwrap()),
+                extensions: Some(vec!["jpg".to_string(), "png".to_string()]),
+                exclude_globs: Some(vec!["**/cache/**".to_string(), "**/*.raw".to_string()]),
+                exclude_paths: Some(vec!["/data/tmp".to_string()]),
+                include_hidden: Some(true),
+                current_user_only: Some(true),
+                max_depth: Some(2),
+                newer_than: Some("30d".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_filter_sizes_are_checked_while_parsing() {
+        let err = parse(&["scan", "/data", "--min-size", "lots"])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn test_dry_run_is_refused_only_by_commands_that_cannot_plan() {
+        let lacks = |args: &[&str]| lacks_dry_run(&parse(args).unwrap().command);
+        assert_eq!(lacks(&["archive", "/data"]), Some("archive"));
+        assert_eq!(lacks(&["plugins", "disable", "webp"]), Some("plugins"));
+        assert_eq!(lacks(&["scan", "/data"]), None);
+        assert_eq!(lacks(&["compress", "/data", "-j", "2"]), None);
+        assert_eq!(lacks(&["plugins", "list"]), None);
+    }
+
+    fn trash_plan(skipped: Vec<FileError>) -> ActionPlan {
+        let action = PlannedAction {
+            path: PathBuf::from("/data/copy.jpg"),
+            action: "trash".to_string(),
+            size: 100,
+            reclaimed: Some(100),
+            detail: None,
+        };
+        ActionPlan::new(vec![action], skipped)
+    }
+
+    fn protected_skip() -> FileError {
+        FileError::new(
+            Path::new("/etc/hosts"),
+            Error::Protected("/etc/hosts is inside /etc".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_refuse_protected_names_protected_skips_unless_forced() {
+        let plan = trash_plan(vec![protected_skip()]);
+        let err = refuse_protected(&plan, false).unwrap_err();
+        assert_eq!(error_code(&err), Some(ErrorCode::Protected));
+        assert!(err.to_string().contains("1 path(s) are protected"));
+        assert!(err.to_string().contains("--force"));
+
+        refuse_protected(&plan, true).unwrap();
+    }
+
+    #[test]
+    fn test_refuse_protected_lets_other_skips_through() {
+        let missing = FileError::new(
+            Path::new("/data/gone.jpg"),
+            Error::NotFound("/data/gone.jpg".to_string()),
+        );
+        refuse_protected(&trash_plan(vec![missing]), false).unwrap();
+        refuse_protected(&trash_plan(vec![]), false).unwrap();
+    }
+
+    #[test]
+    fn test_approve_asks_unless_yes() {
+        let plan = trash_plan(vec![]);
+        let approve =
+            |yes: bool, answer: &str| approve(&plan, "Delete?", yes, false, &mut answer.as_bytes());
+
+        assert!(approve(false, "y\n").unwrap());
+        assert!(approve(false, "YES\n").unwrap());
+        assert!(!approve(false, "n\n").unwrap());
+        assert!(!approve(false, "\n").unwrap());
+        // --yes doesn't read an answer at all
+        assert!(approve(true, "").unwrap());
+
+        // No one to answer is an error rather than a silent no
+        let err = approve(false, "").unwrap_err();
+        assert_eq!(error_code(&err), Some(ErrorCode::InvalidInput));
+        assert!(err.to_string().contains("--yes"));
+    }
+
+    #[test]
+    fn test_approve_refuses_protected_paths_before_asking() {
+        let plan = trash_plan(vec![protected_skip()]);
+
+        // Even --yes can't go past protected paths
+        let err = approve(&plan, "Delete?", true, false, &mut "y\n".as_bytes()).unwrap_err();
+        assert_eq!(error_code(&err), Some(ErrorCode::Protected));
+
+        assert!(approve(&plan, "Delete?", false, true, &mut "y\n".as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_approve_declines_an_empty_plan_without_asking() {
+        let plan = ActionPlan::new(vec![], vec![]);
+        assert!(!approve(&plan, "Delete?", false, false, &mut "y\n".as_bytes()).unwrap());
+        assert!(!approve(&plan, "Delete?", true, false, &mut "".as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_min_savings_falls_back_to_config_and_rejects_out_of_range() {
+        assert_eq!(min_savings_percent(None, 10.0).unwrap(), 10.0);
+        assert_eq!(min_savings_percent(Some(25.0), 10.0).unwrap(), 25.0);
+        assert_eq!(min_savings_percent(Some(0.0), 10.0).unwrap(), 0.0);
+        assert_eq!(min_savings_percent(Some(100.0), 10.0).unwrap(), 100.0);
+
+        for bad in [-1.0, 100.5, f32::NAN] {
+            let err = min_savings_percent(Some(bad), 10.0).unwrap_err();
+            assert_eq!(error_code(&err), Some(ErrorCode::InvalidInput));
+            assert!(err
+                .to_string()
+                .contains("--min-savings must be between 0 and 100"));
+        }
+        // A broken config value is caught as well
+        assert!(min_savings_percent(None, 150.0).is_err());
+    }
+
+    #[test]
+    fn test_select_plugins_matches_names_then_substrings() {
+        let registered = [
+            plugin("WebP Converter"),
+            plugin("Animated WebP Converter"),
+            plugin("RAW to JPEG Converter"),
+        ];
+
+        assert_eq!(select_plugins(&registered, &[]).unwrap().len(), 3);
+        // An exact name wins over the plugins merely containing it
+        assert_eq!(
+            select_plugins(&registered, &names(&["webp converter"])).unwrap(),
+            names(&["WebP Converter"])
+        );
+        // Order of preference is kept and repeats are dropped
+        assert_eq!(
+            select_plugins(&registered, &names(&["raw", "webp", "WebP Converter"])).unwrap(),
+            names(&[
+                "RAW to JPEG Converter",
+                "WebP Converter",
+                "Animated WebP Converter"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_select_plugins_rejects_unknown_and_blank_names() {
+        let registered = [plugin("WebP Converter")];
+        for wanted in ["avif", " "] {
+            let err = select_plugins(&registered, &names(&[wanted])).unwrap_err();
+            assert_eq!(error_code(&err), Some(ErrorCode::NotFound));
+            assert!(err.to_string().contains("available: WebP Converter"));
+        }
+    }
+
+    #[test]
+    fn test_plugin_named_rejects_ambiguous_names() {
+        let registered = [plugin("WebP Converter"), plugin("Animated WebP Converter")];
+        assert_eq!(
+            plugin_named(&registered, "animated").unwrap(),
+            "Animated WebP Converter"
+        );
+        let err = plugin_named(&registered, "conv").unwrap_err();
+        assert_eq!(error_code(&err), Some(ErrorCode::InvalidInput));
+        assert!(err.to_string().contains("matches several plugins"));
+    }
+
+    #[test]
+    fn test_enabled_plugins_skips_disabled_and_fails_when_none_left() {
+        use space_saver_core::plugins::{TextMinifierPlugin, WebPConverterPlugin};
+
+        let manager = space_saver_core::compress_plugins::init_plugin_manager_with(vec![
+            Box::new(WebPConverterPlugin::new()),
+            Box::new(TextMinifierPlugin::new()),
+        ]);
+        let mut manager = manager.write().unwrap();
+        manager.set_disabled_plugins(["WebP Converter"]);
+
+        assert_eq!(
+            enabled_plugins(&manager, &[]).unwrap(),
+            names(&["Text Asset Minifier"])
+        );
+        let err = enabled_plugins(&manager, &names(&["webp"])).unwrap_err();
+        assert_eq!(error_code(&err), Some(ErrorCode::InvalidInput));
+        assert!(err.to_string().contains("plugins enable"));
+        // Unknown names still fail as not found before the enabled check
+        let err = enabled_plugins(&manager, &names(&["avif"])).unwrap_err();
+        assert_eq!(error_code(&err), Some(ErrorCode::NotFound));
+    }
 }
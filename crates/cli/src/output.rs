@@ -0,0 +1,195 @@
+//! How command results reach stdout.
+//!
+//! By default commands print tables and summaries for people. With
+//! `--format json` or `--format csv` they write their results as data
+//! instead, for scripts and `jq`: JSON as the service API returns them, CSV
+//! one line per file or item with sizes in bytes.
+//! Progress, prompts and status lines then go to stderr, so stdout carries
+//! nothing but the results.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::OnceLock;
+
+/// What stdout carries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Tables and summaries for people
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Set once, from the command line, before any command runs. The `*_to`
+/// functions take the format instead, so tests can try each one.
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Whether output is for people rather than scripts
+pub fn is_table() -> bool {
+    format() == OutputFormat::Table
+}
+
+/// `println!` for people: stdout with `--format table`, stderr otherwise
+macro_rules! note {
+    () => {
+        $crate::output::note_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::note_line(format_args!($($arg)*))
+    };
+}
+pub(crate) use note;
+
+/// Print a [`note!`] line
+pub fn note_line(line: std::fmt::Arguments) {
+    let _ = note_to(
+        format(),
+        &mut std::io::stdout().lock(),
+        &mut std::io::stderr().lock(),
+        line,
+    );
+}
+
+/// Write `line` to `stdout` under [`OutputFormat::Table`], else to `stderr`
+pub fn note_to(
+    format: OutputFormat,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+    line: std::fmt::Arguments,
+) -> std::io::Result<()> {
+    match format {
+        OutputFormat::Table => writeln!(stdout, "{}", line),
+        OutputFormat::Json | OutputFormat::Csv => writeln!(stderr, "{}", line),
+    }
+}
+
+/// Print `text` where [`note!`] would, without a line break, so an answer
+/// typed on stdin follows it
+pub fn prompt(text: &str) -> Result<()> {
+    if is_table() {
+        let mut stdout = std::io::stdout().lock();
+        write!(stdout, "{}", text)?;
+        stdout.flush()?;
+    } else {
+        eprint!("{}", text);
+    }
+    Ok(())
+}
+
+/// Write `data` as JSON, or the CSV `csv` renders, to stdout. False with
+/// `--format table`, where the caller prints for people instead.
+pub fn emit<T: Serialize>(data: &T, csv: impl FnOnce() -> String) -> Result<bool> {
+    emit_to(format(), &mut std::io::stdout().lock(), data, csv)
+}
+
+/// [`emit`] in `format` to `out`
+pub fn emit_to<T: Serialize>(
+    format: OutputFormat,
+    out: &mut impl Write,
+    data: &T,
+    csv: impl FnOnce() -> String,
+) -> Result<bool> {
+    match format {
+        OutputFormat::Table => return Ok(false),
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, data)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => out.write_all(csv().as_bytes())?,
+    }
+    out.flush()?;
+    Ok(true)
+}
+
+/// CSV of `rows` under `headers`
+pub fn csv(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    space_saver_service::export::to_csv(headers, &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[test]
+    fn test_csv_quotes_commas_quotes_and_line_breaks() {
+        let text = csv(
+            &["path", "note"],
+            vec![
+                row(&["/a/plain.txt", "ok"]),
+                row(&["/a/b,c.txt", "two\nlines"]),
+                row(&["/a/\"quoted\".txt", ""]),
+            ],
+        );
+        assert_eq!(
+            text,
+            "path,note\r\n\
+             /a/plain.txt,ok\r\n\
+             \"/a/b,c.txt\",\"two\nlines\"\r\n\
+             \"/a/\"\"quoted\"\".txt\",\r\n"
+        );
+    }
+
+    #[test]
+    fn test_emit_writes_data_only_for_scripts() {
+        #[derive(Serialize)]
+        struct Item {
+            size: u64,
+        }
+        let data = vec![Item { size: 42 }];
+        let render = || csv(&["size"], vec![row(&["42"])]);
+
+        let mut out = Vec::new();
+        assert!(!emit_to(OutputFormat::Table, &mut out, &data, render).unwrap());
+        assert!(out.is_empty());
+
+        assert!(emit_to(OutputFormat::Json, &mut out, &data, render).unwrap());
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json, serde_json::json!([{ "size": 42 }]));
+
+        let mut out = Vec::new();
+        assert!(emit_to(OutputFormat::Csv, &mut out, &data, render).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "size\r\n42\r\n");
+    }
+
+    #[test]
+    fn test_notes_stay_off_stdout_for_scripts() {
+        for format in [OutputFormat::Json, OutputFormat::Csv] {
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            note_to(
+                format,
+                &mut stdout,
+                &mut stderr,
+                format_args!("Scanning {}", 3),
+            )
+            .unwrap();
+            assert!(stdout.is_empty(), "{:?} wrote a note to stdout", format);
+            assert_eq!(stderr, b"Scanning 3\n");
+        }
+
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        note_to(
+            OutputFormat::Table,
+            &mut stdout,
+            &mut stderr,
+            format_args!("Scanning {}", 3),
+        )
+        .unwrap();
+        assert_eq!(stdout, b"Scanning 3\n");
+        assert!(stderr.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! Progress bars for long operations.
+//!
+//! The service reports progress on a channel (see
+//! `ServiceApi::with_progress`). [`LiveProgress`] draws those reports as a bar
+//! with live statistics: files per second, throughput when the operation
+//! counts bytes, and the time left. Like every bar here it draws on stderr,
+//! and not at all when stderr is not a terminal.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use space_saver_service::ProgressUpdate;
+use space_saver_utils::{format_duration, format_size};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// A bar following the reports sent to [`Self::sender`]: a spinner until the
+/// first one arrives
+pub struct LiveProgress {
+    bar: ProgressBar,
+    sender: mpsc::Sender<ProgressUpdate>,
+    task: JoinHandle<()>,
+}
+
+impl LiveProgress {
+    pub fn start(message: &str) -> Self {
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .unwrap(),
+        );
+        bar.set_message(message.to_string());
+        bar.enable_steady_tick(Duration::from_millis(120));
+
+        let (sender, mut receiver) = mpsc::channel(256);
+        let task = tokio::spawn({
+            let bar = bar.clone();
+            async move {
+                let mut phase: Option<(usize, Instant)> = None;
+                while let Some(update) = receiver.recv().await {
+                    let ProgressUpdate::Progress {
+                        current,
+                        total,
+                        message,
+                        bytes,
+                        total_bytes,
+                    } = update
+                    else {
+                        continue;
+                    };
+                    // Operations go through phases (scanning, then hashing)
+                    // with their own totals; rates start over with each
+                    if phase.is_none() {
+                        bar.set_style(bar_style());
+                    }
+                    let started = match phase {
+                        Some((phase_total, started)) if phase_total == total => started,
+                        _ => {
+                            let now = Instant::now();
+                            phase = Some((total, now));
+                            now
+                        }
+                    };
+                    bar.set_length(total as u64);
+                    bar.set_position(current as u64);
+                    bar.set_prefix(live_stats(
+                        (current, total),
+                        (bytes, total_bytes),
+                        started.elapsed(),
+                    ));
+                    bar.set_message(message);
+                }
+            }
+        });
+        Self { bar, sender, task }
+    }
+
+    /// Where the service should send its reports
+    pub fn sender(&self) -> mpsc::Sender<ProgressUpdate> {
+        self.sender.clone()
+    }
+
+    pub fn finish_with_message(self, message: &'static str) {
+        self.task.abort();
+        self.bar.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .unwrap(),
+        );
+        self.bar.finish_with_message(message);
+    }
+
+    pub fn finish_and_clear(self) {
+        self.task.abort();
+        self.bar.finish_and_clear();
+    }
+}
+
+/// The style of a bar fed by progress reports
+pub fn bar_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{spinner:.green} [{bar:30.cyan/blue}] {pos}/{len} {prefix} {wide_msg}")
+        .unwrap()
+        .progress_chars("=> ")
+}
+
+/// "120 files/s, 48.2 MB/s, 8s left" for `files` and `bytes` as (done,
+/// total), `elapsed` into the work. Throughput only when bytes are counted;
+/// nothing until there is enough to go on.
+pub fn live_stats(files: (usize, usize), bytes: (u64, u64), elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    if secs < 0.5 || files.0 == 0 {
+        return String::new();
+    }
+    let files_per_sec = files.0 as f64 / secs;
+    let mut stats = vec![format!("{:.0} files/s", files_per_sec)];
+    let left = if bytes.0 > 0 && bytes.1 >= bytes.0 {
+        let bytes_per_sec = bytes.0 as f64 / secs;
+        stats.push(format!("{}/s", format_size(bytes_per_sec as u64)));
+        (bytes.1 - bytes.0) as f64 / bytes_per_sec
+    } else {
+        files.1.saturating_sub(files.0) as f64 / files_per_sec
+    };
+    stats.push(format!(
+        "{} left",
+        format_duration(Duration::from_secs_f64(left))
+    ));
+    stats.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_stats_waits_for_enough_to_go_on() {
+        assert_eq!(live_stats((0, 10), (0, 0), Duration::from_secs(5)), "");
+        assert_eq!(live_stats((5, 10), (0, 0), Duration::from_millis(100)), "");
+    }
+
+    #[test]
+    fn test_live_stats_estimates_from_files_without_bytes() {
+        let stats = live_stats((20, 30), (0, 0), Duration::from_secs(2));
+        assert_eq!(
+            stats,
+            format!(
+                "10 files/s, {} left",
+                format_duration(Duration::from_secs(1))
+            )
+        );
+    }
+
+    #[test]
+    fn test_live_stats_estimates_from_bytes_when_counted() {
+        // Half the bytes in 4s: 4s more, however many files are left
+        let stats = live_stats((1, 100), (2_000_000, 4_000_000), Duration::from_secs(4));
+        assert_eq!(
+            stats,
+            format!(
+                "0 files/s, {}/s, {} left",
+                format_size(500_000),
+                format_duration(Duration::from_secs(4))
+            )
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! `space-saver review`: going through duplicate or similar groups one at a
+//! time and choosing which copies to keep.
+//!
+//! Each group shows its files side by side, pre-marked the way `dedupe`
+//! would resolve it (similar images keep the one with the most pixels). In
+//! a terminal this is a full-screen view with the groups listed on the left
+//! and the keyboard moving between files and marking them; with stdin or
+//! stdout redirected, groups are printed as tables and keys are read one
+//! line at a time. Nothing is deleted until the review ends and its summary
+//! is confirmed.
+
+use crate::output::{note, prompt};
+use anyhow::Result;
+use comfy_table::{presets::UTF8_FULL, Table};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, List, ListState, Paragraph, Row, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use space_saver_service::api::SimilarGroup;
+use space_saver_service::{DuplicateResolution, ProtectedPaths};
+use space_saver_utils::{format_size, format_timestamp};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+const HELP: &str = "Keys: <n> toggle keep/delete of file n · k <n> keep only file n · \
+Enter accept and go on · s skip the group · b back · n next · q finish · ? help";
+
+const TUI_HELP: &str = "←/→ pick a file · space toggle keep/delete · k keep only it · \
+Enter accept · s skip · ↑/↓ previous/next group · q finish";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    Keep,
+    Delete,
+}
+
+/// One copy in a group under review
+pub struct ReviewFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: i64,
+    /// Pixel width and height of an image, when known
+    pub dimensions: Option<(u32, u32)>,
+    /// Protected copies are always kept
+    pub protected: bool,
+    pub mark: Mark,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Not reviewed yet; left alone
+    Pending,
+    /// Its marks are to be carried out
+    Accepted,
+    Skipped,
+}
+
+/// A duplicate or similar group and what the user decided for it
+pub struct ReviewGroup {
+    /// e.g. "3 identical copies" or "92% similar"
+    pub label: String,
+    pub files: Vec<ReviewFile>,
+    pub decision: Decision,
+}
+
+impl ReviewGroup {
+    /// A duplicate group marked as `resolution` resolves it
+    pub fn from_resolution(resolution: &DuplicateResolution) -> Self {
+        let protected = |path: &PathBuf| resolution.protected.iter().any(|p| &p.path == path);
+        let keep = resolution.keep.iter().map(|file| (file, Mark::Keep));
+        let delete = resolution.delete.iter().map(|file| (file, Mark::Delete));
+        let mut files: Vec<ReviewFile> = keep
+            .chain(delete)
+            .map(|(file, mark)| ReviewFile {
+                protected: protected(&file.path),
+                path: file.path.clone(),
+                size: file.size,
+                modified: file.modified,
+                dimensions: None,
+                mark,
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Self {
+            label: format!("{} identical copies", files.len()),
+            files,
+            decision: Decision::Pending,
+        }
+    }
+
+    /// A similar group keeping the image with the most pixels (the largest
+    /// file on a tie) and every protected one
+    pub fn from_similar(group: &SimilarGroup, protected: &ProtectedPaths) -> Self {
+        let pixels = |f: &space_saver_service::api::SimilarFile| {
+            f.width.zip(f.height).map(|(w, h)| w as u64 * h as u64)
+        };
+        let best = group
+            .files
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, f)| (pixels(f), f.size))
+            .map(|(index, _)| index);
+        let files = group
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let path = PathBuf::from(&file.path);
+                let protected = protected.check(&path).is_err();
+                ReviewFile {
+                    mark: if protected || Some(index) == best {
+                        Mark::Keep
+                    } else {
+                        Mark::Delete
+                    },
+                    path,
+                    size: file.size,
+                    modified: file.modified,
+                    dimensions: file.width.zip(file.height),
+                    protected,
+                }
+            })
+            .collect();
+        Self {
+            label: format!("{:.0}% similar", group.similarity_score * 100.0),
+            files,
+            decision: Decision::Pending,
+        }
+    }
+
+    /// Files to delete once the review is confirmed
+    pub fn to_delete(&self) -> impl Iterator<Item = &ReviewFile> {
+        self.files
+            .iter()
+            .filter(move |f| self.decision == Decision::Accepted && f.mark == Mark::Delete)
+    }
+
+    fn toggle(&mut self, index: usize) -> std::result::Result<(), String> {
+        let file = self
+            .files
+            .get_mut(index)
+            .ok_or_else(|| format!("There is no file {}", index + 1))?;
+        if file.protected {
+            return Err(format!("{} is protected", file.path.display()));
+        }
+        file.mark = match file.mark {
+            Mark::Keep => Mark::Delete,
+            Mark::Delete => Mark::Keep,
+        };
+        Ok(())
+    }
+
+    fn keep_only(&mut self, index: usize) -> std::result::Result<(), String> {
+        if index >= self.files.len() {
+            return Err(format!("There is no file {}", index + 1));
+        }
+        for (i, file) in self.files.iter_mut().enumerate() {
+            file.mark = if i == index || file.protected {
+                Mark::Keep
+            } else {
+                Mark::Delete
+            };
+        }
+        Ok(())
+    }
+
+    /// Size of the files marked for deletion
+    fn marked_size(&self) -> u64 {
+        self.files
+            .iter()
+            .filter(|f| f.mark == Mark::Delete)
+            .map(|f| f.size)
+            .sum()
+    }
+
+    /// The files side by side, one column each
+    fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        let mut header = vec![String::new()];
+        header.extend((1..=self.files.len()).map(|n| format!("#{}", n)));
+        table.set_header(header);
+
+        let row = |label: &str, cell: &dyn Fn(&ReviewFile) -> String| {
+            let mut row = vec![label.to_string()];
+            row.extend(self.files.iter().map(cell));
+            row
+        };
+        table.add_row(row("Path", &|f| f.path.display().to_string()));
+        table.add_row(row("Size", &|f| format_size(f.size)));
+        table.add_row(row("Modified", &|f| format_timestamp(f.modified)));
+        if self.files.iter().any(|f| f.dimensions.is_some()) {
+            table.add_row(row("Pixels", &|f| {
+                f.dimensions
+                    .map(|(w, h)| format!("{}×{}", w, h))
+                    .unwrap_or_default()
+            }));
+        }
+        table.add_row(row("Mark", &|f| f.mark_label().to_string()));
+        table
+    }
+}
+
+impl ReviewFile {
+    fn mark_label(&self) -> &'static str {
+        match (self.mark, self.protected) {
+            (Mark::Keep, true) => "keep (protected)",
+            (Mark::Keep, false) => "✅ keep",
+            (Mark::Delete, _) => "🗑️  delete",
+        }
+    }
+}
+
+/// What the user asked for, typed at the prompt or pressed in the TUI
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Toggle(usize),
+    KeepOnly(usize),
+    Accept,
+    Skip,
+    Back,
+    /// Go on without deciding the group
+    Next,
+    Finish,
+    Help,
+    Unknown(String),
+}
+
+fn parse_key(line: &str) -> Key {
+    let line = line.trim().to_lowercase();
+    let number = |s: &str| s.trim().parse::<usize>().ok().filter(|n| *n > 0);
+    match line.as_str() {
+        "" | "a" => Key::Accept,
+        "s" => Key::Skip,
+        "b" => Key::Back,
+        "n" => Key::Next,
+        "q" => Key::Finish,
+        "?" | "h" => Key::Help,
+        _ => {
+            if let Some(n) = line.strip_prefix('k').and_then(number) {
+                Key::KeepOnly(n - 1)
+            } else if let Some(n) = number(&line) {
+                Key::Toggle(n - 1)
+            } else {
+                Key::Unknown(line)
+            }
+        }
+    }
+}
+
+/// The key a TUI key press stands for with file `selected` highlighted;
+/// `None` for keys that do nothing
+fn tui_key(code: KeyCode, selected: usize) -> Option<Key> {
+    Some(match code {
+        KeyCode::Char(' ') => Key::Toggle(selected),
+        KeyCode::Char('k') => Key::KeepOnly(selected),
+        KeyCode::Char(c @ '1'..='9') => Key::Toggle(c as usize - '1' as usize),
+        KeyCode::Enter | KeyCode::Char('a') => Key::Accept,
+        KeyCode::Char('s') => Key::Skip,
+        KeyCode::Up | KeyCode::Char('b') => Key::Back,
+        KeyCode::Down | KeyCode::Char('n') => Key::Next,
+        KeyCode::Esc | KeyCode::Char('q') => Key::Finish,
+        KeyCode::Char('?') | KeyCode::Char('h') => Key::Help,
+        _ => return None,
+    })
+}
+
+/// What a key did
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    /// The marks of the current group changed
+    Marked,
+    /// `index` now points at another group, or past the last one
+    Moved,
+    Finish,
+}
+
+/// Apply `key` to the group at `index`. An error is a message for the user
+/// and leaves everything as it was.
+fn apply(
+    groups: &mut [ReviewGroup],
+    index: &mut usize,
+    key: Key,
+) -> std::result::Result<Step, String> {
+    let group = &mut groups[*index];
+    match key {
+        Key::Toggle(file) => group.toggle(file).map(|_| Step::Marked),
+        Key::KeepOnly(file) => group.keep_only(file).map(|_| Step::Marked),
+        Key::Accept if group.files.iter().all(|f| f.mark == Mark::Delete) => {
+            Err("Keep at least one copy, or press s to skip the group".to_string())
+        }
+        Key::Accept => {
+            group.decision = Decision::Accepted;
+            *index += 1;
+            Ok(Step::Moved)
+        }
+        Key::Skip => {
+            group.decision = Decision::Skipped;
+            *index += 1;
+            Ok(Step::Moved)
+        }
+        Key::Back => {
+            *index = index.saturating_sub(1);
+            Ok(Step::Moved)
+        }
+        Key::Next => {
+            *index += 1;
+            Ok(Step::Moved)
+        }
+        Key::Finish => Ok(Step::Finish),
+        Key::Help => Err(HELP.to_string()),
+        Key::Unknown(key) => Err(format!("Unknown key: {}. {}", key, HELP)),
+    }
+}
+
+/// Go through `groups`, reading one key per line from `input`, until every
+/// group is passed, `q` is typed or the input ends. Groups not decided
+/// stay [`Decision::Pending`].
+pub fn run(groups: &mut [ReviewGroup], input: &mut impl BufRead) -> Result<()> {
+    note!("{}", HELP);
+    let total = groups.len();
+    let mut index = 0;
+    let mut show = true;
+    while index < total {
+        let group = &groups[index];
+        if show {
+            note!(
+                "\nGroup {}/{} · {} · {} marked for deletion",
+                index + 1,
+                total,
+                group.label,
+                format_size(group.marked_size())
+            );
+            note!("{}", group.table());
+        }
+
+        prompt("> ")?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        match apply(groups, &mut index, parse_key(&line)) {
+            Ok(Step::Finish) => break,
+            Ok(_) => show = true,
+            Err(message) => {
+                note!("{}", message);
+                show = false;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// [`run`] as a full-screen view: the groups on the left, the current
+/// group's files side by side on the right, and a key line at the bottom
+pub fn run_tui(groups: &mut [ReviewGroup]) -> Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = tui_loop(&mut terminal, groups);
+    ratatui::try_restore()?;
+    result
+}
+
+/// Where the TUI is at
+#[derive(Default)]
+struct View {
+    group: usize,
+    /// The highlighted file of the group
+    file: usize,
+    message: Option<String>,
+}
+
+fn tui_loop(terminal: &mut DefaultTerminal, groups: &mut [ReviewGroup]) -> Result<()> {
+    let mut view = View::default();
+    while view.group < groups.len() {
+        terminal.draw(|frame| draw(frame, groups, &view))?;
+        let Event::Key(press) = event::read()? else {
+            continue;
+        };
+        if press.kind != KeyEventKind::Press {
+            continue;
+        }
+        let files = groups[view.group].files.len();
+        match press.code {
+            KeyCode::Left => view.file = view.file.saturating_sub(1),
+            KeyCode::Right => view.file = (view.file + 1).min(files.saturating_sub(1)),
+            KeyCode::Char('?') | KeyCode::Char('h') => view.message = Some(TUI_HELP.to_string()),
+            code => {
+                let Some(key) = tui_key(code, view.file) else {
+                    continue;
+                };
+                let before = view.group;
+                match apply(groups, &mut view.group, key) {
+                    Ok(Step::Finish) => break,
+                    Ok(_) => {
+                        view.message = None;
+                        if view.group != before {
+                            view.file = 0;
+                        }
+                    }
+                    Err(message) => view.message = Some(message),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, groups: &[ReviewGroup], view: &View) {
+    let [main, keys] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(frame.area());
+    let [list, details] =
+        Layout::horizontal([Constraint::Length(32), Constraint::Min(0)]).areas(main);
+
+    let items = groups.iter().map(|group| {
+        let decision = match group.decision {
+            Decision::Pending => "  ",
+            Decision::Accepted => "✅",
+            Decision::Skipped => "⏭️ ",
+        };
+        format!("{} {}", decision, group.label)
+    });
+    let mut list_state = ListState::default().with_selected(Some(view.group));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::bordered().title(format!(" Groups ({}) ", groups.len())))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        list,
+        &mut list_state,
+    );
+
+    let group = &groups[view.group];
+    let title = format!(
+        " Group {}/{} · {} · {} marked for deletion ",
+        view.group + 1,
+        groups.len(),
+        group.label,
+        format_size(group.marked_size())
+    );
+    let row = |label: &'static str, cell: &dyn Fn(&ReviewFile) -> String| {
+        let cells = std::iter::once(Cell::from(label.bold()))
+            .chain(group.files.iter().map(|f| Cell::from(cell(f))));
+        Row::new(cells)
+    };
+    let mut rows = vec![
+        row("Name", &|f| file_name(&f.path)),
+        row("Folder", &|f| {
+            f.path
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default()
+        }),
+        row("Size", &|f| format_size(f.size)),
+        row("Modified", &|f| format_timestamp(f.modified)),
+    ];
+    if group.files.iter().any(|f| f.dimensions.is_some()) {
+        rows.push(row("Pixels", &|f| {
+            f.dimensions
+                .map(|(w, h)| format!("{}×{}", w, h))
+                .unwrap_or_default()
+        }));
+    }
+    rows.push(row("Mark", &|f| f.mark_label().to_string()));
+
+    let header =
+        std::iter::once(String::new()).chain((1..=group.files.len()).map(|n| format!("#{}", n)));
+    let widths = std::iter::once(Constraint::Length(9))
+        .chain(group.files.iter().map(|_| Constraint::Fill(1)));
+    let mut table_state = TableState::default().with_selected_column(Some(view.file + 1));
+    frame.render_stateful_widget(
+        ratatui::widgets::Table::new(rows, widths)
+            .header(Row::new(header).bold())
+            .column_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(title)),
+        details,
+        &mut table_state,
+    );
+
+    let text = view.message.as_deref().unwrap_or(TUI_HELP);
+    frame.render_widget(
+        Paragraph::new(Line::from(text)).wrap(Wrap { trim: true }),
+        keys,
+    );
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn file(path: &str, mark: Mark) -> ReviewFile {
+        ReviewFile {
+            path: PathBuf::from(path),
+            size: 100,
+            modified: 0,
+            dimensions: None,
+            protected: false,
+            mark,
+        }
+    }
+
+    fn group(marks: &[Mark]) -> ReviewGroup {
+        ReviewGroup {
+            label: format!("{} identical copies", marks.len()),
+            files: marks
+                .iter()
+                .enumerate()
+                .map(|(i, &mark)| file(&format!("/photos/{}.jpg", i), mark))
+                .collect(),
+            decision: Decision::Pending,
+        }
+    }
+
+    fn marks(group: &ReviewGroup) -> Vec<Mark> {
+        group.files.iter().map(|f| f.mark).collect()
+    }
+
+    use Mark::{Delete, Keep};
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("\n"), Key::Accept);
+        assert_eq!(parse_key("A"), Key::Accept);
+        assert_eq!(parse_key(" s "), Key::Skip);
+        assert_eq!(parse_key("b"), Key::Back);
+        assert_eq!(parse_key("n"), Key::Next);
+        assert_eq!(parse_key("q"), Key::Finish);
+        assert_eq!(parse_key("?"), Key::Help);
+        assert_eq!(parse_key("2"), Key::Toggle(1));
+        assert_eq!(parse_key("k3"), Key::KeepOnly(2));
+        assert_eq!(parse_key("K 1"), Key::KeepOnly(0));
+        // Files are numbered from 1
+        assert_eq!(parse_key("0"), Key::Unknown("0".to_string()));
+        assert_eq!(parse_key("k0"), Key::Unknown("k0".to_string()));
+        assert_eq!(parse_key("delete"), Key::Unknown("delete".to_string()));
+    }
+
+    #[test]
+    fn test_tui_keys_act_on_the_highlighted_file() {
+        assert_eq!(tui_key(KeyCode::Char(' '), 2), Some(Key::Toggle(2)));
+        assert_eq!(tui_key(KeyCode::Char('k'), 1), Some(Key::KeepOnly(1)));
+        assert_eq!(tui_key(KeyCode::Char('3'), 0), Some(Key::Toggle(2)));
+        assert_eq!(tui_key(KeyCode::Enter, 0), Some(Key::Accept));
+        assert_eq!(tui_key(KeyCode::Up, 0), Some(Key::Back));
+        assert_eq!(tui_key(KeyCode::Down, 0), Some(Key::Next));
+        assert_eq!(tui_key(KeyCode::Esc, 0), Some(Key::Finish));
+        assert_eq!(tui_key(KeyCode::Char('x'), 0), None);
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut group = group(&[Keep, Delete]);
+        group.toggle(1).unwrap();
+        assert_eq!(marks(&group), [Keep, Keep]);
+        group.toggle(0).unwrap();
+        assert_eq!(marks(&group), [Delete, Keep]);
+
+        assert_eq!(group.toggle(2).unwrap_err(), "There is no file 3");
+
+        group.files[1].protected = true;
+        assert!(group.toggle(1).unwrap_err().contains("is protected"));
+        assert_eq!(marks(&group), [Delete, Keep]);
+    }
+
+    #[test]
+    fn test_keep_only() {
+        let mut group = group(&[Keep, Delete, Delete]);
+        group.files[2].protected = true;
+        group.files[2].mark = Keep;
+
+        group.keep_only(1).unwrap();
+        // Protected copies stay kept
+        assert_eq!(marks(&group), [Delete, Keep, Keep]);
+
+        assert_eq!(group.keep_only(3).unwrap_err(), "There is no file 4");
+        assert_eq!(marks(&group), [Delete, Keep, Keep]);
+    }
+
+    #[test]
+    fn test_run_follows_the_keys() {
+        let mut groups = vec![group(&[Keep, Delete]), group(&[Keep, Delete])];
+        // Keep the second file of group 1 instead, skip group 2
+        let mut input = Cursor::new("k2\n\ns\n");
+        run(&mut groups, &mut input).unwrap();
+
+        assert_eq!(groups[0].decision, Decision::Accepted);
+        assert_eq!(marks(&groups[0]), [Delete, Keep]);
+        assert_eq!(groups[1].decision, Decision::Skipped);
+        let to_delete: Vec<&ReviewFile> = groups.iter().flat_map(|g| g.to_delete()).collect();
+        assert_eq!(to_delete.len(), 1);
+        assert_eq!(to_delete[0].path, PathBuf::from("/photos/0.jpg"));
+    }
+
+    #[test]
+    fn test_run_refuses_to_delete_every_copy() {
+        let mut groups = vec![group(&[Keep, Delete])];
+        // Marking both for deletion can't be accepted; the input then ends
+        let mut input = Cursor::new("1\n\n");
+        run(&mut groups, &mut input).unwrap();
+
+        assert_eq!(marks(&groups[0]), [Delete, Delete]);
+        assert_eq!(groups[0].decision, Decision::Pending);
+        assert_eq!(groups[0].to_delete().count(), 0);
+    }
+
+    #[test]
+    fn test_run_goes_back_and_stops_at_q() {
+        let mut groups = vec![
+            group(&[Keep, Delete]),
+            group(&[Keep, Delete]),
+            group(&[Keep]),
+        ];
+        // Accept group 1, go back and skip it after all, pass group 2
+        // undecided, then finish on group 3
+        let mut input = Cursor::new("\nb\ns\nn\nq\n\n");
+        run(&mut groups, &mut input).unwrap();
+
+        let decisions: Vec<Decision> = groups.iter().map(|g| g.decision).collect();
+        assert_eq!(
+            decisions,
+            [Decision::Skipped, Decision::Pending, Decision::Pending]
+        );
+    }
+}
@@ -442,7 +442,7 @@ mod tests {
         let path = dir.path().join("ok.zip");
         let file = fs::File::create(&path).unwrap();
         let mut zip = zip::ZipWriter::new(file);
-        zip.start_file("hello.txt", zip::write::FileOptions::default())
+        zip.start_file("hello.txt", zip::write::SimpleFileOptions::default())
             .unwrap();
         zip.write_all(b"hello").unwrap();
         zip.finish().unwrap();
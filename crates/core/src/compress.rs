@@ -1,27 +1,47 @@
-use anyhow::Result;
-use flate2::write::GzEncoder;
+use anyhow::{bail, Context, Result};
+use flate2::read::MultiGzDecoder;
+use flate2::write::{DeflateEncoder, GzEncoder};
 use flate2::Compression;
+use serde::Serialize;
+use sevenz_rust::lzma::LZMA2Options;
+use sevenz_rust::{AesEncoderOptions, Password, SevenZArchiveEntry, SevenZReader, SevenZWriter};
 use std::fs::{self, File};
-use std::io::{self};
-use std::path::Path;
-use zip::write::FileOptions;
-use zip::{CompressionMethod, ZipWriter};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
+use walkdir::WalkDir;
+use zip::read::ZipFile;
+use zip::result::ZipError;
+use zip::write::{FileOptions, SimpleFileOptions};
+use zip::{AesMode, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::volumes;
 
 /// Compression trait
 pub trait CompressionAlgorithm {
     fn compress_file(&self, source: &Path, dest: &Path) -> Result<u64>;
     fn compress_directory(&self, source: &Path, dest: &Path) -> Result<u64>;
+    /// Restores the single file stored in `source` to `dest`; returns the
+    /// number of bytes written
+    fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64>;
+    /// Unpacks every entry of the archive below `dest_dir`; returns the
+    /// number of entries extracted
+    fn extract_archive(&self, source: &Path, dest_dir: &Path) -> Result<usize>;
 }
 
 /// ZIP compression
 pub struct ZipCompressor {
     compression_level: i32,
+    /// When set, entries are written and read with AES-256 encryption
+    password: Option<String>,
 }
 
 impl ZipCompressor {
     pub fn new() -> Self {
         Self {
             compression_level: 6, // Default compression level
+            password: None,
         }
     }
 
@@ -30,6 +50,64 @@ impl ZipCompressor {
         self
     }
 
+    /// Encrypts file entries with WinZip AES-256 (the zip crate's
+    /// `aes-crypto`) under `password`, and uses it to decrypt when
+    /// extracting. Entry names stay readable.
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// How a file or directory with `metadata` is stored: deflated (files)
+    /// with its mtime and permissions, and AES-256 encrypted when a password
+    /// is set
+    fn entry_options<'a>(&'a self, metadata: &fs::Metadata) -> FileOptions<'a, ()> {
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .compression_level(Some(i64::from(self.compression_level)));
+        if let Some(modified) = metadata
+            .modified()
+            .ok()
+            .and_then(|t| zip::DateTime::try_from(time::OffsetDateTime::from(t)).ok())
+        {
+            options = options.last_modified_time(modified);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(metadata.permissions().mode());
+        }
+        match &self.password {
+            Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+            None => options,
+        }
+    }
+
+    fn writer(&self, dest: &Path) -> Result<ZipWriter<File>> {
+        if self.password.as_deref() == Some("") {
+            return Err(space_saver_utils::Error::InvalidInput(
+                "Archive password must not be empty".to_string(),
+            )
+            .into());
+        }
+        Ok(ZipWriter::new(File::create(dest)?))
+    }
+
+    fn open_entry<'a>(
+        &self,
+        archive: &'a mut ZipArchive<File>,
+        index: usize,
+    ) -> Result<ZipFile<'a>> {
+        let entry = match &self.password {
+            Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+            None => archive.by_index(index),
+        };
+        entry.map_err(|e| match e {
+            ZipError::InvalidPassword => anyhow::anyhow!("Incorrect archive password"),
+            e => e.into(),
+        })
+    }
+
     fn add_directory_to_zip(
         &self,
         zip: &mut ZipWriter<File>,
@@ -39,18 +117,21 @@ impl ZipCompressor {
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
-            let name = path.strip_prefix(prefix)?;
+            let name = path
+                .strip_prefix(prefix)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let metadata = fs::metadata(&path)?;
 
-            if path.is_file() {
-                let options = FileOptions::default()
-                    .compression_method(CompressionMethod::Deflated)
-                    .compression_level(Some(self.compression_level));
-
-                zip.start_file(name.to_string_lossy().to_string(), options)?;
+            if metadata.is_file() {
+                zip.start_file(name, self.entry_options(&metadata))?;
                 let mut file = File::open(&path)?;
                 io::copy(&mut file, zip)?;
-            } else if path.is_dir() {
-                zip.add_directory(name.to_string_lossy().to_string(), FileOptions::default())?;
+            } else if metadata.is_dir() {
+                // Directory entries hold no data, so nothing to encrypt
+                let options =
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+                zip.add_directory(name, options)?;
                 self.add_directory_to_zip(zip, &path, prefix)?;
             }
         }
@@ -66,21 +147,15 @@ impl Default for ZipCompressor {
 
 impl CompressionAlgorithm for ZipCompressor {
     fn compress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
-        let file = File::create(dest)?;
-        let mut zip = ZipWriter::new(file);
-
-        let options = FileOptions::default()
-            .compression_method(CompressionMethod::Deflated)
-            .compression_level(Some(self.compression_level));
+        let mut zip = self.writer(dest)?;
 
         let filename = source
             .file_name()
             .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?
             .to_string_lossy();
 
-        zip.start_file(filename.to_string(), options)?;
-
         let mut file = File::open(source)?;
+        zip.start_file(filename, self.entry_options(&file.metadata()?))?;
         io::copy(&mut file, &mut zip)?;
 
         let result = zip.finish()?;
@@ -90,8 +165,7 @@ impl CompressionAlgorithm for ZipCompressor {
     }
 
     fn compress_directory(&self, source: &Path, dest: &Path) -> Result<u64> {
-        let file = File::create(dest)?;
-        let mut zip = ZipWriter::new(file);
+        let mut zip = self.writer(dest)?;
 
         self.add_directory_to_zip(&mut zip, source, source)?;
 
@@ -100,6 +174,46 @@ impl CompressionAlgorithm for ZipCompressor {
 
         Ok(compressed_size)
     }
+
+    fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut archive = ZipArchive::new(File::open(source)?)?;
+        let files: Vec<usize> = (0..archive.len())
+            .filter(|&i| archive.by_index_raw(i).is_ok_and(|e| !e.is_dir()))
+            .collect();
+        if files.len() != 1 {
+            bail!(
+                "ZIP archive contains {} files; use extract_archive instead",
+                files.len()
+            );
+        }
+        let mut entry = self.open_entry(&mut archive, files[0])?;
+        let mut output = File::create(dest)?;
+        Ok(io::copy(&mut entry, &mut output)?)
+    }
+
+    fn extract_archive(&self, source: &Path, dest_dir: &Path) -> Result<usize> {
+        let mut archive = ZipArchive::new(File::open(source)?)?;
+        fs::create_dir_all(dest_dir)?;
+        let mut dirs = Vec::new();
+        for i in 0..archive.len() {
+            let mut entry = self.open_entry(&mut archive, i)?;
+            let path = entry_destination(dest_dir, entry.name())?;
+            if entry.is_dir() {
+                fs::create_dir_all(&path)?;
+                if let Some(mode) = entry.unix_mode() {
+                    dirs.push((path, mode));
+                }
+            } else {
+                let mut output = create_entry_file(&path)?;
+                io::copy(&mut entry, &mut output)?;
+                if let Some(mode) = entry.unix_mode() {
+                    set_mode(&path, mode)?;
+                }
+            }
+        }
+        restore_dir_modes(dirs)?;
+        Ok(archive.len())
+    }
 }
 
 /// GZIP compression
@@ -141,35 +255,849 @@ impl CompressionAlgorithm for GzipCompressor {
 
     fn compress_directory(&self, _source: &Path, _dest: &Path) -> Result<u64> {
         Err(anyhow::anyhow!(
-            "GZIP does not support directory compression directly. Use tar+gzip instead."
+            "GZIP does not support directory compression directly. Use TarCompressor (tar+gzip) instead."
+        ))
+    }
+
+    fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut decoder = MultiGzDecoder::new(File::open(source)?);
+        let mut output = File::create(dest)?;
+        Ok(io::copy(&mut decoder, &mut output)?)
+    }
+
+    fn extract_archive(&self, _source: &Path, _dest_dir: &Path) -> Result<usize> {
+        Err(anyhow::anyhow!(
+            "GZIP holds a single file, not an archive. Use decompress_file instead."
+        ))
+    }
+}
+
+/// Zstandard compression
+pub struct ZstdCompressor {
+    compression_level: i32,
+    /// Window log for long-distance matching; `None` keeps the level's window
+    long_window: Option<u32>,
+}
+
+impl ZstdCompressor {
+    /// Window log used by `zstd --long` when no size is given (128 MiB)
+    pub const DEFAULT_LONG_WINDOW_LOG: u32 = 27;
+
+    /// Largest window this compressor writes, and accepts when reading
+    pub const MAX_LONG_WINDOW_LOG: u32 = 30;
+
+    pub fn new() -> Self {
+        Self {
+            compression_level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            long_window: None,
+        }
+    }
+
+    pub fn with_compression_level(mut self, level: i32) -> Self {
+        self.compression_level = level.clamp(1, 22);
+        self
+    }
+
+    /// Enables long-distance matching with a `2^window_log` byte window,
+    /// which finds repeats far apart in large backups. Windows above 2^27
+    /// must be allowed explicitly when decompressing (`zstd --long=N -d`).
+    pub fn with_long_window(mut self, window_log: u32) -> Self {
+        self.long_window = Some(window_log.clamp(10, Self::MAX_LONG_WINDOW_LOG));
+        self
+    }
+
+    fn encoder<W: Write>(&self, writer: W) -> Result<zstd::Encoder<'static, W>> {
+        let mut encoder = zstd::Encoder::new(writer, self.compression_level)?;
+        if let Some(window_log) = self.long_window {
+            encoder.long_distance_matching(true)?;
+            encoder.window_log(window_log)?;
+        }
+        Ok(encoder)
+    }
+
+    fn decoder<R: Read>(reader: R) -> Result<zstd::Decoder<'static, io::BufReader<R>>> {
+        let mut decoder = zstd::Decoder::new(reader)?;
+        // Long-window frames need the decoder's default limit raised
+        decoder.window_log_max(Self::MAX_LONG_WINDOW_LOG)?;
+        Ok(decoder)
+    }
+}
+
+impl Default for ZstdCompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressionAlgorithm for ZstdCompressor {
+    fn compress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut input = File::open(source)?;
+        let output = File::create(dest)?;
+        let mut encoder = self.encoder(output)?;
+
+        io::copy(&mut input, &mut encoder)?;
+        let result = encoder.finish()?;
+        let compressed_size = result.metadata()?.len();
+
+        Ok(compressed_size)
+    }
+
+    fn compress_directory(&self, _source: &Path, _dest: &Path) -> Result<u64> {
+        Err(anyhow::anyhow!(
+            "Zstandard does not support directory compression directly. Use TarCompressor (tar+zstd) instead."
+        ))
+    }
+
+    fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut decoder = Self::decoder(File::open(source)?)?;
+        let mut output = File::create(dest)?;
+        Ok(io::copy(&mut decoder, &mut output)?)
+    }
+
+    fn extract_archive(&self, _source: &Path, _dest_dir: &Path) -> Result<usize> {
+        Err(anyhow::anyhow!(
+            "Zstandard holds a single file, not an archive. Use decompress_file instead."
         ))
     }
 }
 
+/// Stream compression applied on top of a tar archive
+pub enum TarCodec {
+    Gzip(GzipCompressor),
+    Zstd(ZstdCompressor),
+}
+
+/// Tar container compression (`.tar.gz` / `.tar.zst`), which keeps file
+/// permissions, ownership, mtimes and symlinks
+pub struct TarCompressor {
+    codec: TarCodec,
+}
+
+impl TarCompressor {
+    pub fn new(codec: TarCodec) -> Self {
+        Self { codec }
+    }
+
+    pub fn gzip() -> Self {
+        Self::new(TarCodec::Gzip(GzipCompressor::new()))
+    }
+
+    pub fn zstd() -> Self {
+        Self::new(TarCodec::Zstd(ZstdCompressor::new()))
+    }
+
+    fn write_archive(&self, dest: &Path, source: TarSource) -> Result<u64> {
+        let output = File::create(dest)?;
+        let result = match &self.codec {
+            TarCodec::Gzip(gzip) => {
+                let encoder = GzEncoder::new(output, Compression::new(gzip.compression_level));
+                fill_tar(encoder, source)?.finish()?
+            }
+            TarCodec::Zstd(zstd) => fill_tar(zstd.encoder(output)?, source)?.finish()?,
+        };
+        let compressed_size = result.metadata()?.len();
+
+        Ok(compressed_size)
+    }
+
+    fn reader(&self, source: &Path) -> Result<tar::Archive<Box<dyn Read>>> {
+        let file = File::open(source)?;
+        let decoded: Box<dyn Read> = match &self.codec {
+            TarCodec::Gzip(_) => Box::new(MultiGzDecoder::new(file)),
+            TarCodec::Zstd(_) => Box::new(ZstdCompressor::decoder(file)?),
+        };
+        Ok(tar::Archive::new(decoded))
+    }
+}
+
+enum TarSource<'a> {
+    File(&'a Path),
+    DirectoryContents(&'a Path),
+}
+
+fn fill_tar<W: Write>(writer: W, source: TarSource) -> Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    tar.mode(tar::HeaderMode::Complete);
+    tar.follow_symlinks(false);
+    match source {
+        TarSource::File(path) => {
+            let filename = path
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?;
+            tar.append_path_with_name(path, filename)?;
+        }
+        TarSource::DirectoryContents(path) => {
+            for entry in WalkDir::new(path).min_depth(1).sort_by_file_name() {
+                let entry = entry?;
+                // Sockets, fifos and devices are left out, as with zip and 7z
+                let file_type = entry.file_type();
+                if file_type.is_file() || file_type.is_dir() || file_type.is_symlink() {
+                    tar.append_path_with_name(entry.path(), entry.path().strip_prefix(path)?)?;
+                }
+            }
+        }
+    }
+    Ok(tar.into_inner()?)
+}
+
+/// Old archivers mark directories as regular files named with a trailing
+/// slash
+fn is_tar_dir<R: Read>(entry: &tar::Entry<R>) -> bool {
+    entry.header().entry_type().is_dir() || entry.path_bytes().ends_with(b"/")
+}
+
+impl CompressionAlgorithm for TarCompressor {
+    fn compress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        self.write_archive(dest, TarSource::File(source))
+    }
+
+    fn compress_directory(&self, source: &Path, dest: &Path) -> Result<u64> {
+        self.write_archive(dest, TarSource::DirectoryContents(source))
+    }
+
+    fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut tar = self.reader(source)?;
+        let mut written = None;
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() || is_tar_dir(&entry) {
+                continue;
+            }
+            if written.is_some() {
+                let _ = fs::remove_file(dest);
+                bail!("Tar archive contains more than one file; use extract_archive instead");
+            }
+            let mut output = File::create(dest)?;
+            written = Some(io::copy(&mut entry, &mut output)?);
+            restore_mtime(&output, entry.header().mtime()?)?;
+        }
+        written.ok_or_else(|| anyhow::anyhow!("Tar archive contains no files"))
+    }
+
+    fn extract_archive(&self, source: &Path, dest_dir: &Path) -> Result<usize> {
+        let mut tar = self.reader(source)?;
+        fs::create_dir_all(dest_dir)?;
+
+        let mut count = 0;
+        // Directory modes are applied last so a read-only directory does not
+        // block its own children; symlinks are created last so no later
+        // entry can be written through one
+        let mut dirs = Vec::new();
+        let mut links = Vec::new();
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let path = entry_destination(dest_dir, entry.path()?)?;
+            let header = entry.header();
+            let (kind, mode) = (header.entry_type(), header.mode()?);
+            if is_tar_dir(&entry) {
+                fs::create_dir_all(&path)?;
+                dirs.push((path, mode));
+            } else if kind.is_file() {
+                let mtime = header.mtime()?;
+                let mut output = create_entry_file(&path)?;
+                io::copy(&mut entry, &mut output)?;
+                restore_mtime(&output, mtime)?;
+                set_mode(&path, mode)?;
+            } else if kind.is_symlink() {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| anyhow::anyhow!("Symlink {} has no target", path.display()))?;
+                links.push((path, target.into_owned()));
+            } else {
+                continue;
+            }
+            count += 1;
+        }
+
+        for (path, target) in links {
+            create_symlink(&target, &path)?;
+        }
+        restore_dir_modes(dirs)?;
+        Ok(count)
+    }
+}
+
+/// 7-Zip compression (LZMA2). Directories are packed solid, so many similar
+/// files compress far better than with per-entry deflate.
+pub struct SevenZipCompressor {
+    preset: u32,
+    password: Option<String>,
+}
+
+impl SevenZipCompressor {
+    pub fn new() -> Self {
+        Self {
+            preset: 6,
+            password: None,
+        }
+    }
+
+    /// LZMA2 preset from 0 (fastest) to 9 (smallest)
+    pub fn with_compression_level(mut self, level: u32) -> Self {
+        self.preset = level.min(9);
+        self
+    }
+
+    /// Encrypts entry contents and the file list with AES-256
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    fn writer(&self, dest: &Path) -> Result<SevenZWriter<File>> {
+        let lzma2 = LZMA2Options::with_preset(self.preset);
+        let methods = match &self.password {
+            Some(password) if password.is_empty() => {
+                return Err(space_saver_utils::Error::InvalidInput(
+                    "Archive password must not be empty".to_string(),
+                )
+                .into())
+            }
+            Some(password) => vec![
+                AesEncoderOptions::new(Password::from(password.as_str())).into(),
+                lzma2.into(),
+            ],
+            None => vec![lzma2.into()],
+        };
+        let mut writer = SevenZWriter::create(dest)?;
+        writer.set_content_methods(methods);
+        Ok(writer)
+    }
+
+    fn reader(&self, source: &Path) -> Result<SevenZReader<File>> {
+        let password = self
+            .password
+            .as_deref()
+            .map(Password::from)
+            .unwrap_or_else(Password::empty);
+        SevenZReader::open(source, password).map_err(|e| self.read_error(e))
+    }
+
+    /// With an encrypted header, a wrong password only shows up as corrupt
+    /// data, so with a password set any decoding failure is reported as one
+    fn read_error(&self, error: sevenz_rust::Error) -> anyhow::Error {
+        use sevenz_rust::Error;
+        match error {
+            Error::PasswordRequired | Error::MaybeBadPassword(_) => {
+                anyhow::anyhow!("Incorrect archive password")
+            }
+            Error::Io(..) | Error::FileOpen(..) => error.into(),
+            _ if self.password.is_some() => anyhow::anyhow!("Incorrect archive password"),
+            _ => error.into(),
+        }
+    }
+
+    /// Streams every entry of `source` to `each`, stopping at the first error
+    fn for_each_entry(
+        &self,
+        source: &Path,
+        mut each: impl FnMut(&SevenZArchiveEntry, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        // Errors of `each` itself (unsafe paths, a full disk) are kept aside
+        // so they are not reported as decoding failures
+        let mut failure = None;
+        let result = self.reader(source)?.for_each_entries(|entry, reader| {
+            let mut reader = DecodeReader {
+                inner: reader,
+                error: None,
+            };
+            match (each(entry, &mut reader), reader.error) {
+                (Ok(()), _) => Ok(true),
+                (Err(_), Some(read_error)) => Err(sevenz_rust::Error::io(read_error)),
+                (Err(e), None) => {
+                    let error = sevenz_rust::Error::other(e.to_string());
+                    failure = Some(e);
+                    Err(error)
+                }
+            }
+        });
+        match failure {
+            Some(e) => Err(e),
+            None => result.map_err(|e| self.read_error(e)),
+        }
+    }
+}
+
+/// Remembers a failed read of entry data, which is how a wrong password or
+/// corrupt archive surfaces while extracting
+struct DecodeReader<'a> {
+    inner: &'a mut dyn Read,
+    error: Option<io::Error>,
+}
+
+impl Read for DecodeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).inspect_err(|e| {
+            self.error = Some(io::Error::new(e.kind(), e.to_string()));
+        })
+    }
+}
+
+impl Default for SevenZipCompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn seven_zip_mtime(entry: &SevenZArchiveEntry) -> Option<u64> {
+    if !entry.has_last_modified_date {
+        return None;
+    }
+    let modified: std::time::SystemTime = entry.last_modified_date().into();
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+impl CompressionAlgorithm for SevenZipCompressor {
+    fn compress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let filename = source
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?
+            .to_string_lossy()
+            .to_string();
+        let mut writer = self.writer(dest)?;
+        writer.push_archive_entry(
+            SevenZArchiveEntry::from_path(source, filename),
+            Some(File::open(source)?),
+        )?;
+        Ok(writer.finish()?.metadata()?.len())
+    }
+
+    fn compress_directory(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let mut writer = self.writer(dest)?;
+        // The solid file stream below only carries files, so directories
+        // get their own entries to keep empty ones
+        for entry in WalkDir::new(source)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !e.path_is_symlink())
+        {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                let name = entry
+                    .path()
+                    .strip_prefix(source)?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                writer.push_archive_entry::<&[u8]>(
+                    SevenZArchiveEntry::from_path(entry.path(), name),
+                    None,
+                )?;
+            }
+        }
+        writer.push_source_path(source, |_| true)?;
+        Ok(writer.finish()?.metadata()?.len())
+    }
+
+    fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let files = self
+            .reader(source)?
+            .archive()
+            .files
+            .iter()
+            .filter(|e| !e.is_directory())
+            .count();
+        if files != 1 {
+            bail!(
+                "7z archive contains {} files; use extract_archive instead",
+                files
+            );
+        }
+        let mut written = 0;
+        self.for_each_entry(source, |entry, reader| {
+            if !entry.is_directory() {
+                let mut output = File::create(dest)?;
+                written = io::copy(reader, &mut output)?;
+                if let Some(mtime) = seven_zip_mtime(entry) {
+                    restore_mtime(&output, mtime)?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(written)
+    }
+
+    fn extract_archive(&self, source: &Path, dest_dir: &Path) -> Result<usize> {
+        fs::create_dir_all(dest_dir)?;
+        let mut count = 0;
+        self.for_each_entry(source, |entry, reader| {
+            let path = entry_destination(dest_dir, entry.name())?;
+            if entry.is_directory() {
+                fs::create_dir_all(&path)?;
+            } else {
+                let mut output = create_entry_file(&path)?;
+                io::copy(reader, &mut output)?;
+                if let Some(mtime) = seven_zip_mtime(entry) {
+                    restore_mtime(&output, mtime)?;
+                }
+            }
+            count += 1;
+            Ok(())
+        })?;
+        Ok(count)
+    }
+}
+
+/// Resolves an archive entry name below `dest_dir`, refusing absolute
+/// paths and `..` so a crafted archive cannot write outside it
+fn entry_destination(dest_dir: &Path, name: impl AsRef<Path>) -> Result<PathBuf> {
+    let name = name.as_ref();
+    let mut path = dest_dir.to_path_buf();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => bail!("Refusing to extract unsafe path: {}", name.display()),
+        }
+    }
+    Ok(path)
+}
+
+fn create_entry_file(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(path).with_context(|| format!("Failed to create {}", path.display()))
+}
+
+fn restore_mtime(file: &File, mtime: u64) -> Result<()> {
+    file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime))?;
+    Ok(())
+}
+
+/// Applies permission bits from the archive; setuid/setgid/sticky are
+/// dropped, as `tar` does for non-root users
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+fn restore_dir_modes(dirs: Vec<(PathBuf, u32)>) -> Result<()> {
+    // Deepest first, so restricting a parent cannot block a child
+    for (path, mode) in dirs.into_iter().rev() {
+        set_mode(&path, mode)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::symlink_metadata(path).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(path)?;
+    }
+    std::os::unix::fs::symlink(target, path)
+        .with_context(|| format!("Failed to create symlink {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn create_symlink(target: &Path, path: &Path) -> Result<()> {
+    tracing::warn!(
+        "Skipping symlink {} -> {}: not supported on this platform",
+        path.display(),
+        target.display()
+    );
+    Ok(())
+}
+
+/// Candidate algorithms weighed by [`Compressor::suggest_algorithm`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestedAlgorithm {
+    /// Not worth compressing (already compressed media, encrypted data)
+    Store,
+    Deflate,
+    Zstd,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlgorithmSuggestion {
+    pub algorithm: SuggestedAlgorithm,
+    /// Estimated space saved by the suggestion, as in `compression_ratio`
+    pub estimated_ratio: f32,
+    /// Estimated ratio of every candidate, Store first
+    pub candidates: Vec<(SuggestedAlgorithm, f32)>,
+    pub sampled_bytes: u64,
+}
+
+/// Savings below this are not worth the CPU time or the archive overhead
+const MIN_USEFUL_RATIO: f32 = 0.05;
+/// Bytes read from each sampled region
+const SAMPLE_CHUNK: u64 = 64 * 1024;
+/// Regions sampled per file (start, evenly spaced middle, end)
+const SAMPLES_PER_FILE: u64 = 4;
+/// Upper bound on bytes sampled across a directory
+const SAMPLE_BUDGET: u64 = 4 * 1024 * 1024;
+
+/// Reads up to `SAMPLES_PER_FILE` chunks spread across the file
+fn sample_file(path: &Path, budget: u64) -> Result<Vec<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let chunk = SAMPLE_CHUNK.min(budget);
+    if len == 0 || chunk == 0 {
+        return Ok(Vec::new());
+    }
+    // Small files are read whole, as far as the budget allows
+    if len <= SAMPLE_CHUNK * SAMPLES_PER_FILE {
+        let mut data = Vec::new();
+        file.take(budget).read_to_end(&mut data)?;
+        return Ok(vec![data]);
+    }
+    let step = (len - chunk) / (SAMPLES_PER_FILE - 1);
+    let mut samples = Vec::new();
+    for i in 0..SAMPLES_PER_FILE {
+        if (i + 1) * chunk > budget {
+            break;
+        }
+        file.seek(io::SeekFrom::Start(i * step))?;
+        let mut data = Vec::with_capacity(chunk as usize);
+        (&mut file).take(chunk).read_to_end(&mut data)?;
+        samples.push(data);
+    }
+    Ok(samples)
+}
+
+fn deflate_len(data: &[u8]) -> Result<u64> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::new(6));
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?.len() as u64)
+}
+
+fn zstd_len(data: &[u8]) -> Result<u64> {
+    Ok(zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)?.len() as u64)
+}
+
+/// Container formats for archiving a file or directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    SevenZip,
+    /// Keeps permissions, mtimes and symlinks; no password support
+    TarZstd,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::SevenZip => "7z",
+            ArchiveFormat::TarZstd => "tar.zst",
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "7z" => Ok(ArchiveFormat::SevenZip),
+            "tar.zst" | "tzst" => Ok(ArchiveFormat::TarZstd),
+            other => Err(space_saver_utils::Error::InvalidInput(format!(
+                "Unsupported archive format: {} (expected zip, 7z or tar.zst)",
+                other
+            ))
+            .into()),
+        }
+    }
+}
+
 /// Main compressor interface
 pub struct Compressor {
     algorithm: Box<dyn CompressionAlgorithm + Send + Sync>,
+    /// Split output into volumes of at most this many bytes
+    volume_size: Option<u64>,
 }
 
 impl Compressor {
-    pub fn new_zip() -> Self {
+    fn with_algorithm(algorithm: Box<dyn CompressionAlgorithm + Send + Sync>) -> Self {
         Self {
-            algorithm: Box::new(ZipCompressor::new()),
+            algorithm,
+            volume_size: None,
         }
     }
 
+    pub fn new_zip() -> Self {
+        Self::with_algorithm(Box::new(ZipCompressor::new()))
+    }
+
+    /// ZIP with AES-256 encrypted entries
+    pub fn new_encrypted_zip(password: impl Into<String>) -> Self {
+        Self::with_algorithm(Box::new(ZipCompressor::new().with_password(password)))
+    }
+
     pub fn new_gzip() -> Self {
-        Self {
-            algorithm: Box::new(GzipCompressor::new()),
-        }
+        Self::with_algorithm(Box::new(GzipCompressor::new()))
+    }
+
+    pub fn new_zstd() -> Self {
+        Self::with_algorithm(Box::new(ZstdCompressor::new()))
+    }
+
+    pub fn new_7z() -> Self {
+        Self::with_algorithm(Box::new(SevenZipCompressor::new()))
+    }
+
+    /// 7z with AES-256 encrypted contents and file list
+    pub fn new_encrypted_7z(password: impl Into<String>) -> Self {
+        Self::with_algorithm(Box::new(SevenZipCompressor::new().with_password(password)))
+    }
+
+    /// Archive compressor for `format`, encrypted when a password is given
+    pub fn new_archive(format: ArchiveFormat, password: Option<String>) -> Result<Self> {
+        Ok(match (format, password) {
+            (ArchiveFormat::Zip, None) => Self::new_zip(),
+            (ArchiveFormat::Zip, Some(password)) => Self::new_encrypted_zip(password),
+            (ArchiveFormat::SevenZip, None) => Self::new_7z(),
+            (ArchiveFormat::SevenZip, Some(password)) => Self::new_encrypted_7z(password),
+            (ArchiveFormat::TarZstd, None) => Self::new_tar_zstd(),
+            (ArchiveFormat::TarZstd, Some(_)) => {
+                return Err(space_saver_utils::Error::InvalidInput(
+                    "tar.zst archives cannot be password protected; use zip or 7z".to_string(),
+                )
+                .into())
+            }
+        })
+    }
+
+    pub fn new_tar_gzip() -> Self {
+        Self::with_algorithm(Box::new(TarCompressor::gzip()))
+    }
+
+    pub fn new_tar_zstd() -> Self {
+        Self::with_algorithm(Box::new(TarCompressor::zstd()))
+    }
+
+    /// Splits every archive this compressor writes into volumes of at most
+    /// `bytes` (e.g. [`volumes::FAT32_VOLUME_SIZE`]): `dest` is replaced by
+    /// `dest.001`, `dest.002`, ... and `dest.manifest.json`. Decompressing
+    /// or extracting `dest` (or its manifest) joins the volumes again.
+    pub fn with_volume_size(mut self, bytes: u64) -> Self {
+        self.volume_size = Some(bytes.max(1));
+        self
     }
 
     pub fn compress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
-        self.algorithm.compress_file(source, dest)
+        let size = self.algorithm.compress_file(source, dest)?;
+        self.split_output(dest, size)
     }
 
     pub fn compress_directory(&self, source: &Path, dest: &Path) -> Result<u64> {
-        self.algorithm.compress_directory(source, dest)
+        let size = self.algorithm.compress_directory(source, dest)?;
+        self.split_output(dest, size)
+    }
+
+    fn split_output(&self, dest: &Path, size: u64) -> Result<u64> {
+        match self.volume_size {
+            Some(volume_size) => Ok(volumes::split_into_volumes(dest, volume_size)?.total_size),
+            None => Ok(size),
+        }
+    }
+
+    /// Runs `f` on `source`, or on a temporary reassembly of it when
+    /// `source` is a split archive
+    fn with_joined<T>(&self, source: &Path, f: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+        let Some(manifest) = volumes::find_manifest(source) else {
+            return f(source);
+        };
+        let joined = manifest.with_extension("joined");
+        volumes::join_volumes(&manifest, &joined)?;
+        let result = f(&joined);
+        let _ = fs::remove_file(&joined);
+        result
+    }
+
+    /// Compresses a file or directory, whichever `source` is. A failed run
+    /// removes the partial output.
+    pub fn compress_path(&self, source: &Path, dest: &Path) -> Result<u64> {
+        let result = if source.is_dir() {
+            self.compress_directory(source, dest)
+        } else {
+            self.compress_file(source, dest)
+        };
+        if result.is_err() {
+            let _ = fs::remove_file(dest);
+        }
+        result
+    }
+
+    pub fn decompress_file(&self, source: &Path, dest: &Path) -> Result<u64> {
+        self.with_joined(source, |archive| {
+            self.algorithm.decompress_file(archive, dest)
+        })
+    }
+
+    pub fn extract_archive(&self, source: &Path, dest_dir: &Path) -> Result<usize> {
+        self.with_joined(source, |archive| {
+            self.algorithm.extract_archive(archive, dest_dir)
+        })
+    }
+
+    /// Estimates how well `path` (a file or directory) compresses by
+    /// compressing samples with each candidate, and recommends the best one.
+    /// Up to 4 MiB is sampled, so the answer is quick but approximate;
+    /// Store is suggested when no candidate saves at least 5%.
+    pub fn suggest_algorithm(path: &Path) -> Result<AlgorithmSuggestion> {
+        let mut samples = Vec::new();
+        let mut budget = SAMPLE_BUDGET;
+        if path.is_dir() {
+            for entry in WalkDir::new(path).sort_by_file_name() {
+                let entry = entry?;
+                if budget == 0 {
+                    break;
+                }
+                if entry.file_type().is_file() {
+                    for sample in sample_file(entry.path(), budget)? {
+                        budget -= sample.len() as u64;
+                        samples.push(sample);
+                    }
+                }
+            }
+        } else {
+            samples = sample_file(path, budget)?;
+        }
+
+        let sampled_bytes: u64 = samples.iter().map(|s| s.len() as u64).sum();
+        let (mut deflated, mut zstd_size) = (0u64, 0u64);
+        for sample in &samples {
+            deflated += deflate_len(sample)?;
+            zstd_size += zstd_len(sample)?;
+        }
+        let ratio = |compressed: u64| Self::compression_ratio(sampled_bytes, compressed).max(0.0);
+        let candidates = vec![
+            (SuggestedAlgorithm::Store, 0.0),
+            (SuggestedAlgorithm::Deflate, ratio(deflated)),
+            (SuggestedAlgorithm::Zstd, ratio(zstd_size)),
+        ];
+
+        // Zstd wins ties: it decompresses faster at a similar ratio
+        let (algorithm, estimated_ratio) = candidates[1..]
+            .iter()
+            .copied()
+            .filter(|&(_, r)| r >= MIN_USEFUL_RATIO)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((SuggestedAlgorithm::Store, 0.0));
+
+        Ok(AlgorithmSuggestion {
+            algorithm,
+            estimated_ratio,
+            candidates,
+            sampled_bytes,
+        })
     }
 
     /// Calculate compression ratio
@@ -223,6 +1151,608 @@ mod tests {
         assert!(dest.exists());
     }
 
+    #[test]
+    fn test_zstd_compress_file_round_trips() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("test.txt");
+        let dest = dir.path().join("test.txt.zst");
+
+        let content = "test content for compression ".repeat(1000);
+        fs::write(&source, &content).unwrap();
+
+        let compressor = Compressor::new_zstd();
+        let compressed_size = compressor.compress_file(&source, &dest).unwrap();
+
+        assert!(compressed_size > 0);
+        assert!(compressed_size < content.len() as u64);
+        let decoded = zstd::decode_all(File::open(&dest).unwrap()).unwrap();
+        assert_eq!(decoded, content.as_bytes());
+    }
+
+    #[test]
+    fn test_zstd_long_window() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("backup.tar");
+        let dest = dir.path().join("backup.tar.zst");
+
+        // Two copies of a block with no short-range redundancy: only a
+        // window covering both lets the second copy compress away
+        let mut seed = 0x9E3779B9u32;
+        let block: Vec<u8> = (0..1024 * 1024)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let data = [block.as_slice(), block.as_slice()].concat();
+        fs::write(&source, &data).unwrap();
+
+        let compressor = ZstdCompressor::new()
+            .with_compression_level(1)
+            .with_long_window(ZstdCompressor::DEFAULT_LONG_WINDOW_LOG);
+        let compressed_size = compressor.compress_file(&source, &dest).unwrap();
+        assert!(compressed_size < block.len() as u64 + block.len() as u64 / 10);
+
+        let mut decoder = zstd::Decoder::new(File::open(&dest).unwrap()).unwrap();
+        decoder
+            .window_log_max(ZstdCompressor::DEFAULT_LONG_WINDOW_LOG)
+            .unwrap();
+        let mut decoded = Vec::new();
+        io::Read::read_to_end(&mut decoder, &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_zstd_rejects_directories() {
+        let dir = tempdir().unwrap();
+        let err = Compressor::new_zstd()
+            .compress_directory(dir.path(), &dir.path().join("out.zst"))
+            .unwrap_err();
+        assert!(err.to_string().contains("tar+zstd"));
+    }
+
+    /// Extracts with the system `tar`, or returns false when it is missing
+    fn system_untar(archive: &Path, dest: &Path, flag: &str) -> bool {
+        std::process::Command::new("tar")
+            .arg(flag)
+            .arg("-xf")
+            .arg(archive)
+            .arg("-C")
+            .arg(dest)
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn test_tar_gzip_compress_directory() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("a.txt"), "alpha ".repeat(500)).unwrap();
+        fs::write(source.join("nested/b.txt"), "beta").unwrap();
+        let dest = dir.path().join("src.tar.gz");
+
+        let compressor = Compressor::new_tar_gzip();
+        let compressed_size = compressor.compress_directory(&source, &dest).unwrap();
+        assert!(compressed_size > 0);
+
+        // gzip magic, then a tar stream padded to whole 512-byte blocks
+        let mut decoder = flate2::read::GzDecoder::new(File::open(&dest).unwrap());
+        let mut tar = Vec::new();
+        io::Read::read_to_end(&mut decoder, &mut tar).unwrap();
+        assert_eq!(tar.len() % 512, 0);
+        assert_eq!(&tar[257..262], b"ustar");
+
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        if system_untar(&dest, &out, "-z") {
+            assert_eq!(
+                fs::read_to_string(out.join("nested/b.txt")).unwrap(),
+                "beta"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tar_zstd_preserves_mode_and_mtime() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir(&source).unwrap();
+        let script = source.join("run.sh");
+        fs::write(&script, "#!/bin/sh\necho hi\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o741)).unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        File::options()
+            .write(true)
+            .open(&script)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        let dest = dir.path().join("src.tar.zst");
+
+        Compressor::new_tar_zstd()
+            .compress_directory(&source, &dest)
+            .unwrap();
+
+        let tar = zstd::decode_all(File::open(&dest).unwrap()).unwrap();
+        assert_eq!(&tar[0..6], b"run.sh");
+        let field = |range: std::ops::Range<usize>| {
+            let text = std::str::from_utf8(&tar[range]).unwrap();
+            u64::from_str_radix(text.trim_end_matches('\0'), 8).unwrap()
+        };
+        // The mode field carries the file type bits along with the permissions
+        assert_eq!(field(100..107) & 0o7777, 0o741);
+        assert_eq!(field(136..147), 1_600_000_000);
+
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        let mut decoded = dir.path().join("src.tar");
+        fs::write(&decoded, &tar).unwrap();
+        if system_untar(&decoded, &out, "-p") {
+            decoded = out.join("run.sh");
+            let meta = fs::metadata(&decoded).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o777, 0o741);
+            assert_eq!(meta.modified().unwrap(), mtime);
+        }
+    }
+
+    #[test]
+    fn test_tar_compress_single_file() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("notes.txt");
+        fs::write(&source, "single file").unwrap();
+        let dest = dir.path().join("notes.tar.zst");
+
+        Compressor::new_tar_zstd()
+            .compress_file(&source, &dest)
+            .unwrap();
+        let tar = zstd::decode_all(File::open(&dest).unwrap()).unwrap();
+        assert_eq!(&tar[0..9], b"notes.txt");
+        assert_eq!(&tar[512..523], b"single file");
+    }
+
+    #[test]
+    fn test_decompress_file_round_trips_every_format() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("data.txt");
+        let content = "round trip ".repeat(200);
+        fs::write(&source, &content).unwrap();
+
+        let formats = [
+            ("zip", Compressor::new_zip()),
+            ("gz", Compressor::new_gzip()),
+            ("zst", Compressor::new_zstd()),
+            ("tar.gz", Compressor::new_tar_gzip()),
+            ("tar.zst", Compressor::new_tar_zstd()),
+            ("7z", Compressor::new_7z()),
+        ];
+        for (ext, compressor) in formats {
+            let packed = dir.path().join(format!("data.{}", ext));
+            let restored = dir.path().join(format!("restored.{}.txt", ext));
+            compressor.compress_file(&source, &packed).unwrap();
+            let written = compressor.decompress_file(&packed, &restored).unwrap();
+            assert_eq!(written, content.len() as u64, "{}", ext);
+            assert_eq!(fs::read_to_string(&restored).unwrap(), content, "{}", ext);
+        }
+    }
+
+    #[test]
+    fn test_extract_archive_restores_tree() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("nested/deeper")).unwrap();
+        fs::write(source.join("top.txt"), "top").unwrap();
+        fs::write(source.join("nested/deeper/leaf.txt"), "leaf").unwrap();
+
+        for (ext, compressor) in [
+            ("zip", Compressor::new_zip()),
+            ("tar.gz", Compressor::new_tar_gzip()),
+            ("tar.zst", Compressor::new_tar_zstd()),
+            ("7z", Compressor::new_7z()),
+        ] {
+            let packed = dir.path().join(format!("src.{}", ext));
+            let out = dir.path().join(format!("out-{}", ext));
+            compressor.compress_directory(&source, &packed).unwrap();
+            let entries = compressor.extract_archive(&packed, &out).unwrap();
+            assert_eq!(entries, 4, "{}", ext);
+            assert_eq!(fs::read_to_string(out.join("top.txt")).unwrap(), "top");
+            assert_eq!(
+                fs::read_to_string(out.join("nested/deeper/leaf.txt")).unwrap(),
+                "leaf"
+            );
+
+            // A multi-file archive cannot be restored as a single file
+            let err = compressor
+                .decompress_file(&packed, &dir.path().join("single"))
+                .unwrap_err();
+            assert!(err.to_string().contains("extract_archive"), "{}", ext);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tar_extract_restores_metadata_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::SystemTime;
+
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir(&source).unwrap();
+        let script = source.join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        File::options()
+            .write(true)
+            .open(&script)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        std::os::unix::fs::symlink("run.sh", source.join("latest")).unwrap();
+
+        let packed = dir.path().join("src.tar.zst");
+        let out = dir.path().join("out");
+        let compressor = Compressor::new_tar_zstd();
+        compressor.compress_directory(&source, &packed).unwrap();
+        compressor.extract_archive(&packed, &out).unwrap();
+
+        let meta = fs::metadata(out.join("run.sh")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o750);
+        assert_eq!(meta.modified().unwrap(), mtime);
+        assert_eq!(
+            fs::read_link(out.join("latest")).unwrap(),
+            Path::new("run.sh")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tar_keeps_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir(&source).unwrap();
+        // Latin-1 "café.txt", which is not valid UTF-8
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        if fs::write(source.join(name), "latin-1").is_err() {
+            return; // The filesystem only takes UTF-8 names
+        }
+
+        let packed = dir.path().join("src.tar.zst");
+        let out = dir.path().join("out");
+        let compressor = Compressor::new_tar_zstd();
+        compressor.compress_directory(&source, &packed).unwrap();
+        compressor.extract_archive(&packed, &out).unwrap();
+
+        assert_eq!(fs::read_to_string(out.join(name)).unwrap(), "latin-1");
+    }
+
+    #[test]
+    fn test_extract_rejects_path_traversal() {
+        let dir = tempdir().unwrap();
+        let packed = dir.path().join("evil.zip");
+        let mut zip = ZipWriter::new(File::create(&packed).unwrap());
+        zip.start_file("../escaped.txt", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"gotcha").unwrap();
+        zip.finish().unwrap();
+
+        let out = dir.path().join("out");
+        let err = Compressor::new_zip()
+            .extract_archive(&packed, &out)
+            .unwrap_err();
+        assert!(err.to_string().contains("unsafe path"));
+        assert!(!dir.path().join("escaped.txt").exists());
+    }
+
+    #[test]
+    fn test_encrypted_zip_round_trip() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("docs");
+        fs::create_dir_all(source.join("2019")).unwrap();
+        fs::write(source.join("2019/tax.txt"), "private figures").unwrap();
+        fs::write(source.join("index.txt"), "list").unwrap();
+        let packed = dir.path().join("docs.zip");
+
+        Compressor::new_encrypted_zip("hunter2")
+            .compress_directory(&source, &packed)
+            .unwrap();
+
+        // Readable only with the password
+        let out = dir.path().join("out");
+        assert!(Compressor::new_zip()
+            .extract_archive(&packed, &out)
+            .is_err());
+        let err = Compressor::new_encrypted_zip("wrong")
+            .extract_archive(&packed, &out)
+            .unwrap_err();
+        assert!(err.to_string().contains("Incorrect archive password"));
+
+        let entries = Compressor::new_encrypted_zip("hunter2")
+            .extract_archive(&packed, &out)
+            .unwrap();
+        assert_eq!(entries, 3);
+        assert_eq!(
+            fs::read_to_string(out.join("2019/tax.txt")).unwrap(),
+            "private figures"
+        );
+
+        let single = dir.path().join("tax.zip");
+        let restored = dir.path().join("tax.txt");
+        let compressor = Compressor::new_encrypted_zip("hunter2");
+        compressor
+            .compress_file(&source.join("2019/tax.txt"), &single)
+            .unwrap();
+        compressor.decompress_file(&single, &restored).unwrap();
+        assert_eq!(fs::read_to_string(&restored).unwrap(), "private figures");
+    }
+
+    #[test]
+    fn test_encrypted_zip_reads_back_through_the_zip_crate() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("tax.txt");
+        fs::write(&source, "private figures").unwrap();
+        let packed = dir.path().join("tax.zip");
+        Compressor::new_encrypted_zip("hunter2")
+            .compress_file(&source, &packed)
+            .unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&packed).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert!(archive.by_index_raw(0).unwrap().encrypted());
+        assert!(matches!(
+            archive.by_index(0).map(|_| ()),
+            Err(ZipError::UnsupportedArchive(_))
+        ));
+        assert!(matches!(
+            archive.by_index_decrypt(0, b"wrong").map(|_| ()),
+            Err(ZipError::InvalidPassword)
+        ));
+        let mut entry = archive.by_index_decrypt(0, b"hunter2").unwrap();
+        assert_eq!(entry.name(), "tax.txt");
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "private figures");
+
+        let err = Compressor::new_encrypted_zip("")
+            .compress_file(&source, &dir.path().join("empty-pw.zip"))
+            .unwrap_err();
+        assert!(err.to_string().contains("must not be empty"));
+    }
+
+    #[test]
+    fn test_7z_beats_zip_on_similar_files() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("logs");
+        fs::create_dir_all(source.join("empty")).unwrap();
+        for day in 0..20 {
+            let lines: String = (0..200)
+                .map(|i| {
+                    format!(
+                        "2024-01-{:02} worker-{} finished job {}\n",
+                        day + 1,
+                        i % 7,
+                        i
+                    )
+                })
+                .collect();
+            fs::write(source.join(format!("day{:02}.log", day)), lines).unwrap();
+        }
+
+        let zip = dir.path().join("logs.zip");
+        let seven = dir.path().join("logs.7z");
+        let zip_size = Compressor::new_zip()
+            .compress_directory(&source, &zip)
+            .unwrap();
+        let seven_size = Compressor::new_7z()
+            .compress_directory(&source, &seven)
+            .unwrap();
+        assert!(
+            seven_size < zip_size,
+            "7z {} vs zip {}",
+            seven_size,
+            zip_size
+        );
+
+        let out = dir.path().join("out");
+        assert_eq!(
+            Compressor::new_7z().extract_archive(&seven, &out).unwrap(),
+            21
+        );
+        assert!(out.join("empty").is_dir());
+        assert_eq!(
+            fs::read(out.join("day07.log")).unwrap(),
+            fs::read(source.join("day07.log")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encrypted_7z_round_trip() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("docs");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("tax.txt"), "private figures").unwrap();
+        let packed = dir.path().join("docs.7z");
+
+        Compressor::new_archive(ArchiveFormat::SevenZip, Some("hunter2".into()))
+            .unwrap()
+            .compress_directory(&source, &packed)
+            .unwrap();
+
+        let out = dir.path().join("out");
+        for compressor in [Compressor::new_7z(), Compressor::new_encrypted_7z("wrong")] {
+            let err = compressor.extract_archive(&packed, &out).unwrap_err();
+            assert!(
+                err.to_string().contains("Incorrect archive password"),
+                "{:#}",
+                err
+            );
+        }
+        Compressor::new_encrypted_7z("hunter2")
+            .extract_archive(&packed, &out)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(out.join("tax.txt")).unwrap(),
+            "private figures"
+        );
+
+        let err = Compressor::new_encrypted_7z("")
+            .compress_directory(&source, &dir.path().join("empty-pw.7z"))
+            .unwrap_err();
+        assert!(err.to_string().contains("must not be empty"));
+    }
+
+    #[test]
+    fn test_archive_format_parsing() {
+        assert_eq!("zip".parse::<ArchiveFormat>().unwrap(), ArchiveFormat::Zip);
+        assert_eq!(
+            "7Z".parse::<ArchiveFormat>().unwrap(),
+            ArchiveFormat::SevenZip
+        );
+        assert_eq!(ArchiveFormat::SevenZip.extension(), "7z");
+        assert_eq!(
+            "tar.zst".parse::<ArchiveFormat>().unwrap(),
+            ArchiveFormat::TarZstd
+        );
+        assert!("rar".parse::<ArchiveFormat>().is_err());
+        assert!(Compressor::new_archive(ArchiveFormat::TarZstd, Some("pw".into())).is_err());
+    }
+
+    #[test]
+    fn test_compress_path_removes_partial_output() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("out.zip");
+        let err = Compressor::new_encrypted_zip("")
+            .compress_path(dir.path(), &dest)
+            .unwrap_err();
+        assert!(err.to_string().contains("must not be empty"));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_split_volumes_round_trip() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("photos");
+        fs::create_dir(&source).unwrap();
+        // Incompressible, so the archive spans several volumes
+        let mut seed = 0x1234_5678u32;
+        let noise: Vec<u8> = (0..20_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        fs::write(source.join("noise.bin"), &noise).unwrap();
+        let dest = dir.path().join("photos.tar.zst");
+
+        let compressor = Compressor::new_tar_zstd().with_volume_size(8192);
+        let total = compressor.compress_directory(&source, &dest).unwrap();
+
+        assert!(!dest.exists());
+        let manifest = volumes::VolumeManifest::load(&volumes::manifest_path(&dest)).unwrap();
+        assert_eq!(manifest.archive_name, "photos.tar.zst");
+        assert_eq!(manifest.total_size, total);
+        assert!(manifest.volumes.len() >= 3);
+        for volume in &manifest.volumes {
+            let size = fs::metadata(dir.path().join(&volume.file_name))
+                .unwrap()
+                .len();
+            assert!(size <= 8192);
+        }
+
+        // Extraction accepts the logical archive path or the manifest
+        let out = dir.path().join("out");
+        compressor.extract_archive(&dest, &out).unwrap();
+        assert_eq!(fs::read(out.join("noise.bin")).unwrap(), noise);
+        let out2 = dir.path().join("out2");
+        compressor
+            .extract_archive(&volumes::manifest_path(&dest), &out2)
+            .unwrap();
+        assert_eq!(fs::read(out2.join("noise.bin")).unwrap(), noise);
+        // The temporary reassembly is cleaned up
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".joined"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_stream_formats_reject_extract_archive() {
+        let dir = tempdir().unwrap();
+        for compressor in [Compressor::new_gzip(), Compressor::new_zstd()] {
+            let err = compressor
+                .extract_archive(&dir.path().join("x"), dir.path())
+                .unwrap_err();
+            assert!(err.to_string().contains("decompress_file"));
+        }
+    }
+
+    #[test]
+    fn test_suggest_algorithm_for_text_and_noise() {
+        let dir = tempdir().unwrap();
+        let text = dir.path().join("log.txt");
+        let lines: String = (0..20_000)
+            .map(|i| format!("2024-01-01 12:00:{:02} INFO request {} served\n", i % 60, i))
+            .collect();
+        fs::write(&text, &lines).unwrap();
+
+        let suggestion = Compressor::suggest_algorithm(&text).unwrap();
+        assert_ne!(suggestion.algorithm, SuggestedAlgorithm::Store);
+        assert!(suggestion.estimated_ratio > 0.5);
+        assert_eq!(suggestion.candidates.len(), 3);
+        // Large file: only the spread-out samples are read
+        assert_eq!(suggestion.sampled_bytes, SAMPLE_CHUNK * SAMPLES_PER_FILE);
+
+        let noise = dir.path().join("video.bin");
+        let mut seed = 0xDEAD_BEEFu32;
+        let bytes: Vec<u8> = (0..100_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        fs::write(&noise, &bytes).unwrap();
+        let suggestion = Compressor::suggest_algorithm(&noise).unwrap();
+        assert_eq!(suggestion.algorithm, SuggestedAlgorithm::Store);
+        assert_eq!(suggestion.estimated_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_suggest_algorithm_samples_directories() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.csv"), "id,name\n1,alpha\n".repeat(500)).unwrap();
+        fs::write(
+            dir.path().join("sub/b.csv"),
+            "id,name\n2,beta\n".repeat(500),
+        )
+        .unwrap();
+
+        let suggestion = Compressor::suggest_algorithm(dir.path()).unwrap();
+        assert!(suggestion.sampled_bytes > 0);
+        assert!(suggestion.estimated_ratio > 0.5);
+
+        let empty = tempdir().unwrap();
+        let suggestion = Compressor::suggest_algorithm(empty.path()).unwrap();
+        assert_eq!(suggestion.algorithm, SuggestedAlgorithm::Store);
+        assert_eq!(suggestion.sampled_bytes, 0);
+    }
+
     #[test]
     fn test_compression_ratio() {
         let ratio = Compressor::compression_ratio(1000, 500);
@@ -1,11 +1,204 @@
 use anyhow::{anyhow, Result};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tracing::warn;
 
+use crate::plugins::{MinifyOptions, RawPolicy};
+
+/// Default number of files `process_batch` works on at once; matches the
+/// default `max_concurrent_tasks` in the app config
+pub const DEFAULT_MAX_WORKERS: usize = 4;
+
+/// Cooperative cancellation flag for compression jobs. Clones share the same
+/// flag, so the caller keeps one handle and passes another into the manager.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation; work in progress stops at its next check
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Return a [`Cancelled`] error if cancellation was requested
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub use space_saver_utils::error::{Cancelled, TimedOut};
+use space_saver_utils::Error;
+
+/// Limits applied to external tool processes started by plugins
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimits {
+    /// Scheduling niceness (Unix `nice`, higher is lower priority)
+    pub nice: Option<i32>,
+    /// Cap on the process's address space, in MiB
+    pub max_memory_mb: Option<u64>,
+    /// Run at idle IO priority, reading the disk only when nothing else
+    /// does. Linux only.
+    #[serde(default)]
+    pub idle_io: bool,
+}
+
+impl ToolLimits {
+    /// Apply the limits to `cmd` before it is spawned. Only supported on
+    /// Unix; elsewhere the tool runs unrestricted.
+    pub fn apply(&self, cmd: &mut Command) {
+        if *self == Self::default() {
+            return;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            let limits = *self;
+            // SAFETY: setpriority, setrlimit and raw syscalls are
+            // async-signal-safe, so they may run between fork and exec
+            unsafe {
+                cmd.pre_exec(move || {
+                    if let Some(nice) = limits.nice {
+                        libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                    }
+                    #[cfg(target_os = "linux")]
+                    if limits.idle_io {
+                        // IOPRIO_WHO_PROCESS, this process, IOPRIO_CLASS_IDLE
+                        libc::syscall(libc::SYS_ioprio_set, 1, 0, 3 << 13);
+                    }
+                    if let Some(mb) = limits.max_memory_mb {
+                        let bytes = mb.saturating_mul(1024 * 1024) as libc::rlim_t;
+                        let limit = libc::rlimit {
+                            rlim_cur: bytes,
+                            rlim_max: bytes,
+                        };
+                        if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = cmd;
+            tracing::debug!("Tool resource limits are not supported on this platform");
+        }
+    }
+}
+
+/// One progress report from a plugin working on a single file. Units are up
+/// to the plugin: archive plugins count entries, others may count bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginProgress {
+    /// Source file being processed
+    pub path: PathBuf,
+    pub current: u64,
+    pub total: u64,
+    /// What the plugin is working on (e.g. the archive entry name)
+    pub message: Option<String>,
+}
+
+type ProgressCallback = dyn Fn(&PluginProgress) + Send + Sync;
+
+/// Channel from a running plugin back to whoever started the job: plugins
+/// report progress through it and poll it for cancellation. Cheap to clone;
+/// clones share the callback and the cancellation token.
+#[derive(Clone, Default)]
+pub struct ProgressSink {
+    callback: Option<Arc<ProgressCallback>>,
+    cancel: CancellationToken,
+    /// Stamped onto every report; set by the manager for the file in flight
+    path: PathBuf,
+}
+
+impl ProgressSink {
+    /// A sink that discards progress and is never cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observe `cancel` instead of a private, never-cancelled token
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Call `callback` for every progress report
+    pub fn on_progress(
+        mut self,
+        callback: impl Fn(&PluginProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// The same sink, reporting against `path`
+    pub fn for_file(&self, path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            ..self.clone()
+        }
+    }
+
+    /// Report that `current` of `total` units of the current file are done
+    pub fn report(&self, current: u64, total: u64, message: Option<String>) {
+        if let Some(callback) = &self.callback {
+            callback(&PluginProgress {
+                path: self.path.clone(),
+                current,
+                total,
+                message,
+            });
+        }
+    }
+
+    pub fn cancel_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Return a [`Cancelled`] error if cancellation was requested
+    pub fn check_cancelled(&self) -> Result<()> {
+        self.cancel.check()
+    }
+}
+
+impl std::fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressSink")
+            .field("has_callback", &self.callback.is_some())
+            .field("cancel", &self.cancel)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
 /// Result of a compression operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionResult {
@@ -20,6 +213,15 @@ pub struct CompressionResult {
     /// backing up the original (e.g. ZIP-to-ZIP conversion keeps the name)
     #[serde(default)]
     pub replace_source: bool,
+    /// Whether EXIF/XMP/ICC metadata of the source was carried into the
+    /// output; None when the source had none or the plugin doesn't track it
+    #[serde(default)]
+    pub metadata_preserved: Option<bool>,
+    /// Files written alongside the output (e.g. an archive of the original),
+    /// counted in `compressed_size` and removed with the output when the
+    /// manager discards it
+    #[serde(default)]
+    pub extra_outputs: Vec<PathBuf>,
 }
 
 /// Outcome of running a plugin through the manager
@@ -38,6 +240,44 @@ pub struct PluginMetadata {
     pub name: String,
     pub description: String,
     pub version: String,
+    /// Output is not bit-for-bit recoverable to the original (lossy encoding)
+    #[serde(default)]
+    pub lossy: bool,
+    /// The output takes over the original's path instead of being written
+    /// next to it, so the original is gone unless a backup is kept. Refused
+    /// by the manager in safe mode.
+    #[serde(default)]
+    pub destructive: bool,
+    /// External programs the plugin shells out to; any one of them is
+    /// enough, and plugins with a built-in fallback also run without them.
+    /// Empty for pure-Rust plugins.
+    #[serde(default)]
+    pub requires_external_tools: Vec<String>,
+}
+
+/// Health of one external tool a plugin can run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolCheck {
+    pub name: String,
+    /// What was probed: the configured path, or the bare name looked up on PATH
+    pub program: String,
+    pub available: bool,
+    /// First line of the tool's version output
+    pub version: Option<String>,
+    /// Why the probe failed
+    pub error: Option<String>,
+}
+
+/// Whether a plugin can run with the tools on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginRequirements {
+    pub plugin_name: String,
+    pub tools: Vec<ToolCheck>,
+    /// The plugin can process files: it needs no tools, one of its tools
+    /// works, or it has a built-in fallback
+    pub ready: bool,
+    /// How the plugin will run (which tool, or the built-in fallback)
+    pub message: Option<String>,
 }
 
 /// Trait that all compression plugins must implement
@@ -64,6 +304,33 @@ pub trait CompressionPlugin: Send + Sync {
     /// Perform the compression/conversion, writing the result into `output_dir`
     fn process(&self, source: &Path, output_dir: &Path) -> Result<CompressionResult>;
 
+    /// Like [`process`](Self::process), reporting progress to `sink` and
+    /// stopping early when the sink is cancelled.
+    ///
+    /// Plugins doing long-running work (large archives, external tools)
+    /// should override this, report per-entry or per-byte progress, check
+    /// for cancellation periodically, and remove any partial output or temp
+    /// files before returning [`Cancelled`]. The default runs `process` to
+    /// completion without reports; the manager then discards the output if
+    /// cancellation was requested meanwhile.
+    fn process_with_progress(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        _sink: &ProgressSink,
+    ) -> Result<CompressionResult> {
+        self.process(source, output_dir)
+    }
+
+    /// Check that the output described by `result` is readable (decodes,
+    /// archive entries match the source) before the manager touches the
+    /// original. Returns `Ok(true)` when verified, `Ok(false)` when this
+    /// plugin has no way to verify (the default), and an error when the
+    /// output is broken.
+    fn verify_output(&self, _source: &Path, _result: &CompressionResult) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Get supported file extensions (e.g., ["png", "jpg", "jpeg"])
     fn supported_extensions(&self) -> Vec<&str>;
 
@@ -76,20 +343,226 @@ pub trait CompressionPlugin: Send + Sync {
     fn set_quality(&mut self, _quality: f32) -> bool {
         false
     }
+
+    /// Whether the plugin copies image metadata (EXIF/XMP/ICC) into its
+    /// output, or None if it has no such setting
+    fn preserve_metadata(&self) -> Option<bool> {
+        None
+    }
+
+    /// Enable or disable metadata copying; returns false if the plugin has no such setting
+    fn set_preserve_metadata(&mut self, _enabled: bool) -> bool {
+        false
+    }
+
+    /// What to do with camera RAW originals after conversion; returns false
+    /// if the plugin doesn't convert RAW files
+    fn set_raw_policy(&mut self, _policy: RawPolicy) -> bool {
+        false
+    }
+
+    /// Where and what to minify; returns false if the plugin doesn't minify
+    fn set_minify_options(&mut self, _options: &MinifyOptions) -> bool {
+        false
+    }
+
+    /// Probe the external tools this plugin uses (version check included).
+    /// The default suits plugins without tools: always ready.
+    fn check_requirements(&self) -> PluginRequirements {
+        PluginRequirements {
+            plugin_name: self.metadata().name,
+            tools: Vec::new(),
+            ready: true,
+            message: None,
+        }
+    }
+
+    /// Where to find external tools, keyed by tool name; tools absent from
+    /// the map are looked up on PATH. Ignored by plugins without tools.
+    fn set_tool_paths(&mut self, _paths: &BTreeMap<String, PathBuf>) {}
+
+    /// Resource limits for external tool processes. Ignored by plugins
+    /// without tools.
+    fn set_tool_limits(&mut self, _limits: ToolLimits) {}
+}
+
+/// Counting semaphore bounding how many external-tool plugins run at once,
+/// across every caller of the manager
+struct ToolSlots {
+    state: Mutex<ToolSlotState>,
+    released: Condvar,
+}
+
+struct ToolSlotState {
+    in_use: usize,
+    limit: usize,
+}
+
+/// Holds one tool slot; released on drop
+struct ToolSlotGuard<'a> {
+    slots: &'a ToolSlots,
+}
+
+impl ToolSlots {
+    fn new(limit: usize) -> Self {
+        Self {
+            state: Mutex::new(ToolSlotState {
+                in_use: 0,
+                limit: limit.max(1),
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    fn set_limit(&self, limit: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.limit = limit.max(1);
+        self.released.notify_all();
+    }
+
+    fn limit(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).limit
+    }
+
+    /// Wait for a free slot, giving up with [`Cancelled`] if the sink is
+    /// cancelled while waiting
+    fn acquire(&self, sink: &ProgressSink) -> Result<ToolSlotGuard<'_>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while state.in_use >= state.limit {
+            sink.check_cancelled()?;
+            state = self
+                .released
+                .wait_timeout(state, Duration::from_millis(50))
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        state.in_use += 1;
+        Ok(ToolSlotGuard { slots: self })
+    }
+}
+
+impl Drop for ToolSlotGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.slots.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.in_use -= 1;
+        self.slots.released.notify_one();
+    }
+}
+
+/// Default number of external-tool plugins allowed to run at once: a quarter
+/// of the cores, since each tool spreads over several threads itself
+fn default_external_tool_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() / 4)
+        .unwrap_or(1)
+        .max(1)
 }
 
 /// Plugin registry and manager
 pub struct PluginManager {
     plugins: Vec<Box<dyn CompressionPlugin>>,
+    max_workers: usize,
+    tool_slots: ToolSlots,
+    require_verification: bool,
+    min_savings_percent: f32,
+    file_timeout: Option<Duration>,
+    safe_mode: bool,
+    disabled: HashSet<String>,
 }
 
 impl PluginManager {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            max_workers: DEFAULT_MAX_WORKERS,
+            tool_slots: ToolSlots::new(default_external_tool_jobs()),
+            require_verification: true,
+            min_savings_percent: 0.0,
+            file_timeout: None,
+            safe_mode: false,
+            disabled: HashSet::new(),
         }
     }
 
+    /// In safe mode, plugins whose metadata is `destructive` are refused:
+    /// scans report them as unable to handle files and processing fails
+    /// without touching the original. Callers pass the config's `safe_mode`.
+    pub fn set_safe_mode(&mut self, enabled: bool) {
+        self.safe_mode = enabled;
+    }
+
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    /// Turned-off plugins are still listed but never picked for a file:
+    /// scans report them as unable to handle files and processing with them
+    /// fails. Replaces the previous set; callers pass the config's
+    /// `disabled_plugins`.
+    pub fn set_disabled_plugins<S: Into<String>>(&mut self, names: impl IntoIterator<Item = S>) {
+        self.disabled = names.into_iter().map(Into::into).collect();
+    }
+
+    pub fn is_plugin_enabled(&self, plugin_name: &str) -> bool {
+        !self.disabled.contains(plugin_name)
+    }
+
+    /// When set (the default), a run that would delete the original (no
+    /// backup kept) is refused unless the plugin verified its output.
+    /// Callers pass the config's `require_output_verification`.
+    /// Outputs must save at least this share of the original size (0-100)
+    /// to be kept; smaller savings are discarded and reported as skipped.
+    /// Callers pass the config's `min_savings_percent`. The default, 0,
+    /// keeps any output that is smaller at all.
+    pub fn set_min_savings_percent(&mut self, percent: f32) {
+        self.min_savings_percent = percent.clamp(0.0, 100.0);
+    }
+
+    pub fn min_savings_percent(&self) -> f32 {
+        self.min_savings_percent
+    }
+
+    /// Give up on a file that takes longer than `timeout` to process
+    /// (waiting for a tool slot doesn't count). The plugin is cancelled,
+    /// which kills its external tools, and the file fails with
+    /// [`TimedOut`]. None (the default) waits indefinitely.
+    pub fn set_file_timeout(&mut self, timeout: Option<Duration>) {
+        self.file_timeout = timeout;
+    }
+
+    pub fn file_timeout(&self) -> Option<Duration> {
+        self.file_timeout
+    }
+
+    pub fn set_require_verification(&mut self, required: bool) {
+        self.require_verification = required;
+    }
+
+    pub fn require_verification(&self) -> bool {
+        self.require_verification
+    }
+
+    /// Number of files `process_batch` works on at once (at least 1).
+    /// Callers pass the config's `max_concurrent_tasks`.
+    pub fn set_max_workers(&mut self, max_workers: usize) {
+        self.max_workers = max_workers.max(1);
+    }
+
+    pub fn max_workers(&self) -> usize {
+        self.max_workers
+    }
+
+    /// How many plugins that shell out to external tools may run at once
+    /// (at least 1). The limit is shared by every caller of this manager,
+    /// including concurrent `process_file` calls.
+    pub fn set_max_external_tool_jobs(&mut self, jobs: usize) {
+        self.tool_slots.set_limit(jobs);
+    }
+
+    pub fn max_external_tool_jobs(&self) -> usize {
+        self.tool_slots.limit()
+    }
+
     /// Register a plugin
     pub fn register(&mut self, plugin: Box<dyn CompressionPlugin>) {
         self.plugins.push(plugin);
@@ -100,9 +573,16 @@ impl PluginManager {
         self.plugins.iter().map(|p| p.metadata()).collect()
     }
 
-    /// Find the best plugin for a file
+    /// Enabled plugins, in registration order
+    fn enabled_plugins(&self) -> impl Iterator<Item = &Box<dyn CompressionPlugin>> {
+        self.plugins
+            .iter()
+            .filter(|p| self.is_plugin_enabled(&p.metadata().name))
+    }
+
+    /// Find the best enabled plugin for a file
     pub fn find_plugin(&self, path: &Path) -> Result<Option<&dyn CompressionPlugin>> {
-        for plugin in &self.plugins {
+        for plugin in self.enabled_plugins() {
             let (can_handle, _reason) = plugin.can_handle(path)?;
             if can_handle {
                 return Ok(Some(plugin.as_ref()));
@@ -111,10 +591,10 @@ impl PluginManager {
         Ok(None)
     }
 
-    /// Find all plugins that can handle a file
+    /// Find all enabled plugins that can handle a file
     pub fn find_all_plugins(&self, path: &Path) -> Result<Vec<&dyn CompressionPlugin>> {
         let mut suitable_plugins = Vec::new();
-        for plugin in &self.plugins {
+        for plugin in self.enabled_plugins() {
             let (can_handle, _reason) = plugin.can_handle(path)?;
             if can_handle {
                 suitable_plugins.push(plugin.as_ref());
@@ -172,21 +652,67 @@ impl PluginManager {
             .and_then(|p| p.quality())
     }
 
+    /// Turn metadata copying on or off for every plugin that supports it.
+    /// Callers pass the config's `preserve_metadata`.
+    pub fn set_preserve_metadata(&mut self, enabled: bool) {
+        for plugin in &mut self.plugins {
+            plugin.set_preserve_metadata(enabled);
+        }
+    }
+
+    /// Apply the RAW policy to every plugin that converts RAW files
+    pub fn set_raw_policy(&mut self, policy: RawPolicy) {
+        for plugin in &mut self.plugins {
+            plugin.set_raw_policy(policy);
+        }
+    }
+
+    /// Apply minification directories and limits to every plugin that minifies
+    pub fn set_minify_options(&mut self, options: &MinifyOptions) {
+        for plugin in &mut self.plugins {
+            plugin.set_minify_options(options);
+        }
+    }
+
+    /// Probe every plugin's external tools
+    pub fn check_plugin_requirements(&self) -> Vec<PluginRequirements> {
+        self.plugins
+            .iter()
+            .map(|p| p.check_requirements())
+            .collect()
+    }
+
+    /// Configure external tool locations for every plugin. Callers pass the
+    /// config's `tool_paths`.
+    pub fn set_tool_paths(&mut self, paths: &BTreeMap<String, PathBuf>) {
+        for plugin in &mut self.plugins {
+            plugin.set_tool_paths(paths);
+        }
+    }
+
+    /// Apply resource limits to every plugin's external tools
+    pub fn set_tool_limits(&mut self, limits: ToolLimits) {
+        for plugin in &mut self.plugins {
+            plugin.set_tool_limits(limits);
+        }
+    }
+
     /// Set the quality of a plugin (0-100)
     pub fn set_plugin_quality(&mut self, plugin_name: &str, quality: f32) -> Result<()> {
         let plugin = self
             .plugins
             .iter_mut()
             .find(|p| p.metadata().name == plugin_name)
-            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_name))?;
+            .ok_or_else(|| Error::NotFound(format!("Plugin not found: {}", plugin_name)))?;
 
         if plugin.set_quality(quality.clamp(0.0, 100.0)) {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(Error::InvalidInput(format!(
                 "Plugin '{}' does not support a quality setting",
                 plugin_name
             ))
+            .into())
         }
     }
 
@@ -199,20 +725,27 @@ impl PluginManager {
     /// When `keep_backup` is false, the original is still renamed aside during
     /// processing (so a failure can never lose it), but it is deleted once the
     /// compression has fully succeeded and `backup_path` will be None.
+    ///
+    /// Plugin progress goes to `sink`. If the sink is cancelled before the
+    /// original is renamed aside, any output is removed and a [`Cancelled`]
+    /// error is returned with the source untouched.
     pub fn process_file(
         &self,
         source: &Path,
         output_dir: &Path,
         plugin_orders: Option<&[String]>,
         keep_backup: bool,
+        sink: Option<&ProgressSink>,
     ) -> Result<CompressionOutcome> {
+        let sink = sink.cloned().unwrap_or_default().for_file(source);
+        sink.check_cancelled()?;
+
         let plugin = match plugin_orders {
             Some(orders) => {
                 let mut selected = None;
                 for plugin_name in orders {
                     if let Some(plugin) = self
-                        .plugins
-                        .iter()
+                        .enabled_plugins()
                         .find(|p| &p.metadata().name == plugin_name)
                     {
                         let (can_handle, _reason) = plugin.can_handle(source)?;
@@ -231,7 +764,7 @@ impl PluginManager {
             })?,
         };
 
-        self.execute_plugin(plugin, source, output_dir, keep_backup)
+        self.execute_plugin(plugin, source, output_dir, keep_backup, &sink)
     }
 
     /// Process a file with a specific plugin by name
@@ -259,30 +792,177 @@ impl PluginManager {
             ));
         }
 
-        self.execute_plugin(plugin.as_ref(), source, output_dir, keep_backup)
+        self.execute_plugin(
+            plugin.as_ref(),
+            source,
+            output_dir,
+            keep_backup,
+            &ProgressSink::new(),
+        )
+    }
+
+    /// Compress `source` with `plugin_name` into `output_dir` only to measure
+    /// the result, then delete the output: the source is never touched, and
+    /// the result's `output_path` no longer exists. Disabled plugins, safe
+    /// mode, the tool slots and the per-file timeout apply as for
+    /// [`Self::process_file`]; the size checks do not, an output larger than
+    /// the source is reported as it is.
+    pub fn trial_compress(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        plugin_name: &str,
+        sink: &ProgressSink,
+    ) -> Result<CompressionResult> {
+        let sink = sink.for_file(source);
+        sink.check_cancelled()?;
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|p| p.metadata().name == plugin_name)
+            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_name))?;
+        let (can_handle, reason) = plugin.can_handle(source)?;
+        if !can_handle {
+            return Err(anyhow!(
+                "Plugin '{}' cannot handle file: {} (Reason: {})",
+                plugin_name,
+                source.display(),
+                reason.unwrap_or_else(|| "Unknown reason".to_string())
+            ));
+        }
+
+        let result = self.run_plugin(plugin.as_ref(), source, output_dir, &sink)?;
+        discard_output(&result, source);
+        if sink.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(result)
     }
 
     /// Run a plugin and apply the shared backup / size-check / replace logic:
     /// 1. The plugin writes its output into `output_dir` (source untouched).
     /// 2. If the output is not smaller, it is deleted and the file is skipped.
-    /// 3. Otherwise the original is renamed to `<name>.bak` (the backup), and
+    /// 3. The plugin verifies its output; broken output is deleted and the
+    ///    file fails. Unverifiable output is refused when the original would
+    ///    be deleted and verification is required.
+    /// 4. Otherwise the original is renamed to `<name>.bak` (the backup), and
     ///    if the plugin requested `replace_source`, the output takes over the
     ///    original path.
-    /// 4. With `keep_backup` false, the backup is deleted only after every
+    /// 5. With `keep_backup` false, the backup is deleted only after every
     ///    step above succeeded, so a failure can never lose the original.
+    ///
+    /// Cancellation is honoured up to step 4: once the original has been
+    /// renamed aside, the swap is finished so the file is never left half-done.
+    /// Run `work` under the per-file timeout. It gets a sink whose token
+    /// fires on the caller's cancellation or at the deadline, so plugins
+    /// that honour cancellation stop promptly; output from a plugin that
+    /// finishes past the deadline regardless is discarded.
+    fn run_with_timeout(
+        &self,
+        source: &Path,
+        sink: &ProgressSink,
+        work: impl FnOnce(&ProgressSink) -> Result<CompressionResult>,
+    ) -> Result<CompressionResult> {
+        let Some(timeout) = self.file_timeout else {
+            return work(sink);
+        };
+
+        let token = CancellationToken::new();
+        let timed_out = AtomicBool::new(false);
+        let scoped_sink = sink.clone().with_cancel(token.clone());
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+
+        let result = std::thread::scope(|scope| {
+            let (token, timed_out) = (&token, &timed_out);
+            scope.spawn(move || {
+                let deadline = Instant::now() + timeout;
+                loop {
+                    if sink.is_cancelled() {
+                        token.cancel();
+                        return;
+                    }
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        timed_out.store(true, Ordering::SeqCst);
+                        token.cancel();
+                        return;
+                    }
+                    // Wakes as soon as the work finishes (sender dropped)
+                    match done_rx.recv_timeout(remaining.min(Duration::from_millis(50))) {
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        _ => return,
+                    }
+                }
+            });
+            let result = work(&scoped_sink);
+            drop(done_tx);
+            result
+        });
+
+        if timed_out.load(Ordering::SeqCst) {
+            if let Ok(result) = &result {
+                discard_output(result, source);
+            }
+            warn!(
+                source = %source.display(),
+                timeout_secs = timeout.as_secs_f64(),
+                "Plugin timed out; file left untouched"
+            );
+            return Err(TimedOut(timeout).into());
+        }
+        result
+    }
+
+    /// Run a plugin, leaving its output in `output_dir`
+    fn run_plugin(
+        &self,
+        plugin: &dyn CompressionPlugin,
+        source: &Path,
+        output_dir: &Path,
+        sink: &ProgressSink,
+    ) -> Result<CompressionResult> {
+        let metadata = plugin.metadata();
+        if !self.is_plugin_enabled(&metadata.name) {
+            return Err(anyhow!("Plugin '{}' is disabled", metadata.name));
+        }
+        if metadata.destructive && self.safe_mode {
+            return Err(anyhow!(
+                "Plugin '{}' replaces original files and is disabled in safe mode",
+                metadata.name
+            ));
+        }
+
+        // External tools are multi-threaded themselves, so fewer of them run
+        // at once than the worker count
+        if !metadata.requires_external_tools.is_empty() {
+            let _slot = self.tool_slots.acquire(sink)?;
+            self.run_with_timeout(source, sink, |sink| {
+                plugin.process_with_progress(source, output_dir, sink)
+            })
+        } else {
+            self.run_with_timeout(source, sink, |sink| {
+                plugin.process_with_progress(source, output_dir, sink)
+            })
+        }
+    }
+
     fn execute_plugin(
         &self,
         plugin: &dyn CompressionPlugin,
         source: &Path,
         output_dir: &Path,
         keep_backup: bool,
+        sink: &ProgressSink,
     ) -> Result<CompressionOutcome> {
-        let mut result = plugin.process(source, output_dir)?;
+        let mut result = self.run_plugin(plugin, source, output_dir, sink)?;
+
+        if sink.is_cancelled() {
+            discard_output(&result, source);
+            return Err(Cancelled.into());
+        }
 
         if result.compressed_size >= result.original_size {
-            if result.output_path != source {
-                let _ = fs::remove_file(&result.output_path);
-            }
+            discard_output(&result, source);
             return Ok(CompressionOutcome::Skipped {
                 plugin_name: result.plugin_name,
                 reason: format!(
@@ -292,9 +972,42 @@ impl PluginManager {
             });
         }
 
+        let savings_percent = (result.original_size - result.compressed_size) as f64 * 100.0
+            / result.original_size as f64;
+        if savings_percent < self.min_savings_percent as f64 {
+            discard_output(&result, source);
+            return Ok(CompressionOutcome::Skipped {
+                plugin_name: result.plugin_name,
+                reason: format!(
+                    "Insufficient savings: {:.1}% is below the {}% minimum; original kept",
+                    savings_percent, self.min_savings_percent
+                ),
+            });
+        }
+
+        let verified = match plugin.verify_output(source, &result) {
+            Ok(verified) => verified,
+            Err(e) => {
+                discard_output(&result, source);
+                return Err(anyhow!(
+                    "Output verification failed for {}: {}",
+                    source.display(),
+                    e
+                ));
+            }
+        };
+        if !verified && !keep_backup && self.require_verification {
+            discard_output(&result, source);
+            return Err(anyhow!(
+                "Plugin '{}' cannot verify its output; keep a backup or turn off required verification to compress {}",
+                result.plugin_name,
+                source.display()
+            ));
+        }
+
         let backup_path = backup_path_for(source);
         if let Err(e) = fs::rename(source, &backup_path) {
-            let _ = fs::remove_file(&result.output_path);
+            discard_output(&result, source);
             return Err(anyhow!(
                 "Failed to back up original file {}: {}",
                 source.display(),
@@ -305,7 +1018,7 @@ impl PluginManager {
         if result.replace_source {
             if let Err(e) = fs::rename(&result.output_path, source) {
                 // Restore the original so the user is never left without the file
-                let _ = fs::remove_file(&result.output_path);
+                discard_output(&result, source);
                 let _ = fs::rename(&backup_path, source);
                 return Err(anyhow!(
                     "Failed to move compressed output over {}: {}",
@@ -351,6 +1064,14 @@ impl PluginManager {
 
         if let Some(plugin) = plugin {
             let metadata = plugin.metadata();
+            if !self.is_plugin_enabled(&metadata.name) {
+                let reason = "Disabled in settings".to_string();
+                return Ok(Some((metadata, false, Some(reason), None)));
+            }
+            if metadata.destructive && self.safe_mode {
+                let reason = "Disabled in safe mode (replaces original files)".to_string();
+                return Ok(Some((metadata, false, Some(reason), None)));
+            }
             let (can_handle, reason) = plugin.can_handle(path)?;
             let estimate_ratio = if can_handle {
                 plugin.estimate_ratio(path).ok().flatten()
@@ -364,22 +1085,64 @@ impl PluginManager {
         }
     }
 
-    /// Batch process multiple files
+    /// Batch process multiple files on up to `max_workers` threads, reporting
+    /// each file's plugin progress to `sink`. Results line up with `sources`
+    /// regardless of completion order; once the sink is cancelled, the files
+    /// in flight and every remaining file report a [`Cancelled`] error.
     pub fn process_batch(
         &self,
         sources: &[PathBuf],
         output_dir: &Path,
         plugin_orders: Option<&[String]>,
         keep_backup: bool,
+        sink: Option<&ProgressSink>,
     ) -> Result<Vec<Result<CompressionOutcome>>> {
         fs::create_dir_all(output_dir)?;
 
-        let results: Vec<Result<CompressionOutcome>> = sources
-            .iter()
-            .map(|source| self.process_file(source, output_dir, plugin_orders, keep_backup))
-            .collect();
+        let workers = self.max_workers.min(sources.len());
+        if workers <= 1 {
+            return Ok(sources
+                .iter()
+                .map(|source| {
+                    self.process_file(source, output_dir, plugin_orders, keep_backup, sink)
+                })
+                .collect());
+        }
+
+        // Workers pull the next index from a shared counter and send each
+        // result back as soon as it completes
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let mut results: Vec<Option<Result<CompressionOutcome>>> =
+            sources.iter().map(|_| None).collect();
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let next = &next;
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(source) = sources.get(index) else {
+                        break;
+                    };
+                    let result =
+                        self.process_file(source, output_dir, plugin_orders, keep_backup, sink);
+                    if tx.send((index, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+
+            for (index, result) in rx {
+                results[index] = Some(result);
+            }
+        });
 
-        Ok(results)
+        Ok(results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(anyhow!("Worker exited early"))))
+            .collect())
     }
 }
 
@@ -407,17 +1170,31 @@ fn backup_path_for(source: &Path) -> PathBuf {
     candidate
 }
 
+/// Remove a plugin's output and any extra files it wrote. An output that
+/// is the source itself is left alone.
+fn discard_output(result: &CompressionResult, source: &Path) {
+    if result.output_path != source {
+        let _ = fs::remove_file(&result.output_path);
+    }
+    for path in &result.extra_outputs {
+        let _ = fs::remove_file(path);
+    }
+}
+
 /// Global plugin manager instance
 static GLOBAL_PLUGIN_MANAGER: Lazy<Arc<RwLock<PluginManager>>> = Lazy::new(|| {
     let mut manager = PluginManager::new();
 
     // Register default plugins
     use crate::plugins::{
-        AnimatedWebPConverterPlugin, ImageZipToWebpZipPlugin, WebPConverterPlugin,
+        AnimatedWebPConverterPlugin, ImageZipToWebpZipPlugin, RawToJpegPlugin, TextMinifierPlugin,
+        WebPConverterPlugin,
     };
     manager.register(Box::new(ImageZipToWebpZipPlugin::new()));
     manager.register(Box::new(WebPConverterPlugin::new()));
     manager.register(Box::new(AnimatedWebPConverterPlugin::new()));
+    manager.register(Box::new(RawToJpegPlugin::new()));
+    manager.register(Box::new(TextMinifierPlugin::new()));
 
     Arc::new(RwLock::new(manager))
 });
@@ -494,6 +1271,34 @@ mod tests {
         output_content: Vec<u8>,
         replace_source: bool,
         quality: Option<f32>,
+        /// Fired while processing, to simulate a cancel arriving mid-file
+        cancel_during_process: Option<CancellationToken>,
+        /// Records how many `process` calls overlap; each call then sleeps
+        /// briefly so overlaps are observable
+        probe: Option<Arc<ConcurrencyProbe>>,
+        external_tools: bool,
+        /// What `verify_output` reports: None = cannot verify, Some(Err) =
+        /// broken output
+        verification: Option<std::result::Result<(), String>>,
+        /// Keep working this long after writing the output, stopping early
+        /// on cancellation only when `hang_checks_cancel` is set
+        hang: Option<Duration>,
+        hang_checks_cancel: bool,
+    }
+
+    #[derive(Default)]
+    struct ConcurrencyProbe {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl ConcurrencyProbe {
+        fn observe(&self) {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(30));
+            self.running.fetch_sub(1, Ordering::SeqCst);
+        }
     }
 
     impl MockPlugin {
@@ -504,6 +1309,12 @@ mod tests {
                 output_content: b"c".to_vec(),
                 replace_source: false,
                 quality: None,
+                cancel_during_process: None,
+                probe: None,
+                external_tools: false,
+                verification: Some(Ok(())),
+                hang: None,
+                hang_checks_cancel: true,
             }
         }
     }
@@ -514,6 +1325,13 @@ mod tests {
                 name: self.name.clone(),
                 description: "Mock plugin".to_string(),
                 version: "1.0.0".to_string(),
+                lossy: false,
+                destructive: self.replace_source,
+                requires_external_tools: if self.external_tools {
+                    vec!["mock-tool".to_string()]
+                } else {
+                    Vec::new()
+                },
             }
         }
 
@@ -538,6 +1356,12 @@ mod tests {
             let original_size = get_file_size(source)?;
             let output_path = output_dir.join(generate_output_filename(source, "mock"));
             fs::write(&output_path, &self.output_content)?;
+            if let Some(probe) = &self.probe {
+                probe.observe();
+            }
+            if let Some(token) = &self.cancel_during_process {
+                token.cancel();
+            }
 
             Ok(CompressionResult {
                 original_size,
@@ -547,9 +1371,33 @@ mod tests {
                 files_processed: 1,
                 backup_path: None,
                 replace_source: self.replace_source,
+                metadata_preserved: None,
+                extra_outputs: Vec::new(),
             })
         }
 
+        fn process_with_progress(
+            &self,
+            source: &Path,
+            output_dir: &Path,
+            sink: &ProgressSink,
+        ) -> Result<CompressionResult> {
+            sink.report(0, 1, None);
+            let result = self.process(source, output_dir)?;
+            if let Some(hang) = self.hang {
+                let start = std::time::Instant::now();
+                while start.elapsed() < hang {
+                    if self.hang_checks_cancel && sink.is_cancelled() {
+                        let _ = fs::remove_file(&result.output_path);
+                        return Err(Cancelled.into());
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            }
+            sink.report(1, 1, Some("done".to_string()));
+            Ok(result)
+        }
+
         fn supported_extensions(&self) -> Vec<&str> {
             self.extensions.iter().map(|s| s.as_str()).collect()
         }
@@ -566,6 +1414,14 @@ mod tests {
                 false
             }
         }
+
+        fn verify_output(&self, _source: &Path, _result: &CompressionResult) -> Result<bool> {
+            match &self.verification {
+                None => Ok(false),
+                Some(Ok(())) => Ok(true),
+                Some(Err(e)) => Err(anyhow!("{}", e)),
+            }
+        }
     }
 
     fn temp_source(dir: &Path, name: &str, content: &[u8]) -> PathBuf {
@@ -593,14 +1449,31 @@ mod tests {
         let manager = manager.read().unwrap();
         let plugins = manager.get_plugins();
 
-        // Should have all 3 default plugins
-        assert_eq!(plugins.len(), 3);
+        // Should have all 5 default plugins
+        assert_eq!(plugins.len(), 5);
 
         // Check plugin names
         let plugin_names: Vec<_> = plugins.iter().map(|p| p.name.as_str()).collect();
         assert!(plugin_names.contains(&"Image ZIP to WebP ZIP"));
         assert!(plugin_names.contains(&"WebP Converter"));
         assert!(plugin_names.contains(&"Animated WebP Converter"));
+        assert!(plugin_names.contains(&"RAW to JPEG"));
+        assert!(plugin_names.contains(&"Text Asset Minifier"));
+
+        // The ZIP and minifier plugins replace originals; the animated and RAW ones shell out
+        let by_name = |name: &str| plugins.iter().find(|p| p.name == name).unwrap();
+        assert!(by_name("Image ZIP to WebP ZIP").destructive);
+        assert!(!by_name("WebP Converter").destructive);
+        assert!(by_name("Text Asset Minifier").destructive);
+        // Minification is the only lossless conversion
+        assert!(plugins
+            .iter()
+            .all(|p| p.lossy == (p.name != "Text Asset Minifier")));
+        assert!(by_name("WebP Converter").requires_external_tools.is_empty());
+        assert!(!by_name("Animated WebP Converter")
+            .requires_external_tools
+            .is_empty());
+        assert_eq!(by_name("RAW to JPEG").requires_external_tools, ["dcraw"]);
     }
 
     #[test]
@@ -625,7 +1498,7 @@ mod tests {
         manager.register(Box::new(MockPlugin::new("Plugin1", &["txt"])));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
@@ -651,7 +1524,7 @@ mod tests {
         manager.register(Box::new(plugin));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Skipped { plugin_name, .. } => {
@@ -666,6 +1539,106 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_process_skips_when_savings_below_minimum() {
+        let dir = tempfile::tempdir().unwrap();
+        // 10 bytes -> 9 bytes: 10% savings
+        let source = temp_source(dir.path(), "file.txt", b"0123456789");
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.output_content = b"012345678".to_vec();
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_min_savings_percent(15.0);
+
+        let outcome = manager
+            .process_file(&source, dir.path(), None, false, None)
+            .unwrap();
+        match outcome {
+            CompressionOutcome::Skipped { reason, .. } => {
+                assert!(reason.starts_with("Insufficient savings"), "{reason}");
+                assert!(reason.contains("10.0%"), "{reason}");
+                assert_eq!(fs::read(&source).unwrap(), b"0123456789");
+                assert!(!dir.path().join("file.mock").exists());
+            }
+            other => panic!("expected Skipped, got {:?}", other),
+        }
+
+        // At or under the savings actually achieved, the output is kept
+        manager.set_min_savings_percent(10.0);
+        let outcome = manager
+            .process_file(&source, dir.path(), None, true, None)
+            .unwrap();
+        assert!(matches!(outcome, CompressionOutcome::Compressed(_)));
+    }
+
+    #[test]
+    fn test_file_timeout_stops_hung_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "stuck.txt", b"original content");
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.hang = Some(Duration::from_secs(10));
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_file_timeout(Some(Duration::from_millis(100)));
+
+        let start = std::time::Instant::now();
+        let err = manager
+            .process_file(&source, dir.path(), None, false, None)
+            .unwrap_err();
+        assert!(err.is::<TimedOut>(), "unexpected error: {err:#}");
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(fs::read(&source).unwrap(), b"original content");
+        assert!(!dir.path().join("stuck.mock").exists());
+    }
+
+    #[test]
+    fn test_file_timeout_discards_late_output_from_uncooperative_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "slow.txt", b"original content");
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.hang = Some(Duration::from_millis(200));
+        plugin.hang_checks_cancel = false;
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_file_timeout(Some(Duration::from_millis(20)));
+
+        let err = manager
+            .process_file(&source, dir.path(), None, true, None)
+            .unwrap_err();
+        assert!(err.is::<TimedOut>());
+        assert!(source.exists());
+        assert!(!dir.path().join("slow.mock").exists());
+
+        // A generous timeout lets the same plugin finish normally
+        manager.set_file_timeout(Some(Duration::from_secs(30)));
+        let outcome = manager
+            .process_file(&source, dir.path(), None, true, None)
+            .unwrap();
+        assert!(matches!(outcome, CompressionOutcome::Compressed(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tool_limits_apply_to_spawned_process() {
+        let limits = ToolLimits {
+            nice: Some(5),
+            max_memory_mb: None,
+            idle_io: false,
+        };
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "nice"]);
+        limits.apply(&mut cmd);
+        let output = cmd.output().unwrap();
+        let niceness: i32 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .unwrap();
+        assert!(niceness >= 5, "niceness {niceness}");
+    }
+
     #[test]
     fn test_replace_source_takes_over_original_path() {
         let dir = tempfile::tempdir().unwrap();
@@ -678,7 +1651,7 @@ mod tests {
         manager.register(Box::new(plugin));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
@@ -691,6 +1664,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trial_compress_measures_without_touching_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "archive.zip", b"original zip content");
+
+        let mut plugin = MockPlugin::new("ZipPlugin", &["zip"]);
+        plugin.replace_source = true;
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+
+        let result = manager
+            .trial_compress(&source, out.path(), "ZipPlugin", &ProgressSink::new())
+            .unwrap();
+        assert_eq!((result.original_size, result.compressed_size), (20, 1));
+        assert!(!result.output_path.exists());
+        assert_eq!(fs::read_dir(out.path()).unwrap().count(), 0);
+        assert_eq!(fs::read(&source).unwrap(), b"original zip content");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let other = temp_source(dir.path(), "notes.txt", b"text");
+        assert!(manager
+            .trial_compress(&other, out.path(), "ZipPlugin", &ProgressSink::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_safe_mode_refuses_destructive_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "archive.zip", b"original zip content");
+
+        let mut plugin = MockPlugin::new("ZipPlugin", &["zip"]);
+        plugin.replace_source = true;
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_safe_mode(true);
+
+        let (_, can_handle, reason, _) = manager
+            .check_plugin_capability(&source, "ZipPlugin")
+            .unwrap()
+            .unwrap();
+        assert!(!can_handle);
+        assert!(reason.unwrap().contains("safe mode"));
+
+        let err = manager
+            .process_file(&source, dir.path(), None, true, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("safe mode"));
+        assert_eq!(fs::read(&source).unwrap(), b"original zip content");
+        assert!(!dir.path().join("archive.zip.bak").exists());
+    }
+
+    #[test]
+    fn test_disabled_plugins_are_never_picked() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "photo.png", b"original png content");
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(MockPlugin::new("First", &["png"])));
+        manager.register(Box::new(MockPlugin::new("Second", &["png"])));
+        manager.set_disabled_plugins(["First"]);
+
+        assert_eq!(manager.get_plugins().len(), 2);
+        assert!(!manager.is_plugin_enabled("First"));
+        let picked = manager.find_plugin(&source).unwrap().unwrap();
+        assert_eq!(picked.metadata().name, "Second");
+        let (_, can_handle, reason, _) = manager
+            .check_plugin_capability(&source, "First")
+            .unwrap()
+            .unwrap();
+        assert!(!can_handle);
+        assert!(reason.unwrap().contains("Disabled"));
+
+        let err = manager
+            .process_with_plugin(&source, dir.path(), "First", true)
+            .unwrap_err();
+        assert!(err.to_string().contains("disabled"));
+        assert_eq!(fs::read(&source).unwrap(), b"original png content");
+
+        manager.set_disabled_plugins(Vec::<String>::new());
+        assert!(manager.is_plugin_enabled("First"));
+    }
+
     #[test]
     fn test_backup_does_not_overwrite_existing_backup() {
         let dir = tempfile::tempdir().unwrap();
@@ -701,7 +1758,7 @@ mod tests {
         manager.register(Box::new(MockPlugin::new("Plugin1", &["txt"])));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
@@ -725,7 +1782,7 @@ mod tests {
         manager.register(Box::new(MockPlugin::new("Plugin1", &["txt"])));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, false)
+            .process_file(&source, dir.path(), None, false, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
@@ -754,7 +1811,7 @@ mod tests {
 
         // Even with backups disabled, a skip must never touch the original
         let outcome = manager
-            .process_file(&source, dir.path(), None, false)
+            .process_file(&source, dir.path(), None, false, None)
             .unwrap();
         assert!(matches!(outcome, CompressionOutcome::Skipped { .. }));
         assert_eq!(fs::read(&source).unwrap(), b"x");
@@ -772,7 +1829,7 @@ mod tests {
         manager.register(Box::new(plugin));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, false)
+            .process_file(&source, dir.path(), None, false, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
@@ -785,6 +1842,250 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_failed_verification_keeps_original_and_removes_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "a.txt", b"original content");
+
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.verification = Some(Err("truncated output".to_string()));
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+
+        let err = manager
+            .process_file(&source, dir.path(), None, false, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("truncated output"));
+        assert_eq!(fs::read(&source).unwrap(), b"original content");
+        assert!(!dir.path().join("a.mock").exists());
+        assert!(!dir.path().join("a.txt.bak").exists());
+    }
+
+    #[test]
+    fn test_unverifiable_output_requires_backup_when_verification_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "a.txt", b"original content");
+
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.verification = None;
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+
+        // Deleting the original without verified output is refused
+        let err = manager
+            .process_file(&source, dir.path(), None, false, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot verify"));
+        assert_eq!(fs::read(&source).unwrap(), b"original content");
+        assert!(!dir.path().join("a.mock").exists());
+
+        // Keeping a backup makes the run non-destructive, so it proceeds
+        let outcome = manager
+            .process_file(&source, dir.path(), None, true, None)
+            .unwrap();
+        assert!(matches!(outcome, CompressionOutcome::Compressed(_)));
+    }
+
+    #[test]
+    fn test_unverifiable_output_allowed_when_verification_not_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "a.txt", b"original content");
+
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.verification = None;
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_require_verification(false);
+
+        let outcome = manager
+            .process_file(&source, dir.path(), None, false, None)
+            .unwrap();
+        assert!(matches!(outcome, CompressionOutcome::Compressed(_)));
+        assert!(!source.exists());
+    }
+
+    #[test]
+    fn test_cancelled_before_start_leaves_source_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "test.txt", b"original content");
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(MockPlugin::new("Plugin1", &["txt"])));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let sink = ProgressSink::new().with_cancel(cancel);
+        let err = manager
+            .process_file(&source, dir.path(), None, true, Some(&sink))
+            .unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert_eq!(fs::read(&source).unwrap(), b"original content");
+        assert!(!dir.path().join("test.mock").exists());
+    }
+
+    #[test]
+    fn test_cancel_during_process_removes_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = temp_source(dir.path(), "test.txt", b"original content");
+
+        let cancel = CancellationToken::new();
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.cancel_during_process = Some(cancel.clone());
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+
+        let sink = ProgressSink::new().with_cancel(cancel);
+        let err = manager
+            .process_file(&source, dir.path(), None, true, Some(&sink))
+            .unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert_eq!(fs::read(&source).unwrap(), b"original content");
+        assert!(
+            !dir.path().join("test.mock").exists(),
+            "partial output must be removed"
+        );
+        assert!(!dir.path().join("test.txt.bak").exists(), "no backup made");
+    }
+
+    #[test]
+    fn test_batch_reports_remaining_files_as_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = temp_source(dir.path(), "a.txt", b"original content");
+        let second = temp_source(dir.path(), "b.txt", b"original content");
+
+        let cancel = CancellationToken::new();
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.cancel_during_process = Some(cancel.clone());
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+
+        let results = manager
+            .process_batch(
+                &[first.clone(), second.clone()],
+                dir.path(),
+                None,
+                true,
+                Some(&ProgressSink::new().with_cancel(cancel)),
+            )
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        for result in &results {
+            assert!(result.as_ref().unwrap_err().is::<Cancelled>());
+        }
+        assert!(first.exists() && second.exists());
+    }
+
+    #[test]
+    fn test_batch_forwards_plugin_progress_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = temp_source(dir.path(), "a.txt", b"original content");
+        let second = temp_source(dir.path(), "b.txt", b"original content");
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(MockPlugin::new("Plugin1", &["txt"])));
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collected = Arc::clone(&reports);
+        let sink = ProgressSink::new().on_progress(move |p| {
+            collected.lock().unwrap().push(p.clone());
+        });
+
+        manager
+            .process_batch(
+                &[first.clone(), second.clone()],
+                dir.path(),
+                None,
+                true,
+                Some(&sink),
+            )
+            .unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 4);
+        // Files run in parallel, so reports interleave; each one is stamped
+        // with the file the plugin was working on
+        for source in [&first, &second] {
+            let for_file: Vec<_> = reports.iter().filter(|p| &p.path == source).collect();
+            assert_eq!(for_file.len(), 2);
+            assert_eq!((for_file[0].current, for_file[0].total), (0, 1));
+            assert_eq!((for_file[1].current, for_file[1].total), (1, 1));
+            assert_eq!(for_file[1].message.as_deref(), Some("done"));
+        }
+    }
+
+    fn probed_sources(dir: &Path, count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|i| temp_source(dir, &format!("{}.txt", i), b"original content"))
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_runs_up_to_max_workers_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = probed_sources(dir.path(), 8);
+
+        let probe = Arc::new(ConcurrencyProbe::default());
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.probe = Some(Arc::clone(&probe));
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_max_workers(3);
+
+        let results = manager
+            .process_batch(&sources, dir.path(), None, true, None)
+            .unwrap();
+
+        let peak = probe.peak.load(Ordering::SeqCst);
+        assert!(peak > 1, "files should overlap, peak was {}", peak);
+        assert!(peak <= 3, "at most 3 workers, peak was {}", peak);
+        // Results line up with sources even though they completed out of order
+        for (source, result) in sources.iter().zip(&results) {
+            match result.as_ref().unwrap() {
+                CompressionOutcome::Compressed(r) => {
+                    let expected = dir.path().join(generate_output_filename(source, "mock"));
+                    assert_eq!(r.output_path, expected);
+                }
+                other => panic!("expected Compressed, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_external_tool_plugins_are_limited_separately() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = probed_sources(dir.path(), 4);
+
+        let probe = Arc::new(ConcurrencyProbe::default());
+        let mut plugin = MockPlugin::new("Plugin1", &["txt"]);
+        plugin.probe = Some(Arc::clone(&probe));
+        plugin.external_tools = true;
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin));
+        manager.set_max_workers(4);
+        manager.set_max_external_tool_jobs(1);
+
+        let results = manager
+            .process_batch(&sources, dir.path(), None, true, None)
+            .unwrap();
+
+        assert_eq!(probe.peak.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn test_worker_limits_are_at_least_one() {
+        let mut manager = PluginManager::new();
+        assert_eq!(manager.max_workers(), DEFAULT_MAX_WORKERS);
+        manager.set_max_workers(0);
+        manager.set_max_external_tool_jobs(0);
+        assert_eq!(manager.max_workers(), 1);
+        assert_eq!(manager.max_external_tool_jobs(), 1);
+    }
+
     #[test]
     fn test_plugin_orders() {
         let dir = tempfile::tempdir().unwrap();
@@ -796,7 +2097,7 @@ mod tests {
         // Without plugin_orders, should use first registered plugin
         let source = temp_source(dir.path(), "a.txt", b"original content");
         match manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap()
         {
             CompressionOutcome::Compressed(result) => assert_eq!(result.plugin_name, "Plugin1"),
@@ -807,7 +2108,7 @@ mod tests {
         let source = temp_source(dir.path(), "b.txt", b"original content");
         let orders = vec!["Plugin2".to_string()];
         match manager
-            .process_file(&source, dir.path(), Some(&orders), true)
+            .process_file(&source, dir.path(), Some(&orders), true, None)
             .unwrap()
         {
             CompressionOutcome::Compressed(result) => assert_eq!(result.plugin_name, "Plugin2"),
@@ -826,7 +2127,7 @@ mod tests {
         // Plugin1 could handle the file, but it is not in the orders list,
         // so it must NOT be used (the user deactivated it)
         let orders = vec!["Nonexistent Plugin".to_string()];
-        let result = manager.process_file(&source, dir.path(), Some(&orders), true);
+        let result = manager.process_file(&source, dir.path(), Some(&orders), true, None);
         assert!(result.is_err());
         assert!(source.exists(), "source must be untouched");
     }
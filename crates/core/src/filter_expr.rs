@@ -0,0 +1,496 @@
+//! A small query language for file filters, e.g.
+//! `size>100MB and ext in (mp4,mkv) and modified<2023-01-01`.
+//!
+//! ```text
+//! expr       := and ("or" and)*
+//! and        := unary ("and" unary)*
+//! unary      := "not" unary | "(" expr ")" | comparison
+//! comparison := field op value | field "in" "(" value ("," value)* ")"
+//! ```
+//!
+//! | field      | operators                  | value                                   |
+//! |------------|----------------------------|-----------------------------------------|
+//! | `size`     | `< <= > >= = !=`           | bytes with optional unit (`1.5GB`)      |
+//! | `ext`      | `= !=`                     | extension, case-insensitive (`mp4`)     |
+//! | `name`     | `= !=` glob, `~` regex     | `IMG_*.jpg`, `'^IMG_\d+'`               |
+//! | `path`     | `= !=`                     | full-path glob (`**/Downloads/**`)      |
+//! | `modified` | `< <= > >=`                | date or duration ago (`2023-01-01`, `30d`) |
+//! | `tag`      | `= !=`                     | tag set by the user (`keep`)            |
+//!
+//! `in (a, b)` is shorthand for `= a or = b`, and `tag:keep` for `tag = keep`.
+//! Tags are case-insensitive and only available where the caller supplies
+//! them (see [`parse_with_tags`]). Keywords are case-insensitive;
+//! values containing spaces, parentheses, commas or operator characters must
+//! be quoted with `'` or `"`. `modified<30d` reads "modified before 30 days
+//! ago", i.e. older than 30 days.
+
+use crate::filters::{
+    AndFilter, ExtensionFilter, Filter, GlobFilter, MaxSizeFilter, MinSizeFilter, NewerThanFilter,
+    NotFilter, OlderThanFilter, OrFilter, RegexFilter, TagIndex, TaggedFilter,
+};
+use anyhow::{bail, Context, Result};
+use space_saver_utils::time::parse_time_bound;
+
+type BoxedFilter = Box<dyn Filter + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Comma,
+    Op(&'static str),
+    /// A value or keyword; `quoted` values are never keywords
+    Word {
+        text: String,
+        quoted: bool,
+    },
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word { text, quoted: false } if text.eq_ignore_ascii_case(keyword))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Op(op) => format!("'{}'", op),
+            Token::Word { text, .. } => format!("'{}'", text),
+        }
+    }
+}
+
+const OPERATORS: [&str; 8] = ["<=", ">=", "!=", "==", "<", ">", "=", "~"];
+
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let consumed = match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                1
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                1
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                1
+            }
+            '\'' | '"' => {
+                let end = rest[1..].find(c).context("Unterminated quote")?;
+                tokens.push(Token::Word {
+                    text: rest[1..=end].to_string(),
+                    quoted: true,
+                });
+                end + 2
+            }
+            _ => {
+                if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+                    tokens.push(Token::Op(if *op == "==" { "=" } else { op }));
+                    op.len()
+                } else {
+                    let end = rest
+                        .find(|c: char| c.is_whitespace() || "(),<>=!~'\"".contains(c))
+                        .unwrap_or(rest.len());
+                    if end == 0 {
+                        bail!("Unexpected '{}'", c);
+                    }
+                    tokens.push(Token::Word {
+                        text: rest[..end].to_string(),
+                        quoted: false,
+                    });
+                    end
+                }
+            }
+        };
+        rest = rest[consumed..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    now: i64,
+    tags: Option<&'a TagIndex>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .context("Unexpected end of filter")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|t| t.is_keyword(keyword));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        let token = self
+            .next()
+            .with_context(|| format!("Expected {}", expected.describe()))?;
+        if token != expected {
+            bail!(
+                "Expected {} but found {}",
+                expected.describe(),
+                token.describe()
+            );
+        }
+        Ok(())
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Word { text, .. } => Ok(text),
+            token => bail!("Expected a value but found {}", token.describe()),
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<BoxedFilter> {
+        let first = self.and_expr()?;
+        if !self.peek().is_some_and(|t| t.is_keyword("or")) {
+            return Ok(first);
+        }
+        let mut or = OrFilter::new().with_filter(first);
+        while self.eat_keyword("or") {
+            or = or.with_filter(self.and_expr()?);
+        }
+        Ok(Box::new(or))
+    }
+
+    fn and_expr(&mut self) -> Result<BoxedFilter> {
+        let first = self.unary()?;
+        if !self.peek().is_some_and(|t| t.is_keyword("and")) {
+            return Ok(first);
+        }
+        let mut and = AndFilter::new().with_filter(first);
+        while self.eat_keyword("and") {
+            and = and.with_filter(self.unary()?);
+        }
+        Ok(Box::new(and))
+    }
+
+    fn unary(&mut self) -> Result<BoxedFilter> {
+        if self.eat_keyword("not") {
+            return Ok(Box::new(NotFilter::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let inner = self.or_expr()?;
+            self.expect(Token::RParen)?;
+            return Ok(inner);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<BoxedFilter> {
+        let field = self.word()?;
+        if let Some(tag) = strip_prefix_ignore_case(&field, "tag:") {
+            // `tag:'review later'` comes in as `tag:` and the quoted value
+            let tag = if tag.is_empty() {
+                self.word()?
+            } else {
+                tag.to_string()
+            };
+            return self.condition("tag", "=", &tag);
+        }
+        let field = field.to_ascii_lowercase();
+        if self.eat_keyword("in") {
+            self.expect(Token::LParen)?;
+            let mut any = OrFilter::new();
+            loop {
+                let value = self.word()?;
+                any = any.with_filter(self.condition(&field, "=", &value)?);
+                match self.next()? {
+                    Token::Comma => continue,
+                    Token::RParen => break,
+                    token => bail!("Expected ',' or ')' but found {}", token.describe()),
+                }
+            }
+            return Ok(Box::new(any));
+        }
+        let op = match self.next()? {
+            Token::Op(op) => op,
+            token => bail!(
+                "Expected an operator after '{}' but found {}",
+                field,
+                token.describe()
+            ),
+        };
+        let value = self.word()?;
+        self.condition(&field, op, &value)
+    }
+
+    fn condition(&self, field: &str, op: &str, value: &str) -> Result<BoxedFilter> {
+        let unsupported = || anyhow::anyhow!("Operator '{}' is not supported for '{}'", op, field);
+        let filter: BoxedFilter = match (field, op) {
+            ("size", _) => {
+                let size = parse_size(value)?;
+                match op {
+                    ">=" => Box::new(MinSizeFilter::new(size)),
+                    ">" => Box::new(MinSizeFilter::new(size.saturating_add(1))),
+                    "<=" => Box::new(MaxSizeFilter::new(size)),
+                    "<" if size == 0 => Box::new(NotFilter::new(Box::new(MinSizeFilter::new(0)))),
+                    "<" => Box::new(MaxSizeFilter::new(size - 1)),
+                    "=" | "!=" => Box::new(
+                        AndFilter::new()
+                            .with_filter(Box::new(MinSizeFilter::new(size)))
+                            .with_filter(Box::new(MaxSizeFilter::new(size))),
+                    ),
+                    _ => return Err(unsupported()),
+                }
+            }
+            ("modified", "<" | "<=" | ">" | ">=") => {
+                let cutoff = parse_time_bound(value, self.now)?;
+                match op {
+                    ">=" => Box::new(NewerThanFilter::new(cutoff)),
+                    ">" => Box::new(NewerThanFilter::new(cutoff.saturating_add(1))),
+                    "<" => Box::new(OlderThanFilter::new(cutoff)),
+                    _ => Box::new(OlderThanFilter::new(cutoff.saturating_add(1))),
+                }
+            }
+            ("ext", "=" | "!=") => {
+                let ext = value.trim_start_matches('.').to_string();
+                Box::new(ExtensionFilter::new(vec![ext]))
+            }
+            ("name" | "path", "=" | "!=") => {
+                if field == "path" && !value.contains('/') {
+                    bail!("Path globs must contain '/', e.g. '**/{}/**'", value);
+                }
+                Box::new(GlobFilter::new(&[value.to_string()], &[])?)
+            }
+            ("name", "~") => Box::new(RegexFilter::new(value)?),
+            ("tag", "=" | "!=") => {
+                let tags = self
+                    .tags
+                    .context("Tag filters need the app database, which is not available here")?;
+                let paths = tags
+                    .get(&value.trim().to_lowercase())
+                    .cloned()
+                    .unwrap_or_default();
+                Box::new(TaggedFilter::new(paths))
+            }
+            ("modified" | "ext" | "name" | "path" | "tag", _) => return Err(unsupported()),
+            _ => bail!(
+                "Unknown filter field '{}' (expected size, ext, name, path, modified or tag)",
+                field
+            ),
+        };
+        Ok(if op == "!=" {
+            Box::new(NotFilter::new(filter))
+        } else {
+            filter
+        })
+    }
+}
+
+/// Parses `expr` into a filter tree. Relative times such as `30d` are
+/// measured back from `now` (Unix seconds).
+pub(crate) fn parse(expr: &str, now: i64) -> Result<BoxedFilter> {
+    parse_tokens(expr, now, None).with_context(|| format!("Invalid filter expression: {}", expr))
+}
+
+/// Like [`parse`], answering `tag` conditions from `tags`
+pub(crate) fn parse_with_tags(expr: &str, now: i64, tags: &TagIndex) -> Result<BoxedFilter> {
+    parse_tokens(expr, now, Some(tags))
+        .with_context(|| format!("Invalid filter expression: {}", expr))
+}
+
+fn parse_tokens(expr: &str, now: i64, tags: Option<&TagIndex>) -> Result<BoxedFilter> {
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        bail!("Filter expression is empty");
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        now,
+        tags,
+    };
+    let filter = parser.or_expr()?;
+    if let Some(token) = parser.peek() {
+        bail!("Unexpected {} after a complete filter", token.describe());
+    }
+    Ok(filter)
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+/// Parses a size such as `512`, `100MB` or `1.5 GiB` (binary units, so
+/// `1KB` is 1024 bytes, matching the rest of the app)
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: f64 = amount
+        .parse()
+        .with_context(|| format!("Invalid size: {}", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => bail!(
+            "Invalid size unit in {} (expected B, KB, MB, GB or TB)",
+            value
+        ),
+    };
+    Ok((amount * multiplier as f64).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{FileInfo, FileType};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 86_400;
+
+    fn file(path: &str, size: u64, modified: i64) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path),
+            size,
+            modified,
+            file_type: FileType::Other,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    fn matches(expr: &str, file: &FileInfo) -> bool {
+        parse(expr, NOW).unwrap().apply(file)
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("100MB").unwrap(), 100 * 1024 * 1024);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+
+    #[test]
+    fn test_example_query() {
+        let expr = "size>100MB and ext in (mp4,mkv) and modified<2023-01-01";
+        let old_movie = file("/v/movie.MKV", 200 << 20, 1_600_000_000);
+        assert!(matches(expr, &old_movie));
+        // Each clause can reject on its own
+        assert!(!matches(
+            expr,
+            &file("/v/movie.mkv", 50 << 20, 1_600_000_000)
+        ));
+        assert!(!matches(
+            expr,
+            &file("/v/movie.avi", 200 << 20, 1_600_000_000)
+        ));
+        assert!(!matches(expr, &file("/v/movie.mkv", 200 << 20, NOW)));
+    }
+
+    #[test]
+    fn test_precedence_not_and_grouping() {
+        let iso = file("/d/disk.iso", 10, 0);
+        let log = file("/d/app.log", 10_000, 0);
+        let txt = file("/d/readme.txt", 10, 0);
+
+        // `and` binds tighter than `or`
+        let expr = "ext=iso or ext=log and size>1KB";
+        assert!(matches(expr, &iso));
+        assert!(matches(expr, &log));
+        assert!(!matches(expr, &txt));
+
+        let grouped = "(ext=iso or ext=log) and size>1KB";
+        assert!(!matches(grouped, &iso));
+        assert!(matches(grouped, &log));
+
+        assert!(matches("NOT ext = log", &txt));
+        assert!(!matches("not (ext=txt or ext=iso)", &iso));
+        assert!(matches("ext != .log", &iso));
+    }
+
+    #[test]
+    fn test_name_path_and_relative_dates() {
+        let recent = file("/home/u/Downloads/IMG_0042.jpg", 10, NOW - 2 * DAY);
+        assert!(matches("name = 'IMG_*.jpg'", &recent));
+        assert!(matches(r"name ~ '^IMG_\d{4}\.jpe?g$'", &recent));
+        assert!(matches("path = **/Downloads/**", &recent));
+        assert!(matches("modified > 7d", &recent));
+        assert!(!matches("modified < 7d", &recent));
+        assert!(matches(
+            "size = 10 and size <= 10B and not size < 10",
+            &recent
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |expr: &str| format!("{:#}", parse(expr, NOW).err().unwrap());
+
+        assert!(err("owner = me").contains("Unknown filter field 'owner'"));
+        assert!(err("ext > mp4").contains("Operator '>' is not supported for 'ext'"));
+        assert!(err("size > 10 and").contains("Unexpected end of filter"));
+        assert!(err("(ext = mp4").contains("Expected ')'"));
+        assert!(err("ext = mp4 ext = mkv").contains("after a complete filter"));
+        assert!(err("name = 'abc").contains("Unterminated quote"));
+        assert!(err("modified < someday").contains("Invalid date or duration"));
+        assert!(err("").contains("empty"));
+        assert!(err("size >").starts_with("Invalid filter expression: size >"));
+        assert!(err("tag:keep").contains("Tag filters need the app database"));
+    }
+
+    #[test]
+    fn test_tags() {
+        let tags = TagIndex::from([
+            (
+                "keep".to_string(),
+                HashSet::from([PathBuf::from("/p/a.jpg"), PathBuf::from("/p/b.jpg")]),
+            ),
+            (
+                "review later".to_string(),
+                HashSet::from([PathBuf::from("/p/b.jpg")]),
+            ),
+        ]);
+        let matches = |expr: &str, path: &str| {
+            parse_with_tags(expr, NOW, &tags)
+                .unwrap()
+                .apply(&file(path, 10, 0))
+        };
+
+        assert!(matches("tag:keep", "/p/a.jpg"));
+        assert!(matches("TAG:Keep", "/p/a.jpg"));
+        assert!(!matches("tag:keep", "/p/c.jpg"));
+        assert!(matches("tag = keep and not tag:'review later'", "/p/a.jpg"));
+        assert!(!matches("tag:keep and tag != 'review later'", "/p/b.jpg"));
+        assert!(matches("tag in (unknown, keep)", "/p/b.jpg"));
+        assert!(!matches("tag:client-x", "/p/a.jpg"));
+    }
+}
@@ -1,5 +1,8 @@
 use crate::scanner::FileInfo;
-use std::collections::HashSet;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 /// File filter trait
@@ -86,6 +89,147 @@ impl Filter for PatternFilter {
     }
 }
 
+/// Keeps files modified at or after `cutoff` (Unix seconds)
+pub struct NewerThanFilter {
+    cutoff: i64,
+}
+
+impl NewerThanFilter {
+    pub fn new(cutoff: i64) -> Self {
+        Self { cutoff }
+    }
+}
+
+impl Filter for NewerThanFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        file.modified >= self.cutoff
+    }
+}
+
+/// Keeps files last modified before `cutoff` (Unix seconds)
+pub struct OlderThanFilter {
+    cutoff: i64,
+}
+
+impl OlderThanFilter {
+    pub fn new(cutoff: i64) -> Self {
+        Self { cutoff }
+    }
+}
+
+impl Filter for OlderThanFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        file.modified < self.cutoff
+    }
+}
+
+/// Filter by a regular expression matched against the file name, e.g.
+/// `^IMG_\d{4}\.(jpg|jpeg)$`. Unanchored patterns match anywhere in the name.
+pub struct RegexFilter {
+    regex: Regex,
+}
+
+impl RegexFilter {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex =
+            Regex::new(pattern).with_context(|| format!("Invalid file name regex: {}", pattern))?;
+        Ok(Self { regex })
+    }
+}
+
+impl Filter for RegexFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        if let Some(name) = file.path.file_name() {
+            self.regex.is_match(&name.to_string_lossy())
+        } else {
+            false
+        }
+    }
+}
+
+/// Filter by include/exclude glob lists matched against the full path, e.g.
+/// `**/Downloads/**` or `!**/*.raw`.
+///
+/// A file is kept when it matches at least one include pattern (or there are
+/// none) and no exclude pattern. Include entries starting with `!` are
+/// treated as excludes. `*` stops at path separators while `**` crosses
+/// them, and a pattern without any `/` is matched against the file name
+/// alone, so `*.raw` behaves like `**/*.raw`.
+pub struct GlobFilter {
+    include: Option<GlobMatcher>,
+    exclude: Option<GlobMatcher>,
+}
+
+/// Path patterns and file name patterns compiled into separate sets
+struct GlobMatcher {
+    paths: GlobSet,
+    names: GlobSet,
+}
+
+impl GlobMatcher {
+    fn new(patterns: &[&str]) -> Result<Option<Self>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        let mut paths = GlobSetBuilder::new();
+        let mut names = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Some(Self {
+            paths: paths.build()?,
+            names: names.build()?,
+        }))
+    }
+
+    fn is_match(&self, file: &FileInfo) -> bool {
+        self.paths.is_match(&file.path)
+            || file
+                .path
+                .file_name()
+                .is_some_and(|name| self.names.is_match(name))
+    }
+}
+
+impl GlobFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let mut includes = Vec::new();
+        let mut excludes = Vec::new();
+        for pattern in include.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match pattern.strip_prefix('!') {
+                Some(negated) => excludes.push(negated),
+                None => includes.push(pattern),
+            }
+        }
+        excludes.extend(
+            exclude
+                .iter()
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+                .map(|p| p.strip_prefix('!').unwrap_or(p)),
+        );
+        Ok(Self {
+            include: GlobMatcher::new(&includes)?,
+            exclude: GlobMatcher::new(&excludes)?,
+        })
+    }
+}
+
+impl Filter for GlobFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        self.include.as_ref().is_none_or(|m| m.is_match(file))
+            && !self.exclude.as_ref().is_some_and(|m| m.is_match(file))
+    }
+}
+
 /// Filter that excludes files located under any of the given paths.
 ///
 /// A file is excluded (dropped) when its path equals, or is nested beneath,
@@ -118,6 +262,32 @@ impl Filter for ExcludePathsFilter {
     }
 }
 
+/// Absolute paths of the files carrying each tag, for `tag` in filter
+/// expressions
+pub type TagIndex = HashMap<String, HashSet<PathBuf>>;
+
+/// Keeps the files among `paths` (absolute); relative file paths are made
+/// absolute before the lookup
+pub struct TaggedFilter {
+    paths: HashSet<PathBuf>,
+}
+
+impl TaggedFilter {
+    pub fn new(paths: HashSet<PathBuf>) -> Self {
+        Self { paths }
+    }
+}
+
+impl Filter for TaggedFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        if file.path.is_absolute() {
+            self.paths.contains(&file.path)
+        } else {
+            std::path::absolute(&file.path).is_ok_and(|path| self.paths.contains(&path))
+        }
+    }
+}
+
 /// Filter to detect empty files
 pub struct EmptyFileFilter;
 
@@ -127,17 +297,74 @@ impl Filter for EmptyFileFilter {
     }
 }
 
-/// Filter to detect hidden files (Unix-style)
+/// Filter to detect hidden files: dot-files, plus files with the hidden or
+/// system attribute on Windows
 pub struct HiddenFileFilter;
 
 impl Filter for HiddenFileFilter {
     fn apply(&self, file: &FileInfo) -> bool {
-        if let Some(name) = file.path.file_name() {
-            name.to_string_lossy().starts_with('.')
-        } else {
-            false
+        crate::scanner::is_hidden(&file.path)
+    }
+}
+
+/// Keeps files owned by a user and/or group (Unix). Files without ownership
+/// information never match, so an owner filter cannot widen a deletion.
+pub struct OwnerFilter {
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+impl OwnerFilter {
+    pub fn new(uid: Option<u32>, gid: Option<u32>) -> Self {
+        Self { uid, gid }
+    }
+
+    /// Files owned by the user running this process; `None` off Unix
+    pub fn current_user() -> Option<Self> {
+        crate::scanner::current_uid().map(|uid| Self::new(Some(uid), None))
+    }
+}
+
+impl Filter for OwnerFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        let matches = |wanted: Option<u32>, actual: Option<u32>| {
+            wanted.is_none_or(|wanted| actual == Some(wanted))
+        };
+        file.uid.is_some() && matches(self.uid, file.uid) && matches(self.gid, file.gid)
+    }
+}
+
+/// Keeps files whose permission bits under `mask` equal `expected` (Unix),
+/// e.g. `PermissionFilter::all(0o002)` for world-writable files
+pub struct PermissionFilter {
+    mask: u32,
+    expected: u32,
+}
+
+impl PermissionFilter {
+    pub fn new(mask: u32, expected: u32) -> Self {
+        Self {
+            mask,
+            expected: expected & mask,
         }
     }
+
+    /// Files with every bit of `bits` set
+    pub fn all(bits: u32) -> Self {
+        Self::new(bits, bits)
+    }
+
+    /// Files with none of `bits` set
+    pub fn none(bits: u32) -> Self {
+        Self::new(bits, 0)
+    }
+}
+
+impl Filter for PermissionFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        file.mode
+            .is_some_and(|mode| mode & self.mask == self.expected)
+    }
 }
 
 /// Composite filter that combines multiple filters with AND logic
@@ -200,6 +427,23 @@ impl Filter for OrFilter {
     }
 }
 
+/// Inverts a filter: keeps exactly the files it would drop
+pub struct NotFilter {
+    filter: Box<dyn Filter + Send + Sync>,
+}
+
+impl NotFilter {
+    pub fn new(filter: Box<dyn Filter + Send + Sync>) -> Self {
+        Self { filter }
+    }
+}
+
+impl Filter for NotFilter {
+    fn apply(&self, file: &FileInfo) -> bool {
+        !self.filter.apply(file)
+    }
+}
+
 /// Main file filter interface
 pub struct FileFilter {
     filter: Box<dyn Filter + Send + Sync>,
@@ -235,6 +479,69 @@ impl FileFilter {
         Self::new(Box::new(PatternFilter::new(pattern)))
     }
 
+    pub fn newer_than(cutoff: i64) -> Self {
+        Self::new(Box::new(NewerThanFilter::new(cutoff)))
+    }
+
+    pub fn older_than(cutoff: i64) -> Self {
+        Self::new(Box::new(OlderThanFilter::new(cutoff)))
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self> {
+        Ok(Self::new(Box::new(RegexFilter::new(pattern)?)))
+    }
+
+    pub fn globs(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self::new(Box::new(GlobFilter::new(include, exclude)?)))
+    }
+
+    /// Drops files with any of `exts`
+    pub fn exclude_extensions(exts: Vec<String>) -> Self {
+        Self::new(Box::new(NotFilter::new(Box::new(ExtensionFilter::new(
+            exts,
+        )))))
+    }
+
+    /// Drops files whose name contains any of `patterns`
+    pub fn exclude_patterns(patterns: Vec<String>) -> Self {
+        let any = patterns
+            .into_iter()
+            .filter(|p| !p.is_empty())
+            .fold(OrFilter::new(), |or, p| {
+                or.with_filter(Box::new(PatternFilter::new(p)))
+            });
+        Self::new(Box::new(NotFilter::new(Box::new(any))))
+    }
+
+    /// Parses a filter expression such as
+    /// `size>100MB and ext in (mp4,mkv) and modified<2023-01-01`. Fields are
+    /// `size`, `ext`, `name`, `path` and `modified`, combined with `and`,
+    /// `or`, `not` and parentheses
+    pub fn parse(expr: &str) -> Result<Self> {
+        Ok(Self::new(crate::filter_expr::parse(
+            expr,
+            space_saver_utils::time::now(),
+        )?))
+    }
+
+    /// Like [`Self::parse`], also accepting `tag` conditions such as
+    /// `tag:keep`, answered from `tags`
+    pub fn parse_with_tags(expr: &str, tags: &TagIndex) -> Result<Self> {
+        Ok(Self::new(crate::filter_expr::parse_with_tags(
+            expr,
+            space_saver_utils::time::now(),
+            tags,
+        )?))
+    }
+
+    pub fn owner(uid: Option<u32>, gid: Option<u32>) -> Self {
+        Self::new(Box::new(OwnerFilter::new(uid, gid)))
+    }
+
+    pub fn permissions(mask: u32, expected: u32) -> Self {
+        Self::new(Box::new(PermissionFilter::new(mask, expected)))
+    }
+
     pub fn exclude_paths(paths: Vec<String>) -> Self {
         Self::new(Box::new(ExcludePathsFilter::new(paths)))
     }
@@ -261,6 +568,9 @@ mod tests {
             modified: 0,
             file_type: FileType::Other,
             hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
         }
     }
 
@@ -304,6 +614,96 @@ mod tests {
         assert!(!filter.apply(&file2));
     }
 
+    #[test]
+    fn test_modified_time_filters() {
+        let mut old = create_test_file("old.txt", 100);
+        old.modified = 1_000;
+        let mut new = create_test_file("new.txt", 100);
+        new.modified = 2_000;
+
+        let newer = NewerThanFilter::new(2_000);
+        assert!(!newer.apply(&old));
+        assert!(newer.apply(&new));
+
+        let older = OlderThanFilter::new(2_000);
+        assert!(older.apply(&old));
+        assert!(!older.apply(&new));
+    }
+
+    #[test]
+    fn test_regex_filter() {
+        let filter = RegexFilter::new(r"^IMG_\d{4}\.(jpg|jpeg)$").unwrap();
+
+        assert!(filter.apply(&create_test_file("/photos/IMG_0042.jpg", 100)));
+        assert!(filter.apply(&create_test_file("/photos/IMG_2024.jpeg", 100)));
+        // Only the file name is matched, not the directories above it
+        assert!(!filter.apply(&create_test_file("/IMG_0001.jpg/notes.txt", 100)));
+        assert!(!filter.apply(&create_test_file("/photos/IMG_42.jpg", 100)));
+        assert!(!filter.apply(&create_test_file("/photos/IMG_0042.jpg.bak", 100)));
+
+        let err = FileFilter::regex("(unclosed").err().unwrap();
+        assert!(err.to_string().contains("Invalid file name regex"));
+    }
+
+    #[test]
+    fn test_glob_filter() {
+        let filter = GlobFilter::new(
+            &["**/Downloads/**".to_string(), "!**/*.raw".to_string()],
+            &["*.part".to_string()],
+        )
+        .unwrap();
+
+        assert!(filter.apply(&create_test_file("/home/u/Downloads/a.zip", 1)));
+        assert!(filter.apply(&create_test_file("/home/u/Downloads/x/y/a.zip", 1)));
+        assert!(!filter.apply(&create_test_file("/home/u/Documents/a.zip", 1)));
+        // `!` entries and the exclude list both drop matches
+        assert!(!filter.apply(&create_test_file("/home/u/Downloads/shot.raw", 1)));
+        assert!(!filter.apply(&create_test_file("/home/u/Downloads/iso.part", 1)));
+
+        // `*` does not cross directories, `**` does
+        let shallow = GlobFilter::new(&["/data/*.log".to_string()], &[]).unwrap();
+        assert!(shallow.apply(&create_test_file("/data/app.log", 1)));
+        assert!(!shallow.apply(&create_test_file("/data/old/app.log", 1)));
+
+        // Exclude-only keeps everything else
+        let exclude_only = GlobFilter::new(&[], &["**/node_modules/**".to_string()]).unwrap();
+        assert!(exclude_only.apply(&create_test_file("/src/main.rs", 1)));
+        assert!(!exclude_only.apply(&create_test_file("/src/node_modules/a/b.js", 1)));
+
+        let err = FileFilter::globs(&["a[".to_string()], &[]).err().unwrap();
+        assert!(err.to_string().contains("Invalid glob pattern"));
+    }
+
+    #[test]
+    fn test_owner_and_permission_filters() {
+        let mut mine = create_test_file("/srv/mine.log", 1);
+        mine.uid = Some(1000);
+        mine.gid = Some(100);
+        mine.mode = Some(0o644);
+        let mut shared = create_test_file("/srv/shared.log", 1);
+        shared.uid = Some(1001);
+        shared.gid = Some(100);
+        shared.mode = Some(0o666);
+        // No ownership information, as on Windows
+        let unknown = create_test_file("/srv/unknown.log", 1);
+
+        let user = OwnerFilter::new(Some(1000), None);
+        assert!(user.apply(&mine));
+        assert!(!user.apply(&shared));
+        assert!(!user.apply(&unknown));
+
+        let group = OwnerFilter::new(None, Some(100));
+        assert!(group.apply(&mine));
+        assert!(group.apply(&shared));
+        assert!(!group.apply(&unknown));
+
+        let world_writable = PermissionFilter::all(0o002);
+        assert!(!world_writable.apply(&mine));
+        assert!(world_writable.apply(&shared));
+        assert!(!world_writable.apply(&unknown));
+        assert!(PermissionFilter::none(0o022).apply(&mine));
+    }
+
     #[test]
     fn test_empty_file_filter() {
         let filter = EmptyFileFilter;
@@ -375,4 +775,42 @@ mod tests {
         assert!(filter.apply(&file2)); // Just right
         assert!(!filter.apply(&file3)); // Too large
     }
+
+    #[test]
+    fn test_exclude_extension_and_pattern_helpers() {
+        let files = || {
+            vec![
+                create_test_file("/v/clip.MP4", 1),
+                create_test_file("/v/notes.txt", 1),
+                create_test_file("/v/backup_notes.txt", 1),
+                create_test_file("/v/Makefile", 1),
+            ]
+        };
+        let names = |kept: Vec<FileInfo>| -> Vec<String> {
+            kept.iter()
+                .map(|f| f.path.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        let no_video = FileFilter::exclude_extensions(vec!["mp4".to_string()]);
+        assert_eq!(
+            names(no_video.filter_files(files())),
+            vec!["notes.txt", "backup_notes.txt", "Makefile"]
+        );
+
+        let no_backups =
+            FileFilter::exclude_patterns(vec!["backup".to_string(), "Make".to_string()]);
+        assert_eq!(
+            names(no_backups.filter_files(files())),
+            vec!["clip.MP4", "notes.txt"]
+        );
+
+        // Nothing to exclude keeps everything
+        assert_eq!(
+            FileFilter::exclude_patterns(vec![])
+                .filter_files(files())
+                .len(),
+            4
+        );
+    }
 }
@@ -1,23 +1,32 @@
+use crate::retry::RetryPolicy;
+use crate::throttle::{IoThrottle, ThrottledReader};
 use anyhow::Result;
 use blake3::Hasher as Blake3Hasher;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use space_saver_utils::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
+use std::str::FromStr;
+use xxhash_rust::xxh3::{xxh3_128, Xxh3};
 
 /// Hash algorithm trait
 pub trait HashAlgorithm {
-    fn hash_file(&self, path: &Path) -> Result<String>;
+    fn hash_reader(&self, reader: &mut dyn Read) -> Result<String>;
     fn hash_bytes(&self, data: &[u8]) -> String;
+
+    fn hash_file(&self, path: &Path) -> Result<String> {
+        self.hash_reader(&mut BufReader::new(File::open(path)?))
+    }
 }
 
 /// BLAKE3 hasher (fast, recommended for large files)
 pub struct Blake3Hash;
 
 impl HashAlgorithm for Blake3Hash {
-    fn hash_file(&self, path: &Path) -> Result<String> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
+    fn hash_reader(&self, reader: &mut dyn Read) -> Result<String> {
         let mut hasher = Blake3Hasher::new();
         let mut buffer = vec![0u8; 8192];
 
@@ -41,9 +50,7 @@ impl HashAlgorithm for Blake3Hash {
 pub struct Sha256Hash;
 
 impl HashAlgorithm for Sha256Hash {
-    fn hash_file(&self, path: &Path) -> Result<String> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
+    fn hash_reader(&self, reader: &mut dyn Read) -> Result<String> {
         let mut hasher = Sha256::new();
         let mut buffer = vec![0u8; 8192];
 
@@ -65,26 +72,140 @@ impl HashAlgorithm for Sha256Hash {
     }
 }
 
+/// XXH3 hasher (128-bit; fastest, for integrity checks rather than
+/// security)
+pub struct Xxh3Hash;
+
+impl HashAlgorithm for Xxh3Hash {
+    fn hash_reader(&self, reader: &mut dyn Read) -> Result<String> {
+        let mut hasher = Xxh3::new();
+        let mut buffer = vec![0u8; 8192];
+
+        loop {
+            let count = reader.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buffer[..count]);
+        }
+
+        Ok(format!("{:032x}", hasher.digest128()))
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> String {
+        format!("{:032x}", xxh3_128(data))
+    }
+}
+
+/// The algorithms a [`FileHasher`] can use, by name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashKind {
+    #[default]
+    Blake3,
+    Sha256,
+    Xxh3,
+}
+
+impl HashKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashKind::Blake3 => "blake3",
+            HashKind::Sha256 => "sha256",
+            HashKind::Xxh3 => "xxh3",
+        }
+    }
+}
+
+impl fmt::Display for HashKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HashKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "blake3" | "b3" => Ok(HashKind::Blake3),
+            "sha256" => Ok(HashKind::Sha256),
+            "xxh3" | "xxh128" => Ok(HashKind::Xxh3),
+            other => Err(Error::InvalidInput(format!(
+                "Unsupported hash algorithm: {} (expected blake3, sha256 or xxh3)",
+                other
+            ))
+            .into()),
+        }
+    }
+}
+
 /// File hasher with configurable algorithm
 pub struct FileHasher {
     algorithm: Box<dyn HashAlgorithm + Send + Sync>,
+    /// Caps the read rate of `hash_file`
+    throttle: Option<IoThrottle>,
+    /// Whether and how `hash_file` starts over after a transient error
+    retry: RetryPolicy,
 }
 
 impl FileHasher {
+    pub fn new(kind: HashKind) -> Self {
+        let algorithm: Box<dyn HashAlgorithm + Send + Sync> = match kind {
+            HashKind::Blake3 => Box::new(Blake3Hash),
+            HashKind::Sha256 => Box::new(Sha256Hash),
+            HashKind::Xxh3 => Box::new(Xxh3Hash),
+        };
+        Self {
+            algorithm,
+            throttle: None,
+            retry: RetryPolicy::none(),
+        }
+    }
+
     pub fn new_blake3() -> Self {
         Self {
             algorithm: Box::new(Blake3Hash),
+            throttle: None,
+            retry: RetryPolicy::none(),
         }
     }
 
     pub fn new_sha256() -> Self {
         Self {
             algorithm: Box::new(Sha256Hash),
+            throttle: None,
+            retry: RetryPolicy::none(),
         }
     }
 
+    /// Read files no faster than `throttle` allows (shared with its clones)
+    pub fn with_throttle(mut self, throttle: IoThrottle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Hash a file again from the start when reading it fails for a moment
+    /// (e.g. on a network share)
+    pub fn with_retries(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     pub fn hash_file(&self, path: &Path) -> Result<String> {
-        self.algorithm.hash_file(path)
+        self.retry.run(&format!("Hashing {}", path.display()), || {
+            self.hash_file_once(path)
+        })
+    }
+
+    fn hash_file_once(&self, path: &Path) -> Result<String> {
+        match &self.throttle {
+            Some(throttle) => {
+                let file = ThrottledReader::new(File::open(path)?, throttle.clone());
+                self.algorithm.hash_reader(&mut BufReader::new(file))
+            }
+            None => self.algorithm.hash_file(path),
+        }
     }
 
     pub fn hash_bytes(&self, data: &[u8]) -> String {
@@ -122,6 +243,27 @@ mod tests {
         assert_eq!(hash.len(), 64); // SHA256 produces 32-byte hash (64 hex chars)
     }
 
+    #[test]
+    fn test_xxh3_hash_streams_like_it_hashes_bytes() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.bin");
+        let data = vec![3u8; 20_000];
+        fs::write(&file_path, &data).unwrap();
+
+        let hasher = FileHasher::new(HashKind::Xxh3);
+        let hash = hasher.hash_file(&file_path).unwrap();
+        assert_eq!(hash.len(), 32); // XXH3-128 produces 16 bytes (32 hex chars)
+        assert_eq!(hash, hasher.hash_bytes(&data));
+    }
+
+    #[test]
+    fn test_hash_kind_names() {
+        assert_eq!("SHA256".parse::<HashKind>().unwrap(), HashKind::Sha256);
+        assert_eq!("b3".parse::<HashKind>().unwrap(), HashKind::Blake3);
+        assert_eq!(HashKind::Xxh3.to_string(), "xxh3");
+        assert!("md5".parse::<HashKind>().is_err());
+    }
+
     #[test]
     fn test_file_hasher() {
         let dir = tempdir().unwrap();
@@ -133,6 +275,19 @@ mod tests {
         assert!(!hash.is_empty());
     }
 
+    #[test]
+    fn test_throttled_hash_matches_unthrottled() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.bin");
+        fs::write(&file_path, vec![7u8; 20_000]).unwrap();
+
+        let hasher = FileHasher::new_blake3().with_throttle(IoThrottle::new(u64::MAX));
+        assert_eq!(
+            hasher.hash_file(&file_path).unwrap(),
+            FileHasher::new_blake3().hash_file(&file_path).unwrap()
+        );
+    }
+
     #[test]
     fn test_consistent_hashing() {
         let data = b"consistent data";
@@ -1,28 +1,46 @@
 pub mod broken;
 pub mod compress;
 pub mod compress_plugins;
+mod filter_expr;
 pub mod filters;
 pub mod hash;
 pub mod hash_cache;
 pub mod image_sim;
+pub mod media_info;
 pub mod plugins;
+pub mod reflink;
+pub mod retry;
 pub mod scanner;
 pub mod skip_cache;
+pub mod storage;
+pub mod throttle;
 pub mod thumbnail;
 pub mod video_sim;
+pub mod volumes;
 
 pub use broken::{BrokenCategory, BrokenFileChecker, BrokenReason};
-pub use compress::Compressor;
+pub use compress::{AlgorithmSuggestion, ArchiveFormat, Compressor, SuggestedAlgorithm};
 pub use compress_plugins::{
-    global_plugin_manager, init_plugin_manager_with, CompressionOutcome, CompressionPlugin,
-    CompressionResult, PluginManager, PluginMetadata,
+    global_plugin_manager, init_plugin_manager_with, CancellationToken, Cancelled,
+    CompressionOutcome, CompressionPlugin, CompressionResult, PluginManager, PluginMetadata,
+    PluginProgress, PluginRequirements, ProgressSink, TimedOut, ToolCheck, ToolLimits,
 };
-pub use filters::FileFilter;
-pub use hash::{FileHasher, HashAlgorithm};
+pub use filter_expr::parse_size;
+pub use filters::{FileFilter, TagIndex};
+pub use hash::{FileHasher, HashAlgorithm, HashKind};
 pub use hash_cache::HashCache;
 pub use image_sim::ImageSimilarity;
-pub use plugins::{AnimatedWebPConverterPlugin, ImageZipToWebpZipPlugin, WebPConverterPlugin};
+pub use media_info::{probe_video, read_exif, ExifInfo, VideoInfo};
+pub use plugins::{
+    AnimatedWebPConverterPlugin, ImageZipToWebpZipPlugin, MinifyOptions, RawPolicy,
+    RawToJpegPlugin, TextMinifierPlugin, WebPConverterPlugin,
+};
+pub use reflink::{reflink, supports_reflink, ReflinkError};
+pub use retry::RetryPolicy;
 pub use scanner::{FileInfo, FileScanner};
 pub use skip_cache::{FileFingerprint, SkipCache};
+pub use storage::{LocalStorage, StorageBackend, StoredFile};
+pub use throttle::{lower_io_priority, IoPolicy, IoPriorityGuard, IoThrottle, ThrottledReader};
 pub use thumbnail::{image_dimensions, thumbnail_data_url};
 pub use video_sim::VideoSimilarity;
+pub use volumes::VolumeManifest;
@@ -0,0 +1,239 @@
+//! What a photo or video says about itself.
+//!
+//! The comparison views show camera, capture date and resolution next to
+//! each thumbnail so the user can tell copies apart. [`read_exif`] reads the
+//! EXIF of JPEG, PNG, WebP and TIFF-based files (RAWs included) with the
+//! same readers the converters use to carry it over; [`probe_video`] asks
+//! ffprobe about a video's first video stream.
+
+use crate::plugins::external_tool::new_command;
+use crate::plugins::raw_converter::{read_raw_metadata, RawMetadata};
+use crate::plugins::webp_metadata::{read_metadata, read_webp_metadata};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::Path;
+use std::process::Stdio;
+
+/// Camera details from a file's EXIF
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExifInfo {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// When the photo was taken, as the camera wrote it
+    /// ("2024:05:01 09:59:58"); the file's own date when no capture date is
+    /// recorded
+    pub date_taken: Option<String>,
+    /// 1-8, see the EXIF Orientation tag
+    pub orientation: Option<u16>,
+    /// Whether the photo records where it was taken
+    pub has_gps: bool,
+}
+
+impl From<RawMetadata> for ExifInfo {
+    fn from(meta: RawMetadata) -> Self {
+        Self {
+            camera_make: meta.make,
+            camera_model: meta.model,
+            date_taken: meta.date_time_original.or(meta.date_time),
+            orientation: meta.orientation,
+            has_gps: meta.has_gps,
+        }
+    }
+}
+
+/// The EXIF of a JPEG, PNG, WebP or TIFF-based file. None for other formats
+/// and for files carrying no EXIF or none of the tags read here.
+pub fn read_exif(path: &Path) -> Result<Option<ExifInfo>> {
+    let mut header = [0u8; 12];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let header = &header[..read];
+
+    let meta = if header.starts_with(b"II*\0") || header.starts_with(b"MM\0*") {
+        read_raw_metadata(BufReader::new(File::open(path)?))
+    } else {
+        let exif = if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP") {
+            read_webp_metadata(&std::fs::read(path)?)?.exif
+        } else if header.starts_with(&[0xFF, 0xD8]) || header.starts_with(b"\x89PNG") {
+            read_metadata(path)?.exif
+        } else {
+            None
+        };
+        match exif {
+            Some(exif) => read_raw_metadata(Cursor::new(exif)),
+            None => return Ok(None),
+        }
+    };
+    Ok(Some(ExifInfo::from(meta)).filter(|info| *info != ExifInfo::default()))
+}
+
+/// A video's length and its first video stream
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoInfo {
+    pub duration_secs: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// ffprobe's codec name, e.g. "h264" or "hevc"
+    pub codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    /// Bits per second of the whole file
+    pub bit_rate: Option<u64>,
+}
+
+/// Ask `ffprobe` (the executable at that path) about the video at `path`
+pub fn probe_video(ffprobe: &Path, path: &Path) -> Result<VideoInfo> {
+    let output = new_command(ffprobe)
+        .args(["-v", "error", "-print_format", "json", "-show_format"])
+        .args(["-show_streams", "-select_streams", "v:0"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {}", ffprobe.display()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "ffprobe could not read {}: {}",
+            path.display(),
+            stderr.trim()
+        );
+    }
+    parse_ffprobe(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+}
+
+/// ffprobe writes numbers in the format section as strings
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+/// The `-print_format json` output of ffprobe
+fn parse_ffprobe(json: &str) -> Result<VideoInfo> {
+    let probe: Probe = serde_json::from_str(json).context("Unexpected ffprobe output")?;
+    let stream = probe.streams.into_iter().next();
+    let format = probe.format;
+    Ok(VideoInfo {
+        duration_secs: format
+            .as_ref()
+            .and_then(|f| f.duration.as_deref()?.parse().ok()),
+        width: stream.as_ref().and_then(|s| s.width),
+        height: stream.as_ref().and_then(|s| s.height),
+        frame_rate: stream
+            .as_ref()
+            .and_then(|s| frame_rate(s.avg_frame_rate.as_deref()?)),
+        codec: stream.and_then(|s| s.codec_name),
+        bit_rate: format.and_then(|f| f.bit_rate?.parse().ok()),
+    })
+}
+
+/// "30000/1001" as frames per second; None for ffprobe's "0/0"
+fn frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+    use tempfile::tempdir;
+
+    /// Little-endian EXIF with a Make tag and a one-entry GPS IFD
+    fn exif_with_gps() -> Vec<u8> {
+        let mut exif = b"II*\0".to_vec();
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        // IFD0: Make (ASCII, stored at 38) and the GPS IFD pointer (44)
+        exif.extend_from_slice(&2u16.to_le_bytes());
+        for (tag, kind, count, value) in [(0x010Fu16, 2u16, 5u32, 38u32), (0x8825, 4, 1, 44)] {
+            exif.extend_from_slice(&tag.to_le_bytes());
+            exif.extend_from_slice(&kind.to_le_bytes());
+            exif.extend_from_slice(&count.to_le_bytes());
+            exif.extend_from_slice(&value.to_le_bytes());
+        }
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif.extend_from_slice(b"Acme\0\0");
+        // GPS IFD: GPSVersionID 2.3.0.0
+        exif.extend_from_slice(&1u16.to_le_bytes());
+        exif.extend_from_slice(&[0, 0, 1, 0, 4, 0, 0, 0, 2, 3, 0, 0]);
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif
+    }
+
+    fn write_jpeg(path: &Path, exif: Option<&[u8]>) {
+        let img = ImageBuffer::from_fn(16, 16, |x, y| Rgb([(x * 16) as u8, (y * 16) as u8, 0u8]));
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new(&mut jpeg)
+            .encode_image(&img)
+            .unwrap();
+        let mut out = jpeg[..2].to_vec();
+        if let Some(exif) = exif {
+            out.extend_from_slice(&[0xFF, 0xE1]);
+            out.extend_from_slice(&((exif.len() + 8) as u16).to_be_bytes());
+            out.extend_from_slice(b"Exif\0\0");
+            out.extend_from_slice(exif);
+        }
+        out.extend_from_slice(&jpeg[2..]);
+        std::fs::write(path, out).unwrap();
+    }
+
+    #[test]
+    fn test_read_exif_finds_camera_and_gps() {
+        let dir = tempdir().unwrap();
+        let photo = dir.path().join("photo.jpg");
+        write_jpeg(&photo, Some(&exif_with_gps()));
+        let exif = read_exif(&photo).unwrap().unwrap();
+        assert_eq!(exif.camera_make.as_deref(), Some("Acme"));
+        assert!(exif.has_gps);
+
+        // TIFF-based files are read directly
+        let tiff = dir.path().join("photo.tif");
+        std::fs::write(&tiff, exif_with_gps()).unwrap();
+        assert_eq!(read_exif(&tiff).unwrap(), Some(exif));
+
+        let plain = dir.path().join("plain.jpg");
+        write_jpeg(&plain, None);
+        assert_eq!(read_exif(&plain).unwrap(), None);
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, b"no pixels here").unwrap();
+        assert_eq!(read_exif(&text).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_ffprobe_output() {
+        let json = r#"{
+            "streams": [{"codec_name": "h264", "width": 1920, "height": 1080,
+                         "avg_frame_rate": "30000/1001"}],
+            "format": {"duration": "12.500000", "bit_rate": "4000000"}
+        }"#;
+        let info = parse_ffprobe(json).unwrap();
+        assert_eq!(info.codec.as_deref(), Some("h264"));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+        assert_eq!(info.duration_secs, Some(12.5));
+        assert_eq!(info.bit_rate, Some(4_000_000));
+        assert!((info.frame_rate.unwrap() - 29.97).abs() < 0.01);
+
+        // Audio-only files have no video stream
+        let info = parse_ffprobe(r#"{"streams": [], "format": {}}"#).unwrap();
+        assert_eq!(info.width, None);
+        assert_eq!(frame_rate("0/0"), None);
+    }
+}
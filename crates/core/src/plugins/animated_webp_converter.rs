@@ -1,43 +1,94 @@
-use crate::compress_plugins::{create_output_file, CompressionPlugin, CompressionResult};
-use once_cell::sync::Lazy;
-use std::path::Path;
-use std::process::Command;
+use super::external_tool::{new_command, probe_tool, run_tool};
+use crate::compress_plugins::{
+    create_output_file, has_extension, CancellationToken, Cancelled, CompressionPlugin,
+    CompressionResult, PluginRequirements, ProgressSink, ToolCheck, ToolLimits,
+};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, Frame};
+use once_cell::sync::OnceCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use tracing::{info, warn};
 
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
+/// External tools for GIF conversion, in order of preference
+const TOOLS: [&str; 2] = ["gif2webp", "ffmpeg"];
 
-/// External tool used for the conversion, detected once per process
-static AVAILABLE_TOOL: Lazy<Option<&'static str>> = Lazy::new(|| {
-    ["gif2webp", "ffmpeg"]
-        .into_iter()
-        .find(|tool| new_command(tool).arg("-version").output().is_ok())
-});
-
-fn new_command(program: &str) -> Command {
-    #[allow(unused_mut)]
-    let mut cmd = Command::new(program);
-
-    // On Windows, prevent opening a new terminal window
-    #[cfg(target_os = "windows")]
-    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+/// Whether `path` is a PNG carrying an animation control (acTL) chunk
+pub(crate) fn is_apng(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|file| PngDecoder::new(BufReader::new(file)).ok())
+        .is_some_and(|decoder| decoder.is_apng())
+}
 
-    cmd
+/// Whether `path` is a GIF with more than one frame. Only the first two
+/// frames are decoded.
+pub(crate) fn is_animated_gif(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|file| GifDecoder::new(BufReader::new(file)).ok())
+        .is_some_and(|decoder| decoder.into_frames().take(2).filter(|f| f.is_ok()).count() > 1)
 }
 
 pub struct AnimatedWebPConverterPlugin {
     quality: f32,
+    tool_paths: BTreeMap<String, PathBuf>,
+    /// First working tool and the program to run for it; probed on first
+    /// use and again after the tool paths change
+    discovered: OnceCell<Option<(&'static str, PathBuf)>>,
+    limits: ToolLimits,
 }
 
 impl AnimatedWebPConverterPlugin {
     pub fn new() -> Self {
-        Self { quality: 85.0 }
+        Self {
+            quality: 85.0,
+            tool_paths: BTreeMap::new(),
+            discovered: OnceCell::new(),
+            limits: ToolLimits::default(),
+        }
     }
 
     pub fn with_quality(mut self, quality: f32) -> Self {
         self.quality = quality.clamp(0.0, 100.0);
         self
     }
+
+    /// Run `tool` from `path` instead of looking it up on PATH
+    pub fn with_tool_path(mut self, tool: &str, path: impl Into<PathBuf>) -> Self {
+        self.tool_paths.insert(tool.to_string(), path.into());
+        self.discovered = OnceCell::new();
+        self
+    }
+
+    /// Run tools with the given priority and memory limits
+    pub fn with_tool_limits(mut self, limits: ToolLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The configured path for `tool`, or its bare name for a PATH lookup
+    fn program(&self, tool: &str) -> PathBuf {
+        self.tool_paths
+            .get(tool)
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(tool))
+    }
+
+    fn available_tool(&self) -> Option<&(&'static str, PathBuf)> {
+        self.discovered
+            .get_or_init(|| {
+                TOOLS.into_iter().find_map(|tool| {
+                    let program = self.program(tool);
+                    probe_tool(&program).ok().map(|_| (tool, program))
+                })
+            })
+            .as_ref()
+    }
 }
 
 impl Default for AnimatedWebPConverterPlugin {
@@ -50,30 +101,52 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
     fn metadata(&self) -> crate::compress_plugins::PluginMetadata {
         crate::compress_plugins::PluginMetadata {
             name: "Animated WebP Converter".to_string(),
-            description: "Convert GIF to Animated WebP with lossy compression for better file size"
-                .to_string(),
+            description:
+                "Convert animated GIF and PNG to Animated WebP with lossy compression for better file size"
+                    .to_string(),
             version: "1.0.0".to_string(),
+            lossy: true,
+            destructive: false,
+            requires_external_tools: vec!["gif2webp".to_string(), "ffmpeg".to_string()],
         }
     }
 
     fn can_handle(&self, path: &Path) -> anyhow::Result<(bool, Option<String>)> {
         if let Some(ext) = path.extension() {
             let ext_lower = ext.to_string_lossy().to_lowercase();
+            if ext_lower == "png" || ext_lower == "apng" {
+                // APNG is decoded and encoded in-process; no tool needed
+                return if is_apng(path) {
+                    Ok((
+                        true,
+                        Some("Animated PNG for animated WebP conversion".to_string()),
+                    ))
+                } else {
+                    Ok((false, Some("Not an animated PNG".to_string())))
+                };
+            }
             if ext_lower == "gif" {
-                if AVAILABLE_TOOL.is_none() {
+                // A single-frame GIF gains nothing from animated encoding;
+                // the regular WebP converter handles it
+                if !is_animated_gif(path) {
                     return Ok((
                         false,
-                        Some("Requires gif2webp or ffmpeg in PATH; neither was found".to_string()),
+                        Some("Static GIF; handled by the WebP Converter".to_string()),
                     ));
                 }
-                Ok((
-                    true,
-                    Some("GIF file for animated WebP conversion".to_string()),
-                ))
+                let reason = if self.available_tool().is_some() {
+                    "GIF file for animated WebP conversion"
+                } else {
+                    "GIF file for animated WebP conversion (built-in encoder)"
+                };
+                Ok((true, Some(reason.to_string())))
             } else {
                 Ok((
                     false,
-                    Some(format!("Not a GIF file (extension: {})", ext_lower)),
+                    Some(format!(
+                        "Not a GIF or animated PNG file (extension: {})",
+                        ext_lower
+                    )),
                 ))
             }
         } else {
@@ -87,6 +160,16 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
     }
 
     fn process(&self, source: &Path, output_dir: &Path) -> anyhow::Result<CompressionResult> {
+        self.process_with_progress(source, output_dir, &ProgressSink::new())
+    }
+
+    fn process_with_progress(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        sink: &ProgressSink,
+    ) -> anyhow::Result<CompressionResult> {
+        let cancel = sink.cancel_token();
         info!(
             "Starting Animated WebP conversion for: {}",
             source.display()
@@ -99,7 +182,7 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
         }
 
         let original_size = std::fs::metadata(source)?.len();
-        info!("Original GIF size: {} bytes", original_size);
+        info!("Original size: {} bytes", original_size);
 
         let stem = source
             .file_stem()
@@ -113,11 +196,18 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
         // writer targeting the same name fails here instead of overwriting
         create_output_file(&output_path)?;
 
-        // Convert using gif2webp (best quality) or ffmpeg as fallback;
-        // the manager handles size comparison, backup, and replacement
-        let conversion_result = self
-            .convert_with_gif2webp(source, &temp_path)
-            .or_else(|_| self.convert_with_ffmpeg(source, &temp_path));
+        // The external tools give no machine-readable progress; report the
+        // start and end so callers at least see which file is in flight
+        sink.report(0, 1, None);
+
+        // APNG is re-encoded in-process; GIFs go through the discovered
+        // tool with the built-in encoder as fallback. The manager handles
+        // size comparison, backup, and replacement.
+        let conversion_result = if has_extension(source, &["png", "apng"]) {
+            self.convert_builtin(source, &temp_path, cancel)
+        } else {
+            self.convert_gif(source, &temp_path, cancel)
+        };
 
         let finish = || -> anyhow::Result<u64> {
             let compressed_size = std::fs::metadata(&temp_path)?.len();
@@ -132,6 +222,7 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
                     "Animated WebP conversion complete. Original: {} bytes, WebP: {} bytes",
                     original_size, compressed_size
                 );
+                sink.report(1, 1, None);
 
                 Ok(CompressionResult {
                     output_path,
@@ -141,19 +232,46 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
                     files_processed: 1,
                     backup_path: None,
                     replace_source: false,
+                    metadata_preserved: None,
+                    extra_outputs: Vec::new(),
                 })
             }
             Err(e) => {
                 // Clean up the temp file and the reserved placeholder
                 let _ = std::fs::remove_file(&temp_path);
                 let _ = std::fs::remove_file(&output_path);
+                if e.is::<Cancelled>() {
+                    return Err(e);
+                }
                 Err(anyhow::anyhow!("Animated WebP conversion failed: {}", e))
             }
         }
     }
 
+    fn verify_output(&self, source: &Path, result: &CompressionResult) -> anyhow::Result<bool> {
+        let data = std::fs::read(&result.output_path)?;
+        let animation = webp::AnimDecoder::new(&data)
+            .decode()
+            .map_err(|e| anyhow::anyhow!("Output is not a decodable animated WebP: {}", e))?;
+        let first = animation
+            .get_frame(0)
+            .ok_or_else(|| anyhow::anyhow!("Output animation has no frames"))?;
+
+        let expected = imagesize::size(source)?;
+        if (first.width() as usize, first.height() as usize) != (expected.width, expected.height) {
+            anyhow::bail!(
+                "Output is {}x{} but the source is {}x{}",
+                first.width(),
+                first.height(),
+                expected.width,
+                expected.height
+            );
+        }
+        Ok(true)
+    }
+
     fn supported_extensions(&self) -> Vec<&str> {
-        vec!["gif"]
+        vec!["gif", "png", "apng"]
     }
 
     fn quality(&self) -> Option<f32> {
@@ -164,15 +282,144 @@ impl CompressionPlugin for AnimatedWebPConverterPlugin {
         self.quality = quality.clamp(0.0, 100.0);
         true
     }
+
+    fn check_requirements(&self) -> PluginRequirements {
+        let tools: Vec<ToolCheck> = TOOLS
+            .into_iter()
+            .map(|tool| {
+                let program = self.program(tool);
+                let probe = probe_tool(&program);
+                ToolCheck {
+                    name: tool.to_string(),
+                    program: program.display().to_string(),
+                    available: probe.is_ok(),
+                    version: probe.as_ref().ok().cloned(),
+                    error: probe.err(),
+                }
+            })
+            .collect();
+        let message = match tools.iter().find(|t| t.available) {
+            Some(tool) => format!("GIFs are converted with {}", tool.name),
+            None => "Neither gif2webp nor ffmpeg works; GIFs use the built-in encoder".to_string(),
+        };
+        PluginRequirements {
+            plugin_name: self.metadata().name,
+            tools,
+            // The built-in encoder covers everything the tools do
+            ready: true,
+            message: Some(message),
+        }
+    }
+
+    fn set_tool_paths(&mut self, paths: &BTreeMap<String, PathBuf>) {
+        self.tool_paths = paths.clone();
+        self.discovered = OnceCell::new();
+    }
+
+    fn set_tool_limits(&mut self, limits: ToolLimits) {
+        self.limits = limits;
+    }
 }
 
 impl AnimatedWebPConverterPlugin {
+    /// Convert a GIF with the discovered tool, falling back to the built-in
+    /// encoder when none is available or the tool fails. A cancelled run
+    /// never falls through.
+    fn convert_gif(
+        &self,
+        input: &Path,
+        output: &Path,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<()> {
+        if let Some((tool, program)) = self.available_tool() {
+            let result = if *tool == "gif2webp" {
+                self.convert_with_gif2webp(program, input, output, cancel)
+            } else {
+                self.convert_with_ffmpeg(program, input, output, cancel)
+            };
+            match result {
+                Err(e) if !e.is::<Cancelled>() => {
+                    warn!("{} failed, using the built-in encoder: {}", tool, e);
+                }
+                other => return other,
+            }
+        }
+        self.convert_builtin(input, output, cancel)
+    }
+
+    /// Convert an animated GIF or PNG with libwebp's animation encoder, no
+    /// external tool needed. Frames come out of the decoders already
+    /// composited onto the full canvas.
+    fn convert_builtin(
+        &self,
+        input: &Path,
+        output: &Path,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<()> {
+        info!("Converting {} to Animated WebP in-process", input.display());
+
+        let reader = BufReader::new(File::open(input)?);
+        let decoded = if has_extension(input, &["gif"]) {
+            GifDecoder::new(reader)?.into_frames()
+        } else {
+            PngDecoder::new(reader)?.apng().into_frames()
+        };
+        let mut frames = Vec::new();
+        for frame in decoded {
+            if cancel.is_cancelled() {
+                return Err(Cancelled.into());
+            }
+            frames.push(frame?);
+        }
+        self.encode_frames(&frames, output)
+    }
+
+    fn encode_frames(&self, frames: &[Frame], output: &Path) -> anyhow::Result<()> {
+        let first = frames
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("Animation has no frames"))?;
+        let (width, height) = first.buffer().dimensions();
+
+        let mut config =
+            webp::WebPConfig::new().map_err(|_| anyhow::anyhow!("Failed to init WebP config"))?;
+        config.lossless = 0;
+        config.quality = self.quality;
+
+        let mut encoder = webp::AnimEncoder::new(width, height, &config);
+        encoder.set_loop_count(0);
+        // Timestamps are each frame's start time in milliseconds
+        let mut timestamp_ms = 0.0f64;
+        for frame in frames {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            encoder.add_frame(webp::AnimFrame::from_rgba(
+                frame.buffer().as_raw(),
+                width,
+                height,
+                timestamp_ms.round() as i32,
+            ));
+            timestamp_ms += numer as f64 / denom.max(1) as f64;
+        }
+
+        let encoded = encoder
+            .try_encode()
+            .map_err(|e| anyhow::anyhow!("Animated WebP encoding failed: {:?}", e))?;
+        std::fs::write(output, &*encoded)?;
+        Ok(())
+    }
+
     /// Convert GIF to Animated WebP using gif2webp (recommended tool)
-    fn convert_with_gif2webp(&self, input: &Path, output: &Path) -> anyhow::Result<()> {
+    fn convert_with_gif2webp(
+        &self,
+        program: &Path,
+        input: &Path,
+        output: &Path,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<()> {
         info!("Attempting GIF to Animated WebP conversion using gif2webp");
 
         let quality = format!("{}", self.quality.round() as u32);
-        let mut cmd = new_command("gif2webp");
+        let mut cmd = new_command(program);
+        self.limits.apply(&mut cmd);
         cmd.args([
             "-q",
             &quality,
@@ -184,24 +431,30 @@ impl AnimatedWebPConverterPlugin {
             output.to_str().unwrap(),
         ]);
 
-        let status = cmd.output()?;
+        let (status, stderr) = run_tool(cmd, Stdio::null(), cancel)?;
 
-        if status.status.success() {
+        if status.success() {
             info!("gif2webp conversion successful");
             Ok(())
         } else {
-            let stderr = String::from_utf8_lossy(&status.stderr);
             warn!("gif2webp conversion failed: {}", stderr);
             Err(anyhow::anyhow!("gif2webp conversion failed: {}", stderr))
         }
     }
 
     /// Convert GIF to Animated WebP using FFmpeg (fallback)
-    fn convert_with_ffmpeg(&self, input: &Path, output: &Path) -> anyhow::Result<()> {
+    fn convert_with_ffmpeg(
+        &self,
+        program: &Path,
+        input: &Path,
+        output: &Path,
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<()> {
         info!("Attempting GIF to Animated WebP conversion using FFmpeg");
 
         let quality = format!("{}", self.quality.round() as u32);
-        let mut cmd = new_command("ffmpeg");
+        let mut cmd = new_command(program);
+        self.limits.apply(&mut cmd);
         cmd.args([
             "-i",
             input.to_str().unwrap(),
@@ -217,13 +470,12 @@ impl AnimatedWebPConverterPlugin {
             output.to_str().unwrap(),
         ]);
 
-        let status = cmd.output()?;
+        let (status, stderr) = run_tool(cmd, Stdio::null(), cancel)?;
 
-        if status.status.success() {
+        if status.success() {
             info!("FFmpeg conversion successful");
             Ok(())
         } else {
-            let stderr = String::from_utf8_lossy(&status.stderr);
             warn!("FFmpeg conversion failed: {}", stderr);
             Err(anyhow::anyhow!("FFmpeg conversion failed: {}", stderr))
         }
@@ -231,32 +483,89 @@ impl AnimatedWebPConverterPlugin {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
-    fn tool_available() -> bool {
-        AVAILABLE_TOOL.is_some()
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, ImageBuffer, Rgba, RgbaImage};
+
+    /// Plugin whose tools point at paths that don't exist, forcing the
+    /// built-in encoder whatever is installed on the machine
+    fn plugin_without_tools(dir: &Path) -> AnimatedWebPConverterPlugin {
+        AnimatedWebPConverterPlugin::new()
+            .with_tool_path("gif2webp", dir.join("no-gif2webp"))
+            .with_tool_path("ffmpeg", dir.join("no-ffmpeg"))
     }
 
-    #[test]
-    fn test_can_handle_gif() {
-        let plugin = AnimatedWebPConverterPlugin::new();
+    fn solid(color: [u8; 4]) -> RgbaImage {
+        ImageBuffer::from_pixel(16, 12, Rgba(color))
+    }
 
-        let (can_handle, reason) = plugin.can_handle(Path::new("test.gif")).unwrap();
-        if tool_available() {
-            assert!(can_handle);
-            assert_eq!(
-                reason,
-                Some("GIF file for animated WebP conversion".to_string())
-            );
+    const COLORS: [[u8; 4]; 2] = [[255, 0, 0, 255], [0, 0, 255, 255]];
 
-            let (can_handle, _) = plugin.can_handle(Path::new("TEST.GIF")).unwrap();
-            assert!(can_handle);
-        } else {
-            // Without gif2webp/ffmpeg installed, GIFs must be rejected up front
-            assert!(!can_handle);
-            assert!(reason.unwrap().contains("gif2webp"));
+    pub(crate) fn write_gif(path: &Path, frame_count: usize) {
+        let mut encoder = GifEncoder::new(File::create(path).unwrap());
+        let frames = COLORS
+            .iter()
+            .take(frame_count)
+            .map(|c| Frame::from_parts(solid(*c), 0, 0, Delay::from_numer_denom_ms(100, 1)));
+        encoder.encode_frames(frames).unwrap();
+    }
+
+    pub(crate) fn write_apng(path: &Path) {
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), 16, 12);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(COLORS.len() as u32, 0).unwrap();
+        encoder.set_frame_delay(1, 10).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for color in COLORS {
+            writer.write_image_data(solid(color).as_raw()).unwrap();
         }
+    }
+
+    #[test]
+    fn test_detects_animation() {
+        let dir = tempfile::tempdir().unwrap();
+        let apng = dir.path().join("anim.png");
+        write_apng(&apng);
+        let still = dir.path().join("still.png");
+        solid(COLORS[0]).save(&still).unwrap();
+        let animated_gif = dir.path().join("anim.gif");
+        write_gif(&animated_gif, 2);
+        let static_gif = dir.path().join("still.gif");
+        write_gif(&static_gif, 1);
+
+        assert!(is_apng(&apng));
+        assert!(!is_apng(&still));
+        assert!(is_animated_gif(&animated_gif));
+        assert!(!is_animated_gif(&static_gif));
+        assert!(!is_animated_gif(&dir.path().join("missing.gif")));
+    }
+
+    #[test]
+    fn test_can_handle_gif() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin_without_tools(dir.path());
+        let gif = dir.path().join("test.gif");
+        write_gif(&gif, 2);
+
+        // Without gif2webp/ffmpeg the built-in encoder takes over
+        let (can_handle, reason) = plugin.can_handle(&gif).unwrap();
+        assert!(can_handle);
+        assert!(reason.unwrap().contains("built-in encoder"));
+
+        let upper = dir.path().join("TEST2.GIF");
+        write_gif(&upper, 2);
+        let (can_handle, _) = plugin.can_handle(&upper).unwrap();
+        assert!(can_handle);
+
+        // Static GIFs are left to the regular WebP converter
+        let still = dir.path().join("still.gif");
+        write_gif(&still, 1);
+        let (can_handle, reason) = plugin.can_handle(&still).unwrap();
+        assert!(!can_handle);
+        assert!(reason.unwrap().contains("WebP Converter"));
 
         let (can_handle, reason) = plugin.can_handle(Path::new("test.png")).unwrap();
         assert!(!can_handle);
@@ -267,6 +576,94 @@ mod tests {
         assert!(reason.is_some());
     }
 
+    #[test]
+    fn test_converts_apng_without_external_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("anim.png");
+        write_apng(&source);
+
+        let plugin = AnimatedWebPConverterPlugin::new();
+        let (can_handle, _) = plugin.can_handle(&source).unwrap();
+        assert!(can_handle);
+
+        let result = plugin.process(&source, dir.path()).unwrap();
+        assert_eq!(result.output_path, dir.path().join("anim.animated.webp"));
+        assert!(source.exists(), "plugin must not touch the source");
+        assert!(plugin.verify_output(&source, &result).unwrap());
+
+        let data = std::fs::read(&result.output_path).unwrap();
+        let animation = webp::AnimDecoder::new(&data).decode().unwrap();
+        assert_eq!(animation.len(), COLORS.len());
+    }
+
+    #[test]
+    fn test_converts_gif_with_builtin_encoder() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("anim.gif");
+        write_gif(&source, 2);
+
+        let plugin = plugin_without_tools(dir.path());
+        let result = plugin.process(&source, dir.path()).unwrap();
+        assert!(plugin.verify_output(&source, &result).unwrap());
+        let data = std::fs::read(&result.output_path).unwrap();
+        let animation = webp::AnimDecoder::new(&data).decode().unwrap();
+        assert_eq!(animation.len(), COLORS.len());
+    }
+
+    #[test]
+    fn test_check_requirements_reports_missing_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = plugin_without_tools(dir.path()).check_requirements();
+
+        assert_eq!(report.plugin_name, "Animated WebP Converter");
+        assert!(report.ready, "the built-in encoder keeps the plugin usable");
+        assert!(report.message.unwrap().contains("built-in encoder"));
+        let names: Vec<&str> = report.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, TOOLS);
+        for tool in &report.tools {
+            assert!(!tool.available);
+            assert!(tool.version.is_none());
+            assert!(tool.error.is_some());
+            assert!(tool.program.contains("no-"), "configured path is probed");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_configured_tool_is_version_checked_and_falls_back_on_failure() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Answers the version probe but fails every conversion
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("fake-gif2webp");
+        std::fs::write(
+            &fake,
+            "#!/bin/sh\n[ \"$1\" = \"-version\" ] && { echo \"1.4.0\"; exit 0; }\nexit 1\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let plugin = plugin_without_tools(dir.path()).with_tool_path("gif2webp", &fake);
+        let report = plugin.check_requirements();
+        let gif2webp = &report.tools[0];
+        assert!(gif2webp.available);
+        assert_eq!(gif2webp.version.as_deref(), Some("1.4.0"));
+        assert_eq!(
+            report.message.as_deref(),
+            Some("GIFs are converted with gif2webp")
+        );
+
+        let source = dir.path().join("anim.gif");
+        write_gif(&source, 2);
+        let (_, reason) = plugin.can_handle(&source).unwrap();
+        assert_eq!(
+            reason.as_deref(),
+            Some("GIF file for animated WebP conversion")
+        );
+        let result = plugin.process(&source, dir.path()).unwrap();
+        assert!(plugin.verify_output(&source, &result).unwrap());
+    }
+
     #[test]
     fn test_metadata() {
         let plugin = AnimatedWebPConverterPlugin::new();
@@ -278,7 +675,7 @@ mod tests {
     fn test_supported_extensions() {
         let plugin = AnimatedWebPConverterPlugin::new();
         let extensions = plugin.supported_extensions();
-        assert_eq!(extensions, vec!["gif"]);
+        assert_eq!(extensions, vec!["gif", "png", "apng"]);
     }
 
     #[test]
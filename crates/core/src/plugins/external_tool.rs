@@ -0,0 +1,104 @@
+//! Helpers for plugins that shell out to external tools
+
+use crate::compress_plugins::{CancellationToken, Cancelled};
+use std::ffi::OsStr;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Duration;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+pub(crate) fn new_command(program: impl AsRef<OsStr>) -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(program);
+
+    // On Windows, prevent opening a new terminal window
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    cmd
+}
+
+/// Health-check a tool: run it with `-version` (both gif2webp and ffmpeg
+/// accept it) and return the first line of output as its version
+pub(crate) fn probe_tool(program: &Path) -> Result<String, String> {
+    let output = new_command(program)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("`-version` exited with {}", output.status));
+    }
+    let text = if output.stdout.is_empty() {
+        String::from_utf8_lossy(&output.stderr)
+    } else {
+        String::from_utf8_lossy(&output.stdout)
+    };
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Run an external tool to completion, killing it if `cancel` fires. stdout
+/// goes to `stdout` (tools that write to a file pass `Stdio::null()`);
+/// stderr is drained on a separate thread so a chatty tool (ffmpeg) cannot
+/// block on a full pipe while we poll.
+pub(crate) fn run_tool(
+    mut cmd: Command,
+    stdout: Stdio,
+    cancel: &CancellationToken,
+) -> anyhow::Result<(ExitStatus, String)> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stderr = child.stderr.take();
+    let reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut buf);
+        }
+        buf
+    });
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let stderr = reader.join().unwrap_or_default();
+            return Ok((status, stderr));
+        }
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = reader.join();
+            return Err(Cancelled.into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_tool_kills_process_on_cancel() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let mut cmd = new_command("sleep");
+        cmd.arg("5");
+
+        let start = std::time::Instant::now();
+        let err = run_tool(cmd, Stdio::null(), &cancel).unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
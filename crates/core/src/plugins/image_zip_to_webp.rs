@@ -1,20 +1,43 @@
 use anyhow::{Context, Result};
 use image::DynamicImage;
+use rayon::prelude::*;
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+use tracing::warn;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
 
 use crate::compress_plugins::{
-    create_output_file, get_file_size, has_extension, CompressionPlugin, CompressionResult,
-    PluginMetadata,
+    create_output_file, get_file_size, has_extension, Cancelled, CompressionPlugin,
+    CompressionResult, PluginMetadata, ProgressSink,
 };
 
+/// Images decoded at the same time by default; each one holds its full
+/// RGBA buffer until it has been encoded
+const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+/// Image entries larger than this are copied instead of decoded
+const DEFAULT_MAX_IMAGE_BYTES: u64 = 256 * 1024 * 1024;
+
 /// Plugin for converting ZIP files containing images to WebP format
 /// Reads ZIP, converts all images to WebP, and creates a new ZIP
+///
+/// Entries are streamed: non-image entries are copied straight through and
+/// at most `max_in_flight` images are held in memory at once, so memory use
+/// does not grow with the size of the archive.
 pub struct ImageZipToWebpZipPlugin {
     quality: f32,
     min_image_ratio: f32, // Minimum ratio of images to total files to process
+    max_in_flight: usize,
+    max_image_bytes: u64,
+}
+
+/// Running totals for one `process_zip` call
+#[derive(Default)]
+struct ZipTotals {
+    files_processed: usize,
+    original: u64,
+    compressed: u64,
 }
 
 impl ImageZipToWebpZipPlugin {
@@ -22,6 +45,8 @@ impl ImageZipToWebpZipPlugin {
         Self {
             quality: 85.0,
             min_image_ratio: 1.0, // At least 100% of files should be images
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
         }
     }
 
@@ -35,6 +60,18 @@ impl ImageZipToWebpZipPlugin {
         self
     }
 
+    /// Maximum number of images decoded concurrently (at least 1)
+    pub fn with_max_in_flight(mut self, count: usize) -> Self {
+        self.max_in_flight = count.max(1);
+        self
+    }
+
+    /// Image entries above this uncompressed size are copied unconverted
+    pub fn with_max_image_bytes(mut self, bytes: u64) -> Self {
+        self.max_image_bytes = bytes;
+        self
+    }
+
     fn is_image_file(filename: &str) -> bool {
         let lower = filename.to_lowercase();
         lower.ends_with(".png")
@@ -101,74 +138,111 @@ impl ImageZipToWebpZipPlugin {
         Ok(encoded.to_vec())
     }
 
-    fn process_zip(&self, source: &Path, output: &Path) -> Result<(usize, u64, u64)> {
+    fn webp_name(name: &str) -> String {
+        match name.rfind('.') {
+            Some(idx) => format!("{}.webp", &name[..idx]),
+            None => format!("{}.webp", name),
+        }
+    }
+
+    /// Converts the queued images in parallel and writes them in archive
+    /// order. Images that fail to convert are stored unchanged.
+    fn flush_images(
+        &self,
+        pending: &mut Vec<(String, Vec<u8>)>,
+        output_archive: &mut ZipWriter<File>,
+        options: SimpleFileOptions,
+        totals: &mut ZipTotals,
+    ) -> Result<()> {
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let converted: Vec<Result<Vec<u8>>> = pending
+            .par_iter()
+            .map(|(name, contents)| self.convert_image_to_webp(contents, name))
+            .collect();
+
+        for ((name, contents), webp) in pending.drain(..).zip(converted) {
+            match webp {
+                Ok(webp_data) => {
+                    output_archive.start_file(Self::webp_name(&name), options)?;
+                    output_archive.write_all(&webp_data)?;
+                    totals.compressed += webp_data.len() as u64;
+                    totals.files_processed += 1;
+                }
+                Err(e) => {
+                    warn!("Failed to convert {}: {}. Copying original.", name, e);
+                    output_archive.start_file(name, options)?;
+                    output_archive.write_all(&contents)?;
+                    totals.compressed += contents.len() as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn process_zip(
+        &self,
+        source: &Path,
+        output_file: File,
+        sink: &ProgressSink,
+    ) -> Result<(usize, u64, u64)> {
         let input_file = File::open(source)?;
         let mut input_archive = ZipArchive::new(input_file)?;
 
-        // create_new (O_EXCL): fails instead of overwriting a concurrent
-        // writer's output with the same name
-        let output_file = create_output_file(output)?;
         let mut output_archive = ZipWriter::new(output_file);
 
-        let options = FileOptions::default()
+        let options = SimpleFileOptions::default()
             .compression_method(CompressionMethod::Deflated)
             .compression_level(Some(6));
 
-        let mut files_processed = 0;
-        let mut original_total = 0u64;
-        let mut compressed_total = 0u64;
+        let mut totals = ZipTotals::default();
+        let mut pending: Vec<(String, Vec<u8>)> = Vec::with_capacity(self.max_in_flight);
 
+        let total_entries = input_archive.len() as u64;
         for i in 0..input_archive.len() {
-            let mut file = input_archive.by_index(i)?;
-            let name = file.name().to_string();
-            let original_size = file.size();
-
-            // Read file contents
-            let mut contents = Vec::new();
-            file.read_to_end(&mut contents)?;
-            drop(file); // Release the borrow
-
-            original_total += original_size;
-
-            if Self::is_image_file(&name) && !Self::is_webp(&name) {
-                // Convert image to WebP
-                match self.convert_image_to_webp(&contents, &name) {
-                    Ok(webp_data) => {
-                        // Change extension to .webp
-                        let new_name = if let Some(idx) = name.rfind('.') {
-                            format!("{}.webp", &name[..idx])
-                        } else {
-                            format!("{}.webp", name)
-                        };
-
-                        output_archive.start_file(new_name, options)?;
-                        output_archive.write_all(&webp_data)?;
-
-                        compressed_total += webp_data.len() as u64;
-                        files_processed += 1;
-                    }
-                    Err(e) => {
-                        // If conversion fails, copy original file
-                        eprintln!(
-                            "Warning: Failed to convert {}: {}. Copying original.",
-                            name, e
-                        );
-                        output_archive.start_file(name, options)?;
-                        output_archive.write_all(&contents)?;
-                        compressed_total += contents.len() as u64;
-                    }
+            // Checked per entry so cancelling a large archive stops promptly
+            sink.check_cancelled()?;
+
+            let mut entry = input_archive.by_index(i)?;
+            let name = entry.name().to_string();
+            let original_size = entry.size();
+            sink.report(i as u64, total_entries, Some(name.clone()));
+            totals.original += original_size;
+
+            let convertible = Self::is_image_file(&name)
+                && !Self::is_webp(&name)
+                && original_size <= self.max_image_bytes;
+            if convertible {
+                let mut contents = Vec::with_capacity(original_size as usize);
+                entry.read_to_end(&mut contents)?;
+                pending.push((name, contents));
+                if pending.len() >= self.max_in_flight {
+                    self.flush_images(&mut pending, &mut output_archive, options, &mut totals)?;
                 }
             } else {
-                // Copy non-image files or already-WebP files as-is
-                output_archive.start_file(name, options)?;
-                output_archive.write_all(&contents)?;
-                compressed_total += contents.len() as u64;
+                // Queued images precede this entry in the source, so they are
+                // written first to keep the archive order
+                self.flush_images(&mut pending, &mut output_archive, options, &mut totals)?;
+                if entry.is_dir() {
+                    output_archive.add_directory(name, options)?;
+                } else {
+                    // Copy non-image files or already-WebP files as-is,
+                    // without holding the entry in memory
+                    output_archive.start_file(name, options)?;
+                    io::copy(&mut entry, &mut output_archive)?;
+                    totals.compressed += original_size;
+                }
             }
         }
+        sink.check_cancelled()?;
+        self.flush_images(&mut pending, &mut output_archive, options, &mut totals)?;
 
         output_archive.finish()?;
+        sink.report(total_entries, total_entries, None);
 
-        Ok((files_processed, original_total, compressed_total))
+        Ok((totals.files_processed, totals.original, totals.compressed))
     }
 }
 
@@ -184,6 +258,9 @@ impl CompressionPlugin for ImageZipToWebpZipPlugin {
             name: "Image ZIP to WebP ZIP".to_string(),
             description: "Converts images inside ZIP archives to WebP format".to_string(),
             version: "1.0.0".to_string(),
+            lossy: true,
+            destructive: true,
+            requires_external_tools: Vec::new(),
         }
     }
 
@@ -240,6 +317,15 @@ impl CompressionPlugin for ImageZipToWebpZipPlugin {
     }
 
     fn process(&self, source: &Path, output_dir: &Path) -> Result<CompressionResult> {
+        self.process_with_progress(source, output_dir, &ProgressSink::new())
+    }
+
+    fn process_with_progress(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        sink: &ProgressSink,
+    ) -> Result<CompressionResult> {
         let original_size = get_file_size(source)?;
 
         // Generate output filename
@@ -254,12 +340,27 @@ impl CompressionPlugin for ImageZipToWebpZipPlugin {
         // Ensure output directory exists
         fs::create_dir_all(output_dir)?;
 
-        // Process the ZIP file (the output is created with create_new, so an
-        // existing file fails the operation); the manager backs up the
-        // original and moves the output over the source path (replace_source)
-        let (files_processed, _original_total, _compressed_total) = self
-            .process_zip(source, &output_path)
-            .with_context(|| format!("Failed to process ZIP file: {}", source.display()))?;
+        // create_new (O_EXCL): fails instead of overwriting a concurrent
+        // writer's output with the same name
+        let output_file = create_output_file(&output_path)?;
+
+        // Process the ZIP file; the manager backs up the original and moves
+        // the output over the source path (replace_source). The output is
+        // ours from here on, so a failed or cancelled run removes it rather
+        // than leaving a half-written ZIP behind.
+        let (files_processed, _original_total, _compressed_total) =
+            match self.process_zip(source, output_file, sink) {
+                Ok(totals) => totals,
+                Err(e) => {
+                    let _ = fs::remove_file(&output_path);
+                    if e.is::<Cancelled>() {
+                        return Err(e);
+                    }
+                    return Err(
+                        e.context(format!("Failed to process ZIP file: {}", source.display()))
+                    );
+                }
+            };
 
         let compressed_size = get_file_size(&output_path)?;
 
@@ -271,9 +372,33 @@ impl CompressionPlugin for ImageZipToWebpZipPlugin {
             files_processed,
             backup_path: None,
             replace_source: true,
+            metadata_preserved: None,
+            extra_outputs: Vec::new(),
         })
     }
 
+    fn verify_output(&self, source: &Path, result: &CompressionResult) -> Result<bool> {
+        let source_entries = ZipArchive::new(File::open(source)?)?.len();
+        let mut output = ZipArchive::new(File::open(&result.output_path)?)
+            .context("Output is not a readable ZIP archive")?;
+        if output.len() != source_entries {
+            anyhow::bail!(
+                "Output has {} entries but the source has {}",
+                output.len(),
+                source_entries
+            );
+        }
+
+        // Reading each entry to the end makes the zip crate check its CRC
+        for i in 0..output.len() {
+            let mut entry = output.by_index(i)?;
+            let name = entry.name().to_string();
+            std::io::copy(&mut entry, &mut std::io::sink())
+                .with_context(|| format!("Output entry {} is corrupt", name))?;
+        }
+        Ok(true)
+    }
+
     fn supported_extensions(&self) -> Vec<&str> {
         vec!["zip"]
     }
@@ -319,7 +444,7 @@ mod tests {
         let file = File::create(path).unwrap();
         let mut writer = ZipWriter::new(file);
         // Stored, so the noise PNG stays large and conversion shows savings
-        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
         for (name, data) in entries {
             writer.start_file(*name, options).unwrap();
             writer.write_all(data).unwrap();
@@ -336,6 +461,25 @@ mod tests {
         assert!(!ImageZipToWebpZipPlugin::is_image_file("video.mp4"));
     }
 
+    #[test]
+    fn test_verify_output_checks_entry_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = ImageZipToWebpZipPlugin::new();
+        let png = noise_png_bytes(32, 32);
+        let source = dir.path().join("album.zip");
+        build_zip(&source, &[("a.png", &png), ("b.png", &png)]);
+
+        let mut result = plugin.process(&source, &dir.path().join("out")).unwrap();
+        assert!(plugin.verify_output(&source, &result).unwrap());
+
+        // An archive that lost an entry must not pass
+        let truncated = dir.path().join("truncated.zip");
+        build_zip(&truncated, &[("a.webp", b"data")]);
+        result.output_path = truncated;
+        let err = plugin.verify_output(&source, &result).unwrap_err();
+        assert!(err.to_string().contains("1 entries but the source has 2"));
+    }
+
     #[test]
     fn test_is_webp() {
         assert!(ImageZipToWebpZipPlugin::is_webp("photo.webp"));
@@ -373,6 +517,118 @@ mod tests {
         assert!(plugin.can_handle(&fake_zip).is_err());
     }
 
+    #[test]
+    fn test_cancelled_conversion_removes_partial_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photos.zip");
+        let png = noise_png_bytes(32, 32);
+        build_zip(&source, &[("a.png", &png), ("b.png", &png)]);
+
+        let cancel = crate::compress_plugins::CancellationToken::new();
+        cancel.cancel();
+        let sink = ProgressSink::new().with_cancel(cancel);
+        let err = ImageZipToWebpZipPlugin::new()
+            .process_with_progress(&source, dir.path(), &sink)
+            .unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert!(!dir.path().join("photos_webp.zip").exists());
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn test_reports_progress_per_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photos.zip");
+        let png = noise_png_bytes(32, 32);
+        build_zip(&source, &[("a.png", &png), ("b.png", &png)]);
+
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collected = std::sync::Arc::clone(&reports);
+        let sink = ProgressSink::new().on_progress(move |p| {
+            collected
+                .lock()
+                .unwrap()
+                .push((p.current, p.total, p.message.clone()));
+        });
+        ImageZipToWebpZipPlugin::new()
+            .process_with_progress(&source, dir.path(), &sink)
+            .unwrap();
+
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![
+                (0, 2, Some("a.png".to_string())),
+                (1, 2, Some("b.png".to_string())),
+                (2, 2, None),
+            ]
+        );
+    }
+
+    fn entry_names(path: &Path) -> Vec<String> {
+        let mut archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_streams_entries_in_order_across_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("mixed.zip");
+        let png = noise_png_bytes(32, 32);
+        // Larger than any image and not an image, so it is streamed through
+        let video: Vec<u8> = (0..4 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+        build_zip(
+            &source,
+            &[
+                ("a.png", &png),
+                ("b.png", &png),
+                ("c.png", &png),
+                ("clip.mp4", &video),
+                ("d.png", &png),
+                ("e.webp", b"webp"),
+            ],
+        );
+
+        // A batch of two forces flushes both mid-archive and before clip.mp4
+        let plugin = ImageZipToWebpZipPlugin::new()
+            .with_min_image_ratio(0.0)
+            .with_max_in_flight(2);
+        let result = plugin.process(&source, &dir.path().join("out")).unwrap();
+        assert_eq!(result.files_processed, 4);
+        assert!(plugin.verify_output(&source, &result).unwrap());
+        assert_eq!(
+            entry_names(&result.output_path),
+            vec!["a.webp", "b.webp", "c.webp", "clip.mp4", "d.webp", "e.webp"]
+        );
+
+        let mut archive = ZipArchive::new(File::open(&result.output_path).unwrap()).unwrap();
+        let mut copied = Vec::new();
+        archive
+            .by_name("clip.mp4")
+            .unwrap()
+            .read_to_end(&mut copied)
+            .unwrap();
+        assert_eq!(copied, video);
+    }
+
+    #[test]
+    fn test_oversized_images_are_copied_unconverted() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photos.zip");
+        let small = noise_png_bytes(16, 16);
+        let large = noise_png_bytes(64, 64);
+        build_zip(&source, &[("small.png", &small), ("large.png", &large)]);
+
+        let plugin = ImageZipToWebpZipPlugin::new().with_max_image_bytes(small.len() as u64);
+        let result = plugin.process(&source, &dir.path().join("out")).unwrap();
+        assert_eq!(result.files_processed, 1);
+        assert_eq!(
+            entry_names(&result.output_path),
+            vec!["small.webp", "large.png"]
+        );
+    }
+
     #[test]
     fn test_end_to_end_manager_replaces_zip_in_place() {
         let dir = tempfile::tempdir().unwrap();
@@ -385,7 +641,7 @@ mod tests {
         manager.register(Box::new(ImageZipToWebpZipPlugin::new()));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
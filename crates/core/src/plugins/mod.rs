@@ -1,7 +1,13 @@
 pub mod animated_webp_converter;
+pub(crate) mod external_tool;
 pub mod image_zip_to_webp;
+pub mod raw_converter;
+pub mod text_minifier;
 pub mod webp_converter;
+pub mod webp_metadata;
 
 pub use animated_webp_converter::AnimatedWebPConverterPlugin;
 pub use image_zip_to_webp::ImageZipToWebpZipPlugin;
+pub use raw_converter::{RawPolicy, RawToJpegPlugin};
+pub use text_minifier::{MinifyOptions, TextMinifierPlugin};
 pub use webp_converter::WebPConverterPlugin;
@@ -0,0 +1,1218 @@
+//! Convert camera RAW files (CR2/NEF/ARW) to JPEG.
+//!
+//! With dcraw available the RAW is developed in full and encoded at the
+//! plugin's quality. Without it the full-size JPEG preview that these
+//! TIFF-based formats embed is extracted byte for byte, so no pixels are
+//! re-encoded. Either way the camera, capture date and orientation are
+//! written into the JPEG's EXIF. The RAW itself is handled by policy:
+//! deleted (or backed up) like any other original, or additionally kept in
+//! a zstd-compressed ZIP next to the JPEG.
+
+use super::external_tool::{new_command, run_tool};
+use crate::compress_plugins::{
+    create_output_file, has_extension, CancellationToken, Cancelled, CompressionPlugin,
+    CompressionResult, PluginMetadata, PluginRequirements, ProgressSink, ToolCheck, ToolLimits,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tracing::{info, warn};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+const DCRAW: &str = "dcraw";
+
+// TIFF tags
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+// TIFF field types
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_IFD: u16 = 13;
+
+/// Old-style JPEG compression, used by CR2 for both previews and raw data
+const COMPRESSION_OJPEG: u32 = 6;
+
+/// Guard against IFD chains that loop or fan out in a corrupt file
+const MAX_IFDS: usize = 64;
+
+/// What happens to the RAW once its JPEG is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawPolicy {
+    /// Handle the RAW like any converted original: kept as a backup or
+    /// deleted, as the compression run asks
+    #[default]
+    Delete,
+    /// Also store the RAW in a zstd-compressed `<name>.zip` next to the JPEG
+    Archive,
+}
+
+/// Camera details copied from the RAW's TIFF structure
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawMetadata {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub orientation: Option<u16>,
+    pub date_time: Option<String>,
+    pub date_time_original: Option<String>,
+    /// Whether a GPS IFD with at least one tag is present. Never written
+    /// back: the location stays in the RAW.
+    pub has_gps: bool,
+}
+
+/// A JPEG stream embedded in a RAW file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddedJpeg {
+    pub offset: u64,
+    pub length: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One IFD entry; `value_pos` is the file offset of its 4-byte value field
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value_pos: u64,
+}
+
+/// Random-access reader over a TIFF structure. Only the IFDs and the bytes
+/// asked for are read, so probing a 50 MB RAW touches a few kilobytes.
+struct TiffReader<R> {
+    reader: R,
+    little_endian: bool,
+    len: u64,
+}
+
+impl<R: Read + Seek> TiffReader<R> {
+    /// Open the TIFF header, returning the reader and the first IFD offset
+    fn new(mut reader: R) -> Option<(Self, u32)> {
+        let len = reader.seek(SeekFrom::End(0)).ok()?;
+        let mut tiff = Self {
+            reader,
+            little_endian: true,
+            len,
+        };
+        let header = tiff.read_at(0, 8)?;
+        tiff.little_endian = match &header[..4] {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return None,
+        };
+        let first = tiff.u32_from(&header[4..8]);
+        Some((tiff, first))
+    }
+
+    fn read_at(&mut self, offset: u64, len: usize) -> Option<Vec<u8>> {
+        if offset.checked_add(len as u64)? > self.len {
+            return None;
+        }
+        self.reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut buf = vec![0; len];
+        self.reader.read_exact(&mut buf).ok()?;
+        Some(buf)
+    }
+
+    fn u16_from(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+
+    fn u32_from(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+
+    /// Entries of the IFD at `offset` and the offset of the next IFD (0 at
+    /// the end of the chain)
+    fn ifd(&mut self, offset: u32) -> Option<(Vec<Entry>, u32)> {
+        let count = self.read_at(offset as u64, 2)?;
+        let count = self.u16_from(&count) as usize;
+        let body = self.read_at(offset as u64 + 2, count * 12 + 4)?;
+        let entries = body[..count * 12]
+            .chunks_exact(12)
+            .enumerate()
+            .map(|(i, raw)| Entry {
+                tag: self.u16_from(&raw[0..2]),
+                kind: self.u16_from(&raw[2..4]),
+                count: self.u32_from(&raw[4..8]),
+                value_pos: offset as u64 + 2 + i as u64 * 12 + 8,
+            })
+            .collect();
+        let next = self.u32_from(&body[count * 12..]);
+        Some((entries, next))
+    }
+
+    /// Raw value bytes of an entry, following the offset when they don't fit
+    /// in the 4-byte value field
+    fn value_bytes(&mut self, entry: &Entry) -> Option<Vec<u8>> {
+        let size = match entry.kind {
+            TYPE_ASCII => 1,
+            TYPE_SHORT => 2,
+            TYPE_LONG | TYPE_IFD => 4,
+            _ => return None,
+        };
+        let total = (entry.count as usize).checked_mul(size)?;
+        if total > 1 << 16 {
+            return None;
+        }
+        if total <= 4 {
+            let field = self.read_at(entry.value_pos, 4)?;
+            Some(field[..total].to_vec())
+        } else {
+            let pointer = self.read_at(entry.value_pos, 4)?;
+            let pointer = self.u32_from(&pointer);
+            self.read_at(pointer as u64, total)
+        }
+    }
+
+    /// Integer values of a SHORT/LONG/IFD entry
+    fn values(&mut self, entry: &Entry) -> Vec<u32> {
+        let Some(bytes) = self.value_bytes(entry) else {
+            return Vec::new();
+        };
+        match entry.kind {
+            TYPE_SHORT => bytes
+                .chunks_exact(2)
+                .map(|b| self.u16_from(b) as u32)
+                .collect(),
+            TYPE_LONG | TYPE_IFD => bytes.chunks_exact(4).map(|b| self.u32_from(b)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn first_value(&mut self, entry: &Entry) -> Option<u32> {
+        self.values(entry).first().copied()
+    }
+
+    fn ascii(&mut self, entry: &Entry) -> Option<String> {
+        if entry.kind != TYPE_ASCII {
+            return None;
+        }
+        let bytes = self.value_bytes(entry)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// Every baseline or progressive JPEG referenced from any IFD. Lossless
+    /// JPEG streams (CR2 sensor data) are skipped since they aren't viewable.
+    fn embedded_jpegs(&mut self, first_ifd: u32) -> Vec<EmbeddedJpeg> {
+        let mut found = Vec::new();
+        let mut queue = vec![first_ifd];
+        let mut visited = HashSet::new();
+        while let Some(offset) = queue.pop() {
+            if offset == 0 || visited.len() >= MAX_IFDS || !visited.insert(offset) {
+                continue;
+            }
+            let Some((entries, next)) = self.ifd(offset) else {
+                continue;
+            };
+            queue.push(next);
+
+            let mut compression = None;
+            let mut strip = (None, None);
+            let mut jpeg = (None, None);
+            for entry in &entries {
+                match entry.tag {
+                    TAG_COMPRESSION => compression = self.first_value(entry),
+                    TAG_STRIP_OFFSETS if entry.count == 1 => strip.0 = self.first_value(entry),
+                    TAG_STRIP_BYTE_COUNTS if entry.count == 1 => strip.1 = self.first_value(entry),
+                    TAG_JPEG_OFFSET => jpeg.0 = self.first_value(entry),
+                    TAG_JPEG_LENGTH => jpeg.1 = self.first_value(entry),
+                    TAG_SUB_IFDS => queue.extend(self.values(entry)),
+                    _ => {}
+                }
+            }
+
+            let mut candidates = Vec::new();
+            if let (Some(offset), Some(length)) = jpeg {
+                candidates.push((offset, length));
+            }
+            if let (Some(COMPRESSION_OJPEG), (Some(offset), Some(length))) = (compression, strip) {
+                candidates.push((offset, length));
+            }
+            for (offset, length) in candidates {
+                if let Some((width, height)) = self.jpeg_dimensions(offset as u64, length as u64) {
+                    found.push(EmbeddedJpeg {
+                        offset: offset as u64,
+                        length: length as u64,
+                        width,
+                        height,
+                    });
+                }
+            }
+        }
+        found
+    }
+
+    /// Dimensions from the SOF segment of a JPEG stream, if it is baseline
+    /// or progressive
+    fn jpeg_dimensions(&mut self, offset: u64, length: u64) -> Option<(u32, u32)> {
+        if length < 4 || offset.checked_add(length)? > self.len {
+            return None;
+        }
+        // Previews may carry large APP segments (maker notes, MPF) before
+        // the frame header
+        let head = self.read_at(offset, length.min(256 * 1024) as usize)?;
+        if !head.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+        let mut pos = 2;
+        while pos + 4 <= head.len() {
+            if head[pos] != 0xFF {
+                return None;
+            }
+            let marker = head[pos + 1];
+            if marker == 0xFF {
+                pos += 1;
+                continue;
+            }
+            let segment_len = u16::from_be_bytes([head[pos + 2], head[pos + 3]]) as usize;
+            match marker {
+                // SOF0-2: baseline, extended and progressive Huffman
+                0xC0..=0xC2 if pos + 9 <= head.len() => {
+                    let height = u16::from_be_bytes([head[pos + 5], head[pos + 6]]) as u32;
+                    let width = u16::from_be_bytes([head[pos + 7], head[pos + 8]]) as u32;
+                    return (width > 0 && height > 0).then_some((width, height));
+                }
+                // Any other frame type (lossless, arithmetic) or start of scan
+                0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => return None,
+                _ => pos += 2 + segment_len,
+            }
+        }
+        None
+    }
+
+    /// Camera details from IFD0 and its EXIF sub-IFD
+    fn metadata(&mut self, first_ifd: u32) -> RawMetadata {
+        let mut meta = RawMetadata::default();
+        let Some((entries, _)) = self.ifd(first_ifd) else {
+            return meta;
+        };
+        let mut exif_ifd = None;
+        for entry in &entries {
+            match entry.tag {
+                TAG_MAKE => meta.make = self.ascii(entry),
+                TAG_MODEL => meta.model = self.ascii(entry),
+                TAG_ORIENTATION => {
+                    meta.orientation = self
+                        .first_value(entry)
+                        .and_then(|v| u16::try_from(v).ok())
+                        .filter(|v| (1..=8).contains(v))
+                }
+                TAG_DATE_TIME => meta.date_time = self.ascii(entry),
+                TAG_EXIF_IFD => exif_ifd = self.first_value(entry),
+                TAG_GPS_IFD => {
+                    meta.has_gps = self
+                        .first_value(entry)
+                        .and_then(|offset| self.ifd(offset))
+                        .is_some_and(|(entries, _)| !entries.is_empty())
+                }
+                _ => {}
+            }
+        }
+        if let Some((entries, _)) = exif_ifd.and_then(|offset| self.ifd(offset)) {
+            if let Some(entry) = entries.iter().find(|e| e.tag == TAG_DATE_TIME_ORIGINAL) {
+                meta.date_time_original = self.ascii(entry);
+            }
+        }
+        meta
+    }
+}
+
+/// The largest viewable JPEG embedded in a RAW file
+pub fn largest_embedded_jpeg(path: &Path) -> Result<Option<EmbeddedJpeg>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let Some((mut tiff, first_ifd)) = TiffReader::new(BufReader::new(file)) else {
+        return Ok(None);
+    };
+    Ok(tiff
+        .embedded_jpegs(first_ifd)
+        .into_iter()
+        .max_by_key(|jpeg| jpeg.width as u64 * jpeg.height as u64))
+}
+
+/// Camera details from a RAW file (or any TIFF structure, such as EXIF)
+pub fn read_raw_metadata<R: Read + Seek>(reader: R) -> RawMetadata {
+    match TiffReader::new(reader) {
+        Some((mut tiff, first_ifd)) => tiff.metadata(first_ifd),
+        None => RawMetadata::default(),
+    }
+}
+
+/// Build a little-endian EXIF TIFF block from the camera details. With
+/// `include_camera` false only the orientation is kept, which viewers need
+/// to display the image upright. Returns None when there is nothing to write.
+fn build_exif(meta: &RawMetadata, include_camera: bool) -> Option<Vec<u8>> {
+    fn ascii(tag: u16, text: &str) -> (u16, u16, u32, Vec<u8>) {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        (tag, TYPE_ASCII, bytes.len() as u32, bytes)
+    }
+
+    let mut ifd0 = Vec::new();
+    if include_camera {
+        if let Some(make) = &meta.make {
+            ifd0.push(ascii(TAG_MAKE, make));
+        }
+        if let Some(model) = &meta.model {
+            ifd0.push(ascii(TAG_MODEL, model));
+        }
+    }
+    if let Some(orientation) = meta.orientation {
+        ifd0.push((
+            TAG_ORIENTATION,
+            TYPE_SHORT,
+            1,
+            orientation.to_le_bytes().to_vec(),
+        ));
+    }
+    if include_camera {
+        if let Some(date) = &meta.date_time {
+            ifd0.push(ascii(TAG_DATE_TIME, date));
+        }
+    }
+    let exif_ifd: Vec<_> = match (&meta.date_time_original, include_camera) {
+        (Some(date), true) => vec![ascii(TAG_DATE_TIME_ORIGINAL, date)],
+        _ => Vec::new(),
+    };
+    if !exif_ifd.is_empty() {
+        // Placeholder; patched once IFD0's size is known
+        ifd0.push((TAG_EXIF_IFD, TYPE_LONG, 1, vec![0; 4]));
+    }
+    if ifd0.is_empty() {
+        return None;
+    }
+
+    let mut out = b"II*\0".to_vec();
+    out.extend_from_slice(&8u32.to_le_bytes());
+    let ifd0_bytes = encode_ifd(&ifd0, out.len());
+    if !exif_ifd.is_empty() {
+        let exif_offset = (out.len() + ifd0_bytes.len()) as u32;
+        let last = ifd0.len() - 1;
+        ifd0[last].3 = exif_offset.to_le_bytes().to_vec();
+        out.extend_from_slice(&encode_ifd(&ifd0, out.len()));
+        let start = out.len();
+        out.extend_from_slice(&encode_ifd(&exif_ifd, start));
+    } else {
+        out.extend_from_slice(&ifd0_bytes);
+    }
+    Some(out)
+}
+
+/// Encode one little-endian IFD starting at file offset `start`, with values
+/// longer than four bytes stored right after it. Entries must be sorted by tag.
+fn encode_ifd(entries: &[(u16, u16, u32, Vec<u8>)], start: usize) -> Vec<u8> {
+    let mut table = Vec::new();
+    let mut data = Vec::new();
+    let data_start = start + 2 + entries.len() * 12 + 4;
+    table.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (tag, kind, count, value) in entries {
+        table.extend_from_slice(&tag.to_le_bytes());
+        table.extend_from_slice(&kind.to_le_bytes());
+        table.extend_from_slice(&count.to_le_bytes());
+        if value.len() <= 4 {
+            let mut field = value.clone();
+            field.resize(4, 0);
+            table.extend_from_slice(&field);
+        } else {
+            let offset = (data_start + data.len()) as u32;
+            table.extend_from_slice(&offset.to_le_bytes());
+            data.extend_from_slice(value);
+            if data.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+    }
+    table.extend_from_slice(&0u32.to_le_bytes());
+    table.extend_from_slice(&data);
+    table
+}
+
+/// Put `exif` in an APP1 segment right after SOI, dropping any EXIF segment
+/// the JPEG already had
+fn insert_exif(jpeg: &[u8], exif: &[u8]) -> Result<Vec<u8>> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        bail!("Not a JPEG stream");
+    }
+    let segment_len = u16::try_from(2 + 6 + exif.len()).context("EXIF block too large")?;
+    let mut out = Vec::with_capacity(jpeg.len() + exif.len() + 10);
+    out.extend_from_slice(&[0xFF, 0xD8, 0xFF, 0xE1]);
+    out.extend_from_slice(&segment_len.to_be_bytes());
+    out.extend_from_slice(b"Exif\0\0");
+    out.extend_from_slice(exif);
+
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() && jpeg[pos] == 0xFF && (0xE0..=0xEF).contains(&jpeg[pos + 1]) {
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let end = (pos + 2 + len).min(jpeg.len());
+        let is_exif = jpeg[pos + 1] == 0xE1 && jpeg[pos + 4..end].starts_with(b"Exif\0\0");
+        if !is_exif {
+            out.extend_from_slice(&jpeg[pos..end]);
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&jpeg[pos..]);
+    Ok(out)
+}
+
+/// Health-check dcraw. It has no version flag; run bare, it prints a usage
+/// banner naming itself and exits non-zero.
+fn probe_dcraw(program: &Path) -> std::result::Result<String, String> {
+    let output = new_command(program)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    text.lines()
+        .map(str::trim)
+        .find(|l| l.contains("dcraw"))
+        .map(str::to_string)
+        .ok_or_else(|| format!("Not dcraw (exited with {})", output.status))
+}
+
+/// Plugin converting camera RAW files to JPEG
+pub struct RawToJpegPlugin {
+    quality: f32,
+    preserve_metadata: bool,
+    policy: RawPolicy,
+    /// Smallest embedded preview (long edge, pixels) worth keeping when
+    /// dcraw isn't available
+    min_preview_edge: u32,
+    tool_paths: BTreeMap<String, PathBuf>,
+    limits: ToolLimits,
+    /// Working dcraw, probed on first use and again after the tool paths
+    /// change
+    dcraw: OnceCell<Option<PathBuf>>,
+}
+
+impl RawToJpegPlugin {
+    pub fn new() -> Self {
+        Self {
+            quality: 92.0,
+            preserve_metadata: true,
+            policy: RawPolicy::default(),
+            min_preview_edge: 2000,
+            tool_paths: BTreeMap::new(),
+            limits: ToolLimits::default(),
+            dcraw: OnceCell::new(),
+        }
+    }
+
+    pub fn with_quality(mut self, quality: f32) -> Self {
+        self.quality = quality.clamp(0.0, 100.0);
+        self
+    }
+
+    pub fn with_preserve_metadata(mut self, enabled: bool) -> Self {
+        self.preserve_metadata = enabled;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: RawPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_min_preview_edge(mut self, pixels: u32) -> Self {
+        self.min_preview_edge = pixels;
+        self
+    }
+
+    /// Run dcraw from `path` instead of looking it up on PATH
+    pub fn with_tool_path(mut self, tool: &str, path: impl Into<PathBuf>) -> Self {
+        self.tool_paths.insert(tool.to_string(), path.into());
+        self.dcraw = OnceCell::new();
+        self
+    }
+
+    fn program(&self) -> PathBuf {
+        self.tool_paths
+            .get(DCRAW)
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(DCRAW))
+    }
+
+    fn available_dcraw(&self) -> Option<&PathBuf> {
+        self.dcraw
+            .get_or_init(|| {
+                let program = self.program();
+                probe_dcraw(&program).ok().map(|_| program)
+            })
+            .as_ref()
+    }
+
+    /// The embedded preview if it is large enough to stand in for the RAW
+    fn usable_preview(&self, path: &Path) -> Result<std::result::Result<EmbeddedJpeg, String>> {
+        Ok(match largest_embedded_jpeg(path)? {
+            Some(jpeg) if jpeg.width.max(jpeg.height) >= self.min_preview_edge => Ok(jpeg),
+            Some(jpeg) => Err(format!(
+                "Embedded preview is only {}x{}; install dcraw to convert this RAW",
+                jpeg.width, jpeg.height
+            )),
+            None => Err("No embedded JPEG preview; install dcraw to convert this RAW".to_string()),
+        })
+    }
+
+    /// Develop the RAW with dcraw and encode it at the plugin's quality.
+    /// dcraw applies the camera orientation itself.
+    fn develop_with_dcraw(
+        &self,
+        program: &Path,
+        source: &Path,
+        temp_ppm: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<u8>> {
+        info!("Developing {} with dcraw", source.display());
+        let ppm = File::create(temp_ppm)?;
+        let mut cmd = new_command(program);
+        self.limits.apply(&mut cmd);
+        // -c: write to stdout, -w: camera white balance
+        cmd.arg("-c").arg("-w").arg(source);
+        let outcome = run_tool(cmd, Stdio::from(ppm), cancel).and_then(|(status, stderr)| {
+            if !status.success() {
+                bail!("dcraw failed: {}", stderr.trim());
+            }
+            let image = image::open(temp_ppm).context("dcraw produced an unreadable image")?;
+            let mut jpeg = Vec::new();
+            JpegEncoder::new_with_quality(&mut jpeg, self.quality.round() as u8)
+                .encode_image(&image)?;
+            Ok(jpeg)
+        });
+        let _ = fs::remove_file(temp_ppm);
+        outcome
+    }
+
+    /// Store the RAW in a zstd-compressed ZIP at `archive_path`, removing a
+    /// partial archive on failure
+    fn archive_raw(
+        &self,
+        source: &Path,
+        archive_path: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<u64> {
+        let file = create_output_file(archive_path)?;
+        let written = Self::write_archive(file, source, cancel);
+        if written.is_err() {
+            let _ = fs::remove_file(archive_path);
+        }
+        written?;
+        Ok(fs::metadata(archive_path)?.len())
+    }
+
+    fn write_archive(file: File, source: &Path, cancel: &CancellationToken) -> Result<()> {
+        let name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("RAW file name is not valid UTF-8"))?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Zstd)
+            .large_file(fs::metadata(source)?.len() >= u32::MAX as u64);
+        zip.start_file(name, options)?;
+
+        let mut input = File::open(source)?;
+        let mut buf = vec![0; 1 << 20];
+        loop {
+            if cancel.is_cancelled() {
+                return Err(Cancelled.into());
+            }
+            let read = input.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            zip.write_all(&buf[..read])?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+impl Default for RawToJpegPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressionPlugin for RawToJpegPlugin {
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "RAW to JPEG".to_string(),
+            description: "Convert camera RAW files (CR2, NEF, ARW) to high-quality JPEG"
+                .to_string(),
+            version: "1.0.0".to_string(),
+            lossy: true,
+            destructive: false,
+            requires_external_tools: vec![DCRAW.to_string()],
+        }
+    }
+
+    fn can_handle(&self, path: &Path) -> Result<(bool, Option<String>)> {
+        if !has_extension(path, &self.supported_extensions()) {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            return Ok((
+                false,
+                Some(format!("Not a camera RAW file (extension: {})", ext)),
+            ));
+        }
+        if self.available_dcraw().is_some() {
+            return Ok((
+                true,
+                Some("Camera RAW for JPEG conversion (dcraw)".to_string()),
+            ));
+        }
+        Ok(match self.usable_preview(path)? {
+            Ok(jpeg) => (
+                true,
+                Some(format!(
+                    "Camera RAW; its embedded {}x{} JPEG will be extracted",
+                    jpeg.width, jpeg.height
+                )),
+            ),
+            Err(reason) => (false, Some(reason)),
+        })
+    }
+
+    fn estimate_ratio(&self, path: &Path) -> Result<Option<f32>> {
+        if self.policy == RawPolicy::Archive {
+            // The archive keeps most of the RAW's size
+            return Ok(Some(0.95));
+        }
+        if self.available_dcraw().is_none() {
+            if let Some(jpeg) = largest_embedded_jpeg(path)? {
+                let size = fs::metadata(path)?.len().max(1);
+                return Ok(Some((jpeg.length as f32 / size as f32).min(1.0)));
+            }
+        }
+        // A full-size JPEG is typically a fifth to a third of the RAW
+        Ok(Some(0.25))
+    }
+
+    fn process(&self, source: &Path, output_dir: &Path) -> Result<CompressionResult> {
+        self.process_with_progress(source, output_dir, &ProgressSink::new())
+    }
+
+    fn process_with_progress(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        sink: &ProgressSink,
+    ) -> Result<CompressionResult> {
+        let cancel = sink.cancel_token();
+        if !source.exists() {
+            bail!("Source file does not exist: {}", source.display());
+        }
+        let original_size = fs::metadata(source)?.len();
+        let file_name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("RAW file name is not valid UTF-8"))?;
+        let stem = source
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let output_path = output_dir.join(format!("{}.jpg", stem));
+        let archive_path = output_dir.join(format!("{}.zip", file_name));
+        let steps = if self.policy == RawPolicy::Archive {
+            2
+        } else {
+            1
+        };
+
+        let mut output = create_output_file(&output_path)?;
+        sink.report(0, steps, None);
+
+        let raw_meta = read_raw_metadata(BufReader::new(File::open(source)?));
+        let convert = || -> Result<(Vec<u8>, Option<bool>)> {
+            let (jpeg, meta) = match self.available_dcraw() {
+                Some(program) => {
+                    let temp_ppm = output_dir.join(format!("{}.dcraw_temp.ppm", stem));
+                    let jpeg = self.develop_with_dcraw(program, source, &temp_ppm, cancel)?;
+                    // Already rotated by dcraw
+                    let meta = RawMetadata {
+                        orientation: None,
+                        ..raw_meta.clone()
+                    };
+                    (jpeg, meta)
+                }
+                None => {
+                    let preview = self
+                        .usable_preview(source)?
+                        .map_err(|reason| anyhow!(reason))?;
+                    let mut file = File::open(source)?;
+                    file.seek(SeekFrom::Start(preview.offset))?;
+                    let mut jpeg = vec![0; preview.length as usize];
+                    file.read_exact(&mut jpeg)?;
+                    (jpeg, raw_meta.clone())
+                }
+            };
+            let has_camera = meta.make.is_some()
+                || meta.model.is_some()
+                || meta.date_time.is_some()
+                || meta.date_time_original.is_some();
+            let jpeg = match build_exif(&meta, self.preserve_metadata) {
+                Some(exif) => insert_exif(&jpeg, &exif)?,
+                None => jpeg,
+            };
+            Ok((jpeg, has_camera.then_some(self.preserve_metadata)))
+        };
+
+        let outcome = convert().and_then(|(jpeg, metadata_preserved)| {
+            output.write_all(&jpeg)?;
+            output.sync_all()?;
+            sink.report(1, steps, None);
+            let mut compressed_size = jpeg.len() as u64;
+            let mut extra_outputs = Vec::new();
+            if self.policy == RawPolicy::Archive {
+                extra_outputs.push(archive_path.clone());
+                compressed_size += self.archive_raw(source, &archive_path, cancel)?;
+                sink.report(2, steps, None);
+            }
+            Ok((compressed_size, metadata_preserved, extra_outputs))
+        });
+
+        match outcome {
+            Ok((compressed_size, metadata_preserved, extra_outputs)) => {
+                info!(
+                    source = %source.display(),
+                    original_size,
+                    compressed_size,
+                    "RAW converted to JPEG"
+                );
+                Ok(CompressionResult {
+                    original_size,
+                    compressed_size,
+                    output_path,
+                    plugin_name: self.metadata().name,
+                    files_processed: 1,
+                    backup_path: None,
+                    replace_source: false,
+                    metadata_preserved,
+                    extra_outputs,
+                })
+            }
+            Err(e) => {
+                drop(output);
+                let _ = fs::remove_file(&output_path);
+                if e.is::<Cancelled>() {
+                    return Err(e);
+                }
+                warn!(source = %source.display(), error = %e, "RAW conversion failed");
+                Err(anyhow!("RAW conversion failed: {}", e))
+            }
+        }
+    }
+
+    fn verify_output(&self, source: &Path, result: &CompressionResult) -> Result<bool> {
+        let jpeg = image::open(&result.output_path).context("Output is not a decodable JPEG")?;
+        if jpeg.width() == 0 || jpeg.height() == 0 {
+            bail!("Output JPEG is empty");
+        }
+
+        for archive_path in &result.extra_outputs {
+            let mut archive = ZipArchive::new(File::open(archive_path)?)
+                .with_context(|| format!("{} is not a readable ZIP", archive_path.display()))?;
+            let mut entry = archive.by_index(0)?;
+            let expected = fs::metadata(source)?.len();
+            if entry.size() != expected {
+                bail!(
+                    "Archived RAW is {} bytes but the source is {} bytes",
+                    entry.size(),
+                    expected
+                );
+            }
+            // Reading to the end checks the entry's CRC
+            io::copy(&mut entry, &mut io::sink()).context("Archived RAW is corrupt")?;
+        }
+        Ok(true)
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        vec!["cr2", "nef", "arw"]
+    }
+
+    fn quality(&self) -> Option<f32> {
+        Some(self.quality)
+    }
+
+    fn set_quality(&mut self, quality: f32) -> bool {
+        self.quality = quality.clamp(0.0, 100.0);
+        true
+    }
+
+    fn preserve_metadata(&self) -> Option<bool> {
+        Some(self.preserve_metadata)
+    }
+
+    fn set_preserve_metadata(&mut self, enabled: bool) -> bool {
+        self.preserve_metadata = enabled;
+        true
+    }
+
+    fn set_raw_policy(&mut self, policy: RawPolicy) -> bool {
+        self.policy = policy;
+        true
+    }
+
+    fn check_requirements(&self) -> PluginRequirements {
+        let program = self.program();
+        let probe = probe_dcraw(&program);
+        let available = probe.is_ok();
+        let tool = ToolCheck {
+            name: DCRAW.to_string(),
+            program: program.display().to_string(),
+            available,
+            version: probe.as_ref().ok().cloned(),
+            error: probe.err(),
+        };
+        let message = if available {
+            "RAW files are developed in full with dcraw"
+        } else {
+            "dcraw not found; RAWs with a full-size embedded preview are converted from it"
+        };
+        PluginRequirements {
+            plugin_name: self.metadata().name,
+            tools: vec![tool],
+            ready: true,
+            message: Some(message.to_string()),
+        }
+    }
+
+    fn set_tool_paths(&mut self, paths: &BTreeMap<String, PathBuf>) {
+        self.tool_paths = paths.clone();
+        self.dcraw = OnceCell::new();
+    }
+
+    fn set_tool_limits(&mut self, limits: ToolLimits) {
+        self.limits = limits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress_plugins::{CompressionOutcome, PluginManager};
+    use crate::plugins::webp_metadata::read_metadata;
+    use image::{ImageBuffer, Rgb};
+    use std::io::Cursor;
+
+    /// Plugin whose dcraw points at a path that doesn't exist, forcing
+    /// preview extraction whatever is installed on the machine
+    fn plugin_without_dcraw(dir: &Path) -> RawToJpegPlugin {
+        RawToJpegPlugin::new()
+            .with_tool_path(DCRAW, dir.join("no-dcraw"))
+            .with_min_preview_edge(64)
+    }
+
+    fn jpeg_bytes(width: u32, height: u32) -> Vec<u8> {
+        let img = ImageBuffer::from_fn(width, height, |x, y| {
+            Rgb([(x * 255 / width) as u8, (y * 255 / height) as u8, 128])
+        });
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, 90)
+            .encode_image(&img)
+            .unwrap();
+        out
+    }
+
+    /// A minimal CR2-like file: IFD0 holds the camera tags and a full-size
+    /// old-style-JPEG strip, a SubIFD points at a small thumbnail, and a
+    /// block of incompressible "sensor data" pads the file out
+    fn write_raw(path: &Path, preview: (u32, u32)) -> Vec<u8> {
+        let large = jpeg_bytes(preview.0, preview.1);
+        let thumb = jpeg_bytes(16, 12);
+        let mut sensor = vec![0u8; 200_000];
+        let mut seed = 0x2545_f491u32;
+        for byte in &mut sensor {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            *byte = seed as u8;
+        }
+
+        let meta = RawMetadata {
+            make: Some("Canon".to_string()),
+            model: Some("Canon EOS Test".to_string()),
+            orientation: Some(6),
+            date_time: Some("2024:05:01 10:00:00".to_string()),
+            date_time_original: Some("2024:05:01 09:59:58".to_string()),
+            has_gps: false,
+        };
+        // Reuse the EXIF builder for the camera tags, then append the image
+        // and SubIFD pointers to a second IFD0 written after the data
+        let exif = build_exif(&meta, true).unwrap();
+        let mut file = exif.clone();
+        let large_offset = file.len() as u32;
+        file.extend_from_slice(&large);
+        let thumb_offset = file.len() as u32;
+        file.extend_from_slice(&thumb);
+        file.extend_from_slice(&sensor);
+
+        let sub_ifd_offset = file.len() as u32;
+        let sub_ifd = vec![
+            (
+                TAG_JPEG_OFFSET,
+                TYPE_LONG,
+                1,
+                thumb_offset.to_le_bytes().to_vec(),
+            ),
+            (
+                TAG_JPEG_LENGTH,
+                TYPE_LONG,
+                1,
+                (thumb.len() as u32).to_le_bytes().to_vec(),
+            ),
+        ];
+        file.extend_from_slice(&encode_ifd(&sub_ifd, sub_ifd_offset as usize));
+
+        // Copy IFD0's entries, adding the strip and SubIFD tags in tag order
+        let (mut tiff, first) = TiffReader::new(Cursor::new(exif.clone())).unwrap();
+        let (entries, _) = tiff.ifd(first).unwrap();
+        let mut ifd0: Vec<(u16, u16, u32, Vec<u8>)> = entries
+            .iter()
+            .map(|e| {
+                let value = if e.kind == TYPE_ASCII || e.tag == TAG_ORIENTATION {
+                    tiff.value_bytes(e).unwrap()
+                } else {
+                    tiff.read_at(e.value_pos, 4).unwrap()
+                };
+                (e.tag, e.kind, e.count, value)
+            })
+            .collect();
+        ifd0.push((TAG_COMPRESSION, TYPE_SHORT, 1, 6u16.to_le_bytes().to_vec()));
+        ifd0.push((
+            TAG_STRIP_OFFSETS,
+            TYPE_LONG,
+            1,
+            large_offset.to_le_bytes().to_vec(),
+        ));
+        ifd0.push((
+            TAG_STRIP_BYTE_COUNTS,
+            TYPE_LONG,
+            1,
+            (large.len() as u32).to_le_bytes().to_vec(),
+        ));
+        ifd0.push((
+            TAG_SUB_IFDS,
+            TYPE_LONG,
+            1,
+            sub_ifd_offset.to_le_bytes().to_vec(),
+        ));
+        ifd0.sort_by_key(|e| e.0);
+        let ifd0_offset = file.len() as u32;
+        file.extend_from_slice(&encode_ifd(&ifd0, ifd0_offset as usize));
+        file[4..8].copy_from_slice(&ifd0_offset.to_le_bytes());
+
+        fs::write(path, &file).unwrap();
+        large
+    }
+
+    #[test]
+    fn test_finds_largest_embedded_jpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0001.CR2");
+        write_raw(&raw, (160, 120));
+
+        let jpeg = largest_embedded_jpeg(&raw).unwrap().unwrap();
+        assert_eq!((jpeg.width, jpeg.height), (160, 120));
+    }
+
+    #[test]
+    fn test_reads_camera_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0001.CR2");
+        write_raw(&raw, (160, 120));
+
+        let meta = read_raw_metadata(File::open(&raw).unwrap());
+        assert_eq!(meta.make.as_deref(), Some("Canon"));
+        assert_eq!(meta.model.as_deref(), Some("Canon EOS Test"));
+        assert_eq!(meta.orientation, Some(6));
+        assert_eq!(
+            meta.date_time_original.as_deref(),
+            Some("2024:05:01 09:59:58")
+        );
+    }
+
+    #[test]
+    fn test_can_handle() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0001.nef");
+        write_raw(&raw, (160, 120));
+        let plugin = plugin_without_dcraw(dir.path());
+
+        let (ok, reason) = plugin.can_handle(&raw).unwrap();
+        assert!(ok);
+        assert!(reason.unwrap().contains("160x120"));
+
+        let (ok, reason) = plugin.with_min_preview_edge(1000).can_handle(&raw).unwrap();
+        assert!(!ok);
+        assert!(reason.unwrap().contains("install dcraw"));
+
+        let other = dir.path().join("photo.jpg");
+        fs::write(&other, jpeg_bytes(8, 8)).unwrap();
+        let (ok, _) = plugin_without_dcraw(dir.path()).can_handle(&other).unwrap();
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_not_a_tiff_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("broken.arw");
+        fs::write(&raw, b"definitely not a raw file").unwrap();
+
+        let (ok, reason) = plugin_without_dcraw(dir.path()).can_handle(&raw).unwrap();
+        assert!(!ok);
+        assert!(reason.unwrap().contains("No embedded JPEG preview"));
+    }
+
+    #[test]
+    fn test_extracts_preview_with_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0001.CR2");
+        let preview = write_raw(&raw, (160, 120));
+        let plugin = plugin_without_dcraw(dir.path());
+
+        let result = plugin.process(&raw, dir.path()).unwrap();
+        assert_eq!(result.output_path, dir.path().join("IMG_0001.jpg"));
+        assert_eq!(result.metadata_preserved, Some(true));
+        assert!(result.extra_outputs.is_empty());
+        assert!(result.compressed_size < result.original_size);
+        assert!(plugin.verify_output(&raw, &result).unwrap());
+
+        // The camera's JPEG is kept as-is, only EXIF is added
+        let output = fs::read(&result.output_path).unwrap();
+        assert!(output.ends_with(&preview[2..]));
+        let exif = read_metadata(&result.output_path).unwrap().exif.unwrap();
+        let meta = read_raw_metadata(Cursor::new(exif));
+        assert_eq!(meta.model.as_deref(), Some("Canon EOS Test"));
+        assert_eq!(meta.orientation, Some(6));
+        assert_eq!(
+            meta.date_time_original.as_deref(),
+            Some("2024:05:01 09:59:58")
+        );
+    }
+
+    #[test]
+    fn test_without_metadata_keeps_only_orientation() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0002.CR2");
+        write_raw(&raw, (160, 120));
+        let plugin = plugin_without_dcraw(dir.path()).with_preserve_metadata(false);
+
+        let result = plugin.process(&raw, dir.path()).unwrap();
+        assert_eq!(result.metadata_preserved, Some(false));
+        let exif = read_metadata(&result.output_path).unwrap().exif.unwrap();
+        let meta = read_raw_metadata(Cursor::new(exif));
+        assert_eq!(meta.orientation, Some(6));
+        assert_eq!(meta.model, None);
+        assert_eq!(meta.date_time_original, None);
+    }
+
+    #[test]
+    fn test_archive_policy_stores_raw_in_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0003.ARW");
+        write_raw(&raw, (160, 120));
+        let original = fs::read(&raw).unwrap();
+        let plugin = plugin_without_dcraw(dir.path()).with_policy(RawPolicy::Archive);
+
+        let result = plugin.process(&raw, dir.path()).unwrap();
+        let archive_path = dir.path().join("IMG_0003.ARW.zip");
+        assert_eq!(result.extra_outputs, vec![archive_path.clone()]);
+        assert_eq!(
+            result.compressed_size,
+            fs::metadata(&result.output_path).unwrap().len()
+                + fs::metadata(&archive_path).unwrap().len()
+        );
+        assert!(plugin.verify_output(&raw, &result).unwrap());
+
+        let mut archive = ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+        let mut entry = archive.by_name("IMG_0003.ARW").unwrap();
+        let mut archived = Vec::new();
+        entry.read_to_end(&mut archived).unwrap();
+        assert_eq!(archived, original);
+    }
+
+    #[test]
+    fn test_manager_discards_archive_with_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0004.CR2");
+        write_raw(&raw, (160, 120));
+
+        // The sensor data is incompressible, so JPEG plus archive ends up
+        // larger than the RAW and the manager discards both
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(
+            plugin_without_dcraw(dir.path()).with_policy(RawPolicy::Archive),
+        ));
+        let outcome = manager
+            .process_file(&raw, dir.path(), None, false, None)
+            .unwrap();
+        assert!(matches!(outcome, CompressionOutcome::Skipped { .. }));
+        assert!(raw.exists());
+        assert!(!dir.path().join("IMG_0004.jpg").exists());
+        assert!(!dir.path().join("IMG_0004.CR2.zip").exists());
+    }
+
+    #[test]
+    fn test_manager_deletes_raw_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("IMG_0005.CR2");
+        write_raw(&raw, (160, 120));
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin_without_dcraw(dir.path())));
+        let outcome = manager
+            .process_file(&raw, dir.path(), None, false, None)
+            .unwrap();
+        assert!(matches!(outcome, CompressionOutcome::Compressed(_)));
+        assert!(!raw.exists());
+        assert!(dir.path().join("IMG_0005.jpg").exists());
+    }
+
+    #[test]
+    fn test_missing_dcraw_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let requirements = plugin_without_dcraw(dir.path()).check_requirements();
+        assert!(requirements.ready);
+        assert_eq!(requirements.tools.len(), 1);
+        assert!(!requirements.tools[0].available);
+        assert!(requirements.message.unwrap().contains("embedded preview"));
+    }
+}
@@ -0,0 +1,629 @@
+//! Lossless minification of SVG, and optionally JSON and XML, in place.
+//!
+//! Only whitespace and comments are removed: whitespace-only text between
+//! elements (except where it is rendered, as in SVG `<text>` or under
+//! `xml:space="preserve"`), whitespace inside tags, XML comments, and
+//! whitespace between JSON tokens. Attribute values, text content and
+//! numbers are never rewritten, and the output is checked to parse to the
+//! same document before it replaces the original.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::compress_plugins::{
+    create_output_file, get_file_size, has_extension, CompressionPlugin, CompressionResult,
+    PluginMetadata, ProgressSink,
+};
+
+/// Elements whose whitespace-only text is rendered or meaningful
+const PRESERVE_ELEMENTS: [&str; 8] = [
+    "text", "tspan", "textPath", "style", "script", "title", "desc", "pre",
+];
+
+/// Where and what the minifier may touch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinifyOptions {
+    /// Only files under these directories are minified; none means no files
+    pub dirs: Vec<PathBuf>,
+    /// Files smaller than this (bytes) aren't worth rewriting
+    pub min_size: u64,
+    /// Also minify `.json` and `.xml` files, not just SVG
+    pub data_files: bool,
+}
+
+impl Default for MinifyOptions {
+    fn default() -> Self {
+        Self {
+            dirs: Vec::new(),
+            min_size: 4096,
+            data_files: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum XmlEvent {
+    Start {
+        name: String,
+        /// (name, quote, value), in source order
+        attrs: Vec<(String, char, String)>,
+        self_closing: bool,
+    },
+    End(String),
+    Text(String),
+    /// Declarations, processing instructions, DOCTYPE and CDATA, kept as-is
+    Verbatim(String),
+}
+
+fn malformed(what: &str, offset: usize) -> anyhow::Error {
+    anyhow!("Malformed XML: {} at byte {}", what, offset)
+}
+
+/// Parse an XML document into the events that survive minification.
+/// Comments are dropped and adjacent text merged, so a document and its
+/// minified form produce identical events.
+fn xml_events(input: &str) -> Result<Vec<XmlEvent>> {
+    let mut events = Vec::new();
+    // (element name, whether its whitespace is preserved)
+    let mut stack: Vec<(String, bool)> = Vec::new();
+    let mut text = String::new();
+    let mut pos = 0;
+
+    let flush = |text: &mut String, events: &mut Vec<XmlEvent>, stack: &[(String, bool)]| {
+        let preserve = stack.last().is_some_and(|(_, preserve)| *preserve);
+        if !text.is_empty() && (preserve || !text.trim().is_empty()) {
+            events.push(XmlEvent::Text(std::mem::take(text)));
+        }
+        text.clear();
+    };
+
+    if let Some(rest) = input.strip_prefix('\u{feff}') {
+        events.push(XmlEvent::Verbatim('\u{feff}'.to_string()));
+        pos = input.len() - rest.len();
+    }
+
+    while pos < input.len() {
+        let rest = &input[pos..];
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            text.push_str(&rest[..end]);
+            pos += end;
+            continue;
+        }
+
+        if rest.starts_with("<!--") {
+            let end = rest
+                .find("-->")
+                .ok_or_else(|| malformed("unclosed comment", pos))?;
+            pos += end + 3;
+            continue;
+        }
+
+        flush(&mut text, &mut events, &stack);
+        let verbatim_end = if rest.starts_with("<![CDATA[") {
+            Some(
+                rest.find("]]>")
+                    .ok_or_else(|| malformed("unclosed CDATA", pos))?
+                    + 3,
+            )
+        } else if rest.starts_with("<?") {
+            Some(
+                rest.find("?>")
+                    .ok_or_else(|| malformed("unclosed declaration", pos))?
+                    + 2,
+            )
+        } else if rest.starts_with("<!") {
+            Some(doctype_end(rest).ok_or_else(|| malformed("unclosed DOCTYPE", pos))?)
+        } else {
+            None
+        };
+        if let Some(end) = verbatim_end {
+            events.push(XmlEvent::Verbatim(rest[..end].to_string()));
+            pos += end;
+            continue;
+        }
+
+        if let Some(close) = rest.strip_prefix("</") {
+            let end = close
+                .find('>')
+                .ok_or_else(|| malformed("unclosed end tag", pos))?;
+            let name = close[..end].trim();
+            match stack.pop() {
+                Some((open, _)) if open == name => {}
+                _ => return Err(malformed(&format!("unexpected </{}>", name), pos)),
+            }
+            events.push(XmlEvent::End(name.to_string()));
+            pos += 2 + end + 1;
+            continue;
+        }
+
+        let (event, len) = parse_start_tag(rest).ok_or_else(|| malformed("bad tag", pos))?;
+        if let XmlEvent::Start {
+            name,
+            attrs,
+            self_closing,
+        } = &event
+        {
+            if !self_closing {
+                let local = name.rsplit(':').next().unwrap_or(name);
+                let inherited = stack.last().is_some_and(|(_, preserve)| *preserve);
+                let preserve = match attrs.iter().find(|(n, _, _)| n == "xml:space") {
+                    Some((_, _, value)) => value == "preserve",
+                    None => inherited || PRESERVE_ELEMENTS.contains(&local),
+                };
+                stack.push((name.clone(), preserve));
+            }
+        }
+        events.push(event);
+        pos += len;
+    }
+
+    flush(&mut text, &mut events, &stack);
+    if let Some((name, _)) = stack.last() {
+        bail!("Malformed XML: <{}> is never closed", name);
+    }
+    Ok(events)
+}
+
+/// Length of a `<!DOCTYPE ...>` declaration, internal subset included
+fn doctype_end(rest: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, '>') if depth == 0 => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse `<name attr="value" ...>` or `.../>`, returning the event and the
+/// tag's length in bytes
+fn parse_start_tag(rest: &str) -> Option<(XmlEvent, usize)> {
+    let bytes = rest.as_bytes();
+    let is_space = |b: u8| b.is_ascii_whitespace();
+    let mut i = 1;
+    let name_end = rest[i..]
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .map(|n| n + i)?;
+    let name = &rest[i..name_end];
+    if name.is_empty() {
+        return None;
+    }
+    i = name_end;
+
+    let mut attrs = Vec::new();
+    loop {
+        while i < bytes.len() && is_space(bytes[i]) {
+            i += 1;
+        }
+        match bytes.get(i)? {
+            b'>' => {
+                let event = XmlEvent::Start {
+                    name: name.to_string(),
+                    attrs,
+                    self_closing: false,
+                };
+                return Some((event, i + 1));
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'>') => {
+                let event = XmlEvent::Start {
+                    name: name.to_string(),
+                    attrs,
+                    self_closing: true,
+                };
+                return Some((event, i + 2));
+            }
+            _ => {}
+        }
+
+        let attr_end = rest[i..]
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '>' || c == '/')
+            .map(|n| n + i)?;
+        let attr = &rest[i..attr_end];
+        if attr.is_empty() {
+            return None;
+        }
+        i = attr_end;
+        while i < bytes.len() && is_space(bytes[i]) {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            return None;
+        }
+        i += 1;
+        while i < bytes.len() && is_space(bytes[i]) {
+            i += 1;
+        }
+        let quote = *bytes.get(i)? as char;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value_end = rest[i + 1..].find(quote).map(|n| n + i + 1)?;
+        attrs.push((attr.to_string(), quote, rest[i + 1..value_end].to_string()));
+        i = value_end + 1;
+    }
+}
+
+/// Minify an SVG or XML document
+pub fn minify_xml(input: &str) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    for event in xml_events(input)? {
+        match event {
+            XmlEvent::Start {
+                name,
+                attrs,
+                self_closing,
+            } => {
+                out.push('<');
+                out.push_str(&name);
+                for (attr, quote, value) in attrs {
+                    out.push(' ');
+                    out.push_str(&attr);
+                    out.push('=');
+                    out.push(quote);
+                    out.push_str(&value);
+                    out.push(quote);
+                }
+                out.push_str(if self_closing { "/>" } else { ">" });
+            }
+            XmlEvent::End(name) => {
+                out.push_str("</");
+                out.push_str(&name);
+                out.push('>');
+            }
+            XmlEvent::Text(text) | XmlEvent::Verbatim(text) => out.push_str(&text),
+        }
+    }
+    Ok(out)
+}
+
+/// Minify JSON by dropping whitespace between tokens; strings and numbers
+/// are copied unchanged
+pub fn minify_json(input: &str) -> Result<String> {
+    serde_json::from_str::<serde_json::Value>(input).context("Invalid JSON")?;
+    let mut out = String::with_capacity(input.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in input.trim_start_matches('\u{feff}').chars() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if !matches!(c, ' ' | '\t' | '\n' | '\r') {
+            out.push(c);
+        }
+    }
+    Ok(out)
+}
+
+/// Plugin minifying text assets in place
+pub struct TextMinifierPlugin {
+    options: MinifyOptions,
+}
+
+impl TextMinifierPlugin {
+    pub fn new() -> Self {
+        Self {
+            options: MinifyOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: MinifyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn in_allowed_dir(&self, path: &Path) -> bool {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.options.dirs.iter().any(|dir| {
+            let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.clone());
+            path.starts_with(dir)
+        })
+    }
+
+    fn minify(path: &Path, text: &str) -> Result<String> {
+        if has_extension(path, &["json"]) {
+            minify_json(text)
+        } else {
+            minify_xml(text)
+        }
+    }
+}
+
+impl Default for TextMinifierPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressionPlugin for TextMinifierPlugin {
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "Text Asset Minifier".to_string(),
+            description: "Losslessly minify SVG (and optionally JSON/XML) files in place"
+                .to_string(),
+            version: "1.0.0".to_string(),
+            lossy: false,
+            destructive: true,
+            requires_external_tools: Vec::new(),
+        }
+    }
+
+    fn can_handle(&self, path: &Path) -> Result<(bool, Option<String>)> {
+        let reject = |reason: &str| Ok((false, Some(reason.to_string())));
+        if has_extension(path, &["json", "xml"]) {
+            if !self.options.data_files {
+                return reject("JSON/XML minification is turned off");
+            }
+        } else if !has_extension(path, &["svg"]) {
+            return reject("Not an SVG, JSON or XML file");
+        }
+        if self.options.dirs.is_empty() {
+            return reject("No directories are configured for minification");
+        }
+        if !self.in_allowed_dir(path) {
+            return reject("Outside the configured minification directories");
+        }
+        let size = get_file_size(path)?;
+        if size < self.options.min_size {
+            return Ok((
+                false,
+                Some(format!(
+                    "Smaller than the {} byte minimum for minification",
+                    self.options.min_size
+                )),
+            ));
+        }
+        Ok((
+            true,
+            Some("Text asset for lossless minification".to_string()),
+        ))
+    }
+
+    fn estimate_ratio(&self, _path: &Path) -> Result<Option<f32>> {
+        // Editor-exported SVG and pretty-printed JSON shrink by about a quarter
+        Ok(Some(0.75))
+    }
+
+    fn process(&self, source: &Path, output_dir: &Path) -> Result<CompressionResult> {
+        self.process_with_progress(source, output_dir, &ProgressSink::new())
+    }
+
+    fn process_with_progress(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        sink: &ProgressSink,
+    ) -> Result<CompressionResult> {
+        let original_size = get_file_size(source)?;
+        let bytes = fs::read(source)?;
+        let text = std::str::from_utf8(&bytes)
+            .map_err(|_| anyhow!("{} is not UTF-8 text", source.display()))?;
+        sink.report(0, 1, None);
+        let minified = Self::minify(source, text)
+            .with_context(|| format!("Failed to minify {}", source.display()))?;
+        sink.check_cancelled()?;
+
+        let stem = source
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("min");
+        let output_path = output_dir.join(format!("{}.min.{}", stem, ext));
+        let mut output = create_output_file(&output_path)?;
+        if let Err(e) = output.write_all(minified.as_bytes()) {
+            drop(output);
+            let _ = fs::remove_file(&output_path);
+            return Err(e.into());
+        }
+        sink.report(1, 1, None);
+
+        // The manager backs up the original and moves the output over it
+        Ok(CompressionResult {
+            original_size,
+            compressed_size: minified.len() as u64,
+            output_path,
+            plugin_name: self.metadata().name,
+            files_processed: 1,
+            backup_path: None,
+            replace_source: true,
+            metadata_preserved: None,
+            extra_outputs: Vec::new(),
+        })
+    }
+
+    fn verify_output(&self, source: &Path, result: &CompressionResult) -> Result<bool> {
+        let original = fs::read_to_string(source)?;
+        let output = fs::read_to_string(&result.output_path)?;
+        let same = if has_extension(source, &["json"]) {
+            let parse = |s: &str| serde_json::from_str::<serde_json::Value>(s);
+            parse(&original)? == parse(&output).context("Output is not valid JSON")?
+        } else {
+            xml_events(&original)? == xml_events(&output).context("Output is not valid XML")?
+        };
+        if !same {
+            bail!("Minified output does not match the original document");
+        }
+        Ok(true)
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        if self.options.data_files {
+            vec!["svg", "json", "xml"]
+        } else {
+            vec!["svg"]
+        }
+    }
+
+    fn set_minify_options(&mut self, options: &MinifyOptions) -> bool {
+        self.options = options.clone();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress_plugins::{CompressionOutcome, PluginManager};
+
+    const SVG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Generator: Some Editor -->
+<svg xmlns="http://www.w3.org/2000/svg"
+     width="100"   height="100">
+    <g   fill="red">
+        <rect x="0" y="0" width="10" height="10" />
+        <text x="5" y="5">Hello <tspan font-weight="bold">big</tspan> world</text>
+    </g>
+    <style><![CDATA[ rect { stroke: blue; } ]]></style>
+</svg>
+"#;
+
+    fn plugin_for(dir: &Path) -> TextMinifierPlugin {
+        TextMinifierPlugin::new().with_options(MinifyOptions {
+            dirs: vec![dir.to_path_buf()],
+            min_size: 0,
+            data_files: true,
+        })
+    }
+
+    #[test]
+    fn test_minify_svg() {
+        let minified = minify_xml(SVG).unwrap();
+        assert_eq!(
+            minified,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><svg xmlns=\"http://www.w3.org/2000/svg\" \
+             width=\"100\" height=\"100\"><g fill=\"red\"><rect x=\"0\" y=\"0\" width=\"10\" \
+             height=\"10\"/><text x=\"5\" y=\"5\">Hello <tspan font-weight=\"bold\">big</tspan> \
+             world</text></g><style><![CDATA[ rect { stroke: blue; } ]]></style></svg>"
+        );
+        // Minifying again changes nothing
+        assert_eq!(minify_xml(&minified).unwrap(), minified);
+        assert_eq!(xml_events(SVG).unwrap(), xml_events(&minified).unwrap());
+    }
+
+    #[test]
+    fn test_minify_keeps_preserved_whitespace() {
+        let xml = "<root>\n  <a xml:space=\"preserve\">  <b/>  </a>\n  <c> </c>\n</root>";
+        assert_eq!(
+            minify_xml(xml).unwrap(),
+            "<root><a xml:space=\"preserve\">  <b/>  </a><c></c></root>"
+        );
+    }
+
+    #[test]
+    fn test_minify_rejects_malformed_xml() {
+        assert!(minify_xml("<svg><g></svg>").is_err());
+        assert!(minify_xml("<svg>").is_err());
+        assert!(minify_xml("<svg attr=unquoted/>").is_err());
+    }
+
+    #[test]
+    fn test_minify_json_keeps_strings_and_order() {
+        let json = "{\n  \"b\": \"a  \\\" b\",\n  \"a\": [1.50, 2e3, true]\n}\n";
+        assert_eq!(
+            minify_json(json).unwrap(),
+            "{\"b\":\"a  \\\" b\",\"a\":[1.50,2e3,true]}"
+        );
+        assert!(minify_json("{ not json").is_err());
+    }
+
+    #[test]
+    fn test_can_handle_respects_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let svg = dir.path().join("icon.svg");
+        let json = dir.path().join("data.json");
+        fs::write(&svg, SVG).unwrap();
+        fs::write(&json, "{ \"a\": 1 }").unwrap();
+
+        assert!(plugin_for(dir.path()).can_handle(&svg).unwrap().0);
+        assert!(plugin_for(dir.path()).can_handle(&json).unwrap().0);
+
+        // No directories configured: nothing is touched
+        let (ok, reason) = TextMinifierPlugin::new().can_handle(&svg).unwrap();
+        assert!(!ok);
+        assert!(reason.unwrap().contains("No directories"));
+
+        let elsewhere = tempfile::tempdir().unwrap();
+        let (ok, reason) = plugin_for(elsewhere.path()).can_handle(&svg).unwrap();
+        assert!(!ok);
+        assert!(reason.unwrap().contains("Outside"));
+
+        let strict = TextMinifierPlugin::new().with_options(MinifyOptions {
+            dirs: vec![dir.path().to_path_buf()],
+            min_size: 1 << 20,
+            data_files: false,
+        });
+        assert!(strict
+            .can_handle(&svg)
+            .unwrap()
+            .1
+            .unwrap()
+            .contains("minimum"));
+        assert!(strict
+            .can_handle(&json)
+            .unwrap()
+            .1
+            .unwrap()
+            .contains("turned off"));
+    }
+
+    #[test]
+    fn test_process_and_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let svg = dir.path().join("icon.svg");
+        fs::write(&svg, SVG).unwrap();
+        let plugin = plugin_for(dir.path());
+
+        let result = plugin.process(&svg, dir.path()).unwrap();
+        assert!(result.replace_source);
+        assert!(result.compressed_size < result.original_size);
+        assert!(plugin.verify_output(&svg, &result).unwrap());
+
+        // A changed document fails verification
+        fs::write(&result.output_path, "<svg></svg>").unwrap();
+        assert!(plugin.verify_output(&svg, &result).is_err());
+    }
+
+    #[test]
+    fn test_manager_minifies_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("data.json");
+        fs::write(
+            &json,
+            "{\n    \"name\": \"demo\",\n    \"items\": [\n        1,\n        2\n    ]\n}\n",
+        )
+        .unwrap();
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(plugin_for(dir.path())));
+        let outcome = manager
+            .process_file(&json, dir.path(), None, false, None)
+            .unwrap();
+        let CompressionOutcome::Compressed(result) = outcome else {
+            panic!("expected compression");
+        };
+        assert_eq!(result.output_path, json);
+        assert_eq!(
+            fs::read_to_string(&json).unwrap(),
+            "{\"name\":\"demo\",\"items\":[1,2]}"
+        );
+        assert!(!dir.path().join("data.min.json").exists());
+    }
+}
@@ -2,21 +2,27 @@ use anyhow::{Context, Result};
 use image::{DynamicImage, GenericImageView};
 use std::fs;
 use std::path::Path;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::compress_plugins::{
     create_output_file, generate_output_filename, get_file_size, has_extension, CompressionPlugin,
     CompressionResult, PluginMetadata,
 };
+use crate::plugins::animated_webp_converter::{is_animated_gif, is_apng};
+use crate::plugins::webp_metadata::{self, ImageMetadata};
 
 /// Plugin for converting images to WebP format
 pub struct WebPConverterPlugin {
     quality: f32,
+    preserve_metadata: bool,
 }
 
 impl WebPConverterPlugin {
     pub fn new() -> Self {
-        Self { quality: 85.0 }
+        Self {
+            quality: 85.0,
+            preserve_metadata: true,
+        }
     }
 
     pub fn with_quality(mut self, quality: f32) -> Self {
@@ -24,12 +30,18 @@ impl WebPConverterPlugin {
         self
     }
 
+    /// Copy EXIF/XMP/ICC metadata from the source into the WebP (default on)
+    pub fn with_preserve_metadata(mut self, enabled: bool) -> Self {
+        self.preserve_metadata = enabled;
+        self
+    }
+
     fn is_webp(path: &Path) -> bool {
         has_extension(path, &["webp"])
     }
 
     fn is_supported_image(path: &Path) -> bool {
-        has_extension(path, &["png", "jpg", "jpeg", "bmp", "tiff", "tif"])
+        has_extension(path, &["png", "jpg", "jpeg", "bmp", "tiff", "tif", "gif"])
     }
 
     /// Calculate bits per pixel (BPP) for an image file
@@ -106,7 +118,25 @@ impl WebPConverterPlugin {
         }
     }
 
-    fn convert_to_webp(&self, source: &Path, output: &Path) -> Result<()> {
+    /// Metadata of `source` worth carrying over; unreadable metadata is
+    /// logged and treated as absent rather than failing the conversion
+    fn source_metadata(source: &Path) -> ImageMetadata {
+        match webp_metadata::read_metadata(source) {
+            Ok(meta) => meta,
+            Err(e) => {
+                warn!(
+                    source = %source.display(),
+                    error = %e,
+                    "Failed to read image metadata; converting without it"
+                );
+                ImageMetadata::default()
+            }
+        }
+    }
+
+    /// Convert `source` into `output`. Returns whether the source's
+    /// metadata made it into the output, or None if it had none.
+    fn convert_to_webp(&self, source: &Path, output: &Path) -> Result<Option<bool>> {
         // Load the image
         let img = match image::open(source) {
             Ok(img) => img,
@@ -134,8 +164,15 @@ impl WebPConverterPlugin {
             }
         }
 
-        match self.encode_webp(&img, output) {
-            Ok(_) => Ok(()),
+        let metadata = Self::source_metadata(source);
+        let keep = if self.preserve_metadata && !metadata.is_empty() {
+            Some(&metadata)
+        } else {
+            None
+        };
+
+        match self.encode_webp(&img, output, keep) {
+            Ok(preserved) => Ok((!metadata.is_empty()).then_some(preserved)),
             Err(e) => {
                 error!(
                     source = %source.display(),
@@ -152,7 +189,14 @@ impl WebPConverterPlugin {
         }
     }
 
-    fn encode_webp(&self, img: &DynamicImage, output: &Path) -> Result<()> {
+    /// Encode `img` into `output`, embedding `metadata` when given. Returns
+    /// whether the metadata was embedded.
+    fn encode_webp(
+        &self,
+        img: &DynamicImage,
+        output: &Path,
+        metadata: Option<&ImageMetadata>,
+    ) -> Result<bool> {
         // Without webp feature, use external webp crate
         use std::io::Write;
         use webp::Encoder;
@@ -163,10 +207,25 @@ impl WebPConverterPlugin {
         let encoder = Encoder::from_rgba(&rgba, width, height);
         let encoded = encoder.encode(self.quality);
 
+        let (bytes, preserved) = match metadata {
+            Some(meta) => match webp_metadata::embed_metadata(&encoded, meta) {
+                Ok(bytes) => (bytes, true),
+                Err(e) => {
+                    warn!(
+                        output = %output.display(),
+                        error = %e,
+                        "Failed to embed metadata; writing WebP without it"
+                    );
+                    (encoded.to_vec(), false)
+                }
+            },
+            None => (encoded.to_vec(), false),
+        };
+
         // create_new (O_EXCL): a concurrent writer targeting the same output
         // name fails here instead of silently overwriting
         let mut file = create_output_file(output)?;
-        file.write_all(&encoded).with_context(|| {
+        file.write_all(&bytes).with_context(|| {
             error!(
                 output = %output.display(),
                 width = width,
@@ -177,7 +236,7 @@ impl WebPConverterPlugin {
             format!("Failed to write WebP file: {}", output.display())
         })?;
 
-        Ok(())
+        Ok(preserved)
     }
 }
 
@@ -191,8 +250,12 @@ impl CompressionPlugin for WebPConverterPlugin {
     fn metadata(&self) -> PluginMetadata {
         PluginMetadata {
             name: "WebP Converter".to_string(),
-            description: "Converts PNG, JPEG, and other image formats to WebP".to_string(),
+            description: "Converts PNG, JPEG, static GIF and other image formats to WebP"
+                .to_string(),
             version: "1.0.0".to_string(),
+            lossy: true,
+            destructive: false,
+            requires_external_tools: Vec::new(),
         }
     }
 
@@ -210,6 +273,17 @@ impl CompressionPlugin for WebPConverterPlugin {
             return Ok((false, Some("Already a WebP file".to_string())));
         }
 
+        // Encoding only the first frame would drop the animation; the
+        // Animated WebP Converter handles these
+        if (has_extension(path, &["png"]) && is_apng(path))
+            || (has_extension(path, &["gif"]) && is_animated_gif(path))
+        {
+            return Ok((
+                false,
+                Some("Animated image; handled by the Animated WebP Converter".to_string()),
+            ));
+        }
+
         // For JPEG files, only process if they have high BPP (bits per pixel)
         // This indicates the file is not heavily compressed and can benefit from WebP conversion
         if has_extension(path, &["jpg", "jpeg"]) {
@@ -256,7 +330,8 @@ impl CompressionPlugin for WebPConverterPlugin {
         let output_path = output_dir.join(&output_filename);
 
         // Convert to WebP; the manager handles size comparison and backups
-        self.convert_to_webp(source, &output_path)
+        let metadata_preserved = self
+            .convert_to_webp(source, &output_path)
             .with_context(|| format!("Failed to convert {} to WebP", source.display()))?;
 
         let compressed_size = get_file_size(&output_path)?;
@@ -265,6 +340,7 @@ impl CompressionPlugin for WebPConverterPlugin {
             source = %source.display(),
             original_size = original_size,
             webp_size = compressed_size,
+            metadata_preserved = ?metadata_preserved,
             "Converted image to WebP"
         );
 
@@ -276,11 +352,35 @@ impl CompressionPlugin for WebPConverterPlugin {
             files_processed: 1,
             backup_path: None,
             replace_source: false,
+            metadata_preserved,
+            extra_outputs: Vec::new(),
         })
     }
 
+    fn verify_output(&self, source: &Path, result: &CompressionResult) -> Result<bool> {
+        let data = fs::read(&result.output_path)
+            .with_context(|| format!("Failed to read {}", result.output_path.display()))?;
+        let decoded = webp::Decoder::new(&data)
+            .decode()
+            .ok_or_else(|| anyhow::anyhow!("Output is not a decodable WebP image"))?;
+
+        let expected = imagesize::size(source)
+            .with_context(|| format!("Failed to read dimensions of {}", source.display()))?;
+        let (width, height) = (decoded.width(), decoded.height());
+        if (width as usize, height as usize) != (expected.width, expected.height) {
+            anyhow::bail!(
+                "Output is {}x{} but the source is {}x{}",
+                width,
+                height,
+                expected.width,
+                expected.height
+            );
+        }
+        Ok(true)
+    }
+
     fn supported_extensions(&self) -> Vec<&str> {
-        vec!["png", "jpg", "jpeg", "bmp", "tiff", "tif"]
+        vec!["png", "jpg", "jpeg", "bmp", "tiff", "tif", "gif"]
     }
 
     fn quality(&self) -> Option<f32> {
@@ -291,6 +391,15 @@ impl CompressionPlugin for WebPConverterPlugin {
         self.quality = quality.clamp(0.0, 100.0);
         true
     }
+
+    fn preserve_metadata(&self) -> Option<bool> {
+        Some(self.preserve_metadata)
+    }
+
+    fn set_preserve_metadata(&mut self, enabled: bool) -> bool {
+        self.preserve_metadata = enabled;
+        true
+    }
 }
 
 #[cfg(test)]
@@ -419,7 +528,7 @@ mod tests {
         manager.register(Box::new(WebPConverterPlugin::new()));
 
         let outcome = manager
-            .process_file(&source, dir.path(), None, true)
+            .process_file(&source, dir.path(), None, true, None)
             .unwrap();
         match outcome {
             CompressionOutcome::Compressed(result) => {
@@ -446,11 +555,13 @@ mod tests {
         let mut manager = PluginManager::new();
         manager.register(Box::new(WebPConverterPlugin::new()));
 
-        let first = manager.process_file(&png, dir.path(), None, true).unwrap();
+        let first = manager
+            .process_file(&png, dir.path(), None, true, None)
+            .unwrap();
         assert!(matches!(first, CompressionOutcome::Compressed(_)));
         let webp_bytes = fs::read(dir.path().join("photo.webp")).unwrap();
 
-        let second = manager.process_file(&bmp, dir.path(), None, true);
+        let second = manager.process_file(&bmp, dir.path(), None, true, None);
         // {:#} shows the full anyhow chain, not just the outermost context
         let err = format!("{:#}", second.unwrap_err());
         assert!(err.contains("already exists"), "unexpected error: {err}");
@@ -462,6 +573,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_process_preserves_jpeg_metadata() {
+        use crate::plugins::webp_metadata::{read_webp_metadata, tests::jpeg_with_metadata};
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photo.jpg");
+        jpeg_with_metadata(&source);
+        let expected = webp_metadata::read_metadata(&source).unwrap();
+
+        let result = WebPConverterPlugin::new()
+            .process(&source, dir.path())
+            .unwrap();
+        assert_eq!(result.metadata_preserved, Some(true));
+        let output = fs::read(&result.output_path).unwrap();
+        assert_eq!(read_webp_metadata(&output).unwrap(), expected);
+        assert!(WebPConverterPlugin::new()
+            .verify_output(&source, &result)
+            .unwrap());
+
+        // Disabled: the output carries no metadata and the result says so
+        let out_dir = dir.path().join("stripped");
+        let result = WebPConverterPlugin::new()
+            .with_preserve_metadata(false)
+            .process(&source, &out_dir)
+            .unwrap();
+        assert_eq!(result.metadata_preserved, Some(false));
+        let output = fs::read(&result.output_path).unwrap();
+        assert!(read_webp_metadata(&output).unwrap().is_empty());
+
+        // A source without metadata has nothing to report
+        let png = save_noise_png(dir.path(), "plain.png", 16, 16);
+        let result = WebPConverterPlugin::new()
+            .process(&png, dir.path())
+            .unwrap();
+        assert_eq!(result.metadata_preserved, None);
+    }
+
+    #[test]
+    fn test_manager_setting_turns_metadata_copy_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photo.jpg");
+        crate::plugins::webp_metadata::tests::jpeg_with_metadata(&source);
+
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(WebPConverterPlugin::new()));
+        manager.set_preserve_metadata(false);
+
+        match manager
+            .process_file(&source, dir.path(), None, true, None)
+            .unwrap()
+        {
+            CompressionOutcome::Compressed(result) => {
+                assert_eq!(result.metadata_preserved, Some(false))
+            }
+            other => panic!("expected Compressed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_static_gif_handled_but_animations_left_to_animated_plugin() {
+        use crate::plugins::animated_webp_converter::tests::{write_apng, write_gif};
+
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = WebPConverterPlugin::new();
+
+        let still = dir.path().join("still.gif");
+        write_gif(&still, 1);
+        assert!(plugin.can_handle(&still).unwrap().0);
+
+        let animated = dir.path().join("anim.gif");
+        write_gif(&animated, 2);
+        let apng = dir.path().join("anim.png");
+        write_apng(&apng);
+        for path in [animated, apng] {
+            let (can_handle, reason) = plugin.can_handle(&path).unwrap();
+            assert!(!can_handle, "{} must not be flattened", path.display());
+            assert!(reason.unwrap().contains("Animated WebP Converter"));
+        }
+    }
+
     #[test]
     fn test_supported_extensions() {
         let plugin = WebPConverterPlugin::new();
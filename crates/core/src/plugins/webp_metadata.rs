@@ -0,0 +1,454 @@
+//! Carry EXIF, XMP and ICC metadata from JPEG/PNG sources into WebP output.
+//!
+//! The WebP encoder only writes pixels, so photo dates, GPS tags and colour
+//! profiles are lost unless copied over. Reading walks the JPEG segments or
+//! PNG chunks; writing rebuilds the RIFF container in the extended (VP8X)
+//! layout with ICCP, EXIF and "XMP " chunks around the encoded bitstream.
+
+use anyhow::{bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const JPEG_ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+// VP8X feature flags
+const FLAG_ICC: u8 = 0x20;
+const FLAG_ALPHA: u8 = 0x10;
+const FLAG_EXIF: u8 = 0x08;
+const FLAG_XMP: u8 = 0x04;
+
+/// Metadata blocks found in a source image
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageMetadata {
+    /// TIFF-structured EXIF data (without the JPEG "Exif\0\0" prefix)
+    pub exif: Option<Vec<u8>>,
+    /// XMP packet
+    pub xmp: Option<Vec<u8>>,
+    /// ICC colour profile
+    pub icc: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.xmp.is_none() && self.icc.is_none()
+    }
+}
+
+/// Read the metadata of a JPEG or PNG file. Other formats (and files
+/// without metadata) yield an empty [`ImageMetadata`].
+pub fn read_metadata(path: &Path) -> Result<ImageMetadata> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if data.starts_with(&[0xFF, 0xD8]) {
+        read_jpeg_metadata(&data)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        read_png_metadata(&data)
+    } else {
+        Ok(ImageMetadata::default())
+    }
+}
+
+fn read_jpeg_metadata(data: &[u8]) -> Result<ImageMetadata> {
+    let mut meta = ImageMetadata::default();
+    // ICC profiles may be split over several APP2 segments, each tagged
+    // with its 1-based sequence number
+    let mut icc_parts: Vec<(u8, &[u8])> = Vec::new();
+
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            bail!("Malformed JPEG: expected a marker at offset {}", pos);
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            // Fill byte
+            pos += 1;
+            continue;
+        }
+        // Start of scan / end of image: no metadata segments follow
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        // Standalone markers carry no length
+        if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            pos += 2;
+            continue;
+        }
+
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            bail!(
+                "Malformed JPEG: segment at offset {} overruns the file",
+                pos
+            );
+        }
+        let payload = &data[pos + 4..end];
+
+        match marker {
+            0xE1 if payload.starts_with(JPEG_EXIF_HEADER) && meta.exif.is_none() => {
+                meta.exif = Some(payload[JPEG_EXIF_HEADER.len()..].to_vec());
+            }
+            0xE1 if payload.starts_with(JPEG_XMP_HEADER) && meta.xmp.is_none() => {
+                meta.xmp = Some(payload[JPEG_XMP_HEADER.len()..].to_vec());
+            }
+            0xE2 if payload.starts_with(JPEG_ICC_HEADER) => {
+                let rest = &payload[JPEG_ICC_HEADER.len()..];
+                if rest.len() >= 2 {
+                    icc_parts.push((rest[0], &rest[2..]));
+                }
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+
+    if !icc_parts.is_empty() {
+        icc_parts.sort_by_key(|(seq, _)| *seq);
+        meta.icc = Some(
+            icc_parts
+                .iter()
+                .flat_map(|(_, part)| *part)
+                .copied()
+                .collect(),
+        );
+    }
+    Ok(meta)
+}
+
+fn read_png_metadata(data: &[u8]) -> Result<ImageMetadata> {
+    let mut meta = ImageMetadata::default();
+
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= data.len() {
+        let length = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let start = pos + 8;
+        let end = start + length;
+        // Chunk data is followed by a 4-byte CRC
+        if end + 4 > data.len() {
+            bail!("Malformed PNG: chunk at offset {} overruns the file", pos);
+        }
+        let payload = &data[start..end];
+
+        match kind {
+            b"eXIf" if meta.exif.is_none() => meta.exif = Some(payload.to_vec()),
+            b"iCCP" if meta.icc.is_none() => {
+                // profile name, NUL, compression method (0 = zlib), data
+                if let Some(nul) = payload.iter().position(|&b| b == 0) {
+                    if let Some(compressed) = payload.get(nul + 2..) {
+                        meta.icc = Some(inflate(compressed).context("Invalid iCCP chunk")?);
+                    }
+                }
+            }
+            b"iTXt" if meta.xmp.is_none() => {
+                if let Some(text) = png_xmp_text(payload)? {
+                    meta.xmp = Some(text);
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        pos = end + 4;
+    }
+    Ok(meta)
+}
+
+/// The text of an iTXt chunk holding XMP, or None for other keywords
+fn png_xmp_text(payload: &[u8]) -> Result<Option<Vec<u8>>> {
+    // keyword, NUL, compression flag, compression method, language tag,
+    // NUL, translated keyword, NUL, text
+    let Some(nul) = payload.iter().position(|&b| b == 0) else {
+        return Ok(None);
+    };
+    if &payload[..nul] != PNG_XMP_KEYWORD || payload.len() < nul + 3 {
+        return Ok(None);
+    }
+    let compressed = payload[nul + 1] == 1;
+    let mut rest = &payload[nul + 3..];
+    for _ in 0..2 {
+        let Some(end) = rest.iter().position(|&b| b == 0) else {
+            return Ok(None);
+        };
+        rest = &rest[end + 1..];
+    }
+    if compressed {
+        Ok(Some(inflate(rest).context("Invalid XMP iTXt chunk")?))
+    } else {
+        Ok(Some(rest.to_vec()))
+    }
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ZlibDecoder::new(data).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// A chunk of a WebP RIFF container
+struct Chunk<'a> {
+    fourcc: [u8; 4],
+    data: &'a [u8],
+}
+
+fn parse_webp_chunks(webp: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    if webp.len() < 12 || &webp[..4] != b"RIFF" || &webp[8..12] != b"WEBP" {
+        bail!("Not a WebP file");
+    }
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+    while pos + 8 <= webp.len() {
+        let fourcc: [u8; 4] = webp[pos..pos + 4].try_into().unwrap();
+        let size = u32::from_le_bytes(webp[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let start = pos + 8;
+        let end = start + size;
+        if end > webp.len() {
+            bail!("Malformed WebP: chunk at offset {} overruns the file", pos);
+        }
+        chunks.push(Chunk {
+            fourcc,
+            data: &webp[start..end],
+        });
+        // Chunks are padded to an even size
+        pos = end + (size & 1);
+    }
+    Ok(chunks)
+}
+
+/// Canvas size and alpha use of a single-image WebP, from its VP8X header
+/// or, for the simple layout, from the VP8/VP8L bitstream header
+fn canvas_info(chunks: &[Chunk<'_>]) -> Result<(u32, u32, bool)> {
+    let mut alpha = chunks.iter().any(|c| &c.fourcc == b"ALPH");
+    for chunk in chunks {
+        let d = chunk.data;
+        match &chunk.fourcc {
+            b"VP8X" if d.len() >= 10 => {
+                let width = u32::from_le_bytes([d[4], d[5], d[6], 0]) + 1;
+                let height = u32::from_le_bytes([d[7], d[8], d[9], 0]) + 1;
+                return Ok((width, height, alpha || d[0] & FLAG_ALPHA != 0));
+            }
+            // Lossy: 3-byte frame tag, start code, 14-bit width and height
+            b"VP8 " if d.len() >= 10 => {
+                let width = u16::from_le_bytes([d[6], d[7]]) as u32 & 0x3FFF;
+                let height = u16::from_le_bytes([d[8], d[9]]) as u32 & 0x3FFF;
+                return Ok((width, height, alpha));
+            }
+            // Lossless: signature byte, then packed width-1, height-1, alpha bit
+            b"VP8L" if d.len() >= 5 => {
+                let bits = u32::from_le_bytes([d[1], d[2], d[3], d[4]]);
+                let width = (bits & 0x3FFF) + 1;
+                let height = ((bits >> 14) & 0x3FFF) + 1;
+                alpha |= (bits >> 28) & 1 == 1;
+                return Ok((width, height, alpha));
+            }
+            _ => {}
+        }
+    }
+    bail!("WebP has no image data")
+}
+
+/// Return `webp` with the metadata embedded. Existing metadata chunks in
+/// `webp` are replaced; the image data is copied through unchanged.
+pub fn embed_metadata(webp: &[u8], meta: &ImageMetadata) -> Result<Vec<u8>> {
+    if meta.is_empty() {
+        return Ok(webp.to_vec());
+    }
+    let chunks = parse_webp_chunks(webp)?;
+    let (width, height, alpha) = canvas_info(&chunks)?;
+
+    let mut flags = 0u8;
+    if alpha {
+        flags |= FLAG_ALPHA;
+    }
+    if meta.icc.is_some() {
+        flags |= FLAG_ICC;
+    }
+    if meta.exif.is_some() {
+        flags |= FLAG_EXIF;
+    }
+    if meta.xmp.is_some() {
+        flags |= FLAG_XMP;
+    }
+
+    let mut vp8x = vec![flags, 0, 0, 0];
+    vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+
+    // Order required by the container spec: VP8X, ICCP, image data, EXIF, XMP
+    let mut body = Vec::with_capacity(webp.len() + 64);
+    body.extend_from_slice(b"WEBP");
+    write_chunk(&mut body, b"VP8X", &vp8x);
+    if let Some(icc) = &meta.icc {
+        write_chunk(&mut body, b"ICCP", icc);
+    }
+    for chunk in &chunks {
+        if !matches!(&chunk.fourcc, b"VP8X" | b"ICCP" | b"EXIF" | b"XMP ") {
+            write_chunk(&mut body, &chunk.fourcc, chunk.data);
+        }
+    }
+    if let Some(exif) = &meta.exif {
+        write_chunk(&mut body, b"EXIF", exif);
+    }
+    if let Some(xmp) = &meta.xmp {
+        write_chunk(&mut body, b"XMP ", xmp);
+    }
+
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+fn write_chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Metadata embedded in a WebP file (used to check conversions)
+pub fn read_webp_metadata(webp: &[u8]) -> Result<ImageMetadata> {
+    let mut meta = ImageMetadata::default();
+    for chunk in parse_webp_chunks(webp)? {
+        match &chunk.fourcc {
+            b"EXIF" => meta.exif = Some(chunk.data.to_vec()),
+            b"XMP " => meta.xmp = Some(chunk.data.to_vec()),
+            b"ICCP" => meta.icc = Some(chunk.data.to_vec()),
+            _ => {}
+        }
+    }
+    Ok(meta)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb, Rgba};
+
+    /// Minimal big-endian TIFF header, enough to stand in for EXIF data
+    pub(crate) const FAKE_EXIF: &[u8] = b"MM\0*\0\0\0\x08\0\0";
+
+    /// Encode a JPEG and splice EXIF, XMP and a two-part ICC profile in
+    /// right after the SOI marker
+    pub(crate) fn jpeg_with_metadata(path: &Path) {
+        let img = ImageBuffer::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 90u8]));
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 95)
+            .encode_image(&img)
+            .unwrap();
+
+        let mut segments = Vec::new();
+        let mut push_segment = |marker: u8, parts: &[&[u8]]| {
+            let payload: Vec<u8> = parts.concat();
+            segments.extend_from_slice(&[0xFF, marker]);
+            segments.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            segments.extend_from_slice(&payload);
+        };
+        push_segment(0xE1, &[JPEG_EXIF_HEADER, FAKE_EXIF]);
+        push_segment(0xE1, &[JPEG_XMP_HEADER, b"<x:xmpmeta/>"]);
+        // Parts deliberately out of order
+        push_segment(0xE2, &[JPEG_ICC_HEADER, &[2, 2], b"-profile"]);
+        push_segment(0xE2, &[JPEG_ICC_HEADER, &[1, 2], b"icc"]);
+
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&segments);
+        out.extend_from_slice(&jpeg[2..]);
+        std::fs::write(path, out).unwrap();
+    }
+
+    fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let mut crc = flate2::Crc::new();
+        crc.update(kind);
+        crc.update(data);
+        out.extend_from_slice(&crc.sum().to_be_bytes());
+    }
+
+    #[test]
+    fn test_reads_jpeg_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        jpeg_with_metadata(&path);
+
+        let meta = read_metadata(&path).unwrap();
+        assert_eq!(meta.exif.as_deref(), Some(FAKE_EXIF));
+        assert_eq!(meta.xmp.as_deref(), Some(&b"<x:xmpmeta/>"[..]));
+        assert_eq!(meta.icc.as_deref(), Some(&b"icc-profile"[..]));
+    }
+
+    #[test]
+    fn test_reads_png_metadata() {
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.png");
+        let img = ImageBuffer::from_pixel(8, 8, Rgb([10u8, 20, 30]));
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(b"png-profile").unwrap();
+        let mut iccp = b"sRGB\0\0".to_vec();
+        iccp.extend_from_slice(&zlib.finish().unwrap());
+        let mut itxt = PNG_XMP_KEYWORD.to_vec();
+        itxt.extend_from_slice(b"\0\0\0\0\0<x:xmpmeta/>");
+
+        // Insert the metadata chunks right after IHDR (8 + 25 bytes)
+        let mut out = png[..33].to_vec();
+        png_chunk(&mut out, b"iCCP", &iccp);
+        png_chunk(&mut out, b"eXIf", FAKE_EXIF);
+        png_chunk(&mut out, b"iTXt", &itxt);
+        out.extend_from_slice(&png[33..]);
+        std::fs::write(&path, out).unwrap();
+
+        let meta = read_metadata(&path).unwrap();
+        assert_eq!(meta.exif.as_deref(), Some(FAKE_EXIF));
+        assert_eq!(meta.xmp.as_deref(), Some(&b"<x:xmpmeta/>"[..]));
+        assert_eq!(meta.icc.as_deref(), Some(&b"png-profile"[..]));
+        // The spliced file must still be a valid PNG
+        assert!(image::open(&path).is_ok());
+    }
+
+    #[test]
+    fn test_embed_round_trips_and_stays_decodable() {
+        let meta = ImageMetadata {
+            exif: Some(FAKE_EXIF.to_vec()),
+            xmp: Some(b"<x:xmpmeta/>".to_vec()),
+            // Odd length exercises chunk padding
+            icc: Some(b"odd".to_vec()),
+        };
+
+        for alpha in [255u8, 128] {
+            let img = ImageBuffer::from_pixel(30, 20, Rgba([200u8, 100, 50, alpha]));
+            let webp = webp::Encoder::from_rgba(&img, 30, 20).encode(80.0).to_vec();
+
+            let embedded = embed_metadata(&webp, &meta).unwrap();
+            assert_eq!(read_webp_metadata(&embedded).unwrap(), meta);
+
+            let decoded = webp::Decoder::new(&embedded).decode().unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (30, 20));
+            assert_eq!(decoded.is_alpha(), alpha < 255);
+        }
+    }
+
+    #[test]
+    fn test_embed_without_metadata_is_a_no_op() {
+        let img = ImageBuffer::from_pixel(4, 4, Rgba([1u8, 2, 3, 255]));
+        let webp = webp::Encoder::from_rgba(&img, 4, 4).encode(80.0).to_vec();
+        assert_eq!(
+            embed_metadata(&webp, &ImageMetadata::default()).unwrap(),
+            webp
+        );
+    }
+}
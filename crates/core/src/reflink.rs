@@ -0,0 +1,151 @@
+//! Copy-on-write clones ("reflinks").
+//!
+//! On Btrfs, XFS and APFS a file can be cloned so both copies share their
+//! data blocks until one is modified: two independent files, stored once.
+//! Linux uses the `FICLONE` ioctl and macOS `clonefile`; other platforms and
+//! file systems without clone support report [`ReflinkError::Unsupported`].
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReflinkError {
+    /// The file system (or platform) cannot clone between these paths
+    #[error("Copy-on-write clones are not supported here ({0})")]
+    Unsupported(io::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Create `dst` as a copy-on-write clone of `src`. `dst` must not exist; on
+/// failure nothing is left behind at `dst`.
+pub fn reflink(src: &Path, dst: &Path) -> Result<(), ReflinkError> {
+    clone_file(src, dst).map_err(|e| {
+        if is_unsupported(&e) {
+            ReflinkError::Unsupported(e)
+        } else {
+            ReflinkError::Io(e)
+        }
+    })
+}
+
+/// Whether files in `dir` can be cloned, by cloning a small probe file
+pub fn supports_reflink(dir: &Path) -> bool {
+    let probe = dir.join(format!(".space-saver-reflink-{}", uuid::Uuid::new_v4()));
+    let clone = probe.with_extension("clone");
+    if fs::write(&probe, b"probe").is_err() {
+        return false;
+    }
+    let supported = reflink(&probe, &clone).is_ok();
+    let _ = fs::remove_file(&clone);
+    let _ = fs::remove_file(&probe);
+    supported
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clone_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = fs::File::open(src)?;
+    let dest = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)?;
+    // SAFETY: both descriptors are open for the duration of the call
+    let ret = unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    if ret == -1 {
+        let err = io::Error::last_os_error();
+        drop(dest);
+        let _ = fs::remove_file(dst);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn clone_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let src = CString::new(src.as_os_str().as_bytes())?;
+    let dst = CString::new(dst.as_os_str().as_bytes())?;
+    // SAFETY: both arguments are valid NUL-terminated paths
+    if unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn clone_file(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Errors meaning "cannot clone here" rather than a real I/O failure: no
+/// clone support, different file systems, or incompatible files
+fn is_unsupported(err: &io::Error) -> bool {
+    if err.kind() == io::ErrorKind::Unsupported {
+        return true;
+    }
+    #[cfg(unix)]
+    if let Some(code) = err.raw_os_error() {
+        return [
+            libc::EOPNOTSUPP,
+            libc::ENOTSUP,
+            libc::EXDEV,
+            libc::EINVAL,
+            libc::ENOTTY,
+            libc::ENOSYS,
+        ]
+        .contains(&code);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reflink_clones_or_reports_unsupported() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.bin");
+        let dst = dir.path().join("b.bin");
+        fs::write(&src, b"shared bytes").unwrap();
+
+        // Whether the temp directory supports clones depends on the machine
+        match reflink(&src, &dst) {
+            Ok(()) => {
+                assert!(supports_reflink(dir.path()));
+                assert_eq!(fs::read(&dst).unwrap(), b"shared bytes");
+                // The copies are independent
+                fs::write(&dst, b"changed").unwrap();
+                assert_eq!(fs::read(&src).unwrap(), b"shared bytes");
+            }
+            Err(ReflinkError::Unsupported(_)) => {
+                assert!(!supports_reflink(dir.path()));
+                assert!(!dst.exists());
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+        // The probe files are cleaned up either way
+        assert_eq!(
+            fs::read_dir(dir.path()).unwrap().count(),
+            1 + usize::from(dst.exists())
+        );
+    }
+
+    #[test]
+    fn test_reflink_never_overwrites() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.bin");
+        let dst = dir.path().join("b.bin");
+        fs::write(&src, b"new").unwrap();
+        fs::write(&dst, b"old").unwrap();
+
+        assert!(reflink(&src, &dst).is_err());
+        assert_eq!(fs::read(&dst).unwrap(), b"old");
+    }
+}
@@ -0,0 +1,145 @@
+//! Retrying reads that fail for a moment.
+//!
+//! Network shares (SMB, NFS) drop connections, time out and hand out stale
+//! handles when the link is busy; the same read usually succeeds a little
+//! later. [`RetryPolicy`] runs an operation again after such a
+//! [transient](is_transient) error, waiting longer each time. Other errors
+//! (a missing file, permission denied) are returned at once.
+
+use anyhow::Result;
+use std::io;
+use std::thread;
+use std::time::Duration;
+use tracing::debug;
+
+/// How often and how patiently to retry after a transient IO error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Up to `retries` more attempts, the first after `backoff` and each
+    /// further one after twice the previous wait
+    pub fn new(retries: u32, backoff: Duration) -> Self {
+        Self { retries, backoff }
+    }
+
+    /// Every error is returned at once
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Run `op`, again while it fails with a transient error and retries
+    /// are left. `what` names the operation in the log.
+    pub fn run<T>(&self, what: &str, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut wait = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(e) if attempt < self.retries && transient(&e) => {
+                    attempt += 1;
+                    debug!(
+                        "{} failed ({}); retry {} of {} in {:?}",
+                        what, e, attempt, self.retries, wait
+                    );
+                    thread::sleep(wait);
+                    wait = wait.saturating_mul(2);
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Whether `error` may well go away when the operation is tried again:
+/// timeouts, interrupted or dropped connections, and (on Unix) stale NFS
+/// handles and busy resources
+pub fn is_transient(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+    if matches!(
+        error.kind(),
+        TimedOut
+            | Interrupted
+            | WouldBlock
+            | ConnectionReset
+            | ConnectionAborted
+            | NotConnected
+            | BrokenPipe
+            | UnexpectedEof
+    ) {
+        return true;
+    }
+    #[cfg(unix)]
+    if let Some(code) = error.raw_os_error() {
+        return [
+            libc::EIO,
+            libc::ESTALE,
+            libc::EAGAIN,
+            libc::EBUSY,
+            libc::EHOSTDOWN,
+        ]
+        .contains(&code);
+    }
+    false
+}
+
+/// Whether the IO error behind `error`, if any, is transient
+fn transient(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(is_transient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_transient_errors_are_retried_others_are_not() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+
+        let calls = Cell::new(0);
+        let read = policy.run("read", || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err(io::Error::from(io::ErrorKind::TimedOut).into())
+            } else {
+                Ok("done")
+            }
+        });
+        assert_eq!(read.unwrap(), "done");
+        assert_eq!(calls.get(), 3);
+
+        // Out of retries
+        calls.set(0);
+        let read: Result<()> = policy.run("read", || {
+            calls.set(calls.get() + 1);
+            Err(io::Error::from(io::ErrorKind::ConnectionReset).into())
+        });
+        assert!(read.is_err());
+        assert_eq!(calls.get(), 3);
+
+        // Not worth retrying
+        calls.set(0);
+        let read: Result<()> = policy.run("read", || {
+            calls.set(calls.get() + 1);
+            Err(io::Error::from(io::ErrorKind::NotFound).into())
+        });
+        assert!(read.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -12,6 +12,54 @@ pub struct FileInfo {
     pub modified: i64,
     pub file_type: FileType,
     pub hash: Option<String>,
+    /// Owning user id; always `None` off Unix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    /// Owning group id; always `None` off Unix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Permission bits (`0o7777` mask of `st_mode`); always `None` off Unix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+}
+
+impl FileInfo {
+    /// A file known only by its path, size and modification time (Unix
+    /// seconds), as object stores list them
+    pub fn new(path: impl Into<PathBuf>, size: u64, modified: i64) -> Self {
+        let path = path.into();
+        Self {
+            file_type: DefaultFileScanner::determine_file_type(&path),
+            path,
+            size,
+            modified,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    /// The file at `path` as a scan reports it, from its `metadata`
+    pub fn from_metadata(path: &Path, metadata: &std::fs::Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let (uid, gid, mode) = ownership(metadata);
+        Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified,
+            file_type: DefaultFileScanner::determine_file_type(path),
+            hash: None,
+            uid,
+            gid,
+            mode,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,9 +77,11 @@ pub trait FileScanner {
 }
 
 /// Default file scanner implementation
+#[derive(Debug, Clone)]
 pub struct DefaultFileScanner {
     max_depth: Option<usize>,
     follow_links: bool,
+    include_hidden: bool,
 }
 
 impl DefaultFileScanner {
@@ -39,6 +89,7 @@ impl DefaultFileScanner {
         Self {
             max_depth: None,
             follow_links: false,
+            include_hidden: true,
         }
     }
 
@@ -52,6 +103,13 @@ impl DefaultFileScanner {
         self
     }
 
+    /// Whether hidden files are reported and hidden directories descended
+    /// into (see [`is_hidden`]). The scan root itself is always scanned.
+    pub fn include_hidden(mut self, include: bool) -> Self {
+        self.include_hidden = include;
+        self
+    }
+
     fn determine_file_type(path: &Path) -> FileType {
         let ext = path
             .extension()
@@ -86,7 +144,11 @@ impl FileScanner for DefaultFileScanner {
             walker = walker.max_depth(depth);
         }
 
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        let include_hidden = self.include_hidden;
+        let entries = walker
+            .into_iter()
+            .filter_entry(|e| include_hidden || e.depth() == 0 || !is_hidden(e.path()));
+        for entry in entries.filter_map(|e| e.ok()) {
             let metadata = match entry.metadata() {
                 Ok(m) => m,
                 Err(e) => {
@@ -100,20 +162,7 @@ impl FileScanner for DefaultFileScanner {
             };
 
             if metadata.is_file() {
-                let modified = metadata
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| d.as_secs() as i64)
-                    .unwrap_or(0);
-
-                results.push(FileInfo {
-                    path: entry.path().to_path_buf(),
-                    size: metadata.len(),
-                    modified,
-                    file_type: Self::determine_file_type(entry.path()),
-                    hash: None,
-                });
+                results.push(FileInfo::from_metadata(entry.path(), &metadata));
             }
         }
 
@@ -122,6 +171,60 @@ impl FileScanner for DefaultFileScanner {
     }
 }
 
+/// Whether `path` is hidden: its name starts with `.`, or on Windows it has
+/// the hidden or system attribute
+pub fn is_hidden(path: &Path) -> bool {
+    let dot_file = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    dot_file || has_hidden_attribute(path)
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    std::fs::symlink_metadata(path).is_ok_and(|metadata| {
+        metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+    })
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_path: &Path) -> bool {
+    false
+}
+
+/// Owner uid, gid and permission bits of a file
+#[cfg(unix)]
+fn ownership(metadata: &std::fs::Metadata) -> (Option<u32>, Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+    (
+        Some(metadata.uid()),
+        Some(metadata.gid()),
+        Some(metadata.mode() & 0o7777),
+    )
+}
+
+#[cfg(not(unix))]
+fn ownership(_metadata: &std::fs::Metadata) -> (Option<u32>, Option<u32>, Option<u32>) {
+    (None, None, None)
+}
+
+/// Effective user id of this process, for "only my files" filters; `None`
+/// off Unix
+pub fn current_uid() -> Option<u32> {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        Some(unsafe { libc::geteuid() })
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
 /// Find the topmost empty directories beneath `path`. A directory counts as
 /// empty when its subtree contains no files (it may contain other empty
 /// directories, which are subsumed by their topmost empty ancestor). The scan
@@ -212,6 +315,42 @@ mod tests {
         assert_eq!(results[0].size, 12);
     }
 
+    #[test]
+    fn test_scan_hidden_files() {
+        // tempdir names start with '.', so the root must never count as hidden
+        let dir = tempfile::Builder::new().prefix(".scan").tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".git/objects")).unwrap();
+        fs::write(dir.path().join(".git/objects/blob"), "x").unwrap();
+        fs::write(dir.path().join(".env"), "x").unwrap();
+        fs::write(dir.path().join("visible.txt"), "x").unwrap();
+
+        let all = DefaultFileScanner::new().scan(dir.path()).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let visible = DefaultFileScanner::new()
+            .include_hidden(false)
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].path, dir.path().join("visible.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_records_ownership() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("private.txt");
+        fs::write(&file_path, "secret").unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        let results = DefaultFileScanner::new().scan(dir.path()).unwrap();
+        assert_eq!(results[0].uid, current_uid());
+        assert!(results[0].gid.is_some());
+        assert_eq!(results[0].mode, Some(0o600));
+    }
+
     #[test]
     fn test_find_empty_dirs_reports_topmost_only() {
         let dir = tempdir().unwrap();
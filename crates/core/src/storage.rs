@@ -0,0 +1,156 @@
+//! Where scanned files live.
+//!
+//! Scans usually walk the local file system, but duplicates and statistics
+//! can be worked out for any store that lists files with their sizes and
+//! reads byte ranges of them: a [`StorageBackend`]. [`LocalStorage`] is the
+//! local file system; object stores (S3 buckets, WebDAV shares) implement
+//! the trait elsewhere.
+
+use crate::scanner::{DefaultFileScanner, FileInfo, FileScanner};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A listed file, with the store's entity tag when it keeps one
+#[derive(Debug, Clone)]
+pub struct StoredFile {
+    pub info: FileInfo,
+    /// Changes whenever the content does. Some stores (S3 for files
+    /// uploaded in one part) use the MD5 of the content; see
+    /// [`StoredFile::content_digest`].
+    pub etag: Option<String>,
+}
+
+impl StoredFile {
+    pub fn new(info: FileInfo) -> Self {
+        Self { info, etag: None }
+    }
+
+    pub fn with_etag(mut self, etag: Option<String>) -> Self {
+        self.etag = etag;
+        self
+    }
+
+    /// The ETag without quotes, when it is a strong one
+    pub fn strong_etag(&self) -> Option<&str> {
+        let etag = self.etag.as_deref()?;
+        if etag.starts_with("W/") {
+            return None;
+        }
+        Some(etag.trim_matches('"')).filter(|etag| !etag.is_empty())
+    }
+
+    /// The ETag when it looks like the MD5 of the content (32 hex digits),
+    /// so two files with different ones differ. Multipart uploads and most
+    /// web servers use other schemes: their ETags say two files are the same
+    /// when equal, but nothing when not.
+    pub fn content_digest(&self) -> Option<&str> {
+        self.strong_etag()
+            .filter(|etag| etag.len() == 32 && etag.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
+}
+
+/// A place files can be listed and read from
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// What the store is, for messages: a folder or a URL
+    fn describe(&self) -> String;
+
+    /// Every file at or beneath `root`: a folder for local storage, a key
+    /// prefix for object stores. Hidden entries (names starting with `.`)
+    /// are left out unless `include_hidden`.
+    async fn list(&self, root: &Path, include_hidden: bool) -> Result<Vec<StoredFile>>;
+
+    /// The bytes of the listed file at `path` in `range`; fewer when the
+    /// file ends first
+    async fn read_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>>;
+}
+
+/// The local file system, walked like scans walk it
+#[derive(Debug, Clone, Default)]
+pub struct LocalStorage {
+    scanner: DefaultFileScanner,
+}
+
+impl LocalStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Walk with `scanner` (depth, symbolic links); whether hidden entries
+    /// are included is still decided per [`StorageBackend::list`] call
+    pub fn with_scanner(mut self, scanner: DefaultFileScanner) -> Self {
+        self.scanner = scanner;
+        self
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    fn describe(&self) -> String {
+        "local storage".to_string()
+    }
+
+    async fn list(&self, root: &Path, include_hidden: bool) -> Result<Vec<StoredFile>> {
+        let scanner = self.scanner.clone().include_hidden(include_hidden);
+        let root = root.to_path_buf();
+        let files = tokio::task::spawn_blocking(move || scanner.scan(&root)).await??;
+        Ok(files.into_iter().map(StoredFile::new).collect())
+    }
+
+    async fn read_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+        let path: PathBuf = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            file.seek(SeekFrom::Start(range.start))?;
+            let mut bytes = Vec::new();
+            file.take(range.end.saturating_sub(range.start))
+                .read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_local_storage_lists_and_reads_ranges() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"hello world").unwrap();
+        std::fs::write(dir.path().join(".hidden"), b"secret").unwrap();
+        let storage = LocalStorage::new();
+
+        assert_eq!(storage.list(dir.path(), false).await.unwrap().len(), 1);
+        assert_eq!(storage.list(dir.path(), true).await.unwrap().len(), 2);
+
+        let path = dir.path().join("a.txt");
+        assert_eq!(storage.read_range(&path, 6..11).await.unwrap(), b"world");
+        assert_eq!(storage.read_range(&path, 6..100).await.unwrap(), b"world");
+    }
+
+    #[test]
+    fn test_only_md5_etags_are_content_digests() {
+        let file = |etag: &str| {
+            StoredFile::new(FileInfo::new("a.txt", 5, 0)).with_etag(Some(etag.to_string()))
+        };
+        let md5 = "\"5d41402abc4b2a76b9719d911017c592\"";
+        assert_eq!(
+            file(md5).content_digest(),
+            Some("5d41402abc4b2a76b9719d911017c592")
+        );
+        assert_eq!(
+            file("\"5d41402abc4b2a76b9719d911017c592-3\"").content_digest(),
+            None
+        );
+        assert_eq!(file("\"2d-5f3e1a\"").content_digest(), None);
+        assert_eq!(file("\"2d-5f3e1a\"").strong_etag(), Some("2d-5f3e1a"));
+        assert_eq!(file(&format!("W/{}", md5)).strong_etag(), None);
+    }
+}
@@ -0,0 +1,297 @@
+//! Keeping background work from monopolising the disk.
+//!
+//! [`IoThrottle`] caps the rate at which file contents are read: a token
+//! bucket shared by every thread reading through it, so parallel hashing
+//! stays under one combined limit. [`lower_io_priority`] asks the operating
+//! system to serve the current thread's IO only when the disk is otherwise
+//! idle (Linux `ioprio` idle class, Windows background mode, macOS throttled
+//! IO policy). [`IoPolicy`] bundles the two for a background job.
+
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Read-rate limit shared by every clone
+#[derive(Debug, Clone)]
+pub struct IoThrottle {
+    bytes_per_sec: u64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes that may be read without waiting; negative while in debt
+    available: f64,
+    refilled_at: Instant,
+}
+
+impl IoThrottle {
+    /// Limit reads to `bytes_per_sec` on average, allowing bursts of up to
+    /// one second's worth. A limit of 0 is treated as 1 byte per second.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Self {
+            bytes_per_sec,
+            bucket: Arc::new(Mutex::new(Bucket {
+                available: bytes_per_sec as f64,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Account for `bytes` about to be (or just) read, sleeping as long as it
+    /// takes to keep the average rate within the limit
+    pub fn consume(&self, bytes: u64) {
+        let wait = {
+            let Ok(mut bucket) = self.bucket.lock() else {
+                return;
+            };
+            let rate = self.bytes_per_sec as f64;
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.available = (bucket.available + elapsed * rate).min(rate) - bytes as f64;
+            bucket.refilled_at = now;
+            (bucket.available < 0.0).then(|| Duration::from_secs_f64(-bucket.available / rate))
+        };
+        if let Some(wait) = wait {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// A reader whose reads go through an [`IoThrottle`]
+pub struct ThrottledReader<R> {
+    inner: R,
+    throttle: IoThrottle,
+}
+
+impl<R: Read> ThrottledReader<R> {
+    pub fn new(inner: R, throttle: IoThrottle) -> Self {
+        Self { inner, throttle }
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.throttle.consume(count as u64);
+        Ok(count)
+    }
+}
+
+/// Restores the thread's previous IO priority when dropped
+#[must_use = "the priority is restored when the guard is dropped"]
+pub struct IoPriorityGuard {
+    previous: Option<i32>,
+}
+
+/// Lower the current thread's IO priority until the returned guard is
+/// dropped. Best effort: where the platform refuses or has no such notion,
+/// the thread keeps its priority.
+pub fn lower_io_priority() -> IoPriorityGuard {
+    IoPriorityGuard {
+        previous: platform::lower(),
+    }
+}
+
+impl Drop for IoPriorityGuard {
+    fn drop(&mut self) {
+        platform::restore(self.previous);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+    /// Moves the calling thread (pid 0) to the idle class, returning its
+    /// previous priority
+    pub fn lower() -> Option<i32> {
+        // SAFETY: ioprio_get/ioprio_set take plain integers and only affect
+        // the calling thread
+        unsafe {
+            let previous = libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0);
+            if previous < 0 {
+                return None;
+            }
+            let idle = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+            (libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, idle) == 0)
+                .then_some(previous as i32)
+        }
+    }
+
+    pub fn restore(previous: Option<i32>) {
+        if let Some(previous) = previous {
+            // SAFETY: as above
+            unsafe {
+                libc::syscall(
+                    libc::SYS_ioprio_set,
+                    IOPRIO_WHO_PROCESS,
+                    0,
+                    previous as libc::c_long,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::os::raw::c_int;
+
+    const IOPOL_TYPE_DISK: c_int = 0;
+    const IOPOL_SCOPE_THREAD: c_int = 1;
+    const IOPOL_THROTTLE: c_int = 3;
+
+    extern "C" {
+        fn getiopolicy_np(iotype: c_int, scope: c_int) -> c_int;
+        fn setiopolicy_np(iotype: c_int, scope: c_int, policy: c_int) -> c_int;
+    }
+
+    pub fn lower() -> Option<i32> {
+        // SAFETY: thread-scoped policy calls with plain integer arguments
+        unsafe {
+            let previous = getiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_THREAD);
+            if previous < 0 {
+                return None;
+            }
+            (setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_THREAD, IOPOL_THROTTLE) == 0)
+                .then_some(previous)
+        }
+    }
+
+    pub fn restore(previous: Option<i32>) {
+        if let Some(previous) = previous {
+            // SAFETY: as above
+            unsafe {
+                setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_THREAD, previous);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+        THREAD_MODE_BACKGROUND_END,
+    };
+
+    /// Background mode lowers both CPU and IO priority of the thread; there
+    /// is no previous value to keep, ending it restores the old priority
+    pub fn lower() -> Option<i32> {
+        // SAFETY: GetCurrentThread returns a pseudo handle that needs no
+        // closing
+        unsafe {
+            (SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) != 0).then_some(0)
+        }
+    }
+
+    pub fn restore(previous: Option<i32>) {
+        if previous.is_some() {
+            // SAFETY: as above
+            unsafe {
+                SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_END);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    pub fn lower() -> Option<i32> {
+        tracing::debug!("Low IO priority is not supported on this platform");
+        None
+    }
+
+    pub fn restore(_previous: Option<i32>) {}
+}
+
+/// How a background job reads from disk: an optional rate limit and
+/// whether to run at low IO priority. The default leaves IO untouched.
+#[derive(Debug, Clone, Default)]
+pub struct IoPolicy {
+    throttle: Option<IoThrottle>,
+    low_priority: bool,
+}
+
+impl IoPolicy {
+    pub fn new(throttle: Option<IoThrottle>, low_priority: bool) -> Self {
+        Self {
+            throttle,
+            low_priority,
+        }
+    }
+
+    pub fn throttle(&self) -> Option<&IoThrottle> {
+        self.throttle.as_ref()
+    }
+
+    pub fn low_priority(&self) -> bool {
+        self.low_priority
+    }
+
+    /// Lower the current thread's IO priority if the policy asks for it
+    pub fn enter(&self) -> Option<IoPriorityGuard> {
+        self.low_priority.then(lower_io_priority)
+    }
+
+    /// Account for `bytes` read outside a [`ThrottledReader`] (e.g. by a
+    /// compression plugin)
+    pub fn consume(&self, bytes: u64) {
+        if let Some(throttle) = &self.throttle {
+            throttle.consume(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_holds_reads_to_the_limit() {
+        // One second of burst, then 2 KB more at 4 KB/s: about half a second
+        let throttle = IoThrottle::new(4096);
+        let data = vec![0u8; 6144];
+        let started = Instant::now();
+        let mut reader = ThrottledReader::new(&data[..], throttle.clone());
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(read.len(), 6144);
+        assert!(elapsed >= Duration::from_millis(450), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(3), "{elapsed:?}");
+    }
+
+    #[test]
+    fn test_priority_guard_restores_on_drop() {
+        // Whether or not the platform allows it, entering and leaving must
+        // not fail or leak the lowered priority into later work
+        let policy = IoPolicy::new(None, true);
+        {
+            let _guard = policy.enter();
+        }
+        assert!(IoPolicy::default().enter().is_none());
+
+        #[cfg(target_os = "linux")]
+        {
+            let before = platform::lower();
+            platform::restore(before);
+            if let Some(before) = before {
+                let guard = lower_io_priority();
+                drop(guard);
+                // SAFETY: reads the calling thread's priority
+                let now = unsafe { libc::syscall(libc::SYS_ioprio_get, 1 as libc::c_long, 0) };
+                assert_eq!(now as i32, before);
+            }
+        }
+    }
+}
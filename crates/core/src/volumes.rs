@@ -0,0 +1,333 @@
+//! Splitting archives into fixed-size volumes and joining them back.
+//!
+//! A split archive `backup.zip` becomes `backup.zip.001`, `backup.zip.002`,
+//! ... plus `backup.zip.manifest.json`, which records every volume's size
+//! and BLAKE3 hash so a missing or damaged part is caught before joining.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Largest file FAT32 can hold (4 GiB - 1 byte)
+pub const FAT32_VOLUME_SIZE: u64 = 4 * 1024 * 1024 * 1024 - 1;
+
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeInfo {
+    /// File name of the volume, relative to the manifest's directory
+    pub file_name: String,
+    pub size: u64,
+    pub blake3: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeManifest {
+    pub version: u32,
+    /// File name of the archive the volumes reassemble into
+    pub archive_name: String,
+    pub total_size: u64,
+    pub volume_size: u64,
+    pub volumes: Vec<VolumeInfo>,
+}
+
+impl VolumeManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read volume manifest: {}", path.display()))?;
+        let manifest: Self = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid volume manifest: {}", path.display()))?;
+        if manifest.version != MANIFEST_VERSION {
+            bail!(
+                "Unsupported volume manifest version {} in {}",
+                manifest.version,
+                path.display()
+            );
+        }
+        Ok(manifest)
+    }
+}
+
+/// Where the manifest of a split `archive` lives
+pub fn manifest_path(archive: &Path) -> PathBuf {
+    let mut name = archive.as_os_str().to_os_string();
+    name.push(MANIFEST_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// The manifest to join when `source` names a split archive: either the
+/// manifest itself, or an archive path that only exists as volumes
+pub fn find_manifest(source: &Path) -> Option<PathBuf> {
+    if source.to_string_lossy().ends_with(MANIFEST_SUFFIX) {
+        return Some(source.to_path_buf());
+    }
+    let manifest = manifest_path(source);
+    (!source.exists() && manifest.is_file()).then_some(manifest)
+}
+
+/// Splits `archive` into volumes of at most `volume_size` bytes next to it,
+/// writes the manifest, and removes the original. Volumes are cut from the
+/// end and the original truncated as it goes, so the extra disk space
+/// needed is one volume rather than a second copy of the archive.
+pub fn split_into_volumes(archive: &Path, volume_size: u64) -> Result<VolumeManifest> {
+    if volume_size == 0 {
+        bail!("Volume size must be greater than zero");
+    }
+    let archive_name = archive
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid archive path: {}", archive.display()))?
+        .to_string_lossy()
+        .to_string();
+    let mut file = OpenOptions::new().read(true).write(true).open(archive)?;
+    let total_size = file.metadata()?.len();
+    let count = total_size.div_ceil(volume_size).max(1);
+
+    let mut volumes = Vec::with_capacity(count as usize);
+    for index in (1..count).rev() {
+        let start = index * volume_size;
+        let len = (total_size - start).min(volume_size);
+        let path = volume_path(archive, index + 1);
+        file.seek(SeekFrom::Start(start))?;
+        let blake3 = copy_hashed(&mut (&mut file).take(len), &mut File::create(&path)?)?;
+        file.set_len(start)?;
+        volumes.push(VolumeInfo {
+            file_name: file_name(&path),
+            size: len,
+            blake3,
+        });
+    }
+
+    // What is left of the original is the first volume
+    file.seek(SeekFrom::Start(0))?;
+    let blake3 = copy_hashed(&mut file, &mut io::sink())?;
+    drop(file);
+    let first = volume_path(archive, 1);
+    fs::rename(archive, &first)?;
+    volumes.push(VolumeInfo {
+        file_name: file_name(&first),
+        size: total_size.min(volume_size),
+        blake3,
+    });
+    volumes.reverse();
+
+    let manifest = VolumeManifest {
+        version: MANIFEST_VERSION,
+        archive_name,
+        total_size,
+        volume_size,
+        volumes,
+    };
+    fs::write(
+        manifest_path(archive),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+/// Concatenates the volumes listed in `manifest_file` into `dest`,
+/// verifying each volume's size and hash. Returns the bytes written; a
+/// failed join removes the partial output. Volumes must sit next to the
+/// manifest: a name that points elsewhere is refused before anything is
+/// written, since the manifest also supplies the hash it is checked with.
+pub fn join_volumes(manifest_file: &Path, dest: &Path) -> Result<u64> {
+    let manifest = VolumeManifest::load(manifest_file)?;
+    let dir = manifest_file.parent().unwrap_or(Path::new("."));
+    let paths = manifest
+        .volumes
+        .iter()
+        .map(|volume| volume_beside(dir, &volume.file_name))
+        .collect::<Result<Vec<_>>>()?;
+    let mut output = File::create(dest)?;
+    let result = manifest
+        .volumes
+        .iter()
+        .zip(paths)
+        .try_fold(0u64, |written, (volume, path)| {
+            let mut input = File::open(&path)
+                .with_context(|| format!("Missing archive volume: {}", path.display()))?;
+            let size = input.metadata()?.len();
+            if size != volume.size {
+                bail!(
+                    "Archive volume {} is {} bytes, expected {}",
+                    volume.file_name,
+                    size,
+                    volume.size
+                );
+            }
+            if copy_hashed(&mut input, &mut output)? != volume.blake3 {
+                bail!(
+                    "Archive volume {} is corrupt (hash mismatch)",
+                    volume.file_name
+                );
+            }
+            Ok(written + size)
+        });
+    if result.is_err() {
+        drop(output);
+        let _ = fs::remove_file(dest);
+    }
+    result
+}
+
+/// `name` in `dir`, when it is a plain file name
+fn volume_beside(dir: &Path, name: &str) -> Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(part)), None) => Ok(dir.join(part)),
+        _ => bail!(
+            "Refusing archive volume outside the manifest's folder: {}",
+            name
+        ),
+    }
+}
+
+fn volume_path(archive: &Path, number: u64) -> PathBuf {
+    let mut name = archive.as_os_str().to_os_string();
+    name.push(format!(".{:03}", number));
+    PathBuf::from(name)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Copies `reader` into `writer`, returning the BLAKE3 hex digest
+fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_split_and_join_round_trip() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("backup.zip");
+        let data = sample(10_000);
+        fs::write(&archive, &data).unwrap();
+
+        let manifest = split_into_volumes(&archive, 4096).unwrap();
+        assert!(!archive.exists());
+        let names: Vec<&str> = manifest
+            .volumes
+            .iter()
+            .map(|v| v.file_name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["backup.zip.001", "backup.zip.002", "backup.zip.003"]
+        );
+        let sizes: Vec<u64> = manifest.volumes.iter().map(|v| v.size).collect();
+        assert_eq!(sizes, vec![4096, 4096, 1808]);
+        assert_eq!(manifest.total_size, 10_000);
+
+        let manifest_file = find_manifest(&archive).unwrap();
+        assert_eq!(VolumeManifest::load(&manifest_file).unwrap(), manifest);
+
+        let joined = dir.path().join("joined.zip");
+        assert_eq!(join_volumes(&manifest_file, &joined).unwrap(), 10_000);
+        assert_eq!(fs::read(&joined).unwrap(), data);
+    }
+
+    #[test]
+    fn test_small_archive_is_a_single_volume() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("tiny.tar.gz");
+        fs::write(&archive, b"tiny").unwrap();
+
+        let manifest = split_into_volumes(&archive, 4096).unwrap();
+        assert_eq!(manifest.volumes.len(), 1);
+        assert_eq!(
+            fs::read(dir.path().join("tiny.tar.gz.001")).unwrap(),
+            b"tiny"
+        );
+    }
+
+    #[test]
+    fn test_join_detects_damaged_and_missing_volumes() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("backup.zip");
+        fs::write(&archive, sample(9000)).unwrap();
+        split_into_volumes(&archive, 4096).unwrap();
+        let manifest_file = manifest_path(&archive);
+        let joined = dir.path().join("joined.zip");
+
+        // Same size, different content
+        let second = dir.path().join("backup.zip.002");
+        let mut bytes = fs::read(&second).unwrap();
+        bytes[0] ^= 0xFF;
+        fs::write(&second, &bytes).unwrap();
+        let err = join_volumes(&manifest_file, &joined).unwrap_err();
+        assert!(err.to_string().contains("backup.zip.002 is corrupt"));
+        assert!(!joined.exists());
+
+        fs::remove_file(dir.path().join("backup.zip.003")).unwrap();
+        bytes[0] ^= 0xFF;
+        fs::write(&second, &bytes).unwrap();
+        let err = join_volumes(&manifest_file, &joined).unwrap_err();
+        assert!(err.to_string().contains("Missing archive volume"));
+    }
+
+    #[test]
+    fn test_join_refuses_volumes_outside_the_manifest_folder() {
+        let dir = tempdir().unwrap();
+        let secret = dir.path().join("secret.txt");
+        fs::write(&secret, "not part of any archive").unwrap();
+        let parts = dir.path().join("parts");
+        fs::create_dir(&parts).unwrap();
+        let manifest_file = parts.join("backup.zip.manifest.json");
+        let joined = dir.path().join("joined.zip");
+
+        for name in [
+            secret.to_string_lossy().to_string(),
+            "../secret.txt".to_string(),
+            "sub/../../secret.txt".to_string(),
+            "..".to_string(),
+            String::new(),
+        ] {
+            let volume = VolumeInfo {
+                file_name: name.clone(),
+                size: 23,
+                blake3: blake3::hash(b"not part of any archive")
+                    .to_hex()
+                    .to_string(),
+            };
+            let manifest = VolumeManifest {
+                version: MANIFEST_VERSION,
+                archive_name: "backup.zip".to_string(),
+                total_size: 23,
+                volume_size: 4096,
+                volumes: vec![volume],
+            };
+            fs::write(&manifest_file, serde_json::to_string(&manifest).unwrap()).unwrap();
+
+            let err = join_volumes(&manifest_file, &joined).unwrap_err();
+            assert!(
+                err.to_string().contains("Refusing archive volume"),
+                "{name}: {err}"
+            );
+            assert!(!joined.exists());
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use sled::Db;
 use std::path::Path;
+use std::time::Duration;
 
 /// Key-value cache using sled (embedded database)
 pub struct Cache {
@@ -68,6 +70,11 @@ impl Cache {
         Ok(())
     }
 
+    /// Bytes the cache takes on disk, log and snapshots included
+    pub fn size_on_disk(&self) -> Result<u64> {
+        Ok(self.db.size_on_disk()?)
+    }
+
     /// Set a string key-value pair
     pub fn set_string(&self, key: &str, value: &str) -> Result<()> {
         self.set(key.as_bytes(), value.as_bytes())
@@ -99,44 +106,203 @@ impl Cache {
     }
 }
 
-/// File hash cache - specialized cache for file hashes
+/// File hash cache - specialized cache for file hashes.
+///
+/// Keys name a file as it was ("path:modified"), so renamed, deleted and
+/// edited files leave entries that can never be hit again. [`Self::compact`]
+/// drops those, entries unused for longer than the TTL and, past the size
+/// cap, the least recently used ones.
 pub struct FileHashCache {
     cache: Cache,
+    /// When each entry was last stored or read, as big-endian Unix seconds.
+    /// Entries from before this was tracked have none and count as oldest.
+    used: sled::Tree,
+    ttl: Option<Duration>,
+    max_entries: Option<usize>,
+}
+
+/// What [`FileHashCache::compact`] removed and kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    /// Entries for files that no longer exist or have changed since
+    pub stale: usize,
+    /// Entries unused for longer than the TTL
+    pub expired: usize,
+    /// Least recently used entries dropped to get under the size cap
+    pub evicted: usize,
+    pub remaining: usize,
+}
+
+impl CompactionReport {
+    pub fn removed(&self) -> usize {
+        self.stale + self.expired + self.evicted
+    }
 }
 
 impl FileHashCache {
     pub fn new(path: &Path) -> Result<Self> {
-        let cache = Cache::new(path)?;
-        Ok(Self { cache })
+        Self::with_cache(Cache::new(path)?)
     }
 
     pub fn temporary() -> Result<Self> {
-        let cache = Cache::temporary()?;
-        Ok(Self { cache })
+        Self::with_cache(Cache::temporary()?)
+    }
+
+    fn with_cache(cache: Cache) -> Result<Self> {
+        let used = cache.db.open_tree(USED_TREE)?;
+        Ok(Self {
+            cache,
+            used,
+            ttl: None,
+            max_entries: None,
+        })
+    }
+
+    /// Let [`Self::compact`] drop entries not used for `ttl`
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Let [`Self::compact`] keep at most `max` entries, the most recently
+    /// used ones
+    pub fn with_max_entries(mut self, max: usize) -> Self {
+        self.max_entries = Some(max);
+        self
     }
 
     /// Get cached hash for a file
     /// Key format: "file_path:modified_timestamp"
     pub fn get_hash(&self, file_path: &str, modified: i64) -> Result<Option<String>> {
-        let key = format!("{}:{}", file_path, modified);
-        self.cache.get_string(&key)
+        let key = hash_key(file_path, modified);
+        let hash = self.cache.get_string(&key)?;
+        if hash.is_some() {
+            self.mark_used(&key)?;
+        }
+        Ok(hash)
     }
 
     /// Set cached hash for a file
     pub fn set_hash(&self, file_path: &str, modified: i64, hash: &str) -> Result<()> {
-        let key = format!("{}:{}", file_path, modified);
-        self.cache.set_string(&key, hash)
+        let key = hash_key(file_path, modified);
+        self.cache.set_string(&key, hash)?;
+        self.mark_used(&key)
     }
 
     /// Check if file hash is cached
     pub fn has_hash(&self, file_path: &str, modified: i64) -> Result<bool> {
-        let key = format!("{}:{}", file_path, modified);
+        let key = hash_key(file_path, modified);
         self.cache.contains(key.as_bytes())
     }
 
     /// Clear all cached hashes
     pub fn clear(&self) -> Result<()> {
-        self.cache.clear()
+        self.cache.clear()?;
+        self.used.clear()?;
+        Ok(())
+    }
+
+    /// Number of cached hashes
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Bytes the cache takes on disk
+    pub fn size_on_disk(&self) -> Result<u64> {
+        self.cache.size_on_disk()
+    }
+
+    /// Drop the entries that can no longer be hit, then those past the TTL
+    /// and the size cap, and flush. A file that cannot be checked (e.g.
+    /// permission denied) keeps its entry.
+    pub fn compact(&self) -> Result<CompactionReport> {
+        let now = chrono::Utc::now().timestamp();
+        let mut report = CompactionReport::default();
+        let mut kept = Vec::new();
+        let keys: Vec<sled::IVec> = self
+            .cache
+            .db
+            .iter()
+            .keys()
+            .collect::<std::result::Result<_, _>>()?;
+        for key in keys {
+            let last_used = self.last_used(&key)?;
+            if is_stale(&key) {
+                report.stale += 1;
+            } else if self
+                .ttl
+                .is_some_and(|ttl| now.saturating_sub(last_used) > ttl.as_secs() as i64)
+            {
+                report.expired += 1;
+            } else {
+                kept.push((last_used, key));
+                continue;
+            }
+            self.remove(&key)?;
+        }
+
+        if let Some(max) = self.max_entries.filter(|&max| kept.len() > max) {
+            kept.sort();
+            for (_, key) in kept.drain(..kept.len() - max) {
+                self.remove(&key)?;
+                report.evicted += 1;
+            }
+        }
+        report.remaining = kept.len();
+        self.cache.flush()?;
+        Ok(report)
+    }
+
+    fn mark_used(&self, key: &str) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.used.insert(key, &now.to_be_bytes())?;
+        Ok(())
+    }
+
+    /// When `key` was last used; 0 when unknown
+    fn last_used(&self, key: &[u8]) -> Result<i64> {
+        Ok(self
+            .used
+            .get(key)?
+            .and_then(|bytes| <[u8; 8]>::try_from(bytes.as_ref()).ok())
+            .map_or(0, i64::from_be_bytes))
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<()> {
+        self.cache.delete(key)?;
+        self.used.remove(key)?;
+        Ok(())
+    }
+}
+
+const USED_TREE: &str = "used";
+
+fn hash_key(file_path: &str, modified: i64) -> String {
+    format!("{}:{}", file_path, modified)
+}
+
+/// Whether the file `key` names is gone or was modified since
+fn is_stale(key: &[u8]) -> bool {
+    let Some((path, modified)) = std::str::from_utf8(key)
+        .ok()
+        .and_then(|key| key.rsplit_once(':'))
+    else {
+        return true;
+    };
+    match std::fs::metadata(path) {
+        Ok(metadata) => {
+            let current = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64);
+            current.is_some_and(|current| modified.parse() != Ok(current))
+        }
+        Err(e) => e.kind() == std::io::ErrorKind::NotFound,
     }
 }
 
@@ -204,4 +370,100 @@ mod tests {
         assert!(cache.has_hash("/test/file.txt", 12345).unwrap());
         assert!(!cache.has_hash("/test/file.txt", 99999).unwrap());
     }
+
+    #[test]
+    fn test_size_on_disk_grows_with_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileHashCache::new(&dir.path().join("hashes")).unwrap();
+        let empty = cache.size_on_disk().unwrap();
+
+        for i in 0..1000 {
+            cache
+                .set_hash(&format!("/photos/{}.jpg", i), 1, &"ab".repeat(32))
+                .unwrap();
+        }
+        cache.cache.flush().unwrap();
+        assert_eq!(cache.len(), 1000);
+        assert!(cache.size_on_disk().unwrap() > empty);
+    }
+
+    fn modified_secs(path: &Path) -> i64 {
+        std::fs::metadata(path)
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    #[test]
+    fn test_compact_drops_entries_for_missing_and_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.jpg");
+        std::fs::write(&file, b"photo").unwrap();
+        let path = file.to_string_lossy();
+        let modified = modified_secs(&file);
+        let cache = FileHashCache::temporary().unwrap();
+
+        cache.set_hash(&path, modified, "current").unwrap();
+        // The same file before an edit, and a file renamed away since
+        cache.set_hash(&path, modified - 60, "edited").unwrap();
+        let renamed = dir.path().join("b.jpg").to_string_lossy().to_string();
+        cache.set_hash(&renamed, modified, "renamed").unwrap();
+
+        let report = cache.compact().unwrap();
+        assert_eq!(report.stale, 2);
+        assert_eq!(report.remaining, 1);
+        assert!(cache.has_hash(&path, modified).unwrap());
+        assert!(!cache.has_hash(&path, modified - 60).unwrap());
+        assert!(!cache.has_hash(&renamed, modified).unwrap());
+    }
+
+    #[test]
+    fn test_compact_expires_entries_past_the_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.jpg");
+        std::fs::write(&file, b"photo").unwrap();
+        let (path, modified) = (file.to_string_lossy(), modified_secs(&file));
+
+        let cache = FileHashCache::temporary()
+            .unwrap()
+            .with_ttl(Duration::from_secs(3600));
+        cache.set_hash(&path, modified, "abc").unwrap();
+        assert_eq!(cache.compact().unwrap().expired, 0);
+
+        // Last used two hours ago
+        let key = hash_key(&path, modified);
+        let then = chrono::Utc::now().timestamp() - 7200;
+        cache.used.insert(&key, &then.to_be_bytes()).unwrap();
+        let report = cache.compact().unwrap();
+        assert_eq!((report.expired, report.remaining), (1, 0));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_compact_evicts_least_recently_used_past_the_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileHashCache::temporary().unwrap().with_max_entries(2);
+        let mut files = Vec::new();
+        for (i, name) in ["a.jpg", "b.jpg", "c.jpg"].iter().enumerate() {
+            let file = dir.path().join(name);
+            std::fs::write(&file, b"photo").unwrap();
+            let (path, modified) = (file.to_string_lossy().to_string(), modified_secs(&file));
+            cache.set_hash(&path, modified, "abc").unwrap();
+            // a.jpg was used longest ago
+            let used = 1_700_000_000 + i as i64;
+            cache
+                .used
+                .insert(hash_key(&path, modified), &used.to_be_bytes())
+                .unwrap();
+            files.push((path, modified));
+        }
+
+        let report = cache.compact().unwrap();
+        assert_eq!((report.evicted, report.remaining), (1, 2));
+        assert!(!cache.has_hash(&files[0].0, files[0].1).unwrap());
+        assert!(cache.has_hash(&files[2].0, files[2].1).unwrap());
+    }
 }
@@ -1,7 +1,16 @@
 pub mod cache;
+pub mod migrations;
 pub mod models;
+pub mod pool;
 pub mod sqlite;
 
-pub use cache::Cache;
-pub use models::{DuplicateRecord, FileRecord, ScanRecord};
+pub use cache::{Cache, CompactionReport, FileHashCache};
+pub use models::{
+    ArchiveRecord, CategoryGrowth, CategoryTotal, CompressionOperation, CompressionRecord,
+    DatabaseSize, DuplicateRecord, FileChange, FileQuery, FileRecord, IgnoredGroupRecord,
+    MaintenanceRecord, MonthlySavings, Page, PluginSavings, QuarantineRecord, ReferenceFileRecord,
+    ReferenceRoot, RestorePoint, SavingsSummary, ScanDiff, ScanFileRecord, ScanRecord,
+    SimilarityRecord, TagCount, TagRecord,
+};
+pub use pool::{Database, PooledConnection};
 pub use sqlite::SqliteDatabase;
@@ -0,0 +1,411 @@
+//! Versioned schema changes.
+//!
+//! The schema is built by the ordered [`MIGRATIONS`]. Each applied migration
+//! is recorded in the `schema_migrations` table, and opening a database
+//! applies the ones it is missing, each in its own transaction, so a
+//! database written by an older release is brought forward without losing
+//! data.
+//!
+//! Migrations are never edited once released: a schema change is a new
+//! migration appended at the end with the next version. Migration 1 is the
+//! schema as it stood before versioning and only creates what is missing, so
+//! databases from those releases adopt it as they are.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use space_saver_utils::Error;
+
+/// One step of the schema
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Position in the sequence, starting at 1 with no gaps
+    pub version: u32,
+    pub description: &'static str,
+    /// Statements run in one transaction
+    pub sql: &'static str,
+}
+
+/// Every migration, oldest first
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial schema",
+        sql: "
+            CREATE TABLE IF NOT EXISTS files (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL UNIQUE,
+                size INTEGER NOT NULL,
+                hash TEXT,
+                file_type TEXT NOT NULL,
+                modified INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS scans (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL,
+                file_count INTEGER NOT NULL,
+                total_size INTEGER NOT NULL,
+                scan_time INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            -- Files seen by each recorded scan, for comparing scans over time
+            CREATE TABLE IF NOT EXISTS scan_files (
+                scan_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                file_type TEXT NOT NULL
+            );
+
+            -- Files of reference volumes, by content hash, replaced on every
+            -- indexing of their root
+            CREATE TABLE IF NOT EXISTS reference_files (
+                root TEXT NOT NULL,
+                path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                hash TEXT NOT NULL,
+                modified INTEGER NOT NULL,
+                indexed_at INTEGER NOT NULL
+            );
+
+            -- Duplicate and similar groups the user chose to keep
+            CREATE TABLE IF NOT EXISTS ignored_groups (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                hash TEXT,
+                file_paths TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS duplicates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                hash TEXT NOT NULL,
+                file_paths TEXT NOT NULL,
+                file_count INTEGER NOT NULL,
+                total_size INTEGER NOT NULL,
+                wasted_space INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            -- Compression history and user skip decisions
+            CREATE TABLE IF NOT EXISTS compressions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL,
+                source_path TEXT NOT NULL,
+                action TEXT NOT NULL,
+                plugin_name TEXT,
+                original_size INTEGER NOT NULL,
+                compressed_size INTEGER,
+                file_size INTEGER NOT NULL,
+                file_modified INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            -- Directories moved to cold storage
+            CREATE TABLE IF NOT EXISTS archives (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source_path TEXT NOT NULL,
+                archive_path TEXT NOT NULL,
+                format TEXT NOT NULL,
+                original_size INTEGER NOT NULL,
+                archive_size INTEGER NOT NULL,
+                file_count INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            -- Files set aside instead of deleted
+            CREATE TABLE IF NOT EXISTS quarantine (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                original_path TEXT NOT NULL,
+                quarantine_path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                quarantined_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_files_hash ON files(hash);
+            CREATE INDEX IF NOT EXISTS idx_compressions_path ON compressions(path);
+            CREATE INDEX IF NOT EXISTS idx_files_size ON files(size);
+            CREATE INDEX IF NOT EXISTS idx_scan_files_scan ON scan_files(scan_id);
+            CREATE INDEX IF NOT EXISTS idx_reference_files_hash ON reference_files(hash);
+            CREATE INDEX IF NOT EXISTS idx_reference_files_root ON reference_files(root);
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "Index scan files by path for scan diffs",
+        sql: "
+            DROP INDEX IF EXISTS idx_scan_files_scan;
+            CREATE INDEX idx_scan_files_scan_path ON scan_files(scan_id, path);
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "Index files by type and modification time for browsing",
+        sql: "
+            CREATE INDEX idx_files_type_size ON files(file_type, size);
+            CREATE INDEX idx_files_modified ON files(modified);
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "Keep every successful compression for savings reports",
+        sql: "
+            -- Unlike compressions, never cleared with the skip cache
+            CREATE TABLE compression_operations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source_path TEXT NOT NULL,
+                output_path TEXT NOT NULL,
+                plugin_name TEXT NOT NULL,
+                original_size INTEGER NOT NULL,
+                compressed_size INTEGER NOT NULL,
+                backup_path TEXT,
+                created_at INTEGER NOT NULL
+            );
+            CREATE INDEX idx_compression_operations_created ON compression_operations(created_at);
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "Record database maintenance runs",
+        sql: "
+            CREATE TABLE maintenance_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                pruned_files INTEGER NOT NULL,
+                size_before INTEGER NOT NULL,
+                size_after INTEGER NOT NULL,
+                ran_at INTEGER NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "Tag files and duplicate groups",
+        sql: "
+            -- target is an absolute path for files, the content hash for
+            -- duplicate groups
+            CREATE TABLE tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                target TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                UNIQUE (kind, target, tag)
+            );
+            CREATE INDEX idx_tags_tag ON tags(tag);
+        ",
+    },
+    Migration {
+        version: 7,
+        description: "Keep image similarity scores between runs",
+        sql: "
+            -- One row per pair, file_a < file_b. A score only applies while
+            -- both files keep the modification times it was computed at.
+            CREATE TABLE similarities (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_a TEXT NOT NULL,
+                modified_a INTEGER NOT NULL,
+                file_b TEXT NOT NULL,
+                modified_b INTEGER NOT NULL,
+                similarity_score REAL NOT NULL,
+                created_at INTEGER NOT NULL,
+                UNIQUE (file_a, file_b)
+            );
+            CREATE INDEX idx_similarities_file_b ON similarities(file_b);
+        ",
+    },
+    Migration {
+        version: 8,
+        description: "Record changes that can be undone",
+        sql: "
+            -- kind is 'compression' (the original was kept at backup_path and
+            -- output_path is the compressed file) or 'trash' (the file is in
+            -- the system trash). operation_id links a compression to its
+            -- row in compression_operations.
+            CREATE TABLE restore_points (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                path TEXT NOT NULL,
+                backup_path TEXT,
+                output_path TEXT,
+                size INTEGER NOT NULL,
+                operation_id INTEGER,
+                created_at INTEGER NOT NULL
+            );
+            CREATE INDEX idx_restore_points_path ON restore_points(path);
+        ",
+    },
+];
+
+/// Schema version this build reads and writes
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Apply the migrations `conn` has not had yet, returning its version after.
+/// A database from a newer release is refused rather than touched.
+pub fn migrate(conn: &mut Connection) -> Result<u32> {
+    run(conn, MIGRATIONS)
+}
+
+/// Version of the newest migration applied to `conn`, 0 for a database that
+/// has never been migrated
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    let has_table = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !has_table {
+        return Ok(0);
+    }
+    let version: Option<u32> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    Ok(version.unwrap_or(0))
+}
+
+fn run(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    let applied = current_version(conn)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if applied > latest {
+        return Err(Error::Database(format!(
+            "Database schema version {} is newer than this release supports ({}); update Space Saver",
+            applied, latest
+        ))
+        .into());
+    }
+
+    let mut version = applied;
+    for migration in migrations.iter().filter(|m| m.version > applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql).with_context(|| {
+            format!(
+                "Failed to apply database migration {} ({})",
+                migration.version, migration.description
+            )
+        })?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, description, applied_at)
+             VALUES (?1, ?2, ?3)",
+            params![
+                migration.version,
+                migration.description,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        tx.commit()?;
+        tracing::info!(
+            version = migration.version,
+            "Applied database migration: {}",
+            migration.description
+        );
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test migrations only ever follow migration 1 on its own, so they
+    // never clash with later real ones
+    const ADD_NOTE: Migration = Migration {
+        version: 2,
+        description: "Add a note to scans",
+        sql: "ALTER TABLE scans ADD COLUMN note TEXT NOT NULL DEFAULT ''",
+    };
+
+    const BROKEN: Migration = Migration {
+        version: 3,
+        description: "Broken",
+        sql: "ALTER TABLE scans ADD COLUMN label TEXT; ALTER TABLE nowhere ADD COLUMN x",
+    };
+
+    #[test]
+    fn test_versions_are_sequential() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1);
+        }
+    }
+
+    #[test]
+    fn test_database_from_before_versioning_is_adopted_and_moved_forward() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // As created by releases without migrations
+        conn.execute_batch(
+            "CREATE TABLE scans (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL,
+                file_count INTEGER NOT NULL,
+                total_size INTEGER NOT NULL,
+                scan_time INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            INSERT INTO scans (path, file_count, total_size, scan_time, created_at)
+            VALUES ('/photos', 3, 300, 1700000000, 1700000000);",
+        )
+        .unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        assert_eq!(run(&mut conn, &[MIGRATIONS[0], ADD_NOTE]).unwrap(), 2);
+        let (path, note): (String, String) = conn
+            .query_row("SELECT path, note FROM scans", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((path.as_str(), note.as_str()), ("/photos", ""));
+
+        // Already up to date: nothing runs twice
+        assert_eq!(run(&mut conn, &[MIGRATIONS[0], ADD_NOTE]).unwrap(), 2);
+        let applied: u32 = conn
+            .query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(applied, 2);
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let err = run(&mut conn, &[MIGRATIONS[0], ADD_NOTE, BROKEN]).unwrap_err();
+        assert!(err.to_string().contains("migration 3 (Broken)"));
+
+        // Migration 2 stays applied, none of migration 3 does
+        assert_eq!(current_version(&conn).unwrap(), 2);
+        assert!(conn.prepare("SELECT label FROM scans").is_err());
+    }
+
+    #[test]
+    fn test_newer_database_is_refused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, description, applied_at)
+             VALUES (?1, 'From the future', 0)",
+            [latest_version() + 1],
+        )
+        .unwrap();
+        let err = migrate(&mut conn).unwrap_err();
+        assert_eq!(
+            Error::from(err).code(),
+            space_saver_utils::ErrorCode::Database
+        );
+    }
+}
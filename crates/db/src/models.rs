@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// File record
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +13,42 @@ pub struct FileRecord {
     pub created_at: i64,
 }
 
+/// Which stored files to list; see [`crate::SqliteDatabase::find_files`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum FileQuery {
+    /// Every file, largest first
+    Largest,
+    /// Files of one category, e.g. "Image", largest first
+    Type { file_type: String },
+    /// Files beneath a directory, by path
+    Under { path: String },
+    /// Files last modified before a Unix timestamp, oldest first
+    OlderThan { before: i64 },
+}
+
+/// A slice of a long list: at most `limit` items after the first `offset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    /// The first `limit` items
+    pub fn first(limit: usize) -> Self {
+        Self { offset: 0, limit }
+    }
+
+    /// The page after this one
+    pub fn next(self) -> Self {
+        Self {
+            offset: self.offset + self.limit,
+            ..self
+        }
+    }
+}
+
 /// Scan record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanRecord {
@@ -23,6 +60,102 @@ pub struct ScanRecord {
     pub created_at: i64,
 }
 
+/// A file as it was when a scan was recorded, so later scans of the same
+/// path can be compared against it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanFileRecord {
+    pub path: String,
+    pub size: u64,
+    /// File category, e.g. "Image" or "Other"
+    pub file_type: String,
+}
+
+/// Files and bytes of one category in a recorded scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryTotal {
+    pub category: String,
+    pub file_count: usize,
+    pub total_size: u64,
+}
+
+/// Change in one category between two recorded scans
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryGrowth {
+    pub category: String,
+    pub file_count_change: i64,
+    pub size_change: i64,
+}
+
+impl CategoryGrowth {
+    /// Change per category from the totals `before` to the totals `after`,
+    /// by category name. A category missing on one side had no files there.
+    pub fn between(before: &[CategoryTotal], after: &[CategoryTotal]) -> Vec<Self> {
+        let mut changes: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+        for total in before {
+            let entry = changes.entry(&total.category).or_default();
+            entry.0 -= total.file_count as i64;
+            entry.1 -= total.total_size as i64;
+        }
+        for total in after {
+            let entry = changes.entry(&total.category).or_default();
+            entry.0 += total.file_count as i64;
+            entry.1 += total.total_size as i64;
+        }
+        changes
+            .into_iter()
+            .map(|(category, (file_count_change, size_change))| Self {
+                category: category.to_string(),
+                file_count_change,
+                size_change,
+            })
+            .collect()
+    }
+}
+
+/// A file that differs between two scans; a size of 0 on one side means it
+/// was absent there
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+/// Files added, removed or resized from one recorded scan of a path to
+/// another, largest change first, with the change per category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanDiff {
+    pub from: ScanRecord,
+    pub to: ScanRecord,
+    pub added: Vec<FileChange>,
+    pub removed: Vec<FileChange>,
+    /// Files present in both scans whose size changed, either way
+    pub resized: Vec<FileChange>,
+    pub categories: Vec<CategoryGrowth>,
+    /// Change in total size, negative when space was freed
+    pub size_change: i64,
+}
+
+/// A file of a reference volume (e.g. a NAS), indexed by content hash so
+/// local copies of it can be recognised while the volume is offline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferenceFileRecord {
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+    pub modified: i64,
+}
+
+/// An indexed reference volume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceRoot {
+    pub path: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Unix timestamp of the latest indexing
+    pub indexed_at: i64,
+}
+
 /// Duplicate record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DuplicateRecord {
@@ -35,12 +168,188 @@ pub struct DuplicateRecord {
     pub created_at: i64,
 }
 
-/// Image similarity record
+/// A duplicate or similar group the user keeps on purpose, hidden from later
+/// results while its files stay the same
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoredGroupRecord {
+    pub id: i64,
+    /// "duplicate" or "similar"
+    pub kind: String,
+    /// Content hash of a duplicate group; None for similar groups
+    pub hash: Option<String>,
+    pub paths: Vec<String>,
+    pub created_at: i64,
+}
+
+/// A label the user put on a file or a duplicate group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagRecord {
+    pub id: i64,
+    /// "file" or "duplicate_group"
+    pub kind: String,
+    /// Absolute path of a file, content hash of a duplicate group
+    pub target: String,
+    pub tag: String,
+    pub created_at: i64,
+}
+
+/// How many files and duplicate groups carry a tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub files: usize,
+    pub groups: usize,
+}
+
+/// Compression history record: a file the compress workflow already dealt
+/// with, so later scans can leave it out. `path`, `file_size` and
+/// `file_modified` describe the file as it was left on disk (the output of a
+/// compression, or the untouched original of a skip); a record only applies
+/// while that file is unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionRecord {
+    pub id: i64,
+    pub path: String,
+    pub source_path: String,
+    /// "compressed", "skipped" (no size reduction) or "user_skipped"
+    pub action: String,
+    pub plugin_name: Option<String>,
+    pub original_size: u64,
+    pub compressed_size: Option<u64>,
+    pub file_size: u64,
+    pub file_modified: i64,
+    pub created_at: i64,
+}
+
+/// A file compressed in place: the ledger behind the savings reports.
+/// Unlike a [`CompressionRecord`] it is kept when the file later changes
+/// and when the compression history is cleared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionOperation {
+    pub id: i64,
+    pub source_path: String,
+    /// The compressed file, which may have a new extension
+    pub output_path: String,
+    pub plugin_name: String,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    /// Where the original was kept, when a backup was made
+    pub backup_path: Option<String>,
+    pub created_at: i64,
+}
+
+/// Space saved by one plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginSavings {
+    pub plugin_name: String,
+    pub file_count: usize,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub saved: u64,
+}
+
+/// Space saved in one calendar month (UTC)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonthlySavings {
+    /// e.g. "2026-10"
+    pub month: String,
+    pub file_count: usize,
+    pub saved: u64,
+}
+
+/// Everything compression has saved, overall, per plugin (most first) and
+/// per month (oldest first)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavingsSummary {
+    pub file_count: usize,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub saved: u64,
+    pub by_plugin: Vec<PluginSavings>,
+    pub by_month: Vec<MonthlySavings>,
+}
+
+/// Space a database file takes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatabaseSize {
+    pub total: u64,
+    /// Unused pages inside the file, given back by a vacuum
+    pub free: u64,
+}
+
+/// One maintenance pass over the database: stale rows pruned, then the
+/// file vacuumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceRecord {
+    pub id: i64,
+    /// Stored files forgotten because they are no longer on disk
+    pub pruned_files: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub ran_at: i64,
+}
+
+/// Cold-storage record: a directory that was archived and then removed, so
+/// it can be located (and restored) from `archive_path` later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRecord {
+    pub id: i64,
+    /// Where the directory used to be
+    pub source_path: String,
+    pub archive_path: String,
+    /// Archive format extension, e.g. "tar.zst" or "7z"
+    pub format: String,
+    pub original_size: u64,
+    pub archive_size: u64,
+    pub file_count: usize,
+    pub created_at: i64,
+}
+
+/// A file moved into the quarantine directory instead of being deleted. It
+/// can be restored to `original_path` until `expires_at`, after which it is
+/// purged for good.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineRecord {
+    pub id: i64,
+    /// Where the file used to be
+    pub original_path: String,
+    /// Where it is kept now
+    pub quarantine_path: String,
+    pub size: u64,
+    pub quarantined_at: i64,
+    /// Unix timestamp after which the file may be purged
+    pub expires_at: i64,
+}
+
+/// A change made by the tool that can be undone: a file compressed with a
+/// backup of its original, or one moved to the system trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePoint {
+    pub id: i64,
+    /// "compression" or "trash"
+    pub kind: String,
+    /// Where the file was before the change, and goes back to
+    pub path: String,
+    /// The original kept by a compression
+    pub backup_path: Option<String>,
+    /// The file a compression produced, removed when the original is restored
+    pub output_path: Option<String>,
+    pub size: u64,
+    /// The [`CompressionOperation`] a compression was recorded as
+    pub operation_id: Option<i64>,
+    pub created_at: i64,
+}
+
+/// Image similarity record: the score of a pair of files as they were at
+/// `modified_a` and `modified_b` (Unix seconds). `file_a` sorts before
+/// `file_b`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimilarityRecord {
     pub id: i64,
     pub file_a: String,
+    pub modified_a: i64,
     pub file_b: String,
+    pub modified_b: i64,
     pub similarity_score: f32,
     pub created_at: i64,
 }
@@ -74,6 +383,47 @@ impl ScanRecord {
     }
 }
 
+impl IgnoredGroupRecord {
+    pub fn new(kind: &str, hash: Option<String>, paths: Vec<String>) -> Self {
+        Self {
+            id: 0,
+            kind: kind.to_string(),
+            hash,
+            paths,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+impl SimilarityRecord {
+    /// The score of two files, each given with its modification time, in
+    /// either order
+    pub fn new(a: (String, i64), b: (String, i64), similarity_score: f32) -> Self {
+        let ((file_a, modified_a), (file_b, modified_b)) = if a.0 <= b.0 { (a, b) } else { (b, a) };
+        Self {
+            id: 0,
+            file_a,
+            modified_a,
+            file_b,
+            modified_b,
+            similarity_score,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+impl TagRecord {
+    pub fn new(kind: &str, target: String, tag: String) -> Self {
+        Self {
+            id: 0,
+            kind: kind.to_string(),
+            target,
+            tag,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
 impl DuplicateRecord {
     pub fn new(
         hash: String,
@@ -94,3 +444,112 @@ impl DuplicateRecord {
         }
     }
 }
+
+impl ArchiveRecord {
+    pub fn new(
+        source_path: String,
+        archive_path: String,
+        format: &str,
+        original_size: u64,
+        archive_size: u64,
+        file_count: usize,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id: 0,
+            source_path,
+            archive_path,
+            format: format.to_string(),
+            original_size,
+            archive_size,
+            file_count,
+            created_at: now,
+        }
+    }
+}
+
+impl QuarantineRecord {
+    /// Record for a file quarantined now and kept for `retention_days`
+    pub fn new(
+        original_path: String,
+        quarantine_path: String,
+        size: u64,
+        retention_days: u32,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id: 0,
+            original_path,
+            quarantine_path,
+            size,
+            quarantined_at: now,
+            expires_at: now + i64::from(retention_days) * 86_400,
+        }
+    }
+}
+
+impl RestorePoint {
+    /// `path` moved to the system trash now
+    pub fn trashed(path: String, size: u64) -> Self {
+        Self {
+            id: 0,
+            kind: "trash".to_string(),
+            path,
+            backup_path: None,
+            output_path: None,
+            size,
+            operation_id: None,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+impl CompressionOperation {
+    /// Operation compressing `source_path` into `output_path` now. The
+    /// backup path is filled in by the caller when one was kept.
+    pub fn new(
+        source_path: String,
+        output_path: String,
+        plugin_name: String,
+        original_size: u64,
+        compressed_size: u64,
+    ) -> Self {
+        Self {
+            id: 0,
+            source_path,
+            output_path,
+            plugin_name,
+            original_size,
+            compressed_size,
+            backup_path: None,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+impl CompressionRecord {
+    /// Record for a file left at `path`; for skips `source_path` is the same
+    /// path. Plugin and compressed size are filled in by the caller when known.
+    pub fn new(
+        source_path: String,
+        path: String,
+        action: &str,
+        original_size: u64,
+        file_size: u64,
+        file_modified: i64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id: 0,
+            path,
+            source_path,
+            action: action.to_string(),
+            plugin_name: None,
+            original_size,
+            compressed_size: None,
+            file_size,
+            file_modified,
+            created_at: now,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! A database handle shared across threads and async tasks.
+//!
+//! [`Database`] keeps a few connections to one database file and lends them
+//! out: [`Database::get`] blocks until one is free, [`Database::run`] does
+//! the same on tokio's blocking threads so async code never waits on SQLite.
+//! Connections are opened in WAL mode, so readers do not block the writer,
+//! and wait out a busy database instead of failing at once.
+//!
+//! Clones share the same connections; hand one to every part of the app that
+//! reads or records something.
+
+use crate::sqlite::SqliteDatabase;
+use anyhow::{Context, Result};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+/// Connections a file database keeps open at most
+pub const DEFAULT_MAX_CONNECTIONS: usize = 4;
+
+/// A pool of connections to one database. Cheap to clone.
+#[derive(Clone)]
+pub struct Database {
+    inner: Arc<Pool>,
+}
+
+struct Pool {
+    /// `None` for an in-memory database, which only ever has one connection
+    path: Option<PathBuf>,
+    max: usize,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+struct PoolState {
+    idle: Vec<SqliteDatabase>,
+    /// Connections opened, idle or lent out
+    open: usize,
+}
+
+impl Database {
+    /// Open the database at `path`, creating it or bringing its schema up
+    /// to date as needed
+    pub fn open(path: &Path) -> Result<Self> {
+        let first = SqliteDatabase::new(path)?;
+        Ok(Self::with_connection(
+            first,
+            Some(path.to_path_buf()),
+            DEFAULT_MAX_CONNECTIONS,
+        ))
+    }
+
+    /// An in-memory database (for testing). Each in-memory connection is a
+    /// database of its own, so this one has a single connection.
+    pub fn in_memory() -> Result<Self> {
+        Ok(Self::with_connection(SqliteDatabase::in_memory()?, None, 1))
+    }
+
+    fn with_connection(first: SqliteDatabase, path: Option<PathBuf>, max: usize) -> Self {
+        Self {
+            inner: Arc::new(Pool {
+                path,
+                max: max.max(1),
+                state: Mutex::new(PoolState {
+                    idle: vec![first],
+                    open: 1,
+                }),
+                returned: Condvar::new(),
+            }),
+        }
+    }
+
+    /// A connection for this thread, waiting for one to be returned when
+    /// all are in use. It goes back to the pool when dropped.
+    pub fn get(&self) -> Result<PooledConnection> {
+        let pool = &self.inner;
+        let mut state = pool.lock();
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return Ok(self.lend(conn));
+            }
+            if state.open < pool.max {
+                if let Some(path) = &pool.path {
+                    state.open += 1;
+                    drop(state);
+                    return match SqliteDatabase::new(path) {
+                        Ok(conn) => Ok(self.lend(conn)),
+                        Err(e) => {
+                            pool.lock().open -= 1;
+                            pool.returned.notify_one();
+                            Err(e)
+                        }
+                    };
+                }
+            }
+            state = pool
+                .returned
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Run `f` with a connection on a blocking thread, for async callers
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&SqliteDatabase) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || f(&*db.get()?))
+            .await
+            .context("Database task failed")?
+    }
+
+    /// Flush the connections not in use (see [`SqliteDatabase::flush`]);
+    /// those lent out belong to work still running
+    pub fn flush(&self) -> Result<()> {
+        for conn in &self.inner.lock().idle {
+            conn.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Version of the schema this database is at
+    pub fn schema_version(&self) -> Result<u32> {
+        self.get()?.schema_version()
+    }
+
+    fn lend(&self, conn: SqliteDatabase) -> PooledConnection {
+        PooledConnection {
+            conn: Some(conn),
+            pool: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl Pool {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A connection lent by [`Database::get`]
+pub struct PooledConnection {
+    conn: Option<SqliteDatabase>,
+    pool: Arc<Pool>,
+}
+
+impl Deref for PooledConnection {
+    type Target = SqliteDatabase;
+
+    fn deref(&self) -> &SqliteDatabase {
+        self.conn
+            .as_ref()
+            .expect("connection is only taken on drop")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.lock().idle.push(conn);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ScanRecord;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_connections_are_reused_and_capped() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("spacesaver.db")).unwrap();
+
+        let held: Vec<_> = (0..DEFAULT_MAX_CONNECTIONS)
+            .map(|_| db.get().unwrap())
+            .collect();
+        // All in use: the next caller waits until one comes back
+        let waiter = {
+            let db = db.clone();
+            thread::spawn(move || db.get().unwrap().get_recent_scans(1).unwrap().len())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        drop(held);
+        assert_eq!(waiter.join().unwrap(), 0);
+        assert_eq!(db.inner.lock().open, DEFAULT_MAX_CONNECTIONS);
+    }
+
+    #[test]
+    fn test_writers_on_several_threads_all_succeed() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("spacesaver.db")).unwrap();
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for j in 0..10 {
+                        let scan = ScanRecord::new(format!("/photos/{}/{}", i, j), 1, 1, 0);
+                        db.get().unwrap().insert_scan(&scan).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(db.get().unwrap().get_recent_scans(100).unwrap().len(), 80);
+    }
+
+    #[tokio::test]
+    async fn test_run_from_async_code() {
+        let db = Database::in_memory().unwrap();
+        let id = db
+            .run(|db| db.insert_scan(&ScanRecord::new("/photos".to_string(), 3, 300, 0)))
+            .await
+            .unwrap();
+        let scan = db.run(move |db| db.get_scan(id)).await.unwrap().unwrap();
+        assert_eq!(scan.path, "/photos");
+        assert_eq!(
+            db.schema_version().unwrap(),
+            crate::migrations::latest_version()
+        );
+    }
+}
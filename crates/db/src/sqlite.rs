@@ -1,7 +1,41 @@
-use crate::models::{DuplicateRecord, FileRecord, ScanRecord};
+use crate::migrations;
+use crate::models::{
+    ArchiveRecord, CategoryGrowth, CategoryTotal, CompressionOperation, CompressionRecord,
+    DatabaseSize, DuplicateRecord, FileChange, FileQuery, FileRecord, IgnoredGroupRecord,
+    MaintenanceRecord, MonthlySavings, Page, PluginSavings, QuarantineRecord, ReferenceFileRecord,
+    ReferenceRoot, RestorePoint, SavingsSummary, ScanDiff, ScanFileRecord, ScanRecord,
+    SimilarityRecord, TagCount, TagRecord,
+};
 use anyhow::Result;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use space_saver_utils::Error;
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
+
+/// How long a connection waits for another one's write to finish before
+/// giving up with "database is locked"
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Insert a file, or update the row already stored for its path. A stored
+/// hash is kept when the new record has none and the file's size and
+/// modification time are unchanged; otherwise it is replaced.
+const UPSERT_FILE: &str = "INSERT INTO files (path, size, hash, file_type, modified, created_at)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+     ON CONFLICT(path) DO UPDATE SET
+        hash = CASE
+            WHEN excluded.hash IS NOT NULL THEN excluded.hash
+            WHEN size = excluded.size AND modified = excluded.modified THEN hash
+            ELSE NULL
+        END,
+        size = excluded.size,
+        file_type = excluded.file_type,
+        modified = excluded.modified
+     RETURNING id";
+
+const INSERT_DUPLICATE: &str =
+    "INSERT INTO duplicates (hash, file_paths, file_count, total_size, wasted_space, created_at)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
 
 /// SQLite database for persistent storage
 pub struct SqliteDatabase {
@@ -9,97 +43,259 @@ pub struct SqliteDatabase {
 }
 
 impl SqliteDatabase {
-    /// Create a new database connection
+    /// Open the database at `path`, creating it or bringing its schema up
+    /// to date as needed. The file is put in WAL mode so several
+    /// connections can use it at once (see [`crate::Database`]).
     pub fn new(path: &Path) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        let db = Self { conn };
-        db.init_tables()?;
-        Ok(db)
+        let mut conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Stays in the old mode where WAL is unsupported, e.g. some network
+        // file systems; that only costs concurrency
+        let mode: String =
+            conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            tracing::warn!(path = %path.display(), mode = %mode, "Database not in WAL mode");
+        }
+        // Safe with WAL: a power cut can only lose the latest commits
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        migrations::migrate(&mut conn)?;
+        Ok(Self { conn })
     }
 
     /// Create an in-memory database (for testing)
     pub fn in_memory() -> Result<Self> {
-        let conn = Connection::open_in_memory()?;
-        let db = Self { conn };
-        db.init_tables()?;
-        Ok(db)
+        let mut conn = Connection::open_in_memory()?;
+        migrations::migrate(&mut conn)?;
+        Ok(Self { conn })
     }
 
-    /// Initialize database tables
-    fn init_tables(&self) -> Result<()> {
-        // Files table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS files (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                path TEXT NOT NULL UNIQUE,
-                size INTEGER NOT NULL,
-                hash TEXT,
-                file_type TEXT NOT NULL,
-                modified INTEGER NOT NULL,
-                created_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
+    /// Write pages changed by finished transactions to disk now, e.g. before
+    /// the process exits
+    pub fn flush(&self) -> Result<()> {
+        self.conn.cache_flush()?;
+        Ok(())
+    }
 
-        // Scans table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS scans (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                path TEXT NOT NULL,
-                file_count INTEGER NOT NULL,
-                total_size INTEGER NOT NULL,
-                scan_time INTEGER NOT NULL,
-                created_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
+    /// Start a transaction that will write. It takes the write lock up
+    /// front, so a busy database is waited out rather than failing midway.
+    fn write_transaction(&self) -> Result<Transaction<'_>> {
+        Ok(Transaction::new_unchecked(
+            &self.conn,
+            TransactionBehavior::Immediate,
+        )?)
+    }
 
-        // Duplicates table
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS duplicates (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                hash TEXT NOT NULL,
-                file_paths TEXT NOT NULL,
-                file_count INTEGER NOT NULL,
-                total_size INTEGER NOT NULL,
-                wasted_space INTEGER NOT NULL,
-                created_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
+    /// Space the database file takes, and how much of it a [`Self::vacuum`]
+    /// would give back
+    pub fn database_size(&self) -> Result<DatabaseSize> {
+        let pragma = |name: &str| -> Result<u64> {
+            Ok(self
+                .conn
+                .pragma_query_value(None, name, |row| row.get::<_, i64>(0))? as u64)
+        };
+        let page_size = pragma("page_size")?;
+        Ok(DatabaseSize {
+            total: pragma("page_count")? * page_size,
+            free: pragma("freelist_count")? * page_size,
+        })
+    }
 
-        // Create indices
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_files_hash ON files(hash)",
-            [],
-        )?;
+    /// Rebuild the database file without its unused pages, then fold the
+    /// write-ahead log back into it. Waits for other connections' writes.
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM")?;
+        // Answers with a row, so it is queried rather than executed
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_files_size ON files(size)",
-            [],
-        )?;
+    /// Forget the stored files that are no longer on disk; returns how many.
+    /// The rows only record what scans saw, so a file on a drive that is not
+    /// attached is forgotten too and comes back with the next scan of it. A
+    /// file that cannot be checked (e.g. permission denied) is kept.
+    pub fn prune_missing_files(&self) -> Result<usize> {
+        let mut missing = Vec::new();
+        {
+            let mut stmt = self.conn.prepare("SELECT id, path FROM files")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let path: String = row.get(1)?;
+                if matches!(std::fs::symlink_metadata(&path),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound)
+                {
+                    missing.push(row.get::<_, i64>(0)?);
+                }
+            }
+        }
+
+        let tx = self.write_transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM files WHERE id = ?1")?;
+            for id in &missing {
+                stmt.execute([id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(missing.len())
+    }
 
+    /// Store similarity scores, replacing those recorded for the same pairs
+    pub fn upsert_similarities(&self, records: &[SimilarityRecord]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO similarities
+                     (file_a, modified_a, file_b, modified_b, similarity_score, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (file_a, file_b) DO UPDATE SET
+                     modified_a = excluded.modified_a,
+                     modified_b = excluded.modified_b,
+                     similarity_score = excluded.similarity_score,
+                     created_at = excluded.created_at",
+            )?;
+            for record in records {
+                stmt.execute(params![
+                    record.file_a,
+                    record.modified_a,
+                    record.file_b,
+                    record.modified_b,
+                    record.similarity_score,
+                    record.created_at,
+                ])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    /// Insert a file record
-    pub fn insert_file(&self, file: &FileRecord) -> Result<i64> {
+    /// Every score recorded for a pair including `path`, whatever the
+    /// modification times it was computed at
+    pub fn get_similarities_of(&self, path: &str) -> Result<Vec<SimilarityRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_a, modified_a, file_b, modified_b, similarity_score, created_at
+             FROM similarities WHERE file_a = ?1
+             UNION ALL
+             SELECT id, file_a, modified_a, file_b, modified_b, similarity_score, created_at
+             FROM similarities WHERE file_b = ?1",
+        )?;
+        let rows = stmt.query_map([path], |row| {
+            Ok(SimilarityRecord {
+                id: row.get(0)?,
+                file_a: row.get(1)?,
+                modified_a: row.get(2)?,
+                file_b: row.get(3)?,
+                modified_b: row.get(4)?,
+                similarity_score: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Drop the similarity scores of pairs where either file is gone or was
+    /// modified since the score was computed, returning how many. A file
+    /// that cannot be checked keeps its scores.
+    pub fn prune_stale_similarities(&self) -> Result<usize> {
+        // Pairs share files: check each file once
+        let mut checked: HashMap<(String, i64), bool> = HashMap::new();
+        let mut unchanged = |path: String, modified: i64| {
+            *checked
+                .entry((path, modified))
+                .or_insert_with_key(|(path, modified)| unchanged_since(path, *modified))
+        };
+        let mut stale = Vec::new();
+        {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT id, file_a, modified_a, file_b, modified_b FROM similarities")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                if !unchanged(row.get(1)?, row.get(2)?) || !unchanged(row.get(3)?, row.get(4)?) {
+                    stale.push(row.get::<_, i64>(0)?);
+                }
+            }
+        }
+
+        let tx = self.write_transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM similarities WHERE id = ?1")?;
+            for id in &stale {
+                stmt.execute([id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(stale.len())
+    }
+
+    /// Record a maintenance pass
+    pub fn insert_maintenance_run(&self, record: &MaintenanceRecord) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO files (path, size, hash, file_type, modified, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO maintenance_runs (pruned_files, size_before, size_after, ran_at)
+             VALUES (?1, ?2, ?3, ?4)",
             params![
-                file.path,
-                file.size as i64,
-                file.hash,
-                file.file_type,
-                file.modified,
-                file.created_at,
+                record.pruned_files as i64,
+                record.size_before as i64,
+                record.size_after as i64,
+                record.ran_at,
             ],
         )?;
 
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// The latest maintenance pass, if any ran
+    pub fn last_maintenance_run(&self) -> Result<Option<MaintenanceRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id, pruned_files, size_before, size_after, ran_at
+                 FROM maintenance_runs ORDER BY ran_at DESC, id DESC LIMIT 1",
+                [],
+                |row| {
+                    Ok(MaintenanceRecord {
+                        id: row.get(0)?,
+                        pruned_files: row.get::<_, i64>(1)? as usize,
+                        size_before: row.get::<_, i64>(2)? as u64,
+                        size_after: row.get::<_, i64>(3)? as u64,
+                        ran_at: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Version of the schema this database is at
+    pub fn schema_version(&self) -> Result<u32> {
+        migrations::current_version(&self.conn)
+    }
+
+    /// Insert a file record, or update the one stored for its path (see
+    /// [`Self::upsert_files`]), returning its id
+    pub fn insert_file(&self, file: &FileRecord) -> Result<i64> {
+        Ok(self
+            .conn
+            .prepare_cached(UPSERT_FILE)?
+            .query_row(file_params(file), |row| row.get(0))?)
+    }
+
+    /// Insert or update file records by path, in one transaction with one
+    /// prepared statement, so recording a large scan takes seconds rather
+    /// than minutes. A stored hash is kept when the new record has none and
+    /// the file's size and modification time are unchanged; otherwise it is
+    /// replaced.
+    pub fn upsert_files(&self, files: &[FileRecord]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        {
+            let mut stmt = tx.prepare(UPSERT_FILE)?;
+            for file in files {
+                stmt.query_row(file_params(file), |_| Ok(()))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Get file by path
     pub fn get_file_by_path(&self, path: &str) -> Result<Option<FileRecord>> {
         let mut stmt = self.conn.prepare(
@@ -153,6 +349,85 @@ impl SqliteDatabase {
         Ok(result)
     }
 
+    /// A page of the stored files matching `query`
+    pub fn find_files(&self, query: &FileQuery, page: Page) -> Result<Vec<FileRecord>> {
+        match query {
+            FileQuery::Largest => self.largest_files(page),
+            FileQuery::Type { file_type } => self.files_by_type(file_type, page),
+            FileQuery::Under { path } => self.files_under(path, page),
+            FileQuery::OlderThan { before } => self.files_older_than(*before, page),
+        }
+    }
+
+    /// Stored files, largest first
+    pub fn largest_files(&self, page: Page) -> Result<Vec<FileRecord>> {
+        self.query_files("ORDER BY size DESC, path", [], page)
+    }
+
+    /// Stored files of category `file_type` (e.g. "Image"), largest first
+    pub fn files_by_type(&self, file_type: &str, page: Page) -> Result<Vec<FileRecord>> {
+        self.query_files(
+            "WHERE file_type = ?1 ORDER BY size DESC, path",
+            [file_type],
+            page,
+        )
+    }
+
+    /// Stored files beneath the directory `dir` at any depth, by path.
+    /// Whole components only: `/photos` does not cover `/photos-old`.
+    pub fn files_under(&self, dir: &str, page: Page) -> Result<Vec<FileRecord>> {
+        let separator = std::path::MAIN_SEPARATOR;
+        let dir = dir.trim_end_matches(['/', separator]);
+        // Paths starting with "dir/" sort between "dir/" and "dir" followed
+        // by the character after the separator, so the path index serves it
+        let past_separator = char::from(separator as u8 + 1);
+        self.query_files(
+            "WHERE path > ?1 AND path < ?2 ORDER BY path",
+            [
+                format!("{}{}", dir, separator),
+                format!("{}{}", dir, past_separator),
+            ],
+            page,
+        )
+    }
+
+    /// Stored files last modified before the Unix timestamp `before`,
+    /// oldest first
+    pub fn files_older_than(&self, before: i64, page: Page) -> Result<Vec<FileRecord>> {
+        self.query_files(
+            "WHERE modified < ?1 ORDER BY modified, path",
+            [before],
+            page,
+        )
+    }
+
+    fn query_files(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+        page: Page,
+    ) -> Result<Vec<FileRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, path, size, hash, file_type, modified, created_at
+             FROM files {} LIMIT {} OFFSET {}",
+            clause, page.limit, page.offset
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok(FileRecord {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                size: row.get::<_, i64>(2)? as u64,
+                hash: row.get(3)?,
+                file_type: row.get(4)?,
+                modified: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
     /// Insert a scan record
     pub fn insert_scan(&self, scan: &ScanRecord) -> Result<i64> {
         self.conn.execute(
@@ -170,14 +445,28 @@ impl SqliteDatabase {
         Ok(self.conn.last_insert_rowid())
     }
 
-    /// Get recent scans
+    /// Get recent scans, newest first
     pub fn get_recent_scans(&self, limit: usize) -> Result<Vec<ScanRecord>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, path, file_count, total_size, scan_time, created_at 
-             FROM scans ORDER BY created_at DESC LIMIT ?1",
-        )?;
+        self.query_scans("ORDER BY created_at DESC, id DESC LIMIT ?1", params![limit])
+    }
+
+    pub fn get_scan(&self, id: i64) -> Result<Option<ScanRecord>> {
+        Ok(self.query_scans("WHERE id = ?1", [id])?.pop())
+    }
+
+    /// Every recorded scan of `path`, oldest first
+    pub fn get_scans_for_path(&self, path: &str) -> Result<Vec<ScanRecord>> {
+        self.query_scans("WHERE path = ?1 ORDER BY created_at, id", [path])
+    }
 
-        let scans = stmt.query_map(params![limit], |row| {
+    fn query_scans(&self, clause: &str, params: impl rusqlite::Params) -> Result<Vec<ScanRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, path, file_count, total_size, scan_time, created_at
+             FROM scans {}",
+            clause
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
             Ok(ScanRecord {
                 id: row.get(0)?,
                 path: row.get(1)?,
@@ -188,34 +477,395 @@ impl SqliteDatabase {
             })
         })?;
 
-        let mut result = Vec::new();
-        for scan in scans {
-            result.push(scan?);
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Store the files a scan saw, in one transaction
+    pub fn insert_scan_files(&self, scan_id: i64, files: &[ScanFileRecord]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO scan_files (scan_id, path, size, file_type)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for file in files {
+                stmt.execute(params![
+                    scan_id,
+                    file.path,
+                    file.size as i64,
+                    file.file_type
+                ])?;
+            }
         }
+        tx.commit()?;
+        Ok(())
+    }
 
-        Ok(result)
+    /// Forget a recorded scan and the files it saw, returning whether `id`
+    /// was known
+    pub fn delete_scan(&self, id: i64) -> Result<bool> {
+        let tx = self.write_transaction()?;
+        tx.execute("DELETE FROM scan_files WHERE scan_id = ?1", [id])?;
+        let deleted = tx.execute("DELETE FROM scans WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(deleted > 0)
     }
 
-    /// Insert a duplicate record
-    pub fn insert_duplicate(&self, dup: &DuplicateRecord) -> Result<i64> {
-        let file_paths_json = serde_json::to_string(&dup.file_paths)?;
+    /// The files recorded for a scan, by path
+    pub fn get_scan_files(&self, scan_id: i64) -> Result<Vec<ScanFileRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, size, file_type FROM scan_files WHERE scan_id = ?1 ORDER BY path",
+        )?;
+
+        let rows = stmt.query_map([scan_id], |row| {
+            Ok(ScanFileRecord {
+                path: row.get(0)?,
+                size: row.get::<_, i64>(1)? as u64,
+                file_type: row.get(2)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// File count and size per category of a recorded scan
+    pub fn get_scan_category_totals(&self, scan_id: i64) -> Result<Vec<CategoryTotal>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_type, COUNT(*), SUM(size) FROM scan_files
+             WHERE scan_id = ?1 GROUP BY file_type ORDER BY file_type",
+        )?;
+
+        let rows = stmt.query_map([scan_id], |row| {
+            Ok(CategoryTotal {
+                category: row.get(0)?,
+                file_count: row.get::<_, i64>(1)? as usize,
+                total_size: row.get::<_, i64>(2)? as u64,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Compare two recorded scans of the same path by file path and size.
+    /// Unknown scans are `NotFound`, scans of different paths
+    /// `InvalidInput`.
+    pub fn diff_scans(&self, from: i64, to: i64) -> Result<ScanDiff> {
+        let scan = |id| -> Result<ScanRecord> {
+            Ok(self
+                .get_scan(id)?
+                .ok_or_else(|| Error::NotFound(format!("Unknown scan: {}", id)))?)
+        };
+        let (from, to) = (scan(from)?, scan(to)?);
+        if from.path != to.path {
+            return Err(Error::InvalidInput(format!(
+                "Scans {} and {} are of different paths ({} and {})",
+                from.id, to.id, from.path, to.path
+            ))
+            .into());
+        }
+
+        // Files of `?1` missing from `?2`
+        let only_in = "SELECT a.path, a.size FROM scan_files a
+             WHERE a.scan_id = ?1 AND NOT EXISTS (
+                 SELECT 1 FROM scan_files b WHERE b.scan_id = ?2 AND b.path = a.path
+             )
+             ORDER BY a.size DESC, a.path";
+        let added = self.query_changes(only_in, (to.id, from.id), |path, size| FileChange {
+            path,
+            old_size: 0,
+            new_size: size,
+        })?;
+        let removed = self.query_changes(only_in, (from.id, to.id), |path, size| FileChange {
+            path,
+            old_size: size,
+            new_size: 0,
+        })?;
+        let mut stmt = self.conn.prepare(
+            "SELECT b.path, a.size, b.size FROM scan_files a
+             JOIN scan_files b ON b.scan_id = ?2 AND b.path = a.path
+             WHERE a.scan_id = ?1 AND a.size != b.size
+             ORDER BY ABS(b.size - a.size) DESC, b.path",
+        )?;
+        let resized = stmt
+            .query_map((from.id, to.id), |row| {
+                Ok(FileChange {
+                    path: row.get(0)?,
+                    old_size: row.get::<_, i64>(1)? as u64,
+                    new_size: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let categories = CategoryGrowth::between(
+            &self.get_scan_category_totals(from.id)?,
+            &self.get_scan_category_totals(to.id)?,
+        );
+        Ok(ScanDiff {
+            size_change: to.total_size as i64 - from.total_size as i64,
+            from,
+            to,
+            added,
+            removed,
+            resized,
+            categories,
+        })
+    }
+
+    fn query_changes(
+        &self,
+        sql: &str,
+        scans: (i64, i64),
+        change: impl Fn(String, u64) -> FileChange,
+    ) -> Result<Vec<FileChange>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(scans, |row| {
+            Ok(change(row.get(0)?, row.get::<_, i64>(1)? as u64))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Replace the indexed files of the reference volume at `root`, in one
+    /// transaction
+    pub fn replace_reference_files(
+        &self,
+        root: &str,
+        files: &[ReferenceFileRecord],
+        indexed_at: i64,
+    ) -> Result<()> {
+        let tx = self.write_transaction()?;
+        tx.execute("DELETE FROM reference_files WHERE root = ?1", [root])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO reference_files (root, path, size, hash, modified, indexed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for file in files {
+                stmt.execute(params![
+                    root,
+                    file.path,
+                    file.size as i64,
+                    file.hash,
+                    file.modified,
+                    indexed_at
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Indexed reference volumes, by path
+    pub fn get_reference_roots(&self) -> Result<Vec<ReferenceRoot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT root, COUNT(*), SUM(size), MAX(indexed_at) FROM reference_files
+             GROUP BY root ORDER BY root",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(ReferenceRoot {
+                path: row.get(0)?,
+                file_count: row.get::<_, i64>(1)? as usize,
+                total_size: row.get::<_, i64>(2)? as u64,
+                indexed_at: row.get(3)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Every distinct file size across the reference volumes
+    pub fn get_reference_sizes(&self) -> Result<Vec<u64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT size FROM reference_files")?;
+        let rows = stmt.query_map([], |row| Ok(row.get::<_, i64>(0)? as u64))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Reference files with this content hash, by path
+    pub fn get_reference_files_by_hash(&self, hash: &str) -> Result<Vec<ReferenceFileRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, size, hash, modified FROM reference_files
+             WHERE hash = ?1 ORDER BY path",
+        )?;
+
+        let rows = stmt.query_map([hash], |row| {
+            Ok(ReferenceFileRecord {
+                path: row.get(0)?,
+                size: row.get::<_, i64>(1)? as u64,
+                hash: row.get(2)?,
+                modified: row.get(3)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
 
+    /// Forget a reference volume, returning how many files were indexed
+    pub fn delete_reference_root(&self, root: &str) -> Result<usize> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM reference_files WHERE root = ?1", [root])?)
+    }
+
+    /// Record an ignored group, returning its id
+    pub fn insert_ignored_group(&self, group: &IgnoredGroupRecord) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO duplicates (hash, file_paths, file_count, total_size, wasted_space, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO ignored_groups (kind, hash, file_paths, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
             params![
-                dup.hash,
-                file_paths_json,
-                dup.file_count as i64,
-                dup.total_size as i64,
-                dup.wasted_space as i64,
-                dup.created_at,
+                group.kind,
+                group.hash,
+                serde_json::to_string(&group.paths)?,
+                group.created_at,
             ],
         )?;
 
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Every ignored group, newest first
+    pub fn get_ignored_groups(&self) -> Result<Vec<IgnoredGroupRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, kind, hash, file_paths, created_at FROM ignored_groups
+             ORDER BY created_at DESC, id DESC",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let paths_json: String = row.get(3)?;
+            Ok(IgnoredGroupRecord {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                hash: row.get(2)?,
+                paths: serde_json::from_str(&paths_json).unwrap_or_default(),
+                created_at: row.get(4)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Stop ignoring a group; false when the id is unknown
+    pub fn delete_ignored_group(&self, id: i64) -> Result<bool> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM ignored_groups WHERE id = ?1", [id])?
+            > 0)
+    }
+
+    /// Tag a file or group; false when it already had the tag
+    pub fn insert_tag(&self, tag: &TagRecord) -> Result<bool> {
+        Ok(self.conn.execute(
+            "INSERT OR IGNORE INTO tags (kind, target, tag, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![tag.kind, tag.target, tag.tag, tag.created_at],
+        )? > 0)
+    }
+
+    /// Take a tag off; false when it was not there
+    pub fn delete_tag(&self, kind: &str, target: &str, tag: &str) -> Result<bool> {
+        Ok(self.conn.execute(
+            "DELETE FROM tags WHERE kind = ?1 AND target = ?2 AND tag = ?3",
+            params![kind, target, tag],
+        )? > 0)
+    }
+
+    /// Tags of one file or group, alphabetically
+    pub fn get_tags(&self, kind: &str, target: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT tag FROM tags WHERE kind = ?1 AND target = ?2 ORDER BY tag")?;
+        let rows = stmt.query_map(params![kind, target], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Everything tagged `tag`, newest first
+    pub fn get_tagged(&self, tag: &str) -> Result<Vec<TagRecord>> {
+        self.query_tags("WHERE tag = ?1", tag)
+    }
+
+    /// Every tag of `kind` ("file" or "duplicate_group"), newest first
+    pub fn get_tags_of_kind(&self, kind: &str) -> Result<Vec<TagRecord>> {
+        self.query_tags("WHERE kind = ?1", kind)
+    }
+
+    /// Tags in use, alphabetically, with how many files and groups carry
+    /// each
+    pub fn tag_counts(&self) -> Result<Vec<TagCount>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tag,
+                    SUM(CASE WHEN kind = 'file' THEN 1 ELSE 0 END),
+                    SUM(CASE WHEN kind = 'file' THEN 0 ELSE 1 END)
+             FROM tags GROUP BY tag ORDER BY tag",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                files: row.get::<_, i64>(1)? as usize,
+                groups: row.get::<_, i64>(2)? as usize,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn query_tags(&self, clause: &str, value: &str) -> Result<Vec<TagRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, kind, target, tag, created_at FROM tags {}
+             ORDER BY created_at DESC, id DESC",
+            clause
+        ))?;
+        let rows = stmt.query_map([value], |row| {
+            Ok(TagRecord {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                target: row.get(2)?,
+                tag: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Insert a duplicate record
+    pub fn insert_duplicate(&self, dup: &DuplicateRecord) -> Result<i64> {
+        insert_duplicate_rows(&self.conn, std::slice::from_ref(dup))?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Insert duplicate records, in one transaction
+    pub fn insert_duplicates(&self, groups: &[DuplicateRecord]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        insert_duplicate_rows(&tx, groups)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Replace the duplicate groups found beneath any of `roots` (a group
+    /// counts when one of its files lies there) with `groups`, in one
+    /// transaction
+    pub fn replace_duplicates(&self, roots: &[String], groups: &[DuplicateRecord]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        let stale: Vec<i64> = self
+            .get_duplicates()?
+            .into_iter()
+            .filter(|dup| {
+                dup.file_paths
+                    .iter()
+                    .any(|path| roots.iter().any(|root| Path::new(path).starts_with(root)))
+            })
+            .map(|dup| dup.id)
+            .collect();
+        {
+            let mut delete = tx.prepare("DELETE FROM duplicates WHERE id = ?1")?;
+            for id in stale {
+                delete.execute([id])?;
+            }
+        }
+        insert_duplicate_rows(&tx, groups)?;
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Get all duplicate groups
     pub fn get_duplicates(&self) -> Result<Vec<DuplicateRecord>> {
         let mut stmt = self.conn.prepare(
@@ -247,6 +897,409 @@ impl SqliteDatabase {
         Ok(result)
     }
 
+    /// Insert a compression history record
+    pub fn insert_compression(&self, record: &CompressionRecord) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO compressions (path, source_path, action, plugin_name, original_size,
+                                       compressed_size, file_size, file_modified, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                record.path,
+                record.source_path,
+                record.action,
+                record.plugin_name,
+                record.original_size as i64,
+                record.compressed_size.map(|s| s as i64),
+                record.file_size as i64,
+                record.file_modified,
+                record.created_at,
+            ],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Latest compression record for `path` that still matches the file on
+    /// disk (same size and mtime). A changed file has no matching record.
+    pub fn find_compression(
+        &self,
+        path: &str,
+        file_size: u64,
+        file_modified: i64,
+    ) -> Result<Option<CompressionRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, source_path, action, plugin_name, original_size, compressed_size,
+                    file_size, file_modified, created_at
+             FROM compressions
+             WHERE path = ?1 AND file_size = ?2 AND file_modified = ?3
+             ORDER BY id DESC LIMIT 1",
+        )?;
+
+        let record = stmt.query_row(params![path, file_size as i64, file_modified], |row| {
+            Ok(CompressionRecord {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                source_path: row.get(2)?,
+                action: row.get(3)?,
+                plugin_name: row.get(4)?,
+                original_size: row.get::<_, i64>(5)? as u64,
+                compressed_size: row.get::<_, Option<i64>>(6)?.map(|s| s as u64),
+                file_size: row.get::<_, i64>(7)? as u64,
+                file_modified: row.get(8)?,
+                created_at: row.get(9)?,
+            })
+        });
+
+        match record {
+            Ok(r) => Ok(Some(r)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Number of compression history records
+    pub fn count_compressions(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM compressions", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Forget the compression history; returns how many records were removed
+    pub fn clear_compressions(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM compressions", [])?)
+    }
+
+    /// Add a compression to the savings ledger. One that kept a backup also
+    /// becomes a [`RestorePoint`], so it can be undone.
+    pub fn insert_compression_operation(&self, op: &CompressionOperation) -> Result<i64> {
+        let tx = self.write_transaction()?;
+        tx.execute(
+            "INSERT INTO compression_operations (source_path, output_path, plugin_name,
+                                                 original_size, compressed_size, backup_path,
+                                                 created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                op.source_path,
+                op.output_path,
+                op.plugin_name,
+                op.original_size as i64,
+                op.compressed_size as i64,
+                op.backup_path,
+                op.created_at,
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        if op.backup_path.is_some() {
+            insert_restore_point(
+                &tx,
+                &RestorePoint {
+                    id: 0,
+                    kind: "compression".to_string(),
+                    path: op.source_path.clone(),
+                    backup_path: op.backup_path.clone(),
+                    output_path: Some(op.output_path.clone()),
+                    size: op.original_size,
+                    operation_id: Some(id),
+                    created_at: op.created_at,
+                },
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Take a compression out of the savings ledger, once it was undone
+    pub fn delete_compression_operation(&self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM compression_operations WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent compressions, newest first
+    pub fn get_compression_operations(&self, limit: usize) -> Result<Vec<CompressionOperation>> {
+        self.query_compression_operations(
+            "ORDER BY created_at DESC, id DESC LIMIT ?1",
+            [limit as i64],
+        )
+    }
+
+    pub fn get_compression_operation(&self, id: i64) -> Result<Option<CompressionOperation>> {
+        Ok(self
+            .query_compression_operations("WHERE id = ?1", [id])?
+            .pop())
+    }
+
+    fn query_compression_operations(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<CompressionOperation>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, source_path, output_path, plugin_name, original_size, compressed_size,
+                    backup_path, created_at
+             FROM compression_operations {}",
+            clause
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok(CompressionOperation {
+                id: row.get(0)?,
+                source_path: row.get(1)?,
+                output_path: row.get(2)?,
+                plugin_name: row.get(3)?,
+                original_size: row.get::<_, i64>(4)? as u64,
+                compressed_size: row.get::<_, i64>(5)? as u64,
+                backup_path: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Space saved by every recorded compression, in total, per plugin and
+    /// per month. An output larger than its original counts as nothing saved.
+    pub fn savings_summary(&self) -> Result<SavingsSummary> {
+        const SAVED: &str = "SUM(MAX(original_size - compressed_size, 0))";
+
+        let (file_count, original_size, compressed_size, saved) = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(original_size), 0),
+                        COALESCE(SUM(compressed_size), 0), COALESCE({}, 0)
+                 FROM compression_operations",
+                SAVED
+            ),
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, i64>(3)? as u64,
+                ))
+            },
+        )?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT plugin_name, COUNT(*), SUM(original_size), SUM(compressed_size), {0}
+             FROM compression_operations
+             GROUP BY plugin_name ORDER BY {0} DESC, plugin_name",
+            SAVED
+        ))?;
+        let by_plugin = stmt
+            .query_map([], |row| {
+                Ok(PluginSavings {
+                    plugin_name: row.get(0)?,
+                    file_count: row.get::<_, i64>(1)? as usize,
+                    original_size: row.get::<_, i64>(2)? as u64,
+                    compressed_size: row.get::<_, i64>(3)? as u64,
+                    saved: row.get::<_, i64>(4)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT strftime('%Y-%m', created_at, 'unixepoch') AS month, COUNT(*), {}
+             FROM compression_operations
+             GROUP BY month ORDER BY month",
+            SAVED
+        ))?;
+        let by_month = stmt
+            .query_map([], |row| {
+                Ok(MonthlySavings {
+                    month: row.get(0)?,
+                    file_count: row.get::<_, i64>(1)? as usize,
+                    saved: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(SavingsSummary {
+            file_count,
+            original_size,
+            compressed_size,
+            saved,
+            by_plugin,
+            by_month,
+        })
+    }
+
+    /// Insert a cold-storage archive record
+    pub fn insert_archive(&self, record: &ArchiveRecord) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO archives (source_path, archive_path, format, original_size,
+                                   archive_size, file_count, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.source_path,
+                record.archive_path,
+                record.format,
+                record.original_size as i64,
+                record.archive_size as i64,
+                record.file_count as i64,
+                record.created_at,
+            ],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Latest archive made from the directory at `source_path`
+    pub fn find_archive(&self, source_path: &str) -> Result<Option<ArchiveRecord>> {
+        Ok(self
+            .query_archives(
+                "WHERE source_path = ?1 ORDER BY id DESC LIMIT 1",
+                [source_path],
+            )?
+            .pop())
+    }
+
+    /// All archive records, newest first
+    pub fn get_archives(&self) -> Result<Vec<ArchiveRecord>> {
+        self.query_archives("ORDER BY id DESC", [])
+    }
+
+    fn query_archives(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<ArchiveRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, source_path, archive_path, format, original_size, archive_size,
+                    file_count, created_at
+             FROM archives {}",
+            clause
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok(ArchiveRecord {
+                id: row.get(0)?,
+                source_path: row.get(1)?,
+                archive_path: row.get(2)?,
+                format: row.get(3)?,
+                original_size: row.get::<_, i64>(4)? as u64,
+                archive_size: row.get::<_, i64>(5)? as u64,
+                file_count: row.get::<_, i64>(6)? as usize,
+                created_at: row.get(7)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Record a change that can be undone
+    pub fn insert_restore_point(&self, point: &RestorePoint) -> Result<i64> {
+        insert_restore_point(&self.conn, point)
+    }
+
+    pub fn get_restore_point(&self, id: i64) -> Result<Option<RestorePoint>> {
+        Ok(self.query_restore_points("WHERE id = ?1", [id])?.pop())
+    }
+
+    /// Every change that can still be undone, newest first
+    pub fn get_restore_points(&self) -> Result<Vec<RestorePoint>> {
+        self.query_restore_points("ORDER BY created_at DESC, id DESC", [])
+    }
+
+    pub fn delete_restore_point(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM restore_points WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn query_restore_points(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<RestorePoint>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, kind, path, backup_path, output_path, size, operation_id, created_at
+             FROM restore_points {}",
+            clause
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok(RestorePoint {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                path: row.get(2)?,
+                backup_path: row.get(3)?,
+                output_path: row.get(4)?,
+                size: row.get::<_, i64>(5)? as u64,
+                operation_id: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Insert a quarantine record
+    pub fn insert_quarantine(&self, record: &QuarantineRecord) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO quarantine (original_path, quarantine_path, size, quarantined_at,
+                                     expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                record.original_path,
+                record.quarantine_path,
+                record.size as i64,
+                record.quarantined_at,
+                record.expires_at,
+            ],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn get_quarantine_record(&self, id: i64) -> Result<Option<QuarantineRecord>> {
+        Ok(self.query_quarantine("WHERE id = ?1", [id])?.pop())
+    }
+
+    /// All quarantined files, newest first
+    pub fn get_quarantine(&self) -> Result<Vec<QuarantineRecord>> {
+        self.query_quarantine("ORDER BY id DESC", [])
+    }
+
+    /// Quarantined files whose retention period ended at or before `now`
+    pub fn get_expired_quarantine(&self, now: i64) -> Result<Vec<QuarantineRecord>> {
+        self.query_quarantine("WHERE expires_at <= ?1 ORDER BY id", [now])
+    }
+
+    pub fn delete_quarantine_record(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM quarantine WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn query_quarantine(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<QuarantineRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, original_path, quarantine_path, size, quarantined_at, expires_at
+             FROM quarantine {}",
+            clause
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            Ok(QuarantineRecord {
+                id: row.get(0)?,
+                original_path: row.get(1)?,
+                quarantine_path: row.get(2)?,
+                size: row.get::<_, i64>(3)? as u64,
+                quarantined_at: row.get(4)?,
+                expires_at: row.get(5)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
     /// Delete a file record
     pub fn delete_file(&self, id: i64) -> Result<()> {
         self.conn
@@ -258,11 +1311,79 @@ impl SqliteDatabase {
     pub fn clear_all(&self) -> Result<()> {
         self.conn.execute("DELETE FROM files", [])?;
         self.conn.execute("DELETE FROM scans", [])?;
+        self.conn.execute("DELETE FROM scan_files", [])?;
         self.conn.execute("DELETE FROM duplicates", [])?;
+        self.conn.execute("DELETE FROM compressions", [])?;
+        self.conn.execute("DELETE FROM archives", [])?;
+        self.conn.execute("DELETE FROM quarantine", [])?;
+        self.conn.execute("DELETE FROM reference_files", [])?;
+        self.conn.execute("DELETE FROM ignored_groups", [])?;
+        self.conn.execute("DELETE FROM tags", [])?;
+        self.conn.execute("DELETE FROM similarities", [])?;
         Ok(())
     }
 }
 
+/// Whether the file at `path` still has modification time `modified` (Unix
+/// seconds); a file that cannot be checked counts as unchanged
+fn unchanged_since(path: &str, modified: i64) -> bool {
+    match std::fs::metadata(path) {
+        Ok(metadata) => {
+            metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs() as i64)
+                == modified
+        }
+        Err(e) => e.kind() != std::io::ErrorKind::NotFound,
+    }
+}
+
+fn file_params(file: &FileRecord) -> impl rusqlite::Params + '_ {
+    (
+        &file.path,
+        file.size as i64,
+        &file.hash,
+        &file.file_type,
+        file.modified,
+        file.created_at,
+    )
+}
+
+fn insert_restore_point(conn: &Connection, point: &RestorePoint) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO restore_points (kind, path, backup_path, output_path, size, operation_id,
+                                     created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            point.kind,
+            point.path,
+            point.backup_path,
+            point.output_path,
+            point.size as i64,
+            point.operation_id,
+            point.created_at,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn insert_duplicate_rows(conn: &Connection, groups: &[DuplicateRecord]) -> Result<()> {
+    let mut stmt = conn.prepare_cached(INSERT_DUPLICATE)?;
+    for dup in groups {
+        stmt.execute(params![
+            dup.hash,
+            serde_json::to_string(&dup.file_paths)?,
+            dup.file_count as i64,
+            dup.total_size as i64,
+            dup.wasted_space as i64,
+            dup.created_at,
+        ])?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +1394,26 @@ mod tests {
         // Just ensure it can be created
     }
 
+    #[test]
+    fn test_reopened_database_keeps_its_data_and_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spacesaver.db");
+        let db = SqliteDatabase::new(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), migrations::latest_version());
+        db.insert_scan(&ScanRecord::new(
+            "/photos".to_string(),
+            3,
+            300,
+            1_700_000_000,
+        ))
+        .unwrap();
+        drop(db);
+
+        let db = SqliteDatabase::new(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), migrations::latest_version());
+        assert_eq!(db.get_recent_scans(10).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_insert_and_get_file() {
         let db = SqliteDatabase::in_memory().unwrap();
@@ -291,6 +1432,166 @@ mod tests {
         let retrieved = retrieved.unwrap();
         assert_eq!(retrieved.path, "/test/file.txt");
         assert_eq!(retrieved.size, 1024);
+
+        // A re-scan updates the row instead of violating the unique path
+        let grown = FileRecord { size: 2048, ..file };
+        assert_eq!(db.insert_file(&grown).unwrap(), id);
+        assert_eq!(
+            db.get_file_by_path("/test/file.txt").unwrap().unwrap().size,
+            2048
+        );
+    }
+
+    #[test]
+    fn test_browse_stored_files_by_page() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let file = |path: &str, size, file_type: &str, modified| {
+            FileRecord::new(path.to_string(), size, file_type.to_string(), modified)
+        };
+        db.upsert_files(&[
+            file("/photos/a.jpg", 300, "Image", 30),
+            file("/photos/trip/b.jpg", 100, "Image", 10),
+            file("/photos-old/c.jpg", 500, "Image", 50),
+            file("/photos/notes.txt", 5, "Document", 20),
+            file("/videos/d.mp4", 900, "Video", 40),
+        ])
+        .unwrap();
+        let paths =
+            |files: Vec<FileRecord>| -> Vec<String> { files.into_iter().map(|f| f.path).collect() };
+
+        let first = Page::first(2);
+        assert_eq!(
+            paths(db.largest_files(first).unwrap()),
+            ["/videos/d.mp4", "/photos-old/c.jpg"]
+        );
+        assert_eq!(
+            paths(db.largest_files(first.next()).unwrap()),
+            ["/photos/a.jpg", "/photos/trip/b.jpg"]
+        );
+        assert_eq!(db.largest_files(first.next().next()).unwrap().len(), 1);
+        assert!(db.largest_files(Page::first(0)).unwrap().is_empty());
+
+        assert_eq!(
+            paths(db.files_by_type("Image", Page::first(10)).unwrap()),
+            ["/photos-old/c.jpg", "/photos/a.jpg", "/photos/trip/b.jpg"]
+        );
+        assert!(db
+            .files_by_type("Audio", Page::first(10))
+            .unwrap()
+            .is_empty());
+
+        // Whole components, with or without a trailing separator
+        for dir in ["/photos", "/photos/"] {
+            assert_eq!(
+                paths(db.files_under(dir, Page::first(10)).unwrap()),
+                ["/photos/a.jpg", "/photos/notes.txt", "/photos/trip/b.jpg"]
+            );
+        }
+        assert_eq!(db.files_under("/", Page::first(10)).unwrap().len(), 5);
+
+        let old = FileQuery::OlderThan { before: 30 };
+        assert_eq!(
+            paths(db.find_files(&old, Page::first(10)).unwrap()),
+            ["/photos/trip/b.jpg", "/photos/notes.txt"]
+        );
+    }
+
+    #[test]
+    fn test_upsert_files_records_a_large_scan_twice() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let files: Vec<FileRecord> = (0..20_000)
+            .map(|i| FileRecord::new(format!("/photos/{}.jpg", i), i, "Image".to_string(), 100))
+            .collect();
+        db.upsert_files(&files).unwrap();
+        db.upsert_files(&files).unwrap();
+
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 20_000);
+        let last = db.get_file_by_path("/photos/19999.jpg").unwrap().unwrap();
+        assert_eq!(last.size, 19_999);
+    }
+
+    #[test]
+    fn test_insert_duplicates_in_one_batch() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let groups: Vec<DuplicateRecord> = ["a", "b", "c"]
+            .iter()
+            .map(|hash| {
+                DuplicateRecord::new(
+                    hash.to_string(),
+                    vec![format!("/x/{}1", hash), format!("/x/{}2", hash)],
+                    2,
+                    20,
+                    10,
+                )
+            })
+            .collect();
+        db.insert_duplicates(&groups).unwrap();
+        db.insert_duplicates(&[]).unwrap();
+
+        let stored = db.get_duplicates().unwrap();
+        assert_eq!(stored.len(), 3);
+        assert!(stored
+            .iter()
+            .any(|g| g.hash == "b" && g.file_paths[1] == "/x/b2"));
+    }
+
+    #[test]
+    fn test_upsert_files_keeps_hash_of_unchanged_file() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let file = |size, hash: Option<&str>| FileRecord {
+            hash: hash.map(str::to_string),
+            ..FileRecord::new("/a.jpg".to_string(), size, "Image".to_string(), 100)
+        };
+        db.upsert_files(&[file(10, Some("abc"))]).unwrap();
+        db.upsert_files(&[file(10, None)]).unwrap();
+        let stored = db.get_file_by_path("/a.jpg").unwrap().unwrap();
+        assert_eq!(stored.hash.as_deref(), Some("abc"));
+
+        // Changed since it was hashed: the hash no longer applies
+        db.upsert_files(&[file(12, None)]).unwrap();
+        let stored = db.get_file_by_path("/a.jpg").unwrap().unwrap();
+        assert_eq!((stored.size, stored.hash), (12, None));
+    }
+
+    #[test]
+    fn test_replace_duplicates_under_roots() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let group = |hash: &str, paths: &[&str]| {
+            DuplicateRecord::new(
+                hash.to_string(),
+                paths.iter().map(|p| p.to_string()).collect(),
+                paths.len(),
+                20,
+                10,
+            )
+        };
+        db.replace_duplicates(
+            &["/photos".to_string(), "/music".to_string()],
+            &[
+                group("p", &["/photos/a.jpg", "/photos/b.jpg"]),
+                group("m", &["/music/a.mp3", "/music/b.mp3"]),
+            ],
+        )
+        .unwrap();
+        // Scanning /photos again replaces its groups only; /photosets is
+        // not beneath /photos
+        db.replace_duplicates(
+            &["/photos".to_string()],
+            &[group("q", &["/photos/c.jpg", "/photosets/c.jpg"])],
+        )
+        .unwrap();
+        let mut hashes: Vec<String> = db
+            .get_duplicates()
+            .unwrap()
+            .into_iter()
+            .map(|d| d.hash)
+            .collect();
+        hashes.sort();
+        assert_eq!(hashes, ["m", "q"]);
     }
 
     #[test]
@@ -305,4 +1606,559 @@ mod tests {
         assert_eq!(scans.len(), 1);
         assert_eq!(scans[0].path, "/test");
     }
+
+    #[test]
+    fn test_scan_files_and_category_totals() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let file = |path: &str, size, file_type: &str| ScanFileRecord {
+            path: path.to_string(),
+            size,
+            file_type: file_type.to_string(),
+        };
+        let first = db
+            .insert_scan(&ScanRecord::new("/data".to_string(), 3, 600, 1))
+            .unwrap();
+        db.insert_scan_files(
+            first,
+            &[
+                file("/data/b.jpg", 100, "Image"),
+                file("/data/a.jpg", 200, "Image"),
+                file("/data/c.bin", 300, "Other"),
+            ],
+        )
+        .unwrap();
+        let other = db
+            .insert_scan(&ScanRecord::new("/elsewhere".to_string(), 0, 0, 1))
+            .unwrap();
+
+        let files = db.get_scan_files(first).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].path, "/data/a.jpg");
+        assert_eq!(
+            db.get_scan_category_totals(first).unwrap(),
+            vec![
+                CategoryTotal {
+                    category: "Image".to_string(),
+                    file_count: 2,
+                    total_size: 300,
+                },
+                CategoryTotal {
+                    category: "Other".to_string(),
+                    file_count: 1,
+                    total_size: 300,
+                },
+            ]
+        );
+        assert!(db.get_scan_files(other).unwrap().is_empty());
+        let scans = db.get_scans_for_path("/data").unwrap();
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].id, first);
+        assert_eq!(db.get_scan(other).unwrap().unwrap().path, "/elsewhere");
+        assert!(db.get_scan(999).unwrap().is_none());
+
+        assert!(db.delete_scan(first).unwrap());
+        assert!(!db.delete_scan(first).unwrap());
+        assert!(db.get_scan(first).unwrap().is_none());
+        assert!(db.get_scan_files(first).unwrap().is_empty());
+        assert_eq!(db.get_recent_scans(10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_diff_scans_in_sql() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let scan = |files: &[(&str, u64, &str)]| {
+            let total = files.iter().map(|f| f.1).sum();
+            let id = db
+                .insert_scan(&ScanRecord::new("/data".to_string(), files.len(), total, 1))
+                .unwrap();
+            let files: Vec<ScanFileRecord> = files
+                .iter()
+                .map(|(path, size, file_type)| ScanFileRecord {
+                    path: path.to_string(),
+                    size: *size,
+                    file_type: file_type.to_string(),
+                })
+                .collect();
+            db.insert_scan_files(id, &files).unwrap();
+            id
+        };
+        let first = scan(&[
+            ("/data/same.jpg", 100, "Image"),
+            ("/data/grew.log", 10, "Document"),
+            ("/data/shrank.mp4", 900, "Video"),
+            ("/data/gone.bin", 50, "Other"),
+        ]);
+        let second = scan(&[
+            ("/data/same.jpg", 100, "Image"),
+            ("/data/grew.log", 40, "Document"),
+            ("/data/shrank.mp4", 300, "Video"),
+            ("/data/new.jpg", 200, "Image"),
+            ("/data/new.txt", 5, "Document"),
+        ]);
+
+        let diff = db.diff_scans(first, second).unwrap();
+        assert_eq!(diff.size_change, 645 - 1060);
+        let paths = |changes: &[FileChange]| -> Vec<String> {
+            changes.iter().map(|c| c.path.clone()).collect()
+        };
+        assert_eq!(paths(&diff.added), ["/data/new.jpg", "/data/new.txt"]);
+        assert_eq!(
+            diff.removed,
+            [FileChange {
+                path: "/data/gone.bin".to_string(),
+                old_size: 50,
+                new_size: 0,
+            }]
+        );
+        // Largest change first, whichever way
+        assert_eq!(paths(&diff.resized), ["/data/shrank.mp4", "/data/grew.log"]);
+        let change = |category: &str| {
+            let c = diff
+                .categories
+                .iter()
+                .find(|c| c.category == category)
+                .unwrap();
+            (c.file_count_change, c.size_change)
+        };
+        assert_eq!(change("Image"), (1, 200));
+        assert_eq!(change("Document"), (1, 35));
+        assert_eq!(change("Video"), (0, -600));
+        assert_eq!(change("Other"), (-1, -50));
+
+        // Nothing changed between a scan and itself
+        let same = db.diff_scans(second, second).unwrap();
+        assert!(same.added.is_empty() && same.removed.is_empty() && same.resized.is_empty());
+        assert!(same.categories.iter().all(|c| c.size_change == 0));
+    }
+
+    #[test]
+    fn test_diff_scans_refuses_unknown_or_unrelated_scans() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let data = db
+            .insert_scan(&ScanRecord::new("/data".to_string(), 0, 0, 1))
+            .unwrap();
+        let other = db
+            .insert_scan(&ScanRecord::new("/elsewhere".to_string(), 0, 0, 1))
+            .unwrap();
+        let code = |err: anyhow::Error| Error::from(err).code();
+        assert_eq!(
+            code(db.diff_scans(data, 999).unwrap_err()),
+            space_saver_utils::ErrorCode::NotFound
+        );
+        assert_eq!(
+            code(db.diff_scans(data, other).unwrap_err()),
+            space_saver_utils::ErrorCode::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_compression_record_matches_unchanged_file_only() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let mut record = CompressionRecord::new(
+            "/photos/a.png".to_string(),
+            "/photos/a.webp".to_string(),
+            "compressed",
+            4096,
+            1024,
+            12345,
+        );
+        record.plugin_name = Some("WebP Converter".to_string());
+        record.compressed_size = Some(1024);
+        db.insert_compression(&record).unwrap();
+
+        let found = db.find_compression("/photos/a.webp", 1024, 12345).unwrap();
+        let found = found.unwrap();
+        assert_eq!(found.action, "compressed");
+        assert_eq!(found.source_path, "/photos/a.png");
+        assert_eq!(found.plugin_name.as_deref(), Some("WebP Converter"));
+        assert_eq!(found.compressed_size, Some(1024));
+
+        // A modified file no longer matches its record
+        assert!(db
+            .find_compression("/photos/a.webp", 2048, 12345)
+            .unwrap()
+            .is_none());
+        assert!(db
+            .find_compression("/photos/a.webp", 1024, 99999)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_archive_records() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        assert!(db.find_archive("/projects/old").unwrap().is_none());
+
+        for archive in ["/cold/old.7z", "/cold/old.tar.zst"] {
+            let record = ArchiveRecord::new(
+                "/projects/old".to_string(),
+                archive.to_string(),
+                "tar.zst",
+                10_000,
+                2_000,
+                42,
+            );
+            db.insert_archive(&record).unwrap();
+        }
+
+        let found = db.find_archive("/projects/old").unwrap().unwrap();
+        assert_eq!(found.archive_path, "/cold/old.tar.zst");
+        assert_eq!(found.file_count, 42);
+        assert_eq!(found.archive_size, 2_000);
+        assert_eq!(db.get_archives().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_quarantine_records() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let kept = QuarantineRecord::new(
+            "/photos/a.jpg".to_string(),
+            "/q/1_a.jpg".to_string(),
+            100,
+            30,
+        );
+        let mut expired = kept.clone();
+        expired.original_path = "/photos/b.jpg".to_string();
+        expired.expires_at = kept.quarantined_at - 1;
+        let kept_id = db.insert_quarantine(&kept).unwrap();
+        let expired_id = db.insert_quarantine(&expired).unwrap();
+
+        assert_eq!(kept.expires_at - kept.quarantined_at, 30 * 86_400);
+        assert_eq!(db.get_quarantine().unwrap()[0].id, expired_id);
+        let due = db.get_expired_quarantine(kept.quarantined_at).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].original_path, "/photos/b.jpg");
+
+        db.delete_quarantine_record(expired_id).unwrap();
+        assert!(db.get_quarantine_record(expired_id).unwrap().is_none());
+        let found = db.get_quarantine_record(kept_id).unwrap().unwrap();
+        assert_eq!(found.quarantine_path, "/q/1_a.jpg");
+        assert_eq!(found.size, 100);
+    }
+
+    #[test]
+    fn test_reference_files_replaced_per_root() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let file = |path: &str, size, hash: &str| ReferenceFileRecord {
+            path: path.to_string(),
+            size,
+            hash: hash.to_string(),
+            modified: 1,
+        };
+        db.replace_reference_files(
+            "/nas",
+            &[
+                file("/nas/a.jpg", 100, "aaa"),
+                file("/nas/old.jpg", 50, "old"),
+            ],
+            10,
+        )
+        .unwrap();
+        db.replace_reference_files("/backup", &[file("/backup/a.jpg", 100, "aaa")], 10)
+            .unwrap();
+        // Re-indexing a root replaces its files only
+        db.replace_reference_files("/nas", &[file("/nas/a.jpg", 100, "aaa")], 20)
+            .unwrap();
+
+        assert!(db.get_reference_files_by_hash("old").unwrap().is_empty());
+        let copies = db.get_reference_files_by_hash("aaa").unwrap();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].path, "/backup/a.jpg");
+        assert_eq!(db.get_reference_sizes().unwrap(), vec![100]);
+
+        let roots = db.get_reference_roots().unwrap();
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[1].path, "/nas");
+        assert_eq!((roots[1].file_count, roots[1].indexed_at), (1, 20));
+
+        assert_eq!(db.delete_reference_root("/nas").unwrap(), 1);
+        assert_eq!(db.get_reference_roots().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ignored_groups() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let paths = vec!["/a/x.jpg".to_string(), "/b/x.jpg".to_string()];
+        let id = db
+            .insert_ignored_group(&IgnoredGroupRecord::new(
+                "duplicate",
+                Some("abc".to_string()),
+                paths.clone(),
+            ))
+            .unwrap();
+        db.insert_ignored_group(&IgnoredGroupRecord::new("similar", None, paths.clone()))
+            .unwrap();
+
+        let groups = db.get_ignored_groups().unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].id, id);
+        assert_eq!(groups[1].hash.as_deref(), Some("abc"));
+        assert_eq!(groups[1].paths, paths);
+
+        assert!(db.delete_ignored_group(id).unwrap());
+        assert!(!db.delete_ignored_group(id).unwrap());
+        assert_eq!(db.get_ignored_groups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_tags() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let tag = |kind: &str, target: &str, tag: &str| {
+            db.insert_tag(&TagRecord::new(kind, target.to_string(), tag.to_string()))
+                .unwrap()
+        };
+        assert!(tag("file", "/photos/a.jpg", "keep"));
+        assert!(!tag("file", "/photos/a.jpg", "keep"));
+        assert!(tag("file", "/photos/a.jpg", "client-x"));
+        assert!(tag("file", "/photos/b.jpg", "keep"));
+        assert!(tag("duplicate_group", "abc", "keep"));
+
+        assert_eq!(
+            db.get_tags("file", "/photos/a.jpg").unwrap(),
+            ["client-x", "keep"]
+        );
+        let kept = db.get_tagged("keep").unwrap();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].target, "abc");
+        assert_eq!(db.get_tags_of_kind("file").unwrap().len(), 3);
+        assert_eq!(
+            db.tag_counts().unwrap(),
+            [
+                TagCount {
+                    tag: "client-x".to_string(),
+                    files: 1,
+                    groups: 0
+                },
+                TagCount {
+                    tag: "keep".to_string(),
+                    files: 2,
+                    groups: 1
+                },
+            ]
+        );
+
+        assert!(db.delete_tag("file", "/photos/a.jpg", "keep").unwrap());
+        assert!(!db.delete_tag("file", "/photos/a.jpg", "keep").unwrap());
+        assert_eq!(db.get_tags("file", "/photos/a.jpg").unwrap(), ["client-x"]);
+    }
+
+    #[test]
+    fn test_clear_compressions() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let record = CompressionRecord::new(
+            "/docs/a.zip".to_string(),
+            "/docs/a.zip".to_string(),
+            "user_skipped",
+            2048,
+            2048,
+            1,
+        );
+        db.insert_compression(&record).unwrap();
+        db.insert_compression(&record).unwrap();
+        assert_eq!(db.count_compressions().unwrap(), 2);
+
+        assert_eq!(db.clear_compressions().unwrap(), 2);
+        assert_eq!(db.count_compressions().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_similarities_are_replaced_and_pruned_when_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let path = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, name).unwrap();
+            let modified = std::fs::metadata(&path)
+                .unwrap()
+                .modified()
+                .unwrap()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64;
+            (path.to_string_lossy().to_string(), modified)
+        };
+        let (a, b, c) = (path("a.png"), path("b.png"), path("c.png"));
+        let gone = (dir.path().join("gone.png").to_string_lossy().to_string(), 1);
+
+        // Stored in path order whatever the order given
+        db.upsert_similarities(&[
+            SimilarityRecord::new(b.clone(), a.clone(), 0.5),
+            SimilarityRecord::new(a.clone(), c.clone(), 0.7),
+            SimilarityRecord::new(b.clone(), (c.0.clone(), c.1 - 60), 0.2),
+            SimilarityRecord::new(a.clone(), gone, 0.9),
+        ])
+        .unwrap();
+        db.upsert_similarities(&[SimilarityRecord::new(a.clone(), b.clone(), 0.6)])
+            .unwrap();
+
+        let of_a = db.get_similarities_of(&a.0).unwrap();
+        assert_eq!(of_a.len(), 3);
+        let ab = of_a.iter().find(|r| r.file_b == b.0).unwrap();
+        assert_eq!(
+            (ab.file_a.as_str(), ab.similarity_score),
+            (a.0.as_str(), 0.6)
+        );
+        assert_eq!(db.get_similarities_of(&b.0).unwrap().len(), 2);
+
+        // c.png changed since its score with b.png, gone.png is gone
+        assert_eq!(db.prune_stale_similarities().unwrap(), 2);
+        assert_eq!(db.get_similarities_of(&a.0).unwrap().len(), 2);
+        assert_eq!(db.get_similarities_of(&b.0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_prune_missing_files_and_vacuum() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SqliteDatabase::new(&dir.path().join("spacesaver.db")).unwrap();
+        let kept = dir.path().join("kept.txt");
+        std::fs::write(&kept, b"still here").unwrap();
+        let mut files: Vec<FileRecord> = (0..500)
+            .map(|i| {
+                let path = dir.path().join(format!("gone-{}.txt", i));
+                FileRecord::new(
+                    path.to_string_lossy().to_string(),
+                    10,
+                    "Document".to_string(),
+                    1,
+                )
+            })
+            .collect();
+        files.push(FileRecord::new(
+            kept.to_string_lossy().to_string(),
+            10,
+            "Document".to_string(),
+            1,
+        ));
+        db.upsert_files(&files).unwrap();
+
+        assert_eq!(db.prune_missing_files().unwrap(), 500);
+        assert_eq!(db.largest_files(Page::first(10)).unwrap().len(), 1);
+        let before = db.database_size().unwrap();
+        assert!(before.free > 0);
+
+        db.vacuum().unwrap();
+        let after = db.database_size().unwrap();
+        assert_eq!(after.free, 0);
+        assert!(after.total < before.total);
+        assert_eq!(db.prune_missing_files().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_last_maintenance_run() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        assert!(db.last_maintenance_run().unwrap().is_none());
+        for ran_at in [200, 100] {
+            db.insert_maintenance_run(&MaintenanceRecord {
+                id: 0,
+                pruned_files: 3,
+                size_before: 8192,
+                size_after: 4096,
+                ran_at,
+            })
+            .unwrap();
+        }
+        assert_eq!(db.last_maintenance_run().unwrap().unwrap().ran_at, 200);
+    }
+
+    #[test]
+    fn test_savings_summary_per_plugin_and_month() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        assert_eq!(db.savings_summary().unwrap(), SavingsSummary::default());
+
+        // 2026-09-15, 2026-10-01 and 2026-10-20 (UTC)
+        let operations = [
+            ("/photos/a.png", "image_png", 1000, 400, 1_789_430_400),
+            ("/photos/b.png", "image_png", 2000, 1500, 1_790_812_800),
+            ("/docs/c.pdf", "pdf", 500, 100, 1_792_454_400),
+            // Grew: counted as compressed, but nothing saved
+            ("/docs/d.pdf", "pdf", 300, 350, 1_792_454_400),
+        ];
+        for (path, plugin, original, compressed, at) in operations {
+            let mut op = CompressionOperation::new(
+                path.to_string(),
+                path.to_string(),
+                plugin.to_string(),
+                original,
+                compressed,
+            );
+            op.created_at = at;
+            if plugin == "pdf" {
+                op.backup_path = Some(format!("{}.bak", path));
+            }
+            db.insert_compression_operation(&op).unwrap();
+        }
+        // The compression history is cleared on its own
+        db.clear_compressions().unwrap();
+
+        let summary = db.savings_summary().unwrap();
+        assert_eq!(summary.file_count, 4);
+        assert_eq!(summary.original_size, 3800);
+        assert_eq!(summary.compressed_size, 2350);
+        assert_eq!(summary.saved, 1500);
+        assert_eq!(
+            summary.by_plugin,
+            [
+                PluginSavings {
+                    plugin_name: "image_png".to_string(),
+                    file_count: 2,
+                    original_size: 3000,
+                    compressed_size: 1900,
+                    saved: 1100,
+                },
+                PluginSavings {
+                    plugin_name: "pdf".to_string(),
+                    file_count: 2,
+                    original_size: 800,
+                    compressed_size: 450,
+                    saved: 400,
+                },
+            ]
+        );
+        let months: Vec<_> = summary
+            .by_month
+            .iter()
+            .map(|m| (m.month.as_str(), m.file_count, m.saved))
+            .collect();
+        assert_eq!(months, [("2026-09", 1, 600), ("2026-10", 3, 900)]);
+
+        let recent = db.get_compression_operations(1).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].source_path, "/docs/d.pdf");
+        assert_eq!(recent[0].backup_path.as_deref(), Some("/docs/d.pdf.bak"));
+    }
+
+    #[test]
+    fn test_backed_up_compressions_and_trashed_files_can_be_restored() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let mut op = CompressionOperation::new(
+            "/photos/a.png".to_string(),
+            "/photos/a.webp".to_string(),
+            "WebP Converter".to_string(),
+            1000,
+            400,
+        );
+        db.insert_compression_operation(&op).unwrap();
+        op.backup_path = Some("/photos/a.png.bak".to_string());
+        let op_id = db.insert_compression_operation(&op).unwrap();
+        let trashed = db
+            .insert_restore_point(&RestorePoint::trashed("/docs/old.pdf".to_string(), 50))
+            .unwrap();
+
+        // Only the compression that kept a backup can be undone
+        let points = db.get_restore_points().unwrap();
+        assert_eq!(points.len(), 2);
+        let compression = points.iter().find(|p| p.kind == "compression").unwrap();
+        assert_eq!(compression.path, "/photos/a.png");
+        assert_eq!(compression.output_path.as_deref(), Some("/photos/a.webp"));
+        assert_eq!(compression.operation_id, Some(op_id));
+        assert_eq!(db.get_restore_point(trashed).unwrap().unwrap().size, 50);
+        let recorded = db.get_compression_operation(op_id).unwrap().unwrap();
+        assert_eq!(recorded.compressed_size, 400);
+
+        db.delete_restore_point(trashed).unwrap();
+        db.delete_compression_operation(op_id).unwrap();
+        assert!(db.get_restore_point(trashed).unwrap().is_none());
+        assert!(db.get_compression_operation(op_id).unwrap().is_none());
+        assert_eq!(db.savings_summary().unwrap().file_count, 1);
+    }
 }
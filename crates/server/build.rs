@@ -0,0 +1,106 @@
+//! Generates the gRPC service stubs when the `grpc` feature is on. The
+//! service is described here rather than in a `.proto` file: its messages
+//! are the REST types, sent as JSON, so no `protoc` is needed.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    /// `(method, route, request type, response type, streams)`
+    const METHODS: &[(&str, &str, &str, &str, bool)] = &[
+        (
+            "scan",
+            "Scan",
+            "crate::routes::PathsRequest",
+            "crate::JobEvent",
+            true,
+        ),
+        (
+            "find_duplicates",
+            "FindDuplicates",
+            "crate::routes::PathsRequest",
+            "crate::JobEvent",
+            true,
+        ),
+        (
+            "find_similar",
+            "FindSimilar",
+            "crate::routes::SimilarRequest",
+            "crate::JobEvent",
+            true,
+        ),
+        (
+            "compress",
+            "Compress",
+            "crate::routes::CompressRequest",
+            "crate::JobEvent",
+            true,
+        ),
+        (
+            "stats",
+            "Stats",
+            "crate::routes::PathsRequest",
+            "space_saver_service::api::StorageStats",
+            false,
+        ),
+        (
+            "list_jobs",
+            "ListJobs",
+            "crate::grpc::Empty",
+            "Vec<space_saver_service::JobInfo>",
+            false,
+        ),
+        (
+            "get_job",
+            "GetJob",
+            "crate::grpc::JobRef",
+            "crate::JobView",
+            false,
+        ),
+        (
+            "cancel_job",
+            "CancelJob",
+            "crate::grpc::JobRef",
+            "crate::routes::Cancelled",
+            false,
+        ),
+        (
+            "history",
+            "History",
+            "crate::routes::HistoryQuery",
+            "Vec<space_saver_service::ScanHistoryEntry>",
+            false,
+        ),
+        (
+            "scan_details",
+            "ScanDetails",
+            "crate::grpc::ScanRef",
+            "space_saver_service::ScanDetails",
+            false,
+        ),
+    ];
+
+    pub fn generate() {
+        let mut service = Service::builder().name("SpaceSaver").package("space_saver");
+        for &(name, route, input, output, streams) in METHODS {
+            let method = Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(input)
+                .output_type(output)
+                .codec_path("crate::grpc::JsonCodec");
+            let method = if streams {
+                method.server_streaming()
+            } else {
+                method
+            };
+            service = service.method(method.build());
+        }
+        Builder::new().compile(&[service.build()]);
+    }
+}
@@ -0,0 +1,60 @@
+//! Errors as HTTP responses.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use space_saver_utils::{Error, ErrorCode, ErrorInfo};
+
+/// An [`Error`] on its way to a client: the status its code maps to, with
+/// the `{ "code": "not_found", "message": "..." }` body the desktop app
+/// receives
+#[derive(Debug)]
+pub struct ApiError(pub Error);
+
+impl<E: Into<Error>> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let info = ErrorInfo::from(&self.0);
+        (status_of(info.code), Json(info)).into_response()
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl From<ApiError> for tonic::Status {
+    fn from(err: ApiError) -> Self {
+        let info = ErrorInfo::from(&err.0);
+        tonic::Status::new(grpc_code_of(info.code), info.message)
+    }
+}
+
+/// The gRPC status code for an error of kind `code`
+#[cfg(feature = "grpc")]
+pub fn grpc_code_of(code: ErrorCode) -> tonic::Code {
+    match code {
+        ErrorCode::NotFound => tonic::Code::NotFound,
+        ErrorCode::PermissionDenied | ErrorCode::Protected => tonic::Code::PermissionDenied,
+        ErrorCode::InvalidInput | ErrorCode::Config => tonic::Code::InvalidArgument,
+        ErrorCode::Cancelled => tonic::Code::Cancelled,
+        ErrorCode::TimedOut => tonic::Code::DeadlineExceeded,
+        ErrorCode::Database => tonic::Code::Unavailable,
+        _ => tonic::Code::Internal,
+    }
+}
+
+/// The HTTP status for an error of kind `code`
+pub fn status_of(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::PermissionDenied | ErrorCode::Protected => StatusCode::FORBIDDEN,
+        ErrorCode::InvalidInput | ErrorCode::Config => StatusCode::BAD_REQUEST,
+        ErrorCode::Cancelled => StatusCode::CONFLICT,
+        ErrorCode::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+        ErrorCode::Database => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
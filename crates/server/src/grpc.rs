@@ -0,0 +1,346 @@
+//! gRPC access to the same operations as the REST routes, for orchestrating
+//! many machines from one place. Enabled by the `grpc` feature and served
+//! with [`crate::Server::with_grpc_listener`].
+//!
+//! The service (`space_saver.SpaceSaver`) has no `.proto` file: its
+//! messages are the REST request and response types, sent as JSON (see
+//! [`JsonCodec`]), so a scan asked for over gRPC reads exactly like one
+//! posted to `/api/scan`. Long operations stream the job's [`JobEvent`]s:
+//! its id, its progress, then its result; closing the stream cancels the
+//! job. Jobs started either way show up in both.
+//!
+//! Calls carry the server's token as `authorization: Bearer <token>`
+//! metadata. [`proto::space_saver_client::SpaceSaverClient`] is the
+//! matching client.
+
+use crate::error::ApiError;
+use crate::jobs::{JobEvent, JobView};
+use crate::routes::{self, Cancelled, CompressRequest, HistoryQuery, PathsRequest, SimilarRequest};
+use crate::{authorized, AppState};
+use bytes::{Buf, BufMut};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use space_saver_service::api::StorageStats;
+use space_saver_service::{JobId, JobInfo, ScanDetails, ScanHistoryEntry};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::{Request, Response, Status};
+
+/// The generated server and client
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/space_saver.SpaceSaver.rs"));
+}
+
+use proto::space_saver_server::{SpaceSaver, SpaceSaverServer};
+
+/// No arguments
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Empty {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRef {
+    pub job_id: JobId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRef {
+    pub id: i64,
+}
+
+/// Messages as JSON instead of protobuf
+#[derive(Debug)]
+pub struct JsonCodec<T, U>(PhantomData<(T, U)>);
+
+impl<T, U> Default for JsonCodec<T, U> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T, U> Codec for JsonCodec<T, U>
+where
+    T: Serialize + Send + 'static,
+    U: DeserializeOwned + Send + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+    type Encoder = JsonEncoder<T>;
+    type Decoder = JsonDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        JsonEncoder(PhantomData)
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        JsonDecoder(PhantomData)
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonEncoder<T>(PhantomData<T>);
+
+impl<T: Serialize> Encoder for JsonEncoder<T> {
+    type Item = T;
+    type Error = Status;
+
+    fn encode(&mut self, item: T, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        serde_json::to_writer(dst.writer(), &item).map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonDecoder<U>(PhantomData<U>);
+
+impl<U: DeserializeOwned> Decoder for JsonDecoder<U> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<U>, Status> {
+        if !src.has_remaining() {
+            return Ok(None);
+        }
+        serde_json::from_reader(src.reader())
+            .map(Some)
+            .map_err(|e| Status::invalid_argument(e.to_string()))
+    }
+}
+
+type Events = Pin<Box<dyn Stream<Item = Result<JobEvent, Status>> + Send>>;
+
+/// Refuses calls without the server's token
+#[derive(Clone)]
+pub(crate) struct TokenCheck(Arc<str>);
+
+impl Interceptor for TokenCheck {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let given = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        if authorized(given, &self.0) {
+            Ok(request)
+        } else {
+            Err(Status::unauthenticated("Missing or wrong token"))
+        }
+    }
+}
+
+/// The service over the server's state
+pub(crate) fn service(
+    state: Arc<AppState>,
+) -> InterceptedService<SpaceSaverServer<Grpc>, TokenCheck> {
+    let check = TokenCheck(Arc::from(state.token.as_str()));
+    SpaceSaverServer::with_interceptor(Grpc(state), check)
+}
+
+pub(crate) struct Grpc(Arc<AppState>);
+
+impl Grpc {
+    /// Start a job with `start` and stream what it does
+    fn follow(
+        &self,
+        start: impl FnOnce(&Arc<AppState>, Option<mpsc::Sender<JobEvent>>) -> Result<JobId, ApiError>,
+    ) -> Result<Response<Events>, Status> {
+        let (events_tx, events_rx) = mpsc::channel(256);
+        start(&self.0, Some(events_tx))?;
+        let events = ReceiverStream::new(events_rx).map(Ok);
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+#[tonic::async_trait]
+impl SpaceSaver for Grpc {
+    type ScanStream = Events;
+    type FindDuplicatesStream = Events;
+    type FindSimilarStream = Events;
+    type CompressStream = Events;
+
+    async fn scan(&self, request: Request<PathsRequest>) -> Result<Response<Events>, Status> {
+        self.follow(|state, observer| routes::start_scan(state, request.into_inner(), observer))
+    }
+
+    async fn find_duplicates(
+        &self,
+        request: Request<PathsRequest>,
+    ) -> Result<Response<Events>, Status> {
+        self.follow(|state, observer| {
+            routes::start_duplicates(state, request.into_inner(), observer)
+        })
+    }
+
+    async fn find_similar(
+        &self,
+        request: Request<SimilarRequest>,
+    ) -> Result<Response<Events>, Status> {
+        self.follow(|state, observer| routes::start_similar(state, request.into_inner(), observer))
+    }
+
+    async fn compress(
+        &self,
+        request: Request<CompressRequest>,
+    ) -> Result<Response<Events>, Status> {
+        self.follow(|state, observer| routes::start_compress(state, request.into_inner(), observer))
+    }
+
+    async fn stats(
+        &self,
+        request: Request<PathsRequest>,
+    ) -> Result<Response<StorageStats>, Status> {
+        let request = request.into_inner();
+        let paths = routes::checked(&request.paths)?;
+        let stats = self
+            .0
+            .api()
+            .get_storage_stats_for_paths(paths, request.filter)
+            .await
+            .map_err(ApiError::from)?;
+        Ok(Response::new(stats))
+    }
+
+    async fn list_jobs(&self, _: Request<Empty>) -> Result<Response<Vec<JobInfo>>, Status> {
+        Ok(Response::new(self.0.jobs.list()))
+    }
+
+    async fn get_job(&self, request: Request<JobRef>) -> Result<Response<JobView>, Status> {
+        let view = self
+            .0
+            .jobs
+            .view(request.into_inner().job_id)
+            .map_err(ApiError::from)?;
+        Ok(Response::new(view))
+    }
+
+    async fn cancel_job(&self, request: Request<JobRef>) -> Result<Response<Cancelled>, Status> {
+        let cancelled = self
+            .0
+            .jobs
+            .cancel(request.into_inner().job_id)
+            .map_err(ApiError::from)?;
+        Ok(Response::new(Cancelled { cancelled }))
+    }
+
+    async fn history(
+        &self,
+        request: Request<HistoryQuery>,
+    ) -> Result<Response<Vec<ScanHistoryEntry>>, Status> {
+        let limit = request.into_inner().limit.unwrap_or(20);
+        let history = self.0.api().scan_history(limit).map_err(ApiError::from)?;
+        Ok(Response::new(history))
+    }
+
+    async fn scan_details(
+        &self,
+        request: Request<ScanRef>,
+    ) -> Result<Response<ScanDetails>, Status> {
+        let details = self
+            .0
+            .api()
+            .scan_details(request.into_inner().id)
+            .map_err(ApiError::from)?;
+        Ok(Response::new(details))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Server;
+    use proto::space_saver_client::SpaceSaverClient;
+    use std::net::SocketAddr;
+    use tempfile::tempdir;
+    use tokio::net::TcpListener;
+    use tonic::transport::Channel;
+
+    const TOKEN: &str = "secret";
+
+    /// A server on free ports, stopped when the returned sender drops
+    async fn start() -> (SocketAddr, tokio::sync::oneshot::Sender<()>) {
+        let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = grpc.local_addr().unwrap();
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+        let server = Server::new(TOKEN).with_grpc_listener(grpc);
+        tokio::spawn(server.serve(rest, async {
+            let _ = stop_rx.await;
+        }));
+        (address, stop_tx)
+    }
+
+    async fn channel(address: SocketAddr) -> Channel {
+        Channel::from_shared(format!("http://{}", address))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scan_streams_progress_then_result() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"hello").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"world!").unwrap();
+        let (address, _stop) = start().await;
+        let mut client = SpaceSaverClient::with_interceptor(
+            channel(address).await,
+            |mut request: Request<()>| {
+                let token = format!("Bearer {}", TOKEN).parse().unwrap();
+                request.metadata_mut().insert("authorization", token);
+                Ok(request)
+            },
+        );
+
+        let request = PathsRequest {
+            paths: vec![dir.path().to_path_buf()],
+            filter: None,
+        };
+        let mut events = client.scan(request).await.unwrap().into_inner();
+        let mut seen = Vec::new();
+        while let Some(event) = events.next().await {
+            seen.push(event.unwrap());
+        }
+        let Some(JobEvent::Started { job_id }) = seen.first().cloned() else {
+            panic!("no job id first: {:?}", seen);
+        };
+        assert!(seen
+            .iter()
+            .any(|event| matches!(event, JobEvent::Progress { .. })));
+        let Some(JobEvent::Finished { result, error }) = seen.last().cloned() else {
+            panic!("no result last: {:?}", seen);
+        };
+        assert_eq!(error, None);
+        assert_eq!(result.unwrap()[0]["total_size"], 11);
+
+        // The job is kept like one started over REST
+        let job = client
+            .get_job(JobRef { job_id })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(job.job.is_finished());
+        assert_eq!(
+            client.list_jobs(Empty {}).await.unwrap().into_inner().len(),
+            1
+        );
+
+        let status = client
+            .get_job(JobRef { job_id: job_id + 1 })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_calls_need_the_token() {
+        let (address, _stop) = start().await;
+        let mut client = SpaceSaverClient::new(channel(address).await);
+        let status = client.list_jobs(Empty {}).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+}
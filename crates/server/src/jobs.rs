@@ -0,0 +1,180 @@
+//! Background jobs started over HTTP, and what they produced.
+//!
+//! The registry is a [`JobManager`] like the desktop app's; on top of it
+//! [`Jobs`] keeps each finished job's result (or error) as JSON until a
+//! client asks for it with `GET /api/jobs/{id}`. Streaming clients (gRPC)
+//! can also follow a job as it runs through its [`JobEvent`]s.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use space_saver_core::CancellationToken;
+use space_saver_service::{JobId, JobInfo, JobManager, ProgressUpdate, TaskType};
+use space_saver_utils::{Error, ErrorInfo, Result};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::mpsc;
+
+/// Results kept for finished jobs; the oldest are forgotten first
+const KEPT_RESULTS: usize = 50;
+
+/// A job with its result once it finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobView {
+    #[serde(flatten)]
+    pub job: JobInfo,
+    /// What the job produced, when it completed
+    pub result: Option<Value>,
+    /// Why it failed or stopped
+    pub error: Option<ErrorInfo>,
+}
+
+/// What a client following a job sees: its id, its progress, then how it
+/// ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent {
+    Started {
+        job_id: JobId,
+    },
+    Progress {
+        update: ProgressUpdate,
+    },
+    Finished {
+        result: Option<Value>,
+        error: Option<ErrorInfo>,
+    },
+}
+
+type Outcome = std::result::Result<Value, ErrorInfo>;
+
+/// Running and finished jobs. Clones share the same registry.
+#[derive(Clone, Default)]
+pub(crate) struct Jobs {
+    manager: JobManager,
+    outcomes: Arc<Mutex<BTreeMap<JobId, Outcome>>>,
+}
+
+impl Jobs {
+    /// Run `work` in the background as a job of `task_type`, returning its
+    /// id. `work` reports progress on the sender it is given and stops once
+    /// the token is cancelled; `kind` names it in the progress messages.
+    /// When given, `observer` receives the job's [`JobEvent`]s; dropping its
+    /// receiver cancels the job.
+    pub fn spawn<T, F, Fut>(
+        &self,
+        task_type: TaskType,
+        kind: &'static str,
+        observer: Option<mpsc::Sender<JobEvent>>,
+        work: F,
+    ) -> JobId
+    where
+        T: Serialize + Send + 'static,
+        F: FnOnce(mpsc::Sender<ProgressUpdate>, CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let total_items = task_type.item_count();
+        let handle = self.manager.register(task_type);
+        let job_id = handle.id();
+        let token = handle.token();
+        let (progress_tx, mut progress_rx) = mpsc::channel(256);
+        let watcher = observer.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(update) = progress_rx.recv().await {
+                handle.update(&update);
+                let Some(observer) = &watcher else {
+                    continue;
+                };
+                if observer.send(JobEvent::Progress { update }).await.is_err() {
+                    handle.token().cancel();
+                }
+            }
+        });
+
+        let jobs = self.clone();
+        tokio::spawn(async move {
+            if let Some(observer) = &observer {
+                let _ = observer.send(JobEvent::Started { job_id }).await;
+            }
+            let _ = progress_tx
+                .send(ProgressUpdate::Started {
+                    task_type: kind.to_string(),
+                    total_items,
+                })
+                .await;
+            let outcome = match work(progress_tx.clone(), token.clone()).await {
+                Err(_) if token.is_cancelled() => Err(Error::Cancelled),
+                outcome => outcome,
+            };
+            let last = match &outcome {
+                Ok(_) => ProgressUpdate::Completed {
+                    message: format!("{} finished", kind),
+                },
+                Err(Error::Cancelled) => ProgressUpdate::Cancelled,
+                Err(error) => ProgressUpdate::Failed {
+                    error: error.to_string(),
+                },
+            };
+            // The result is in place before the job shows as finished
+            let outcome = outcome
+                .and_then(|result| Ok(serde_json::to_value(result)?))
+                .map_err(|error| ErrorInfo::from(&error));
+            jobs.keep(job_id, outcome.clone());
+            let _ = progress_tx.send(last).await;
+            drop(progress_tx);
+            let _ = forward.await;
+            if let Some(observer) = observer {
+                let (result, error) = match outcome {
+                    Ok(result) => (Some(result), None),
+                    Err(error) => (None, Some(error)),
+                };
+                let _ = observer.send(JobEvent::Finished { result, error }).await;
+            }
+        });
+        job_id
+    }
+
+    /// Every known job, oldest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.manager.list()
+    }
+
+    pub fn view(&self, id: JobId) -> Result<JobView> {
+        let job = self
+            .manager
+            .status(id)
+            .ok_or_else(|| Error::NotFound(format!("Unknown job: {}", id)))?;
+        let (result, error) = match self.lock().get(&id).cloned() {
+            Some(Ok(result)) => (Some(result), None),
+            Some(Err(error)) => (None, Some(error)),
+            None => (None, None),
+        };
+        Ok(JobView { job, result, error })
+    }
+
+    /// Ask a job to stop; false when it had already finished
+    pub fn cancel(&self, id: JobId) -> Result<bool> {
+        Ok(self.manager.cancel(id)?)
+    }
+
+    /// Ask every unfinished job to stop
+    pub fn cancel_all(&self) {
+        for job in self.manager.list() {
+            if !job.is_finished() {
+                let _ = self.manager.cancel(job.id);
+            }
+        }
+    }
+
+    fn keep(&self, id: JobId, outcome: Outcome) {
+        let mut outcomes = self.lock();
+        outcomes.insert(id, outcome);
+        while outcomes.len() > KEPT_RESULTS {
+            outcomes.pop_first();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<JobId, Outcome>> {
+        self.outcomes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
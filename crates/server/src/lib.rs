@@ -0,0 +1,413 @@
+//! HTTP/JSON access to the [`ServiceApi`], for headless machines (a NAS)
+//! driven from scripts or a web dashboard. Started by `space-saver serve`.
+//!
+//! Every route but `GET /api/health` wants an `Authorization: Bearer
+//! <token>` header carrying the server's token. Errors come back with the
+//! status their code maps to (see [`error::status_of`]) and the same
+//! `{ "code", "message" }` body the desktop app receives.
+//!
+//! | Route                       | Does                                        |
+//! |-----------------------------|---------------------------------------------|
+//! | `GET /api/health`           | `{ "status": "ok", "version" }`             |
+//! | `POST /api/scan`            | job: scan `paths`                           |
+//! | `POST /api/duplicates`      | job: duplicate report for `paths`           |
+//! | `POST /api/similar`         | job: similar media in `paths`               |
+//! | `POST /api/compress`        | job: compress files in place                |
+//! | `POST /api/stats`           | sizes per file type, right away             |
+//! | `GET /api/jobs`             | every job                                   |
+//! | `GET /api/jobs/{id}`        | one job, with its result once finished      |
+//! | `DELETE /api/jobs/{id}`     | cancel a job                                |
+//! | `GET /api/history?limit=`   | recorded scans, newest first                |
+//! | `GET /api/history/{id}`     | one recorded scan                           |
+//!
+//! Long operations answer `202 Accepted` with `{ "job_id" }` right away;
+//! clients poll the job until its `status` is finished, then read its
+//! `result` (or `error`).
+//!
+//! With the `grpc` feature the same operations are also offered over gRPC,
+//! streaming progress; see [`grpc`].
+
+pub mod error;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod jobs;
+pub mod routes;
+
+pub use error::ApiError;
+pub use jobs::{JobEvent, JobView};
+
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::Router;
+use jobs::Jobs;
+use space_saver_core::compress_plugins::{global_plugin_manager, PluginManager};
+use space_saver_db::Database;
+use space_saver_service::{Hooks, NetworkShares, ProtectedPaths, ScanCache, ServiceApi};
+use space_saver_utils::{Error, Result};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use tokio::net::TcpListener;
+
+/// The REST server: its token, and what the handlers work with
+pub struct Server {
+    token: String,
+    db: Option<Database>,
+    protected: ProtectedPaths,
+    manager: Arc<RwLock<PluginManager>>,
+    network: NetworkShares,
+    hooks: Hooks,
+    #[cfg(feature = "grpc")]
+    grpc: Option<TcpListener>,
+}
+
+impl Server {
+    /// A server accepting requests that carry `token`. Scans are not
+    /// recorded and only system directories are protected until configured.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            db: None,
+            protected: ProtectedPaths::new(),
+            manager: global_plugin_manager(),
+            network: NetworkShares::default(),
+            hooks: Hooks::default(),
+            #[cfg(feature = "grpc")]
+            grpc: None,
+        }
+    }
+
+    /// Record scans in `db`, and serve the scan history from it
+    pub fn with_database(mut self, db: Database) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Refuse to compress files under `protected`
+    pub fn with_protected_paths(mut self, protected: ProtectedPaths) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Compress with `manager` instead of the global plugin manager
+    pub fn with_plugin_manager(mut self, manager: Arc<RwLock<PluginManager>>) -> Self {
+        self.manager = manager;
+        self
+    }
+
+    /// Hash files on the network shares in `network` gently; see
+    /// [`ServiceApi::with_network_shares`]
+    pub fn with_network_shares(mut self, network: NetworkShares) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Run the "compress" hooks in `hooks` around compressions
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Also answer gRPC calls on `listener`, sharing jobs with the REST API
+    #[cfg(feature = "grpc")]
+    pub fn with_grpc_listener(mut self, listener: TcpListener) -> Self {
+        self.grpc = Some(listener);
+        self
+    }
+
+    /// The routes, with their state; for serving or testing in process
+    pub fn router(self) -> Router {
+        Self::routes(Arc::new(self.into_state()))
+    }
+
+    /// Serve on `listener` until `shutdown` completes, then cancel the jobs
+    /// still running
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<()> {
+        if self.token.is_empty() {
+            return Err(Error::Config("The server needs a token".to_string()));
+        }
+        #[cfg(feature = "grpc")]
+        let (grpc_listener, server) = (self.grpc, Self { grpc: None, ..self });
+        #[cfg(not(feature = "grpc"))]
+        let server = self;
+        let state = Arc::new(server.into_state());
+        let jobs = state.jobs.clone();
+
+        // Both servers stop on the one signal, or once either stops
+        let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+        let stop_tx = Arc::new(stop_tx);
+        let stop = Arc::clone(&stop_tx);
+        tokio::spawn(async move {
+            shutdown.await;
+            stop.send_replace(true);
+        });
+        let stopped = |mut stop_rx: tokio::sync::watch::Receiver<bool>| async move {
+            let _ = stop_rx.wait_for(|stop| *stop).await;
+        };
+
+        #[cfg(feature = "grpc")]
+        let grpc = grpc_listener.map(|listener| {
+            tracing::info!(address = ?listener.local_addr().ok(), "Serving the gRPC API");
+            let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+            tokio::spawn(
+                tonic::transport::Server::builder()
+                    .add_service(grpc::service(Arc::clone(&state)))
+                    .serve_with_incoming_shutdown(incoming, stopped(stop_rx.clone())),
+            )
+        });
+
+        tracing::info!(address = ?listener.local_addr().ok(), "Serving the REST API");
+        let served = axum::serve(listener, Self::routes(state))
+            .with_graceful_shutdown(stopped(stop_rx))
+            .await;
+        stop_tx.send_replace(true);
+        jobs.cancel_all();
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = grpc {
+            grpc.await
+                .map_err(|e| Error::Task(e.to_string()))?
+                .map_err(anyhow::Error::from)?;
+        }
+        Ok(served?)
+    }
+
+    fn into_state(self) -> AppState {
+        AppState {
+            token: self.token,
+            db: self.db,
+            protected: self.protected,
+            manager: self.manager,
+            network: self.network,
+            hooks: self.hooks,
+            jobs: Jobs::default(),
+            scan_cache: ScanCache::new(),
+        }
+    }
+
+    fn routes(state: Arc<AppState>) -> Router {
+        Router::new()
+            .route("/api/scan", post(routes::scan))
+            .route("/api/duplicates", post(routes::duplicates))
+            .route("/api/similar", post(routes::similar))
+            .route("/api/compress", post(routes::compress))
+            .route("/api/stats", post(routes::stats))
+            .route("/api/jobs", get(routes::list_jobs))
+            .route(
+                "/api/jobs/{id}",
+                get(routes::job).delete(routes::cancel_job),
+            )
+            .route("/api/history", get(routes::history))
+            .route("/api/history/{id}", get(routes::scan_details))
+            .route_layer(middleware::from_fn_with_state(
+                Arc::clone(&state),
+                require_token,
+            ))
+            // Added after the layer, so it needs no token
+            .route("/api/health", get(routes::health))
+            .with_state(state)
+    }
+}
+
+/// What every handler shares
+pub(crate) struct AppState {
+    token: String,
+    db: Option<Database>,
+    protected: ProtectedPaths,
+    manager: Arc<RwLock<PluginManager>>,
+    network: NetworkShares,
+    hooks: Hooks,
+    jobs: Jobs,
+    scan_cache: ScanCache,
+}
+
+impl AppState {
+    /// A [`ServiceApi`] set up with the server's database, protected paths,
+    /// network shares and scan cache
+    fn api(&self) -> ServiceApi {
+        let api = ServiceApi::new()
+            .with_protected_paths(self.protected.clone())
+            .with_network_shares(self.network.clone())
+            .with_scan_cache(self.scan_cache.clone());
+        match &self.db {
+            Some(db) => api.with_database(db.clone()),
+            None => api,
+        }
+    }
+}
+
+async fn require_token(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> std::result::Result<Response, Response> {
+    let given = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if authorized(given, &state.token) {
+        Ok(next.run(request).await)
+    } else {
+        Err(unauthorized())
+    }
+}
+
+/// Whether an `Authorization` value carries `token`; nothing does when no
+/// token was set
+pub(crate) fn authorized(header: Option<&str>, token: &str) -> bool {
+    match header.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(given) => !token.is_empty() && same(given, token),
+        None => false,
+    }
+}
+
+fn unauthorized() -> Response {
+    use axum::response::IntoResponse;
+    let info = space_saver_utils::ErrorInfo::from(&Error::PermissionDenied(
+        "Missing or wrong token".to_string(),
+    ));
+    (axum::http::StatusCode::UNAUTHORIZED, axum::Json(info)).into_response()
+}
+
+/// Compare tokens in time independent of where they differ
+fn same(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Method, StatusCode};
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "secret";
+
+    async fn call(
+        router: &Router,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_the_token() {
+        let router = Server::new(TOKEN).router();
+
+        let (status, body) = call(&router, Method::GET, "/api/health", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+
+        let (status, body) = call(&router, Method::GET, "/api/jobs", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "permission_denied");
+        let (status, _) = call(&router, Method::GET, "/api/jobs", Some("wrong"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = call(&router, Method::GET, "/api/jobs", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([]));
+
+        // Nothing gets in when no token was set
+        let router = Server::new("").router();
+        let (status, _) = call(&router, Method::GET, "/api/jobs", Some(""), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_scan_job_result_is_polled() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"hello").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"world!").unwrap();
+        let router = Server::new(TOKEN).router();
+
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/api/scan",
+            Some(TOKEN),
+            Some(json!({ "paths": [dir.path()] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let uri = format!("/api/jobs/{}", body["job_id"]);
+
+        let mut job = Value::Null;
+        for _ in 0..200 {
+            let (status, body) = call(&router, Method::GET, &uri, Some(TOKEN), None).await;
+            assert_eq!(status, StatusCode::OK);
+            job = body;
+            if job["result"] != Value::Null || job["error"] != Value::Null {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(job["error"], Value::Null);
+        assert_eq!(job["result"][0]["file_count"], 2);
+        assert_eq!(job["result"][0]["total_size"], 11);
+    }
+
+    #[tokio::test]
+    async fn test_bad_requests_get_error_codes() {
+        let router = Server::new(TOKEN).router();
+
+        let (status, body) = call(&router, Method::GET, "/api/jobs/999", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "not_found");
+
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/api/duplicates",
+            Some(TOKEN),
+            Some(json!({ "paths": ["relative/path"] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_input");
+
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/api/stats",
+            Some(TOKEN),
+            Some(json!({ "folders": [] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_input");
+    }
+}
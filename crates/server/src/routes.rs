@@ -0,0 +1,349 @@
+//! The request handlers, one per route of [`crate::Server::router`], and
+//! the request and response types they share with the gRPC service.
+
+use crate::error::ApiError;
+use crate::jobs::{JobEvent, JobView};
+use crate::AppState;
+use axum::extract::{FromRequest, Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use space_saver_core::compress_plugins::{CompressionOutcome, ProgressSink};
+use space_saver_service::api::{FilterConfig, MediaKind, StorageStats};
+use space_saver_service::{JobId, JobInfo, ScanDetails, ScanHistoryEntry, TaskType};
+use space_saver_utils::{Error, ErrorInfo};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+type Shared = State<Arc<AppState>>;
+
+/// [`Json`] whose rejections come back like every other error
+pub(crate) struct Body<T>(pub T);
+
+impl<S: Send + Sync, T: DeserializeOwned> FromRequest<S> for Body<T> {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, ApiError> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(body)) => Ok(Body(body)),
+            Err(rejection) => Err(Error::InvalidInput(rejection.body_text()).into()),
+        }
+    }
+}
+
+/// Folders to work on, for scans, duplicate checks and statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathsRequest {
+    pub paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub filter: Option<FilterConfig>,
+}
+
+/// Folders to look for similar media in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarRequest {
+    pub paths: Vec<PathBuf>,
+    /// 0-1; 0.9 unless given
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+    /// Images unless given
+    #[serde(default)]
+    pub media_types: Vec<MediaKind>,
+    #[serde(default)]
+    pub filter: Option<FilterConfig>,
+}
+
+fn default_threshold() -> f32 {
+    0.9
+}
+
+/// Files to compress in place
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressRequest {
+    pub paths: Vec<PathBuf>,
+    /// Plugins to try, in order; every registered one when empty
+    #[serde(default)]
+    pub plugins: Vec<String>,
+    /// Keep each original as `<name>.bak`; true unless given
+    #[serde(default = "keep_backup_default")]
+    pub keep_backup: bool,
+}
+
+fn keep_backup_default() -> bool {
+    true
+}
+
+/// What a compression job reports per file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressedFile {
+    pub path: PathBuf,
+    pub outcome: Option<CompressionOutcome>,
+    pub error: Option<ErrorInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStarted {
+    pub job_id: JobId,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Health {
+    pub status: &'static str,
+    pub version: &'static str,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    /// 20 unless given
+    pub limit: Option<usize>,
+}
+
+/// `paths` if there are any and each is absolute: relative paths would
+/// depend on where the server was started
+pub(crate) fn checked(paths: &[PathBuf]) -> Result<Vec<PathBuf>, ApiError> {
+    if paths.is_empty() {
+        return Err(Error::InvalidInput("No paths given".to_string()).into());
+    }
+    if let Some(relative) = paths.iter().find(|path| !path.is_absolute()) {
+        return Err(
+            Error::InvalidInput(format!("Paths must be absolute: {}", relative.display())).into(),
+        );
+    }
+    Ok(paths.to_vec())
+}
+
+fn accepted(job_id: JobId) -> (StatusCode, Json<JobStarted>) {
+    (StatusCode::ACCEPTED, Json(JobStarted { job_id }))
+}
+
+pub(crate) async fn health() -> Json<Health> {
+    Json(Health {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+    })
+}
+
+pub(crate) async fn scan(
+    State(state): Shared,
+    Body(request): Body<PathsRequest>,
+) -> Result<(StatusCode, Json<JobStarted>), ApiError> {
+    Ok(accepted(start_scan(&state, request, None)?))
+}
+
+/// Scan folders; the job's result is a `ScanResult` per path
+pub(crate) fn start_scan(
+    state: &Arc<AppState>,
+    request: PathsRequest,
+    observer: Option<mpsc::Sender<JobEvent>>,
+) -> Result<JobId, ApiError> {
+    let paths = checked(&request.paths)?;
+    let api = state.api();
+    let job_id = state.jobs.spawn(
+        TaskType::Scan(paths.clone()),
+        "scan",
+        observer,
+        move |progress, cancel| async move {
+            api.with_progress(progress)
+                .with_cancel(cancel)
+                .scan_directories(paths, request.filter)
+                .await
+        },
+    );
+    Ok(job_id)
+}
+
+pub(crate) async fn duplicates(
+    State(state): Shared,
+    Body(request): Body<PathsRequest>,
+) -> Result<(StatusCode, Json<JobStarted>), ApiError> {
+    Ok(accepted(start_duplicates(&state, request, None)?))
+}
+
+/// Find duplicate files; the job's result is a `DuplicateReport`
+pub(crate) fn start_duplicates(
+    state: &Arc<AppState>,
+    request: PathsRequest,
+    observer: Option<mpsc::Sender<JobEvent>>,
+) -> Result<JobId, ApiError> {
+    let paths = checked(&request.paths)?;
+    let api = state.api();
+    let job_id = state.jobs.spawn(
+        TaskType::FindDuplicates(paths.clone()),
+        "duplicates",
+        observer,
+        move |progress, cancel| async move {
+            api.with_progress(progress)
+                .with_cancel(cancel)
+                .find_duplicate_report(paths, request.filter)
+                .await
+        },
+    );
+    Ok(job_id)
+}
+
+pub(crate) async fn similar(
+    State(state): Shared,
+    Body(request): Body<SimilarRequest>,
+) -> Result<(StatusCode, Json<JobStarted>), ApiError> {
+    Ok(accepted(start_similar(&state, request, None)?))
+}
+
+/// Find similar media; the job's result is a list of `SimilarGroup`s
+pub(crate) fn start_similar(
+    state: &Arc<AppState>,
+    request: SimilarRequest,
+    observer: Option<mpsc::Sender<JobEvent>>,
+) -> Result<JobId, ApiError> {
+    let paths = checked(&request.paths)?;
+    if !(0.0..=1.0).contains(&request.threshold) {
+        return Err(Error::InvalidInput(format!(
+            "Threshold must be between 0 and 1, not {}",
+            request.threshold
+        ))
+        .into());
+    }
+    let api = state.api();
+    let job_id = state.jobs.spawn(
+        TaskType::FindSimilarImages(paths.clone(), request.threshold),
+        "similar",
+        observer,
+        move |progress, cancel| async move {
+            api.with_progress(progress)
+                .with_cancel(cancel)
+                .find_similar_media_in_paths(
+                    paths,
+                    request.threshold,
+                    request.media_types,
+                    request.filter,
+                )
+                .await
+        },
+    );
+    Ok(job_id)
+}
+
+pub(crate) async fn compress(
+    State(state): Shared,
+    Body(request): Body<CompressRequest>,
+) -> Result<(StatusCode, Json<JobStarted>), ApiError> {
+    Ok(accepted(start_compress(&state, request, None)?))
+}
+
+/// Compress files in place; the job's result is a [`CompressedFile`] per
+/// path, in order. Protected and missing files fail on their own.
+pub(crate) fn start_compress(
+    state: &Arc<AppState>,
+    request: CompressRequest,
+    observer: Option<mpsc::Sender<JobEvent>>,
+) -> Result<JobId, ApiError> {
+    let paths = checked(&request.paths)?;
+    let shared = Arc::clone(state);
+    let job_id = state.jobs.spawn(
+        TaskType::CompressFiles(paths.clone()),
+        "compress",
+        observer,
+        move |progress, cancel| async move {
+            // Plugins block (image encoding, external tools)
+            tokio::task::spawn_blocking(move || {
+                let manager = shared.manager.read().map_err(Error::from)?;
+                let sink = ProgressSink::new()
+                    .with_cancel(cancel)
+                    .on_progress(space_saver_service::forward_progress(progress));
+                let plugins = (!request.plugins.is_empty()).then_some(request.plugins.as_slice());
+                let outcomes = shared.hooks.around(
+                    "compress",
+                    &paths,
+                    || {
+                        space_saver_service::compress_in_place(
+                            &manager,
+                            &paths,
+                            plugins,
+                            request.keep_backup,
+                            &shared.protected,
+                            &sink,
+                        )
+                    },
+                    |outcomes| space_saver_service::compression_outcome(outcomes),
+                )?;
+                for path in &paths {
+                    shared.scan_cache.invalidate(path);
+                }
+                let files: Vec<CompressedFile> = paths
+                    .into_iter()
+                    .zip(outcomes)
+                    .map(|(path, outcome)| match outcome {
+                        Ok(outcome) => CompressedFile {
+                            path,
+                            outcome: Some(outcome),
+                            error: None,
+                        },
+                        Err(e) => CompressedFile {
+                            path,
+                            outcome: None,
+                            error: Some(ErrorInfo::from(&Error::from(e))),
+                        },
+                    })
+                    .collect();
+                Ok(files)
+            })
+            .await
+            .map_err(|e| Error::Task(format!("Compression stopped unexpectedly: {}", e)))?
+        },
+    );
+    Ok(job_id)
+}
+
+/// Sizes per file type across folders, walked right away
+pub(crate) async fn stats(
+    State(state): Shared,
+    Body(request): Body<PathsRequest>,
+) -> Result<Json<StorageStats>, ApiError> {
+    let paths = checked(&request.paths)?;
+    let stats = state
+        .api()
+        .get_storage_stats_for_paths(paths, request.filter)
+        .await?;
+    Ok(Json(stats))
+}
+
+pub(crate) async fn list_jobs(State(state): Shared) -> Json<Vec<JobInfo>> {
+    Json(state.jobs.list())
+}
+
+pub(crate) async fn job(
+    State(state): Shared,
+    Path(id): Path<JobId>,
+) -> Result<Json<JobView>, ApiError> {
+    Ok(Json(state.jobs.view(id)?))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cancelled {
+    /// False when the job had already finished
+    pub cancelled: bool,
+}
+
+pub(crate) async fn cancel_job(
+    State(state): Shared,
+    Path(id): Path<JobId>,
+) -> Result<Json<Cancelled>, ApiError> {
+    let cancelled = state.jobs.cancel(id)?;
+    Ok(Json(Cancelled { cancelled }))
+}
+
+/// The most recent recorded scans, newest first
+pub(crate) async fn history(
+    State(state): Shared,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<ScanHistoryEntry>>, ApiError> {
+    Ok(Json(state.api().scan_history(query.limit.unwrap_or(20))?))
+}
+
+pub(crate) async fn scan_details(
+    State(state): Shared,
+    Path(id): Path<i64>,
+) -> Result<Json<ScanDetails>, ApiError> {
+    Ok(Json(state.api().scan_details(id)?))
+}
@@ -1,12 +1,22 @@
-use anyhow::Result;
+use crate::progress::ProgressUpdate;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use space_saver_core::{
-    scanner::DefaultFileScanner, BrokenCategory, FileFilter, FileInfo, FileScanner,
+    scanner::DefaultFileScanner, ArchiveFormat, BrokenCategory, FileFilter, FileInfo, FileScanner,
+    StorageBackend, StoredFile,
 };
-use std::path::PathBuf;
+use space_saver_db::{
+    ArchiveRecord, CategoryTotal, Database, DuplicateRecord, FileQuery, FileRecord,
+    IgnoredGroupRecord, MaintenanceRecord, Page, PooledConnection, ReferenceRoot, SavingsSummary,
+    ScanRecord, SqliteDatabase, TagCount, TagRecord,
+};
+use space_saver_utils::time::{now, parse_time_bound};
+use space_saver_utils::{Error, ErrorCode, Result};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
 
 /// Filter configuration for file operations
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct FilterConfig {
     /// Minimum file size in bytes
@@ -15,16 +25,95 @@ pub struct FilterConfig {
     pub max_size: Option<u64>,
     /// File extensions to include (e.g., ["jpg", "png"])
     pub extensions: Option<Vec<String>>,
-    /// Pattern to match in filename
+    /// Substring to match in filename. `include_globs` covers this and can
+    /// also match directories.
     pub file_pattern: Option<String>,
+    /// Regular expression the filename must match, e.g. `^IMG_\d{4}\.jpe?g$`
+    pub file_regex: Option<String>,
+    /// Globs matched against the full path, e.g. `**/Downloads/**`; a file
+    /// must match at least one. Entries starting with `!` exclude instead.
+    pub include_globs: Option<Vec<String>>,
+    /// Globs whose matches are dropped, e.g. `**/*.raw`. A pattern without
+    /// `/` matches the file name anywhere.
+    pub exclude_globs: Option<Vec<String>>,
+    /// Only files modified at or after this point: an ISO date (`2024-01-01`)
+    /// or a duration ago (`30d`, `6mo`), see [`parse_time_bound`]
+    pub newer_than: Option<String>,
+    /// Only files last modified before this point, e.g. `1y` or `2020`
+    pub older_than: Option<String>,
+    /// File extensions to drop, e.g. `["tmp", "part"]`
+    pub exclude_extensions: Option<Vec<String>>,
+    /// Filename substrings to drop, e.g. `["backup", "~$"]`
+    pub exclude_patterns: Option<Vec<String>>,
     /// Paths to exclude; files located at or beneath any of these are dropped
     /// from results (component-wise prefix match)
     pub exclude_paths: Option<Vec<String>>,
+    /// Filter expression applied on top of the fields above, e.g.
+    /// `size>100MB and ext in (mp4,mkv) and modified<2023-01-01`. `tag:keep`
+    /// conditions need the app database.
+    pub query: Option<String>,
+    /// Only files owned by the user running the app (Unix)
+    pub current_user_only: Option<bool>,
+    /// Only files owned by this user id (Unix)
+    pub owner_uid: Option<u32>,
+    /// Only files owned by this group id (Unix)
+    pub owner_gid: Option<u32>,
+    /// Octal permission bits a file must have all of, e.g. `"002"` for
+    /// world-writable files (Unix)
+    pub permissions: Option<String>,
+    /// Scan dot-files and hidden directories (and files with the Windows
+    /// hidden or system attribute). Defaults to false.
+    pub include_hidden: Option<bool>,
+    /// Descend at most this many levels below the scan root; 1 scans only
+    /// the files directly in it. Defaults to no limit.
+    pub max_depth: Option<usize>,
+    /// Follow symbolic links to directories. Defaults to false.
+    pub follow_links: Option<bool>,
 }
 
 impl FilterConfig {
-    /// Apply filters to a list of files
-    pub fn apply(&self, files: Vec<FileInfo>) -> Vec<FileInfo> {
+    /// Whether no filter is set, so a scan sees every (non-hidden) file
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether scans should include hidden files and directories
+    pub fn includes_hidden(&self) -> bool {
+        self.include_hidden.unwrap_or(false)
+    }
+
+    /// `scanner` set to walk the tree as this filter asks: hidden entries,
+    /// depth and symbolic links
+    pub fn configure_scanner(&self, scanner: DefaultFileScanner) -> DefaultFileScanner {
+        let scanner = scanner
+            .include_hidden(self.includes_hidden())
+            .follow_links(self.follow_links.unwrap_or(false));
+        match self.max_depth {
+            Some(depth) => scanner.with_max_depth(depth),
+            None => scanner,
+        }
+    }
+
+    /// Whether `query` holds an expression
+    fn has_query(&self) -> bool {
+        self.query.as_deref().is_some_and(|q| !q.trim().is_empty())
+    }
+
+    /// Apply filters to a list of files. Fails on an invalid `file_regex`,
+    /// glob, `newer_than`, `older_than`, `query` or `permissions`, on owner
+    /// or permission filters off Unix, and on `tag` conditions (see
+    /// [`Self::apply_with_tags`]).
+    pub fn apply(&self, files: Vec<FileInfo>) -> Result<Vec<FileInfo>> {
+        self.apply_with_tags(files, None)
+    }
+
+    /// Like [`Self::apply`], answering `tag` conditions of `query` from
+    /// `tags`
+    pub fn apply_with_tags(
+        &self,
+        files: Vec<FileInfo>,
+        tags: Option<&space_saver_core::TagIndex>,
+    ) -> Result<Vec<FileInfo>> {
         let mut filtered = files;
 
         // Apply min size filter
@@ -55,7 +144,49 @@ impl FilterConfig {
             }
         }
 
-        // Apply exclude paths filter
+        // Apply regex filter
+        if let Some(ref regex) = self.file_regex {
+            if !regex.is_empty() {
+                let filter = FileFilter::regex(regex)?;
+                filtered = filter.filter_files(filtered);
+            }
+        }
+
+        // Apply glob filters
+        let include_globs = self.include_globs.as_deref().unwrap_or_default();
+        let exclude_globs = self.exclude_globs.as_deref().unwrap_or_default();
+        if !include_globs.is_empty() || !exclude_globs.is_empty() {
+            let filter = FileFilter::globs(include_globs, exclude_globs)?;
+            filtered = filter.filter_files(filtered);
+        }
+
+        // Apply modification time filters
+        if let Some(ref newer_than) = self.newer_than {
+            if !newer_than.is_empty() {
+                let cutoff = parse_time_bound(newer_than, now())?;
+                filtered = FileFilter::newer_than(cutoff).filter_files(filtered);
+            }
+        }
+        if let Some(ref older_than) = self.older_than {
+            if !older_than.is_empty() {
+                let cutoff = parse_time_bound(older_than, now())?;
+                filtered = FileFilter::older_than(cutoff).filter_files(filtered);
+            }
+        }
+
+        // Apply exclusion filters
+        if let Some(ref exclude_extensions) = self.exclude_extensions {
+            if !exclude_extensions.is_empty() {
+                let filter = FileFilter::exclude_extensions(exclude_extensions.clone());
+                filtered = filter.filter_files(filtered);
+            }
+        }
+        if let Some(ref exclude_patterns) = self.exclude_patterns {
+            if !exclude_patterns.is_empty() {
+                let filter = FileFilter::exclude_patterns(exclude_patterns.clone());
+                filtered = filter.filter_files(filtered);
+            }
+        }
         if let Some(ref exclude_paths) = self.exclude_paths {
             if !exclude_paths.is_empty() {
                 let filter = FileFilter::exclude_paths(exclude_paths.clone());
@@ -63,7 +194,53 @@ impl FilterConfig {
             }
         }
 
-        filtered
+        // Apply owner and permission filters
+        let current_user_only = self.current_user_only.unwrap_or(false);
+        let permissions = self.permissions.as_deref().map(str::trim).unwrap_or("");
+        if current_user_only
+            || self.owner_uid.is_some()
+            || self.owner_gid.is_some()
+            || !permissions.is_empty()
+        {
+            if !cfg!(unix) {
+                return Err(Error::InvalidInput(
+                    "Owner and permission filters are only supported on Unix".to_string(),
+                ));
+            }
+            if current_user_only {
+                let uid = space_saver_core::scanner::current_uid();
+                filtered = FileFilter::owner(uid, None).filter_files(filtered);
+            }
+            if self.owner_uid.is_some() || self.owner_gid.is_some() {
+                let filter = FileFilter::owner(self.owner_uid, self.owner_gid);
+                filtered = filter.filter_files(filtered);
+            }
+            if !permissions.is_empty() {
+                let bits = u32::from_str_radix(permissions, 8)
+                    .ok()
+                    .filter(|bits| *bits <= 0o7777)
+                    .with_context(|| {
+                        format!(
+                            "Invalid permission bits: {} (expected octal, e.g. 002)",
+                            permissions
+                        )
+                    })?;
+                filtered = FileFilter::permissions(bits, bits).filter_files(filtered);
+            }
+        }
+
+        // Apply filter expression
+        if let Some(ref query) = self.query {
+            if !query.trim().is_empty() {
+                let filter = match tags {
+                    Some(tags) => FileFilter::parse_with_tags(query, tags)?,
+                    None => FileFilter::parse(query)?,
+                };
+                filtered = filter.filter_files(filtered);
+            }
+        }
+
+        Ok(filtered)
     }
 }
 
@@ -73,6 +250,30 @@ pub struct ServiceApi {
     /// Optional content-hash cache shared by duplicate scans; unchanged
     /// files (same size+mtime) are not re-read
     hash_cache: Option<std::sync::Arc<std::sync::RwLock<space_saver_core::HashCache>>>,
+    /// Paths duplicate resolution never marks for deletion
+    protected: crate::ProtectedPaths,
+    /// Receives [`ProgressUpdate::Progress`] reports from long operations
+    progress: Option<mpsc::Sender<ProgressUpdate>>,
+    /// Stops scans, duplicate checks and similarity checks early
+    cancel: Option<space_saver_core::CancellationToken>,
+    jobs: crate::JobManager,
+    /// Groups the user keeps on purpose, left out of duplicate and similar
+    /// results
+    ignored: crate::IgnoreList,
+    /// Read-rate limit and IO priority for scans, hashing and similarity
+    /// checks
+    io: space_saver_core::IoPolicy,
+    /// Where scans and duplicate checks record their results, if anywhere
+    db: Option<Database>,
+    /// Outputs of work in progress, removed if the app shuts down first
+    partial: crate::PartialOutputs,
+    /// Recent walks to reuse instead of walking a tree again
+    scan_cache: Option<crate::ScanCache>,
+    /// Mounted network shares, hashed with fewer threads and retries
+    network: crate::NetworkShares,
+    /// Run around cleanups that are not dry runs, cache cleaning and build
+    /// output removal
+    hooks: crate::Hooks,
 }
 
 impl ServiceApi {
@@ -80,6 +281,147 @@ impl ServiceApi {
         Self {
             scanner: DefaultFileScanner::new(),
             hash_cache: None,
+            protected: crate::ProtectedPaths::new(),
+            progress: None,
+            cancel: None,
+            jobs: crate::JobManager::new(),
+            ignored: crate::IgnoreList::default(),
+            io: space_saver_core::IoPolicy::default(),
+            db: None,
+            partial: crate::PartialOutputs::new(),
+            scan_cache: None,
+            network: crate::NetworkShares::default(),
+            hooks: crate::Hooks::default(),
+        }
+    }
+
+    /// Report progress of scans, duplicate checks and similarity checks to
+    /// `progress_tx`. Reports are sent with `try_send` (hashing runs on rayon
+    /// threads), so a lagging receiver misses some rather than stalling the
+    /// work. Callers send their own `Started`/`Completed` around the call.
+    pub fn with_progress(mut self, progress_tx: mpsc::Sender<ProgressUpdate>) -> Self {
+        self.progress = Some(progress_tx);
+        self
+    }
+
+    /// Make scans, duplicate checks and similarity checks stop with a
+    /// [`space_saver_core::Cancelled`] error once `token` is cancelled
+    pub fn with_cancel(mut self, token: space_saver_core::CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Share a job registry (a fresh one by default)
+    pub fn with_jobs(mut self, jobs: crate::JobManager) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Share a registry of partial outputs (a fresh one by default), so
+    /// [`Self::shutdown`] on any API sharing it cleans up after this one
+    pub fn with_partial_outputs(mut self, partial: crate::PartialOutputs) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    /// Stop for good: cancel the unfinished jobs, give them `grace` to wind
+    /// down, remove partial outputs and flush the attached database.
+    /// Connections still in use by work that did not stop are left alone.
+    /// Blocking; see [`crate::shutdown::shutdown`].
+    pub fn shutdown(&self, grace: std::time::Duration) -> crate::ShutdownReport {
+        let report = crate::shutdown::shutdown(&self.jobs, &self.partial, grace);
+        if let Some(db) = &self.db {
+            if let Err(e) = db.flush() {
+                tracing::warn!(error = %e, "Failed to flush the database");
+            }
+        }
+        report
+    }
+
+    /// Running and recently finished jobs, oldest first
+    pub fn list_jobs(&self) -> Vec<crate::JobInfo> {
+        self.jobs.list()
+    }
+
+    pub fn job_status(&self, id: crate::JobId) -> Result<crate::JobInfo> {
+        self.jobs
+            .status(id)
+            .ok_or_else(|| Error::NotFound(format!("Unknown job: {}", id)))
+    }
+
+    /// Ask a job to stop; false when it had already finished
+    pub fn cancel_job(&self, id: crate::JobId) -> Result<bool> {
+        Ok(self.jobs.cancel(id)?)
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel {
+            Some(token) => Ok(token.check()?),
+            None => Ok(()),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|t| t.is_cancelled())
+    }
+
+    fn report_progress(&self, current: usize, total: usize, message: impl FnOnce() -> String) {
+        self.report_throughput(current, total, (0, 0), message);
+    }
+
+    /// [`Self::report_progress`] for work that reads files, with `bytes` as
+    /// (done, total) so frontends can show throughput
+    fn report_throughput(
+        &self,
+        current: usize,
+        total: usize,
+        bytes: (u64, u64),
+        message: impl FnOnce() -> String,
+    ) {
+        if let Some(tx) = &self.progress {
+            let _ = tx.try_send(ProgressUpdate::Progress {
+                current,
+                total,
+                message: message(),
+                bytes: bytes.0,
+                total_bytes: bytes.1,
+            });
+        }
+    }
+
+    /// Scans `path` as `filter` asks (skipping hidden entries unless it
+    /// includes them), or reuses a cached walk, and applies `filter`
+    pub(crate) fn scan_filtered(
+        &self,
+        path: &Path,
+        filter: Option<&FilterConfig>,
+    ) -> Result<Vec<FileInfo>> {
+        let cached = self.scan_cache.as_ref().and_then(|c| c.get(path, filter));
+        let files = match cached {
+            Some(files) => files,
+            None => {
+                let _priority = self.io.enter();
+                let scanner = match filter {
+                    Some(filter) => filter.configure_scanner(self.scanner.clone()),
+                    None => self.scanner.clone().include_hidden(false),
+                };
+                let files = scanner.scan(path)?;
+                if let Some(cache) = &self.scan_cache {
+                    cache.insert(path, filter, &files);
+                }
+                files
+            }
+        };
+        match filter {
+            Some(filter) => {
+                // Tags only matter to filter expressions
+                let tags = match &self.db {
+                    Some(db) if filter.has_query() => Some(crate::tags::tag_index(&*db.get()?)?),
+                    _ => None,
+                };
+                filter.apply_with_tags(files, tags.as_ref())
+            }
+            None => Ok(files),
         }
     }
 
@@ -91,6 +433,140 @@ impl ServiceApi {
         self
     }
 
+    /// Replace the protected-paths list (system directories by default)
+    pub fn with_protected_paths(mut self, protected: crate::ProtectedPaths) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Throttle scans, hashing and similarity checks (e.g. when they run
+    /// as background jobs): hashing reads no faster than the policy's limit,
+    /// and all of them run at low IO priority if it asks for that
+    pub fn with_io_policy(mut self, io: space_saver_core::IoPolicy) -> Self {
+        self.io = io;
+        self
+    }
+
+    /// Hash files on the network shares in `network` with its parallelism,
+    /// read-rate caps and retries instead of a thread per core. See
+    /// [`crate::network`].
+    pub fn with_network_shares(mut self, network: crate::NetworkShares) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Run the "cleanup" hooks in `hooks` around [`Self::run_cleanup`]
+    /// (not in dry runs) and the "delete" hooks around
+    /// [`Self::clean_app_caches`] and [`Self::remove_dev_artifacts`]. See
+    /// [`crate::hooks`].
+    pub fn with_hooks(mut self, hooks: crate::Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Reuse the walks kept in `cache`, and keep new ones there, instead of
+    /// walking a tree for every operation. See [`crate::scan_cache`].
+    pub fn with_scan_cache(mut self, cache: crate::ScanCache) -> Self {
+        self.scan_cache = Some(cache);
+        self
+    }
+
+    /// Leave the groups in `ignored` out of duplicate and similar results
+    pub fn with_ignore_list(mut self, ignored: crate::IgnoreList) -> Self {
+        self.ignored = ignored;
+        self
+    }
+
+    /// Record the results of scans and duplicate checks in `db`: the files
+    /// seen, unfiltered scans for the storage history and the duplicate
+    /// groups found. See [`crate::records`].
+    pub fn with_database(mut self, db: Database) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Run `record` on the attached database, if any. Failures are logged:
+    /// the results are still returned even when they cannot be kept.
+    fn persist(&self, what: &str, record: impl FnOnce(&SqliteDatabase) -> anyhow::Result<()>) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        if let Err(e) = db.get().and_then(|db| record(&db)) {
+            tracing::warn!(error = %e, "Failed to record {}", what);
+        }
+    }
+
+    fn database(&self) -> Result<PooledConnection> {
+        Ok(self
+            .db
+            .as_ref()
+            .ok_or_else(|| Error::Database("No database attached".to_string()))?
+            .get()?)
+    }
+
+    /// The most recently recorded scans, newest first
+    pub fn recent_scans(&self, limit: usize) -> Result<Vec<ScanRecord>> {
+        Ok(self.database()?.get_recent_scans(limit)?)
+    }
+
+    /// A recorded scan in detail; see [`crate::history::scan_details`]
+    pub fn scan_details(&self, id: i64) -> Result<crate::ScanDetails> {
+        Ok(crate::history::scan_details(id, &*self.database()?)?)
+    }
+
+    /// The most recent recorded scans with what their duplicates would free;
+    /// see [`crate::history::scan_history`]
+    pub fn scan_history(&self, limit: usize) -> Result<Vec<crate::ScanHistoryEntry>> {
+        Ok(crate::history::scan_history(limit, &*self.database()?)?)
+    }
+
+    /// Forget a recorded scan; see [`crate::history::delete_scan`]
+    pub fn delete_scan(&self, id: i64) -> Result<()> {
+        Ok(crate::history::delete_scan(id, &*self.database()?)?)
+    }
+
+    /// Forget stored files no longer on disk and vacuum the database; see
+    /// [`crate::maintenance`]. Blocking.
+    pub fn maintain_database(&self) -> Result<MaintenanceRecord> {
+        Ok(crate::maintenance::run_maintenance(&*self.database()?)?)
+    }
+
+    /// Whether no maintenance pass ran in the last `interval`
+    pub fn maintenance_due(&self, interval: std::time::Duration) -> Result<bool> {
+        Ok(crate::maintenance::maintenance_due(
+            &*self.database()?,
+            interval,
+        )?)
+    }
+
+    /// Space saved by the compressions recorded so far
+    pub fn savings_summary(&self) -> Result<SavingsSummary> {
+        Ok(self.database()?.savings_summary()?)
+    }
+
+    /// The duplicate groups last found, most wasted space first
+    pub fn recorded_duplicates(&self) -> Result<Vec<DuplicateRecord>> {
+        Ok(self.database()?.get_duplicates()?)
+    }
+
+    /// What the last scan or duplicate check saw of the file at `path`
+    pub fn recorded_file(&self, path: &Path) -> Result<Option<FileRecord>> {
+        let key = crate::records::record_key(path)?;
+        Ok(self.database()?.get_file_by_path(&key)?)
+    }
+
+    /// A page of the files scans and duplicate checks recorded, without
+    /// rescanning; see [`FileQuery`]
+    pub fn recorded_files(&self, query: &FileQuery, page: Page) -> Result<Vec<FileRecord>> {
+        let query = match query {
+            FileQuery::Under { path } => FileQuery::Under {
+                path: crate::records::record_key(Path::new(path))?,
+            },
+            other => other.clone(),
+        };
+        Ok(self.database()?.find_files(&query, page)?)
+    }
+
     /// Scan multiple directories (primary method)
     pub async fn scan_directories(
         &self,
@@ -98,24 +574,37 @@ impl ServiceApi {
         filter: Option<FilterConfig>,
     ) -> Result<Vec<ScanResult>> {
         let mut results = Vec::new();
-
-        for path in paths {
-            let mut files = self.scanner.scan(&path)?;
-
-            // Apply filters if provided
-            if let Some(ref filter_config) = filter {
-                files = filter_config.apply(files);
+        let total = paths.len();
+        // Only unfiltered scans go into the storage history, so snapshots
+        // compare like with like
+        let unfiltered = filter.as_ref().is_none_or(FilterConfig::is_empty);
+
+        for (index, path) in paths.into_iter().enumerate() {
+            self.check_cancelled()?;
+            // An explicit scan looks at the disk again
+            if let Some(cache) = &self.scan_cache {
+                cache.invalidate(&path);
             }
+            let started = std::time::Instant::now();
+            let files = self.scan_filtered(&path, filter.as_ref())?;
+            self.report_progress(index + 1, total, || format!("Scanned {}", path.display()));
 
             let total_size: u64 = files.iter().map(|f| f.size).sum();
             let file_count = files.len();
 
-            results.push(ScanResult {
+            let result = ScanResult {
                 path,
                 file_count,
                 total_size,
                 files,
+            };
+            self.persist("scan", |db| {
+                if unfiltered {
+                    crate::history::record_scan(&result, started.elapsed(), db)?;
+                }
+                crate::records::record_files(result.files.iter().map(|f| (f, None)), db)
             });
+            results.push(result);
         }
 
         Ok(results)
@@ -131,7 +620,113 @@ impl ServiceApi {
         results
             .into_iter()
             .next()
-            .ok_or_else(|| anyhow::anyhow!("No scan results returned"))
+            .ok_or_else(|| Error::Unknown("No scan results returned".to_string()))
+    }
+
+    /// Content hashes of `files`, in parallel, consulting the hash cache for
+    /// unchanged files. Unreadable files come back separately, with why; they
+    /// cannot be safely treated as copies of anything. Files on network
+    /// shares are hashed one share at a time with a small pool of threads,
+    /// see [`Self::with_network_shares`].
+    fn hash_files(&self, files: Vec<FileInfo>) -> Result<(Vec<HashedFile>, Vec<FileError>)> {
+        use rayon::prelude::*;
+        use space_saver_core::skip_cache::FileFingerprint;
+        use space_saver_core::FileHasher;
+
+        // `fresh` carries the cache key for newly computed hashes; they are
+        // inserted after the parallel section so workers never contend on the
+        // cache's write lock
+        type Hashed = (String, FileInfo, Option<(String, FileFingerprint)>);
+        type Outcome = std::result::Result<Hashed, FileError>;
+        let hasher = match self.io.throttle() {
+            Some(throttle) => FileHasher::new_blake3().with_throttle(throttle.clone()),
+            None => FileHasher::new_blake3(),
+        };
+        let file_count = files.len();
+        let total_bytes: u64 = files.iter().map(|f| f.size).sum();
+        let checked = std::sync::atomic::AtomicUsize::new(0);
+        let read = std::sync::atomic::AtomicU64::new(0);
+        let hash_all = |files: Vec<FileInfo>, hasher: &FileHasher| -> Vec<Outcome> {
+            files
+                .into_par_iter()
+                .filter_map(|file| {
+                    if self.is_cancelled() {
+                        return None;
+                    }
+                    // Hashing runs on rayon's shared threads; the guard hands
+                    // each one back at its usual priority
+                    let _priority = self.io.enter();
+                    let done = checked.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                    let bytes =
+                        read.fetch_add(file.size, std::sync::atomic::Ordering::Relaxed) + file.size;
+                    self.report_throughput(done, file_count, (bytes, total_bytes), || {
+                        format!("Hashing {}", file.path.display())
+                    });
+                    let path_str = file.path.to_string_lossy().to_string();
+                    let fingerprint = FileFingerprint {
+                        size: file.size,
+                        mtime: file.modified,
+                    };
+
+                    if let Some(cache) = &self.hash_cache {
+                        if let Ok(cache) = cache.read() {
+                            if let Some(hash) = cache.get(&path_str, &fingerprint) {
+                                return Some(Ok((hash.to_string(), file, None)));
+                            }
+                        }
+                    }
+
+                    Some(match hasher.hash_file(&file.path) {
+                        Ok(hash) => Ok((hash, file, Some((path_str, fingerprint)))),
+                        Err(e) => Err(FileError::new(&file.path, e.into())),
+                    })
+                })
+                .collect()
+        };
+
+        let mut on_shares: Vec<(&crate::NetworkShare, Vec<FileInfo>)> = Vec::new();
+        let mut local = Vec::new();
+        for file in files {
+            match self.network.share_of(&file.path) {
+                Some(share) => match on_shares.iter_mut().find(|(s, _)| std::ptr::eq(*s, share)) {
+                    Some((_, files)) => files.push(file),
+                    None => on_shares.push((share, vec![file])),
+                },
+                None => local.push(file),
+            }
+        }
+        let mut outcomes = hash_all(local, &hasher);
+        for (share, files) in on_shares {
+            let hasher = match share.throttle.as_ref().or(self.io.throttle()) {
+                Some(throttle) => FileHasher::new_blake3().with_throttle(throttle.clone()),
+                None => FileHasher::new_blake3(),
+            }
+            .with_retries(self.network.retry());
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.network.parallelism())
+                .build()
+                .map_err(|e| Error::Unknown(format!("Failed to start hashing threads: {}", e)))?;
+            outcomes.extend(pool.install(|| hash_all(files, &hasher)));
+        }
+        self.check_cancelled()?;
+
+        let mut cache_guard = self.hash_cache.as_ref().and_then(|c| c.write().ok());
+        let mut result = Vec::with_capacity(outcomes.len());
+        let mut unreadable = Vec::new();
+        for outcome in outcomes {
+            let (hash, file, fresh) = match outcome {
+                Ok(hashed) => hashed,
+                Err(e) => {
+                    unreadable.push(e);
+                    continue;
+                }
+            };
+            if let (Some(cache), Some((path_str, fingerprint))) = (cache_guard.as_mut(), fresh) {
+                cache.insert(&path_str, fingerprint, hash.clone());
+            }
+            result.push((hash, file));
+        }
+        Ok((result, unreadable))
     }
 
     /// Find duplicate files across multiple directories (primary method)
@@ -140,21 +735,50 @@ impl ServiceApi {
         paths: Vec<PathBuf>,
         filter: Option<FilterConfig>,
     ) -> Result<Vec<DuplicateGroup>> {
-        use space_saver_core::FileHasher;
-        use std::collections::HashMap;
-
-        // Collect files from all paths
-        let mut all_files = Vec::new();
-        for path in paths {
-            let mut files = self.scanner.scan(&path)?;
+        Ok(self.find_duplicate_report(paths, filter).await?.groups)
+    }
 
-            // Apply filters if provided
-            if let Some(ref filter_config) = filter {
-                files = filter_config.apply(files);
-            }
+    /// Like [`Self::find_duplicates_in_paths`], also listing the candidate
+    /// files that could not be read, and so were left out of every group.
+    /// `paths` may be files as well as directories, and may overlap.
+    pub async fn find_duplicate_report(
+        &self,
+        paths: Vec<PathBuf>,
+        filter: Option<FilterConfig>,
+    ) -> Result<DuplicateReport> {
+        let all_files = self.collect_files(&paths, filter.as_ref())?;
+        self.group_duplicates(&paths, all_files)
+    }
 
-            all_files.extend(files);
+    /// The files at or beneath each of `paths`, filtered, each once however
+    /// many of `paths` reach it (a folder and the files in it, as `find`
+    /// lists them). Nothing is recorded, unlike [`Self::scan_directories`].
+    pub fn collect_files(
+        &self,
+        paths: &[PathBuf],
+        filter: Option<&FilterConfig>,
+    ) -> Result<Vec<FileInfo>> {
+        let mut all_files = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for (index, path) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            let files = self.scan_filtered(path, filter)?;
+            self.report_progress(index + 1, paths.len(), || {
+                format!("Scanned {}", path.display())
+            });
+            all_files.extend(files.into_iter().filter(|f| seen.insert(f.path.clone())));
         }
+        Ok(all_files)
+    }
+
+    /// Duplicate groups among `all_files`, scanned from `paths`; hashes and
+    /// groups are recorded like [`Self::find_duplicate_report`]'s
+    fn group_duplicates(
+        &self,
+        paths: &[PathBuf],
+        all_files: Vec<FileInfo>,
+    ) -> Result<DuplicateReport> {
+        use std::collections::HashMap;
 
         // Step 1: Group files by size first. Empty files are excluded: they
         // are all trivially identical and belong to the Empty Files feature.
@@ -166,55 +790,21 @@ impl ServiceApi {
             size_map.entry(file.size).or_default().push(file);
         }
 
-        // Step 2: Hash only files that share a size (potential duplicates),
-        // in parallel, consulting the hash cache for unchanged files
-        use rayon::prelude::*;
-        use space_saver_core::skip_cache::FileFingerprint;
-
+        // Step 2: Hash only files that share a size (potential duplicates)
         let candidates: Vec<FileInfo> = size_map
             .into_values()
             .filter(|files| files.len() > 1)
             .flatten()
             .collect();
 
-        // `fresh` carries the cache key for newly computed hashes; they are
-        // inserted after the parallel section so workers never contend on the
-        // cache's write lock
-        type Hashed = (String, FileInfo, Option<(String, FileFingerprint)>);
-        let hasher = FileHasher::new_blake3();
-        let hashed: Vec<Hashed> = candidates
-            .into_par_iter()
-            .filter_map(|file| {
-                let path_str = file.path.to_string_lossy().to_string();
-                let fingerprint = FileFingerprint {
-                    size: file.size,
-                    mtime: file.modified,
-                };
-
-                if let Some(cache) = &self.hash_cache {
-                    if let Ok(cache) = cache.read() {
-                        if let Some(hash) = cache.get(&path_str, &fingerprint) {
-                            return Some((hash.to_string(), file, None));
-                        }
-                    }
-                }
-
-                // Unreadable files are dropped from the result; they cannot
-                // be safely treated as duplicates of anything
-                let hash = hasher.hash_file(&file.path).ok()?;
-                Some((hash, file, Some((path_str, fingerprint))))
-            })
-            .collect();
-
-        let mut cache_guard = self.hash_cache.as_ref().and_then(|c| c.write().ok());
+        let (hashed, unreadable) = self.hash_files(candidates)?;
+        self.persist("file hashes", |db| {
+            crate::records::record_files(hashed.iter().map(|(h, f)| (f, Some(h.as_str()))), db)
+        });
         let mut hash_map: HashMap<String, Vec<FileInfo>> = HashMap::new();
-        for (hash, file, fresh) in hashed {
-            if let (Some(cache), Some((path_str, fingerprint))) = (cache_guard.as_mut(), fresh) {
-                cache.insert(&path_str, fingerprint, hash.clone());
-            }
+        for (hash, file) in hashed {
             hash_map.entry(hash).or_default().push(file);
         }
-        drop(cache_guard);
 
         // Step 3: Build duplicate groups
         let duplicates: Vec<DuplicateGroup> = hash_map
@@ -233,9 +823,74 @@ impl ServiceApi {
                     wasted_space,
                 }
             })
+            .filter(|group| !self.ignored.ignores_duplicate(group))
             .collect();
+        self.persist("duplicates", |db| {
+            crate::records::record_duplicates(paths, &duplicates, db)
+        });
+
+        Ok(DuplicateReport {
+            groups: duplicates,
+            unreadable,
+        })
+    }
 
-        Ok(duplicates)
+    /// The files at or beneath each of `roots` in `storage`, filtered, each
+    /// once. Only whether hidden files count is passed on to the backend;
+    /// depth and links are up to it.
+    pub async fn list_storage(
+        &self,
+        storage: &dyn StorageBackend,
+        roots: &[PathBuf],
+        filter: Option<&FilterConfig>,
+    ) -> Result<Vec<StoredFile>> {
+        let include_hidden = filter.is_some_and(FilterConfig::includes_hidden);
+        let mut files = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for (index, root) in roots.iter().enumerate() {
+            self.check_cancelled()?;
+            let listed = storage.list(root, include_hidden).await?;
+            self.report_progress(index + 1, roots.len(), || {
+                format!("Listed {}", root.display())
+            });
+            files.extend(
+                listed
+                    .into_iter()
+                    .filter(|f| seen.insert(f.info.path.clone())),
+            );
+        }
+        let Some(filter) = filter else {
+            return Ok(files);
+        };
+        let kept: std::collections::HashSet<PathBuf> = filter
+            .apply(files.iter().map(|f| f.info.clone()).collect())?
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        files.retain(|f| kept.contains(&f.info.path));
+        Ok(files)
+    }
+
+    /// Like [`Self::find_duplicate_report`] for files in `storage` (an S3
+    /// bucket, a WebDAV share), trusting ETags where they tell whether files
+    /// match and reading byte ranges only where they cannot; see
+    /// [`crate::storage`]. Nothing is recorded: the paths are not local.
+    pub async fn find_duplicates_in_storage(
+        &self,
+        storage: &dyn StorageBackend,
+        roots: Vec<PathBuf>,
+        filter: Option<FilterConfig>,
+    ) -> Result<DuplicateReport> {
+        let files = self.list_storage(storage, &roots, filter.as_ref()).await?;
+        let (groups, unreadable) =
+            crate::storage::duplicate_groups(storage, files, self.cancel.as_ref()).await?;
+        Ok(DuplicateReport {
+            groups: groups
+                .into_iter()
+                .filter(|group| !self.ignored.ignores_duplicate(group))
+                .collect(),
+            unreadable,
+        })
     }
 
     /// Find duplicate files in a single directory (delegates to find_duplicates_in_paths)
@@ -247,6 +902,205 @@ impl ServiceApi {
         self.find_duplicates_in_paths(vec![path], filter).await
     }
 
+    /// Decide which copies of each duplicate group to keep and which to
+    /// delete. Protected copies are always kept. Nothing is deleted here;
+    /// pass the `delete` lists to
+    /// [`crate::FileOperations::delete_files_with_mode`].
+    pub fn resolve_duplicates(
+        &self,
+        groups: &[DuplicateGroup],
+        strategy: &dyn crate::KeepStrategy,
+    ) -> Vec<crate::DuplicateResolution> {
+        let mut resolutions = crate::dedupe::resolve_duplicates(groups, strategy);
+        for resolution in &mut resolutions {
+            resolution.protect(&self.protected);
+        }
+        resolutions
+    }
+
+    /// Deduplicate with copy-on-write clones instead of deleting: each
+    /// `delete` copy is replaced by a clone of the group's first `keep` copy.
+    /// Blocking; see [`crate::reflink::reflink_duplicates`].
+    pub fn reflink_duplicates(
+        &self,
+        resolutions: &[crate::DuplicateResolution],
+    ) -> Vec<crate::ReflinkResult> {
+        crate::reflink::reflink_duplicates(resolutions, &self.protected)
+    }
+
+    /// Deduplicate with hard links instead of deleting: each `delete` copy
+    /// becomes another name for the group's first `keep` copy. Blocking; see
+    /// [`crate::hardlink::hardlink_duplicates`].
+    pub fn hardlink_duplicates(
+        &self,
+        resolutions: &[crate::DuplicateResolution],
+    ) -> Vec<crate::HardlinkResult> {
+        crate::hardlink::hardlink_duplicates(resolutions, &self.protected)
+    }
+
+    /// Whether the file system holding `dir` supports copy-on-write clones
+    pub fn supports_reflink(&self, dir: &Path) -> bool {
+        space_saver_core::supports_reflink(dir)
+    }
+
+    /// Find directories whose whole trees are identical across multiple
+    /// directories, reporting only the topmost duplicated trees. Hidden
+    /// files always count: two folders only match if everything in them
+    /// does. See [`crate::duplicate_dirs`].
+    pub async fn find_duplicate_directories(
+        &self,
+        paths: Vec<PathBuf>,
+    ) -> Result<Vec<crate::DuplicateDirectoryGroup>> {
+        let mut all_files = Vec::new();
+        let total = paths.len();
+        for (index, path) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            all_files.extend(self.scanner.clone().include_hidden(true).scan(path)?);
+            self.report_progress(index + 1, total, || format!("Scanned {}", path.display()));
+        }
+        self.check_cancelled()?;
+        Ok(crate::duplicate_dirs::find_duplicate_directories(
+            &paths, &all_files,
+        ))
+    }
+
+    /// Delete one copy of a duplicated directory tree, keeping `keep`. The
+    /// two trees are compared again first and protected paths are refused.
+    pub fn delete_duplicate_directory(
+        &self,
+        target: &Path,
+        keep: &Path,
+        mode: crate::DeleteMode,
+    ) -> crate::DeleteResult {
+        crate::duplicate_dirs::delete_duplicate_directory(target, keep, mode, &self.protected)
+    }
+
+    /// What deleting all but one copy of each group would do; see
+    /// [`crate::duplicate_dirs::deletion_plan`]
+    pub fn plan_duplicate_directory_deletion(
+        &self,
+        groups: &[crate::DuplicateDirectoryGroup],
+        mode: crate::DeleteMode,
+    ) -> crate::ActionPlan {
+        crate::duplicate_dirs::deletion_plan(groups, mode, &self.protected)
+    }
+
+    /// Find application caches and build output that are rebuilt when
+    /// needed: the current user's browser, package manager and thumbnail
+    /// caches, and the `node_modules` and `target` folders beneath `roots`.
+    /// Blocking; see [`crate::app_caches`].
+    pub fn scan_app_caches(&self, roots: &[PathBuf]) -> Result<crate::AppCacheReport> {
+        self.scan_app_caches_with(&crate::CacheLocator::detect(), roots)
+    }
+
+    /// [`Self::scan_app_caches`] looking where `locator` says
+    pub fn scan_app_caches_with(
+        &self,
+        locator: &crate::CacheLocator,
+        roots: &[PathBuf],
+    ) -> Result<crate::AppCacheReport> {
+        let found = locator.locate(roots);
+        let total = found.len();
+        let mut locations = Vec::with_capacity(total);
+        for (index, (category, path)) in found.into_iter().enumerate() {
+            self.check_cancelled()?;
+            let location = crate::CacheLocation::measure(category, path);
+            self.report_progress(index + 1, total, || {
+                format!("Measured {}", location.path.display())
+            });
+            locations.push(location);
+        }
+        Ok(crate::AppCacheReport::new(locations))
+    }
+
+    /// What cleaning the `categories` of `report` would do; see
+    /// [`crate::app_caches::cleaning_plan`]
+    pub fn plan_app_cache_cleaning(
+        &self,
+        report: &crate::AppCacheReport,
+        categories: &[crate::CacheCategory],
+        mode: crate::DeleteMode,
+    ) -> crate::ActionPlan {
+        crate::app_caches::cleaning_plan(report, categories, mode, &self.protected)
+    }
+
+    /// Remove the `categories` of `report`, each location checked again
+    /// first, with the "delete" hooks around it
+    pub fn clean_app_caches(
+        &self,
+        report: &crate::AppCacheReport,
+        categories: &[crate::CacheCategory],
+        mode: crate::DeleteMode,
+    ) -> Vec<crate::DeleteResult> {
+        let paths: Vec<PathBuf> = report
+            .locations(categories)
+            .map(|location| location.path.clone())
+            .collect();
+        crate::file_ops::hooked_deletion(&self.hooks, &paths, || {
+            crate::app_caches::clean(report, categories, mode, &self.protected)
+        })
+    }
+
+    /// Find project folders beneath `roots` with their build output
+    /// (`target`, `node_modules`, `dist`, `.venv`), when each was last
+    /// built, and which projects nobody touched for `untouched_months`.
+    /// Blocking; see [`crate::dev_artifacts`].
+    pub fn find_dev_artifacts(
+        &self,
+        roots: &[PathBuf],
+        untouched_months: u32,
+    ) -> Result<crate::DevArtifactReport> {
+        let cutoff = crate::dev_artifacts::untouched_cutoff(now(), untouched_months);
+        let found = crate::dev_artifacts::find_projects(roots);
+        let total = found.len();
+        let mut projects = Vec::with_capacity(total);
+        for (index, (root, artifacts)) in found.into_iter().enumerate() {
+            self.check_cancelled()?;
+            let project = crate::DevProject::measure(root, artifacts, cutoff);
+            self.report_progress(index + 1, total, || {
+                format!("Measured {}", project.root.display())
+            });
+            projects.push(project);
+        }
+        Ok(crate::DevArtifactReport::new(projects, untouched_months))
+    }
+
+    /// What removing the build output of the projects `report` suggests
+    /// would do; see [`crate::dev_artifacts::removal_plan`]
+    pub fn plan_dev_artifact_removal(
+        &self,
+        report: &crate::DevArtifactReport,
+        mode: crate::DeleteMode,
+    ) -> crate::ActionPlan {
+        crate::dev_artifacts::removal_plan(report, mode, &self.protected)
+    }
+
+    /// Remove the build output of the projects `report` suggests, each
+    /// folder checked again first, with the "delete" hooks around it
+    pub fn remove_dev_artifacts(
+        &self,
+        report: &crate::DevArtifactReport,
+        mode: crate::DeleteMode,
+    ) -> Vec<crate::DeleteResult> {
+        let paths: Vec<PathBuf> = report
+            .suggested()
+            .map(|artifact| artifact.path.clone())
+            .collect();
+        crate::file_ops::hooked_deletion(&self.hooks, &paths, || {
+            crate::dev_artifacts::remove(report, mode, &self.protected)
+        })
+    }
+
+    /// What deleting the copies `resolutions` do not keep with `action`
+    /// would do; see [`crate::plan::dedupe_plan`]
+    pub fn plan_dedupe(
+        &self,
+        resolutions: &[crate::DuplicateResolution],
+        action: &str,
+    ) -> crate::ActionPlan {
+        crate::plan::dedupe_plan(resolutions, action)
+    }
+
     /// Find similar media across multiple directories (primary method).
     ///
     /// `media_types` selects which kinds to scan; an empty list defaults to
@@ -278,12 +1132,7 @@ impl ServiceApi {
             // Collect image files from all paths
             let mut image_files = Vec::new();
             for path in &paths {
-                let mut files = self.scanner.scan(path)?;
-
-                // Apply filters if provided
-                if let Some(ref filter_config) = filter {
-                    files = filter_config.apply(files);
-                }
+                let files = self.scan_filtered(path, filter.as_ref())?;
 
                 image_files.extend(
                     files
@@ -293,13 +1142,47 @@ impl ServiceApi {
             }
 
             let similarity = ImageSimilarity::new();
+            let _priority = self.io.enter();
+
+            // Scores recorded by earlier runs stand in for comparisons of
+            // files that have not changed since
+            let keys = image_files
+                .iter()
+                .map(|f| crate::records::record_key(&f.path))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut recorded = crate::records::SimilarityScores::default();
+            self.persist("similarity scores", |db| {
+                recorded = crate::records::load_similarities(&keys, db)?;
+                Ok(())
+            });
+            let mut computed = Vec::new();
 
             // Simple pairwise comparison (can be optimized)
-            for i in 0..image_files.len() {
-                for j in (i + 1)..image_files.len() {
-                    if let Ok(score) =
-                        similarity.compare(&image_files[i].path, &image_files[j].path)
-                    {
+            let compared = (|| -> Result<()> {
+                for i in 0..image_files.len() {
+                    self.check_cancelled()?;
+                    self.report_progress(i + 1, image_files.len(), || {
+                        format!("Comparing {}", image_files[i].path.display())
+                    });
+                    let a = (keys[i].as_str(), image_files[i].modified);
+                    for j in (i + 1)..image_files.len() {
+                        let b = (keys[j].as_str(), image_files[j].modified);
+                        let score = match recorded.get(a, b) {
+                            Some(score) => score,
+                            None => {
+                                let Ok(score) =
+                                    similarity.compare(&image_files[i].path, &image_files[j].path)
+                                else {
+                                    continue;
+                                };
+                                computed.push(space_saver_db::SimilarityRecord::new(
+                                    (a.0.to_string(), a.1),
+                                    (b.0.to_string(), b.1),
+                                    score,
+                                ));
+                                score
+                            }
+                        };
                         if score >= threshold {
                             similar_groups.push(SimilarGroup {
                                 media_kind: MediaKind::Image,
@@ -312,13 +1195,20 @@ impl ServiceApi {
                         }
                     }
                 }
+                Ok(())
+            })();
+            // Kept even when cancelled, so the next run picks up from here
+            if !computed.is_empty() {
+                self.persist("similarity scores", |db| db.upsert_similarities(&computed));
             }
+            compared?;
         }
 
         // MediaKind::Video intentionally produces no groups for now: video
         // similarity requires ffmpeg-based frame sampling which is not yet
         // wired up. The frontend keeps the Videos option disabled accordingly.
 
+        similar_groups.retain(|group| !self.ignored.ignores_similar(group));
         Ok(similar_groups)
     }
 
@@ -352,12 +1242,7 @@ impl ServiceApi {
         let mut empty_folders = Vec::new();
 
         for path in paths {
-            let mut files = self.scanner.scan(&path)?;
-
-            // Apply filters if provided
-            if let Some(ref filter_config) = filter {
-                files = filter_config.apply(files);
-            }
+            let files = self.scan_filtered(&path, filter.as_ref())?;
 
             empty_files.extend(
                 files
@@ -393,12 +1278,7 @@ impl ServiceApi {
         // Collect files from all paths
         let mut all_files = Vec::new();
         for path in paths {
-            let mut files = self.scanner.scan(&path)?;
-
-            // Apply filters if provided
-            if let Some(ref filter_config) = filter {
-                files = filter_config.apply(files);
-            }
+            let files = self.scan_filtered(&path, filter.as_ref())?;
 
             all_files.extend(files);
         }
@@ -419,77 +1299,653 @@ impl ServiceApi {
             })
             .collect();
 
-        Ok(broken)
+        Ok(broken)
+    }
+
+    /// Find broken files in a single directory (delegates to
+    /// find_broken_files_in_paths)
+    pub async fn find_broken_files(
+        &self,
+        path: PathBuf,
+        filter: Option<FilterConfig>,
+    ) -> Result<Vec<BrokenFile>> {
+        self.find_broken_files_in_paths(vec![path], filter).await
+    }
+
+    /// Find files untouched for at least `older_than_days` across multiple
+    /// directories: neither modified nor (where the file system records it)
+    /// accessed since the cutoff. Results are grouped by parent directory,
+    /// largest first, answering "what could be archived?".
+    pub async fn find_stale_files(
+        &self,
+        paths: Vec<PathBuf>,
+        older_than_days: u32,
+        filter: Option<FilterConfig>,
+    ) -> Result<StaleFilesReport> {
+        use std::collections::HashMap;
+
+        let cutoff = now() - i64::from(older_than_days) * 86_400;
+        let mut by_dir: HashMap<PathBuf, Vec<StaleFile>> = HashMap::new();
+        let total = paths.len();
+
+        for (index, path) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            let files = self.scan_filtered(path, filter.as_ref())?;
+            self.report_progress(index + 1, total, || format!("Scanned {}", path.display()));
+
+            for file in files {
+                let Some(stale) = stale_file(&file, cutoff) else {
+                    continue;
+                };
+                let dir = file
+                    .path
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                by_dir.entry(dir).or_default().push(stale);
+            }
+        }
+
+        let mut directories: Vec<StaleDirectory> = by_dir
+            .into_iter()
+            .map(|(dir, mut files)| {
+                sort_stale_files(&mut files);
+                StaleDirectory {
+                    path: dir.to_string_lossy().to_string(),
+                    file_count: files.len(),
+                    total_size: files.iter().map(|f| f.size).sum(),
+                    files,
+                }
+            })
+            .collect();
+        directories.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        Ok(StaleFilesReport {
+            cutoff,
+            file_count: directories.iter().map(|d| d.file_count).sum(),
+            total_size: directories.iter().map(|d| d.total_size).sum(),
+            directories,
+        })
+    }
+
+    /// Find files beneath `root` untouched since `cutoff` (a Unix
+    /// timestamp), grouped by the entry directly beneath `root` they are in:
+    /// what each top-level folder would free. Files directly in `root` form
+    /// a group of their own. Folders that also hold recently used files say
+    /// how many, since archiving such a folder whole would take those too.
+    pub async fn find_stale_folders(
+        &self,
+        root: &Path,
+        cutoff: i64,
+        filter: Option<FilterConfig>,
+    ) -> Result<StaleFoldersReport> {
+        use std::collections::HashMap;
+
+        self.check_cancelled()?;
+        let files = self.scan_filtered(root, filter.as_ref())?;
+        self.report_progress(1, 1, || format!("Scanned {}", root.display()));
+
+        // (loose, stale files, recent count) per top-level entry
+        let mut by_folder: HashMap<PathBuf, (bool, Vec<StaleFile>, usize)> = HashMap::new();
+        for file in files {
+            let relative = file.path.strip_prefix(root).unwrap_or(&file.path);
+            let mut components = relative.components();
+            let (folder, loose) = match (components.next(), components.next()) {
+                (Some(first), Some(_)) => (root.join(first), false),
+                _ => (root.to_path_buf(), true),
+            };
+            let entry = by_folder
+                .entry(folder)
+                .or_insert_with(|| (loose, Vec::new(), 0));
+            match stale_file(&file, cutoff) {
+                Some(stale) => entry.1.push(stale),
+                None => entry.2 += 1,
+            }
+        }
+
+        let mut folders: Vec<StaleFolder> = by_folder
+            .into_iter()
+            .filter(|(_, (_, files, _))| !files.is_empty())
+            .map(|(folder, (loose, mut files, recent_count))| {
+                sort_stale_files(&mut files);
+                StaleFolder {
+                    path: folder.to_string_lossy().to_string(),
+                    loose,
+                    file_count: files.len(),
+                    total_size: files.iter().map(|f| f.size).sum(),
+                    recent_count,
+                    files,
+                }
+            })
+            .collect();
+        folders.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        Ok(StaleFoldersReport {
+            root: root.to_string_lossy().to_string(),
+            cutoff,
+            file_count: folders.iter().map(|f| f.file_count).sum(),
+            total_size: folders.iter().map(|f| f.total_size).sum(),
+            folders,
+        })
+    }
+
+    /// The `n` largest files across multiple directories, largest first
+    pub async fn get_largest_files(
+        &self,
+        paths: Vec<PathBuf>,
+        n: usize,
+        filter: Option<FilterConfig>,
+    ) -> Result<Vec<FileInfo>> {
+        let mut all_files = Vec::new();
+        let total = paths.len();
+        for (index, path) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            all_files.extend(self.scan_filtered(path, filter.as_ref())?);
+            self.report_progress(index + 1, total, || format!("Scanned {}", path.display()));
+        }
+
+        all_files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        all_files.truncate(n);
+        Ok(all_files)
+    }
+
+    /// The `n` largest directories beneath the given roots, largest first.
+    /// A directory's size covers everything in its subtree, hidden files
+    /// included, so nested directories are listed alongside their parents.
+    /// The roots themselves are not reported.
+    pub async fn get_largest_directories(
+        &self,
+        paths: Vec<PathBuf>,
+        n: usize,
+    ) -> Result<Vec<DirectorySize>> {
+        use std::collections::HashMap;
+
+        let mut sizes: HashMap<PathBuf, DirectorySize> = HashMap::new();
+        let total = paths.len();
+        for (index, root) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            let files = self.scanner.clone().include_hidden(true).scan(root)?;
+            self.report_progress(index + 1, total, || format!("Scanned {}", root.display()));
+
+            for file in files {
+                let dirs = file
+                    .path
+                    .ancestors()
+                    .skip(1)
+                    .take_while(|dir| *dir != root.as_path() && dir.starts_with(root));
+                for dir in dirs {
+                    let entry = sizes
+                        .entry(dir.to_path_buf())
+                        .or_insert_with(|| DirectorySize {
+                            path: dir.to_string_lossy().to_string(),
+                            total_size: 0,
+                            file_count: 0,
+                        });
+                    entry.total_size += file.size;
+                    entry.file_count += 1;
+                }
+            }
+        }
+
+        let mut directories: Vec<DirectorySize> = sizes.into_values().collect();
+        directories.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        directories.truncate(n);
+        Ok(directories)
+    }
+
+    /// A size-weighted directory tree per root for treemap views, `depth`
+    /// levels deep (0 gives just the roots). Each node counts everything
+    /// beneath it, hidden files included, with a breakdown by file type;
+    /// children are sorted largest first. Directories deeper than `depth`
+    /// are folded into their ancestor at the last level.
+    pub async fn get_treemap(&self, paths: Vec<PathBuf>, depth: usize) -> Result<Vec<TreemapNode>> {
+        let total = paths.len();
+        let mut trees = Vec::new();
+        for (index, root) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            let files = self.scanner.clone().include_hidden(true).scan(root)?;
+            self.report_progress(index + 1, total, || format!("Scanned {}", root.display()));
+
+            let mut tree = TreeBuilder::default();
+            for file in files {
+                let Ok(relative) = file.path.strip_prefix(root) else {
+                    continue;
+                };
+                let category = format!("{:?}", file.file_type);
+                let dirs = relative
+                    .parent()
+                    .into_iter()
+                    .flat_map(Path::components)
+                    .take(depth);
+                let mut node = &mut tree;
+                node.add(&category, file.size);
+                for dir in dirs {
+                    node = node.children.entry(dir.as_os_str().to_owned()).or_default();
+                    node.add(&category, file.size);
+                }
+            }
+            trees.push(tree.build(root.clone()));
+        }
+        Ok(trees)
+    }
+
+    /// A shareable report on `path` from a single scan: its breakdown by
+    /// file type, the `top` largest files, folders and duplicate groups,
+    /// and, with a plugin `manager`, the files worth compressing. See
+    /// [`crate::report::build_report`].
+    pub fn storage_report(
+        &self,
+        path: PathBuf,
+        filter: Option<FilterConfig>,
+        top: usize,
+        manager: Option<&space_saver_core::compress_plugins::PluginManager>,
+    ) -> Result<crate::StorageReport> {
+        self.check_cancelled()?;
+        let files = self.scan_filtered(&path, filter.as_ref())?;
+        self.report_progress(1, 2, || format!("Scanned {}", path.display()));
+        let roots = [path];
+        let duplicates = self.group_duplicates(&roots, files.clone())?;
+        self.report_progress(2, 2, || "Compared duplicates".to_string());
+        Ok(crate::report::build_report(
+            &roots[0],
+            &files,
+            duplicates.groups,
+            manager,
+            top,
+        ))
+    }
+
+    /// Get storage statistics across multiple directories (primary method)
+    pub async fn get_storage_stats_for_paths(
+        &self,
+        paths: Vec<PathBuf>,
+        filter: Option<FilterConfig>,
+    ) -> Result<StorageStats> {
+        let all_files = self.collect_files(&paths, filter.as_ref())?;
+        Ok(StorageStats::from_files(&all_files))
+    }
+
+    /// Like [`Self::get_storage_stats_for_paths`] for files in `storage`
+    pub async fn get_storage_stats_in(
+        &self,
+        storage: &dyn StorageBackend,
+        roots: Vec<PathBuf>,
+        filter: Option<FilterConfig>,
+    ) -> Result<StorageStats> {
+        let files = self.list_storage(storage, &roots, filter.as_ref()).await?;
+        let files: Vec<FileInfo> = files.into_iter().map(|f| f.info).collect();
+        Ok(StorageStats::from_files(&files))
+    }
+
+    /// Get storage statistics for a single directory (delegates to get_storage_stats_for_paths)
+    pub async fn get_storage_stats(
+        &self,
+        path: PathBuf,
+        filter: Option<FilterConfig>,
+    ) -> Result<StorageStats> {
+        self.get_storage_stats_for_paths(vec![path], filter).await
+    }
+
+    /// Record a finished scan in `db` so later scans of the same path can be
+    /// compared with it. See [`crate::history::record_scan`].
+    pub fn record_scan(
+        &self,
+        result: &ScanResult,
+        duration: std::time::Duration,
+        db: &SqliteDatabase,
+    ) -> Result<i64> {
+        Ok(crate::history::record_scan(result, duration, db)?)
+    }
+
+    /// Recorded scans of `path`, oldest first, with growth per file category
+    /// from the first to the latest
+    pub fn get_storage_history(
+        &self,
+        path: &Path,
+        db: &SqliteDatabase,
+    ) -> Result<crate::StorageHistory> {
+        Ok(crate::history::storage_history(path, db)?)
+    }
+
+    /// Files added, removed or grown between two recorded scans of the same
+    /// path
+    pub fn diff_scans(&self, from: i64, to: i64, db: &SqliteDatabase) -> Result<crate::ScanDiff> {
+        Ok(crate::history::diff_scans(from, to, db)?)
+    }
+
+    /// Index the reference volume (e.g. a NAS) at `path`: hash every file,
+    /// hidden ones included, and store the hashes in `db`, replacing its
+    /// previous index. Blocking; see [`crate::reference::index_reference`].
+    pub fn index_reference(&self, path: &Path, db: &SqliteDatabase) -> Result<ReferenceRoot> {
+        let files = if path.is_dir() {
+            self.scanner.clone().include_hidden(true).scan(path)?
+        } else {
+            Vec::new()
+        };
+        Ok(crate::reference::index_reference(
+            path,
+            files,
+            db,
+            |files| Ok(self.hash_files(files)?.0),
+        )?)
     }
 
-    /// Find broken files in a single directory (delegates to
-    /// find_broken_files_in_paths)
-    pub async fn find_broken_files(
+    /// Check the files under `paths` against every indexed reference volume,
+    /// reporting those with an identical copy there as safe to delete.
+    /// Blocking.
+    pub fn check_against_reference(
         &self,
-        path: PathBuf,
+        paths: Vec<PathBuf>,
         filter: Option<FilterConfig>,
-    ) -> Result<Vec<BrokenFile>> {
-        self.find_broken_files_in_paths(vec![path], filter).await
+        db: &SqliteDatabase,
+    ) -> Result<crate::ReferenceCheck> {
+        let mut all_files = Vec::new();
+        let total = paths.len();
+        for (index, path) in paths.iter().enumerate() {
+            self.check_cancelled()?;
+            all_files.extend(self.scan_filtered(path, filter.as_ref())?);
+            self.report_progress(index + 1, total, || format!("Scanned {}", path.display()));
+        }
+        Ok(crate::reference::check_files(all_files, db, |files| {
+            Ok(self.hash_files(files)?.0)
+        })?)
     }
 
-    /// Get storage statistics across multiple directories (primary method)
-    pub async fn get_storage_stats_for_paths(
+    /// Indexed reference volumes, by path
+    pub fn list_references(&self, db: &SqliteDatabase) -> Result<Vec<ReferenceRoot>> {
+        Ok(db.get_reference_roots()?)
+    }
+
+    /// Forget the index of the reference volume at `path`, returning how many
+    /// files it held
+    pub fn remove_reference(&self, path: &Path, db: &SqliteDatabase) -> Result<usize> {
+        Ok(db.delete_reference_root(&std::path::absolute(path)?.to_string_lossy())?)
+    }
+
+    /// Write `results` to `path` as CSV, JSON or a self-contained HTML
+    /// report, replacing any existing file
+    pub fn export_results(
         &self,
-        paths: Vec<PathBuf>,
-        filter: Option<FilterConfig>,
-    ) -> Result<StorageStats> {
-        use space_saver_core::scanner::FileType;
+        results: &crate::ExportResults,
+        format: crate::ExportFormat,
+        path: &Path,
+    ) -> Result<()> {
+        Ok(crate::export::export_results(results, format, path)?)
+    }
 
-        // Collect files from all paths
-        let mut all_files = Vec::new();
-        for path in paths {
-            let mut files = self.scanner.scan(&path)?;
+    /// Stop showing a duplicate or similar group: its files are left out of
+    /// later results while they stay the same. Duplicate groups need their
+    /// content `hash`.
+    pub fn ignore_group(
+        &self,
+        kind: crate::IgnoreKind,
+        paths: &[String],
+        hash: Option<String>,
+        db: &SqliteDatabase,
+    ) -> Result<IgnoredGroupRecord> {
+        Ok(crate::ignore::ignore_group(kind, paths, hash, db)?)
+    }
 
-            // Apply filters if provided
-            if let Some(ref filter_config) = filter {
-                files = filter_config.apply(files);
-            }
+    /// Show an ignored group again; false when `id` is unknown
+    pub fn unignore_group(&self, id: i64, db: &SqliteDatabase) -> Result<bool> {
+        Ok(db.delete_ignored_group(id)?)
+    }
 
-            all_files.extend(files);
-        }
+    /// Ignored groups, newest first
+    pub fn list_ignored_groups(&self, db: &SqliteDatabase) -> Result<Vec<IgnoredGroupRecord>> {
+        Ok(db.get_ignored_groups()?)
+    }
 
-        let mut stats = StorageStats {
-            total_files: all_files.len(),
-            total_size: 0,
-            images: 0,
-            videos: 0,
-            documents: 0,
-            archives: 0,
-            others: 0,
-            empty_files: 0,
-        };
+    /// Tag a file or duplicate group, e.g. "keep" or "review later"; false
+    /// when it already had the tag. Tags are stored lowercase.
+    pub fn add_tag(
+        &self,
+        target: &crate::TagTarget,
+        tag: &str,
+        db: &SqliteDatabase,
+    ) -> Result<bool> {
+        Ok(crate::tags::add_tag(target, tag, db)?)
+    }
 
-        for file in all_files {
-            stats.total_size += file.size;
+    /// Take a tag off; false when the target did not have it
+    pub fn remove_tag(
+        &self,
+        target: &crate::TagTarget,
+        tag: &str,
+        db: &SqliteDatabase,
+    ) -> Result<bool> {
+        Ok(crate::tags::remove_tag(target, tag, db)?)
+    }
 
-            if file.size == 0 {
-                stats.empty_files += 1;
-            }
+    /// Tags of a file or duplicate group, alphabetically
+    pub fn tags_of(&self, target: &crate::TagTarget, db: &SqliteDatabase) -> Result<Vec<String>> {
+        Ok(crate::tags::tags_of(target, db)?)
+    }
 
-            match file.file_type {
-                FileType::Image => stats.images += 1,
-                FileType::Video => stats.videos += 1,
-                FileType::Document => stats.documents += 1,
-                FileType::Archive => stats.archives += 1,
-                FileType::Other => stats.others += 1,
-            }
+    /// Files and groups tagged `tag`, newest first
+    pub fn find_tagged(&self, tag: &str, db: &SqliteDatabase) -> Result<Vec<TagRecord>> {
+        Ok(db.get_tagged(&crate::tags::normalize_tag(tag)?)?)
+    }
+
+    /// Tags in use, alphabetically, with how many files and groups carry
+    /// each
+    pub fn list_tags(&self, db: &SqliteDatabase) -> Result<Vec<TagCount>> {
+        Ok(db.tag_counts()?)
+    }
+
+    /// Run cleanup rules: plan the files each enabled rule matches and, unless
+    /// `dry_run`, apply its action to them. A rule that cannot run (missing
+    /// folder, bad filter) is reported with its error and the others still
+    /// run. `db` records the archives of "archive" rules. Blocking; see
+    /// [`crate::cleanup`].
+    pub fn run_cleanup(
+        &self,
+        rules: &[space_saver_utils::CleanupRule],
+        dry_run: bool,
+        db: &SqliteDatabase,
+    ) -> Result<crate::CleanupReport> {
+        if dry_run {
+            return self.cleanup_rules(rules, dry_run, db);
         }
+        let folders: Vec<PathBuf> = rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .flat_map(|rule| rule.paths.iter().cloned())
+            .collect();
+        self.hooks.around(
+            "cleanup",
+            &folders,
+            || self.cleanup_rules(rules, dry_run, db),
+            |report| match report {
+                Ok(report) => cleanup_outcome(report),
+                Err(_) => crate::HookOutcome {
+                    failed: 1,
+                    ..Default::default()
+                },
+            },
+        )?
+    }
 
-        Ok(stats)
+    fn cleanup_rules(
+        &self,
+        rules: &[space_saver_utils::CleanupRule],
+        dry_run: bool,
+        db: &SqliteDatabase,
+    ) -> Result<crate::CleanupReport> {
+        let manager = space_saver_core::compress_plugins::global_plugin_manager();
+        let manager = manager.read().ok();
+        let rules: Vec<_> = rules.iter().filter(|rule| rule.enabled).collect();
+        let total = rules.len();
+        let mut reports = Vec::new();
+        for (index, rule) in rules.into_iter().enumerate() {
+            self.check_cancelled()?;
+            let planned = rule.validate().and_then(|()| {
+                let filter = crate::cleanup::rule_filter(rule);
+                let mut files = Vec::new();
+                for path in &rule.paths {
+                    if !path.exists() {
+                        return Err(Error::NotFound(format!(
+                            "Folder not found: {}",
+                            path.display()
+                        ))
+                        .into());
+                    }
+                    files.extend(self.scan_filtered(path, Some(&filter))?);
+                }
+                Ok(files)
+            });
+            reports.push(match planned {
+                Ok(files) => crate::cleanup::apply_rule(
+                    rule,
+                    files,
+                    dry_run,
+                    &self.protected,
+                    manager.as_deref(),
+                    db,
+                ),
+                Err(e) => crate::cleanup::failed_rule(rule, &e),
+            });
+            self.report_progress(index + 1, total, || format!("Ran rule {}", rule.name));
+        }
+        Ok(crate::CleanupReport::new(dry_run, reports))
     }
 
-    /// Get storage statistics for a single directory (delegates to get_storage_stats_for_paths)
-    pub async fn get_storage_stats(
+    /// Move a directory into cold storage: archive it to `dest`, verify the
+    /// archive, record the mapping in `db` and delete the original. Protected
+    /// directories are refused. Blocking; see
+    /// [`crate::cold_storage::archive_directory`].
+    pub fn archive_directory(
         &self,
-        path: PathBuf,
-        filter: Option<FilterConfig>,
-    ) -> Result<StorageStats> {
-        self.get_storage_stats_for_paths(vec![path], filter).await
+        source: &Path,
+        dest: &Path,
+        format: ArchiveFormat,
+        password: Option<String>,
+        db: &SqliteDatabase,
+    ) -> Result<ArchiveRecord> {
+        let _archive = self.partial.track(dest);
+        let _scratch = self
+            .partial
+            .track(&crate::cold_storage::verify_dir_for(dest));
+        Ok(crate::cold_storage::archive_directory(
+            source,
+            dest,
+            format,
+            password,
+            &self.protected,
+            db,
+        )?)
+    }
+}
+
+/// What a cleanup came to, for its after hooks: rules that could not run
+/// count as failed along with the files their action failed on
+fn cleanup_outcome(report: &crate::CleanupReport) -> crate::HookOutcome {
+    let files = report.rules.iter().flat_map(|rule| &rule.files);
+    crate::HookOutcome {
+        succeeded: files
+            .clone()
+            .filter(|file| file.error.is_none() && !file.protected)
+            .count(),
+        failed: files.filter(|file| file.error.is_some()).count()
+            + report
+                .rules
+                .iter()
+                .filter(|rule| rule.error.is_some())
+                .count(),
+        freed: report.freed,
+    }
+}
+
+/// `file` as a [`StaleFile`], unless it was modified or (where the file
+/// system records it) accessed since `cutoff`
+fn stale_file(file: &FileInfo, cutoff: i64) -> Option<StaleFile> {
+    let accessed = last_accessed(&file.path);
+    let last_used = accessed.map_or(file.modified, |a| a.max(file.modified));
+    (last_used < cutoff).then(|| StaleFile {
+        path: file.path.to_string_lossy().to_string(),
+        size: file.size,
+        modified: file.modified,
+        accessed,
+    })
+}
+
+/// Largest first, then by path
+fn sort_stale_files(files: &mut [StaleFile]) {
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+}
+
+/// Access time of `path` as a Unix timestamp, if the platform records one
+fn last_accessed(path: &Path) -> Option<i64> {
+    let accessed = std::fs::metadata(path).ok()?.accessed().ok()?;
+    let secs = accessed
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(secs as i64)
+}
+
+/// A directory being totalled up for [`ServiceApi::get_treemap`]
+#[derive(Default)]
+struct TreeBuilder {
+    size: u64,
+    file_count: usize,
+    types: std::collections::HashMap<String, (usize, u64)>,
+    children: std::collections::HashMap<std::ffi::OsString, TreeBuilder>,
+}
+
+impl TreeBuilder {
+    fn add(&mut self, category: &str, size: u64) {
+        self.size += size;
+        self.file_count += 1;
+        let entry = self.types.entry(category.to_string()).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+    }
+
+    fn build(self, path: PathBuf) -> TreemapNode {
+        let mut types: Vec<CategoryTotal> = self
+            .types
+            .into_iter()
+            .map(|(category, (file_count, total_size))| CategoryTotal {
+                category,
+                file_count,
+                total_size,
+            })
+            .collect();
+        types.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.category.cmp(&b.category))
+        });
+        let mut children: Vec<TreemapNode> = self
+            .children
+            .into_iter()
+            .map(|(name, child)| child.build(path.join(name)))
+            .collect();
+        children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        TreemapNode {
+            name: path
+                .file_name()
+                .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy())
+                .to_string(),
+            path: path.to_string_lossy().to_string(),
+            size: self.size,
+            file_count: self.file_count,
+            types,
+            children,
+        }
     }
 }
 
@@ -518,6 +1974,35 @@ pub struct DuplicateGroup {
     pub wasted_space: u64,
 }
 
+/// A file a batch operation could not process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub error: String,
+    pub code: ErrorCode,
+}
+
+impl FileError {
+    pub fn new(path: &Path, error: Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            error: error.to_string(),
+            code: error.code(),
+        }
+    }
+}
+
+/// A file with its content hash
+type HashedFile = (String, FileInfo);
+
+/// Duplicate groups found, with the files that could not be hashed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    /// Same-size candidates that could not be read, so could not be compared
+    pub unreadable: Vec<FileError>,
+}
+
 /// Kind of media a similar-group is made of. A group is homogeneous: all its
 /// files are the same kind, so the frontend can pick the right preview widget
 /// and "keep best" heuristic per group.
@@ -592,6 +2077,92 @@ pub struct BrokenFile {
     pub suggested_extension: Option<String>,
 }
 
+/// A directory in a treemap: the total size of everything beneath it, split
+/// by file type, and its subdirectories largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreemapNode {
+    /// Last path component (the whole path for a root without one)
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// Files anywhere in the subtree
+    pub file_count: usize,
+    /// Size per file category ("Image", "Video", ...), largest first
+    pub types: Vec<CategoryTotal>,
+    /// Empty beyond the requested depth
+    pub children: Vec<TreemapNode>,
+}
+
+/// A directory and the total size of everything beneath it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectorySize {
+    pub path: String,
+    pub total_size: u64,
+    /// Files anywhere in the subtree
+    pub file_count: usize,
+}
+
+/// A file untouched since the cutoff of [`ServiceApi::find_stale_files`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: i64,
+    /// Last access time; `None` where the platform does not report it
+    pub accessed: Option<i64>,
+}
+
+/// Stale files sharing a parent directory, largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleDirectory {
+    pub path: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    pub files: Vec<StaleFile>,
+}
+
+/// Files untouched for a number of days, grouped by directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleFilesReport {
+    /// Unix timestamp the files were last used before
+    pub cutoff: i64,
+    pub file_count: usize,
+    /// Bytes that archiving or deleting every stale file would free
+    pub total_size: u64,
+    /// Directories with stale files, largest total first
+    pub directories: Vec<StaleDirectory>,
+}
+
+/// Stale files beneath one entry of the folder
+/// [`ServiceApi::find_stale_folders`] scanned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleFolder {
+    pub path: String,
+    /// The files directly in the scanned folder rather than in a folder
+    /// beneath it
+    pub loose: bool,
+    pub file_count: usize,
+    /// Bytes that archiving or deleting the stale files would free
+    pub total_size: u64,
+    /// Files in the folder used since the cutoff
+    pub recent_count: usize,
+    /// Largest first
+    pub files: Vec<StaleFile>,
+}
+
+/// Files untouched since a cutoff, grouped by top-level folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleFoldersReport {
+    /// The scanned folder
+    pub root: String,
+    /// Unix timestamp the files were last used before
+    pub cutoff: i64,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Folders with stale files, largest total first
+    pub folders: Vec<StaleFolder>,
+}
+
 /// Storage statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageStats {
@@ -605,6 +2176,42 @@ pub struct StorageStats {
     pub empty_files: usize,
 }
 
+impl StorageStats {
+    /// Counts and sizes of `files` by type
+    pub fn from_files(files: &[FileInfo]) -> Self {
+        use space_saver_core::scanner::FileType;
+
+        let mut stats = StorageStats {
+            total_files: files.len(),
+            total_size: 0,
+            images: 0,
+            videos: 0,
+            documents: 0,
+            archives: 0,
+            others: 0,
+            empty_files: 0,
+        };
+
+        for file in files {
+            stats.total_size += file.size;
+
+            if file.size == 0 {
+                stats.empty_files += 1;
+            }
+
+            match file.file_type {
+                FileType::Image => stats.images += 1,
+                FileType::Video => stats.videos += 1,
+                FileType::Document => stats.documents += 1,
+                FileType::Archive => stats.archives += 1,
+                FileType::Other => stats.others += 1,
+            }
+        }
+
+        stats
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -652,20 +2259,208 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_find_duplicates_excludes_empty_files() {
+    async fn test_overlapping_paths_count_each_file_once() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.bin"), b"same content").unwrap();
+        fs::write(dir.path().join("b.bin"), b"other content").unwrap();
+        // A folder and the files in it, as `find` lists them
+        let paths = vec![
+            dir.path().to_path_buf(),
+            dir.path().join("a.bin"),
+            dir.path().join("b.bin"),
+        ];
+
+        let api = ServiceApi::new();
+        let groups = api
+            .find_duplicates_in_paths(paths.clone(), None)
+            .await
+            .unwrap();
+        assert!(groups.is_empty(), "a file is not a copy of itself");
+        let stats = api.get_storage_stats_for_paths(paths, None).await.unwrap();
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_size, 25);
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_excludes_empty_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("empty1.txt"), b"").unwrap();
+        fs::write(dir.path().join("empty2.txt"), b"").unwrap();
+
+        let api = ServiceApi::new();
+        let groups = api
+            .find_duplicates_in_paths(vec![dir.path().to_path_buf()], None)
+            .await
+            .unwrap();
+        assert!(
+            groups.is_empty(),
+            "empty files must not form a duplicate group"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_candidates_are_reported_not_dropped() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "gone.txt"] {
+            fs::write(dir.path().join(name), b"same").unwrap();
+        }
+        let api = ServiceApi::new();
+        let files = api.scanner.scan(dir.path()).unwrap();
+        fs::remove_file(dir.path().join("gone.txt")).unwrap();
+
+        let (hashed, unreadable) = api.hash_files(files).unwrap();
+        assert_eq!(hashed.len(), 2);
+        assert_eq!(unreadable.len(), 1);
+        assert_eq!(unreadable[0].path, dir.path().join("gone.txt"));
+        assert_eq!(unreadable[0].code, ErrorCode::NotFound);
+
+        let report = api
+            .find_duplicate_report(vec![dir.path().to_path_buf()], None)
+            .await
+            .unwrap();
+        assert_eq!(report.groups.len(), 1);
+        assert!(report.unreadable.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_under_io_policy() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.bin"), vec![1u8; 48 * 1024]).unwrap();
+        fs::write(dir.path().join("b.bin"), vec![1u8; 48 * 1024]).unwrap();
+
+        // 96 KiB at 64 KiB/s: a second of burst, then about half a second
+        let io = space_saver_core::IoPolicy::new(
+            Some(space_saver_core::IoThrottle::new(64 * 1024)),
+            true,
+        );
+        let started = std::time::Instant::now();
+        let groups = ServiceApi::new()
+            .with_io_policy(io)
+            .find_duplicates_in_paths(vec![dir.path().to_path_buf()], None)
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert!(started.elapsed() >= std::time::Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_leaves_out_ignored_groups() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), b"kept twice").unwrap();
+        fs::write(dir.path().join("b.txt"), b"kept twice").unwrap();
+        let paths = vec![dir.path().to_path_buf()];
+        let groups = ServiceApi::new()
+            .find_duplicates_in_paths(paths.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+
+        let db = space_saver_db::SqliteDatabase::in_memory().unwrap();
+        let files: Vec<String> = groups[0]
+            .files
+            .iter()
+            .map(|f| f.path.to_string_lossy().to_string())
+            .collect();
+        crate::ignore::ignore_group(
+            crate::IgnoreKind::Duplicate,
+            &files,
+            Some(groups[0].hash.clone()),
+            &db,
+        )
+        .unwrap();
+        let api = ServiceApi::new().with_ignore_list(crate::IgnoreList::load(&db).unwrap());
+        assert!(api
+            .find_duplicates_in_paths(paths, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_attached_database_keeps_scans_files_and_duplicates() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), b"same text").unwrap();
+        fs::write(dir.path().join("b.txt"), b"same text").unwrap();
+        fs::write(dir.path().join("c.jpg"), b"a photo").unwrap();
+        let paths = vec![dir.path().to_path_buf()];
+        let db = Database::in_memory().unwrap();
+        let api = ServiceApi::new().with_database(db);
+
+        api.scan_directories(paths.clone(), None).await.unwrap();
+        // Filtered scans keep their files but stay out of the history
+        let filter = FilterConfig {
+            extensions: Some(vec!["jpg".to_string()]),
+            ..Default::default()
+        };
+        api.scan_directories(paths.clone(), Some(filter))
+            .await
+            .unwrap();
+        let scans = api.recent_scans(10).unwrap();
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].file_count, 3);
+        let photo = api.recorded_file(&dir.path().join("c.jpg")).unwrap();
+        assert_eq!(photo.unwrap().file_type, "Image");
+
+        api.find_duplicates_in_paths(paths, None).await.unwrap();
+        let duplicates = api.recorded_duplicates().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].file_count, 2);
+        let hashed = api.recorded_file(&dir.path().join("a.txt")).unwrap();
+        assert_eq!(hashed.unwrap().hash, Some(duplicates[0].hash.clone()));
+
+        let under = FileQuery::Under {
+            path: dir.path().to_string_lossy().to_string(),
+        };
+        let files = api.recorded_files(&under, Page::first(2)).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].path.ends_with("a.txt"));
+        let images = FileQuery::Type {
+            file_type: "Image".to_string(),
+        };
+        assert_eq!(
+            api.recorded_files(&images, Page::first(10)).unwrap().len(),
+            1
+        );
+
+        // Without a database there is nothing to retrieve
+        assert!(ServiceApi::new().recent_scans(10).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tag_filters_pick_out_tagged_files() {
         let dir = TempDir::new().unwrap();
-        fs::write(dir.path().join("empty1.txt"), b"").unwrap();
-        fs::write(dir.path().join("empty2.txt"), b"").unwrap();
+        fs::write(dir.path().join("a.jpg"), b"one").unwrap();
+        fs::write(dir.path().join("b.jpg"), b"two").unwrap();
+        let db = Database::in_memory().unwrap();
+        let api = ServiceApi::new().with_database(db.clone());
+        let target = crate::TagTarget::File {
+            path: dir.path().join("a.jpg").to_string_lossy().to_string(),
+        };
+        assert!(api.add_tag(&target, "Keep", &db.get().unwrap()).unwrap());
 
-        let api = ServiceApi::new();
-        let groups = api
-            .find_duplicates_in_paths(vec![dir.path().to_path_buf()], None)
+        let tagged = |query: &str| FilterConfig {
+            query: Some(query.to_string()),
+            ..Default::default()
+        };
+        let kept = api
+            .scan_directory(dir.path().to_path_buf(), Some(tagged("tag:keep")))
             .await
             .unwrap();
-        assert!(
-            groups.is_empty(),
-            "empty files must not form a duplicate group"
-        );
+        assert_eq!(kept.files.len(), 1);
+        assert!(kept.files[0].path.ends_with("a.jpg"));
+        let rest = api
+            .scan_directory(dir.path().to_path_buf(), Some(tagged("not tag:keep")))
+            .await
+            .unwrap();
+        assert!(rest.files[0].path.ends_with("b.jpg"));
+        assert_eq!(api.list_tags(&db.get().unwrap()).unwrap()[0].files, 1);
+
+        // Without a database tags cannot be looked up
+        let err = ServiceApi::new()
+            .scan_directory(dir.path().to_path_buf(), Some(tagged("tag:keep")))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("need the app database"));
     }
 
     #[tokio::test]
@@ -737,7 +2532,22 @@ mod tests {
             max_size: None,
             extensions: Some(vec!["log".to_string()]),
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
         let result = api
             .find_empty_in_paths(vec![dir.path().to_path_buf()], Some(filter))
@@ -766,6 +2576,9 @@ mod tests {
             modified: 0,
             file_type: FileType::Other,
             hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
         };
 
         let filter = FilterConfig {
@@ -773,15 +2586,32 @@ mod tests {
             max_size: None,
             extensions: None,
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: Some(vec!["/data/node_modules".to_string()]),
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
 
-        let kept = filter.apply(vec![
-            make("/data/node_modules/dep/index.js"),
-            make("/data/src/main.rs"),
-            // A sibling sharing a name prefix must not be excluded
-            make("/data/node_modules_backup/x.js"),
-        ]);
+        let kept = filter
+            .apply(vec![
+                make("/data/node_modules/dep/index.js"),
+                make("/data/src/main.rs"),
+                // A sibling sharing a name prefix must not be excluded
+                make("/data/node_modules_backup/x.js"),
+            ])
+            .unwrap();
 
         let paths: Vec<String> = kept
             .iter()
@@ -800,7 +2630,410 @@ mod tests {
             exclude_paths: Some(vec![]),
             ..Default::default()
         };
-        assert_eq!(noop.apply(vec![make("/data/a.txt")]).len(), 1);
+        assert_eq!(noop.apply(vec![make("/data/a.txt")]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_filter_config_apply_query() {
+        use space_saver_core::scanner::{FileInfo, FileType};
+        use std::path::PathBuf;
+
+        let make = |p: &str, size: u64| FileInfo {
+            path: PathBuf::from(p),
+            size,
+            modified: 0,
+            file_type: FileType::Other,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        };
+
+        let filter = FilterConfig {
+            exclude_patterns: Some(vec!["sample".to_string()]),
+            query: Some("size>100MB and ext in (mp4,mkv) and modified<2023-01-01".to_string()),
+            ..Default::default()
+        };
+        let kept = filter
+            .apply(vec![
+                make("/v/movie.mkv", 200 << 20),
+                make("/v/sample.mkv", 200 << 20),
+                make("/v/clip.mp4", 1 << 20),
+                make("/v/disk.iso", 200 << 20),
+            ])
+            .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].path, PathBuf::from("/v/movie.mkv"));
+
+        let invalid = FilterConfig {
+            query: Some("size >> 1".to_string()),
+            ..Default::default()
+        };
+        let err = invalid.apply(vec![]).unwrap_err();
+        assert!(err.to_string().contains("Invalid filter expression"));
+    }
+
+    #[test]
+    fn test_filter_config_apply_exclusions() {
+        use space_saver_core::scanner::{FileInfo, FileType};
+        use std::path::PathBuf;
+
+        let make = |p: &str| FileInfo {
+            path: PathBuf::from(p),
+            size: 100,
+            modified: 0,
+            file_type: FileType::Other,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        };
+
+        let filter = FilterConfig {
+            extensions: Some(vec!["docx".to_string(), "tmp".to_string()]),
+            exclude_extensions: Some(vec!["TMP".to_string()]),
+            exclude_patterns: Some(vec!["~$".to_string()]),
+            exclude_paths: Some(vec!["/data/old".to_string()]),
+            ..Default::default()
+        };
+        let kept = filter
+            .apply(vec![
+                make("/data/report.docx"),
+                make("/data/~$report.docx"),
+                make("/data/report.tmp"),
+                make("/data/old/report.docx"),
+                make("/data/notes.txt"),
+            ])
+            .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].path, PathBuf::from("/data/report.docx"));
+    }
+
+    #[tokio::test]
+    async fn test_storage_stats_date_filters() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = TempDir::new().unwrap();
+        let two_years = Duration::from_secs(2 * 365 * 86_400);
+        for (name, age) in [("old.txt", two_years), ("fresh.txt", Duration::ZERO)] {
+            let path = dir.path().join(name);
+            fs::write(&path, b"data").unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() - age).unwrap();
+        }
+
+        let api = ServiceApi::new();
+        let stats_with =
+            |filter: FilterConfig| api.get_storage_stats(dir.path().to_path_buf(), Some(filter));
+        let older = stats_with(FilterConfig {
+            older_than: Some("1 year".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(older.total_files, 1);
+        let newer = stats_with(FilterConfig {
+            newer_than: Some("30d".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(newer.total_files, 1);
+        let untouched_since_2020 = stats_with(FilterConfig {
+            older_than: Some("2020-01-01".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(untouched_since_2020.total_files, 0);
+
+        let err = stats_with(FilterConfig {
+            newer_than: Some("last tuesday".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid date or duration"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_directories_file_regex() {
+        let dir = TempDir::new().unwrap();
+        for name in ["IMG_0001.jpg", "IMG_0002.jpeg", "IMG_3.jpg", "notes.txt"] {
+            fs::write(dir.path().join(name), b"x").unwrap();
+        }
+
+        let api = ServiceApi::new();
+        let filter = FilterConfig {
+            file_regex: Some(r"^IMG_\d{4}\.(jpg|jpeg)$".to_string()),
+            ..Default::default()
+        };
+        let result = api
+            .scan_directory(dir.path().to_path_buf(), Some(filter))
+            .await
+            .unwrap();
+        let mut names: Vec<String> = result
+            .files
+            .iter()
+            .map(|f| f.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["IMG_0001.jpg", "IMG_0002.jpeg"]);
+
+        let invalid = FilterConfig {
+            file_regex: Some("[".to_string()),
+            ..Default::default()
+        };
+        let err = api
+            .scan_directory(dir.path().to_path_buf(), Some(invalid))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid file name regex"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_directories_globs() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("Downloads/camera")).unwrap();
+        fs::create_dir_all(dir.path().join("Documents")).unwrap();
+        for name in [
+            "Downloads/setup.exe",
+            "Downloads/camera/shot.raw",
+            "Downloads/camera/shot.jpg",
+            "Downloads/movie.part",
+            "Documents/setup.exe",
+        ] {
+            fs::write(dir.path().join(name), b"x").unwrap();
+        }
+
+        let api = ServiceApi::new();
+        let filter = FilterConfig {
+            include_globs: Some(vec!["**/Downloads/**".to_string(), "!**/*.raw".to_string()]),
+            exclude_globs: Some(vec!["*.part".to_string()]),
+            ..Default::default()
+        };
+        let result = api
+            .scan_directory(dir.path().to_path_buf(), Some(filter))
+            .await
+            .unwrap();
+        let mut names: Vec<String> = result
+            .files
+            .iter()
+            .map(|f| {
+                f.path
+                    .strip_prefix(dir.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["Downloads/camera/shot.jpg", "Downloads/setup.exe"]
+        );
+
+        let invalid = FilterConfig {
+            exclude_globs: Some(vec!["{unclosed".to_string()]),
+            ..Default::default()
+        };
+        let err = api
+            .scan_directory(dir.path().to_path_buf(), Some(invalid))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid glob pattern"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_scan_directories_owner_and_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        for (name, mode) in [("private.txt", 0o600), ("shared.txt", 0o666)] {
+            let path = dir.path().join(name);
+            fs::write(&path, b"x").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let api = ServiceApi::new();
+        let scan =
+            |filter: FilterConfig| api.scan_directory(dir.path().to_path_buf(), Some(filter));
+
+        let mine = scan(FilterConfig {
+            current_user_only: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(mine.files.len(), 2);
+
+        let uid = space_saver_core::scanner::current_uid().unwrap();
+        let someone_else = scan(FilterConfig {
+            owner_uid: Some(uid.wrapping_add(1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(someone_else.files.is_empty());
+
+        let world_writable = scan(FilterConfig {
+            permissions: Some("002".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(world_writable.files.len(), 1);
+        assert!(world_writable.files[0].path.ends_with("shared.txt"));
+
+        let err = scan(FilterConfig {
+            permissions: Some("rwx".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid permission bits"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_job_stops_scan() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        let api = ServiceApi::new();
+        let handle = api
+            .jobs
+            .register(crate::TaskType::Scan(vec![dir.path().to_path_buf()]));
+        let api = api.with_cancel(handle.token());
+
+        assert!(api.cancel_job(handle.id()).unwrap());
+        let err = api
+            .scan_directories(vec![dir.path().to_path_buf()], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), space_saver_utils::ErrorCode::Cancelled);
+
+        assert_eq!(api.list_jobs().len(), 1);
+        assert_eq!(
+            api.job_status(handle.id() + 1).unwrap_err().code(),
+            space_saver_utils::ErrorCode::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_reports_progress() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(dir.path().join(name), b"same content").unwrap();
+        }
+        let (tx, mut rx) = mpsc::channel(16);
+        let api = ServiceApi::new().with_progress(tx);
+
+        let groups = api
+            .find_duplicates(dir.path().to_path_buf(), None)
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        drop(api);
+
+        let mut updates = Vec::new();
+        while let Some(update) = rx.recv().await {
+            updates.push(update);
+        }
+        // One report for the scanned path, then one per hashed file
+        assert_eq!(updates.len(), 4);
+        assert!(matches!(
+            &updates[0],
+            ProgressUpdate::Progress { current: 1, total: 1, message, .. } if message.starts_with("Scanned")
+        ));
+        // Hashing runs in parallel, so reports may arrive out of order; the
+        // last one has read every byte
+        assert!(updates.iter().any(|u| matches!(
+            u,
+            ProgressUpdate::Progress {
+                current: 3,
+                total: 3,
+                bytes: 36,
+                total_bytes: 36,
+                ..
+            }
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_scan_directories_hidden_files() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".cache")).unwrap();
+        fs::write(dir.path().join(".cache/blob"), b"x").unwrap();
+        fs::write(dir.path().join(".bashrc"), b"x").unwrap();
+        fs::write(dir.path().join("notes.txt"), b"x").unwrap();
+        let api = ServiceApi::new();
+
+        // Hidden entries are skipped by default, with or without a filter
+        let default = api
+            .scan_directory(dir.path().to_path_buf(), None)
+            .await
+            .unwrap();
+        assert_eq!(default.file_count, 1);
+        let stats = api
+            .get_storage_stats_for_paths(
+                vec![dir.path().to_path_buf()],
+                Some(FilterConfig::default()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(stats.total_files, 1);
+
+        let with_hidden = FilterConfig {
+            include_hidden: Some(true),
+            ..Default::default()
+        };
+        let all = api
+            .scan_directory(dir.path().to_path_buf(), Some(with_hidden))
+            .await
+            .unwrap();
+        assert_eq!(all.file_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_scan_directories_max_depth_and_links() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("top.txt"), b"x").unwrap();
+        fs::write(dir.path().join("a/mid.txt"), b"x").unwrap();
+        fs::write(dir.path().join("a/b/deep.txt"), b"x").unwrap();
+        let api = ServiceApi::new();
+
+        let shallow = FilterConfig {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let result = api
+            .scan_directory(dir.path().to_path_buf(), Some(shallow))
+            .await
+            .unwrap();
+        assert_eq!(result.file_count, 1);
+
+        #[cfg(unix)]
+        {
+            let outside = TempDir::new().unwrap();
+            fs::write(outside.path().join("linked.txt"), b"x").unwrap();
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+            let count = |follow_links| {
+                let filter = FilterConfig {
+                    follow_links,
+                    ..Default::default()
+                };
+                let path = dir.path().to_path_buf();
+                let api = &api;
+                async move {
+                    api.scan_directory(path, Some(filter))
+                        .await
+                        .unwrap()
+                        .file_count
+                }
+            };
+            assert_eq!(count(None).await, 3);
+            assert_eq!(count(Some(true)).await, 4);
+        }
     }
 
     #[tokio::test]
@@ -926,7 +3159,22 @@ mod tests {
             max_size: None,
             extensions: None,
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
 
         let duplicates = api
@@ -981,7 +3229,22 @@ mod tests {
             max_size: Some(1_000),
             extensions: None,
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
 
         let duplicates = api
@@ -1032,7 +3295,22 @@ mod tests {
             max_size: None,
             extensions: Some(vec!["txt".to_string()]),
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
 
         let duplicates = api
@@ -1086,7 +3364,22 @@ mod tests {
             max_size: None,
             extensions: None,
             file_pattern: Some("report".to_string()),
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
 
         let duplicates = api
@@ -1149,7 +3442,22 @@ mod tests {
             max_size: None,
             extensions: Some(vec!["txt".to_string()]),
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
 
         let duplicates = api
@@ -1269,7 +3577,22 @@ mod tests {
             max_size: None,
             extensions: Some(vec!["jpg".to_string()]),
             file_pattern: None,
+            file_regex: None,
+            include_globs: None,
+            exclude_globs: None,
+            newer_than: None,
+            older_than: None,
+            exclude_extensions: None,
+            exclude_patterns: None,
             exclude_paths: None,
+            query: None,
+            current_user_only: None,
+            owner_uid: None,
+            owner_gid: None,
+            permissions: None,
+            include_hidden: None,
+            max_depth: None,
+            follow_links: None,
         };
         let broken = api
             .find_broken_files_in_paths(vec![dir.path().to_path_buf()], Some(filter))
@@ -1321,6 +3644,45 @@ mod tests {
         img.save(path).unwrap();
     }
 
+    #[tokio::test]
+    async fn find_similar_media_reuses_scores_until_a_file_changes() {
+        let dir = TempDir::new().unwrap();
+        save_gradient_png(&dir.path().join("a.png"), 32, 32);
+        std::fs::copy(dir.path().join("a.png"), dir.path().join("b.png")).unwrap();
+        let db = Database::in_memory().unwrap();
+        let api = ServiceApi::new().with_database(db.clone());
+        let find = || {
+            api.find_similar_media_in_paths(
+                vec![dir.path().to_path_buf()],
+                0.4,
+                vec![MediaKind::Image],
+                None,
+            )
+        };
+
+        assert_eq!(find().await.unwrap()[0].similarity_score, 1.0);
+        let a = dir.path().join("a.png").to_string_lossy().to_string();
+        let mut recorded = db.get().unwrap().get_similarities_of(&a).unwrap();
+        assert_eq!(recorded.len(), 1);
+
+        // A recorded score is taken as it is while both files are unchanged
+        recorded[0].similarity_score = 0.5;
+        db.get().unwrap().upsert_similarities(&recorded).unwrap();
+        assert_eq!(find().await.unwrap()[0].similarity_score, 0.5);
+
+        std::fs::File::options()
+            .write(true)
+            .open(dir.path().join("b.png"))
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(find().await.unwrap()[0].similarity_score, 1.0);
+        assert_eq!(
+            db.get().unwrap().get_similarities_of(&a).unwrap()[0].similarity_score,
+            1.0
+        );
+    }
+
     #[tokio::test]
     async fn find_similar_media_groups_identical_images_with_dimensions() {
         let dir = TempDir::new().unwrap();
@@ -1440,4 +3802,176 @@ mod tests {
             .unwrap();
         assert!(groups.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_find_stale_files_uses_modified_and_accessed_times() {
+        use std::fs::FileTimes;
+        use std::time::{Duration, SystemTime};
+
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("old")).unwrap();
+        let year = Duration::from_secs(365 * 86_400);
+        let long_ago = SystemTime::now() - 2 * year;
+        let times = [
+            // Untouched for two years
+            ("old/a.bin", 300, long_ago, long_ago),
+            ("old/b.bin", 200, long_ago, long_ago),
+            ("c.bin", 50, long_ago, long_ago),
+            // Old content, but read recently
+            ("read.bin", 400, SystemTime::now(), long_ago),
+            ("fresh.bin", 500, SystemTime::now(), SystemTime::now()),
+        ];
+        for (name, size, accessed, modified) in times {
+            let path = dir.path().join(name);
+            fs::write(&path, vec![0u8; size]).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_times(
+                FileTimes::new()
+                    .set_accessed(accessed)
+                    .set_modified(modified),
+            )
+            .unwrap();
+        }
+
+        let report = ServiceApi::new()
+            .find_stale_files(vec![dir.path().to_path_buf()], 365, None)
+            .await
+            .unwrap();
+        assert_eq!(report.file_count, 3);
+        assert_eq!(report.total_size, 550);
+        assert_eq!(report.directories.len(), 2);
+        let largest = &report.directories[0];
+        assert!(largest.path.ends_with("old"));
+        assert_eq!(largest.total_size, 500);
+        assert!(largest.files[0].path.ends_with("a.bin"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_folders_group_by_top_level_entry() {
+        use std::fs::FileTimes;
+        use std::time::{Duration, SystemTime};
+
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("photos/2019/raw")).unwrap();
+        fs::create_dir(dir.path().join("work")).unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(2 * 365 * 86_400);
+        for (name, size, old) in [
+            ("photos/2019/a.jpg", 300, true),
+            ("photos/2019/raw/b.cr2", 700, true),
+            ("work/old.doc", 100, true),
+            ("work/current.doc", 50, false),
+            ("notes.txt", 20, true),
+        ] {
+            let path = dir.path().join(name);
+            fs::write(&path, vec![0u8; size]).unwrap();
+            if old {
+                let file = fs::File::options().write(true).open(&path).unwrap();
+                file.set_times(
+                    FileTimes::new()
+                        .set_accessed(long_ago)
+                        .set_modified(long_ago),
+                )
+                .unwrap();
+            }
+        }
+
+        let cutoff = now() - 365 * 86_400;
+        let report = ServiceApi::new()
+            .find_stale_folders(dir.path(), cutoff, None)
+            .await
+            .unwrap();
+        assert_eq!(report.file_count, 4);
+        assert_eq!(report.total_size, 1120);
+        let folders: Vec<(&str, bool, u64, usize)> = report
+            .folders
+            .iter()
+            .map(|f| {
+                let name = Path::new(&f.path).file_name().unwrap().to_str().unwrap();
+                (name, f.loose, f.total_size, f.recent_count)
+            })
+            .collect();
+        let root_name = dir.path().file_name().unwrap().to_str().unwrap();
+        assert_eq!(
+            folders,
+            [
+                ("photos", false, 1000, 0),
+                ("work", false, 100, 1),
+                (root_name, true, 20, 0)
+            ]
+        );
+        assert!(report.folders[0].files[0].path.ends_with("b.cr2"));
+    }
+
+    #[tokio::test]
+    async fn test_largest_files_and_directories() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("media/videos")).unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        for (name, size) in [
+            ("media/videos/a.mp4", 500),
+            ("media/b.jpg", 200),
+            ("docs/c.txt", 300),
+            ("d.bin", 100),
+        ] {
+            fs::write(dir.path().join(name), vec![0u8; size]).unwrap();
+        }
+        let api = ServiceApi::new();
+        let paths = vec![dir.path().to_path_buf()];
+
+        let files = api.get_largest_files(paths.clone(), 2, None).await.unwrap();
+        let sizes: Vec<u64> = files.iter().map(|f| f.size).collect();
+        assert_eq!(sizes, vec![500, 300]);
+
+        let dirs = api.get_largest_directories(paths, 10).await.unwrap();
+        let summary: Vec<(String, u64, usize)> = dirs
+            .iter()
+            .map(|d| {
+                let relative = Path::new(&d.path).strip_prefix(dir.path()).unwrap();
+                (relative.display().to_string(), d.total_size, d.file_count)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("media".to_string(), 700, 2),
+                (Path::new("media/videos").display().to_string(), 500, 1),
+                ("docs".to_string(), 300, 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_treemap_nests_directories_to_depth() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("media/photos/2024")).unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        for (name, size) in [
+            ("media/photos/2024/a.jpg", 400),
+            ("media/clip.mp4", 300),
+            ("docs/b.txt", 100),
+            ("c.bin", 50),
+        ] {
+            fs::write(dir.path().join(name), vec![0u8; size]).unwrap();
+        }
+
+        let trees = ServiceApi::new()
+            .get_treemap(vec![dir.path().to_path_buf()], 2)
+            .await
+            .unwrap();
+        assert_eq!(trees.len(), 1);
+        let root = &trees[0];
+        assert_eq!((root.size, root.file_count), (850, 4));
+        let names: Vec<&str> = root.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["media", "docs"]);
+
+        let media = &root.children[0];
+        assert_eq!(media.size, 700);
+        assert_eq!(media.types[0].category, "Image");
+        assert_eq!(media.types[0].total_size, 400);
+        // "2024" is deeper than requested, so "photos" holds its files
+        let photos = &media.children[0];
+        assert_eq!(photos.name, "photos");
+        assert_eq!(photos.size, 400);
+        assert!(photos.children.is_empty());
+    }
 }
@@ -0,0 +1,546 @@
+//! Application caches and build output: space that is rebuilt on demand.
+//!
+//! Browsers, package managers and build tools keep caches that can be
+//! deleted at any time and are filled again when needed. A
+//! [`CacheLocator`] knows where the usual ones live on each OS (browser
+//! caches, npm/yarn, pip, the Cargo registry, thumbnail caches) and finds
+//! `node_modules` and Rust `target` folders beneath given roots, taking
+//! only those sitting next to the `package.json` or `Cargo.toml` that
+//! rebuilds them. Docker's storage is left to Docker: what its layer
+//! directories belong to is only known to the daemon (build cache snapshots
+//! are referenced from its BuildKit database alone), so `docker system
+//! prune` and `docker builder prune` are the way to reclaim it.
+//!
+//! Cleaning removes whole locations, and only ones that still look like
+//! what was reported: a real directory (not a link), still next to its
+//! manifest, not protected and not holding a protected path.
+
+use crate::api::FileError;
+use crate::file_ops::{DeleteMode, DeleteResult};
+use crate::plan::{ActionPlan, PlannedAction};
+use crate::protection::ProtectedPaths;
+use serde::{Deserialize, Serialize};
+use space_saver_utils::Error;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// A kind of reclaimable location
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheCategory {
+    Browser,
+    Npm,
+    Pip,
+    Cargo,
+    NodeModules,
+    Target,
+    Thumbnails,
+}
+
+impl CacheCategory {
+    pub const ALL: [CacheCategory; 7] = [
+        CacheCategory::Browser,
+        CacheCategory::Npm,
+        CacheCategory::Pip,
+        CacheCategory::Cargo,
+        CacheCategory::NodeModules,
+        CacheCategory::Target,
+        CacheCategory::Thumbnails,
+    ];
+
+    /// Name used on the command line and in JSON, e.g. "node_modules"
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheCategory::Browser => "browser",
+            CacheCategory::Npm => "npm",
+            CacheCategory::Pip => "pip",
+            CacheCategory::Cargo => "cargo",
+            CacheCategory::NodeModules => "node_modules",
+            CacheCategory::Target => "target",
+            CacheCategory::Thumbnails => "thumbnails",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CacheCategory::Browser => "Browser caches",
+            CacheCategory::Npm => "npm and yarn caches",
+            CacheCategory::Pip => "pip cache",
+            CacheCategory::Cargo => "Cargo registry",
+            CacheCategory::NodeModules => "node_modules folders",
+            CacheCategory::Target => "Rust target folders",
+            CacheCategory::Thumbnails => "Thumbnail caches",
+        }
+    }
+}
+
+impl fmt::Display for CacheCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CacheCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim().to_ascii_lowercase().replace('-', "_");
+        CacheCategory::ALL
+            .into_iter()
+            .find(|category| category.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = CacheCategory::ALL.iter().map(|c| c.name()).collect();
+                Error::InvalidInput(format!(
+                    "Unknown cache category: {} (expected one of {})",
+                    s,
+                    names.join(", ")
+                ))
+                .into()
+            })
+    }
+}
+
+/// Caches beneath the user's cache directory (~/.cache, ~/Library/Caches,
+/// %LOCALAPPDATA%)
+#[cfg(all(unix, not(target_os = "macos")))]
+const CACHE_DIR_LOCATIONS: &[(CacheCategory, &str)] = &[
+    (CacheCategory::Browser, "google-chrome"),
+    (CacheCategory::Browser, "chromium"),
+    (CacheCategory::Browser, "mozilla/firefox"),
+    (CacheCategory::Browser, "BraveSoftware"),
+    (CacheCategory::Browser, "microsoft-edge"),
+    (CacheCategory::Npm, "yarn"),
+    (CacheCategory::Pip, "pip"),
+    (CacheCategory::Thumbnails, "thumbnails"),
+];
+
+#[cfg(target_os = "macos")]
+const CACHE_DIR_LOCATIONS: &[(CacheCategory, &str)] = &[
+    (CacheCategory::Browser, "Google/Chrome"),
+    (CacheCategory::Browser, "Firefox"),
+    (CacheCategory::Browser, "com.apple.Safari"),
+    (CacheCategory::Browser, "BraveSoftware"),
+    (CacheCategory::Browser, "Microsoft Edge"),
+    (CacheCategory::Npm, "Yarn"),
+    (CacheCategory::Pip, "pip"),
+];
+
+#[cfg(windows)]
+const CACHE_DIR_LOCATIONS: &[(CacheCategory, &str)] = &[
+    (
+        CacheCategory::Browser,
+        "Google/Chrome/User Data/Default/Cache",
+    ),
+    (
+        CacheCategory::Browser,
+        "Microsoft/Edge/User Data/Default/Cache",
+    ),
+    (
+        CacheCategory::Browser,
+        "BraveSoftware/Brave-Browser/User Data/Default/Cache",
+    ),
+    // Only the cache half of Firefox profiles lives in the local folder
+    (CacheCategory::Browser, "Mozilla/Firefox/Profiles"),
+    (CacheCategory::Npm, "npm-cache"),
+    (CacheCategory::Npm, "Yarn/Cache"),
+    (CacheCategory::Pip, "pip/Cache"),
+    (CacheCategory::Thumbnails, "Microsoft/Windows/Explorer"),
+];
+
+#[cfg(not(any(unix, windows)))]
+const CACHE_DIR_LOCATIONS: &[(CacheCategory, &str)] = &[];
+
+/// Caches beneath the home directory
+const HOME_LOCATIONS: &[(CacheCategory, &str)] = &[
+    (CacheCategory::Npm, ".npm/_cacache"),
+    (CacheCategory::Thumbnails, ".thumbnails"),
+];
+
+/// Downloaded crates and git checkouts beneath Cargo's home; the index and
+/// installed binaries stay
+const CARGO_LOCATIONS: &[&str] = &["registry/cache", "registry/src", "git/checkouts"];
+
+/// Where to look for caches
+#[derive(Debug, Clone, Default)]
+pub struct CacheLocator {
+    home: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    cargo_home: Option<PathBuf>,
+}
+
+impl CacheLocator {
+    /// Caches of `home`, with its cache directory at `cache_dir` and Cargo
+    /// at `home/.cargo`
+    pub fn new(home: impl Into<PathBuf>, cache_dir: impl Into<PathBuf>) -> Self {
+        let home = home.into();
+        Self {
+            cargo_home: Some(home.join(".cargo")),
+            home: Some(home),
+            cache_dir: Some(cache_dir.into()),
+        }
+    }
+
+    /// The current user's caches
+    pub fn detect() -> Self {
+        let Some(dirs) = directories::BaseDirs::new() else {
+            return Self::default();
+        };
+        let mut locator = Self::new(dirs.home_dir(), dirs.cache_dir());
+        if let Some(cargo_home) = std::env::var_os("CARGO_HOME") {
+            locator.cargo_home = Some(PathBuf::from(cargo_home));
+        }
+        locator
+    }
+
+    pub fn with_cargo_home(mut self, cargo_home: impl Into<PathBuf>) -> Self {
+        self.cargo_home = Some(cargo_home.into());
+        self
+    }
+
+    /// The known locations that exist, with the `node_modules` and `target`
+    /// folders beneath `roots`. Sizes are not taken yet.
+    pub fn locate(&self, roots: &[PathBuf]) -> Vec<(CacheCategory, PathBuf)> {
+        let mut found = Vec::new();
+        let mut known = |category, path: PathBuf| {
+            if is_real_dir(&path) {
+                found.push((category, path));
+            }
+        };
+        if let Some(cache_dir) = &self.cache_dir {
+            for (category, relative) in CACHE_DIR_LOCATIONS {
+                known(*category, cache_dir.join(relative));
+            }
+        }
+        if let Some(home) = &self.home {
+            for (category, relative) in HOME_LOCATIONS {
+                known(*category, home.join(relative));
+            }
+        }
+        if let Some(cargo_home) = &self.cargo_home {
+            for relative in CARGO_LOCATIONS {
+                known(CacheCategory::Cargo, cargo_home.join(relative));
+            }
+        }
+        for root in roots {
+            found.extend(build_outputs(root));
+        }
+        // A root inside a known cache would list its folders twice
+        let mut seen = HashSet::new();
+        found.retain(|(_, path)| seen.insert(path.clone()));
+        found
+    }
+}
+
+/// A directory, not a link to one
+pub(crate) fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+}
+
+/// What `dir` is when it is build output its project rebuilds: a
+/// `node_modules` next to a `package.json`, or a `target` next to a
+/// `Cargo.toml`
+pub(crate) fn build_output(dir: &Path) -> Option<CacheCategory> {
+    let parent = dir.parent()?;
+    let (category, manifest) = match dir.file_name()?.to_str()? {
+        "node_modules" => (CacheCategory::NodeModules, "package.json"),
+        "target" => (CacheCategory::Target, "Cargo.toml"),
+        _ => return None,
+    };
+    parent.join(manifest).is_file().then_some(category)
+}
+
+/// The build output folders beneath `root`; nothing inside one is looked at
+fn build_outputs(root: &Path) -> Vec<(CacheCategory, PathBuf)> {
+    let mut found = Vec::new();
+    let mut walk = WalkDir::new(root).into_iter();
+    while let Some(entry) = walk.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        if let Some(category) = build_output(entry.path()) {
+            found.push((category, entry.into_path()));
+            walk.skip_current_dir();
+        } else if entry.file_name() == ".git" {
+            walk.skip_current_dir();
+        }
+    }
+    found
+}
+
+/// One reclaimable location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheLocation {
+    pub category: CacheCategory,
+    pub path: PathBuf,
+    pub size: u64,
+    pub file_count: usize,
+}
+
+impl CacheLocation {
+    /// `path` with the size and number of files beneath it. Links are not
+    /// followed and unreadable entries are left out.
+    pub fn measure(category: CacheCategory, path: PathBuf) -> Self {
+        let (size, file_count) = WalkDir::new(&path)
+            .into_iter()
+            .flatten()
+            .filter(|entry| !entry.file_type().is_dir())
+            .fold((0, 0), |(size, count), entry| {
+                let len = entry.metadata().map_or(0, |m| m.len());
+                (size + len, count + 1)
+            });
+        Self {
+            category,
+            path,
+            size,
+            file_count,
+        }
+    }
+}
+
+/// The locations of one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheCategoryReport {
+    pub category: CacheCategory,
+    pub label: String,
+    /// Largest first
+    pub locations: Vec<CacheLocation>,
+    pub size: u64,
+    pub file_count: usize,
+}
+
+/// What a cache scan found, by category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppCacheReport {
+    /// Categories with something to reclaim, largest first
+    pub categories: Vec<CacheCategoryReport>,
+    pub total_size: u64,
+}
+
+impl AppCacheReport {
+    /// Group `locations` by category, leaving out empty ones
+    pub fn new(locations: Vec<CacheLocation>) -> Self {
+        let mut categories: Vec<CacheCategoryReport> = CacheCategory::ALL
+            .into_iter()
+            .filter_map(|category| {
+                let mut locations: Vec<CacheLocation> = locations
+                    .iter()
+                    .filter(|l| l.category == category && l.file_count > 0)
+                    .cloned()
+                    .collect();
+                if locations.is_empty() {
+                    return None;
+                }
+                locations.sort_by_key(|location| std::cmp::Reverse(location.size));
+                Some(CacheCategoryReport {
+                    category,
+                    label: category.label().to_string(),
+                    size: locations.iter().map(|l| l.size).sum(),
+                    file_count: locations.iter().map(|l| l.file_count).sum(),
+                    locations,
+                })
+            })
+            .collect();
+        categories.sort_by_key(|category| std::cmp::Reverse(category.size));
+        Self {
+            total_size: categories.iter().map(|c| c.size).sum(),
+            categories,
+        }
+    }
+
+    /// The locations of `categories`
+    pub fn locations<'a>(
+        &'a self,
+        categories: &'a [CacheCategory],
+    ) -> impl Iterator<Item = &'a CacheLocation> {
+        self.categories
+            .iter()
+            .filter(|c| categories.contains(&c.category))
+            .flat_map(|c| &c.locations)
+    }
+}
+
+/// Why `location` must not be removed now, if it must not
+fn refusal(location: &CacheLocation, protected: &ProtectedPaths) -> Option<Error> {
+    if let Some(e) = crate::duplicate_dirs::refusal(&location.path, protected) {
+        return Some(e);
+    }
+    if !is_real_dir(&location.path) {
+        return Some(Error::NotFound(format!(
+            "No longer a folder: {}",
+            location.path.display()
+        )));
+    }
+    let build = matches!(
+        location.category,
+        CacheCategory::NodeModules | CacheCategory::Target
+    );
+    if build && build_output(&location.path) != Some(location.category) {
+        return Some(Error::InvalidInput(format!(
+            "No longer next to its project manifest: {}",
+            location.path.display()
+        )));
+    }
+    None
+}
+
+/// What cleaning the `categories` of `report` with `mode` would do
+pub fn cleaning_plan(
+    report: &AppCacheReport,
+    categories: &[CacheCategory],
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+) -> ActionPlan {
+    let mut actions = Vec::new();
+    let mut skipped = Vec::new();
+    for location in report.locations(categories) {
+        match refusal(location, protected) {
+            Some(e) => skipped.push(FileError::new(&location.path, e)),
+            None => actions.push(PlannedAction {
+                path: location.path.clone(),
+                action: mode.action().to_string(),
+                size: location.size,
+                reclaimed: Some(location.size),
+                detail: Some(location.category.label().to_string()),
+            }),
+        }
+    }
+    ActionPlan::new(actions, skipped)
+}
+
+/// Remove the locations of `categories` in `report` with `mode`, each
+/// checked again first, reporting a per-location outcome
+pub fn clean(
+    report: &AppCacheReport,
+    categories: &[CacheCategory],
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+) -> Vec<DeleteResult> {
+    report
+        .locations(categories)
+        .map(|location| {
+            let outcome = match refusal(location, protected) {
+                Some(e) => Err(e),
+                None => remove_tree(&location.path, mode),
+            };
+            DeleteResult::of(&location.path, outcome)
+        })
+        .collect()
+}
+
+pub(crate) fn remove_tree(path: &Path, mode: DeleteMode) -> std::result::Result<(), Error> {
+    match mode {
+        DeleteMode::Trash => trash::delete(path).map_err(|e| Error::FileOperation(e.to_string())),
+        DeleteMode::Permanent => Ok(fs::remove_dir_all(path)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_known_caches_and_build_output_are_found_and_cleaned() {
+        let home = tempdir().unwrap();
+        let cache_dir = home.path().join("cache");
+        let (category, relative) = CACHE_DIR_LOCATIONS[0];
+        write(&cache_dir.join(relative).join("entry"), "cached page");
+        write(&home.path().join(".npm/_cacache/index"), "npm");
+        write(
+            &home.path().join(".cargo/registry/cache/serde.crate"),
+            "crate",
+        );
+        // Installed binaries are not a cache
+        write(&home.path().join(".cargo/bin/tool"), "binary");
+
+        let projects = home.path().join("projects");
+        write(&projects.join("web/package.json"), "{}");
+        write(&projects.join("web/node_modules/left-pad/index.js"), "pad");
+        // Nested packages come with the folder they are in
+        write(
+            &projects.join("web/node_modules/a/node_modules/b/x.js"),
+            "b",
+        );
+        write(&projects.join("app/Cargo.toml"), "[package]");
+        write(&projects.join("app/target/debug/app"), "binary");
+        // A folder called target that no Cargo.toml builds is left alone
+        write(&projects.join("site/target/index.html"), "page");
+
+        let locator = CacheLocator::new(home.path(), &cache_dir);
+        let found = locator.locate(std::slice::from_ref(&projects));
+        let locations: Vec<_> = found
+            .into_iter()
+            .map(|(category, path)| CacheLocation::measure(category, path))
+            .collect();
+        let report = AppCacheReport::new(locations);
+
+        let by_category = |c| report.categories.iter().find(|r| r.category == c);
+        assert_eq!(by_category(category).unwrap().size, 11);
+        assert_eq!(by_category(CacheCategory::Npm).unwrap().file_count, 1);
+        let cargo = by_category(CacheCategory::Cargo).unwrap();
+        assert_eq!(cargo.locations.len(), 1);
+        let modules = by_category(CacheCategory::NodeModules).unwrap();
+        assert_eq!(modules.locations.len(), 1);
+        assert_eq!(modules.file_count, 2);
+        let targets = by_category(CacheCategory::Target).unwrap();
+        assert_eq!(targets.locations[0].path, projects.join("app/target"));
+        assert_eq!(
+            report.total_size,
+            report.categories.iter().map(|c| c.size).sum::<u64>()
+        );
+
+        // Only the chosen categories go, and protected ones are refused
+        let chosen = [CacheCategory::NodeModules, CacheCategory::Target];
+        let protected = ProtectedPaths::empty().with_paths([projects.join("app")]);
+        let plan = cleaning_plan(&report, &chosen, DeleteMode::Permanent, &protected);
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.skipped.len(), 1);
+        let results = clean(&report, &chosen, DeleteMode::Permanent, &protected);
+        assert_eq!(results.iter().filter(|r| r.success).count(), 1);
+        assert!(!projects.join("web/node_modules").exists());
+        assert!(projects.join("web/package.json").exists());
+        assert!(projects.join("app/target").exists());
+        assert!(home.path().join(".npm/_cacache").exists());
+    }
+
+    #[test]
+    fn test_build_output_must_still_be_next_to_its_manifest() {
+        let dir = tempdir().unwrap();
+        write(&dir.path().join("Cargo.toml"), "[package]");
+        write(&dir.path().join("target/debug/app"), "binary");
+        let location = CacheLocation::measure(CacheCategory::Target, dir.path().join("target"));
+        let report = AppCacheReport::new(vec![location]);
+
+        fs::remove_file(dir.path().join("Cargo.toml")).unwrap();
+        let results = clean(
+            &report,
+            &[CacheCategory::Target],
+            DeleteMode::Permanent,
+            &ProtectedPaths::empty(),
+        );
+        assert!(!results[0].success);
+        assert!(dir.path().join("target").exists());
+    }
+
+    #[test]
+    fn test_categories_parse_by_name() {
+        assert_eq!(
+            "node-modules".parse::<CacheCategory>().unwrap(),
+            CacheCategory::NodeModules
+        );
+        let err = "logs".parse::<CacheCategory>().unwrap_err();
+        assert!(err.to_string().contains("browser, npm"), "{err}");
+    }
+}
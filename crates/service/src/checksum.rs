@@ -0,0 +1,268 @@
+//! Checksum manifests.
+//!
+//! [`hash_tree`] hashes a file, or every file beneath a folder, into a
+//! [`Manifest`]: one `<hash>  <path>` line per file, the format `sha256sum`,
+//! `b3sum` and `xxhsum` write, so their check modes read it as well. Paths
+//! are relative to the hashed folder (to the file's own folder for a single
+//! file), so a manifest kept next to the data stays valid when both move.
+//! [`verify_manifest`] hashes the files again and reports what changed.
+
+use crate::api::FileError;
+use anyhow::{bail, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+use space_saver_core::{FileHasher, HashKind};
+use space_saver_utils::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One file of a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub hash: String,
+    /// Relative to the manifest's base folder, `/`-separated
+    pub path: String,
+}
+
+/// Hashes of a set of files, all made with one algorithm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub algorithm: HashKind,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// The manifest as `<hash>  <path>` lines
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{}  {}\n", entry.hash, entry.path))
+            .collect()
+    }
+
+    /// Read `<hash>  <path>` lines (`<hash> *<path>` also marks a path);
+    /// blank lines and `#` comments are skipped. Hashes must have the
+    /// length `algorithm` gives.
+    pub fn parse(text: &str, algorithm: HashKind) -> Result<Self> {
+        let digits = FileHasher::new(algorithm).hash_bytes(b"").len();
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: String| {
+                Error::InvalidInput(format!("Manifest line {}: {}", index + 1, reason))
+            };
+            let (hash, path) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid("expected <hash>  <path>".to_string()))?;
+            let path = path
+                .strip_prefix(' ')
+                .or_else(|| path.strip_prefix('*'))
+                .unwrap_or(path);
+            if hash.len() != digits || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!(invalid(format!(
+                    "not a {} hash ({} hex digits); pass the algorithm the manifest was made with",
+                    algorithm, digits
+                )));
+            }
+            if path.is_empty() {
+                bail!(invalid("the path is missing".to_string()));
+            }
+            entries.push(ManifestEntry {
+                hash: hash.to_lowercase(),
+                path: path.to_string(),
+            });
+        }
+        Ok(Self { algorithm, entries })
+    }
+}
+
+/// The algorithm a manifest file's extension names, e.g. `.sha256`
+pub fn algorithm_for(manifest: &Path) -> Option<HashKind> {
+    match manifest
+        .extension()?
+        .to_str()?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "sha256" | "sha256sum" => Some(HashKind::Sha256),
+        "b3" | "blake3" | "b3sum" => Some(HashKind::Blake3),
+        "xxh3" | "xxh128" => Some(HashKind::Xxh3),
+        _ => None,
+    }
+}
+
+/// A manifest of a file or folder, with the files that could not be read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashedTree {
+    pub manifest: Manifest,
+    pub unreadable: Vec<FileError>,
+}
+
+/// Hash `path`, or every file beneath it, hidden ones included, with
+/// `algorithm`. Entries are sorted by path; symlinks are not followed.
+pub fn hash_tree(path: &Path, algorithm: HashKind) -> Result<HashedTree> {
+    let metadata = fs::metadata(path).map_err(Error::from)?;
+    let (base, files): (&Path, Vec<PathBuf>) = if metadata.is_file() {
+        let base = path.parent().unwrap_or(Path::new(""));
+        (base, vec![path.to_path_buf()])
+    } else {
+        let files = DefaultFileScanner::new().include_hidden(true).scan(path)?;
+        (path, files.into_iter().map(|f| f.path).collect())
+    };
+
+    let hasher = FileHasher::new(algorithm);
+    let outcomes: Vec<std::result::Result<ManifestEntry, FileError>> = files
+        .par_iter()
+        .map(|file| {
+            let hash = hasher
+                .hash_file(file)
+                .map_err(|e| FileError::new(file, Error::from(e)))?;
+            Ok(ManifestEntry {
+                hash,
+                path: relative_path(file, base),
+            })
+        })
+        .collect();
+    let (entries, unreadable): (Vec<_>, Vec<_>) = outcomes.into_iter().partition(Result::is_ok);
+    let mut entries: Vec<ManifestEntry> = entries.into_iter().filter_map(Result::ok).collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(HashedTree {
+        manifest: Manifest { algorithm, entries },
+        unreadable: unreadable.into_iter().filter_map(Result::err).collect(),
+    })
+}
+
+/// `file` relative to `base`, `/`-separated on every platform
+fn relative_path(file: &Path, base: &Path) -> String {
+    let relative = file.strip_prefix(base).unwrap_or(file);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// How a file compares with its manifest entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumStatus {
+    Ok,
+    /// The content no longer matches
+    Changed,
+    Missing,
+    Unreadable,
+}
+
+/// Per-file outcome of verifying a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecksumResult {
+    pub path: String,
+    pub status: ChecksumStatus,
+    pub expected: String,
+    /// The hash the file has now, when it could be read
+    pub actual: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Hash every file of `manifest` again, resolving its paths against
+/// `base`. Results follow the manifest's order.
+pub fn verify_manifest(manifest: &Manifest, base: &Path) -> Vec<ChecksumResult> {
+    let hasher = FileHasher::new(manifest.algorithm);
+    manifest
+        .entries
+        .par_iter()
+        .map(|entry| {
+            let path = base.join(&entry.path);
+            let (status, actual, error) = match hasher.hash_file(&path) {
+                Ok(hash) if hash.eq_ignore_ascii_case(&entry.hash) => {
+                    (ChecksumStatus::Ok, Some(hash), None)
+                }
+                Ok(hash) => (ChecksumStatus::Changed, Some(hash), None),
+                Err(_) if path.symlink_metadata().is_err() => (ChecksumStatus::Missing, None, None),
+                Err(e) => (ChecksumStatus::Unreadable, None, Some(format!("{:#}", e))),
+            };
+            ChecksumResult {
+                path: entry.path.clone(),
+                status,
+                expected: entry.hash.clone(),
+                actual,
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_tree_manifest_round_trips_and_verifies() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.txt"), b"alpha").unwrap();
+        fs::write(dir.path().join("sub/b.txt"), b"beta").unwrap();
+        fs::write(dir.path().join(".hidden"), b"gamma").unwrap();
+
+        let tree = hash_tree(dir.path(), HashKind::Sha256).unwrap();
+        assert!(tree.unreadable.is_empty());
+        let paths: Vec<&str> = tree
+            .manifest
+            .entries
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect();
+        assert_eq!(paths, [".hidden", "a.txt", "sub/b.txt"]);
+
+        let text = tree.manifest.to_text();
+        assert!(text.starts_with(&format!(
+            "{}  .hidden\n",
+            FileHasher::new_sha256().hash_bytes(b"gamma")
+        )));
+        let parsed = Manifest::parse(&text, HashKind::Sha256).unwrap();
+        assert_eq!(parsed.entries, tree.manifest.entries);
+
+        fs::write(dir.path().join("a.txt"), b"edited").unwrap();
+        fs::remove_file(dir.path().join("sub/b.txt")).unwrap();
+        let results = verify_manifest(&parsed, dir.path());
+        let statuses: Vec<ChecksumStatus> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                ChecksumStatus::Ok,
+                ChecksumStatus::Changed,
+                ChecksumStatus::Missing
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_files_are_relative_to_their_folder() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), b"alpha").unwrap();
+        let tree = hash_tree(&dir.path().join("a.txt"), HashKind::Xxh3).unwrap();
+        assert_eq!(tree.manifest.entries.len(), 1);
+        assert_eq!(tree.manifest.entries[0].path, "a.txt");
+    }
+
+    #[test]
+    fn test_parse_accepts_checksum_tool_output_and_rejects_other_lengths() {
+        let hash = FileHasher::new_blake3().hash_bytes(b"alpha");
+        let text = format!("# made by b3sum\n\n{} *a b.txt\r\n", hash);
+        let manifest = Manifest::parse(&text, HashKind::Blake3).unwrap();
+        assert_eq!(manifest.entries[0].path, "a b.txt");
+
+        let err = Manifest::parse(&text, HashKind::Xxh3).unwrap_err();
+        assert!(err.to_string().contains("line 3"));
+        assert_eq!(
+            algorithm_for(Path::new("SHA256SUMS.sha256")),
+            Some(HashKind::Sha256)
+        );
+        assert_eq!(algorithm_for(Path::new("MANIFEST")), None);
+    }
+}
@@ -0,0 +1,395 @@
+//! Rules-based cleanup.
+//!
+//! A [`CleanupRule`] from the config names folders, a filter expression, an
+//! age and an action. Running a rule first plans it — the files it matches —
+//! and then, unless it is a dry run, applies the action to each of them:
+//! deleting, moving to the trash, compressing in place, or archiving them
+//! into a verified tar.zst under the rule's `archive_dir` before deleting
+//! the originals. Protected files are reported and never touched.
+
+use crate::api::FilterConfig;
+use crate::file_ops::{trash_path, DeleteMode, FileOperations};
+use crate::protection::ProtectedPaths;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_core::compress_plugins::{CompressionOutcome, PluginManager, ProgressSink};
+use space_saver_core::{ArchiveFormat, FileInfo};
+use space_saver_db::{RestorePoint, SqliteDatabase};
+use space_saver_utils::CleanupRule;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// What a rule did, or would do, to one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: i64,
+    /// Bytes freed; 0 in a dry run and when the action failed
+    pub freed: u64,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub protected: bool,
+}
+
+/// The files one rule matched and what became of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupRuleReport {
+    pub name: String,
+    pub action: String,
+    pub files: Vec<CleanupFile>,
+    pub matched_size: u64,
+    pub freed: u64,
+    /// Set when the rule could not run at all (e.g. a missing folder or an
+    /// invalid filter); its files are then empty
+    pub error: Option<String>,
+    /// The archive written by an "archive" rule
+    pub archive_path: Option<String>,
+}
+
+/// Plan (in a dry run) or outcome of a cleanup run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    pub rules: Vec<CleanupRuleReport>,
+    pub matched_count: usize,
+    pub matched_size: u64,
+    pub freed: u64,
+}
+
+impl CleanupReport {
+    pub fn new(dry_run: bool, rules: Vec<CleanupRuleReport>) -> Self {
+        Self {
+            dry_run,
+            matched_count: rules.iter().map(|r| r.files.len()).sum(),
+            matched_size: rules.iter().map(|r| r.matched_size).sum(),
+            freed: rules.iter().map(|r| r.freed).sum(),
+            rules,
+        }
+    }
+}
+
+/// The scan filter selecting a rule's files
+pub fn rule_filter(rule: &CleanupRule) -> FilterConfig {
+    FilterConfig {
+        query: rule.filter.clone().filter(|q| !q.trim().is_empty()),
+        older_than: rule.older_than_days.map(|days| format!("{}d", days)),
+        ..Default::default()
+    }
+}
+
+/// Report for a rule that could not be planned
+pub fn failed_rule(rule: &CleanupRule, error: &anyhow::Error) -> CleanupRuleReport {
+    CleanupRuleReport {
+        name: rule.name.clone(),
+        action: rule.action.clone(),
+        files: Vec::new(),
+        matched_size: 0,
+        freed: 0,
+        error: Some(format!("{:#}", error)),
+        archive_path: None,
+    }
+}
+
+/// Apply `rule` to the `files` it matched; in a dry run only report them.
+/// `manager` runs "compress" rules and `db` records "archive" rules.
+pub fn apply_rule(
+    rule: &CleanupRule,
+    mut files: Vec<FileInfo>,
+    dry_run: bool,
+    protected: &ProtectedPaths,
+    manager: Option<&PluginManager>,
+    db: &SqliteDatabase,
+) -> CleanupRuleReport {
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut report = CleanupRuleReport {
+        name: rule.name.clone(),
+        action: rule.action.clone(),
+        matched_size: files.iter().map(|f| f.size).sum(),
+        files: files
+            .iter()
+            .map(|file| {
+                let check = protected.check(&file.path);
+                CleanupFile {
+                    path: file.path.to_string_lossy().to_string(),
+                    size: file.size,
+                    modified: file.modified,
+                    freed: 0,
+                    protected: check.is_err(),
+                    error: check.err().map(|e| e.to_string()),
+                }
+            })
+            .collect(),
+        freed: 0,
+        error: None,
+        archive_path: None,
+    };
+    let targets: Vec<&mut CleanupFile> = report.files.iter_mut().filter(|f| !f.protected).collect();
+    if dry_run || targets.is_empty() {
+        return report;
+    }
+    let outcome = match rule.action.as_str() {
+        "delete" => {
+            delete(targets, DeleteMode::Permanent, protected, db);
+            Ok(())
+        }
+        "trash" => {
+            delete(targets, DeleteMode::Trash, protected, db);
+            Ok(())
+        }
+        "compress" => match manager {
+            Some(manager) => {
+                compress(targets, manager, protected, db);
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!("No compression plugins available")),
+        },
+        "archive" => match &rule.archive_dir {
+            Some(dir) => archive(&rule.name, targets, dir, db).map(|archive| {
+                report.archive_path = Some(archive.to_string_lossy().to_string());
+            }),
+            None => Err(anyhow::anyhow!("No archive_dir set")),
+        },
+        other => Err(anyhow::anyhow!("Unknown cleanup action: {}", other)),
+    };
+    if let Err(e) = outcome {
+        report.error = Some(format!("{:#}", e));
+    }
+    report.freed = report.files.iter().map(|f| f.freed).sum();
+    report
+}
+
+/// Trashed files are recorded in `db` so they can be restored
+fn delete(
+    files: Vec<&mut CleanupFile>,
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+    db: &SqliteDatabase,
+) {
+    let ops = FileOperations::new().with_protected_paths(protected.clone());
+    let paths: Vec<PathBuf> = files.iter().map(|f| PathBuf::from(&f.path)).collect();
+    for (file, result) in files
+        .into_iter()
+        .zip(ops.delete_files_with_mode(&paths, mode))
+    {
+        if result.success {
+            file.freed = file.size;
+            if mode == DeleteMode::Trash {
+                let path = trash_path(Path::new(&file.path));
+                let point = RestorePoint::trashed(path.to_string_lossy().to_string(), file.size);
+                if let Err(e) = db.insert_restore_point(&point) {
+                    tracing::warn!(path = %file.path, error = %e, "Failed to record trashed file");
+                }
+            }
+        } else {
+            file.error = result.error;
+            file.protected = result.protected;
+        }
+    }
+}
+
+/// Compress in place without keeping backups: the point of the rule is the
+/// space. The manager's output verification still applies. `db` records
+/// each compression in the savings ledger.
+fn compress(
+    files: Vec<&mut CleanupFile>,
+    manager: &PluginManager,
+    protected: &ProtectedPaths,
+    db: &SqliteDatabase,
+) {
+    let paths: Vec<PathBuf> = files.iter().map(|f| PathBuf::from(&f.path)).collect();
+    let results = crate::compress::compress_in_place(
+        manager,
+        &paths,
+        None,
+        false,
+        protected,
+        &ProgressSink::new(),
+    );
+    for (file, result) in files.into_iter().zip(results) {
+        match result {
+            Ok(CompressionOutcome::Compressed(result)) => {
+                file.freed = result.original_size.saturating_sub(result.compressed_size);
+                let op = crate::compress::compression_operation(Path::new(&file.path), &result);
+                if let Err(e) = db.insert_compression_operation(&op) {
+                    tracing::warn!(path = %file.path, error = %e, "Failed to record compression savings");
+                }
+            }
+            Ok(CompressionOutcome::Skipped { reason, .. }) => file.error = Some(reason),
+            Err(e) => file.error = Some(format!("{:#}", e)),
+        }
+    }
+}
+
+/// Copy the files into `<dir>/<rule>-<timestamp>` (mirroring their full
+/// paths), move that folder into a verified `.tar.zst` next to it, and only
+/// then delete the originals. Needs as much free space in `dir` as the files
+/// take while staging.
+fn archive(
+    rule: &str,
+    files: Vec<&mut CleanupFile>,
+    dir: &Path,
+    db: &SqliteDatabase,
+) -> Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create archive directory {}", dir.display()))?;
+    let slug: String = rule
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let stem = format!("{}-{}", slug, space_saver_utils::time::now());
+    let staging = dir.join(&stem);
+    let dest = dir.join(format!("{}.{}", stem, ArchiveFormat::TarZstd.extension()));
+
+    let stage = || -> Result<()> {
+        for file in &files {
+            let relative: PathBuf = Path::new(&file.path)
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect();
+            let target = staging.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&file.path, &target)
+                .with_context(|| format!("Failed to copy {}", file.path))?;
+        }
+        // The staging folder is our own copy; protected originals were
+        // already left out
+        crate::cold_storage::archive_directory(
+            &staging,
+            &dest,
+            ArchiveFormat::TarZstd,
+            None,
+            &ProtectedPaths::empty(),
+            db,
+        )?;
+        Ok(())
+    };
+    if let Err(e) = stage() {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    // The archive is verified; the originals can go
+    for file in files {
+        match fs::remove_file(&file.path) {
+            Ok(()) => file.freed = file.size,
+            Err(e) => file.error = Some(format!("Archived but not deleted: {}", e)),
+        }
+    }
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use tempfile::tempdir;
+
+    fn rule(dir: &Path, action: &str) -> CleanupRule {
+        CleanupRule {
+            name: "old logs".to_string(),
+            paths: vec![dir.to_path_buf()],
+            filter: Some("ext in (log)".to_string()),
+            older_than_days: None,
+            action: action.to_string(),
+            archive_dir: None,
+            enabled: true,
+        }
+    }
+
+    fn matched(rule: &CleanupRule) -> Vec<FileInfo> {
+        let files = DefaultFileScanner::new().scan(&rule.paths[0]).unwrap();
+        rule_filter(rule).apply(files).unwrap()
+    }
+
+    #[test]
+    fn test_dry_run_plans_without_touching_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), b"log line").unwrap();
+        fs::write(dir.path().join("keep.txt"), b"notes").unwrap();
+        fs::create_dir(dir.path().join("locked")).unwrap();
+        fs::write(dir.path().join("locked/b.log"), b"log").unwrap();
+        let rule = rule(dir.path(), "delete");
+        let protected = ProtectedPaths::empty().with_paths([dir.path().join("locked")]);
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        let report = apply_rule(&rule, matched(&rule), true, &protected, None, &db);
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.matched_size, 11);
+        assert_eq!(report.freed, 0);
+        assert!(report.files[1].protected);
+        assert!(dir.path().join("a.log").exists());
+
+        let report = apply_rule(&rule, matched(&rule), false, &protected, None, &db);
+        assert_eq!(report.freed, 8);
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("locked/b.log").exists());
+        assert!(dir.path().join("keep.txt").exists());
+    }
+
+    #[test]
+    fn test_archive_rule_moves_files_into_a_verified_archive() {
+        let (dir, archives) = (tempdir().unwrap(), tempdir().unwrap());
+        fs::write(dir.path().join("a.log"), b"first log").unwrap();
+        fs::write(dir.path().join("b.log"), b"second log").unwrap();
+        let rule = CleanupRule {
+            archive_dir: Some(archives.path().to_path_buf()),
+            ..rule(dir.path(), "archive")
+        };
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        let report = apply_rule(
+            &rule,
+            matched(&rule),
+            false,
+            &ProtectedPaths::empty(),
+            None,
+            &db,
+        );
+        assert_eq!(report.error, None);
+        assert_eq!(report.freed, 19);
+        let archive = report.archive_path.unwrap();
+        assert!(archive.ends_with(".tar.zst"));
+        assert!(Path::new(&archive).is_file());
+        assert!(!dir.path().join("a.log").exists());
+        // Only the archive is left; the staging folder went into it
+        assert_eq!(fs::read_dir(archives.path()).unwrap().count(), 1);
+        assert_eq!(db.get_archives().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_compress_rule_records_its_savings() {
+        let dir = tempdir().unwrap();
+        let mut seed = 0x2545F491u32;
+        let noise: image::RgbImage = image::ImageBuffer::from_fn(64, 64, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            image::Rgb([seed as u8, (seed >> 8) as u8, (seed >> 16) as u8])
+        });
+        noise.save(dir.path().join("noise.png")).unwrap();
+        let rule = CleanupRule {
+            filter: Some("ext in (png)".to_string()),
+            ..rule(dir.path(), "compress")
+        };
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(space_saver_core::WebPConverterPlugin::new()));
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        let report = apply_rule(
+            &rule,
+            matched(&rule),
+            false,
+            &ProtectedPaths::empty(),
+            Some(&manager),
+            &db,
+        );
+        assert_eq!(report.error, None);
+        let summary = db.savings_summary().unwrap();
+        assert_eq!(summary.file_count, 1);
+        assert_eq!(summary.saved, report.freed);
+        assert_eq!(summary.by_plugin[0].plugin_name, "WebP Converter");
+    }
+}
@@ -0,0 +1,294 @@
+//! "Archive and replace": moving a directory into cold storage.
+//!
+//! The directory is compressed, the archive is extracted to a scratch
+//! directory and compared entry by entry with the original, the mapping is
+//! recorded in the database, and only then is the original tree deleted.
+//! Any failure before the deletion leaves the original untouched and removes
+//! the partial archive.
+
+use crate::protection::ProtectedPaths;
+use anyhow::{bail, Context, Result};
+use space_saver_core::{ArchiveFormat, Compressor, FileHasher};
+use space_saver_db::{ArchiveRecord, SqliteDatabase};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// What verification compares for each path below the archived directory
+#[derive(Debug, PartialEq, Eq)]
+enum TreeEntry {
+    Dir,
+    File { size: u64, hash: String },
+    Symlink(PathBuf),
+}
+
+/// Archives the directory `source` into `dest`, verifies the archive,
+/// records the mapping in `db` and deletes `source`. A `source` that is
+/// protected, or holds a protected path, is refused before anything is
+/// written.
+///
+/// Formats that cannot store something in the tree (7z and ZIP drop
+/// symlinks) fail verification, so such a directory is never deleted.
+/// Verification extracts a full copy next to `dest`, which needs as much
+/// free space as the directory itself.
+pub fn archive_directory(
+    source: &Path,
+    dest: &Path,
+    format: ArchiveFormat,
+    password: Option<String>,
+    protected: &ProtectedPaths,
+    db: &SqliteDatabase,
+) -> Result<ArchiveRecord> {
+    let source = source
+        .canonicalize()
+        .with_context(|| format!("Source not found: {}", source.display()))?;
+    if let Some(refusal) = crate::duplicate_dirs::refusal(&source, protected) {
+        return Err(refusal.into());
+    }
+    if !source.is_dir() {
+        bail!("Not a directory: {}", source.display());
+    }
+    if dest.exists() {
+        bail!("Destination already exists: {}", dest.display());
+    }
+    let dest_parent = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    if dest_parent.starts_with(&source) {
+        bail!("The archive cannot be stored inside the directory being archived");
+    }
+
+    let original = snapshot(&source)?;
+    let compressor = Compressor::new_archive(format, password)?;
+    let archive_size = compressor.compress_path(&source, dest)?;
+    if let Err(e) = verify(&compressor, dest, &original) {
+        let _ = fs::remove_file(dest);
+        return Err(e);
+    }
+
+    let file_sizes: Vec<u64> = original
+        .values()
+        .filter_map(|entry| match entry {
+            TreeEntry::File { size, .. } => Some(*size),
+            _ => None,
+        })
+        .collect();
+    let mut record = ArchiveRecord::new(
+        source.to_string_lossy().to_string(),
+        dest.to_string_lossy().to_string(),
+        format.extension(),
+        file_sizes.iter().sum(),
+        archive_size,
+        file_sizes.len(),
+    );
+    record.id = db
+        .insert_archive(&record)
+        .context("Failed to record the archive; the original was kept")?;
+
+    fs::remove_dir_all(&source)
+        .with_context(|| format!("Archive verified but failed to remove {}", source.display()))?;
+    Ok(record)
+}
+
+/// Extracts `archive` to a scratch directory and checks every entry of
+/// `original` came back identical
+fn verify(
+    compressor: &Compressor,
+    archive: &Path,
+    original: &BTreeMap<PathBuf, TreeEntry>,
+) -> Result<()> {
+    let scratch = verify_dir_for(archive);
+    if scratch.exists() {
+        bail!(
+            "Verification directory already exists: {}",
+            scratch.display()
+        );
+    }
+
+    let result = compressor
+        .extract_archive(archive, &scratch)
+        .and_then(|_| snapshot(&scratch))
+        .and_then(|extracted| {
+            for (path, entry) in original {
+                match extracted.get(path) {
+                    None => bail!(
+                        "Archive verification failed: {} is missing from the archive",
+                        path.display()
+                    ),
+                    Some(found) if found != entry => bail!(
+                        "Archive verification failed: {} differs in the archive",
+                        path.display()
+                    ),
+                    Some(_) => {}
+                }
+            }
+            Ok(())
+        });
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+/// Scratch directory verification extracts `archive` into
+pub(crate) fn verify_dir_for(archive: &Path) -> PathBuf {
+    let mut scratch = archive.as_os_str().to_os_string();
+    scratch.push(".verify");
+    PathBuf::from(scratch)
+}
+
+/// Every directory, file and symlink below `root`, keyed by relative path
+fn snapshot(root: &Path) -> Result<BTreeMap<PathBuf, TreeEntry>> {
+    let hasher = FileHasher::new_blake3();
+    let mut entries = BTreeMap::new();
+    for entry in WalkDir::new(root).min_depth(1) {
+        let entry = entry?;
+        let file_type = entry.file_type();
+        let tree_entry = if file_type.is_symlink() {
+            TreeEntry::Symlink(fs::read_link(entry.path())?)
+        } else if file_type.is_dir() {
+            TreeEntry::Dir
+        } else {
+            TreeEntry::File {
+                size: entry.metadata()?.len(),
+                hash: hasher.hash_file(entry.path())?,
+            }
+        };
+        entries.insert(entry.path().strip_prefix(root)?.to_path_buf(), tree_entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn unprotected() -> ProtectedPaths {
+        ProtectedPaths::empty()
+    }
+
+    fn make_project(root: &Path) -> PathBuf {
+        let project = root.join("old-project");
+        fs::create_dir_all(project.join("src/empty")).unwrap();
+        fs::write(project.join("README.md"), "# Old project\n".repeat(50)).unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+        project
+    }
+
+    #[test]
+    fn test_archive_directory_replaces_tree() {
+        let dir = tempdir().unwrap();
+        let project = make_project(dir.path());
+        let dest = dir.path().join("old-project.tar.zst");
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        let record = archive_directory(
+            &project,
+            &dest,
+            ArchiveFormat::TarZstd,
+            None,
+            &unprotected(),
+            &db,
+        )
+        .unwrap();
+        assert!(!project.exists());
+        assert!(dest.is_file());
+        assert!(!dir.path().join("old-project.tar.zst.verify").exists());
+        assert_eq!(record.file_count, 2);
+        assert_eq!(record.original_size, 14 * 50 + 13);
+        assert_eq!(record.format, "tar.zst");
+
+        let found = db
+            .find_archive(&record.source_path)
+            .unwrap()
+            .expect("archive recorded");
+        assert_eq!(found.archive_path, dest.to_string_lossy());
+
+        // The record is enough to restore the tree
+        Compressor::new_tar_zstd()
+            .extract_archive(
+                Path::new(&found.archive_path),
+                Path::new(&found.source_path),
+            )
+            .unwrap();
+        assert!(project.join("src/empty").is_dir());
+        assert_eq!(
+            fs::read_to_string(project.join("src/main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unverifiable_archive_keeps_original() {
+        let dir = tempdir().unwrap();
+        let project = make_project(dir.path());
+        std::os::unix::fs::symlink("README.md", project.join("LINK.md")).unwrap();
+        let dest = dir.path().join("old-project.7z");
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        // 7z has no symlinks, so the archive does not match the tree
+        let err = archive_directory(
+            &project,
+            &dest,
+            ArchiveFormat::SevenZip,
+            None,
+            &unprotected(),
+            &db,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("LINK.md is missing"));
+        assert!(project.join("LINK.md").exists());
+        assert!(!dest.exists());
+        assert!(db.get_archives().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_archive_inside_source_is_rejected() {
+        let dir = tempdir().unwrap();
+        let project = make_project(dir.path());
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        let err = archive_directory(
+            &project,
+            &project.join("self.7z"),
+            ArchiveFormat::SevenZip,
+            None,
+            &unprotected(),
+            &db,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("inside the directory"));
+        assert!(project.join("README.md").exists());
+    }
+
+    #[test]
+    fn test_protected_source_is_kept() {
+        let dir = tempdir().unwrap();
+        let project = make_project(dir.path());
+        let dest = dir.path().join("old-project.tar.zst");
+        let db = SqliteDatabase::in_memory().unwrap();
+
+        // The directory itself, and one holding a protected folder
+        for protected in [
+            ProtectedPaths::empty().with_paths([&project]),
+            ProtectedPaths::empty().with_paths([project.join("src")]),
+        ] {
+            let err = archive_directory(
+                &project,
+                &dest,
+                ArchiveFormat::TarZstd,
+                None,
+                &protected,
+                &db,
+            )
+            .unwrap_err();
+            let err = space_saver_utils::Error::from(err);
+            assert_eq!(err.code(), space_saver_utils::ErrorCode::Protected);
+            assert!(project.join("src/main.rs").exists());
+            assert!(!dest.exists());
+        }
+        assert!(db.get_archives().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+//! In-place compression through the plugin manager.
+//!
+//! Frontends (the Tauri app, the CLI) compress each file next to itself:
+//! the output lands in the source's directory and the manager takes care of
+//! backups and replacement. This module owns that per-file loop so every
+//! frontend gets the same semantics for missing files, cancellation, and
+//! progress reporting.
+
+use crate::api::FileError;
+use crate::hooks::HookOutcome;
+use crate::plan::{ActionPlan, PlannedAction};
+use crate::progress::ProgressUpdate;
+use crate::protection::ProtectedPaths;
+use anyhow::Result;
+use space_saver_core::compress_plugins::{
+    CompressionOutcome, CompressionResult, PluginManager, PluginProgress, ProgressSink,
+};
+use space_saver_core::{FileInfo, MinifyOptions, RawPolicy, ToolLimits};
+use space_saver_db::{CompressionOperation, SqliteDatabase};
+use space_saver_utils::{Config, Error};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// Apply the config's manager-wide settings (worker count, required output
+/// verification, safe mode, metadata copying, minimum savings, tool paths
+/// and limits, RAW policy, minification, disabled plugins) to `manager`
+pub fn configure_manager(manager: &mut PluginManager, config: &Config) {
+    manager.set_max_workers(config.max_concurrent_tasks);
+    manager.set_require_verification(config.require_output_verification);
+    manager.set_safe_mode(config.safe_mode);
+    manager.set_disabled_plugins(&config.disabled_plugins);
+    manager.set_preserve_metadata(config.preserve_metadata);
+    manager.set_min_savings_percent(config.min_savings_percent);
+    // Blank entries mean "look it up on PATH"
+    let tool_paths: BTreeMap<String, PathBuf> = config
+        .tool_paths
+        .iter()
+        .filter(|(_, path)| !path.trim().is_empty())
+        .map(|(tool, path)| (tool.clone(), PathBuf::from(path.trim())))
+        .collect();
+    manager.set_tool_paths(&tool_paths);
+    manager.set_file_timeout(
+        (config.file_timeout_secs > 0)
+            .then(|| std::time::Duration::from_secs(config.file_timeout_secs)),
+    );
+    manager.set_tool_limits(ToolLimits {
+        nice: config.tool_nice_level,
+        max_memory_mb: config.tool_max_memory_mb,
+        idle_io: config.background_io_low_priority,
+    });
+    manager.set_raw_policy(if config.raw_policy == "archive" {
+        RawPolicy::Archive
+    } else {
+        RawPolicy::Delete
+    });
+    manager.set_minify_options(&MinifyOptions {
+        dirs: config
+            .minify_dirs
+            .iter()
+            .filter(|dir| !dir.trim().is_empty())
+            .map(|dir| PathBuf::from(dir.trim()))
+            .collect(),
+        min_size: config.minify_min_size,
+        data_files: config.minify_data_files,
+    });
+}
+
+/// Compress each file in place with the first listed plugin that can handle
+/// it (any registered plugin when `plugin_orders` is None). Results line up
+/// with `sources`; a missing or protected file is reported as a per-file
+/// error (a [`crate::ProtectedPathError`] for the latter) rather than
+/// failing the batch; `Error::from` gives each error its code.
+pub fn compress_in_place(
+    manager: &PluginManager,
+    sources: &[PathBuf],
+    plugin_orders: Option<&[String]>,
+    keep_backup: bool,
+    protected: &ProtectedPaths,
+    sink: &ProgressSink,
+) -> Vec<Result<CompressionOutcome>> {
+    sources
+        .iter()
+        .map(|source| {
+            protected.check(source)?;
+            if !source.exists() {
+                return Err(Error::NotFound("File not found".to_string()).into());
+            }
+            let output_dir = source.parent().ok_or_else(|| {
+                Error::InvalidInput(format!("No parent directory: {}", source.display()))
+            })?;
+            manager.process_file(source, output_dir, plugin_orders, keep_backup, Some(sink))
+        })
+        .collect()
+}
+
+/// How a batch of compressions went, for the after "compress" hooks:
+/// compressed and skipped files succeeded, and each compressed one freed
+/// what it saved
+pub fn compression_outcome(outcomes: &[Result<CompressionOutcome>]) -> HookOutcome {
+    let mut outcome = HookOutcome::default();
+    for result in outcomes {
+        match result {
+            Ok(CompressionOutcome::Compressed(result)) => {
+                outcome.succeeded += 1;
+                outcome.freed += result.original_size.saturating_sub(result.compressed_size);
+            }
+            Ok(CompressionOutcome::Skipped { .. }) => outcome.succeeded += 1,
+            Err(_) => outcome.failed += 1,
+        }
+    }
+    outcome
+}
+
+/// What [`compress_in_place`] would do to `files`, touching nothing: each
+/// file goes to the first of `plugins` able to take it, with the saving
+/// the plugin estimates. Files an earlier compression produced (as `db`
+/// recorded them), files no plugin takes and files expected to shrink by
+/// less than `min_savings_percent` are left out; protected ones are listed
+/// as skipped. Largest estimated savings first.
+pub fn compression_plan(
+    manager: &PluginManager,
+    files: Vec<FileInfo>,
+    plugins: &[String],
+    min_savings_percent: f32,
+    protected: &ProtectedPaths,
+    db: &SqliteDatabase,
+) -> Result<ActionPlan> {
+    let mut actions = Vec::new();
+    let mut skipped = Vec::new();
+    for file in files {
+        if let Err(e) = protected.check(&file.path) {
+            skipped.push(FileError::new(&file.path, Error::from(e)));
+            continue;
+        }
+        // This exact file state came out of an earlier compression
+        let compressed_before = db
+            .find_compression(&file.path.to_string_lossy(), file.size, file.modified)?
+            .is_some_and(|record| record.action != "skipped");
+        if compressed_before {
+            continue;
+        }
+        let accepted = plugins.iter().find_map(|name| {
+            match manager.check_plugin_capability(&file.path, name) {
+                Ok(Some((_, true, _, ratio))) => Some((name.clone(), ratio)),
+                _ => None,
+            }
+        });
+        let Some((plugin, ratio)) = accepted else {
+            continue;
+        };
+        if ratio.is_some_and(|ratio| ratio * 100.0 < min_savings_percent) {
+            continue;
+        }
+        actions.push(PlannedAction {
+            reclaimed: ratio.map(|ratio| (file.size as f64 * ratio as f64) as u64),
+            path: file.path,
+            action: "compress".to_string(),
+            size: file.size,
+            detail: Some(plugin),
+        });
+    }
+    actions.sort_by_key(|a| std::cmp::Reverse(a.reclaimed.unwrap_or(0)));
+    Ok(ActionPlan::new(actions, skipped))
+}
+
+/// The savings-ledger entry for `source` compressed into `result`
+pub fn compression_operation(source: &Path, result: &CompressionResult) -> CompressionOperation {
+    let mut op = CompressionOperation::new(
+        source.to_string_lossy().to_string(),
+        result.output_path.to_string_lossy().to_string(),
+        result.plugin_name.clone(),
+        result.original_size,
+        result.compressed_size,
+    );
+    op.backup_path = result
+        .backup_path
+        .as_ref()
+        .map(|p| p.to_string_lossy().to_string());
+    op
+}
+
+/// Progress callback that forwards plugin reports as
+/// [`ProgressUpdate::Progress`] messages. Plugins run on blocking threads, so
+/// this uses `try_send`: when the receiver lags, reports are dropped rather
+/// than stalling the compression.
+pub fn forward_progress(
+    progress_tx: mpsc::Sender<ProgressUpdate>,
+) -> impl Fn(&PluginProgress) + Send + Sync + 'static {
+    move |progress| {
+        let file = progress.path.display();
+        let message = match &progress.message {
+            Some(detail) => format!("{}: {}", file, detail),
+            None => file.to_string(),
+        };
+        let _ = progress_tx.try_send(ProgressUpdate::Progress {
+            current: progress.current as usize,
+            total: progress.total as usize,
+            message,
+            bytes: 0,
+            total_bytes: 0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+    use space_saver_core::WebPConverterPlugin;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn save_noise_png(path: &Path) {
+        let mut seed = 0x2545F491u32;
+        let img: image::RgbImage = ImageBuffer::from_fn(64, 64, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            Rgb([seed as u8, (seed >> 8) as u8, (seed >> 16) as u8])
+        });
+        img.save(path).unwrap();
+    }
+
+    fn webp_manager() -> PluginManager {
+        let mut manager = PluginManager::new();
+        manager.register(Box::new(WebPConverterPlugin::new()));
+        manager
+    }
+
+    #[test]
+    fn test_compress_in_place_writes_output_next_to_source() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source);
+
+        let results = compress_in_place(
+            &webp_manager(),
+            std::slice::from_ref(&source),
+            None,
+            false,
+            &ProtectedPaths::new(),
+            &ProgressSink::new(),
+        );
+
+        assert_eq!(results.len(), 1);
+        match results.into_iter().next().unwrap().unwrap() {
+            CompressionOutcome::Compressed(result) => {
+                assert_eq!(result.output_path, dir.path().join("noise.webp"));
+                assert!(result.output_path.exists());
+                let op = compression_operation(&source, &result);
+                assert_eq!(op.source_path, source.to_string_lossy());
+                assert_eq!(op.output_path, result.output_path.to_string_lossy());
+                assert_eq!(op.backup_path, None);
+            }
+            other => panic!("expected Compressed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compress_in_place_reports_missing_file_per_file() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("missing.png");
+
+        let results = compress_in_place(
+            &webp_manager(),
+            &[missing],
+            None,
+            true,
+            &ProtectedPaths::new(),
+            &ProgressSink::new(),
+        );
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].as_ref().unwrap_err().to_string(),
+            "File not found"
+        );
+    }
+
+    #[test]
+    fn test_compress_in_place_refuses_protected_files() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("noise.png");
+        save_noise_png(&source);
+
+        let results = compress_in_place(
+            &webp_manager(),
+            std::slice::from_ref(&source),
+            None,
+            false,
+            &ProtectedPaths::empty().with_paths([dir.path()]),
+            &ProgressSink::new(),
+        );
+
+        let err = results[0].as_ref().unwrap_err();
+        assert!(err.downcast_ref::<crate::ProtectedPathError>().is_some());
+        assert!(source.exists());
+        assert!(!dir.path().join("noise.webp").exists());
+    }
+
+    #[test]
+    fn test_compression_plan_picks_a_plugin_and_touches_nothing() {
+        use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("locked")).unwrap();
+        save_noise_png(&dir.path().join("noise.png"));
+        save_noise_png(&dir.path().join("locked/noise.png"));
+        fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+        let files = DefaultFileScanner::new().scan(dir.path()).unwrap();
+
+        let manager = webp_manager();
+        let plugins: Vec<String> = manager.get_plugins().into_iter().map(|p| p.name).collect();
+        let plan = compression_plan(
+            &manager,
+            files,
+            &plugins,
+            0.0,
+            &ProtectedPaths::empty().with_paths([dir.path().join("locked")]),
+            &SqliteDatabase::in_memory().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].path, dir.path().join("noise.png"));
+        assert_eq!(plan.actions[0].action, "compress");
+        assert_eq!(plan.actions[0].detail.as_ref(), Some(&plugins[0]));
+        assert_eq!(plan.skipped.len(), 1);
+        assert!(!dir.path().join("noise.webp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_forward_progress_sends_updates() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let sink = ProgressSink::new()
+            .on_progress(forward_progress(tx))
+            .for_file(Path::new("/photos/album.zip"));
+
+        sink.report(3, 10, Some("page-03.png".to_string()));
+
+        match rx.recv().await.unwrap() {
+            ProgressUpdate::Progress {
+                current,
+                total,
+                message,
+                ..
+            } => {
+                assert_eq!((current, total), (3, 10));
+                assert_eq!(message, "/photos/album.zip: page-03.png");
+            }
+            other => panic!("expected Progress, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,1111 @@
+//! Running headless, as a long-lived daemon.
+//!
+//! A [`Daemon`] runs the config's [`Schedule`]s, queueing each on a
+//! [`Scheduler`] every `every_minutes`, and watches the folders of its
+//! [`WatchRule`]s. Watching is done by polling: every few seconds the
+//! folders are scanned and compared with the previous look, and a file that
+//! is new or changed gets the rule's action once it has stayed the same for
+//! the rule's `settle_secs`. Files already there when the daemon starts are
+//! left to cleanup rules. Scheduled runs end with a desktop notification
+//! when the config's `notifications` ask for one.
+//!
+//! The daemon answers on a local socket: a client writes one command per
+//! line, `status`, `run <schedule>` or `stop`, and reads back one
+//! [`ControlResponse`] as a JSON line. Without a daemon a schedule can still
+//! be run once with [`run_schedule_once`]. It also stops on Ctrl-C and, on Unix, SIGTERM, giving running tasks
+//! a grace period to finish.
+
+use crate::api::ServiceApi;
+use crate::cleanup::{apply_rule, rule_filter};
+use crate::hooks::Hooks;
+use crate::maintenance::run_maintenance;
+use crate::notifications::Notifications;
+use crate::progress::ProgressUpdate;
+use crate::protection::ProtectedPaths;
+use crate::scheduler::Scheduler;
+use crate::task::{Task, TaskStatus, TaskType};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use space_saver_core::FileInfo;
+use space_saver_db::Database;
+use space_saver_utils::time::now;
+use space_saver_utils::{format_size, CleanupRule, Config, Error, Schedule, WatchRule};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+/// How often watched folders are scanned
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long running tasks get to finish once the daemon is stopped
+const STOP_GRACE: Duration = Duration::from_secs(30);
+
+/// What the daemon is doing, as answered to `status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started_at: i64,
+    /// Tasks waiting for a slot on the scheduler
+    pub queued: usize,
+    pub running: usize,
+    pub schedules: Vec<ScheduleStatus>,
+    pub watches: Vec<WatchStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    pub name: String,
+    pub task: String,
+    pub every_minutes: u32,
+    pub next_run: i64,
+    pub last_run: Option<i64>,
+    /// What the last run did, or why it failed
+    pub last_result: Option<String>,
+    pub last_failed: bool,
+    /// Queued or running; a run due meanwhile is skipped
+    pub busy: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub name: String,
+    pub action: String,
+    pub paths: Vec<String>,
+    /// New or changed files waiting to settle
+    pub pending: usize,
+    /// Files the action was applied to
+    pub handled: usize,
+    pub freed: u64,
+    pub last_event: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+/// A run the daemon will start
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpcomingRun {
+    pub schedule: String,
+    pub task: String,
+    pub at: i64,
+}
+
+impl DaemonStatus {
+    /// The next `count` runs of the schedules, soonest first. A run due
+    /// while the last one is still busy is listed, though it will be skipped.
+    pub fn upcoming_runs(&self, count: usize) -> Vec<UpcomingRun> {
+        let mut runs: Vec<UpcomingRun> = self
+            .schedules
+            .iter()
+            .flat_map(|s| {
+                let period = s.every_minutes.max(1) as i64 * 60;
+                (0..count as i64).map(move |n| UpcomingRun {
+                    schedule: s.name.clone(),
+                    task: s.task.clone(),
+                    at: s.next_run + n * period,
+                })
+            })
+            .collect();
+        runs.sort_by(|a, b| a.at.cmp(&b.at).then_with(|| a.schedule.cmp(&b.schedule)));
+        runs.truncate(count);
+        runs
+    }
+}
+
+/// Answer to a control command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub status: Option<DaemonStatus>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+type SharedStatus = Arc<Mutex<DaemonStatus>>;
+
+fn lock(status: &SharedStatus) -> MutexGuard<'_, DaemonStatus> {
+    status.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The config's schedules and watch rules, run until stopped
+pub struct Daemon {
+    config: Config,
+    db: Database,
+    protected: ProtectedPaths,
+    poll_interval: Duration,
+}
+
+impl Daemon {
+    /// A daemon for the enabled schedules and watch rules of `config`,
+    /// recording into `db`
+    pub fn new(config: Config, db: Database) -> Self {
+        Self {
+            config,
+            db,
+            protected: ProtectedPaths::new(),
+            poll_interval: POLL_INTERVAL,
+        }
+    }
+
+    /// Paths scheduled cleanups and watch rules never touch
+    pub fn with_protected_paths(mut self, protected: ProtectedPaths) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Scan watched folders this often instead of every [`POLL_INTERVAL`]
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Run until a `stop` command or a termination signal. Fails at once
+    /// when another daemon answers on the config's socket.
+    pub async fn run(self) -> Result<()> {
+        let socket = self.config.daemon_socket.clone();
+        let listener = control::bind(&socket)?;
+        let schedules: Vec<Schedule> = self
+            .config
+            .schedules
+            .iter()
+            .filter(|s| s.enabled)
+            .cloned()
+            .collect();
+        let watches: Vec<WatchRule> = self
+            .config
+            .watch_rules
+            .iter()
+            .filter(|r| r.enabled)
+            .cloned()
+            .collect();
+        let started_at = now();
+        let status: SharedStatus = Arc::new(Mutex::new(DaemonStatus {
+            pid: std::process::id(),
+            started_at,
+            queued: 0,
+            running: 0,
+            schedules: schedules
+                .iter()
+                .map(|s| ScheduleStatus {
+                    name: s.name.clone(),
+                    task: s.task.clone(),
+                    every_minutes: s.every_minutes,
+                    next_run: started_at + s.every_minutes as i64 * 60,
+                    last_run: None,
+                    last_result: None,
+                    last_failed: false,
+                    busy: false,
+                })
+                .collect(),
+            watches: watches
+                .iter()
+                .map(|r| WatchStatus {
+                    name: r.name.clone(),
+                    action: r.action.clone(),
+                    paths: r
+                        .paths
+                        .iter()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .collect(),
+                    pending: 0,
+                    handled: 0,
+                    freed: 0,
+                    last_event: None,
+                    last_error: None,
+                })
+                .collect(),
+        }));
+
+        let (scheduler, mut progress_rx) = Scheduler::new(self.config.max_concurrent_tasks);
+        let scheduler = Arc::new(scheduler);
+        let runs = Runs {
+            schedules: Arc::new(schedules),
+            rules: self.config.cleanup_rules.clone(),
+            db: self.db.clone(),
+            protected: self.protected.clone(),
+            hooks: Hooks::new(self.config.hooks.clone()),
+            notifications: Notifications::system(self.config.notifications.clone()),
+            scheduler: Arc::clone(&scheduler),
+            status: Arc::clone(&status),
+        };
+        let (stop_tx, mut stop_rx) = watch::channel(false);
+        let mut workers = tokio::task::JoinSet::new();
+        workers.spawn(async move {
+            while let Some(update) = progress_rx.recv().await {
+                tracing::debug!(?update, "Task progress");
+            }
+        });
+        let runner = Arc::clone(&scheduler);
+        workers.spawn(async move {
+            if let Err(e) = runner.start().await {
+                tracing::error!(error = %e, "Scheduler stopped");
+            }
+        });
+        for index in 0..runs.schedules.len() {
+            workers.spawn(run_schedule(runs.clone(), index));
+        }
+        for (index, rule) in watches.into_iter().enumerate() {
+            workers.spawn(run_watch(
+                rule,
+                index,
+                self.db.clone(),
+                self.protected.clone(),
+                self.poll_interval,
+                Arc::clone(&status),
+            ));
+        }
+        workers.spawn(control::serve(listener, runs, stop_tx));
+        tracing::info!(
+            schedules = self.config.schedules.iter().filter(|s| s.enabled).count(),
+            watches = self.config.watch_rules.iter().filter(|r| r.enabled).count(),
+            socket = %socket.display(),
+            "Daemon started"
+        );
+
+        tokio::select! {
+            _ = stop_rx.changed() => tracing::info!("Stop requested"),
+            _ = terminated() => tracing::info!("Terminated"),
+        }
+        workers.abort_all();
+        let deadline = Instant::now() + STOP_GRACE;
+        while scheduler.running_count() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        if scheduler.running_count() > 0 {
+            tracing::warn!(
+                running = scheduler.running_count(),
+                "Stopping with tasks still running"
+            );
+        }
+        let _ = fs::remove_file(&socket);
+        tracing::info!("Daemon stopped");
+        Ok(())
+    }
+}
+
+/// Ctrl-C, or SIGTERM on Unix
+async fn terminated() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = term.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// What queueing a run of a schedule takes, shared by the timers and the
+/// control socket
+#[derive(Clone)]
+struct Runs {
+    /// The enabled schedules, in the order of the status' `schedules`
+    schedules: Arc<Vec<Schedule>>,
+    rules: Vec<CleanupRule>,
+    db: Database,
+    protected: ProtectedPaths,
+    hooks: Hooks,
+    notifications: Notifications,
+    scheduler: Arc<Scheduler>,
+    status: SharedStatus,
+}
+
+impl Runs {
+    /// Queue a run of the schedule at `index`, unless its last run is still
+    /// queued or running; false when it is
+    async fn queue(&self, index: usize) -> Result<bool> {
+        if std::mem::replace(&mut lock(&self.status).schedules[index].busy, true) {
+            return Ok(false);
+        }
+        let task = ScheduledTask::new(self.schedules[index].clone(), index, self);
+        if let Err(e) = self.scheduler.submit(Box::new(task)).await {
+            lock(&self.status).schedules[index].busy = false;
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    /// Queue a run of the schedule called `name` now, as `run <name>` asks
+    async fn queue_named(&self, name: &str) -> Result<()> {
+        let index = self
+            .schedules
+            .iter()
+            .position(|s| s.name == name)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "The daemon runs no schedule called '{}'; it picks up new and enabled schedules when restarted",
+                    name
+                ))
+            })?;
+        if !self.queue(index).await? {
+            anyhow::bail!(Error::InvalidInput(format!(
+                "Schedule '{}' is already queued or running",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Queue the schedule at `index` every `every_minutes`, unless its last run
+/// is still queued or running
+async fn run_schedule(runs: Runs, index: usize) {
+    let schedule = &runs.schedules[index];
+    let period = Duration::from_secs(schedule.every_minutes as u64 * 60);
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        lock(&runs.status).schedules[index].next_run = now() + period.as_secs() as i64;
+        match runs.queue(index).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::info!(schedule = %schedule.name, "Previous run not finished; skipped")
+            }
+            Err(e) => tracing::error!(schedule = %schedule.name, error = %e, "Failed to queue"),
+        }
+    }
+}
+
+/// Run `schedule` once, now and without a daemon, taking its cleanup rules
+/// from `rules`. Describes what was done, as the daemon's status does.
+pub async fn run_schedule_once(
+    schedule: &Schedule,
+    rules: &[CleanupRule],
+    db: Database,
+    protected: ProtectedPaths,
+    hooks: Hooks,
+) -> Result<String> {
+    run_task(
+        schedule,
+        schedule_rules(schedule, rules),
+        &db,
+        &protected,
+        &hooks,
+    )
+    .await
+}
+
+/// The cleanup rules `schedule` runs
+fn schedule_rules(schedule: &Schedule, rules: &[CleanupRule]) -> Vec<CleanupRule> {
+    rules
+        .iter()
+        .filter(|r| schedule.rules.is_empty() || schedule.rules.contains(&r.name))
+        .cloned()
+        .collect()
+}
+
+/// Do the work of `schedule`, describing what was done
+async fn run_task(
+    schedule: &Schedule,
+    rules: Vec<CleanupRule>,
+    db: &Database,
+    protected: &ProtectedPaths,
+    hooks: &Hooks,
+) -> Result<String> {
+    match schedule.task.as_str() {
+        "scan" => {
+            let api = ServiceApi::new().with_database(db.clone());
+            let results = api.scan_directories(schedule.paths.clone(), None).await?;
+            let files: usize = results.iter().map(|r| r.file_count).sum();
+            let size: u64 = results.iter().map(|r| r.total_size).sum();
+            Ok(format!("Scanned {} files ({})", files, format_size(size)))
+        }
+        "cleanup" => {
+            let api = ServiceApi::new()
+                .with_protected_paths(protected.clone())
+                .with_hooks(hooks.clone());
+            let report = db
+                .run(move |db| Ok(api.run_cleanup(&rules, false, db)?))
+                .await?;
+            Ok(format!(
+                "Freed {} from {} files",
+                format_size(report.freed),
+                report.matched_count
+            ))
+        }
+        "maintenance" => {
+            let record = db.run(run_maintenance).await?;
+            Ok(format!(
+                "Pruned {} stored files, database now {}",
+                record.pruned_files,
+                format_size(record.size_after)
+            ))
+        }
+        task => Err(Error::InvalidInput(format!("Unknown scheduled task: {}", task)).into()),
+    }
+}
+
+/// A run of a [`Schedule`] as a scheduler task
+struct ScheduledTask {
+    schedule: Schedule,
+    index: usize,
+    /// The cleanup rules a "cleanup" runs
+    rules: Vec<CleanupRule>,
+    db: Database,
+    protected: ProtectedPaths,
+    hooks: Hooks,
+    /// Told when the run ends
+    notifications: Notifications,
+    daemon: SharedStatus,
+    task_type: TaskType,
+    status: TaskStatus,
+}
+
+impl ScheduledTask {
+    /// A run of `schedule`, the one at `index`, with what `runs` shares
+    fn new(schedule: Schedule, index: usize, runs: &Runs) -> Self {
+        let rules = schedule_rules(&schedule, &runs.rules);
+        let task_type = match schedule.task.as_str() {
+            "scan" => TaskType::Scan(schedule.paths.clone()),
+            "cleanup" => TaskType::Cleanup(rules.iter().flat_map(|r| r.paths.clone()).collect()),
+            _ => TaskType::Maintenance,
+        };
+        Self {
+            schedule,
+            index,
+            rules,
+            db: runs.db.clone(),
+            protected: runs.protected.clone(),
+            hooks: runs.hooks.clone(),
+            notifications: runs.notifications.clone(),
+            daemon: Arc::clone(&runs.status),
+            task_type,
+            status: TaskStatus::Pending,
+        }
+    }
+
+    /// Do the work, describing what was done
+    async fn work(&self) -> Result<String> {
+        run_task(
+            &self.schedule,
+            self.rules.clone(),
+            &self.db,
+            &self.protected,
+            &self.hooks,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Task for ScheduledTask {
+    async fn run(&mut self, progress_tx: mpsc::Sender<ProgressUpdate>) -> Result<()> {
+        self.status = TaskStatus::Running;
+        let _ = progress_tx
+            .send(ProgressUpdate::Started {
+                task_type: format!("{} ({})", self.schedule.task, self.schedule.name),
+                total_items: 1,
+            })
+            .await;
+
+        let started = Instant::now();
+        let outcome = self.work().await;
+        let named = match &outcome {
+            Ok(message) => Ok(format!("{}: {}", self.schedule.name, message)),
+            Err(e) => Err(format!("{}: {:#}", self.schedule.name, e)),
+        };
+        self.notifications.task_finished(
+            self.task_type.kind(),
+            named.as_deref().map_err(String::as_str),
+            started.elapsed(),
+        );
+        {
+            let mut daemon = lock(&self.daemon);
+            let entry = &mut daemon.schedules[self.index];
+            entry.busy = false;
+            entry.last_run = Some(now());
+            entry.last_failed = outcome.is_err();
+            entry.last_result = Some(match &outcome {
+                Ok(message) => message.clone(),
+                Err(e) => format!("{:#}", e),
+            });
+        }
+        match outcome {
+            Ok(message) => {
+                tracing::info!(schedule = %self.schedule.name, "{}", message);
+                self.status = TaskStatus::Completed;
+                let _ = progress_tx
+                    .send(ProgressUpdate::Completed { message })
+                    .await;
+                Ok(())
+            }
+            Err(e) => {
+                self.status = TaskStatus::Failed(e.to_string());
+                let _ = progress_tx
+                    .send(ProgressUpdate::Failed {
+                        error: e.to_string(),
+                    })
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    fn task_type(&self) -> &TaskType {
+        &self.task_type
+    }
+
+    fn status(&self) -> &TaskStatus {
+        &self.status
+    }
+}
+
+/// Poll the folders of `rule`, applying its action to files that settled
+async fn run_watch(
+    rule: WatchRule,
+    index: usize,
+    db: Database,
+    protected: ProtectedPaths,
+    poll_interval: Duration,
+    status: SharedStatus,
+) {
+    let cleanup = rule.to_cleanup_rule();
+    let mut watcher = Watcher::new(Duration::from_secs(rule.settle_secs));
+    let mut ticks = tokio::time::interval(poll_interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let scanned = {
+            let cleanup = cleanup.clone();
+            tokio::task::spawn_blocking(move || scan_watched(&cleanup)).await
+        };
+        let files = match scanned {
+            Ok(Ok(files)) => files,
+            Ok(Err(e)) => {
+                lock(&status).watches[index].last_error = Some(format!("{:#}", e));
+                continue;
+            }
+            Err(e) => {
+                tracing::error!(rule = %rule.name, error = %e, "Watch scan failed");
+                continue;
+            }
+        };
+        let settled = watcher.observe(files, Instant::now());
+        lock(&status).watches[index].pending = watcher.pending();
+        if settled.is_empty() {
+            continue;
+        }
+
+        let paths: Vec<PathBuf> = settled.iter().map(|f| f.path.clone()).collect();
+        tracing::info!(
+            rule = %rule.name,
+            files = paths.len(),
+            action = %rule.action,
+            "Acting on watched files"
+        );
+        let report = {
+            let cleanup = cleanup.clone();
+            let protected = protected.clone();
+            db.run(move |db| {
+                let manager = space_saver_core::compress_plugins::global_plugin_manager();
+                let manager = manager.read().ok();
+                Ok(apply_rule(
+                    &cleanup,
+                    settled,
+                    false,
+                    &protected,
+                    manager.as_deref(),
+                    db,
+                ))
+            })
+            .await
+        };
+        watcher.acted_on(&paths);
+
+        let mut status = lock(&status);
+        let entry = &mut status.watches[index];
+        entry.last_event = Some(now());
+        match report {
+            Ok(report) => {
+                entry.handled += report.files.iter().filter(|f| f.error.is_none()).count();
+                entry.freed += report.freed;
+                entry.last_error = report
+                    .error
+                    .or_else(|| report.files.iter().find_map(|f| f.error.clone()));
+            }
+            Err(e) => entry.last_error = Some(format!("{:#}", e)),
+        }
+        if let Some(error) = &entry.last_error {
+            tracing::warn!(rule = %rule.name, error = %error, "Watch rule action failed");
+        }
+    }
+}
+
+/// The files under the folders of `rule` matching its filter; missing
+/// folders are skipped, as they may be mounted later
+fn scan_watched(rule: &CleanupRule) -> Result<Vec<FileInfo>> {
+    let api = ServiceApi::new();
+    let filter = rule_filter(rule);
+    let mut files = Vec::new();
+    for path in rule.paths.iter().filter(|p| p.exists()) {
+        files.extend(api.scan_filtered(path, Some(&filter))?);
+    }
+    Ok(files)
+}
+
+/// Size and modification time, how a change is noticed
+type FileState = (u64, i64);
+
+/// What a watch rule has seen of its folders between polls
+struct Watcher {
+    settle: Duration,
+    /// Files as last seen at rest
+    known: HashMap<PathBuf, FileState>,
+    /// Files new or changed since, and when they were first seen so
+    changed: HashMap<PathBuf, (FileState, Instant)>,
+    primed: bool,
+}
+
+impl Watcher {
+    fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            known: HashMap::new(),
+            changed: HashMap::new(),
+            primed: false,
+        }
+    }
+
+    /// Compare the folders' `files` at `now` with the last look; returns the
+    /// new or changed files that have stayed the same for the settle time.
+    /// The first look only learns what is there.
+    fn observe(&mut self, files: Vec<FileInfo>, now: Instant) -> Vec<FileInfo> {
+        let first = !std::mem::replace(&mut self.primed, true);
+        let mut present = HashSet::new();
+        let mut settled = Vec::new();
+        for file in files {
+            let state = (file.size, file.modified);
+            present.insert(file.path.clone());
+            if first {
+                self.known.insert(file.path, state);
+                continue;
+            }
+            if self.known.get(&file.path) == Some(&state) {
+                self.changed.remove(&file.path);
+                continue;
+            }
+            match self.changed.get(&file.path) {
+                Some((seen, since)) if *seen == state => {
+                    if now.duration_since(*since) >= self.settle {
+                        settled.push(file);
+                    }
+                }
+                _ => {
+                    self.changed.insert(file.path, (state, now));
+                }
+            }
+        }
+        self.known.retain(|path, _| present.contains(path));
+        self.changed.retain(|path, _| present.contains(path));
+        settled
+    }
+
+    /// Files waiting to settle
+    fn pending(&self) -> usize {
+        self.changed.len()
+    }
+
+    /// Take what `paths` look like after the action as at rest, so the
+    /// action's own changes (a file compressed in place) are not acted on
+    /// again
+    fn acted_on(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.changed.remove(path);
+            match file_state(path) {
+                Some(state) => self.known.insert(path.clone(), state),
+                None => self.known.remove(path),
+            };
+        }
+    }
+}
+
+fn file_state(path: &Path) -> Option<FileState> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs() as i64;
+    Some((metadata.len(), modified))
+}
+
+/// Send `command` (`status`, `run <schedule>` or `stop`) to the daemon
+/// listening on `socket`
+pub async fn send_command(socket: &Path, command: &str) -> Result<ControlResponse> {
+    control::send(socket, command).await
+}
+
+#[cfg(unix)]
+mod control {
+    use super::*;
+    use anyhow::Context;
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// Listen on `socket`, replacing a stale socket file left by a daemon
+    /// that did not stop cleanly
+    pub fn bind(socket: &Path) -> Result<UnixListener> {
+        if socket.exists() {
+            if std::os::unix::net::UnixStream::connect(socket).is_ok() {
+                anyhow::bail!("A daemon is already running (socket {})", socket.display());
+            }
+            fs::remove_file(socket)?;
+        }
+        if let Some(parent) = socket.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(socket)
+            .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+        // Only the owner may control the daemon
+        fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    pub async fn serve(listener: UnixListener, runs: Runs, stop: watch::Sender<bool>) {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Control connection failed");
+                    continue;
+                }
+            };
+            let (runs, stop) = (runs.clone(), stop.clone());
+            tokio::spawn(async move {
+                if let Err(e) = answer(stream, &runs, &stop).await {
+                    tracing::warn!(error = %e, "Control connection failed");
+                }
+            });
+        }
+    }
+
+    async fn answer(stream: UnixStream, runs: &Runs, stop: &watch::Sender<bool>) -> Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        while let Some(line) = lines.next_line().await? {
+            let response = match line.trim() {
+                "status" => {
+                    let mut current = lock(&runs.status).clone();
+                    current.queued = runs.scheduler.queue_length().await;
+                    current.running = runs.scheduler.running_count();
+                    ControlResponse {
+                        ok: true,
+                        status: Some(current),
+                        error: None,
+                    }
+                }
+                command if command.starts_with("run ") => {
+                    match runs.queue_named(command["run ".len()..].trim()).await {
+                        Ok(()) => ControlResponse {
+                            ok: true,
+                            status: None,
+                            error: None,
+                        },
+                        Err(e) => ControlResponse {
+                            ok: false,
+                            status: None,
+                            error: Some(format!("{:#}", e)),
+                        },
+                    }
+                }
+                "stop" => ControlResponse {
+                    ok: true,
+                    status: None,
+                    error: None,
+                },
+                other => ControlResponse {
+                    ok: false,
+                    status: None,
+                    error: Some(format!("Unknown command: {}", other)),
+                },
+            };
+            let mut reply = serde_json::to_string(&response)?;
+            reply.push('\n');
+            write.write_all(reply.as_bytes()).await?;
+            if line.trim() == "stop" {
+                let _ = stop.send(true);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn send(socket: &Path, command: &str) -> Result<ControlResponse> {
+        let stream = UnixStream::connect(socket).await.map_err(|e| {
+            Error::NotFound(format!(
+                "No daemon is running (socket {}): {}",
+                socket.display(),
+                e
+            ))
+        })?;
+        let (read, mut write) = stream.into_split();
+        write.write_all(format!("{}\n", command).as_bytes()).await?;
+        let line = BufReader::new(read)
+            .lines()
+            .next_line()
+            .await?
+            .context("The daemon closed the connection without answering")?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+#[cfg(not(unix))]
+mod control {
+    use super::*;
+
+    fn unsupported() -> anyhow::Error {
+        Error::InvalidInput("The daemon needs a Unix domain socket, not available here".to_string())
+            .into()
+    }
+
+    pub struct Listener;
+
+    pub fn bind(_socket: &Path) -> Result<Listener> {
+        Err(unsupported())
+    }
+
+    pub async fn serve(_listener: Listener, _runs: Runs, _stop: watch::Sender<bool>) {}
+
+    pub async fn send(_socket: &Path, _command: &str) -> Result<ControlResponse> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::FileType;
+    use tempfile::tempdir;
+
+    fn file(path: &str, size: u64, modified: i64) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path),
+            size,
+            modified,
+            file_type: FileType::Other,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    #[test]
+    fn test_watcher_acts_on_new_files_once_they_settle() {
+        let settle = Duration::from_secs(10);
+        let mut watcher = Watcher::new(settle);
+        let start = Instant::now();
+        assert!(watcher
+            .observe(vec![file("/w/old.log", 1, 1)], start)
+            .is_empty());
+
+        let files = || vec![file("/w/old.log", 1, 1), file("/w/new.log", 5, 2)];
+        assert!(watcher.observe(files(), start).is_empty());
+        assert_eq!(watcher.pending(), 1);
+        assert!(watcher.observe(files(), start + settle / 2).is_empty());
+        // Still being written: the settle time starts over
+        let growing = vec![file("/w/old.log", 1, 1), file("/w/new.log", 9, 3)];
+        assert!(watcher.observe(growing.clone(), start + settle).is_empty());
+        let settled = watcher.observe(growing, start + settle * 2);
+        assert_eq!(settled.len(), 1);
+        assert_eq!(settled[0].path, PathBuf::from("/w/new.log"));
+
+        // Removed files are forgotten
+        assert!(watcher.observe(Vec::new(), start + settle * 3).is_empty());
+        assert_eq!(watcher.pending(), 0);
+        assert!(watcher.known.is_empty());
+    }
+
+    #[test]
+    fn test_watcher_ignores_its_own_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.png");
+        fs::write(&path, b"original").unwrap();
+        let mut watcher = Watcher::new(Duration::ZERO);
+        let start = Instant::now();
+        watcher.observe(Vec::new(), start);
+        let (size, modified) = file_state(&path).unwrap();
+        let seen = || {
+            vec![FileInfo {
+                path: path.clone(),
+                ..file("", size, modified)
+            }]
+        };
+        watcher.observe(seen(), start);
+        assert_eq!(watcher.observe(seen(), start).len(), 1);
+
+        fs::write(&path, b"small").unwrap();
+        watcher.acted_on(std::slice::from_ref(&path));
+        let (size, modified) = file_state(&path).unwrap();
+        let after = vec![FileInfo {
+            path: path.clone(),
+            ..file("", size, modified)
+        }];
+        assert!(watcher.observe(after.clone(), start).is_empty());
+        assert!(watcher.observe(after, start).is_empty());
+    }
+
+    #[test]
+    fn test_upcoming_runs_interleave_the_schedules() {
+        let schedule = |name: &str, every_minutes: u32, next_run: i64| ScheduleStatus {
+            name: name.to_string(),
+            task: "scan".to_string(),
+            every_minutes,
+            next_run,
+            last_run: None,
+            last_result: None,
+            last_failed: false,
+            busy: false,
+        };
+        let status = DaemonStatus {
+            pid: 1,
+            started_at: 0,
+            queued: 0,
+            running: 0,
+            schedules: vec![
+                schedule("hourly", 60, 3600),
+                schedule("weekly", 10080, 5000),
+            ],
+            watches: vec![],
+        };
+        let runs: Vec<(String, i64)> = status
+            .upcoming_runs(4)
+            .into_iter()
+            .map(|run| (run.schedule, run.at))
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                ("hourly".to_string(), 3600),
+                ("weekly".to_string(), 5000),
+                ("hourly".to_string(), 7200),
+                ("hourly".to_string(), 10800),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_schedule_once_without_a_daemon() {
+        let schedule = Schedule {
+            name: "tidy".to_string(),
+            task: "maintenance".to_string(),
+            every_minutes: 60,
+            paths: vec![],
+            rules: vec![],
+            enabled: false,
+        };
+        let db = Database::in_memory().unwrap();
+        let message = run_schedule_once(
+            &schedule,
+            &[],
+            db,
+            ProtectedPaths::empty(),
+            Hooks::default(),
+        )
+        .await
+        .unwrap();
+        assert!(message.starts_with("Pruned 0 stored files"));
+    }
+
+    async fn eventually(done: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        done()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_daemon_applies_watch_rules_and_answers_on_its_socket() {
+        let dir = tempdir().unwrap();
+        let watched = dir.path().join("incoming");
+        fs::create_dir(&watched).unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let config = Config {
+            watch_rules: vec![WatchRule {
+                name: "logs".to_string(),
+                paths: vec![watched.clone()],
+                filter: Some("ext in (log)".to_string()),
+                action: "delete".to_string(),
+                archive_dir: None,
+                settle_secs: 0,
+                enabled: true,
+            }],
+            schedules: vec![Schedule {
+                name: "tidy".to_string(),
+                task: "maintenance".to_string(),
+                every_minutes: 60,
+                paths: vec![],
+                rules: vec![],
+                enabled: true,
+            }],
+            daemon_socket: socket.clone(),
+            // Nothing pops up on the machine running the tests
+            notifications: space_saver_utils::NotificationConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let daemon = Daemon::new(config, Database::in_memory().unwrap())
+            .with_protected_paths(ProtectedPaths::empty())
+            .with_poll_interval(Duration::from_millis(20));
+        let running = tokio::spawn(daemon.run());
+
+        assert!(eventually(|| socket.exists()).await);
+        // Let the watcher take its first look
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        fs::write(watched.join("build.log"), b"noise").unwrap();
+        fs::write(watched.join("notes.txt"), b"keep").unwrap();
+        assert!(eventually(|| !watched.join("build.log").exists()).await);
+        assert!(watched.join("notes.txt").exists());
+
+        let response = send_command(&socket, "status").await.unwrap();
+        let status = response.status.unwrap();
+        assert_eq!(status.pid, std::process::id());
+        assert_eq!(status.watches[0].handled, 1);
+        assert_eq!(status.watches[0].freed, 5);
+
+        // Schedules run on request as well as on their timer
+        assert!(send_command(&socket, "run tidy").await.unwrap().ok);
+        let ran = || async {
+            let status = send_command(&socket, "status").await.unwrap().status;
+            status.unwrap().schedules[0].last_run.is_some()
+        };
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !ran().await && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(ran().await);
+        let response = send_command(&socket, "run nightly").await.unwrap();
+        assert!(response
+            .error
+            .unwrap()
+            .contains("no schedule called 'nightly'"));
+
+        let response = send_command(&socket, "bogus").await.unwrap();
+        assert!(!response.ok);
+        assert!(send_command(&socket, "stop").await.unwrap().ok);
+        running.await.unwrap().unwrap();
+        assert!(!socket.exists());
+        assert!(send_command(&socket, "status").await.is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! Keep strategies for duplicate groups: which copy survives when the rest
+//! of a group is deleted.
+
+use crate::api::DuplicateGroup;
+use crate::protection::ProtectedPaths;
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use space_saver_core::FileInfo;
+use std::cmp::Reverse;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Chooses the copies to keep in a duplicate group
+pub trait KeepStrategy: Send + Sync {
+    /// Indices into `files` of the copies to keep. An empty result leaves
+    /// the group untouched.
+    fn keep(&self, files: &[FileInfo]) -> Vec<usize>;
+}
+
+/// Keeps the most recently modified copy
+pub struct KeepNewest;
+
+impl KeepStrategy for KeepNewest {
+    fn keep(&self, files: &[FileInfo]) -> Vec<usize> {
+        best_by_key(files, |f| (Reverse(f.modified), f.path.clone()))
+    }
+}
+
+/// Keeps the least recently modified copy
+pub struct KeepOldest;
+
+impl KeepStrategy for KeepOldest {
+    fn keep(&self, files: &[FileInfo]) -> Vec<usize> {
+        best_by_key(files, |f| (f.modified, f.path.clone()))
+    }
+}
+
+/// Keeps the copy with the shortest path, usually the "original" rather
+/// than `Copy of ...` or a deeply nested backup
+pub struct KeepShortestPath;
+
+impl KeepStrategy for KeepShortestPath {
+    fn keep(&self, files: &[FileInfo]) -> Vec<usize> {
+        best_by_key(files, |f| {
+            (
+                f.path.as_os_str().to_string_lossy().chars().count(),
+                f.path.clone(),
+            )
+        })
+    }
+}
+
+/// Keeps the copy whose path sorts first, a stable choice that does not
+/// depend on modification times
+pub struct KeepFirstPath;
+
+impl KeepStrategy for KeepFirstPath {
+    fn keep(&self, files: &[FileInfo]) -> Vec<usize> {
+        best_by_key(files, |f| f.path.clone())
+    }
+}
+
+/// Keeps every copy at or beneath `prefix` (component-wise) and deletes the
+/// copies elsewhere. Groups with no copy under `prefix` are left untouched.
+pub struct KeepInDirectory {
+    prefix: PathBuf,
+}
+
+impl KeepInDirectory {
+    pub fn new(prefix: impl Into<PathBuf>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl KeepStrategy for KeepInDirectory {
+    fn keep(&self, files: &[FileInfo]) -> Vec<usize> {
+        files
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.path.starts_with(&self.prefix))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+fn best_by_key<K: Ord>(files: &[FileInfo], key: impl Fn(&FileInfo) -> K) -> Vec<usize> {
+    files
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, f)| key(f))
+        .map(|(i, _)| vec![i])
+        .unwrap_or_default()
+}
+
+/// Serializable choice of [`KeepStrategy`] for the CLI and frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum KeepRule {
+    Newest,
+    Oldest,
+    ShortestPath,
+    FirstPath,
+    InDirectory { prefix: String },
+}
+
+impl KeepRule {
+    pub fn strategy(&self) -> Box<dyn KeepStrategy> {
+        match self {
+            KeepRule::Newest => Box::new(KeepNewest),
+            KeepRule::Oldest => Box::new(KeepOldest),
+            KeepRule::ShortestPath => Box::new(KeepShortestPath),
+            KeepRule::FirstPath => Box::new(KeepFirstPath),
+            KeepRule::InDirectory { prefix } => Box::new(KeepInDirectory::new(prefix)),
+        }
+    }
+}
+
+impl FromStr for KeepRule {
+    type Err = anyhow::Error;
+
+    /// `newest`, `oldest`, `shortest-path` or `first-path`; use [`KeepRule::InDirectory`]
+    /// directly for a directory
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "newest" => Ok(KeepRule::Newest),
+            "oldest" => Ok(KeepRule::Oldest),
+            "shortest-path" | "shortest" => Ok(KeepRule::ShortestPath),
+            "first-path" | "first" => Ok(KeepRule::FirstPath),
+            _ => bail!(
+                "Unknown keep strategy: {} (expected newest, oldest, shortest-path or first-path)",
+                s
+            ),
+        }
+    }
+}
+
+/// What a keep strategy decided for one duplicate group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateResolution {
+    pub hash: String,
+    pub keep: Vec<FileInfo>,
+    pub delete: Vec<FileInfo>,
+    /// Bytes freed by deleting `delete`
+    pub reclaimable: u64,
+    /// Copies the strategy would have deleted but that are protected; they
+    /// are also listed in `keep`
+    #[serde(default)]
+    pub protected: Vec<FileInfo>,
+}
+
+impl DuplicateResolution {
+    /// Moves every protected copy from `delete` to `keep` (and `protected`)
+    pub fn protect(&mut self, protected: &ProtectedPaths) {
+        let (refused, delete): (Vec<FileInfo>, Vec<FileInfo>) = std::mem::take(&mut self.delete)
+            .into_iter()
+            .partition(|f| protected.is_protected(&f.path));
+        self.delete = delete;
+        self.reclaimable = self.delete.iter().map(|f| f.size).sum();
+        self.keep.extend(refused.iter().cloned());
+        self.protected.extend(refused);
+    }
+}
+
+/// Splits every group into keep/delete sets. A group always keeps at least
+/// one copy: when the strategy keeps nothing, nothing is deleted.
+pub fn resolve_duplicates(
+    groups: &[DuplicateGroup],
+    strategy: &dyn KeepStrategy,
+) -> Vec<DuplicateResolution> {
+    groups
+        .iter()
+        .map(|group| {
+            let kept = strategy.keep(&group.files);
+            let (keep, delete): (Vec<FileInfo>, Vec<FileInfo>) =
+                if kept.iter().any(|&i| i < group.files.len()) {
+                    let mut keep = Vec::new();
+                    let mut delete = Vec::new();
+                    for (i, file) in group.files.iter().enumerate() {
+                        if kept.contains(&i) {
+                            keep.push(file.clone());
+                        } else {
+                            delete.push(file.clone());
+                        }
+                    }
+                    (keep, delete)
+                } else {
+                    (group.files.clone(), Vec::new())
+                };
+            DuplicateResolution {
+                hash: group.hash.clone(),
+                reclaimable: delete.iter().map(|f| f.size).sum(),
+                keep,
+                delete,
+                protected: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::FileType;
+
+    fn file(path: &str, modified: i64) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path),
+            size: 100,
+            modified,
+            file_type: FileType::Other,
+            hash: Some("abc".to_string()),
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    fn group(files: Vec<FileInfo>) -> DuplicateGroup {
+        DuplicateGroup {
+            hash: "abc".to_string(),
+            count: files.len(),
+            total_size: files.iter().map(|f| f.size).sum(),
+            wasted_space: 100 * (files.len() as u64 - 1),
+            files,
+        }
+    }
+
+    fn paths(files: &[FileInfo]) -> Vec<&str> {
+        files.iter().map(|f| f.path.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_single_keeper_strategies() {
+        let groups = [group(vec![
+            file("/photos/backup/2019/img.jpg", 300),
+            file("/photos/img.jpg", 200),
+            file("/photos/Copy of img.jpg", 100),
+        ])];
+
+        let newest = &resolve_duplicates(&groups, &KeepNewest)[0];
+        assert_eq!(paths(&newest.keep), vec!["/photos/backup/2019/img.jpg"]);
+        assert_eq!(newest.delete.len(), 2);
+        assert_eq!(newest.reclaimable, 200);
+
+        let oldest = &resolve_duplicates(&groups, &KeepOldest)[0];
+        assert_eq!(paths(&oldest.keep), vec!["/photos/Copy of img.jpg"]);
+
+        let shortest = &resolve_duplicates(&groups, &KeepShortestPath)[0];
+        assert_eq!(paths(&shortest.keep), vec!["/photos/img.jpg"]);
+        assert_eq!(
+            paths(&shortest.delete),
+            vec!["/photos/backup/2019/img.jpg", "/photos/Copy of img.jpg"]
+        );
+
+        let first = &resolve_duplicates(&groups, &KeepFirstPath)[0];
+        assert_eq!(paths(&first.keep), vec!["/photos/Copy of img.jpg"]);
+    }
+
+    #[test]
+    fn test_keep_in_directory() {
+        let groups = [
+            group(vec![
+                file("/archive/a.pdf", 0),
+                file("/archive/old/a.pdf", 0),
+                file("/downloads/a.pdf", 0),
+            ]),
+            // No copy under the prefix: left alone rather than emptied
+            group(vec![file("/downloads/b.pdf", 0), file("/desktop/b.pdf", 0)]),
+            // A sibling sharing a name prefix is not "in" the directory
+            group(vec![file("/archive2/c.pdf", 0), file("/tmp/c.pdf", 0)]),
+        ];
+        let resolved = resolve_duplicates(&groups, &KeepInDirectory::new("/archive"));
+
+        assert_eq!(
+            paths(&resolved[0].keep),
+            vec!["/archive/a.pdf", "/archive/old/a.pdf"]
+        );
+        assert_eq!(paths(&resolved[0].delete), vec!["/downloads/a.pdf"]);
+        assert!(resolved[1].delete.is_empty());
+        assert_eq!(resolved[1].keep.len(), 2);
+        assert!(resolved[2].delete.is_empty());
+    }
+
+    #[test]
+    fn test_protected_copies_are_kept() {
+        let groups = [group(vec![
+            file("/photos/img.jpg", 0),
+            file("/archive/img.jpg", 0),
+            file("/downloads/img.jpg", 0),
+        ])];
+        let mut resolved = resolve_duplicates(&groups, &KeepShortestPath);
+        resolved[0].protect(&ProtectedPaths::empty().with_paths(["/archive"]));
+
+        assert_eq!(
+            paths(&resolved[0].keep),
+            vec!["/photos/img.jpg", "/archive/img.jpg"]
+        );
+        assert_eq!(paths(&resolved[0].delete), vec!["/downloads/img.jpg"]);
+        assert_eq!(paths(&resolved[0].protected), vec!["/archive/img.jpg"]);
+        assert_eq!(resolved[0].reclaimable, 100);
+    }
+
+    #[test]
+    fn test_keep_rule_parsing_and_serde() {
+        assert_eq!("newest".parse::<KeepRule>().unwrap(), KeepRule::Newest);
+        assert_eq!(
+            "Shortest_Path".parse::<KeepRule>().unwrap(),
+            KeepRule::ShortestPath
+        );
+        assert_eq!(
+            "first-path".parse::<KeepRule>().unwrap(),
+            KeepRule::FirstPath
+        );
+        assert!("largest".parse::<KeepRule>().is_err());
+
+        let rule: KeepRule =
+            serde_json::from_str(r#"{"strategy":"in_directory","prefix":"/archive"}"#).unwrap();
+        assert_eq!(
+            rule,
+            KeepRule::InDirectory {
+                prefix: "/archive".to_string()
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&KeepRule::Oldest).unwrap(),
+            r#"{"strategy":"oldest"}"#
+        );
+    }
+}
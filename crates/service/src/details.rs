@@ -0,0 +1,129 @@
+//! Everything worth showing about one file.
+//!
+//! [`file_details`] gathers what the comparison views put next to a
+//! thumbnail: size and timestamps for any file, pixel dimensions and EXIF
+//! for photos, and length, resolution and codec for videos when ffprobe is
+//! around (see [`ffprobe_path`]).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use space_saver_core::scanner::{DefaultFileScanner, FileScanner, FileType};
+use space_saver_core::{ExifInfo, VideoInfo};
+use space_saver_utils::{Config, Error};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Size, timestamps and media metadata of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDetails {
+    pub path: String,
+    pub size: u64,
+    pub file_type: FileType,
+    /// Seconds since the Unix epoch, like `FileInfo::modified`
+    pub modified: i64,
+    /// None where the file system does not record it
+    pub created: Option<i64>,
+    pub accessed: Option<i64>,
+    /// Pixel dimensions of an image, or of a video's picture
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub exif: Option<ExifInfo>,
+    /// None for other files, and for videos when ffprobe is missing or
+    /// cannot read them
+    pub video: Option<VideoInfo>,
+}
+
+/// Where to find ffprobe: `tool_paths.ffprobe`, else the PATH
+pub fn ffprobe_path(config: &Config) -> Option<PathBuf> {
+    match config.tool_paths.get("ffprobe").map(|p| p.trim()) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => crate::tools::find_executable("ffprobe"),
+    }
+}
+
+/// The details of the file at `path`, asking `ffprobe` about videos. Fails
+/// only when the file itself cannot be read; metadata that cannot be is
+/// left out.
+pub fn file_details(path: &Path, ffprobe: Option<&Path>) -> Result<FileDetails> {
+    let metadata = fs::metadata(path).map_err(Error::from)?;
+    if !metadata.is_file() {
+        return Err(Error::InvalidInput(format!("Not a file: {}", path.display())).into());
+    }
+    let file = DefaultFileScanner::new()
+        .include_hidden(true)
+        .scan(path)?
+        .pop()
+        .ok_or_else(|| Error::NotFound(format!("File not found: {}", path.display())))?;
+
+    let mut details = FileDetails {
+        path: path.to_string_lossy().to_string(),
+        size: file.size,
+        modified: file.modified,
+        created: unix_secs(metadata.created()),
+        accessed: unix_secs(metadata.accessed()),
+        width: None,
+        height: None,
+        exif: None,
+        video: None,
+        file_type: file.file_type,
+    };
+    if matches!(details.file_type, FileType::Video) {
+        details.video = ffprobe.and_then(|ffprobe| {
+            space_saver_core::probe_video(ffprobe, path)
+                .map_err(|e| debug!("No video details for {}: {:#}", path.display(), e))
+                .ok()
+        });
+        if let Some(video) = &details.video {
+            (details.width, details.height) = (video.width, video.height);
+        }
+    } else {
+        // Headers decide, not extensions: RAWs and TIFFs count as "other"
+        if let Some((width, height)) = space_saver_core::image_dimensions(path) {
+            (details.width, details.height) = (Some(width), Some(height));
+        }
+        details.exif = space_saver_core::read_exif(path)
+            .map_err(|e| debug!("No EXIF for {}: {:#}", path.display(), e))
+            .ok()
+            .flatten();
+    }
+    Ok(details)
+}
+
+fn unix_secs(time: std::io::Result<SystemTime>) -> Option<i64> {
+    let secs = time.ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    i64::try_from(secs).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_utils::ErrorCode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_file_details_of_images_and_other_files() {
+        let dir = tempdir().unwrap();
+        let image = dir.path().join("photo.png");
+        image::RgbImage::new(40, 30).save(&image).unwrap();
+        let details = file_details(&image, None).unwrap();
+        assert!(matches!(details.file_type, FileType::Image));
+        assert_eq!((details.width, details.height), (Some(40), Some(30)));
+        assert!(details.modified > 0);
+        assert!(details.exif.is_none());
+
+        // Without ffprobe a video still has its size and dates
+        let video = dir.path().join("clip.mp4");
+        fs::write(&video, b"not really a video").unwrap();
+        let details = file_details(&video, Some(&dir.path().join("no-ffprobe"))).unwrap();
+        assert_eq!(details.size, 18);
+        assert!(details.video.is_none());
+        assert_eq!(details.width, None);
+
+        let err = file_details(&dir.path().join("gone.png"), None).unwrap_err();
+        assert_eq!(Error::from(err).code(), ErrorCode::NotFound);
+        let err = file_details(dir.path(), None).unwrap_err();
+        assert_eq!(Error::from(err).code(), ErrorCode::InvalidInput);
+    }
+}
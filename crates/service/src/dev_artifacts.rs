@@ -0,0 +1,541 @@
+//! Build output left behind in project folders.
+//!
+//! Old repositories hold most of a developer's reclaimable space: a Rust
+//! `target`, a `node_modules`, a `dist` bundle or a Python `.venv` each
+//! rebuilt by one command. [`find_projects`] walks roots for project
+//! folders (a `Cargo.toml`, `package.json`, `pyproject.toml` or `.git`) and
+//! the build output directly inside them. A `dist` is only build output when
+//! git leaves it to the build: plenty of projects commit theirs.
+//! [`DevProject::measure`] sizes each
+//! artifact with the time it was last built, and takes the project's last
+//! activity from its own files (the artifacts left out). Projects untouched
+//! for the given number of months are suggested for cleaning, the way
+//! `cargo clean` would.
+
+use crate::api::FileError;
+use crate::app_caches::{build_output, is_real_dir, remove_tree, CacheCategory};
+use crate::file_ops::{DeleteMode, DeleteResult};
+use crate::plan::{ActionPlan, PlannedAction};
+use crate::protection::ProtectedPaths;
+use serde::{Deserialize, Serialize};
+use space_saver_utils::Error;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Projects untouched for this many months are suggested for cleaning
+/// unless told otherwise
+pub const DEFAULT_UNTOUCHED_MONTHS: u32 = 6;
+
+/// What makes a folder a project
+const PROJECT_MARKERS: &[&str] = &["Cargo.toml", "package.json", "pyproject.toml", ".git"];
+
+/// Files in `.git` that change on every commit and checkout
+const GIT_ACTIVITY: &[&str] = &["HEAD", "index"];
+
+/// A kind of build output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// Rust `target`
+    Target,
+    NodeModules,
+    /// A JavaScript or Python `dist` bundle
+    Dist,
+    /// A Python virtual environment in `.venv`
+    Venv,
+}
+
+impl ArtifactKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ArtifactKind::Target => "target",
+            ArtifactKind::NodeModules => "node_modules",
+            ArtifactKind::Dist => "dist",
+            ArtifactKind::Venv => ".venv",
+        }
+    }
+
+    /// What brings it back once removed
+    pub fn rebuilt_by(self) -> &'static str {
+        match self {
+            ArtifactKind::Target => "cargo build",
+            ArtifactKind::NodeModules => "npm install",
+            ArtifactKind::Dist => "the project's build",
+            ArtifactKind::Venv => "python -m venv .venv",
+        }
+    }
+}
+
+/// What `dir` is when it is build output of the project it sits in: a
+/// `target` or `node_modules` next to its manifest, a `dist` next to a
+/// `package.json` or `pyproject.toml` that git does not track, or a `.venv`
+/// holding a `pyvenv.cfg` in a project folder
+pub fn artifact_kind(dir: &Path) -> Option<ArtifactKind> {
+    match build_output(dir) {
+        Some(CacheCategory::Target) => return Some(ArtifactKind::Target),
+        Some(CacheCategory::NodeModules) => return Some(ArtifactKind::NodeModules),
+        _ => {}
+    }
+    let parent = dir.parent()?;
+    match dir.file_name()?.to_str()? {
+        "dist"
+            if ["package.json", "pyproject.toml"]
+                .iter()
+                .any(|manifest| parent.join(manifest).is_file())
+                && !tracked_by_git(dir) =>
+        {
+            Some(ArtifactKind::Dist)
+        }
+        ".venv" if dir.join("pyvenv.cfg").is_file() && is_project(parent) => {
+            Some(ArtifactKind::Venv)
+        }
+        _ => None,
+    }
+}
+
+/// Whether `dir` holds files committed to git and git does not ignore it.
+/// Outside a repository, or without git, nothing is tracked.
+fn tracked_by_git(dir: &Path) -> bool {
+    let Some(parent) = dir.parent() else {
+        return false;
+    };
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(parent)
+            .args(args)
+            .arg(dir)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+    };
+    let tracked = git(&["ls-files", "--"])
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty());
+    tracked && !git(&["check-ignore", "-q", "--"]).is_ok_and(|output| output.status.success())
+}
+
+fn is_project(dir: &Path) -> bool {
+    !project_markers(dir).is_empty()
+}
+
+fn project_markers(dir: &Path) -> Vec<String> {
+    PROJECT_MARKERS
+        .iter()
+        .filter(|marker| dir.join(marker).exists())
+        .map(|marker| marker.to_string())
+        .collect()
+}
+
+/// The project folders beneath `roots` that hold build output, with it.
+/// Nothing inside build output or `.git` is looked at.
+pub fn find_projects(roots: &[PathBuf]) -> Vec<(PathBuf, Vec<(ArtifactKind, PathBuf)>)> {
+    let mut projects: BTreeMap<PathBuf, Vec<(ArtifactKind, PathBuf)>> = BTreeMap::new();
+    for root in roots {
+        let mut walk = WalkDir::new(root).into_iter();
+        while let Some(entry) = walk.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            if let Some(kind) = artifact_kind(entry.path()) {
+                walk.skip_current_dir();
+                let Some(project) = entry.path().parent().filter(|p| is_project(p)) else {
+                    continue;
+                };
+                let artifacts = projects.entry(project.to_path_buf()).or_default();
+                // Overlapping roots would list an artifact twice
+                if !artifacts.iter().any(|(_, path)| path == entry.path()) {
+                    artifacts.push((kind, entry.into_path()));
+                }
+            } else if entry.file_name() == ".git" {
+                walk.skip_current_dir();
+            }
+        }
+    }
+    projects.into_iter().collect()
+}
+
+/// One build output folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildArtifact {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+    pub size: u64,
+    pub file_count: usize,
+    /// When a file in it was last written (Unix seconds)
+    pub last_build: Option<i64>,
+}
+
+impl BuildArtifact {
+    /// `path` with its size, file count and last build. Links are not
+    /// followed and unreadable entries are left out.
+    pub fn measure(kind: ArtifactKind, path: PathBuf) -> Self {
+        let mut size = 0;
+        let mut file_count = 0;
+        let mut last_build = None;
+        for entry in WalkDir::new(&path).into_iter().flatten() {
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            size += metadata.len();
+            file_count += 1;
+            last_build = last_build.max(modified(&metadata));
+        }
+        Self {
+            kind,
+            path,
+            size,
+            file_count,
+            last_build,
+        }
+    }
+}
+
+/// A project folder and its build output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevProject {
+    pub root: PathBuf,
+    /// What makes it a project, e.g. ["Cargo.toml", ".git"]
+    pub markers: Vec<String>,
+    /// When one of its own files (build output left out) or its git
+    /// checkout last changed (Unix seconds)
+    pub last_activity: Option<i64>,
+    /// Largest first
+    pub artifacts: Vec<BuildArtifact>,
+    pub size: u64,
+    /// Untouched since before the cutoff: its build output can go
+    pub suggested: bool,
+}
+
+impl DevProject {
+    /// `root` with its `artifacts` measured; suggested when nothing in it
+    /// changed since `cutoff` (Unix seconds). Artifacts without files are
+    /// left out.
+    pub fn measure(root: PathBuf, artifacts: Vec<(ArtifactKind, PathBuf)>, cutoff: i64) -> Self {
+        let mut artifacts: Vec<BuildArtifact> = artifacts
+            .into_iter()
+            .map(|(kind, path)| BuildArtifact::measure(kind, path))
+            .filter(|artifact| artifact.file_count > 0)
+            .collect();
+        artifacts.sort_by_key(|artifact| std::cmp::Reverse(artifact.size));
+        let last_activity = last_activity(&root);
+        Self {
+            markers: project_markers(&root),
+            suggested: last_activity.is_some_and(|at| at < cutoff),
+            size: artifacts.iter().map(|a| a.size).sum(),
+            last_activity,
+            artifacts,
+            root,
+        }
+    }
+}
+
+/// Newest change among the files of the project at `root`, build output
+/// and the rest of `.git` left out
+fn last_activity(root: &Path) -> Option<i64> {
+    let git = root.join(".git");
+    let mut newest = GIT_ACTIVITY
+        .iter()
+        .filter_map(|file| fs::metadata(git.join(file)).ok())
+        .filter_map(|metadata| modified(&metadata))
+        .max();
+    let mut walk = WalkDir::new(root).into_iter();
+    while let Some(entry) = walk.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.file_type().is_dir() {
+            if entry.depth() > 0
+                && (entry.file_name() == ".git" || artifact_kind(entry.path()).is_some())
+            {
+                walk.skip_current_dir();
+            }
+            continue;
+        }
+        if let Ok(metadata) = entry.metadata() {
+            newest = newest.max(modified(&metadata));
+        }
+    }
+    newest
+}
+
+fn modified(metadata: &fs::Metadata) -> Option<i64> {
+    let since_epoch = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(since_epoch.as_secs()).ok()
+}
+
+/// The projects with build output found beneath some roots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevArtifactReport {
+    /// Suggested projects first, then the largest
+    pub projects: Vec<DevProject>,
+    pub total_size: u64,
+    /// Bytes in the build output of suggested projects
+    pub reclaimable: u64,
+    /// Projects untouched for this many months are suggested
+    pub untouched_months: u32,
+}
+
+impl DevArtifactReport {
+    /// `projects` with build output left, suggested ones first
+    pub fn new(projects: Vec<DevProject>, untouched_months: u32) -> Self {
+        let mut projects: Vec<DevProject> = projects
+            .into_iter()
+            .filter(|project| !project.artifacts.is_empty())
+            .collect();
+        projects.sort_by_key(|project| (!project.suggested, std::cmp::Reverse(project.size)));
+        Self {
+            total_size: projects.iter().map(|p| p.size).sum(),
+            reclaimable: projects
+                .iter()
+                .filter(|p| p.suggested)
+                .map(|p| p.size)
+                .sum(),
+            untouched_months,
+            projects,
+        }
+    }
+
+    /// The build output of the suggested projects
+    pub fn suggested(&self) -> impl Iterator<Item = &BuildArtifact> {
+        self.projects
+            .iter()
+            .filter(|project| project.suggested)
+            .flat_map(|project| &project.artifacts)
+    }
+}
+
+/// The start of the cutoff for projects untouched for `months` months
+/// (of 30 days) before `now`
+pub fn untouched_cutoff(now: i64, months: u32) -> i64 {
+    now - i64::from(months) * 30 * 86_400
+}
+
+/// Why `artifact` must not be removed now, if it must not
+fn refusal(artifact: &BuildArtifact, protected: &ProtectedPaths) -> Option<Error> {
+    if let Some(e) = crate::duplicate_dirs::refusal(&artifact.path, protected) {
+        return Some(e);
+    }
+    if !is_real_dir(&artifact.path) {
+        return Some(Error::NotFound(format!(
+            "No longer a folder: {}",
+            artifact.path.display()
+        )));
+    }
+    if artifact_kind(&artifact.path) != Some(artifact.kind) {
+        return Some(Error::InvalidInput(format!(
+            "No longer build output of its project: {}",
+            artifact.path.display()
+        )));
+    }
+    None
+}
+
+/// What removing the build output of the suggested projects in `report`
+/// with `mode` would do
+pub fn removal_plan(
+    report: &DevArtifactReport,
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+) -> ActionPlan {
+    let mut actions = Vec::new();
+    let mut skipped = Vec::new();
+    for artifact in report.suggested() {
+        match refusal(artifact, protected) {
+            Some(e) => skipped.push(FileError::new(&artifact.path, e)),
+            None => actions.push(PlannedAction {
+                path: artifact.path.clone(),
+                action: mode.action().to_string(),
+                size: artifact.size,
+                reclaimed: Some(artifact.size),
+                detail: Some(format!("rebuilt by {}", artifact.kind.rebuilt_by())),
+            }),
+        }
+    }
+    ActionPlan::new(actions, skipped)
+}
+
+/// Remove the build output of the suggested projects in `report` with
+/// `mode`, each folder checked again first, reporting a per-folder outcome
+pub fn remove(
+    report: &DevArtifactReport,
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+) -> Vec<DeleteResult> {
+    report
+        .suggested()
+        .map(|artifact| {
+            let outcome = match refusal(artifact, protected) {
+                Some(e) => Err(e),
+                None => remove_tree(&artifact.path, mode),
+            };
+            DeleteResult::of(&artifact.path, outcome)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn age(path: &Path, days: u64) {
+        let then = SystemTime::now() - Duration::from_secs(days * 86_400);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|f| f.set_modified(then))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_build_output_is_found_only_inside_projects() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(&root.join("tool/Cargo.toml"), "[package]");
+        write(&root.join("tool/target/debug/tool"), "binary");
+        write(&root.join("web/package.json"), "{}");
+        write(&root.join("web/node_modules/react/index.js"), "module");
+        write(&root.join("web/dist/app.js"), "bundle");
+        write(&root.join("py/.git/HEAD"), "ref: refs/heads/main");
+        write(&root.join("py/.venv/pyvenv.cfg"), "home = /usr/bin");
+        write(&root.join("py/.venv/lib/site.py"), "site");
+        // Not build output: no manifest, or a .venv that is no environment
+        write(&root.join("notes/dist/readme.txt"), "mine");
+        write(&root.join("notes/target/plan.txt"), "mine");
+        write(&root.join("other/.git/HEAD"), "ref: refs/heads/main");
+        write(&root.join("other/.venv/data.txt"), "mine");
+
+        let projects = find_projects(&[root.to_path_buf(), root.join("web")]);
+        let found: Vec<(String, Vec<ArtifactKind>)> = projects
+            .iter()
+            .map(|(project, artifacts)| {
+                let name = project.file_name().unwrap().to_string_lossy().into_owned();
+                let mut kinds: Vec<ArtifactKind> =
+                    artifacts.iter().map(|(kind, _)| *kind).collect();
+                kinds.sort();
+                (name, kinds)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("py".to_string(), vec![ArtifactKind::Venv]),
+                ("tool".to_string(), vec![ArtifactKind::Target]),
+                (
+                    "web".to_string(),
+                    vec![ArtifactKind::NodeModules, ArtifactKind::Dist]
+                ),
+            ]
+        );
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    #[test]
+    fn test_dist_committed_to_git_is_not_build_output() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        if !git(root, &["init", "-q"]) {
+            return; // no git here
+        }
+        write(&root.join("lib/package.json"), "{}");
+        write(&root.join("lib/dist/index.js"), "published bundle");
+        write(&root.join("site/package.json"), "{}");
+        write(&root.join("site/.gitignore"), "dist/\n");
+        write(&root.join("site/dist/app.js"), "bundle");
+        write(&root.join("app/pyproject.toml"), "[project]");
+        write(&root.join("app/dist/app.whl"), "wheel");
+        assert!(git(root, &["add", "lib", "site", "app/pyproject.toml"]));
+        assert!(git(root, &["commit", "-q", "-m", "init"]));
+
+        assert_eq!(artifact_kind(&root.join("lib/dist")), None);
+        // Ignored, and not committed
+        assert_eq!(
+            artifact_kind(&root.join("site/dist")),
+            Some(ArtifactKind::Dist)
+        );
+        assert_eq!(
+            artifact_kind(&root.join("app/dist")),
+            Some(ArtifactKind::Dist)
+        );
+        let projects: Vec<PathBuf> = find_projects(&[root.to_path_buf()])
+            .into_iter()
+            .map(|(project, _)| project)
+            .collect();
+        assert_eq!(projects, [root.join("app"), root.join("site")]);
+    }
+
+    #[test]
+    fn test_untouched_projects_are_suggested_and_cleaned() {
+        let dir = tempdir().unwrap();
+        let old = dir.path().join("old");
+        let active = dir.path().join("active");
+        for project in [&old, &active] {
+            write(&project.join("Cargo.toml"), "[package]");
+            write(&project.join("src/main.rs"), "fn main() {}");
+            write(&project.join("target/debug/app"), "binary");
+        }
+        for file in ["Cargo.toml", "src/main.rs", "target/debug/app"] {
+            age(&old.join(file), 400);
+        }
+        // A fresh build alone does not make a project active
+        write(&active.join("target/debug/app"), "new binary");
+        age(&active.join("src/main.rs"), 400);
+        age(&active.join("Cargo.toml"), 10);
+
+        let cutoff = untouched_cutoff(space_saver_utils::time::now(), 6);
+        let projects = find_projects(&[dir.path().to_path_buf()])
+            .into_iter()
+            .map(|(root, artifacts)| DevProject::measure(root, artifacts, cutoff))
+            .collect();
+        let report = DevArtifactReport::new(projects, 6);
+
+        assert_eq!(report.projects.len(), 2);
+        let first = &report.projects[0];
+        assert_eq!(first.root, old);
+        assert!(first.suggested);
+        assert_eq!(first.markers, ["Cargo.toml"]);
+        assert!(first.artifacts[0].last_build.unwrap() < cutoff);
+        assert!(!report.projects[1].suggested);
+        assert_eq!(report.reclaimable, 6);
+        assert_eq!(report.total_size, 16);
+
+        let protected = ProtectedPaths::new();
+        let plan = removal_plan(&report, DeleteMode::Permanent, &protected);
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].path, old.join("target"));
+
+        let results = remove(&report, DeleteMode::Permanent, &protected);
+        assert!(results.iter().all(|r| r.success), "{results:?}");
+        assert!(!old.join("target").exists());
+        assert!(active.join("target").exists());
+
+        // Gone since the scan: refused rather than removed again
+        let results = remove(&report, DeleteMode::Permanent, &protected);
+        assert!(!results[0].success);
+    }
+}
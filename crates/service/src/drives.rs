@@ -0,0 +1,130 @@
+//! Drives and volumes: what is mounted, how big it is and how much is free.
+//!
+//! Volumes come from the operating system (via `sysinfo`); pseudo file
+//! systems without any capacity are left out. A drive counts as a network
+//! drive when its file system is a network protocol (NFS, SMB, AFP, sshfs,
+//! WebDAV, ...), as removable when the operating system reports it so, and
+//! as fixed otherwise.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use sysinfo::{Disk, Disks};
+
+/// Where a drive lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriveKind {
+    Fixed,
+    Removable,
+    Network,
+}
+
+/// A mounted drive or volume with its capacity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskInfo {
+    /// Device or volume name, e.g. "/dev/sda1" or "Backup"
+    pub name: String,
+    pub mount_point: String,
+    /// File system type, e.g. "ext4", "NTFS" or "apfs"
+    pub file_system: String,
+    pub total_space: u64,
+    pub available_space: u64,
+    pub used_space: u64,
+    pub kind: DriveKind,
+    pub read_only: bool,
+}
+
+/// File systems served over the network (lowercase; FUSE mounts report
+/// `fuse.<name>`)
+const NETWORK_FILE_SYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb",
+    "smb2",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "afs",
+    "ncpfs",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "webdav",
+    "davfs",
+    "sshfs",
+    "rclone",
+];
+
+/// Classify a drive by its file system type and the OS's removable flag
+pub fn classify(file_system: &str, removable: bool) -> DriveKind {
+    let file_system = file_system.to_ascii_lowercase();
+    let name = file_system.strip_prefix("fuse.").unwrap_or(&file_system);
+    if NETWORK_FILE_SYSTEMS.contains(&name) {
+        DriveKind::Network
+    } else if removable {
+        DriveKind::Removable
+    } else {
+        DriveKind::Fixed
+    }
+}
+
+impl From<&Disk> for DiskInfo {
+    fn from(disk: &Disk) -> Self {
+        let file_system = disk.file_system().to_string_lossy().to_string();
+        let total_space = disk.total_space();
+        let available_space = disk.available_space().min(total_space);
+        Self {
+            name: disk.name().to_string_lossy().to_string(),
+            mount_point: disk.mount_point().to_string_lossy().to_string(),
+            kind: classify(&file_system, disk.is_removable()),
+            file_system,
+            total_space,
+            available_space,
+            used_space: total_space - available_space,
+            read_only: disk.is_read_only(),
+        }
+    }
+}
+
+/// Every mounted drive with a capacity, by mount point. A volume mounted in
+/// several places is listed once.
+pub fn list_drives() -> Vec<DiskInfo> {
+    let disks = Disks::new_with_refreshed_list();
+    let mut drives: Vec<DiskInfo> = disks
+        .list()
+        .iter()
+        .map(DiskInfo::from)
+        .filter(|d| d.total_space > 0)
+        .collect();
+    drives.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    let mut seen = HashSet::new();
+    drives.retain(|d| seen.insert(d.mount_point.clone()));
+    drives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_file_systems_are_classified_before_removable() {
+        assert_eq!(classify("ext4", false), DriveKind::Fixed);
+        assert_eq!(classify("vfat", true), DriveKind::Removable);
+        assert_eq!(classify("nfs4", false), DriveKind::Network);
+        assert_eq!(classify("fuse.sshfs", false), DriveKind::Network);
+        assert_eq!(classify("SMBFS", true), DriveKind::Network);
+        assert_eq!(classify("fuse.portal", false), DriveKind::Fixed);
+    }
+
+    #[test]
+    fn test_listed_drives_have_consistent_space() {
+        let drives = list_drives();
+        for drive in &drives {
+            assert!(drive.total_space > 0);
+            assert_eq!(drive.used_space + drive.available_space, drive.total_space);
+        }
+        let mounts: HashSet<&str> = drives.iter().map(|d| d.mount_point.as_str()).collect();
+        assert_eq!(mounts.len(), drives.len());
+    }
+}
@@ -0,0 +1,435 @@
+//! Duplicate folders: whole directory trees with identical content.
+//!
+//! Each directory gets a Merkle hash built from its children — file names
+//! with their content hashes, subdirectory names with their own Merkle
+//! hashes — so two trees match only if every file and folder matches.
+//! Directories are first compared by a cheap "shape" hash of names and sizes,
+//! and only files inside directories sharing a shape are read. Only the
+//! topmost duplicated trees are reported: a copied photo export is one group,
+//! not thousands of duplicate-file groups.
+
+use crate::api::FileError;
+use crate::file_ops::{DeleteMode, DeleteResult};
+use crate::plan::{ActionPlan, PlannedAction};
+use crate::protection::ProtectedPaths;
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use space_saver_core::{FileHasher, FileInfo};
+use space_saver_utils::Error;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directories whose whole trees are identical
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateDirectoryGroup {
+    pub hash: String,
+    pub directories: Vec<String>,
+    /// Files in each copy
+    pub file_count: usize,
+    /// Size of each copy
+    pub size: u64,
+    /// Space freed by deleting all copies but one
+    pub wasted_space: u64,
+}
+
+impl DuplicateDirectoryGroup {
+    /// The copy kept when the others are deleted: the shortest path
+    pub fn kept(&self) -> Option<&str> {
+        self.directories
+            .iter()
+            .min_by_key(|d| d.len())
+            .map(String::as_str)
+    }
+}
+
+/// Files and subdirectories directly inside a directory
+#[derive(Default)]
+struct DirNode {
+    files: Vec<(String, usize)>,
+    subdirs: BTreeMap<String, PathBuf>,
+    size: u64,
+    file_count: usize,
+}
+
+/// Group identical directory trees among the directories holding `files`
+/// beneath `roots` (roots included). `files` should be a full scan, hidden
+/// files included: a directory is only as identical as what was scanned.
+pub fn find_duplicate_directories(
+    roots: &[PathBuf],
+    files: &[FileInfo],
+) -> Vec<DuplicateDirectoryGroup> {
+    let tree = build_tree(roots, files);
+    let hasher = FileHasher::new_blake3();
+
+    // Cheap pass: names and sizes only
+    let mut shapes = HashMap::new();
+    for dir in tree.keys() {
+        shape_hash(dir, &tree, files, &hasher, &mut shapes);
+    }
+    let mut by_shape: HashMap<&String, usize> = HashMap::new();
+    for shape in shapes.values() {
+        *by_shape.entry(shape).or_default() += 1;
+    }
+    let candidates: HashSet<&PathBuf> = tree
+        .iter()
+        .filter(|(dir, node)| node.file_count > 0 && by_shape[&shapes[*dir]] > 1)
+        .map(|(dir, _)| dir)
+        .collect();
+
+    // Read only the files directly inside candidates; their subdirectories
+    // share a shape too, so they are candidates themselves
+    let to_hash: Vec<usize> = candidates
+        .iter()
+        .flat_map(|dir| tree[*dir].files.iter().map(|(_, index)| *index))
+        .collect();
+    let content: HashMap<usize, String> = to_hash
+        .into_par_iter()
+        .filter_map(|index| Some((index, hasher.hash_file(&files[index].path).ok()?)))
+        .collect();
+
+    let mut merkle = HashMap::new();
+    let mut groups: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    for dir in &candidates {
+        if let Some(hash) = merkle_hash(dir, &tree, &content, &hasher, &mut merkle) {
+            groups.entry(hash).or_default().push(dir);
+        }
+    }
+
+    // A directory inside a duplicated tree is reported through its ancestor
+    let duplicated: HashSet<&Path> = groups
+        .values()
+        .filter(|dirs| dirs.len() > 1)
+        .flatten()
+        .map(|dir| dir.as_path())
+        .collect();
+    let mut result: Vec<DuplicateDirectoryGroup> = groups
+        .into_iter()
+        .filter(|(_, dirs)| dirs.len() > 1)
+        .filter(|(_, dirs)| {
+            !dirs
+                .iter()
+                .all(|dir| dir.parent().is_some_and(|p| duplicated.contains(p)))
+        })
+        .map(|(hash, dirs)| {
+            let node = &tree[dirs[0]];
+            let mut directories: Vec<String> = dirs
+                .iter()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .collect();
+            directories.sort();
+            DuplicateDirectoryGroup {
+                hash,
+                file_count: node.file_count,
+                size: node.size,
+                wasted_space: node.size * (directories.len() as u64 - 1),
+                directories,
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        b.wasted_space
+            .cmp(&a.wasted_space)
+            .then_with(|| a.directories.cmp(&b.directories))
+    });
+    result
+}
+
+fn build_tree(roots: &[PathBuf], files: &[FileInfo]) -> HashMap<PathBuf, DirNode> {
+    let mut tree: HashMap<PathBuf, DirNode> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        let Some(root) = roots.iter().find(|root| file.path.starts_with(root)) else {
+            continue;
+        };
+        let (Some(parent), Some(name)) = (file.path.parent(), file.path.file_name()) else {
+            continue;
+        };
+        tree.entry(parent.to_path_buf())
+            .or_default()
+            .files
+            .push((name.to_string_lossy().to_string(), index));
+        let mut child: Option<&Path> = None;
+        for dir in parent.ancestors() {
+            let node = tree.entry(dir.to_path_buf()).or_default();
+            node.size += file.size;
+            node.file_count += 1;
+            if let Some(child) = child {
+                let name = child.file_name().unwrap_or_default();
+                node.subdirs
+                    .insert(name.to_string_lossy().to_string(), child.to_path_buf());
+            }
+            if dir == root.as_path() {
+                break;
+            }
+            child = Some(dir);
+        }
+    }
+    for node in tree.values_mut() {
+        node.files.sort();
+    }
+    tree
+}
+
+fn shape_hash(
+    dir: &Path,
+    tree: &HashMap<PathBuf, DirNode>,
+    files: &[FileInfo],
+    hasher: &FileHasher,
+    memo: &mut HashMap<PathBuf, String>,
+) -> String {
+    if let Some(hash) = memo.get(dir) {
+        return hash.clone();
+    }
+    let node = &tree[dir];
+    let mut entries = String::new();
+    for (name, index) in &node.files {
+        entries.push_str(&format!("f\0{}\0{}\n", name, files[*index].size));
+    }
+    for (name, child) in &node.subdirs {
+        let child_hash = shape_hash(child, tree, files, hasher, memo);
+        entries.push_str(&format!("d\0{}\0{}\n", name, child_hash));
+    }
+    let hash = hasher.hash_bytes(entries.as_bytes());
+    memo.insert(dir.to_path_buf(), hash.clone());
+    hash
+}
+
+/// `None` when a file in the tree could not be read
+fn merkle_hash(
+    dir: &Path,
+    tree: &HashMap<PathBuf, DirNode>,
+    content: &HashMap<usize, String>,
+    hasher: &FileHasher,
+    memo: &mut HashMap<PathBuf, Option<String>>,
+) -> Option<String> {
+    if let Some(hash) = memo.get(dir) {
+        return hash.clone();
+    }
+    let node = &tree[dir];
+    let mut entries = String::new();
+    let mut complete = true;
+    for (name, index) in &node.files {
+        match content.get(index) {
+            Some(hash) => entries.push_str(&format!("f\0{}\0{}\n", name, hash)),
+            None => complete = false,
+        }
+    }
+    for (name, child) in &node.subdirs {
+        match merkle_hash(child, tree, content, hasher, memo) {
+            Some(hash) => entries.push_str(&format!("d\0{}\0{}\n", name, hash)),
+            None => complete = false,
+        }
+    }
+    let hash = complete.then(|| hasher.hash_bytes(entries.as_bytes()));
+    memo.insert(dir.to_path_buf(), hash.clone());
+    hash
+}
+
+/// Hash of everything under `dir` as it is on disk now: file contents,
+/// symlink targets and empty directories included
+fn tree_hash(dir: &Path) -> Result<String> {
+    let hasher = FileHasher::new_blake3();
+    let mut entries = String::new();
+    let walk = WalkDir::new(dir).min_depth(1).sort_by_file_name();
+    for entry in walk {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(dir)?.to_string_lossy();
+        let kind = entry.file_type();
+        if kind.is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            entries.push_str(&format!("l\0{}\0{}\n", relative, target.display()));
+        } else if kind.is_dir() {
+            entries.push_str(&format!("d\0{}\n", relative));
+        } else {
+            let hash = hasher.hash_file(entry.path())?;
+            entries.push_str(&format!("f\0{}\0{}\n", relative, hash));
+        }
+    }
+    Ok(hasher.hash_bytes(entries.as_bytes()))
+}
+
+/// Delete `target`, one copy of a duplicated tree, keeping `keep`. Both trees
+/// are compared again first, so a copy that changed since the scan is never
+/// deleted; a protected directory, or one containing a protected path, is
+/// refused.
+pub fn delete_duplicate_directory(
+    target: &Path,
+    keep: &Path,
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+) -> DeleteResult {
+    let outcome = match refusal(target, protected) {
+        Some(e) => Err(e),
+        None => delete_verified(target, keep, mode).map_err(Error::from),
+    };
+    DeleteResult::of(target, outcome)
+}
+
+/// What deleting every copy but the [kept](DuplicateDirectoryGroup::kept)
+/// one of each group in `mode` would do, touching nothing. Protected copies
+/// are listed as skipped; the trees are compared again only when deleting.
+pub fn deletion_plan(
+    groups: &[DuplicateDirectoryGroup],
+    mode: DeleteMode,
+    protected: &ProtectedPaths,
+) -> ActionPlan {
+    let mut actions = Vec::new();
+    let mut skipped = Vec::new();
+    for group in groups {
+        let Some(keep) = group.kept() else {
+            continue;
+        };
+        for target in group.directories.iter().filter(|d| *d != keep) {
+            match refusal(Path::new(target), protected) {
+                Some(e) => skipped.push(FileError::new(Path::new(target), e)),
+                None => actions.push(PlannedAction {
+                    path: PathBuf::from(target),
+                    action: mode.action().to_string(),
+                    size: group.size,
+                    reclaimed: Some(group.size),
+                    detail: Some(format!("copy of {}", keep)),
+                }),
+            }
+        }
+    }
+    ActionPlan::new(actions, skipped)
+}
+
+/// Why `target` must not be deleted: it is protected, or holds a protected
+/// path
+pub(crate) fn refusal(target: &Path, protected: &ProtectedPaths) -> Option<Error> {
+    protected.check(target).err().map(Error::from).or_else(|| {
+        protected
+            .roots()
+            .iter()
+            .find(|root| root.starts_with(target))
+            .map(|root| {
+                Error::Protected(format!("Protected path: {} is inside it", root.display()))
+            })
+    })
+}
+
+fn delete_verified(target: &Path, keep: &Path, mode: DeleteMode) -> Result<()> {
+    let (target_abs, keep_abs) = (fs::canonicalize(target), fs::canonicalize(keep));
+    let target_abs = target_abs.with_context(|| format!("Not found: {}", target.display()))?;
+    let keep_abs = keep_abs.with_context(|| format!("Not found: {}", keep.display()))?;
+    if !target_abs.is_dir() || !keep_abs.is_dir() {
+        return Err(Error::InvalidInput("Both copies must be directories".to_string()).into());
+    }
+    if target_abs.starts_with(&keep_abs) || keep_abs.starts_with(&target_abs) {
+        return Err(Error::InvalidInput(format!(
+            "{} and {} overlap",
+            target.display(),
+            keep.display()
+        ))
+        .into());
+    }
+    if tree_hash(&target_abs)? != tree_hash(&keep_abs)? {
+        bail!(
+            "{} no longer matches {}; it changed since the scan",
+            target.display(),
+            keep.display()
+        );
+    }
+    match mode {
+        DeleteMode::Trash => trash::delete(&target_abs)?,
+        DeleteMode::Permanent => fs::remove_dir_all(&target_abs)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn export(dir: &Path) {
+        write(&dir.join("a.jpg"), "photo a");
+        write(&dir.join("b.jpg"), "photo b");
+        write(&dir.join("raw/a.cr2"), "raw a");
+    }
+
+    fn scan(root: &Path) -> Vec<DuplicateDirectoryGroup> {
+        let files = DefaultFileScanner::new()
+            .include_hidden(true)
+            .scan(root)
+            .unwrap();
+        find_duplicate_directories(&[root.to_path_buf()], &files)
+    }
+
+    #[test]
+    fn test_copied_tree_is_one_group() {
+        let dir = tempdir().unwrap();
+        export(&dir.path().join("export"));
+        export(&dir.path().join("backup/export copy"));
+        // Same names and sizes, different content
+        export(&dir.path().join("lookalike"));
+        write(&dir.path().join("lookalike/b.jpg"), "photo x");
+        write(&dir.path().join("lookalike/raw/a.cr2"), "raw x");
+
+        let groups = scan(dir.path());
+        assert_eq!(groups.len(), 1, "{:?}", groups);
+        let group = &groups[0];
+        assert_eq!(group.directories.len(), 2);
+        assert!(group.directories[0].ends_with("export copy"));
+        assert!(group.directories[1].ends_with("export"));
+        assert_eq!(group.file_count, 3);
+        assert_eq!(group.size, 19);
+        assert_eq!(group.wasted_space, 19);
+    }
+
+    #[test]
+    fn test_delete_verifies_both_copies() {
+        let dir = tempdir().unwrap();
+        let (keep, copy) = (dir.path().join("keep"), dir.path().join("copy"));
+        export(&keep);
+        export(&copy);
+        write(&copy.join("raw/extra.txt"), "added after the scan");
+
+        let none = ProtectedPaths::empty();
+        let result = delete_duplicate_directory(&copy, &keep, DeleteMode::Permanent, &none);
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("changed since the scan"));
+        assert!(!delete_duplicate_directory(&keep, &keep, DeleteMode::Permanent, &none).success);
+
+        let protected = ProtectedPaths::empty().with_paths([copy.join("raw")]);
+        let result = delete_duplicate_directory(&copy, &keep, DeleteMode::Permanent, &protected);
+        assert!(result.protected);
+
+        fs::remove_file(copy.join("raw/extra.txt")).unwrap();
+        let result = delete_duplicate_directory(&copy, &keep, DeleteMode::Permanent, &none);
+        assert!(result.success, "error: {:?}", result.error);
+        assert!(!copy.exists());
+        assert!(keep.join("raw/a.cr2").exists());
+    }
+
+    #[test]
+    fn test_deletion_plan_keeps_the_shortest_path() {
+        let dir = tempdir().unwrap();
+        export(&dir.path().join("export"));
+        export(&dir.path().join("backup/export copy"));
+        export(&dir.path().join("locked/export"));
+
+        let groups = scan(dir.path());
+        let locked = dir.path().join("locked");
+        let plan = deletion_plan(
+            &groups,
+            DeleteMode::Trash,
+            &ProtectedPaths::empty().with_paths([&locked]),
+        );
+        assert_eq!(plan.actions.len(), 1);
+        assert!(plan.actions[0].path.ends_with("export copy"));
+        assert_eq!(plan.actions[0].action, "trash");
+        assert_eq!(plan.reclaimed, 19);
+        assert_eq!(plan.skipped.len(), 1);
+        assert!(dir.path().join("backup/export copy").exists());
+    }
+}
@@ -0,0 +1,232 @@
+//! Measured compression estimates.
+//!
+//! A plugin's own guess at how much a file will shrink is a fixed ratio per
+//! format. [`estimate_compression`] measures instead: it groups the files by
+//! the plugin that would take them and their extension, converts a random
+//! sample of each group into a temporary directory (the files themselves
+//! are never touched) and extrapolates the savings seen on the sample to
+//! the rest of the group.
+
+use crate::api::FileError;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_core::compress_plugins::{PluginManager, ProgressSink};
+use space_saver_core::FileInfo;
+use space_saver_utils::Error;
+use std::collections::BTreeMap;
+
+/// Files sampled per plugin and extension when the caller does not say
+pub const DEFAULT_SAMPLE_COUNT: usize = 5;
+
+/// The files of one extension a plugin would compress, and what they save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimateGroup {
+    pub plugin_name: String,
+    /// Lowercase, without the dot; empty for files without one
+    pub extension: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Files converted to measure the ratio
+    pub sampled: usize,
+    /// Share of the size a compression saves, as measured on the sample;
+    /// the plugin's own guess when no sample could be converted
+    pub savings_ratio: f64,
+    /// False when `savings_ratio` is the plugin's guess
+    pub measured: bool,
+    pub estimated_savings: u64,
+    /// Sampled files the plugin failed on
+    pub failed: Vec<FileError>,
+}
+
+/// What compressing a set of files would save, per plugin and extension
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompressionEstimate {
+    /// Largest estimated savings first
+    pub groups: Vec<EstimateGroup>,
+    pub file_count: usize,
+    pub total_size: u64,
+    pub estimated_savings: u64,
+}
+
+/// (plugin, extension) -> files, with the plugin's guess for each
+type Groups = BTreeMap<(String, String), Vec<(FileInfo, Option<f32>)>>;
+
+/// Estimate what compressing `files` would save, each going to the first of
+/// `plugins` able to take it. Up to `sample_count` files of every plugin
+/// and extension are converted to measure the savings; files the real
+/// compression would keep (output not smaller, or below the manager's
+/// minimum savings) count as saving nothing. Files no plugin takes are left
+/// out. Stops with a cancellation error when `sink` is cancelled.
+pub fn estimate_compression(
+    manager: &PluginManager,
+    files: Vec<FileInfo>,
+    plugins: &[String],
+    sample_count: usize,
+    sink: &ProgressSink,
+) -> Result<CompressionEstimate> {
+    let mut groups = Groups::new();
+    for file in files {
+        let accepted = plugins.iter().find_map(|name| {
+            match manager.check_plugin_capability(&file.path, name) {
+                Ok(Some((_, true, _, ratio))) => Some((name.clone(), ratio)),
+                _ => None,
+            }
+        });
+        if let Some((plugin, ratio)) = accepted {
+            let extension = file
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            groups
+                .entry((plugin, extension))
+                .or_default()
+                .push((file, ratio));
+        }
+    }
+
+    let output_dir = tempfile::Builder::new()
+        .prefix("space-saver-estimate")
+        .tempdir()
+        .context("Failed to create a directory for sample conversions")?;
+    let mut rng = fastrand::Rng::new();
+    let mut estimate = CompressionEstimate::default();
+    for ((plugin_name, extension), files) in groups {
+        let mut measured = (0u64, 0u64);
+        let mut sampled = 0;
+        let mut failed = Vec::new();
+        for (file, _) in rng.choose_multiple(files.iter(), sample_count) {
+            sink.check_cancelled()?;
+            match manager.trial_compress(&file.path, output_dir.path(), &plugin_name, sink) {
+                Ok(result) => {
+                    let saved = result.original_size.saturating_sub(result.compressed_size);
+                    let percent = saved as f64 * 100.0 / result.original_size.max(1) as f64;
+                    measured.0 += result.original_size;
+                    if percent >= manager.min_savings_percent() as f64 {
+                        measured.1 += saved;
+                    }
+                    sampled += 1;
+                }
+                Err(e) => {
+                    sink.check_cancelled()?;
+                    failed.push(FileError::new(&file.path, Error::from(e)));
+                }
+            }
+        }
+
+        let total_size: u64 = files.iter().map(|(file, _)| file.size).sum();
+        let (savings_ratio, measured) = if sampled > 0 {
+            (measured.1 as f64 / measured.0.max(1) as f64, true)
+        } else {
+            // Nothing converted: average what the plugin guessed per file
+            let guesses: Vec<f64> = files
+                .iter()
+                .filter_map(|(_, r)| *r)
+                .map(f64::from)
+                .collect();
+            let guess = guesses.iter().sum::<f64>() / guesses.len().max(1) as f64;
+            (guess, false)
+        };
+        let group = EstimateGroup {
+            plugin_name,
+            extension,
+            file_count: files.len(),
+            total_size,
+            sampled,
+            savings_ratio,
+            measured,
+            estimated_savings: (total_size as f64 * savings_ratio) as u64,
+            failed,
+        };
+        estimate.file_count += group.file_count;
+        estimate.total_size += group.total_size;
+        estimate.estimated_savings += group.estimated_savings;
+        estimate.groups.push(group);
+    }
+    estimate
+        .groups
+        .sort_by_key(|group| std::cmp::Reverse(group.estimated_savings));
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::compress_plugins::global_plugin_manager;
+    use space_saver_core::FileScanner;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn noise_png(path: &Path, size: u32) {
+        let mut seed = 7u32;
+        let img = image::RgbImage::from_fn(size, size, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = seed.to_le_bytes();
+            image::Rgb([r, g, b])
+        });
+        img.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_estimate_measures_samples_and_extrapolates() {
+        let dir = tempdir().unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            noise_png(&dir.path().join(name), 48);
+        }
+        std::fs::write(dir.path().join("notes.txt"), b"no plugin takes this").unwrap();
+        let files = space_saver_core::scanner::DefaultFileScanner::new()
+            .scan(dir.path())
+            .unwrap();
+        let before = std::fs::read_dir(dir.path()).unwrap().count();
+
+        let manager = global_plugin_manager();
+        let manager = manager.read().unwrap();
+        let plugins = ["WebP Converter".to_string()];
+        let estimate =
+            estimate_compression(&manager, files, &plugins, 2, &ProgressSink::new()).unwrap();
+
+        assert_eq!(estimate.groups.len(), 1);
+        let group = &estimate.groups[0];
+        assert_eq!(
+            (group.plugin_name.as_str(), group.extension.as_str()),
+            ("WebP Converter", "png")
+        );
+        assert_eq!((group.file_count, group.sampled), (3, 2));
+        assert!(group.measured);
+        assert!(group.savings_ratio > 0.0 && group.savings_ratio < 1.0);
+        assert_eq!(estimate.file_count, 3);
+        assert_eq!(
+            estimate.estimated_savings,
+            (estimate.total_size as f64 * group.savings_ratio) as u64
+        );
+        // Samples are converted elsewhere
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), before);
+    }
+
+    #[test]
+    fn test_estimate_falls_back_to_guesses_when_samples_fail() {
+        let dir = tempdir().unwrap();
+        // A PNG header the converter cannot decode
+        let broken = dir.path().join("broken.png");
+        std::fs::write(&broken, b"\x89PNG\r\n\x1a\nnot really").unwrap();
+        let files = space_saver_core::scanner::DefaultFileScanner::new()
+            .scan(dir.path())
+            .unwrap();
+
+        let manager = global_plugin_manager();
+        let manager = manager.read().unwrap();
+        let plugins = ["WebP Converter".to_string()];
+        let estimate =
+            estimate_compression(&manager, files.clone(), &plugins, 5, &ProgressSink::new())
+                .unwrap();
+        let group = &estimate.groups[0];
+        assert!(!group.measured);
+        assert_eq!(group.sampled, 0);
+        assert_eq!(group.failed.len(), 1);
+        assert_eq!(group.failed[0].path, broken);
+
+        let cancelled = ProgressSink::new();
+        cancelled.cancel_token().cancel();
+        assert!(estimate_compression(&manager, files, &plugins, 5, &cancelled).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+//! Exporting results to files.
+//!
+//! Duplicate groups, similar groups, storage statistics and compressible-file
+//! scans can be written as CSV (one row per file, sizes in bytes), as JSON
+//! (the results as the API returns them) or as a self-contained HTML report
+//! that opens in any browser without network access.
+
+use crate::api::{DuplicateGroup, SimilarGroup, StorageStats};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_utils::Error;
+use space_saver_utils::{format_size, format_timestamp};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    /// The format matching the extension of `path`, if any
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "html" | "htm" => Ok(ExportFormat::Html),
+            other => Err(Error::InvalidInput(format!(
+                "Unsupported export format: {} (expected csv, json or html)",
+                other
+            ))
+            .into()),
+        }
+    }
+}
+
+/// A file a compression plugin can shrink, as listed by a compressible-file
+/// scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressibleFile {
+    pub path: String,
+    pub original_size: u64,
+    pub estimated_compressed_size: u64,
+    pub estimated_savings: u64,
+    pub plugin_name: String,
+}
+
+/// Results to export, tagged with what they are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum ExportResults {
+    Duplicates(Vec<DuplicateGroup>),
+    Similar(Vec<SimilarGroup>),
+    Stats(StorageStats),
+    Compressible(Vec<CompressibleFile>),
+}
+
+/// Rows of a report; sizes are in bytes
+struct Table {
+    title: &'static str,
+    summary: String,
+    headers: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+impl ExportResults {
+    fn table(&self) -> Table {
+        match self {
+            ExportResults::Duplicates(groups) => Table {
+                title: "Duplicate files",
+                summary: format!(
+                    "{} groups, {} wasted",
+                    groups.len(),
+                    format_size(groups.iter().map(|g| g.wasted_space).sum())
+                ),
+                headers: &["group", "hash", "path", "size", "modified", "wasted_space"],
+                rows: groups
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, group)| {
+                        group.files.iter().map(move |file| {
+                            vec![
+                                (index + 1).to_string(),
+                                group.hash.clone(),
+                                file.path.to_string_lossy().to_string(),
+                                file.size.to_string(),
+                                format_timestamp(file.modified),
+                                group.wasted_space.to_string(),
+                            ]
+                        })
+                    })
+                    .collect(),
+            },
+            ExportResults::Similar(groups) => Table {
+                title: "Similar media",
+                summary: format!("{} groups", groups.len()),
+                headers: &[
+                    "group",
+                    "similarity",
+                    "media_kind",
+                    "path",
+                    "size",
+                    "width",
+                    "height",
+                ],
+                rows: groups
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, group)| {
+                        group.files.iter().map(move |file| {
+                            let dimension = |d: Option<u32>| d.map(|d| d.to_string());
+                            vec![
+                                (index + 1).to_string(),
+                                format!("{:.3}", group.similarity_score),
+                                format!("{:?}", group.media_kind),
+                                file.path.clone(),
+                                file.size.to_string(),
+                                dimension(file.width).unwrap_or_default(),
+                                dimension(file.height).unwrap_or_default(),
+                            ]
+                        })
+                    })
+                    .collect(),
+            },
+            ExportResults::Stats(stats) => Table {
+                title: "Storage statistics",
+                summary: format!(
+                    "{} files, {}",
+                    stats.total_files,
+                    format_size(stats.total_size)
+                ),
+                headers: &["metric", "value"],
+                rows: [
+                    ("total_files", stats.total_files as u64),
+                    ("total_size", stats.total_size),
+                    ("images", stats.images as u64),
+                    ("videos", stats.videos as u64),
+                    ("documents", stats.documents as u64),
+                    ("archives", stats.archives as u64),
+                    ("others", stats.others as u64),
+                    ("empty_files", stats.empty_files as u64),
+                ]
+                .into_iter()
+                .map(|(metric, value)| vec![metric.to_string(), value.to_string()])
+                .collect(),
+            },
+            ExportResults::Compressible(files) => Table {
+                title: "Compressible files",
+                summary: format!(
+                    "{} files, {} estimated savings",
+                    files.len(),
+                    format_size(files.iter().map(|f| f.estimated_savings).sum())
+                ),
+                headers: &[
+                    "path",
+                    "plugin",
+                    "original_size",
+                    "estimated_compressed_size",
+                    "estimated_savings",
+                ],
+                rows: files
+                    .iter()
+                    .map(|file| {
+                        vec![
+                            file.path.clone(),
+                            file.plugin_name.clone(),
+                            file.original_size.to_string(),
+                            file.estimated_compressed_size.to_string(),
+                            file.estimated_savings.to_string(),
+                        ]
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Render `results` in `format`
+pub fn render(results: &ExportResults, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(results)?),
+        ExportFormat::Csv => Ok(render_csv(&results.table())),
+        ExportFormat::Html => Ok(render_html(&results.table())),
+    }
+}
+
+/// Write `results` to `path` in `format`, replacing any existing file
+pub fn export_results(results: &ExportResults, format: ExportFormat, path: &Path) -> Result<()> {
+    let content = render(results, format)?;
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn render_csv(table: &Table) -> String {
+    to_csv(table.headers, &table.rows)
+}
+
+/// CSV with a header line and one line per row (RFC 4180 line breaks and
+/// quoting)
+pub fn to_csv(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        cells.map(csv_field).collect::<Vec<_>>().join(",") + "\r\n"
+    };
+    let mut csv = line(&mut headers.iter().copied());
+    for row in rows {
+        csv.push_str(&line(&mut row.iter().map(String::as_str)));
+    }
+    csv
+}
+
+/// Quote a field holding a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_html(table: &Table) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>\n\
+         body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}\n\
+         table {{ border-collapse: collapse; width: 100%; font-size: 0.9rem; }}\n\
+         th, td {{ border: 1px solid #ddd; padding: 0.3rem 0.6rem; text-align: left; }}\n\
+         th {{ background: #f3f3f3; }}\n\
+         tr:nth-child(even) td {{ background: #fafafa; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n<p>{summary} &middot; generated {generated}</p>\n\
+         <table>\n<thead><tr>",
+        title = table.title,
+        summary = html_escape(&table.summary),
+        generated = format_timestamp(space_saver_utils::time::now()),
+    );
+    for header in table.headers {
+        let _ = write!(html, "<th>{}</th>", html_escape(header));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for row in &table.rows {
+        html.push_str("<tr>");
+        for cell in row {
+            let _ = write!(html, "<td>{}</td>", html_escape(cell));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+pub(crate) fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::FileType;
+    use space_saver_core::FileInfo;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn duplicates() -> ExportResults {
+        let file = |path: &str| FileInfo {
+            path: PathBuf::from(path),
+            size: 100,
+            modified: 0,
+            file_type: FileType::Document,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        };
+        ExportResults::Duplicates(vec![DuplicateGroup {
+            hash: "abc".to_string(),
+            files: vec![file("/a/report, final.txt"), file("/b/<copy>.txt")],
+            count: 2,
+            total_size: 200,
+            wasted_space: 100,
+        }])
+    }
+
+    #[test]
+    fn test_csv_has_a_row_per_file_with_quoted_fields() {
+        let csv = render(&duplicates(), ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "group,hash,path,size,modified,wasted_space");
+        assert!(lines[1].starts_with("1,abc,\"/a/report, final.txt\",100,"));
+        assert!(lines[2].ends_with(",100"));
+    }
+
+    #[test]
+    fn test_html_is_escaped_and_json_round_trips() {
+        let html = render(&duplicates(), ExportFormat::Html).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("/b/&lt;copy&gt;.txt"));
+        assert!(!html.contains("<copy>"));
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("duplicates.json");
+        export_results(&duplicates(), ExportFormat::Json, &path).unwrap();
+        let parsed: ExportResults = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert!(matches!(parsed, ExportResults::Duplicates(groups) if groups[0].count == 2));
+    }
+
+    #[test]
+    fn test_format_from_name_or_extension() {
+        assert_eq!("HTML".parse::<ExportFormat>().unwrap(), ExportFormat::Html);
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out/report.csv")),
+            Some(ExportFormat::Csv)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("report")), None);
+        assert!("xml".parse::<ExportFormat>().is_err());
+    }
+}
@@ -1,5 +1,13 @@
+use crate::api::FileError;
+use crate::dedupe::DuplicateResolution;
+use crate::hooks::{HookOutcome, Hooks};
+use crate::plan::{ActionPlan, PlannedAction};
+use crate::protection::ProtectedPaths;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use space_saver_core::FileInfo;
+use space_saver_db::{Database, RestorePoint};
+use space_saver_utils::{Error, ErrorCode};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -13,12 +21,162 @@ pub enum DeleteMode {
     Permanent,
 }
 
+impl DeleteMode {
+    /// How a plan names it: "trash" or "delete"
+    pub fn action(self) -> &'static str {
+        match self {
+            DeleteMode::Trash => "trash",
+            DeleteMode::Permanent => "delete",
+        }
+    }
+}
+
 /// Per-file outcome of a delete operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeleteResult {
     pub path: String,
     pub success: bool,
     pub error: Option<String>,
+    /// The path is protected (see [`ProtectedPaths`]) and was left alone
+    #[serde(default)]
+    pub protected: bool,
+    /// What kind of failure `error` is
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+}
+
+impl DeleteResult {
+    pub fn succeeded(path: &Path) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            success: true,
+            error: None,
+            protected: false,
+            error_code: None,
+        }
+    }
+
+    pub fn failed(path: &Path, error: Error) -> Self {
+        let code = error.code();
+        Self {
+            path: path.to_string_lossy().to_string(),
+            success: false,
+            error: Some(error.to_string()),
+            protected: code == ErrorCode::Protected,
+            error_code: Some(code),
+        }
+    }
+
+    /// The outcome of removing `path`
+    pub fn of(path: &Path, outcome: std::result::Result<(), Error>) -> Self {
+        match outcome {
+            Ok(()) => Self::succeeded(path),
+            Err(e) => Self::failed(path, e),
+        }
+    }
+}
+
+/// `delete` with the "delete" `hooks` around it; its results must line up
+/// with `paths`. When a before hook aborts, every path fails with its error.
+pub(crate) fn hooked_deletion(
+    hooks: &Hooks,
+    paths: &[PathBuf],
+    delete: impl FnOnce() -> Vec<DeleteResult>,
+) -> Vec<DeleteResult> {
+    if hooks.is_empty() {
+        return delete();
+    }
+    let sizes: Vec<u64> = paths
+        .iter()
+        .map(|path| fs::symlink_metadata(path).map_or(0, |m| m.len()))
+        .collect();
+    let outcome = |results: &Vec<DeleteResult>| outcome_of(results, &sizes);
+    match hooks.around("delete", paths, delete, outcome) {
+        Ok(results) => results,
+        Err(e) => {
+            let message = match Error::from(e) {
+                Error::Task(message) => message,
+                other => other.to_string(),
+            };
+            paths
+                .iter()
+                .map(|path| DeleteResult::failed(path, Error::Task(message.clone())))
+                .collect()
+        }
+    }
+}
+
+/// How a batch of deletions went, for the after hooks; `sizes` are those of
+/// the files `results` are for, taken before
+fn outcome_of(results: &[DeleteResult], sizes: &[u64]) -> HookOutcome {
+    let succeeded = results.iter().filter(|result| result.success).count();
+    HookOutcome {
+        succeeded,
+        failed: results.len() - succeeded,
+        freed: results
+            .iter()
+            .zip(sizes)
+            .filter(|(result, _)| result.success)
+            .map(|(_, size)| size)
+            .sum(),
+    }
+}
+
+/// Outcome of removing one empty directory tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmptyTreeResult {
+    /// The tree's topmost directory
+    pub path: String,
+    /// Directories removed, deepest first; the whole tree when it went to
+    /// the trash in one piece
+    pub removed: Vec<String>,
+    pub success: bool,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+}
+
+/// `path` with its parent directory resolved, as the trash records it
+pub(crate) fn trash_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match (path.parent().map(fs::canonicalize), path.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => path,
+    }
+}
+
+/// The directories of the tree at `dir`, deepest first; refused when
+/// anything but a directory is inside
+fn empty_tree(dir: &Path) -> std::result::Result<Vec<PathBuf>, Error> {
+    if !fs::symlink_metadata(dir)?.is_dir() {
+        return Err(Error::InvalidInput(format!(
+            "Not a directory: {}",
+            dir.display()
+        )));
+    }
+    let mut tree = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).contents_first(true) {
+        let entry = entry.map_err(std::io::Error::from)?;
+        if !entry.file_type().is_dir() {
+            return Err(Error::InvalidInput(format!(
+                "Directory is not empty: {} is inside",
+                entry.path().display()
+            )));
+        }
+        tree.push(entry.into_path());
+    }
+    Ok(tree)
+}
+
+/// Remove the empty tree at `dir` deepest first, adding each directory
+/// removed to `removed`. A directory that is not empty is never removed,
+/// and nothing above it either.
+fn collapse_tree(dir: &Path, removed: &mut Vec<PathBuf>) -> std::result::Result<(), Error> {
+    for entry in empty_tree(dir)? {
+        fs::remove_dir(&entry)?;
+        removed.push(entry);
+    }
+    Ok(())
 }
 
 /// Per-file outcome of a fix-extension (rename) operation
@@ -32,29 +190,63 @@ pub struct FixExtensionResult {
     pub error: Option<String>,
 }
 
-/// File operations (delete, move, copy, etc.)
-pub struct FileOperations;
+/// File operations (delete, move, copy, etc.). Deleting and moving refuse
+/// protected paths; by default those are the system directories.
+pub struct FileOperations {
+    protected: ProtectedPaths,
+    /// Where files moved to the trash are recorded as restore points
+    database: Option<Database>,
+    /// Run around every batch of deletions
+    hooks: Hooks,
+}
 
 impl FileOperations {
     pub fn new() -> Self {
-        Self
+        Self {
+            protected: ProtectedPaths::new(),
+            database: None,
+            hooks: Hooks::default(),
+        }
     }
 
-    /// Delete a file
+    /// Replace the protected-paths list
+    pub fn with_protected_paths(mut self, protected: ProtectedPaths) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Record files moved to the trash in `db`, so `restore` can bring them
+    /// back
+    pub fn with_database(mut self, db: Database) -> Self {
+        self.database = Some(db);
+        self
+    }
+
+    /// Run the "delete" hooks around each batch of deletions, moves to the
+    /// trash and duplicate removals. A before hook that aborts fails every
+    /// file of the batch with its error.
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Delete a file. A protected path fails with a
+    /// [`crate::ProtectedPathError`].
     pub fn delete_file(&self, path: &Path) -> Result<()> {
+        self.protected.check(path)?;
         fs::remove_file(path)?;
         Ok(())
     }
 
-    /// Delete multiple files
-    pub fn delete_files(&self, paths: &[PathBuf]) -> Result<usize> {
-        let mut count = 0;
-        for path in paths {
-            if self.delete_file(path).is_ok() {
-                count += 1;
-            }
-        }
-        Ok(count)
+    /// Delete multiple files for good, reporting a per-file outcome.
+    /// Protected paths are refused.
+    pub fn delete_files(&self, paths: &[PathBuf]) -> Vec<DeleteResult> {
+        self.hooked(paths, || {
+            paths
+                .iter()
+                .map(|path| DeleteResult::of(path, self.delete_file(path).map_err(Error::from)))
+                .collect()
+        })
     }
 
     /// Delete files or empty directories reporting a per-file outcome instead
@@ -64,47 +256,193 @@ impl FileOperations {
     /// subtree contains no files (empty-subfolder scaffolding is removed with
     /// them) — this operation backs the cleanup UI and must never take real
     /// data along with a "empty" folder that gained content after the scan.
+    /// Protected paths are reported with `protected` set and never touched.
     pub fn delete_files_with_mode(&self, paths: &[PathBuf], mode: DeleteMode) -> Vec<DeleteResult> {
+        self.hooked(paths, || self.delete_each(paths, mode))
+    }
+
+    fn delete_each(&self, paths: &[PathBuf], mode: DeleteMode) -> Vec<DeleteResult> {
         paths
             .iter()
             .map(|path| {
-                let outcome = self.delete_path_with_mode(path, mode);
-                match outcome {
-                    Ok(()) => DeleteResult {
-                        path: path.to_string_lossy().to_string(),
-                        success: true,
-                        error: None,
-                    },
-                    Err(e) => DeleteResult {
-                        path: path.to_string_lossy().to_string(),
-                        success: false,
-                        error: Some(e),
-                    },
+                // Where the trash will say the file came from
+                let trashed_as = trash_path(path);
+                let size = fs::symlink_metadata(path).map_or(0, |m| m.len());
+                let outcome = self
+                    .protected
+                    .check(path)
+                    .map_err(Error::from)
+                    .and_then(|()| self.delete_path_with_mode(path, mode));
+                if outcome.is_ok() && mode == DeleteMode::Trash {
+                    self.record_trashed(trashed_as, size);
                 }
+                DeleteResult::of(path, outcome)
             })
             .collect()
     }
 
+    /// Remove the copies `resolutions` do not keep, in `mode`. A copy goes
+    /// only while it and the group's first kept copy are as the scan saw
+    /// them, so a plan made before either changed removes nothing of that
+    /// group, and only when it is not itself a kept copy reached through
+    /// another path. Results follow the order of the `delete` lists.
+    pub fn remove_duplicates(
+        &self,
+        resolutions: &[DuplicateResolution],
+        mode: DeleteMode,
+    ) -> Vec<DeleteResult> {
+        let paths: Vec<PathBuf> = resolutions
+            .iter()
+            .flat_map(|resolution| resolution.delete.iter().map(|file| file.path.clone()))
+            .collect();
+        self.hooked(&paths, || self.remove_each(resolutions, mode))
+    }
+
+    fn remove_each(
+        &self,
+        resolutions: &[DuplicateResolution],
+        mode: DeleteMode,
+    ) -> Vec<DeleteResult> {
+        let mut results = Vec::new();
+        for resolution in resolutions {
+            for file in &resolution.delete {
+                match duplicate_removable(resolution, file) {
+                    Ok(()) => {
+                        results.extend(self.delete_each(std::slice::from_ref(&file.path), mode))
+                    }
+                    Err(e) => results.push(DeleteResult::failed(&file.path, e)),
+                }
+            }
+        }
+        results
+    }
+
+    fn hooked(
+        &self,
+        paths: &[PathBuf],
+        delete: impl FnOnce() -> Vec<DeleteResult>,
+    ) -> Vec<DeleteResult> {
+        hooked_deletion(&self.hooks, paths, delete)
+    }
+
+    /// A file already in the trash stays there when it cannot be recorded;
+    /// it can still be restored from the desktop's trash
+    fn record_trashed(&self, path: PathBuf, size: u64) {
+        let Some(db) = &self.database else {
+            return;
+        };
+        let point = RestorePoint::trashed(path.to_string_lossy().to_string(), size);
+        if let Err(e) = db.get().and_then(|db| db.insert_restore_point(&point)) {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to record trashed file");
+        }
+    }
+
+    /// Move files or empty directories to the platform trash / recycle bin,
+    /// reporting a per-file outcome. Same rules as
+    /// [`Self::delete_files_with_mode`] in [`DeleteMode::Trash`].
+    pub fn trash_files(&self, paths: &[PathBuf]) -> Vec<DeleteResult> {
+        self.delete_files_with_mode(paths, DeleteMode::Trash)
+    }
+
+    /// What [`Self::delete_files_with_mode`] would do to `paths`, touching
+    /// nothing: the files that pass its checks, labelled `action` ("trash",
+    /// "delete" or "quarantine"), and the ones it would refuse
+    pub fn plan_removal(&self, paths: &[PathBuf], action: &str) -> ActionPlan {
+        let mut actions = Vec::new();
+        let mut skipped = Vec::new();
+        for path in paths {
+            let checked = self
+                .protected
+                .check(path)
+                .map_err(Error::from)
+                .and_then(|()| self.check_removable(path));
+            match checked {
+                Ok(size) => actions.push(PlannedAction {
+                    path: path.clone(),
+                    action: action.to_string(),
+                    size,
+                    reclaimed: Some(size),
+                    detail: None,
+                }),
+                Err(e) => skipped.push(FileError::new(path, e)),
+            }
+        }
+        ActionPlan::new(actions, skipped)
+    }
+
+    /// The size `path` frees once removed; refused for a directory with
+    /// files inside
+    fn check_removable(&self, path: &Path) -> std::result::Result<u64, Error> {
+        let metadata = fs::symlink_metadata(path)?;
+        if !path.is_dir() {
+            return Ok(metadata.len());
+        }
+        match self.count_files(path)? {
+            0 => Ok(0),
+            n => Err(Error::InvalidInput(format!(
+                "Directory is not empty ({} file(s) inside)",
+                n
+            ))),
+        }
+    }
+
     fn delete_path_with_mode(
         &self,
         path: &Path,
         mode: DeleteMode,
-    ) -> std::result::Result<(), String> {
+    ) -> std::result::Result<(), Error> {
+        self.check_removable(path)?;
         let is_dir = path.is_dir();
-        if is_dir {
-            match self.count_files(path) {
-                Ok(0) => {}
-                Ok(n) => return Err(format!("Directory is not empty ({} file(s) inside)", n)),
-                Err(e) => return Err(e.to_string()),
-            }
-        }
         match mode {
-            DeleteMode::Trash => trash::delete(path).map_err(|e| e.to_string()),
-            DeleteMode::Permanent if is_dir => fs::remove_dir_all(path).map_err(|e| e.to_string()),
-            DeleteMode::Permanent => fs::remove_file(path).map_err(|e| e.to_string()),
+            DeleteMode::Trash => {
+                trash::delete(path).map_err(|e| Error::FileOperation(e.to_string()))
+            }
+            DeleteMode::Permanent if is_dir => Ok(fs::remove_dir_all(path)?),
+            DeleteMode::Permanent => Ok(fs::remove_file(path)?),
         }
     }
 
+    /// Remove empty directory trees, as `find_empty_dirs` reports them,
+    /// reporting a per-tree outcome. A tree that gained a file since the
+    /// scan is left alone. Permanently, each tree is removed deepest
+    /// directory first, so it collapses from the bottom up; should a file
+    /// appear meanwhile, removal stops at its directory, keeping it and
+    /// everything above. To the trash, a tree goes whole, so it is restored
+    /// in one piece. Protected trees are refused.
+    pub fn remove_empty_trees(&self, dirs: &[PathBuf], mode: DeleteMode) -> Vec<EmptyTreeResult> {
+        dirs.iter()
+            .map(|dir| {
+                let mut removed = Vec::new();
+                let outcome = self
+                    .protected
+                    .check(dir)
+                    .map_err(Error::from)
+                    .and_then(|()| match mode {
+                        DeleteMode::Permanent => collapse_tree(dir, &mut removed),
+                        DeleteMode::Trash => {
+                            let tree = empty_tree(dir)?;
+                            let trashed_as = trash_path(dir);
+                            trash::delete(dir).map_err(|e| Error::FileOperation(e.to_string()))?;
+                            self.record_trashed(trashed_as, 0);
+                            removed = tree;
+                            Ok(())
+                        }
+                    });
+                let error_code = outcome.as_ref().err().map(Error::code);
+                EmptyTreeResult {
+                    path: dir.to_string_lossy().to_string(),
+                    removed: removed
+                        .iter()
+                        .map(|d| d.to_string_lossy().to_string())
+                        .collect(),
+                    success: outcome.is_ok(),
+                    error: outcome.err().map(|e| e.to_string()),
+                    error_code,
+                }
+            })
+            .collect()
+    }
+
     /// Rename files whose extension does not match their content so the
     /// extension matches the detected content (e.g. a PDF named `.jpg` becomes
     /// `.pdf`), reporting a per-file outcome. The content is re-detected here
@@ -149,8 +487,10 @@ impl FileOperations {
         Ok(target.to_string_lossy().to_string())
     }
 
-    /// Move a file
+    /// Move a file. Fails when either end is protected.
     pub fn move_file(&self, source: &Path, dest: &Path) -> Result<()> {
+        self.protected.check(source)?;
+        self.protected.check(dest)?;
         fs::rename(source, dest)?;
         Ok(())
     }
@@ -227,11 +567,122 @@ impl Default for FileOperations {
     }
 }
 
+/// Whether `file`, a copy `resolution` removes, may go now: a copy is kept,
+/// neither `file` nor the first kept copy changed since the scan, and `file`
+/// is not a kept copy itself
+pub(crate) fn duplicate_removable(
+    resolution: &DuplicateResolution,
+    file: &FileInfo,
+) -> Result<(), Error> {
+    let Some(kept) = resolution.keep.first() else {
+        return Err(Error::InvalidInput(format!(
+            "No copy of {} is kept",
+            file.path.display()
+        )));
+    };
+    crate::reflink::unchanged_since_scan(kept)
+        .and_then(|()| crate::reflink::unchanged_since_scan(file))
+        .map_err(Error::from)
+        .and_then(|()| not_kept(&resolution.keep, file))
+}
+
+/// Refuses `file` when it is one of the `kept` copies: the same path listed
+/// twice, or the same file reached through a symlinked or bind-mounted
+/// directory. Removing it would remove the copy that is kept.
+fn not_kept(kept: &[FileInfo], file: &FileInfo) -> Result<(), Error> {
+    for copy in kept {
+        if crate::hardlink::same_file(&copy.path, &file.path)? {
+            return Err(Error::InvalidInput(format!(
+                "{} is the kept copy {}",
+                file.path.display(),
+                copy.path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
     use tempfile::tempdir;
 
+    #[test]
+    fn test_remove_duplicates_only_while_the_copies_are_unchanged() {
+        let dir = tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(dir.path().join(name), "same").unwrap();
+        }
+        let mut files = DefaultFileScanner::new().scan(dir.path()).unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let delete = files.split_off(1);
+        let resolution = DuplicateResolution {
+            hash: "abc".to_string(),
+            reclaimable: 8,
+            keep: files,
+            delete,
+            protected: Vec::new(),
+        };
+        fs::write(dir.path().join("c.txt"), "edited").unwrap();
+
+        let ops = FileOperations::new();
+        let results =
+            ops.remove_duplicates(std::slice::from_ref(&resolution), DeleteMode::Permanent);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].success);
+        assert!(!dir.path().join("b.txt").exists());
+        assert!(!results[1].success);
+        assert!(results[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("changed since the scan"));
+        assert!(dir.path().join("c.txt").exists());
+
+        let orphan = DuplicateResolution {
+            keep: Vec::new(),
+            ..resolution
+        };
+        let results = ops.remove_duplicates(&[orphan], DeleteMode::Permanent);
+        assert_eq!(results[0].error_code, Some(ErrorCode::InvalidInput));
+        assert!(dir.path().join("a.txt").exists());
+    }
+
+    #[test]
+    fn test_remove_duplicates_never_removes_the_kept_copy() {
+        let dir = tempdir().unwrap();
+        let real = dir.path().join("real");
+        fs::create_dir(&real).unwrap();
+        fs::write(real.join("a.txt"), "only copy").unwrap();
+        let kept = DefaultFileScanner::new().scan(&real).unwrap();
+        let resolution = |delete: PathBuf| DuplicateResolution {
+            hash: "abc".to_string(),
+            reclaimable: 9,
+            keep: kept.clone(),
+            delete: vec![FileInfo {
+                path: delete,
+                ..kept[0].clone()
+            }],
+            protected: Vec::new(),
+        };
+
+        let ops = FileOperations::new();
+        let mut plans = vec![resolution(real.join("a.txt"))];
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&real, dir.path().join("link")).unwrap();
+            plans.push(resolution(dir.path().join("link/a.txt")));
+        }
+        let results = ops.remove_duplicates(&plans, DeleteMode::Permanent);
+        assert_eq!(results.len(), plans.len());
+        for result in &results {
+            assert!(!result.success);
+            assert_eq!(result.error_code, Some(ErrorCode::InvalidInput));
+        }
+        assert!(real.join("a.txt").exists());
+    }
+
     #[test]
     fn test_file_operations() {
         let dir = tempdir().unwrap();
@@ -279,6 +730,64 @@ mod tests {
         assert!(results[1].error.is_some());
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_an_aborting_hook_keeps_the_files() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("keep.txt");
+        fs::write(&file, "content").unwrap();
+        let hook = |command: &str| {
+            Hooks::new(vec![space_saver_utils::Hook {
+                name: "check".to_string(),
+                operation: "delete".to_string(),
+                when: "before".to_string(),
+                command: command.to_string(),
+                timeout_secs: 5,
+                on_failure: "abort".to_string(),
+                enabled: true,
+            }])
+        };
+
+        let results = FileOperations::new()
+            .with_hooks(hook("echo 'backup running' >&2; exit 1"))
+            .delete_files_with_mode(std::slice::from_ref(&file), DeleteMode::Permanent);
+        assert!(!results[0].success);
+        let error = results[0].error.as_deref().unwrap();
+        assert!(error.contains("backup running"), "{error}");
+        assert!(file.exists());
+
+        let results = FileOperations::new()
+            .with_hooks(hook("true"))
+            .delete_files_with_mode(std::slice::from_ref(&file), DeleteMode::Permanent);
+        assert!(results[0].success);
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn test_delete_files_reports_why_each_file_failed() {
+        let dir = tempdir().unwrap();
+        let existing = dir.path().join("existing.txt");
+        fs::write(&existing, "content").unwrap();
+        let kept = dir.path().join("kept");
+        fs::create_dir(&kept).unwrap();
+        let precious = kept.join("precious.txt");
+        fs::write(&precious, "content").unwrap();
+        let missing = dir.path().join("missing.txt");
+
+        let ops =
+            FileOperations::new().with_protected_paths(ProtectedPaths::new().with_paths([&kept]));
+        let results = ops.delete_files(&[existing.clone(), precious.clone(), missing]);
+
+        assert!(results[0].success);
+        assert_eq!(results[0].error_code, None);
+        assert!(!existing.exists());
+        assert!(results[1].protected);
+        assert_eq!(results[1].error_code, Some(ErrorCode::Protected));
+        assert!(precious.exists());
+        assert!(!results[2].success);
+        assert_eq!(results[2].error_code, Some(ErrorCode::NotFound));
+    }
+
     #[test]
     fn test_delete_empty_directory_permanently() {
         let dir = tempdir().unwrap();
@@ -311,6 +820,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_empty_trees_collapse_deepest_first() {
+        let dir = tempdir().unwrap();
+        let hollow = dir.path().join("hollow");
+        fs::create_dir_all(hollow.join("a/b")).unwrap();
+        fs::create_dir_all(hollow.join("c")).unwrap();
+        let occupied = dir.path().join("occupied");
+        fs::create_dir_all(occupied.join("later")).unwrap();
+        // Content that appeared after the scan reported the tree as empty
+        fs::write(occupied.join("later/new.txt"), "data").unwrap();
+
+        let ops = FileOperations::new();
+        let results =
+            ops.remove_empty_trees(&[hollow.clone(), occupied.clone()], DeleteMode::Permanent);
+
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert_eq!(results[0].removed.len(), 4);
+        assert!(results[0].removed[0].ends_with("b") || results[0].removed[0].ends_with("c"));
+        assert_eq!(results[0].removed[3], hollow.to_string_lossy());
+        assert!(!hollow.exists());
+        assert!(!results[1].success);
+        assert!(results[1].removed.is_empty());
+        assert!(occupied.join("later/new.txt").exists());
+    }
+
     #[test]
     fn test_delete_to_trash() {
         // Trash availability depends on the environment (e.g. tmpfs mounts
@@ -332,6 +866,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_protected_paths_are_refused() {
+        let dir = tempdir().unwrap();
+        let keep = dir.path().join("keep");
+        fs::create_dir(&keep).unwrap();
+        let precious = keep.join("precious.txt");
+        fs::write(&precious, "data").unwrap();
+        let loose = dir.path().join("loose.txt");
+        fs::write(&loose, "data").unwrap();
+
+        let ops =
+            FileOperations::new().with_protected_paths(ProtectedPaths::new().with_paths([&keep]));
+        let results =
+            ops.delete_files_with_mode(&[precious.clone(), loose.clone()], DeleteMode::Permanent);
+
+        assert!(!results[0].success);
+        assert!(results[0].protected);
+        assert!(results[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Protected path"));
+        assert!(precious.exists());
+        assert!(results[1].success);
+        assert!(!results[1].protected);
+        assert!(!loose.exists());
+
+        let err = ops.delete_file(&precious).unwrap_err();
+        assert!(err.downcast_ref::<crate::ProtectedPathError>().is_some());
+        assert!(ops
+            .move_file(&precious, &dir.path().join("moved.txt"))
+            .is_err());
+        assert!(precious.exists());
+    }
+
+    #[test]
+    fn test_plan_removal_touches_nothing_and_refuses_like_delete() {
+        let dir = tempdir().unwrap();
+        let keep = dir.path().join("keep");
+        fs::create_dir_all(keep.join("nested")).unwrap();
+        fs::write(keep.join("nested/precious.txt"), "data").unwrap();
+        let loose = dir.path().join("loose.txt");
+        fs::write(&loose, "12345").unwrap();
+        let empty = dir.path().join("empty");
+        fs::create_dir(&empty).unwrap();
+
+        let ops = FileOperations::new()
+            .with_protected_paths(ProtectedPaths::new().with_paths([&keep.join("nested")]));
+        let paths = [
+            loose.clone(),
+            empty.clone(),
+            keep.clone(),
+            keep.join("nested/precious.txt"),
+            dir.path().join("gone.txt"),
+        ];
+        let plan = ops.plan_removal(&paths, "delete");
+
+        let planned: Vec<&PathBuf> = plan.actions.iter().map(|a| &a.path).collect();
+        assert_eq!(planned, [&loose, &empty]);
+        assert_eq!(plan.reclaimed, 5);
+        let codes: Vec<ErrorCode> = plan.skipped.iter().map(|s| s.code).collect();
+        assert_eq!(
+            codes,
+            [
+                ErrorCode::InvalidInput,
+                ErrorCode::Protected,
+                ErrorCode::NotFound
+            ]
+        );
+        assert!(loose.exists() && empty.exists());
+    }
+
+    #[test]
+    fn test_trash_files_never_removes_refused_paths() {
+        let dir = tempdir().unwrap();
+        let keep = dir.path().join("keep");
+        fs::create_dir(&keep).unwrap();
+        let precious = keep.join("precious.txt");
+        fs::write(&precious, "data").unwrap();
+        let full = dir.path().join("full");
+        fs::create_dir(&full).unwrap();
+        fs::write(full.join("a.txt"), "a").unwrap();
+
+        let ops =
+            FileOperations::new().with_protected_paths(ProtectedPaths::new().with_paths([&keep]));
+        let results = ops.trash_files(&[precious.clone(), full.clone()]);
+
+        assert!(results[0].protected);
+        assert!(!results[1].success);
+        assert!(results[1].error.as_deref().unwrap().contains("not empty"));
+        assert!(precious.exists());
+        assert!(full.join("a.txt").exists());
+    }
+
     #[test]
     fn test_fix_extension_renames_to_detected_format() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,184 @@
+//! Deduplication by hard links instead of deletion.
+//!
+//! Each copy a [`DuplicateResolution`] would delete is instead replaced by a
+//! hard link to a kept copy, so every path still opens the same content but
+//! the data is stored once. Unlike a reflink, the paths are then one file:
+//! editing it through any of them changes all of them. Hard links cannot
+//! cross file systems; such copies are reported as failed and left alone.
+
+use crate::dedupe::DuplicateResolution;
+use crate::protection::ProtectedPaths;
+use crate::reflink::unchanged_since_scan;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_core::FileInfo;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-file outcome of hard link deduplication
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardlinkResult {
+    pub path: String,
+    /// The kept copy it is now a link to
+    pub source: String,
+    pub success: bool,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub protected: bool,
+    /// Bytes no longer stored twice; 0 when the paths were already linked
+    pub reclaimed: u64,
+}
+
+/// Replace every `delete` copy of each resolution with a hard link to its
+/// first `keep` copy. Copies changed since the scan are refused, as are
+/// protected paths; a failed link leaves the original file in place.
+pub fn hardlink_duplicates(
+    resolutions: &[DuplicateResolution],
+    protected: &ProtectedPaths,
+) -> Vec<HardlinkResult> {
+    let mut results = Vec::new();
+    for resolution in resolutions {
+        let Some(source) = resolution.keep.first() else {
+            continue;
+        };
+        for target in &resolution.delete {
+            let check = protected.check(&target.path);
+            let outcome = match &check {
+                Err(e) => Err(e.to_string()),
+                Ok(()) => replace_with_link(source, target).map_err(|e| format!("{:#}", e)),
+            };
+            results.push(HardlinkResult {
+                path: target.path.to_string_lossy().to_string(),
+                source: source.path.to_string_lossy().to_string(),
+                success: outcome.is_ok(),
+                reclaimed: match outcome {
+                    Ok(true) => target.size,
+                    _ => 0,
+                },
+                protected: check.is_err(),
+                error: outcome.err(),
+            });
+        }
+    }
+    results
+}
+
+/// Links `target` to `source`; false when it already was
+fn replace_with_link(source: &FileInfo, target: &FileInfo) -> Result<bool> {
+    unchanged_since_scan(source)?;
+    unchanged_since_scan(target)?;
+    if same_file(&source.path, &target.path)? {
+        return Ok(false);
+    }
+
+    let temp = temp_path(&target.path);
+    fs::hard_link(&source.path, &temp).with_context(|| {
+        format!(
+            "Failed to link {} to {}",
+            target.path.display(),
+            source.path.display()
+        )
+    })?;
+    fs::rename(&temp, &target.path)
+        .with_context(|| format!("Failed to replace {}", target.path.display()))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })?;
+    Ok(true)
+}
+
+/// Whether `a` and `b` are one file, reached through the same path or
+/// another (a hard link, a symlinked or bind-mounted directory)
+#[cfg(unix)]
+pub(crate) fn same_file(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn same_file(a: &Path, b: &Path) -> Result<bool> {
+    Ok(fs::canonicalize(a)? == fs::canonicalize(b)?)
+}
+
+/// Hidden sibling of `path` the link is made at before replacing it
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.hardlink-tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use tempfile::tempdir;
+
+    fn resolution(dir: &Path) -> DuplicateResolution {
+        let mut files = DefaultFileScanner::new().scan(dir).unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let delete = files.split_off(1);
+        DuplicateResolution {
+            hash: "abc".to_string(),
+            reclaimable: delete.iter().map(|f| f.size).sum(),
+            keep: files,
+            delete,
+            protected: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_copies_become_links_to_the_kept_file() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
+        fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
+        let resolutions = [resolution(dir.path())];
+
+        let results = hardlink_duplicates(&resolutions, &ProtectedPaths::empty());
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert_eq!(results[0].reclaimed, 15);
+        assert!(results[0].source.ends_with("a.bin"));
+        assert_eq!(
+            fs::read(dir.path().join("b.bin")).unwrap(),
+            b"identical bytes"
+        );
+        // No temp file is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        // Running again finds them already linked
+        #[cfg(unix)]
+        {
+            let again = hardlink_duplicates(&resolutions, &ProtectedPaths::empty());
+            assert!(again[0].success);
+            assert_eq!(again[0].reclaimed, 0);
+        }
+    }
+
+    #[test]
+    fn test_changed_and_protected_copies_are_refused() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
+        fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
+        fs::write(dir.path().join("c.bin"), b"identical bytes").unwrap();
+        let resolutions = [resolution(dir.path())];
+        fs::write(dir.path().join("b.bin"), b"edited since the scan").unwrap();
+
+        let protected = ProtectedPaths::empty().with_paths([dir.path().join("c.bin")]);
+        let results = hardlink_duplicates(&resolutions, &protected);
+        assert!(results[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("changed since"));
+        assert!(results[1].protected);
+        assert!(results.iter().all(|r| !r.success));
+        assert_eq!(
+            fs::read(dir.path().join("b.bin")).unwrap(),
+            b"edited since the scan"
+        );
+    }
+}
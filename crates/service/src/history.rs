@@ -0,0 +1,318 @@
+//! Storage trends: scans recorded in the database and compared over time.
+//!
+//! Each recorded scan keeps the size and category of every file it saw.
+//! [`storage_history`] lines up the scans of one path to show growth per
+//! category; [`diff_scans`] lists the files added, removed or resized
+//! between two of them. [`scan_details`] looks back at a single scan and
+//! [`scan_history`] lists the latest ones.
+
+use crate::api::ScanResult;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use space_saver_db::{CategoryTotal, DuplicateRecord, ScanFileRecord, ScanRecord, SqliteDatabase};
+use space_saver_utils::Error;
+use std::path::Path;
+use std::time::Duration;
+
+pub use space_saver_db::{CategoryGrowth, FileChange, ScanDiff};
+
+/// One recorded scan of a path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageSnapshot {
+    pub scan_id: i64,
+    /// Unix timestamp of the scan
+    pub scanned_at: i64,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Totals per file category, by category name
+    pub categories: Vec<CategoryTotal>,
+}
+
+/// Recorded scans of a path, oldest first, with growth per category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageHistory {
+    pub path: String,
+    pub snapshots: Vec<StorageSnapshot>,
+    /// Change per category from the first to the latest snapshot; empty
+    /// until the path has been scanned at least twice
+    pub growth: Vec<CategoryGrowth>,
+}
+
+/// How many of a scan's largest files [`scan_details`] lists
+pub const LARGEST_FILES: usize = 10;
+
+/// One recorded scan in detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanDetails {
+    pub scan: ScanRecord,
+    /// Totals per file category, by category name
+    pub categories: Vec<CategoryTotal>,
+    /// The largest files it saw, largest first
+    pub largest: Vec<ScanFileRecord>,
+    /// Recorded duplicate groups with a copy under the scanned path, most
+    /// wasted space first
+    pub duplicates: Vec<DuplicateRecord>,
+}
+
+/// A recorded scan with the space recorded duplicates waste under its path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanHistoryEntry {
+    pub scan: ScanRecord,
+    /// Bytes keeping one copy of each recorded duplicate group with a copy
+    /// under the scanned path would free
+    pub reclaimable: u64,
+}
+
+/// Record a scan and the files it saw, returning the scan id. The path is
+/// stored in absolute form so later scans of it line up.
+pub fn record_scan(result: &ScanResult, duration: Duration, db: &SqliteDatabase) -> Result<i64> {
+    let path = history_key(&result.path)?;
+    let scan = ScanRecord::new(
+        path,
+        result.file_count,
+        result.total_size,
+        duration.as_secs() as i64,
+    );
+    let id = db.insert_scan(&scan)?;
+    let files: Vec<ScanFileRecord> = result
+        .files
+        .iter()
+        .map(|file| ScanFileRecord {
+            path: file.path.to_string_lossy().to_string(),
+            size: file.size,
+            file_type: format!("{:?}", file.file_type),
+        })
+        .collect();
+    db.insert_scan_files(id, &files)?;
+    Ok(id)
+}
+
+/// Every recorded scan of `path`, oldest first, with the change per category
+/// from the first to the latest
+pub fn storage_history(path: &Path, db: &SqliteDatabase) -> Result<StorageHistory> {
+    let path = history_key(path)?;
+    let snapshots = db
+        .get_scans_for_path(&path)?
+        .into_iter()
+        .map(|scan| {
+            Ok(StorageSnapshot {
+                categories: db.get_scan_category_totals(scan.id)?,
+                scan_id: scan.id,
+                scanned_at: scan.created_at,
+                file_count: scan.file_count,
+                total_size: scan.total_size,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let growth = match (snapshots.first(), snapshots.last()) {
+        (Some(first), Some(last)) if snapshots.len() > 1 => {
+            CategoryGrowth::between(&first.categories, &last.categories)
+        }
+        _ => Vec::new(),
+    };
+    Ok(StorageHistory {
+        path,
+        snapshots,
+        growth,
+    })
+}
+
+/// The recorded scan `id` with its categories, largest files and the
+/// duplicate groups found under its path. Unknown scans are `NotFound`.
+pub fn scan_details(id: i64, db: &SqliteDatabase) -> Result<ScanDetails> {
+    let scan = db
+        .get_scan(id)?
+        .ok_or_else(|| Error::NotFound(format!("Unknown scan: {}", id)))?;
+    let mut largest = db.get_scan_files(id)?;
+    largest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    largest.truncate(LARGEST_FILES);
+    let duplicates = duplicates_under(&scan.path, &db.get_duplicates()?)
+        .cloned()
+        .collect();
+    Ok(ScanDetails {
+        categories: db.get_scan_category_totals(id)?,
+        largest,
+        duplicates,
+        scan,
+    })
+}
+
+/// The `limit` most recent recorded scans of any path, newest first, each
+/// with what its path's recorded duplicates would free
+pub fn scan_history(limit: usize, db: &SqliteDatabase) -> Result<Vec<ScanHistoryEntry>> {
+    let duplicates = db.get_duplicates()?;
+    Ok(db
+        .get_recent_scans(limit)?
+        .into_iter()
+        .map(|scan| ScanHistoryEntry {
+            reclaimable: duplicates_under(&scan.path, &duplicates)
+                .map(|group| group.wasted_space)
+                .sum(),
+            scan,
+        })
+        .collect())
+}
+
+/// Forget the recorded scan `id` and the files it saw. Unknown scans are
+/// `NotFound`.
+pub fn delete_scan(id: i64, db: &SqliteDatabase) -> Result<()> {
+    if !db.delete_scan(id)? {
+        return Err(Error::NotFound(format!("Unknown scan: {}", id)).into());
+    }
+    Ok(())
+}
+
+/// The groups of `duplicates` with a copy under `root`
+fn duplicates_under<'a>(
+    root: &'a str,
+    duplicates: &'a [DuplicateRecord],
+) -> impl Iterator<Item = &'a DuplicateRecord> {
+    duplicates.iter().filter(move |group| {
+        group
+            .file_paths
+            .iter()
+            .any(|p| Path::new(p).starts_with(root))
+    })
+}
+
+/// Compare two recorded scans of the same path; see
+/// [`SqliteDatabase::diff_scans`]
+pub fn diff_scans(from: i64, to: i64, db: &SqliteDatabase) -> Result<ScanDiff> {
+    db.diff_scans(from, to)
+}
+
+fn history_key(path: &Path) -> Result<String> {
+    Ok(std::path::absolute(path)?.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn record(dir: &Path, db: &SqliteDatabase) -> i64 {
+        let files = DefaultFileScanner::new().scan(dir).unwrap();
+        let result = ScanResult {
+            path: dir.to_path_buf(),
+            file_count: files.len(),
+            total_size: files.iter().map(|f| f.size).sum(),
+            files,
+        };
+        record_scan(&result, Duration::from_secs(1), db).unwrap()
+    }
+
+    #[test]
+    fn test_history_and_diff_between_scans() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        fs::write(dir.path().join("a.jpg"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("log.txt"), vec![0u8; 10]).unwrap();
+        fs::write(dir.path().join("old.bin"), vec![0u8; 50]).unwrap();
+        let first = record(dir.path(), &db);
+
+        fs::write(dir.path().join("b.jpg"), vec![0u8; 300]).unwrap();
+        fs::write(dir.path().join("log.txt"), vec![0u8; 40]).unwrap();
+        fs::remove_file(dir.path().join("old.bin")).unwrap();
+        let second = record(dir.path(), &db);
+
+        let history = storage_history(dir.path(), &db).unwrap();
+        assert_eq!(history.snapshots.len(), 2);
+        assert_eq!(history.snapshots[1].total_size, 440);
+        let growth = |category: &str| {
+            history
+                .growth
+                .iter()
+                .find(|g| g.category == category)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(growth("Image").size_change, 300);
+        assert_eq!(growth("Image").file_count_change, 1);
+        assert_eq!(growth("Document").size_change, 30);
+        assert_eq!(growth("Other").size_change, -50);
+
+        let diff = diff_scans(first, second, &db).unwrap();
+        assert_eq!(diff.size_change, 280);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.added[0].path.ends_with("b.jpg"));
+        assert_eq!(diff.removed[0].old_size, 50);
+        assert_eq!(
+            (diff.resized[0].old_size, diff.resized[0].new_size),
+            (10, 40)
+        );
+        assert_eq!(diff.categories, history.growth);
+    }
+
+    #[test]
+    fn test_diff_refuses_unknown_or_unrelated_scans() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let (a, b) = (tempdir().unwrap(), tempdir().unwrap());
+        let first = record(a.path(), &db);
+        let other = record(b.path(), &db);
+
+        assert!(diff_scans(first, 999, &db)
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown scan"));
+        assert!(diff_scans(first, other, &db)
+            .unwrap_err()
+            .to_string()
+            .contains("different paths"));
+        assert!(storage_history(a.path(), &db).unwrap().growth.is_empty());
+    }
+
+    #[test]
+    fn test_scan_details_list_largest_files_and_duplicates_under_the_path() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        fs::write(dir.path().join("a.jpg"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("b.jpg"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("log.txt"), vec![0u8; 10]).unwrap();
+        let id = record(dir.path(), &db);
+        let inside = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        db.insert_duplicates(&[
+            DuplicateRecord::new(
+                "abc".to_string(),
+                vec![inside("a.jpg"), inside("b.jpg")],
+                2,
+                200,
+                100,
+            ),
+            DuplicateRecord::new(
+                "def".to_string(),
+                vec!["/elsewhere/x".to_string(), "/elsewhere/y".to_string()],
+                2,
+                20,
+                10,
+            ),
+        ])
+        .unwrap();
+
+        let details = scan_details(id, &db).unwrap();
+        assert_eq!(details.scan.file_count, 3);
+        assert_eq!(details.largest.len(), 3);
+        assert!(details.largest[0].path.ends_with("a.jpg"));
+        assert!(details.largest[2].path.ends_with("log.txt"));
+        assert_eq!(details.categories.len(), 2);
+        assert_eq!(details.duplicates.len(), 1);
+        assert_eq!(details.duplicates[0].hash, "abc");
+
+        let err = Error::from(scan_details(999, &db).unwrap_err());
+        assert_eq!(err.code(), space_saver_utils::ErrorCode::NotFound);
+
+        let other = record(tempdir().unwrap().path(), &db);
+        let history = scan_history(10, &db).unwrap();
+        let reclaimable: Vec<(i64, u64)> = history
+            .iter()
+            .map(|entry| (entry.scan.id, entry.reclaimable))
+            .collect();
+        assert_eq!(reclaimable, [(other, 0), (id, 100)]);
+        delete_scan(id, &db).unwrap();
+        assert_eq!(scan_history(10, &db).unwrap().len(), 1);
+        let err = Error::from(delete_scan(id, &db).unwrap_err());
+        assert_eq!(err.code(), space_saver_utils::ErrorCode::NotFound);
+    }
+}
@@ -0,0 +1,395 @@
+//! Hook commands around deletions, compressions and cleanups.
+//!
+//! A [`Hook`] from the config names an operation, whether it runs before or
+//! after it, a shell command and what to do when the command fails.
+//! [`Hooks::around`] runs the matching before hooks, then the operation
+//! unless one of them failed with `on_failure = "abort"`, then the after
+//! hooks with the outcome. Hooks run one at a time in config order, each
+//! killed after its `timeout_secs`.
+//!
+//! Commands see what the operation is about in their environment:
+//! `SPACE_SAVER_HOOK`, `SPACE_SAVER_OPERATION`, `SPACE_SAVER_WHEN`,
+//! `SPACE_SAVER_COUNT` and `SPACE_SAVER_PATHS_FILE` (a file listing the
+//! paths, one per line); after hooks also get `SPACE_SAVER_STATUS` ("ok",
+//! "partial" or "failed"), `SPACE_SAVER_SUCCEEDED`, `SPACE_SAVER_FAILED` and
+//! `SPACE_SAVER_FREED` (bytes). The same names in braces, lowercase and
+//! without the prefix (`{count}`), are replaced in the command itself.
+
+use anyhow::{Context, Result};
+use space_saver_utils::{Error, Hook};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// What an operation came to, for after hooks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HookOutcome {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Bytes freed
+    pub freed: u64,
+}
+
+impl HookOutcome {
+    /// "ok" when nothing failed, "failed" when nothing succeeded,
+    /// "partial" otherwise
+    pub fn status(&self) -> &'static str {
+        match (self.succeeded, self.failed) {
+            (_, 0) => "ok",
+            (0, _) => "failed",
+            _ => "partial",
+        }
+    }
+}
+
+/// The enabled hooks of a config
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<Hook>,
+}
+
+impl Hooks {
+    pub fn new(hooks: Vec<Hook>) -> Self {
+        Self {
+            hooks: hooks.into_iter().filter(|hook| hook.enabled).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run `operation` ("delete", "compress" or "cleanup") on `paths` with
+    /// its hooks around it; `outcome` tells the after hooks how it went.
+    /// Fails, without running `op`, when a before hook fails and aborts.
+    /// Once `op` ran its result is returned, whatever the after hooks do.
+    pub fn around<T>(
+        &self,
+        operation: &str,
+        paths: &[PathBuf],
+        op: impl FnOnce() -> T,
+        outcome: impl FnOnce(&T) -> HookOutcome,
+    ) -> Result<T> {
+        if self.is_empty() {
+            return Ok(op());
+        }
+        self.run(operation, "before", paths, None)?;
+        let result = op();
+        if let Err(e) = self.run(operation, "after", paths, Some(outcome(&result))) {
+            tracing::warn!(operation, error = %e, "After hooks could not run");
+        }
+        Ok(result)
+    }
+
+    fn run(
+        &self,
+        operation: &str,
+        when: &str,
+        paths: &[PathBuf],
+        outcome: Option<HookOutcome>,
+    ) -> Result<()> {
+        let hooks: Vec<&Hook> = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.operation == operation && hook.when == when)
+            .collect();
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        let mut listed = tempfile::NamedTempFile::new().context("Failed to list paths")?;
+        for path in paths {
+            writeln!(listed, "{}", path.display())?;
+        }
+        listed.flush()?;
+
+        let mut vars = vec![
+            ("operation", operation.to_string()),
+            ("when", when.to_string()),
+            ("count", paths.len().to_string()),
+        ];
+        if let Some(outcome) = outcome {
+            vars.extend([
+                ("status", outcome.status().to_string()),
+                ("succeeded", outcome.succeeded.to_string()),
+                ("failed", outcome.failed.to_string()),
+                ("freed", outcome.freed.to_string()),
+            ]);
+        }
+        for hook in hooks {
+            let Err(e) = run_hook(hook, &vars, listed.path()) else {
+                continue;
+            };
+            match hook.on_failure.as_str() {
+                // Whatever the config says, what already happened stands
+                "abort" if when == "before" => {
+                    return Err(Error::Task(format!(
+                        "Hook '{}' failed, so the {} did not run: {:#}",
+                        hook.name, operation, e
+                    ))
+                    .into())
+                }
+                "ignore" => tracing::debug!(hook = %hook.name, error = %e, "Hook failed"),
+                _ => tracing::warn!(hook = %hook.name, error = %e, "Hook failed"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `hook`'s command with `vars` substituted and in its environment,
+/// waiting at most its timeout. Processes it leaves behind still holding
+/// its stderr are killed when the timeout runs out.
+fn run_hook(hook: &Hook, vars: &[(&str, String)], paths_file: &std::path::Path) -> Result<()> {
+    let command = vars
+        .iter()
+        .fold(hook.command.clone(), |command, (name, value)| {
+            command.replace(&format!("{{{}}}", name), value)
+        });
+    let mut cmd = shell(&command);
+    cmd.env("SPACE_SAVER_HOOK", &hook.name)
+        .env("SPACE_SAVER_PATHS_FILE", paths_file);
+    for (name, value) in vars {
+        cmd.env(format!("SPACE_SAVER_{}", name.to_ascii_uppercase()), value);
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start `{}`", command))?;
+
+    // Drained on a thread so a chatty command cannot block on a full pipe
+    let stderr = child.stderr.take();
+    let (sender, drained) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut buf);
+        }
+        let _ = sender.send(buf);
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(hook.timeout_secs);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill_group(&mut child);
+            let _ = child.wait();
+            anyhow::bail!(Error::TimedOut(format!(
+                "Timed out after {}s",
+                hook.timeout_secs
+            )));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    // Background processes of the command keep stderr open until they exit
+    let left = deadline.saturating_duration_since(Instant::now());
+    let stderr = drained.recv_timeout(left).unwrap_or_else(|_| {
+        kill_group(&mut child);
+        drained
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap_or_default()
+    });
+    if !status.success() {
+        let last = stderr.lines().rfind(|line| !line.trim().is_empty());
+        match last {
+            Some(line) => anyhow::bail!("Exited with {}: {}", status, line.trim()),
+            None => anyhow::bail!("Exited with {}", status),
+        }
+    }
+    Ok(())
+}
+
+/// The command runs in a process group of its own, so [`kill_group`]
+/// reaches whatever it starts
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).process_group(0);
+    cmd
+}
+
+/// Kill `child` and the processes it started
+#[cfg(unix)]
+fn kill_group(child: &mut Child) {
+    // The group keeps the leader's id while any member is left, even after
+    // the leader was reaped, so this can't hit an unrelated process
+    let group = -(child.id() as libc::pid_t);
+    // SAFETY: kill takes no pointers; a negative pid names a process group
+    unsafe { libc::kill(group, libc::SIGKILL) };
+}
+
+#[cfg(windows)]
+fn kill_group(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut cmd = Command::new("cmd");
+    // Passed as is: cmd does its own parsing of the line
+    cmd.arg("/C").raw_arg(command);
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    cmd
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn hook(name: &str, when: &str, command: String, on_failure: &str) -> Hook {
+        Hook {
+            name: name.to_string(),
+            operation: "delete".to_string(),
+            when: when.to_string(),
+            command,
+            timeout_secs: 5,
+            on_failure: on_failure.to_string(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_hooks_see_the_operation_and_its_outcome() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("log");
+        let hooks = Hooks::new(vec![
+            hook(
+                "pause",
+                "before",
+                format!(
+                    "echo \"$SPACE_SAVER_HOOK {{when}} {{count}}\" >> {0}; cat \"$SPACE_SAVER_PATHS_FILE\" >> {0}",
+                    log.display()
+                ),
+                "abort",
+            ),
+            hook(
+                "notify",
+                "after",
+                format!(
+                    "echo \"{{status}} $SPACE_SAVER_SUCCEEDED {{freed}}\" >> {}",
+                    log.display()
+                ),
+                "warn",
+            ),
+        ]);
+        let paths = [PathBuf::from("/data/a.txt"), PathBuf::from("/data/b.txt")];
+        let result = hooks
+            .around(
+                "delete",
+                &paths,
+                || 7,
+                |_| HookOutcome {
+                    succeeded: 1,
+                    failed: 1,
+                    freed: 42,
+                },
+            )
+            .unwrap();
+        assert_eq!(result, 7);
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "pause before 2\n/data/a.txt\n/data/b.txt\npartial 1 42\n"
+        );
+
+        // Other operations run without them
+        std::fs::remove_file(&log).unwrap();
+        hooks
+            .around("compress", &paths, || (), |_| HookOutcome::default())
+            .unwrap();
+        assert!(!log.exists());
+    }
+
+    #[test]
+    fn test_failing_before_hooks_abort_only_when_asked() {
+        let ran = std::cell::Cell::new(false);
+        let failing = |on_failure| {
+            Hooks::new(vec![hook(
+                "check",
+                "before",
+                "echo 'agent busy' >&2; exit 3".to_string(),
+                on_failure,
+            )])
+        };
+
+        let err = failing("abort")
+            .around("delete", &[], || ran.set(true), |_| HookOutcome::default())
+            .unwrap_err();
+        assert!(!ran.get());
+        assert!(err.to_string().contains("agent busy"), "{err:#}");
+
+        failing("warn")
+            .around("delete", &[], || ran.set(true), |_| HookOutcome::default())
+            .unwrap();
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn test_hooks_are_killed_after_their_timeout() {
+        let mut slow = hook("slow", "before", "sleep 10".to_string(), "abort");
+        slow.timeout_secs = 1;
+        let started = Instant::now();
+        let err = Hooks::new(vec![slow])
+            .around("delete", &[], || (), |_| HookOutcome::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("Timed out"), "{err:#}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_timeouts_kill_what_the_hook_started() {
+        let dir = tempdir().unwrap();
+        let marker = dir.path().join("late");
+        let mut slow = hook(
+            "slow",
+            "before",
+            format!("(sleep 2; touch {}) & sleep 10", marker.display()),
+            "abort",
+        );
+        slow.timeout_secs = 1;
+        let err = Hooks::new(vec![slow])
+            .around("delete", &[], || (), |_| HookOutcome::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("Timed out"), "{err:#}");
+
+        std::thread::sleep(Duration::from_secs(3));
+        assert!(!marker.exists(), "the background process outlived the hook");
+    }
+
+    #[test]
+    fn test_background_processes_holding_stderr_cannot_stall_the_hook() {
+        let dir = tempdir().unwrap();
+        let marker = dir.path().join("late");
+        let mut quick = hook(
+            "quick",
+            "before",
+            format!("(sleep 3; touch {}) &", marker.display()),
+            "abort",
+        );
+        quick.timeout_secs = 1;
+        let started = Instant::now();
+        Hooks::new(vec![quick])
+            .around("delete", &[], || (), |_| HookOutcome::default())
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        std::thread::sleep(Duration::from_secs(3));
+        assert!(!marker.exists(), "the background process outlived the hook");
+    }
+
+    #[test]
+    fn test_failing_after_hooks_leave_the_result_alone() {
+        let hooks = Hooks::new(vec![hook("notify", "after", "exit 1".to_string(), "abort")]);
+        let result = hooks
+            .around("delete", &[], || 7, |_| HookOutcome::default())
+            .unwrap();
+        assert_eq!(result, 7);
+    }
+}
@@ -0,0 +1,203 @@
+//! Groups the user keeps on purpose.
+//!
+//! Ignoring a duplicate or similar group stores its files (and, for
+//! duplicates, their content hash) in the database; later results leave out
+//! any group made only of those files. A group that gains a new copy, or
+//! whose files changed content, shows up again.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use space_saver_db::{IgnoredGroupRecord, SqliteDatabase};
+use space_saver_utils::Error;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::api::{DuplicateGroup, SimilarGroup};
+
+/// Which results an ignored group applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IgnoreKind {
+    Duplicate,
+    Similar,
+}
+
+impl IgnoreKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IgnoreKind::Duplicate => "duplicate",
+            IgnoreKind::Similar => "similar",
+        }
+    }
+}
+
+/// Ignored groups, loaded once per search
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreList {
+    entries: Vec<IgnoredGroupRecord>,
+}
+
+impl IgnoreList {
+    pub fn new(entries: Vec<IgnoredGroupRecord>) -> Self {
+        Self { entries }
+    }
+
+    pub fn load(db: &SqliteDatabase) -> Result<Self> {
+        Ok(Self::new(db.get_ignored_groups()?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether `group` has the hash of an ignored duplicate group and no
+    /// files beyond it
+    pub fn ignores_duplicate(&self, group: &DuplicateGroup) -> bool {
+        let paths: Vec<&Path> = group.files.iter().map(|f| f.path.as_path()).collect();
+        self.matches(IgnoreKind::Duplicate, Some(&group.hash), &paths)
+    }
+
+    /// Whether every file of `group` belongs to one ignored similar group
+    pub fn ignores_similar(&self, group: &SimilarGroup) -> bool {
+        let paths: Vec<&Path> = group.files.iter().map(|f| Path::new(&f.path)).collect();
+        self.matches(IgnoreKind::Similar, None, &paths)
+    }
+
+    fn matches(&self, kind: IgnoreKind, hash: Option<&str>, paths: &[&Path]) -> bool {
+        let Ok(keys) = paths
+            .iter()
+            .map(|p| path_key(p))
+            .collect::<Result<Vec<_>>>()
+        else {
+            return false;
+        };
+        self.entries.iter().any(|entry| {
+            entry.kind == kind.as_str() && (hash.is_none() || entry.hash.as_deref() == hash) && {
+                let ignored: HashSet<&str> = entry.paths.iter().map(String::as_str).collect();
+                keys.iter().all(|key| ignored.contains(key.as_str()))
+            }
+        })
+    }
+}
+
+/// Store a group to ignore. Needs at least two files, and the content hash
+/// for a duplicate group.
+pub fn ignore_group(
+    kind: IgnoreKind,
+    paths: &[String],
+    hash: Option<String>,
+    db: &SqliteDatabase,
+) -> Result<IgnoredGroupRecord> {
+    let mut keys = paths
+        .iter()
+        .map(|p| path_key(Path::new(p)))
+        .collect::<Result<Vec<_>>>()?;
+    keys.sort();
+    keys.dedup();
+    if keys.len() < 2 {
+        return Err(
+            Error::InvalidInput("An ignored group needs at least two files".to_string()).into(),
+        );
+    }
+    let hash = hash.filter(|h| !h.is_empty());
+    if kind == IgnoreKind::Duplicate && hash.is_none() {
+        return Err(Error::InvalidInput(
+            "An ignored duplicate group needs its content hash".to_string(),
+        )
+        .into());
+    }
+    let mut record = IgnoredGroupRecord::new(kind.as_str(), hash, keys);
+    record.id = db.insert_ignored_group(&record)?;
+    Ok(record)
+}
+
+fn path_key(path: &Path) -> Result<String> {
+    Ok(std::path::absolute(path)?.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{MediaKind, SimilarFile};
+    use space_saver_core::scanner::FileType;
+    use space_saver_core::FileInfo;
+    use std::path::PathBuf;
+
+    fn duplicate(hash: &str, paths: &[&str]) -> DuplicateGroup {
+        let files: Vec<FileInfo> = paths
+            .iter()
+            .map(|p| FileInfo {
+                path: PathBuf::from(p),
+                size: 10,
+                modified: 0,
+                file_type: FileType::Image,
+                hash: None,
+                uid: None,
+                gid: None,
+                mode: None,
+            })
+            .collect();
+        DuplicateGroup {
+            hash: hash.to_string(),
+            count: files.len(),
+            total_size: 10 * files.len() as u64,
+            wasted_space: 10 * (files.len() as u64 - 1),
+            files,
+        }
+    }
+
+    fn strings(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_ignored_duplicate_group_hidden_until_it_changes() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let pair = ["/photos/a.jpg", "/backup/a.jpg"];
+        ignore_group(
+            IgnoreKind::Duplicate,
+            &strings(&pair),
+            Some("abc".into()),
+            &db,
+        )
+        .unwrap();
+        let list = IgnoreList::load(&db).unwrap();
+
+        assert!(list.ignores_duplicate(&duplicate("abc", &pair)));
+        // A third copy appeared
+        assert!(!list.ignores_duplicate(&duplicate(
+            "abc",
+            &["/photos/a.jpg", "/backup/a.jpg", "/tmp/a.jpg"]
+        )));
+        // Same files, new content
+        assert!(!list.ignores_duplicate(&duplicate("def", &pair)));
+
+        let similar = SimilarGroup {
+            media_kind: MediaKind::Image,
+            files: pair
+                .iter()
+                .map(|p| SimilarFile {
+                    path: p.to_string(),
+                    size: 10,
+                    modified: 0,
+                    width: None,
+                    height: None,
+                })
+                .collect(),
+            similarity_score: 0.95,
+        };
+        assert!(!list.ignores_similar(&similar));
+        ignore_group(IgnoreKind::Similar, &strings(&pair), None, &db).unwrap();
+        assert!(IgnoreList::load(&db).unwrap().ignores_similar(&similar));
+    }
+
+    #[test]
+    fn test_ignore_group_validation() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let err = ignore_group(IgnoreKind::Similar, &strings(&["/a", "/a"]), None, &db);
+        assert!(err.unwrap_err().to_string().contains("two files"));
+        let err = ignore_group(IgnoreKind::Duplicate, &strings(&["/a", "/b"]), None, &db);
+        assert!(err.unwrap_err().to_string().contains("hash"));
+        assert!(db.get_ignored_groups().unwrap().is_empty());
+    }
+}
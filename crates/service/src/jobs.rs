@@ -0,0 +1,281 @@
+//! Registry of background jobs, so frontends can list them, show their
+//! progress and cancel them.
+//!
+//! Whoever runs a job registers it with [`JobManager::register`] and feeds
+//! every [`ProgressUpdate`] it emits to the returned [`JobHandle`]; the work
+//! itself checks the handle's [`CancellationToken`].
+
+use crate::progress::ProgressUpdate;
+use crate::task::{TaskStatus, TaskType};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use space_saver_core::CancellationToken;
+use space_saver_utils::Error;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+pub type JobId = u64;
+
+/// Finished jobs kept for `list`/`status`; older ones are forgotten
+const MAX_FINISHED_JOBS: usize = 50;
+
+/// Snapshot of one job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub task_type: TaskType,
+    pub status: TaskStatus,
+    /// Items done and items in total, from the latest progress report
+    pub current: usize,
+    pub total: usize,
+    /// Latest progress message
+    pub message: String,
+    /// Unix timestamp of registration
+    pub started_at: i64,
+}
+
+impl JobInfo {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.status, TaskStatus::Pending | TaskStatus::Running)
+    }
+}
+
+struct JobEntry {
+    info: JobInfo,
+    token: CancellationToken,
+}
+
+#[derive(Default)]
+struct JobTable {
+    next_id: JobId,
+    jobs: BTreeMap<JobId, JobEntry>,
+}
+
+/// Tracks running and recently finished jobs. Clones share the same table.
+#[derive(Clone, Default)]
+pub struct JobManager {
+    table: Arc<Mutex<JobTable>>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a pending job
+    pub fn register(&self, task_type: TaskType) -> JobHandle {
+        let token = CancellationToken::new();
+        let mut table = self.lock();
+        table.next_id += 1;
+        let id = table.next_id;
+        table.jobs.insert(
+            id,
+            JobEntry {
+                info: JobInfo {
+                    id,
+                    task_type,
+                    status: TaskStatus::Pending,
+                    current: 0,
+                    total: 0,
+                    message: String::new(),
+                    started_at: space_saver_utils::time::now(),
+                },
+                token: token.clone(),
+            },
+        );
+        JobHandle {
+            id,
+            token,
+            manager: self.clone(),
+        }
+    }
+
+    /// Every known job, oldest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.lock().jobs.values().map(|e| e.info.clone()).collect()
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobInfo> {
+        self.lock().jobs.get(&id).map(|e| e.info.clone())
+    }
+
+    /// Ask a job to stop. The job reports `Cancelled` once it notices.
+    /// Returns false when the job had already finished.
+    pub fn cancel(&self, id: JobId) -> Result<bool> {
+        let table = self.lock();
+        let Some(entry) = table.jobs.get(&id) else {
+            return Err(Error::NotFound(format!("Unknown job: {}", id)).into());
+        };
+        if entry.info.is_finished() {
+            return Ok(false);
+        }
+        entry.token.cancel();
+        Ok(true)
+    }
+
+    fn update(&self, id: JobId, update: &ProgressUpdate) {
+        let mut table = self.lock();
+        let Some(entry) = table.jobs.get_mut(&id) else {
+            return;
+        };
+        let info = &mut entry.info;
+        match update {
+            ProgressUpdate::Started { total_items, .. } => {
+                info.status = TaskStatus::Running;
+                info.total = *total_items;
+            }
+            ProgressUpdate::Progress {
+                current,
+                total,
+                message,
+                ..
+            } => {
+                info.status = TaskStatus::Running;
+                info.current = *current;
+                info.total = *total;
+                info.message.clone_from(message);
+            }
+            ProgressUpdate::Completed { message } => {
+                info.status = TaskStatus::Completed;
+                info.message.clone_from(message);
+            }
+            // A job that fails after being asked to stop was cancelled
+            ProgressUpdate::Failed { .. } if entry.token.is_cancelled() => {
+                info.status = TaskStatus::Cancelled;
+            }
+            ProgressUpdate::Failed { error } => {
+                info.status = TaskStatus::Failed(error.clone());
+            }
+            ProgressUpdate::Cancelled => {
+                info.status = TaskStatus::Cancelled;
+            }
+        }
+        if entry.info.is_finished() {
+            table.prune();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, JobTable> {
+        // The table holds plain data; a panic elsewhere cannot leave it torn
+        self.table.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl JobTable {
+    fn prune(&mut self) {
+        let finished: Vec<JobId> = self
+            .jobs
+            .values()
+            .filter(|e| e.info.is_finished())
+            .map(|e| e.info.id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// A registered job, held by whoever runs it
+pub struct JobHandle {
+    id: JobId,
+    token: CancellationToken,
+    manager: JobManager,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Cancelled by [`JobManager::cancel`]
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Record a progress update the job emitted
+    pub fn update(&self, update: &ProgressUpdate) {
+        self.manager.update(self.id, update);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn scan_job() -> TaskType {
+        TaskType::Scan(vec![PathBuf::from("/photos")])
+    }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let jobs = JobManager::new();
+        let handle = jobs.register(scan_job());
+        assert_eq!(
+            jobs.status(handle.id()).unwrap().status,
+            TaskStatus::Pending
+        );
+
+        handle.update(&ProgressUpdate::Started {
+            task_type: "scan".to_string(),
+            total_items: 2,
+        });
+        handle.update(&ProgressUpdate::Progress {
+            current: 1,
+            total: 2,
+            message: "Scanned /photos".to_string(),
+            bytes: 0,
+            total_bytes: 0,
+        });
+        let info = jobs.status(handle.id()).unwrap();
+        assert_eq!(info.status, TaskStatus::Running);
+        assert_eq!((info.current, info.total), (1, 2));
+        assert_eq!(info.message, "Scanned /photos");
+
+        handle.update(&ProgressUpdate::Completed {
+            message: "scan finished".to_string(),
+        });
+        assert_eq!(jobs.list().len(), 1);
+        assert!(jobs.list()[0].is_finished());
+        // Finished jobs cannot be cancelled
+        assert!(!jobs.cancel(handle.id()).unwrap());
+    }
+
+    #[test]
+    fn test_cancel_signals_the_token() {
+        let jobs = JobManager::new();
+        let handle = jobs.register(scan_job());
+        let token = handle.token();
+
+        assert!(jobs.cancel(handle.id()).unwrap());
+        assert!(token.is_cancelled());
+        // The work notices and stops with an error
+        handle.update(&ProgressUpdate::Failed {
+            error: "Cancelled".to_string(),
+        });
+        assert_eq!(
+            jobs.status(handle.id()).unwrap().status,
+            TaskStatus::Cancelled
+        );
+
+        let err = jobs.cancel(999).unwrap_err();
+        assert!(err.to_string().contains("Unknown job: 999"));
+    }
+
+    #[test]
+    fn test_finished_jobs_are_pruned() {
+        let jobs = JobManager::new();
+        let running = jobs.register(scan_job());
+        for _ in 0..MAX_FINISHED_JOBS + 5 {
+            jobs.register(scan_job()).update(&ProgressUpdate::Cancelled);
+        }
+
+        let listed = jobs.list();
+        assert_eq!(listed.len(), MAX_FINISHED_JOBS + 1);
+        // Running jobs are never pruned, however old
+        assert_eq!(listed[0].id, running.id());
+    }
+}
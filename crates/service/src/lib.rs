@@ -1,13 +1,91 @@
 pub mod api;
+pub mod app_caches;
+pub mod checksum;
+pub mod cleanup;
+pub mod cold_storage;
+pub mod compress;
+pub mod daemon;
+pub mod dedupe;
+pub mod details;
+pub mod dev_artifacts;
+pub mod drives;
+pub mod duplicate_dirs;
+pub mod estimate;
+pub mod export;
 pub mod file_ops;
+pub mod hardlink;
+pub mod history;
+pub mod hooks;
+pub mod ignore;
+pub mod jobs;
+pub mod maintenance;
+pub mod network;
+pub mod notifications;
+#[cfg(feature = "remote")]
+pub mod object_storage;
+pub mod path_check;
+pub mod plan;
 pub mod progress;
+pub mod protection;
+pub mod quarantine;
+pub mod records;
+pub mod reference;
+pub mod reflink;
+pub mod report;
+pub mod restore;
+pub mod scan_cache;
 pub mod scheduler;
+pub mod shutdown;
+pub mod storage;
+pub mod tags;
 pub mod task;
 pub mod tools;
 
 pub use api::ServiceApi;
-pub use file_ops::{DeleteMode, DeleteResult, FileOperations, FixExtensionResult};
+pub use app_caches::{
+    AppCacheReport, CacheCategory, CacheCategoryReport, CacheLocation, CacheLocator,
+};
+pub use checksum::{ChecksumResult, ChecksumStatus, HashedTree, Manifest, ManifestEntry};
+pub use cleanup::{CleanupFile, CleanupReport, CleanupRuleReport};
+pub use compress::{
+    compress_in_place, compression_operation, compression_outcome, configure_manager,
+    forward_progress,
+};
+pub use daemon::{run_schedule_once, Daemon, DaemonStatus, ScheduleStatus, UpcomingRun};
+pub use dedupe::{
+    DuplicateResolution, KeepFirstPath, KeepInDirectory, KeepNewest, KeepOldest, KeepRule,
+    KeepShortestPath, KeepStrategy,
+};
+pub use details::{ffprobe_path, file_details, FileDetails};
+pub use dev_artifacts::{ArtifactKind, BuildArtifact, DevArtifactReport, DevProject};
+pub use drives::{list_drives, DiskInfo, DriveKind};
+pub use duplicate_dirs::DuplicateDirectoryGroup;
+pub use estimate::{estimate_compression, CompressionEstimate, EstimateGroup};
+pub use export::{CompressibleFile, ExportFormat, ExportResults};
+pub use file_ops::{DeleteMode, DeleteResult, EmptyTreeResult, FileOperations, FixExtensionResult};
+pub use hardlink::{hardlink_duplicates, HardlinkResult};
+pub use history::{
+    CategoryGrowth, FileChange, ScanDetails, ScanDiff, ScanHistoryEntry, StorageHistory,
+    StorageSnapshot,
+};
+pub use hooks::{HookOutcome, Hooks};
+pub use ignore::{IgnoreKind, IgnoreList};
+pub use jobs::{JobHandle, JobId, JobInfo, JobManager};
+pub use maintenance::{schedule_maintenance, MaintenanceTask, MAINTENANCE_INTERVAL};
+pub use network::{NetworkShare, NetworkShares};
+pub use notifications::{Notification, Notifications, Notifier, SystemNotifier};
+pub use path_check::{check_paths, PathCheck};
+pub use plan::{ActionPlan, PlannedAction};
 pub use progress::{ProgressTracker, ProgressUpdate};
-pub use scheduler::Scheduler;
-pub use task::{Task, TaskStatus, TaskType};
+pub use protection::{ProtectedPathError, ProtectedPaths};
+pub use quarantine::Quarantine;
+pub use reference::{ReferenceCheck, ReferenceMatch};
+pub use reflink::{reflink_duplicates, ReflinkResult};
+pub use report::{StorageReport, DEFAULT_REPORT_TOP};
+pub use restore::{BackupPurge, CompressionBackup, RestoreResult};
+pub use scan_cache::ScanCache;
+pub use scheduler::{Scheduler, TaskPriority};
+pub use shutdown::{InterruptedJob, PartialOutputs, ShutdownReport};
+pub use tags::TagTarget;
+pub use task::{Task, TaskKind, TaskStatus, TaskType};
 pub use tools::{detect_tools, ToolStatus};
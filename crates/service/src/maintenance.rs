@@ -0,0 +1,257 @@
+//! Keeping the app database lean on long-lived installs.
+//!
+//! A maintenance pass forgets stored files that are gone from disk and
+//! similarity scores of files that changed, then vacuums the database file,
+//! recording what it did. [`schedule_maintenance`]
+//! queues a pass on a [`Scheduler`] at low priority once the last one is
+//! older than the interval, so it never holds up the user's own work. Given
+//! a [`FileHashCache`], the pass also compacts it.
+
+use crate::progress::ProgressUpdate;
+use crate::scheduler::{Scheduler, TaskPriority};
+use crate::task::{Task, TaskStatus, TaskType};
+use anyhow::Result;
+use async_trait::async_trait;
+use space_saver_db::{
+    CompactionReport, Database, FileHashCache, MaintenanceRecord, SqliteDatabase,
+};
+use space_saver_utils::format_size;
+use space_saver_utils::time::now;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How often a maintenance pass is due
+pub const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(7 * 86_400);
+
+/// Prune the files no longer on disk and the stale similarity scores from
+/// `db`, vacuum it and record the pass. Blocking; the vacuum waits for other connections' writes.
+pub fn run_maintenance(db: &SqliteDatabase) -> Result<MaintenanceRecord> {
+    let size_before = db.database_size()?.total;
+    let pruned_files = db.prune_missing_files()?;
+    let pruned_similarities = db.prune_stale_similarities()?;
+    db.vacuum()?;
+    let mut record = MaintenanceRecord {
+        id: 0,
+        pruned_files,
+        size_before,
+        size_after: db.database_size()?.total,
+        ran_at: now(),
+    };
+    record.id = db.insert_maintenance_run(&record)?;
+    tracing::info!(
+        pruned_files,
+        pruned_similarities,
+        size_before,
+        size_after = record.size_after,
+        "Database maintenance finished"
+    );
+    Ok(record)
+}
+
+/// Whether no pass over `db` ran in the last `interval`
+pub fn maintenance_due(db: &SqliteDatabase, interval: Duration) -> Result<bool> {
+    Ok(match db.last_maintenance_run()? {
+        Some(last) => now().saturating_sub(last.ran_at) >= interval.as_secs() as i64,
+        None => true,
+    })
+}
+
+/// Queue a maintenance pass over `db` on `scheduler` at low priority if one
+/// is due; returns whether it was queued
+pub async fn schedule_maintenance(
+    scheduler: &Scheduler,
+    db: &Database,
+    interval: Duration,
+) -> Result<bool> {
+    if !db.run(move |db| maintenance_due(db, interval)).await? {
+        return Ok(false);
+    }
+    scheduler
+        .submit_with_priority(
+            Box::new(MaintenanceTask::new(db.clone())),
+            TaskPriority::Low,
+        )
+        .await?;
+    Ok(true)
+}
+
+/// A maintenance pass as a scheduler task
+pub struct MaintenanceTask {
+    db: Database,
+    hash_cache: Option<Arc<FileHashCache>>,
+    task_type: TaskType,
+    status: TaskStatus,
+}
+
+impl MaintenanceTask {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            hash_cache: None,
+            task_type: TaskType::Maintenance,
+            status: TaskStatus::Pending,
+        }
+    }
+
+    /// Also compact `cache` (see [`FileHashCache::compact`]) after the
+    /// database
+    pub fn with_hash_cache(mut self, cache: Arc<FileHashCache>) -> Self {
+        self.hash_cache = Some(cache);
+        self
+    }
+
+    async fn run_pass(&self) -> Result<(MaintenanceRecord, Option<CompactionReport>)> {
+        let record = self.db.run(run_maintenance).await?;
+        let compaction = match &self.hash_cache {
+            Some(cache) => {
+                let cache = Arc::clone(cache);
+                Some(tokio::task::spawn_blocking(move || cache.compact()).await??)
+            }
+            None => None,
+        };
+        Ok((record, compaction))
+    }
+}
+
+#[async_trait]
+impl Task for MaintenanceTask {
+    async fn run(&mut self, progress_tx: mpsc::Sender<ProgressUpdate>) -> Result<()> {
+        self.status = TaskStatus::Running;
+        let _ = progress_tx
+            .send(ProgressUpdate::Started {
+                task_type: "Maintenance".to_string(),
+                total_items: 1,
+            })
+            .await;
+
+        let (record, compaction) = match self.run_pass().await {
+            Ok(done) => done,
+            Err(e) => {
+                self.status = TaskStatus::Failed(format!("{:#}", e));
+                let _ = progress_tx
+                    .send(ProgressUpdate::Failed {
+                        error: format!("{:#}", e),
+                    })
+                    .await;
+                return Err(e);
+            }
+        };
+
+        let mut message = format!(
+            "Forgot {} missing files; database {} -> {}",
+            record.pruned_files,
+            format_size(record.size_before),
+            format_size(record.size_after)
+        );
+        if let Some(report) = compaction {
+            message.push_str(&format!(
+                "; dropped {} cached hashes, {} kept",
+                report.removed(),
+                report.remaining
+            ));
+        }
+        self.status = TaskStatus::Completed;
+        let _ = progress_tx
+            .send(ProgressUpdate::Completed { message })
+            .await;
+        Ok(())
+    }
+
+    fn task_type(&self) -> &TaskType {
+        &self.task_type
+    }
+
+    fn status(&self) -> &TaskStatus {
+        &self.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_db::FileRecord;
+    use tempfile::tempdir;
+
+    fn stored(path: &std::path::Path) -> FileRecord {
+        FileRecord::new(
+            path.to_string_lossy().to_string(),
+            10,
+            "Document".to_string(),
+            1,
+        )
+    }
+
+    #[test]
+    fn test_maintenance_prunes_and_is_due_again_after_the_interval() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::new(&dir.path().join("spacesaver.db")).unwrap();
+        let kept = dir.path().join("kept.txt");
+        std::fs::write(&kept, b"notes").unwrap();
+        db.upsert_files(&[stored(&kept), stored(&dir.path().join("gone.txt"))])
+            .unwrap();
+        assert!(maintenance_due(&db, MAINTENANCE_INTERVAL).unwrap());
+
+        let record = run_maintenance(&db).unwrap();
+        assert_eq!(record.pruned_files, 1);
+        assert!(record.size_after > 0);
+        assert!(!maintenance_due(&db, MAINTENANCE_INTERVAL).unwrap());
+        assert!(maintenance_due(&db, Duration::ZERO).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_only_when_due() {
+        let db = Database::in_memory().unwrap();
+        let (scheduler, mut rx) = Scheduler::new(1);
+
+        assert!(schedule_maintenance(&scheduler, &db, MAINTENANCE_INTERVAL)
+            .await
+            .unwrap());
+        assert_eq!(scheduler.queue_length().await, 1);
+
+        let mut task = MaintenanceTask::new(db.clone());
+        let (tx, mut task_rx) = mpsc::channel(4);
+        task.run(tx).await.unwrap();
+        assert_eq!(*task.status(), TaskStatus::Completed);
+        assert!(matches!(
+            task_rx.recv().await,
+            Some(ProgressUpdate::Started { .. })
+        ));
+        assert!(matches!(
+            task_rx.recv().await,
+            Some(ProgressUpdate::Completed { .. })
+        ));
+
+        // The pass above counts: nothing to queue until the interval is up
+        assert!(!schedule_maintenance(&scheduler, &db, MAINTENANCE_INTERVAL)
+            .await
+            .unwrap());
+        assert_eq!(scheduler.queue_length().await, 1);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_compacts_the_hash_cache() {
+        let cache = Arc::new(FileHashCache::temporary().unwrap());
+        cache
+            .set_hash("/definitely/not/here.jpg", 1, "abc")
+            .unwrap();
+        let mut task = MaintenanceTask::new(Database::in_memory().unwrap())
+            .with_hash_cache(Arc::clone(&cache));
+        let (tx, mut rx) = mpsc::channel(4);
+        task.run(tx).await.unwrap();
+
+        assert!(cache.is_empty());
+        rx.recv().await;
+        match rx.recv().await {
+            Some(ProgressUpdate::Completed { message }) => {
+                assert!(
+                    message.ends_with("dropped 1 cached hashes, 0 kept"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("unexpected update: {:?}", other),
+        }
+    }
+}
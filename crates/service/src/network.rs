@@ -0,0 +1,143 @@
+//! Reading network shares gently.
+//!
+//! Hashing a mounted SMB or NFS share with a thread per core saturates the
+//! link and makes reads time out. [`NetworkShares`] knows which mounted
+//! drives are network file systems (see [`crate::drives::classify`]) and how
+//! the [`ScanConfig`] says to read them: with a few threads per share, a
+//! read-rate cap per share and retries after transient errors.
+//! [`crate::ServiceApi::with_network_shares`] applies it to duplicate
+//! checks.
+
+use crate::drives::{list_drives, DiskInfo, DriveKind};
+use space_saver_core::{IoThrottle, RetryPolicy};
+use space_saver_utils::config::ScanConfig;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A mounted network share and how to read it
+#[derive(Debug, Clone)]
+pub struct NetworkShare {
+    pub mount_point: PathBuf,
+    /// File system type, e.g. "cifs" or "nfs4"
+    pub file_system: String,
+    /// Shared by every read from this share
+    pub throttle: Option<IoThrottle>,
+}
+
+/// The network shares mounted, with the settings for reading from them
+#[derive(Debug, Clone, Default)]
+pub struct NetworkShares {
+    shares: Vec<NetworkShare>,
+    parallelism: usize,
+    retry: RetryPolicy,
+}
+
+impl NetworkShares {
+    /// The network shares mounted now, read as `config` says. None are
+    /// found when `config.network_aware` is off.
+    pub fn detect(config: &ScanConfig) -> Self {
+        if !config.network_aware {
+            return Self::default();
+        }
+        Self::from_drives(&list_drives(), config)
+    }
+
+    /// The network shares among `drives`, read as `config` says
+    pub fn from_drives(drives: &[DiskInfo], config: &ScanConfig) -> Self {
+        let shares = drives
+            .iter()
+            .filter(|drive| drive.kind == DriveKind::Network)
+            .map(|drive| NetworkShare {
+                mount_point: PathBuf::from(&drive.mount_point),
+                file_system: drive.file_system.clone(),
+                throttle: config
+                    .share_bandwidth
+                    .iter()
+                    .find(|(mount, _)| Path::new(mount) == Path::new(&drive.mount_point))
+                    .map(|(_, &limit)| IoThrottle::new(limit)),
+            })
+            .collect();
+        Self {
+            shares,
+            parallelism: config.network_parallelism.max(1),
+            retry: RetryPolicy::new(
+                config.network_retries,
+                Duration::from_millis(config.network_retry_backoff_ms),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shares.is_empty()
+    }
+
+    pub fn shares(&self) -> &[NetworkShare] {
+        &self.shares
+    }
+
+    /// The share holding `path`, if it is on one. Mounts nested in a share
+    /// count as their own.
+    pub fn share_of(&self, path: &Path) -> Option<&NetworkShare> {
+        self.shares
+            .iter()
+            .filter(|share| path.starts_with(&share.mount_point))
+            .max_by_key(|share| share.mount_point.components().count())
+    }
+
+    /// Files read at once from one share
+    pub fn parallelism(&self) -> usize {
+        self.parallelism
+    }
+
+    /// How reads from a share are retried
+    pub fn retry(&self) -> RetryPolicy {
+        self.retry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive(mount_point: &str, file_system: &str, kind: DriveKind) -> DiskInfo {
+        DiskInfo {
+            name: mount_point.to_string(),
+            mount_point: mount_point.to_string(),
+            file_system: file_system.to_string(),
+            total_space: 100,
+            available_space: 50,
+            used_space: 50,
+            kind,
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn test_only_network_drives_are_shares_with_their_own_caps() {
+        let drives = [
+            drive("/", "ext4", DriveKind::Fixed),
+            drive("/mnt/nas", "cifs", DriveKind::Network),
+            drive("/mnt/nas/backup", "nfs4", DriveKind::Network),
+        ];
+        let mut config = ScanConfig::default();
+        config.share_bandwidth.insert("/mnt/nas/".to_string(), 1024);
+        let shares = NetworkShares::from_drives(&drives, &config);
+
+        assert_eq!(shares.shares().len(), 2);
+        assert!(shares.share_of(Path::new("/home/me/a.txt")).is_none());
+        let nas = shares.share_of(Path::new("/mnt/nas/photos/a.jpg")).unwrap();
+        assert_eq!(nas.file_system, "cifs");
+        assert_eq!(
+            nas.throttle.as_ref().map(IoThrottle::bytes_per_sec),
+            Some(1024)
+        );
+        let backup = shares.share_of(Path::new("/mnt/nas/backup/b.jpg")).unwrap();
+        assert_eq!(backup.file_system, "nfs4");
+        assert!(backup.throttle.is_none());
+        assert_eq!(shares.parallelism(), 2);
+        assert_eq!(shares.retry().retries(), 3);
+
+        config.network_aware = false;
+        assert!(NetworkShares::detect(&config).is_empty());
+    }
+}
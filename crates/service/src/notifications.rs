@@ -0,0 +1,225 @@
+//! Desktop notifications when background tasks finish.
+//!
+//! Long scans, duplicate checks and compressions otherwise end silently
+//! while the window is minimized or the daemon runs unattended.
+//! [`Notifications`] decides from the config's [`NotificationConfig`]
+//! whether a finished task is worth a notification (its kind turned on,
+//! success or failure wanted, long enough to have been left alone) and
+//! hands it to a [`Notifier`]. [`SystemNotifier`] uses what the OS ships
+//! with: `notify-send` on Linux and the BSDs, `osascript` on macOS and a
+//! PowerShell toast on Windows.
+
+use crate::task::TaskKind;
+use anyhow::{Context, Result};
+use space_saver_utils::time::format_duration;
+use space_saver_utils::NotificationConfig;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Name notifications are shown under
+const APP_NAME: &str = "Space Saver";
+
+/// A notification about a finished task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    /// The task failed; shown as urgent where the OS has that
+    pub failed: bool,
+}
+
+/// Shows notifications
+pub trait Notifier: Send + Sync {
+    fn show(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Shows notifications with the tools the OS ships with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemNotifier;
+
+impl Notifier for SystemNotifier {
+    fn show(&self, notification: &Notification) -> Result<()> {
+        let mut cmd = system_command(notification);
+        let output = cmd
+            .output()
+            .context("Failed to start the notification command")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Notification command exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_command(notification: &Notification) -> Command {
+    let mut cmd = Command::new("notify-send");
+    cmd.arg("--app-name").arg(APP_NAME);
+    if notification.failed {
+        cmd.arg("--urgency=critical");
+    }
+    cmd.arg(&notification.title).arg(&notification.body);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn system_command(notification: &Notification) -> Command {
+    // Read from the environment so the text needs no AppleScript quoting
+    let mut cmd = Command::new("osascript");
+    cmd.arg("-e")
+        .arg(
+            "display notification (system attribute \"SPACE_SAVER_BODY\") \
+             with title (system attribute \"SPACE_SAVER_TITLE\") \
+             subtitle (system attribute \"SPACE_SAVER_APP\")",
+        )
+        .env("SPACE_SAVER_TITLE", &notification.title)
+        .env("SPACE_SAVER_BODY", &notification.body)
+        .env("SPACE_SAVER_APP", APP_NAME);
+    cmd
+}
+
+#[cfg(windows)]
+fn system_command(notification: &Notification) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    // Read from the environment so the text needs no PowerShell quoting
+    const SCRIPT: &str = "\
+        [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+        $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+        $text = $xml.GetElementsByTagName('text'); \
+        $text.Item(0).AppendChild($xml.CreateTextNode($env:SPACE_SAVER_TITLE)) > $null; \
+        $text.Item(1).AppendChild($xml.CreateTextNode($env:SPACE_SAVER_BODY)) > $null; \
+        [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($env:SPACE_SAVER_APP).Show([Windows.UI.Notifications.ToastNotification]::new($xml))";
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .env("SPACE_SAVER_TITLE", &notification.title)
+        .env("SPACE_SAVER_BODY", &notification.body)
+        .env("SPACE_SAVER_APP", APP_NAME);
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    cmd
+}
+
+/// Which finished tasks notify, and how. The default shows nothing.
+#[derive(Clone, Default)]
+pub struct Notifications {
+    settings: NotificationConfig,
+    notifier: Option<Arc<dyn Notifier>>,
+}
+
+impl Notifications {
+    /// Notify through `notifier` as `settings` say
+    pub fn new(settings: NotificationConfig, notifier: Arc<dyn Notifier>) -> Self {
+        Self {
+            settings,
+            notifier: Some(notifier),
+        }
+    }
+
+    /// Notify through the OS as `settings` say
+    pub fn system(settings: NotificationConfig) -> Self {
+        Self::new(settings, Arc::new(SystemNotifier))
+    }
+
+    /// The notification for a task of `kind` that ran for `elapsed` and
+    /// came to `outcome`: a summary of what it did, or its error. None when
+    /// the settings leave it out.
+    pub fn notification(
+        &self,
+        kind: TaskKind,
+        outcome: std::result::Result<&str, &str>,
+        elapsed: Duration,
+    ) -> Option<Notification> {
+        let failed = outcome.is_err();
+        if !self.settings.wants(kind.name(), !failed, elapsed.as_secs()) {
+            return None;
+        }
+        let (title, summary) = match outcome {
+            Ok(summary) => (format!("{} finished", kind.label()), summary),
+            Err(error) => (format!("{} failed", kind.label()), error),
+        };
+        Some(Notification {
+            title,
+            body: format!("{}\nTook {}", summary, format_duration(elapsed)),
+            failed,
+        })
+    }
+
+    /// Show the [notification](Self::notification) for a finished task, if
+    /// any, without waiting for it. Failing to show it is only logged.
+    pub fn task_finished(
+        &self,
+        kind: TaskKind,
+        outcome: std::result::Result<&str, &str>,
+        elapsed: Duration,
+    ) {
+        let Some(notifier) = self.notifier.clone() else {
+            return;
+        };
+        let Some(notification) = self.notification(kind, outcome, elapsed) else {
+            return;
+        };
+        std::thread::spawn(move || {
+            if let Err(e) = notifier.show(&notification) {
+                tracing::warn!(error = %format!("{:#}", e), "Failed to show a notification");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+
+    struct Recorder(Mutex<mpsc::Sender<Notification>>);
+
+    impl Notifier for Recorder {
+        fn show(&self, notification: &Notification) -> Result<()> {
+            self.0.lock().unwrap().send(notification.clone())?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_finished_tasks_notify_as_configured() {
+        let (tx, rx) = mpsc::channel();
+        let mut settings = NotificationConfig::default();
+        settings.tasks.insert("maintenance".to_string(), false);
+        let notifications = Notifications::new(settings, Arc::new(Recorder(Mutex::new(tx))));
+
+        notifications.task_finished(
+            TaskKind::FindDuplicates,
+            Ok("12 duplicate groups, 1.50 GB wasted"),
+            Duration::from_secs(125),
+        );
+        let shown = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(shown.title, "Duplicate check finished");
+        assert_eq!(
+            shown.body,
+            "12 duplicate groups, 1.50 GB wasted\nTook 2m 5s"
+        );
+        assert!(!shown.failed);
+
+        // Quick successes and turned off kinds stay quiet; failures do not
+        let quick = Duration::from_secs(2);
+        assert!(notifications
+            .notification(TaskKind::Scan, Ok("Found 3 files"), quick)
+            .is_none());
+        assert!(notifications
+            .notification(TaskKind::Maintenance, Err("disk full"), quick)
+            .is_none());
+        let failed = notifications
+            .notification(TaskKind::CompressFiles, Err("disk full"), quick)
+            .unwrap();
+        assert_eq!(failed.title, "Compression failed");
+        assert!(failed.failed);
+
+        // Without a notifier nothing is shown
+        Notifications::default().task_finished(TaskKind::Scan, Err("x"), quick);
+    }
+}
@@ -0,0 +1,177 @@
+//! S3 buckets and WebDAV shares as a [`StorageBackend`], through the
+//! `object_store` crate. Enabled by the `remote` feature.
+//!
+//! A store is opened from a URL: `s3://bucket/prefix` or
+//! `https://host/dav/folder` (WebDAV, also plain `http://`). Files are
+//! reported under that URL, e.g. `s3://bucket/photos/a.jpg`, and listing
+//! roots may be given the same way or as key prefixes within the store.
+//! S3 credentials and region come from the usual `AWS_*` environment
+//! variables.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use space_saver_core::{FileInfo, StorageBackend, StoredFile};
+use space_saver_utils::Error;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Schemes [`ObjectStorage::from_url`] opens
+pub const SCHEMES: &[&str] = &["s3://", "http://", "https://"];
+
+/// Whether `path` names a remote store rather than a local path
+pub fn is_remote(path: &str) -> bool {
+    SCHEMES.iter().any(|scheme| path.starts_with(scheme))
+}
+
+/// A bucket or share, listed and read through `object_store`
+#[derive(Debug, Clone)]
+pub struct ObjectStorage {
+    store: Arc<dyn ObjectStore>,
+    /// `scheme://host`, put in front of every key
+    base: String,
+}
+
+impl ObjectStorage {
+    /// `store`, with keys reported under `base` (e.g. `s3://bucket`)
+    pub fn new(store: Arc<dyn ObjectStore>, base: impl Into<String>) -> Self {
+        Self {
+            store,
+            base: base.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// The store `url` is in, with credentials from the environment
+    pub fn from_url(url: &str) -> Result<Self> {
+        let options = std::env::vars()
+            .filter(|(key, _)| key.starts_with("AWS_"))
+            .map(|(key, value)| (key.to_ascii_lowercase(), value));
+        Self::from_url_with_options(url, options)
+    }
+
+    /// The store `url` is in, configured with `options` (`object_store`
+    /// keys such as `aws_region`; unknown ones are ignored)
+    pub fn from_url_with_options(
+        url: &str,
+        options: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| Error::InvalidInput(format!("Invalid URL {}: {}", url, e)))?;
+        let (store, _) = object_store::parse_url_opts(&parsed, options)
+            .with_context(|| format!("Failed to open {}", url))?;
+        let base = &parsed[..url::Position::BeforePath];
+        Ok(Self::new(Arc::from(store), base))
+    }
+
+    /// The key of `path`, given as a URL under the store or as a key
+    fn key(&self, path: &Path) -> ObjectPath {
+        let path = path.to_string_lossy();
+        let key = path.strip_prefix(&self.base).unwrap_or(&path);
+        ObjectPath::from(key.trim_matches('/'))
+    }
+
+    fn url(&self, key: &ObjectPath) -> PathBuf {
+        PathBuf::from(format!("{}/{}", self.base, key))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ObjectStorage {
+    fn describe(&self) -> String {
+        self.base.clone()
+    }
+
+    async fn list(&self, root: &Path, include_hidden: bool) -> Result<Vec<StoredFile>> {
+        let prefix = self.key(root);
+        let prefix = (!prefix.as_ref().is_empty()).then_some(prefix);
+        let listed: Vec<_> = self
+            .store
+            .list(prefix.as_ref())
+            .try_collect()
+            .await
+            .with_context(|| format!("Failed to list {}", self.url(&self.key(root)).display()))?;
+        Ok(listed
+            .into_iter()
+            .filter(|meta| {
+                include_hidden || !meta.location.parts().any(|p| p.as_ref().starts_with('.'))
+            })
+            .map(|meta| {
+                let info = FileInfo::new(
+                    self.url(&meta.location),
+                    meta.size,
+                    meta.last_modified.timestamp(),
+                );
+                StoredFile::new(info).with_etag(meta.e_tag)
+            })
+            .collect())
+    }
+
+    async fn read_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+        let bytes = self
+            .store
+            .get_range(&self.key(path), range)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServiceApi;
+    use object_store::memory::InMemory;
+    use object_store::PutPayload;
+
+    async fn put(store: &InMemory, key: &str, content: &[u8]) {
+        let payload = PutPayload::from(content.to_vec());
+        store.put(&ObjectPath::from(key), payload).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_in_a_bucket() {
+        let store = InMemory::new();
+        put(&store, "photos/a.jpg", b"same bytes").await;
+        put(&store, "photos/b.jpg", b"same bytes").await;
+        put(&store, "photos/c.jpg", b"diff bytes").await;
+        put(&store, "photos/.trash/d.jpg", b"same bytes").await;
+        put(&store, "other/e.jpg", b"same bytes").await;
+        let storage = ObjectStorage::new(Arc::new(store), "s3://bucket/");
+        let api = ServiceApi::new();
+
+        let root = PathBuf::from("s3://bucket/photos");
+        let report = api
+            .find_duplicates_in_storage(&storage, vec![root.clone()], None)
+            .await
+            .unwrap();
+        assert_eq!(report.groups.len(), 1);
+        let mut paths: Vec<_> = report.groups[0].files.iter().map(|f| &f.path).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                Path::new("s3://bucket/photos/a.jpg"),
+                Path::new("s3://bucket/photos/b.jpg")
+            ]
+        );
+
+        let stats = api
+            .get_storage_stats_in(&storage, vec![root], None)
+            .await
+            .unwrap();
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.images, 3);
+        assert_eq!(stats.total_size, 30);
+    }
+
+    #[test]
+    fn test_remote_paths_are_told_apart() {
+        assert!(is_remote("s3://bucket/photos"));
+        assert!(is_remote("https://nas.local/dav"));
+        assert!(!is_remote("/srv/photos"));
+        assert!(ObjectStorage::from_url("not a url").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! Checking paths before scanning them.
+//!
+//! A folder dropped on the window may be a typo'd path, unreadable, on a
+//! slow network share or hold millions of files. [`check_paths`] finds out
+//! cheaply, so a frontend can warn before starting a scan that would take
+//! an hour: the file count comes from a shallow probe
+//! ([`probe_file_count`]) that reads a bounded number of directory entries
+//! and extrapolates the rest.
+
+use crate::drives::{list_drives, DiskInfo, DriveKind};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory entries [`check_paths`] reads per path before extrapolating
+pub const PROBE_ENTRIES: usize = 5_000;
+
+/// Above this many files a scan is worth a warning
+pub const LARGE_SCAN_FILES: u64 = 200_000;
+
+/// What a path dropped for scanning turned out to be
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathCheck {
+    pub path: PathBuf,
+    pub exists: bool,
+    pub is_dir: bool,
+    /// Whether the file can be opened or the directory listed
+    pub readable: bool,
+    /// The drive holding the path; None when it does not exist or no
+    /// mounted drive contains it
+    pub drive_kind: Option<DriveKind>,
+    pub mount_point: Option<String>,
+    /// Files under a directory (1 for a file), estimated unless `exact`
+    pub estimated_files: Option<u64>,
+    /// Whether the probe saw the whole tree
+    pub exact: bool,
+    /// Why scanning this path may fail or take long, for the user
+    pub warnings: Vec<String>,
+}
+
+/// Files under a directory as seen by [`probe_file_count`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileCountProbe {
+    pub files: u64,
+    /// False when the probe stopped at its budget and extrapolated
+    pub exact: bool,
+}
+
+/// Check each of `paths` for scanning. Never fails: problems with a path
+/// are reported on its [`PathCheck`].
+pub fn check_paths(paths: &[PathBuf]) -> Vec<PathCheck> {
+    let drives = list_drives();
+    paths.iter().map(|path| check_path(path, &drives)).collect()
+}
+
+fn check_path(path: &Path, drives: &[DiskInfo]) -> PathCheck {
+    let mut check = PathCheck {
+        path: path.to_path_buf(),
+        exists: false,
+        is_dir: false,
+        readable: false,
+        drive_kind: None,
+        mount_point: None,
+        estimated_files: None,
+        exact: false,
+        warnings: Vec::new(),
+    };
+    let Ok(metadata) = fs::metadata(path) else {
+        check.warnings.push("Path does not exist".to_string());
+        return check;
+    };
+    check.exists = true;
+    check.is_dir = metadata.is_dir();
+    check.readable = if check.is_dir {
+        fs::read_dir(path).is_ok()
+    } else {
+        fs::File::open(path).is_ok()
+    };
+    if !check.readable {
+        check.warnings.push("Permission denied".to_string());
+    }
+
+    let full = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Some(drive) = drive_of(&full, drives) {
+        check.drive_kind = Some(drive.kind);
+        check.mount_point = Some(drive.mount_point.clone());
+        match drive.kind {
+            DriveKind::Network => check
+                .warnings
+                .push("On a network drive; scanning may be slow".to_string()),
+            DriveKind::Removable => check
+                .warnings
+                .push("On a removable drive; keep it connected while scanning".to_string()),
+            DriveKind::Fixed => {}
+        }
+    }
+
+    if !check.is_dir {
+        (check.estimated_files, check.exact) = (Some(1), true);
+    } else if check.readable {
+        let probe = probe_file_count(path, PROBE_ENTRIES);
+        (check.estimated_files, check.exact) = (Some(probe.files), probe.exact);
+        if probe.files > LARGE_SCAN_FILES {
+            check.warnings.push(format!(
+                "About {} files; scanning may take a long time",
+                probe.files
+            ));
+        }
+    }
+    check
+}
+
+/// The drive whose mount point holds `path` most closely
+fn drive_of<'a>(path: &Path, drives: &'a [DiskInfo]) -> Option<&'a DiskInfo> {
+    drives
+        .iter()
+        .filter(|drive| path.starts_with(&drive.mount_point))
+        .max_by_key(|drive| Path::new(&drive.mount_point).components().count())
+}
+
+/// Count the files under `dir` breadth-first, reading at most `budget`
+/// directory entries. When the budget runs out, each directory not yet
+/// read is taken to hold as many files as those read so far did on
+/// average, plus one level of subdirectories just as full. Symlinks are
+/// not followed.
+pub fn probe_file_count(dir: &Path, budget: usize) -> FileCountProbe {
+    let mut queue = VecDeque::from([dir.to_path_buf()]);
+    let (mut files, mut dirs_read, mut entries) = (0u64, 0u64, 0usize);
+    while entries < budget {
+        let Some(dir) = queue.pop_front() else {
+            return FileCountProbe { files, exact: true };
+        };
+        let Ok(listing) = fs::read_dir(&dir) else {
+            continue;
+        };
+        dirs_read += 1;
+        for entry in listing.flatten() {
+            entries += 1;
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => queue.push_back(entry.path()),
+                _ => files += 1,
+            }
+        }
+    }
+    if queue.is_empty() {
+        return FileCountProbe { files, exact: true };
+    }
+    // Subdirectories per directory read give the second level
+    let per_dir = files as f64 / dirs_read.max(1) as f64;
+    let growth = (dirs_read + queue.len() as u64) as f64 / dirs_read.max(1) as f64;
+    let unread = queue.len() as f64 * per_dir * growth.max(1.0);
+    FileCountProbe {
+        files: files + unread as u64,
+        exact: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_probe_counts_small_trees_exactly_and_extrapolates_large_ones() {
+        let dir = tempdir().unwrap();
+        for d in 0..10 {
+            let sub = dir.path().join(format!("d{}", d));
+            fs::create_dir(&sub).unwrap();
+            for f in 0..20 {
+                fs::write(sub.join(format!("{}.txt", f)), b"x").unwrap();
+            }
+        }
+        fs::write(dir.path().join("top.txt"), b"x").unwrap();
+
+        let full = probe_file_count(dir.path(), PROBE_ENTRIES);
+        assert_eq!(
+            full,
+            FileCountProbe {
+                files: 201,
+                exact: true
+            }
+        );
+
+        // Room for the top level and about half of the subdirectories
+        let partial = probe_file_count(dir.path(), 100);
+        assert!(!partial.exact);
+        assert!(
+            (100..=400).contains(&partial.files),
+            "estimated {} files",
+            partial.files
+        );
+    }
+
+    #[test]
+    fn test_check_paths_reports_missing_files_and_directories() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"a").unwrap();
+        let missing = dir.path().join("missing");
+
+        let checks = check_paths(&[dir.path().to_path_buf(), file, missing]);
+        assert!(checks[0].exists && checks[0].is_dir && checks[0].readable);
+        assert_eq!(checks[0].estimated_files, Some(1));
+        assert!(checks[0].exact);
+        assert!(!checks[1].is_dir);
+        assert_eq!(checks[1].estimated_files, Some(1));
+        assert!(!checks[2].exists);
+        assert_eq!(checks[2].warnings, ["Path does not exist"]);
+        assert_eq!(checks[2].estimated_files, None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Dry runs.
+//!
+//! Every destructive operation can be planned before it runs. An
+//! [`ActionPlan`] lists each file the operation would touch, what it would
+//! do to it and the space that would free, and nothing on disk changes:
+//! deletions come from [`crate::FileOperations::plan_removal`], dedupe from
+//! [`dedupe_plan`] and in-place compression from
+//! [`crate::compress::compression_plan`]. Cleanup rules plan themselves, see
+//! [`crate::CleanupReport`].
+
+use crate::api::FileError;
+use crate::dedupe::DuplicateResolution;
+use serde::{Deserialize, Serialize};
+use space_saver_utils::Error;
+use std::path::PathBuf;
+
+/// What an operation would do to one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedAction {
+    pub path: PathBuf,
+    /// "trash", "delete", "quarantine", "hardlink", "reflink", "compress"
+    /// or "archive"
+    pub action: String,
+    pub size: u64,
+    /// Bytes the action would free; None when that cannot be told in
+    /// advance
+    pub reclaimed: Option<u64>,
+    /// The copy kept in its place for dedupe, the plugin for compress
+    pub detail: Option<String>,
+}
+
+/// Everything an operation would do, and the files it would leave alone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionPlan {
+    pub actions: Vec<PlannedAction>,
+    /// Files the operation would refuse: protected, missing, or otherwise
+    /// not fit for the action
+    pub skipped: Vec<FileError>,
+    pub total_size: u64,
+    /// Bytes the plan would free, counting only actions that can tell
+    pub reclaimed: u64,
+}
+
+impl ActionPlan {
+    pub fn new(actions: Vec<PlannedAction>, skipped: Vec<FileError>) -> Self {
+        Self {
+            total_size: actions.iter().map(|a| a.size).sum(),
+            reclaimed: actions.iter().filter_map(|a| a.reclaimed).sum(),
+            actions,
+            skipped,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+/// The plan for dealing with the copies `resolutions` do not keep, each
+/// with `action` ("trash", "delete", "quarantine", "hardlink" or
+/// "reflink"). Every one frees its size: the data stays once, in the kept
+/// copy. Protected copies are listed as skipped.
+pub fn dedupe_plan(resolutions: &[DuplicateResolution], action: &str) -> ActionPlan {
+    let mut actions = Vec::new();
+    let mut skipped = Vec::new();
+    for resolution in resolutions {
+        // Links and clones point at the first kept copy
+        let kept = resolution.keep.first();
+        for file in &resolution.delete {
+            actions.push(PlannedAction {
+                path: file.path.clone(),
+                action: action.to_string(),
+                size: file.size,
+                reclaimed: Some(file.size),
+                detail: kept.map(|kept| format!("copy of {}", kept.path.display())),
+            });
+        }
+        skipped.extend(resolution.protected.iter().map(|file| {
+            FileError::new(
+                &file.path,
+                Error::Protected(format!("Protected path: {}", file.path.display())),
+            )
+        }));
+    }
+    ActionPlan::new(actions, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::FileType;
+    use space_saver_core::FileInfo;
+    use space_saver_utils::ErrorCode;
+
+    fn file(path: &str, size: u64) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path),
+            size,
+            modified: 0,
+            file_type: FileType::Other,
+            hash: Some("abc".to_string()),
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    #[test]
+    fn test_dedupe_plan_lists_each_removed_copy_and_the_protected_ones() {
+        let resolution = DuplicateResolution {
+            hash: "abc".to_string(),
+            keep: vec![file("/a/keep.jpg", 10), file("/sys/copy.jpg", 10)],
+            delete: vec![file("/b/copy.jpg", 10), file("/c/copy.jpg", 10)],
+            reclaimable: 20,
+            protected: vec![file("/sys/copy.jpg", 10)],
+        };
+        let plan = dedupe_plan(&[resolution], "hardlink");
+        assert_eq!(plan.actions.len(), 2);
+        assert_eq!(plan.reclaimed, 20);
+        assert_eq!(plan.actions[0].action, "hardlink");
+        assert_eq!(
+            plan.actions[0].detail.as_deref(),
+            Some("copy of /a/keep.jpg")
+        );
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].code, ErrorCode::Protected);
+    }
+}
@@ -11,6 +11,12 @@ pub enum ProgressUpdate {
         current: usize,
         total: usize,
         message: String,
+        /// Bytes of `total_bytes` processed so far, for throughput; both 0
+        /// when the operation does not count bytes
+        #[serde(default)]
+        bytes: u64,
+        #[serde(default)]
+        total_bytes: u64,
     },
     Completed {
         message: String,
@@ -78,6 +84,8 @@ impl ProgressTracker {
             current: self.current,
             total: self.total,
             message: self.message.clone(),
+            bytes: 0,
+            total_bytes: 0,
         }
     }
 }
@@ -0,0 +1,193 @@
+//! Paths destructive operations must never touch.
+//!
+//! Deleting, deduplicating and compressing in place all check candidate
+//! paths against a [`ProtectedPaths`] list: the operating system's own
+//! directories plus any folders the user protected in the config. A match is
+//! reported as a [`ProtectedPathError`] for that file instead of acting on it.
+
+use std::path::{Component, Path, PathBuf};
+
+pub use space_saver_utils::ProtectedPathError;
+
+/// Directories (and everything beneath them) that deletion, deduplication
+/// and in-place compression refuse to touch
+#[derive(Debug, Clone)]
+pub struct ProtectedPaths {
+    roots: Vec<PathBuf>,
+}
+
+impl ProtectedPaths {
+    /// The operating system's directories
+    pub fn new() -> Self {
+        Self::empty().with_paths(system_paths())
+    }
+
+    /// No protection at all
+    pub fn empty() -> Self {
+        Self { roots: Vec::new() }
+    }
+
+    /// Also protect `paths` (typically `Config::protected_paths`). A
+    /// directory protects its whole subtree; a file protects just itself.
+    pub fn with_paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        for path in paths {
+            let path = path.into();
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            // Match the path as written and where it resolves to, so /bin is
+            // still caught on systems where it links to /usr/bin
+            if let Ok(resolved) = path.canonicalize() {
+                if resolved != path {
+                    self.roots.push(resolved);
+                }
+            }
+            self.roots.push(normalize(&path));
+        }
+        self
+    }
+
+    /// The protected directories, as given and resolved
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Err when `path` is at or beneath a protected directory. Both the path
+    /// as given (with `.` and `..` collapsed) and its resolved form are
+    /// checked, so a symlink into a protected directory is caught.
+    pub fn check(&self, path: &Path) -> Result<(), ProtectedPathError> {
+        let mut candidates = vec![normalize(path)];
+        if let Ok(resolved) = path.canonicalize() {
+            candidates.push(resolved);
+        }
+        for candidate in &candidates {
+            if let Some(root) = self.roots.iter().find(|root| candidate.starts_with(root)) {
+                return Err(ProtectedPathError {
+                    path: path.to_path_buf(),
+                    root: root.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_protected(&self, path: &Path) -> bool {
+        self.check(path).is_err()
+    }
+}
+
+impl Default for ProtectedPaths {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collapses `.` and `..` without touching the file system, so
+/// `/usr/../usr/bin` cannot sidestep a lexical match
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[cfg(unix)]
+fn system_paths() -> Vec<PathBuf> {
+    [
+        "/bin",
+        "/boot",
+        "/dev",
+        "/etc",
+        "/lib",
+        "/lib32",
+        "/lib64",
+        "/proc",
+        "/sbin",
+        "/sys",
+        "/usr",
+        "/System",
+        "/Library",
+        "/private/etc",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect()
+}
+
+#[cfg(windows)]
+fn system_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = ["SystemRoot", "ProgramFiles", "ProgramFiles(x86)"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .collect();
+    if paths.is_empty() {
+        paths = vec![
+            PathBuf::from(r"C:\Windows"),
+            PathBuf::from(r"C:\Program Files"),
+            PathBuf::from(r"C:\Program Files (x86)"),
+        ];
+    }
+    paths
+}
+
+#[cfg(not(any(unix, windows)))]
+fn system_paths() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_directories_are_protected() {
+        let protected = ProtectedPaths::new();
+        assert!(protected.is_protected(Path::new("/etc/passwd")));
+        assert!(protected.is_protected(Path::new("/usr")));
+        assert!(protected.is_protected(Path::new("/home/../usr/bin/ls")));
+        // Component-wise: /usrdata is not /usr
+        assert!(!protected.is_protected(Path::new("/usrdata/file.txt")));
+        assert!(!protected.is_protected(Path::new("/home/me/file.txt")));
+    }
+
+    #[test]
+    fn test_user_paths_protect_their_subtree() {
+        let dir = tempdir().unwrap();
+        let keep = dir.path().join("keep");
+        std::fs::create_dir(&keep).unwrap();
+        let protected = ProtectedPaths::empty().with_paths([&keep]);
+
+        let err = protected.check(&keep.join("photo.jpg")).unwrap_err();
+        assert_eq!(err.path, keep.join("photo.jpg"));
+        assert!(err.to_string().starts_with("Protected path:"));
+        assert!(protected.check(&dir.path().join("keep2.jpg")).is_ok());
+        assert!(protected.check(&dir.path().join("other/photo.jpg")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_into_protected_directory_is_caught() {
+        let dir = tempdir().unwrap();
+        let keep = dir.path().join("keep");
+        std::fs::create_dir(&keep).unwrap();
+        std::fs::write(keep.join("a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(&keep, dir.path().join("link")).unwrap();
+        let protected = ProtectedPaths::empty().with_paths([&keep]);
+
+        assert!(protected.is_protected(&dir.path().join("link/a.txt")));
+    }
+}
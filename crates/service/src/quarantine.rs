@@ -0,0 +1,318 @@
+//! Quarantine: a safer alternative to deleting.
+//!
+//! Quarantined files are moved into a managed directory and recorded in the
+//! database. They can be restored to where they came from until their
+//! retention period ends; [`Quarantine::purge_expired`] then deletes them
+//! for good.
+
+use crate::dedupe::DuplicateResolution;
+use crate::file_ops::{duplicate_removable, hooked_deletion, DeleteResult};
+use crate::hooks::Hooks;
+use crate::protection::ProtectedPaths;
+use anyhow::{bail, Context, Result};
+use space_saver_db::{QuarantineRecord, SqliteDatabase};
+use space_saver_utils::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Days a quarantined file is kept when the config does not say otherwise
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// Quarantine directory and its records in `db`
+pub struct Quarantine<'a> {
+    db: &'a SqliteDatabase,
+    dir: PathBuf,
+    retention_days: u32,
+    protected: ProtectedPaths,
+    hooks: Hooks,
+}
+
+impl<'a> Quarantine<'a> {
+    pub fn new(db: &'a SqliteDatabase, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            db,
+            dir: dir.into(),
+            retention_days: DEFAULT_RETENTION_DAYS,
+            protected: ProtectedPaths::new(),
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Keep quarantined files this many days before purging them
+    pub fn with_retention_days(mut self, days: u32) -> Self {
+        self.retention_days = days;
+        self
+    }
+
+    /// Replace the protected-paths list; protected files are never moved
+    pub fn with_protected_paths(mut self, protected: ProtectedPaths) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Run the "delete" hooks around each batch of files quarantined, as
+    /// [`crate::FileOperations::with_hooks`] does
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Move files into quarantine, reporting a per-file outcome like
+    /// [`crate::FileOperations::delete_files_with_mode`]. Only regular files
+    /// are accepted.
+    pub fn quarantine_files(&self, paths: &[PathBuf]) -> Vec<DeleteResult> {
+        hooked_deletion(&self.hooks, paths, || {
+            paths.iter().map(|path| self.quarantine_one(path)).collect()
+        })
+    }
+
+    /// Quarantine the copies `resolutions` do not keep, each only while
+    /// [`crate::FileOperations::remove_duplicates`] would remove it. Results
+    /// follow the order of the `delete` lists.
+    pub fn quarantine_duplicates(&self, resolutions: &[DuplicateResolution]) -> Vec<DeleteResult> {
+        let paths: Vec<PathBuf> = resolutions
+            .iter()
+            .flat_map(|resolution| resolution.delete.iter().map(|file| file.path.clone()))
+            .collect();
+        hooked_deletion(&self.hooks, &paths, || {
+            resolutions
+                .iter()
+                .flat_map(|resolution| {
+                    resolution.delete.iter().map(move |file| {
+                        match duplicate_removable(resolution, file) {
+                            Ok(()) => self.quarantine_one(&file.path),
+                            Err(e) => DeleteResult::failed(&file.path, e),
+                        }
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn quarantine_one(&self, path: &Path) -> DeleteResult {
+        let outcome = match self.protected.check(path) {
+            Err(e) => Err(e.into()),
+            Ok(()) => self.quarantine_file(path).map(|_| ()).map_err(Error::from),
+        };
+        DeleteResult::of(path, outcome)
+    }
+
+    fn quarantine_file(&self, path: &Path) -> Result<QuarantineRecord> {
+        let metadata = fs::symlink_metadata(path)
+            .with_context(|| format!("File not found: {}", path.display()))?;
+        if !metadata.is_file() {
+            return Err(
+                Error::InvalidInput(format!("Not a regular file: {}", path.display())).into(),
+            );
+        }
+        let original = std::path::absolute(path)?;
+        fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "Failed to create quarantine directory {}",
+                self.dir.display()
+            )
+        })?;
+        let target = self.unique_target(&original);
+        move_file(&original, &target)?;
+
+        let mut record = QuarantineRecord::new(
+            original.to_string_lossy().to_string(),
+            target.to_string_lossy().to_string(),
+            metadata.len(),
+            self.retention_days,
+        );
+        match self.db.insert_quarantine(&record) {
+            Ok(id) => record.id = id,
+            Err(e) => {
+                // Without a record the file could never be restored
+                let _ = move_file(&target, &original);
+                return Err(e.context("Failed to record the quarantined file; it was put back"));
+            }
+        }
+        Ok(record)
+    }
+
+    /// `<dir>/<timestamp>-<n>-<file name>`, with the first free `n`
+    fn unique_target(&self, original: &Path) -> PathBuf {
+        let name = original
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let now = space_saver_utils::time::now();
+        (0..)
+            .map(|n| self.dir.join(format!("{}-{}-{}", now, n, name)))
+            .find(|candidate| !candidate.exists())
+            .expect("an unused name")
+    }
+
+    /// Every quarantined file, newest first
+    pub fn list(&self) -> Result<Vec<QuarantineRecord>> {
+        self.db.get_quarantine()
+    }
+
+    /// Move a quarantined file back to where it came from. Refused when
+    /// something else now occupies that path.
+    pub fn restore(&self, id: i64) -> Result<QuarantineRecord> {
+        let record = self
+            .db
+            .get_quarantine_record(id)?
+            .ok_or_else(|| Error::NotFound(format!("Unknown quarantine entry: {}", id)))?;
+        let original = Path::new(&record.original_path);
+        if original.symlink_metadata().is_ok() {
+            bail!("Cannot restore: {} already exists", record.original_path);
+        }
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(Path::new(&record.quarantine_path), original)?;
+        self.db.delete_quarantine_record(id)?;
+        Ok(record)
+    }
+
+    /// Permanently delete quarantined files whose retention ended at or
+    /// before `now`, returning them. A file already gone from the quarantine
+    /// directory just loses its record.
+    pub fn purge_expired(&self, now: i64) -> Result<Vec<QuarantineRecord>> {
+        let expired = self.db.get_expired_quarantine(now)?;
+        for record in &expired {
+            match fs::remove_file(&record.quarantine_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to purge {}", record.quarantine_path))
+                }
+            }
+            self.db.delete_quarantine_record(record.id)?;
+        }
+        Ok(expired)
+    }
+}
+
+/// Rename, falling back to copy-and-remove across file systems
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    if let Err(e) = fs::remove_file(from) {
+        let _ = fs::remove_file(to);
+        return Err(e).with_context(|| format!("Failed to remove {}", from.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_quarantine_and_restore() {
+        let dir = tempdir().unwrap();
+        let photo = dir.path().join("photos/a.jpg");
+        fs::create_dir_all(photo.parent().unwrap()).unwrap();
+        fs::write(&photo, "jpeg").unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let quarantine = Quarantine::new(&db, dir.path().join("quarantine"));
+
+        let results = quarantine.quarantine_files(std::slice::from_ref(&photo));
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert!(!photo.exists());
+        let records = quarantine.list().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].size, 4);
+        assert!(Path::new(&records[0].quarantine_path).is_file());
+
+        // Something new at the original path blocks the restore
+        fs::write(&photo, "new").unwrap();
+        let err = quarantine.restore(records[0].id).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        fs::remove_file(&photo).unwrap();
+
+        quarantine.restore(records[0].id).unwrap();
+        assert_eq!(fs::read_to_string(&photo).unwrap(), "jpeg");
+        assert!(quarantine.list().unwrap().is_empty());
+        assert!(quarantine.restore(records[0].id).is_err());
+    }
+
+    #[test]
+    fn test_quarantine_duplicates_only_while_the_kept_copy_is_unchanged() {
+        use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+
+        let dir = tempdir().unwrap();
+        let photos = dir.path().join("photos");
+        fs::create_dir(&photos).unwrap();
+        for name in ["a.jpg", "b.jpg", "c.jpg", "d.jpg"] {
+            fs::write(photos.join(name), "jpeg").unwrap();
+        }
+        let mut files = DefaultFileScanner::new().scan(&photos).unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let resolution = |keep: usize, delete: usize| DuplicateResolution {
+            hash: "abc".to_string(),
+            reclaimable: 4,
+            keep: vec![files[keep].clone()],
+            delete: vec![files[delete].clone()],
+            protected: Vec::new(),
+        };
+        // The kept copy of the second group changes after the scan
+        fs::write(photos.join("c.jpg"), "edited").unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let quarantine = Quarantine::new(&db, dir.path().join("quarantine"));
+
+        let results = quarantine.quarantine_duplicates(&[resolution(0, 1), resolution(2, 3)]);
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert!(!photos.join("b.jpg").exists());
+        assert!(!results[1].success);
+        assert!(photos.join("d.jpg").exists());
+        assert_eq!(quarantine.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_purge_expired_only() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let short = Quarantine::new(&db, dir.path().join("q")).with_retention_days(0);
+        let long = Quarantine::new(&db, dir.path().join("q")).with_retention_days(30);
+        for name in ["old.txt", "new.txt"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        short.quarantine_files(&[dir.path().join("old.txt")]);
+        long.quarantine_files(&[dir.path().join("new.txt")]);
+
+        let purged = long.purge_expired(space_saver_utils::time::now()).unwrap();
+        assert_eq!(purged.len(), 1);
+        assert!(purged[0].original_path.ends_with("old.txt"));
+        assert!(!Path::new(&purged[0].quarantine_path).exists());
+        let left = long.list().unwrap();
+        assert_eq!(left.len(), 1);
+        assert!(Path::new(&left[0].quarantine_path).exists());
+    }
+
+    #[test]
+    fn test_refused_paths_stay_put() {
+        let dir = tempdir().unwrap();
+        let keep = dir.path().join("keep");
+        fs::create_dir(&keep).unwrap();
+        fs::write(keep.join("a.txt"), "a").unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let quarantine = Quarantine::new(&db, dir.path().join("q"))
+            .with_protected_paths(ProtectedPaths::empty().with_paths([&keep]));
+
+        let results = quarantine.quarantine_files(&[
+            keep.join("a.txt"),
+            keep.clone(),
+            dir.path().join("missing.txt"),
+        ]);
+        assert!(results[0].protected);
+        assert!(results.iter().all(|r| !r.success));
+        assert!(results[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("File not found"));
+        assert!(keep.join("a.txt").exists());
+        assert!(db.get_quarantine().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! Results kept in the database between runs.
+//!
+//! With a database attached ([`crate::ServiceApi::with_database`]), scans
+//! and duplicate checks persist what they found: unfiltered scans go into the
+//! storage history ([`crate::history`]), every file seen is kept as a
+//! [`FileRecord`] by absolute path, with its content hash once it has been
+//! hashed, and the duplicate groups found replace those previously found
+//! beneath the same folders. Image similarity scores are kept per pair of
+//! files and reused while neither file changes.
+
+use crate::api::DuplicateGroup;
+use anyhow::Result;
+use space_saver_core::FileInfo;
+use space_saver_db::{DuplicateRecord, FileRecord, SimilarityRecord, SqliteDatabase};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Store `files` with their content hashes where known. A file recorded
+/// without a hash keeps its stored one while its size and modification time
+/// are unchanged.
+pub fn record_files<'a>(
+    files: impl IntoIterator<Item = (&'a FileInfo, Option<&'a str>)>,
+    db: &SqliteDatabase,
+) -> Result<()> {
+    let records = files
+        .into_iter()
+        .map(|(file, hash)| {
+            Ok(FileRecord {
+                hash: hash.map(str::to_string),
+                ..FileRecord::new(
+                    record_key(&file.path)?,
+                    file.size,
+                    format!("{:?}", file.file_type),
+                    file.modified,
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    db.upsert_files(&records)
+}
+
+/// Store the duplicate `groups` found beneath `roots`, replacing the groups
+/// recorded for them before
+pub fn record_duplicates(
+    roots: &[PathBuf],
+    groups: &[DuplicateGroup],
+    db: &SqliteDatabase,
+) -> Result<()> {
+    let roots = roots
+        .iter()
+        .map(|root| record_key(root))
+        .collect::<Result<Vec<_>>>()?;
+    let records = groups
+        .iter()
+        .map(|group| {
+            let paths = group
+                .files
+                .iter()
+                .map(|file| record_key(&file.path))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(DuplicateRecord::new(
+                group.hash.clone(),
+                paths,
+                group.count,
+                group.total_size,
+                group.wasted_space,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    db.replace_duplicates(&roots, &records)
+}
+
+/// Similarity scores recorded for pairs of files, as loaded by
+/// [`load_similarities`]
+#[derive(Debug, Default)]
+pub struct SimilarityScores {
+    by_pair: HashMap<(String, String), SimilarityRecord>,
+}
+
+impl SimilarityScores {
+    /// The recorded score of `a` and `b`, each given by record key and
+    /// modification time, if neither file changed since it was computed
+    pub fn get(&self, a: (&str, i64), b: (&str, i64)) -> Option<f32> {
+        let (a, b) = if a.0 <= b.0 { (a, b) } else { (b, a) };
+        self.by_pair
+            .get(&(a.0.to_string(), b.0.to_string()))
+            .filter(|r| r.modified_a == a.1 && r.modified_b == b.1)
+            .map(|r| r.similarity_score)
+    }
+}
+
+/// The scores recorded for pairs among the files with record keys `keys`
+pub fn load_similarities(keys: &[String], db: &SqliteDatabase) -> Result<SimilarityScores> {
+    let wanted: std::collections::HashSet<&str> = keys.iter().map(String::as_str).collect();
+    let mut by_pair = HashMap::new();
+    for key in keys {
+        for record in db.get_similarities_of(key)? {
+            if wanted.contains(record.file_a.as_str()) && wanted.contains(record.file_b.as_str()) {
+                by_pair.insert((record.file_a.clone(), record.file_b.clone()), record);
+            }
+        }
+    }
+    Ok(SimilarityScores { by_pair })
+}
+
+pub(crate) fn record_key(path: &Path) -> Result<String> {
+    Ok(std::path::absolute(path)?.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_duplicates_replace_earlier_groups_of_the_same_root() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), b"same").unwrap();
+        fs::write(dir.path().join("b.txt"), b"same").unwrap();
+        let files = DefaultFileScanner::new().scan(dir.path()).unwrap();
+        let group = DuplicateGroup {
+            hash: "h".to_string(),
+            count: 2,
+            total_size: 8,
+            wasted_space: 4,
+            files: files.clone(),
+        };
+        let db = SqliteDatabase::in_memory().unwrap();
+        let roots = [dir.path().to_path_buf()];
+
+        record_files(files.iter().map(|f| (f, Some("h"))), &db).unwrap();
+        record_duplicates(&roots, &[group.clone(), group], &db).unwrap();
+        record_duplicates(&roots, &[], &db).unwrap();
+        assert!(db.get_duplicates().unwrap().is_empty());
+
+        let stored = db
+            .get_file_by_path(&record_key(&dir.path().join("a.txt")).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.hash.as_deref(), Some("h"));
+        assert_eq!(stored.file_type, "Document");
+    }
+}
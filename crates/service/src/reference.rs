@@ -0,0 +1,193 @@
+//! Reference volumes: checking local files against copies kept elsewhere.
+//!
+//! A reference volume (a NAS, a backup drive) is indexed once: the content
+//! hash of each of its files is stored in the database. Local files are then
+//! checked against that index without the volume having to be mounted; a
+//! local file whose content is indexed can be deleted, since the reference
+//! keeps an identical copy.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use space_saver_core::FileInfo;
+use space_saver_db::{ReferenceFileRecord, ReferenceRoot, SqliteDatabase};
+use space_saver_utils::Error;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// A local file with an identical copy on a reference volume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceMatch {
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+    /// The indexed copy
+    pub reference_path: String,
+}
+
+/// Local files checked against every indexed reference volume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceCheck {
+    /// Files safe to delete locally, largest first
+    pub matched: Vec<ReferenceMatch>,
+    pub matched_size: u64,
+    /// Files with no copy on any reference volume
+    pub unmatched_count: usize,
+    pub unmatched_size: u64,
+    /// The volumes checked against; their `indexed_at` tells how current the
+    /// index is
+    pub references: Vec<ReferenceRoot>,
+}
+
+/// Store the content hashes of the files under `root`, replacing its previous
+/// index. `hash` computes content hashes, dropping unreadable files. Refuses
+/// a `root` that is not a directory, so an unmounted volume never wipes its
+/// index.
+pub fn index_reference<F>(
+    root: &Path,
+    files: Vec<FileInfo>,
+    db: &SqliteDatabase,
+    hash: F,
+) -> Result<ReferenceRoot>
+where
+    F: FnOnce(Vec<FileInfo>) -> Result<Vec<(String, FileInfo)>>,
+{
+    if !root.is_dir() {
+        return Err(
+            Error::NotFound(format!("Reference volume not found: {}", root.display())).into(),
+        );
+    }
+    let key = absolute_key(root)?;
+    // Empty files are all identical; they say nothing about a copy
+    let files = files.into_iter().filter(|f| f.size > 0).collect();
+    let records = hash(files)?
+        .into_iter()
+        .map(|(hash, file)| {
+            Ok(ReferenceFileRecord {
+                path: absolute_key(&file.path)?,
+                size: file.size,
+                hash,
+                modified: file.modified,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let indexed_at = space_saver_utils::time::now();
+    db.replace_reference_files(&key, &records, indexed_at)?;
+    Ok(ReferenceRoot {
+        path: key,
+        file_count: records.len(),
+        total_size: records.iter().map(|r| r.size).sum(),
+        indexed_at,
+    })
+}
+
+/// Check `files` against the indexed reference volumes. Only files sharing a
+/// size with some indexed file are hashed; a file only matching itself (when
+/// it lies on a reference volume) does not count.
+pub fn check_files<F>(files: Vec<FileInfo>, db: &SqliteDatabase, hash: F) -> Result<ReferenceCheck>
+where
+    F: FnOnce(Vec<FileInfo>) -> Result<Vec<(String, FileInfo)>>,
+{
+    let references = db.get_reference_roots()?;
+    let sizes: HashSet<u64> = db.get_reference_sizes()?.into_iter().collect();
+    let (candidates, others): (Vec<FileInfo>, Vec<FileInfo>) = files
+        .into_iter()
+        .filter(|f| f.size > 0)
+        .partition(|f| sizes.contains(&f.size));
+
+    let mut matched = Vec::new();
+    let mut unmatched_count = others.len();
+    let mut unmatched_size: u64 = others.iter().map(|f| f.size).sum();
+    for (hash, file) in hash(candidates)? {
+        let path = absolute_key(&file.path)?;
+        let copy = db
+            .get_reference_files_by_hash(&hash)?
+            .into_iter()
+            .find(|r| r.size == file.size && r.path != path);
+        match copy {
+            Some(copy) => matched.push(ReferenceMatch {
+                path,
+                size: file.size,
+                hash,
+                reference_path: copy.path,
+            }),
+            None => {
+                unmatched_count += 1;
+                unmatched_size += file.size;
+            }
+        }
+    }
+    matched.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+    Ok(ReferenceCheck {
+        matched_size: matched.iter().map(|m| m.size).sum(),
+        matched,
+        unmatched_count,
+        unmatched_size,
+        references,
+    })
+}
+
+fn absolute_key(path: &Path) -> Result<String> {
+    Ok(std::path::absolute(path)?.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use space_saver_core::FileHasher;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn blake3(files: Vec<FileInfo>) -> Result<Vec<(String, FileInfo)>> {
+        let hasher = FileHasher::new_blake3();
+        Ok(files
+            .into_iter()
+            .filter_map(|f| Some((hasher.hash_file(&f.path).ok()?, f)))
+            .collect())
+    }
+
+    fn scan(dir: &Path) -> Vec<FileInfo> {
+        DefaultFileScanner::new().scan(dir).unwrap()
+    }
+
+    #[test]
+    fn test_local_copies_of_reference_files_match() {
+        let (nas, laptop) = (tempdir().unwrap(), tempdir().unwrap());
+        let db = SqliteDatabase::in_memory().unwrap();
+        fs::write(nas.path().join("photo.jpg"), b"holiday photo").unwrap();
+        fs::write(nas.path().join("other.jpg"), b"something else").unwrap();
+        let root = index_reference(nas.path(), scan(nas.path()), &db, blake3).unwrap();
+        assert_eq!(root.file_count, 2);
+
+        fs::write(laptop.path().join("copy.jpg"), b"holiday photo").unwrap();
+        // Same size as an indexed file, different content
+        fs::write(laptop.path().join("edit.jpg"), b"holiday phot0").unwrap();
+        fs::write(laptop.path().join("new.txt"), b"not on the nas").unwrap();
+
+        let check = check_files(scan(laptop.path()), &db, blake3).unwrap();
+        assert_eq!(check.matched.len(), 1);
+        assert!(check.matched[0].path.ends_with("copy.jpg"));
+        assert!(check.matched[0].reference_path.ends_with("photo.jpg"));
+        assert_eq!(check.matched_size, 13);
+        assert_eq!((check.unmatched_count, check.unmatched_size), (2, 27));
+        assert_eq!(check.references.len(), 1);
+
+        // Checking the reference volume itself finds no copies
+        let check = check_files(scan(nas.path()), &db, blake3).unwrap();
+        assert!(check.matched.is_empty());
+    }
+
+    #[test]
+    fn test_missing_reference_volume_keeps_its_index() {
+        let nas = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        fs::write(nas.path().join("a.bin"), b"data").unwrap();
+        index_reference(nas.path(), scan(nas.path()), &db, blake3).unwrap();
+
+        let missing = nas.path().join("unmounted");
+        let err = index_reference(&missing, Vec::new(), &db, blake3).unwrap_err();
+        assert!(err.to_string().contains("not found"));
+        assert_eq!(db.get_reference_roots().unwrap()[0].file_count, 1);
+    }
+}
@@ -0,0 +1,208 @@
+//! Deduplication by copy-on-write clones instead of deletion.
+//!
+//! Each copy a [`DuplicateResolution`] would delete is instead replaced by a
+//! clone of a kept copy: every path stays a separate, independently editable
+//! file, but the data is stored once. Needs a file system with clone support
+//! (Btrfs, XFS, APFS); elsewhere each file is reported as `unsupported` and
+//! left untouched.
+
+use crate::dedupe::DuplicateResolution;
+use crate::protection::ProtectedPaths;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_core::{reflink, FileInfo, ReflinkError};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-file outcome of reflink deduplication
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReflinkResult {
+    pub path: String,
+    /// The kept copy it now shares its data with
+    pub source: String,
+    pub success: bool,
+    pub error: Option<String>,
+    /// The file system cannot clone here; the file was left as it was
+    #[serde(default)]
+    pub unsupported: bool,
+    #[serde(default)]
+    pub protected: bool,
+    /// Bytes no longer stored twice
+    pub reclaimed: u64,
+}
+
+/// Replace every `delete` copy of each resolution with a clone of its first
+/// `keep` copy. Copies changed since the scan are refused, as are protected
+/// paths; a failed clone leaves the original file in place.
+pub fn reflink_duplicates(
+    resolutions: &[DuplicateResolution],
+    protected: &ProtectedPaths,
+) -> Vec<ReflinkResult> {
+    let mut results = Vec::new();
+    for resolution in resolutions {
+        let Some(source) = resolution.keep.first() else {
+            continue;
+        };
+        for target in &resolution.delete {
+            let check = protected.check(&target.path);
+            let outcome = match &check {
+                Err(e) => Err(ReflinkFailure::Other(e.to_string())),
+                Ok(()) => replace_with_clone(source, target),
+            };
+            results.push(ReflinkResult {
+                path: target.path.to_string_lossy().to_string(),
+                source: source.path.to_string_lossy().to_string(),
+                success: outcome.is_ok(),
+                reclaimed: if outcome.is_ok() { target.size } else { 0 },
+                unsupported: matches!(outcome, Err(ReflinkFailure::Unsupported(_))),
+                protected: check.is_err(),
+                error: outcome.err().map(|e| match e {
+                    ReflinkFailure::Unsupported(message) | ReflinkFailure::Other(message) => {
+                        message
+                    }
+                }),
+            });
+        }
+    }
+    results
+}
+
+enum ReflinkFailure {
+    Unsupported(String),
+    Other(String),
+}
+
+fn replace_with_clone(
+    source: &FileInfo,
+    target: &FileInfo,
+) -> std::result::Result<(), ReflinkFailure> {
+    let other = |e: anyhow::Error| ReflinkFailure::Other(format!("{:#}", e));
+    unchanged_since_scan(source).map_err(other)?;
+    unchanged_since_scan(target).map_err(other)?;
+
+    let temp = temp_path(&target.path);
+    match reflink(&source.path, &temp) {
+        Ok(()) => {}
+        Err(ReflinkError::Unsupported(e)) => {
+            return Err(ReflinkFailure::Unsupported(format!(
+                "Copy-on-write clones are not supported here ({})",
+                e
+            )))
+        }
+        Err(e) => return Err(ReflinkFailure::Other(e.to_string())),
+    }
+    let swap = || -> Result<()> {
+        // The clone takes the place of the copy, so it keeps its permissions
+        // and modification time
+        let metadata = fs::metadata(&target.path)?;
+        fs::set_permissions(&temp, metadata.permissions())?;
+        fs::File::options()
+            .write(true)
+            .open(&temp)?
+            .set_modified(metadata.modified()?)?;
+        fs::rename(&temp, &target.path)
+            .with_context(|| format!("Failed to replace {}", target.path.display()))?;
+        Ok(())
+    };
+    swap().map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        other(e)
+    })
+}
+
+/// Refuses a file whose size or modification time no longer match the scan,
+/// so a copy edited since is never replaced by the other's content
+pub(crate) fn unchanged_since_scan(file: &FileInfo) -> Result<()> {
+    let metadata = fs::metadata(&file.path)
+        .with_context(|| format!("File not found: {}", file.path.display()))?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    if metadata.len() != file.size || modified != file.modified {
+        bail!("{} changed since the scan", file.path.display());
+    }
+    Ok(())
+}
+
+/// Hidden sibling of `path` the clone is made at before replacing it
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.reflink-tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::{DefaultFileScanner, FileScanner};
+    use space_saver_core::supports_reflink;
+    use tempfile::tempdir;
+
+    fn resolution(dir: &Path) -> DuplicateResolution {
+        let mut files = DefaultFileScanner::new().scan(dir).unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let delete = files.split_off(1);
+        DuplicateResolution {
+            hash: "abc".to_string(),
+            reclaimable: delete.iter().map(|f| f.size).sum(),
+            keep: files,
+            delete,
+            protected: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_reflink_replaces_copies_or_reports_unsupported() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
+        fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
+        let resolutions = [resolution(dir.path())];
+
+        let results = reflink_duplicates(&resolutions, &ProtectedPaths::empty());
+        assert_eq!(results.len(), 1);
+        let result = &results[0];
+        assert!(result.source.ends_with("a.bin"));
+        if supports_reflink(dir.path()) {
+            assert!(result.success, "error: {:?}", result.error);
+            assert_eq!(result.reclaimed, 15);
+        } else {
+            assert!(result.unsupported);
+            assert!(!result.success);
+            assert_eq!(result.reclaimed, 0);
+        }
+        // Either way both files are intact and no temp file is left
+        assert_eq!(
+            fs::read(dir.path().join("b.bin")).unwrap(),
+            b"identical bytes"
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_changed_and_protected_copies_are_refused() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"identical bytes").unwrap();
+        fs::write(dir.path().join("b.bin"), b"identical bytes").unwrap();
+        fs::write(dir.path().join("c.bin"), b"identical bytes").unwrap();
+        let resolutions = [resolution(dir.path())];
+        // Edited after the scan
+        fs::write(dir.path().join("b.bin"), b"edited since the scan").unwrap();
+
+        let protected = ProtectedPaths::empty().with_paths([dir.path().join("c.bin")]);
+        let results = reflink_duplicates(&resolutions, &protected);
+        assert!(results[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("changed since"));
+        assert!(results[1].protected);
+        assert!(results.iter().all(|r| !r.success && !r.unsupported));
+        assert_eq!(
+            fs::read(dir.path().join("b.bin")).unwrap(),
+            b"edited since the scan"
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Shareable storage reports.
+//!
+//! A [`StorageReport`] gathers from one scan what other commands show one
+//! at a time: the breakdown by file type, the largest files and folders,
+//! duplicate groups and the files a compression plugin could shrink.
+//! [`render_html`] turns it into a single HTML page with its styles and bar
+//! charts inline, so it can be attached to a ticket or sent to a team and
+//! opened in any browser without network access.
+
+use crate::api::{DirectorySize, DuplicateGroup};
+use crate::export::{html_escape, CompressibleFile};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_core::compress_plugins::PluginManager;
+use space_saver_core::FileInfo;
+use space_saver_db::CategoryTotal;
+use space_saver_utils::{format_size, format_timestamp};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// How many entries each list of a report keeps by default
+pub const DEFAULT_REPORT_TOP: usize = 20;
+
+/// What a directory holds and where its space could be won back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageReport {
+    pub root: String,
+    pub generated_at: i64,
+    pub total_files: usize,
+    pub total_size: u64,
+    /// Totals per file category, largest first
+    pub categories: Vec<CategoryTotal>,
+    /// Largest files first
+    pub largest_files: Vec<FileInfo>,
+    /// Largest folders beneath the root first
+    pub largest_directories: Vec<DirectorySize>,
+    /// Most wasted space first
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Bytes all duplicate groups waste, including those not listed
+    pub duplicate_waste: u64,
+    /// Largest estimated savings first
+    pub compressible: Vec<CompressibleFile>,
+    /// Estimated savings of all compressible files, including those not
+    /// listed
+    pub compressible_savings: u64,
+}
+
+/// Build a report on the `files` scanned from `root` and the `duplicates`
+/// among them, keeping the `top` entries of each list. With a `manager`,
+/// files one of its plugins accepts with at least its minimum savings are
+/// listed as compressible.
+pub fn build_report(
+    root: &Path,
+    files: &[FileInfo],
+    mut duplicates: Vec<DuplicateGroup>,
+    manager: Option<&PluginManager>,
+    top: usize,
+) -> StorageReport {
+    let mut categories: HashMap<String, CategoryTotal> = HashMap::new();
+    let mut directories: HashMap<&Path, DirectorySize> = HashMap::new();
+    for file in files {
+        let category = format!("{:?}", file.file_type);
+        let total = categories
+            .entry(category.clone())
+            .or_insert_with(|| CategoryTotal {
+                category,
+                file_count: 0,
+                total_size: 0,
+            });
+        total.file_count += 1;
+        total.total_size += file.size;
+
+        let dirs = file
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| *dir != root && dir.starts_with(root));
+        for dir in dirs {
+            let entry = directories.entry(dir).or_insert_with(|| DirectorySize {
+                path: dir.to_string_lossy().to_string(),
+                total_size: 0,
+                file_count: 0,
+            });
+            entry.total_size += file.size;
+            entry.file_count += 1;
+        }
+    }
+
+    let mut categories: Vec<CategoryTotal> = categories.into_values().collect();
+    categories.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.category.cmp(&b.category))
+    });
+    let mut largest_directories: Vec<DirectorySize> = directories.into_values().collect();
+    largest_directories.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    largest_directories.truncate(top);
+    let mut largest_files = files.to_vec();
+    largest_files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    largest_files.truncate(top);
+
+    let duplicate_waste = duplicates.iter().map(|g| g.wasted_space).sum();
+    duplicates.sort_by(|a, b| {
+        b.wasted_space
+            .cmp(&a.wasted_space)
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+    duplicates.truncate(top);
+
+    let mut compressible = manager
+        .map(|manager| compressible_files(files, manager))
+        .unwrap_or_default();
+    let compressible_savings = compressible.iter().map(|f| f.estimated_savings).sum();
+    compressible.truncate(top);
+
+    StorageReport {
+        root: root.to_string_lossy().to_string(),
+        generated_at: space_saver_utils::time::now(),
+        total_files: files.len(),
+        total_size: files.iter().map(|f| f.size).sum(),
+        categories,
+        largest_files,
+        largest_directories,
+        duplicates,
+        duplicate_waste,
+        compressible,
+        compressible_savings,
+    }
+}
+
+/// Files the first plugin taking them expects to shrink by at least the
+/// manager's minimum savings, largest savings first
+fn compressible_files(files: &[FileInfo], manager: &PluginManager) -> Vec<CompressibleFile> {
+    let plugins = manager.get_plugins();
+    let extensions: HashSet<String> = plugins
+        .iter()
+        .flat_map(|p| manager.get_supported_extensions(&p.name))
+        .map(|ext| ext.to_lowercase())
+        .collect();
+    let min_ratio = manager.min_savings_percent() / 100.0;
+
+    let mut compressible: Vec<CompressibleFile> = files
+        .iter()
+        .filter(|file| {
+            file.path
+                .extension()
+                .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase()))
+        })
+        .filter_map(|file| {
+            let (plugin, ratio) = plugins.iter().find_map(|plugin| {
+                match manager.check_plugin_capability(&file.path, &plugin.name) {
+                    Ok(Some((_, true, _, Some(ratio)))) => Some((plugin.name.clone(), ratio)),
+                    _ => None,
+                }
+            })?;
+            let savings = (file.size as f64 * ratio as f64) as u64;
+            (ratio > 0.0 && ratio >= min_ratio).then(|| CompressibleFile {
+                path: file.path.to_string_lossy().to_string(),
+                original_size: file.size,
+                estimated_compressed_size: file.size - savings,
+                estimated_savings: savings,
+                plugin_name: plugin,
+            })
+        })
+        .collect();
+    compressible.sort_by(|a, b| {
+        b.estimated_savings
+            .cmp(&a.estimated_savings)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    compressible
+}
+
+/// Write `report` as HTML to `path`, replacing any existing file
+pub fn write_report(report: &StorageReport, path: &Path) -> Result<()> {
+    fs::write(path, render_html(report))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// `report` as a standalone HTML page
+pub fn render_html(report: &StorageReport) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Storage report: {root}</title>\n<style>\n\
+         body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; max-width: 72rem; }}\n\
+         .cards {{ display: flex; flex-wrap: wrap; gap: 1rem; margin: 1rem 0 2rem; }}\n\
+         .card {{ border: 1px solid #ddd; border-radius: 6px; padding: 0.8rem 1.2rem; min-width: 10rem; }}\n\
+         .card b {{ display: block; font-size: 1.4rem; }}\n\
+         table {{ border-collapse: collapse; width: 100%; font-size: 0.9rem; margin-bottom: 0.5rem; }}\n\
+         th, td {{ border: 1px solid #ddd; padding: 0.3rem 0.6rem; text-align: left; vertical-align: top; }}\n\
+         th {{ background: #f3f3f3; }}\n\
+         td.num {{ text-align: right; white-space: nowrap; }}\n\
+         td.bar {{ width: 30%; }}\n\
+         .bar div {{ background: #4a90d9; height: 0.8rem; border-radius: 2px; }}\n\
+         .muted {{ color: #777; }}\n\
+         </style>\n</head>\n<body>\n<h1>Storage report</h1>\n\
+         <p><code>{root}</code> &middot; generated {generated}</p>\n<div class=\"cards\">",
+        root = html_escape(&report.root),
+        generated = format_timestamp(report.generated_at),
+    );
+    for (label, value) in [
+        ("Files", report.total_files.to_string()),
+        ("Total size", format_size(report.total_size)),
+        ("Wasted by duplicates", format_size(report.duplicate_waste)),
+        (
+            "Estimated compression savings",
+            format_size(report.compressible_savings),
+        ),
+    ] {
+        let _ = write!(
+            html,
+            "<div class=\"card\">{}<b>{}</b></div>",
+            label,
+            html_escape(&value)
+        );
+    }
+    html.push_str("</div>\n");
+
+    section(
+        &mut html,
+        "Storage by file type",
+        &["Type", "Files", "Size"],
+        report.categories.iter().map(|c| {
+            (
+                vec![c.category.clone(), c.file_count.to_string()],
+                c.total_size,
+            )
+        }),
+        report.total_size,
+    );
+    let largest = report.largest_files.first().map_or(0, |f| f.size);
+    section(
+        &mut html,
+        "Largest files",
+        &["Path", "Size"],
+        report
+            .largest_files
+            .iter()
+            .map(|f| (vec![f.path.to_string_lossy().to_string()], f.size)),
+        largest,
+    );
+    let largest = report
+        .largest_directories
+        .first()
+        .map_or(0, |d| d.total_size);
+    section(
+        &mut html,
+        "Largest folders",
+        &["Path", "Files", "Size"],
+        report
+            .largest_directories
+            .iter()
+            .map(|d| (vec![d.path.clone(), d.file_count.to_string()], d.total_size)),
+        largest,
+    );
+    let largest = report.duplicates.first().map_or(0, |g| g.wasted_space);
+    section(
+        &mut html,
+        "Duplicate groups",
+        &["Copies", "Paths", "Wasted"],
+        report.duplicates.iter().map(|g| {
+            let paths: Vec<String> = g
+                .files
+                .iter()
+                .map(|f| f.path.to_string_lossy().to_string())
+                .collect();
+            (vec![g.count.to_string(), paths.join("\n")], g.wasted_space)
+        }),
+        largest,
+    );
+    let largest = report
+        .compressible
+        .first()
+        .map_or(0, |f| f.estimated_savings);
+    section(
+        &mut html,
+        "Compression opportunities",
+        &["Path", "Plugin", "Size", "Estimated savings"],
+        report.compressible.iter().map(|f| {
+            (
+                vec![
+                    f.path.clone(),
+                    f.plugin_name.clone(),
+                    format_size(f.original_size),
+                ],
+                f.estimated_savings,
+            )
+        }),
+        largest,
+    );
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// A titled table whose rows end in a size drawn as a bar relative to `scale`
+fn section(
+    html: &mut String,
+    title: &str,
+    headers: &[&str],
+    rows: impl Iterator<Item = (Vec<String>, u64)>,
+    scale: u64,
+) {
+    let _ = write!(html, "<h2>{}</h2>\n<table>\n<thead><tr>", title);
+    for header in headers {
+        let _ = write!(html, "<th>{}</th>", header);
+    }
+    html.push_str("<th></th></tr></thead>\n<tbody>\n");
+    let mut empty = true;
+    for (cells, size) in rows {
+        empty = false;
+        html.push_str("<tr>");
+        for cell in cells {
+            let _ = write!(
+                html,
+                "<td>{}</td>",
+                html_escape(&cell).replace('\n', "<br>")
+            );
+        }
+        let width = if scale == 0 {
+            0.0
+        } else {
+            size as f64 * 100.0 / scale as f64
+        };
+        let _ = writeln!(
+            html,
+            "<td class=\"num\">{}</td><td class=\"bar\"><div style=\"width: {:.1}%\"></div></td></tr>",
+            format_size(size),
+            width
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+    if empty {
+        html.push_str("<p class=\"muted\">Nothing found.</p>\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_core::scanner::FileType;
+    use std::path::PathBuf;
+
+    fn file(path: &str, size: u64, file_type: FileType) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path),
+            size,
+            modified: 0,
+            file_type,
+            hash: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    #[test]
+    fn test_report_breaks_down_categories_folders_and_duplicates() {
+        let files = vec![
+            file("/r/photos/a.jpg", 300, FileType::Image),
+            file("/r/photos/old/b.jpg", 300, FileType::Image),
+            file("/r/notes.txt", 10, FileType::Document),
+        ];
+        let duplicates = vec![DuplicateGroup {
+            hash: "abc".to_string(),
+            files: files[..2].to_vec(),
+            count: 2,
+            total_size: 600,
+            wasted_space: 300,
+        }];
+
+        let report = build_report(Path::new("/r"), &files, duplicates, None, 1);
+        assert_eq!(report.total_files, 3);
+        assert_eq!(report.total_size, 610);
+        assert_eq!(report.categories.len(), 2);
+        assert_eq!(report.categories[0].category, "Image");
+        assert_eq!(report.categories[0].total_size, 600);
+        // Only the top entry of each list is kept
+        assert_eq!(report.largest_files.len(), 1);
+        assert_eq!(report.largest_directories.len(), 1);
+        assert_eq!(report.largest_directories[0].path, "/r/photos");
+        assert_eq!(report.largest_directories[0].file_count, 2);
+        assert_eq!(report.duplicate_waste, 300);
+        assert!(report.compressible.is_empty());
+    }
+
+    #[test]
+    fn test_html_is_standalone_and_escaped() {
+        let files = vec![file("/r/<script>.txt", 10, FileType::Document)];
+        let report = build_report(Path::new("/r"), &files, Vec::new(), None, 10);
+        let html = render_html(&report);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("/r/&lt;script&gt;.txt"));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("http"));
+        assert!(html.contains("Compression opportunities"));
+    }
+}
@@ -0,0 +1,375 @@
+//! Undoing what the tool did to files.
+//!
+//! Two kinds of change leave a [`RestorePoint`] behind: a compression that
+//! kept a backup of its original (recorded with the savings ledger) and a
+//! file moved to the system trash by [`crate::FileOperations`] with a
+//! database. Restoring puts the file back where it was: the backup replaces
+//! the compressed output, or the trashed file is taken out of the trash.
+//! Quarantined files have their own restore, see [`crate::Quarantine`].
+//!
+//! Compression backups can be browsed with what each compression saved
+//! ([`compression_backups`]), and purged once old enough to trust the
+//! output ([`purge_backups`]); the savings then stay for good.
+
+use crate::api::FileError;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use space_saver_db::{RestorePoint, SqliteDatabase};
+use space_saver_utils::Error;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Per-file outcome of restoring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreResult {
+    pub id: i64,
+    pub kind: String,
+    pub path: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// A compression that kept its original, and can be undone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionBackup {
+    /// The restore point, what [`restore_backup`] takes
+    pub id: i64,
+    /// The original's path, where restoring puts it back
+    pub path: String,
+    pub backup_path: String,
+    pub output_path: Option<String>,
+    /// None when the compression is no longer in the savings ledger
+    pub plugin_name: Option<String>,
+    pub original_size: u64,
+    pub compressed_size: Option<u64>,
+    /// Bytes the compression saves; 0 when the output came out larger
+    pub saved: u64,
+    /// A backup no longer on disk cannot be restored
+    pub backup_exists: bool,
+    pub created_at: i64,
+}
+
+impl CompressionBackup {
+    fn new(db: &SqliteDatabase, point: RestorePoint) -> Result<Self> {
+        let operation = match point.operation_id {
+            Some(id) => db.get_compression_operation(id)?,
+            None => None,
+        };
+        let backup_path = point.backup_path.unwrap_or_default();
+        let compressed_size = operation.as_ref().map(|op| op.compressed_size);
+        Ok(Self {
+            id: point.id,
+            path: point.path,
+            backup_exists: Path::new(&backup_path).symlink_metadata().is_ok(),
+            backup_path,
+            output_path: point.output_path,
+            plugin_name: operation.map(|op| op.plugin_name),
+            original_size: point.size,
+            compressed_size,
+            saved: compressed_size.map_or(0, |size| point.size.saturating_sub(size)),
+            created_at: point.created_at,
+        })
+    }
+}
+
+/// Backups purged by [`purge_backups`], and those that could not be
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupPurge {
+    pub purged: Vec<CompressionBackup>,
+    /// Bytes the removed backups took
+    pub freed: u64,
+    pub failed: Vec<FileError>,
+}
+
+/// Compressions that can still be undone, newest first
+pub fn compression_backups(db: &SqliteDatabase) -> Result<Vec<CompressionBackup>> {
+    db.get_restore_points()?
+        .into_iter()
+        .filter(|point| point.kind == "compression")
+        .map(|point| CompressionBackup::new(db, point))
+        .collect()
+}
+
+/// Undo the compression recorded as restore point `id`, see [`restore`]
+pub fn restore_backup(db: &SqliteDatabase, id: i64) -> Result<RestorePoint> {
+    match db.get_restore_point(id)? {
+        Some(point) if point.kind != "compression" => Err(Error::InvalidInput(format!(
+            "Restore point {} is not a compression but a {} of {}",
+            id, point.kind, point.path
+        ))
+        .into()),
+        _ => restore(db, id),
+    }
+}
+
+/// Delete the backups of compressions made before `older_than` (Unix
+/// seconds), so they can no longer be undone. Backups already gone are
+/// forgotten as well, freeing nothing.
+pub fn purge_backups(db: &SqliteDatabase, older_than: i64) -> Result<BackupPurge> {
+    let mut purge = BackupPurge::default();
+    for backup in compression_backups(db)? {
+        if backup.created_at >= older_than {
+            continue;
+        }
+        let path = Path::new(&backup.backup_path);
+        let size = path.symlink_metadata().map(|m| m.len()).unwrap_or(0);
+        match fs::remove_file(path) {
+            Ok(()) => purge.freed += size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                purge.failed.push(FileError::new(path, Error::from(e)));
+                continue;
+            }
+        }
+        db.delete_restore_point(backup.id)?;
+        purge.purged.push(backup);
+    }
+    Ok(purge)
+}
+
+/// Changes that can still be undone, newest first; only those at or under
+/// `under` when given
+pub fn restore_points(db: &SqliteDatabase, under: Option<&Path>) -> Result<Vec<RestorePoint>> {
+    let mut points = db.get_restore_points()?;
+    if let Some(under) = under {
+        let under = std::path::absolute(under)?;
+        points.retain(|p| Path::new(&p.path).starts_with(&under));
+    }
+    Ok(points)
+}
+
+/// Undo the change recorded as `id`. Refused when something else now
+/// occupies the original path; a point whose backup or trashed file is gone
+/// is forgotten.
+pub fn restore(db: &SqliteDatabase, id: i64) -> Result<RestorePoint> {
+    let point = db
+        .get_restore_point(id)?
+        .ok_or_else(|| Error::NotFound(format!("Unknown restore point: {}", id)))?;
+    let outcome = match point.kind.as_str() {
+        "compression" => restore_compression(&point),
+        "trash" => restore_trashed(&point),
+        kind => bail!("Unknown kind of restore point: {}", kind),
+    };
+    if let Err(e) = outcome {
+        if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) {
+            db.delete_restore_point(id)?;
+        }
+        return Err(e);
+    }
+    db.delete_restore_point(id)?;
+    if let Some(operation) = point.operation_id {
+        // Undone, it no longer saves anything
+        db.delete_compression_operation(operation)?;
+    }
+    Ok(point)
+}
+
+/// Restore the latest change to every file at or under `under`
+pub fn restore_under(db: &SqliteDatabase, under: &Path) -> Result<Vec<RestoreResult>> {
+    let mut seen = HashSet::new();
+    let latest: Vec<RestorePoint> = restore_points(db, Some(under))?
+        .into_iter()
+        .filter(|p| seen.insert(p.path.clone()))
+        .collect();
+    Ok(latest
+        .into_iter()
+        .map(|point| {
+            let outcome = restore(db, point.id);
+            RestoreResult {
+                id: point.id,
+                kind: point.kind,
+                path: point.path,
+                success: outcome.is_ok(),
+                error: outcome.err().map(|e| format!("{:#}", e)),
+            }
+        })
+        .collect())
+}
+
+fn restore_compression(point: &RestorePoint) -> Result<()> {
+    let original = Path::new(&point.path);
+    let backup = Path::new(point.backup_path.as_deref().unwrap_or_default());
+    if backup.symlink_metadata().is_err() {
+        return Err(Error::NotFound(format!("The backup {} is gone", backup.display())).into());
+    }
+    let output = point.output_path.as_deref().map(Path::new);
+    // The output may have replaced the original under the same name
+    if output != Some(original) && original.symlink_metadata().is_ok() {
+        bail!("Cannot restore: {} already exists", point.path);
+    }
+    fs::rename(backup, original)
+        .with_context(|| format!("Failed to move {} back", backup.display()))?;
+    if let Some(output) = output.filter(|o| *o != original) {
+        match fs::remove_file(output) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!(
+                path = %output.display(),
+                error = %e,
+                "Failed to remove the compressed file"
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore_trashed(point: &RestorePoint) -> Result<()> {
+    let original = Path::new(&point.path);
+    // The same path may have been trashed more than once; take the one
+    // deleted closest to when it was recorded
+    let item = trash::os_limited::list()
+        .map_err(|e| Error::FileOperation(e.to_string()))?
+        .into_iter()
+        .filter(|item| item.original_path() == original)
+        .min_by_key(|item| (item.time_deleted - point.created_at).abs())
+        .ok_or_else(|| Error::NotFound(format!("{} is no longer in the trash", point.path)))?;
+    if original.symlink_metadata().is_ok() {
+        bail!("Cannot restore: {} already exists", point.path);
+    }
+    trash::os_limited::restore_all([item]).map_err(|e| Error::FileOperation(e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore_trashed(point: &RestorePoint) -> Result<()> {
+    Err(Error::InvalidInput(format!(
+        "Files cannot be taken out of the trash here; use Put Back on {}",
+        point.path
+    ))
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use space_saver_db::CompressionOperation;
+    use tempfile::tempdir;
+
+    fn compressed(db: &SqliteDatabase, dir: &Path, name: &str, output: &str) -> i64 {
+        let source = dir.join(name);
+        let backup = dir.join(format!("{}.bak", name));
+        fs::write(&backup, b"original").unwrap();
+        fs::write(dir.join(output), b"small").unwrap();
+        let mut op = CompressionOperation::new(
+            source.to_string_lossy().to_string(),
+            dir.join(output).to_string_lossy().to_string(),
+            "WebP Converter".to_string(),
+            8,
+            5,
+        );
+        op.backup_path = Some(backup.to_string_lossy().to_string());
+        db.insert_compression_operation(&op).unwrap();
+        db.get_restore_points().unwrap()[0].id
+    }
+
+    #[test]
+    fn test_restoring_a_compression_puts_the_original_back() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let id = compressed(&db, dir.path(), "a.png", "a.webp");
+
+        let point = restore(&db, id).unwrap();
+        assert!(point.path.ends_with("a.png"));
+        assert_eq!(fs::read(dir.path().join("a.png")).unwrap(), b"original");
+        assert!(!dir.path().join("a.webp").exists());
+        assert!(!dir.path().join("a.png.bak").exists());
+        assert!(db.get_restore_points().unwrap().is_empty());
+        assert_eq!(db.savings_summary().unwrap().file_count, 0);
+    }
+
+    #[test]
+    fn test_an_output_with_the_same_name_is_replaced() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let id = compressed(&db, dir.path(), "notes.txt", "notes.txt");
+
+        restore(&db, id).unwrap();
+        assert_eq!(fs::read(dir.path().join("notes.txt")).unwrap(), b"original");
+    }
+
+    #[test]
+    fn test_occupied_paths_are_refused_and_missing_backups_forgotten() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let id = compressed(&db, dir.path(), "a.png", "a.webp");
+        fs::write(dir.path().join("a.png"), b"someone else").unwrap();
+        assert!(restore(&db, id)
+            .unwrap_err()
+            .to_string()
+            .contains("already exists"));
+        assert_eq!(db.get_restore_points().unwrap().len(), 1);
+
+        fs::remove_file(dir.path().join("a.png.bak")).unwrap();
+        let err = Error::from(restore(&db, id).unwrap_err());
+        assert_eq!(err.code(), space_saver_utils::ErrorCode::NotFound);
+        assert!(db.get_restore_points().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backups_list_their_savings_and_purge_by_age() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        let old = compressed(&db, dir.path(), "a.png", "a.webp");
+        let recent = compressed(&db, dir.path(), "b.png", "b.webp");
+        let trashed = db
+            .insert_restore_point(&RestorePoint::trashed("/docs/old.pdf".to_string(), 50))
+            .unwrap();
+
+        let backups = compression_backups(&db).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].plugin_name.as_deref(), Some("WebP Converter"));
+        assert_eq!(backups[0].saved, 3);
+        assert!(backups.iter().all(|b| b.backup_exists));
+        let err = Error::from(restore_backup(&db, trashed).unwrap_err());
+        assert_eq!(err.code(), space_saver_utils::ErrorCode::InvalidInput);
+
+        // Both were made at the same second; purge everything before the
+        // recent one's
+        let created = db.get_restore_point(recent).unwrap().unwrap().created_at;
+        let purge = purge_backups(&db, created + 1).unwrap();
+        assert_eq!(purge.purged.len(), 2);
+        assert_eq!(purge.freed, 16);
+        assert!(!dir.path().join("a.png.bak").exists());
+        assert!(dir.path().join("a.webp").exists());
+        assert!(db.get_restore_point(old).unwrap().is_none());
+        // The savings stay, and so does the trashed file's restore point
+        assert_eq!(db.savings_summary().unwrap().file_count, 2);
+        assert!(db.get_restore_point(trashed).unwrap().is_some());
+        assert!(purge_backups(&db, created).unwrap().purged.is_empty());
+    }
+
+    #[test]
+    fn test_restore_under_takes_the_latest_change_per_file() {
+        let dir = tempdir().unwrap();
+        let db = SqliteDatabase::in_memory().unwrap();
+        fs::create_dir(dir.path().join("photos")).unwrap();
+        compressed(&db, &dir.path().join("photos"), "a.png", "a.webp");
+        compressed(&db, dir.path(), "b.png", "b.webp");
+
+        let points = restore_points(&db, Some(&dir.path().join("photos"))).unwrap();
+        assert_eq!(points.len(), 1);
+        let results = restore_under(&db, &dir.path().join("photos")).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success, "error: {:?}", results[0].error);
+        assert!(dir.path().join("photos/a.png").exists());
+        assert!(dir.path().join("b.webp").exists());
+    }
+}
@@ -0,0 +1,291 @@
+//! Directory walks shared between operations.
+//!
+//! Finding duplicates, similar photos or compression candidates right after
+//! scanning the same folders would walk each tree again. A [`ScanCache`]
+//! attached with [`crate::ServiceApi::with_scan_cache`] keeps the files a
+//! walk found, keyed by the scanned path and the walk options (hidden
+//! entries, depth, symbolic links). Filters are applied to the cached files
+//! afterwards, so differently filtered operations share one walk.
+//!
+//! A reused walk is checked first, which is much cheaper than walking
+//! again: every file is looked up for its current size and date (files gone
+//! are left out), and a folder holding the files that changed its date,
+//! because something was added, removed or renamed in it, means walking
+//! again. Files added to folders the walk found no files in go unnoticed,
+//! so entries also expire after [`DEFAULT_MAX_AGE`], and whoever changes
+//! files calls [`ScanCache::invalidate`] with the paths touched. Explicit
+//! scans ([`crate::ServiceApi::scan_directories`]) always walk the tree and
+//! refresh the entry.
+
+use crate::api::FilterConfig;
+use space_saver_core::FileInfo;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a walk is reused unless [`ScanCache::with_max_age`] says
+/// otherwise
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Walks kept at most; the oldest goes first
+pub const MAX_ENTRIES: usize = 8;
+
+/// A scanned path and how it was walked
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct WalkKey {
+    root: PathBuf,
+    include_hidden: bool,
+    follow_links: bool,
+    max_depth: Option<usize>,
+}
+
+impl WalkKey {
+    fn new(root: &Path, filter: Option<&FilterConfig>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            include_hidden: filter.is_some_and(FilterConfig::includes_hidden),
+            follow_links: filter.and_then(|f| f.follow_links).unwrap_or(false),
+            max_depth: filter.and_then(|f| f.max_depth),
+        }
+    }
+}
+
+struct Entry {
+    files: Vec<FileInfo>,
+    /// The root and the folders holding files, with their modification
+    /// times when walked
+    dirs: Vec<(PathBuf, Option<SystemTime>)>,
+    /// The root resolved, so invalidating through another spelling of a
+    /// path still finds it
+    canonical: PathBuf,
+    scanned: Instant,
+}
+
+/// Files found by recent walks, unfiltered. Clones share the same entries.
+#[derive(Clone)]
+pub struct ScanCache {
+    entries: Arc<Mutex<HashMap<WalkKey, Entry>>>,
+    max_age: Duration,
+}
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+impl ScanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reuse walks for `max_age` instead of [`DEFAULT_MAX_AGE`]
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The files of the last walk of `root` as `filter` asks, as they are
+    /// now, unless it expired or a folder changed since. `filter` itself is
+    /// not applied.
+    pub(crate) fn get(&self, root: &Path, filter: Option<&FilterConfig>) -> Option<Vec<FileInfo>> {
+        let key = WalkKey::new(root, filter);
+        // Checked without the lock held; the files may be many
+        let (files, dirs) = {
+            let mut entries = self.lock();
+            match entries.get(&key) {
+                Some(entry) if entry.scanned.elapsed() <= self.max_age => {
+                    (entry.files.clone(), entry.dirs.clone())
+                }
+                Some(_) => {
+                    entries.remove(&key);
+                    return None;
+                }
+                None => return None,
+            }
+        };
+        if dirs
+            .iter()
+            .any(|(dir, modified)| modified_time(dir) != *modified)
+        {
+            self.lock().remove(&key);
+            return None;
+        }
+        let files = files
+            .into_iter()
+            .filter_map(|file| {
+                let metadata = fs::metadata(&file.path).ok().filter(|m| m.is_file())?;
+                Some(FileInfo::from_metadata(&file.path, &metadata))
+            })
+            .collect();
+        Some(files)
+    }
+
+    /// Keep the unfiltered `files` a walk of `root` found
+    pub(crate) fn insert(&self, root: &Path, filter: Option<&FilterConfig>, files: &[FileInfo]) {
+        let dirs: BTreeSet<&Path> = std::iter::once(root)
+            .chain(files.iter().filter_map(|file| file.path.parent()))
+            .collect();
+        let entry = Entry {
+            dirs: dirs
+                .into_iter()
+                .map(|dir| (dir.to_path_buf(), modified_time(dir)))
+                .collect(),
+            files: files.to_vec(),
+            canonical: canonical(root),
+            scanned: Instant::now(),
+        };
+        let key = WalkKey::new(root, filter);
+        let mut entries = self.lock();
+        if !entries.contains_key(&key) && entries.len() >= MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.scanned)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, entry);
+    }
+
+    /// Forget every walk that saw `path` or something beneath it, because
+    /// files there changed. Returns how many were dropped.
+    pub fn invalidate(&self, path: &Path) -> usize {
+        let path = canonical(path);
+        let mut entries = self.lock();
+        let before = entries.len();
+        entries.retain(|_, entry| {
+            !(path.starts_with(&entry.canonical) || entry.canonical.starts_with(&path))
+        });
+        before - entries.len()
+    }
+
+    /// Forget every walk, returning how many there were
+    pub fn clear(&self) -> usize {
+        let mut entries = self.lock();
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+
+    /// Walks kept, expired or not
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<WalkKey, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `path` resolved; deleted files resolve through their parent, and
+/// anything unresolvable stays as given
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(resolved) = std::fs::canonicalize(path) {
+        return resolved;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonical(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServiceApi;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_operations_reuse_walks_until_invalidated() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.jpg"), b"same").unwrap();
+        std::fs::write(dir.path().join("b.jpg"), b"same").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"text").unwrap();
+        std::fs::create_dir(dir.path().join("empty")).unwrap();
+        let cache = ScanCache::new();
+        let api = ServiceApi::new().with_scan_cache(cache.clone());
+
+        let scanned = api
+            .scan_directory(dir.path().to_path_buf(), None)
+            .await
+            .unwrap();
+        assert_eq!(scanned.file_count, 3);
+        assert_eq!(cache.len(), 1);
+
+        // The walk is reused and filtered anew, so a file added to a folder
+        // it found no files in stays unseen; files changed in place are not
+        let new_file = dir.path().join("empty").join("c.jpg");
+        std::fs::write(&new_file, b"same").unwrap();
+        std::fs::write(dir.path().join("b.jpg"), b"changed").unwrap();
+        let filter = FilterConfig {
+            extensions: Some(vec!["jpg".to_string()]),
+            ..Default::default()
+        };
+        let files = api
+            .collect_files(&[dir.path().to_path_buf()], Some(&filter))
+            .unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().any(|f| f.size == 7));
+        std::fs::write(dir.path().join("b.jpg"), b"same").unwrap();
+
+        // Invalidating anything beneath the root drops the walk
+        assert_eq!(cache.invalidate(&new_file), 1);
+        let groups = api
+            .find_duplicates(dir.path().to_path_buf(), Some(filter.clone()))
+            .await
+            .unwrap();
+        assert_eq!(groups[0].count, 3);
+
+        // So does a file added to a folder that held files
+        std::fs::write(dir.path().join("d.jpg"), b"same").unwrap();
+        let groups = api
+            .find_duplicates(dir.path().to_path_buf(), Some(filter))
+            .await
+            .unwrap();
+        assert_eq!(groups[0].count, 4);
+
+        // Hidden files need a walk of their own
+        let hidden = FilterConfig {
+            include_hidden: Some(true),
+            ..Default::default()
+        };
+        api.collect_files(&[dir.path().to_path_buf()], Some(&hidden))
+            .unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.clear(), 2);
+    }
+
+    #[test]
+    fn test_walks_expire_and_make_room() {
+        let dir = tempdir().unwrap();
+        let cache = ScanCache::new().with_max_age(Duration::ZERO);
+        cache.insert(dir.path(), None, &[]);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.get(dir.path(), None).is_none());
+        assert!(cache.is_empty());
+
+        let cache = ScanCache::new();
+        for i in 0..=MAX_ENTRIES {
+            std::thread::sleep(Duration::from_millis(1));
+            cache.insert(&dir.path().join(i.to_string()), None, &[]);
+        }
+        assert_eq!(cache.len(), MAX_ENTRIES);
+        assert!(cache.get(&dir.path().join("0"), None).is_none());
+        assert!(cache.get(&dir.path().join("1"), None).is_some());
+    }
+}
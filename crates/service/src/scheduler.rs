@@ -1,35 +1,92 @@
 use crate::progress::ProgressUpdate;
-use crate::task::Task;
+use crate::task::{Task, TaskKind};
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Notify, OwnedSemaphorePermit, Semaphore};
 use tracing::{error, info};
 
-/// Task scheduler for managing concurrent tasks
+/// How urgently a queued task should run. Higher priorities start first;
+/// tasks of the same priority start in submission order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Queue position: highest priority first, then oldest first
+type QueueKey = (Reverse<TaskPriority>, u64);
+
+/// A task cleared to start, with the slots it holds while running
+type Runnable = (Box<dyn Task>, Vec<OwnedSemaphorePermit>);
+
+/// Task scheduler for managing concurrent tasks.
+///
+/// At most `max_concurrent` tasks run at once, and kinds with a limit of
+/// their own (one compression batch at a time by default) never exceed it.
+/// A task held back by its kind's limit does not block the tasks queued
+/// behind it, so a heavy batch cannot starve quick scans.
 pub struct Scheduler {
-    task_queue: Arc<RwLock<Vec<Box<dyn Task>>>>,
+    task_queue: Arc<Mutex<BTreeMap<QueueKey, Box<dyn Task>>>>,
+    next_seq: AtomicU64,
     max_concurrent: usize,
+    running: Arc<Semaphore>,
+    /// Slots per task kind, for kinds with a limit
+    kind_limits: HashMap<TaskKind, Arc<Semaphore>>,
+    /// Woken when a task is submitted or finishes
+    wake: Arc<Notify>,
     progress_tx: mpsc::Sender<ProgressUpdate>,
 }
 
 impl Scheduler {
     pub fn new(max_concurrent: usize) -> (Self, mpsc::Receiver<ProgressUpdate>) {
         let (progress_tx, progress_rx) = mpsc::channel(100);
+        let max_concurrent = max_concurrent.max(1);
 
         let scheduler = Self {
-            task_queue: Arc::new(RwLock::new(Vec::new())),
+            task_queue: Arc::new(Mutex::new(BTreeMap::new())),
+            next_seq: AtomicU64::new(0),
             max_concurrent,
+            running: Arc::new(Semaphore::new(max_concurrent)),
+            kind_limits: HashMap::from([(TaskKind::CompressFiles, Arc::new(Semaphore::new(1)))]),
+            wake: Arc::new(Notify::new()),
             progress_tx,
         };
 
         (scheduler, progress_rx)
     }
 
-    /// Submit a task to the queue
+    /// Run at most `limit` tasks of `kind` at once (compression defaults to
+    /// one). A limit of 0 is treated as 1.
+    pub fn with_kind_limit(mut self, kind: TaskKind, limit: usize) -> Self {
+        self.kind_limits
+            .insert(kind, Arc::new(Semaphore::new(limit.max(1))));
+        self
+    }
+
+    /// Submit a task to the queue at normal priority
     pub async fn submit(&self, task: Box<dyn Task>) -> Result<()> {
-        let mut queue = self.task_queue.write().await;
-        queue.push(task);
-        info!("Task submitted. Queue length: {}", queue.len());
+        self.submit_with_priority(task, TaskPriority::Normal).await
+    }
+
+    /// Submit a task to the queue, ahead of tasks of lower priority
+    pub async fn submit_with_priority(
+        &self,
+        task: Box<dyn Task>,
+        priority: TaskPriority,
+    ) -> Result<()> {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let len = {
+            let mut queue = self.lock_queue()?;
+            queue.insert((Reverse(priority), seq), task);
+            queue.len()
+        };
+        info!("Task submitted. Queue length: {}", len);
+        self.wake.notify_one();
         Ok(())
     }
 
@@ -41,14 +98,14 @@ impl Scheduler {
         );
 
         loop {
-            let task = {
-                let mut queue = self.task_queue.write().await;
-                queue.pop()
-            };
+            // Registered before looking at the queue so a submission or a
+            // finished task in between is not missed
+            let woken = self.wake.notified();
 
-            match task {
-                Some(mut task) => {
+            match self.next_runnable()? {
+                Some((mut task, permits)) => {
                     let progress_tx = self.progress_tx.clone();
+                    let wake = Arc::clone(&self.wake);
 
                     tokio::spawn(async move {
                         info!("Executing task: {:?}", task.task_type());
@@ -61,26 +118,63 @@ impl Scheduler {
                                 error!("Task failed: {}", e);
                             }
                         }
+                        drop(permits);
+                        wake.notify_one();
                     });
                 }
+                None => woken.await,
+            }
+        }
+    }
+
+    /// Take the first queued task that may start now
+    fn next_runnable(&self) -> Result<Option<Runnable>> {
+        let Ok(slot) = Arc::clone(&self.running).try_acquire_owned() else {
+            return Ok(None);
+        };
+        let mut queue = self.lock_queue()?;
+        let mut found = None;
+        for (key, task) in queue.iter() {
+            match self.kind_limits.get(&task.task_type().kind()) {
                 None => {
-                    // No tasks in queue, wait a bit
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                    found = Some((*key, None));
+                    break;
+                }
+                Some(limit) => {
+                    if let Ok(kind_slot) = Arc::clone(limit).try_acquire_owned() {
+                        found = Some((*key, Some(kind_slot)));
+                        break;
+                    }
                 }
             }
         }
+        Ok(found.and_then(|(key, kind_slot)| {
+            let task = queue.remove(&key)?;
+            Some((task, std::iter::once(slot).chain(kind_slot).collect()))
+        }))
+    }
+
+    fn lock_queue(&self) -> Result<std::sync::MutexGuard<'_, BTreeMap<QueueKey, Box<dyn Task>>>> {
+        self.task_queue
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Task queue lock poisoned"))
     }
 
     /// Get the number of tasks in the queue
     pub async fn queue_length(&self) -> usize {
-        let queue = self.task_queue.read().await;
-        queue.len()
+        self.lock_queue().map(|queue| queue.len()).unwrap_or(0)
+    }
+
+    /// Number of tasks running right now
+    pub fn running_count(&self) -> usize {
+        self.max_concurrent - self.running.available_permits()
     }
 
     /// Clear all pending tasks
     pub async fn clear_queue(&self) {
-        let mut queue = self.task_queue.write().await;
-        queue.clear();
+        if let Ok(mut queue) = self.lock_queue() {
+            queue.clear();
+        }
         info!("Task queue cleared");
     }
 }
@@ -88,8 +182,92 @@ impl Scheduler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::task::ScanTask;
+    use crate::task::{ScanTask, TaskStatus, TaskType};
+    use async_trait::async_trait;
     use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// Logs its name when it starts and finishes, taking `duration` to run
+    struct LoggedTask {
+        name: &'static str,
+        task_type: TaskType,
+        duration: Duration,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Task for LoggedTask {
+        async fn run(&mut self, _progress_tx: mpsc::Sender<ProgressUpdate>) -> Result<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("start {}", self.name));
+            tokio::time::sleep(self.duration).await;
+            self.log.lock().unwrap().push(format!("end {}", self.name));
+            Ok(())
+        }
+
+        fn task_type(&self) -> &TaskType {
+            &self.task_type
+        }
+
+        fn status(&self) -> &TaskStatus {
+            &TaskStatus::Pending
+        }
+    }
+
+    fn task(
+        name: &'static str,
+        task_type: TaskType,
+        millis: u64,
+        log: &Arc<Mutex<Vec<String>>>,
+    ) -> Box<dyn Task> {
+        Box::new(LoggedTask {
+            name,
+            task_type,
+            duration: Duration::from_millis(millis),
+            log: Arc::clone(log),
+        })
+    }
+
+    fn scan() -> TaskType {
+        TaskType::Scan(vec![PathBuf::from("/photos")])
+    }
+
+    fn compress() -> TaskType {
+        TaskType::CompressFiles(vec![PathBuf::from("/photos/a.png")])
+    }
+
+    /// Run `scheduler` until `count` tasks have finished, returning the log
+    async fn run_until_done(
+        scheduler: Scheduler,
+        log: &Arc<Mutex<Vec<String>>>,
+        count: usize,
+    ) -> Vec<String> {
+        let runner = tokio::spawn(async move { scheduler.start().await });
+        let finished = || {
+            log.lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.starts_with("end"))
+                .count()
+        };
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while finished() < count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("tasks finish");
+        runner.abort();
+        log.lock().unwrap().clone()
+    }
+
+    fn starts(log: &[String]) -> Vec<&str> {
+        log.iter()
+            .filter_map(|e| e.strip_prefix("start "))
+            .collect()
+    }
 
     #[tokio::test]
     async fn test_scheduler_submit() {
@@ -111,4 +289,90 @@ mod tests {
         scheduler.clear_queue().await;
         assert_eq!(scheduler.queue_length().await, 0);
     }
+
+    #[tokio::test]
+    async fn test_higher_priority_first_then_submission_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (scheduler, _rx) = Scheduler::new(1);
+        for (name, priority) in [
+            ("a", TaskPriority::Low),
+            ("b", TaskPriority::Normal),
+            ("c", TaskPriority::Normal),
+            ("d", TaskPriority::High),
+        ] {
+            scheduler
+                .submit_with_priority(task(name, scan(), 1, &log), priority)
+                .await
+                .unwrap();
+        }
+
+        let log = run_until_done(scheduler, &log, 4).await;
+        assert_eq!(starts(&log), ["d", "b", "c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_running_tasks_stay_within_max_concurrent() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (scheduler, _rx) = Scheduler::new(2);
+        for name in ["a", "b", "c", "d", "e"] {
+            scheduler
+                .submit(task(name, scan(), 20, &log))
+                .await
+                .unwrap();
+        }
+
+        let log = run_until_done(scheduler, &log, 5).await;
+        let mut running: usize = 0;
+        let mut most = 0;
+        for entry in &log {
+            if entry.starts_with("start") {
+                running += 1;
+            } else {
+                running -= 1;
+            }
+            most = most.max(running);
+        }
+        assert_eq!(most, 2);
+    }
+
+    #[tokio::test]
+    async fn test_one_compression_at_a_time_without_holding_up_scans() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (scheduler, _rx) = Scheduler::new(4);
+        scheduler
+            .submit(task("compress 1", compress(), 50, &log))
+            .await
+            .unwrap();
+        scheduler
+            .submit(task("compress 2", compress(), 10, &log))
+            .await
+            .unwrap();
+        scheduler
+            .submit(task("scan", scan(), 1, &log))
+            .await
+            .unwrap();
+
+        let log = run_until_done(scheduler, &log, 3).await;
+        assert_eq!(starts(&log), ["compress 1", "scan", "compress 2"]);
+        let position = |entry: &str| log.iter().position(|e| e == entry).unwrap();
+        assert!(position("end compress 1") < position("start compress 2"));
+    }
+
+    #[tokio::test]
+    async fn test_kind_limit_can_be_raised() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (scheduler, _rx) = Scheduler::new(4);
+        let scheduler = scheduler.with_kind_limit(TaskKind::CompressFiles, 2);
+        scheduler
+            .submit(task("compress 1", compress(), 50, &log))
+            .await
+            .unwrap();
+        scheduler
+            .submit(task("compress 2", compress(), 10, &log))
+            .await
+            .unwrap();
+
+        let log = run_until_done(scheduler, &log, 2).await;
+        assert_eq!(log[1], "start compress 2");
+    }
 }
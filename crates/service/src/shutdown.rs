@@ -0,0 +1,307 @@
+//! Stopping cleanly when the app closes.
+//!
+//! [`shutdown`] asks every unfinished job to stop and waits a grace period
+//! for them to wind down. A cancelled compression finishes or discards the
+//! file in hand (an original is never left half-replaced) and stops before
+//! the next one, so what it already compressed stays compressed. Outputs
+//! that work still in progress registered with [`PartialOutputs`], such as
+//! an archive being written and its verification copy, are then removed.
+//!
+//! What was interrupted comes back as a [`ShutdownReport`], which a frontend
+//! saves on the way out and reads back with [`ShutdownReport::take`] on the
+//! next launch.
+
+use crate::jobs::{JobId, JobManager};
+use crate::task::{TaskStatus, TaskType};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often [`shutdown`] checks whether the cancelled jobs have stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Files and directories being written by work in progress, which must not
+/// outlive it. Clones share the same set.
+#[derive(Clone, Default)]
+pub struct PartialOutputs {
+    paths: Arc<Mutex<BTreeSet<PathBuf>>>,
+}
+
+impl PartialOutputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `path`, which the caller is about to create, until the
+    /// returned guard is dropped. A path that already exists is not
+    /// registered, so shutdown never removes something the work did not
+    /// write.
+    pub fn track(&self, path: &Path) -> PartialOutput {
+        let path = (!path.exists()).then(|| {
+            self.lock().insert(path.to_path_buf());
+            path.to_path_buf()
+        });
+        PartialOutput {
+            outputs: self.clone(),
+            path,
+        }
+    }
+
+    /// Paths currently registered
+    pub fn list(&self) -> Vec<PathBuf> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Remove every registered path from disk, returning those that existed
+    fn remove_all(&self) -> Vec<PathBuf> {
+        let paths = std::mem::take(&mut *self.lock());
+        paths
+            .into_iter()
+            .filter(|path| {
+                let removed = if path.is_dir() {
+                    fs::remove_dir_all(path)
+                } else {
+                    fs::remove_file(path)
+                };
+                match removed {
+                    Ok(()) => true,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+                    Err(e) => {
+                        tracing::warn!(path = %path.display(), error = %e, "Failed to remove partial output");
+                        false
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeSet<PathBuf>> {
+        self.paths.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A path registered with [`PartialOutputs::track`]; dropping it means the
+/// work finished with the path (kept or cleaned up itself)
+#[must_use = "the path is unregistered when the guard is dropped"]
+pub struct PartialOutput {
+    outputs: PartialOutputs,
+    path: Option<PathBuf>,
+}
+
+impl Drop for PartialOutput {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            self.outputs.lock().remove(path);
+        }
+    }
+}
+
+/// A job that was still running when the app closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptedJob {
+    pub id: JobId,
+    pub task_type: TaskType,
+    /// Items done and items in total when it stopped
+    pub current: usize,
+    pub total: usize,
+    /// Latest progress message, e.g. the file in hand
+    pub message: String,
+    /// False when the job had not stopped by the end of the grace period
+    pub stopped: bool,
+}
+
+/// What closing the app interrupted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    /// Unix timestamp of the shutdown
+    pub at: i64,
+    pub interrupted: Vec<InterruptedJob>,
+    /// Partial outputs removed
+    pub removed: Vec<PathBuf>,
+}
+
+impl ShutdownReport {
+    /// Nothing was interrupted or cleaned up
+    pub fn is_empty(&self) -> bool {
+        self.interrupted.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Read the report saved at `path` and delete it, so it is shown once
+    pub fn take(path: &Path) -> Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        fs::remove_file(path)?;
+        Ok(Some(serde_json::from_str(&json)?))
+    }
+}
+
+/// Cancel every unfinished job in `jobs`, wait up to `grace` for them to
+/// stop, then remove whatever `partial` still lists. Blocking.
+pub fn shutdown(jobs: &JobManager, partial: &PartialOutputs, grace: Duration) -> ShutdownReport {
+    let unfinished: Vec<JobId> = jobs
+        .list()
+        .into_iter()
+        .filter(|job| !job.is_finished())
+        .map(|job| job.id)
+        .collect();
+    for id in &unfinished {
+        let _ = jobs.cancel(*id);
+    }
+
+    let deadline = Instant::now() + grace;
+    let all_stopped = || {
+        unfinished
+            .iter()
+            .all(|id| jobs.status(*id).is_none_or(|job| job.is_finished()))
+    };
+    while !all_stopped() && Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // A job that completed during the grace period was not interrupted
+    let interrupted: Vec<InterruptedJob> = unfinished
+        .iter()
+        .filter_map(|id| jobs.status(*id))
+        .filter(|job| job.status != TaskStatus::Completed)
+        .map(|job| InterruptedJob {
+            stopped: job.is_finished(),
+            id: job.id,
+            task_type: job.task_type,
+            current: job.current,
+            total: job.total,
+            message: job.message,
+        })
+        .collect();
+    if interrupted.iter().any(|job| !job.stopped) {
+        tracing::warn!("Shutting down with jobs still running after {:?}", grace);
+    }
+
+    ShutdownReport {
+        at: space_saver_utils::time::now(),
+        interrupted,
+        removed: partial.remove_all(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressUpdate;
+    use tempfile::tempdir;
+
+    fn scan_job() -> TaskType {
+        TaskType::Scan(vec![PathBuf::from("/photos")])
+    }
+
+    #[test]
+    fn test_shutdown_cancels_jobs_and_removes_partial_outputs() {
+        let dir = tempdir().unwrap();
+        let jobs = JobManager::new();
+        let partial = PartialOutputs::new();
+
+        jobs.register(scan_job())
+            .update(&ProgressUpdate::Completed {
+                message: "scan finished".to_string(),
+            });
+        // Stops as soon as it is cancelled
+        let polite = jobs.register(scan_job());
+        polite.update(&ProgressUpdate::Progress {
+            current: 3,
+            total: 10,
+            message: "Hashing /photos/c.jpg".to_string(),
+            bytes: 0,
+            total_bytes: 0,
+        });
+        let worker = thread::spawn(move || {
+            while !polite.token().is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            polite.update(&ProgressUpdate::Cancelled);
+        });
+        // Never notices
+        let stuck = jobs.register(scan_job());
+
+        let archive = dir.path().join("old.tar.zst");
+        let _writing = partial.track(&archive);
+        fs::write(&archive, b"half an archive").unwrap();
+        let existing = dir.path().join("kept.txt");
+        fs::write(&existing, b"not ours").unwrap();
+        let _not_tracked = partial.track(&existing);
+
+        let report = shutdown(&jobs, &partial, Duration::from_millis(200));
+        worker.join().unwrap();
+
+        assert_eq!(report.interrupted.len(), 2);
+        let first = &report.interrupted[0];
+        assert!(first.stopped);
+        assert_eq!((first.current, first.total), (3, 10));
+        assert_eq!(first.message, "Hashing /photos/c.jpg");
+        assert_eq!(report.interrupted[1].id, stuck.id());
+        assert!(!report.interrupted[1].stopped);
+
+        assert_eq!(report.removed, std::slice::from_ref(&archive));
+        assert!(!archive.exists());
+        assert!(existing.exists());
+        assert!(partial.list().is_empty());
+    }
+
+    #[test]
+    fn test_finished_work_unregisters_its_output() {
+        let dir = tempdir().unwrap();
+        let partial = PartialOutputs::new();
+        let output = dir.path().join("done.tar.zst");
+        {
+            let _writing = partial.track(&output);
+            assert_eq!(partial.list(), std::slice::from_ref(&output));
+            fs::write(&output, b"archive").unwrap();
+        }
+
+        let report = shutdown(&JobManager::new(), &partial, Duration::ZERO);
+        assert!(report.is_empty());
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn test_report_is_taken_once() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("last_shutdown.json");
+        let report = ShutdownReport {
+            at: 1_700_000_000,
+            interrupted: vec![InterruptedJob {
+                id: 4,
+                task_type: TaskType::CompressFiles(vec![PathBuf::from("/photos/a.png")]),
+                current: 0,
+                total: 1,
+                message: String::new(),
+                stopped: true,
+            }],
+            removed: vec![],
+        };
+
+        report.save(&path).unwrap();
+        let taken = ShutdownReport::take(&path).unwrap().unwrap();
+        assert_eq!(taken.at, report.at);
+        assert_eq!(taken.interrupted.len(), 1);
+        assert!(matches!(
+            &taken.interrupted[0].task_type,
+            TaskType::CompressFiles(paths) if paths == &[PathBuf::from("/photos/a.png")]
+        ));
+        assert!(ShutdownReport::take(&path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,312 @@
+//! Duplicates among files in any [`StorageBackend`], reading as little as
+//! possible: reads from object stores are slow and often billed.
+//!
+//! Files are compared by size first. Same-size files with the same strong
+//! ETag are taken to be the same without reading them, and when every ETag
+//! of a size is the MD5 of the content (S3 for files uploaded in one part)
+//! different ETags are taken to differ. Only when ETags cannot tell is a
+//! copy of each read: its first [`HEAD_BYTES`] as one ranged read, then,
+//! for files whose beginnings match, the rest in [`CHUNK_BYTES`] ranges.
+//! See [`crate::ServiceApi::find_duplicates_in_storage`].
+
+use crate::api::{DuplicateGroup, FileError};
+use anyhow::Result;
+use space_saver_core::{CancellationToken, Cancelled, StorageBackend, StoredFile};
+use space_saver_utils::Error;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Bytes read from the start of each candidate first
+pub const HEAD_BYTES: u64 = 64 * 1024;
+
+/// Bytes read per request when a whole file has to be hashed
+pub const CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Duplicate groups among `files`, listed from `storage`, and the candidates
+/// that could not be read
+pub(crate) async fn duplicate_groups(
+    storage: &dyn StorageBackend,
+    files: Vec<StoredFile>,
+    cancel: Option<&CancellationToken>,
+) -> Result<(Vec<DuplicateGroup>, Vec<FileError>)> {
+    // Empty files are all the same; they belong to the Empty Files feature
+    let mut by_size: HashMap<u64, Vec<StoredFile>> = HashMap::new();
+    for file in files.into_iter().filter(|f| f.info.size > 0) {
+        by_size.entry(file.info.size).or_default().push(file);
+    }
+
+    let mut groups = Vec::new();
+    let mut unreadable = Vec::new();
+    for same_size in by_size.into_values().filter(|files| files.len() > 1) {
+        let copies = by_etag(same_size);
+        if copies.len() == 1 || copies.iter().all(|c| c[0].content_digest().is_some()) {
+            groups.extend(copies.into_iter().filter_map(same_etag));
+            continue;
+        }
+        let (same, failed) = compare_contents(storage, copies, cancel).await?;
+        groups.extend(same);
+        unreadable.extend(failed);
+    }
+    Ok((groups, unreadable))
+}
+
+/// `files` (all the same size) in sets with the same strong ETag; files
+/// without one are each on their own
+fn by_etag(files: Vec<StoredFile>) -> Vec<Vec<StoredFile>> {
+    let mut sets: Vec<Vec<StoredFile>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for file in files {
+        match file.strong_etag().map(str::to_string) {
+            Some(etag) => match index.get(&etag) {
+                Some(&i) => sets[i].push(file),
+                None => {
+                    index.insert(etag, sets.len());
+                    sets.push(vec![file]);
+                }
+            },
+            None => sets.push(vec![file]),
+        }
+    }
+    sets
+}
+
+/// Merge the sets of same-size `copies` whose contents match, reading one
+/// file of each
+async fn compare_contents(
+    storage: &dyn StorageBackend,
+    copies: Vec<Vec<StoredFile>>,
+    cancel: Option<&CancellationToken>,
+) -> Result<(Vec<DuplicateGroup>, Vec<FileError>)> {
+    let size = copies[0][0].info.size;
+    let mut unreadable = Vec::new();
+
+    // Each set with the hash of its head so far
+    let mut by_head: HashMap<String, Vec<(Vec<StoredFile>, blake3::Hasher)>> = HashMap::new();
+    for set in copies {
+        let mut hasher = blake3::Hasher::new();
+        match hash_range(
+            storage,
+            &set[0],
+            0..size.min(HEAD_BYTES),
+            &mut hasher,
+            cancel,
+        )
+        .await
+        {
+            Ok(()) => {
+                let head = hasher.finalize().to_hex().to_string();
+                by_head.entry(head).or_default().push((set, hasher));
+            }
+            Err(e) => unreadable.extend(failed(&set, e)?),
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (head, mut sets) in by_head {
+        if sets.len() == 1 {
+            groups.extend(sets.pop().and_then(|(set, _)| same_etag(set)));
+            continue;
+        }
+        // The head is the whole file
+        if size <= HEAD_BYTES {
+            let files = sets.into_iter().flat_map(|(set, _)| set).collect();
+            groups.push(group(head, files));
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<StoredFile>> = HashMap::new();
+        for (set, mut hasher) in sets {
+            match hash_range(storage, &set[0], HEAD_BYTES..size, &mut hasher, cancel).await {
+                Ok(()) => {
+                    let hash = hasher.finalize().to_hex().to_string();
+                    by_hash.entry(hash).or_default().extend(set);
+                }
+                Err(e) => unreadable.extend(failed(&set, e)?),
+            }
+        }
+        groups.extend(
+            by_hash
+                .into_iter()
+                .filter(|(_, files)| files.len() > 1)
+                .map(|(hash, files)| group(hash, files)),
+        );
+    }
+    Ok((groups, unreadable))
+}
+
+/// Feed `file`'s bytes in `range` to `hasher`, read [`CHUNK_BYTES`] at a
+/// time
+async fn hash_range(
+    storage: &dyn StorageBackend,
+    file: &StoredFile,
+    range: Range<u64>,
+    hasher: &mut blake3::Hasher,
+    cancel: Option<&CancellationToken>,
+) -> Result<()> {
+    let mut offset = range.start;
+    while offset < range.end {
+        check(cancel)?;
+        let next = range.end.min(offset + CHUNK_BYTES);
+        let bytes = storage.read_range(&file.info.path, offset..next).await?;
+        if bytes.is_empty() {
+            anyhow::bail!(Error::FileOperation(format!(
+                "{} ended before its listed size",
+                file.info.path.display()
+            )));
+        }
+        offset += bytes.len() as u64;
+        hasher.update(&bytes);
+    }
+    Ok(())
+}
+
+fn check(cancel: Option<&CancellationToken>) -> Result<()> {
+    match cancel {
+        Some(token) => token.check(),
+        None => Ok(()),
+    }
+}
+
+/// The group of a set of files sharing an ETag, when there are several
+fn same_etag(set: Vec<StoredFile>) -> Option<DuplicateGroup> {
+    if set.len() < 2 {
+        return None;
+    }
+    let hash = format!("etag:{}", set[0].strong_etag().unwrap_or_default());
+    Some(group(hash, set))
+}
+
+/// Every file of `set`, as failed to read with `error`. Cancelling is not a
+/// read failure: it stops the whole search.
+fn failed(set: &[StoredFile], error: anyhow::Error) -> Result<Vec<FileError>> {
+    if error.is::<Cancelled>() {
+        return Err(error);
+    }
+    let error = FileError::new(&set[0].info.path, Error::from(error));
+    Ok(set
+        .iter()
+        .map(|file| FileError {
+            path: file.info.path.clone(),
+            ..error.clone()
+        })
+        .collect())
+}
+
+fn group(hash: String, files: Vec<StoredFile>) -> DuplicateGroup {
+    let files: Vec<_> = files.into_iter().map(|f| f.info).collect();
+    let total_size: u64 = files.iter().map(|f| f.size).sum();
+    DuplicateGroup {
+        hash,
+        count: files.len(),
+        wasted_space: total_size - files[0].size,
+        total_size,
+        files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use space_saver_core::FileInfo;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Files held in memory, counting the bytes read
+    #[derive(Default)]
+    struct Fake {
+        files: Vec<(PathBuf, Vec<u8>, Option<String>)>,
+        read: AtomicUsize,
+    }
+
+    impl Fake {
+        fn with(mut self, path: &str, content: &[u8], etag: Option<&str>) -> Self {
+            let etag = etag.map(|e| format!("\"{}\"", e));
+            self.files
+                .push((PathBuf::from(path), content.to_vec(), etag));
+            self
+        }
+
+        fn stored(&self) -> Vec<StoredFile> {
+            self.files
+                .iter()
+                .map(|(path, content, etag)| {
+                    StoredFile::new(FileInfo::new(path, content.len() as u64, 0))
+                        .with_etag(etag.clone())
+                })
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl StorageBackend for Fake {
+        fn describe(&self) -> String {
+            "fake".to_string()
+        }
+
+        async fn list(&self, _: &Path, _: bool) -> Result<Vec<StoredFile>> {
+            Ok(self.stored())
+        }
+
+        async fn read_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+            let (_, content, _) = self.files.iter().find(|(p, ..)| p == path).unwrap();
+            let end = (range.end as usize).min(content.len());
+            let bytes = content[range.start as usize..end].to_vec();
+            self.read.fetch_add(bytes.len(), Ordering::SeqCst);
+            Ok(bytes)
+        }
+    }
+
+    async fn groups(fake: &Fake) -> Vec<Vec<PathBuf>> {
+        let (groups, unreadable) = duplicate_groups(fake, fake.stored(), None).await.unwrap();
+        assert!(unreadable.is_empty());
+        let mut groups: Vec<Vec<PathBuf>> = groups
+            .into_iter()
+            .map(|g| {
+                let mut paths: Vec<_> = g.files.into_iter().map(|f| f.path).collect();
+                paths.sort();
+                paths
+            })
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    #[tokio::test]
+    async fn test_content_digests_need_no_reads() {
+        let md5 = |n: u8| Some(format!("{:032x}", n));
+        let fake = Fake::default()
+            .with("a", b"one", md5(1).as_deref())
+            .with("b", b"one", md5(1).as_deref())
+            .with("c", b"two", md5(2).as_deref());
+        assert_eq!(
+            groups(&fake).await,
+            [vec![PathBuf::from("a"), PathBuf::from("b")]]
+        );
+        assert_eq!(fake.read.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_other_etags_are_checked_by_reading_one_of_each() {
+        let big = vec![7u8; HEAD_BYTES as usize + 10];
+        let mut other = big.clone();
+        *other.last_mut().unwrap() = 8;
+        let fake = Fake::default()
+            .with("a", b"one", Some("1-a"))
+            .with("b", b"one", Some("1-a"))
+            .with("c", b"one", Some("2-b"))
+            .with("d", b"two", None)
+            .with("big1", &big, Some("3"))
+            .with("big2", &big, None)
+            .with("big3", &other, None);
+        assert_eq!(
+            groups(&fake).await,
+            [
+                vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")],
+                vec![PathBuf::from("big1"), PathBuf::from("big2")],
+            ]
+        );
+        // One read of a or b, c and d each; the big files' heads all match,
+        // so each was read to the end
+        assert_eq!(fake.read.load(Ordering::SeqCst), 3 * 3 + 3 * big.len());
+    }
+}
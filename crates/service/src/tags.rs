@@ -0,0 +1,136 @@
+//! Labels the user puts on files and duplicate groups.
+//!
+//! Tags such as "keep", "review later" or "client-x" record triage decisions
+//! in the database so they outlast the session. Files are tagged by absolute
+//! path and duplicate groups by content hash, so a group keeps its tags while
+//! its content stays the same. Tagged files can be picked out of any scan
+//! with a `tag:keep` filter expression.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use space_saver_core::TagIndex;
+use space_saver_db::{SqliteDatabase, TagRecord};
+use space_saver_utils::Error;
+use std::path::{Path, PathBuf};
+
+/// What a tag is put on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TagTarget {
+    File { path: String },
+    DuplicateGroup { hash: String },
+}
+
+impl TagTarget {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TagTarget::File { .. } => "file",
+            TagTarget::DuplicateGroup { .. } => "duplicate_group",
+        }
+    }
+
+    /// How the target is stored: the absolute path of a file, the hash of a
+    /// group
+    fn key(&self) -> Result<String> {
+        match self {
+            TagTarget::File { path } => Ok(std::path::absolute(Path::new(path))?
+                .to_string_lossy()
+                .to_string()),
+            TagTarget::DuplicateGroup { hash } if hash.trim().is_empty() => Err(
+                Error::InvalidInput("A duplicate group is tagged by its content hash".to_string())
+                    .into(),
+            ),
+            TagTarget::DuplicateGroup { hash } => Ok(hash.trim().to_string()),
+        }
+    }
+}
+
+/// Tags are compared case-insensitively and stored lowercase, trimmed
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err(Error::InvalidInput("A tag cannot be empty".to_string()).into());
+    }
+    Ok(tag)
+}
+
+/// Put `tag` on `target`; false when it already had it
+pub fn add_tag(target: &TagTarget, tag: &str, db: &SqliteDatabase) -> Result<bool> {
+    let record = TagRecord::new(target.kind(), target.key()?, normalize_tag(tag)?);
+    db.insert_tag(&record)
+}
+
+/// Take `tag` off `target`; false when it did not have it
+pub fn remove_tag(target: &TagTarget, tag: &str, db: &SqliteDatabase) -> Result<bool> {
+    db.delete_tag(target.kind(), &target.key()?, &normalize_tag(tag)?)
+}
+
+/// Tags of `target`, alphabetically
+pub fn tags_of(target: &TagTarget, db: &SqliteDatabase) -> Result<Vec<String>> {
+    db.get_tags(target.kind(), &target.key()?)
+}
+
+/// Every tagged file by tag, for `tag` filter expressions
+pub fn tag_index(db: &SqliteDatabase) -> Result<TagIndex> {
+    let mut index = TagIndex::new();
+    for record in db.get_tags_of_kind("file")? {
+        index
+            .entry(record.tag)
+            .or_default()
+            .insert(PathBuf::from(record.target));
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str) -> TagTarget {
+        TagTarget::File {
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tags_are_normalized_and_indexed_by_path() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        assert!(add_tag(&file("/photos/a.jpg"), " Keep ", &db).unwrap());
+        assert!(!add_tag(&file("/photos/a.jpg"), "keep", &db).unwrap());
+        let group = TagTarget::DuplicateGroup {
+            hash: "abc".to_string(),
+        };
+        assert!(add_tag(&group, "Review later", &db).unwrap());
+
+        assert_eq!(tags_of(&file("/photos/a.jpg"), &db).unwrap(), ["keep"]);
+        assert_eq!(tags_of(&group, &db).unwrap(), ["review later"]);
+        let index = tag_index(&db).unwrap();
+        assert!(index["keep"].contains(Path::new("/photos/a.jpg")));
+        assert!(!index.contains_key("review later"));
+
+        assert!(remove_tag(&file("/photos/a.jpg"), "KEEP", &db).unwrap());
+        assert!(tags_of(&file("/photos/a.jpg"), &db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_tags_and_hashes_are_refused() {
+        let db = SqliteDatabase::in_memory().unwrap();
+        let err = add_tag(&file("/photos/a.jpg"), "  ", &db).unwrap_err();
+        assert_eq!(
+            space_saver_utils::Error::from(err).code(),
+            space_saver_utils::ErrorCode::InvalidInput
+        );
+        let group = TagTarget::DuplicateGroup {
+            hash: String::new(),
+        };
+        assert!(add_tag(&group, "keep", &db).is_err());
+    }
+
+    #[test]
+    fn test_target_serializes_with_its_kind() {
+        assert_eq!(
+            serde_json::to_value(file("/photos/a.jpg")).unwrap(),
+            serde_json::json!({ "kind": "file", "path": "/photos/a.jpg" })
+        );
+    }
+}
@@ -8,12 +8,87 @@ use tokio::sync::mpsc;
 /// Task type enumeration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskType {
-    Scan(PathBuf),
-    FindDuplicates(PathBuf),
-    FindSimilarImages(PathBuf, f32), // path, threshold
+    Scan(Vec<PathBuf>),
+    FindDuplicates(Vec<PathBuf>),
+    FindSimilarImages(Vec<PathBuf>, f32), // paths, threshold
     CleanEmpty(PathBuf),
     CompressFiles(Vec<PathBuf>),
     DeleteFiles(Vec<PathBuf>),
+    /// Running cleanup rules over their folders
+    Cleanup(Vec<PathBuf>),
+    /// Pruning and vacuuming the app database
+    Maintenance,
+}
+
+/// The kind of a task, without its arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TaskKind {
+    Scan,
+    FindDuplicates,
+    FindSimilarImages,
+    CleanEmpty,
+    CompressFiles,
+    DeleteFiles,
+    Cleanup,
+    Maintenance,
+}
+
+impl TaskKind {
+    /// The name of this kind in the config, e.g. "duplicates"
+    pub fn name(self) -> &'static str {
+        match self {
+            TaskKind::Scan => "scan",
+            TaskKind::FindDuplicates => "duplicates",
+            TaskKind::FindSimilarImages => "similar",
+            TaskKind::CleanEmpty => "empty",
+            TaskKind::CompressFiles => "compress",
+            TaskKind::DeleteFiles => "delete",
+            TaskKind::Cleanup => "cleanup",
+            TaskKind::Maintenance => "maintenance",
+        }
+    }
+
+    /// What a task of this kind is called in messages, e.g. "Duplicate check"
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskKind::Scan => "Scan",
+            TaskKind::FindDuplicates => "Duplicate check",
+            TaskKind::FindSimilarImages => "Similar file check",
+            TaskKind::CleanEmpty => "Empty folder cleanup",
+            TaskKind::CompressFiles => "Compression",
+            TaskKind::DeleteFiles => "Deletion",
+            TaskKind::Cleanup => "Cleanup",
+            TaskKind::Maintenance => "Database maintenance",
+        }
+    }
+}
+
+impl TaskType {
+    pub fn kind(&self) -> TaskKind {
+        match self {
+            TaskType::Scan(_) => TaskKind::Scan,
+            TaskType::FindDuplicates(_) => TaskKind::FindDuplicates,
+            TaskType::FindSimilarImages(..) => TaskKind::FindSimilarImages,
+            TaskType::CleanEmpty(_) => TaskKind::CleanEmpty,
+            TaskType::CompressFiles(_) => TaskKind::CompressFiles,
+            TaskType::DeleteFiles(_) => TaskKind::DeleteFiles,
+            TaskType::Cleanup(_) => TaskKind::Cleanup,
+            TaskType::Maintenance => TaskKind::Maintenance,
+        }
+    }
+
+    /// How many items a job of this type starts with: its paths, or one
+    pub fn item_count(&self) -> usize {
+        match self {
+            TaskType::Scan(paths)
+            | TaskType::FindDuplicates(paths)
+            | TaskType::FindSimilarImages(paths, _)
+            | TaskType::CompressFiles(paths)
+            | TaskType::DeleteFiles(paths)
+            | TaskType::Cleanup(paths) => paths.len(),
+            TaskType::CleanEmpty(_) | TaskType::Maintenance => 1,
+        }
+    }
 }
 
 /// Task status
@@ -43,7 +118,7 @@ pub struct ScanTask {
 impl ScanTask {
     pub fn new(path: PathBuf) -> Self {
         Self {
-            task_type: TaskType::Scan(path),
+            task_type: TaskType::Scan(vec![path]),
             status: TaskStatus::Pending,
         }
     }
@@ -56,7 +131,7 @@ impl Task for ScanTask {
 
         self.status = TaskStatus::Running;
 
-        let path = match &self.task_type {
+        let paths = match &self.task_type {
             TaskType::Scan(p) => p.clone(),
             _ => unreachable!(),
         };
@@ -69,13 +144,18 @@ impl Task for ScanTask {
             .await;
 
         let scanner = DefaultFileScanner::new();
-        let files = scanner.scan(&path)?;
+        let mut files = Vec::new();
+        for path in &paths {
+            files.extend(scanner.scan(path)?);
+        }
 
         let _ = progress_tx
             .send(ProgressUpdate::Progress {
                 current: files.len(),
                 total: files.len(),
                 message: format!("Scanned {} files", files.len()),
+                bytes: 0,
+                total_bytes: 0,
             })
             .await;
 
@@ -108,7 +188,7 @@ pub struct FindDuplicatesTask {
 impl FindDuplicatesTask {
     pub fn new(path: PathBuf) -> Self {
         Self {
-            task_type: TaskType::FindDuplicates(path),
+            task_type: TaskType::FindDuplicates(vec![path]),
             status: TaskStatus::Pending,
         }
     }
@@ -122,7 +202,7 @@ impl Task for FindDuplicatesTask {
 
         self.status = TaskStatus::Running;
 
-        let path = match &self.task_type {
+        let paths = match &self.task_type {
             TaskType::FindDuplicates(p) => p.clone(),
             _ => unreachable!(),
         };
@@ -136,7 +216,10 @@ impl Task for FindDuplicatesTask {
 
         // Scan files
         let scanner = DefaultFileScanner::new();
-        let files = scanner.scan(&path)?;
+        let mut files = Vec::new();
+        for path in &paths {
+            files.extend(scanner.scan(path)?);
+        }
 
         // Hash files
         let hasher = FileHasher::new_blake3();
@@ -153,6 +236,8 @@ impl Task for FindDuplicatesTask {
                         current: idx,
                         total: files.len(),
                         message: format!("Hashing files... {}/{}", idx, files.len()),
+                        bytes: 0,
+                        total_bytes: 0,
                     })
                     .await;
             }
@@ -1,5 +1,7 @@
+use crate::error::Error;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -25,8 +27,9 @@ pub struct Config {
     /// Image similarity threshold
     pub image_similarity_threshold: f32,
 
-    /// Default delete mode for delete actions ("trash" or "permanent").
-    /// Consumed by the frontend as the default for delete dialogs.
+    /// Default delete mode for delete actions ("trash", "quarantine" or
+    /// "permanent"). Consumed by the frontend as the default for delete
+    /// dialogs.
     #[serde(default = "default_delete_mode")]
     pub default_delete_mode: String,
 
@@ -35,6 +38,88 @@ pub struct Config {
     #[serde(default = "default_compress_backup")]
     pub default_compress_backup: bool,
 
+    /// Refuse to compress without a backup when the plugin cannot verify its
+    /// output is readable. Plugins that can verify always do, whatever this
+    /// is set to.
+    #[serde(default = "default_require_output_verification")]
+    pub require_output_verification: bool,
+
+    /// Safe mode: refuse plugins that replace original files in place
+    #[serde(default)]
+    pub safe_mode: bool,
+
+    /// Copy EXIF/XMP/ICC metadata (photo dates, GPS, colour profiles) into
+    /// converted images
+    #[serde(default = "default_preserve_metadata")]
+    pub preserve_metadata: bool,
+
+    /// Minimum size reduction (percent of the original) for a compression
+    /// result to be kept; smaller savings leave the original untouched
+    #[serde(default = "default_min_savings_percent")]
+    pub min_savings_percent: f32,
+
+    /// Give up on a file after this many seconds of processing, killing any
+    /// external tool working on it. 0 disables the limit.
+    #[serde(default = "default_file_timeout_secs")]
+    pub file_timeout_secs: u64,
+
+    /// Niceness (0-19) for external tool processes; higher yields more CPU
+    /// to other programs. Unix only.
+    #[serde(default)]
+    pub tool_nice_level: Option<i32>,
+
+    /// Address-space cap in MiB for external tool processes. Unix only.
+    #[serde(default)]
+    pub tool_max_memory_mb: Option<u64>,
+
+    /// Cap in bytes per second on how fast background jobs (scans,
+    /// duplicate hashing, compression) read files. None reads at full speed.
+    #[serde(default)]
+    pub background_io_limit: Option<u64>,
+
+    /// Run background jobs, and the tools they start, at low IO priority so
+    /// they use the disk only when nothing else does (idle class on Linux,
+    /// background mode on Windows, throttled IO on macOS)
+    #[serde(default)]
+    pub background_io_low_priority: bool,
+
+    /// What happens to camera RAW files once converted to JPEG: "delete"
+    /// (handled like any compressed original) or "archive" (also kept in a
+    /// compressed ZIP next to the JPEG)
+    #[serde(default = "default_raw_policy")]
+    pub raw_policy: String,
+
+    /// Directories whose SVG (and optionally JSON/XML) files may be minified
+    /// in place. Empty turns minification off.
+    #[serde(default)]
+    pub minify_dirs: Vec<String>,
+
+    /// Text assets smaller than this many bytes are left alone
+    #[serde(default = "default_minify_min_size")]
+    pub minify_min_size: u64,
+
+    /// Also minify pretty-printed JSON and XML, not just SVG
+    #[serde(default)]
+    pub minify_data_files: bool,
+
+    /// Folders that delete, dedupe and in-place compression must never
+    /// touch, on top of the built-in system directories
+    #[serde(default)]
+    pub protected_paths: Vec<PathBuf>,
+
+    /// Where quarantined files are kept until restored or purged
+    #[serde(default = "default_quarantine_dir")]
+    pub quarantine_dir: PathBuf,
+
+    /// Days a quarantined file can be restored before it is purged
+    #[serde(default = "default_quarantine_days")]
+    pub quarantine_days: u32,
+
+    /// Explicit locations of external tools (e.g. "gif2webp", "ffmpeg"),
+    /// keyed by tool name. Tools not listed are looked up on PATH.
+    #[serde(default)]
+    pub tool_paths: BTreeMap<String, String>,
+
     /// Per-plugin compression quality (0-100), keyed by plugin name. The single
     /// source of truth for quality: the plugin manager is seeded from this at
     /// startup, and changes are written back here. Plugins absent from the map
@@ -42,18 +127,455 @@ pub struct Config {
     #[serde(default)]
     pub plugin_quality: BTreeMap<String, f32>,
 
+    /// Compression plugins turned off by name; they are never picked for a
+    /// file. Unknown names are ignored.
+    #[serde(default)]
+    pub disabled_plugins: Vec<String>,
+
+    /// Rules run by the `clean` command and the cleanup screen, in order
+    #[serde(default)]
+    pub cleanup_rules: Vec<CleanupRule>,
+
+    /// Tasks the daemon runs on a timer
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+
+    /// Folders the daemon watches, acting on files as they appear or change
+    #[serde(default)]
+    pub watch_rules: Vec<WatchRule>,
+
+    /// Commands run before and after deletions, compressions and cleanups
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+
+    /// Desktop notifications when background tasks finish
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+
+    /// Log file of the daemon
+    #[serde(default = "default_daemon_log")]
+    pub daemon_log: PathBuf,
+
+    /// Local socket the daemon answers `status` and `stop` on
+    #[serde(default = "default_daemon_socket")]
+    pub daemon_socket: PathBuf,
+
     /// Scan settings
     pub scan: ScanConfig,
 }
 
+/// Actions a cleanup rule can take on the files it matches
+pub const CLEANUP_ACTIONS: [&str; 4] = ["delete", "trash", "archive", "compress"];
+
+/// An automatic cleanup rule: files under `paths` matching `filter` and
+/// untouched for `older_than_days` get `action`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CleanupRule {
+    /// Unique name, used to pick rules to run
+    pub name: String,
+
+    /// Folders the rule applies to
+    pub paths: Vec<PathBuf>,
+
+    /// Filter expression files must match, e.g. `ext in (log,tmp)`; no
+    /// filter matches every file
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Only files not modified for this many days
+    #[serde(default)]
+    pub older_than_days: Option<u32>,
+
+    /// "delete" (permanent), "trash", "archive" or "compress" (in place)
+    pub action: String,
+
+    /// Where "archive" stores its archives
+    #[serde(default)]
+    pub archive_dir: Option<PathBuf>,
+
+    /// Disabled rules are kept but not run
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+impl CleanupRule {
+    /// Reject rules that cannot run: no name or folders, an unknown action,
+    /// or "archive" without a destination
+    pub fn validate(&self) -> Result<()> {
+        validate_rule(
+            "Cleanup rule",
+            &self.name,
+            &self.paths,
+            &self.action,
+            self.archive_dir.as_ref(),
+        )
+    }
+}
+
+/// Checks shared by cleanup and watch rules; `kind` names the rule in errors
+fn validate_rule(
+    kind: &str,
+    name: &str,
+    paths: &[PathBuf],
+    action: &str,
+    archive_dir: Option<&PathBuf>,
+) -> Result<()> {
+    if name.trim().is_empty() {
+        anyhow::bail!("{}s need a name", kind);
+    }
+    if paths.is_empty() {
+        anyhow::bail!("{} '{}' has no folders", kind, name);
+    }
+    if !CLEANUP_ACTIONS.contains(&action) {
+        anyhow::bail!(
+            "{} '{}': action must be 'delete', 'trash', 'archive' or 'compress', got '{}'",
+            kind,
+            name,
+            action
+        );
+    }
+    if action == "archive" && archive_dir.is_none() {
+        anyhow::bail!("{} '{}' archives files but has no archive_dir", kind, name);
+    }
+    Ok(())
+}
+
+/// Tasks a schedule can run
+pub const SCHEDULE_TASKS: [&str; 3] = ["scan", "cleanup", "maintenance"];
+
+/// A task the daemon runs every `every_minutes`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Unique name, shown in the daemon's status
+    pub name: String,
+
+    /// "scan" (recorded in the storage history), "cleanup" or "maintenance"
+    pub task: String,
+
+    pub every_minutes: u32,
+
+    /// Folders a "scan" covers
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+
+    /// Cleanup rules a "cleanup" runs; none runs them all
+    #[serde(default)]
+    pub rules: Vec<String>,
+
+    /// Disabled schedules are kept but not run
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+impl Schedule {
+    /// Reject schedules that cannot run: no name, an unknown task, no
+    /// interval, or a scan of no folders
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Schedules need a name");
+        }
+        if !SCHEDULE_TASKS.contains(&self.task.as_str()) {
+            anyhow::bail!(
+                "Schedule '{}': task must be 'scan', 'cleanup' or 'maintenance', got '{}'",
+                self.name,
+                self.task
+            );
+        }
+        if self.every_minutes == 0 {
+            anyhow::bail!("Schedule '{}' must run at least a minute apart", self.name);
+        }
+        if self.task == "scan" && self.paths.is_empty() {
+            anyhow::bail!("Schedule '{}' scans but has no folders", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Files appearing or changing under `paths` that match `filter` get
+/// `action` once they have stopped changing for `settle_secs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchRule {
+    /// Unique name, shown in the daemon's status
+    pub name: String,
+
+    /// Folders watched, with everything under them
+    pub paths: Vec<PathBuf>,
+
+    /// Filter expression files must match; no filter matches every file
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// "delete" (permanent), "trash", "archive" or "compress" (in place)
+    pub action: String,
+
+    /// Where "archive" stores its archives
+    #[serde(default)]
+    pub archive_dir: Option<PathBuf>,
+
+    /// How long a file must stay unchanged before it is acted on, so files
+    /// still being written or downloaded are left alone
+    #[serde(default = "default_settle_secs")]
+    pub settle_secs: u64,
+
+    /// Disabled rules are kept but not run
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_settle_secs() -> u64 {
+    10
+}
+
+impl WatchRule {
+    /// Reject rules that cannot run, as for [`CleanupRule::validate`]
+    pub fn validate(&self) -> Result<()> {
+        validate_rule(
+            "Watch rule",
+            &self.name,
+            &self.paths,
+            &self.action,
+            self.archive_dir.as_ref(),
+        )
+    }
+
+    /// The cleanup rule applying this rule's action to the files it matches
+    pub fn to_cleanup_rule(&self) -> CleanupRule {
+        CleanupRule {
+            name: self.name.clone(),
+            paths: self.paths.clone(),
+            filter: self.filter.clone(),
+            older_than_days: None,
+            action: self.action.clone(),
+            archive_dir: self.archive_dir.clone(),
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// Operations hooks run around
+pub const HOOK_OPERATIONS: [&str; 3] = ["delete", "compress", "cleanup"];
+
+/// What a failing hook does: stop the operation (before hooks only), log a
+/// warning, or nothing
+pub const HOOK_FAILURE_POLICIES: [&str; 3] = ["abort", "warn", "ignore"];
+
+/// A shell command run before or after an operation, e.g. to pause a backup
+/// agent while files are deleted or to call a webhook once they are
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    /// Unique name, passed to the command and used in messages
+    pub name: String,
+
+    /// "delete" (also trash and quarantine), "compress" or "cleanup"
+    pub operation: String,
+
+    /// "before" or "after"
+    pub when: String,
+
+    /// Run by the shell (`sh -c`, `cmd /C` on Windows). `{operation}`,
+    /// `{when}`, `{count}` and, after, `{status}`, `{succeeded}`, `{failed}`
+    /// and `{freed}` are replaced; the same and the paths are also in
+    /// `SPACE_SAVER_*` environment variables.
+    pub command: String,
+
+    /// The command is killed, and counts as failed, after this long
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// "abort" (the operation does not run; before hooks only), "warn" or
+    /// "ignore"
+    #[serde(default = "default_hook_on_failure")]
+    pub on_failure: String,
+
+    /// Disabled hooks are kept but not run
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    60
+}
+
+fn default_hook_on_failure() -> String {
+    "warn".to_string()
+}
+
+impl Hook {
+    /// Reject hooks that cannot run: no name or command, an unknown
+    /// operation, time or failure policy, no timeout, or an after hook
+    /// that would abort what already happened
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Hooks need a name");
+        }
+        if self.command.trim().is_empty() {
+            anyhow::bail!("Hook '{}' has no command", self.name);
+        }
+        if !HOOK_OPERATIONS.contains(&self.operation.as_str()) {
+            anyhow::bail!(
+                "Hook '{}': operation must be 'delete', 'compress' or 'cleanup', got '{}'",
+                self.name,
+                self.operation
+            );
+        }
+        if self.when != "before" && self.when != "after" {
+            anyhow::bail!(
+                "Hook '{}': when must be 'before' or 'after', got '{}'",
+                self.name,
+                self.when
+            );
+        }
+        if !HOOK_FAILURE_POLICIES.contains(&self.on_failure.as_str()) {
+            anyhow::bail!(
+                "Hook '{}': on_failure must be 'abort', 'warn' or 'ignore', got '{}'",
+                self.name,
+                self.on_failure
+            );
+        }
+        if self.on_failure == "abort" && self.when == "after" {
+            anyhow::bail!(
+                "Hook '{}' runs after the operation, so it cannot abort it",
+                self.name
+            );
+        }
+        if self.timeout_secs == 0 {
+            anyhow::bail!("Hook '{}' needs a timeout of at least a second", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Kinds of task that notify when they finish
+pub const NOTIFICATION_TASKS: [&str; 8] = [
+    "scan",
+    "duplicates",
+    "similar",
+    "empty",
+    "compress",
+    "delete",
+    "cleanup",
+    "maintenance",
+];
+
+/// When finished tasks show a desktop notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Off turns every notification off
+    pub enabled: bool,
+
+    /// Notify when a task succeeds
+    pub on_success: bool,
+
+    /// Notify when a task fails
+    pub on_failure: bool,
+
+    /// Tasks finishing quicker than this are not worth a notification;
+    /// failures always are
+    pub min_duration_secs: u64,
+
+    /// Kinds of task (see [`NOTIFICATION_TASKS`]) turned on or off; kinds
+    /// not listed notify
+    pub tasks: BTreeMap<String, bool>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_success: true,
+            on_failure: true,
+            min_duration_secs: 10,
+            tasks: BTreeMap::new(),
+        }
+    }
+}
+
+impl NotificationConfig {
+    /// Whether a `task` that succeeded, or not, after `elapsed_secs` is
+    /// worth a notification
+    pub fn wants(&self, task: &str, succeeded: bool, elapsed_secs: u64) -> bool {
+        self.enabled
+            && self.tasks.get(task).copied().unwrap_or(true)
+            && if succeeded {
+                self.on_success && elapsed_secs >= self.min_duration_secs
+            } else {
+                self.on_failure
+            }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for task in self.tasks.keys() {
+            if !NOTIFICATION_TASKS.contains(&task.as_str()) {
+                anyhow::bail!(
+                    "notifications.tasks: unknown task '{}', expected one of {}",
+                    task,
+                    NOTIFICATION_TASKS.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
 fn default_delete_mode() -> String {
     "trash".to_string()
 }
 
+fn data_dir() -> PathBuf {
+    directories::ProjectDirs::from("com", "spacesaver", "Space-Saver")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn default_quarantine_dir() -> PathBuf {
+    data_dir().join("quarantine")
+}
+
+fn default_daemon_log() -> PathBuf {
+    data_dir().join("daemon.log")
+}
+
+fn default_daemon_socket() -> PathBuf {
+    data_dir().join("daemon.sock")
+}
+
+fn default_quarantine_days() -> u32 {
+    30
+}
+
 fn default_compress_backup() -> bool {
     true
 }
 
+fn default_require_output_verification() -> bool {
+    true
+}
+
+fn default_preserve_metadata() -> bool {
+    true
+}
+
+fn default_min_savings_percent() -> f32 {
+    5.0
+}
+
+fn default_raw_policy() -> String {
+    "delete".to_string()
+}
+
+fn default_minify_min_size() -> u64 {
+    4096
+}
+
+fn default_file_timeout_secs() -> u64 {
+    300
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanConfig {
     /// Follow symbolic links
@@ -67,6 +589,46 @@ pub struct ScanConfig {
 
     /// File patterns to exclude
     pub exclude_patterns: Vec<String>,
+
+    /// Read files on network shares (SMB, NFS, ...) gently: with
+    /// `network_parallelism` threads and retrying reads that fail for a
+    /// moment
+    #[serde(default = "default_network_aware")]
+    pub network_aware: bool,
+
+    /// Files hashed at once on each network share
+    #[serde(default = "default_network_parallelism")]
+    pub network_parallelism: usize,
+
+    /// Attempts after a read on a network share times out or loses its
+    /// connection
+    #[serde(default = "default_network_retries")]
+    pub network_retries: u32,
+
+    /// Wait before the first retry (ms), doubled for each further one
+    #[serde(default = "default_network_retry_backoff_ms")]
+    pub network_retry_backoff_ms: u64,
+
+    /// Read-rate caps (bytes per second) by the mount point of a share,
+    /// e.g. `"/mnt/nas" = 10485760`
+    #[serde(default)]
+    pub share_bandwidth: BTreeMap<String, u64>,
+}
+
+fn default_network_aware() -> bool {
+    true
+}
+
+fn default_network_parallelism() -> usize {
+    2
+}
+
+fn default_network_retries() -> u32 {
+    3
+}
+
+fn default_network_retry_backoff_ms() -> u64 {
+    500
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,9 +643,7 @@ impl Default for Config {
             .map(|dirs| dirs.config_dir().to_path_buf())
             .unwrap_or_else(|| PathBuf::from("."));
 
-        let data_dir = directories::ProjectDirs::from("com", "spacesaver", "Space-Saver")
-            .map(|dirs| dirs.data_dir().to_path_buf())
-            .unwrap_or_else(|| PathBuf::from("."));
+        let data_dir = data_dir();
 
         Self {
             database_path: data_dir.join("spacesaver.db"),
@@ -94,7 +654,32 @@ impl Default for Config {
             image_similarity_threshold: 0.9,
             default_delete_mode: default_delete_mode(),
             default_compress_backup: default_compress_backup(),
+            require_output_verification: default_require_output_verification(),
+            safe_mode: false,
+            preserve_metadata: default_preserve_metadata(),
+            min_savings_percent: default_min_savings_percent(),
+            file_timeout_secs: default_file_timeout_secs(),
+            tool_nice_level: None,
+            tool_max_memory_mb: None,
+            background_io_limit: None,
+            background_io_low_priority: false,
+            raw_policy: default_raw_policy(),
+            minify_dirs: Vec::new(),
+            minify_min_size: default_minify_min_size(),
+            minify_data_files: false,
+            tool_paths: BTreeMap::new(),
             plugin_quality: BTreeMap::new(),
+            disabled_plugins: Vec::new(),
+            protected_paths: Vec::new(),
+            quarantine_dir: default_quarantine_dir(),
+            quarantine_days: default_quarantine_days(),
+            cleanup_rules: Vec::new(),
+            schedules: Vec::new(),
+            watch_rules: Vec::new(),
+            hooks: Vec::new(),
+            notifications: NotificationConfig::default(),
+            daemon_log: default_daemon_log(),
+            daemon_socket: default_daemon_socket(),
             scan: ScanConfig::default(),
         }
     }
@@ -112,6 +697,11 @@ impl Default for ScanConfig {
                 ".git/*".to_string(),
                 "node_modules/*".to_string(),
             ],
+            network_aware: default_network_aware(),
+            network_parallelism: default_network_parallelism(),
+            network_retries: default_network_retries(),
+            network_retry_backoff_ms: default_network_retry_backoff_ms(),
+            share_bandwidth: BTreeMap::new(),
         }
     }
 }
@@ -177,12 +767,35 @@ impl Config {
                 self.log_level
             );
         }
-        if self.default_delete_mode != "trash" && self.default_delete_mode != "permanent" {
+        if !["trash", "quarantine", "permanent"].contains(&self.default_delete_mode.as_str()) {
             anyhow::bail!(
-                "default_delete_mode must be 'trash' or 'permanent', got '{}'",
+                "default_delete_mode must be 'trash', 'quarantine' or 'permanent', got '{}'",
                 self.default_delete_mode
             );
         }
+        if !(0.0..=100.0).contains(&self.min_savings_percent) {
+            anyhow::bail!(
+                "min_savings_percent must be between 0 and 100, got {}",
+                self.min_savings_percent
+            );
+        }
+        if self.raw_policy != "delete" && self.raw_policy != "archive" {
+            anyhow::bail!(
+                "raw_policy must be 'delete' or 'archive', got '{}'",
+                self.raw_policy
+            );
+        }
+        if let Some(nice) = self.tool_nice_level {
+            if !(0..=19).contains(&nice) {
+                anyhow::bail!("tool_nice_level must be between 0 and 19, got {}", nice);
+            }
+        }
+        if self.tool_max_memory_mb == Some(0) {
+            anyhow::bail!("tool_max_memory_mb must be at least 1");
+        }
+        if self.background_io_limit == Some(0) {
+            anyhow::bail!("background_io_limit must be at least 1 byte per second");
+        }
         for (name, quality) in &self.plugin_quality {
             if !(0.0..=100.0).contains(quality) {
                 anyhow::bail!(
@@ -192,9 +805,168 @@ impl Config {
                 );
             }
         }
+        self.notifications.validate()?;
+        let mut names = std::collections::HashSet::new();
+        for rule in &self.cleanup_rules {
+            rule.validate()?;
+            if !names.insert(rule.name.as_str()) {
+                anyhow::bail!(
+                    "Cleanup rule names must be unique, '{}' is used twice",
+                    rule.name
+                );
+            }
+        }
+        let mut names = std::collections::HashSet::new();
+        for schedule in &self.schedules {
+            schedule.validate()?;
+            if !names.insert(schedule.name.as_str()) {
+                anyhow::bail!(
+                    "Schedule names must be unique, '{}' is used twice",
+                    schedule.name
+                );
+            }
+            for rule in &schedule.rules {
+                if !self.cleanup_rules.iter().any(|r| &r.name == rule) {
+                    anyhow::bail!(
+                        "Schedule '{}' runs an unknown cleanup rule: {}",
+                        schedule.name,
+                        rule
+                    );
+                }
+            }
+        }
+        let mut names = std::collections::HashSet::new();
+        for rule in &self.watch_rules {
+            rule.validate()?;
+            if !names.insert(rule.name.as_str()) {
+                anyhow::bail!(
+                    "Watch rule names must be unique, '{}' is used twice",
+                    rule.name
+                );
+            }
+        }
+        let mut names = std::collections::HashSet::new();
+        for hook in &self.hooks {
+            hook.validate()?;
+            if !names.insert(hook.name.as_str()) {
+                anyhow::bail!("Hook names must be unique, '{}' is used twice", hook.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The setting at dotted `key`, e.g. `log_level`, `scan.max_depth`,
+    /// `tool_paths.ffmpeg` or `cleanup_rules.0.enabled`; null for an unset
+    /// optional one
+    pub fn get_key(&self, key: &str) -> Result<Value> {
+        let settings = self.to_settings()?;
+        setting(&settings, key)
+            .cloned()
+            .ok_or_else(|| unknown_setting(key).into())
+    }
+
+    /// Set dotted `key` to `value`, written as in TOML (`30`, `true`,
+    /// `["a", "b"]`); anything else is a string, and `null` clears an
+    /// optional setting. Refused when the result does not validate.
+    pub fn set_key(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut settings = self.to_settings()?;
+        let (parent, name) = parent_of(&mut settings, key)?;
+        let current = child(parent, name).cloned();
+        let value = parse_setting(value, current.as_ref());
+        match parent {
+            Value::Object(map) => {
+                map.insert(name.to_string(), value);
+            }
+            Value::Array(items) => {
+                match name.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                    Some(item) => *item = value,
+                    None => return Err(unknown_setting(key).into()),
+                }
+            }
+            _ => return Err(unknown_setting(key).into()),
+        }
+        let config = Self::from_settings(settings, key)?;
+        // Fields serde does not know are dropped rather than rejected
+        config.get_key(key)?;
+        *self = config;
         Ok(())
     }
 
+    /// Apply `changes`, a partial config as JSON: objects merge into the
+    /// settings they name, other values (lists included) replace them, and
+    /// `null` clears an optional setting. Refused as a whole when a setting
+    /// is unknown or the result does not validate.
+    pub fn update(&mut self, changes: &Value) -> Result<()> {
+        if !changes.is_object() {
+            return Err(Error::InvalidInput(
+                "Config changes must be an object of settings".to_string(),
+            )
+            .into());
+        }
+        let mut changed = Vec::new();
+        leaf_settings(changes, "", &mut changed);
+        let mut settings = self.to_settings()?;
+        for (key, value) in &changed {
+            match parent_of(&mut settings, key)? {
+                (Value::Object(map), name) => {
+                    map.insert(name.to_string(), value.clone());
+                }
+                _ => return Err(unknown_setting(key).into()),
+            }
+        }
+        let keys: Vec<&str> = changed.iter().map(|(key, _)| key.as_str()).collect();
+        let config = Self::from_settings(settings, &keys.join(", "))?;
+        // Fields serde does not know are dropped rather than rejected
+        for key in keys {
+            config.get_key(key)?;
+        }
+        *self = config;
+        Ok(())
+    }
+
+    /// Put dotted `key` back to its default; an entry of a table such as
+    /// `tool_paths` is removed
+    pub fn reset_key(&mut self, key: &str) -> Result<()> {
+        let defaults = Config::default().to_settings()?;
+        let mut settings = self.to_settings()?;
+        let (parent, name) = parent_of(&mut settings, key)?;
+        match (setting(&defaults, key), parent) {
+            (Some(default), Value::Object(map)) => {
+                map.insert(name.to_string(), default.clone());
+            }
+            (Some(default), Value::Array(items)) => {
+                if let Some(item) = name.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                    *item = default.clone();
+                }
+            }
+            (None, Value::Object(map)) => {
+                if map.remove(name).is_none() {
+                    return Err(unknown_setting(key).into());
+                }
+            }
+            _ => return Err(unknown_setting(key).into()),
+        }
+        *self = Self::from_settings(settings, key)?;
+        Ok(())
+    }
+
+    /// The config as a JSON tree. Through text, so `f32` settings read
+    /// `0.9` rather than the `f64` nearest to it.
+    fn to_settings(&self) -> Result<Value> {
+        Ok(serde_json::from_str(&serde_json::to_string(self)?)?)
+    }
+
+    /// The config edited `settings` describe, if they parse and validate;
+    /// `key` is the setting that was changed
+    fn from_settings(settings: Value, key: &str) -> Result<Self> {
+        let config: Config = serde_json::from_value(settings)
+            .map_err(|e| Error::Config(format!("{}: {}", key, e)))?;
+        config
+            .validate()
+            .map_err(|e| Error::Config(format!("{:#}", e)))?;
+        Ok(config)
+    }
+
     /// Ensure directories exist
     pub fn ensure_directories(&self) -> Result<()> {
         if let Some(parent) = self.database_path.parent() {
@@ -205,6 +977,76 @@ impl Config {
     }
 }
 
+fn unknown_setting(key: &str) -> Error {
+    Error::InvalidInput(format!("Unknown setting: {}", key))
+}
+
+fn child<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map.get(name),
+        Value::Array(items) => items.get(name.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+/// The values of `changes` that are not objects, under their dotted keys
+fn leaf_settings(changes: &Value, prefix: &str, leaves: &mut Vec<(String, Value)>) {
+    match changes {
+        Value::Object(map) => {
+            for (name, value) in map {
+                let key = match prefix {
+                    "" => name.clone(),
+                    prefix => format!("{}.{}", prefix, name),
+                };
+                leaf_settings(value, &key, leaves);
+            }
+        }
+        value => leaves.push((prefix.to_string(), value.clone())),
+    }
+}
+
+fn setting<'a>(settings: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(settings, child)
+}
+
+/// The table or list holding dotted `key`, and the last part of the key
+fn parent_of<'a, 'k>(settings: &'a mut Value, key: &'k str) -> Result<(&'a mut Value, &'k str)> {
+    let (path, name) = match key.rsplit_once('.') {
+        Some((path, name)) => (Some(path), name),
+        None => (None, key),
+    };
+    let mut parent = settings;
+    for part in path.into_iter().flat_map(|p| p.split('.')) {
+        parent = match parent {
+            Value::Object(map) => map.get_mut(part),
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| unknown_setting(key))?;
+    }
+    if name.is_empty() {
+        return Err(unknown_setting(key).into());
+    }
+    Ok((parent, name))
+}
+
+/// `raw` as a setting value: a TOML value when it is one, else a string.
+/// Strings stay strings, so a numeric-looking path is not turned into a
+/// number.
+fn parse_setting(raw: &str, current: Option<&Value>) -> Value {
+    if raw.trim() == "null" {
+        return Value::Null;
+    }
+    if let Some(Value::String(_)) = current {
+        return Value::String(raw.to_string());
+    }
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .and_then(|value| serde_json::to_value(value).ok())
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,6 +1076,9 @@ mod tests {
         let scan = ScanConfig::default();
         assert!(!scan.follow_links);
         assert!(!scan.exclude_patterns.is_empty());
+        assert!(scan.network_aware);
+        assert_eq!(scan.network_parallelism, 2);
+        assert!(scan.share_bandwidth.is_empty());
     }
 
     #[test]
@@ -241,7 +1086,37 @@ mod tests {
         let config = Config::default();
         assert_eq!(config.default_delete_mode, "trash");
         assert!(config.default_compress_backup);
+        assert!(config.require_output_verification);
+        assert!(!config.safe_mode);
+        assert!(config.preserve_metadata);
+        assert!(config.tool_paths.is_empty());
+        assert_eq!(config.min_savings_percent, 5.0);
+        assert_eq!(config.file_timeout_secs, 300);
+        assert_eq!(config.tool_nice_level, None);
+        assert_eq!(config.tool_max_memory_mb, None);
+        assert_eq!(config.raw_policy, "delete");
+        assert!(config.minify_dirs.is_empty());
+        assert_eq!(config.minify_min_size, 4096);
+        assert!(!config.minify_data_files);
         assert!(config.plugin_quality.is_empty());
+        assert!(config.protected_paths.is_empty());
+        assert!(config.quarantine_dir.ends_with("quarantine"));
+        assert_eq!(config.quarantine_days, 30);
+    }
+
+    #[test]
+    fn test_protected_paths_roundtrip() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+
+        let config = Config {
+            protected_paths: vec![PathBuf::from("/home/me/Photos")],
+            ..Default::default()
+        };
+        config.save(&config_path).unwrap();
+
+        let loaded = Config::load(&config_path).unwrap();
+        assert_eq!(loaded.protected_paths, config.protected_paths);
     }
 
     #[test]
@@ -268,6 +1143,272 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_rejects_out_of_range_min_savings() {
+        let mut config = Config {
+            min_savings_percent: 120.0,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        config.min_savings_percent = -1.0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_raw_policy() {
+        let config = Config {
+            raw_policy: "shred".to_string(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let archive = Config {
+            raw_policy: "archive".to_string(),
+            ..Config::default()
+        };
+        assert!(archive.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_tool_limits() {
+        let nice = Config {
+            tool_nice_level: Some(10),
+            tool_max_memory_mb: Some(512),
+            ..Config::default()
+        };
+        assert!(nice.validate().is_ok());
+        let too_nice = Config {
+            tool_nice_level: Some(20),
+            ..Config::default()
+        };
+        assert!(too_nice.validate().is_err());
+        let negative = Config {
+            tool_nice_level: Some(-5),
+            ..Config::default()
+        };
+        assert!(negative.validate().is_err());
+        let no_memory = Config {
+            tool_max_memory_mb: Some(0),
+            ..Config::default()
+        };
+        assert!(no_memory.validate().is_err());
+        let stalled = Config {
+            background_io_limit: Some(0),
+            ..Config::default()
+        };
+        assert!(stalled.validate().is_err());
+    }
+
+    #[test]
+    fn test_cleanup_rules_roundtrip_and_validate() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let rule = CleanupRule {
+            name: "old logs".to_string(),
+            paths: vec![PathBuf::from("/var/tmp/logs")],
+            filter: Some("ext in (log)".to_string()),
+            older_than_days: Some(30),
+            action: "trash".to_string(),
+            archive_dir: None,
+            enabled: true,
+        };
+        let config = Config {
+            cleanup_rules: vec![rule.clone()],
+            ..Default::default()
+        };
+        config.save(&config_path).unwrap();
+        let loaded = Config::load(&config_path).unwrap();
+        assert_eq!(loaded.cleanup_rules, config.cleanup_rules);
+        assert!(loaded.validate().is_ok());
+
+        let invalid = |rule: CleanupRule| Config {
+            cleanup_rules: vec![rule],
+            ..Default::default()
+        };
+        let err = invalid(CleanupRule {
+            action: "shred".to_string(),
+            ..rule.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("action must be"));
+        let err = invalid(CleanupRule {
+            action: "archive".to_string(),
+            ..rule.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("archive_dir"));
+        let twice = Config {
+            cleanup_rules: vec![rule.clone(), rule],
+            ..Default::default()
+        };
+        assert!(twice.validate().unwrap_err().to_string().contains("unique"));
+    }
+
+    #[test]
+    fn test_schedules_and_watch_rules_roundtrip_and_validate() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let schedule = Schedule {
+            name: "nightly scan".to_string(),
+            task: "scan".to_string(),
+            every_minutes: 1440,
+            paths: vec![PathBuf::from("/srv/data")],
+            rules: Vec::new(),
+            enabled: true,
+        };
+        let watch = WatchRule {
+            name: "downloads".to_string(),
+            paths: vec![PathBuf::from("/srv/downloads")],
+            filter: Some("ext in (png)".to_string()),
+            action: "compress".to_string(),
+            archive_dir: None,
+            settle_secs: 30,
+            enabled: true,
+        };
+        let config = Config {
+            schedules: vec![schedule.clone()],
+            watch_rules: vec![watch.clone()],
+            ..Default::default()
+        };
+        config.save(&config_path).unwrap();
+        let loaded = Config::load(&config_path).unwrap();
+        assert_eq!(loaded.schedules, config.schedules);
+        assert_eq!(loaded.watch_rules, config.watch_rules);
+        assert!(loaded.validate().is_ok());
+        assert_eq!(watch.to_cleanup_rule().action, "compress");
+
+        let with = |schedule: Schedule| Config {
+            schedules: vec![schedule],
+            ..Default::default()
+        };
+        let err = with(Schedule {
+            every_minutes: 0,
+            ..schedule.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("a minute apart"));
+        let err = with(Schedule {
+            paths: Vec::new(),
+            ..schedule.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("no folders"));
+        let err = with(Schedule {
+            task: "cleanup".to_string(),
+            rules: vec!["missing".to_string()],
+            ..schedule
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown cleanup rule"));
+
+        let err = Config {
+            watch_rules: vec![WatchRule {
+                action: "shred".to_string(),
+                ..watch.clone()
+            }],
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().starts_with("Watch rule 'downloads'"));
+        let twice = Config {
+            watch_rules: vec![watch.clone(), watch],
+            ..Default::default()
+        };
+        assert!(twice.validate().unwrap_err().to_string().contains("unique"));
+    }
+
+    #[test]
+    fn test_hooks_round_trip_and_validate() {
+        let hook: Hook = toml::from_str(
+            r#"
+name = "pause-backup"
+operation = "delete"
+when = "before"
+command = "backup-agent pause"
+on_failure = "abort"
+"#,
+        )
+        .unwrap();
+        assert_eq!(hook.timeout_secs, 60);
+        assert!(hook.enabled);
+
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let config = Config {
+            hooks: vec![hook.clone()],
+            ..Default::default()
+        };
+        config.save(&config_path).unwrap();
+        let loaded = Config::load(&config_path).unwrap();
+        assert_eq!(loaded.hooks, config.hooks);
+        assert!(loaded.validate().is_ok());
+
+        let with = |hook: Hook| Config {
+            hooks: vec![hook],
+            ..Default::default()
+        };
+        let err = with(Hook {
+            when: "after".to_string(),
+            ..hook.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("cannot abort"));
+        let err = with(Hook {
+            operation: "scan".to_string(),
+            ..hook.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("operation must be"));
+        let twice = Config {
+            hooks: vec![hook.clone(), hook],
+            ..Default::default()
+        };
+        assert!(twice.validate().unwrap_err().to_string().contains("unique"));
+    }
+
+    #[test]
+    fn test_notifications_by_task_and_outcome() {
+        let notifications: NotificationConfig = toml::from_str(
+            r#"
+min_duration_secs = 60
+[tasks]
+maintenance = false
+"#,
+        )
+        .unwrap();
+        assert!(notifications.enabled && notifications.on_failure);
+
+        assert!(notifications.wants("scan", true, 90));
+        // Quick successes are not worth it, quick failures are
+        assert!(!notifications.wants("scan", true, 5));
+        assert!(notifications.wants("scan", false, 5));
+        assert!(!notifications.wants("maintenance", false, 90));
+        let off = NotificationConfig {
+            enabled: false,
+            ..notifications.clone()
+        };
+        assert!(!off.wants("scan", false, 90));
+
+        let mut config = Config {
+            notifications,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        config
+            .notifications
+            .tasks
+            .insert("backup".to_string(), true);
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("unknown task 'backup'"));
+    }
+
     #[test]
     fn test_validate_accepts_default() {
         assert!(Config::default().validate().is_ok());
@@ -319,6 +1460,15 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_accepts_quarantine_delete_mode() {
+        let config = Config {
+            default_delete_mode: "quarantine".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_validate_rejects_unknown_delete_mode() {
         let config = Config {
@@ -352,5 +1502,104 @@ exclude_patterns = ["*.tmp"]
         let loaded = Config::load(&config_path).unwrap();
         assert_eq!(loaded.default_delete_mode, "trash");
         assert!(loaded.default_compress_backup);
+        assert!(loaded.require_output_verification);
+        assert!(!loaded.safe_mode);
+        assert!(loaded.preserve_metadata);
+        assert_eq!(loaded.min_savings_percent, 5.0);
+        assert_eq!(loaded.file_timeout_secs, 300);
+        assert_eq!(loaded.tool_nice_level, None);
+        assert_eq!(loaded.raw_policy, "delete");
+        assert!(loaded.minify_dirs.is_empty());
+        assert_eq!(loaded.minify_min_size, 4096);
+        assert!(loaded.protected_paths.is_empty());
+        assert_eq!(loaded.quarantine_days, 30);
+        assert!(loaded.schedules.is_empty());
+        assert!(loaded.watch_rules.is_empty());
+        assert!(loaded.daemon_socket.ends_with("daemon.sock"));
+    }
+
+    #[test]
+    fn test_settings_by_key() {
+        let mut config = Config::default();
+        assert_eq!(config.get_key("log_level").unwrap(), "info");
+        assert_eq!(config.get_key("image_similarity_threshold").unwrap(), 0.9);
+        assert_eq!(config.get_key("tool_nice_level").unwrap(), Value::Null);
+
+        config.set_key("scan.max_depth", "3").unwrap();
+        config.set_key("tool_nice_level", "10").unwrap();
+        config
+            .set_key("tool_paths.ffmpeg", "/opt/bin/ffmpeg")
+            .unwrap();
+        config.set_key("minify_dirs", r#"["web", "docs"]"#).unwrap();
+        config.set_key("log_level", "debug").unwrap();
+        assert_eq!(config.scan.max_depth, Some(3));
+        assert_eq!(config.tool_nice_level, Some(10));
+        assert_eq!(config.tool_paths["ffmpeg"], "/opt/bin/ffmpeg");
+        assert_eq!(config.minify_dirs, ["web", "docs"]);
+        assert_eq!(config.log_level, "debug");
+
+        config.set_key("tool_nice_level", "null").unwrap();
+        assert_eq!(config.tool_nice_level, None);
+        config.reset_key("tool_paths.ffmpeg").unwrap();
+        assert!(config.tool_paths.is_empty());
+        config.reset_key("scan").unwrap();
+        assert_eq!(config.scan.max_depth, None);
+    }
+
+    #[test]
+    fn test_bad_settings_are_refused_with_typed_errors() {
+        let mut config = Config::default();
+        let code = |err: anyhow::Error| Error::from(err).code();
+        assert_eq!(
+            code(config.set_key("no_such_setting", "1").unwrap_err()),
+            crate::ErrorCode::InvalidInput
+        );
+        assert_eq!(
+            code(config.get_key("scan.nope").unwrap_err()),
+            crate::ErrorCode::InvalidInput
+        );
+        let err = config.set_key("max_concurrent_tasks", "many").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("max_concurrent_tasks: invalid type"));
+        assert_eq!(code(err), crate::ErrorCode::Config);
+        let err = config.set_key("max_concurrent_tasks", "0").unwrap_err();
+        assert!(err.to_string().contains("at least 1"));
+        assert_eq!(config.max_concurrent_tasks, 4);
+    }
+
+    #[test]
+    fn test_update_merges_partial_changes_or_refuses_them_all() {
+        let mut config = Config::default();
+        config
+            .update(&serde_json::json!({
+                "log_level": "debug",
+                "scan": { "max_depth": 3 },
+                "tool_paths": { "ffmpeg": "/opt/ffmpeg" },
+            }))
+            .unwrap();
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.scan.max_depth, Some(3));
+        assert!(config.scan.exclude_patterns.contains(&".git/*".to_string()));
+        assert_eq!(config.tool_paths["ffmpeg"], "/opt/ffmpeg");
+
+        let code = |err: anyhow::Error| Error::from(err).code();
+        let err = config
+            .update(&serde_json::json!({ "log_level": "info", "max_concurrent_tasks": 0 }))
+            .unwrap_err();
+        assert_eq!(code(err), crate::ErrorCode::Config);
+        assert_eq!(config.log_level, "debug");
+        let err = config
+            .update(&serde_json::json!({ "scan": { "nope": 1 } }))
+            .unwrap_err();
+        assert_eq!(code(err), crate::ErrorCode::InvalidInput);
+        let err = config
+            .update(&serde_json::json!({ "log_level": { "nested": 1 } }))
+            .unwrap_err();
+        assert_eq!(code(err), crate::ErrorCode::InvalidInput);
+        config
+            .update(&serde_json::json!({ "scan": { "max_depth": null } }))
+            .unwrap();
+        assert_eq!(config.scan.max_depth, None);
     }
 }
@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Custom error type for the application
@@ -6,6 +9,26 @@ pub enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    PermissionDenied(String),
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("{0}")]
+    TimedOut(String),
+
+    /// A destructive operation refused because the path is protected
+    #[error("{0}")]
+    Protected(String),
+
+    /// Arguments the operation cannot work with
+    #[error("{0}")]
+    InvalidInput(String),
+
     #[error("Database error: {0}")]
     Database(String),
 
@@ -33,22 +56,192 @@ pub enum Error {
     #[error("Serialization error: {0}")]
     Serialization(String),
 
-    #[error("Unknown error: {0}")]
+    #[error("{0}")]
     Unknown(String),
 }
 
 /// Custom result type
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// What kind of failure an [`Error`] is, stable across releases so
+/// frontends can branch on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Io,
+    NotFound,
+    PermissionDenied,
+    Cancelled,
+    TimedOut,
+    Protected,
+    InvalidInput,
+    Database,
+    Config,
+    FileOperation,
+    Hash,
+    Compression,
+    Image,
+    Video,
+    Task,
+    Serialization,
+    Unknown,
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Io(e) => io_code(e),
+            Error::NotFound(_) => ErrorCode::NotFound,
+            Error::PermissionDenied(_) => ErrorCode::PermissionDenied,
+            Error::Cancelled => ErrorCode::Cancelled,
+            Error::TimedOut(_) => ErrorCode::TimedOut,
+            Error::Protected(_) => ErrorCode::Protected,
+            Error::InvalidInput(_) => ErrorCode::InvalidInput,
+            Error::Database(_) => ErrorCode::Database,
+            Error::Config(_) => ErrorCode::Config,
+            Error::FileOperation(_) => ErrorCode::FileOperation,
+            Error::Hash(_) => ErrorCode::Hash,
+            Error::Compression(_) => ErrorCode::Compression,
+            Error::Image(_) => ErrorCode::Image,
+            Error::Video(_) => ErrorCode::Video,
+            Error::Task(_) => ErrorCode::Task,
+            Error::Serialization(_) => ErrorCode::Serialization,
+            Error::Unknown(_) => ErrorCode::Unknown,
+        }
+    }
+
+    /// An error of kind `code` reading `message`
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match code {
+            ErrorCode::Io => Error::Io(std::io::Error::other(message)),
+            ErrorCode::NotFound => Error::NotFound(message),
+            ErrorCode::PermissionDenied => Error::PermissionDenied(message),
+            ErrorCode::Cancelled => Error::Cancelled,
+            ErrorCode::TimedOut => Error::TimedOut(message),
+            ErrorCode::Protected => Error::Protected(message),
+            ErrorCode::InvalidInput => Error::InvalidInput(message),
+            ErrorCode::Database => Error::Database(message),
+            ErrorCode::Config => Error::Config(message),
+            ErrorCode::FileOperation => Error::FileOperation(message),
+            ErrorCode::Hash => Error::Hash(message),
+            ErrorCode::Compression => Error::Compression(message),
+            ErrorCode::Image => Error::Image(message),
+            ErrorCode::Video => Error::Video(message),
+            ErrorCode::Task => Error::Task(message),
+            ErrorCode::Serialization => Error::Serialization(message),
+            ErrorCode::Unknown => Error::Unknown(message),
+        }
+    }
+}
+
+fn io_code(err: &std::io::Error) -> ErrorCode {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+        std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+        _ => ErrorCode::Io,
+    }
+}
+
+/// What frontends receive for an [`Error`]:
+/// `{ "code": "not_found", "message": "..." }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl From<&Error> for ErrorInfo {
+    fn from(err: &Error) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        ErrorInfo::from(self).serialize(serializer)
+    }
+}
+
 impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Self {
         Error::Serialization(err.to_string())
     }
 }
 
+impl From<ProtectedPathError> for Error {
+    fn from(err: ProtectedPathError) -> Self {
+        Error::Protected(err.to_string())
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for Error {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        Error::Unknown(err.to_string())
+    }
+}
+
+/// Classifies an error raised with `anyhow` by the most specific cause in
+/// its chain: an [`Error`] raised deeper down, [`Cancelled`], [`TimedOut`],
+/// [`ProtectedPathError`] or an IO error's kind. The message is the whole
+/// chain, context first.
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<Error>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        let code = err
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<Error>() {
+                    Some(e.code())
+                } else if cause.is::<Cancelled>() {
+                    Some(ErrorCode::Cancelled)
+                } else if cause.is::<TimedOut>() {
+                    Some(ErrorCode::TimedOut)
+                } else if cause.is::<ProtectedPathError>() {
+                    Some(ErrorCode::Protected)
+                } else {
+                    cause.downcast_ref::<std::io::Error>().map(io_code)
+                }
+            })
+            .unwrap_or(ErrorCode::Unknown);
+        Error::new(code, format!("{:#}", err))
+    }
+}
+
+/// Error returned when a job stops because its cancellation token was
+/// cancelled. Callers can tell it apart from real failures with
+/// `err.is::<Cancelled>()`.
+#[derive(Debug, thiserror::Error)]
+#[error("Compression cancelled")]
+pub struct Cancelled;
+
+/// Error returned when a file exceeds the per-file timeout. The plugin was
+/// cancelled (killing any external tool) and its output removed; the
+/// original is untouched. Check with `err.is::<TimedOut>()`.
+#[derive(Debug, thiserror::Error)]
+#[error("Timed out after {}s", .0.as_secs_f64())]
+pub struct TimedOut(pub Duration);
+
+/// A destructive operation refused because the path is protected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Protected path: {} is inside {}", .path.display(), .root.display())]
+pub struct ProtectedPathError {
+    /// The path the operation was asked to touch
+    pub path: PathBuf,
+    /// The protected directory it falls under
+    pub root: PathBuf,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Context;
 
     #[test]
     fn test_error_display() {
@@ -62,4 +255,38 @@ mod tests {
         let err: Error = io_err.into();
         assert!(matches!(err, Error::Io(_)));
     }
+
+    #[test]
+    fn test_anyhow_errors_are_classified_by_their_cause() {
+        let missing = std::fs::read("/definitely/not/here")
+            .context("Failed to read /definitely/not/here")
+            .unwrap_err();
+        let err = Error::from(missing);
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        assert!(err
+            .to_string()
+            .starts_with("Failed to read /definitely/not/here: "));
+
+        let cancelled = anyhow::Error::from(Cancelled).context("Scan stopped");
+        assert_eq!(Error::from(cancelled).code(), ErrorCode::Cancelled);
+
+        let typed = anyhow::Error::from(Error::InvalidInput("Empty list".to_string()));
+        assert!(matches!(Error::from(typed), Error::InvalidInput(m) if m == "Empty list"));
+
+        let plain = Error::from(anyhow::anyhow!("Something broke"));
+        assert_eq!(plain.code(), ErrorCode::Unknown);
+        assert_eq!(plain.to_string(), "Something broke");
+    }
+
+    #[test]
+    fn test_errors_serialize_with_their_code() {
+        let err = Error::PermissionDenied("Permission denied (os error 13)".to_string());
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": "permission_denied",
+                "message": "Permission denied (os error 13)",
+            })
+        );
+    }
 }
@@ -3,7 +3,7 @@ pub mod error;
 pub mod logger;
 pub mod time;
 
-pub use config::Config;
-pub use error::{Error, Result};
-pub use logger::init_logger;
-pub use time::{format_duration, format_size, format_timestamp};
+pub use config::{CleanupRule, Config, Hook, NotificationConfig, Schedule, WatchRule};
+pub use error::{Cancelled, Error, ErrorCode, ErrorInfo, ProtectedPathError, Result, TimedOut};
+pub use logger::{init_logger, init_logger_with_file, init_logger_with_level, set_log_level};
+pub use time::{format_duration, format_size, format_timestamp, parse_time_bound};
@@ -1,34 +1,64 @@
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use std::sync::OnceLock;
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+/// The filter of the logger [`init_logger_with_level`] installed, for
+/// [`set_log_level`]
+static LEVEL: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 /// Initialize the logger with default settings
 pub fn init_logger() {
     init_logger_with_level("info")
 }
 
-/// Initialize the logger with a specific level
+/// Initialize the logger with a specific level, which [`set_log_level`] can
+/// change later
 pub fn init_logger_with_level(level: &str) {
     let filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new(level))
         .unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
 
     tracing_subscriber::registry()
         .with(filter)
         .with(fmt::layer())
         .init();
+    let _ = LEVEL.set(handle);
+}
+
+/// Log at `level` (an env filter directive such as "debug") from now on,
+/// without a restart. Does nothing when RUST_LOG is set or the logger was
+/// not installed by [`init_logger_with_level`].
+pub fn set_log_level(level: &str) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(level)?;
+    if std::env::var_os(EnvFilter::DEFAULT_ENV).is_none() {
+        if let Some(handle) = LEVEL.get() {
+            handle.reload(filter)?;
+        }
+    }
+    Ok(())
 }
 
-/// Initialize logger for file output
-pub fn init_logger_with_file(file_path: &str) -> anyhow::Result<()> {
+/// Initialize logger for file output, at `level` (an env filter directive
+/// such as "info") unless RUST_LOG is set
+pub fn init_logger_with_file(file_path: &str, level: &str) -> anyhow::Result<()> {
     let file = std::fs::OpenOptions::new()
         .create(true)
         .append(true)
         .open(file_path)?;
 
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
 
     tracing_subscriber::registry()
         .with(filter)
-        .with(fmt::layer().with_writer(std::sync::Arc::new(file)))
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(std::sync::Arc::new(file)),
+        )
         .init();
 
     Ok(())
@@ -1,4 +1,5 @@
-use chrono::{DateTime, Local, Utc};
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use std::time::Duration;
 
 /// Format a duration in human-readable format
@@ -54,6 +55,53 @@ pub fn now() -> i64 {
     Utc::now().timestamp()
 }
 
+/// Parse a point in time for date filters, as a Unix timestamp. Accepts
+/// an ISO date (`2020-01-01`, midnight UTC), a bare year (`2020`), an
+/// RFC 3339 timestamp, or a duration before `now` such as `30d`, `2 weeks`,
+/// `6mo` or `1 year ago` (a month is 30 days, a year 365).
+pub fn parse_time_bound(value: &str, now: i64) -> anyhow::Result<i64> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&value) {
+        return Ok(datetime.timestamp());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+        return Ok(date.and_time(Default::default()).and_utc().timestamp());
+    }
+    if value.len() == 4 && value.bytes().all(|b| b.is_ascii_digit()) {
+        let year = value.parse()?;
+        if let Some(date) = NaiveDate::from_ymd_opt(year, 1, 1) {
+            return Ok(date.and_time(Default::default()).and_utc().timestamp());
+        }
+    }
+
+    let duration = value.strip_suffix("ago").unwrap_or(&value).trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (amount, unit) = duration.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("Invalid date or duration: {}", value))?;
+    let unit_secs = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86_400,
+        "w" | "week" | "weeks" => 7 * 86_400,
+        "mo" | "month" | "months" => 30 * 86_400,
+        "y" | "year" | "years" => 365 * 86_400,
+        "m" => bail!(
+            "Ambiguous unit in {}: use 'min' for minutes or 'mo' for months",
+            value
+        ),
+        _ => bail!(
+            "Invalid date or duration: {} (expected e.g. 2020-01-01 or 30d)",
+            value
+        ),
+    };
+    Ok(now - amount.saturating_mul(unit_secs))
+}
+
 /// Calculate time difference between two timestamps
 pub fn time_diff(start: i64, end: i64) -> Duration {
     Duration::from_secs((end - start).unsigned_abs())
@@ -86,6 +134,31 @@ mod tests {
         assert_eq!(format_size(1024 * 1024 * 1024), "1.00 GB");
     }
 
+    #[test]
+    fn test_parse_time_bound() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_time_bound("2020-01-01", now).unwrap(), 1_577_836_800);
+        assert_eq!(parse_time_bound("2020", now).unwrap(), 1_577_836_800);
+        assert_eq!(
+            parse_time_bound("2020-01-01T01:00:00+01:00", now).unwrap(),
+            1_577_836_800
+        );
+        assert_eq!(parse_time_bound("30d", now).unwrap(), now - 30 * 86_400);
+        assert_eq!(parse_time_bound("2 weeks", now).unwrap(), now - 14 * 86_400);
+        assert_eq!(parse_time_bound("6mo", now).unwrap(), now - 180 * 86_400);
+        assert_eq!(
+            parse_time_bound(" 1 Year ago ", now).unwrap(),
+            now - 365 * 86_400
+        );
+
+        assert!(parse_time_bound("5m", now)
+            .unwrap_err()
+            .to_string()
+            .contains("Ambiguous"));
+        assert!(parse_time_bound("yesterday", now).is_err());
+        assert!(parse_time_bound("2020-13-01", now).is_err());
+    }
+
     #[test]
     fn test_time_diff() {
         let start = 1000;